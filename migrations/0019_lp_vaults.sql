@@ -0,0 +1,60 @@
+-- Liquidity provider vaults backing the auto market maker
+-- Migration: 0019_lp_vaults.sql
+
+-- Vault definitions
+-- Capital deposited into a vault is moved to the operator (market maker) account;
+-- the vault's NAV is the operator's collateral balance plus its marked share holdings.
+CREATE TABLE IF NOT EXISTS lp_vaults (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(100) NOT NULL,
+    token VARCHAR(20) NOT NULL,
+    operator_address VARCHAR(42) NOT NULL UNIQUE,
+    epoch_seconds BIGINT NOT NULL DEFAULT 604800,
+    lock_epochs INTEGER NOT NULL DEFAULT 1,
+    total_shares DECIMAL(30, 8) NOT NULL DEFAULT 0,
+    status VARCHAR(20) NOT NULL DEFAULT 'active',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT lp_vaults_epoch_positive CHECK (epoch_seconds > 0),
+    CONSTRAINT lp_vaults_lock_non_negative CHECK (lock_epochs >= 0),
+    CONSTRAINT lp_vaults_shares_non_negative CHECK (total_shares >= 0)
+);
+
+-- Per-user vault share balances
+CREATE TABLE IF NOT EXISTS lp_vault_positions (
+    vault_id UUID NOT NULL REFERENCES lp_vaults(id),
+    user_address VARCHAR(42) NOT NULL,
+    shares DECIMAL(30, 8) NOT NULL DEFAULT 0,
+    cost_basis DECIMAL(30, 8) NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (vault_id, user_address),
+    CONSTRAINT lp_vault_positions_shares_non_negative CHECK (shares >= 0)
+);
+
+-- Vault share ledger (append-only record of every deposit and withdrawal)
+CREATE TABLE IF NOT EXISTS lp_vault_ledger (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    vault_id UUID NOT NULL REFERENCES lp_vaults(id),
+    user_address VARCHAR(42) NOT NULL,
+    entry_type VARCHAR(20) NOT NULL,
+    amount DECIMAL(30, 8) NOT NULL,
+    shares DECIMAL(30, 8) NOT NULL,
+    nav_per_share DECIMAL(30, 8) NOT NULL,
+    epoch BIGINT NOT NULL,
+    unlock_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT lp_vault_ledger_entry_type CHECK (entry_type IN ('deposit', 'withdraw'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_lp_vault_ledger_user ON lp_vault_ledger(vault_id, user_address, created_at DESC);
+
+-- Comments
+COMMENT ON TABLE lp_vaults IS 'Pooled LP vaults backing the auto market maker';
+COMMENT ON COLUMN lp_vaults.operator_address IS 'Market maker account holding the vault capital';
+COMMENT ON COLUMN lp_vaults.lock_epochs IS 'Epochs deposited shares stay locked before they can be withdrawn';
+COMMENT ON TABLE lp_vault_ledger IS 'Vault share ledger: deposits mint shares, withdrawals burn them';
+COMMENT ON COLUMN lp_vault_ledger.unlock_at IS 'For deposits: when the minted shares become withdrawable';
@@ -0,0 +1,14 @@
+-- Account-level margin mode (isolated / cross)
+-- Migration: 0020_margin_mode.sql
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS margin_mode VARCHAR(10) NOT NULL DEFAULT 'isolated';
+
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conname = 'users_margin_mode_check') THEN
+        ALTER TABLE users ADD CONSTRAINT users_margin_mode_check CHECK (margin_mode IN ('isolated', 'cross'));
+    END IF;
+END$$;
+
+-- Comment
+COMMENT ON COLUMN users.margin_mode IS 'Margin mode: isolated (each position backed by its own cost) or cross (positions share the collateral balance)';
@@ -0,0 +1,62 @@
+-- Request-for-quote (RFQ) block trading
+-- Migration: 0021_rfq.sql
+
+-- Block trades are executed off-book and reported through the normal trade pipeline
+ALTER TABLE trades ADD COLUMN IF NOT EXISTS is_block_trade BOOLEAN NOT NULL DEFAULT false;
+
+-- Block trades have no orderbook orders: maker_order_id / taker_order_id hold the
+-- quote id / RFQ id instead, so the order foreign keys no longer apply
+ALTER TABLE trades DROP CONSTRAINT IF EXISTS trades_maker_order_id_fkey;
+ALTER TABLE trades DROP CONSTRAINT IF EXISTS trades_taker_order_id_fkey;
+
+-- Market makers allowed to receive and answer RFQs
+CREATE TABLE IF NOT EXISTS rfq_market_makers (
+    address VARCHAR(42) PRIMARY KEY,
+    active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Quote requests
+CREATE TABLE IF NOT EXISTS rfq_requests (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    taker_address VARCHAR(42) NOT NULL,
+    market_id UUID NOT NULL REFERENCES markets(id),
+    outcome_id UUID NOT NULL REFERENCES outcomes(id),
+    share_type share_type NOT NULL,
+    side order_side NOT NULL,
+    amount DECIMAL(30, 8) NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'open',
+    quote_deadline TIMESTAMPTZ NOT NULL,
+    filled_quote_id UUID,
+    filled_price DECIMAL(30, 8),
+    trade_id UUID,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT rfq_requests_amount_positive CHECK (amount > 0),
+    CONSTRAINT rfq_requests_status CHECK (status IN ('open', 'filled', 'expired', 'cancelled'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_rfq_requests_open ON rfq_requests(quote_deadline) WHERE status = 'open';
+CREATE INDEX IF NOT EXISTS idx_rfq_requests_taker ON rfq_requests(taker_address, created_at DESC);
+
+-- Market maker quotes (one live quote per maker per request; re-quoting replaces it)
+CREATE TABLE IF NOT EXISTS rfq_quotes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    rfq_id UUID NOT NULL REFERENCES rfq_requests(id),
+    maker_address VARCHAR(42) NOT NULL,
+    price DECIMAL(30, 8) NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    UNIQUE (rfq_id, maker_address),
+    CONSTRAINT rfq_quotes_price_range CHECK (price > 0 AND price < 1),
+    CONSTRAINT rfq_quotes_status CHECK (status IN ('pending', 'filled', 'rejected'))
+);
+
+-- Comments
+COMMENT ON COLUMN trades.is_block_trade IS 'Privately matched RFQ trade (not executed on the orderbook)';
+COMMENT ON TABLE rfq_requests IS 'RFQ lifecycle: open -> filled | expired | cancelled';
+COMMENT ON COLUMN rfq_requests.quote_deadline IS 'End of the quote window; the best quote is executed after this time';
@@ -0,0 +1,15 @@
+-- Per-position margin mode override
+-- Migration: 0022_position_margin_mode.sql
+
+-- NULL = follow the account margin mode (users.margin_mode)
+ALTER TABLE shares ADD COLUMN IF NOT EXISTS margin_mode VARCHAR(10);
+
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conname = 'shares_margin_mode_check') THEN
+        ALTER TABLE shares ADD CONSTRAINT shares_margin_mode_check CHECK (margin_mode IS NULL OR margin_mode IN ('isolated', 'cross'));
+    END IF;
+END$$;
+
+-- Comment
+COMMENT ON COLUMN shares.margin_mode IS 'Position margin mode override: isolated, cross, or NULL to follow the account mode';
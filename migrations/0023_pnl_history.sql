@@ -0,0 +1,28 @@
+-- Realized PnL history
+-- Migration: 0023_pnl_history.sql
+
+-- One row per position decrease (sell / merge) that closed held shares
+CREATE TABLE IF NOT EXISTS pnl_history (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_address VARCHAR(42) NOT NULL,
+    market_id UUID NOT NULL REFERENCES markets(id),
+    outcome_id UUID NOT NULL REFERENCES outcomes(id),
+    share_type share_type NOT NULL,
+    trade_id UUID,
+    token VARCHAR(42) NOT NULL,
+    amount DECIMAL(30, 8) NOT NULL,
+    entry_price DECIMAL(30, 8) NOT NULL,
+    exit_price DECIMAL(30, 8) NOT NULL,
+    realized_pnl DECIMAL(36, 18) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT pnl_history_amount_positive CHECK (amount > 0)
+);
+
+CREATE INDEX IF NOT EXISTS idx_pnl_history_user ON pnl_history(user_address, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_pnl_history_trade ON pnl_history(trade_id);
+
+-- Comments
+COMMENT ON TABLE pnl_history IS 'Realized PnL per position decrease; also credited to balances.available';
+COMMENT ON COLUMN pnl_history.entry_price IS 'Position average cost at the time of the decrease';
+COMMENT ON COLUMN pnl_history.realized_pnl IS '(exit_price - entry_price) * amount';
@@ -0,0 +1,27 @@
+-- Funding settlement against share positions
+-- Migration: 0024_funding_settlement.sql
+
+-- Funding symbols are outcome keys ({market_id}:{outcome_id}), longer than the perp tickers
+ALTER TABLE funding_rates ALTER COLUMN symbol TYPE VARCHAR(128);
+ALTER TABLE funding_settlements ALTER COLUMN symbol TYPE VARCHAR(128);
+ALTER TABLE market_funding_config ALTER COLUMN symbol TYPE VARCHAR(128);
+
+-- Settlements now reference share holdings; the legacy perp position link is optional
+ALTER TABLE funding_settlements ALTER COLUMN position_id DROP NOT NULL;
+ALTER TABLE funding_settlements ADD COLUMN IF NOT EXISTS share_id UUID REFERENCES shares(id);
+ALTER TABLE funding_settlements ADD COLUMN IF NOT EXISTS funding_rate_id UUID REFERENCES funding_rates(id);
+ALTER TABLE funding_settlements ADD COLUMN IF NOT EXISTS token VARCHAR(20);
+ALTER TABLE funding_settlements ADD COLUMN IF NOT EXISTS mark_price DECIMAL(36, 18);
+ALTER TABLE funding_settlements ADD COLUMN IF NOT EXISTS margin_mode VARCHAR(10);
+
+-- A rate is applied to each position at most once
+CREATE UNIQUE INDEX IF NOT EXISTS idx_funding_settlements_rate_share
+    ON funding_settlements(funding_rate_id, share_id);
+
+-- Due, unsettled rates
+CREATE INDEX IF NOT EXISTS idx_funding_rates_due
+    ON funding_rates(next_funding_time) WHERE settled_at IS NULL;
+
+-- Comments
+COMMENT ON COLUMN funding_settlements.funding_fee IS 'Funding paid by the position (negative = received)';
+COMMENT ON COLUMN funding_settlements.margin_mode IS 'cross: applied to the collateral balance; isolated: applied to the position cost basis';
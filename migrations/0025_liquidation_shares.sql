@@ -0,0 +1,33 @@
+-- Liquidation of share positions
+-- Migration: 0025_liquidation_shares.sql
+
+-- Symbols are outcome orderbook keys ({market_id}:{outcome_id}:{share_type})
+ALTER TABLE liquidations ALTER COLUMN symbol TYPE VARCHAR(128);
+ALTER TABLE insurance_fund ALTER COLUMN symbol TYPE VARCHAR(128);
+ALTER TABLE insurance_fund_transactions ALTER COLUMN symbol TYPE VARCHAR(128);
+
+-- Liquidations now reference share holdings; the legacy perp position link is optional
+ALTER TABLE liquidations ALTER COLUMN position_id DROP NOT NULL;
+ALTER TABLE liquidations ADD COLUMN IF NOT EXISTS share_id UUID REFERENCES shares(id);
+ALTER TABLE liquidations ADD COLUMN IF NOT EXISTS market_id UUID;
+ALTER TABLE liquidations ADD COLUMN IF NOT EXISTS outcome_id UUID;
+ALTER TABLE liquidations ADD COLUMN IF NOT EXISTS order_id UUID;
+ALTER TABLE liquidations ADD COLUMN IF NOT EXISTS target_amount DECIMAL(38, 18);
+ALTER TABLE liquidations ADD COLUMN IF NOT EXISTS filled_amount DECIMAL(38, 18) NOT NULL DEFAULT 0;
+ALTER TABLE liquidations ADD COLUMN IF NOT EXISTS is_partial BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE liquidations ADD COLUMN IF NOT EXISTS status VARCHAR(20) NOT NULL DEFAULT 'executed';
+
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conname = 'liquidations_status_check') THEN
+        ALTER TABLE liquidations ADD CONSTRAINT liquidations_status_check CHECK (status IN ('pending', 'executed', 'failed'));
+    END IF;
+END$$;
+
+-- One liquidation record per liquidation order
+CREATE UNIQUE INDEX IF NOT EXISTS idx_liquidations_order ON liquidations(order_id);
+CREATE INDEX IF NOT EXISTS idx_liquidations_pending ON liquidations(user_address) WHERE status = 'pending';
+
+-- Comments
+COMMENT ON COLUMN liquidations.status IS 'pending: order submitted, results not yet recorded; executed; failed';
+COMMENT ON COLUMN liquidations.is_partial IS 'Only part of the position was closed to restore maintenance margin';
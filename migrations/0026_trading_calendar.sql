@@ -0,0 +1,2 @@
+-- Optional per-market trading sessions (NULL = trades 24/7)
+ALTER TABLE markets ADD COLUMN IF NOT EXISTS trading_calendar JSONB;
@@ -0,0 +1,4 @@
+-- Optimistic concurrency for position (shares) updates: every write that
+-- changes amount / avg_cost bumps the version, and fills only apply against
+-- the version they read
+ALTER TABLE shares ADD COLUMN IF NOT EXISTS version BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,9 @@
+-- Per-symbol mark price settings (symbols without a row use the defaults from config)
+CREATE TABLE IF NOT EXISTS mark_price_configs (
+    symbol VARCHAR(128) PRIMARY KEY,              -- {market_id}:{outcome_id}:{share_type}
+    method VARCHAR(20) NOT NULL DEFAULT 'median', -- 'median' or 'last_trade'
+    basis_ema_secs BIGINT NOT NULL DEFAULT 3600,
+    index_sources TEXT[] NOT NULL DEFAULT '{}',   -- empty = all registered sources
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
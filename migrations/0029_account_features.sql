@@ -0,0 +1,27 @@
+-- Per-account feature entitlements (beta features gated by flag, grant or token holding)
+
+CREATE TABLE IF NOT EXISTS feature_flags (
+    key VARCHAR(64) PRIMARY KEY,
+    description TEXT,
+    enabled_for_all BOOLEAN NOT NULL DEFAULT FALSE,
+    gate_token VARCHAR(42),                            -- ERC-20 / ERC-721 contract; holders are entitled
+    gate_min_balance NUMERIC(78, 0) NOT NULL DEFAULT 1, -- raw token units
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS account_features (
+    user_address VARCHAR(42) NOT NULL,
+    feature VARCHAR(64) NOT NULL REFERENCES feature_flags(key) ON DELETE CASCADE,
+    granted_by VARCHAR(42),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_address, feature)
+);
+
+CREATE INDEX IF NOT EXISTS idx_account_features_feature ON account_features(feature);
+
+-- RFQ stays available to everyone until an admin narrows it
+INSERT INTO feature_flags (key, description, enabled_for_all) VALUES
+    ('rfq', 'Request-for-quote block trading', TRUE),
+    ('copy_trading', 'Copy trading (beta)', FALSE)
+ON CONFLICT (key) DO NOTHING;
@@ -0,0 +1,37 @@
+-- Funding clamp alerts and parameter governance log
+-- Migration: 0030_funding_governance.sql
+
+-- Consecutive settled intervals whose rate sat on a clamp bound
+ALTER TABLE market_funding_config ADD COLUMN IF NOT EXISTS clamp_side VARCHAR(5);
+ALTER TABLE market_funding_config ADD COLUMN IF NOT EXISTS clamp_streak INTEGER NOT NULL DEFAULT 0;
+
+-- Raised when a symbol's rate stays clamped for the configured number of intervals
+CREATE TABLE IF NOT EXISTS funding_clamp_alerts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    symbol VARCHAR(128) NOT NULL,
+    funding_rate_id UUID REFERENCES funding_rates(id),
+    side VARCHAR(5) NOT NULL,             -- 'cap' or 'floor'
+    streak INTEGER NOT NULL,
+    funding_rate DECIMAL(36, 18) NOT NULL,
+    bound DECIMAL(36, 18) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_funding_clamp_alerts_symbol_time
+    ON funding_clamp_alerts(symbol, created_at DESC);
+
+-- Every change to a symbol's funding parameters: who, when, old and new values
+CREATE TABLE IF NOT EXISTS funding_parameter_changes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    symbol VARCHAR(128) NOT NULL,
+    changed_by VARCHAR(42) NOT NULL,
+    old_values JSONB,                     -- NULL when the config was created
+    new_values JSONB NOT NULL,
+    reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_funding_parameter_changes_symbol_time
+    ON funding_parameter_changes(symbol, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_funding_parameter_changes_time
+    ON funding_parameter_changes(created_at DESC);
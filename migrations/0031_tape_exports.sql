@@ -0,0 +1,29 @@
+-- Trade tape export jobs
+-- Migration: 0031_tape_exports.sql
+
+-- One export per symbol per UTC day; failed exports can be re-requested
+CREATE TABLE IF NOT EXISTS tape_exports (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    symbol VARCHAR(128) NOT NULL,         -- {market_id}:{outcome_id}:{share_type}
+    trade_date DATE NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending',  -- pending, running, completed, failed
+    requested_by VARCHAR(42) NOT NULL,
+    row_count BIGINT,
+    data_key TEXT,
+    data_sha256 VARCHAR(64),
+    manifest_key TEXT,
+    manifest_signature VARCHAR(64),       -- HMAC-SHA256 of the manifest (NULL when signing is off)
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    started_at TIMESTAMPTZ,
+    completed_at TIMESTAMPTZ,
+    UNIQUE (symbol, trade_date)
+);
+
+CREATE INDEX IF NOT EXISTS idx_tape_exports_pending
+    ON tape_exports(created_at) WHERE status IN ('pending', 'running');
+
+-- Tape access is granted per partner account
+INSERT INTO feature_flags (key, description, enabled_for_all)
+VALUES ('trade_tape', 'Historical trade tape exports', FALSE)
+ON CONFLICT (key) DO NOTHING;
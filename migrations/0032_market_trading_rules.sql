@@ -0,0 +1,26 @@
+-- Runtime per-market trading rules
+-- Migration: 0032_market_trading_rules.sql
+
+-- Named fee schedules markets can be assigned to
+CREATE TABLE IF NOT EXISTS fee_tiers (
+    name VARCHAR(32) PRIMARY KEY,
+    base_fee_bps INTEGER NOT NULL,
+    max_fee_bps INTEGER NOT NULL,
+    maker_discount_pct INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (base_fee_bps >= 0 AND max_fee_bps >= base_fee_bps),
+    CHECK (maker_discount_pct BETWEEN 0 AND 100)
+);
+
+-- Matches the engine's built-in schedule
+INSERT INTO fee_tiers (name, base_fee_bps, max_fee_bps, maker_discount_pct)
+VALUES ('standard', 200, 1000, 50)
+ON CONFLICT (name) DO NOTHING;
+
+ALTER TABLE markets ADD COLUMN IF NOT EXISTS trading_paused BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE markets ADD COLUMN IF NOT EXISTS delisted_at TIMESTAMPTZ;
+ALTER TABLE markets ADD COLUMN IF NOT EXISTS max_leverage INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE markets ADD COLUMN IF NOT EXISTS fee_tier VARCHAR(32) REFERENCES fee_tiers(name);
+ALTER TABLE markets ADD COLUMN IF NOT EXISTS tick_size DECIMAL(36, 18);
+ALTER TABLE markets ADD COLUMN IF NOT EXISTS lot_size DECIMAL(36, 18);
@@ -0,0 +1,9 @@
+-- Per-market order size limits and maintenance margin tiers
+-- Migration: 0033_market_config.sql
+
+ALTER TABLE markets ADD COLUMN IF NOT EXISTS min_order_size DECIMAL(36, 18);
+ALTER TABLE markets ADD COLUMN IF NOT EXISTS max_order_size DECIMAL(36, 18);
+
+-- [{"max_notional": "10000", "maintenance_margin_rate": "0.005"}, ...]
+-- ascending by max_notional; the last tier may omit max_notional (unbounded)
+ALTER TABLE markets ADD COLUMN IF NOT EXISTS margin_tiers JSONB NOT NULL DEFAULT '[]';
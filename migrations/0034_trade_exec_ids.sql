@@ -0,0 +1,6 @@
+-- Engine-assigned trade execution sequence numbers
+-- Migration: 0034_trade_exec_ids.sql
+
+-- Consecutive across all trades; NULL for trades recorded before execution ids
+ALTER TABLE trades ADD COLUMN IF NOT EXISTS exec_id BIGINT;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_trades_exec_id ON trades(exec_id) WHERE exec_id IS NOT NULL;
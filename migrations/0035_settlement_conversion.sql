@@ -0,0 +1,14 @@
+-- Settlement currency conversion for markets not quoted in the collateral token
+-- Migration: 0035_settlement_conversion.sql
+
+-- Asset prices and PnL are quoted in; NULL = the collateral token
+ALTER TABLE markets ADD COLUMN IF NOT EXISTS quote_asset VARCHAR(16);
+
+-- Rate realized PnL was converted into collateral at
+ALTER TABLE pnl_history ADD COLUMN IF NOT EXISTS quote_asset VARCHAR(16);
+ALTER TABLE pnl_history ADD COLUMN IF NOT EXISTS quote_realized_pnl DECIMAL(36, 18);
+ALTER TABLE pnl_history ADD COLUMN IF NOT EXISTS conversion_rate DECIMAL(36, 18) NOT NULL DEFAULT 1;
+
+COMMENT ON COLUMN pnl_history.realized_pnl IS 'Realized PnL in the collateral token: (exit_price - entry_price) * amount * conversion_rate';
+COMMENT ON COLUMN pnl_history.quote_realized_pnl IS 'Realized PnL in the market quote asset (NULL for collateral-quoted markets)';
+COMMENT ON COLUMN pnl_history.conversion_rate IS 'Collateral per unit of quote asset at settlement';
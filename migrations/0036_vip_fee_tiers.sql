@@ -0,0 +1,34 @@
+-- VIP fee tiers by rolling taker volume, with maker rebates and per-account overrides
+-- Migration: 0036_vip_fee_tiers.sql
+
+-- Share of the market fee schedule each side pays; negative maker share = rebate
+CREATE TABLE IF NOT EXISTS vip_fee_tiers (
+    level INTEGER PRIMARY KEY,
+    name VARCHAR(32) NOT NULL,
+    min_volume DECIMAL(36, 18) NOT NULL,
+    taker_fee_pct INTEGER NOT NULL,
+    maker_fee_pct INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (level >= 0 AND min_volume >= 0),
+    CHECK (taker_fee_pct BETWEEN 0 AND 100),
+    CHECK (maker_fee_pct BETWEEN -100 AND 100)
+);
+
+INSERT INTO vip_fee_tiers (level, name, min_volume, taker_fee_pct, maker_fee_pct) VALUES
+    (0, 'VIP0', 0, 100, 100),
+    (1, 'VIP1', 100000, 90, 80),
+    (2, 'VIP2', 1000000, 80, 50),
+    (3, 'VIP3', 10000000, 70, 0),
+    (4, 'VIP4', 50000000, 60, -20)
+ON CONFLICT (level) DO NOTHING;
+
+-- Tier pinned by an admin regardless of volume
+CREATE TABLE IF NOT EXISTS user_fee_overrides (
+    user_address VARCHAR(42) PRIMARY KEY,
+    level INTEGER NOT NULL REFERENCES vip_fee_tiers(level),
+    note TEXT,
+    set_by VARCHAR(42),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
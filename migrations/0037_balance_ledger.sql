@@ -0,0 +1,28 @@
+-- Double-entry journal of every balance mutation
+-- Migration: 0037_balance_ledger.sql
+
+-- One row per mutation; the external leg offsets the account legs so every
+-- row sums to zero
+CREATE TABLE IF NOT EXISTS balance_ledger (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_address VARCHAR(42) NOT NULL,
+    token VARCHAR(42) NOT NULL,
+    reason VARCHAR(32) NOT NULL,
+    -- Order, withdrawal, trade, market, ... the change belongs to
+    reference_id UUID,
+    available_delta DECIMAL(36, 18) NOT NULL,
+    frozen_delta DECIMAL(36, 18) NOT NULL,
+    external_delta DECIMAL(36, 18) NOT NULL,
+    available_before DECIMAL(36, 18) NOT NULL,
+    available_after DECIMAL(36, 18) NOT NULL,
+    frozen_before DECIMAL(36, 18) NOT NULL,
+    frozen_after DECIMAL(36, 18) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (available_delta + frozen_delta + external_delta = 0),
+    CHECK (available_after = available_before + available_delta),
+    CHECK (frozen_after = frozen_before + frozen_delta)
+);
+
+CREATE INDEX IF NOT EXISTS idx_balance_ledger_user ON balance_ledger(user_address, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_balance_ledger_reason ON balance_ledger(user_address, reason, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_balance_ledger_reference ON balance_ledger(reference_id) WHERE reference_id IS NOT NULL;
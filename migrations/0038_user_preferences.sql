@@ -0,0 +1,16 @@
+-- Per-account order defaults applied when an order omits the field
+-- Migration: 0038_user_preferences.sql
+
+CREATE TABLE IF NOT EXISTS user_preferences (
+    user_address VARCHAR(42) PRIMARY KEY,
+    -- Leverage per market: {"<market_id>": 5}
+    default_leverage JSONB NOT NULL DEFAULT '{}',
+    default_order_type VARCHAR(16),
+    -- Fraction of the order price a market order may fill beyond
+    slippage_tolerance DECIMAL(10, 6) NOT NULL DEFAULT 0,
+    reduce_only BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (slippage_tolerance >= 0 AND slippage_tolerance <= 0.5),
+    CHECK (default_order_type IS NULL OR default_order_type IN ('limit', 'market'))
+);
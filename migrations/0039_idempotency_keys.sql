@@ -0,0 +1,20 @@
+-- Idempotency keys: responses replayed to retried requests
+-- Migration: 0039_idempotency_keys.sql
+
+-- A key is claimed (response_status NULL) before the request runs and holds
+-- the response once it completes
+CREATE TABLE IF NOT EXISTS idempotency_keys (
+    user_address VARCHAR(42) NOT NULL,
+    -- Endpoint the key was used on, e.g. "POST /orders"
+    scope VARCHAR(64) NOT NULL,
+    idempotency_key VARCHAR(255) NOT NULL,
+    -- Hash of the request body; a key may not be reused for another request
+    request_hash VARCHAR(64) NOT NULL,
+    response_status SMALLINT,
+    response_body TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (user_address, scope, idempotency_key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_idempotency_keys_expires ON idempotency_keys(expires_at);
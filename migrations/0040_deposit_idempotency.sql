@@ -0,0 +1,16 @@
+-- Idempotent deposit crediting
+-- Migration: 0040_deposit_idempotency.sql
+
+-- A deposit is one transfer log, not one transaction: a transaction can carry
+-- several deposits, and a re-scanned log must map to the same row
+ALTER TABLE deposits ADD COLUMN IF NOT EXISTS log_index INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE deposits ADD COLUMN IF NOT EXISTS credited_at TIMESTAMPTZ;
+ALTER TABLE deposits DROP CONSTRAINT IF EXISTS deposits_tx_hash_key;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_deposits_tx_log ON deposits(tx_hash, log_index);
+
+-- At most one journal row per key; a replayed credit cannot land twice
+ALTER TABLE balance_ledger ADD COLUMN IF NOT EXISTS idempotency_key VARCHAR(128);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_balance_ledger_idempotency_key
+ON balance_ledger(idempotency_key) WHERE idempotency_key IS NOT NULL;
+
+COMMENT ON COLUMN balance_ledger.idempotency_key IS 'Set for changes that must apply once, e.g. deposit:<tx_hash>:<log_index>';
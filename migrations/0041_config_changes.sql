@@ -0,0 +1,16 @@
+-- Audit trail of runtime config changes
+-- Migration: 0041_config_changes.sql
+
+-- One row per key applied by a config reload
+CREATE TABLE IF NOT EXISTS config_changes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    key VARCHAR(64) NOT NULL,
+    old_value JSONB NOT NULL,
+    new_value JSONB NOT NULL,
+    -- Admin who triggered the reload
+    changed_by VARCHAR(42) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_config_changes_created ON config_changes(created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_config_changes_key ON config_changes(key, created_at DESC);
@@ -0,0 +1,13 @@
+-- Orderbook snapshots for warm restarts
+-- Migration: 0042_orderbook_snapshots.sql
+
+-- Latest snapshot per book; restart replays only journal entries after `sequence`
+CREATE TABLE IF NOT EXISTS orderbook_snapshots (
+    symbol VARCHAR(128) PRIMARY KEY,
+    -- Engine journal sequence the snapshot includes
+    sequence BIGINT NOT NULL,
+    taken_at TIMESTAMPTZ NOT NULL,
+    last_trade_price DECIMAL(30, 8),
+    -- Resting orders in price-time priority
+    orders JSONB NOT NULL
+);
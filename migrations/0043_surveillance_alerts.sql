@@ -0,0 +1,22 @@
+-- Trade surveillance alert review queue
+-- Migration: 0043_surveillance_alerts.sql
+
+CREATE TABLE IF NOT EXISTS surveillance_alerts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_address VARCHAR(42) NOT NULL,
+    symbol VARCHAR(128) NOT NULL,
+    -- spoofing | layering | momentum_ignition
+    pattern VARCHAR(32) NOT NULL,
+    -- 0-100
+    score INTEGER NOT NULL,
+    details JSONB NOT NULL DEFAULT '{}',
+    -- open | dismissed | escalated
+    status VARCHAR(16) NOT NULL DEFAULT 'open',
+    reviewed_by VARCHAR(42),
+    review_note TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    reviewed_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_surveillance_alerts_status ON surveillance_alerts(status, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_surveillance_alerts_user ON surveillance_alerts(user_address, symbol, pattern, created_at DESC);
@@ -0,0 +1,45 @@
+-- Withdrawal fee policies, locked fee quotes and treasury income
+-- Migration: 0044_withdrawal_fees.sql
+
+CREATE TABLE IF NOT EXISTS withdrawal_fee_policies (
+    token VARCHAR(42) PRIMARY KEY,
+    flat_fee DECIMAL(36, 18) NOT NULL DEFAULT 0,
+    -- Fraction of the amount, e.g. 0.001 = 0.1%
+    percentage DECIMAL(36, 18) NOT NULL DEFAULT 0,
+    min_fee DECIMAL(36, 18),
+    max_fee DECIMAL(36, 18),
+    congestion_enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS withdrawal_quotes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_address VARCHAR(42) NOT NULL,
+    token VARCHAR(42) NOT NULL,
+    amount DECIMAL(36, 18) NOT NULL,
+    fee DECIMAL(36, 18) NOT NULL,
+    flat_fee DECIMAL(36, 18) NOT NULL,
+    percentage_fee DECIMAL(36, 18) NOT NULL,
+    congestion_multiplier DECIMAL(36, 18) NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_withdrawal_quotes_user ON withdrawal_quotes(user_address, created_at DESC);
+
+CREATE TABLE IF NOT EXISTS treasury_entries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    token VARCHAR(42) NOT NULL,
+    amount DECIMAL(36, 18) NOT NULL,
+    -- withdrawal_fee
+    source VARCHAR(32) NOT NULL,
+    reference_id UUID,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_treasury_entries_token ON treasury_entries(token, source, created_at);
+
+ALTER TABLE withdrawals
+    ADD COLUMN IF NOT EXISTS fee DECIMAL(36, 18) NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS quote_id UUID;
@@ -0,0 +1,20 @@
+-- Nonces of signed trading messages (orders, cancels, amends)
+-- Migration: 0045_trading_nonces.sql
+
+-- Highest nonce issued per account; Redis issues nonces and writes the
+-- high-water mark through, so a Redis flush never reissues an old nonce
+CREATE TABLE IF NOT EXISTS trading_nonces (
+    user_address VARCHAR(42) PRIMARY KEY,
+    last_issued BIGINT NOT NULL DEFAULT 0,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Nonces already used in a signed request
+CREATE TABLE IF NOT EXISTS used_trading_nonces (
+    user_address VARCHAR(42) NOT NULL,
+    nonce BIGINT NOT NULL,
+    used_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_address, nonce)
+);
+
+CREATE INDEX IF NOT EXISTS idx_used_trading_nonces_used_at ON used_trading_nonces(used_at);
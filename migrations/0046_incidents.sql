@@ -0,0 +1,32 @@
+-- Status page incidents
+-- Migration: 0046_incidents.sql
+
+CREATE TABLE IF NOT EXISTS incidents (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    title VARCHAR(200) NOT NULL,
+    -- api | matching | websocket | market_data | deposits | withdrawals
+    components TEXT[] NOT NULL,
+    -- degraded | partial_outage | major_outage
+    impact VARCHAR(16) NOT NULL,
+    -- investigating | identified | monitoring | resolved
+    status VARCHAR(16) NOT NULL DEFAULT 'investigating',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    resolved_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_incidents_active ON incidents(created_at DESC) WHERE resolved_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_incidents_created ON incidents(created_at DESC);
+
+-- Timeline of an incident (the opening message is the first update)
+CREATE TABLE IF NOT EXISTS incident_updates (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    incident_id UUID NOT NULL REFERENCES incidents(id) ON DELETE CASCADE,
+    status VARCHAR(16) NOT NULL,
+    impact VARCHAR(16) NOT NULL,
+    message TEXT NOT NULL,
+    author VARCHAR(42) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_incident_updates_incident ON incident_updates(incident_id, created_at);
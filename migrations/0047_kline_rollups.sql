@@ -0,0 +1,248 @@
+-- K-line rollups from 1m base candles
+-- Migration: 0047_kline_rollups.sql
+--
+-- Only klines_1m is aggregated from trades. Every higher timeframe is a
+-- hierarchical continuous aggregate over klines_1m, so a backfilled or
+-- corrected minute shows up identically in all periods once refreshed
+-- (ztdx-admin replay-klines refreshes klines_1m first, then the rollups).
+-- `ztdx-admin check-klines` compares stored rollups with the 1m candles.
+--
+-- The rollups are created empty; backfill them with
+--   ztdx-admin replay-klines --from <start of kept history>
+
+DROP MATERIALIZED VIEW IF EXISTS klines_5m CASCADE;
+DROP MATERIALIZED VIEW IF EXISTS klines_15m CASCADE;
+DROP MATERIALIZED VIEW IF EXISTS klines_1h CASCADE;
+DROP MATERIALIZED VIEW IF EXISTS klines_4h CASCADE;
+DROP MATERIALIZED VIEW IF EXISTS klines_1d CASCADE;
+DROP MATERIALIZED VIEW IF EXISTS klines_1w CASCADE;
+
+CREATE MATERIALIZED VIEW IF NOT EXISTS klines_5m
+WITH (timescaledb.continuous) AS
+SELECT
+    symbol,
+    time_bucket('5 minutes', bucket) AS bucket,
+    FIRST(open, bucket) AS open,
+    MAX(high) AS high,
+    MIN(low) AS low,
+    LAST(close, bucket) AS close,
+    SUM(volume) AS volume,
+    SUM(quote_volume) AS quote_volume,
+    SUM(trade_count)::BIGINT AS trade_count
+FROM klines_1m
+GROUP BY symbol, time_bucket('5 minutes', bucket)
+WITH NO DATA;
+
+SELECT add_continuous_aggregate_policy('klines_5m',
+    start_offset => INTERVAL '6 hours',
+    end_offset => INTERVAL '5 minutes',
+    schedule_interval => INTERVAL '5 minutes',
+    if_not_exists => TRUE
+);
+
+CREATE INDEX IF NOT EXISTS idx_klines_5m_symbol_bucket ON klines_5m (symbol, bucket DESC);
+
+CREATE MATERIALIZED VIEW IF NOT EXISTS klines_15m
+WITH (timescaledb.continuous) AS
+SELECT
+    symbol,
+    time_bucket('15 minutes', bucket) AS bucket,
+    FIRST(open, bucket) AS open,
+    MAX(high) AS high,
+    MIN(low) AS low,
+    LAST(close, bucket) AS close,
+    SUM(volume) AS volume,
+    SUM(quote_volume) AS quote_volume,
+    SUM(trade_count)::BIGINT AS trade_count
+FROM klines_1m
+GROUP BY symbol, time_bucket('15 minutes', bucket)
+WITH NO DATA;
+
+SELECT add_continuous_aggregate_policy('klines_15m',
+    start_offset => INTERVAL '12 hours',
+    end_offset => INTERVAL '15 minutes',
+    schedule_interval => INTERVAL '15 minutes',
+    if_not_exists => TRUE
+);
+
+CREATE INDEX IF NOT EXISTS idx_klines_15m_symbol_bucket ON klines_15m (symbol, bucket DESC);
+
+CREATE MATERIALIZED VIEW IF NOT EXISTS klines_1h
+WITH (timescaledb.continuous) AS
+SELECT
+    symbol,
+    time_bucket('1 hour', bucket) AS bucket,
+    FIRST(open, bucket) AS open,
+    MAX(high) AS high,
+    MIN(low) AS low,
+    LAST(close, bucket) AS close,
+    SUM(volume) AS volume,
+    SUM(quote_volume) AS quote_volume,
+    SUM(trade_count)::BIGINT AS trade_count
+FROM klines_1m
+GROUP BY symbol, time_bucket('1 hour', bucket)
+WITH NO DATA;
+
+SELECT add_continuous_aggregate_policy('klines_1h',
+    start_offset => INTERVAL '24 hours',
+    end_offset => INTERVAL '1 hour',
+    schedule_interval => INTERVAL '1 hour',
+    if_not_exists => TRUE
+);
+
+CREATE INDEX IF NOT EXISTS idx_klines_1h_symbol_bucket ON klines_1h (symbol, bucket DESC);
+
+CREATE MATERIALIZED VIEW IF NOT EXISTS klines_4h
+WITH (timescaledb.continuous) AS
+SELECT
+    symbol,
+    time_bucket('4 hours', bucket) AS bucket,
+    FIRST(open, bucket) AS open,
+    MAX(high) AS high,
+    MIN(low) AS low,
+    LAST(close, bucket) AS close,
+    SUM(volume) AS volume,
+    SUM(quote_volume) AS quote_volume,
+    SUM(trade_count)::BIGINT AS trade_count
+FROM klines_1m
+GROUP BY symbol, time_bucket('4 hours', bucket)
+WITH NO DATA;
+
+SELECT add_continuous_aggregate_policy('klines_4h',
+    start_offset => INTERVAL '48 hours',
+    end_offset => INTERVAL '4 hours',
+    schedule_interval => INTERVAL '4 hours',
+    if_not_exists => TRUE
+);
+
+CREATE INDEX IF NOT EXISTS idx_klines_4h_symbol_bucket ON klines_4h (symbol, bucket DESC);
+
+CREATE MATERIALIZED VIEW IF NOT EXISTS klines_1d
+WITH (timescaledb.continuous) AS
+SELECT
+    symbol,
+    time_bucket('1 day', bucket) AS bucket,
+    FIRST(open, bucket) AS open,
+    MAX(high) AS high,
+    MIN(low) AS low,
+    LAST(close, bucket) AS close,
+    SUM(volume) AS volume,
+    SUM(quote_volume) AS quote_volume,
+    SUM(trade_count)::BIGINT AS trade_count
+FROM klines_1m
+GROUP BY symbol, time_bucket('1 day', bucket)
+WITH NO DATA;
+
+SELECT add_continuous_aggregate_policy('klines_1d',
+    start_offset => INTERVAL '7 days',
+    end_offset => INTERVAL '1 day',
+    schedule_interval => INTERVAL '1 day',
+    if_not_exists => TRUE
+);
+
+CREATE INDEX IF NOT EXISTS idx_klines_1d_symbol_bucket ON klines_1d (symbol, bucket DESC);
+
+CREATE MATERIALIZED VIEW IF NOT EXISTS klines_1w
+WITH (timescaledb.continuous) AS
+SELECT
+    symbol,
+    time_bucket('1 week', bucket) AS bucket,
+    FIRST(open, bucket) AS open,
+    MAX(high) AS high,
+    MIN(low) AS low,
+    LAST(close, bucket) AS close,
+    SUM(volume) AS volume,
+    SUM(quote_volume) AS quote_volume,
+    SUM(trade_count)::BIGINT AS trade_count
+FROM klines_1m
+GROUP BY symbol, time_bucket('1 week', bucket)
+WITH NO DATA;
+
+SELECT add_continuous_aggregate_policy('klines_1w',
+    start_offset => INTERVAL '4 weeks',
+    end_offset => INTERVAL '1 week',
+    schedule_interval => INTERVAL '1 week',
+    if_not_exists => TRUE
+);
+
+CREATE INDEX IF NOT EXISTS idx_klines_1w_symbol_bucket ON klines_1w (symbol, bucket DESC);
+
+CREATE MATERIALIZED VIEW IF NOT EXISTS klines_1mo
+WITH (timescaledb.continuous) AS
+SELECT
+    symbol,
+    time_bucket('1 month', bucket) AS bucket,
+    FIRST(open, bucket) AS open,
+    MAX(high) AS high,
+    MIN(low) AS low,
+    LAST(close, bucket) AS close,
+    SUM(volume) AS volume,
+    SUM(quote_volume) AS quote_volume,
+    SUM(trade_count)::BIGINT AS trade_count
+FROM klines_1m
+GROUP BY symbol, time_bucket('1 month', bucket)
+WITH NO DATA;
+
+SELECT add_continuous_aggregate_policy('klines_1mo',
+    start_offset => INTERVAL '3 months',
+    end_offset => INTERVAL '1 day',
+    schedule_interval => INTERVAL '1 day',
+    if_not_exists => TRUE
+);
+
+CREATE INDEX IF NOT EXISTS idx_klines_1mo_symbol_bucket ON klines_1mo (symbol, bucket DESC);
+
+-- Rollups are refreshed from the 1m candles: keep those longer than the
+-- widest refresh window (3 months for klines_1mo), or a refresh over dropped
+-- minutes would empty the rollup buckets
+SELECT remove_retention_policy('klines_1m', if_exists => TRUE);
+SELECT add_retention_policy('klines_1m', INTERVAL '120 days', if_not_exists => TRUE);
+SELECT add_retention_policy('klines_5m', INTERVAL '60 days', if_not_exists => TRUE);
+SELECT add_retention_policy('klines_15m', INTERVAL '90 days', if_not_exists => TRUE);
+
+-- '1M' (month) is stored as klines_1mo: unquoted klines_1M would name klines_1m
+CREATE OR REPLACE FUNCTION get_klines(
+    p_symbol VARCHAR,
+    p_period VARCHAR,
+    p_start_time TIMESTAMPTZ,
+    p_end_time TIMESTAMPTZ,
+    p_limit INT DEFAULT 500
+)
+RETURNS TABLE (
+    symbol VARCHAR,
+    open_time TIMESTAMPTZ,
+    open DECIMAL,
+    high DECIMAL,
+    low DECIMAL,
+    close DECIMAL,
+    volume DECIMAL,
+    quote_volume DECIMAL,
+    trade_count BIGINT
+) AS $$
+BEGIN
+    RETURN QUERY EXECUTE format(
+        'SELECT symbol, bucket as open_time, open, high, low, close, volume, quote_volume, trade_count
+         FROM klines_%s
+         WHERE symbol = $1
+           AND bucket >= $2
+           AND bucket < $3
+         ORDER BY bucket DESC
+         LIMIT $4',
+        CASE WHEN p_period = '1M' THEN '1mo' ELSE p_period END
+    ) USING p_symbol, p_start_time, p_end_time, p_limit;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION refresh_klines(
+    p_period VARCHAR,
+    p_start_time TIMESTAMPTZ,
+    p_end_time TIMESTAMPTZ
+)
+RETURNS VOID AS $$
+BEGIN
+    EXECUTE format(
+        'CALL refresh_continuous_aggregate(''klines_%s'', $1, $2)',
+        CASE WHEN p_period = '1M' THEN '1mo' ELSE p_period END
+    ) USING p_start_time, p_end_time;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,46 @@
+-- Margin call escalation ladder
+-- Migration: 0048_margin_calls.sql
+
+-- Per-account ladder settings; accounts without a row use the defaults
+CREATE TABLE IF NOT EXISTS margin_call_settings (
+    user_address VARCHAR(42) PRIMARY KEY,
+    -- Margin ratios (maintenance / equity) at which each step fires
+    notice_ratio DECIMAL(10, 4) NOT NULL,
+    alert_ratio DECIMAL(10, 4) NOT NULL,
+    reduce_ratio DECIMAL(10, 4) NOT NULL,
+    webhook_url TEXT,
+    email VARCHAR(254),
+    -- Percent of every position sold at the reduce step (0 = off)
+    auto_reduce_percent DECIMAL(5, 2) NOT NULL DEFAULT 0,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT margin_call_ratios_ascending CHECK (
+        notice_ratio > 0 AND notice_ratio < alert_ratio AND alert_ratio < reduce_ratio AND reduce_ratio < 1
+    ),
+    CONSTRAINT margin_call_reduce_percent CHECK (auto_reduce_percent >= 0 AND auto_reduce_percent <= 100)
+);
+
+-- Step each account last escalated to; an account only escalates when its
+-- level rises above this, and drops back once its ratio recovers
+CREATE TABLE IF NOT EXISTS margin_call_state (
+    user_address VARCHAR(42) PRIMARY KEY,
+    level SMALLINT NOT NULL,
+    margin_ratio DECIMAL(20, 8) NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Every action taken by the ladder
+CREATE TABLE IF NOT EXISTS margin_call_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_address VARCHAR(42) NOT NULL,
+    -- 1 notice | 2 alert | 3 reduce
+    level SMALLINT NOT NULL,
+    margin_ratio DECIMAL(20, 8) NOT NULL,
+    -- in_app | webhook | email | auto_reduce
+    action VARCHAR(16) NOT NULL,
+    -- sent | failed | skipped
+    status VARCHAR(16) NOT NULL,
+    detail TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_margin_call_events_user ON margin_call_events(user_address, created_at DESC);
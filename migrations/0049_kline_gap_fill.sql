@@ -0,0 +1,12 @@
+-- Per-symbol K-line gap filling
+-- Migration: 0049_kline_gap_fill.sql
+--
+-- Candle queries fill buckets without trades with flat candles (OHLC at the
+-- previous close, volume 0) unless turned off here; symbols without a row
+-- use KLINE_GAP_FILL_DEFAULT.
+
+CREATE TABLE IF NOT EXISTS kline_settings (
+    symbol VARCHAR(64) PRIMARY KEY,
+    gap_fill BOOLEAN NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,19 @@
+-- Order durability acknowledgment modes
+-- Migration: 0050_order_durability.sql
+
+-- ack_fast: acknowledged once matched in memory
+-- ack_after_journal: acknowledged once the engine journal entry is on disk
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS durability_mode VARCHAR(24) NOT NULL DEFAULT 'ack_fast';
+-- Journal sequence the order was durable at (ack_after_journal only)
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS journal_sequence BIGINT;
+
+ALTER TABLE orders DROP CONSTRAINT IF EXISTS orders_durability_mode_check;
+ALTER TABLE orders ADD CONSTRAINT orders_durability_mode_check
+    CHECK (durability_mode IN ('ack_fast', 'ack_after_journal'));
+
+-- Per-account default mode (NULL = ack_fast)
+ALTER TABLE user_preferences ADD COLUMN IF NOT EXISTS durability_mode VARCHAR(24);
+
+ALTER TABLE user_preferences DROP CONSTRAINT IF EXISTS user_preferences_durability_mode_check;
+ALTER TABLE user_preferences ADD CONSTRAINT user_preferences_durability_mode_check
+    CHECK (durability_mode IS NULL OR durability_mode IN ('ack_fast', 'ack_after_journal'));
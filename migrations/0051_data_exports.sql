@@ -0,0 +1,28 @@
+-- History export jobs (trades, funding settlements, candles)
+-- Migration: 0051_data_exports.sql
+
+CREATE TABLE IF NOT EXISTS data_exports (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_address VARCHAR(42) NOT NULL,
+    kind VARCHAR(32) NOT NULL,            -- trades, funding_settlements, candles
+    format VARCHAR(16) NOT NULL,          -- csv
+    symbol VARCHAR(128),                  -- candles only
+    period VARCHAR(8),                    -- candles only
+    market_id UUID,                       -- optional trades / funding filter
+    range_start TIMESTAMPTZ NOT NULL,
+    range_end TIMESTAMPTZ NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending',  -- pending, running, completed, failed
+    row_count BIGINT,
+    byte_size BIGINT,
+    data_key TEXT,
+    data_sha256 VARCHAR(64),
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    started_at TIMESTAMPTZ,
+    completed_at TIMESTAMPTZ,
+    CHECK (range_start < range_end)
+);
+
+CREATE INDEX IF NOT EXISTS idx_data_exports_user ON data_exports(user_address, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_data_exports_pending
+    ON data_exports(created_at) WHERE status IN ('pending', 'running');
@@ -0,0 +1,27 @@
+-- Per-symbol market statistics snapshots
+-- Migration: 0052_market_stats.sql
+
+-- One row per symbol ({market_id}:{outcome_id}:{share_type}) per minute
+CREATE TABLE IF NOT EXISTS market_stats (
+    symbol VARCHAR(128) NOT NULL,
+    ts TIMESTAMPTZ NOT NULL,
+    -- Outstanding shares (positive holdings)
+    open_interest DECIMAL(36, 18) NOT NULL,
+    long_open_interest DECIMAL(36, 18) NOT NULL,
+    -- Negative holdings, as a positive amount
+    short_open_interest DECIMAL(36, 18) NOT NULL,
+    -- NULL when there are no shorts
+    long_short_ratio DECIMAL(20, 4),
+    volume_24h DECIMAL(36, 18) NOT NULL DEFAULT 0,
+    quote_volume_24h DECIMAL(36, 18) NOT NULL DEFAULT 0,
+    trade_count_24h BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (symbol, ts)
+);
+
+SELECT create_hypertable(
+    'market_stats',
+    'ts',
+    chunk_time_interval => INTERVAL '7 days',
+    if_not_exists => TRUE,
+    migrate_data => TRUE
+);
@@ -0,0 +1,19 @@
+-- Official end-of-day settlement prices
+-- Migration: 0053_settlement_prices.sql
+
+-- One row per symbol ({market_id}:{outcome_id}:{share_type}) per UTC day
+CREATE TABLE IF NOT EXISTS settlement_prices (
+    symbol VARCHAR(128) NOT NULL,
+    -- UTC day the price settles (the window ends at the following 00:00)
+    settlement_date DATE NOT NULL,
+    price DECIMAL(36, 18) NOT NULL,
+    -- twap: time-weighted over the window; last_trade: no trades in the window
+    method VARCHAR(16) NOT NULL CHECK (method IN ('twap', 'last_trade')),
+    window_start TIMESTAMPTZ NOT NULL,
+    window_end TIMESTAMPTZ NOT NULL,
+    trade_count INTEGER NOT NULL DEFAULT 0,
+    published_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (symbol, settlement_date)
+);
+
+CREATE INDEX IF NOT EXISTS idx_settlement_prices_date ON settlement_prices(settlement_date);
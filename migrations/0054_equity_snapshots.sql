@@ -0,0 +1,28 @@
+-- Daily account equity snapshots
+-- Migration: 0054_equity_snapshots.sql
+
+-- One row per account per UTC day, taken once the day's settlement prices are published
+CREATE TABLE IF NOT EXISTS equity_snapshots (
+    user_address VARCHAR(42) NOT NULL,
+    snapshot_date DATE NOT NULL,
+    -- Collateral token the amounts are in
+    token VARCHAR(42) NOT NULL,
+    -- Collateral balance (available + frozen)
+    collateral DECIMAL(36, 18) NOT NULL,
+    -- Holdings marked at the day's settlement prices
+    positions_value DECIMAL(36, 18) NOT NULL,
+    unrealized_pnl DECIMAL(36, 18) NOT NULL,
+    equity DECIMAL(36, 18) NOT NULL,
+    -- Activity during the day
+    realized_pnl DECIMAL(36, 18) NOT NULL DEFAULT 0,
+    volume DECIMAL(36, 18) NOT NULL DEFAULT 0,
+    fees_paid DECIMAL(36, 18) NOT NULL DEFAULT 0,
+    trade_count INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_address, snapshot_date)
+);
+
+CREATE INDEX IF NOT EXISTS idx_equity_snapshots_date ON equity_snapshots(snapshot_date);
+
+COMMENT ON TABLE equity_snapshots IS 'End-of-day account equity and PnL, for portfolio charts';
+COMMENT ON COLUMN equity_snapshots.unrealized_pnl IS '(settlement price - avg_cost) * amount over held shares';
@@ -0,0 +1,40 @@
+-- Auto-MM protection against one-sided taker flow
+-- Migration: 0055_mm_imbalance_protection.sql
+
+-- Per-symbol parameters; symbols without a row use the built-in defaults
+CREATE TABLE IF NOT EXISTS mm_imbalance_params (
+    symbol VARCHAR(128) PRIMARY KEY,
+    enabled BOOLEAN NOT NULL DEFAULT true,
+    -- Rolling window of taker flow
+    window_secs INTEGER NOT NULL CHECK (window_secs > 0),
+    -- |buy - sell| / (buy + sell) that triggers protection
+    threshold DECIMAL(10, 4) NOT NULL CHECK (threshold > 0 AND threshold <= 1),
+    -- Taker volume the window needs before it can trigger
+    min_volume DECIMAL(36, 18) NOT NULL DEFAULT 0,
+    -- Ticks added to both sides of the MM's quotes while protected
+    widen_ticks INTEGER NOT NULL CHECK (widen_ticks >= 0),
+    -- Quote size multiplier on the exposed side while protected
+    size_factor DECIMAL(10, 4) NOT NULL CHECK (size_factor > 0 AND size_factor <= 1),
+    cooldown_secs INTEGER NOT NULL CHECK (cooldown_secs > 0),
+    updated_by VARCHAR(42),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- One row per protection triggered, with the parameters applied
+CREATE TABLE IF NOT EXISTS mm_imbalance_decisions (
+    id BIGSERIAL PRIMARY KEY,
+    symbol VARCHAR(128) NOT NULL,
+    -- Side of the MM's quotes being hit: sell when takers buy
+    exposed_side VARCHAR(4) NOT NULL CHECK (exposed_side IN ('buy', 'sell')),
+    imbalance DECIMAL(10, 4) NOT NULL,
+    buy_volume DECIMAL(36, 18) NOT NULL,
+    sell_volume DECIMAL(36, 18) NOT NULL,
+    window_secs INTEGER NOT NULL,
+    threshold DECIMAL(10, 4) NOT NULL,
+    widen_ticks INTEGER NOT NULL,
+    size_factor DECIMAL(10, 4) NOT NULL,
+    triggered_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_mm_imbalance_decisions_symbol ON mm_imbalance_decisions(symbol, triggered_at DESC);
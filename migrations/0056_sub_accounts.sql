@@ -0,0 +1,32 @@
+-- Named sub-accounts with isolated balances and positions
+-- Migration: 0056_sub_accounts.sql
+
+-- Each sub-account trades under its own derived address, so balances, orders
+-- and positions stay keyed by address and are isolated from the owner's
+CREATE TABLE IF NOT EXISTS sub_accounts (
+    id UUID PRIMARY KEY,
+    owner_address VARCHAR(42) NOT NULL,
+    label VARCHAR(64) NOT NULL,
+    -- keccak256(owner || id), last 20 bytes
+    address VARCHAR(42) NOT NULL UNIQUE,
+    -- Place, amend and cancel orders (read access is always granted)
+    can_trade BOOLEAN NOT NULL DEFAULT true,
+    status VARCHAR(16) NOT NULL DEFAULT 'active' CHECK (status IN ('active', 'frozen')),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (owner_address, label)
+);
+
+-- Internal transfers between an owner's main account and sub-accounts
+CREATE TABLE IF NOT EXISTS sub_account_transfers (
+    id UUID PRIMARY KEY,
+    owner_address VARCHAR(42) NOT NULL,
+    -- NULL is the main account
+    from_sub_account UUID REFERENCES sub_accounts(id),
+    to_sub_account UUID REFERENCES sub_accounts(id),
+    token VARCHAR(42) NOT NULL,
+    amount DECIMAL(36, 18) NOT NULL CHECK (amount > 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_sub_account_transfers_owner ON sub_account_transfers(owner_address, created_at DESC);
@@ -0,0 +1,23 @@
+-- API keys for trading bots
+-- Migration: 0057_api_keys.sql
+
+-- Secrets are not stored: a key's secret is HMAC-SHA256(server secret, key)
+CREATE TABLE IF NOT EXISTS api_keys (
+    id UUID PRIMARY KEY,
+    owner_address VARCHAR(42) NOT NULL,
+    label VARCHAR(64) NOT NULL,
+    -- Public key id sent in X-API-Key
+    api_key VARCHAR(64) NOT NULL UNIQUE,
+    -- read, trade (withdrawals are never granted to keys)
+    scopes TEXT[] NOT NULL,
+    -- Client IPs or CIDR ranges allowed to use the key (empty = any)
+    ip_allowlist TEXT[] NOT NULL DEFAULT '{}',
+    -- Act as this sub-account instead of the main account
+    sub_account_id UUID REFERENCES sub_accounts(id),
+    status VARCHAR(16) NOT NULL DEFAULT 'active' CHECK (status IN ('active', 'revoked')),
+    last_used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_api_keys_owner ON api_keys(owner_address, created_at DESC);
@@ -0,0 +1,27 @@
+-- Account risk profiles
+-- Migration: 0058_risk_profiles.sql
+
+-- Markets only accounts on a profile with high-volatility access may trade
+ALTER TABLE markets ADD COLUMN IF NOT EXISTS high_volatility BOOLEAN NOT NULL DEFAULT false;
+
+-- Selected profile per wallet; wallets without a row are on 'standard'
+CREATE TABLE IF NOT EXISTS account_risk_profiles (
+    user_address VARCHAR(42) PRIMARY KEY,
+    profile VARCHAR(16) NOT NULL CHECK (profile IN ('conservative', 'standard', 'pro')),
+    -- Disclosure version acknowledged for the current profile (upgrades only)
+    acknowledged_version INTEGER,
+    acknowledged_at TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Every profile change, with the acknowledgment given
+CREATE TABLE IF NOT EXISTS risk_profile_changes (
+    id BIGSERIAL PRIMARY KEY,
+    user_address VARCHAR(42) NOT NULL,
+    from_profile VARCHAR(16) NOT NULL,
+    to_profile VARCHAR(16) NOT NULL,
+    acknowledged_version INTEGER,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_risk_profile_changes_user ON risk_profile_changes(user_address, changed_at DESC);
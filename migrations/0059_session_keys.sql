@@ -0,0 +1,23 @@
+-- Session keys (delegated order signers)
+-- Migration: 0059_session_keys.sql
+
+-- A key the owner authorized (EIP-712 AuthorizeSessionKey) to sign its
+-- trading messages until it expires or is revoked
+CREATE TABLE IF NOT EXISTS session_keys (
+    id UUID PRIMARY KEY,
+    owner_address VARCHAR(42) NOT NULL,
+    session_address VARCHAR(42) NOT NULL,
+    -- place, amend, cancel
+    scopes TEXT[] NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    -- Owner's authorization signature
+    authorization_signature VARCHAR(132) NOT NULL,
+    status VARCHAR(16) NOT NULL DEFAULT 'active' CHECK (status IN ('active', 'revoked')),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    revoked_at TIMESTAMPTZ
+);
+
+-- One active delegation per (owner, session key); re-registering replaces it
+CREATE UNIQUE INDEX IF NOT EXISTS idx_session_keys_active
+    ON session_keys(owner_address, session_address) WHERE status = 'active';
+CREATE INDEX IF NOT EXISTS idx_session_keys_owner ON session_keys(owner_address, created_at DESC);
@@ -0,0 +1,34 @@
+-- Refresh tokens and access token revocation
+-- Migration: 0060_auth_tokens.sql
+
+-- Rotating refresh tokens; only a SHA-256 hash of each token is stored.
+-- Every refresh replaces the token with a new one of the same family, and
+-- presenting a replaced token again revokes the whole family.
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+    id UUID PRIMARY KEY,
+    user_address VARCHAR(42) NOT NULL,
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    -- Login the token descends from
+    family_id UUID NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- Exchanged for its successor
+    used_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_refresh_tokens_family ON refresh_tokens(family_id);
+CREATE INDEX IF NOT EXISTS idx_refresh_tokens_user ON refresh_tokens(user_address) WHERE revoked_at IS NULL;
+
+-- Access tokens (JWT ids) refused before they expire
+CREATE TABLE IF NOT EXISTS revoked_access_tokens (
+    jti VARCHAR(64) PRIMARY KEY,
+    user_address VARCHAR(42) NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_revoked_access_tokens_expiry ON revoked_access_tokens(expires_at);
+
+-- Access tokens issued before this time are refused (log out everywhere)
+ALTER TABLE users ADD COLUMN IF NOT EXISTS sessions_revoked_at TIMESTAMPTZ;
@@ -0,0 +1,13 @@
+-- Consumed signatures of signed messages without a nonce
+-- Migration: 0061_used_signatures.sql
+
+-- EIP-712 struct hash of every accepted nonce-less order, cancel, amend or
+-- withdrawal message, so the same signed payload is accepted once. Rows only
+-- need to outlive the signed timestamp's validity.
+CREATE TABLE IF NOT EXISTS used_signatures (
+    message_hash VARCHAR(66) PRIMARY KEY,
+    user_address VARCHAR(42) NOT NULL,
+    used_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_used_signatures_used_at ON used_signatures(used_at);
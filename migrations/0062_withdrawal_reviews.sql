@@ -0,0 +1,36 @@
+-- Withdrawal risk checks and manual review
+-- Migration: 0062_withdrawal_reviews.sql
+
+-- Withdrawal status becomes plain text: the handlers already write
+-- 'cancelled' and 'completed', which the original enum lacks, and review
+-- adds 'pending_review' and 'rejected'.
+DROP INDEX IF EXISTS idx_withdrawals_pending;
+ALTER TABLE withdrawals ALTER COLUMN status DROP DEFAULT;
+ALTER TABLE withdrawals ALTER COLUMN status TYPE VARCHAR(20) USING status::text;
+ALTER TABLE withdrawals ALTER COLUMN status SET DEFAULT 'pending';
+
+CREATE INDEX IF NOT EXISTS idx_withdrawals_pending
+    ON withdrawals(status, created_at) WHERE status IN ('pending', 'pending_review');
+-- Rolling daily limit
+CREATE INDEX IF NOT EXISTS idx_withdrawals_user_created ON withdrawals(user_address, token, created_at);
+
+-- Every risk decision on a withdrawal request: the automatic one when it is
+-- requested, and the reviewer's when it leaves the review queue. Requests
+-- rejected outright never become withdrawals (withdrawal_id is NULL).
+CREATE TABLE IF NOT EXISTS withdrawal_reviews (
+    id UUID PRIMARY KEY,
+    withdrawal_id UUID REFERENCES withdrawals(id),
+    user_address VARCHAR(42) NOT NULL,
+    token VARCHAR(42) NOT NULL,
+    amount DECIMAL(36, 18) NOT NULL,
+    decision VARCHAR(16) NOT NULL CHECK (decision IN ('approved', 'review', 'rejected')),
+    -- daily_limit, large_amount, deposit_velocity
+    reasons TEXT[] NOT NULL DEFAULT '{}',
+    -- Admin address; NULL for automatic decisions
+    reviewer VARCHAR(42),
+    note TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_withdrawal_reviews_withdrawal ON withdrawal_reviews(withdrawal_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_withdrawal_reviews_user ON withdrawal_reviews(user_address, created_at DESC);
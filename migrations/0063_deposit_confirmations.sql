@@ -0,0 +1,15 @@
+-- Deposit confirmations and reorg handling
+-- Migration: 0063_deposit_confirmations.sql
+
+-- Deposits are recorded as soon as their log is seen and credited once
+-- buried under `deposit_confirmations` blocks:
+--   pending -> credited -> finalized
+--   pending -> reorged   (transaction left the chain before crediting)
+--   credited -> reverted (left the chain before finality; credit reversed)
+ALTER TABLE deposits ADD COLUMN IF NOT EXISTS block_hash VARCHAR(66);
+ALTER TABLE deposits ADD COLUMN IF NOT EXISTS finalized_at TIMESTAMPTZ;
+ALTER TABLE deposits ADD COLUMN IF NOT EXISTS reverted_at TIMESTAMPTZ;
+
+-- Deposits the confirmation tracker still rechecks
+CREATE INDEX IF NOT EXISTS idx_deposits_unsettled
+    ON deposits(block_number) WHERE status IN ('pending', 'credited');
@@ -0,0 +1,11 @@
+-- Multi-chain deposits and withdrawals
+-- Migration: 0064_multi_chain.sql
+
+-- Chain a deposit was made on / a withdrawal is paid out on. NULL for rows
+-- from before multi-chain support, which belong to the primary chain.
+ALTER TABLE deposits ADD COLUMN IF NOT EXISTS chain_id BIGINT;
+ALTER TABLE withdrawals ADD COLUMN IF NOT EXISTS chain_id BIGINT;
+
+DROP INDEX IF EXISTS idx_deposits_unsettled;
+CREATE INDEX IF NOT EXISTS idx_deposits_unsettled
+    ON deposits(chain_id, block_number) WHERE status IN ('pending', 'credited');
@@ -0,0 +1,28 @@
+-- Transactional outbox for events published to Redis
+-- Migration: 0065_event_outbox.sql
+
+-- Events are written in the same transaction as the rows they describe and
+-- published afterwards by the outbox relay, so a Redis outage delays them
+-- instead of losing them. `sequence` numbers each channel's events without
+-- gaps, letting consumers spot duplicates and missed messages.
+CREATE TABLE IF NOT EXISTS event_outbox (
+    id BIGSERIAL PRIMARY KEY,
+    channel VARCHAR(128) NOT NULL,
+    sequence BIGINT NOT NULL,
+    payload JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    published_at TIMESTAMPTZ,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    UNIQUE (channel, sequence)
+);
+
+-- Events the relay still has to publish, oldest first
+CREATE INDEX IF NOT EXISTS idx_event_outbox_pending ON event_outbox(id) WHERE published_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_event_outbox_created ON event_outbox(created_at);
+
+-- Last sequence handed out per channel; the row lock serializes writers of a channel
+CREATE TABLE IF NOT EXISTS event_outbox_sequences (
+    channel VARCHAR(128) PRIMARY KEY,
+    last_sequence BIGINT NOT NULL
+);
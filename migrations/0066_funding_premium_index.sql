@@ -0,0 +1,16 @@
+-- Premium index samples behind funding rates
+-- Migration: 0066_funding_premium_index.sql
+
+-- One sample per outcome (`{market_id}:{outcome_id}`) per minute: the YES
+-- book's mark price against its index price. The running average over a
+-- funding interval is the predicted rate for that interval.
+CREATE TABLE IF NOT EXISTS funding_premium_index (
+    symbol VARCHAR(128) NOT NULL,
+    ts TIMESTAMPTZ NOT NULL,
+    mark_price DECIMAL(36, 18) NOT NULL,
+    index_price DECIMAL(36, 18) NOT NULL,
+    premium DECIMAL(36, 18) NOT NULL,
+    PRIMARY KEY (symbol, ts)
+);
+
+CREATE INDEX IF NOT EXISTS idx_funding_premium_index_ts ON funding_premium_index(ts);
@@ -0,0 +1,53 @@
+-- Closed position history
+-- Migration: 0067_position_history.sql
+
+-- Running totals of the position a holding currently carries, from the fill
+-- that opened it (`opened_at`) on; archived to position_history and reset
+-- when the position closes (back to zero shares, or flipped long <-> short)
+ALTER TABLE shares
+    ADD COLUMN IF NOT EXISTS opened_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS entry_amount DECIMAL(30, 8) NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS entry_notional DECIMAL(36, 18) NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS exit_amount DECIMAL(30, 8) NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS exit_notional DECIMAL(36, 18) NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS realized_pnl DECIMAL(36, 18) NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS fees_paid DECIMAL(36, 18) NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS funding_paid DECIMAL(36, 18) NOT NULL DEFAULT 0;
+
+-- Open positions predating the columns start from their current holding
+UPDATE shares
+SET opened_at = created_at, entry_amount = ABS(amount), entry_notional = ABS(amount) * avg_cost
+WHERE amount <> 0 AND opened_at IS NULL;
+
+-- One row per closed position
+CREATE TABLE IF NOT EXISTS position_history (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    share_id UUID NOT NULL REFERENCES shares(id),
+    user_address VARCHAR(42) NOT NULL,
+    market_id UUID NOT NULL REFERENCES markets(id),
+    outcome_id UUID NOT NULL REFERENCES outcomes(id),
+    share_type share_type NOT NULL,
+    side VARCHAR(8) NOT NULL,
+    token VARCHAR(42) NOT NULL,
+    amount DECIMAL(30, 8) NOT NULL,
+    avg_entry_price DECIMAL(30, 8) NOT NULL,
+    avg_exit_price DECIMAL(30, 8) NOT NULL,
+    realized_pnl DECIMAL(36, 18) NOT NULL,
+    fees DECIMAL(36, 18) NOT NULL,
+    funding DECIMAL(36, 18) NOT NULL,
+    close_reason VARCHAR(16) NOT NULL,
+    trade_id UUID,
+    opened_at TIMESTAMPTZ NOT NULL,
+    closed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT position_history_side CHECK (side IN ('long', 'short')),
+    CONSTRAINT position_history_close_reason CHECK (close_reason IN ('trade', 'liquidation', 'settlement'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_position_history_user ON position_history(user_address, closed_at DESC);
+
+-- Comments
+COMMENT ON COLUMN position_history.amount IS 'Shares opened over the life of the position';
+COMMENT ON COLUMN position_history.realized_pnl IS 'Realized PnL in the collateral token, before fees';
+COMMENT ON COLUMN position_history.fees IS 'Trading fees of the fills that opened and closed the position';
+COMMENT ON COLUMN position_history.funding IS 'Funding paid while open (negative = received)';
@@ -0,0 +1,38 @@
+-- Multi-leg take-profit / stop-loss on positions
+-- Migration: 0068_position_tp_sl_legs.sql
+
+-- Each TP/SL set on a holding is a group of legs sharing a set_id: take-profit
+-- legs closing a percentage of the position as it was when the set was placed
+-- (`base_amount`, signed), executed in `leg_index` order, and at most one stop
+-- closing whatever remains. Placing a new set cancels the pending legs of the
+-- previous one.
+CREATE TABLE IF NOT EXISTS position_tp_sl_legs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    set_id UUID NOT NULL,
+    share_id UUID NOT NULL REFERENCES shares(id),
+    user_address VARCHAR(42) NOT NULL,
+    kind VARCHAR(16) NOT NULL,
+    leg_index SMALLINT NOT NULL,
+    trigger_price DECIMAL(30, 8) NOT NULL,
+    close_percent DECIMAL(10, 4),
+    base_amount DECIMAL(30, 8) NOT NULL,
+    status VARCHAR(16) NOT NULL DEFAULT 'pending',
+    order_id UUID,
+    filled_amount DECIMAL(30, 8),
+    avg_price DECIMAL(30, 8),
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT position_tp_sl_legs_kind CHECK (kind IN ('take_profit', 'stop_loss')),
+    CONSTRAINT position_tp_sl_legs_status CHECK (status IN ('pending', 'triggered', 'executed', 'failed', 'cancelled')),
+    CONSTRAINT position_tp_sl_legs_percent CHECK (
+        (kind = 'take_profit' AND close_percent > 0 AND close_percent <= 100)
+        OR (kind = 'stop_loss' AND close_percent IS NULL)
+    )
+);
+
+-- Legs the keeper watches
+CREATE INDEX IF NOT EXISTS idx_position_tp_sl_legs_pending
+    ON position_tp_sl_legs(share_id, leg_index) WHERE status = 'pending';
+CREATE INDEX IF NOT EXISTS idx_position_tp_sl_legs_share ON position_tp_sl_legs(share_id, created_at DESC);
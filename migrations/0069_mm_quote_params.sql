@@ -0,0 +1,23 @@
+-- Auto-MM two-sided quoting
+-- Migration: 0069_mm_quote_params.sql
+
+-- Per-symbol quoting parameters; symbols without a row are not quoted
+CREATE TABLE IF NOT EXISTS mm_quote_params (
+    symbol VARCHAR(128) PRIMARY KEY,
+    enabled BOOLEAN NOT NULL DEFAULT true,
+    -- Distance between the innermost bid and ask
+    spread DECIMAL(10, 4) NOT NULL CHECK (spread > 0 AND spread < 1),
+    -- Quotes per side, each further level `level_spacing` out
+    levels INTEGER NOT NULL CHECK (levels > 0),
+    level_spacing DECIMAL(10, 4) NOT NULL CHECK (level_spacing >= 0),
+    level_size DECIMAL(30, 8) NOT NULL CHECK (level_size > 0),
+    -- Shift of the quotes' center at a full max_position, against the inventory
+    skew DECIMAL(10, 4) NOT NULL CHECK (skew >= 0),
+    max_position DECIMAL(30, 8) NOT NULL CHECK (max_position > 0),
+    -- 24h loss that halts the symbol
+    max_loss DECIMAL(36, 18) NOT NULL CHECK (max_loss > 0),
+    -- Index move that requotes the symbol
+    refresh_threshold DECIMAL(10, 4) NOT NULL CHECK (refresh_threshold > 0),
+    updated_by VARCHAR(42),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
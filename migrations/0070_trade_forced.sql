@@ -0,0 +1,6 @@
+-- Trades forced on their taker by a liquidation or ADL
+-- Migration: 0070_trade_forced.sql
+
+-- NULL for voluntary trades
+ALTER TABLE trades ADD COLUMN IF NOT EXISTS forced VARCHAR(16) CHECK (forced IN ('liquidation', 'adl'));
+CREATE INDEX IF NOT EXISTS idx_trades_forced ON trades(market_id, created_at DESC) WHERE forced IS NOT NULL;
@@ -0,0 +1,26 @@
+-- Per-user attribution of each trade fill
+-- Migration: 0071_trade_fills.sql
+
+-- One row per party to a trade: the position it hit, the user's own side,
+-- share type and price (complements for mint / merge makers), fee and the
+-- PnL the fill realized. Kept past trade archiving, so no FK to trades.
+CREATE TABLE IF NOT EXISTS trade_fills (
+    trade_id UUID NOT NULL,
+    user_address VARCHAR(42) NOT NULL,
+    role VARCHAR(8) NOT NULL CHECK (role IN ('maker', 'taker')),
+    side VARCHAR(4) NOT NULL CHECK (side IN ('buy', 'sell')),
+    market_id UUID NOT NULL,
+    outcome_id UUID NOT NULL,
+    share_type share_type NOT NULL,
+    position_id UUID NOT NULL,
+    price DECIMAL(30, 8) NOT NULL,
+    amount DECIMAL(30, 8) NOT NULL,
+    fee DECIMAL(30, 8) NOT NULL DEFAULT 0,
+    fee_token VARCHAR(42) NOT NULL,
+    realized_pnl DECIMAL(36, 18) NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (trade_id, user_address, role)
+);
+
+CREATE INDEX IF NOT EXISTS idx_trade_fills_user ON trade_fills(user_address, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_trade_fills_position ON trade_fills(position_id);
@@ -0,0 +1,30 @@
+-- Scheduled referral earnings settlement
+-- Migration: 0072_referral_epochs.sql
+
+-- One row per settled epoch; the unique end keeps each epoch paid once
+CREATE TABLE IF NOT EXISTS referral_epochs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    start_at TIMESTAMPTZ NOT NULL,
+    end_at TIMESTAMPTZ NOT NULL UNIQUE,
+    referrer_count INTEGER NOT NULL DEFAULT 0,
+    earnings_count INTEGER NOT NULL DEFAULT 0,
+    settled_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_referral_epochs_end ON referral_epochs(end_at DESC);
+
+-- What each referrer was paid for an epoch, per token
+CREATE TABLE IF NOT EXISTS referral_payouts (
+    epoch_id UUID NOT NULL REFERENCES referral_epochs(id),
+    referrer_address VARCHAR(42) NOT NULL,
+    token VARCHAR(42) NOT NULL,
+    amount DECIMAL(36, 18) NOT NULL,
+    earnings_count INTEGER NOT NULL,
+    PRIMARY KEY (epoch_id, referrer_address, token)
+);
+
+CREATE INDEX IF NOT EXISTS idx_referral_payouts_referrer ON referral_payouts(referrer_address, epoch_id);
+
+-- Epoch that paid the earning (NULL while pending or for manual claims)
+ALTER TABLE referral_earnings ADD COLUMN IF NOT EXISTS epoch_id UUID REFERENCES referral_epochs(id);
+CREATE INDEX IF NOT EXISTS idx_referral_earnings_epoch ON referral_earnings(epoch_id);
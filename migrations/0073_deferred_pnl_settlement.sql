@@ -0,0 +1,9 @@
+-- Realized PnL whose settlement rate was not known when the trade was persisted
+-- Migration: 0073_deferred_pnl_settlement.sql
+
+-- Pending rows carry the unconverted PnL in quote_realized_pnl and no credit yet
+ALTER TABLE pnl_history ADD COLUMN IF NOT EXISTS settlement_pending BOOLEAN NOT NULL DEFAULT FALSE;
+CREATE INDEX IF NOT EXISTS idx_pnl_history_pending ON pnl_history(created_at) WHERE settlement_pending;
+
+-- The PnL row a fill realized, so a deferred settlement can update its attribution
+ALTER TABLE trade_fills ADD COLUMN IF NOT EXISTS pnl_id UUID;
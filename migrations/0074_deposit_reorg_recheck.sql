@@ -0,0 +1,23 @@
+-- Deposit reorg recheck
+-- Migration: 0074_deposit_reorg_recheck.sql
+
+-- A deposit whose receipt is missing is reverted only after it stays missing
+-- over several polls and blocks, so one lagging or load-balanced node can't
+-- reverse a credit:
+--   missing_polls  consecutive polls without a receipt
+--   missing_since  chain head at the first of them
+ALTER TABLE deposits ADD COLUMN IF NOT EXISTS missing_polls INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE deposits ADD COLUMN IF NOT EXISTS missing_since BIGINT;
+
+-- Times the deposit left the chain. A `reorged` or `reverted` deposit whose
+-- transaction reappears goes back to `pending`, and each generation credits
+-- and reverses under its own ledger key.
+ALTER TABLE deposits ADD COLUMN IF NOT EXISTS reorgs INTEGER NOT NULL DEFAULT 0;
+
+-- `reverted_at` now marks either status leaving the chain
+UPDATE deposits SET reverted_at = NOW()
+WHERE status = 'reorged' AND reverted_at IS NULL;
+
+-- Dropped deposits the tracker rechecks for a reappearing receipt
+CREATE INDEX IF NOT EXISTS idx_deposits_dropped
+    ON deposits(reverted_at) WHERE status IN ('reorged', 'reverted');
@@ -0,0 +1,6 @@
+-- Reconciling stale pending liquidations
+-- Migration: 0075_liquidation_recovery.sql
+
+-- Pending liquidations by age, and the trades a liquidation order took
+CREATE INDEX IF NOT EXISTS idx_liquidations_pending_age ON liquidations(created_at) WHERE status = 'pending';
+CREATE INDEX IF NOT EXISTS idx_trades_taker_order ON trades(taker_order_id);
@@ -0,0 +1,47 @@
+-- Double-entry postings for the balance ledger
+-- Migration: 0076_ledger_postings.sql
+
+-- Balances of the platform's system accounts (chain, treasury, insurance
+-- fund, clearing), per token
+CREATE TABLE IF NOT EXISTS ledger_accounts (
+    account VARCHAR(32) NOT NULL,
+    token VARCHAR(42) NOT NULL,
+    balance DECIMAL(36, 18) NOT NULL DEFAULT 0,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (account, token)
+);
+
+-- Legs of each journal entry: the user's available / frozen buckets and the
+-- system account on the other side
+CREATE TABLE IF NOT EXISTS ledger_postings (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    entry_id UUID NOT NULL REFERENCES balance_ledger(id),
+    account VARCHAR(42) NOT NULL,
+    bucket VARCHAR(16) NOT NULL,
+    token VARCHAR(42) NOT NULL,
+    amount DECIMAL(36, 18) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (amount <> 0)
+);
+
+CREATE INDEX IF NOT EXISTS idx_ledger_postings_entry ON ledger_postings(entry_id);
+CREATE INDEX IF NOT EXISTS idx_ledger_postings_account ON ledger_postings(account, token, created_at DESC);
+
+-- An entry's legs must balance by the time its transaction commits
+CREATE OR REPLACE FUNCTION check_ledger_entry_balanced() RETURNS TRIGGER AS $$
+DECLARE
+    total DECIMAL(36, 18);
+BEGIN
+    SELECT SUM(amount) INTO total FROM ledger_postings WHERE entry_id = NEW.entry_id;
+    IF total <> 0 THEN
+        RAISE EXCEPTION 'ledger entry % is unbalanced by %', NEW.entry_id, total;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS ledger_postings_balanced ON ledger_postings;
+CREATE CONSTRAINT TRIGGER ledger_postings_balanced
+    AFTER INSERT ON ledger_postings
+    DEFERRABLE INITIALLY DEFERRED
+    FOR EACH ROW EXECUTE FUNCTION check_ledger_entry_balanced();
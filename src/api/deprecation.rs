@@ -0,0 +1,546 @@
+//! API Changelog and Deprecations
+//!
+//! The changelog of the public API and the registry of deprecated endpoints
+//! and fields are kept here, in the crate, so they ship with the code they
+//! describe. [`deprecation_middleware`] stamps every response of a deprecated
+//! route with machine-readable signals:
+//!
+//! - `Deprecation: @<unix seconds>` (RFC 9745): when the route (or a field of
+//!   it) was deprecated
+//! - `Sunset: <HTTP date>` (RFC 8594): when it stops being served, if decided
+//! - `Link: <...>; rel="deprecation"` pointing at the changelog entry, plus
+//!   `rel="successor-version"` when a replacement exists
+//! - `X-Deprecated-Fields`: deprecated fields of a route that is otherwise
+//!   still supported
+//!
+//! `GET /api/v1/changelog` serves [`CHANGELOG`] and [`DEPRECATIONS`].
+//! Handlers never set these headers themselves: deprecating something is one
+//! registry entry plus one changelog entry.
+
+use axum::{
+    extract::{MatchedPath, Request},
+    http::{HeaderName, HeaderValue, Method},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+
+/// Deprecated fields of a route that is still supported
+pub const DEPRECATED_FIELDS_HEADER: &str = "x-deprecated-fields";
+
+/// Where `Link: rel="deprecation"` points
+const CHANGELOG_PATH: &str = "/api/v1/changelog";
+
+/// Kind of API change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Changed,
+    Deprecated,
+    Removed,
+}
+
+/// One changelog entry
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangelogEntry {
+    /// Stable anchor, referenced by deprecation links
+    pub id: &'static str,
+    pub date: &'static str,
+    pub kind: ChangeKind,
+    /// `METHOD /path` of the affected routes
+    pub routes: &'static [&'static str],
+    pub summary: &'static str,
+}
+
+/// A deprecated route, or deprecated fields of a route
+#[derive(Debug, Clone, Serialize)]
+pub struct Deprecation {
+    pub method: &'static str,
+    /// Full route pattern, as matched (`/api/v1/markets/:market_id/price`)
+    pub path: &'static str,
+    /// Deprecated fields; empty when the whole route is deprecated
+    pub fields: &'static [&'static str],
+    /// Day the deprecation was announced (`YYYY-MM-DD`, UTC)
+    pub deprecated: &'static str,
+    /// Day the route or fields stop being served, if decided
+    pub sunset: Option<&'static str>,
+    /// Route to use instead
+    pub successor: Option<&'static str>,
+    /// Changelog entry announcing it
+    pub changelog_id: &'static str,
+}
+
+/// API changes, newest first
+pub const CHANGELOG: &[ChangelogEntry] = &[
+    ChangelogEntry {
+        id: "2026-10-15-referral-epochs",
+        date: "2026-10-15",
+        kind: ChangeKind::Added,
+        routes: &["GET /api/v1/referral/epochs"],
+        summary: "Referral earnings are paid into balances automatically at the end of each settlement epoch \
+                  (weekly, Monday 00:00 UTC). The endpoint lists settled epochs with the user's payouts per token, \
+                  pending earnings, the next settlement time and the minimum payout below which earnings carry \
+                  over.",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-trade-fills",
+        date: "2026-10-15",
+        kind: ChangeKind::Changed,
+        routes: &["GET /api/v1/account/trades", "POST /api/v1/exports"],
+        summary: "Trades carry the user's `role` (`maker` / `taker`), `fee_token` and the `position_id` the fill was \
+                  netted into. `side`, `share_type`, `price` and `realized_pnl` are the user's own for the fill \
+                  (mint / merge makers see the complement share type and price); trades before this change keep \
+                  the taker side and report null `fee_token` / `position_id`. A self-trade is listed once per \
+                  role. Trade and tax exports use the same attribution; trade exports add `fee_token` and \
+                  `realized_pnl` columns.",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-trade-flags",
+        date: "2026-10-15",
+        kind: ChangeKind::Added,
+        routes: &["GET /api/v1/markets/:market_id/trades"],
+        summary: "Trades carry `aggressor` (the side that took liquidity, null for block trades) and `forced` \
+                  (`liquidation` or `adl` when the taker order was placed by force), as do WebSocket trade messages.",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-ticker-book",
+        date: "2026-10-15",
+        kind: ChangeKind::Added,
+        routes: &["GET /api/v1/markets/:market_id/ticker", "GET /api/v2/markets/:market_id/ticker"],
+        summary: "Outcomes carry `yes_book` / `no_book`: best bid / ask, spread, a depth-weighted mid and the bid / \
+                  ask imbalance of the top levels of the live book. The `ticker:{symbol}` WebSocket channel now \
+                  pushes the same figures with the rolling 24h statistics every 2 seconds.",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-position-tp-sl",
+        date: "2026-10-15",
+        kind: ChangeKind::Added,
+        routes: &[
+            "GET /api/v1/positions/:position_id/tp-sl",
+            "PUT /api/v1/positions/:position_id/tp-sl",
+            "DELETE /api/v1/positions/:position_id/tp-sl",
+        ],
+        summary: "Take-profit / stop-loss sets on positions: up to 5 take-profit legs each closing a percentage of \
+                  the position (100% at most in total), executed in order, plus a stop closing the remainder.",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-position-history",
+        date: "2026-10-15",
+        kind: ChangeKind::Added,
+        routes: &["GET /api/v1/account/positions/history"],
+        summary: "Closed positions with their open / close times, average entry and exit prices, realized PnL, fees \
+                  and funding paid, and totals (win / loss count, net PnL) over the filter.",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-funding-premium-index",
+        date: "2026-10-15",
+        kind: ChangeKind::Added,
+        routes: &[
+            "GET /api/v1/funding-rates/:symbol/predicted",
+            "GET /api/v1/funding-rates/:symbol/premium-index",
+        ],
+        summary: "Per-minute premium index samples of an outcome and the predicted rate of the current funding \
+                  interval (their running average, clamped to the funding bounds).",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-trades-cursor",
+        date: "2026-10-15",
+        kind: ChangeKind::Added,
+        routes: &["GET /api/v1/markets/:market_id/trades"],
+        summary: "Pages of trades: `before` / `after` take a trade id cursor, `side` filters by taker side, `limit` \
+                  goes up to 1000, and the response reports `has_more`.",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-orderbook-grouping",
+        date: "2026-10-15",
+        kind: ChangeKind::Added,
+        routes: &["GET /api/v1/markets/:market_id/orderbook"],
+        summary: "`group` merges levels into price buckets of that size (bids round down, asks up); `depth` then \
+                  counts buckets and the response echoes `group`.",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-multi-chain",
+        date: "2026-10-15",
+        kind: ChangeKind::Changed,
+        routes: &[
+            "POST /api/v1/deposit/prepare",
+            "GET /api/v1/deposit/history",
+            "POST /api/v1/withdraw/request",
+            "GET /api/v1/withdraw/history",
+            "GET /api/v1/withdraw/:id",
+            "GET /api/v1/account/balances",
+        ],
+        summary: "Deposits and withdrawals run on several chains. `POST /deposit/prepare` and \
+                  `POST /withdraw/request` take an optional `chain_id` (default: the primary chain); deposits and \
+                  withdrawals report their `chain_id`, and `required_confirmations` moved onto each deposit. \
+                  Balances are shared across chains and list the `chains` each token can be withdrawn on.",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-deposit-confirmations",
+        date: "2026-10-15",
+        kind: ChangeKind::Changed,
+        routes: &["GET /api/v1/deposit/history"],
+        summary: "Deposits are credited after `required_confirmations` block confirmations. Each deposit carries \
+                  its `block_number` and current `confirmations`, and its status moves from `pending` to \
+                  `credited` and `finalized`; deposits dropped by a chain reorg become `reorged`, or \
+                  `reverted` with the credit reversed.",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-withdrawal-review",
+        date: "2026-10-15",
+        kind: ChangeKind::Changed,
+        routes: &[
+            "POST /api/v1/withdraw/request",
+            "DELETE /api/v1/withdraw/:id/cancel",
+            "POST /api/v1/withdraw/:id/confirm",
+        ],
+        summary: "Withdrawals are risk checked: requests over the per-token daily limit fail with \
+                  `WITHDRAW_DAILY_LIMIT`, and large withdrawals or withdrawals shortly after a large deposit are \
+                  created with status `pending_review`. They can be cancelled but not confirmed until approved \
+                  (status `pending`) or rejected (status `rejected`, funds unfrozen).",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-signature-replay",
+        date: "2026-10-15",
+        kind: ChangeKind::Changed,
+        routes: &[
+            "POST /api/v1/orders",
+            "DELETE /api/v1/orders/:order_id",
+            "PUT /api/v1/orders/:order_id",
+            "POST /api/v1/orders/batch",
+            "POST /api/v1/withdraw",
+        ],
+        summary: "A signed message without a nonce is accepted once: resubmitting the same signed payload within its \
+                  timestamp window fails with `SIGNATURE_REUSED`. Withdrawals accept the wallet's EIP-712 \
+                  `Withdraw` signature (`signature`, `timestamp`, optional `nonce`), required once \
+                  `require_withdraw_signature` is enabled.",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-refresh-tokens",
+        date: "2026-10-15",
+        kind: ChangeKind::Added,
+        routes: &["POST /api/v1/auth/login", "POST /api/v1/auth/refresh", "POST /api/v1/auth/logout"],
+        summary: "Login also returns a rotating `refresh_token` (with `refresh_expires_at`); `POST /auth/refresh` \
+                  exchanges it for a new pair, and reusing a spent refresh token revokes the login's sessions. \
+                  `POST /auth/logout` revokes the current access token, or every session with `all`. Revoked \
+                  tokens are refused over HTTP and WebSocket with 401.",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-session-keys",
+        date: "2026-10-15",
+        kind: ChangeKind::Added,
+        routes: &[
+            "GET /api/v1/account/session-keys",
+            "POST /api/v1/account/session-keys",
+            "DELETE /api/v1/account/session-keys/:id",
+            "POST /api/v1/orders",
+        ],
+        summary: "Session keys: a wallet authorizes a delegated signer (EIP-712 `AuthorizeSessionKey`) with an \
+                  expiry and scopes (place / amend / cancel). Order, amend and cancel signatures from an active \
+                  session key are accepted in place of the wallet's.",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-risk-disclosure",
+        date: "2026-10-15",
+        kind: ChangeKind::Added,
+        routes: &["GET /api/v1/markets/:symbol/risk-disclosure", "GET /api/v1/markets/:market_id/config"],
+        summary: "Machine-readable risk disclosure per symbol: fee schedule and VIP tiers, funding interval and rate \
+                  clamp, maintenance tiers, liquidation fee and its split, deleveraging policy, price limits and \
+                  mark price method, built from the live settings. Market config gains `max_fee`.",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-risk-profiles",
+        date: "2026-10-15",
+        kind: ChangeKind::Added,
+        routes: &[
+            "GET /api/v1/account/risk-profile",
+            "PUT /api/v1/account/risk-profile",
+            "GET /api/v1/account/profile",
+            "POST /api/v1/orders",
+        ],
+        summary: "Account risk profiles (conservative / standard / pro) capping leverage and open notional and \
+                  gating high-volatility markets; upgrades acknowledge the risk disclosure version. The profile is \
+                  returned as `risk_profile` in the account profile and orders beyond it fail with \
+                  `RISK_PROFILE_LIMIT`.",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-api-keys",
+        date: "2026-10-15",
+        kind: ChangeKind::Added,
+        routes: &["GET /api/v1/account/api-keys", "POST /api/v1/account/api-keys", "DELETE /api/v1/account/api-keys/:id"],
+        summary: "API keys with `read` / `trade` scopes, IP allowlists and revocation. Requests signed with \
+                  `X-API-Key`, `X-API-Timestamp` and `X-API-Signature` (HMAC-SHA256) need no per-order EIP-712 \
+                  signature; withdrawals are never available to keys.",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-sub-accounts",
+        date: "2026-10-15",
+        kind: ChangeKind::Added,
+        routes: &[
+            "GET /api/v1/account/sub-accounts",
+            "POST /api/v1/account/sub-accounts",
+            "PUT /api/v1/account/sub-accounts/:id",
+            "POST /api/v1/account/sub-accounts/transfer",
+            "GET /api/v1/account/sub-accounts/transfers",
+        ],
+        summary: "Named sub-accounts with isolated balances and positions. Send `X-Sub-Account: <id>` to act as \
+                  one (orders are signed by the owner's wallet with the sub-account address as `wallet`); \
+                  read-only sub-accounts cannot trade and sub-accounts cannot withdraw.",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-tax-exports",
+        date: "2026-10-15",
+        kind: ChangeKind::Added,
+        routes: &["GET /api/v1/account/export/tax", "POST /api/v1/exports"],
+        summary: "Yearly tax exports of trades, funding and fees in Koinly or CoinTracking CSV layout, run as \
+                  history export jobs (`kind: tax`, `format: koinly | cointracking`).",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-portfolio-pnl",
+        date: "2026-10-15",
+        kind: ChangeKind::Added,
+        routes: &["GET /api/v1/account/pnl/daily", "GET /api/v1/account/pnl/summary"],
+        summary: "Daily equity snapshots (marked at settlement prices) and PnL summaries with win rate, volume \
+                  and fees paid over a date range.",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-error-statuses",
+        date: "2026-10-15",
+        kind: ChangeKind::Changed,
+        routes: &[
+            "POST /api/v1/orders",
+            "PUT /api/v1/orders/:order_id",
+            "POST /api/v1/withdraw/quote",
+            "POST /api/v1/withdraw/request",
+        ],
+        summary: "Service failures are classified: unknown orders and markets return 404, invalid requests 400, \
+                  lost races 409 and matching or database outages 503 (retryable) instead of a blanket 500.",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-settlement-prices",
+        date: "2026-10-15",
+        kind: ChangeKind::Added,
+        routes: &["GET /api/v1/markets/:symbol/settlement-prices"],
+        summary: "Official end-of-day settlement price per symbol (30-minute TWAP before 00:00 UTC), also \
+                  pushed on the `settlement_prices:{symbol}` WebSocket channel.",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-rolling-ticker",
+        date: "2026-10-15",
+        kind: ChangeKind::Added,
+        routes: &["GET /api/v1/markets/:market_id/ticker", "GET /api/v2/markets/:market_id/ticker"],
+        summary: "Outcomes carry `yes_24h` / `no_24h`: rolling 24h open, high, low, last, change and volume, \
+                  rebuilt from persisted trades on startup.",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-market-stats",
+        date: "2026-10-15",
+        kind: ChangeKind::Added,
+        routes: &["GET /api/v1/markets/:symbol/stats/history"],
+        summary: "Per-minute open interest, long/short ratio and 24h volume snapshots, served in K-line periods.",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-market-price-deprecated",
+        date: "2026-10-15",
+        kind: ChangeKind::Deprecated,
+        routes: &["GET /api/v1/markets/:market_id/price", "GET /api/v2/markets/:market_id/price"],
+        summary: "The price endpoint returns the same body as the ticker endpoint; use /markets/:market_id/ticker. \
+                  It is removed on the sunset date.",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-history-exports",
+        date: "2026-10-15",
+        kind: ChangeKind::Added,
+        routes: &[
+            "POST /api/v1/exports",
+            "GET /api/v1/exports",
+            "GET /api/v1/exports/:export_id",
+            "GET /api/v1/exports/:export_id/download",
+        ],
+        summary: "Bulk CSV exports of account trades, funding settlements and market candles, run as async jobs.",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-order-durability",
+        date: "2026-10-15",
+        kind: ChangeKind::Added,
+        routes: &["POST /api/v1/orders", "PUT /api/v1/account/preferences"],
+        summary: "Orders accept `durability` (`ack_fast` or `ack_after_journal`); the response reports the mode \
+                  applied and, for journal acknowledgments, `journal_sequence`.",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-margin-calls",
+        date: "2026-10-15",
+        kind: ChangeKind::Added,
+        routes: &[
+            "GET /api/v1/account/margin-calls",
+            "PUT /api/v1/account/margin-calls",
+            "GET /api/v1/account/margin-calls/history",
+        ],
+        summary: "Per-account margin call escalation ladder and the log of actions it took.",
+    },
+    ChangelogEntry {
+        id: "2026-10-15-klines",
+        date: "2026-10-15",
+        kind: ChangeKind::Added,
+        routes: &["GET /api/v1/market-data/klines/:symbol"],
+        summary: "Candles for 1m through 1M periods, with idle buckets filled by flat candles.",
+    },
+];
+
+/// Deprecated routes and fields
+pub const DEPRECATIONS: &[Deprecation] = &[
+    Deprecation {
+        method: "GET",
+        path: "/api/v1/markets/:market_id/price",
+        fields: &[],
+        deprecated: "2026-10-15",
+        sunset: Some("2027-04-15"),
+        successor: Some("/api/v1/markets/:market_id/ticker"),
+        changelog_id: "2026-10-15-market-price-deprecated",
+    },
+    Deprecation {
+        method: "GET",
+        path: "/api/v2/markets/:market_id/price",
+        fields: &[],
+        deprecated: "2026-10-15",
+        sunset: Some("2027-04-15"),
+        successor: Some("/api/v2/markets/:market_id/ticker"),
+        changelog_id: "2026-10-15-market-price-deprecated",
+    },
+];
+
+/// Midnight UTC of a `YYYY-MM-DD` registry date
+fn day_start(date: &str) -> Option<DateTime<Utc>> {
+    let day = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+    Some(day.and_hms_opt(0, 0, 0)?.and_utc())
+}
+
+/// IMF-fixdate, as used by `Sunset`
+fn http_date(at: DateTime<Utc>) -> String {
+    at.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+/// Registry entries for a matched route
+pub fn deprecations_for(method: &Method, path: &str) -> Vec<&'static Deprecation> {
+    DEPRECATIONS
+        .iter()
+        .filter(|d| d.method == method.as_str() && d.path == path)
+        .collect()
+}
+
+/// Deprecation headers for the entries of one route
+pub fn deprecation_headers(entries: &[&Deprecation]) -> Vec<(HeaderName, String)> {
+    let mut headers = Vec::new();
+    if entries.is_empty() {
+        return headers;
+    }
+
+    // Several entries (e.g. two deprecated fields) report the earliest dates
+    if let Some(deprecated) = entries.iter().filter_map(|d| day_start(d.deprecated)).min() {
+        headers.push((HeaderName::from_static("deprecation"), format!("@{}", deprecated.timestamp())));
+    }
+    if let Some(sunset) = entries.iter().filter_map(|d| d.sunset.and_then(day_start)).min() {
+        headers.push((HeaderName::from_static("sunset"), http_date(sunset)));
+    }
+
+    let mut links: Vec<String> = Vec::new();
+    for entry in entries {
+        let link = format!("<{}#{}>; rel=\"deprecation\"", CHANGELOG_PATH, entry.changelog_id);
+        if !links.contains(&link) {
+            links.push(link);
+        }
+        if let Some(successor) = entry.successor {
+            links.push(format!("<{}>; rel=\"successor-version\"", successor));
+        }
+    }
+    headers.push((HeaderName::from_static("link"), links.join(", ")));
+
+    let fields: Vec<&str> = entries.iter().flat_map(|d| d.fields.iter().copied()).collect();
+    if !fields.is_empty() {
+        headers.push((HeaderName::from_static(DEPRECATED_FIELDS_HEADER), fields.join(", ")));
+    }
+    headers
+}
+
+/// Add deprecation headers to responses of deprecated routes
+pub async fn deprecation_middleware(request: Request, next: Next) -> Response {
+    let entries = match request.extensions().get::<MatchedPath>() {
+        Some(path) => deprecations_for(request.method(), path.as_str()),
+        None => Vec::new(),
+    };
+
+    let mut response = next.run(request).await;
+    for (name, value) in deprecation_headers(&entries) {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            response.headers_mut().append(name, value);
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_is_consistent() {
+        for entry in DEPRECATIONS {
+            assert!(day_start(entry.deprecated).is_some(), "{}", entry.path);
+            if let Some(sunset) = entry.sunset {
+                assert!(day_start(sunset) > day_start(entry.deprecated), "{}", entry.path);
+            }
+            assert!(
+                CHANGELOG
+                    .iter()
+                    .any(|c| c.id == entry.changelog_id && c.kind == ChangeKind::Deprecated),
+                "{} has no changelog entry",
+                entry.path
+            );
+        }
+        for entry in CHANGELOG {
+            assert!(day_start(entry.date).is_some(), "{}", entry.id);
+        }
+        assert!(CHANGELOG.windows(2).all(|w| w[0].date >= w[1].date));
+    }
+
+    #[test]
+    fn test_builds_headers_for_deprecated_routes() {
+        assert!(deprecations_for(&Method::GET, "/api/v1/markets/:market_id/ticker").is_empty());
+        assert!(deprecations_for(&Method::POST, "/api/v1/markets/:market_id/price").is_empty());
+
+        let entries = deprecations_for(&Method::GET, "/api/v1/markets/:market_id/price");
+        let headers = deprecation_headers(&entries);
+        let get = |name: &str| headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str());
+
+        assert_eq!(get("deprecation"), Some("@1792022400"));
+        assert_eq!(get("sunset"), Some("Thu, 15 Apr 2027 00:00:00 GMT"));
+        assert_eq!(
+            get("link"),
+            Some(
+                "</api/v1/changelog#2026-10-15-market-price-deprecated>; rel=\"deprecation\", \
+                 </api/v1/markets/:market_id/ticker>; rel=\"successor-version\""
+            )
+        );
+        assert_eq!(get(DEPRECATED_FIELDS_HEADER), None);
+    }
+
+    #[test]
+    fn test_lists_deprecated_fields() {
+        let entry = Deprecation {
+            method: "GET",
+            path: "/api/v1/example",
+            fields: &["legacy_price"],
+            deprecated: "2026-01-01",
+            sunset: None,
+            successor: None,
+            changelog_id: "example",
+        };
+        let headers = deprecation_headers(&[&entry]);
+        assert!(headers.iter().all(|(n, _)| n != "sunset"));
+        assert!(headers
+            .iter()
+            .any(|(n, v)| n == DEPRECATED_FIELDS_HEADER && v == "legacy_price"));
+    }
+}
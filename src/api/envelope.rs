@@ -0,0 +1,212 @@
+//! API v2 Response Envelopes
+//!
+//! Every `/api/v2` response has the same shape:
+//!
+//! ```json
+//! { "data": ..., "error": null, "meta": { "api_version": "v2", "timestamp": ..., "sequence": ..., "pagination": ... } }
+//! ```
+//!
+//! - `data`: the resource (the v1 response body), null on error
+//! - `error`: `{ "code", "message" }`, null on success
+//! - `meta.sequence`: matching engine sequence when the response was built, so
+//!   clients can order REST snapshots against WebSocket updates
+//! - `meta.pagination`: present for paginated lists (`total`, `limit`,
+//!   `offset`, `count`, `has_more`)
+//!
+//! v2 routes reuse the v1 handlers; [`envelope_middleware`] rewraps their
+//! bodies, so both versions are served concurrently from one implementation
+//! and v1 formats stay untouched.
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::AppState;
+
+/// Version reported in `meta` and the `API-Version` header
+pub const API_VERSION: &str = "v2";
+
+/// Largest handler body the middleware will buffer
+const MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
+
+/// Response envelope
+#[derive(Debug, Serialize)]
+pub struct Envelope<T> {
+    pub data: Option<T>,
+    pub error: Option<EnvelopeError>,
+    pub meta: Meta,
+}
+
+/// Error body
+#[derive(Debug, Serialize)]
+pub struct EnvelopeError {
+    pub code: String,
+    pub message: String,
+}
+
+/// Response metadata
+#[derive(Debug, Serialize)]
+pub struct Meta {
+    pub api_version: &'static str,
+    /// Unix millis
+    pub timestamp: i64,
+    pub sequence: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pagination: Option<Pagination>,
+}
+
+/// Pagination details for list responses
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Pagination {
+    pub total: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    pub offset: i64,
+    /// Items in this page
+    pub count: usize,
+    pub has_more: bool,
+}
+
+/// `limit` / `offset` from a query string
+fn page_params(query: Option<&str>) -> (Option<i64>, i64) {
+    let (mut limit, mut offset) = (None, 0);
+    for pair in query.unwrap_or("").split('&') {
+        match pair.split_once('=') {
+            Some(("limit", v)) => limit = v.parse().ok(),
+            Some(("offset", v)) => offset = v.parse().unwrap_or(0),
+            _ => {}
+        }
+    }
+    (limit, offset)
+}
+
+/// Pagination for a list body: an object with a numeric `total` and an array of items
+fn pagination(body: &Value, limit: Option<i64>, offset: i64) -> Option<Pagination> {
+    let fields = body.as_object()?;
+    let total = fields.get("total")?.as_i64()?;
+    let count = fields.values().find_map(|v| v.as_array())?.len();
+    Some(Pagination {
+        total,
+        limit,
+        offset,
+        count,
+        has_more: offset + (count as i64) < total,
+    })
+}
+
+/// Wrap a v1 body into an envelope
+fn wrap(status: StatusCode, body: Option<Value>, meta: Meta) -> Envelope<Value> {
+    if status.is_success() {
+        return Envelope {
+            data: body,
+            error: None,
+            meta,
+        };
+    }
+
+    let field = |name: &str| {
+        body.as_ref()
+            .and_then(|b| b.get(name))
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+    };
+    Envelope {
+        data: None,
+        error: Some(EnvelopeError {
+            code: field("code").unwrap_or_else(|| status.as_str().to_string()),
+            message: field("error")
+                .unwrap_or_else(|| status.canonical_reason().unwrap_or("error").to_string()),
+        }),
+        meta,
+    }
+}
+
+/// Rewrap handler responses into v2 envelopes
+pub async fn envelope_middleware(State(state): State<Arc<AppState>>, request: Request, next: Next) -> Response {
+    let (limit, offset) = page_params(request.uri().query());
+
+    let response = next.run(request).await;
+    let (mut parts, body) = response.into_parts();
+
+    let bytes = match to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!("Failed to buffer response body for v2 envelope: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let body: Option<Value> = serde_json::from_slice(&bytes).ok();
+
+    let meta = Meta {
+        api_version: API_VERSION,
+        timestamp: chrono::Utc::now().timestamp_millis(),
+        sequence: state.matching_engine.last_sequence(),
+        pagination: if parts.status.is_success() {
+            body.as_ref().and_then(|b| pagination(b, limit, offset))
+        } else {
+            None
+        },
+    };
+    let envelope = wrap(parts.status, body, meta);
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.remove(header::CONTENT_TYPE);
+    parts
+        .headers
+        .insert("api-version", HeaderValue::from_static(API_VERSION));
+
+    let mut response = Json(envelope).into_response();
+    response.headers_mut().extend(parts.headers);
+    *response.status_mut() = parts.status;
+    response.map(Body::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn meta() -> Meta {
+        Meta {
+            api_version: API_VERSION,
+            timestamp: 0,
+            sequence: 7,
+            pagination: None,
+        }
+    }
+
+    #[test]
+    fn test_error_body_becomes_envelope_error() {
+        let body = json!({"error": "订单不存在", "code": "ORDER_NOT_FOUND"});
+        let envelope = serde_json::to_value(wrap(StatusCode::NOT_FOUND, Some(body), meta())).unwrap();
+        assert_eq!(envelope["data"], Value::Null);
+        assert_eq!(envelope["error"]["code"], "ORDER_NOT_FOUND");
+        assert_eq!(envelope["error"]["message"], "订单不存在");
+        assert_eq!(envelope["meta"]["sequence"], 7);
+    }
+
+    #[test]
+    fn test_pagination_from_list_body() {
+        let (limit, offset) = page_params(Some("market_id=x&limit=2&offset=4"));
+        let body = json!({"orders": [{}, {}], "total": 10});
+        assert_eq!(
+            pagination(&body, limit, offset),
+            Some(Pagination {
+                total: 10,
+                limit: Some(2),
+                offset: 4,
+                count: 2,
+                has_more: true,
+            })
+        );
+        assert_eq!(pagination(&json!({"balances": []}), None, 0), None);
+    }
+}
@@ -0,0 +1,45 @@
+//! HTTP Mapping of Service Errors
+//!
+//! The one place where the service error taxonomy
+//! ([`crate::services::errors`]) becomes HTTP status codes. Handlers keep
+//! their own response bodies; they take the status and code from
+//! [`classify`]:
+//!
+//! - `NotFound` -> 404, `InvalidInput` / `InsufficientMargin` -> 400
+//! - `Conflict` -> 409 (retry the request)
+//! - `UpstreamUnavailable` -> 503 (retry with backoff)
+//! - `Internal` -> 500 (don't retry)
+//!
+//! Unavailable and internal failures are logged here, so handlers only show
+//! clients a generic message for them.
+
+use axum::http::StatusCode;
+
+use crate::services::errors::{ErrorKind, ServiceError};
+
+/// HTTP status of an error kind
+pub fn status(kind: ErrorKind) -> StatusCode {
+    match kind {
+        ErrorKind::NotFound => StatusCode::NOT_FOUND,
+        ErrorKind::InvalidInput | ErrorKind::InsufficientMargin => StatusCode::BAD_REQUEST,
+        ErrorKind::Conflict => StatusCode::CONFLICT,
+        ErrorKind::UpstreamUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+        ErrorKind::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Status and code of a service error, logging server-side failures
+pub fn classify<E: ServiceError + ?Sized>(e: &E) -> (StatusCode, &'static str) {
+    let kind = e.kind();
+    match kind {
+        ErrorKind::UpstreamUnavailable => tracing::warn!("Upstream unavailable ({}): {}", e.code(), e),
+        ErrorKind::Internal => tracing::error!("Internal error ({}): {}", e.code(), e),
+        _ => {}
+    }
+    (status(kind), e.code())
+}
+
+/// Whether an error's message may be shown to clients
+pub fn is_client_error<E: ServiceError + ?Sized>(e: &E) -> bool {
+    !matches!(e.kind(), ErrorKind::UpstreamUnavailable | ErrorKind::Internal)
+}
@@ -13,11 +13,21 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::api::error as api_error;
 use crate::auth::middleware::AuthUser;
 use crate::models::market::ShareType;
 use crate::models::{BalanceResponse, UserProfile};
+use crate::services::archive::{ArchiveError, ArchiveService, ArchivedTrade};
+use crate::services::errors::ServiceError;
+use crate::services::ledger::{LedgerEntry, LedgerFilter, LedgerReason, LedgerService};
+use crate::services::position::{
+    AccountMargin, ClosedPosition, FillRole, MarginMode, PnlEntry, PositionError, PositionHistoryStats, PositionMargin,
+    PositionService,
+};
+use crate::services::preferences::{OrderPreferences, PreferenceService, PreferencesError};
+use crate::services::risk_profile::{AccountRiskProfile, ProfileLimits, RiskProfile, RiskProfileError, RiskProfileService};
 use crate::services::settlement::{SettlementService, SettlementError};
-use crate::AppState;
+use crate::{AppState, PositionUpdateEvent};
 
 // ============================================================================
 // Helper Modules
@@ -45,6 +55,34 @@ pub struct ErrorResponse {
     pub code: String,
 }
 
+#[derive(Debug, Serialize)]
+pub struct ProfileResponse {
+    #[serde(flatten)]
+    pub profile: UserProfile,
+    pub risk_profile: AccountRiskProfile,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RiskProfileOption {
+    pub profile: RiskProfile,
+    pub limits: ProfileLimits,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RiskProfileResponse {
+    #[serde(flatten)]
+    pub current: AccountRiskProfile,
+    /// Every selectable profile
+    pub profiles: Vec<RiskProfileOption>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetRiskProfileRequest {
+    pub profile: RiskProfile,
+    /// Disclosure version acknowledged (required to move to a riskier profile)
+    pub acknowledged_version: Option<i32>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct BalancesResponse {
     pub balances: Vec<BalanceResponse>,
@@ -109,10 +147,18 @@ pub struct TradeRecord {
     pub market_id: Uuid,
     pub outcome_id: Uuid,
     pub share_type: ShareType,
+    /// The user's own side (taker side on trades before fill attribution)
     pub side: String,
+    pub role: FillRole,
     pub price: Decimal,
     pub amount: Decimal,
     pub fee: Decimal,
+    /// Token the fee was paid in (None before fill attribution)
+    pub fee_token: Option<String>,
+    /// Holding the fill was netted into (None before fill attribution)
+    pub position_id: Option<Uuid>,
+    /// PnL realized by this trade (sells / merges closing held shares)
+    pub realized_pnl: Decimal,
     #[serde(serialize_with = "datetime_as_millis::serialize")]
     pub timestamp: DateTime<Utc>,
 }
@@ -123,6 +169,44 @@ pub struct TradesResponse {
     pub total: i64,
 }
 
+#[derive(Debug, Serialize)]
+pub struct PnlHistoryResponse {
+    pub entries: Vec<PnlEntry>,
+    /// Total realized PnL over the filter (all pages)
+    pub total_realized_pnl: Decimal,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PositionHistoryResponse {
+    pub positions: Vec<ClosedPosition>,
+    /// Totals over the filter (all pages)
+    pub stats: PositionHistoryStats,
+}
+
+/// Leverage default of a market, with the market's limits
+#[derive(Debug, Serialize)]
+pub struct MarketPreference {
+    pub market_id: Uuid,
+    pub default_leverage: u32,
+    pub max_leverage: u32,
+    pub tick_size: Option<Decimal>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PreferencesResponse {
+    #[serde(flatten)]
+    pub preferences: OrderPreferences,
+    /// Markets with a leverage default
+    pub markets: Vec<MarketPreference>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LedgerResponse {
+    pub entries: Vec<LedgerEntry>,
+    /// Entries matching the filter (all pages)
+    pub total: i64,
+}
+
 // ============================================================================
 // Query Parameters
 // ============================================================================
@@ -142,6 +226,22 @@ pub struct TradesQuery {
     pub offset: Option<i64>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct PnlQuery {
+    pub market_id: Option<Uuid>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LedgerQuery {
+    pub token: Option<String>,
+    /// Ledger reason, e.g. `order_freeze`, `realized_pnl`
+    pub reason: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct SharesQuery {
     pub market_id: Option<Uuid>,
@@ -158,7 +258,7 @@ pub struct SharesQuery {
 pub async fn get_profile(
     State(state): State<Arc<AppState>>,
     Extension(auth_user): Extension<AuthUser>,
-) -> Result<Json<UserProfile>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<ProfileResponse>, (StatusCode, Json<ErrorResponse>)> {
     let user: Option<UserProfile> = sqlx::query_as(
         r#"
         SELECT address, username, avatar_url, created_at, updated_at
@@ -180,16 +280,20 @@ pub async fn get_profile(
         )
     })?;
 
-    match user {
-        Some(profile) => Ok(Json(profile)),
-        None => Err((
+    let Some(profile) = user else {
+        return Err((
             StatusCode::NOT_FOUND,
             Json(ErrorResponse {
                 error: "用户不存在".to_string(),
                 code: "USER_NOT_FOUND".to_string(),
             }),
-        )),
-    }
+        ));
+    };
+    let risk_profile = RiskProfileService::get(&state.db.pool, auth_user.signer())
+        .await
+        .map_err(risk_profile_error)?;
+
+    Ok(Json(ProfileResponse { profile, risk_profile }))
 }
 
 /// Get user balances
@@ -223,6 +327,7 @@ pub async fn get_balances(
     let balances: Vec<BalanceResponse> = rows
         .into_iter()
         .map(|(token, available, frozen)| BalanceResponse {
+            chains: state.chains.chains_for_token(&token),
             token,
             available,
             frozen,
@@ -335,7 +440,7 @@ pub async fn get_orders(
         )
     })?;
 
-    let orders: Vec<OrderDetail> = rows
+    let mut orders: Vec<OrderDetail> = rows
         .into_iter()
         .map(
             |(
@@ -370,6 +475,61 @@ pub async fn get_orders(
         )
         .collect();
 
+    if let Some(store) = state.archive.as_deref() {
+        if (orders.len() as i64) < limit {
+            // Page runs past the rows still in Postgres: continue from the archive
+            let live_total: i64 = sqlx::query_scalar(
+                r#"
+                SELECT COUNT(*) FROM orders
+                WHERE user_address = $1
+                  AND ($2::uuid IS NULL OR market_id = $2)
+                  AND ($3::text IS NULL OR status::text = $3)
+                "#,
+            )
+            .bind(auth_user.address.to_lowercase())
+            .bind(query.market_id)
+            .bind(query.status.as_deref())
+            .fetch_one(&state.db.pool)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to count orders: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: "获取订单失败".to_string(),
+                        code: "ORDER_FETCH_FAILED".to_string(),
+                    }),
+                )
+            })?;
+
+            let archived = ArchiveService::user_orders(
+                store,
+                &auth_user.address,
+                query.market_id,
+                query.status.as_deref(),
+                (offset - live_total).max(0) as usize,
+                (limit - orders.len() as i64) as usize,
+            )
+            .await
+            .map_err(archive_error)?;
+
+            orders.extend(archived.into_iter().map(|o| OrderDetail {
+                id: o.id,
+                market_id: o.market_id,
+                outcome_id: o.outcome_id,
+                share_type: o.share_type.parse().unwrap_or(ShareType::Yes),
+                side: o.side,
+                order_type: o.order_type,
+                price: o.price,
+                amount: o.amount,
+                filled_amount: o.filled_amount,
+                status: o.status,
+                created_at: o.created_at,
+                updated_at: o.updated_at,
+            }));
+        }
+    }
+
     let total = orders.len() as i64;
 
     Ok(Json(OrdersResponse { orders, total }))
@@ -386,54 +546,24 @@ pub async fn get_trades(
     let offset = query.offset.unwrap_or(0);
     let user_address = auth_user.address.to_lowercase();
 
-    let rows: Vec<(
-        Uuid,
-        Uuid,
-        Uuid,
-        String,
-        String,
-        Decimal,
-        Decimal,
-        Decimal,
-        DateTime<Utc>,
-    )> = if let Some(market_id) = query.market_id {
-        sqlx::query_as(
-            r#"
-            SELECT id, market_id, outcome_id, share_type::text, side::text,
-                   price, amount,
-                   CASE WHEN maker_address = $1 THEN maker_fee ELSE taker_fee END as fee,
-                   created_at
-            FROM trades
-            WHERE (maker_address = $1 OR taker_address = $1) AND market_id = $4
-            ORDER BY created_at DESC
-            LIMIT $2 OFFSET $3
-            "#,
-        )
-        .bind(&user_address)
-        .bind(limit)
-        .bind(offset)
-        .bind(market_id)
-        .fetch_all(&state.db.pool)
-        .await
-    } else {
-        sqlx::query_as(
-            r#"
-            SELECT id, market_id, outcome_id, share_type::text, side::text,
-                   price, amount,
-                   CASE WHEN maker_address = $1 THEN maker_fee ELSE taker_fee END as fee,
-                   created_at
-            FROM trades
-            WHERE maker_address = $1 OR taker_address = $1
-            ORDER BY created_at DESC
-            LIMIT $2 OFFSET $3
-            "#,
-        )
-        .bind(&user_address)
-        .bind(limit)
-        .bind(offset)
-        .fetch_all(&state.db.pool)
-        .await
-    }
+    let rows: Vec<ArchivedTrade> = sqlx::query_as(
+        r#"
+        SELECT id, market_id, outcome_id, share_type::text AS share_type, side::text AS side,
+               maker_address, taker_address, price, amount, maker_fee, taker_fee,
+               is_block_trade, created_at, exec_id
+        FROM trades
+        WHERE (maker_address = $1 OR taker_address = $1)
+          AND ($4::uuid IS NULL OR market_id = $4)
+        ORDER BY created_at DESC
+        LIMIT $2 OFFSET $3
+        "#,
+    )
+    .bind(&user_address)
+    .bind(limit)
+    .bind(offset)
+    .bind(query.market_id)
+    .fetch_all(&state.db.pool)
+    .await
     .map_err(|e| {
         tracing::error!("Failed to fetch trades: {}", e);
         (
@@ -444,31 +574,189 @@ pub async fn get_trades(
             }),
         )
     })?;
+    let live = rows.len() as i64;
 
-    let trades: Vec<TradeRecord> = rows
-        .into_iter()
-        .map(
-            |(id, market_id, outcome_id, share_type, side, price, amount, fee, timestamp)| {
-                TradeRecord {
-                    id,
-                    market_id,
-                    outcome_id,
-                    share_type: share_type.parse().unwrap_or(ShareType::Yes),
-                    side,
-                    price,
-                    amount,
-                    fee,
-                    timestamp,
-                }
-            },
-        )
-        .collect();
+    let mut trades: Vec<TradeRecord> = rows.iter().flat_map(|t| trade_records(t, &user_address)).collect();
+
+    if let Some(store) = state.archive.as_deref() {
+        if live < limit {
+            // Page runs past the rows still in Postgres: continue from the archive
+            let live_total: i64 = sqlx::query_scalar(
+                r#"
+                SELECT COUNT(*) FROM trades
+                WHERE (maker_address = $1 OR taker_address = $1)
+                  AND ($2::uuid IS NULL OR market_id = $2)
+                "#,
+            )
+            .bind(&user_address)
+            .bind(query.market_id)
+            .fetch_one(&state.db.pool)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to count trades: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: "获取交易记录失败".to_string(),
+                        code: "TRADE_FETCH_FAILED".to_string(),
+                    }),
+                )
+            })?;
+
+            let archived = ArchiveService::user_trades(
+                store,
+                &user_address,
+                query.market_id,
+                (offset - live_total).max(0) as usize,
+                (limit - live) as usize,
+            )
+            .await
+            .map_err(archive_error)?;
+
+            trades.extend(archived.iter().flat_map(|t| trade_records(t, &user_address)));
+        }
+    }
+
+    let trade_ids: Vec<Uuid> = trades.iter().map(|t| t.id).collect();
+    let realized = PositionService::realized_pnl_by_trade(&state.db.pool, &user_address, &trade_ids)
+        .await
+        .map_err(position_error)?;
+    for (trade_id, pnl) in realized {
+        if let Some(trade) = trades.iter_mut().find(|t| t.id == trade_id) {
+            trade.realized_pnl = pnl;
+        }
+    }
+
+    // Attributed fills carry the user's own economics of the trade, per role
+    let fills = PositionService::fill_attributions(&state.db.pool, &user_address, &trade_ids)
+        .await
+        .map_err(position_error)?;
+    for fill in fills {
+        let role = fill.fill_role();
+        if let Some(trade) = trades.iter_mut().find(|t| t.id == fill.trade_id && t.role == role) {
+            trade.side = fill.side;
+            trade.share_type = fill.share_type;
+            trade.price = fill.price;
+            trade.fee = fill.fee;
+            trade.fee_token = Some(fill.fee_token);
+            trade.position_id = Some(fill.position_id);
+            trade.realized_pnl = fill.realized_pnl;
+        }
+    }
 
     let total = trades.len() as i64;
 
     Ok(Json(TradesResponse { trades, total }))
 }
 
+/// The user's fills of a trade, one per role (two in a self-trade), as the
+/// trade row shows them until attribution overrides them. Without
+/// attribution `side` is the taker's.
+fn trade_records(trade: &ArchivedTrade, user_address: &str) -> Vec<TradeRecord> {
+    FillRole::of(user_address, &trade.maker_address, &trade.taker_address)
+        .into_iter()
+        .map(|role| TradeRecord {
+            id: trade.id,
+            market_id: trade.market_id,
+            outcome_id: trade.outcome_id,
+            share_type: trade.share_type.parse().unwrap_or(ShareType::Yes),
+            side: trade.side.clone(),
+            role,
+            price: trade.price,
+            amount: trade.amount,
+            fee: if role == FillRole::Maker { trade.maker_fee } else { trade.taker_fee },
+            fee_token: None,
+            position_id: None,
+            realized_pnl: Decimal::ZERO,
+            timestamp: trade.created_at,
+        })
+        .collect()
+}
+
+/// Get realized PnL history
+/// GET /account/pnl
+pub async fn get_pnl_history(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Query(query): Query<PnlQuery>,
+) -> Result<Json<PnlHistoryResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let limit = query.limit.unwrap_or(50).min(100);
+    let offset = query.offset.unwrap_or(0);
+
+    let (entries, total_realized_pnl) =
+        PositionService::get_pnl_history(&state.db.pool, &auth_user.address, query.market_id, limit, offset)
+            .await
+            .map_err(position_error)?;
+
+    Ok(Json(PnlHistoryResponse {
+        entries,
+        total_realized_pnl,
+    }))
+}
+
+/// Get closed positions
+/// GET /account/positions/history
+pub async fn get_position_history(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Query(query): Query<PnlQuery>,
+) -> Result<Json<PositionHistoryResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let limit = query.limit.unwrap_or(50).min(100);
+    let offset = query.offset.unwrap_or(0);
+
+    let (positions, stats) =
+        PositionService::get_position_history(&state.db.pool, &auth_user.address, query.market_id, limit, offset)
+            .await
+            .map_err(position_error)?;
+
+    Ok(Json(PositionHistoryResponse { positions, stats }))
+}
+
+/// Get the balance ledger
+/// GET /account/ledger
+pub async fn get_ledger(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Query(query): Query<LedgerQuery>,
+) -> Result<Json<LedgerResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let limit = query.limit.unwrap_or(50).min(100);
+    let offset = query.offset.unwrap_or(0);
+
+    let reason = query
+        .reason
+        .as_deref()
+        .map(str::parse::<LedgerReason>)
+        .transpose()
+        .map_err(|_| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: format!("无效的账本类型: {}", query.reason.as_deref().unwrap_or_default()),
+                    code: "INVALID_LEDGER_REASON".to_string(),
+                }),
+            )
+        })?;
+    let filter = LedgerFilter {
+        token: query.token,
+        reason,
+    };
+
+    let (entries, total) = LedgerService::get_entries(&state.db.pool, &auth_user.address, &filter, limit, offset)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load ledger: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "数据库错误".to_string(),
+                    code: "DB_ERROR".to_string(),
+                }),
+            )
+        })?;
+
+    Ok(Json(LedgerResponse { entries, total }))
+}
+
 /// Get user share holdings
 /// GET /account/shares
 pub async fn get_shares(
@@ -790,3 +1078,304 @@ pub async fn get_settlement_status(
         )),
     }
 }
+
+// ============================================================================
+// Margin Mode
+// ============================================================================
+
+/// Margin mode response
+#[derive(Debug, Serialize)]
+pub struct MarginModeResponse {
+    pub margin_mode: MarginMode,
+}
+
+/// Margin mode update request
+#[derive(Debug, Deserialize)]
+pub struct SetMarginModeRequest {
+    pub margin_mode: String,
+}
+
+fn archive_error(e: ArchiveError) -> (StatusCode, Json<ErrorResponse>) {
+    tracing::error!("History archive read failed: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse {
+            error: "读取历史归档失败".to_string(),
+            code: "ARCHIVE_READ_FAILED".to_string(),
+        }),
+    )
+}
+
+fn position_error(e: PositionError) -> (StatusCode, Json<ErrorResponse>) {
+    let (status, code) = api_error::classify(&e);
+    let message = match &e {
+        PositionError::UserNotFound(_) => "用户不存在".to_string(),
+        PositionError::InvalidMarginMode(mode) => format!("无效的保证金模式: {} (可选 isolated / cross)", mode),
+        PositionError::PositionNotFound(_) => "持仓不存在".to_string(),
+        PositionError::MaintenanceBreach { mode, ratio } => {
+            format!("切换为 {} 保证金将低于维持保证金 (保证金率 {})", mode, ratio)
+        }
+        PositionError::DatabaseError(_) if e.kind().retryable() => "数据库暂不可用, 请稍后重试".to_string(),
+        PositionError::DatabaseError(_) => "数据库错误".to_string(),
+    };
+
+    (
+        status,
+        Json(ErrorResponse {
+            error: message,
+            code: code.to_string(),
+        }),
+    )
+}
+
+fn risk_profile_error(e: RiskProfileError) -> (StatusCode, Json<ErrorResponse>) {
+    let (status, code) = api_error::classify(&e);
+    let message = match &e {
+        RiskProfileError::UnknownProfile(profile) => format!("未知的风险档位: {}", profile),
+        RiskProfileError::AcknowledgmentRequired { profile, version } => {
+            format!("切换到 {} 档位需要确认风险披露 (版本 {})", profile, version)
+        }
+        RiskProfileError::DatabaseError(_) if e.kind().retryable() => "数据库暂不可用, 请稍后重试".to_string(),
+        RiskProfileError::DatabaseError(_) => "数据库错误".to_string(),
+    };
+
+    (
+        status,
+        Json(ErrorResponse {
+            error: message,
+            code: code.to_string(),
+        }),
+    )
+}
+
+fn preferences_error(e: PreferencesError) -> (StatusCode, Json<ErrorResponse>) {
+    let (status, code, message) = match &e {
+        PreferencesError::InvalidLeverage { market_id, leverage } => (
+            StatusCode::BAD_REQUEST,
+            "INVALID_LEVERAGE",
+            format!("市场 {} 的杠杆倍数 {} 无效", market_id, leverage),
+        ),
+        PreferencesError::InvalidSlippage(max) => (
+            StatusCode::BAD_REQUEST,
+            "INVALID_SLIPPAGE",
+            format!("滑点容忍度必须在 0 到 {} 之间", max),
+        ),
+        PreferencesError::DatabaseError(db) => {
+            tracing::error!("Preferences database error: {}", db);
+            (StatusCode::INTERNAL_SERVER_ERROR, "DB_ERROR", "数据库错误".to_string())
+        }
+    };
+
+    (
+        status,
+        Json(ErrorResponse {
+            error: message,
+            code: code.to_string(),
+        }),
+    )
+}
+
+/// Attach market limits to the leverage defaults; unknown markets are skipped
+async fn preferences_response(
+    state: &AppState,
+    preferences: OrderPreferences,
+) -> Result<PreferencesResponse, (StatusCode, Json<ErrorResponse>)> {
+    let mut markets = Vec::with_capacity(preferences.default_leverage.len());
+    for (&market_id, &default_leverage) in &preferences.default_leverage {
+        let config = state
+            .market_service
+            .get_market_config(&state.db.pool, market_id)
+            .await
+            .map_err(|e| preferences_error(PreferencesError::DatabaseError(e)))?;
+        if let Some(config) = config {
+            markets.push(MarketPreference {
+                market_id,
+                default_leverage,
+                max_leverage: config.max_leverage,
+                tick_size: config.tick_size,
+            });
+        }
+    }
+    markets.sort_by_key(|m| m.market_id);
+
+    Ok(PreferencesResponse { preferences, markets })
+}
+
+/// Get the user's order preferences
+/// GET /account/preferences
+pub async fn get_preferences(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<PreferencesResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let preferences = PreferenceService::get(&state.db.pool, &auth_user.address)
+        .await
+        .map_err(preferences_error)?;
+
+    Ok(Json(preferences_response(&state, preferences).await?))
+}
+
+/// Replace the user's order preferences
+/// PUT /account/preferences
+pub async fn set_preferences(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(preferences): Json<OrderPreferences>,
+) -> Result<Json<PreferencesResponse>, (StatusCode, Json<ErrorResponse>)> {
+    // Leverage defaults must be allowed by their market
+    for (&market_id, &leverage) in &preferences.default_leverage {
+        let config = state
+            .market_service
+            .get_market_config(&state.db.pool, market_id)
+            .await
+            .map_err(|e| preferences_error(PreferencesError::DatabaseError(e)))?
+            .ok_or_else(|| {
+                (
+                    StatusCode::NOT_FOUND,
+                    Json(ErrorResponse {
+                        error: format!("市场不存在: {}", market_id),
+                        code: "MARKET_NOT_FOUND".to_string(),
+                    }),
+                )
+            })?;
+        if leverage > config.max_leverage {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: format!("杠杆 {} 超过市场上限 {}", leverage, config.max_leverage),
+                    code: "INVALID_LEVERAGE".to_string(),
+                }),
+            ));
+        }
+    }
+
+    PreferenceService::set(&state.db.pool, &auth_user.address, &preferences)
+        .await
+        .map_err(preferences_error)?;
+
+    Ok(Json(preferences_response(&state, preferences).await?))
+}
+
+/// Get the wallet's risk profile and the selectable ones
+/// GET /account/risk-profile
+pub async fn get_risk_profile(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<RiskProfileResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let current = RiskProfileService::get(&state.db.pool, auth_user.signer())
+        .await
+        .map_err(risk_profile_error)?;
+    let profiles = RiskProfile::ALL
+        .into_iter()
+        .map(|profile| RiskProfileOption {
+            profile,
+            limits: profile.limits(),
+        })
+        .collect();
+
+    Ok(Json(RiskProfileResponse { current, profiles }))
+}
+
+/// Switch the wallet's risk profile (upgrades acknowledge the risk disclosure)
+/// PUT /account/risk-profile
+pub async fn set_risk_profile(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(req): Json<SetRiskProfileRequest>,
+) -> Result<Json<AccountRiskProfile>, (StatusCode, Json<ErrorResponse>)> {
+    // The acknowledgment is the wallet holder's own
+    if auth_user.sub_account.is_some() || auth_user.api_key.is_some() {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse {
+                error: "风险档位只能由钱包本人修改".to_string(),
+                code: "MAIN_ACCOUNT_REQUIRED".to_string(),
+            }),
+        ));
+    }
+
+    RiskProfileService::select(&state.db.pool, &auth_user.address, req.profile, req.acknowledged_version)
+        .await
+        .map(Json)
+        .map_err(risk_profile_error)
+}
+
+/// Get the user's margin mode
+/// GET /account/margin-mode
+pub async fn get_margin_mode(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<MarginModeResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let margin_mode = PositionService::get_margin_mode(&state.db.pool, &auth_user.address)
+        .await
+        .map_err(position_error)?;
+
+    Ok(Json(MarginModeResponse { margin_mode }))
+}
+
+/// Switch the user's margin mode
+/// POST /account/margin-mode
+pub async fn set_margin_mode(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(req): Json<SetMarginModeRequest>,
+) -> Result<Json<MarginModeResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let margin_mode: MarginMode = req.margin_mode.parse().map_err(position_error)?;
+
+    PositionService::set_margin_mode(&state.db.pool, &auth_user.address, margin_mode)
+        .await
+        .map_err(position_error)?;
+
+    Ok(Json(MarginModeResponse { margin_mode }))
+}
+
+/// Get account equity, maintenance margin and margin ratio in the user's margin mode
+/// GET /account/margin
+pub async fn get_account_margin(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<AccountMargin>, (StatusCode, Json<ErrorResponse>)> {
+    let summary = PositionService::get_account_margin(
+        &state.db.pool,
+        &state.matching_engine,
+        &state.market_service,
+        &auth_user.address,
+        state.config.collateral_symbol(),
+        state.live_config.current().maintenance_margin_rate(),
+    )
+    .await
+    .map_err(position_error)?;
+
+    Ok(Json(summary))
+}
+
+/// Switch a single position between isolated and cross margin
+/// POST /positions/:position_id/margin-mode
+pub async fn set_position_margin_mode(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    axum::extract::Path(position_id): axum::extract::Path<Uuid>,
+    Json(req): Json<SetMarginModeRequest>,
+) -> Result<Json<PositionMargin>, (StatusCode, Json<ErrorResponse>)> {
+    let margin_mode: MarginMode = req.margin_mode.parse().map_err(position_error)?;
+
+    let position = PositionService::set_position_margin_mode(
+        &state.db.pool,
+        &state.matching_engine,
+        &state.market_service,
+        position_id,
+        &auth_user.address,
+        margin_mode,
+        state.config.collateral_symbol(),
+        state.live_config.current().maintenance_margin_rate(),
+    )
+    .await
+    .map_err(position_error)?;
+
+    // Push the updated position to the user's WebSocket connections
+    let _ = state.position_update_sender.send(PositionUpdateEvent {
+        user_address: auth_user.address.to_lowercase(),
+        position: position.clone(),
+    });
+
+    Ok(Json(position))
+}
@@ -0,0 +1,101 @@
+//! API Key Handlers
+//!
+//! Issuing, listing and revoking the authenticated wallet's API keys. Keys
+//! are managed only from the wallet's own session, never with another key.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::api::error as api_error;
+use crate::auth::middleware::AuthUser;
+use crate::services::api_keys::{ApiKey, ApiKeyError, ApiKeyService, IssuedApiKey, NewApiKey};
+use crate::AppState;
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: String,
+}
+
+fn api_key_error(e: ApiKeyError) -> (StatusCode, Json<ErrorResponse>) {
+    let (status, code) = api_error::classify(&e);
+    let error = if api_error::is_client_error(&e) {
+        e.to_string()
+    } else {
+        "API key request failed".to_string()
+    };
+    (
+        status,
+        Json(ErrorResponse {
+            error,
+            code: code.to_string(),
+        }),
+    )
+}
+
+/// Wallet managing its keys; refused with an API key or inside a sub-account
+fn key_owner(auth_user: &AuthUser) -> Result<&str, (StatusCode, Json<ErrorResponse>)> {
+    if auth_user.api_key.is_some() || auth_user.sub_account.is_some() {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse {
+                error: "API keys are managed from the wallet's own session".to_string(),
+                code: "MAIN_ACCOUNT_REQUIRED".to_string(),
+            }),
+        ));
+    }
+    Ok(&auth_user.address)
+}
+
+// ============================================================================
+// Account Handlers
+// ============================================================================
+
+/// Issue an API key; its secret is only in this response
+/// POST /account/api-keys
+pub async fn create_api_key(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(req): Json<NewApiKey>,
+) -> Result<(StatusCode, Json<IssuedApiKey>), (StatusCode, Json<ErrorResponse>)> {
+    let owner = key_owner(&auth_user)?;
+    let issued = state.api_keys.issue(&state.db.pool, owner, &req).await.map_err(api_key_error)?;
+    Ok((StatusCode::CREATED, Json(issued)))
+}
+
+/// API keys of the authenticated wallet, newest first
+/// GET /account/api-keys
+pub async fn list_api_keys(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<Vec<ApiKey>>, (StatusCode, Json<ErrorResponse>)> {
+    let owner = key_owner(&auth_user)?;
+    ApiKeyService::list(&state.db.pool, owner)
+        .await
+        .map(Json)
+        .map_err(api_key_error)
+}
+
+/// Revoke an API key
+/// DELETE /account/api-keys/:id
+pub async fn revoke_api_key(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiKey>, (StatusCode, Json<ErrorResponse>)> {
+    let owner = key_owner(&auth_user)?;
+    ApiKeyService::revoke(&state.db.pool, owner, id)
+        .await
+        .map(Json)
+        .map_err(api_key_error)
+}
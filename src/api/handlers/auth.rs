@@ -1,15 +1,21 @@
 use axum::{
     extract::{Path, State},
     http::StatusCode,
-    Json,
+    Extension, Json,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
 use crate::auth::{
     eip712::{get_login_typed_data, verify_login_signature_with_debug, LoginMessage},
-    jwt::JwtManager,
+    middleware::AuthUser,
+    server_time::{
+        issue_time_token, now_secs, validate_request_timestamp, TIMESTAMP_TOLERANCE_SECS,
+        TIME_TOKEN_TTL_SECS,
+    },
 };
+use crate::api::error as api_error;
+use crate::services::auth_tokens::{AuthTokenError, AuthTokenService, TokenPair};
 use crate::AppState;
 
 #[derive(Debug, Deserialize)]
@@ -17,12 +23,39 @@ pub struct LoginRequest {
     pub address: String,
     pub signature: String,
     pub timestamp: u64,
+    /// Server time token from GET /time (for clients with skewed clocks)
+    #[serde(default)]
+    pub timestamp_token: Option<String>,
+}
+
+/// Access token and the refresh token that renews it
+pub type LoginResponse = TokenPair;
+
+#[derive(Debug, Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LogoutRequest {
+    /// Refresh token of the session, revoked with it
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    /// Log out every session of the wallet
+    #[serde(default)]
+    pub all: bool,
 }
 
 #[derive(Debug, Serialize)]
-pub struct LoginResponse {
-    pub token: String,
-    pub expires_at: i64,
+pub struct LogoutResponse {
+    pub success: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RevokeSessionsResponse {
+    pub address: String,
+    /// Refresh tokens revoked
+    pub revoked: u64,
 }
 
 #[derive(Debug, Serialize)]
@@ -31,6 +64,28 @@ pub struct NonceResponse {
     pub typed_data: serde_json::Value,
 }
 
+#[derive(Debug, Serialize)]
+pub struct TradingNonceResponse {
+    /// Nonce to sign into the next trading message (used once)
+    pub nonce: u64,
+    /// Signed trading requests without a nonce are rejected
+    pub required: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ServerTimeResponse {
+    /// Server time (unix seconds)
+    pub server_time: u64,
+    /// Server time (unix milliseconds)
+    pub server_time_ms: i64,
+    /// Signed token for `server_time`; send back as `timestamp_token`
+    pub timestamp_token: String,
+    /// Allowed distance between a request timestamp and the reference time
+    pub tolerance_secs: u64,
+    /// Seconds until `timestamp_token` expires
+    pub token_ttl_secs: u64,
+}
+
 #[derive(Debug, Serialize)]
 pub struct ErrorResponse {
     pub error: String,
@@ -39,6 +94,51 @@ pub struct ErrorResponse {
     pub details: Option<serde_json::Value>,
 }
 
+/// Token failures are authentication failures; storage failures stay 5xx
+fn auth_token_error(e: AuthTokenError) -> (StatusCode, Json<ErrorResponse>) {
+    let (status, code) = api_error::classify(&e);
+    let (status, error) = if api_error::is_client_error(&e) {
+        (StatusCode::UNAUTHORIZED, e.to_string())
+    } else {
+        (status, "Token request failed".to_string())
+    };
+    (
+        status,
+        Json(ErrorResponse {
+            error,
+            code: code.to_string(),
+            details: None,
+        }),
+    )
+}
+
+/// Get server time and a signed timestamp token
+/// GET /time
+pub async fn get_server_time(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ServerTimeResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let now = now_secs();
+    let timestamp_token = issue_time_token(&state.config.jwt_secret, now).map_err(|e| {
+        tracing::error!("Failed to issue server time token: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "Failed to issue time token".to_string(),
+                code: "INTERNAL_ERROR".to_string(),
+                details: None,
+            }),
+        )
+    })?;
+
+    Ok(Json(ServerTimeResponse {
+        server_time: now,
+        server_time_ms: chrono::Utc::now().timestamp_millis(),
+        timestamp_token,
+        tolerance_secs: TIMESTAMP_TOLERANCE_SECS,
+        token_ttl_secs: TIME_TOKEN_TTL_SECS,
+    }))
+}
+
 /// Get nonce and EIP-712 typed data for signing
 pub async fn get_nonce(
     State(state): State<Arc<AppState>>,
@@ -112,13 +212,10 @@ pub async fn login(
 ) -> Result<Json<LoginResponse>, (StatusCode, Json<ErrorResponse>)> {
     let address = req.address.to_lowercase();
 
-    // Validate timestamp (within 5 minutes)
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
+    // Validate timestamp (within 5 minutes of server time, or of a server time token)
+    let now = now_secs();
 
-    if now.abs_diff(req.timestamp) > 300 {
+    if !validate_request_timestamp(req.timestamp, req.timestamp_token.as_deref(), &state.config.jwt_secret) {
         return Err((
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
@@ -228,10 +325,9 @@ pub async fn login(
         // Continue anyway - login is still valid
     }
 
-    // Generate JWT token
-    let jwt_manager = JwtManager::new(&state.config.jwt_secret, state.config.jwt_expiry_seconds);
-    let token = match jwt_manager.generate_token(&address) {
-        Ok(t) => t,
+    // Generate JWT and refresh token
+    let tokens = match state.auth_tokens.issue(&state.db.pool, &address).await {
+        Ok(tokens) => tokens,
         Err(e) => {
             tracing::error!("Failed to generate JWT: {}", e);
             return Err((
@@ -245,9 +341,89 @@ pub async fn login(
         }
     };
 
-    let expires_at = chrono::Utc::now().timestamp() + state.config.jwt_expiry_seconds as i64;
-
     tracing::info!("User {} logged in successfully", address);
 
-    Ok(Json(LoginResponse { token, expires_at }))
+    Ok(Json(tokens))
+}
+
+/// Exchange a refresh token for a new access / refresh token pair
+/// POST /auth/refresh
+///
+/// The refresh token is used up; presenting it again revokes every session
+/// of its login.
+pub async fn refresh(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<RefreshRequest>,
+) -> Result<Json<TokenPair>, (StatusCode, Json<ErrorResponse>)> {
+    state
+        .auth_tokens
+        .refresh(&state.db.pool, &req.refresh_token)
+        .await
+        .map(Json)
+        .map_err(auth_token_error)
+}
+
+/// Revoke the caller's access token (and its refresh token), or every
+/// session of the wallet with `all`
+/// POST /auth/logout
+pub async fn logout(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(req): Json<LogoutRequest>,
+) -> Result<Json<LogoutResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let Some(claims) = &auth_user.token else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "Logout needs a session token".to_string(),
+                code: "SESSION_TOKEN_REQUIRED".to_string(),
+                details: None,
+            }),
+        ));
+    };
+    if req.all {
+        AuthTokenService::revoke_all(&state.db.pool, &claims.sub).await.map_err(auth_token_error)?;
+    } else {
+        AuthTokenService::logout(&state.db.pool, claims, req.refresh_token.as_deref())
+            .await
+            .map_err(auth_token_error)?;
+    }
+    Ok(Json(LogoutResponse { success: true }))
+}
+
+/// Revoke every session of a (compromised) wallet - Admin only
+/// POST /admin/users/:address/revoke-sessions
+pub async fn revoke_sessions(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(address): Path<String>,
+) -> Result<Json<RevokeSessionsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let address = address.to_lowercase();
+    tracing::warn!("Sessions of {} revoked by {}", address, auth_user.address);
+    let revoked = AuthTokenService::revoke_all(&state.db.pool, &address).await.map_err(auth_token_error)?;
+    Ok(Json(RevokeSessionsResponse { address, revoked }))
+}
+
+/// Issue a nonce for a signed trading message (order, cancel, amend)
+/// GET /auth/nonce/trading
+pub async fn get_trading_nonce(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<TradingNonceResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let nonce = state.nonces.issue(&state.db.pool, &auth_user.address).await.map_err(|e| {
+        tracing::error!("Failed to issue trading nonce: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "获取交易nonce失败".to_string(),
+                code: "NONCE_ISSUE_FAILED".to_string(),
+                details: None,
+            }),
+        )
+    })?;
+
+    Ok(Json(TradingNonceResponse {
+        nonce,
+        required: state.live_config.current().require_trading_nonce,
+    }))
 }
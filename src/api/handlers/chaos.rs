@@ -0,0 +1,75 @@
+//! Chaos Injection API Handlers
+//!
+//! Admin controls for the fault injection layer; only compiled with the
+//! `chaos` feature.
+
+use axum::{extract::Path, http::StatusCode, Extension, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::middleware::AuthUser;
+use crate::chaos::{self, Fault, FaultConfig, FaultStatus};
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FaultsResponse {
+    pub faults: Vec<FaultStatus>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetFaultRequest {
+    /// false disarms the fault
+    pub enabled: bool,
+    #[serde(default)]
+    pub probability: Option<f64>,
+    #[serde(default)]
+    pub delay_ms: u64,
+}
+
+/// List faults and how often they fired - Admin only
+/// GET /admin/chaos
+pub async fn list_faults() -> Json<FaultsResponse> {
+    Json(FaultsResponse {
+        faults: chaos::statuses(),
+    })
+}
+
+/// Arm or disarm a fault - Admin only
+/// PUT /admin/chaos/:fault
+pub async fn set_fault(
+    Extension(auth_user): Extension<AuthUser>,
+    Path(fault): Path<String>,
+    Json(req): Json<SetFaultRequest>,
+) -> Result<Json<FaultsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let bad_request = |error: String, code: &str| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error,
+                code: code.to_string(),
+            }),
+        )
+    };
+    let fault: Fault = fault.parse().map_err(|e| bad_request(e, "UNKNOWN_FAULT"))?;
+    let config = req.enabled.then(|| FaultConfig {
+        probability: req.probability.unwrap_or(1.0),
+        delay_ms: req.delay_ms,
+    });
+    chaos::set(fault, config).map_err(|e| bad_request(e, "INVALID_FAULT_CONFIG"))?;
+
+    let action = if req.enabled { "armed" } else { "disarmed" };
+    tracing::warn!("Chaos fault {} {} by {}", fault.as_str(), action, auth_user.address);
+    Ok(list_faults().await)
+}
+
+/// Disarm every fault - Admin only
+/// DELETE /admin/chaos
+pub async fn reset_faults(Extension(auth_user): Extension<AuthUser>) -> Json<FaultsResponse> {
+    chaos::reset();
+    tracing::warn!("Chaos faults reset by {}", auth_user.address);
+    list_faults().await
+}
@@ -0,0 +1,93 @@
+//! Config Reload API Handlers
+//!
+//! Admin endpoints to apply hot-reloadable config changes and read the audit
+//! trail of past changes (see [`crate::config::reload`]).
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::auth::middleware::AuthUser;
+use crate::config::reload::{ConfigChangeRecord, ConfigDiff, ConfigError, ConfigStore};
+use crate::config::AppConfig;
+use crate::AppState;
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfigChangesQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConfigChangesResponse {
+    pub changes: Vec<ConfigChangeRecord>,
+}
+
+fn config_error(e: ConfigError) -> (StatusCode, Json<ErrorResponse>) {
+    let (status, code) = match &e {
+        ConfigError::Load(_) => (StatusCode::BAD_REQUEST, "CONFIG_LOAD_FAILED"),
+        ConfigError::Invalid { .. } => (StatusCode::BAD_REQUEST, "INVALID_CONFIG"),
+        ConfigError::DatabaseError(db) => {
+            tracing::error!("Config audit error: {}", db);
+            (StatusCode::INTERNAL_SERVER_ERROR, "DB_ERROR")
+        }
+    };
+    (
+        status,
+        Json(ErrorResponse {
+            error: e.to_string(),
+            code: code.to_string(),
+        }),
+    )
+}
+
+// ============================================================================
+// Admin Handlers
+// ============================================================================
+
+/// Re-read `.env` and the environment and apply reloadable changes - Admin only
+/// POST /admin/config/reload
+pub async fn reload_config(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<ConfigDiff>, (StatusCode, Json<ErrorResponse>)> {
+    dotenvy::dotenv_override().ok();
+    let candidate = AppConfig::load().map_err(|e| config_error(ConfigError::Load(e.to_string())))?;
+
+    let diff = state.live_config.apply(&candidate).map_err(config_error)?;
+    if !diff.restart_required.is_empty() {
+        tracing::warn!("Config reload: restart required for {}", diff.restart_required.join(", "));
+    }
+    if !diff.applied.is_empty() {
+        tracing::info!("Config reload by {} applied {} changes", auth_user.address, diff.applied.len());
+        ConfigStore::record_changes(&state.db.pool, &auth_user.address, &diff.applied)
+            .await
+            .map_err(config_error)?;
+    }
+
+    Ok(Json(diff))
+}
+
+/// Audit trail of applied config changes, newest first - Admin only
+/// GET /admin/config/changes
+pub async fn list_changes(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ConfigChangesQuery>,
+) -> Result<Json<ConfigChangesResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let limit = query.limit.unwrap_or(100).clamp(1, 500);
+    let changes = ConfigStore::changes(&state.db.pool, limit).await.map_err(config_error)?;
+    Ok(Json(ConfigChangesResponse { changes }))
+}
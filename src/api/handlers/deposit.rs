@@ -6,16 +6,21 @@ use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::auth::middleware::AuthUser;
+use crate::services::deposit::{CreditOutcome, DepositError, DepositEvent, DepositService};
 use crate::AppState;
 
 #[derive(Debug, Deserialize)]
 pub struct PrepareDepositRequest {
     pub token: String,
     pub amount: Decimal,
+    /// Chain to deposit on (default: the primary chain)
+    #[serde(default)]
+    pub chain_id: Option<u64>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct PrepareDepositResponse {
+    pub chain_id: u64,
     pub contract_address: String,
     pub token_address: String,
     pub amount: String,
@@ -32,23 +37,50 @@ pub struct DepositRecord {
     pub id: String,
     pub token: String,
     pub amount: Decimal,
+    pub chain_id: u64,
     pub tx_hash: String,
+    pub block_number: i64,
+    /// pending | credited | finalized | reorged | reverted
     pub status: String,
+    /// None until the chain head is known; 0 for deposits a reorg dropped
+    pub confirmations: Option<u64>,
+    /// Confirmations before the deposit is credited on its chain
+    pub required_confirmations: u64,
     pub created_at: i64,
 }
 
+#[derive(sqlx::FromRow)]
+struct DepositHistoryRow {
+    id: Uuid,
+    token: String,
+    amount: Decimal,
+    tx_hash: String,
+    block_number: i64,
+    chain_id: Option<i64>,
+    status: String,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: String,
+}
+
+
 /// Prepare deposit - returns contract call parameters
 pub async fn prepare_deposit(
     State(state): State<Arc<AppState>>,
     Extension(_auth_user): Extension<AuthUser>,
     Json(req): Json<PrepareDepositRequest>,
 ) -> Result<Json<PrepareDepositResponse>, StatusCode> {
-    // Get token address from config
-    let token_address = state.config.get_token_address(&req.token)
-        .ok_or(StatusCode::BAD_REQUEST)?;
+    // Vault and token address on the chosen chain
+    let chain = state.chains.for_token(req.chain_id, &req.token).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let token_address = chain.token_address(&req.token).ok_or(StatusCode::BAD_REQUEST)?;
 
     Ok(Json(PrepareDepositResponse {
-        contract_address: state.config.vault_address.clone(),
+        chain_id: chain.chain_id,
+        contract_address: chain.vault_address.clone(),
         token_address: token_address.to_string(),
         amount: req.amount.to_string(),
         estimated_gas: 100000,
@@ -61,9 +93,9 @@ pub async fn get_history(
     Extension(auth_user): Extension<AuthUser>,
 ) -> Result<Json<DepositHistoryResponse>, StatusCode> {
     // Fetch deposit history from database
-    let rows: Vec<(Uuid, String, Decimal, String, String, DateTime<Utc>)> = sqlx::query_as(
+    let rows: Vec<DepositHistoryRow> = sqlx::query_as(
         r#"
-        SELECT id, token, amount, tx_hash, status, created_at
+        SELECT id, token, amount, tx_hash, block_number, chain_id, status, created_at
         FROM deposits
         WHERE user_address = $1
         ORDER BY created_at DESC
@@ -78,19 +110,76 @@ pub async fn get_history(
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
+    let config = state.live_config.current();
     let deposits: Vec<DepositRecord> = rows
         .into_iter()
-        .map(|(id, token, amount, tx_hash, status, created_at)| {
+        .map(|row| {
+            let chain_id = row.chain_id.map_or(state.chains.primary_id(), |id| id as u64);
+            let finality = state
+                .deposit_confirmations
+                .finality(chain_id, config.deposit_confirmations, config.deposit_finality_blocks);
+            let confirmations = match row.status.as_str() {
+                "reorged" | "reverted" => Some(0),
+                _ => finality.confirmations_of(row.block_number),
+            };
             DepositRecord {
-                id: id.to_string(),
-                token,
-                amount,
-                tx_hash,
-                status,
-                created_at: created_at.timestamp(),
+                id: row.id.to_string(),
+                token: row.token,
+                amount: row.amount,
+                chain_id,
+                tx_hash: row.tx_hash,
+                block_number: row.block_number,
+                status: row.status,
+                confirmations,
+                required_confirmations: finality.confirmations,
+                created_at: row.created_at.timestamp(),
             }
         })
         .collect();
 
     Ok(Json(DepositHistoryResponse { deposits }))
 }
+
+/// Report a deposit log - Admin only
+/// POST /admin/deposits
+///
+/// Records the deposit and credits it once it has `deposit_confirmations`
+/// confirmations; until then the confirmation tracker follows it. Idempotent
+/// per (tx_hash, log_index): re-reporting a log returns no ledger entry and
+/// leaves the balance untouched.
+pub async fn credit_deposit(
+    State(state): State<Arc<AppState>>,
+    Json(mut event): Json<DepositEvent>,
+) -> Result<Json<CreditOutcome>, (StatusCode, Json<ErrorResponse>)> {
+    let chain_id = state.chains.for_token(event.chain_id, &event.token).map(|chain| chain.chain_id).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: e.to_string(),
+                code: "UNSUPPORTED_CHAIN".to_string(),
+            }),
+        )
+    })?;
+    event.chain_id = Some(chain_id);
+    let config = state.live_config.current();
+    let finality = state
+        .deposit_confirmations
+        .finality(chain_id, config.deposit_confirmations, config.deposit_finality_blocks);
+    DepositService::credit(&state.db.pool, &event, &finality).await.map(Json).map_err(|e| {
+        let (status, code) = match &e {
+            DepositError::InvalidAmount => (StatusCode::BAD_REQUEST, "INVALID_AMOUNT"),
+            DepositError::Conflict { .. } => (StatusCode::CONFLICT, "DEPOSIT_CONFLICT"),
+            DepositError::DatabaseError(db) => {
+                tracing::error!("Failed to credit deposit: {}", db);
+                (StatusCode::INTERNAL_SERVER_ERROR, "DB_ERROR")
+            }
+        };
+        (
+            status,
+            Json(ErrorResponse {
+                error: e.to_string(),
+                code: code.to_string(),
+            }),
+        )
+    })
+}
@@ -0,0 +1,263 @@
+//! History Export API Handlers
+//!
+//! Accounts queue bulk exports of their trades or funding settlements, or of
+//! a symbol's candles, poll the job, then download the finished file. Tax
+//! exports of a calendar year are requested by year and tax tool format.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::auth::middleware::AuthUser;
+use crate::db::timescale::KlinePeriod;
+use crate::services::export::{DataExport, ExportError, ExportFormat, ExportKind, ExportRequest};
+use crate::AppState;
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateExportRequest {
+    /// trades, funding_settlements, candles or tax
+    pub kind: String,
+    /// File format (default csv; koinly or cointracking for tax)
+    pub format: Option<String>,
+    /// Start time (Unix milliseconds)
+    pub from: i64,
+    /// End time (Unix milliseconds, exclusive)
+    pub to: i64,
+    /// Candle symbol (candles only)
+    pub symbol: Option<String>,
+    /// Candle period: 1m, 5m, 15m, 1h, 4h, 1d, 1w or 1M (candles only)
+    pub period: Option<String>,
+    /// Restrict trades / funding settlements to one market
+    pub market_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TaxExportQuery {
+    /// Calendar year (UTC)
+    pub year: i32,
+    /// Tax tool layout: koinly or cointracking
+    pub format: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListExportsQuery {
+    /// Most recent jobs returned (default 20, max 100)
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportResponse {
+    #[serde(flatten)]
+    pub job: DataExport,
+    /// Where to fetch the file once the export has completed
+    pub download_url: Option<String>,
+}
+
+impl From<DataExport> for ExportResponse {
+    fn from(job: DataExport) -> Self {
+        let download_url = job
+            .data_key
+            .is_some()
+            .then(|| format!("/api/v1/exports/{}/download", job.id));
+        Self { job, download_url }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportListResponse {
+    pub exports: Vec<ExportResponse>,
+}
+
+type ApiError = (StatusCode, Json<ErrorResponse>);
+
+fn error(status: StatusCode, message: impl Into<String>, code: &str) -> ApiError {
+    (
+        status,
+        Json(ErrorResponse {
+            error: message.into(),
+            code: code.to_string(),
+        }),
+    )
+}
+
+fn export_error(e: ExportError) -> ApiError {
+    let (status, code) = match &e {
+        ExportError::InvalidKind(_) => (StatusCode::BAD_REQUEST, "INVALID_EXPORT_KIND"),
+        ExportError::UnsupportedFormat(_) | ExportError::FormatNotForKind(..) => {
+            (StatusCode::BAD_REQUEST, "UNSUPPORTED_FORMAT")
+        }
+        ExportError::InvalidRange(_) => (StatusCode::BAD_REQUEST, "INVALID_RANGE"),
+        ExportError::MissingSymbol => (StatusCode::BAD_REQUEST, "MISSING_SYMBOL"),
+        ExportError::InvalidPeriod(_) => (StatusCode::BAD_REQUEST, "INVALID_PERIOD"),
+        ExportError::TooManyActive(_) => (StatusCode::TOO_MANY_REQUESTS, "TOO_MANY_EXPORTS"),
+        ExportError::NotFound => (StatusCode::NOT_FOUND, "EXPORT_NOT_FOUND"),
+        ExportError::NotReady => (StatusCode::CONFLICT, "EXPORT_NOT_READY"),
+        _ => {
+            tracing::error!("History export error: {}", e);
+            return error(StatusCode::INTERNAL_SERVER_ERROR, "History export error", "EXPORT_ERROR");
+        }
+    };
+    error(status, e.to_string(), code)
+}
+
+fn timestamp(millis: i64, field: &str) -> Result<DateTime<Utc>, ApiError> {
+    Utc.timestamp_millis_opt(millis)
+        .single()
+        .ok_or_else(|| error(StatusCode::BAD_REQUEST, format!("Invalid {}: {}", field, millis), "INVALID_RANGE"))
+}
+
+// ============================================================================
+// Handlers
+// ============================================================================
+
+/// Queue a history export
+/// POST /exports
+pub async fn create_export(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(req): Json<CreateExportRequest>,
+) -> Result<(StatusCode, Json<ExportResponse>), ApiError> {
+    let kind: ExportKind = req.kind.parse().map_err(export_error)?;
+    let format: ExportFormat = req.format.as_deref().unwrap_or("csv").parse().map_err(export_error)?;
+    let period = match req.period.as_deref() {
+        Some(period) => Some(
+            KlinePeriod::parse(period).ok_or_else(|| export_error(ExportError::InvalidPeriod(period.to_string())))?,
+        ),
+        None => None,
+    };
+    let symbol = match req.symbol.as_deref() {
+        Some(symbol) => Some(
+            state
+                .symbols
+                .resolve(symbol)
+                .map_err(|e| error(StatusCode::BAD_REQUEST, e.to_string(), "INVALID_SYMBOL"))?,
+        ),
+        None => None,
+    };
+
+    let request = ExportRequest {
+        kind,
+        format,
+        from: timestamp(req.from, "from")?,
+        to: timestamp(req.to, "to")?,
+        symbol,
+        period,
+        market_id: req.market_id,
+    };
+    let job = state
+        .exports
+        .request(&state.db.pool, &auth_user.address, &request)
+        .await
+        .map_err(export_error)?;
+
+    tracing::info!("Export {} ({}) requested by {}", job.id, job.kind, auth_user.address);
+    Ok((StatusCode::ACCEPTED, Json(job.into())))
+}
+
+/// Tax export of a calendar year: returns the year's export in that format if
+/// one is in progress or was completed after the year ended, otherwise queues one
+/// GET /account/export/tax
+pub async fn tax_export(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Query(query): Query<TaxExportQuery>,
+) -> Result<(StatusCode, Json<ExportResponse>), ApiError> {
+    let format: ExportFormat = query.format.parse().map_err(export_error)?;
+    if !format.supports(ExportKind::Tax) {
+        return Err(export_error(ExportError::FormatNotForKind(
+            format.as_str().to_string(),
+            ExportKind::Tax.as_str().to_string(),
+        )));
+    }
+    if !(2000..=Utc::now().year()).contains(&query.year) {
+        return Err(error(StatusCode::BAD_REQUEST, format!("Invalid year: {}", query.year), "INVALID_RANGE"));
+    }
+
+    let (job, queued) = state
+        .exports
+        .tax_export(&state.db.pool, &auth_user.address, query.year, format)
+        .await
+        .map_err(export_error)?;
+    if queued {
+        tracing::info!("Tax export {} ({} {}) requested by {}", job.id, query.year, job.format, auth_user.address);
+    }
+    let status = if job.data_key.is_some() { StatusCode::OK } else { StatusCode::ACCEPTED };
+    Ok((status, Json(job.into())))
+}
+
+/// The account's history exports, newest first
+/// GET /exports
+pub async fn list_exports(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Query(query): Query<ListExportsQuery>,
+) -> Result<Json<ExportListResponse>, ApiError> {
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    let jobs = state
+        .exports
+        .list(&state.db.pool, &auth_user.address, limit)
+        .await
+        .map_err(export_error)?;
+    Ok(Json(ExportListResponse {
+        exports: jobs.into_iter().map(ExportResponse::from).collect(),
+    }))
+}
+
+/// Poll an export job
+/// GET /exports/:export_id
+pub async fn get_export(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(export_id): Path<Uuid>,
+) -> Result<Json<ExportResponse>, ApiError> {
+    let job = state
+        .exports
+        .get(&state.db.pool, &auth_user.address, export_id)
+        .await
+        .map_err(export_error)?;
+    Ok(Json(job.into()))
+}
+
+/// Download a completed export
+/// GET /exports/:export_id/download
+pub async fn download_export(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(export_id): Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (job, data) = state
+        .exports
+        .data(&state.db.pool, &auth_user.address, export_id)
+        .await
+        .map_err(export_error)?;
+    let content_type = job
+        .format
+        .parse::<ExportFormat>()
+        .map(|f| f.content_type())
+        .unwrap_or("application/octet-stream");
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", job.filename())),
+            (header::ETAG, format!("\"{}\"", job.data_sha256.unwrap_or_default())),
+        ],
+        data,
+    ))
+}
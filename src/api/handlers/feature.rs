@@ -0,0 +1,186 @@
+//! Feature Entitlement API Handlers
+//!
+//! Lets accounts list the beta features available to them, and admins manage
+//! feature flags and per-account grants.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::auth::middleware::AuthUser;
+use crate::services::features::{Entitlement, FeatureError, FeatureFlag, FeatureService};
+use crate::AppState;
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeaturesResponse {
+    pub features: Vec<Entitlement>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeatureFlagsResponse {
+    pub flags: Vec<FeatureFlag>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpsertFeatureFlagRequest {
+    pub description: Option<String>,
+    #[serde(default)]
+    pub enabled_for_all: bool,
+    /// ERC-20 / ERC-721 contract whose holders get the feature
+    pub gate_token: Option<String>,
+    /// Minimum raw balance for the token gate (default 1)
+    pub gate_min_balance: Option<Decimal>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GrantFeatureRequest {
+    pub address: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GrantResponse {
+    pub feature: String,
+    pub address: String,
+    pub granted: bool,
+}
+
+pub(crate) fn feature_error(e: FeatureError) -> (StatusCode, Json<ErrorResponse>) {
+    let (status, code) = match &e {
+        FeatureError::UnknownFeature(_) => (StatusCode::NOT_FOUND, "FEATURE_NOT_FOUND"),
+        FeatureError::InvalidAddress(_) => (StatusCode::BAD_REQUEST, "INVALID_ADDRESS"),
+        FeatureError::ChainError(_) => (StatusCode::BAD_GATEWAY, "CHAIN_ERROR"),
+        FeatureError::DatabaseError(db) => {
+            tracing::error!("Feature database error: {}", db);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Database error".to_string(),
+                    code: "DB_ERROR".to_string(),
+                }),
+            );
+        }
+    };
+    (
+        status,
+        Json(ErrorResponse {
+            error: e.to_string(),
+            code: code.to_string(),
+        }),
+    )
+}
+
+// ============================================================================
+// Account Handlers
+// ============================================================================
+
+/// Features available to the authenticated account
+/// GET /account/features
+pub async fn get_features(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<FeaturesResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let features = state
+        .features
+        .entitlements(&state.db.pool, &auth_user.address)
+        .await
+        .map_err(feature_error)?;
+
+    Ok(Json(FeaturesResponse { features }))
+}
+
+// ============================================================================
+// Admin Handlers
+// ============================================================================
+
+/// List feature flags - Admin only
+/// GET /admin/features
+pub async fn list_flags(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<FeatureFlagsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let flags = FeatureService::list_flags(&state.db.pool).await.map_err(feature_error)?;
+    Ok(Json(FeatureFlagsResponse { flags }))
+}
+
+/// Create or update a feature flag - Admin only
+/// PUT /admin/features/:key
+pub async fn upsert_flag(
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<String>,
+    Json(req): Json<UpsertFeatureFlagRequest>,
+) -> Result<Json<FeatureFlag>, (StatusCode, Json<ErrorResponse>)> {
+    let flag = FeatureService::upsert_flag(
+        &state.db.pool,
+        &key,
+        req.description.as_deref(),
+        req.enabled_for_all,
+        req.gate_token.as_deref(),
+        req.gate_min_balance.unwrap_or(Decimal::ONE),
+    )
+    .await
+    .map_err(feature_error)?;
+
+    tracing::info!("Feature flag {} updated (enabled_for_all: {})", key, flag.enabled_for_all);
+    Ok(Json(flag))
+}
+
+/// Grant a feature to an account - Admin only
+/// POST /admin/features/:key/grants
+pub async fn grant_feature(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(key): Path<String>,
+    Json(req): Json<GrantFeatureRequest>,
+) -> Result<Json<GrantResponse>, (StatusCode, Json<ErrorResponse>)> {
+    FeatureService::grant(&state.db.pool, &key, &req.address, &auth_user.address)
+        .await
+        .map_err(feature_error)?;
+
+    tracing::info!("Feature {} granted to {} by {}", key, req.address, auth_user.address);
+    Ok(Json(GrantResponse {
+        feature: key,
+        address: req.address.to_lowercase(),
+        granted: true,
+    }))
+}
+
+/// Revoke a granted feature - Admin only
+/// DELETE /admin/features/:key/grants/:address
+pub async fn revoke_feature(
+    State(state): State<Arc<AppState>>,
+    Path((key, address)): Path<(String, String)>,
+) -> Result<Json<GrantResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let revoked = FeatureService::revoke(&state.db.pool, &key, &address)
+        .await
+        .map_err(feature_error)?;
+    if !revoked {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Grant not found".to_string(),
+                code: "GRANT_NOT_FOUND".to_string(),
+            }),
+        ));
+    }
+
+    tracing::info!("Feature {} revoked from {}", key, address);
+    Ok(Json(GrantResponse {
+        feature: key,
+        address: address.to_lowercase(),
+        granted: false,
+    }))
+}
@@ -0,0 +1,169 @@
+//! Fee Tier API Handlers
+//!
+//! Lets accounts see their VIP fee tier and the tier schedule, and admins
+//! manage tiers and per-account overrides.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::auth::middleware::AuthUser;
+use crate::services::fees::{AccountFeeTier, FeeError, VipTier};
+use crate::AppState;
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VipTiersResponse {
+    pub tiers: Vec<VipTier>,
+    pub window_days: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetFeeOverrideRequest {
+    pub level: i32,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeeOverrideResponse {
+    pub address: String,
+    /// Pinned tier (None once the override is removed)
+    pub tier: Option<VipTier>,
+}
+
+fn fee_error(e: FeeError) -> (StatusCode, Json<ErrorResponse>) {
+    let (status, code) = match &e {
+        FeeError::UnknownTier(_) => (StatusCode::NOT_FOUND, "FEE_TIER_NOT_FOUND"),
+        FeeError::InvalidTier(_) => (StatusCode::BAD_REQUEST, "INVALID_FEE_TIER"),
+        FeeError::InvalidAddress(_) => (StatusCode::BAD_REQUEST, "INVALID_ADDRESS"),
+        FeeError::DatabaseError(db) => {
+            tracing::error!("Fee tier database error: {}", db);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Database error".to_string(),
+                    code: "DB_ERROR".to_string(),
+                }),
+            );
+        }
+    };
+    (
+        status,
+        Json(ErrorResponse {
+            error: e.to_string(),
+            code: code.to_string(),
+        }),
+    )
+}
+
+// ============================================================================
+// Public / Account Handlers
+// ============================================================================
+
+/// VIP fee tier schedule
+/// GET /fee-tiers
+pub async fn list_tiers(State(state): State<Arc<AppState>>) -> Json<VipTiersResponse> {
+    Json(VipTiersResponse {
+        tiers: state.fees.tiers(),
+        window_days: state.config.fee_volume_window_days,
+    })
+}
+
+/// Fee tier of the authenticated account, with its window volume
+/// GET /account/fee-tier
+pub async fn get_account_tier(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<AccountFeeTier>, (StatusCode, Json<ErrorResponse>)> {
+    let tier = state
+        .fees
+        .account_tier(&state.db.pool, &auth_user.address)
+        .await
+        .map_err(fee_error)?;
+    Ok(Json(tier))
+}
+
+// ============================================================================
+// Admin Handlers
+// ============================================================================
+
+/// Create or update a VIP fee tier - Admin only
+/// PUT /admin/vip-tiers/:level
+pub async fn upsert_tier(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(level): Path<i32>,
+    Json(mut tier): Json<VipTier>,
+) -> Result<Json<VipTier>, (StatusCode, Json<ErrorResponse>)> {
+    tier.level = level;
+    let tier = state.fees.upsert_tier(&state.db.pool, &tier).await.map_err(fee_error)?;
+
+    tracing::info!(
+        "VIP fee tier {} ({}) set by {}: min volume {}, taker {}%, maker {}%",
+        tier.level,
+        tier.name,
+        auth_user.address,
+        tier.min_volume,
+        tier.taker_fee_pct,
+        tier.maker_fee_pct
+    );
+    Ok(Json(tier))
+}
+
+/// Pin an account to a fee tier - Admin only
+/// PUT /admin/users/:address/fee-tier
+pub async fn set_override(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(address): Path<String>,
+    Json(req): Json<SetFeeOverrideRequest>,
+) -> Result<Json<FeeOverrideResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let tier = state
+        .fees
+        .set_override(&state.db.pool, &address, req.level, req.note.as_deref(), &auth_user.address)
+        .await
+        .map_err(fee_error)?;
+
+    tracing::info!("Fee tier of {} pinned to {} by {}", address, tier.name, auth_user.address);
+    Ok(Json(FeeOverrideResponse {
+        address: address.to_lowercase(),
+        tier: Some(tier),
+    }))
+}
+
+/// Remove an account's pinned fee tier - Admin only
+/// DELETE /admin/users/:address/fee-tier
+pub async fn clear_override(
+    State(state): State<Arc<AppState>>,
+    Path(address): Path<String>,
+) -> Result<Json<FeeOverrideResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let removed = state.fees.clear_override(&state.db.pool, &address).await.map_err(fee_error)?;
+    if !removed {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Fee tier override not found".to_string(),
+                code: "OVERRIDE_NOT_FOUND".to_string(),
+            }),
+        ));
+    }
+
+    tracing::info!("Fee tier override of {} removed", address);
+    Ok(Json(FeeOverrideResponse {
+        address: address.to_lowercase(),
+        tier: None,
+    }))
+}
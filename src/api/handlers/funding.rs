@@ -0,0 +1,232 @@
+//! Funding API Handlers
+//!
+//! Public endpoints for the premium index behind funding rates (the current
+//! interval's predicted rate and the sample history), and admin endpoints for
+//! funding parameters. Every parameter change is written to the governance
+//! log together with the admin who made it.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::auth::middleware::AuthUser;
+use crate::services::funding::{
+    ClampAlert, FundingConfig, FundingError, FundingParamsUpdate, FundingParameterChange, FundingService,
+};
+use crate::services::premium_index::{self, FundingPrediction, PremiumIndexError, PremiumIndexService, PremiumSample};
+use crate::AppState;
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FundingConfigsResponse {
+    pub configs: Vec<FundingConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateFundingConfigRequest {
+    #[serde(flatten)]
+    pub params: FundingParamsUpdate,
+    /// Why the parameters are changing, kept in the governance log
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FundingLogQuery {
+    pub symbol: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FundingChangesResponse {
+    pub changes: Vec<FundingParameterChange>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClampAlertsResponse {
+    pub alerts: Vec<ClampAlert>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PremiumIndexQuery {
+    /// Start time (Unix seconds)
+    pub from: Option<i64>,
+    /// End time (Unix seconds, exclusive; default now)
+    pub to: Option<i64>,
+    /// Most recent samples returned (default 480, max 1500)
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PremiumIndexResponse {
+    pub symbol: String,
+    /// Newest first
+    pub samples: Vec<PremiumSample>,
+}
+
+fn funding_error(e: FundingError) -> (StatusCode, Json<ErrorResponse>) {
+    match e {
+        FundingError::InvalidParameters(_) => (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: e.to_string(),
+                code: "INVALID_FUNDING_PARAMS".to_string(),
+            }),
+        ),
+        FundingError::DatabaseError(db) => {
+            tracing::error!("Funding database error: {}", db);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Database error".to_string(),
+                    code: "DB_ERROR".to_string(),
+                }),
+            )
+        }
+    }
+}
+
+fn premium_index_error(e: PremiumIndexError) -> (StatusCode, Json<ErrorResponse>) {
+    match e {
+        PremiumIndexError::InvalidSymbol(_) => (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: e.to_string(),
+                code: "INVALID_SYMBOL".to_string(),
+            }),
+        ),
+        PremiumIndexError::Funding(e) => funding_error(e),
+        PremiumIndexError::DatabaseError(e) => funding_error(FundingError::DatabaseError(e)),
+    }
+}
+
+// ============================================================================
+// Public Handlers
+// ============================================================================
+
+/// Running estimate of the current funding interval's rate
+/// GET /funding-rates/:symbol/predicted
+pub async fn get_predicted_rate(
+    State(state): State<Arc<AppState>>,
+    Path(symbol): Path<String>,
+) -> Result<Json<FundingPrediction>, (StatusCode, Json<ErrorResponse>)> {
+    let symbol = premium_index::parse_symbol(&symbol).map_err(premium_index_error)?;
+    let prediction = PremiumIndexService::predicted(&state.db.pool, &symbol, Utc::now())
+        .await
+        .map_err(premium_index_error)?;
+    Ok(Json(prediction))
+}
+
+/// Premium index samples of a symbol
+/// GET /funding-rates/:symbol/premium-index
+pub async fn get_premium_index(
+    State(state): State<Arc<AppState>>,
+    Path(symbol): Path<String>,
+    Query(query): Query<PremiumIndexQuery>,
+) -> Result<Json<PremiumIndexResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let symbol = premium_index::parse_symbol(&symbol).map_err(premium_index_error)?;
+    let limit = query.limit.unwrap_or(480).clamp(1, 1500);
+    let end = query.to.and_then(|to| DateTime::from_timestamp(to, 0)).unwrap_or_else(Utc::now);
+    let start = query
+        .from
+        .and_then(|from| DateTime::from_timestamp(from, 0))
+        .unwrap_or(DateTime::UNIX_EPOCH);
+
+    let samples = PremiumIndexService::history(&state.db.pool, &symbol, start, end, limit)
+        .await
+        .map_err(premium_index_error)?;
+    Ok(Json(PremiumIndexResponse { symbol, samples }))
+}
+
+// ============================================================================
+// Admin Handlers
+// ============================================================================
+
+/// List funding parameters - Admin only
+/// GET /admin/funding/config
+pub async fn list_configs(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<FundingConfigsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let configs = FundingService::list_configs(&state.db.pool).await.map_err(funding_error)?;
+    Ok(Json(FundingConfigsResponse { configs }))
+}
+
+/// Change a symbol's funding parameters - Admin only
+/// PUT /admin/funding/config/:symbol
+pub async fn update_config(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(symbol): Path<String>,
+    Json(req): Json<UpdateFundingConfigRequest>,
+) -> Result<Json<FundingConfig>, (StatusCode, Json<ErrorResponse>)> {
+    let config = FundingService::update_config(
+        &state.db.pool,
+        &symbol,
+        &req.params,
+        &auth_user.address,
+        req.reason.as_deref(),
+    )
+    .await
+    .map_err(funding_error)?;
+
+    tracing::info!(
+        "Funding parameters for {} changed by {} (interval {}h, rate [{}, {}], impact pool {})",
+        symbol,
+        auth_user.address,
+        config.funding_interval_hours,
+        config.min_funding_rate,
+        config.max_funding_rate,
+        config.impact_pool_size
+    );
+    Ok(Json(config))
+}
+
+/// Funding parameter governance log - Admin only
+/// GET /admin/funding/changes
+pub async fn list_changes(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<FundingLogQuery>,
+) -> Result<Json<FundingChangesResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let changes = FundingService::list_changes(
+        &state.db.pool,
+        query.symbol.as_deref(),
+        query.limit.unwrap_or(50).min(100),
+        query.offset.unwrap_or(0),
+    )
+    .await
+    .map_err(funding_error)?;
+
+    Ok(Json(FundingChangesResponse { changes }))
+}
+
+/// Funding clamp alerts - Admin only
+/// GET /admin/funding/alerts
+pub async fn list_alerts(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<FundingLogQuery>,
+) -> Result<Json<ClampAlertsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let alerts = FundingService::list_alerts(
+        &state.db.pool,
+        query.symbol.as_deref(),
+        query.limit.unwrap_or(50).min(100),
+        query.offset.unwrap_or(0),
+    )
+    .await
+    .map_err(funding_error)?;
+
+    Ok(Json(ClampAlertsResponse { alerts }))
+}
@@ -0,0 +1,100 @@
+//! Background Job API Handlers
+//!
+//! Admin endpoints to inspect the periodic job scheduler and trigger a job run
+//! on demand.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::auth::middleware::AuthUser;
+use crate::services::jobs::{JobError, JobStatus};
+use crate::services::leader::LeaderStatus;
+use crate::AppState;
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JobsResponse {
+    pub jobs: Vec<JobStatus>,
+    /// Which instance runs the `leader_only` jobs (None if it can't be read)
+    pub leader: Option<LeaderStatus>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TriggerJobResponse {
+    pub triggered: bool,
+    pub job: JobStatus,
+}
+
+fn job_not_found(name: &str) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(ErrorResponse {
+            error: format!("Job not found: {}", name),
+            code: "JOB_NOT_FOUND".to_string(),
+        }),
+    )
+}
+
+// ============================================================================
+// Admin Handlers
+// ============================================================================
+
+/// List background jobs and their last run - Admin only
+/// GET /admin/jobs
+pub async fn list_jobs(State(state): State<Arc<AppState>>) -> Json<JobsResponse> {
+    let leader = match state.leader.status().await {
+        Ok(status) => Some(status),
+        Err(e) => {
+            tracing::warn!("Failed to read job leader: {}", e);
+            None
+        }
+    };
+    Json(JobsResponse {
+        jobs: state.jobs.statuses(),
+        leader,
+    })
+}
+
+/// Get one background job - Admin only
+/// GET /admin/jobs/:name
+pub async fn get_job(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<Json<JobStatus>, (StatusCode, Json<ErrorResponse>)> {
+    state.jobs.status(&name).map(Json).ok_or_else(|| job_not_found(&name))
+}
+
+/// Run a background job now - Admin only
+/// POST /admin/jobs/:name/run
+pub async fn run_job(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(name): Path<String>,
+) -> Result<Json<TriggerJobResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let job = state.jobs.trigger(&name).map_err(|e| match e {
+        JobError::NotFound(_) | JobError::AlreadyRegistered(_) => job_not_found(&name),
+        JobError::NotLeader(_) => (
+            StatusCode::CONFLICT,
+            Json(ErrorResponse {
+                error: e.to_string(),
+                code: "JOB_NOT_LEADER".to_string(),
+            }),
+        ),
+    })?;
+    tracing::info!("Job {} triggered by {}", name, auth_user.address);
+    Ok(Json(TriggerJobResponse { triggered: true, job }))
+}
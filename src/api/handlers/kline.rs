@@ -21,45 +21,6 @@ use std::sync::Arc;
 use crate::services::kline::{Candle, HistoricalKline, KlinePeriod};
 use crate::AppState;
 
-/// Normalize symbol format to backend format (BTCUSDT)
-/// Supports multiple input formats:
-/// - "BTCUSDT" -> "BTCUSDT" (already correct)
-/// - "BTC-USD" -> "BTCUSDT" (frontend TradingView format)
-/// - "BTC-USDT" -> "BTCUSDT"
-/// - "btcusdt" -> "BTCUSDT" (lowercase)
-fn normalize_symbol(symbol: &str) -> String {
-    let upper = symbol.to_uppercase();
-    
-    // If already in BTCUSDT format (no separators), return as is
-    if !upper.contains('-') && !upper.contains('/') && !upper.contains('_') {
-        return upper;
-    }
-    
-    // Handle BTC-USD format (convert to BTCUSDT)
-    if upper.ends_with("-USD") {
-        let base = upper.strip_suffix("-USD").unwrap_or(&upper);
-        return format!("{}USDT", base);
-    }
-    
-    // Handle BTC-USDT format (convert to BTCUSDT)
-    if upper.contains("-USDT") {
-        return upper.replace("-", "");
-    }
-    
-    // Handle BTC/USD or BTC_USD formats
-    if upper.contains("/") || upper.contains("_") {
-        let cleaned = upper.replace("/", "").replace("_", "");
-        if !cleaned.ends_with("USDT") && cleaned.ends_with("USD") {
-            let base = cleaned.strip_suffix("USD").unwrap_or(&cleaned);
-            return format!("{}USDT", base);
-        }
-        return cleaned;
-    }
-    
-    // Default: return uppercase version
-    upper
-}
-
 /// Query parameters for historical candles
 #[derive(Debug, Deserialize)]
 pub struct CandlesQuery {
@@ -133,6 +94,21 @@ pub struct KlineError {
     pub code: String,
 }
 
+/// Canonical symbol of a path symbol, or the error response listing supported formats
+fn resolve_symbol(state: &AppState, symbol: &str) -> Result<String, axum::response::Response> {
+    state.symbols.resolve(symbol).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(KlineError {
+                error: "unknown_symbol".to_string(),
+                message: e.to_string(),
+                code: "ERR_UNKNOWN_SYMBOL".to_string(),
+            }),
+        )
+            .into_response()
+    })
+}
+
 /// Get historical candles
 ///
 /// GET /api/v1/markets/{symbol}/candles?period=5m&limit=100
@@ -141,11 +117,14 @@ pub async fn get_candles(
     Path(symbol): Path<String>,
     Query(query): Query<CandlesQuery>,
 ) -> impl IntoResponse {
-    // Normalize symbol format (supports BTC-USD, BTCUSDT, etc.)
-    let normalized_symbol = normalize_symbol(&symbol);
-    
+    // Resolve frontend aliases (BTC-USD, btc/usdt, ...) to the canonical symbol
+    let normalized_symbol = match resolve_symbol(&state, &symbol) {
+        Ok(symbol) => symbol,
+        Err(response) => return response,
+    };
+
     // Validate period
-    let period = match KlinePeriod::from_str(&query.period) {
+    let period = match KlinePeriod::parse(&query.period) {
         Some(p) => p,
         None => {
             return (
@@ -202,11 +181,14 @@ pub async fn get_latest_candle(
     Path(symbol): Path<String>,
     Query(query): Query<CandlesQuery>,
 ) -> impl IntoResponse {
-    // Normalize symbol format (supports BTC-USD, BTCUSDT, etc.)
-    let normalized_symbol = normalize_symbol(&symbol);
-    
+    // Resolve frontend aliases (BTC-USD, btc/usdt, ...) to the canonical symbol
+    let normalized_symbol = match resolve_symbol(&state, &symbol) {
+        Ok(symbol) => symbol,
+        Err(response) => return response,
+    };
+
     // Validate period
-    let period = match KlinePeriod::from_str(&query.period) {
+    let period = match KlinePeriod::parse(&query.period) {
         Some(p) => p,
         None => {
             return (
@@ -487,7 +469,7 @@ pub async fn batch_import_klines(
 
     for (idx, dto) in request.klines.iter().enumerate() {
         // Validate period
-        if KlinePeriod::from_str(&dto.period).is_none() {
+        if KlinePeriod::parse(&dto.period).is_none() {
             conversion_errors.push(format!(
                 "K-line #{}: Invalid period '{}'", 
                 idx + 1, 
@@ -746,7 +728,10 @@ pub async fn repair_klines(
 
     // Determine symbols to repair
     let symbols_to_repair: Vec<String> = if let Some(ref sym) = query.symbol {
-        vec![sym.to_uppercase()]
+        match resolve_symbol(&state, sym) {
+            Ok(symbol) => vec![symbol],
+            Err(response) => return response,
+        }
     } else {
         // Use configured trading pairs
         state.config.get_trading_pairs()
@@ -0,0 +1,116 @@
+//! Margin Call API Handlers
+//!
+//! Lets accounts configure their margin call escalation ladder and review
+//! the actions it took.
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::auth::middleware::AuthUser;
+use crate::services::margin_call::{MarginCallError, MarginCallEventRecord, MarginCallService, MarginCallSettings};
+use crate::AppState;
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HistoryQuery {
+    /// Most recent actions returned (default 50, max 500)
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MarginCallHistoryResponse {
+    pub events: Vec<MarginCallEventRecord>,
+}
+
+fn margin_call_error(e: MarginCallError) -> (StatusCode, Json<ErrorResponse>) {
+    let (status, code) = match &e {
+        MarginCallError::InvalidRatios | MarginCallError::InvalidReducePercent => {
+            (StatusCode::BAD_REQUEST, "INVALID_MARGIN_CALL_SETTINGS")
+        }
+        MarginCallError::InvalidWebhook(_) => (StatusCode::BAD_REQUEST, "INVALID_WEBHOOK_URL"),
+        MarginCallError::InvalidEmail(_) => (StatusCode::BAD_REQUEST, "INVALID_EMAIL"),
+        MarginCallError::PositionError(_) | MarginCallError::DatabaseError(_) => {
+            tracing::error!("Margin call error: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Database error".to_string(),
+                    code: "DB_ERROR".to_string(),
+                }),
+            );
+        }
+    };
+    (
+        status,
+        Json(ErrorResponse {
+            error: e.to_string(),
+            code: code.to_string(),
+        }),
+    )
+}
+
+// ============================================================================
+// Account Handlers
+// ============================================================================
+
+/// Margin call ladder settings of the authenticated account
+/// GET /account/margin-calls
+pub async fn get_settings(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<MarginCallSettings>, (StatusCode, Json<ErrorResponse>)> {
+    let settings = MarginCallService::get_settings(&state.db.pool, &auth_user.address)
+        .await
+        .map_err(margin_call_error)?;
+    Ok(Json(settings))
+}
+
+/// Replace the margin call ladder settings
+/// PUT /account/margin-calls
+pub async fn set_settings(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(settings): Json<MarginCallSettings>,
+) -> Result<Json<MarginCallSettings>, (StatusCode, Json<ErrorResponse>)> {
+    MarginCallService::set_settings(&state.db.pool, &auth_user.address, &settings)
+        .await
+        .map_err(margin_call_error)?;
+
+    tracing::info!(
+        "Margin call ladder of {} set: notice {}, alert {}, reduce {} ({}%)",
+        auth_user.address,
+        settings.notice_ratio,
+        settings.alert_ratio,
+        settings.reduce_ratio,
+        settings.auto_reduce_percent
+    );
+    Ok(Json(settings))
+}
+
+/// Margin call actions taken for the authenticated account, newest first
+/// GET /account/margin-calls/history
+pub async fn get_history(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<MarginCallHistoryResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let events = MarginCallService::history(&state.db.pool, &auth_user.address, limit)
+        .await
+        .map_err(margin_call_error)?;
+    Ok(Json(MarginCallHistoryResponse { events }))
+}
@@ -7,13 +7,27 @@ use axum::{
     http::StatusCode,
     Json,
 };
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::db::timescale::{Kline, KlinePeriod, TimescaleOps};
 use crate::models::market::ShareType;
+use crate::services::funding::FundingService;
+use crate::services::matching::{validate_group, TradeCursor, TradeRecord};
+use crate::services::market::calendar::{SessionState, TradingCalendar};
+use crate::services::market::index_price::IndexPrice;
+use crate::services::market::mark_price::{MarkPriceConfig, MarkPriceError};
+use crate::services::market::{MarketConfig, RiskLimit};
+use crate::services::market::rules::{MarketLimits, MarketRules, MarketRulesError, MarketRulesService};
+use crate::services::market::symbols::SymbolMapping;
+use crate::services::market::ticker::{book_ticker, BookTicker, RollingTicker};
+use crate::services::risk_disclosure::{self, DisclosureInputs, RiskDisclosure};
+use crate::services::settlement_price::{SettlementPrice, SettlementPriceError, SettlementPriceService};
+use crate::services::stats::{self, MarketStats, StatsError, StatsService};
+use crate::websocket::signing::{FeedKeyInfo, FEED_SIGNATURE_ALG};
 use crate::AppState;
 
 // ============================================================================
@@ -49,6 +63,9 @@ pub struct MarketInfo {
     pub total_volume: Decimal,
     pub liquidity: Decimal,
     pub created_at: i64,
+    /// Trading session state (absent for markets that trade 24/7)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session: Option<SessionState>,
 }
 
 #[derive(Debug, Serialize)]
@@ -80,6 +97,9 @@ pub struct OrderbookResponse {
     pub share_type: ShareType,
     pub bids: Vec<OrderbookLevel>,
     pub asks: Vec<OrderbookLevel>,
+    /// Price bucket size the levels are grouped by
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<Decimal>,
     pub timestamp: i64,
 }
 
@@ -87,10 +107,16 @@ pub struct OrderbookResponse {
 #[derive(Debug, Serialize)]
 pub struct TradeInfo {
     pub id: Uuid,
+    /// Engine execution sequence number (None for trades before it existed)
+    pub exec_id: Option<i64>,
     pub price: Decimal,
     pub amount: Decimal,
     pub side: String,
     pub share_type: ShareType,
+    /// Side that took liquidity (None for block trades)
+    pub aggressor: Option<String>,
+    /// "liquidation" or "adl" when forced on the taker (None for voluntary trades)
+    pub forced: Option<String>,
     pub timestamp: i64,
 }
 
@@ -98,7 +124,10 @@ pub struct TradeInfo {
 pub struct TradesResponse {
     pub market_id: Uuid,
     pub outcome_id: Uuid,
+    /// Newest first
     pub trades: Vec<TradeInfo>,
+    /// More trades lie beyond the page: older ones, or newer ones for `after`
+    pub has_more: bool,
 }
 
 /// Price/ticker information for a market
@@ -117,6 +146,21 @@ pub struct OutcomeTicker {
     pub yes_price: Decimal,
     pub no_price: Decimal,
     pub probability: Decimal,
+    /// Mark prices of the Yes / No books (absent until first computed)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub yes_mark_price: Option<Decimal>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub no_mark_price: Option<Decimal>,
+    /// Rolling 24h statistics of the Yes / No books (absent without trades)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub yes_24h: Option<RollingTicker>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub no_24h: Option<RollingTicker>,
+    /// Top of the live Yes / No books (absent for books not loaded)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub yes_book: Option<BookTicker>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub no_book: Option<BookTicker>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -124,12 +168,20 @@ pub struct OrderbookQuery {
     pub outcome_id: Uuid,
     pub share_type: Option<String>,
     pub depth: Option<usize>,
+    /// Merge levels into price buckets of this size (e.g. 0.01, 0.05, 0.1)
+    pub group: Option<Decimal>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct TradesQuery {
     pub outcome_id: Uuid,
     pub limit: Option<i64>,
+    /// Trades older than this trade id
+    pub before: Option<Uuid>,
+    /// Trades newer than this trade id
+    pub after: Option<Uuid>,
+    /// Taker side: buy or sell
+    pub side: Option<String>,
 }
 
 // ============================================================================
@@ -164,6 +216,7 @@ pub async fn list_markets(
         FROM markets m
         WHERE ($1::text IS NULL OR m.category = $1)
         AND ($2::text IS NULL OR m.status::text = $2)
+        AND m.delisted_at IS NULL
         ORDER BY m.volume_24h DESC
         LIMIT $3 OFFSET $4
         "#,
@@ -191,6 +244,7 @@ pub async fn list_markets(
         SELECT COUNT(*) FROM markets
         WHERE ($1::text IS NULL OR category = $1)
         AND ($2::text IS NULL OR status::text = $2)
+        AND delisted_at IS NULL
         "#,
     )
     .bind(query.category.as_ref())
@@ -250,6 +304,7 @@ pub async fn list_markets(
             total_volume,
             liquidity,
             created_at: created_at.timestamp_millis(),
+            session: state.matching_engine.session_state(id),
         });
     }
 
@@ -272,6 +327,19 @@ pub async fn get_orderbook(
         .as_ref()
         .and_then(|s| s.parse().ok())
         .unwrap_or(ShareType::Yes);
+    let group = query
+        .group
+        .map(validate_group)
+        .transpose()
+        .map_err(|message| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: message,
+                    code: "INVALID_GROUP".to_string(),
+                }),
+            )
+        })?;
 
     // Validate market exists
     let market_exists: Option<(Uuid,)> = sqlx::query_as("SELECT id FROM markets WHERE id = $1")
@@ -303,7 +371,11 @@ pub async fn get_orderbook(
     let orderbook_key = format!("{}:{}:{}", market_id, query.outcome_id, share_type);
 
     // Try to get orderbook from matching engine
-    match state.matching_engine.get_orderbook(&orderbook_key, depth) {
+    let snapshot = match group {
+        Some(group) => state.matching_engine.get_grouped_orderbook(&orderbook_key, depth, group),
+        None => state.matching_engine.get_orderbook(&orderbook_key, depth),
+    };
+    match snapshot {
         Ok(snapshot) => {
             let bids: Vec<OrderbookLevel> = snapshot
                 .bids
@@ -322,6 +394,7 @@ pub async fn get_orderbook(
                 share_type,
                 bids,
                 asks,
+                group,
                 timestamp: snapshot.timestamp,
             }))
         }
@@ -333,65 +406,166 @@ pub async fn get_orderbook(
                 share_type,
                 bids: vec![],
                 asks: vec![],
+                group,
                 timestamp: chrono::Utc::now().timestamp_millis(),
             }))
         }
     }
 }
 
-/// Get recent trades for a market outcome
+/// Get recent trades for a market outcome, paged by trade id cursors
 /// GET /markets/:market_id/trades
+///
+/// Pages are served from the engine's recent trade history when it holds
+/// them, and read from the database otherwise.
 pub async fn get_trades(
     State(state): State<Arc<AppState>>,
     Path(market_id): Path<Uuid>,
     Query(query): Query<TradesQuery>,
 ) -> Result<Json<TradesResponse>, (StatusCode, Json<ErrorResponse>)> {
-    let limit = query.limit.unwrap_or(50).min(100);
-
-    let rows: Vec<(Uuid, Decimal, Decimal, String, String, DateTime<Utc>)> = sqlx::query_as(
-        r#"
-        SELECT id, price, amount, side::text, share_type::text, created_at
-        FROM trades
-        WHERE market_id = $1 AND outcome_id = $2
-        ORDER BY created_at DESC
-        LIMIT $3
-        "#,
-    )
-    .bind(market_id)
-    .bind(query.outcome_id)
-    .bind(limit)
-    .fetch_all(&state.db.pool)
-    .await
-    .map_err(|e| {
-        tracing::error!("Failed to fetch trades: {}", e);
+    let bad_request = |error: &str, code: &str| {
         (
-            StatusCode::INTERNAL_SERVER_ERROR,
+            StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
-                error: "Failed to fetch trades".to_string(),
-                code: "TRADES_FETCH_FAILED".to_string(),
+                error: error.to_string(),
+                code: code.to_string(),
             }),
         )
-    })?;
+    };
+    let limit = query.limit.unwrap_or(50).clamp(1, MAX_TRADES_LIMIT) as usize;
+    let side = match query.side.as_deref().map(str::to_lowercase) {
+        Some(side) if side != "buy" && side != "sell" => {
+            return Err(bad_request("side must be buy or sell", "INVALID_SIDE"));
+        }
+        side => side,
+    };
+    let cursor = match (query.before, query.after) {
+        (Some(_), Some(_)) => return Err(bad_request("Use either before or after", "INVALID_CURSOR")),
+        (Some(before), None) => TradeCursor::Before(before.to_string()),
+        (None, Some(after)) => TradeCursor::After(after.to_string()),
+        (None, None) => TradeCursor::Latest,
+    };
 
-    let trades: Vec<TradeInfo> = rows
-        .into_iter()
-        .map(|(id, price, amount, side, share_type, created_at)| TradeInfo {
-            id,
-            price,
-            amount,
-            side,
-            share_type: share_type.parse().unwrap_or(ShareType::Yes),
-            timestamp: created_at.timestamp_millis(),
-        })
-        .collect();
+    // Both books of the outcome
+    let symbols = [ShareType::Yes, ShareType::No]
+        .map(|share_type| format!("{}:{}:{}", market_id, query.outcome_id, share_type));
+    let page = state.matching_engine.history().trade_page(&symbols, &cursor, side.as_deref(), limit);
+    let (trades, has_more) = match page {
+        Some(page) => (page.trades.iter().filter_map(trade_info).collect(), page.has_more),
+        None => fetch_trade_page(&state.db.pool, market_id, query.outcome_id, &query, side.as_deref(), limit)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to fetch trades: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: "Failed to fetch trades".to_string(),
+                        code: "TRADES_FETCH_FAILED".to_string(),
+                    }),
+                )
+            })?,
+    };
 
     Ok(Json(TradesResponse {
         market_id,
         outcome_id: query.outcome_id,
         trades,
+        has_more,
     }))
 }
 
+/// Largest page of trades
+const MAX_TRADES_LIMIT: i64 = 1000;
+
+/// A trade held by the engine's trade history
+fn trade_info(trade: &TradeRecord) -> Option<TradeInfo> {
+    Some(TradeInfo {
+        id: trade.trade_id.parse().ok()?,
+        exec_id: (trade.exec_id > 0).then_some(trade.exec_id as i64),
+        price: trade.price.parse().ok()?,
+        amount: trade.amount.parse().ok()?,
+        side: trade.side.to_lowercase(),
+        share_type: trade.share_type.parse().unwrap_or(ShareType::Yes),
+        aggressor: (!trade.is_block_trade).then(|| trade.side.to_lowercase()),
+        forced: trade.forced.map(|forced| forced.to_string()),
+        timestamp: trade.timestamp,
+    })
+}
+
+/// A trade as read from the database
+#[derive(sqlx::FromRow)]
+struct TradeRow {
+    id: Uuid,
+    exec_id: Option<i64>,
+    price: Decimal,
+    amount: Decimal,
+    side: String,
+    share_type: String,
+    is_block_trade: bool,
+    forced: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+/// Read a page of trades from the database, in the engine history's order
+async fn fetch_trade_page(
+    pool: &sqlx::PgPool,
+    market_id: Uuid,
+    outcome_id: Uuid,
+    query: &TradesQuery,
+    side: Option<&str>,
+    limit: usize,
+) -> Result<(Vec<TradeInfo>, bool), sqlx::Error> {
+    // `after` pages are the trades just newer than the cursor: read upwards, then flip
+    let newer = query.after.is_some();
+    let order = if newer { "ASC" } else { "DESC" };
+    let sql = format!(
+        r#"
+        SELECT id, exec_id, price, amount, side::text AS side, share_type::text AS share_type,
+               is_block_trade, forced, created_at
+        FROM trades
+        WHERE market_id = $1 AND outcome_id = $2
+          AND ($3::text IS NULL OR side::text = $3)
+          AND ($4::uuid IS NULL OR (created_at, COALESCE(exec_id, 0), id)
+              < (SELECT created_at, COALESCE(exec_id, 0), id FROM trades WHERE id = $4))
+          AND ($5::uuid IS NULL OR (created_at, COALESCE(exec_id, 0), id)
+              > (SELECT created_at, COALESCE(exec_id, 0), id FROM trades WHERE id = $5))
+        ORDER BY created_at {order}, COALESCE(exec_id, 0) {order}, id {order}
+        LIMIT $6
+        "#,
+        order = order
+    );
+    let rows: Vec<TradeRow> = sqlx::query_as(&sql)
+        .bind(market_id)
+        .bind(outcome_id)
+        .bind(side)
+        .bind(query.before)
+        .bind(query.after)
+        .bind(limit as i64 + 1)
+        .fetch_all(pool)
+        .await?;
+
+    let has_more = rows.len() > limit;
+    let mut trades: Vec<TradeInfo> = rows
+        .into_iter()
+        .take(limit)
+        .map(|row| TradeInfo {
+            id: row.id,
+            exec_id: row.exec_id,
+            price: row.price,
+            amount: row.amount,
+            aggressor: (!row.is_block_trade).then(|| row.side.clone()),
+            side: row.side,
+            share_type: row.share_type.parse().unwrap_or(ShareType::Yes),
+            forced: row.forced,
+            timestamp: row.created_at.timestamp_millis(),
+        })
+        .collect();
+    if newer {
+        trades.reverse();
+    }
+    Ok((trades, has_more))
+}
+
 /// Get ticker/price info for a market
 /// GET /markets/:market_id/ticker
 pub async fn get_ticker(
@@ -440,18 +614,34 @@ pub async fn get_ticker(
     .await
     .unwrap_or_default();
 
+    let levels = state.live_config.current().ticker_book_levels;
+    let book = |symbol: &str| {
+        state
+            .matching_engine
+            .get_orderbook(symbol, levels)
+            .ok()
+            .map(|snapshot| book_ticker(&snapshot.bids, &snapshot.asks, levels))
+    };
     let outcomes: Vec<OutcomeTicker> = outcomes_data
         .into_iter()
         .map(|(outcome_id, name, probability)| {
             // In prediction markets, Yes price = probability, No price = 1 - probability
             let yes_price = probability;
             let no_price = Decimal::ONE - probability;
+            let yes_symbol = format!("{}:{}:yes", market_id, outcome_id);
+            let no_symbol = format!("{}:{}:no", market_id, outcome_id);
             OutcomeTicker {
                 outcome_id,
                 name,
                 yes_price,
                 no_price,
                 probability,
+                yes_mark_price: state.matching_engine.mark_price(&yes_symbol),
+                no_mark_price: state.matching_engine.mark_price(&no_symbol),
+                yes_24h: state.ticker.ticker(&yes_symbol),
+                no_24h: state.ticker.ticker(&no_symbol),
+                yes_book: book(&yes_symbol),
+                no_book: book(&no_symbol),
             }
         })
         .collect();
@@ -466,6 +656,8 @@ pub async fn get_ticker(
 
 /// Get price for a specific outcome
 /// GET /markets/:market_id/price
+///
+/// Deprecated in favour of the ticker (see [`crate::api::deprecation`]).
 pub async fn get_price(
     State(state): State<Arc<AppState>>,
     Path(market_id): Path<Uuid>,
@@ -474,6 +666,441 @@ pub async fn get_price(
     get_ticker(State(state), Path(market_id)).await
 }
 
+/// Signed market data key discovery response
+#[derive(Debug, Serialize)]
+pub struct FeedKeysResponse {
+    pub signing_enabled: bool,
+    pub algorithm: &'static str,
+    pub rotation_secs: u64,
+    /// Previous, current and next verification keys
+    pub keys: Vec<FeedKeyInfo>,
+}
+
+/// Get the verification keys for signed WebSocket market data
+/// GET /market-data/keys
+pub async fn get_feed_keys(State(state): State<Arc<AppState>>) -> Json<FeedKeysResponse> {
+    let response = match &state.feed_signer {
+        Some(signer) => FeedKeysResponse {
+            signing_enabled: true,
+            algorithm: FEED_SIGNATURE_ALG,
+            rotation_secs: signer.rotation_secs(),
+            keys: signer.public_keys(),
+        },
+        None => FeedKeysResponse {
+            signing_enabled: false,
+            algorithm: FEED_SIGNATURE_ALG,
+            rotation_secs: 0,
+            keys: Vec::new(),
+        },
+    };
+    Json(response)
+}
+
+/// External index prices response
+#[derive(Debug, Serialize)]
+pub struct IndexPricesResponse {
+    pub indices: Vec<IndexPrice>,
+}
+
+/// Fresh external index prices of all instruments
+/// GET /market-data/index
+pub async fn get_index_prices(State(state): State<Arc<AppState>>) -> Json<IndexPricesResponse> {
+    Json(IndexPricesResponse {
+        indices: state.index_aggregator.indices(),
+    })
+}
+
+/// Trading pair symbols and the frontend aliases accepted for them
+#[derive(Debug, Serialize)]
+pub struct SymbolsResponse {
+    pub symbols: Vec<SymbolMapping>,
+}
+
+/// Canonical-to-alias symbol mappings
+/// GET /market-data/symbols
+pub async fn get_symbols(State(state): State<Arc<AppState>>) -> Json<SymbolsResponse> {
+    Json(SymbolsResponse {
+        symbols: state.symbols.mappings(),
+    })
+}
+
+/// External index price of one instrument
+/// GET /market-data/index/:instrument
+pub async fn get_index_price(
+    State(state): State<Arc<AppState>>,
+    Path(instrument): Path<String>,
+) -> Result<Json<IndexPrice>, (StatusCode, Json<ErrorResponse>)> {
+    state.index_aggregator.index(&instrument).map(Json).ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("No fresh index price for {}", instrument),
+                code: "INDEX_UNAVAILABLE".to_string(),
+            }),
+        )
+    })
+}
+
+/// Query parameters for candles
+#[derive(Debug, Deserialize)]
+pub struct KlinesQuery {
+    /// 1m, 5m, 15m, 1h, 4h, 1d, 1w or 1M
+    pub period: String,
+    /// Start time (Unix seconds)
+    pub from: Option<i64>,
+    /// End time (Unix seconds, exclusive; default now)
+    pub to: Option<i64>,
+    /// Most recent candles returned (default 300, max 1500)
+    pub limit: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct KlinesResponse {
+    pub symbol: String,
+    pub period: String,
+    /// Buckets without trades are returned as flat candles
+    pub gap_fill: bool,
+    /// Newest first
+    pub klines: Vec<Kline>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetGapFillRequest {
+    pub gap_fill: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GapFillResponse {
+    pub symbol: String,
+    pub gap_fill: bool,
+}
+
+fn kline_db_error(e: sqlx::Error) -> (StatusCode, Json<ErrorResponse>) {
+    tracing::error!("K-line query failed: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse {
+            error: "Database error".to_string(),
+            code: "DB_ERROR".to_string(),
+        }),
+    )
+}
+
+fn resolve_kline_symbol(state: &AppState, symbol: &str) -> Result<String, (StatusCode, Json<ErrorResponse>)> {
+    state.symbols.resolve(symbol).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: e.to_string(),
+                code: "INVALID_SYMBOL".to_string(),
+            }),
+        )
+    })
+}
+
+/// Candles of a symbol, with idle buckets filled unless turned off for it
+/// GET /market-data/klines/:symbol
+pub async fn get_klines(
+    State(state): State<Arc<AppState>>,
+    Path(symbol): Path<String>,
+    Query(query): Query<KlinesQuery>,
+) -> Result<Json<KlinesResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let symbol = resolve_kline_symbol(&state, &symbol)?;
+    let period = KlinePeriod::parse(&query.period).ok_or_else(|| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: format!("Invalid period: {}", query.period),
+                code: "INVALID_PERIOD".to_string(),
+            }),
+        )
+    })?;
+    let limit = query.limit.unwrap_or(300).clamp(1, 1500);
+    let end = query.to.and_then(|to| DateTime::from_timestamp(to, 0)).unwrap_or_else(Utc::now);
+    let start = query
+        .from
+        .and_then(|from| DateTime::from_timestamp(from, 0))
+        .unwrap_or(DateTime::UNIX_EPOCH);
+
+    let timescale = TimescaleOps::new(state.db.pool.clone());
+    let gap_fill = timescale
+        .gap_fill_setting(&symbol)
+        .await
+        .map_err(kline_db_error)?
+        .unwrap_or(state.config.kline_gap_fill_default);
+    let klines = if gap_fill {
+        timescale.get_klines_filled(&symbol, period, start, end, limit).await
+    } else {
+        timescale.get_klines(&symbol, period, start, end, limit).await
+    }
+    .map_err(kline_db_error)?;
+
+    Ok(Json(KlinesResponse {
+        symbol,
+        period: period.to_str().to_string(),
+        gap_fill,
+        klines,
+    }))
+}
+
+/// Turn candle gap filling of a symbol on or off - Admin only
+/// PUT /admin/market-data/klines/:symbol/gap-fill
+pub async fn set_kline_gap_fill(
+    State(state): State<Arc<AppState>>,
+    Path(symbol): Path<String>,
+    Json(req): Json<SetGapFillRequest>,
+) -> Result<Json<GapFillResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let symbol = resolve_kline_symbol(&state, &symbol)?;
+    TimescaleOps::new(state.db.pool.clone())
+        .set_gap_fill(&symbol, req.gap_fill)
+        .await
+        .map_err(kline_db_error)?;
+
+    tracing::info!("K-line gap filling for {} turned {}", symbol, if req.gap_fill { "on" } else { "off" });
+    Ok(Json(GapFillResponse {
+        symbol,
+        gap_fill: req.gap_fill,
+    }))
+}
+
+/// Query parameters for market statistics history
+#[derive(Debug, Deserialize)]
+pub struct StatsHistoryQuery {
+    /// 1m, 5m, 15m, 1h, 4h, 1d, 1w or 1M (default 5m)
+    pub period: Option<String>,
+    /// Start time (Unix seconds)
+    pub from: Option<i64>,
+    /// End time (Unix seconds, exclusive; default now)
+    pub to: Option<i64>,
+    /// Most recent buckets returned (default 288, max 1500)
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatsHistoryResponse {
+    pub symbol: String,
+    pub period: String,
+    /// Newest first
+    pub stats: Vec<MarketStats>,
+}
+
+/// Open interest, long/short ratio and 24h volume of a symbol over time
+/// GET /markets/:symbol/stats/history
+///
+/// `symbol` is `{market_id}:{outcome_id}:{share_type}`; each bucket reports
+/// the last snapshot taken in it.
+pub async fn get_stats_history(
+    State(state): State<Arc<AppState>>,
+    Path(symbol): Path<String>,
+    Query(query): Query<StatsHistoryQuery>,
+) -> Result<Json<StatsHistoryResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let stats_error = |e: StatsError| match e {
+        StatsError::InvalidSymbol(_) => (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: e.to_string(),
+                code: "INVALID_SYMBOL".to_string(),
+            }),
+        ),
+        StatsError::DatabaseError(e) => kline_db_error(e),
+    };
+    let symbol = stats::parse_symbol(&symbol).map_err(stats_error)?;
+    let period_str = query.period.as_deref().unwrap_or("5m");
+    let period = KlinePeriod::parse(period_str).ok_or_else(|| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: format!("Invalid period: {}", period_str),
+                code: "INVALID_PERIOD".to_string(),
+            }),
+        )
+    })?;
+    let limit = query.limit.unwrap_or(288).clamp(1, 1500);
+    let end = query.to.and_then(|to| DateTime::from_timestamp(to, 0)).unwrap_or_else(Utc::now);
+    let start = query
+        .from
+        .and_then(|from| DateTime::from_timestamp(from, 0))
+        .unwrap_or(DateTime::UNIX_EPOCH);
+
+    let stats = StatsService::history(&state.db.pool, &symbol, period, start, end, limit)
+        .await
+        .map_err(stats_error)?;
+    Ok(Json(StatsHistoryResponse {
+        symbol,
+        period: period.to_str().to_string(),
+        stats,
+    }))
+}
+
+/// Query parameters for settlement price history
+#[derive(Debug, Deserialize)]
+pub struct SettlementPricesQuery {
+    /// First day (YYYY-MM-DD, default 30 days before `to`)
+    pub from: Option<NaiveDate>,
+    /// Last day (YYYY-MM-DD, inclusive; default yesterday)
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SettlementPricesResponse {
+    pub symbol: String,
+    /// Newest first
+    pub prices: Vec<SettlementPrice>,
+}
+
+/// Official end-of-day settlement prices of a symbol
+/// GET /markets/:symbol/settlement-prices
+///
+/// `symbol` is `{market_id}:{outcome_id}:{share_type}`.
+pub async fn get_settlement_prices(
+    State(state): State<Arc<AppState>>,
+    Path(symbol): Path<String>,
+    Query(query): Query<SettlementPricesQuery>,
+) -> Result<Json<SettlementPricesResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let bad_request = |error: String, code: &str| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error,
+                code: code.to_string(),
+            }),
+        )
+    };
+    let symbol = stats::parse_symbol(&symbol).map_err(|e| bad_request(e.to_string(), "INVALID_SYMBOL"))?;
+    let to = query.to.unwrap_or_else(|| Utc::now().date_naive() - chrono::Duration::days(1));
+    let from = query.from.unwrap_or(to - chrono::Duration::days(30));
+
+    let prices = SettlementPriceService::history(&state.db.pool, &symbol, from, to)
+        .await
+        .map_err(|e| match e {
+            SettlementPriceError::DatabaseError(e) => {
+                tracing::error!("Settlement price query failed: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: "Failed to fetch settlement prices".to_string(),
+                        code: "SETTLEMENT_PRICES_FETCH_FAILED".to_string(),
+                    }),
+                )
+            }
+            SettlementPriceError::InvalidRange(_) => bad_request(e.to_string(), "INVALID_RANGE"),
+            SettlementPriceError::InvalidSymbol(_) => bad_request(e.to_string(), "INVALID_SYMBOL"),
+        })?;
+    Ok(Json(SettlementPricesResponse { symbol, prices }))
+}
+
+/// Trading configuration of a market (tick / lot sizes, order size limits,
+/// fees, leverage and margin tiers)
+/// GET /markets/:market_id/config
+pub async fn get_market_config(
+    State(state): State<Arc<AppState>>,
+    Path(market_id): Path<Uuid>,
+) -> Result<Json<MarketConfig>, (StatusCode, Json<ErrorResponse>)> {
+    let config = state
+        .market_service
+        .get_market_config(&state.db.pool, market_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load market config: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Database error".to_string(),
+                    code: "DB_ERROR".to_string(),
+                }),
+            )
+        })?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: "Market not found".to_string(),
+                    code: "MARKET_NOT_FOUND".to_string(),
+                }),
+            )
+        })?;
+    Ok(Json(config.as_ref().clone()))
+}
+
+#[derive(Debug, Serialize)]
+pub struct RiskLimitsResponse {
+    pub market_id: Uuid,
+    pub tiers: Vec<RiskLimit>,
+}
+
+/// Risk limits of a market: maintenance margin rate and leverage cap by
+/// position notional
+/// GET /markets/:market_id/risk-limits
+pub async fn get_risk_limits(
+    State(state): State<Arc<AppState>>,
+    Path(market_id): Path<Uuid>,
+) -> Result<Json<RiskLimitsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let Json(config) = get_market_config(State(state.clone()), Path(market_id)).await?;
+    let tiers = config.risk_limits(state.live_config.current().maintenance_margin_rate());
+    Ok(Json(RiskLimitsResponse { market_id, tiers }))
+}
+
+/// Every parameter that affects a position's outcome on a symbol: fee
+/// schedule, funding parameters, maintenance tiers, liquidation fee and its
+/// split, deleveraging policy and price limits
+/// GET /markets/:symbol/risk-disclosure
+///
+/// `symbol` is `{market_id}:{outcome_id}:{share_type}`. Built from the live
+/// settings on each request.
+pub async fn get_risk_disclosure(
+    State(state): State<Arc<AppState>>,
+    Path(symbol): Path<String>,
+) -> Result<Json<RiskDisclosure>, (StatusCode, Json<ErrorResponse>)> {
+    let invalid_symbol = |e: StatsError| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: e.to_string(),
+                code: "INVALID_SYMBOL".to_string(),
+            }),
+        )
+    };
+    let symbol = stats::parse_symbol(&symbol).map_err(invalid_symbol)?;
+    // Funding is configured per outcome (`{market_id}:{outcome_id}`)
+    let outcome = symbol.rsplit_once(':').map_or(symbol.as_str(), |(outcome, _)| outcome);
+    let market_id: Uuid = outcome
+        .split(':')
+        .next()
+        .and_then(|id| id.parse().ok())
+        .ok_or_else(|| invalid_symbol(StatsError::InvalidSymbol(symbol.clone())))?;
+
+    let Json(market) = get_market_config(State(state.clone()), Path(market_id)).await?;
+    let funding = FundingService::params(&state.db.pool, outcome)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load funding params: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Database error".to_string(),
+                    code: "DB_ERROR".to_string(),
+                }),
+            )
+        })?;
+
+    let config = state.live_config.current();
+    let disclosure = risk_disclosure::build(
+        DisclosureInputs {
+            symbol: &symbol,
+            market: &market,
+            funding,
+            mark_price: state.mark_price_service.config(&symbol),
+            vip_tiers: state.fees.tiers(),
+            vip_window_days: state.config.fee_volume_window_days,
+            maintenance_margin_rate: config.maintenance_margin_rate(),
+            liquidation_target_margin_ratio: config.liquidation_target_margin_ratio(),
+            liquidation_penalty_ratio: config.liquidation_penalty_ratio(),
+        },
+        Utc::now(),
+    );
+    Ok(Json(disclosure))
+}
+
 // ============================================================================
 // Admin Handlers for Market Management
 // ============================================================================
@@ -497,6 +1124,13 @@ pub struct CreateMarketRequest {
     pub yes_token_id: String,
     /// No outcome token ID
     pub no_token_id: String,
+    /// Trading sessions (omit to trade 24/7)
+    pub trading_calendar: Option<TradingCalendar>,
+    /// Leverage cap, fee tier, tick / lot sizes, order size limits and margin
+    /// tiers (omit for the defaults)
+    pub rules: Option<MarketLimits>,
+    /// Asset prices and PnL are quoted in (omit for the collateral token)
+    pub quote_asset: Option<String>,
 }
 
 /// Create market response
@@ -563,6 +1197,7 @@ pub async fn get_market(
                 total_volume: cached.total_volume,
                 liquidity: Decimal::ZERO,
                 created_at: cached.created_at,
+                session: state.matching_engine.session_state(cached.id),
             }));
         }
     }
@@ -678,6 +1313,7 @@ pub async fn get_market(
         total_volume,
         liquidity,
         created_at: created_at.timestamp_millis(),
+        session: state.matching_engine.session_state(id),
     }))
 }
 
@@ -726,6 +1362,59 @@ pub async fn create_market(
         ));
     }
 
+    if let Some(calendar) = &req.trading_calendar {
+        calendar.validate().map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                    code: "INVALID_TRADING_CALENDAR".to_string(),
+                }),
+            )
+        })?;
+    }
+
+    let rules = match &req.rules {
+        Some(limits) => MarketRulesService::check_limits(&state.db.pool, limits).await.map_err(|e| match e {
+            MarketRulesError::DatabaseError(db) => {
+                tracing::error!("Failed to check market rules: {}", db);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: "Database error".to_string(),
+                        code: "DB_ERROR".to_string(),
+                    }),
+                )
+            }
+            e => (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                    code: "INVALID_MARKET_RULES".to_string(),
+                }),
+            ),
+        })?,
+        None => MarketRules::default(),
+    };
+
+    // Realized PnL must be convertible into the collateral token at settlement
+    let quote_asset = req
+        .quote_asset
+        .as_deref()
+        .map(|asset| asset.trim().to_uppercase())
+        .filter(|asset| !asset.is_empty() && asset != state.token_prices.collateral());
+    if let Some(asset) = &quote_asset {
+        if !state.token_prices.has_path(asset) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: format!("No conversion path from {} to {}", asset, state.token_prices.collateral()),
+                    code: "NO_CONVERSION_PATH".to_string(),
+                }),
+            ));
+        }
+    }
+
     let market_id = Uuid::new_v4();
     let yes_outcome_id = Uuid::new_v4();
     let no_outcome_id = Uuid::new_v4();
@@ -751,8 +1440,10 @@ pub async fn create_market(
     // Create market
     sqlx::query(
         r#"
-        INSERT INTO markets (id, condition_id, question, description, category, resolution_source, end_time)
-        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        INSERT INTO markets (id, condition_id, question, description, category, resolution_source, end_time, trading_calendar,
+                             max_leverage, fee_tier, tick_size, lot_size, min_order_size, max_order_size, margin_tiers,
+                             quote_asset)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
         "#,
     )
     .bind(market_id)
@@ -762,6 +1453,15 @@ pub async fn create_market(
     .bind(&category)
     .bind(&resolution_source)
     .bind(end_time)
+    .bind(req.trading_calendar.clone().map(sqlx::types::Json))
+    .bind(rules.max_leverage as i32)
+    .bind(&rules.fee_tier)
+    .bind(rules.tick_size)
+    .bind(rules.lot_size)
+    .bind(req.rules.as_ref().and_then(|l| l.min_order_size))
+    .bind(req.rules.as_ref().and_then(|l| l.max_order_size))
+    .bind(sqlx::types::Json(req.rules.as_ref().map(|l| l.margin_tiers.clone()).unwrap_or_default()))
+    .bind(&quote_asset)
     .execute(&mut *tx)
     .await
     .map_err(|e| {
@@ -835,6 +1535,9 @@ pub async fn create_market(
         )
     })?;
 
+    state.matching_engine.set_calendar(market_id, req.trading_calendar);
+    state.matching_engine.set_market_rules(market_id, rules);
+
     tracing::info!(
         "Created market {} with question: {}",
         market_id,
@@ -849,6 +1552,146 @@ pub async fn create_market(
     }))
 }
 
+/// Set trading calendar request
+#[derive(Debug, Deserialize)]
+pub struct SetTradingCalendarRequest {
+    /// Trading sessions (null = trade 24/7)
+    pub trading_calendar: Option<TradingCalendar>,
+}
+
+/// Trading calendar response
+#[derive(Debug, Serialize)]
+pub struct TradingCalendarResponse {
+    pub market_id: Uuid,
+    pub trading_calendar: Option<TradingCalendar>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session: Option<SessionState>,
+}
+
+/// Set or clear a market's trading calendar - Admin only
+/// PUT /admin/markets/:market_id/calendar
+pub async fn set_trading_calendar(
+    State(state): State<Arc<AppState>>,
+    Path(market_id): Path<Uuid>,
+    Json(req): Json<SetTradingCalendarRequest>,
+) -> Result<Json<TradingCalendarResponse>, (StatusCode, Json<ErrorResponse>)> {
+    if let Some(calendar) = &req.trading_calendar {
+        calendar.validate().map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                    code: "INVALID_TRADING_CALENDAR".to_string(),
+                }),
+            )
+        })?;
+    }
+
+    let result = sqlx::query("UPDATE markets SET trading_calendar = $1 WHERE id = $2")
+        .bind(req.trading_calendar.clone().map(sqlx::types::Json))
+        .bind(market_id)
+        .execute(&state.db.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to update trading calendar: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Database error".to_string(),
+                    code: "DB_ERROR".to_string(),
+                }),
+            )
+        })?;
+
+    if result.rows_affected() == 0 {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Market not found".to_string(),
+                code: "MARKET_NOT_FOUND".to_string(),
+            }),
+        ));
+    }
+
+    state.matching_engine.set_calendar(market_id, req.trading_calendar.clone());
+    tracing::info!("Updated trading calendar for market {}", market_id);
+
+    Ok(Json(TradingCalendarResponse {
+        market_id,
+        trading_calendar: req.trading_calendar,
+        session: state.matching_engine.session_state(market_id),
+    }))
+}
+
+/// Set mark price config request
+#[derive(Debug, Deserialize)]
+pub struct SetMarkPriceConfigRequest {
+    /// Settings for the symbol (null = use the defaults)
+    pub config: Option<MarkPriceConfig>,
+}
+
+/// Mark price config response
+#[derive(Debug, Serialize)]
+pub struct MarkPriceConfigResponse {
+    pub symbol: String,
+    pub config: MarkPriceConfig,
+    pub mark_price: Option<Decimal>,
+    pub index_sources: Vec<&'static str>,
+}
+
+fn mark_price_config_response(state: &AppState, symbol: String, config: MarkPriceConfig) -> MarkPriceConfigResponse {
+    MarkPriceConfigResponse {
+        mark_price: state.matching_engine.mark_price(&symbol),
+        index_sources: state.mark_price_service.source_names(),
+        symbol,
+        config,
+    }
+}
+
+/// Get a symbol's mark price settings - Admin only
+/// GET /admin/mark-price/:symbol
+pub async fn get_mark_price_config(
+    State(state): State<Arc<AppState>>,
+    Path(symbol): Path<String>,
+) -> Json<MarkPriceConfigResponse> {
+    let config = state.mark_price_service.config(&symbol);
+    Json(mark_price_config_response(&state, symbol, config))
+}
+
+/// Set or reset a symbol's mark price settings - Admin only
+/// PUT /admin/mark-price/:symbol
+pub async fn set_mark_price_config(
+    State(state): State<Arc<AppState>>,
+    Path(symbol): Path<String>,
+    Json(req): Json<SetMarkPriceConfigRequest>,
+) -> Result<Json<MarkPriceConfigResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let config = state
+        .mark_price_service
+        .set_config(&state.db.pool, &symbol, req.config)
+        .await
+        .map_err(|e| {
+            let (status, code) = match &e {
+                MarkPriceError::InvalidMethod(_) | MarkPriceError::UnknownSource(_) => {
+                    (StatusCode::BAD_REQUEST, "INVALID_MARK_PRICE_CONFIG")
+                }
+                MarkPriceError::DatabaseError(_) => {
+                    tracing::error!("Failed to save mark price config: {}", e);
+                    (StatusCode::INTERNAL_SERVER_ERROR, "DB_ERROR")
+                }
+            };
+            (
+                status,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                    code: code.to_string(),
+                }),
+            )
+        })?;
+
+    tracing::info!("Updated mark price config for {}", symbol);
+    Ok(Json(mark_price_config_response(&state, symbol, config)))
+}
+
 /// Close a market (pause trading) - Admin only
 /// POST /admin/markets/:market_id/close
 pub async fn close_market(
@@ -0,0 +1,204 @@
+//! Market Rules API Handlers
+//!
+//! Admin endpoints for managing markets at runtime: pause / resume / delist,
+//! leverage caps, tick / lot sizes and fee tiers. Changes are persisted and
+//! applied to the matching engine immediately.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::auth::middleware::AuthUser;
+use crate::services::market::rules::{FeeTier, MarketLimits, MarketRules, MarketRulesError, MarketRulesService};
+use crate::AppState;
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MarketRulesResponse {
+    pub market_id: Uuid,
+    #[serde(flatten)]
+    pub rules: MarketRules,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DelistMarketResponse {
+    pub market_id: Uuid,
+    #[serde(flatten)]
+    pub rules: MarketRules,
+    pub cancelled_orders: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeeTiersResponse {
+    pub tiers: Vec<FeeTier>,
+}
+
+fn rules_error(e: MarketRulesError) -> (StatusCode, Json<ErrorResponse>) {
+    let (status, code) = match &e {
+        MarketRulesError::MarketNotFound(_) => (StatusCode::NOT_FOUND, "MARKET_NOT_FOUND"),
+        MarketRulesError::UnknownFeeTier(_) => (StatusCode::BAD_REQUEST, "UNKNOWN_FEE_TIER"),
+        MarketRulesError::InvalidRules(_) => (StatusCode::BAD_REQUEST, "INVALID_MARKET_RULES"),
+        MarketRulesError::DatabaseError(db) => {
+            tracing::error!("Market rules database error: {}", db);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Database error".to_string(),
+                    code: "DB_ERROR".to_string(),
+                }),
+            );
+        }
+    };
+    (
+        status,
+        Json(ErrorResponse {
+            error: e.to_string(),
+            code: code.to_string(),
+        }),
+    )
+}
+
+// ============================================================================
+// Admin Handlers
+// ============================================================================
+
+/// Get a market's trading rules - Admin only
+/// GET /admin/markets/:market_id/rules
+pub async fn get_rules(
+    State(state): State<Arc<AppState>>,
+    Path(market_id): Path<Uuid>,
+) -> Result<Json<MarketRulesResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let rules = MarketRulesService::get(&state.db.pool, market_id).await.map_err(rules_error)?;
+    Ok(Json(MarketRulesResponse { market_id, rules }))
+}
+
+/// Replace a market's leverage cap, fee tier, tick / lot sizes, order size
+/// limits and margin tiers - Admin only
+/// PUT /admin/markets/:market_id/rules
+pub async fn set_rules(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(market_id): Path<Uuid>,
+    Json(req): Json<MarketLimits>,
+) -> Result<Json<MarketRulesResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let rules = MarketRulesService::set_limits(&state.db.pool, &state.matching_engine, market_id, &req)
+        .await
+        .map_err(rules_error)?;
+    state.market_service.invalidate(market_id);
+
+    tracing::info!(
+        "Market {} rules changed by {} (leverage {}x, fee tier {:?}, tick {:?}, lot {:?})",
+        market_id,
+        auth_user.address,
+        rules.max_leverage,
+        rules.fee_tier,
+        rules.tick_size,
+        rules.lot_size
+    );
+    Ok(Json(MarketRulesResponse { market_id, rules }))
+}
+
+/// Stop accepting new orders on a market - Admin only
+/// POST /admin/markets/:market_id/pause
+pub async fn pause_market(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(market_id): Path<Uuid>,
+) -> Result<Json<MarketRulesResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let rules = MarketRulesService::set_paused(&state.db.pool, &state.matching_engine, market_id, true)
+        .await
+        .map_err(rules_error)?;
+    tracing::info!("Market {} paused by {}", market_id, auth_user.address);
+    Ok(Json(MarketRulesResponse { market_id, rules }))
+}
+
+/// Resume a paused market - Admin only
+/// POST /admin/markets/:market_id/resume
+pub async fn resume_market(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(market_id): Path<Uuid>,
+) -> Result<Json<MarketRulesResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let rules = MarketRulesService::set_paused(&state.db.pool, &state.matching_engine, market_id, false)
+        .await
+        .map_err(rules_error)?;
+    tracing::info!("Market {} resumed by {}", market_id, auth_user.address);
+    Ok(Json(MarketRulesResponse { market_id, rules }))
+}
+
+/// Delist a market and cancel its resting orders - Admin only
+/// POST /admin/markets/:market_id/delist
+pub async fn delist_market(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(market_id): Path<Uuid>,
+) -> Result<Json<DelistMarketResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let (rules, cancelled_orders) = MarketRulesService::delist(
+        &state.db.pool,
+        &state.matching_engine,
+        market_id,
+        state.config.collateral_symbol(),
+    )
+    .await
+    .map_err(rules_error)?;
+
+    tracing::info!(
+        "Market {} delisted by {} ({} resting orders cancelled)",
+        market_id,
+        auth_user.address,
+        cancelled_orders
+    );
+    Ok(Json(DelistMarketResponse {
+        market_id,
+        rules,
+        cancelled_orders,
+    }))
+}
+
+/// List fee tiers - Admin only
+/// GET /admin/fee-tiers
+pub async fn list_fee_tiers(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<FeeTiersResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let tiers = MarketRulesService::list_fee_tiers(&state.db.pool).await.map_err(rules_error)?;
+    Ok(Json(FeeTiersResponse { tiers }))
+}
+
+/// Create or update a fee tier - Admin only
+/// PUT /admin/fee-tiers/:name
+pub async fn upsert_fee_tier(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(name): Path<String>,
+    Json(mut tier): Json<FeeTier>,
+) -> Result<Json<FeeTier>, (StatusCode, Json<ErrorResponse>)> {
+    tier.name = name;
+    let tier = MarketRulesService::upsert_fee_tier(&state.db.pool, &state.matching_engine, &tier)
+        .await
+        .map_err(rules_error)?;
+    state.market_service.invalidate_all();
+
+    tracing::info!(
+        "Fee tier {} set by {} (base {} bps, max {} bps, maker discount {}%)",
+        tier.name,
+        auth_user.address,
+        tier.base_fee_bps,
+        tier.max_fee_bps,
+        tier.maker_discount_pct
+    );
+    Ok(Json(tier))
+}
@@ -0,0 +1,269 @@
+//! Market Maker API Handlers
+//!
+//! Internal endpoints reporting the auto-MM's inventory per symbol, with a
+//! suggested hedge against the external index, managing its protection
+//! against one-sided taker flow and its two-sided quoting, including the
+//! external books it mirrors.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::auth::middleware::AuthUser;
+use crate::services::mm_imbalance::{
+    ImbalanceDecision, ImbalanceError, ImbalanceParams, MmImbalanceService, Protection, SymbolParams,
+};
+use crate::services::mm_inventory::{InventoryError, InventoryReport};
+use crate::services::mm_mirror::MirrorStatus;
+use crate::services::mm_quoter::{MmQuoterService, QuoteParams, QuoteState, QuoterError, SymbolQuoteParams};
+use crate::services::stats;
+use crate::AppState;
+
+/// Default and longest fill ratio window
+const DEFAULT_WINDOW_HOURS: i64 = 24;
+const MAX_WINDOW_HOURS: i64 = 24 * 30;
+
+/// Default and largest page of imbalance decisions
+const DEFAULT_DECISIONS_LIMIT: i64 = 100;
+const MAX_DECISIONS_LIMIT: i64 = 1000;
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InventoryQuery {
+    /// Account to report on; defaults to the configured auto-MM account
+    pub address: Option<String>,
+    /// Fill ratio window in hours
+    pub window_hours: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImbalanceStatusResponse {
+    /// Whether `mm_imbalance_protection` is on
+    pub enabled: bool,
+    /// Parameters of symbols without a row
+    pub defaults: ImbalanceParams,
+    pub symbols: Vec<SymbolParams>,
+    /// Protections in force now
+    pub active: Vec<Protection>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuotingStatusResponse {
+    /// Whether `auto_mm_enabled` is on
+    pub enabled: bool,
+    /// Suggested parameters for a new symbol
+    pub defaults: QuoteParams,
+    pub symbols: Vec<SymbolQuoteParams>,
+    /// Quotes, inventory and halts of the symbols quoted since startup
+    pub states: Vec<QuoteState>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DecisionsQuery {
+    pub symbol: Option<String>,
+    pub limit: Option<i64>,
+}
+
+fn imbalance_error(e: ImbalanceError) -> (StatusCode, Json<ErrorResponse>) {
+    let (status, code) = match &e {
+        ImbalanceError::InvalidParams(_) => (StatusCode::BAD_REQUEST, "INVALID_PARAMS"),
+        ImbalanceError::DatabaseError(err) => {
+            tracing::error!("MM imbalance query failed: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, "DB_ERROR")
+        }
+    };
+    (
+        status,
+        Json(ErrorResponse {
+            error: e.to_string(),
+            code: code.to_string(),
+        }),
+    )
+}
+
+fn quoter_error(e: QuoterError) -> (StatusCode, Json<ErrorResponse>) {
+    let (status, code) = match &e {
+        QuoterError::InvalidParams(_) => (StatusCode::BAD_REQUEST, "INVALID_PARAMS"),
+        QuoterError::DatabaseError(err) => {
+            tracing::error!("MM quoting query failed: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, "DB_ERROR")
+        }
+    };
+    (
+        status,
+        Json(ErrorResponse {
+            error: e.to_string(),
+            code: code.to_string(),
+        }),
+    )
+}
+
+fn symbol_error(e: stats::StatsError) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ErrorResponse {
+            error: e.to_string(),
+            code: "INVALID_SYMBOL".to_string(),
+        }),
+    )
+}
+
+fn inventory_error(e: InventoryError) -> (StatusCode, Json<ErrorResponse>) {
+    let (status, code) = match &e {
+        InventoryError::NoAccount => (StatusCode::BAD_REQUEST, "MM_ACCOUNT_NOT_CONFIGURED"),
+        InventoryError::DatabaseError(err) => {
+            tracing::error!("MM inventory query failed: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, "DB_ERROR")
+        }
+    };
+    (
+        status,
+        Json(ErrorResponse {
+            error: e.to_string(),
+            code: code.to_string(),
+        }),
+    )
+}
+
+// ============================================================================
+// Admin Handlers
+// ============================================================================
+
+/// Auto-MM inventory per symbol - Admin only
+/// GET /internal/mm/inventory
+pub async fn get_inventory(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<InventoryQuery>,
+) -> Result<Json<InventoryReport>, (StatusCode, Json<ErrorResponse>)> {
+    let address = query
+        .address
+        .unwrap_or_else(|| state.live_config.current().auto_mm_test_account.clone());
+    let window_hours = query.window_hours.unwrap_or(DEFAULT_WINDOW_HOURS).clamp(1, MAX_WINDOW_HOURS);
+
+    state
+        .mm_inventory
+        .report(&state.db.pool, &state.matching_engine, &state.mark_price_service, &address, window_hours)
+        .await
+        .map(Json)
+        .map_err(inventory_error)
+}
+
+/// Imbalance protection parameters and active protections - Admin only
+/// GET /internal/mm/imbalance
+pub async fn get_imbalance(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ImbalanceStatusResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let symbols = MmImbalanceService::list_params(&state.db.pool).await.map_err(imbalance_error)?;
+    Ok(Json(ImbalanceStatusResponse {
+        enabled: state.live_config.current().mm_imbalance_protection,
+        defaults: ImbalanceParams::default(),
+        symbols,
+        active: state.mm_imbalance.active(Utc::now()),
+    }))
+}
+
+/// Set a symbol's imbalance protection parameters - Admin only
+/// PUT /internal/mm/imbalance/:symbol
+pub async fn set_imbalance_params(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(symbol): Path<String>,
+    Json(params): Json<ImbalanceParams>,
+) -> Result<Json<SymbolParams>, (StatusCode, Json<ErrorResponse>)> {
+    let symbol = stats::parse_symbol(&symbol).map_err(symbol_error)?;
+    let stored = state
+        .mm_imbalance
+        .set_params(&state.db.pool, &symbol, &params, &auth_user.address)
+        .await
+        .map_err(imbalance_error)?;
+
+    tracing::info!(
+        "MM imbalance parameters for {} changed by {} (enabled {}, threshold {}, window {}s, widen {} ticks, size x{})",
+        symbol,
+        auth_user.address,
+        params.enabled,
+        params.threshold,
+        params.window_secs,
+        params.widen_ticks,
+        params.size_factor
+    );
+    Ok(Json(stored))
+}
+
+/// Imbalance protections triggered, newest first - Admin only
+/// GET /internal/mm/imbalance/decisions
+pub async fn list_imbalance_decisions(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<DecisionsQuery>,
+) -> Result<Json<Vec<ImbalanceDecision>>, (StatusCode, Json<ErrorResponse>)> {
+    let symbol = query.symbol.as_deref().map(stats::parse_symbol).transpose().map_err(symbol_error)?;
+    let limit = query.limit.unwrap_or(DEFAULT_DECISIONS_LIMIT).clamp(1, MAX_DECISIONS_LIMIT);
+    MmImbalanceService::decisions(&state.db.pool, symbol.as_deref(), limit)
+        .await
+        .map(Json)
+        .map_err(imbalance_error)
+}
+
+/// Quoting parameters and state per symbol - Admin only
+/// GET /internal/mm/quoting
+pub async fn get_quoting(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<QuotingStatusResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let symbols = MmQuoterService::list_params(&state.db.pool).await.map_err(quoter_error)?;
+    Ok(Json(QuotingStatusResponse {
+        enabled: state.live_config.current().auto_mm_enabled,
+        defaults: QuoteParams::default(),
+        symbols,
+        states: state.mm_quoter.states(),
+    }))
+}
+
+/// Set a symbol's quoting parameters, lifting a loss-limit halt - Admin only
+/// PUT /internal/mm/quoting/:symbol
+pub async fn set_quoting_params(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(symbol): Path<String>,
+    Json(params): Json<QuoteParams>,
+) -> Result<Json<SymbolQuoteParams>, (StatusCode, Json<ErrorResponse>)> {
+    let symbol = stats::parse_symbol(&symbol).map_err(symbol_error)?;
+    let stored = state
+        .mm_quoter
+        .set_params(&state.db.pool, &symbol, &params, &auth_user.address)
+        .await
+        .map_err(quoter_error)?;
+
+    tracing::info!(
+        "MM quoting parameters for {} changed by {} (enabled {}, spread {}, {} levels of {}, max position {}, max loss {})",
+        symbol,
+        auth_user.address,
+        params.enabled,
+        params.spread,
+        params.levels,
+        params.level_size,
+        params.max_position,
+        params.max_loss
+    );
+    Ok(Json(stored))
+}
+
+/// Mirrored external books, their best prices and freshness - Admin only
+/// GET /internal/mm/mirror
+pub async fn get_mirror(State(state): State<Arc<AppState>>) -> Json<Vec<MirrorStatus>> {
+    let max_age = chrono::Duration::seconds(state.live_config.current().mm_mirror_stale_secs as i64);
+    Json(state.mm_mirror.status(Utc::now(), max_age))
+}
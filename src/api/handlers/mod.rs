@@ -1,11 +1,36 @@
 //! API Handlers for Prediction Market
 
 pub mod account;
+pub mod api_keys;
 pub mod auth;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+pub mod config;
 pub mod deposit;
+pub mod export;
+pub mod feature;
+pub mod fees;
+pub mod funding;
+pub mod jobs;
+pub mod margin_call;
 pub mod market;
+pub mod market_rules;
+pub mod mm;
 pub mod order;
+pub mod portfolio;
+pub mod referral_epochs;
+pub mod replication;
+pub mod rfq;
+pub mod session_keys;
+pub mod sub_account;
+pub mod surveillance;
+pub mod system;
+pub mod tape;
+pub mod tp_sl;
+pub mod vault;
 pub mod withdraw;
+pub mod withdrawal_fees;
+pub mod withdrawal_reviews;
 
 // TODO: Re-enable when needed
 // pub mod adl;
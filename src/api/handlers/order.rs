@@ -13,17 +13,27 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::api::error as api_error;
+use crate::api::middleware::idempotency::IdempotencyCommit;
 use crate::auth::eip712::{
-    verify_cancel_order_signature, verify_create_order_signature_with_debug,
-    CancelOrderMessage, CreateOrderMessage,
+    verify_trading_signature, AmendOrderMessage, BatchCancelMessage, CancelOrderMessage, CreateOrderMessage,
 };
 use crate::auth::middleware::AuthUser;
+use crate::auth::replay::{check_replay, ReplayError, SignedFields};
 use crate::models::market::ShareType;
 use crate::models::{
-    CreateOrderRequest, Order, OrderResponse, OrderSide, OrderStatus, OrderType,
+    CreateOrderRequest, DurabilityMode, Order, OrderResponse, OrderSide, OrderStatus, OrderType,
 };
+use crate::services::ledger::{BalanceChange, LedgerReason, LedgerService};
+use crate::services::market::rules::RuleViolation;
+use crate::services::mm_guard::{CrossPolicy, MmGuardError, MmQuoteGuard};
+use crate::services::nonce::NonceError;
+use crate::services::preferences::{reduces_holding, slippage_limit, OrderPreferences, PreferenceService};
+use crate::services::risk_profile::{self, RiskProfileError, RiskProfileService};
+use crate::services::session_keys::SessionScope;
+use crate::services::user_events::{self, OrderEvent};
 use crate::services::matching::{
-    OrderType as MatchingOrderType, Side as MatchingSide,
+    DeadManTimer, MatchingError, OrderType as MatchingOrderType, Side as MatchingSide,
 };
 use crate::AppState;
 
@@ -33,16 +43,55 @@ use crate::AppState;
 
 #[derive(Debug, Deserialize)]
 pub struct CancelOrderRequest {
+    /// EIP-712 signature (not needed on API key requests)
+    #[serde(default)]
     pub signature: String,
     pub timestamp: u64,
+    #[serde(default)]
+    pub timestamp_token: Option<String>,
+    /// Trading nonce (GET /auth/nonce/trading)
+    #[serde(default)]
+    pub nonce: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AmendOrderRequest {
+    /// New limit price (unchanged when omitted)
+    pub price: Option<Decimal>,
+    /// New total order amount, including what has already filled (unchanged when omitted)
+    pub amount: Option<Decimal>,
+    /// EIP-712 signature (not needed on API key requests)
+    #[serde(default)]
+    pub signature: String,
+    pub timestamp: u64,
+    #[serde(default)]
+    pub timestamp_token: Option<String>,
+    /// Trading nonce (GET /auth/nonce/trading)
+    #[serde(default)]
+    pub nonce: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AmendOrderResponse {
+    #[serde(flatten)]
+    pub order: OrderResponse,
+    /// The order kept its time priority (size reduced at the same price)
+    pub priority_kept: bool,
 }
 
 #[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 pub struct BatchCancelRequest {
     pub order_ids: Vec<Uuid>,
+    /// EIP-712 signature (not needed on API key requests)
+    #[serde(default)]
     pub signature: String,
     pub timestamp: u64,
+    #[serde(default)]
+    pub timestamp_token: Option<String>,
+    /// Trading nonce (GET /auth/nonce/trading)
+    #[serde(default)]
+    pub nonce: Option<u64>,
 }
 
 #[derive(Debug, Serialize)]
@@ -51,6 +100,30 @@ pub struct BatchCancelResponse {
     pub failed: Vec<Uuid>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct CancelAllAfterRequest {
+    /// Countdown in milliseconds; 0 disarms the switch
+    pub timeout_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CancelAllAfterResponse {
+    pub armed: bool,
+    pub timeout_ms: u64,
+    /// When resting orders are cancelled unless re-armed (ms)
+    pub trigger_at: Option<i64>,
+}
+
+impl From<Option<DeadManTimer>> for CancelAllAfterResponse {
+    fn from(timer: Option<DeadManTimer>) -> Self {
+        Self {
+            armed: timer.is_some(),
+            timeout_ms: timer.map_or(0, |timer| timer.timeout_ms),
+            trigger_at: timer.map(|timer| timer.trigger_at.timestamp_millis()),
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct ErrorResponse {
     pub error: String,
@@ -69,6 +142,11 @@ pub struct CreateOrderResponse {
     pub average_price: Decimal,
     #[serde(serialize_with = "serialize_datetime_as_millis")]
     pub created_at: chrono::DateTime<chrono::Utc>,
+    /// How the order was acknowledged
+    pub durability: DurabilityMode,
+    /// Journal sequence on disk when acknowledged (ack_after_journal only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub journal_sequence: Option<u64>,
 }
 
 fn serialize_datetime_as_millis<S>(
@@ -85,20 +163,70 @@ where
 // Validation Helpers
 // ============================================================================
 
-/// Validate timestamp (within 5 minutes)
-fn validate_timestamp(timestamp: u64) -> bool {
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    now.abs_diff(timestamp) <= 300
+/// Reject a stale or replayed signed request (timestamp and trading nonce)
+async fn check_signed_request(
+    state: &AppState,
+    auth_user: &AuthUser,
+    fields: SignedFields<'_>,
+) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    check_replay(state, &auth_user.address, fields).await.map_err(|e| {
+        let error = match &e {
+            ReplayError::TimestampExpired => "时间戳已过期".to_string(),
+            ReplayError::NonceRequired => "缺少交易nonce, 请先获取 (GET /auth/nonce/trading)".to_string(),
+            ReplayError::Nonce(NonceError::NotIssued(nonce)) => format!("nonce {} 无效", nonce),
+            ReplayError::Nonce(NonceError::Reused(nonce)) => format!("nonce {} 已使用", nonce),
+            ReplayError::Nonce(NonceError::SignatureReused) => "签名已使用, 请重新签名".to_string(),
+            ReplayError::Nonce(e) => format!("校验nonce失败: {}", e),
+        };
+        let status = if e.is_internal() {
+            StatusCode::INTERNAL_SERVER_ERROR
+        } else {
+            StatusCode::BAD_REQUEST
+        };
+        (
+            status,
+            Json(ErrorResponse {
+                error,
+                code: e.code().to_string(),
+            }),
+        )
+    })
+}
+
+/// Failure loading the account's risk profile or exposure
+fn risk_profile_error(e: RiskProfileError) -> (StatusCode, Json<ErrorResponse>) {
+    let (status, code) = api_error::classify(&e);
+    let error = if api_error::is_client_error(&e) {
+        format!("风险档位校验失败: {}", e)
+    } else {
+        "风险档位校验失败, 请稍后重试".to_string()
+    };
+    (
+        status,
+        Json(ErrorResponse {
+            error,
+            code: code.to_string(),
+        }),
+    )
+}
+
+/// Announce a persisted order status on the user's `orders` stream
+async fn publish_order_status(
+    state: &AppState,
+    user_address: &str,
+    order_id: Uuid,
+    status: OrderStatus,
+    filled_amount: Option<Decimal>,
+) {
+    let event = OrderEvent::status(user_address, order_id, status, filled_amount);
+    if let Err(e) = user_events::publish(&state.db.pool, &event).await {
+        tracing::warn!("Failed to publish order {} status: {}", order_id, e);
+    }
 }
 
 /// Validate price is within prediction market range (0.01 - 0.99)
 fn validate_price(price: Decimal) -> bool {
-    let min = Decimal::new(1, 2); // 0.01
-    let max = Decimal::new(99, 2); // 0.99
-    price >= min && price <= max
+    price >= CreateOrderRequest::min_price() && price <= CreateOrderRequest::max_price()
 }
 
 // ============================================================================
@@ -110,8 +238,19 @@ fn validate_price(price: Decimal) -> bool {
 pub async fn create_order(
     State(state): State<Arc<AppState>>,
     Extension(auth_user): Extension<AuthUser>,
+    commit: Option<Extension<IdempotencyCommit>>,
     Json(req): Json<CreateOrderRequest>,
 ) -> Result<Json<CreateOrderResponse>, (StatusCode, Json<ErrorResponse>)> {
+    if state.shutdown.is_draining() {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ErrorResponse {
+                error: "服务正在关闭，暂停接收新订单".to_string(),
+                code: "SHUTTING_DOWN".to_string(),
+            }),
+        ));
+    }
+
     // Validate price range
     if !validate_price(req.price) {
         return Err((
@@ -134,13 +273,54 @@ pub async fn create_order(
         ));
     }
 
-    // Validate timestamp
-    if !state.config.is_auth_disabled() && !validate_timestamp(req.timestamp) {
+    // Validate optional order fields
+    if req.leverage == Some(0) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "杠杆倍数必须大于 0".to_string(),
+                code: "INVALID_LEVERAGE".to_string(),
+            }),
+        ));
+    }
+    if req
+        .slippage_tolerance
+        .is_some_and(|s| s < Decimal::ZERO || s > OrderPreferences::MAX_SLIPPAGE)
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: format!("滑点容忍度必须在 0 到 {} 之间", OrderPreferences::MAX_SLIPPAGE),
+                code: "INVALID_SLIPPAGE".to_string(),
+            }),
+        ));
+    }
+
+    // Account defaults fill in omitted optional fields
+    let preferences = PreferenceService::get(&state.db.pool, &auth_user.address)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("查询账户偏好失败: {}", e),
+                    code: "DB_ERROR".to_string(),
+                }),
+            )
+        })?;
+    let order_type = req.order_type.unwrap_or_else(|| preferences.order_type());
+    let leverage = req.leverage.unwrap_or_else(|| preferences.leverage_for(req.market_id));
+    let reduce_only = req.reduce_only.unwrap_or(preferences.reduce_only);
+    let slippage_tolerance = req.slippage_tolerance.unwrap_or(preferences.slippage_tolerance);
+    let durability = req.durability.unwrap_or_else(|| preferences.durability());
+
+    // A journal-backed acknowledgment needs the engine journal
+    if durability == DurabilityMode::AckAfterJournal && !state.matching_engine.journal_enabled() {
         return Err((
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
-                error: "时间戳已过期".to_string(),
-                code: "TIMESTAMP_EXPIRED".to_string(),
+                error: "撮合引擎日志未启用，无法使用 ack_after_journal".to_string(),
+                code: "DURABILITY_UNAVAILABLE".to_string(),
             }),
         ));
     }
@@ -152,26 +332,37 @@ pub async fn create_order(
         outcome_id: req.outcome_id.to_string(),
         share_type: req.share_type.to_string(),
         side: req.side.to_string(),
-        order_type: req.order_type.to_string(),
+        order_type: order_type.to_string(),
         price: req.price.to_string(),
         amount: req.amount.to_string(),
+        nonce: req.nonce,
         timestamp: req.timestamp,
     };
 
-    // Verify EIP-712 signature
-    if !state.config.is_auth_disabled() {
-        let verify_result = verify_create_order_signature_with_debug(&order_msg, &req.signature, &auth_user.address)
-            .map_err(|e| {
-                (
-                    StatusCode::BAD_REQUEST,
-                    Json(ErrorResponse {
-                        error: format!("签名验证失败: {}", e),
-                        code: "SIGNATURE_INVALID".to_string(),
-                    }),
-                )
-            })?;
+    // Verify EIP-712 signature, by the wallet or one of its session keys (API
+    // key requests are HMAC-signed instead)
+    let mut signed_hash = None;
+    if !state.config.is_auth_disabled() && auth_user.api_key.is_none() {
+        let struct_hash = order_msg.struct_hash();
+        let valid = verify_trading_signature(
+            &state.db.pool,
+            struct_hash,
+            &req.signature,
+            auth_user.signer(),
+            SessionScope::Place,
+        )
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: format!("签名验证失败: {}", e),
+                    code: "SIGNATURE_INVALID".to_string(),
+                }),
+            )
+        })?;
 
-        if !verify_result.is_valid {
+        if !valid {
             return Err((
                 StatusCode::BAD_REQUEST,
                 Json(ErrorResponse {
@@ -180,18 +371,245 @@ pub async fn create_order(
                 }),
             ));
         }
+        signed_hash = Some(struct_hash);
     }
 
+    // Reject stale or replayed requests
+    check_signed_request(
+        &state,
+        &auth_user,
+        SignedFields {
+            timestamp: req.timestamp,
+            timestamp_token: req.timestamp_token.as_deref(),
+            nonce: req.nonce,
+            signed_hash,
+        },
+    )
+    .await?;
+
+    // Reject outside the market's trading session (before any collateral is frozen)
+    if state.matching_engine.check_session(&req.market_id.to_string()).is_err() {
+        let next_open = state
+            .matching_engine
+            .session_state(req.market_id)
+            .and_then(|s| s.next_open)
+            .and_then(chrono::DateTime::from_timestamp_millis)
+            .map(|t| format!("，下次开盘时间 {}", t.to_rfc3339()))
+            .unwrap_or_default();
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: format!("市场当前不在交易时段{}", next_open),
+                code: "MARKET_CLOSED".to_string(),
+            }),
+        ));
+    }
+
+    // Market configuration (order size limits), cached after the first load
+    let market_config = state
+        .market_service
+        .get_market_config(&state.db.pool, req.market_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("查询市场配置失败: {}", e),
+                    code: "DB_ERROR".to_string(),
+                }),
+            )
+        })?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: "市场不存在".to_string(),
+                    code: "MARKET_NOT_FOUND".to_string(),
+                }),
+            )
+        })?;
+
+    // Market orders fill no further than the slippage tolerance past their price
+    let price = match order_type {
+        OrderType::Market => slippage_limit(req.side, req.price, slippage_tolerance, market_config.tick_size),
+        OrderType::Limit => req.price,
+    };
+
+    // Keep the auto-MM's quotes from crossing its own or the real book
+    let live_config = state.live_config.current();
+    let mm_account = !live_config.auto_mm_test_account.is_empty()
+        && auth_user.address.eq_ignore_ascii_case(&live_config.auto_mm_test_account);
+    let mm_quote = matches!(order_type, OrderType::Limit) && mm_account;
+    let side = if matches!(req.side, OrderSide::Buy) { MatchingSide::Buy } else { MatchingSide::Sell };
+    let market_key = format!("{}:{}:{}", req.market_id, req.outcome_id, req.share_type);
+    let price = if mm_quote {
+        let policy = live_config.mm_cross_policy.parse().unwrap_or(CrossPolicy::Off);
+        let quote = MmQuoteGuard::check(
+            &state.db.pool,
+            &state.matching_engine,
+            policy,
+            &market_key,
+            &auth_user.address,
+            side,
+            price,
+            market_config.tick_size,
+            state.config.collateral_symbol(),
+        )
+        .await
+        .map_err(|e| {
+            let (status, code) = match e {
+                MmGuardError::DatabaseError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "DB_ERROR"),
+                _ => (StatusCode::BAD_REQUEST, "MM_QUOTE_WOULD_CROSS"),
+            };
+            (
+                status,
+                Json(ErrorResponse {
+                    error: format!("做市报价会穿越盘口: {}", e),
+                    code: code.to_string(),
+                }),
+            )
+        })?;
+        quote.price
+    } else {
+        price
+    };
+
+    // Back the auto-MM off while taker flow is one-sided. The quote is signed,
+    // so it is refused rather than widened or scaled here; the in-process
+    // quoter adjusts its own quotes before placing them.
+    if mm_quote && live_config.mm_imbalance_protection {
+        if let Some(protection) = state.mm_imbalance.protection(&market_key, Utc::now()) {
+            tracing::info!(
+                "MM quote on {} refused during imbalance protection: {} {} @ {}",
+                market_key, req.side, req.amount, price
+            );
+            return Err((
+                StatusCode::CONFLICT,
+                Json(ErrorResponse {
+                    error: format!(
+                        "做市报价暂停: 单边成交保护中, 至 {} 结束",
+                        protection.expires_at.to_rfc3339()
+                    ),
+                    code: "MM_IMBALANCE_PROTECTED".to_string(),
+                }),
+            ));
+        }
+    }
+
+    // Current holding, for the risk-limit tiers, reduce-only orders and the risk profile
+    let held = if reduce_only || !market_config.margin_tiers.is_empty() || !mm_account {
+        let held: Option<Decimal> =
+            sqlx::query_scalar("SELECT amount FROM shares WHERE user_address = $1 AND outcome_id = $2")
+                .bind(auth_user.address.to_lowercase())
+                .bind(req.outcome_id)
+                .fetch_optional(&state.db.pool)
+                .await
+                .map_err(|e| {
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(ErrorResponse {
+                            error: format!("查询持仓失败: {}", e),
+                            code: "DB_ERROR".to_string(),
+                        }),
+                    )
+                })?;
+        held.unwrap_or(Decimal::ZERO)
+    } else {
+        Decimal::ZERO
+    };
+
+    if reduce_only && !reduces_holding(held, req.side, req.amount) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: format!("只减仓订单不能增加持仓，当前持仓 {}", held),
+                code: "REDUCE_ONLY".to_string(),
+            }),
+        ));
+    }
+
+    // Position notional the order would build, checked against the market's risk-limit tiers
+    let position_notional = if market_config.margin_tiers.is_empty() {
+        req.amount * price
+    } else {
+        let delta = if matches!(req.side, OrderSide::Buy) { req.amount } else { -req.amount };
+        (held + delta).abs() * price
+    };
+
+    // Reject orders that break the market's trading rules
+    let violation = match state
+        .matching_engine
+        .check_rules(&req.market_id.to_string(), Some(price), req.amount, leverage)
+    {
+        Err(MatchingError::RuleViolation(violation)) => Some(violation),
+        _ => market_config
+            .check_order_size(req.amount)
+            .and_then(|_| market_config.check_leverage(position_notional, leverage))
+            .err(),
+    };
+    if let Some(violation) = violation {
+        let (error, code) = match violation {
+            RuleViolation::Paused => ("市场已暂停交易".to_string(), "MARKET_PAUSED"),
+            RuleViolation::Delisted => ("市场已下架".to_string(), "MARKET_DELISTED"),
+            RuleViolation::LeverageTooHigh { requested, max } => {
+                (format!("杠杆 {} 超过市场上限 {}", requested, max), "INVALID_LEVERAGE")
+            }
+            RuleViolation::OffTick { tick, .. } => (format!("价格必须是最小变动单位 {} 的整数倍", tick), "INVALID_PRICE"),
+            RuleViolation::OffLot { lot, .. } => (format!("数量必须是最小交易单位 {} 的整数倍", lot), "INVALID_AMOUNT"),
+            RuleViolation::BelowMinSize { min, .. } => (format!("订单数量不能小于 {}", min), "INVALID_AMOUNT"),
+            RuleViolation::AboveMaxSize { max, .. } => (format!("订单数量不能大于 {}", max), "INVALID_AMOUNT"),
+        };
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error,
+                code: code.to_string(),
+            }),
+        ));
+    }
+
+    // Hold the order to the account's risk profile (sub-accounts follow their owner's)
+    if !mm_account {
+        let profile = RiskProfileService::get(&state.db.pool, auth_user.signer())
+            .await
+            .map_err(risk_profile_error)?;
+        let open_notional = RiskProfileService::open_notional(&state.db.pool, &auth_user.address)
+            .await
+            .map_err(risk_profile_error)?;
+        let reduces = reduce_only || reduces_holding(held, req.side, req.amount);
+        risk_profile::check_order(
+            profile.profile,
+            leverage,
+            market_config.high_volatility,
+            open_notional,
+            req.amount * price,
+            reduces,
+        )
+        .map_err(|violation| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: format!("超出风险档位限制: {}", violation),
+                    code: "RISK_PROFILE_LIMIT".to_string(),
+                }),
+            )
+        })?;
+    }
+
+    // Generate order ID
+    let order_id = Uuid::new_v4();
+
     // Check balance for buy orders
+    let mut frozen_collateral = Decimal::ZERO;
     if matches!(req.side, OrderSide::Buy) {
-        let required_collateral = req.amount * req.price;
+        let required_collateral = req.amount * price;
         let collateral_symbol = state.config.collateral_symbol();
 
         let balance: Option<Decimal> = sqlx::query_scalar(
             "SELECT available FROM balances WHERE user_address = $1 AND token = $2"
         )
-        .bind(&auth_user.address.to_lowercase())
-        .bind(&collateral_symbol)
+        .bind(auth_user.address.to_lowercase())
+        .bind(collateral_symbol)
         .fetch_optional(&state.db.pool)
         .await
         .map_err(|e| {
@@ -219,16 +637,10 @@ pub async fn create_order(
         }
 
         // Freeze collateral
-        sqlx::query(
-            "UPDATE balances SET available = available - $1, frozen = frozen + $1, updated_at = NOW()
-             WHERE user_address = $2 AND token = $3"
-        )
-        .bind(required_collateral)
-        .bind(&auth_user.address.to_lowercase())
-        .bind(&collateral_symbol)
-        .execute(&state.db.pool)
-        .await
-        .map_err(|e| {
+        let change = BalanceChange::freeze(&auth_user.address, collateral_symbol, required_collateral, LedgerReason::OrderFreeze)
+            .reference(order_id)
+            .checked();
+        let frozen = LedgerService::post(&state.db.pool, &change).await.map_err(|e| {
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(ErrorResponse {
@@ -237,6 +649,16 @@ pub async fn create_order(
                 }),
             )
         })?;
+        if frozen.is_none() {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: format!("余额不足，需要 {} {}", required_collateral, collateral_symbol),
+                    code: "INSUFFICIENT_BALANCE".to_string(),
+                }),
+            ));
+        }
+        frozen_collateral = required_collateral;
     }
 
     // Convert to matching engine types
@@ -245,20 +667,17 @@ pub async fn create_order(
         OrderSide::Sell => MatchingSide::Sell,
     };
 
-    let matching_order_type = match req.order_type {
+    let matching_order_type = match order_type {
         OrderType::Limit => MatchingOrderType::Limit,
         OrderType::Market => MatchingOrderType::Market,
     };
 
-    // Generate order ID
-    let order_id = Uuid::new_v4();
-
     // Build market key for orderbook: market_id:outcome_id:share_type
     let market_key = format!("{}:{}:{}", req.market_id, req.outcome_id, req.share_type);
 
     // Submit to matching engine
-    // For prediction markets, we use market_key as the "symbol" and leverage=1
-    let match_result = state
+    // For prediction markets, we use market_key as the "symbol"
+    let submitted = state
         .matching_engine
         .submit_order(
             order_id,
@@ -267,18 +686,63 @@ pub async fn create_order(
             matching_side,
             matching_order_type,
             req.amount,
-            Some(req.price),
-            1, // No leverage in prediction markets
-        )
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
+            Some(price),
+            leverage,
+        );
+    let match_result = match submitted {
+        Ok(match_result) => match_result,
+        Err(e) => {
+            // Not accepted: hand the collateral back so a retry starts clean
+            if frozen_collateral > Decimal::ZERO {
+                let change = BalanceChange::unfreeze(
+                    &auth_user.address,
+                    state.config.collateral_symbol(),
+                    frozen_collateral,
+                    LedgerReason::OrderUnfreeze,
+                )
+                .reference(order_id);
+                if let Err(unfreeze_error) = LedgerService::post(&state.db.pool, &change).await {
+                    tracing::error!("Failed to unfreeze collateral of rejected order {}: {}", order_id, unfreeze_error);
+                    // Collateral stays frozen: the request has taken effect
+                    if let Some(Extension(commit)) = &commit {
+                        commit.commit(order_id);
+                    }
+                }
+            }
+            let (status, code) = api_error::classify(&e);
+            let error = if api_error::is_client_error(&e) {
+                format!("订单提交失败: {}", e)
+            } else {
+                "订单提交失败, 请稍后重试".to_string()
+            };
+            return Err((
+                status,
                 Json(ErrorResponse {
-                    error: format!("订单提交失败: {}", e),
-                    code: "MATCHING_ERROR".to_string(),
+                    error,
+                    code: code.to_string(),
                 }),
-            )
-        })?;
+            ));
+        }
+    };
+    // The order is live in the engine: a retry must not place it again
+    if let Some(Extension(commit)) = &commit {
+        commit.commit(order_id);
+    }
+
+    // The journal entry was written before matching; with ack_after_journal
+    // it must also be on disk before the order is acknowledged. If the sync
+    // fails the order is already matched, so it falls back to ack_fast and
+    // the response reports the mode actually achieved.
+    let (durability, journal_sequence) = match durability {
+        DurabilityMode::AckAfterJournal => match state.matching_engine.sync_journal() {
+            Ok(sequence) => (DurabilityMode::AckAfterJournal, sequence),
+            Err(e) => {
+                tracing::error!("Order {} acknowledged without journal sync: {}", order_id, e);
+                (DurabilityMode::AckFast, None)
+            }
+        },
+        DurabilityMode::AckFast => (DurabilityMode::AckFast, None),
+    };
 
     // Convert status
     let status = match match_result.status {
@@ -309,28 +773,30 @@ pub async fn create_order(
         INSERT INTO orders (
             id, user_address, market_id, outcome_id, share_type,
             side, order_type, price, amount, filled_amount, status, signature,
-            created_at, updated_at
+            created_at, updated_at, durability_mode, journal_sequence
         )
         VALUES (
             $1, $2, $3, $4, $5::share_type,
             $6::order_side, $7::order_type, $8, $9, $10, $11::order_status, $12,
-            $13, $13
+            $13, $13, $14, $15
         )
         "#,
     )
     .bind(order_id)
-    .bind(&auth_user.address.to_lowercase())
+    .bind(auth_user.address.to_lowercase())
     .bind(req.market_id)
     .bind(req.outcome_id)
     .bind(req.share_type.to_string())
     .bind(req.side.to_string())
-    .bind(req.order_type.to_string())
-    .bind(req.price)
+    .bind(order_type.to_string())
+    .bind(price)
     .bind(req.amount)
     .bind(match_result.filled_amount)
     .bind(status.to_string())
     .bind(&req.signature)
     .bind(now)
+    .bind(durability.to_string())
+    .bind(journal_sequence.map(|s| s as i64))
     .execute(&state.db.pool)
     .await
     .map_err(|e| {
@@ -343,6 +809,7 @@ pub async fn create_order(
             }),
         )
     })?;
+    publish_order_status(&state, &auth_user.address, order_id, status, Some(match_result.filled_amount)).await;
 
     Ok(Json(CreateOrderResponse {
         order_id,
@@ -354,6 +821,8 @@ pub async fn create_order(
         remaining_amount: req.amount - match_result.filled_amount,
         average_price,
         created_at: now,
+        durability,
+        journal_sequence,
     }))
 }
 
@@ -374,7 +843,7 @@ pub async fn get_order(
         "#,
     )
     .bind(order_id)
-    .bind(&auth_user.address.to_lowercase())
+    .bind(auth_user.address.to_lowercase())
     .fetch_optional(&state.db.pool)
     .await
     .map_err(|e| {
@@ -407,35 +876,34 @@ pub async fn cancel_order(
     Path(order_id): Path<Uuid>,
     Json(req): Json<CancelOrderRequest>,
 ) -> Result<Json<OrderResponse>, (StatusCode, Json<ErrorResponse>)> {
-    // Validate timestamp
-    if !state.config.is_auth_disabled() && !validate_timestamp(req.timestamp) {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: "时间戳已过期".to_string(),
-                code: "TIMESTAMP_EXPIRED".to_string(),
-            }),
-        ));
-    }
-
     // Verify signature
-    if !state.config.is_auth_disabled() {
+    let mut signed_hash = None;
+    if !state.config.is_auth_disabled() && auth_user.api_key.is_none() {
         let cancel_msg = CancelOrderMessage {
             wallet: auth_user.address.to_lowercase(),
             order_id: order_id.to_string(),
+            nonce: req.nonce,
             timestamp: req.timestamp,
         };
 
-        let valid = verify_cancel_order_signature(&cancel_msg, &req.signature, &auth_user.address)
-            .map_err(|e| {
-                (
-                    StatusCode::BAD_REQUEST,
-                    Json(ErrorResponse {
-                        error: format!("签名验证失败: {}", e),
-                        code: "SIGNATURE_INVALID".to_string(),
-                    }),
-                )
-            })?;
+        let struct_hash = cancel_msg.struct_hash();
+        let valid = verify_trading_signature(
+            &state.db.pool,
+            struct_hash,
+            &req.signature,
+            auth_user.signer(),
+            SessionScope::Cancel,
+        )
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: format!("签名验证失败: {}", e),
+                    code: "SIGNATURE_INVALID".to_string(),
+                }),
+            )
+        })?;
 
         if !valid {
             return Err((
@@ -446,8 +914,22 @@ pub async fn cancel_order(
                 }),
             ));
         }
+        signed_hash = Some(struct_hash);
     }
 
+    // Reject stale or replayed requests
+    check_signed_request(
+        &state,
+        &auth_user,
+        SignedFields {
+            timestamp: req.timestamp,
+            timestamp_token: req.timestamp_token.as_deref(),
+            nonce: req.nonce,
+            signed_hash,
+        },
+    )
+    .await?;
+
     // Get order from database
     let order: Option<Order> = sqlx::query_as(
         r#"
@@ -459,7 +941,7 @@ pub async fn cancel_order(
         "#,
     )
     .bind(order_id)
-    .bind(&auth_user.address.to_lowercase())
+    .bind(auth_user.address.to_lowercase())
     .fetch_optional(&state.db.pool)
     .await
     .map_err(|e| {
@@ -538,22 +1020,16 @@ pub async fn cancel_order(
                 }),
             )
         })?;
+    publish_order_status(&state, &auth_user.address, order_id, OrderStatus::Cancelled, None).await;
 
     // Unfreeze collateral for buy orders
     if matches!(order.side, OrderSide::Buy) {
         let remaining_collateral = order.remaining_amount() * order.price;
         let collateral_symbol = state.config.collateral_symbol();
 
-        sqlx::query(
-            "UPDATE balances SET available = available + $1, frozen = frozen - $1, updated_at = NOW()
-             WHERE user_address = $2 AND token = $3"
-        )
-        .bind(remaining_collateral)
-        .bind(&auth_user.address.to_lowercase())
-        .bind(&collateral_symbol)
-        .execute(&state.db.pool)
-        .await
-        .map_err(|e| {
+        let change = BalanceChange::unfreeze(&auth_user.address, collateral_symbol, remaining_collateral, LedgerReason::OrderUnfreeze)
+            .reference(order_id);
+        LedgerService::post(&state.db.pool, &change).await.map_err(|e| {
             tracing::error!("Failed to unfreeze collateral: {}", e);
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -575,6 +1051,244 @@ pub async fn cancel_order(
     Ok(Json(OrderResponse::from(updated_order)))
 }
 
+/// Amend the price and/or amount of a resting limit order
+/// PUT /orders/:order_id
+///
+/// Reducing the amount at the same price keeps the order's time priority;
+/// other changes are an atomic cancel-and-replace in the matching engine.
+/// Collateral of buy orders is adjusted by the difference.
+pub async fn amend_order(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(order_id): Path<Uuid>,
+    Json(req): Json<AmendOrderRequest>,
+) -> Result<Json<AmendOrderResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let bad_request = |error: String, code: &str| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error,
+                code: code.to_string(),
+            }),
+        )
+    };
+    let db_error = |error: String| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error,
+                code: "DB_ERROR".to_string(),
+            }),
+        )
+    };
+
+    if req.price.is_none() && req.amount.is_none() {
+        return Err(bad_request("价格和数量至少修改一项".to_string(), "INVALID_AMEND"));
+    }
+
+    // Get order from database
+    let order: Option<Order> = sqlx::query_as(
+        r#"
+        SELECT id, user_address, market_id, outcome_id, share_type,
+               side, order_type, price, amount, filled_amount, status, signature,
+               created_at, updated_at
+        FROM orders
+        WHERE id = $1 AND user_address = $2
+        "#,
+    )
+    .bind(order_id)
+    .bind(auth_user.address.to_lowercase())
+    .fetch_optional(&state.db.pool)
+    .await
+    .map_err(|e| db_error(format!("查询订单失败: {}", e)))?;
+
+    let order = order.ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "订单不存在".to_string(),
+                code: "ORDER_NOT_FOUND".to_string(),
+            }),
+        )
+    })?;
+
+    if !order.is_cancellable() || !matches!(order.order_type, OrderType::Limit) {
+        return Err(bad_request(
+            format!("订单状态 {} 无法修改", order.status),
+            "ORDER_NOT_AMENDABLE",
+        ));
+    }
+
+    let price = req.price.unwrap_or(order.price);
+    let amount = req.amount.unwrap_or(order.amount);
+    if !validate_price(price) {
+        return Err(bad_request("价格必须在 0.01 到 0.99 之间".to_string(), "INVALID_PRICE"));
+    }
+
+    // Verify signature
+    let mut signed_hash = None;
+    if !state.config.is_auth_disabled() && auth_user.api_key.is_none() {
+        let amend_msg = AmendOrderMessage {
+            wallet: auth_user.address.to_lowercase(),
+            order_id: order_id.to_string(),
+            price: price.to_string(),
+            amount: amount.to_string(),
+            nonce: req.nonce,
+            timestamp: req.timestamp,
+        };
+
+        let struct_hash = amend_msg.struct_hash();
+        let valid = verify_trading_signature(
+            &state.db.pool,
+            struct_hash,
+            &req.signature,
+            auth_user.signer(),
+            SessionScope::Amend,
+        )
+        .await
+        .map_err(|e| bad_request(format!("签名验证失败: {}", e), "SIGNATURE_INVALID"))?;
+
+        if !valid {
+            return Err(bad_request("签名验证失败".to_string(), "SIGNATURE_INVALID"));
+        }
+        signed_hash = Some(struct_hash);
+    }
+
+    // Reject stale or replayed requests
+    check_signed_request(
+        &state,
+        &auth_user,
+        SignedFields {
+            timestamp: req.timestamp,
+            timestamp_token: req.timestamp_token.as_deref(),
+            nonce: req.nonce,
+            signed_hash,
+        },
+    )
+    .await?;
+
+    // Buy orders hold collateral for their remaining amount at their price
+    let collateral_symbol = state.config.collateral_symbol();
+    let collateral_delta = if matches!(order.side, OrderSide::Buy) {
+        (amount - order.filled_amount) * price - order.remaining_amount() * order.price
+    } else {
+        Decimal::ZERO
+    };
+
+    if collateral_delta > Decimal::ZERO {
+        let change = BalanceChange::freeze(&auth_user.address, collateral_symbol, collateral_delta, LedgerReason::OrderFreeze)
+            .reference(order_id)
+            .checked();
+        let frozen = LedgerService::post(&state.db.pool, &change)
+            .await
+            .map_err(|e| db_error(format!("冻结资金失败: {}", e)))?;
+        if frozen.is_none() {
+            return Err(bad_request(
+                format!("余额不足，需要追加 {} {}", collateral_delta, collateral_symbol),
+                "INSUFFICIENT_BALANCE",
+            ));
+        }
+    }
+
+    // Amend in matching engine
+    let market_key = format!("{}:{}:{}", order.market_id, order.outcome_id, order.share_type);
+    let amended = state.matching_engine.amend_order(
+        &market_key,
+        order_id,
+        &auth_user.address.to_lowercase(),
+        price,
+        amount,
+    );
+
+    let amended = match amended {
+        Ok(amended) => amended,
+        Err(e) => {
+            // Release the extra collateral frozen for the amendment
+            if collateral_delta > Decimal::ZERO {
+                let change = BalanceChange::unfreeze(&auth_user.address, collateral_symbol, collateral_delta, LedgerReason::OrderUnfreeze)
+                    .reference(order_id);
+                if let Err(e) = LedgerService::post(&state.db.pool, &change).await {
+                    tracing::error!("Failed to release amend collateral of order {}: {}", order_id, e);
+                }
+            }
+            let (status, code) = api_error::classify(&e);
+            let error = if api_error::is_client_error(&e) {
+                format!("修改订单失败: {}", e)
+            } else {
+                "修改订单失败, 请稍后重试".to_string()
+            };
+            return Err((
+                status,
+                Json(ErrorResponse {
+                    error,
+                    code: code.to_string(),
+                }),
+            ));
+        }
+    };
+
+    let status = match amended.status {
+        crate::services::matching::OrderStatus::PartiallyFilled => OrderStatus::PartiallyFilled,
+        crate::services::matching::OrderStatus::Filled => OrderStatus::Filled,
+        _ => OrderStatus::Open,
+    };
+
+    // Update order in database
+    sqlx::query(
+        r#"
+        UPDATE orders
+        SET price = $1, amount = $2, filled_amount = $3, status = $4::order_status, updated_at = NOW()
+        WHERE id = $5
+        "#,
+    )
+    .bind(price)
+    .bind(amount)
+    .bind(amended.filled_amount)
+    .bind(status.to_string())
+    .bind(order_id)
+    .execute(&state.db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to persist amended order {}: {}", order_id, e);
+        db_error(format!("更新订单失败: {}", e))
+    })?;
+    publish_order_status(&state, &auth_user.address, order_id, status, Some(amended.filled_amount)).await;
+
+    // Release collateral the amendment no longer needs
+    if collateral_delta < Decimal::ZERO {
+        let change = BalanceChange::unfreeze(&auth_user.address, collateral_symbol, -collateral_delta, LedgerReason::OrderUnfreeze)
+            .reference(order_id);
+        LedgerService::post(&state.db.pool, &change).await.map_err(|e| {
+            tracing::error!("Failed to unfreeze collateral: {}", e);
+            db_error(format!("解冻资金失败: {}", e))
+        })?;
+    }
+
+    tracing::info!(
+        "Order amended - id: {}, price: {} -> {}, amount: {} -> {}, priority kept: {}",
+        order_id,
+        order.price,
+        price,
+        order.amount,
+        amount,
+        amended.priority_kept
+    );
+
+    let updated_order = Order {
+        price,
+        amount,
+        filled_amount: amended.filled_amount,
+        status,
+        updated_at: Utc::now(),
+        ..order
+    };
+
+    Ok(Json(AmendOrderResponse {
+        order: OrderResponse::from(updated_order),
+        priority_kept: amended.priority_kept,
+    }))
+}
+
 /// Batch cancel orders
 /// POST /orders/batch
 pub async fn batch_cancel(
@@ -582,17 +1296,51 @@ pub async fn batch_cancel(
     Extension(auth_user): Extension<AuthUser>,
     Json(req): Json<BatchCancelRequest>,
 ) -> Result<Json<BatchCancelResponse>, (StatusCode, Json<ErrorResponse>)> {
-    // Validate timestamp
-    if !state.config.is_auth_disabled() && !validate_timestamp(req.timestamp) {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: "时间戳已过期".to_string(),
-                code: "TIMESTAMP_EXPIRED".to_string(),
-            }),
-        ));
+    // Verify signature
+    let mut signed_hash = None;
+    if !state.config.is_auth_disabled() && auth_user.api_key.is_none() {
+        let batch_msg = BatchCancelMessage {
+            wallet: auth_user.address.to_lowercase(),
+            order_ids: req.order_ids.iter().map(Uuid::to_string).collect::<Vec<_>>().join(","),
+            nonce: req.nonce,
+            timestamp: req.timestamp,
+        };
+
+        let struct_hash = batch_msg.struct_hash();
+        let valid = verify_trading_signature(
+            &state.db.pool,
+            struct_hash,
+            &req.signature,
+            auth_user.signer(),
+            SessionScope::Cancel,
+        )
+        .await
+        .unwrap_or(false);
+        if !valid {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: "签名验证失败".to_string(),
+                    code: "SIGNATURE_INVALID".to_string(),
+                }),
+            ));
+        }
+        signed_hash = Some(struct_hash);
     }
 
+    // Reject stale or replayed requests
+    check_signed_request(
+        &state,
+        &auth_user,
+        SignedFields {
+            timestamp: req.timestamp,
+            timestamp_token: req.timestamp_token.as_deref(),
+            nonce: req.nonce,
+            signed_hash,
+        },
+    )
+    .await?;
+
     let mut cancelled = Vec::new();
     let mut failed = Vec::new();
 
@@ -608,7 +1356,7 @@ pub async fn batch_cancel(
             "#,
         )
         .bind(order_id)
-        .bind(&auth_user.address.to_lowercase())
+        .bind(auth_user.address.to_lowercase())
         .fetch_optional(&state.db.pool)
         .await
         .unwrap_or(None);
@@ -633,21 +1381,21 @@ pub async fn batch_cancel(
                     .bind(order_id)
                     .execute(&state.db.pool)
                     .await;
+                    publish_order_status(&state, &auth_user.address, order_id, OrderStatus::Cancelled, None).await;
 
                     // Unfreeze collateral for buy orders
                     if matches!(order.side, OrderSide::Buy) {
                         let remaining_collateral = order.remaining_amount() * order.price;
                         let collateral_symbol = state.config.collateral_symbol();
 
-                        let _ = sqlx::query(
-                            "UPDATE balances SET available = available + $1, frozen = frozen - $1, updated_at = NOW()
-                             WHERE user_address = $2 AND token = $3"
+                        let change = BalanceChange::unfreeze(
+                            &auth_user.address,
+                            collateral_symbol,
+                            remaining_collateral,
+                            LedgerReason::OrderUnfreeze,
                         )
-                        .bind(remaining_collateral)
-                        .bind(&auth_user.address.to_lowercase())
-                        .bind(&collateral_symbol)
-                        .execute(&state.db.pool)
-                        .await;
+                        .reference(order_id);
+                        let _ = LedgerService::post(&state.db.pool, &change).await;
                     }
 
                     cancelled.push(order_id);
@@ -664,3 +1412,33 @@ pub async fn batch_cancel(
 
     Ok(Json(BatchCancelResponse { cancelled, failed }))
 }
+
+/// Arm, restart or disarm (timeout 0) the dead man's switch: all resting
+/// orders are cancelled unless this is called again before the countdown ends
+/// POST /orders/cancel-all-after
+pub async fn cancel_all_after(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(req): Json<CancelAllAfterRequest>,
+) -> Result<Json<CancelAllAfterResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let timer = state.dead_man.arm(&auth_user.address, req.timeout_ms).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: e.to_string(),
+                code: "INVALID_TIMEOUT".to_string(),
+            }),
+        )
+    })?;
+
+    Ok(Json(CancelAllAfterResponse::from(timer)))
+}
+
+/// Current dead man's switch countdown
+/// GET /orders/cancel-all-after
+pub async fn get_cancel_all_after(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Json<CancelAllAfterResponse> {
+    Json(CancelAllAfterResponse::from(state.dead_man.status(&auth_user.address)))
+}
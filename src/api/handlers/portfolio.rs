@@ -0,0 +1,127 @@
+//! Portfolio API Handlers
+//!
+//! Daily equity / PnL history for portfolio charts and performance
+//! summaries over a date range.
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::{Duration, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::api::error as api_error;
+use crate::auth::middleware::AuthUser;
+use crate::services::errors::ServiceError;
+use crate::services::portfolio::{DailyPnl, PnlSummary, PortfolioService};
+use crate::services::position::PositionService;
+use crate::AppState;
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PnlRangeQuery {
+    /// First day (UTC, default 30 days before `to`)
+    pub from: Option<NaiveDate>,
+    /// Last day (UTC, default today)
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DailyPnlResponse {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// One entry per snapshotted day, oldest first
+    pub days: Vec<DailyPnl>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PnlSummaryResponse {
+    #[serde(flatten)]
+    pub summary: PnlSummary,
+    /// Unrealized PnL of open positions, marked now
+    pub unrealized_pnl: Decimal,
+    /// Current equity (collateral + positions value)
+    pub equity: Decimal,
+}
+
+fn portfolio_error<E: ServiceError>(e: E) -> (StatusCode, Json<ErrorResponse>) {
+    let (status, code) = api_error::classify(&e);
+    let error = if api_error::is_client_error(&e) {
+        e.to_string()
+    } else {
+        "Failed to load portfolio PnL".to_string()
+    };
+    (
+        status,
+        Json(ErrorResponse {
+            error,
+            code: code.to_string(),
+        }),
+    )
+}
+
+/// Resolve the query range, defaulting to the last 30 days
+fn range(query: &PnlRangeQuery) -> (NaiveDate, NaiveDate) {
+    let to = query.to.unwrap_or_else(|| Utc::now().date_naive());
+    let from = query.from.unwrap_or(to - Duration::days(30));
+    (from, to)
+}
+
+// ============================================================================
+// Account Handlers
+// ============================================================================
+
+/// Daily equity and PnL snapshots of the authenticated account
+/// GET /account/pnl/daily
+pub async fn get_daily_pnl(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Query(query): Query<PnlRangeQuery>,
+) -> Result<Json<DailyPnlResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let (from, to) = range(&query);
+    let days = PortfolioService::daily(&state.db.pool, &auth_user.address, from, to)
+        .await
+        .map_err(portfolio_error)?;
+    Ok(Json(DailyPnlResponse { from, to, days }))
+}
+
+/// Realized / unrealized PnL, win rate, volume and fees of the authenticated account
+/// GET /account/pnl/summary
+pub async fn get_pnl_summary(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Query(query): Query<PnlRangeQuery>,
+) -> Result<Json<PnlSummaryResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let (from, to) = range(&query);
+    let summary = PortfolioService::summary(&state.db.pool, &auth_user.address, from, to)
+        .await
+        .map_err(portfolio_error)?;
+    let margin = PositionService::get_account_margin(
+        &state.db.pool,
+        &state.matching_engine,
+        &state.market_service,
+        &auth_user.address,
+        state.config.collateral_symbol(),
+        state.live_config.current().maintenance_margin_rate(),
+    )
+    .await
+    .map_err(portfolio_error)?;
+
+    Ok(Json(PnlSummaryResponse {
+        summary,
+        unrealized_pnl: margin.unrealized_pnl,
+        equity: margin.equity,
+    }))
+}
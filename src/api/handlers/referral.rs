@@ -6,6 +6,7 @@ use axum::{extract::State, http::StatusCode, Extension, Json};
 use rust_decimal::Decimal;
 use serde::{Serialize, Serializer};
 // use sqlx::PgPool;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 // use tokio::sync::RwLock;
 use chrono::{DateTime, Utc};
@@ -17,6 +18,7 @@ use crate::auth::eip712::{
     CreateReferralMessage, BindReferralMessage,
 };
 use crate::models::{BindReferralRequest, CreateReferralCodeRequest};
+use crate::services::ledger::{BalanceChange, LedgerReason, LedgerService};
 use crate::AppState;
 
 // Helper module to serialize DateTime as milliseconds timestamp
@@ -853,7 +855,8 @@ pub async fn claim_earnings(
     State(state): State<Arc<AppState>>,
     Extension(auth_user): Extension<AuthUser>,
 ) -> Result<Json<ClaimResponse>, (StatusCode, Json<ErrorResponse>)> {
-    // Get pending earnings
+    // Pending earnings, to explain a rejected claim (only what the claim
+    // below marks as claimed is credited)
     let pending: Decimal = sqlx::query_scalar(
         "SELECT COALESCE(SUM(commission), 0) FROM referral_earnings WHERE referrer_address = $1 AND status = 'pending'"
     )
@@ -897,12 +900,26 @@ pub async fn claim_earnings(
         )
     })?;
 
-    // Update earnings status to claimed
-    sqlx::query(
-        "UPDATE referral_earnings SET status = 'claimed', claimed_at = NOW() WHERE referrer_address = $1 AND status = 'pending'"
+    // Claim the earnings and credit exactly what was claimed, per token: an
+    // earning the epoch settlement paid meanwhile is no longer pending
+    let claimed: Vec<(String, Decimal)> = sqlx::query_as(
+        r#"
+        UPDATE referral_earnings
+        SET status = 'claimed', claimed_at = NOW()
+        WHERE referrer_address = $1 AND status = 'pending'
+          AND token IN (
+              SELECT token
+              FROM referral_earnings
+              WHERE referrer_address = $1 AND status = 'pending'
+              GROUP BY token
+              HAVING SUM(commission) >= $2
+          )
+        RETURNING token, commission
+        "#
     )
-    .bind(&auth_user.address.to_lowercase())
-    .execute(&mut *tx)
+    .bind(auth_user.address.to_lowercase())
+    .bind(min_claim)
+    .fetch_all(&mut *tx)
     .await
     .map_err(|e| {
         tracing::error!("Failed to update earnings status: {}", e);
@@ -915,30 +932,34 @@ pub async fn claim_earnings(
         )
     })?;
 
-    // Add to user balance - use collateral token from config
-    sqlx::query(
-        r#"
-        INSERT INTO balances (user_address, token, available, frozen)
-        VALUES ($1, $2, $3, 0)
-        ON CONFLICT (user_address, token)
-        DO UPDATE SET available = balances.available + $3
-        "#
-    )
-    .bind(&auth_user.address.to_lowercase())
-    .bind(collateral_symbol)
-    .bind(pending)
-    .execute(&mut *tx)
-    .await
-    .map_err(|e| {
-        tracing::error!("Failed to add balance: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
+    let mut per_token: BTreeMap<String, Decimal> = BTreeMap::new();
+    for (token, commission) in claimed {
+        *per_token.entry(token).or_default() += commission;
+    }
+    if per_token.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
-                error: "添加余额失败".to_string(),
-                code: "BALANCE_UPDATE_FAILED".to_string(),
+                error: "没有待领取的佣金".to_string(),
+                code: "NO_PENDING_EARNINGS".to_string(),
             }),
-        )
-    })?;
+        ));
+    }
+
+    // Add to user balance, in the token each earning was accrued in
+    for (token, amount) in &per_token {
+        let change = BalanceChange::credit(&auth_user.address, token, *amount, LedgerReason::ReferralClaim);
+        LedgerService::apply(&mut tx, &change).await.map_err(|e| {
+            tracing::error!("Failed to add balance: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "添加余额失败".to_string(),
+                    code: "BALANCE_UPDATE_FAILED".to_string(),
+                }),
+            )
+        })?;
+    }
 
     tx.commit().await.map_err(|e| {
         tracing::error!("Failed to commit transaction: {}", e);
@@ -951,11 +972,12 @@ pub async fn claim_earnings(
         )
     })?;
 
-    tracing::info!("Referral earnings claimed: {} {} for {}", pending, collateral_symbol, auth_user.address);
+    let amount: Decimal = per_token.values().sum();
+    tracing::info!("Referral earnings claimed: {:?} for {}", per_token, auth_user.address);
 
     Ok(Json(ClaimResponse {
         success: true,
-        amount: pending,
+        amount,
         tx_hash: None, // Off-chain claim, funds added to balance
     }))
 }
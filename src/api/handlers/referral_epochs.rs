@@ -0,0 +1,122 @@
+//! Referral Epoch API Handlers
+//!
+//! History of the scheduled referral earnings settlements, with what the
+//! authenticated referrer was paid in each epoch.
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::api::error as api_error;
+use crate::auth::middleware::AuthUser;
+use crate::services::errors::ServiceError;
+use crate::services::referral_settlement::{epoch_end, ReferralEpoch, ReferralPayout, ReferralSettlementService};
+use crate::AppState;
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EpochsQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// A settled epoch with the user's payouts
+#[derive(Debug, Serialize)]
+pub struct EpochEntry {
+    #[serde(flatten)]
+    pub epoch: ReferralEpoch,
+    /// Paid to the user, per token (empty if nothing was due)
+    pub payouts: Vec<ReferralPayout>,
+}
+
+/// Pending earnings in one token
+#[derive(Debug, Serialize)]
+pub struct PendingEarnings {
+    pub token: String,
+    pub amount: Decimal,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EpochsResponse {
+    /// Newest first
+    pub epochs: Vec<EpochEntry>,
+    /// Earnings waiting for the next epoch
+    pub pending: Vec<PendingEarnings>,
+    /// When the current epoch ends
+    pub next_settlement_at: DateTime<Utc>,
+    /// Pending sums below this carry over to the following epoch
+    pub min_payout: Decimal,
+}
+
+fn referral_error<E: ServiceError>(e: E) -> (StatusCode, Json<ErrorResponse>) {
+    let (status, code) = api_error::classify(&e);
+    let error = if api_error::is_client_error(&e) {
+        e.to_string()
+    } else {
+        "Failed to load referral epochs".to_string()
+    };
+    (
+        status,
+        Json(ErrorResponse {
+            error,
+            code: code.to_string(),
+        }),
+    )
+}
+
+// ============================================================================
+// Account Handlers
+// ============================================================================
+
+/// Settled referral epochs and the authenticated user's payouts
+/// GET /referral/epochs
+pub async fn get_epochs(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Query(query): Query<EpochsQuery>,
+) -> Result<Json<EpochsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let (epochs, payouts) = ReferralSettlementService::epochs(&state.db.pool, &auth_user.address, limit, offset)
+        .await
+        .map_err(referral_error)?;
+    let pending = ReferralSettlementService::pending(&state.db.pool, &auth_user.address)
+        .await
+        .map_err(referral_error)?;
+
+    let config = state.live_config.current();
+    let epoch_days = config.referral_epoch_days.max(1);
+    let epochs = epochs
+        .into_iter()
+        .map(|epoch| EpochEntry {
+            payouts: payouts.iter().filter(|p| p.epoch_id == epoch.id).cloned().collect(),
+            epoch,
+        })
+        .collect();
+
+    Ok(Json(EpochsResponse {
+        epochs,
+        pending: pending
+            .into_iter()
+            .map(|(token, amount)| PendingEarnings { token, amount })
+            .collect(),
+        next_settlement_at: epoch_end(Utc::now(), epoch_days) + Duration::days(epoch_days),
+        min_payout: config.referral_min_payout(),
+    }))
+}
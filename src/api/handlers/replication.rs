@@ -0,0 +1,118 @@
+//! Engine Replication API Handlers
+//!
+//! The internal feed warm standbys follow the primary's engine commands
+//! through, and admin endpoints to check replication lag and promote a
+//! standby on failover.
+
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::api::error as api_error;
+use crate::auth::middleware::AuthUser;
+use crate::services::matching::{ReplicationBatch, ReplicationError, ReplicationStatus, REPLICATION_SECRET_HEADER};
+use crate::AppState;
+
+/// Default batch of commands per request
+const DEFAULT_ENTRIES_LIMIT: usize = 1_000;
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EntriesQuery {
+    /// Last sequence the standby has applied
+    #[serde(default)]
+    pub after: u64,
+    pub limit: Option<usize>,
+    /// How long to wait for a command when none is available yet
+    #[serde(default)]
+    pub wait_ms: u64,
+}
+
+fn replication_error(e: ReplicationError) -> (StatusCode, Json<ErrorResponse>) {
+    let (status, code) = api_error::classify(&e);
+    let error = if api_error::is_client_error(&e) {
+        e.to_string()
+    } else {
+        "Replication request failed".to_string()
+    };
+    (
+        status,
+        Json(ErrorResponse {
+            error,
+            code: code.to_string(),
+        }),
+    )
+}
+
+// ============================================================================
+// Internal Handlers
+// ============================================================================
+
+/// Engine commands after a sequence, for a warm standby - Replication secret only
+/// GET /internal/replication/entries
+pub async fn list_entries(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<EntriesQuery>,
+) -> Result<Json<ReplicationBatch>, (StatusCode, Json<ErrorResponse>)> {
+    let secret = headers.get(REPLICATION_SECRET_HEADER).and_then(|h| h.to_str().ok());
+    if !state.replication.authorize(secret) {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(ErrorResponse {
+                error: "Missing or invalid replication secret".to_string(),
+                code: "REPLICATION_UNAUTHORIZED".to_string(),
+            }),
+        ));
+    }
+
+    state
+        .replication
+        .entries_after(
+            query.after,
+            query.limit.unwrap_or(DEFAULT_ENTRIES_LIMIT),
+            Duration::from_millis(query.wait_ms),
+        )
+        .await
+        .map(Json)
+        .map_err(replication_error)
+}
+
+// ============================================================================
+// Admin Handlers
+// ============================================================================
+
+/// Role and replication lag of this process - Admin only
+/// GET /admin/replication
+pub async fn get_status(State(state): State<Arc<AppState>>) -> Json<ReplicationStatus> {
+    Json(state.replication.status())
+}
+
+/// Promote this warm standby to primary - Admin only
+/// POST /admin/replication/promote
+pub async fn promote(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<ReplicationStatus>, (StatusCode, Json<ErrorResponse>)> {
+    tracing::warn!("Standby promotion requested by {}", auth_user.address);
+    state
+        .replication
+        .promote(&state.db.pool)
+        .await
+        .map(Json)
+        .map_err(replication_error)
+}
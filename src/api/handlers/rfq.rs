@@ -0,0 +1,303 @@
+//! RFQ API Handlers
+//!
+//! Endpoints for requesting quotes on large block trades, quoting as a
+//! registered market maker, and managing the market maker registry.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::auth::middleware::AuthUser;
+use crate::models::market::ShareType;
+use crate::services::matching::Side;
+use crate::services::rfq::{RfqError, RfqQuote, RfqRequest, RfqService};
+use crate::AppState;
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateRfqRequest {
+    pub market_id: Uuid,
+    pub outcome_id: Uuid,
+    pub share_type: ShareType,
+    pub side: Side,
+    pub amount: Decimal,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SubmitQuoteRequest {
+    pub price: Decimal,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterMakerRequest {
+    pub address: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RfqQuoteResponse {
+    pub id: Uuid,
+    pub rfq_id: Uuid,
+    pub maker_address: String,
+    pub price: Decimal,
+    pub status: String,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RfqResponse {
+    pub id: Uuid,
+    pub taker_address: String,
+    pub market_id: Uuid,
+    pub outcome_id: Uuid,
+    pub share_type: String,
+    pub side: String,
+    pub amount: Decimal,
+    pub status: String,
+    pub quote_deadline: i64,
+    pub filled_quote_id: Option<Uuid>,
+    pub filled_price: Option<Decimal>,
+    pub trade_id: Option<Uuid>,
+    pub created_at: i64,
+    pub quotes: Vec<RfqQuoteResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MakersResponse {
+    pub makers: Vec<String>,
+}
+
+impl From<RfqQuote> for RfqQuoteResponse {
+    fn from(quote: RfqQuote) -> Self {
+        Self {
+            id: quote.id,
+            rfq_id: quote.rfq_id,
+            maker_address: quote.maker_address,
+            price: quote.price,
+            status: quote.status,
+            created_at: quote.created_at.timestamp_millis(),
+        }
+    }
+}
+
+fn rfq_response(request: RfqRequest, quotes: Vec<RfqQuote>) -> RfqResponse {
+    RfqResponse {
+        id: request.id,
+        taker_address: request.taker_address,
+        market_id: request.market_id,
+        outcome_id: request.outcome_id,
+        share_type: request.share_type,
+        side: request.side,
+        amount: request.amount,
+        status: request.status,
+        quote_deadline: request.quote_deadline.timestamp_millis(),
+        filled_quote_id: request.filled_quote_id,
+        filled_price: request.filled_price,
+        trade_id: request.trade_id,
+        created_at: request.created_at.timestamp_millis(),
+        quotes: quotes.into_iter().map(Into::into).collect(),
+    }
+}
+
+fn rfq_error(e: RfqError) -> (StatusCode, Json<ErrorResponse>) {
+    let (status, code) = match &e {
+        RfqError::RequestNotFound(_) => (StatusCode::NOT_FOUND, "RFQ_NOT_FOUND"),
+        RfqError::MarketNotFound(_) => (StatusCode::NOT_FOUND, "MARKET_NOT_FOUND"),
+        RfqError::MarketNotActive(_) => (StatusCode::BAD_REQUEST, "MARKET_NOT_ACTIVE"),
+        RfqError::AmountTooSmall(_) => (StatusCode::BAD_REQUEST, "RFQ_AMOUNT_TOO_SMALL"),
+        RfqError::InvalidPrice => (StatusCode::BAD_REQUEST, "INVALID_PRICE"),
+        RfqError::NotMarketMaker(_) => (StatusCode::FORBIDDEN, "NOT_MARKET_MAKER"),
+        RfqError::SelfQuote => (StatusCode::BAD_REQUEST, "SELF_QUOTE"),
+        RfqError::RequestClosed(_) => (StatusCode::CONFLICT, "RFQ_CLOSED"),
+        RfqError::QuoteWindowClosed(_) => (StatusCode::CONFLICT, "QUOTE_WINDOW_CLOSED"),
+        RfqError::NotOwner(_) => (StatusCode::FORBIDDEN, "NOT_RFQ_OWNER"),
+        RfqError::DatabaseError(db) => {
+            tracing::error!("RFQ database error: {}", db);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Database error".to_string(),
+                    code: "DB_ERROR".to_string(),
+                }),
+            );
+        }
+    };
+
+    (
+        status,
+        Json(ErrorResponse {
+            error: e.to_string(),
+            code: code.to_string(),
+        }),
+    )
+}
+
+// ============================================================================
+// Handlers
+// ============================================================================
+
+/// Request quotes for a block trade
+/// POST /rfq
+pub async fn create_request(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(req): Json<CreateRfqRequest>,
+) -> Result<Json<RfqResponse>, (StatusCode, Json<ErrorResponse>)> {
+    require_rfq_feature(&state, &auth_user.address).await?;
+
+    let config = state.live_config.current();
+    let request = RfqService::create_request(
+        &state.db.pool,
+        &state.rfq_sender,
+        &auth_user.address,
+        req.market_id,
+        req.outcome_id,
+        req.share_type,
+        req.side,
+        req.amount,
+        config.rfq_min_amount(),
+        config.rfq_quote_window_ms,
+    )
+    .await
+    .map_err(rfq_error)?;
+
+    Ok(Json(rfq_response(request, Vec::new())))
+}
+
+/// Require the RFQ feature for an account
+async fn require_rfq_feature(state: &AppState, address: &str) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    let enabled = state
+        .features
+        .has_feature(&state.db.pool, address, "rfq")
+        .await
+        .map_err(|e| {
+            tracing::error!("RFQ feature check failed: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Feature check failed".to_string(),
+                    code: "FEATURE_CHECK_FAILED".to_string(),
+                }),
+            )
+        })?;
+    if !enabled {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse {
+                error: "RFQ is not enabled for this account".to_string(),
+                code: "FEATURE_NOT_ENABLED".to_string(),
+            }),
+        ));
+    }
+    Ok(())
+}
+
+/// Get a quote request
+/// GET /rfq/:rfq_id
+///
+/// The taker sees every quote; a market maker sees only their own.
+pub async fn get_request(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(rfq_id): Path<Uuid>,
+) -> Result<Json<RfqResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let caller = auth_user.address.to_lowercase();
+    let (request, quotes) = RfqService::get_request(&state.db.pool, rfq_id).await.map_err(rfq_error)?;
+
+    if request.taker_address == caller {
+        return Ok(Json(rfq_response(request, quotes)));
+    }
+
+    let makers = RfqService::active_makers(&state.db.pool).await.map_err(rfq_error)?;
+    let own_quotes: Vec<RfqQuote> = quotes.into_iter().filter(|q| q.maker_address == caller).collect();
+    if own_quotes.is_empty() && !makers.contains(&caller) {
+        return Err(rfq_error(RfqError::NotOwner(rfq_id)));
+    }
+
+    Ok(Json(rfq_response(request, own_quotes)))
+}
+
+/// Cancel an open quote request
+/// DELETE /rfq/:rfq_id
+pub async fn cancel_request(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(rfq_id): Path<Uuid>,
+) -> Result<Json<RfqResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let request = RfqService::cancel_request(&state.db.pool, &state.rfq_sender, rfq_id, &auth_user.address)
+        .await
+        .map_err(rfq_error)?;
+
+    let (_, quotes) = RfqService::get_request(&state.db.pool, rfq_id).await.map_err(rfq_error)?;
+    Ok(Json(rfq_response(request, quotes)))
+}
+
+/// Quote an open request (registered market makers)
+/// POST /rfq/:rfq_id/quotes
+pub async fn submit_quote(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(rfq_id): Path<Uuid>,
+    Json(req): Json<SubmitQuoteRequest>,
+) -> Result<Json<RfqQuoteResponse>, (StatusCode, Json<ErrorResponse>)> {
+    require_rfq_feature(&state, &auth_user.address).await?;
+
+    let quote = RfqService::submit_quote(&state.db.pool, rfq_id, &auth_user.address, req.price)
+        .await
+        .map_err(rfq_error)?;
+
+    Ok(Json(quote.into()))
+}
+
+/// List active RFQ market makers (admin)
+/// GET /admin/rfq/makers
+pub async fn list_makers(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<MakersResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let makers = RfqService::active_makers(&state.db.pool).await.map_err(rfq_error)?;
+    Ok(Json(MakersResponse { makers }))
+}
+
+/// Register an RFQ market maker (admin)
+/// POST /admin/rfq/makers
+pub async fn register_maker(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<RegisterMakerRequest>,
+) -> Result<Json<MakersResponse>, (StatusCode, Json<ErrorResponse>)> {
+    if !req.address.starts_with("0x") || req.address.len() != 42 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "Invalid address".to_string(),
+                code: "INVALID_ADDRESS".to_string(),
+            }),
+        ));
+    }
+
+    RfqService::register_maker(&state.db.pool, &req.address).await.map_err(rfq_error)?;
+    list_makers(State(state)).await
+}
+
+/// Deactivate an RFQ market maker (admin)
+/// DELETE /admin/rfq/makers/:address
+pub async fn deactivate_maker(
+    State(state): State<Arc<AppState>>,
+    Path(address): Path<String>,
+) -> Result<Json<MakersResponse>, (StatusCode, Json<ErrorResponse>)> {
+    RfqService::deactivate_maker(&state.db.pool, &address).await.map_err(rfq_error)?;
+    list_makers(State(state)).await
+}
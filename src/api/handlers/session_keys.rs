@@ -0,0 +1,144 @@
+//! Session Key Handlers
+//!
+//! Registering, listing and revoking the session keys allowed to sign the
+//! authenticated wallet's trading messages. Keys are managed only from the
+//! wallet's own session; registering one takes the wallet's EIP-712
+//! `AuthorizeSessionKey` signature.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::api::error as api_error;
+use crate::auth::eip712::{verify_session_key_signature, SessionKeyMessage};
+use crate::auth::middleware::AuthUser;
+use crate::auth::server_time::validate_request_timestamp;
+use crate::services::session_keys::{NewSessionKey, SessionKey, SessionKeyError, SessionKeyService};
+use crate::AppState;
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterSessionKeyRequest {
+    #[serde(flatten)]
+    pub key: NewSessionKey,
+    /// Unix seconds, signed
+    pub timestamp: u64,
+    /// Wallet's signature of the `AuthorizeSessionKey` message
+    pub signature: String,
+}
+
+fn session_key_error(e: SessionKeyError) -> (StatusCode, Json<ErrorResponse>) {
+    let (status, code) = api_error::classify(&e);
+    let error = if api_error::is_client_error(&e) {
+        e.to_string()
+    } else {
+        "Session key request failed".to_string()
+    };
+    (
+        status,
+        Json(ErrorResponse {
+            error,
+            code: code.to_string(),
+        }),
+    )
+}
+
+fn bad_request(error: &str, code: &str) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ErrorResponse {
+            error: error.to_string(),
+            code: code.to_string(),
+        }),
+    )
+}
+
+/// Wallet managing its keys; refused with an API key or inside a sub-account
+fn key_owner(auth_user: &AuthUser) -> Result<&str, (StatusCode, Json<ErrorResponse>)> {
+    if auth_user.api_key.is_some() || auth_user.sub_account.is_some() {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse {
+                error: "Session keys are managed from the wallet's own session".to_string(),
+                code: "MAIN_ACCOUNT_REQUIRED".to_string(),
+            }),
+        ));
+    }
+    Ok(&auth_user.address)
+}
+
+// ============================================================================
+// Account Handlers
+// ============================================================================
+
+/// Register a session key authorized by the wallet's signature
+/// POST /account/session-keys
+pub async fn register_session_key(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(req): Json<RegisterSessionKeyRequest>,
+) -> Result<(StatusCode, Json<SessionKey>), (StatusCode, Json<ErrorResponse>)> {
+    let owner = key_owner(&auth_user)?;
+    if !state.config.is_auth_disabled() {
+        if !validate_request_timestamp(req.timestamp, None, &state.config.jwt_secret) {
+            return Err(bad_request("Signed timestamp has expired", "TIMESTAMP_EXPIRED"));
+        }
+        let message = SessionKeyMessage {
+            wallet: owner.to_lowercase(),
+            session_key: req.key.session_key.clone(),
+            scopes: req.key.signed_scopes(),
+            expires_at: req.key.expires_at,
+            timestamp: req.timestamp,
+        };
+        if !verify_session_key_signature(&message, &req.signature, owner).unwrap_or(false) {
+            return Err(bad_request("Invalid authorization signature", "SIGNATURE_INVALID"));
+        }
+    }
+
+    let key = SessionKeyService::register(&state.db.pool, owner, &req.key, &req.signature, Utc::now())
+        .await
+        .map_err(session_key_error)?;
+    Ok((StatusCode::CREATED, Json(key)))
+}
+
+/// Session keys of the authenticated wallet, newest first
+/// GET /account/session-keys
+pub async fn list_session_keys(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<Vec<SessionKey>>, (StatusCode, Json<ErrorResponse>)> {
+    let owner = key_owner(&auth_user)?;
+    SessionKeyService::list(&state.db.pool, owner)
+        .await
+        .map(Json)
+        .map_err(session_key_error)
+}
+
+/// Revoke a session key
+/// DELETE /account/session-keys/:id
+pub async fn revoke_session_key(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<SessionKey>, (StatusCode, Json<ErrorResponse>)> {
+    let owner = key_owner(&auth_user)?;
+    SessionKeyService::revoke(&state.db.pool, owner, id)
+        .await
+        .map(Json)
+        .map_err(session_key_error)
+}
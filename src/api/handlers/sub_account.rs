@@ -0,0 +1,175 @@
+//! Sub-Account API Handlers
+//!
+//! Opening and managing sub-accounts, and internal transfers between a
+//! wallet's main account and its sub-accounts. Only the wallet's own session
+//! (not a sub-account or an API key) can use these endpoints.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::api::error as api_error;
+use crate::auth::middleware::AuthUser;
+use crate::services::sub_account::{SubAccount, SubAccountError, SubAccountService, SubAccountTransfer};
+use crate::AppState;
+
+/// Default and largest page of transfers
+const DEFAULT_TRANSFERS_LIMIT: i64 = 50;
+const MAX_TRANSFERS_LIMIT: i64 = 500;
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateSubAccountRequest {
+    pub label: String,
+    /// Allow trading (default true); read-only otherwise
+    #[serde(default = "default_can_trade")]
+    pub can_trade: bool,
+}
+
+fn default_can_trade() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateSubAccountRequest {
+    pub can_trade: Option<bool>,
+    /// Freeze (true) or reactivate (false) the sub-account
+    pub frozen: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransferRequest {
+    /// Source sub-account (omitted: the main account)
+    pub from: Option<Uuid>,
+    /// Destination sub-account (omitted: the main account)
+    pub to: Option<Uuid>,
+    pub amount: Decimal,
+    /// Token to move (default: the collateral token)
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransfersQuery {
+    pub limit: Option<i64>,
+}
+
+fn sub_account_error(e: SubAccountError) -> (StatusCode, Json<ErrorResponse>) {
+    let (status, code) = api_error::classify(&e);
+    let error = if api_error::is_client_error(&e) {
+        e.to_string()
+    } else {
+        "Sub-account request failed".to_string()
+    };
+    (
+        status,
+        Json(ErrorResponse {
+            error,
+            code: code.to_string(),
+        }),
+    )
+}
+
+/// Owner of the request's main account; refused inside a sub-account or with an API key
+fn main_account(auth_user: &AuthUser) -> Result<&str, (StatusCode, Json<ErrorResponse>)> {
+    if auth_user.sub_account.is_some() || auth_user.api_key.is_some() {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse {
+                error: "Only the wallet's own session can manage its account".to_string(),
+                code: "MAIN_ACCOUNT_REQUIRED".to_string(),
+            }),
+        ));
+    }
+    Ok(&auth_user.address)
+}
+
+// ============================================================================
+// Account Handlers
+// ============================================================================
+
+/// Open a sub-account
+/// POST /account/sub-accounts
+pub async fn create_sub_account(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(req): Json<CreateSubAccountRequest>,
+) -> Result<Json<SubAccount>, (StatusCode, Json<ErrorResponse>)> {
+    let owner = main_account(&auth_user)?;
+    SubAccountService::create(&state.db.pool, owner, &req.label, req.can_trade)
+        .await
+        .map(Json)
+        .map_err(sub_account_error)
+}
+
+/// Sub-accounts of the authenticated wallet
+/// GET /account/sub-accounts
+pub async fn list_sub_accounts(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<Vec<SubAccount>>, (StatusCode, Json<ErrorResponse>)> {
+    let owner = main_account(&auth_user)?;
+    SubAccountService::list(&state.db.pool, owner)
+        .await
+        .map(Json)
+        .map_err(sub_account_error)
+}
+
+/// Change a sub-account's permissions, or freeze it
+/// PUT /account/sub-accounts/:id
+pub async fn update_sub_account(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<UpdateSubAccountRequest>,
+) -> Result<Json<SubAccount>, (StatusCode, Json<ErrorResponse>)> {
+    let owner = main_account(&auth_user)?;
+    SubAccountService::update(&state.db.pool, owner, id, req.can_trade, req.frozen)
+        .await
+        .map(Json)
+        .map_err(sub_account_error)
+}
+
+/// Move collateral between the main account and sub-accounts
+/// POST /account/sub-accounts/transfer
+pub async fn transfer(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(req): Json<TransferRequest>,
+) -> Result<Json<SubAccountTransfer>, (StatusCode, Json<ErrorResponse>)> {
+    let owner = main_account(&auth_user)?;
+    let token = req.token.as_deref().unwrap_or(state.config.collateral_symbol());
+    SubAccountService::transfer(&state.db.pool, owner, req.from, req.to, token, req.amount)
+        .await
+        .map(Json)
+        .map_err(sub_account_error)
+}
+
+/// Internal transfers of the authenticated wallet, newest first
+/// GET /account/sub-accounts/transfers
+pub async fn list_transfers(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Query(query): Query<TransfersQuery>,
+) -> Result<Json<Vec<SubAccountTransfer>>, (StatusCode, Json<ErrorResponse>)> {
+    let owner = main_account(&auth_user)?;
+    let limit = query.limit.unwrap_or(DEFAULT_TRANSFERS_LIMIT).clamp(1, MAX_TRANSFERS_LIMIT);
+    SubAccountService::transfers(&state.db.pool, owner, limit)
+        .await
+        .map(Json)
+        .map_err(sub_account_error)
+}
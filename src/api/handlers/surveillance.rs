@@ -0,0 +1,102 @@
+//! Trade Surveillance API Handlers
+//!
+//! Admin endpoints for the surveillance alert review queue.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::auth::middleware::AuthUser;
+use crate::services::surveillance::{AlertFilter, SurveillanceAlert, SurveillanceError, SurveillanceService};
+use crate::AppState;
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AlertsQuery {
+    #[serde(flatten)]
+    pub filter: AlertFilter,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AlertsResponse {
+    pub alerts: Vec<SurveillanceAlert>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewAlertRequest {
+    /// open | dismissed | escalated
+    pub status: String,
+    pub note: Option<String>,
+}
+
+fn surveillance_error(e: SurveillanceError) -> (StatusCode, Json<ErrorResponse>) {
+    let (status, code) = match &e {
+        SurveillanceError::NotFound(_) => (StatusCode::NOT_FOUND, "ALERT_NOT_FOUND"),
+        SurveillanceError::InvalidStatus(_) => (StatusCode::BAD_REQUEST, "INVALID_STATUS"),
+        _ => {
+            tracing::error!("Surveillance error: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "DB_ERROR")
+        }
+    };
+    (
+        status,
+        Json(ErrorResponse {
+            error: e.to_string(),
+            code: code.to_string(),
+        }),
+    )
+}
+
+// ============================================================================
+// Admin Handlers
+// ============================================================================
+
+/// Surveillance alerts, newest first - Admin only
+/// GET /admin/surveillance/alerts
+pub async fn list_alerts(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<AlertsQuery>,
+) -> Result<Json<AlertsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let alerts = SurveillanceService::list_alerts(
+        &state.db.pool,
+        &query.filter,
+        query.limit.unwrap_or(50).clamp(1, 200),
+        query.offset.unwrap_or(0).max(0),
+    )
+    .await
+    .map_err(surveillance_error)?;
+
+    Ok(Json(AlertsResponse { alerts }))
+}
+
+/// Record a review decision on an alert - Admin only
+/// POST /admin/surveillance/alerts/:id/review
+pub async fn review_alert(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<ReviewAlertRequest>,
+) -> Result<Json<SurveillanceAlert>, (StatusCode, Json<ErrorResponse>)> {
+    let alert = SurveillanceService::review_alert(&state.db.pool, id, &req.status, &auth_user.address, req.note.as_deref())
+        .await
+        .map_err(surveillance_error)?;
+
+    tracing::info!("Surveillance alert {} marked {} by {}", id, alert.status, auth_user.address);
+    Ok(Json(alert))
+}
@@ -0,0 +1,157 @@
+//! System Status API Handlers
+//!
+//! Public status page data (component health and incident history), the API
+//! changelog, and the admin endpoints that open and update incidents.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::api::deprecation::{ChangelogEntry, Deprecation, CHANGELOG, DEPRECATIONS};
+use crate::auth::middleware::AuthUser;
+use crate::services::incidents::{
+    component_statuses, overall_status, ComponentStatus, Incident, IncidentChange, IncidentError, IncidentService,
+    NewIncident,
+};
+use crate::AppState;
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SystemHealthResponse {
+    /// `operational` or the worst component status
+    pub status: &'static str,
+    pub components: Vec<ComponentStatus>,
+    /// Unresolved incidents with their timelines
+    pub incidents: Vec<Incident>,
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IncidentsQuery {
+    /// History window in days (default 90)
+    pub days: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IncidentsResponse {
+    pub incidents: Vec<Incident>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChangelogResponse {
+    /// API changes, newest first
+    pub entries: &'static [ChangelogEntry],
+    /// Routes and fields currently deprecated
+    pub deprecations: &'static [Deprecation],
+}
+
+fn incident_error(e: IncidentError) -> (StatusCode, Json<ErrorResponse>) {
+    let (status, code) = match &e {
+        IncidentError::NotFound(_) => (StatusCode::NOT_FOUND, "INCIDENT_NOT_FOUND"),
+        IncidentError::AlreadyResolved(_) => (StatusCode::CONFLICT, "INCIDENT_RESOLVED"),
+        IncidentError::NoComponents => (StatusCode::BAD_REQUEST, "NO_COMPONENTS"),
+        IncidentError::DatabaseError(_) => {
+            tracing::error!("Incident error: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "DB_ERROR")
+        }
+    };
+    (
+        status,
+        Json(ErrorResponse {
+            error: e.to_string(),
+            code: code.to_string(),
+        }),
+    )
+}
+
+// ============================================================================
+// Public Handlers
+// ============================================================================
+
+/// Current status of every component
+/// GET /system/health
+pub async fn get_health(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<SystemHealthResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let incidents = IncidentService::active(&state.db.pool).await.map_err(incident_error)?;
+    let components = component_statuses(&incidents);
+
+    Ok(Json(SystemHealthResponse {
+        status: overall_status(&components),
+        components,
+        incidents,
+        timestamp: chrono::Utc::now().timestamp_millis(),
+    }))
+}
+
+/// Incident history, newest first
+/// GET /system/incidents
+pub async fn list_incidents(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<IncidentsQuery>,
+) -> Result<Json<IncidentsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let incidents = IncidentService::history(
+        &state.db.pool,
+        query.days.unwrap_or(90).clamp(1, 365),
+        query.limit.unwrap_or(50).clamp(1, 200),
+    )
+    .await
+    .map_err(incident_error)?;
+
+    Ok(Json(IncidentsResponse { incidents }))
+}
+
+/// API changelog and active deprecations
+/// GET /changelog
+pub async fn get_changelog() -> Json<ChangelogResponse> {
+    Json(ChangelogResponse {
+        entries: CHANGELOG,
+        deprecations: DEPRECATIONS,
+    })
+}
+
+// ============================================================================
+// Admin Handlers
+// ============================================================================
+
+/// Open an incident - Admin only
+/// POST /admin/incidents
+pub async fn open_incident(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(req): Json<NewIncident>,
+) -> Result<Json<Incident>, (StatusCode, Json<ErrorResponse>)> {
+    let incident = IncidentService::open(&state.db.pool, &req, &auth_user.address)
+        .await
+        .map_err(incident_error)?;
+    Ok(Json(incident))
+}
+
+/// Post an update to an incident (status `resolved` closes it) - Admin only
+/// POST /admin/incidents/:id/updates
+pub async fn update_incident(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<IncidentChange>,
+) -> Result<Json<Incident>, (StatusCode, Json<ErrorResponse>)> {
+    let incident = IncidentService::update(&state.db.pool, id, &req, &auth_user.address)
+        .await
+        .map_err(incident_error)?;
+    Ok(Json(incident))
+}
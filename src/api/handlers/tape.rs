@@ -0,0 +1,174 @@
+//! Trade Tape Export API Handlers
+//!
+//! Partners with the `trade_tape` feature request a symbol's tape for a UTC
+//! day, poll the export job, then download the signed manifest and the
+//! compressed NDJSON tape.
+
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::auth::middleware::AuthUser;
+use crate::services::tape::{TapeError, TapeExport};
+use crate::AppState;
+
+/// Feature accounts need to pull trade tapes
+const TAPE_FEATURE: &str = "trade_tape";
+
+/// Response header carrying the manifest's HMAC-SHA256 signature
+const SIGNATURE_HEADER: &str = "x-tape-signature";
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TapeExportRequest {
+    /// `{market_id}:{outcome_id}:{share_type}`
+    pub symbol: String,
+    /// UTC day, `YYYY-MM-DD`
+    pub date: NaiveDate,
+}
+
+type ApiError = (StatusCode, Json<ErrorResponse>);
+
+fn error(status: StatusCode, message: impl Into<String>, code: &str) -> ApiError {
+    (
+        status,
+        Json(ErrorResponse {
+            error: message.into(),
+            code: code.to_string(),
+        }),
+    )
+}
+
+fn tape_error(e: TapeError) -> ApiError {
+    let (status, code) = match &e {
+        TapeError::InvalidSymbol(_) => (StatusCode::BAD_REQUEST, "INVALID_SYMBOL"),
+        TapeError::DayNotFinished(_) => (StatusCode::BAD_REQUEST, "DAY_NOT_FINISHED"),
+        TapeError::NotFound => (StatusCode::NOT_FOUND, "EXPORT_NOT_FOUND"),
+        TapeError::NotReady => (StatusCode::CONFLICT, "EXPORT_NOT_READY"),
+        _ => {
+            tracing::error!("Trade tape error: {}", e);
+            return error(StatusCode::INTERNAL_SERVER_ERROR, "Trade tape export error", "TAPE_ERROR");
+        }
+    };
+    error(status, e.to_string(), code)
+}
+
+/// Require the trade tape feature for an account
+async fn require_tape_feature(state: &AppState, address: &str) -> Result<(), ApiError> {
+    let enabled = state
+        .features
+        .has_feature(&state.db.pool, address, TAPE_FEATURE)
+        .await
+        .map_err(|e| {
+            tracing::error!("Trade tape feature check failed: {}", e);
+            error(StatusCode::INTERNAL_SERVER_ERROR, "Feature check failed", "FEATURE_CHECK_FAILED")
+        })?;
+    if !enabled {
+        return Err(error(
+            StatusCode::FORBIDDEN,
+            "Trade tape exports are not enabled for this account",
+            "FEATURE_NOT_ENABLED",
+        ));
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Handlers
+// ============================================================================
+
+/// Request a trade tape export
+/// POST /exports/trade-tape
+///
+/// Returns 202 with the queued job, or 200 with the existing job when the
+/// symbol and day were already requested.
+pub async fn request_export(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(req): Json<TapeExportRequest>,
+) -> Result<(StatusCode, Json<TapeExport>), ApiError> {
+    require_tape_feature(&state, &auth_user.address).await?;
+
+    let job = state
+        .tape
+        .request(&state.db.pool, &req.symbol, req.date, &auth_user.address)
+        .await
+        .map_err(tape_error)?;
+
+    let status = if job.status == "pending" && job.started_at.is_none() {
+        StatusCode::ACCEPTED
+    } else {
+        StatusCode::OK
+    };
+    tracing::info!("Trade tape {} for {} {} requested by {}", job.id, job.symbol, job.trade_date, auth_user.address);
+    Ok((status, Json(job)))
+}
+
+/// Poll an export job
+/// GET /exports/trade-tape/:export_id
+pub async fn get_export(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(export_id): Path<Uuid>,
+) -> Result<Json<TapeExport>, ApiError> {
+    require_tape_feature(&state, &auth_user.address).await?;
+
+    let job = state.tape.get(&state.db.pool, export_id).await.map_err(tape_error)?;
+    Ok(Json(job))
+}
+
+/// Download an export's manifest; the signature is in the `X-Tape-Signature` header
+/// GET /exports/trade-tape/:export_id/manifest
+pub async fn get_manifest(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(export_id): Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    require_tape_feature(&state, &auth_user.address).await?;
+
+    let (manifest, signature) = state.tape.manifest(&state.db.pool, export_id).await.map_err(tape_error)?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/json".to_string()),
+            (header::HeaderName::from_static(SIGNATURE_HEADER), signature.unwrap_or_default()),
+        ],
+        manifest,
+    ))
+}
+
+/// Download an export's compressed NDJSON tape
+/// GET /exports/trade-tape/:export_id/download
+pub async fn download_export(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(export_id): Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    require_tape_feature(&state, &auth_user.address).await?;
+
+    let (job, data) = state.tape.data(&state.db.pool, export_id).await.map_err(tape_error)?;
+    let filename = format!("trades-{}-{}.ndjson.gz", job.symbol.replace(':', "_"), job.trade_date);
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/gzip".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+            (header::ETAG, format!("\"{}\"", job.data_sha256.unwrap_or_default())),
+        ],
+        data,
+    ))
+}
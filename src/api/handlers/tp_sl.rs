@@ -0,0 +1,101 @@
+//! Position TP/SL API Handlers
+//!
+//! Lets accounts place multi-leg take-profit / stop-loss sets on their
+//! positions and follow how each leg was executed by the keeper.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::auth::middleware::AuthUser;
+use crate::services::tp_sl::{PositionTpSl, SetTpSlRequest, TpSlError, TpSlService};
+use crate::AppState;
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CancelTpSlResponse {
+    /// Pending legs cancelled
+    pub cancelled: u64,
+}
+
+fn tp_sl_error(e: TpSlError) -> (StatusCode, Json<ErrorResponse>) {
+    let (status, code) = match &e {
+        TpSlError::PositionNotFound(_) => (StatusCode::NOT_FOUND, "POSITION_NOT_FOUND"),
+        TpSlError::InvalidLegs(_) => (StatusCode::BAD_REQUEST, "INVALID_TP_SL"),
+        TpSlError::DatabaseError(_) => {
+            tracing::error!("TP/SL error: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Database error".to_string(),
+                    code: "DB_ERROR".to_string(),
+                }),
+            );
+        }
+    };
+    (
+        status,
+        Json(ErrorResponse {
+            error: e.to_string(),
+            code: code.to_string(),
+        }),
+    )
+}
+
+// ============================================================================
+// Account Handlers
+// ============================================================================
+
+/// TP/SL legs of a position and their status
+/// GET /positions/:position_id/tp-sl
+pub async fn get_tp_sl(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(position_id): Path<Uuid>,
+) -> Result<Json<PositionTpSl>, (StatusCode, Json<ErrorResponse>)> {
+    let status = TpSlService::status(&state.db.pool, &state.matching_engine, position_id, &auth_user.address)
+        .await
+        .map_err(tp_sl_error)?;
+    Ok(Json(status))
+}
+
+/// Place a TP/SL set on a position, replacing the pending legs of the previous one
+/// PUT /positions/:position_id/tp-sl
+pub async fn set_tp_sl(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(position_id): Path<Uuid>,
+    Json(req): Json<SetTpSlRequest>,
+) -> Result<Json<PositionTpSl>, (StatusCode, Json<ErrorResponse>)> {
+    let status = TpSlService::set(&state.db.pool, &state.matching_engine, position_id, &auth_user.address, &req)
+        .await
+        .map_err(tp_sl_error)?;
+    Ok(Json(status))
+}
+
+/// Cancel a position's pending TP/SL legs
+/// DELETE /positions/:position_id/tp-sl
+pub async fn cancel_tp_sl(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(position_id): Path<Uuid>,
+) -> Result<Json<CancelTpSlResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let cancelled = TpSlService::cancel(&state.db.pool, position_id, &auth_user.address)
+        .await
+        .map_err(tp_sl_error)?;
+    Ok(Json(CancelTpSlResponse { cancelled }))
+}
@@ -0,0 +1,295 @@
+//! LP Vault API Handlers
+//!
+//! Endpoints for listing vaults, depositing into / withdrawing from the
+//! market-making vaults, and querying a user's vault position.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::auth::middleware::AuthUser;
+use crate::services::vault::{Vault, VaultError, VaultLedgerEntry, VaultNav, VaultService};
+use crate::AppState;
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VaultInfo {
+    pub id: Uuid,
+    pub name: String,
+    pub token: String,
+    pub operator_address: String,
+    pub status: String,
+    pub epoch_seconds: i64,
+    pub lock_epochs: i32,
+    pub current_epoch: i64,
+    pub total_shares: Decimal,
+    pub cash: Decimal,
+    pub positions_value: Decimal,
+    pub nav: Decimal,
+    pub nav_per_share: Decimal,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VaultsResponse {
+    pub vaults: Vec<VaultInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VaultDepositRequest {
+    pub amount: Decimal,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VaultWithdrawRequest {
+    pub shares: Decimal,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VaultLedgerResponse {
+    pub vault_id: Uuid,
+    pub entry_type: String,
+    pub amount: Decimal,
+    pub shares: Decimal,
+    pub nav_per_share: Decimal,
+    pub epoch: i64,
+    pub unlock_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VaultPositionResponse {
+    pub vault_id: Uuid,
+    pub shares: Decimal,
+    pub locked_shares: Decimal,
+    pub withdrawable_shares: Decimal,
+    pub cost_basis: Decimal,
+    pub value: Decimal,
+    pub pnl: Decimal,
+    pub next_unlock_at: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateVaultRequest {
+    pub name: String,
+    pub operator_address: String,
+    pub token: Option<String>,
+    /// Epoch length in seconds (default: 7 days)
+    pub epoch_seconds: Option<i64>,
+    /// Epochs deposits stay locked (default: 1)
+    pub lock_epochs: Option<i32>,
+}
+
+fn vault_info(vault: Vault, nav: VaultNav) -> VaultInfo {
+    VaultInfo {
+        id: vault.id,
+        name: vault.name,
+        token: vault.token,
+        operator_address: vault.operator_address,
+        status: vault.status,
+        epoch_seconds: vault.epoch_seconds,
+        lock_epochs: vault.lock_epochs,
+        current_epoch: nav.epoch,
+        total_shares: nav.total_shares,
+        cash: nav.cash,
+        positions_value: nav.positions_value,
+        nav: nav.nav,
+        nav_per_share: nav.nav_per_share,
+        created_at: vault.created_at.timestamp_millis(),
+    }
+}
+
+impl From<VaultLedgerEntry> for VaultLedgerResponse {
+    fn from(entry: VaultLedgerEntry) -> Self {
+        Self {
+            vault_id: entry.vault_id,
+            entry_type: entry.entry_type.to_string(),
+            amount: entry.amount,
+            shares: entry.shares,
+            nav_per_share: entry.nav_per_share,
+            epoch: entry.epoch,
+            unlock_at: entry.unlock_at.map(|t| t.timestamp_millis()),
+        }
+    }
+}
+
+fn vault_error(e: VaultError) -> (StatusCode, Json<ErrorResponse>) {
+    let (status, code) = match &e {
+        VaultError::VaultNotFound(_) => (StatusCode::NOT_FOUND, "VAULT_NOT_FOUND"),
+        VaultError::VaultInactive(_) => (StatusCode::BAD_REQUEST, "VAULT_INACTIVE"),
+        VaultError::InvalidAmount => (StatusCode::BAD_REQUEST, "INVALID_AMOUNT"),
+        VaultError::InsufficientBalance => (StatusCode::BAD_REQUEST, "INSUFFICIENT_BALANCE"),
+        VaultError::InsufficientShares => (StatusCode::BAD_REQUEST, "INSUFFICIENT_SHARES"),
+        VaultError::SharesLocked(_) => (StatusCode::BAD_REQUEST, "SHARES_LOCKED"),
+        VaultError::InsufficientLiquidity(_) => (StatusCode::CONFLICT, "INSUFFICIENT_VAULT_LIQUIDITY"),
+        VaultError::Insolvent(_) => (StatusCode::BAD_REQUEST, "VAULT_INSOLVENT"),
+        VaultError::DatabaseError(db) => {
+            tracing::error!("Vault database error: {}", db);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Database error".to_string(),
+                    code: "DB_ERROR".to_string(),
+                }),
+            );
+        }
+    };
+
+    (
+        status,
+        Json(ErrorResponse {
+            error: e.to_string(),
+            code: code.to_string(),
+        }),
+    )
+}
+
+// ============================================================================
+// Handlers
+// ============================================================================
+
+/// List vaults with current NAV
+/// GET /vaults
+pub async fn list_vaults(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<VaultsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let vaults = VaultService::list_vaults(&state.db.pool).await.map_err(vault_error)?;
+
+    let mut infos = Vec::with_capacity(vaults.len());
+    for vault in vaults {
+        let nav = VaultService::compute_nav(&state.db.pool, &state.matching_engine, &vault)
+            .await
+            .map_err(vault_error)?;
+        infos.push(vault_info(vault, nav));
+    }
+
+    Ok(Json(VaultsResponse { vaults: infos }))
+}
+
+/// Get a vault with current NAV
+/// GET /vaults/:vault_id
+pub async fn get_vault(
+    State(state): State<Arc<AppState>>,
+    Path(vault_id): Path<Uuid>,
+) -> Result<Json<VaultInfo>, (StatusCode, Json<ErrorResponse>)> {
+    let vault = VaultService::get_vault(&state.db.pool, vault_id).await.map_err(vault_error)?;
+    let nav = VaultService::compute_nav(&state.db.pool, &state.matching_engine, &vault)
+        .await
+        .map_err(vault_error)?;
+
+    Ok(Json(vault_info(vault, nav)))
+}
+
+/// Get the caller's position in a vault
+/// GET /vaults/:vault_id/position
+pub async fn get_position(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(vault_id): Path<Uuid>,
+) -> Result<Json<VaultPositionResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let position = VaultService::get_position(&state.db.pool, &state.matching_engine, vault_id, &auth_user.address)
+        .await
+        .map_err(vault_error)?;
+
+    Ok(Json(VaultPositionResponse {
+        vault_id: position.vault_id,
+        shares: position.shares,
+        locked_shares: position.locked_shares,
+        withdrawable_shares: position.shares - position.locked_shares,
+        cost_basis: position.cost_basis,
+        value: position.value,
+        pnl: position.value - position.cost_basis,
+        next_unlock_at: position.next_unlock_at.map(|t| t.timestamp_millis()),
+    }))
+}
+
+/// Deposit collateral into a vault
+/// POST /vaults/:vault_id/deposit
+pub async fn deposit(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(vault_id): Path<Uuid>,
+    Json(req): Json<VaultDepositRequest>,
+) -> Result<Json<VaultLedgerResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let entry = VaultService::deposit(&state.db.pool, &state.matching_engine, vault_id, &auth_user.address, req.amount)
+        .await
+        .map_err(vault_error)?;
+
+    Ok(Json(entry.into()))
+}
+
+/// Withdraw from a vault by burning shares
+/// POST /vaults/:vault_id/withdraw
+pub async fn withdraw(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(vault_id): Path<Uuid>,
+    Json(req): Json<VaultWithdrawRequest>,
+) -> Result<Json<VaultLedgerResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let entry = VaultService::withdraw(&state.db.pool, &state.matching_engine, vault_id, &auth_user.address, req.shares)
+        .await
+        .map_err(vault_error)?;
+
+    Ok(Json(entry.into()))
+}
+
+/// Create a vault (admin)
+/// POST /admin/vaults
+pub async fn create_vault(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<CreateVaultRequest>,
+) -> Result<Json<VaultInfo>, (StatusCode, Json<ErrorResponse>)> {
+    if !req.operator_address.starts_with("0x") || req.operator_address.len() != 42 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "Invalid operator_address".to_string(),
+                code: "INVALID_ADDRESS".to_string(),
+            }),
+        ));
+    }
+
+    let epoch_seconds = req.epoch_seconds.unwrap_or(7 * 24 * 3600);
+    let lock_epochs = req.lock_epochs.unwrap_or(1);
+    if epoch_seconds <= 0 || lock_epochs < 0 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "epoch_seconds must be positive and lock_epochs non-negative".to_string(),
+                code: "INVALID_EPOCH".to_string(),
+            }),
+        ));
+    }
+
+    let token = req.token.unwrap_or_else(|| state.config.collateral_symbol().to_string());
+    let vault = VaultService::create_vault(
+        &state.db.pool,
+        &req.name,
+        &token,
+        &req.operator_address,
+        epoch_seconds,
+        lock_epochs,
+    )
+    .await
+    .map_err(vault_error)?;
+
+    let nav = VaultService::compute_nav(&state.db.pool, &state.matching_engine, &vault)
+        .await
+        .map_err(vault_error)?;
+
+    Ok(Json(vault_info(vault, nav)))
+}
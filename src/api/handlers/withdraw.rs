@@ -13,7 +13,16 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::api::error as api_error;
+use crate::api::middleware::idempotency::IdempotencyCommit;
+use crate::auth::eip712::{verify_withdraw_signature, WithdrawMessage};
 use crate::auth::middleware::AuthUser;
+use crate::auth::replay::{check_replay, SignedFields};
+use crate::services::errors::ServiceError;
+use crate::services::ledger::{BalanceChange, LedgerReason, LedgerService};
+use crate::services::treasury::{TreasuryService, TreasurySource};
+use crate::services::withdrawal_fees::{WithdrawalFeeError, WithdrawalFeeService, WithdrawalQuote};
+use crate::services::withdrawal_risk::{ReviewDecision, WithdrawalRiskError, WithdrawalRiskService};
 use crate::AppState;
 
 // ============================================================================
@@ -24,6 +33,29 @@ use crate::AppState;
 pub struct WithdrawRequest {
     pub token: String,
     pub amount: Decimal,
+    /// Fee quote from POST /withdraw/quote; required for tokens with a fee policy
+    pub quote_id: Option<Uuid>,
+    /// Chain to pay out on (default: the primary chain)
+    #[serde(default)]
+    pub chain_id: Option<u64>,
+    /// Wallet's EIP-712 `Withdraw` signature; required with `require_withdraw_signature`
+    #[serde(default)]
+    pub signature: Option<String>,
+    /// Signed timestamp (unix seconds)
+    #[serde(default)]
+    pub timestamp: u64,
+    /// Server time token from GET /time (for clients with skewed clocks)
+    #[serde(default)]
+    pub timestamp_token: Option<String>,
+    /// Trading nonce from GET /auth/nonce/trading, signed when present
+    #[serde(default)]
+    pub nonce: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuoteWithdrawRequest {
+    pub token: String,
+    pub amount: Decimal,
 }
 
 #[derive(Debug, Deserialize)]
@@ -43,8 +75,13 @@ pub struct ErrorResponse {
 #[derive(Debug, Serialize)]
 pub struct WithdrawResponse {
     pub withdraw_id: String,
+    pub chain_id: u64,
     pub token: String,
     pub amount: String,
+    /// Withdrawal fee, included in `amount`
+    pub fee: String,
+    /// Amount sent on chain (`amount - fee`)
+    pub net_amount: String,
     pub status: String,
     pub created_at: i64,
 }
@@ -57,26 +94,185 @@ pub struct WithdrawHistoryResponse {
 #[derive(Debug, Serialize)]
 pub struct WithdrawHistoryRecord {
     pub id: String,
+    pub chain_id: u64,
     pub token: String,
     pub amount: Decimal,
+    pub fee: Decimal,
     pub tx_hash: Option<String>,
     pub status: String,
     pub created_at: i64,
 }
 
+#[derive(sqlx::FromRow)]
+struct WithdrawalRow {
+    id: Uuid,
+    chain_id: Option<i64>,
+    token: String,
+    amount: Decimal,
+    fee: Decimal,
+    tx_hash: Option<String>,
+    status: String,
+    created_at: DateTime<Utc>,
+}
+
+impl WithdrawalRow {
+    /// Rows without a chain predate multi-chain support: primary chain
+    fn into_record(self, primary_chain: u64) -> WithdrawHistoryRecord {
+        WithdrawHistoryRecord {
+            id: self.id.to_string(),
+            chain_id: self.chain_id.map_or(primary_chain, |id| id as u64),
+            token: self.token,
+            amount: self.amount,
+            fee: self.fee,
+            tx_hash: self.tx_hash,
+            status: self.status,
+            created_at: self.created_at.timestamp_millis(),
+        }
+    }
+}
+
 // ============================================================================
 // Handlers
 // ============================================================================
 
+fn fee_error(e: WithdrawalFeeError) -> (StatusCode, Json<ErrorResponse>) {
+    let (status, _) = api_error::classify(&e);
+    let error = if api_error::is_client_error(&e) {
+        e.to_string()
+    } else {
+        "Withdrawal fee lookup failed".to_string()
+    };
+    (status, Json(ErrorResponse { error }))
+}
+
+fn risk_error(e: WithdrawalRiskError) -> (StatusCode, Json<ErrorResponse>) {
+    let (status, _) = api_error::classify(&e);
+    let error = if api_error::is_client_error(&e) {
+        e.to_string()
+    } else {
+        "Withdrawal risk check failed".to_string()
+    };
+    (status, Json(ErrorResponse { error }))
+}
+
+/// Map a database error to its classified status, logging `context` and
+/// showing the client `message`
+fn db_error(context: &'static str, message: &'static str) -> impl Fn(sqlx::Error) -> (StatusCode, Json<ErrorResponse>) {
+    move |e| {
+        tracing::error!("{}: {}", context, e);
+        (
+            api_error::status(e.kind()),
+            Json(ErrorResponse {
+                error: message.to_string(),
+            }),
+        )
+    }
+}
+
+/// Quote the fee of a withdrawal; the fee is locked to the quote id until it expires
+/// POST /withdraw/quote
+pub async fn quote_withdraw(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(req): Json<QuoteWithdrawRequest>,
+) -> Result<Json<WithdrawalQuote>, (StatusCode, Json<ErrorResponse>)> {
+    if req.amount <= Decimal::ZERO {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "Amount must be positive".to_string(),
+            }),
+        ));
+    }
+
+    let quote = WithdrawalFeeService::quote(
+        &state.db.pool,
+        &auth_user.address,
+        &req.token,
+        req.amount,
+        state.gas_oracle.multiplier(),
+        state.live_config.current().withdrawal_quote_ttl_secs as i64,
+    )
+    .await
+    .map_err(fee_error)?;
+
+    Ok(Json(quote))
+}
+
+/// Verify a withdrawal's wallet signature and use it up; unsigned requests
+/// pass unless `require_withdraw_signature` is set
+async fn check_withdraw_signature(
+    state: &AppState,
+    user_address: &str,
+    req: &WithdrawRequest,
+) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    let rejected = |status: StatusCode, error: String| (status, Json(ErrorResponse { error }));
+    let Some(signature) = &req.signature else {
+        if state.live_config.current().require_withdraw_signature {
+            return Err(rejected(StatusCode::BAD_REQUEST, "A signed withdrawal is required".to_string()));
+        }
+        return Ok(());
+    };
+
+    let message = WithdrawMessage {
+        wallet: user_address.to_string(),
+        token: req.token.clone(),
+        amount: req.amount.to_string(),
+        nonce: req.nonce,
+        timestamp: req.timestamp,
+    };
+    if !verify_withdraw_signature(&message, signature, user_address).unwrap_or(false) {
+        return Err(rejected(StatusCode::BAD_REQUEST, "Invalid withdrawal signature".to_string()));
+    }
+    let fields = SignedFields {
+        timestamp: req.timestamp,
+        timestamp_token: req.timestamp_token.as_deref(),
+        nonce: req.nonce,
+        signed_hash: Some(message.struct_hash()),
+    };
+    check_replay(state, user_address, fields).await.map_err(|e| {
+        if e.is_internal() {
+            tracing::error!("Withdrawal replay check failed: {}", e);
+            rejected(StatusCode::INTERNAL_SERVER_ERROR, "Failed to check the withdrawal signature".to_string())
+        } else {
+            rejected(StatusCode::BAD_REQUEST, e.to_string())
+        }
+    })
+}
+
 /// Request a withdrawal
 /// POST /withdraw
 pub async fn request_withdraw(
     State(state): State<Arc<AppState>>,
     Extension(auth_user): Extension<AuthUser>,
+    commit: Option<Extension<IdempotencyCommit>>,
     Json(req): Json<WithdrawRequest>,
 ) -> Result<Json<WithdrawResponse>, (StatusCode, Json<ErrorResponse>)> {
     let user_address = auth_user.address.to_lowercase();
 
+    // Sub-account collateral goes back to the main account by internal transfer
+    if auth_user.sub_account.is_some() {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse {
+                error: "Withdrawals are not available from sub-accounts".to_string(),
+            }),
+        ));
+    }
+    if auth_user.api_key.is_some() {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse {
+                error: "Withdrawals cannot be requested with an API key".to_string(),
+            }),
+        ));
+    }
+
+    // Signed withdrawals are accepted once, while their timestamp is valid
+    if !state.config.is_auth_disabled() {
+        check_withdraw_signature(&state, &user_address, &req).await?;
+    }
+
     // Validate amount
     if req.amount <= Decimal::ZERO {
         return Err((
@@ -87,6 +283,13 @@ pub async fn request_withdraw(
         ));
     }
 
+    // Chain to pay out on
+    let chain_id = state
+        .chains
+        .for_token(req.chain_id, &req.token)
+        .map(|chain| chain.chain_id)
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e.to_string() })))?;
+
     // Check user balance
     let balance: Option<(Decimal,)> = sqlx::query_as(
         "SELECT available FROM balances WHERE user_address = $1 AND token = $2",
@@ -95,15 +298,7 @@ pub async fn request_withdraw(
     .bind(&req.token)
     .fetch_optional(&state.db.pool)
     .await
-    .map_err(|e| {
-        tracing::error!("Failed to check balance: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: "Failed to check balance".to_string(),
-            }),
-        )
-    })?;
+    .map_err(db_error("Failed to check balance", "Failed to check balance"))?;
 
     let available = balance.map(|(b,)| b).unwrap_or(Decimal::ZERO);
     if available < req.amount {
@@ -115,89 +310,122 @@ pub async fn request_withdraw(
         ));
     }
 
-    // Create withdrawal record and freeze funds in a transaction
-    let withdraw_id = Uuid::new_v4();
-    let mut tx = state.db.pool.begin().await.map_err(|e| {
-        tracing::error!("Failed to start transaction: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
+    // Tokens with a fee policy need a quote
+    let policy = WithdrawalFeeService::get_policy(&state.db.pool, &req.token)
+        .await
+        .map_err(fee_error)?;
+    if policy.is_some() && req.quote_id.is_none() {
+        return Err((
+            StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
-                error: "Failed to process withdrawal".to_string(),
+                error: "A fee quote is required: POST /withdraw/quote".to_string(),
             }),
-        )
-    })?;
+        ));
+    }
+
+    // Create withdrawal record and freeze funds in a transaction
+    let withdraw_id = Uuid::new_v4();
+    let mut tx = state.db.pool.begin().await.map_err(db_error("Failed to start transaction", "Failed to process withdrawal"))?;
+
+    // Risk checks: over the daily limit is refused, large withdrawals and
+    // withdrawals right after a large deposit wait for review
+    let policy = state.live_config.current().withdrawal_risk_policy();
+    let (assessment, inputs) =
+        WithdrawalRiskService::assess(&mut tx, &policy, &user_address, &req.token, req.amount, Utc::now())
+            .await
+            .map_err(risk_error)?;
+    if assessment.decision == ReviewDecision::Rejected {
+        WithdrawalRiskService::record(&mut tx, None, &user_address, &req.token, req.amount, &assessment, None, None)
+            .await
+            .map_err(risk_error)?;
+        tx.commit().await.map_err(db_error("Failed to record withdrawal rejection", "Failed to process withdrawal"))?;
+        tracing::warn!(
+            "Withdrawal refused - user: {}, token: {}, amount: {}, reasons: {:?}",
+            user_address,
+            req.token,
+            req.amount,
+            assessment.reasons
+        );
+        return Err(risk_error(WithdrawalRiskError::DailyLimitExceeded {
+            limit: policy.daily_limit,
+            used: inputs.withdrawn_24h,
+        }));
+    }
+    let status = assessment.decision.withdrawal_status();
+
+    // Redeem the quote, locking its fee to this withdrawal
+    let fee = match req.quote_id {
+        Some(quote_id) => {
+            WithdrawalFeeService::redeem_quote(&mut tx, quote_id, &user_address, &req.token, req.amount)
+                .await
+                .map_err(fee_error)?
+        }
+        None => Decimal::ZERO,
+    };
 
     // Freeze funds
-    sqlx::query(
-        r#"
-        UPDATE balances
-        SET available = available - $1, frozen = frozen + $1
-        WHERE user_address = $2 AND token = $3 AND available >= $1
-        "#,
-    )
-    .bind(req.amount)
-    .bind(&user_address)
-    .bind(&req.token)
-    .execute(&mut *tx)
-    .await
-    .map_err(|e| {
-        tracing::error!("Failed to freeze funds: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
+    let change = BalanceChange::freeze(&user_address, &req.token, req.amount, LedgerReason::WithdrawalFreeze)
+        .reference(withdraw_id)
+        .checked();
+    let frozen = LedgerService::apply(&mut tx, &change).await.map_err(db_error("Failed to freeze funds", "Failed to freeze funds"))?;
+    if frozen.is_none() {
+        return Err((
+            StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
-                error: "Failed to freeze funds".to_string(),
+                error: "Insufficient balance".to_string(),
             }),
-        )
-    })?;
+        ));
+    }
 
     // Create withdrawal record
     let created_at = Utc::now();
     sqlx::query(
         r#"
-        INSERT INTO withdrawals (id, user_address, token, amount, status, created_at)
-        VALUES ($1, $2, $3, $4, 'pending', $5)
+        INSERT INTO withdrawals (id, user_address, token, amount, fee, quote_id, status, chain_id, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
         "#,
     )
     .bind(withdraw_id)
     .bind(&user_address)
     .bind(&req.token)
     .bind(req.amount)
+    .bind(fee)
+    .bind(req.quote_id)
+    .bind(status)
+    .bind(chain_id as i64)
     .bind(created_at)
     .execute(&mut *tx)
     .await
-    .map_err(|e| {
-        tracing::error!("Failed to create withdrawal: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: "Failed to create withdrawal".to_string(),
-            }),
-        )
-    })?;
+    .map_err(db_error("Failed to create withdrawal", "Failed to create withdrawal"))?;
+    WithdrawalRiskService::record(&mut tx, Some(withdraw_id), &user_address, &req.token, req.amount, &assessment, None, None)
+        .await
+        .map_err(risk_error)?;
 
-    tx.commit().await.map_err(|e| {
-        tracing::error!("Failed to commit transaction: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: "Failed to process withdrawal".to_string(),
-            }),
-        )
-    })?;
+    // A failed commit may still have applied: a retry must not freeze the funds again
+    if let Some(Extension(commit)) = &commit {
+        commit.commit(withdraw_id);
+    }
+    tx.commit().await.map_err(db_error("Failed to commit transaction", "Failed to process withdrawal"))?;
 
     tracing::info!(
-        "Withdrawal requested - user: {}, token: {}, amount: {}, id: {}",
+        "Withdrawal requested - user: {}, chain: {}, token: {}, amount: {}, fee: {}, id: {}, status: {}",
         user_address,
+        chain_id,
         req.token,
         req.amount,
-        withdraw_id
+        fee,
+        withdraw_id,
+        status
     );
 
     Ok(Json(WithdrawResponse {
         withdraw_id: withdraw_id.to_string(),
+        chain_id,
         token: req.token,
         amount: req.amount.to_string(),
-        status: "pending".to_string(),
+        fee: fee.to_string(),
+        net_amount: (req.amount - fee).to_string(),
+        status: status.to_string(),
         created_at: created_at.timestamp_millis(),
     }))
 }
@@ -210,9 +438,9 @@ pub async fn get_history(
 ) -> Result<Json<WithdrawHistoryResponse>, (StatusCode, Json<ErrorResponse>)> {
     let user_address = auth_user.address.to_lowercase();
 
-    let rows: Vec<(Uuid, String, Decimal, Option<String>, String, DateTime<Utc>)> = sqlx::query_as(
+    let rows: Vec<WithdrawalRow> = sqlx::query_as(
         r#"
-        SELECT id, token, amount, tx_hash, status, created_at
+        SELECT id, chain_id, token, amount, fee, tx_hash, status, created_at
         FROM withdrawals
         WHERE user_address = $1
         ORDER BY created_at DESC
@@ -222,27 +450,11 @@ pub async fn get_history(
     .bind(&user_address)
     .fetch_all(&state.db.pool)
     .await
-    .map_err(|e| {
-        tracing::error!("Failed to fetch withdrawals: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: "Failed to fetch withdrawal history".to_string(),
-            }),
-        )
-    })?;
+    .map_err(db_error("Failed to fetch withdrawals", "Failed to fetch withdrawal history"))?;
 
-    let withdrawals: Vec<WithdrawHistoryRecord> = rows
-        .into_iter()
-        .map(|(id, token, amount, tx_hash, status, created_at)| WithdrawHistoryRecord {
-            id: id.to_string(),
-            token,
-            amount,
-            tx_hash,
-            status,
-            created_at: created_at.timestamp_millis(),
-        })
-        .collect();
+    let primary_chain = state.chains.primary_id();
+    let withdrawals: Vec<WithdrawHistoryRecord> =
+        rows.into_iter().map(|row| row.into_record(primary_chain)).collect();
 
     Ok(Json(WithdrawHistoryResponse { withdrawals }))
 }
@@ -256,10 +468,10 @@ pub async fn get_withdrawal(
 ) -> Result<Json<WithdrawHistoryRecord>, (StatusCode, Json<ErrorResponse>)> {
     let user_address = auth_user.address.to_lowercase();
 
-    let row: Option<(Uuid, String, Decimal, Option<String>, String, DateTime<Utc>)> =
+    let row: Option<WithdrawalRow> =
         sqlx::query_as(
             r#"
-        SELECT id, token, amount, tx_hash, status, created_at
+        SELECT id, chain_id, token, amount, fee, tx_hash, status, created_at
         FROM withdrawals
         WHERE id = $1 AND user_address = $2
         "#,
@@ -268,27 +480,10 @@ pub async fn get_withdrawal(
         .bind(&user_address)
         .fetch_optional(&state.db.pool)
         .await
-        .map_err(|e| {
-            tracing::error!("Failed to fetch withdrawal: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: "Failed to fetch withdrawal".to_string(),
-                }),
-            )
-        })?;
+        .map_err(db_error("Failed to fetch withdrawal", "Failed to fetch withdrawal"))?;
 
     match row {
-        Some((id, token, amount, tx_hash, status, created_at)) => {
-            Ok(Json(WithdrawHistoryRecord {
-                id: id.to_string(),
-                token,
-                amount,
-                tx_hash,
-                status,
-                created_at: created_at.timestamp_millis(),
-            }))
-        }
+        Some(row) => Ok(Json(row.into_record(state.chains.primary_id()))),
         None => Err((
             StatusCode::NOT_FOUND,
             Json(ErrorResponse {
@@ -298,7 +493,7 @@ pub async fn get_withdrawal(
     }
 }
 
-/// Cancel a pending withdrawal
+/// Cancel a pending withdrawal, or one awaiting review
 /// DELETE /withdraw/:withdrawal_id
 pub async fn cancel_withdraw(
     State(state): State<Arc<AppState>>,
@@ -315,15 +510,7 @@ pub async fn cancel_withdraw(
     .bind(&user_address)
     .fetch_optional(&state.db.pool)
     .await
-    .map_err(|e| {
-        tracing::error!("Failed to fetch withdrawal: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: "Failed to fetch withdrawal".to_string(),
-            }),
-        )
-    })?;
+    .map_err(db_error("Failed to fetch withdrawal", "Failed to fetch withdrawal"))?;
 
     let (token, amount, status) = withdrawal.ok_or_else(|| {
         (
@@ -334,7 +521,7 @@ pub async fn cancel_withdraw(
         )
     })?;
 
-    if status != "pending" {
+    if status != "pending" && status != "pending_review" {
         return Err((
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
@@ -344,63 +531,33 @@ pub async fn cancel_withdraw(
     }
 
     // Unfreeze funds and cancel withdrawal
-    let mut tx = state.db.pool.begin().await.map_err(|e| {
-        tracing::error!("Failed to start transaction: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: "Failed to cancel withdrawal".to_string(),
-            }),
-        )
-    })?;
+    let mut tx = state.db.pool.begin().await.map_err(db_error("Failed to start transaction", "Failed to cancel withdrawal"))?;
 
     // Unfreeze funds
-    sqlx::query(
-        r#"
-        UPDATE balances
-        SET available = available + $1, frozen = frozen - $1
-        WHERE user_address = $2 AND token = $3
-        "#,
+    let change = BalanceChange::unfreeze(&user_address, &token, amount, LedgerReason::WithdrawalUnfreeze)
+        .reference(withdrawal_id);
+    LedgerService::apply(&mut tx, &change).await.map_err(db_error("Failed to unfreeze funds", "Failed to unfreeze funds"))?;
+
+    // Update withdrawal status
+    // Conditional: a concurrent review decision may have settled it first
+    let updated = sqlx::query(
+        "UPDATE withdrawals SET status = 'cancelled' WHERE id = $1 AND status IN ('pending', 'pending_review')",
     )
-    .bind(amount)
-    .bind(&user_address)
-    .bind(&token)
+    .bind(withdrawal_id)
     .execute(&mut *tx)
     .await
-    .map_err(|e| {
-        tracing::error!("Failed to unfreeze funds: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
+    .map_err(db_error("Failed to cancel withdrawal", "Failed to cancel withdrawal"))?
+    .rows_affected();
+    if updated == 0 {
+        return Err((
+            StatusCode::CONFLICT,
             Json(ErrorResponse {
-                error: "Failed to unfreeze funds".to_string(),
+                error: "Withdrawal status changed; try again".to_string(),
             }),
-        )
-    })?;
+        ));
+    }
 
-    // Update withdrawal status
-    sqlx::query("UPDATE withdrawals SET status = 'cancelled' WHERE id = $1")
-        .bind(withdrawal_id)
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| {
-            tracing::error!("Failed to cancel withdrawal: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: "Failed to cancel withdrawal".to_string(),
-                }),
-            )
-        })?;
-
-    tx.commit().await.map_err(|e| {
-        tracing::error!("Failed to commit transaction: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: "Failed to cancel withdrawal".to_string(),
-            }),
-        )
-    })?;
+    tx.commit().await.map_err(db_error("Failed to commit transaction", "Failed to cancel withdrawal"))?;
 
     tracing::info!(
         "Withdrawal cancelled - user: {}, id: {}",
@@ -425,24 +582,16 @@ pub async fn confirm_withdraw(
     let user_address = auth_user.address.to_lowercase();
 
     // Get withdrawal info
-    let withdrawal: Option<(String, Decimal, String)> = sqlx::query_as(
-        "SELECT token, amount, status FROM withdrawals WHERE id = $1 AND user_address = $2",
+    let withdrawal: Option<(String, Decimal, Decimal, String)> = sqlx::query_as(
+        "SELECT token, amount, fee, status FROM withdrawals WHERE id = $1 AND user_address = $2",
     )
     .bind(withdrawal_id)
     .bind(&user_address)
     .fetch_optional(&state.db.pool)
     .await
-    .map_err(|e| {
-        tracing::error!("Failed to fetch withdrawal: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: "Failed to fetch withdrawal".to_string(),
-            }),
-        )
-    })?;
+    .map_err(db_error("Failed to fetch withdrawal", "Failed to fetch withdrawal"))?;
 
-    let (token, amount, status) = withdrawal.ok_or_else(|| {
+    let (token, amount, fee, status) = withdrawal.ok_or_else(|| {
         (
             StatusCode::NOT_FOUND,
             Json(ErrorResponse {
@@ -451,6 +600,14 @@ pub async fn confirm_withdraw(
         )
     })?;
 
+    if status == "pending_review" {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ErrorResponse {
+                error: "Withdrawal is awaiting manual review".to_string(),
+            }),
+        ));
+    }
     if status != "pending" {
         return Err((
             StatusCode::BAD_REQUEST,
@@ -461,64 +618,47 @@ pub async fn confirm_withdraw(
     }
 
     // Update withdrawal with tx_hash and deduct frozen balance
-    let mut tx = state.db.pool.begin().await.map_err(|e| {
-        tracing::error!("Failed to start transaction: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: "Failed to confirm withdrawal".to_string(),
-            }),
-        )
-    })?;
+    let mut tx = state.db.pool.begin().await.map_err(db_error("Failed to start transaction", "Failed to confirm withdrawal"))?;
 
-    // Deduct frozen balance
-    sqlx::query(
-        r#"
-        UPDATE balances
-        SET frozen = frozen - $1
-        WHERE user_address = $2 AND token = $3
-        "#,
-    )
-    .bind(amount)
-    .bind(&user_address)
-    .bind(&token)
-    .execute(&mut *tx)
-    .await
-    .map_err(|e| {
-        tracing::error!("Failed to deduct frozen balance: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: "Failed to deduct frozen balance".to_string(),
-            }),
-        )
-    })?;
-
-    // Update withdrawal status
-    sqlx::query("UPDATE withdrawals SET status = 'completed', tx_hash = $1 WHERE id = $2")
+    // Claim the withdrawal first: of concurrent confirms (or a review
+    // decision) only one moves it out of pending and settles it
+    let updated = sqlx::query("UPDATE withdrawals SET status = 'completed', tx_hash = $1 WHERE id = $2 AND status = 'pending'")
         .bind(&req.tx_hash)
         .bind(withdrawal_id)
         .execute(&mut *tx)
         .await
-        .map_err(|e| {
-            tracing::error!("Failed to confirm withdrawal: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: "Failed to confirm withdrawal".to_string(),
-                }),
-            )
-        })?;
-
-    tx.commit().await.map_err(|e| {
-        tracing::error!("Failed to commit transaction: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
+        .map_err(db_error("Failed to confirm withdrawal", "Failed to confirm withdrawal"))?
+        .rows_affected();
+    if updated == 0 {
+        return Err((
+            StatusCode::CONFLICT,
             Json(ErrorResponse {
-                error: "Failed to confirm withdrawal".to_string(),
+                error: "Withdrawal status changed; try again".to_string(),
             }),
-        )
-    })?;
+        ));
+    }
+
+    // Deduct frozen balance: the amount sent on chain, then the fee
+    let mut changes = vec![
+        BalanceChange::settle_frozen(&user_address, &token, amount - fee, LedgerReason::Withdrawal)
+            .reference(withdrawal_id),
+    ];
+    if fee > Decimal::ZERO {
+        changes.push(
+            BalanceChange::settle_frozen(&user_address, &token, fee, LedgerReason::WithdrawalFee)
+                .reference(withdrawal_id),
+        );
+    }
+    for change in &changes {
+        LedgerService::apply(&mut tx, change).await.map_err(db_error("Failed to deduct frozen balance", "Failed to deduct frozen balance"))?;
+    }
+
+    // Account the collected fee
+    TreasuryService::record(&mut tx, &token, fee, TreasurySource::WithdrawalFee, Some(withdrawal_id))
+        .await
+        .map_err(db_error("Failed to record withdrawal fee", "Failed to confirm withdrawal"))?;
+
+    tx.commit().await.map_err(db_error("Failed to commit transaction", "Failed to confirm withdrawal"))?;
 
     tracing::info!(
         "Withdrawal confirmed - user: {}, id: {}, tx: {}",
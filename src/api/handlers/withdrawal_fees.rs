@@ -0,0 +1,142 @@
+//! Withdrawal Fee API Handlers
+//!
+//! Admin endpoints for per-token withdrawal fee policies and the treasury
+//! totals of collected fees.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::services::gas_oracle::GasReading;
+use crate::services::treasury::{TreasuryService, TreasuryTotal};
+use crate::services::withdrawal_fees::{WithdrawalFeeError, WithdrawalFeePolicy, WithdrawalFeeService};
+use crate::AppState;
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeePoliciesResponse {
+    pub policies: Vec<WithdrawalFeePolicy>,
+    /// Latest gas reading (None until the oracle has polled)
+    pub gas: Option<GasReading>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpsertFeePolicyRequest {
+    #[serde(default)]
+    pub flat_fee: Decimal,
+    #[serde(default)]
+    pub percentage: Decimal,
+    pub min_fee: Option<Decimal>,
+    pub max_fee: Option<Decimal>,
+    #[serde(default = "default_congestion_enabled")]
+    pub congestion_enabled: bool,
+}
+
+fn default_congestion_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TreasuryQuery {
+    pub since: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TreasuryResponse {
+    pub totals: Vec<TreasuryTotal>,
+}
+
+fn db_error(e: impl std::fmt::Display) -> (StatusCode, Json<ErrorResponse>) {
+    tracing::error!("Withdrawal fee database error: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse {
+            error: "Database error".to_string(),
+            code: "DB_ERROR".to_string(),
+        }),
+    )
+}
+
+fn fee_error(e: WithdrawalFeeError) -> (StatusCode, Json<ErrorResponse>) {
+    match &e {
+        WithdrawalFeeError::InvalidPolicy(_) => (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: e.to_string(),
+                code: "INVALID_FEE_POLICY".to_string(),
+            }),
+        ),
+        _ => db_error(e),
+    }
+}
+
+// ============================================================================
+// Admin Handlers
+// ============================================================================
+
+/// Withdrawal fee policies and the current gas reading - Admin only
+/// GET /admin/withdrawal-fees
+pub async fn list_policies(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<FeePoliciesResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let policies = WithdrawalFeeService::list_policies(&state.db.pool)
+        .await
+        .map_err(fee_error)?;
+
+    Ok(Json(FeePoliciesResponse {
+        policies,
+        gas: state.gas_oracle.latest(),
+    }))
+}
+
+/// Create or replace a token's withdrawal fee policy - Admin only
+/// PUT /admin/withdrawal-fees/:token
+pub async fn upsert_policy(
+    State(state): State<Arc<AppState>>,
+    Path(token): Path<String>,
+    Json(req): Json<UpsertFeePolicyRequest>,
+) -> Result<Json<WithdrawalFeePolicy>, (StatusCode, Json<ErrorResponse>)> {
+    let policy = WithdrawalFeePolicy {
+        token,
+        flat_fee: req.flat_fee,
+        percentage: req.percentage,
+        min_fee: req.min_fee,
+        max_fee: req.max_fee,
+        congestion_enabled: req.congestion_enabled,
+        updated_at: Utc::now(),
+    };
+    let policy = WithdrawalFeeService::upsert_policy(&state.db.pool, &policy)
+        .await
+        .map_err(fee_error)?;
+
+    tracing::info!("Withdrawal fee policy for {} updated: {:?}", policy.token, policy);
+    Ok(Json(policy))
+}
+
+/// Collected treasury income per token and source - Admin only
+/// GET /admin/treasury
+pub async fn get_treasury(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TreasuryQuery>,
+) -> Result<Json<TreasuryResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let totals = TreasuryService::totals(&state.db.pool, query.since)
+        .await
+        .map_err(db_error)?;
+
+    Ok(Json(TreasuryResponse { totals }))
+}
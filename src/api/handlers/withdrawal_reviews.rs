@@ -0,0 +1,122 @@
+//! Withdrawal Review Handlers
+//!
+//! Admin endpoints for the queue of withdrawals held by the risk checks.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::api::error as api_error;
+use crate::auth::middleware::AuthUser;
+use crate::services::withdrawal_risk::{QueuedWithdrawal, WithdrawalReview, WithdrawalRiskError, WithdrawalRiskService};
+use crate::AppState;
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QueueQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReviewQueueResponse {
+    pub withdrawals: Vec<QueuedWithdrawal>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReviewHistoryResponse {
+    pub reviews: Vec<WithdrawalReview>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewRequest {
+    pub note: Option<String>,
+}
+
+fn risk_error(e: WithdrawalRiskError) -> (StatusCode, Json<ErrorResponse>) {
+    let (status, code) = api_error::classify(&e);
+    let error = if api_error::is_client_error(&e) {
+        e.to_string()
+    } else {
+        "Withdrawal review failed".to_string()
+    };
+    (
+        status,
+        Json(ErrorResponse {
+            error,
+            code: code.to_string(),
+        }),
+    )
+}
+
+// ============================================================================
+// Admin Handlers
+// ============================================================================
+
+/// Withdrawals awaiting review, oldest first - Admin only
+/// GET /admin/withdrawals/reviews
+pub async fn list_queue(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<QueueQuery>,
+) -> Result<Json<ReviewQueueResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let limit = query.limit.unwrap_or(100).clamp(1, 500);
+    let offset = query.offset.unwrap_or(0).max(0);
+    let withdrawals = WithdrawalRiskService::queue(&state.db.pool, limit, offset)
+        .await
+        .map_err(risk_error)?;
+    Ok(Json(ReviewQueueResponse { withdrawals }))
+}
+
+/// Risk decisions on a withdrawal - Admin only
+/// GET /admin/withdrawals/:id/reviews
+pub async fn get_history(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ReviewHistoryResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let reviews = WithdrawalRiskService::history(&state.db.pool, id)
+        .await
+        .map_err(risk_error)?;
+    Ok(Json(ReviewHistoryResponse { reviews }))
+}
+
+/// Release a held withdrawal - Admin only
+/// POST /admin/withdrawals/:id/approve
+pub async fn approve(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<ReviewRequest>,
+) -> Result<Json<WithdrawalReview>, (StatusCode, Json<ErrorResponse>)> {
+    WithdrawalRiskService::approve(&state.db.pool, id, &auth_user.address, req.note.as_deref())
+        .await
+        .map(Json)
+        .map_err(risk_error)
+}
+
+/// Refuse a held withdrawal, unfreezing its funds - Admin only
+/// POST /admin/withdrawals/:id/reject
+pub async fn reject(
+    State(state): State<Arc<AppState>>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<ReviewRequest>,
+) -> Result<Json<WithdrawalReview>, (StatusCode, Json<ErrorResponse>)> {
+    WithdrawalRiskService::reject(&state.db.pool, id, &auth_user.address, req.note.as_deref())
+        .await
+        .map(Json)
+        .map_err(risk_error)
+}
@@ -0,0 +1,271 @@
+//! Idempotency Key Middleware
+//!
+//! Wraps endpoints that must not run twice for one client intent. The key is
+//! read from the `Idempotency-Key` header, or from an `idempotency_key` field
+//! of the JSON body. Requests without a key pass through unchanged.
+//!
+//! - first use: the request runs and its response is stored
+//! - retry after completion: the stored response is returned with
+//!   `Idempotent-Replayed: true`
+//! - retry while the first request runs: 409 `IDEMPOTENCY_IN_PROGRESS`
+//! - same key, different body: 422 `IDEMPOTENCY_KEY_REUSED`
+//!
+//! A server error releases the key so the request can be retried, unless the
+//! handler had already taken effect (order accepted by the engine, funds
+//! frozen) and marked it through [`IdempotencyCommit`]. Such a response is
+//! stored like any other, with the `reference_id` of what was created, so a
+//! retry cannot place the order or withdrawal a second time.
+//!
+//! See [`crate::services::idempotency`] for storage and expiry.
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{MatchedPath, Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::auth::middleware::AuthUser;
+use crate::services::idempotency::{request_hash, Claim};
+use crate::AppState;
+
+/// Request header carrying the key
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Response header marking a replayed response
+pub const REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// Body field accepted when the header is absent
+const IDEMPOTENCY_KEY_FIELD: &str = "idempotency_key";
+
+const MAX_KEY_LEN: usize = 255;
+
+/// Largest request or response body the middleware will buffer
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// Handed to the handler through the request extensions: marks the point
+/// after which the request has taken effect and must not be run again
+#[derive(Debug, Clone, Default)]
+pub struct IdempotencyCommit(Arc<Mutex<Option<Uuid>>>);
+
+impl IdempotencyCommit {
+    /// The request created `reference_id` (an order, a withdrawal)
+    pub fn commit(&self, reference_id: Uuid) {
+        *self.0.lock() = Some(reference_id);
+    }
+
+    fn committed(&self) -> Option<Uuid> {
+        *self.0.lock()
+    }
+}
+
+/// What becomes of a claimed key once the handler has responded
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    /// Nothing took effect: free the key for a real retry
+    Release,
+    /// Store (and return) this response for every retry
+    Store(Vec<u8>),
+}
+
+/// Server errors before the request took effect release the key; everything
+/// else is final. A committed server error carries the created `reference_id`.
+fn outcome(status: StatusCode, committed: Option<Uuid>, body: &[u8]) -> Outcome {
+    if !status.is_server_error() {
+        return Outcome::Store(body.to_vec());
+    }
+    let Some(reference_id) = committed else {
+        return Outcome::Release;
+    };
+    let mut json: serde_json::Value = serde_json::from_slice(body).unwrap_or_else(|_| serde_json::json!({}));
+    if let Some(object) = json.as_object_mut() {
+        object.insert("reference_id".to_string(), serde_json::json!(reference_id));
+    }
+    Outcome::Store(serde_json::to_vec(&json).unwrap_or_else(|_| body.to_vec()))
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorResponse {
+    error: String,
+    code: String,
+}
+
+fn error(status: StatusCode, error: &str, code: &str) -> Response {
+    (
+        status,
+        Json(ErrorResponse {
+            error: error.to_string(),
+            code: code.to_string(),
+        }),
+    )
+        .into_response()
+}
+
+/// Key from the header, else from the JSON body
+fn idempotency_key(request: &Request, body: &[u8]) -> Option<String> {
+    if let Some(value) = request.headers().get(IDEMPOTENCY_KEY_HEADER) {
+        return Some(value.to_str().unwrap_or_default().trim().to_string());
+    }
+    let body: serde_json::Value = serde_json::from_slice(body).ok()?;
+    body.get(IDEMPOTENCY_KEY_FIELD)?.as_str().map(|key| key.trim().to_string())
+}
+
+fn replay(status: u16, body: String) -> Response {
+    let status = StatusCode::from_u16(status).unwrap_or(StatusCode::OK);
+    let mut response = (status, body).into_response();
+    let headers = response.headers_mut();
+    headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    headers.insert(REPLAYED_HEADER, HeaderValue::from_static("true"));
+    response
+}
+
+/// Run a request at most once per idempotency key
+pub async fn idempotency_middleware(State(state): State<Arc<AppState>>, request: Request, next: Next) -> Response {
+    let (parts, body) = request.into_parts();
+    let bytes = match to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(_) => return error(StatusCode::PAYLOAD_TOO_LARGE, "Request body too large", "PAYLOAD_TOO_LARGE"),
+    };
+    let request = Request::from_parts(parts, Body::from(bytes.clone()));
+
+    let Some(key) = idempotency_key(&request, &bytes) else {
+        return next.run(request).await;
+    };
+    if key.is_empty() || key.len() > MAX_KEY_LEN {
+        return error(
+            StatusCode::BAD_REQUEST,
+            &format!("Idempotency key must be 1 to {} characters", MAX_KEY_LEN),
+            "INVALID_IDEMPOTENCY_KEY",
+        );
+    }
+    // Mounted behind auth; without a user there is nothing to scope the key to
+    let Some(user_address) = request.extensions().get::<AuthUser>().map(|u| u.address.clone()) else {
+        return next.run(request).await;
+    };
+    let path = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    let scope = format!("{} {}", request.method(), path);
+
+    let pool = &state.db.pool;
+    let claim = state
+        .idempotency
+        .claim(pool, &user_address, &scope, &key, &request_hash(&bytes))
+        .await;
+    match claim {
+        Ok(Claim::New) => {}
+        Ok(Claim::Replay { status, body }) => {
+            tracing::info!("Replaying {} for idempotency key {} of {}", scope, key, user_address);
+            return replay(status, body);
+        }
+        Ok(Claim::InProgress) => {
+            return error(
+                StatusCode::CONFLICT,
+                "A request with this idempotency key is still in progress",
+                "IDEMPOTENCY_IN_PROGRESS",
+            );
+        }
+        Ok(Claim::Mismatch) => {
+            return error(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Idempotency key was already used for a different request",
+                "IDEMPOTENCY_KEY_REUSED",
+            );
+        }
+        Err(e) => {
+            tracing::error!("Failed to claim idempotency key: {}", e);
+            return error(StatusCode::INTERNAL_SERVER_ERROR, "Database error", "DB_ERROR");
+        }
+    }
+
+    let commit = IdempotencyCommit::default();
+    let mut request = request;
+    request.extensions_mut().insert(commit.clone());
+
+    let response = next.run(request).await;
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!("Failed to buffer response for idempotency key {}: {}", key, e);
+            if commit.committed().is_none() {
+                let _ = state.idempotency.release(pool, &user_address, &scope, &key).await;
+            }
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let (stored, bytes) = match outcome(parts.status, commit.committed(), &bytes) {
+        Outcome::Release => (state.idempotency.release(pool, &user_address, &scope, &key).await, bytes.to_vec()),
+        Outcome::Store(body) => {
+            let stored = state
+                .idempotency
+                .complete(pool, &user_address, &scope, &key, parts.status.as_u16(), &String::from_utf8_lossy(&body))
+                .await;
+            (stored, body)
+        }
+    };
+    if let Err(e) = stored {
+        tracing::error!("Failed to store response for idempotency key {}: {}", key, e);
+    }
+    parts.headers.remove(header::CONTENT_LENGTH);
+
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_from_header_or_body() {
+        let with_header = Request::builder()
+            .header(IDEMPOTENCY_KEY_HEADER, " retry-1 ")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(idempotency_key(&with_header, b"{}").as_deref(), Some("retry-1"));
+
+        let bare = Request::builder().body(Body::empty()).unwrap();
+        let body = br#"{"amount":"1","idempotency_key":"retry-2"}"#;
+        assert_eq!(idempotency_key(&bare, body).as_deref(), Some("retry-2"));
+        assert_eq!(idempotency_key(&bare, br#"{"amount":"1"}"#), None);
+        assert_eq!(idempotency_key(&bare, b"not json"), None);
+    }
+
+    #[test]
+    fn test_server_error_releases_key_before_side_effect() {
+        let body = br#"{"error":"Service unavailable","code":"DRAINING"}"#;
+        assert_eq!(outcome(StatusCode::SERVICE_UNAVAILABLE, None, body), Outcome::Release);
+        assert_eq!(outcome(StatusCode::OK, None, b"{}"), Outcome::Store(b"{}".to_vec()));
+        assert_eq!(outcome(StatusCode::BAD_REQUEST, None, body), Outcome::Store(body.to_vec()));
+    }
+
+    #[test]
+    fn test_engine_accepted_db_insert_failed_retry_replays() {
+        // The engine accepted the order, then persisting it failed
+        let order_id = Uuid::new_v4();
+        let commit = IdempotencyCommit::default();
+        commit.commit(order_id);
+        let body = br#"{"error":"save failed","code":"DB_ERROR"}"#;
+
+        let Outcome::Store(stored) = outcome(StatusCode::INTERNAL_SERVER_ERROR, commit.committed(), body) else {
+            panic!("a committed request must keep its key");
+        };
+        let stored: serde_json::Value = serde_json::from_slice(&stored).unwrap();
+        assert_eq!(stored["code"], "DB_ERROR");
+        assert_eq!(stored["reference_id"], order_id.to_string());
+
+        // The retry gets the stored response instead of placing a second order
+        let retry = replay(500, stored.to_string());
+        assert_eq!(retry.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(retry.headers().get(REPLAYED_HEADER).unwrap(), "true");
+    }
+}
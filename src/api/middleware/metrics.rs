@@ -29,7 +29,7 @@ pub async fn metrics_middleware(request: Request<Body>, next: Next) -> Response
         .unwrap_or_else(|| request.uri().path().to_string());
 
     // Track in-flight requests
-    metrics::set_http_requests_in_flight(1);
+    metrics::add_http_requests_in_flight(1);
 
     // Process the request
     let response = next.run(request).await;
@@ -39,7 +39,7 @@ pub async fn metrics_middleware(request: Request<Body>, next: Next) -> Response
     let status = response.status().as_u16();
 
     metrics::record_http_request(&method, &path, status, duration);
-    metrics::set_http_requests_in_flight(-1);
+    metrics::add_http_requests_in_flight(-1);
 
     response
 }
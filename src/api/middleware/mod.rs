@@ -2,9 +2,17 @@
 //!
 //! Contains middleware for:
 //! - HTTP metrics recording
-//! - Rate limiting (future)
+//! - Idempotency keys for retried writes
+//! - Token-bucket rate limiting per account / IP
+//! - Request IDs and per-request tracing spans
 //! - Request logging
 
+pub mod idempotency;
 pub mod metrics;
+pub mod rate_limit;
+pub mod request_id;
 
+pub use idempotency::idempotency_middleware;
 pub use metrics::metrics_middleware;
+pub use rate_limit::rate_limit_middleware;
+pub use request_id::request_id_middleware;
@@ -0,0 +1,494 @@
+//! Rate Limiting Middleware
+//!
+//! Token buckets per caller. Authenticated requests are keyed by account
+//! address; anonymous ones by client IP: the socket peer address, or the
+//! forwarded client address when the peer is one of `trusted_proxies`. A
+//! bucket holds up to `burst` tokens and refills at `per_minute`; each request
+//! takes one token.
+//!
+//! Buckets live in Redis when it is available, so all instances share them,
+//! and in process memory otherwise. Routes listed in `rate_limit_routes` get
+//! their own bucket and limit, keyed by method and path without the API
+//! version (`"POST /orders"` covers `/api/v1/orders` and `/api/v2/orders`).
+//!
+//! Responses carry `X-RateLimit-Limit`, `X-RateLimit-Remaining` and
+//! `X-RateLimit-Reset` (seconds until the bucket is full again). Rejected
+//! requests get 429 `RATE_LIMITED` with `Retry-After`.
+
+use axum::{
+    extract::{ConnectInfo, MatchedPath, Request, State},
+    http::{HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use redis::{RedisError, Script};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, LazyLock};
+
+use crate::auth::middleware::AuthUser;
+use crate::cache::keys::CacheKey;
+use crate::cache::RedisClient;
+use crate::config::AppConfig;
+use crate::services::api_keys::{ip_matches, valid_allowlist_entry};
+use crate::AppState;
+
+/// Rate limit errors
+#[derive(Debug, thiserror::Error)]
+pub enum RateLimitError {
+    #[error("Invalid rate limit config: {0}")]
+    InvalidConfig(String),
+}
+
+/// Bucket size and refill rate
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RateLimit {
+    pub burst: u32,
+    pub per_minute: u32,
+}
+
+impl RateLimit {
+    pub fn new(burst: u32, per_minute: u32) -> Self {
+        Self {
+            burst: burst.max(1),
+            per_minute: per_minute.max(1),
+        }
+    }
+
+    fn refill_per_ms(&self) -> f64 {
+        self.per_minute as f64 / 60_000.0
+    }
+
+    /// Time an empty bucket takes to fill
+    fn fill_ms(&self) -> i64 {
+        (self.burst as f64 / self.refill_per_ms()).ceil() as i64
+    }
+}
+
+/// Parse the `rate_limit_routes` JSON config (empty = no overrides)
+pub fn parse_route_limits(config: &str) -> Result<HashMap<String, RateLimit>, RateLimitError> {
+    if config.trim().is_empty() {
+        return Ok(HashMap::new());
+    }
+    let routes: HashMap<String, RateLimit> =
+        serde_json::from_str(config).map_err(|e| RateLimitError::InvalidConfig(e.to_string()))?;
+    for (route, limit) in &routes {
+        let valid = route.split_once(' ').is_some_and(|(method, path)| !method.is_empty() && path.starts_with('/'));
+        if !valid {
+            return Err(RateLimitError::InvalidConfig(format!("{}: expected \"METHOD /path\"", route)));
+        }
+        if limit.burst == 0 || limit.per_minute == 0 {
+            return Err(RateLimitError::InvalidConfig(format!("{}: burst and per_minute must be positive", route)));
+        }
+    }
+    Ok(routes)
+}
+
+/// Parse the `trusted_proxies` config: comma-separated IPs or CIDR blocks
+pub fn parse_trusted_proxies(config: &str) -> Result<Vec<String>, RateLimitError> {
+    config
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match valid_allowlist_entry(entry) {
+            true => Ok(entry.to_string()),
+            false => Err(RateLimitError::InvalidConfig(format!("trusted proxy {}: not an IP or CIDR block", entry))),
+        })
+        .collect()
+}
+
+/// Rate limiter settings
+#[derive(Debug, Clone)]
+pub struct RateLimitSettings {
+    pub enabled: bool,
+    /// Per authenticated account
+    pub user: RateLimit,
+    /// Per IP for anonymous requests
+    pub ip: RateLimit,
+    /// Overrides by "METHOD /path"
+    pub routes: HashMap<String, RateLimit>,
+    /// Proxies whose forwarding headers name the client (see [`client_ip_from`])
+    pub trusted_proxies: Vec<String>,
+}
+
+impl RateLimitSettings {
+    pub fn from_config(config: &AppConfig) -> Result<Self, RateLimitError> {
+        Ok(Self {
+            enabled: config.rate_limit_enabled,
+            user: RateLimit::new(config.rate_limit_user_burst, config.rate_limit_user_per_minute),
+            ip: RateLimit::new(config.rate_limit_ip_burst, config.rate_limit_ip_per_minute),
+            routes: parse_route_limits(&config.rate_limit_routes)?,
+            trusted_proxies: parse_trusted_proxies(&config.trusted_proxies)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Bucket {
+    tokens: f64,
+    updated_ms: i64,
+}
+
+impl Bucket {
+    fn full(limit: RateLimit, now_ms: i64) -> Self {
+        Self {
+            tokens: limit.burst as f64,
+            updated_ms: now_ms,
+        }
+    }
+
+    /// Refill up to `now_ms`, then take a token if there is one
+    fn take(&mut self, limit: RateLimit, now_ms: i64) -> bool {
+        let elapsed = (now_ms - self.updated_ms).max(0) as f64;
+        self.tokens = (self.tokens + elapsed * limit.refill_per_ms()).min(limit.burst as f64);
+        self.updated_ms = now_ms;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Outcome of taking a token
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    /// Seconds until the bucket is full
+    pub reset_secs: u64,
+    /// Seconds until the next token (0 when allowed)
+    pub retry_after_secs: u64,
+}
+
+impl Decision {
+    fn new(limit: RateLimit, tokens: f64, allowed: bool) -> Self {
+        let secs_until = |target: f64| ((target - tokens).max(0.0) / limit.refill_per_ms() / 1000.0).ceil() as u64;
+        Self {
+            allowed,
+            limit: limit.burst,
+            remaining: tokens.floor() as u32,
+            reset_secs: secs_until(limit.burst as f64),
+            retry_after_secs: if allowed { 0 } else { secs_until(1.0).max(1) },
+        }
+    }
+
+    fn set_headers(&self, headers: &mut HeaderMap) {
+        headers.insert("x-ratelimit-limit", HeaderValue::from(self.limit));
+        headers.insert("x-ratelimit-remaining", HeaderValue::from(self.remaining));
+        headers.insert("x-ratelimit-reset", HeaderValue::from(self.reset_secs));
+        if !self.allowed {
+            headers.insert("retry-after", HeaderValue::from(self.retry_after_secs));
+        }
+    }
+}
+
+/// Same refill and take as [`Bucket::take`], atomically in Redis.
+/// KEYS[1] bucket; ARGV burst, refill per ms, now (ms), ttl (ms).
+static TOKEN_BUCKET: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r#"
+        local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'ts')
+        local burst = tonumber(ARGV[1])
+        local rate = tonumber(ARGV[2])
+        local now = tonumber(ARGV[3])
+        local tokens = tonumber(bucket[1])
+        if tokens == nil then
+            tokens = burst
+        else
+            tokens = math.min(burst, tokens + math.max(0, now - tonumber(bucket[2])) * rate)
+        end
+        local allowed = 0
+        if tokens >= 1 then
+            tokens = tokens - 1
+            allowed = 1
+        end
+        redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'ts', ARGV[3])
+        redis.call('PEXPIRE', KEYS[1], ARGV[4])
+        return {allowed, tostring(tokens)}
+        "#,
+    )
+});
+
+/// Token-bucket limiter shared by all routes
+pub struct RateLimiter {
+    settings: RwLock<RateLimitSettings>,
+    redis: Option<Arc<RedisClient>>,
+    /// Fallback buckets while Redis is unavailable
+    local: DashMap<String, Bucket>,
+}
+
+impl RateLimiter {
+    pub fn new(settings: RateLimitSettings, redis: Option<Arc<RedisClient>>) -> Self {
+        Self {
+            settings: RwLock::new(settings),
+            redis,
+            local: DashMap::new(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.settings.read().enabled
+    }
+
+    /// Replace the limits (config reload); existing buckets keep their tokens
+    pub fn update(&self, settings: RateLimitSettings) {
+        *self.settings.write() = settings;
+    }
+
+    /// Client IP of a request, believing only the configured proxies
+    pub fn client_ip(&self, request: &Request) -> String {
+        let peer = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| *addr);
+        self.client_ip_from(request.headers(), peer)
+    }
+
+    /// [`Self::client_ip`] from a request's headers and peer address
+    pub fn client_ip_from(&self, headers: &HeaderMap, peer: Option<SocketAddr>) -> String {
+        client_ip_from(headers, peer, &self.settings.read().trusted_proxies)
+    }
+
+    /// Bucket key and limit for a request
+    fn bucket(&self, method: &str, path: &str, user: Option<&str>, ip: &str) -> (String, RateLimit) {
+        let route = format!("{} {}", method, path);
+        let settings = self.settings.read();
+        match (settings.routes.get(&route), user) {
+            (Some(&limit), Some(address)) => (CacheKey::rate_limit_endpoint(method, path, &address.to_lowercase()), limit),
+            (Some(&limit), None) => (CacheKey::rate_limit_endpoint(method, path, ip), limit),
+            (None, Some(address)) => (CacheKey::rate_limit_user(address), settings.user),
+            (None, None) => (CacheKey::rate_limit_ip(ip), settings.ip),
+        }
+    }
+
+    /// Take a token from `key`'s bucket
+    pub async fn check(&self, key: &str, limit: RateLimit) -> Decision {
+        let now_ms = Utc::now().timestamp_millis();
+        if let Some(redis) = &self.redis {
+            match Self::take_shared(redis, key, limit, now_ms).await {
+                Ok((tokens, allowed)) => return Decision::new(limit, tokens, allowed),
+                Err(e) => tracing::debug!("Rate limiting from memory, Redis unavailable: {}", e),
+            }
+        }
+        let mut bucket = self.local.entry(key.to_string()).or_insert_with(|| Bucket::full(limit, now_ms));
+        let allowed = bucket.take(limit, now_ms);
+        Decision::new(limit, bucket.tokens, allowed)
+    }
+
+    async fn take_shared(redis: &RedisClient, key: &str, limit: RateLimit, now_ms: i64) -> Result<(f64, bool), RedisError> {
+        let mut conn = redis.get_connection().await?;
+        let (allowed, tokens): (i64, String) = TOKEN_BUCKET
+            .key(key)
+            .arg(limit.burst)
+            .arg(limit.refill_per_ms())
+            .arg(now_ms)
+            .arg(limit.fill_ms() + 1000)
+            .invoke_async(&mut conn)
+            .await?;
+        Ok((tokens.parse().unwrap_or(0.0), allowed == 1))
+    }
+
+    /// Drop in-memory buckets that have refilled completely; returns how many
+    pub fn prune_local(&self) -> usize {
+        let now_ms = Utc::now().timestamp_millis();
+        let longest_fill = {
+            let settings = self.settings.read();
+            settings
+                .routes
+                .values()
+                .chain([&settings.user, &settings.ip])
+                .map(RateLimit::fill_ms)
+                .max()
+                .unwrap_or(0)
+        };
+        let before = self.local.len();
+        self.local.retain(|_, bucket| now_ms - bucket.updated_ms < longest_fill);
+        before - self.local.len()
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorResponse {
+    error: String,
+    code: String,
+}
+
+/// Path without the `/api/vN` prefix
+fn unversioned(path: &str) -> &str {
+    if let Some(rest) = path.strip_prefix("/api/v") {
+        if let Some(slash) = rest.find('/') {
+            if slash > 0 && rest[..slash].bytes().all(|b| b.is_ascii_digit()) {
+                return &rest[slash..];
+            }
+        }
+    }
+    path
+}
+
+/// Client IP behind `trusted_proxies`
+///
+/// A request straight from a client is keyed by its peer address, whatever
+/// headers it sends. From a trusted proxy, `X-Forwarded-For` is read right to
+/// left: each proxy appends the address it received the request from, so the
+/// first hop that is not a trusted proxy is the client; anything further left
+/// was supplied by the client itself. `X-Real-IP` covers proxies that only
+/// set that header.
+pub(crate) fn client_ip_from(headers: &HeaderMap, peer: Option<SocketAddr>, trusted_proxies: &[String]) -> String {
+    let Some(peer) = peer.map(|addr| addr.ip().to_canonical()) else {
+        return "unknown".to_string();
+    };
+    let trusted = |ip: IpAddr| trusted_proxies.iter().any(|entry| ip_matches(entry, ip));
+    if !trusted(peer) {
+        return peer.to_string();
+    }
+
+    let hops: Vec<&str> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|h| h.to_str().ok())
+        .flat_map(|s| s.split(','))
+        .collect();
+    let mut client = None;
+    for hop in hops.iter().rev() {
+        // Past a malformed hop everything is the client's own
+        let Ok(ip) = hop.trim().parse::<IpAddr>() else {
+            break;
+        };
+        client = Some(ip.to_canonical());
+        if !trusted(ip.to_canonical()) {
+            break;
+        }
+    }
+    client
+        .or_else(|| {
+            headers
+                .get("x-real-ip")
+                .and_then(|h| h.to_str().ok())
+                .and_then(|s| s.trim().parse::<IpAddr>().ok())
+        })
+        .unwrap_or(peer)
+        .to_string()
+}
+
+/// Take a token for the caller, or reject with 429
+pub async fn rate_limit_middleware(State(state): State<Arc<AppState>>, request: Request, next: Next) -> Response {
+    let limiter = &state.rate_limiter;
+    if !limiter.enabled() {
+        return next.run(request).await;
+    }
+
+    let path = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    let user = request.extensions().get::<AuthUser>().map(|u| u.address.clone());
+    let (key, limit) = limiter.bucket(request.method().as_str(), unversioned(&path), user.as_deref(), &limiter.client_ip(&request));
+
+    let decision = limiter.check(&key, limit).await;
+    let mut response = if decision.allowed {
+        next.run(request).await
+    } else {
+        tracing::warn!("Rate limit exceeded for {}: retry after {}s", key, decision.retry_after_secs);
+        (
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(ErrorResponse {
+                error: "Too many requests. Please try again later.".to_string(),
+                code: "RATE_LIMITED".to_string(),
+            }),
+        )
+            .into_response()
+    };
+    decision.set_headers(response.headers_mut());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_bursts_then_refills() {
+        let limit = RateLimit::new(2, 60); // one token per second
+        let mut bucket = Bucket::full(limit, 0);
+        assert!(bucket.take(limit, 0));
+        assert!(bucket.take(limit, 0));
+        assert!(!bucket.take(limit, 500));
+
+        let decision = Decision::new(limit, bucket.tokens, false);
+        assert_eq!((decision.remaining, decision.retry_after_secs, decision.reset_secs), (0, 1, 2));
+
+        assert!(bucket.take(limit, 1000));
+        // Never refills past the burst
+        bucket.take(limit, 60_000);
+        assert_eq!(bucket.tokens, 1.0);
+    }
+
+    #[test]
+    fn test_routes_and_paths() {
+        assert_eq!(unversioned("/api/v1/orders"), "/orders");
+        assert_eq!(unversioned("/api/v2/orders/:order_id"), "/orders/:order_id");
+        assert_eq!(unversioned("/api/vaults"), "/api/vaults");
+        assert_eq!(unversioned("/health"), "/health");
+
+        let routes = parse_route_limits(r#"{"POST /orders": {"burst": 5, "per_minute": 30}}"#).unwrap();
+        assert_eq!(routes["POST /orders"], RateLimit::new(5, 30));
+        assert!(parse_route_limits(r#"{"/orders": {"burst": 5, "per_minute": 30}}"#).is_err());
+        assert!(parse_route_limits(r#"{"POST /orders": {"burst": 0, "per_minute": 30}}"#).is_err());
+        assert!(parse_route_limits("").unwrap().is_empty());
+
+        let limiter = RateLimiter::new(
+            RateLimitSettings {
+                enabled: true,
+                user: RateLimit::new(100, 600),
+                ip: RateLimit::new(50, 300),
+                routes,
+                trusted_proxies: Vec::new(),
+            },
+            None,
+        );
+        assert_eq!(limiter.bucket("POST", "/orders", Some("0xAB"), "1.2.3.4").1, RateLimit::new(5, 30));
+        assert_eq!(limiter.bucket("GET", "/orders", Some("0xAB"), "1.2.3.4").1, RateLimit::new(100, 600));
+        assert_eq!(limiter.bucket("GET", "/markets", None, "1.2.3.4").1, RateLimit::new(50, 300));
+
+        // No trusted proxies: a spoofed header cannot move an anonymous caller to another bucket
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", HeaderValue::from_static("1.2.3.4"));
+        assert_eq!(limiter.client_ip_from(&headers, Some("5.5.5.5:443".parse().unwrap())), "5.5.5.5");
+    }
+
+    #[test]
+    fn test_client_ip_believes_only_trusted_proxies() {
+        let proxies = parse_trusted_proxies("10.0.0.0/8, 192.168.1.5").unwrap();
+        assert!(parse_trusted_proxies("10.0.0.0/33").is_err());
+        assert!(parse_trusted_proxies("").unwrap().is_empty());
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", HeaderValue::from_static("6.6.6.6, 1.2.3.4, 10.0.0.7"));
+        let direct: SocketAddr = "5.5.5.5:443".parse().unwrap();
+        let proxied: SocketAddr = "192.168.1.5:443".parse().unwrap();
+
+        // Straight from a client: the header is ignored
+        assert_eq!(client_ip_from(&headers, Some(direct), &proxies), "5.5.5.5");
+        assert_eq!(client_ip_from(&headers, Some(proxied), &[]), "192.168.1.5");
+        // Behind the proxies: the rightmost hop that is not one of them
+        assert_eq!(client_ip_from(&headers, Some(proxied), &proxies), "1.2.3.4");
+
+        headers.insert("x-forwarded-for", HeaderValue::from_static("junk, 10.0.0.7"));
+        assert_eq!(client_ip_from(&headers, Some(proxied), &proxies), "10.0.0.7");
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-real-ip", HeaderValue::from_static("1.2.3.4"));
+        assert_eq!(client_ip_from(&headers, Some(proxied), &proxies), "1.2.3.4");
+        assert_eq!(client_ip_from(&headers, Some(direct), &proxies), "5.5.5.5");
+        assert_eq!(client_ip_from(&headers, None, &proxies), "unknown");
+    }
+}
@@ -0,0 +1,75 @@
+//! Request ID Middleware
+//!
+//! Gives every request an ID — the inbound `X-Request-Id` if it is usable,
+//! else a new UUID — and handles the request inside a `request` span with
+//! `request_id`, `method`, `route` and (once authenticated) `user` fields, so
+//! every log line of the request carries them. The ID is echoed in the
+//! `X-Request-Id` response header and exposed through
+//! [`crate::utils::request_context`], which the engine stamps on the trades a
+//! request produces so persistence logs can be traced back to it.
+
+use axum::{
+    extract::{MatchedPath, Request},
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+use tracing::Instrument;
+use uuid::Uuid;
+
+use crate::utils::request_context;
+
+/// Request and response header carrying the ID
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Inbound ID if it is short and made of safe characters
+fn inbound_request_id(request: &Request) -> Option<String> {
+    let id = request.headers().get(REQUEST_ID_HEADER)?.to_str().ok()?.trim();
+    let valid = !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'));
+    valid.then(|| id.to_string())
+}
+
+/// Attach a request ID and a tracing span to each request
+pub async fn request_id_middleware(request: Request, next: Next) -> Response {
+    let request_id = inbound_request_id(&request).unwrap_or_else(|| Uuid::new_v4().to_string());
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    let span = tracing::info_span!(
+        "request",
+        request_id = %request_id,
+        method = %request.method(),
+        route = %route,
+        user = tracing::field::Empty,
+    );
+
+    let mut response = request_context::scope(request_id.clone(), next.run(request).instrument(span)).await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+
+    fn request(id: &str) -> Request {
+        Request::builder().header(REQUEST_ID_HEADER, id).body(Body::empty()).unwrap()
+    }
+
+    #[test]
+    fn test_honors_safe_inbound_ids_only() {
+        assert_eq!(inbound_request_id(&request(" abc-123 ")).as_deref(), Some("abc-123"));
+        assert_eq!(inbound_request_id(&request("a b")), None);
+        assert_eq!(inbound_request_id(&request(&"x".repeat(129))), None);
+        assert_eq!(inbound_request_id(&Request::builder().body(Body::empty()).unwrap()), None);
+    }
+}
@@ -1,3 +1,6 @@
+pub mod deprecation;
+pub mod envelope;
+pub mod error;
 pub mod handlers;
 pub mod middleware;
 pub mod routes;
@@ -1,11 +1,14 @@
+pub mod v2;
+
 use axum::{
     middleware as axum_middleware,
-    routing::{delete, get, post},
+    routing::{delete, get, post, put},
     Router,
 };
 use std::sync::Arc;
 
 use crate::api::handlers;
+use crate::api::middleware::{idempotency_middleware, rate_limit_middleware};
 use crate::auth::middleware::{admin_middleware, auth_middleware};
 use crate::AppState;
 
@@ -14,39 +17,145 @@ pub fn create_router(state: Arc<AppState>) -> Router<Arc<AppState>> {
     let public_routes = Router::new()
         // Auth
         .route("/auth/login", post(handlers::auth::login))
+        .route("/auth/refresh", post(handlers::auth::refresh))
         .route("/auth/nonce/:address", get(handlers::auth::get_nonce))
+        .route("/time", get(handlers::auth::get_server_time))
         // Markets (prediction market specific)
         .route("/markets", get(handlers::market::list_markets))
         .route("/markets/:market_id", get(handlers::market::get_market))
         .route("/markets/:market_id/orderbook", get(handlers::market::get_orderbook))
         .route("/markets/:market_id/trades", get(handlers::market::get_trades))
         .route("/markets/:market_id/ticker", get(handlers::market::get_ticker))
-        .route("/markets/:market_id/price", get(handlers::market::get_price));
+        .route("/markets/:market_id/price", get(handlers::market::get_price))
+        .route("/markets/:market_id/config", get(handlers::market::get_market_config))
+        .route("/markets/:market_id/risk-limits", get(handlers::market::get_risk_limits))
+        // Path segment is the `{market_id}:{outcome_id}:{share_type}` symbol
+        .route("/markets/:market_id/stats/history", get(handlers::market::get_stats_history))
+        .route("/markets/:market_id/settlement-prices", get(handlers::market::get_settlement_prices))
+        .route("/markets/:market_id/risk-disclosure", get(handlers::market::get_risk_disclosure))
+        .route("/market-data/keys", get(handlers::market::get_feed_keys))
+        .route("/market-data/index", get(handlers::market::get_index_prices))
+        .route("/market-data/symbols", get(handlers::market::get_symbols))
+        .route("/market-data/index/:instrument", get(handlers::market::get_index_price))
+        .route("/market-data/klines/:symbol", get(handlers::market::get_klines))
+        // Path segment is the `{market_id}:{outcome_id}` funding symbol
+        .route("/funding-rates/:symbol/predicted", get(handlers::funding::get_predicted_rate))
+        .route("/funding-rates/:symbol/premium-index", get(handlers::funding::get_premium_index))
+        .route("/fee-tiers", get(handlers::fees::list_tiers))
+        // Status page
+        .route("/system/health", get(handlers::system::get_health))
+        .route("/system/incidents", get(handlers::system::list_incidents))
+        // API changelog and deprecations
+        .route("/changelog", get(handlers::system::get_changelog))
+        // LP Vaults
+        .route("/vaults", get(handlers::vault::list_vaults))
+        .route("/vaults/:vault_id", get(handlers::vault::get_vault))
+        .layer(axum_middleware::from_fn_with_state(state.clone(), rate_limit_middleware));
 
     // Protected routes (auth required)
     let protected_routes = Router::new()
+        // Trading nonces
+        .route("/auth/nonce/trading", get(handlers::auth::get_trading_nonce))
+        .route("/auth/logout", post(handlers::auth::logout))
         // Account
         .route("/account/profile", get(handlers::account::get_profile))
         .route("/account/balances", get(handlers::account::get_balances))
         .route("/account/shares", get(handlers::account::get_shares))
         .route("/account/orders", get(handlers::account::get_orders))
         .route("/account/trades", get(handlers::account::get_trades))
+        .route("/account/pnl", get(handlers::account::get_pnl_history))
+        .route("/account/positions/history", get(handlers::account::get_position_history))
+        .route("/account/pnl/daily", get(handlers::portfolio::get_daily_pnl))
+        .route("/account/pnl/summary", get(handlers::portfolio::get_pnl_summary))
+        .route("/account/ledger", get(handlers::account::get_ledger))
+        .route("/account/margin", get(handlers::account::get_account_margin))
+        .route("/account/features", get(handlers::feature::get_features))
+        .route("/account/fee-tier", get(handlers::fees::get_account_tier))
+        .route("/account/preferences", get(handlers::account::get_preferences).put(handlers::account::set_preferences))
+        .route(
+            "/account/risk-profile",
+            get(handlers::account::get_risk_profile).put(handlers::account::set_risk_profile),
+        )
+        .route(
+            "/account/margin-calls",
+            get(handlers::margin_call::get_settings).put(handlers::margin_call::set_settings),
+        )
+        .route("/account/margin-calls/history", get(handlers::margin_call::get_history))
+        .route("/account/margin-mode", get(handlers::account::get_margin_mode).post(handlers::account::set_margin_mode))
+        .route("/positions/:position_id/margin-mode", post(handlers::account::set_position_margin_mode))
+        .route(
+            "/positions/:position_id/tp-sl",
+            get(handlers::tp_sl::get_tp_sl).put(handlers::tp_sl::set_tp_sl).delete(handlers::tp_sl::cancel_tp_sl),
+        )
+        // API keys
+        .route("/account/api-keys", get(handlers::api_keys::list_api_keys).post(handlers::api_keys::create_api_key))
+        .route("/account/api-keys/:id", delete(handlers::api_keys::revoke_api_key))
+        // Session keys
+        .route(
+            "/account/session-keys",
+            get(handlers::session_keys::list_session_keys).post(handlers::session_keys::register_session_key),
+        )
+        .route("/account/session-keys/:id", delete(handlers::session_keys::revoke_session_key))
+        // Sub-accounts
+        .route(
+            "/account/sub-accounts",
+            get(handlers::sub_account::list_sub_accounts).post(handlers::sub_account::create_sub_account),
+        )
+        .route("/account/sub-accounts/transfer", post(handlers::sub_account::transfer))
+        .route("/account/sub-accounts/transfers", get(handlers::sub_account::list_transfers))
+        .route("/account/sub-accounts/:id", put(handlers::sub_account::update_sub_account))
         // Settlement
         .route("/account/settle/:market_id", post(handlers::account::settle_market))
         .route("/account/settle/:market_id/status", get(handlers::account::get_settlement_status))
         // Orders
-        .route("/orders", post(handlers::order::create_order))
+        .route(
+            "/orders",
+            post(handlers::order::create_order)
+                .layer(axum_middleware::from_fn_with_state(state.clone(), idempotency_middleware)),
+        )
         .route("/orders/:order_id", get(handlers::order::get_order))
         .route("/orders/:order_id", delete(handlers::order::cancel_order))
+        .route("/orders/:order_id", put(handlers::order::amend_order))
         .route("/orders/batch", post(handlers::order::batch_cancel))
+        .route(
+            "/orders/cancel-all-after",
+            get(handlers::order::get_cancel_all_after).post(handlers::order::cancel_all_after),
+        )
+        // Referral settlement
+        .route("/referral/epochs", get(handlers::referral_epochs::get_epochs))
         // Deposits & Withdrawals
         .route("/deposit/prepare", post(handlers::deposit::prepare_deposit))
         .route("/deposit/history", get(handlers::deposit::get_history))
-        .route("/withdraw/request", post(handlers::withdraw::request_withdraw))
+        .route(
+            "/withdraw/request",
+            post(handlers::withdraw::request_withdraw)
+                .layer(axum_middleware::from_fn_with_state(state.clone(), idempotency_middleware)),
+        )
+        .route("/withdraw/quote", post(handlers::withdraw::quote_withdraw))
         .route("/withdraw/history", get(handlers::withdraw::get_history))
         .route("/withdraw/:id", get(handlers::withdraw::get_withdrawal))
         .route("/withdraw/:id/cancel", delete(handlers::withdraw::cancel_withdraw))
         .route("/withdraw/:id/confirm", post(handlers::withdraw::confirm_withdraw))
+        // LP Vaults
+        .route("/vaults/:vault_id/position", get(handlers::vault::get_position))
+        .route("/vaults/:vault_id/deposit", post(handlers::vault::deposit))
+        .route("/vaults/:vault_id/withdraw", post(handlers::vault::withdraw))
+        // RFQ block trades
+        .route("/rfq", post(handlers::rfq::create_request))
+        .route("/rfq/:rfq_id", get(handlers::rfq::get_request).delete(handlers::rfq::cancel_request))
+        .route("/rfq/:rfq_id/quotes", post(handlers::rfq::submit_quote))
+        // Trade tape exports
+        .route("/exports/trade-tape", post(handlers::tape::request_export))
+        .route("/exports/trade-tape/:export_id", get(handlers::tape::get_export))
+        .route("/exports/trade-tape/:export_id/manifest", get(handlers::tape::get_manifest))
+        .route("/exports/trade-tape/:export_id/download", get(handlers::tape::download_export))
+        // Account history exports
+        .route("/exports", post(handlers::export::create_export).get(handlers::export::list_exports))
+        .route("/exports/:export_id", get(handlers::export::get_export))
+        .route("/exports/:export_id/download", get(handlers::export::download_export))
+        .route("/account/export/tax", get(handlers::export::tax_export))
+        // Auth runs first so the limit applies per account
+        .layer(axum_middleware::from_fn_with_state(state.clone(), rate_limit_middleware))
         .layer(axum_middleware::from_fn_with_state(state.clone(), auth_middleware));
 
     // Admin routes (auth required + admin role check)
@@ -57,13 +166,87 @@ pub fn create_router(state: Arc<AppState>) -> Router<Arc<AppState>> {
         .route("/admin/markets/:market_id/cancel", post(handlers::market::cancel_market))
         .route("/admin/markets/:market_id/probability", post(handlers::market::update_probability))
         .route("/admin/markets/:market_id/refresh-probability", post(handlers::market::refresh_probability))
+        .route("/admin/markets/:market_id/calendar", put(handlers::market::set_trading_calendar))
+        .route(
+            "/admin/markets/:market_id/rules",
+            get(handlers::market_rules::get_rules).put(handlers::market_rules::set_rules),
+        )
+        .route("/admin/markets/:market_id/pause", post(handlers::market_rules::pause_market))
+        .route("/admin/markets/:market_id/resume", post(handlers::market_rules::resume_market))
+        .route("/admin/markets/:market_id/delist", post(handlers::market_rules::delist_market))
+        .route("/admin/fee-tiers", get(handlers::market_rules::list_fee_tiers))
+        .route("/admin/fee-tiers/:name", put(handlers::market_rules::upsert_fee_tier))
+        .route("/admin/vip-tiers/:level", put(handlers::fees::upsert_tier))
+        .route(
+            "/admin/users/:address/fee-tier",
+            put(handlers::fees::set_override).delete(handlers::fees::clear_override),
+        )
+        .route("/admin/users/:address/revoke-sessions", post(handlers::auth::revoke_sessions))
+        .route(
+            "/admin/market-data/klines/:symbol/gap-fill",
+            put(handlers::market::set_kline_gap_fill),
+        )
+        .route(
+            "/admin/mark-price/:symbol",
+            get(handlers::market::get_mark_price_config).put(handlers::market::set_mark_price_config),
+        )
+        .route("/admin/vaults", post(handlers::vault::create_vault))
+        .route("/admin/deposits", post(handlers::deposit::credit_deposit))
+        .route("/admin/rfq/makers", get(handlers::rfq::list_makers).post(handlers::rfq::register_maker))
+        .route("/admin/rfq/makers/:address", delete(handlers::rfq::deactivate_maker))
+        .route("/admin/features", get(handlers::feature::list_flags))
+        .route("/admin/features/:key", put(handlers::feature::upsert_flag))
+        .route("/admin/features/:key/grants", post(handlers::feature::grant_feature))
+        .route("/admin/features/:key/grants/:address", delete(handlers::feature::revoke_feature))
+        .route("/admin/funding/config", get(handlers::funding::list_configs))
+        .route("/admin/funding/config/:symbol", put(handlers::funding::update_config))
+        .route("/admin/funding/changes", get(handlers::funding::list_changes))
+        .route("/admin/funding/alerts", get(handlers::funding::list_alerts))
+        .route("/admin/jobs", get(handlers::jobs::list_jobs))
+        .route("/admin/jobs/:name", get(handlers::jobs::get_job))
+        .route("/admin/jobs/:name/run", post(handlers::jobs::run_job))
+        .route("/admin/config/reload", post(handlers::config::reload_config))
+        .route("/admin/config/changes", get(handlers::config::list_changes))
+        .route("/admin/surveillance/alerts", get(handlers::surveillance::list_alerts))
+        .route("/admin/surveillance/alerts/:id/review", post(handlers::surveillance::review_alert))
+        .route("/admin/incidents", post(handlers::system::open_incident))
+        .route("/admin/incidents/:id/updates", post(handlers::system::update_incident))
+        .route("/admin/replication", get(handlers::replication::get_status))
+        .route("/admin/replication/promote", post(handlers::replication::promote))
+        .route("/internal/mm/inventory", get(handlers::mm::get_inventory))
+        .route("/internal/mm/imbalance", get(handlers::mm::get_imbalance))
+        .route("/internal/mm/imbalance/decisions", get(handlers::mm::list_imbalance_decisions))
+        .route("/internal/mm/imbalance/:symbol", put(handlers::mm::set_imbalance_params))
+        .route("/internal/mm/quoting", get(handlers::mm::get_quoting))
+        .route("/internal/mm/quoting/:symbol", put(handlers::mm::set_quoting_params))
+        .route("/internal/mm/mirror", get(handlers::mm::get_mirror))
+        .route("/admin/withdrawal-fees", get(handlers::withdrawal_fees::list_policies))
+        .route("/admin/withdrawal-fees/:token", put(handlers::withdrawal_fees::upsert_policy))
+        .route("/admin/treasury", get(handlers::withdrawal_fees::get_treasury))
+        .route("/admin/withdrawals/reviews", get(handlers::withdrawal_reviews::list_queue))
+        .route("/admin/withdrawals/:id/reviews", get(handlers::withdrawal_reviews::get_history))
+        .route("/admin/withdrawals/:id/approve", post(handlers::withdrawal_reviews::approve))
+        .route("/admin/withdrawals/:id/reject", post(handlers::withdrawal_reviews::reject));
+
+    // Fault injection controls (chaos builds only)
+    #[cfg(feature = "chaos")]
+    let admin_routes = admin_routes
+        .route("/admin/chaos", get(handlers::chaos::list_faults).delete(handlers::chaos::reset_faults))
+        .route("/admin/chaos/:fault", put(handlers::chaos::set_fault));
+
+    let admin_routes = admin_routes
         // Admin middleware must come BEFORE auth middleware in the layer chain
         // (layers are applied in reverse order, so auth runs first, then admin)
         .layer(axum_middleware::from_fn(admin_middleware))
         .layer(axum_middleware::from_fn_with_state(state.clone(), auth_middleware));
 
+    // Standby feed: authenticated by the replication secret, not a session
+    let replication_routes =
+        Router::new().route("/internal/replication/entries", get(handlers::replication::list_entries));
+
     Router::new()
         .merge(public_routes)
         .merge(protected_routes)
         .merge(admin_routes)
+        .merge(replication_routes)
 }
@@ -0,0 +1,58 @@
+//! API v2 routes
+//!
+//! Same handlers as v1, served under `/api/v2` with response envelopes
+//! (see [`crate::api::envelope`]). Groups are migrated one at a time; v1 keeps
+//! serving every route unchanged.
+
+use axum::{
+    middleware as axum_middleware,
+    routing::{delete, get, post},
+    Router,
+};
+use std::sync::Arc;
+
+use crate::api::envelope::envelope_middleware;
+use crate::api::handlers;
+use crate::api::middleware::{idempotency_middleware, rate_limit_middleware};
+use crate::auth::middleware::auth_middleware;
+use crate::AppState;
+
+pub fn create_router(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    // Markets (public)
+    let market_routes = Router::new()
+        .route("/markets", get(handlers::market::list_markets))
+        .route("/markets/:market_id", get(handlers::market::get_market))
+        .route("/markets/:market_id/orderbook", get(handlers::market::get_orderbook))
+        .route("/markets/:market_id/trades", get(handlers::market::get_trades))
+        .route("/markets/:market_id/ticker", get(handlers::market::get_ticker))
+        .route("/markets/:market_id/price", get(handlers::market::get_price))
+        .layer(axum_middleware::from_fn_with_state(state.clone(), rate_limit_middleware));
+
+    // Account and orders (auth required)
+    let protected_routes = Router::new()
+        .route("/account/profile", get(handlers::account::get_profile))
+        .route("/account/balances", get(handlers::account::get_balances))
+        .route("/account/shares", get(handlers::account::get_shares))
+        .route("/account/orders", get(handlers::account::get_orders))
+        .route("/account/trades", get(handlers::account::get_trades))
+        .route("/account/pnl", get(handlers::account::get_pnl_history))
+        .route("/account/margin", get(handlers::account::get_account_margin))
+        .route("/account/features", get(handlers::feature::get_features))
+        .route("/account/margin-mode", get(handlers::account::get_margin_mode).post(handlers::account::set_margin_mode))
+        .route(
+            "/orders",
+            post(handlers::order::create_order)
+                .layer(axum_middleware::from_fn_with_state(state.clone(), idempotency_middleware)),
+        )
+        .route("/orders/:order_id", get(handlers::order::get_order))
+        .route("/orders/:order_id", delete(handlers::order::cancel_order))
+        .route("/orders/batch", post(handlers::order::batch_cancel))
+        .layer(axum_middleware::from_fn_with_state(state.clone(), rate_limit_middleware))
+        .layer(axum_middleware::from_fn_with_state(state.clone(), auth_middleware));
+
+    // Envelope the outermost response, including auth failures
+    Router::new()
+        .merge(market_routes)
+        .merge(protected_routes)
+        .layer(axum_middleware::from_fn_with_state(state, envelope_middleware))
+}
@@ -3,17 +3,30 @@ use ethers::abi::Token;
 use ethers::types::{Address, Signature, H256, U256};
 use ethers::utils::keccak256;
 use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
 use std::str::FromStr;
 use std::sync::OnceLock;
 
+use crate::services::session_keys::{SessionKeyService, SessionScope};
+
 /// EIP-712 Type Hashes
 pub const LOGIN_TYPEHASH: &str = "Login(address wallet,uint256 nonce,uint256 timestamp)";
 pub const CREATE_ORDER_TYPEHASH: &str = "CreateOrder(address wallet,string marketId,string outcomeId,string shareType,string side,string orderType,string price,string amount,uint256 timestamp)";
 pub const CANCEL_ORDER_TYPEHASH: &str = "CancelOrder(address wallet,string orderId,uint256 timestamp)";
+pub const AMEND_ORDER_TYPEHASH: &str = "AmendOrder(address wallet,string orderId,string price,string amount,uint256 timestamp)";
 pub const BATCH_CANCEL_TYPEHASH: &str = "BatchCancelOrders(address wallet,string orderIds,uint256 timestamp)";
+
+/// Type hashes of trading messages signed with a nonce (`GET /auth/nonce/trading`)
+pub const CREATE_ORDER_NONCE_TYPEHASH: &str = "CreateOrder(address wallet,string marketId,string outcomeId,string shareType,string side,string orderType,string price,string amount,uint256 nonce,uint256 timestamp)";
+pub const CANCEL_ORDER_NONCE_TYPEHASH: &str = "CancelOrder(address wallet,string orderId,uint256 nonce,uint256 timestamp)";
+pub const AMEND_ORDER_NONCE_TYPEHASH: &str = "AmendOrder(address wallet,string orderId,string price,string amount,uint256 nonce,uint256 timestamp)";
+pub const BATCH_CANCEL_NONCE_TYPEHASH: &str = "BatchCancelOrders(address wallet,string orderIds,uint256 nonce,uint256 timestamp)";
 pub const CREATE_REFERRAL_TYPEHASH: &str = "CreateReferralCode(address wallet,uint256 timestamp)";
 pub const BIND_REFERRAL_TYPEHASH: &str = "BindReferralCode(address wallet,string code,uint256 timestamp)";
 pub const WS_AUTH_TYPEHASH: &str = "WebSocketAuth(address wallet,uint256 timestamp)";
+pub const WITHDRAW_TYPEHASH: &str = "Withdraw(address wallet,string token,string amount,uint256 timestamp)";
+pub const WITHDRAW_NONCE_TYPEHASH: &str = "Withdraw(address wallet,string token,string amount,uint256 nonce,uint256 timestamp)";
+pub const SESSION_KEY_TYPEHASH: &str = "AuthorizeSessionKey(address wallet,address sessionKey,string scopes,uint256 expiresAt,uint256 timestamp)";
 
 /// Global EIP-712 domain configuration (initialized from AppConfig at startup)
 static DOMAIN: OnceLock<EIP712Domain> = OnceLock::new();
@@ -96,15 +109,18 @@ pub struct CreateOrderMessage {
     pub order_type: String,
     pub price: String,
     pub amount: String,
+    /// Trading nonce, signed when present
+    #[serde(default)]
+    pub nonce: Option<u64>,
     pub timestamp: u64,
 }
 
 impl CreateOrderMessage {
     pub fn struct_hash(&self) -> H256 {
-        let type_hash = keccak256(CREATE_ORDER_TYPEHASH.as_bytes());
+        let type_hash = keccak256(nonce_typehash(self.nonce, CREATE_ORDER_TYPEHASH, CREATE_ORDER_NONCE_TYPEHASH));
         let wallet_address = Address::from_str(&self.wallet).unwrap_or_default();
 
-        let encoded = ethers::abi::encode(&[
+        let encoded = encode_with_nonce(vec![
             Token::FixedBytes(type_hash.to_vec()),
             Token::Address(wallet_address),
             Token::FixedBytes(keccak256(self.market_id.as_bytes()).to_vec()),
@@ -114,8 +130,7 @@ impl CreateOrderMessage {
             Token::FixedBytes(keccak256(self.order_type.as_bytes()).to_vec()),
             Token::FixedBytes(keccak256(self.price.as_bytes()).to_vec()),
             Token::FixedBytes(keccak256(self.amount.as_bytes()).to_vec()),
-            Token::Uint(U256::from(self.timestamp)),
-        ]);
+        ], self.nonce, self.timestamp);
 
         H256::from(keccak256(&encoded))
     }
@@ -126,20 +141,52 @@ impl CreateOrderMessage {
 pub struct CancelOrderMessage {
     pub wallet: String,
     pub order_id: String,
+    /// Trading nonce, signed when present
+    #[serde(default)]
+    pub nonce: Option<u64>,
     pub timestamp: u64,
 }
 
 impl CancelOrderMessage {
     pub fn struct_hash(&self) -> H256 {
-        let type_hash = keccak256(CANCEL_ORDER_TYPEHASH.as_bytes());
+        let type_hash = keccak256(nonce_typehash(self.nonce, CANCEL_ORDER_TYPEHASH, CANCEL_ORDER_NONCE_TYPEHASH));
         let wallet_address = Address::from_str(&self.wallet).unwrap_or_default();
 
-        let encoded = ethers::abi::encode(&[
+        let encoded = encode_with_nonce(vec![
             Token::FixedBytes(type_hash.to_vec()),
             Token::Address(wallet_address),
             Token::FixedBytes(keccak256(self.order_id.as_bytes()).to_vec()),
-            Token::Uint(U256::from(self.timestamp)),
-        ]);
+        ], self.nonce, self.timestamp);
+
+        H256::from(keccak256(&encoded))
+    }
+}
+
+/// Amend Order message for EIP-712 signature verification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AmendOrderMessage {
+    pub wallet: String,
+    pub order_id: String,
+    pub price: String,
+    pub amount: String,
+    /// Trading nonce, signed when present
+    #[serde(default)]
+    pub nonce: Option<u64>,
+    pub timestamp: u64,
+}
+
+impl AmendOrderMessage {
+    pub fn struct_hash(&self) -> H256 {
+        let type_hash = keccak256(nonce_typehash(self.nonce, AMEND_ORDER_TYPEHASH, AMEND_ORDER_NONCE_TYPEHASH));
+        let wallet_address = Address::from_str(&self.wallet).unwrap_or_default();
+
+        let encoded = encode_with_nonce(vec![
+            Token::FixedBytes(type_hash.to_vec()),
+            Token::Address(wallet_address),
+            Token::FixedBytes(keccak256(self.order_id.as_bytes()).to_vec()),
+            Token::FixedBytes(keccak256(self.price.as_bytes()).to_vec()),
+            Token::FixedBytes(keccak256(self.amount.as_bytes()).to_vec()),
+        ], self.nonce, self.timestamp);
 
         H256::from(keccak256(&encoded))
     }
@@ -150,25 +197,44 @@ impl CancelOrderMessage {
 pub struct BatchCancelMessage {
     pub wallet: String,
     pub order_ids: String, // Comma-separated list of order IDs
+    /// Trading nonce, signed when present
+    #[serde(default)]
+    pub nonce: Option<u64>,
     pub timestamp: u64,
 }
 
 impl BatchCancelMessage {
     pub fn struct_hash(&self) -> H256 {
-        let type_hash = keccak256(BATCH_CANCEL_TYPEHASH.as_bytes());
+        let type_hash = keccak256(nonce_typehash(self.nonce, BATCH_CANCEL_TYPEHASH, BATCH_CANCEL_NONCE_TYPEHASH));
         let wallet_address = Address::from_str(&self.wallet).unwrap_or_default();
 
-        let encoded = ethers::abi::encode(&[
+        let encoded = encode_with_nonce(vec![
             Token::FixedBytes(type_hash.to_vec()),
             Token::Address(wallet_address),
             Token::FixedBytes(keccak256(self.order_ids.as_bytes()).to_vec()),
-            Token::Uint(U256::from(self.timestamp)),
-        ]);
+        ], self.nonce, self.timestamp);
 
         H256::from(keccak256(&encoded))
     }
 }
 
+/// Type string of a trading message, with or without the signed nonce
+fn nonce_typehash(nonce: Option<u64>, without: &'static str, with: &'static str) -> &'static [u8] {
+    match nonce {
+        Some(_) => with.as_bytes(),
+        None => without.as_bytes(),
+    }
+}
+
+/// ABI-encode message fields followed by the nonce (when signed) and timestamp
+fn encode_with_nonce(mut tokens: Vec<Token>, nonce: Option<u64>, timestamp: u64) -> Vec<u8> {
+    if let Some(nonce) = nonce {
+        tokens.push(Token::Uint(U256::from(nonce)));
+    }
+    tokens.push(Token::Uint(U256::from(timestamp)));
+    ethers::abi::encode(&tokens)
+}
+
 /// Create Referral Code message for EIP-712 signature verification
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateReferralMessage {
@@ -237,15 +303,64 @@ impl WebSocketAuthMessage {
     }
 }
 
-/// Withdraw message for signature verification (not yet implemented)
+/// Session key authorization message for EIP-712 signature verification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionKeyMessage {
+    pub wallet: String,
+    pub session_key: String,
+    /// Comma-separated scopes (place, amend, cancel)
+    pub scopes: String,
+    pub expires_at: u64,
+    pub timestamp: u64,
+}
+
+impl SessionKeyMessage {
+    pub fn struct_hash(&self) -> H256 {
+        let type_hash = keccak256(SESSION_KEY_TYPEHASH.as_bytes());
+        let wallet_address = Address::from_str(&self.wallet).unwrap_or_default();
+        let session_key = Address::from_str(&self.session_key).unwrap_or_default();
+
+        let encoded = ethers::abi::encode(&[
+            Token::FixedBytes(type_hash.to_vec()),
+            Token::Address(wallet_address),
+            Token::Address(session_key),
+            Token::FixedBytes(keccak256(self.scopes.as_bytes()).to_vec()),
+            Token::Uint(U256::from(self.expires_at)),
+            Token::Uint(U256::from(self.timestamp)),
+        ]);
+
+        H256::from(keccak256(&encoded))
+    }
+}
+
+/// Withdraw message for EIP-712 signature verification
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WithdrawMessage {
+    pub wallet: String,
     pub token: String,
     pub amount: String,
-    pub to_address: String,
+    /// Trading nonce, signed when present
+    #[serde(default)]
+    pub nonce: Option<u64>,
     pub timestamp: u64,
 }
 
+impl WithdrawMessage {
+    pub fn struct_hash(&self) -> H256 {
+        let type_hash = keccak256(nonce_typehash(self.nonce, WITHDRAW_TYPEHASH, WITHDRAW_NONCE_TYPEHASH));
+        let wallet_address = Address::from_str(&self.wallet).unwrap_or_default();
+
+        let encoded = encode_with_nonce(vec![
+            Token::FixedBytes(type_hash.to_vec()),
+            Token::Address(wallet_address),
+            Token::FixedBytes(keccak256(self.token.as_bytes()).to_vec()),
+            Token::FixedBytes(keccak256(self.amount.as_bytes()).to_vec()),
+        ], self.nonce, self.timestamp);
+
+        H256::from(keccak256(&encoded))
+    }
+}
+
 /// Verify EIP-712 typed data signature for login
 ///
 /// This follows the EIP-712 standard:
@@ -307,6 +422,17 @@ pub fn verify_cancel_order_signature(
     verify_typed_signature(domain, struct_hash, signature, expected_address)
 }
 
+/// Verify EIP-712 typed data signature for amending an order
+pub fn verify_amend_order_signature(
+    msg: &AmendOrderMessage,
+    signature: &str,
+    expected_address: &str,
+) -> anyhow::Result<bool> {
+    let domain = get_domain();
+    let struct_hash = msg.struct_hash();
+    verify_typed_signature(domain, struct_hash, signature, expected_address)
+}
+
 /// Verify EIP-712 typed data signature for batch canceling orders
 pub fn verify_batch_cancel_signature(
     msg: &BatchCancelMessage,
@@ -351,6 +477,50 @@ pub fn verify_ws_auth_signature(
     verify_typed_signature(domain, struct_hash, signature, expected_address)
 }
 
+/// Verify EIP-712 typed data signature for a withdrawal (the wallet's own,
+/// never a session key's)
+pub fn verify_withdraw_signature(
+    msg: &WithdrawMessage,
+    signature: &str,
+    expected_address: &str,
+) -> anyhow::Result<bool> {
+    let domain = get_domain();
+    let struct_hash = msg.struct_hash();
+    verify_typed_signature(domain, struct_hash, signature, expected_address)
+}
+
+/// Verify EIP-712 typed data signature for authorizing a session key
+pub fn verify_session_key_signature(
+    msg: &SessionKeyMessage,
+    signature: &str,
+    expected_address: &str,
+) -> anyhow::Result<bool> {
+    let domain = get_domain();
+    let struct_hash = msg.struct_hash();
+    verify_typed_signature(domain, struct_hash, signature, expected_address)
+}
+
+/// Verify a trading message signed by `owner` or by one of its session keys
+/// allowed to sign `scope`
+///
+/// The session key table is only consulted when the signature isn't the
+/// owner's.
+pub async fn verify_trading_signature(
+    pool: &PgPool,
+    struct_hash: H256,
+    signature: &str,
+    owner: &str,
+    scope: SessionScope,
+) -> anyhow::Result<bool> {
+    let result = verify_typed_signature_with_debug(get_domain(), struct_hash, signature, owner)?;
+    if result.is_valid {
+        return Ok(true);
+    }
+    let authorized =
+        SessionKeyService::is_authorized(pool, owner, &result.recovered_address, scope, chrono::Utc::now()).await?;
+    Ok(authorized)
+}
+
 /// Result of EIP-712 signature verification with debug info
 #[derive(Debug)]
 pub struct VerifyResult {
@@ -445,7 +615,7 @@ pub fn get_login_typed_data(wallet: &str, nonce: u64, timestamp: u64) -> serde_j
 pub fn get_create_order_typed_data(msg: &CreateOrderMessage) -> serde_json::Value {
     let domain = get_domain();
 
-    serde_json::json!({
+    let mut typed_data = serde_json::json!({
         "types": {
             "EIP712Domain": [
                 { "name": "name", "type": "string" },
@@ -483,7 +653,14 @@ pub fn get_create_order_typed_data(msg: &CreateOrderMessage) -> serde_json::Valu
             "amount": msg.amount,
             "timestamp": msg.timestamp.to_string()
         }
-    })
+    });
+    if let Some(nonce) = msg.nonce {
+        if let Some(fields) = typed_data["types"]["CreateOrder"].as_array_mut() {
+            fields.insert(fields.len() - 1, serde_json::json!({ "name": "nonce", "type": "uint256" }));
+        }
+        typed_data["message"]["nonce"] = serde_json::json!(nonce.to_string());
+    }
+    typed_data
 }
 
 fn compute_domain_separator(domain: &EIP712Domain) -> H256 {
@@ -519,4 +696,38 @@ mod tests {
         let separator = compute_domain_separator(&domain);
         assert!(!separator.is_zero());
     }
+
+    #[test]
+    fn test_nonce_is_signed_when_present() {
+        let cancel = |nonce| CancelOrderMessage {
+            wallet: "0xFDe43f8e6e082975d246844DEF4fE8E704403d43".to_string(),
+            order_id: "order-1".to_string(),
+            nonce,
+            timestamp: 1704067200,
+        };
+        // Without a nonce the legacy type hash is unchanged
+        let legacy = ethers::abi::encode(&[
+            Token::FixedBytes(keccak256(CANCEL_ORDER_TYPEHASH.as_bytes()).to_vec()),
+            Token::Address(Address::from_str("0xFDe43f8e6e082975d246844DEF4fE8E704403d43").unwrap()),
+            Token::FixedBytes(keccak256(b"order-1").to_vec()),
+            Token::Uint(U256::from(1704067200u64)),
+        ]);
+        assert_eq!(cancel(None).struct_hash(), H256::from(keccak256(legacy)));
+        assert_ne!(cancel(Some(1)).struct_hash(), cancel(None).struct_hash());
+        assert_ne!(cancel(Some(1)).struct_hash(), cancel(Some(2)).struct_hash());
+    }
+
+    #[test]
+    fn test_withdraw_hash_covers_amount() {
+        let withdraw = |amount: &str, nonce| WithdrawMessage {
+            wallet: "0xFDe43f8e6e082975d246844DEF4fE8E704403d43".to_string(),
+            token: "USDT".to_string(),
+            amount: amount.to_string(),
+            nonce,
+            timestamp: 1704067200,
+        };
+        assert_eq!(withdraw("10", None).struct_hash(), withdraw("10", None).struct_hash());
+        assert_ne!(withdraw("10", None).struct_hash(), withdraw("11", None).struct_hash());
+        assert_ne!(withdraw("10", Some(1)).struct_hash(), withdraw("10", None).struct_hash());
+    }
 }
@@ -1,12 +1,16 @@
 use chrono::{Duration, Utc};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, TokenData, Validation};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,       // User address
     pub exp: i64,          // Expiration time
     pub iat: i64,          // Issued at
+    /// Token id, the key of the revocation list (empty on tokens issued before it)
+    #[serde(default)]
+    pub jti: String,
 }
 
 pub struct JwtManager {
@@ -32,6 +36,11 @@ impl JwtManager {
     }
 
     pub fn generate_token(&self, address: &str) -> anyhow::Result<String> {
+        self.issue_token(address).map(|(token, _)| token)
+    }
+
+    /// Sign a new token and return it with its claims
+    pub fn issue_token(&self, address: &str) -> anyhow::Result<(String, Claims)> {
         let now = Utc::now();
         let exp = now + Duration::seconds(self.expiry_seconds as i64);
 
@@ -39,10 +48,11 @@ impl JwtManager {
             sub: address.to_lowercase(),
             exp: exp.timestamp(),
             iat: now.timestamp(),
+            jti: Uuid::new_v4().to_string(),
         };
 
         let token = encode(&Header::default(), &claims, &self.encoding_key)?;
-        Ok(token)
+        Ok((token, claims))
     }
 
     pub fn verify_token(&self, token: &str) -> anyhow::Result<Claims> {
@@ -65,5 +75,7 @@ mod tests {
         let claims = manager.verify_token(&token).unwrap();
 
         assert_eq!(claims.sub, address.to_lowercase());
+        assert!(!claims.jti.is_empty());
+        assert_ne!(manager.verify_token(&manager.generate_token(address).unwrap()).unwrap().jti, claims.jti);
     }
 }
@@ -1,13 +1,18 @@
 use axum::{
-    body::Body,
-    extract::State,
-    http::{header, Request, StatusCode},
+    body::{to_bytes, Body},
+    extract::{OriginalUri, State},
+    http::{header, HeaderMap, Method, Request, StatusCode},
     middleware::Next,
     response::Response,
 };
+use chrono::Utc;
 use std::sync::Arc;
+use uuid::Uuid;
 
-use crate::auth::jwt::JwtManager;
+use crate::auth::jwt::Claims;
+use crate::services::auth_tokens::AuthTokenError;
+use crate::services::api_keys::{signing_payload, ApiKeyError, ApiScope, SignedRequest};
+use crate::services::sub_account::{SubAccountError, SubAccountService};
 use crate::AppState;
 
 /// User role enum
@@ -19,7 +24,8 @@ pub enum UserRole {
 }
 
 impl UserRole {
-    pub fn from_str(s: &str) -> Self {
+    /// Role as stored in `users.role`; anything unknown is a plain user
+    pub fn parse(s: &str) -> Self {
         match s.to_lowercase().as_str() {
             "admin" => UserRole::Admin,
             "superadmin" => UserRole::SuperAdmin,
@@ -32,10 +38,60 @@ impl UserRole {
     }
 }
 
+/// Header selecting one of the caller's sub-accounts
+pub const SUB_ACCOUNT_HEADER: &str = "X-Sub-Account";
+
+/// API key request signing headers
+pub const API_KEY_HEADER: &str = "X-API-Key";
+pub const API_TIMESTAMP_HEADER: &str = "X-API-Timestamp";
+pub const API_SIGNATURE_HEADER: &str = "X-API-Signature";
+
+/// Largest body of an API key request
+const MAX_SIGNED_BODY_BYTES: usize = 1024 * 1024;
+
+/// Sub-account a request acts as
+#[derive(Clone, Debug)]
+pub struct SubAccountScope {
+    pub id: Uuid,
+    /// Wallet that owns the sub-account and signs its orders
+    pub owner: String,
+    pub can_trade: bool,
+}
+
+impl SubAccountScope {
+    /// Reads are always allowed; anything else needs trading permission
+    pub fn allows(&self, method: &Method) -> bool {
+        matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) || self.can_trade
+    }
+}
+
 #[derive(Clone)]
 pub struct AuthUser {
+    /// Account the request acts on (a sub-account's derived address inside one)
     pub address: String,
     pub role: UserRole,
+    pub sub_account: Option<SubAccountScope>,
+    /// API key the request was signed with
+    pub api_key: Option<Uuid>,
+    /// Access token the request was authenticated with
+    pub token: Option<Claims>,
+}
+
+impl AuthUser {
+    fn new(address: String, role: UserRole) -> Self {
+        Self {
+            address,
+            role,
+            sub_account: None,
+            api_key: None,
+            token: None,
+        }
+    }
+
+    /// Wallet whose signatures authorize the request
+    pub fn signer(&self) -> &str {
+        self.sub_account.as_ref().map_or(&self.address, |scope| &scope.owner)
+    }
 }
 
 pub async fn auth_middleware(
@@ -59,14 +115,22 @@ pub async fn auth_middleware(
             .headers()
             .get("X-Test-Role")
             .and_then(|h| h.to_str().ok())
-            .map(UserRole::from_str)
+            .map(UserRole::parse)
             .unwrap_or(UserRole::User);
 
         tracing::debug!("Auth disabled - using address: {}, role: {:?}", address, role);
-        request.extensions_mut().insert(AuthUser { address, role });
+        let selected = selected_sub_account(request.headers())?;
+        let auth_user = with_sub_account(&state, selected, request.method(), AuthUser::new(address, role)).await?;
+        tracing::Span::current().record("user", auth_user.address.as_str());
+        request.extensions_mut().insert(auth_user);
         return Ok(next.run(request).await);
     }
 
+    // API key requests are signed with HMAC instead of carrying a session token
+    if request.headers().contains_key(API_KEY_HEADER) {
+        return api_key_auth(state, request, next).await;
+    }
+
     // Extract token from Authorization header
     let auth_header = request
         .headers()
@@ -78,11 +142,15 @@ pub async fn auth_middleware(
         _ => return Err(StatusCode::UNAUTHORIZED),
     };
 
-    // Verify token
-    let jwt_manager = JwtManager::new(&state.config.jwt_secret, state.config.jwt_expiry_seconds);
-    let claims = jwt_manager
-        .verify_token(token)
-        .map_err(|_| StatusCode::UNAUTHORIZED)?;
+    // Verify token, refusing revoked ones
+    let claims = match state.auth_tokens.verify(&state.db.pool, token).await {
+        Ok(claims) => claims,
+        Err(AuthTokenError::DatabaseError(e)) => {
+            tracing::error!("Failed to check token revocation: {}", e);
+            return Err(StatusCode::SERVICE_UNAVAILABLE);
+        }
+        Err(_) => return Err(StatusCode::UNAUTHORIZED),
+    };
 
     let address = claims.sub.to_lowercase();
 
@@ -90,7 +158,14 @@ pub async fn auth_middleware(
     let role = fetch_user_role(&state.db.pool, &address).await;
 
     // Insert auth user into request extensions
-    request.extensions_mut().insert(AuthUser { address, role });
+    let selected = selected_sub_account(request.headers())?;
+    let user = AuthUser {
+        token: Some(claims),
+        ..AuthUser::new(address, role)
+    };
+    let auth_user = with_sub_account(&state, selected, request.method(), user).await?;
+    tracing::Span::current().record("user", auth_user.address.as_str());
+    request.extensions_mut().insert(auth_user);
 
     Ok(next.run(request).await)
 }
@@ -126,6 +201,132 @@ pub async fn admin_middleware(
     Ok(next.run(request).await)
 }
 
+/// Resolve the sub-account selected by the request, if any
+///
+/// The caller must own the sub-account and it must be active; inside it the
+/// request runs as a plain user on the sub-account's address.
+async fn with_sub_account(
+    state: &AppState,
+    selected: Option<Uuid>,
+    method: &Method,
+    user: AuthUser,
+) -> Result<AuthUser, StatusCode> {
+    let Some(id) = selected else {
+        return Ok(user);
+    };
+    let address = user.address;
+
+    let sub_account = match SubAccountService::get(&state.db.pool, &address, id).await {
+        Ok(sub_account) => sub_account,
+        Err(SubAccountError::NotFound(_)) => return Err(StatusCode::FORBIDDEN),
+        Err(e) => {
+            tracing::error!("Failed to resolve sub-account {} of {}: {}", id, address, e);
+            return Err(StatusCode::SERVICE_UNAVAILABLE);
+        }
+    };
+    if sub_account.status != "active" {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let scope = SubAccountScope {
+        id,
+        owner: address,
+        can_trade: sub_account.can_trade,
+    };
+    if !scope.allows(method) {
+        tracing::warn!("Sub-account {} of {} has no trading permission", id, scope.owner);
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(AuthUser {
+        address: sub_account.address,
+        role: UserRole::User,
+        sub_account: Some(scope),
+        api_key: user.api_key,
+        token: user.token,
+    })
+}
+
+/// Sub-account named by the request's header
+fn selected_sub_account(headers: &HeaderMap) -> Result<Option<Uuid>, StatusCode> {
+    headers
+        .get(SUB_ACCOUNT_HEADER)
+        .map(|header| header.to_str().ok().and_then(|s| s.trim().parse().ok()).ok_or(StatusCode::BAD_REQUEST))
+        .transpose()
+}
+
+/// Authenticate a request signed with an API key
+///
+/// The key's owner (or the sub-account it is bound to) becomes the caller;
+/// keys never carry an admin role. Writes need the `trade` scope.
+async fn api_key_auth(state: Arc<AppState>, request: Request<Body>, next: Next) -> Result<Response, StatusCode> {
+    let headers = request.headers();
+    let header = |name: &str| headers.get(name).and_then(|h| h.to_str().ok()).map(str::to_string);
+    let (Some(api_key), Some(timestamp), Some(signature)) = (
+        header(API_KEY_HEADER),
+        header(API_TIMESTAMP_HEADER).and_then(|t| t.trim().parse::<i64>().ok()),
+        header(API_SIGNATURE_HEADER),
+    ) else {
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+    let selected = selected_sub_account(request.headers())?;
+    let client_ip = state.rate_limiter.client_ip(&request);
+    let method = request.method().clone();
+    // Clients sign the full path, before the API version prefix is stripped
+    let path_and_query = request
+        .extensions()
+        .get::<OriginalUri>()
+        .map_or(request.uri(), |uri| &uri.0)
+        .path_and_query()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_default();
+
+    let (parts, body) = request.into_parts();
+    let body = to_bytes(body, MAX_SIGNED_BODY_BYTES).await.map_err(|_| StatusCode::PAYLOAD_TOO_LARGE)?;
+    let payload = signing_payload(timestamp, method.as_str(), &path_and_query, &body);
+    let signed = SignedRequest {
+        api_key: &api_key,
+        timestamp,
+        signature: &signature,
+        payload: &payload,
+        client_ip: &client_ip,
+    };
+    let key = match state.api_keys.authenticate(&state.db.pool, signed, Utc::now()).await {
+        Ok(key) => key,
+        Err(ApiKeyError::DatabaseError(e)) => {
+            tracing::error!("Failed to authenticate API key request: {}", e);
+            return Err(StatusCode::SERVICE_UNAVAILABLE);
+        }
+        Err(e @ ApiKeyError::IpNotAllowed(_)) => {
+            tracing::warn!("API key request refused: {}", e);
+            return Err(StatusCode::FORBIDDEN);
+        }
+        Err(e) => {
+            tracing::debug!("API key request refused: {}", e);
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+    };
+
+    let allowed = match method {
+        Method::GET | Method::HEAD | Method::OPTIONS => key.has_scope(ApiScope::Read) || key.has_scope(ApiScope::Trade),
+        _ => key.has_scope(ApiScope::Trade),
+    };
+    if !allowed {
+        tracing::warn!("API key {} of {} lacks the scope for {}", key.id, key.owner_address, method);
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let user = AuthUser {
+        api_key: Some(key.id),
+        ..AuthUser::new(key.owner_address, UserRole::User)
+    };
+    let auth_user = with_sub_account(&state, key.sub_account_id.or(selected), &method, user).await?;
+    tracing::Span::current().record("user", auth_user.address.as_str());
+
+    let mut request = Request::from_parts(parts, Body::from(body));
+    request.extensions_mut().insert(auth_user);
+    Ok(next.run(request).await)
+}
+
 /// Fetch user role from database
 async fn fetch_user_role(pool: &sqlx::PgPool, address: &str) -> UserRole {
     let result: Option<(String,)> = sqlx::query_as(
@@ -138,7 +339,7 @@ async fn fetch_user_role(pool: &sqlx::PgPool, address: &str) -> UserRole {
     .flatten();
 
     match result {
-        Some((role_str,)) => UserRole::from_str(&role_str),
+        Some((role_str,)) => UserRole::parse(&role_str),
         None => UserRole::User,
     }
 }
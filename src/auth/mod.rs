@@ -1,9 +1,9 @@
 pub mod eip712;
 pub mod jwt;
 pub mod middleware;
-// pub mod rate_limit; // Rate limiting handled by nginx for HFT performance
+pub mod replay;
+pub mod server_time;
 
 // pub use eip712::*;
 // pub use jwt::*;
 // pub use middleware::*;
-// pub use rate_limit::*;
@@ -0,0 +1,122 @@
+//! Server Time Tokens
+//!
+//! Signed request timestamps are normally checked against the server's wall
+//! clock, which rejects clients whose clocks have drifted (common on mobile).
+//! `GET /time` hands out the server time together with a signed timestamp
+//! token; a request carrying a valid, unexpired token is validated against the
+//! token's time instead of the server clock.
+
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+
+/// Maximum allowed difference between a request timestamp and the reference time (seconds)
+pub const TIMESTAMP_TOLERANCE_SECS: u64 = 300;
+
+/// Lifetime of a server time token (seconds)
+pub const TIME_TOKEN_TTL_SECS: u64 = 900;
+
+/// Token purpose claim, so login JWTs cannot be used as time tokens
+const TIME_TOKEN_PURPOSE: &str = "server_time";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TimeTokenClaims {
+    /// Server time at issue (unix seconds)
+    pub ts: u64,
+    /// Expiration time (unix seconds)
+    pub exp: i64,
+    /// Always "server_time"
+    pub purpose: String,
+}
+
+/// Current server time in unix seconds
+pub fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Issue a signed token for the given server time
+pub fn issue_time_token(secret: &str, now: u64) -> anyhow::Result<String> {
+    let claims = TimeTokenClaims {
+        ts: now,
+        exp: (now + TIME_TOKEN_TTL_SECS) as i64,
+        purpose: TIME_TOKEN_PURPOSE.to_string(),
+    };
+    let token = encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_bytes()))?;
+    Ok(token)
+}
+
+/// Verify a time token and return the server time it was issued at
+pub fn verify_time_token(token: &str, secret: &str) -> anyhow::Result<u64> {
+    let data = decode::<TimeTokenClaims>(
+        token,
+        &DecodingKey::from_secret(secret.as_bytes()),
+        &Validation::default(),
+    )?;
+    if data.claims.purpose != TIME_TOKEN_PURPOSE {
+        anyhow::bail!("not a server time token");
+    }
+    Ok(data.claims.ts)
+}
+
+/// Validate a signed request timestamp (unix seconds)
+///
+/// Accepts the timestamp if it is within tolerance of the server clock, or,
+/// when a valid time token is supplied, within tolerance of the token's time.
+pub fn validate_request_timestamp(timestamp: u64, time_token: Option<&str>, secret: &str) -> bool {
+    if now_secs().abs_diff(timestamp) <= TIMESTAMP_TOLERANCE_SECS {
+        return true;
+    }
+
+    match time_token.map(|token| verify_time_token(token, secret)) {
+        Some(Ok(issued_at)) => issued_at.abs_diff(timestamp) <= TIMESTAMP_TOLERANCE_SECS,
+        Some(Err(e)) => {
+            tracing::debug!("Rejected server time token: {}", e);
+            false
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "test_secret";
+
+    #[test]
+    fn test_wall_clock_within_tolerance() {
+        assert!(validate_request_timestamp(now_secs() - 10, None, SECRET));
+        assert!(!validate_request_timestamp(now_secs() - 3600, None, SECRET));
+    }
+
+    #[test]
+    fn test_skewed_timestamp_accepted_with_token() {
+        // Token issued 10 minutes ago; client signed with that server time
+        let issued_at = now_secs() - 600;
+        let token = issue_time_token(SECRET, issued_at).unwrap();
+
+        assert_eq!(verify_time_token(&token, SECRET).unwrap(), issued_at);
+        assert!(validate_request_timestamp(issued_at + 5, Some(&token), SECRET));
+        assert!(!validate_request_timestamp(issued_at - 3600, Some(&token), SECRET));
+        assert!(!validate_request_timestamp(issued_at, Some(&token), "other_secret"));
+    }
+
+    #[test]
+    fn test_expired_token_rejected() {
+        let issued_at = now_secs() - TIME_TOKEN_TTL_SECS - 600;
+        let token = issue_time_token(SECRET, issued_at).unwrap();
+
+        assert!(verify_time_token(&token, SECRET).is_err());
+        assert!(!validate_request_timestamp(issued_at, Some(&token), SECRET));
+    }
+
+    #[test]
+    fn test_login_jwt_is_not_a_time_token() {
+        let jwt = super::super::jwt::JwtManager::new(SECRET, 3600)
+            .generate_token("0xabc")
+            .unwrap();
+        assert!(verify_time_token(&jwt, SECRET).is_err());
+    }
+}
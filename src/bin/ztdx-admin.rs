@@ -0,0 +1,506 @@
+//! Operations CLI
+//!
+//! Runs incident and maintenance tasks against the same database and services
+//! as the server, so operators don't write SQL by hand:
+//!
+//! ```text
+//! ztdx-admin migrate
+//! ztdx-admin replay-klines --from <RFC3339> [--to <RFC3339>] [--period 1m|5m|15m|1h|4h|1d|1w|1M]
+//! ztdx-admin check-klines --from <RFC3339> [--to <RFC3339>] [--period <period>] [--symbol <symbol>]
+//! ztdx-admin reconcile [--journal <path>] [--apply]
+//! ztdx-admin cancel-orders <address>
+//! ztdx-admin inspect [--journal <path>] [--symbol <market key>] [--depth <n>]
+//! ztdx-admin replay-journal [--journal <path>]
+//! ztdx-admin repair-deposits [--apply]
+//! ```
+//!
+//! Configuration is read from `.env` / the environment, as for the server.
+//! Engine state is rebuilt from the journal (`ENGINE_JOURNAL_PATH` unless
+//! `--journal` is given).
+//!
+//! `cancel-orders` and `reconcile --apply` change orders in the database
+//! only, while a running engine keeps its own book: they refuse to run while
+//! a server instance holds the background job leader lock. Stop the servers
+//! first.
+
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use polymarket_backend::config::AppConfig;
+use polymarket_backend::db::timescale::{KlinePeriod, TimescaleOps};
+use polymarket_backend::db::Database;
+use polymarket_backend::services::deposit::DepositService;
+use polymarket_backend::services::leader::{LeaderElection, JOBS_LEADER_KEY};
+use polymarket_backend::services::matching::{
+    find_mismatches, JournalConfig, MatchingEngine, OrderReconciler, ReconcileConfig, ReconcilePolicy,
+};
+
+const USAGE: &str = "usage: ztdx-admin <migrate | replay-klines | check-klines | reconcile | cancel-orders | inspect \
+                     | replay-journal | repair-deposits> [options]";
+
+/// A parsed command line
+#[derive(Debug, PartialEq)]
+enum Command {
+    Migrate,
+    ReplayKlines {
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        periods: Vec<KlinePeriod>,
+    },
+    CheckKlines {
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        periods: Vec<KlinePeriod>,
+        symbol: Option<String>,
+    },
+    Reconcile {
+        journal: PathBuf,
+        apply: bool,
+    },
+    CancelOrders {
+        address: String,
+    },
+    Inspect {
+        journal: PathBuf,
+        symbol: Option<String>,
+        depth: usize,
+    },
+    ReplayJournal {
+        journal: PathBuf,
+    },
+    RepairDeposits {
+        apply: bool,
+    },
+}
+
+impl Command {
+    /// Parse `<command> [options]`
+    fn parse(args: &[String]) -> anyhow::Result<Self> {
+        let Some((command, args)) = args.split_first() else {
+            anyhow::bail!(USAGE);
+        };
+        Ok(match command.as_str() {
+            "migrate" => Command::Migrate,
+            "replay-klines" => {
+                let (from, to) = time_range(args)?;
+                Command::ReplayKlines {
+                    from,
+                    to,
+                    periods: periods(args)?,
+                }
+            }
+            "check-klines" => {
+                let (from, to) = time_range(args)?;
+                Command::CheckKlines {
+                    from,
+                    to,
+                    periods: periods(args)?,
+                    symbol: option(args, "--symbol").map(str::to_string),
+                }
+            }
+            "reconcile" => Command::Reconcile {
+                journal: journal_path(args),
+                apply: flag(args, "--apply"),
+            },
+            "cancel-orders" => Command::CancelOrders {
+                address: args
+                    .first()
+                    .filter(|arg| !arg.starts_with("--"))
+                    .ok_or_else(|| anyhow::anyhow!("cancel-orders requires an address"))?
+                    .to_lowercase(),
+            },
+            "inspect" => Command::Inspect {
+                journal: journal_path(args),
+                symbol: option(args, "--symbol").map(str::to_string),
+                depth: option(args, "--depth").map(str::parse).transpose()?.unwrap_or(5),
+            },
+            "replay-journal" => Command::ReplayJournal {
+                journal: journal_path(args),
+            },
+            "repair-deposits" => Command::RepairDeposits {
+                apply: flag(args, "--apply"),
+            },
+            _ => anyhow::bail!(USAGE),
+        })
+    }
+
+    /// Needs no database
+    fn is_offline(&self) -> bool {
+        matches!(self, Command::Inspect { .. } | Command::ReplayJournal { .. })
+    }
+
+    /// Changes orders a running engine also holds
+    fn needs_stopped_server(&self) -> bool {
+        matches!(self, Command::CancelOrders { .. } | Command::Reconcile { apply: true, .. })
+    }
+}
+
+/// Refuse while a server instance leads (`leader` holds the job leader lock)
+fn ensure_server_stopped(leader: Option<String>) -> anyhow::Result<()> {
+    match leader {
+        Some(instance) => anyhow::bail!(
+            "server instance {} is running and its engine holds the orders; stop the servers first",
+            instance
+        ),
+        None => Ok(()),
+    }
+}
+
+/// Value following `--name`
+fn option<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|pos| args.get(pos + 1))
+        .map(String::as_str)
+}
+
+fn flag(args: &[String], name: &str) -> bool {
+    args.iter().any(|arg| arg == name)
+}
+
+fn parse_time(value: &str) -> anyhow::Result<DateTime<Utc>> {
+    Ok(DateTime::parse_from_rfc3339(value)
+        .map_err(|e| anyhow::anyhow!("invalid time {}: {}", value, e))?
+        .with_timezone(&Utc))
+}
+
+/// `--from` (required) and `--to` (default now)
+fn time_range(args: &[String]) -> anyhow::Result<(DateTime<Utc>, DateTime<Utc>)> {
+    let from = parse_time(option(args, "--from").ok_or_else(|| anyhow::anyhow!("--from is required"))?)?;
+    let to = option(args, "--to").map(parse_time).transpose()?.unwrap_or_else(Utc::now);
+    if from >= to {
+        anyhow::bail!("--from must be before --to");
+    }
+    Ok((from, to))
+}
+
+/// `--period`, or all periods
+fn periods(args: &[String]) -> anyhow::Result<Vec<KlinePeriod>> {
+    match option(args, "--period") {
+        Some(period) => Ok(vec![
+            KlinePeriod::parse(period).ok_or_else(|| anyhow::anyhow!("unknown period {}", period))?
+        ]),
+        None => Ok(KlinePeriod::ALL.to_vec()),
+    }
+}
+
+fn journal_path(args: &[String]) -> PathBuf {
+    option(args, "--journal")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| JournalConfig::from_env().path)
+}
+
+/// Rebuild engine state from a journal
+fn replay(path: &std::path::Path) -> anyhow::Result<MatchingEngine> {
+    let engine = MatchingEngine::new();
+    let entries = engine.replay_journal(path)?;
+    println!("Replayed {} journal entries from {}", entries, path.display());
+    Ok(engine)
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "polymarket_backend=info".into()),
+        )
+        .init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = Command::parse(&args)?;
+
+    // Offline: needs only the journal
+    if command.is_offline() {
+        return match command {
+            Command::Inspect { journal, symbol, depth } => inspect(&journal, symbol.as_deref(), depth),
+            Command::ReplayJournal { journal } => replay_journal(&journal),
+            _ => unreachable!("online command"),
+        };
+    }
+
+    dotenvy::dotenv().ok();
+    let config = AppConfig::load()?;
+    let db = Database::connect(&config.database_url).await?;
+
+    if command.needs_stopped_server() {
+        let election = LeaderElection::new(db.pool.clone(), JOBS_LEADER_KEY, "ztdx-admin");
+        ensure_server_stopped(election.current_leader().await?)?;
+    }
+
+    match command {
+        Command::Migrate => migrate(&db).await,
+        Command::ReplayKlines { from, to, periods } => replay_klines(&db, from, to, periods).await,
+        Command::CheckKlines {
+            from,
+            to,
+            periods,
+            symbol,
+        } => check_klines(&db, from, to, periods, symbol.as_deref()).await,
+        Command::Reconcile { journal, apply } => reconcile(&config, &db, &journal, apply).await,
+        Command::CancelOrders { address } => cancel_orders(&config, &db, &address).await,
+        Command::RepairDeposits { apply } => repair_deposits(&db, apply).await,
+        Command::Inspect { .. } | Command::ReplayJournal { .. } => unreachable!("offline command"),
+    }
+}
+
+/// Apply pending migrations from `migrations/`
+async fn migrate(db: &Database) -> anyhow::Result<()> {
+    sqlx::migrate!("./migrations").run(&db.pool).await?;
+    println!("Migrations up to date");
+    Ok(())
+}
+
+/// Rebuild K-line aggregates from the trades in a time range, e.g. after
+/// trades were backfilled or corrected. The 1m candles are refreshed before
+/// the rollups built from them.
+async fn replay_klines(
+    db: &Database,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    periods: Vec<KlinePeriod>,
+) -> anyhow::Result<()> {
+    let timescale = TimescaleOps::new(db.pool.clone());
+    for period in periods {
+        timescale.refresh_continuous_aggregate(period, from, to).await?;
+        println!("Refreshed {} from {} to {}", period.table_name(), from, to);
+    }
+    Ok(())
+}
+
+/// Compare stored K-line rollups with the 1m candles they are built from.
+/// Exits with an error if any bucket differs; `replay-klines` over the
+/// reported range repairs them while the 1m candles are retained.
+async fn check_klines(
+    db: &Database,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    periods: Vec<KlinePeriod>,
+    symbol: Option<&str>,
+) -> anyhow::Result<()> {
+    let timescale = TimescaleOps::new(db.pool.clone());
+    let mut total = 0;
+    for period in periods.into_iter().filter(|period| !period.is_base()) {
+        let mismatches = timescale.check_rollup(period, symbol, from, to).await?;
+        println!("{}: {} mismatched buckets", period.table_name(), mismatches.len());
+        for m in &mismatches {
+            println!(
+                "  {} {} close {:?}/{:?} volume {:?}/{:?} trades {:?}/{:?} (derived/stored)",
+                m.symbol,
+                m.bucket,
+                m.derived_close,
+                m.stored_close,
+                m.derived_volume,
+                m.stored_volume,
+                m.derived_trade_count,
+                m.stored_trade_count
+            );
+        }
+        total += mismatches.len();
+    }
+    if total > 0 {
+        anyhow::bail!("{} K-line buckets differ from the 1m candles", total);
+    }
+    Ok(())
+}
+
+/// Compare open orders in the database with the engine state in the journal.
+/// `--apply` cancels database orders the journal doesn't hold (servers
+/// stopped).
+async fn reconcile(config: &AppConfig, db: &Database, journal: &std::path::Path, apply: bool) -> anyhow::Result<()> {
+    let engine = replay(journal)?;
+    let grace_secs = config.order_reconcile_grace_secs.max(0);
+
+    if apply {
+        let reconciler = OrderReconciler::new(ReconcileConfig {
+            policy: ReconcilePolicy::Cancel,
+            grace_secs,
+            token: config.collateral_symbol().to_string(),
+        });
+        let report = reconciler.sweep(&db.pool, &engine, true).await?;
+        println!("{:?}", report);
+        return Ok(());
+    }
+
+    let db_orders = OrderReconciler::open_orders(&db.pool, None).await?;
+    let mismatches = find_mismatches(db_orders, engine.resting_orders(), Utc::now().timestamp_millis(), grace_secs * 1000);
+    println!("Missing in engine: {}", mismatches.missing_in_engine.len());
+    for order in &mismatches.missing_in_engine {
+        println!("  {} {} {} {} @ {}", order.id, order.user_address, order.market_key(), order.remaining_amount(), order.price);
+    }
+    println!("Orphaned in engine: {}", mismatches.orphaned_in_engine.len());
+    for (symbol, order) in &mismatches.orphaned_in_engine {
+        println!("  {} {} {}", order.id, order.user_address, symbol);
+    }
+    if !mismatches.missing_in_engine.is_empty() {
+        println!("Dry run; re-run with --apply (server stopped) to cancel orders missing in engine");
+    }
+    Ok(())
+}
+
+/// Cancel all open orders of an account and release their collateral
+/// (servers stopped)
+async fn cancel_orders(config: &AppConfig, db: &Database, address: &str) -> anyhow::Result<()> {
+    let reconciler = OrderReconciler::new(ReconcileConfig {
+        policy: ReconcilePolicy::Cancel,
+        grace_secs: 0,
+        token: config.collateral_symbol().to_string(),
+    });
+    let cancelled = reconciler.cancel_user_orders(&db.pool, address).await?;
+    println!("Cancelled {} orders of {}", cancelled.len(), address);
+    for order_id in cancelled {
+        println!("  {}", order_id);
+    }
+    Ok(())
+}
+
+/// Print engine state rebuilt from a journal
+fn inspect(journal: &std::path::Path, symbol: Option<&str>, depth: usize) -> anyhow::Result<()> {
+    let engine = replay(journal)?;
+    let stats = engine.stats();
+    println!("last_sequence: {}", stats.last_sequence);
+    println!("resting orders: {}", stats.total_orders_in_book);
+
+    let symbols = match symbol {
+        Some(symbol) => vec![symbol.to_string()],
+        None => {
+            let mut symbols = engine.orderbook_symbols();
+            symbols.sort();
+            symbols
+        }
+    };
+    for symbol in symbols {
+        let book = engine.get_orderbook(&symbol, depth)?;
+        println!("{} (last price {:?})", book.symbol, book.last_price);
+        for [price, amount] in book.asks.iter().rev() {
+            println!("  ask {:>12} {:>16}", price, amount);
+        }
+        for [price, amount] in &book.bids {
+            println!("  bid {:>12} {:>16}", price, amount);
+        }
+    }
+    Ok(())
+}
+
+/// Replay a journal into a fresh engine and report the resulting state
+fn replay_journal(journal: &std::path::Path) -> anyhow::Result<()> {
+    let engine = replay(journal)?;
+    let stats = engine.stats();
+    println!("  last_sequence:        {}", stats.last_sequence);
+    println!("  orderbooks:           {}", stats.symbols_count);
+    println!("  resting orders:       {}", stats.total_orders_in_book);
+    println!("  total bid depth:      {}", stats.total_bid_depth);
+    println!("  total ask depth:      {}", stats.total_ask_depth);
+    println!("  trades reproduced:    {}", stats.total_trades_recorded);
+    Ok(())
+}
+
+/// List deposits credited more than once; `--apply` reverses the excess
+async fn repair_deposits(db: &Database, apply: bool) -> anyhow::Result<()> {
+    let found = DepositService::find_double_credits(&db.pool).await?;
+    println!("Found {} double-credited deposits", found.len());
+    for double_credit in &found {
+        println!(
+            "  {} {} {}: deposited {}, credited {} ({} entries), excess {}",
+            double_credit.deposit_id,
+            double_credit.user_address,
+            double_credit.token,
+            double_credit.amount,
+            double_credit.credited,
+            double_credit.entries,
+            double_credit.excess()
+        );
+        if apply {
+            match DepositService::repair(&db.pool, double_credit).await? {
+                Some(entry) => println!("    reversed in ledger entry {}", entry.id),
+                None => println!("    already reversed"),
+            }
+        }
+    }
+    if !apply && !found.is_empty() {
+        println!("Dry run; re-run with --apply to reverse the excess");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> anyhow::Result<Command> {
+        let args: Vec<String> = line.split_whitespace().map(str::to_string).collect();
+        Command::parse(&args)
+    }
+
+    #[test]
+    fn test_parses_commands_and_options() {
+        assert_eq!(parse("migrate").unwrap(), Command::Migrate);
+        assert_eq!(
+            parse("check-klines --from 2026-10-01T00:00:00Z --to 2026-10-02T00:00:00Z --period 1h --symbol BTC").unwrap(),
+            Command::CheckKlines {
+                from: parse_time("2026-10-01T00:00:00Z").unwrap(),
+                to: parse_time("2026-10-02T00:00:00Z").unwrap(),
+                periods: vec![KlinePeriod::OneHour],
+                symbol: Some("BTC".to_string()),
+            }
+        );
+        assert_eq!(
+            parse("inspect --journal /tmp/j --depth 3").unwrap(),
+            Command::Inspect {
+                journal: PathBuf::from("/tmp/j"),
+                symbol: None,
+                depth: 3,
+            }
+        );
+        assert_eq!(
+            parse("replay-journal --journal /tmp/j").unwrap(),
+            Command::ReplayJournal {
+                journal: PathBuf::from("/tmp/j")
+            }
+        );
+        assert_eq!(parse("repair-deposits --apply").unwrap(), Command::RepairDeposits { apply: true });
+        assert_eq!(parse("repair-deposits").unwrap(), Command::RepairDeposits { apply: false });
+    }
+
+    #[test]
+    fn test_rejects_bad_command_lines() {
+        assert!(parse("").is_err());
+        assert!(parse("drop-tables").is_err());
+        assert!(parse("replay-klines").is_err());
+        assert!(parse("replay-klines --from 2026-10-02T00:00:00Z --to 2026-10-01T00:00:00Z").is_err());
+        assert!(parse("replay-klines --from 2026-10-01T00:00:00Z --period 2h").is_err());
+        assert!(parse("inspect --journal /tmp/j --depth deep").is_err());
+        assert!(parse("cancel-orders").is_err());
+        assert!(parse("cancel-orders --apply").is_err());
+    }
+
+    #[test]
+    fn test_order_writes_need_stopped_servers() {
+        let cancel = parse("cancel-orders 0xABC").unwrap();
+        assert_eq!(
+            cancel,
+            Command::CancelOrders {
+                address: "0xabc".to_string()
+            }
+        );
+        assert!(cancel.needs_stopped_server());
+
+        let apply = parse("reconcile --journal /tmp/j --apply").unwrap();
+        assert!(apply.needs_stopped_server());
+        // A dry run only reads
+        let dry_run = parse("reconcile --journal /tmp/j").unwrap();
+        assert_eq!(
+            dry_run,
+            Command::Reconcile {
+                journal: PathBuf::from("/tmp/j"),
+                apply: false
+            }
+        );
+        assert!(!dry_run.needs_stopped_server());
+        assert!(!parse("repair-deposits --apply").unwrap().needs_stopped_server());
+        assert!(parse("inspect --journal /tmp/j").unwrap().is_offline());
+
+        assert!(ensure_server_stopped(None).is_ok());
+        let err = ensure_server_stopped(Some("api-1".to_string())).unwrap_err();
+        assert!(err.to_string().contains("api-1"));
+    }
+}
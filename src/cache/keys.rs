@@ -158,6 +158,11 @@ impl CacheKey {
         format!("{}:{}", prefix::NONCE, address.to_lowercase())
     }
 
+    /// Key for the trading nonce counter: nonce:trading:{address}
+    pub fn trading_nonce(address: &str) -> String {
+        format!("{}:trading:{}", prefix::NONCE, address.to_lowercase())
+    }
+
     // ==================== Rate Limit Keys ====================
 
     /// Key for IP rate limit: rate:ip:{ip}
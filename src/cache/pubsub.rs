@@ -137,8 +137,7 @@ pub struct Subscription {
     pub channel: String,
 }
 
-/// Pub/Sub subscriber (placeholder implementation)
-/// Full implementation requires dedicated pub/sub connection
+/// Pub/Sub subscriber; each subscription uses a dedicated connection
 pub struct Subscriber {
     redis_url: String,
     config: SubscriberConfig,
@@ -172,6 +171,18 @@ impl Subscriber {
         }
     }
 
+    /// Open a dedicated pub/sub connection subscribed to channel patterns
+    /// (`channel:trades:*`). Messages arrive on `into_on_message()`; the
+    /// stream ends when the connection drops.
+    pub async fn psubscribe(&self, patterns: &[String]) -> Result<redis::aio::PubSub, RedisError> {
+        let client = redis::Client::open(self.redis_url.as_str())?;
+        let mut pubsub = client.get_async_connection().await?.into_pubsub();
+        for pattern in patterns {
+            pubsub.psubscribe(pattern).await?;
+        }
+        Ok(pubsub)
+    }
+
     /// Get list of channels for market data
     pub fn get_market_channels(symbol: &str) -> Vec<String> {
         vec![
@@ -9,6 +9,8 @@ use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::RwLock;
 
+use crate::chaos::{self, Fault};
+
 /// Redis connection configuration
 #[derive(Debug, Clone)]
 pub struct RedisConfig {
@@ -84,6 +86,9 @@ impl RedisClient {
 
     /// Get connection manager, reconnecting if necessary
     pub async fn get_connection(&self) -> Result<ConnectionManager, RedisError> {
+        if chaos::fails(Fault::RedisOutage) {
+            return Err(RedisError::from((redis::ErrorKind::IoError, "Injected Redis outage")));
+        }
         self.ensure_connected().await?;
         let conn = self.connection.read().await;
         conn.clone().ok_or_else(|| {
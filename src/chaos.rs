@@ -0,0 +1,191 @@
+//! Chaos Injection
+//!
+//! Test-only fault injection for resilience testing of the background
+//! workers. Faults are only compiled in with the `chaos` cargo feature;
+//! without it every hook below is a no-op.
+//!
+//! | Fault              | Hook                                              |
+//! |--------------------|---------------------------------------------------|
+//! | `drop_trade_event` | trade persistence worker skips the event          |
+//! | `delay_db_write`   | trade persistence sleeps before writing           |
+//! | `redis_outage`     | Redis connections fail                            |
+//! | `rpc_error`        | on-chain oracle and token gate RPC calls fail     |
+//!
+//! Each fault fires with its configured probability (and waits `delay_ms`
+//! for delays). Faults are toggled at runtime through `/admin/chaos`, which
+//! also only exists with the feature.
+
+use serde::{Deserialize, Serialize};
+
+/// An injectable fault
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Fault {
+    DropTradeEvent,
+    DelayDbWrite,
+    RedisOutage,
+    RpcError,
+}
+
+/// Whether `fault` fires now
+#[cfg(not(feature = "chaos"))]
+#[inline(always)]
+pub fn fails(_fault: Fault) -> bool {
+    false
+}
+
+/// Sleep if `fault` fires now
+#[cfg(not(feature = "chaos"))]
+#[inline(always)]
+pub async fn delay(_fault: Fault) {}
+
+#[cfg(feature = "chaos")]
+pub use injector::*;
+
+#[cfg(feature = "chaos")]
+mod injector {
+    use super::Fault;
+    use dashmap::DashMap;
+    use rand::Rng;
+    use serde::{Deserialize, Serialize};
+    use std::str::FromStr;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::LazyLock;
+    use std::time::Duration;
+
+    impl Fault {
+        pub const ALL: [Fault; 4] = [Fault::DropTradeEvent, Fault::DelayDbWrite, Fault::RedisOutage, Fault::RpcError];
+
+        pub fn as_str(&self) -> &'static str {
+            match self {
+                Fault::DropTradeEvent => "drop_trade_event",
+                Fault::DelayDbWrite => "delay_db_write",
+                Fault::RedisOutage => "redis_outage",
+                Fault::RpcError => "rpc_error",
+            }
+        }
+    }
+
+    impl FromStr for Fault {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            Fault::ALL
+                .into_iter()
+                .find(|fault| fault.as_str() == s)
+                .ok_or_else(|| format!("unknown fault: {}", s))
+        }
+    }
+
+    /// How an armed fault behaves
+    #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+    pub struct FaultConfig {
+        /// Chance (0 to 1) the fault fires each time its hook runs
+        pub probability: f64,
+        /// Sleep for delay faults
+        #[serde(default)]
+        pub delay_ms: u64,
+    }
+
+    impl FaultConfig {
+        pub fn validate(&self) -> Result<(), String> {
+            if !(0.0..=1.0).contains(&self.probability) {
+                return Err("probability must be between 0 and 1".to_string());
+            }
+            Ok(())
+        }
+    }
+
+    /// Current state of a fault
+    #[derive(Debug, Clone, Serialize)]
+    pub struct FaultStatus {
+        pub fault: Fault,
+        /// None = disarmed
+        pub config: Option<FaultConfig>,
+        /// Times the fault fired since startup
+        pub injected: u64,
+    }
+
+    #[derive(Default)]
+    struct Injector {
+        armed: DashMap<Fault, FaultConfig>,
+        injected: DashMap<Fault, AtomicU64>,
+    }
+
+    static INJECTOR: LazyLock<Injector> = LazyLock::new(Injector::default);
+
+    fn roll(fault: Fault) -> Option<FaultConfig> {
+        let config = *INJECTOR.armed.get(&fault)?;
+        if !rand::thread_rng().gen_bool(config.probability) {
+            return None;
+        }
+        INJECTOR.injected.entry(fault).or_default().fetch_add(1, Ordering::Relaxed);
+        tracing::warn!("Chaos: injecting {}", fault.as_str());
+        Some(config)
+    }
+
+    /// Whether `fault` fires now
+    pub fn fails(fault: Fault) -> bool {
+        roll(fault).is_some()
+    }
+
+    /// Sleep if `fault` fires now
+    pub async fn delay(fault: Fault) {
+        if let Some(config) = roll(fault) {
+            tokio::time::sleep(Duration::from_millis(config.delay_ms)).await;
+        }
+    }
+
+    /// Arm (Some) or disarm (None) a fault
+    pub fn set(fault: Fault, config: Option<FaultConfig>) -> Result<(), String> {
+        match config {
+            Some(config) => {
+                config.validate()?;
+                INJECTOR.armed.insert(fault, config);
+            }
+            None => {
+                INJECTOR.armed.remove(&fault);
+            }
+        }
+        Ok(())
+    }
+
+    /// Disarm every fault
+    pub fn reset() {
+        INJECTOR.armed.clear();
+    }
+
+    /// State of every fault
+    pub fn statuses() -> Vec<FaultStatus> {
+        Fault::ALL
+            .into_iter()
+            .map(|fault| FaultStatus {
+                fault,
+                config: INJECTOR.armed.get(&fault).map(|c| *c),
+                injected: INJECTOR.injected.get(&fault).map_or(0, |n| n.load(Ordering::Relaxed)),
+            })
+            .collect()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_armed_faults_fire_by_probability() {
+            assert!(!fails(Fault::RpcError));
+            assert!(set(Fault::RpcError, Some(FaultConfig { probability: 1.5, delay_ms: 0 })).is_err());
+
+            set(Fault::RpcError, Some(FaultConfig { probability: 1.0, delay_ms: 0 })).unwrap();
+            assert!(fails(Fault::RpcError));
+            set(Fault::RpcError, Some(FaultConfig { probability: 0.0, delay_ms: 0 })).unwrap();
+            assert!(!fails(Fault::RpcError));
+
+            let status = statuses().into_iter().find(|s| s.fault == Fault::RpcError).unwrap();
+            assert_eq!(status.injected, 1);
+            set(Fault::RpcError, None).unwrap();
+            assert!(statuses().iter().all(|s| s.fault != Fault::RpcError || s.config.is_none()));
+            assert_eq!("redis_outage".parse::<Fault>(), Ok(Fault::RedisOutage));
+        }
+    }
+}
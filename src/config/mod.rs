@@ -1,7 +1,9 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 // use std::collections::HashMap;
 
-#[derive(Debug, Clone, Deserialize)]
+pub mod reload;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
     #[serde(default = "default_environment")]
     pub environment: String,
@@ -19,6 +21,14 @@ pub struct AppConfig {
     #[serde(default = "default_jwt_expiry")]
     pub jwt_expiry_seconds: u64,
 
+    /// Lifetime of a refresh token (each refresh issues a new one)
+    #[serde(default = "default_refresh_token_expiry")]
+    pub refresh_token_expiry_seconds: u64,
+
+    /// Secret API key secrets are derived from (empty = `jwt_secret`)
+    #[serde(default)]
+    pub api_key_secret: String,
+
     // Auth settings - set to true to disable JWT/EIP verification
     #[serde(default)]
     pub auth_disabled: bool,
@@ -30,6 +40,10 @@ pub struct AppConfig {
     pub referral_storage_address: String,
     pub referral_rebate_address: String,
 
+    /// Chains besides the primary one, as JSON (see [`crate::services::chains`])
+    #[serde(default)]
+    pub extra_chains: String,
+
     // Collateral token settings (default: USDT)
     #[serde(default = "default_collateral_token_symbol")]
     pub collateral_token_symbol: String,
@@ -51,6 +65,15 @@ pub struct AppConfig {
     #[serde(default = "default_trading_pairs")]
     pub trading_pairs: String,
 
+    /// Extra frontend aliases per trading pair as JSON, e.g. `{"BTCUSDT": ["XBT-USD"]}`
+    /// (separator spellings like `BTC-USD` are always accepted)
+    #[serde(default)]
+    pub symbol_aliases: String,
+
+    /// Fill candle gaps with flat candles for symbols without a `kline_settings` row
+    #[serde(default = "default_kline_gap_fill")]
+    pub kline_gap_fill_default: bool,
+
     // Backend signer for withdrawals
     pub backend_signer_private_key: String,
 
@@ -79,6 +102,30 @@ pub struct AppConfig {
 
     #[serde(default = "default_auto_mm_slippage")]
     pub auto_mm_slippage: String,
+
+    /// How often the MM's two-sided quoting is sampled for its uptime
+    #[serde(default = "default_mm_quote_sample_interval")]
+    pub mm_quote_sample_secs: u64,
+
+    /// MM quotes that would cross the book: off, clamp or cancel (own quotes first)
+    #[serde(default = "default_mm_cross_policy")]
+    pub mm_cross_policy: String,
+
+    /// Widen and shrink MM quotes on one-sided taker flow (per-symbol parameters)
+    #[serde(default)]
+    pub mm_imbalance_protection: bool,
+
+    /// How often the auto-MM's two-sided quotes are checked against the index (per-symbol parameters)
+    #[serde(default = "default_mm_quote_interval")]
+    pub mm_quote_interval_secs: u64,
+
+    /// Symbols quoted from an external venue's book as JSON (empty = none mirrored)
+    #[serde(default)]
+    pub mm_mirror_books: String,
+
+    /// Mirrored books without a frame for this long are stale and their quotes pulled
+    #[serde(default = "default_mm_mirror_stale")]
+    pub mm_mirror_stale_secs: u64,
     
     // Position service settings
     #[serde(default = "default_min_collateral_usd")]
@@ -99,6 +146,428 @@ pub struct AppConfig {
     // Block sync settings
     #[serde(default = "default_block_sync_lookback")]
     pub block_sync_lookback: u64,
+
+    // Deposit confirmations
+    /// Confirmations before a deposit is credited (0 credits on sight)
+    #[serde(default = "default_deposit_confirmations")]
+    pub deposit_confirmations: u64,
+
+    /// Confirmations after which a credited deposit is no longer checked for reorgs
+    #[serde(default = "default_deposit_finality_blocks")]
+    pub deposit_finality_blocks: u64,
+
+    /// How often the chain head and unsettled deposits are checked
+    #[serde(default = "default_deposit_confirmation_interval")]
+    pub deposit_confirmation_interval_secs: u64,
+
+    // Metrics settings
+    /// Emit per-symbol metric labels (false = aggregate everything under "all")
+    #[serde(default = "default_metrics_per_symbol_labels")]
+    pub metrics_per_symbol_labels: bool,
+
+    /// Number of most active symbols labelled individually; the rest become "other"
+    #[serde(default = "default_metrics_max_symbols")]
+    pub metrics_max_symbols: usize,
+
+    #[serde(default = "default_metrics_symbol_sample_interval")]
+    pub metrics_symbol_sample_interval_secs: u64,
+
+    // Market statistics settings
+    /// Interval between open interest / volume snapshots
+    #[serde(default = "default_market_stats_interval")]
+    pub market_stats_interval_secs: u64,
+
+    /// Snapshots older than this are deleted
+    #[serde(default = "default_market_stats_retention_days")]
+    pub market_stats_retention_days: i64,
+
+    // Daily settlement price settings
+    /// TWAP window before 00:00 UTC
+    #[serde(default = "default_settlement_price_window_mins")]
+    pub settlement_price_window_mins: i64,
+
+    /// How often missing settlement prices are published
+    #[serde(default = "default_settlement_price_interval")]
+    pub settlement_price_interval_secs: u64,
+
+    // Portfolio analytics settings
+    /// How often missing daily equity snapshots are taken
+    #[serde(default = "default_equity_snapshot_interval")]
+    pub equity_snapshot_interval_secs: u64,
+
+    // Referral settlement settings
+    /// Length of a referral earnings settlement epoch (aligned to Monday 00:00 UTC)
+    #[serde(default = "default_referral_epoch_days")]
+    pub referral_epoch_days: i64,
+
+    /// Smallest pending referral sum paid out at an epoch; smaller sums carry over
+    #[serde(default = "default_referral_min_payout")]
+    pub referral_min_payout: String,
+
+    /// How often ended referral epochs are settled
+    #[serde(default = "default_referral_settlement_interval")]
+    pub referral_settlement_interval_secs: u64,
+
+    // RFQ block trade settings
+    /// Minimum size for a quote request
+    #[serde(default = "default_rfq_min_amount")]
+    pub rfq_min_amount: String,
+
+    /// How long market makers have to quote (milliseconds)
+    #[serde(default = "default_rfq_quote_window_ms")]
+    pub rfq_quote_window_ms: u64,
+
+    /// Let block trades update the orderbook's last traded price (and price-derived data)
+    #[serde(default)]
+    pub rfq_block_trades_update_last_price: bool,
+
+    // Signed market data feed settings
+    /// Sign public trade WebSocket messages (sequence number + Ed25519 signature)
+    #[serde(default)]
+    pub ws_feed_signing_enabled: bool,
+
+    /// Secret the rotating feed keys are derived from (empty = random per process)
+    #[serde(default)]
+    pub ws_feed_signing_secret: String,
+
+    #[serde(default = "default_ws_feed_key_rotation")]
+    pub ws_feed_key_rotation_secs: u64,
+
+    // WebSocket keepalive settings (new connections pick up reloaded values)
+    /// How often the server pings each connection
+    #[serde(default = "default_ws_ping_interval")]
+    pub ws_ping_interval_secs: u64,
+
+    /// Connections that send nothing (not even a pong) for this long are closed
+    #[serde(default = "default_ws_idle_timeout")]
+    pub ws_idle_timeout_secs: u64,
+
+    /// Open connections per authenticated user (0 = unlimited)
+    #[serde(default = "default_ws_max_connections_per_user")]
+    pub ws_max_connections_per_user: usize,
+
+    /// Open connections per client IP (0 = unlimited)
+    #[serde(default = "default_ws_max_connections_per_ip")]
+    pub ws_max_connections_per_ip: usize,
+
+    /// Multi-node market data over Redis pub/sub: off, publish (the engine
+    /// node) or consume (gateway nodes serving WebSocket clients)
+    #[serde(default)]
+    pub market_data_relay: String,
+
+    /// How often due funding rates are checked and settled
+    #[serde(default = "default_funding_settlement_interval")]
+    pub funding_settlement_interval_secs: u64,
+
+    /// Consecutive clamped funding intervals before an alert is raised (0 = off)
+    #[serde(default = "default_funding_clamp_alert_intervals")]
+    pub funding_clamp_alert_intervals: u32,
+
+    /// Webhook that receives funding clamp alerts as JSON (empty = log only)
+    #[serde(default)]
+    pub funding_alert_webhook_url: String,
+
+    /// Interval between funding premium index samples
+    #[serde(default = "default_premium_index_interval")]
+    pub premium_index_interval_secs: u64,
+
+    /// Premium index samples older than this are deleted
+    #[serde(default = "default_premium_index_retention_days")]
+    pub premium_index_retention_days: i64,
+
+    // Liquidation settings
+    /// Run the liquidation engine
+    #[serde(default)]
+    pub liquidation_enabled: bool,
+
+    /// Only log planned liquidations (set to false to execute them)
+    #[serde(default = "default_liquidation_dry_run")]
+    pub liquidation_dry_run: bool,
+
+    #[serde(default = "default_liquidation_interval")]
+    pub liquidation_interval_secs: u64,
+
+    /// Margin ratio partial liquidations restore the cross pool to
+    #[serde(default = "default_liquidation_target_margin_ratio")]
+    pub liquidation_target_margin_ratio: String,
+
+    /// Share of the released maintenance margin charged as the liquidation fee (to the insurance fund)
+    #[serde(default = "default_liquidation_penalty_ratio")]
+    pub liquidation_penalty_ratio: String,
+
+    // Margin call settings
+    /// Run the margin call escalation ladder
+    #[serde(default)]
+    pub margin_call_enabled: bool,
+
+    #[serde(default = "default_margin_call_interval")]
+    pub margin_call_interval_secs: u64,
+
+    /// HTTP relay that sends margin call emails (empty = emails are skipped)
+    #[serde(default)]
+    pub margin_call_email_relay_url: String,
+
+    /// How often positions' take-profit / stop-loss legs are checked against the mark price
+    #[serde(default = "default_tp_sl_interval")]
+    pub tp_sl_interval_secs: u64,
+
+    // Order reconciliation settings
+    /// How open database orders missing from the engine are resolved ("reinject" or "cancel")
+    #[serde(default = "default_order_reconcile_policy")]
+    pub order_reconcile_policy: String,
+
+    /// Seconds between reconciliation sweeps (0 = startup sweep only)
+    #[serde(default = "default_order_reconcile_interval")]
+    pub order_reconcile_interval_secs: u64,
+
+    /// Orders updated more recently than this are not reconciled
+    #[serde(default = "default_order_reconcile_grace")]
+    pub order_reconcile_grace_secs: i64,
+
+    /// How often instances campaign for leadership of the singleton jobs
+    #[serde(default = "default_leader_election_interval")]
+    pub leader_election_interval_secs: u64,
+
+    /// Outbox events are deleted this long after being written, published or not
+    #[serde(default = "default_outbox_retention_hours")]
+    pub outbox_retention_hours: i64,
+
+    // Shutdown settings
+    /// Seconds to wait for workers to flush on shutdown before exiting anyway
+    #[serde(default = "default_shutdown_timeout")]
+    pub shutdown_timeout_secs: u64,
+
+    /// Where the engine writes its resting orders on shutdown
+    #[serde(default = "default_engine_snapshot_path")]
+    pub engine_snapshot_path: String,
+
+    /// How often changed orderbooks are snapshotted to the database
+    #[serde(default = "default_orderbook_snapshot_interval")]
+    pub orderbook_snapshot_interval_secs: u64,
+
+    /// Book levels per side behind the ticker's depth-weighted mid and imbalance
+    #[serde(default = "default_ticker_book_levels")]
+    pub ticker_book_levels: usize,
+
+    /// How often the surveillance job scans new journal entries
+    #[serde(default = "default_surveillance_interval")]
+    pub surveillance_interval_secs: u64,
+
+    /// How often expired dead man's switch countdowns are swept
+    #[serde(default = "default_dead_man_interval")]
+    pub dead_man_interval_secs: u64,
+
+    /// Order activity window the surveillance patterns look at
+    #[serde(default = "default_surveillance_window")]
+    pub surveillance_window_secs: i64,
+
+    // Withdrawal fee settings
+    /// How often the gas oracle polls the chain's gas price
+    #[serde(default = "default_gas_oracle_interval")]
+    pub gas_oracle_interval_secs: u64,
+
+    /// Gas price (gwei) at which the congestion multiplier is 1
+    #[serde(default = "default_gas_baseline_gwei")]
+    pub gas_baseline_gwei: String,
+
+    /// Cap on the congestion multiplier applied to flat withdrawal fees
+    #[serde(default = "default_gas_congestion_max_multiplier")]
+    pub gas_congestion_max_multiplier: String,
+
+    /// How long a withdrawal fee quote stays valid
+    #[serde(default = "default_withdrawal_quote_ttl")]
+    pub withdrawal_quote_ttl_secs: u64,
+
+    // Feature entitlement settings
+    /// Check token-gated features on chain (via `rpc_url`)
+    #[serde(default)]
+    pub feature_token_gates_enabled: bool,
+
+    /// How long an on-chain gate balance is cached
+    #[serde(default = "default_feature_gate_cache")]
+    pub feature_gate_cache_secs: u64,
+
+    // VIP fee tier settings
+    /// Rolling window of taker volume fee tiers are derived from
+    #[serde(default = "default_fee_volume_window")]
+    pub fee_volume_window_days: i64,
+
+    /// How long an account's volume and tier override are cached
+    #[serde(default = "default_fee_volume_cache")]
+    pub fee_volume_cache_secs: u64,
+
+    // Idempotency keys (POST /orders, POST /withdraw/request)
+    /// How long a stored response is replayed for its key
+    #[serde(default = "default_idempotency_key_ttl")]
+    pub idempotency_key_ttl_secs: u64,
+
+    // Trading nonces (GET /auth/nonce/trading)
+    /// Reject signed order requests that carry no nonce
+    #[serde(default)]
+    pub require_trading_nonce: bool,
+
+    /// Reject withdrawal requests without the wallet's EIP-712 signature
+    #[serde(default)]
+    pub require_withdraw_signature: bool,
+
+    // Withdrawal risk checks (zero disables a check)
+    /// Most a user may withdraw of a token over a rolling 24 hours
+    #[serde(default = "default_withdraw_daily_limit")]
+    pub withdraw_daily_limit: String,
+
+    /// Withdrawals of at least this amount wait for manual review
+    #[serde(default = "default_withdraw_review_threshold")]
+    pub withdraw_review_threshold: String,
+
+    /// Withdrawals this soon after a large deposit wait for manual review
+    #[serde(default = "default_withdraw_velocity_window")]
+    pub withdraw_velocity_window_secs: u64,
+
+    /// Smallest deposit that counts as large for the velocity check
+    #[serde(default = "default_withdraw_velocity_deposit_threshold")]
+    pub withdraw_velocity_deposit_threshold: String,
+
+    // Rate limiting (token buckets; Redis-backed when available)
+    #[serde(default = "default_rate_limit_enabled")]
+    pub rate_limit_enabled: bool,
+
+    /// Sustained requests per minute per authenticated account
+    #[serde(default = "default_rate_limit_user_per_minute")]
+    pub rate_limit_user_per_minute: u32,
+
+    /// Requests an account may burst above the sustained rate
+    #[serde(default = "default_rate_limit_user_burst")]
+    pub rate_limit_user_burst: u32,
+
+    /// Sustained requests per minute per IP on public routes
+    #[serde(default = "default_rate_limit_ip_per_minute")]
+    pub rate_limit_ip_per_minute: u32,
+
+    #[serde(default = "default_rate_limit_ip_burst")]
+    pub rate_limit_ip_burst: u32,
+
+    /// Per-route limits as JSON, e.g. `{"POST /orders": {"burst": 20, "per_minute": 120}}`
+    /// (empty = defaults everywhere)
+    #[serde(default)]
+    pub rate_limit_routes: String,
+
+    /// Reverse proxies in front of the API, comma-separated IPs or CIDR blocks.
+    /// Only their `X-Forwarded-For` / `X-Real-IP` is believed when resolving a
+    /// client IP (empty = always the socket peer address)
+    #[serde(default)]
+    pub trusted_proxies: String,
+
+    // Mark price settings (defaults for symbols without their own config)
+    /// "median" (mid / index + basis EMA / last trade) or "last_trade"
+    #[serde(default = "default_mark_price_method")]
+    pub mark_price_method: String,
+
+    /// Time constant of the basis EMA
+    #[serde(default = "default_mark_price_basis_ema")]
+    pub mark_price_basis_ema_secs: u64,
+
+    /// Comma-separated index sources ("probability", "cache"; empty = all)
+    #[serde(default)]
+    pub mark_price_index_sources: String,
+
+    #[serde(default = "default_mark_price_interval")]
+    pub mark_price_interval_secs: u64,
+
+    // External index price settings
+    /// Instruments and their venue sources as JSON (empty = no external index)
+    #[serde(default)]
+    pub index_instruments: String,
+
+    /// Largest accepted deviation of a venue quote from the weighted median
+    #[serde(default = "default_index_max_deviation")]
+    pub index_max_deviation: String,
+
+    /// Quotes and index prices older than this are stale
+    #[serde(default = "default_index_stale")]
+    pub index_stale_secs: i64,
+
+    /// Fewest accepted venue quotes that make an index price
+    #[serde(default = "default_index_min_sources")]
+    pub index_min_sources: usize,
+
+    #[serde(default = "default_index_poll_interval")]
+    pub index_poll_interval_secs: u64,
+
+    // On-chain oracle settings
+    /// Chainlink / Pyth feeds as JSON (empty = no on-chain feeds)
+    #[serde(default)]
+    pub price_feeds: String,
+
+    /// Pyth contract on the configured chain (required for Pyth feeds)
+    #[serde(default)]
+    pub pyth_contract_address: String,
+
+    /// Preferred oracle when a feed has both ("chainlink" or "pyth")
+    #[serde(default = "default_oracle_primary")]
+    pub oracle_primary: String,
+
+    /// Oracle prices older than this fall back to the other oracle
+    #[serde(default = "default_oracle_max_age")]
+    pub oracle_max_age_secs: i64,
+
+    /// Relative Chainlink / Pyth disagreement that is logged
+    #[serde(default = "default_oracle_max_deviation")]
+    pub oracle_max_deviation: String,
+
+    #[serde(default = "default_oracle_poll_interval")]
+    pub oracle_poll_interval_secs: u64,
+
+    // Settlement conversion settings
+    /// Assets worth exactly 1 USD when converting a market's quote asset into
+    /// collateral (comma-separated)
+    #[serde(default = "default_usd_pegged_assets")]
+    pub usd_pegged_assets: String,
+
+    // History archive settings
+    /// Export old orders / trades / klines to the archive store
+    #[serde(default)]
+    pub archive_enabled: bool,
+
+    /// Archive storage backend ("local")
+    #[serde(default = "default_archive_backend")]
+    pub archive_backend: String,
+
+    /// Archive location (directory for the local backend)
+    #[serde(default = "default_archive_location")]
+    pub archive_location: String,
+
+    /// Rows older than this are archived
+    #[serde(default = "default_archive_retention_days")]
+    pub archive_retention_days: i64,
+
+    #[serde(default = "default_archive_interval")]
+    pub archive_interval_secs: u64,
+
+    /// Delete archived rows from Postgres (history endpoints then read them from the archive)
+    #[serde(default)]
+    pub archive_prune: bool,
+
+    // Trade tape export settings
+    /// Where trade tape exports are written (directory for the local backend;
+    /// stored with `archive_backend`)
+    #[serde(default = "default_tape_export_location")]
+    pub tape_export_location: String,
+
+    /// Secret trade tape manifests are signed with (HMAC-SHA256; empty = unsigned)
+    #[serde(default)]
+    pub tape_signing_secret: String,
+
+    #[serde(default = "default_tape_export_interval")]
+    pub tape_export_interval_secs: u64,
+
+    // History export settings
+    /// Where account history exports are written (directory for the local
+    /// backend; stored with `archive_backend`)
+    #[serde(default = "default_tape_export_location")]
+    pub history_export_location: String,
+
+    #[serde(default = "default_history_export_interval")]
+    pub history_export_interval_secs: u64,
 }
 
 fn default_weth_address() -> String {
@@ -140,6 +609,10 @@ fn default_jwt_expiry() -> u64 {
     86400 // 24 hours
 }
 
+fn default_refresh_token_expiry() -> u64 {
+    30 * 86400 // 30 days
+}
+
 fn default_price_feed_top_markets() -> usize {
     50 // Top 50 markets by volume
 }
@@ -168,6 +641,22 @@ fn default_auto_mm_slippage() -> String {
     "0.001".to_string()
 }
 
+fn default_mm_cross_policy() -> String {
+    "off".to_string()
+}
+
+fn default_mm_quote_interval() -> u64 {
+    1
+}
+
+fn default_mm_mirror_stale() -> u64 {
+    10
+}
+
+fn default_mm_quote_sample_interval() -> u64 {
+    10
+}
+
 fn default_min_collateral_usd() -> String {
     "10".to_string()
 }
@@ -188,10 +677,330 @@ fn default_position_fee_rate() -> String {
     "0.001".to_string() // 0.1%
 }
 
+fn default_kline_gap_fill() -> bool {
+    true
+}
+
+fn default_metrics_per_symbol_labels() -> bool {
+    true
+}
+
+fn default_metrics_max_symbols() -> usize {
+    20 // Top 20 symbols by activity
+}
+
+fn default_metrics_symbol_sample_interval() -> u64 {
+    15 // 15 seconds
+}
+
+fn default_market_stats_interval() -> u64 {
+    60 // 1 minute
+}
+
+fn default_market_stats_retention_days() -> i64 {
+    90
+}
+
+fn default_settlement_price_window_mins() -> i64 {
+    30
+}
+
+fn default_settlement_price_interval() -> u64 {
+    60 // 1 minute
+}
+
+fn default_equity_snapshot_interval() -> u64 {
+    300 // 5 minutes
+}
+
+fn default_referral_epoch_days() -> i64 {
+    7
+}
+
+fn default_referral_min_payout() -> String {
+    "10".to_string()
+}
+
+fn default_referral_settlement_interval() -> u64 {
+    300 // 5 minutes
+}
+
+fn default_rfq_min_amount() -> String {
+    "1000".to_string() // 1000 shares
+}
+
+fn default_rfq_quote_window_ms() -> u64 {
+    3000 // 3 seconds
+}
+
+fn default_ws_feed_key_rotation() -> u64 {
+    86400 // 24 hours
+}
+
+fn default_ws_ping_interval() -> u64 {
+    20
+}
+
+fn default_ws_idle_timeout() -> u64 {
+    60
+}
+
+fn default_ws_max_connections_per_user() -> usize {
+    20
+}
+
+fn default_ws_max_connections_per_ip() -> usize {
+    50
+}
+
+fn default_funding_settlement_interval() -> u64 {
+    60
+}
+
+fn default_funding_clamp_alert_intervals() -> u32 {
+    3
+}
+
+fn default_premium_index_interval() -> u64 {
+    60 // 1 minute
+}
+
+fn default_premium_index_retention_days() -> i64 {
+    30
+}
+
+fn default_liquidation_dry_run() -> bool {
+    true
+}
+
+fn default_liquidation_interval() -> u64 {
+    10
+}
+
+fn default_liquidation_target_margin_ratio() -> String {
+    "0.8".to_string()
+}
+
+fn default_liquidation_penalty_ratio() -> String {
+    "0.5".to_string()
+}
+
+fn default_margin_call_interval() -> u64 {
+    30
+}
+
+fn default_tp_sl_interval() -> u64 {
+    1
+}
+
+fn default_order_reconcile_policy() -> String {
+    "reinject".to_string()
+}
+
+fn default_order_reconcile_interval() -> u64 {
+    300 // 5 minutes
+}
+
+fn default_order_reconcile_grace() -> i64 {
+    30
+}
+
+fn default_leader_election_interval() -> u64 {
+    5
+}
+
+fn default_outbox_retention_hours() -> i64 {
+    24
+}
+
+fn default_shutdown_timeout() -> u64 {
+    30
+}
+
+fn default_engine_snapshot_path() -> String {
+    "data/engine.snapshot.json".to_string()
+}
+
+fn default_orderbook_snapshot_interval() -> u64 {
+    60
+}
+
+fn default_ticker_book_levels() -> usize {
+    10
+}
+
+fn default_dead_man_interval() -> u64 {
+    1
+}
+
+fn default_surveillance_interval() -> u64 {
+    60
+}
+
+fn default_surveillance_window() -> i64 {
+    600
+}
+
+fn default_gas_oracle_interval() -> u64 {
+    30
+}
+
+fn default_gas_baseline_gwei() -> String {
+    "30".to_string()
+}
+
+fn default_gas_congestion_max_multiplier() -> String {
+    "3".to_string()
+}
+
+fn default_withdrawal_quote_ttl() -> u64 {
+    60
+}
+
+fn default_withdraw_daily_limit() -> String {
+    "100000".to_string()
+}
+
+fn default_withdraw_review_threshold() -> String {
+    "10000".to_string()
+}
+
+fn default_withdraw_velocity_window() -> u64 {
+    3600 // 1 hour
+}
+
+fn default_withdraw_velocity_deposit_threshold() -> String {
+    "5000".to_string()
+}
+
+fn default_feature_gate_cache() -> u64 {
+    300 // 5 minutes
+}
+
+fn default_fee_volume_window() -> i64 {
+    30
+}
+
+fn default_fee_volume_cache() -> u64 {
+    300 // 5 minutes
+}
+
+fn default_idempotency_key_ttl() -> u64 {
+    86400 // 24 hours
+}
+
+fn default_rate_limit_enabled() -> bool {
+    true
+}
+
+fn default_rate_limit_user_per_minute() -> u32 {
+    600
+}
+
+fn default_rate_limit_user_burst() -> u32 {
+    100
+}
+
+fn default_rate_limit_ip_per_minute() -> u32 {
+    300
+}
+
+fn default_rate_limit_ip_burst() -> u32 {
+    50
+}
+
+fn default_mark_price_method() -> String {
+    "median".to_string()
+}
+
+fn default_mark_price_basis_ema() -> u64 {
+    3600 // 1 hour
+}
+
+fn default_mark_price_interval() -> u64 {
+    5
+}
+
+fn default_index_max_deviation() -> String {
+    "0.02".to_string() // 2%
+}
+
+fn default_index_stale() -> i64 {
+    30
+}
+
+fn default_index_min_sources() -> usize {
+    1
+}
+
+fn default_index_poll_interval() -> u64 {
+    5
+}
+
+fn default_oracle_primary() -> String {
+    "chainlink".to_string()
+}
+
+fn default_oracle_max_age() -> i64 {
+    300
+}
+
+fn default_oracle_max_deviation() -> String {
+    "0.01".to_string()
+}
+
+fn default_oracle_poll_interval() -> u64 {
+    15
+}
+
+fn default_usd_pegged_assets() -> String {
+    "USD,USDT,USDC".to_string()
+}
+
+fn default_archive_backend() -> String {
+    "local".to_string()
+}
+
+fn default_archive_location() -> String {
+    "./archive".to_string()
+}
+
+fn default_archive_retention_days() -> i64 {
+    90
+}
+
+fn default_archive_interval() -> u64 {
+    3600 // 1 hour
+}
+
+fn default_tape_export_location() -> String {
+    "./exports".to_string()
+}
+
+fn default_tape_export_interval() -> u64 {
+    10
+}
+
+fn default_history_export_interval() -> u64 {
+    10
+}
+
 fn default_block_sync_lookback() -> u64 {
     100000 // ~7 hours on Arbitrum (0.25s blocks)
 }
 
+fn default_deposit_confirmations() -> u64 {
+    12
+}
+
+fn default_deposit_finality_blocks() -> u64 {
+    64
+}
+
+fn default_deposit_confirmation_interval() -> u64 {
+    15
+}
+
 impl AppConfig {
     pub fn load() -> anyhow::Result<Self> {
         let config = config::Config::builder()
@@ -202,6 +1011,21 @@ impl AppConfig {
         Ok(app_config)
     }
 
+    /// The chain of `chain_id`, `rpc_url` and `vault_address`, with the collateral token
+    pub fn primary_chain(&self) -> crate::services::chains::ChainConfig {
+        let mut tokens = std::collections::BTreeMap::new();
+        tokens.insert("USDT".to_string(), self.collateral_token_address.clone());
+        tokens.insert(self.collateral_token_symbol.to_uppercase(), self.collateral_token_address.clone());
+        crate::services::chains::ChainConfig {
+            chain_id: self.chain_id,
+            name: "primary".to_string(),
+            rpc_url: self.rpc_url.clone(),
+            vault_address: self.vault_address.clone(),
+            tokens,
+            confirmations: None,
+        }
+    }
+
     /// Get token address by symbol (only USDT supported)
     pub fn get_token_address(&self, symbol: &str) -> Option<&str> {
         let upper = symbol.to_uppercase();
@@ -229,6 +1053,15 @@ impl AppConfig {
         &self.collateral_token_address
     }
 
+    /// Secret API key secrets are derived from
+    pub fn api_key_secret(&self) -> &str {
+        if self.api_key_secret.is_empty() {
+            &self.jwt_secret
+        } else {
+            &self.api_key_secret
+        }
+    }
+
     /// Get collateral token symbol (e.g., "USDT")
     pub fn collateral_symbol(&self) -> &str {
         &self.collateral_token_symbol
@@ -254,6 +1087,108 @@ impl AppConfig {
         self.get_trading_pairs().contains(&symbol_upper)
     }
 
+    /// Maintenance margin rate as a decimal (falls back to 0.5% if misconfigured)
+    pub fn maintenance_margin_rate(&self) -> rust_decimal::Decimal {
+        self.maintenance_margin_rate
+            .parse()
+            .unwrap_or_else(|_| rust_decimal::Decimal::new(5, 3))
+    }
+
+    /// Minimum referral payout (falls back to 10 if misconfigured)
+    pub fn referral_min_payout(&self) -> rust_decimal::Decimal {
+        self.referral_min_payout
+            .parse()
+            .unwrap_or_else(|_| rust_decimal::Decimal::new(10, 0))
+    }
+
+    /// Liquidation target margin ratio (falls back to 0.8 if misconfigured)
+    pub fn liquidation_target_margin_ratio(&self) -> rust_decimal::Decimal {
+        self.liquidation_target_margin_ratio
+            .parse()
+            .unwrap_or_else(|_| rust_decimal::Decimal::new(8, 1))
+    }
+
+    /// Liquidation penalty ratio (falls back to 0.5 if misconfigured)
+    pub fn liquidation_penalty_ratio(&self) -> rust_decimal::Decimal {
+        self.liquidation_penalty_ratio
+            .parse()
+            .unwrap_or_else(|_| rust_decimal::Decimal::new(5, 1))
+    }
+
+    /// Gas price baseline in gwei (falls back to 30 if misconfigured)
+    pub fn gas_baseline_gwei(&self) -> rust_decimal::Decimal {
+        self.gas_baseline_gwei
+            .parse()
+            .unwrap_or_else(|_| rust_decimal::Decimal::new(30, 0))
+    }
+
+    /// Congestion multiplier cap (falls back to 3 if misconfigured)
+    pub fn gas_congestion_max_multiplier(&self) -> rust_decimal::Decimal {
+        self.gas_congestion_max_multiplier
+            .parse()
+            .unwrap_or_else(|_| rust_decimal::Decimal::new(3, 0))
+    }
+
+    /// Default mark price settings (falls back to the median method if misconfigured)
+    pub fn mark_price_config(&self) -> crate::services::market::mark_price::MarkPriceConfig {
+        crate::services::market::mark_price::MarkPriceConfig {
+            method: self.mark_price_method.parse().unwrap_or(crate::services::market::mark_price::MarkPriceMethod::Median),
+            basis_ema_secs: self.mark_price_basis_ema_secs,
+            index_sources: self
+                .mark_price_index_sources
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+
+    /// Index price aggregation parameters (deviation falls back to 2% if misconfigured)
+    pub fn index_aggregation_params(&self) -> crate::services::market::index_price::AggregationParams {
+        crate::services::market::index_price::AggregationParams {
+            max_deviation: self
+                .index_max_deviation
+                .parse()
+                .unwrap_or_else(|_| rust_decimal::Decimal::new(2, 2)),
+            stale_secs: self.index_stale_secs,
+            min_sources: self.index_min_sources,
+        }
+    }
+
+    /// On-chain oracle feed parameters (Chainlink primary and 1% deviation if misconfigured)
+    pub fn oracle_feed_params(&self) -> crate::services::price_feed::FeedParams {
+        crate::services::price_feed::FeedParams {
+            primary: self
+                .oracle_primary
+                .parse()
+                .unwrap_or(crate::services::price_feed::OracleKind::Chainlink),
+            max_age_secs: self.oracle_max_age_secs,
+            max_deviation: self
+                .oracle_max_deviation
+                .parse()
+                .unwrap_or_else(|_| rust_decimal::Decimal::new(1, 2)),
+        }
+    }
+
+    /// Minimum RFQ size as a decimal
+    pub fn rfq_min_amount(&self) -> rust_decimal::Decimal {
+        self.rfq_min_amount
+            .parse()
+            .unwrap_or_else(|_| rust_decimal::Decimal::new(1000, 0))
+    }
+
+    /// Withdrawal risk limits (a misconfigured amount disables its check)
+    pub fn withdrawal_risk_policy(&self) -> crate::services::withdrawal_risk::RiskPolicy {
+        let amount = |value: &str| value.parse().unwrap_or(rust_decimal::Decimal::ZERO);
+        crate::services::withdrawal_risk::RiskPolicy {
+            daily_limit: amount(&self.withdraw_daily_limit),
+            review_threshold: amount(&self.withdraw_review_threshold),
+            velocity_window_secs: self.withdraw_velocity_window_secs,
+            velocity_deposit_threshold: amount(&self.withdraw_velocity_deposit_threshold),
+        }
+    }
+
     /// Check if auth is disabled (for development)
     pub fn is_auth_disabled(&self) -> bool {
         self.auth_disabled
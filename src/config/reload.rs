@@ -0,0 +1,404 @@
+//! Config Hot Reload
+//!
+//! Settings are read once at startup, except the keys in [`HOT_RELOADABLE`].
+//! `POST /admin/config/reload` re-reads `.env` and the environment, diffs the
+//! result against the running config, validates the reloadable keys that
+//! changed and applies them. Other changed keys are reported as needing a
+//! restart and left alone.
+//!
+//! Applied changes are written to `config_changes` (audit trail) and broadcast
+//! as a [`ConfigChangedEvent`], so services that copied a value at startup
+//! (rate limiter, job intervals) can pick it up. Handlers read hot values
+//! through [`ConfigStore::current`].
+
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sqlx::PgPool;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use super::AppConfig;
+use crate::api::middleware::rate_limit::RateLimitSettings;
+use crate::services::mm_guard::CrossPolicy;
+
+/// Keys that can change without a restart
+pub const HOT_RELOADABLE: &[&str] = &[
+    "maintenance_margin_rate",
+    "liquidation_dry_run",
+    "liquidation_target_margin_ratio",
+    "liquidation_penalty_ratio",
+    "rfq_min_amount",
+    "rfq_quote_window_ms",
+    "rate_limit_enabled",
+    "rate_limit_user_per_minute",
+    "rate_limit_user_burst",
+    "rate_limit_ip_per_minute",
+    "rate_limit_ip_burst",
+    "rate_limit_routes",
+    "metrics_symbol_sample_interval_secs",
+    "market_stats_interval_secs",
+    "market_stats_retention_days",
+    "premium_index_interval_secs",
+    "premium_index_retention_days",
+    "settlement_price_window_mins",
+    "settlement_price_interval_secs",
+    "equity_snapshot_interval_secs",
+    "referral_epoch_days",
+    "referral_min_payout",
+    "referral_settlement_interval_secs",
+    "liquidation_interval_secs",
+    "margin_call_interval_secs",
+    "tp_sl_interval_secs",
+    "index_poll_interval_secs",
+    "oracle_poll_interval_secs",
+    "mark_price_interval_secs",
+    "tape_export_interval_secs",
+    "history_export_interval_secs",
+    "auto_mm_enabled",
+    "mm_quote_sample_secs",
+    "mm_quote_interval_secs",
+    "mm_mirror_stale_secs",
+    "mm_cross_policy",
+    "mm_imbalance_protection",
+    "orderbook_snapshot_interval_secs",
+    "ticker_book_levels",
+    "surveillance_interval_secs",
+    "dead_man_interval_secs",
+    "gas_oracle_interval_secs",
+    "withdrawal_quote_ttl_secs",
+    "require_trading_nonce",
+    "require_withdraw_signature",
+    "withdraw_daily_limit",
+    "withdraw_review_threshold",
+    "withdraw_velocity_window_secs",
+    "withdraw_velocity_deposit_threshold",
+    "deposit_confirmations",
+    "deposit_finality_blocks",
+    "deposit_confirmation_interval_secs",
+    "archive_interval_secs",
+    "ws_ping_interval_secs",
+    "ws_idle_timeout_secs",
+    "ws_max_connections_per_user",
+    "ws_max_connections_per_ip",
+    "leader_election_interval_secs",
+    "outbox_retention_hours",
+];
+
+/// Interval keys: (config key, job, shortest interval in seconds)
+pub const JOB_INTERVALS: &[(&str, &str, u64)] = &[
+    ("metrics_symbol_sample_interval_secs", "symbol_metrics", 1),
+    ("market_stats_interval_secs", "market_stats", 10),
+    ("premium_index_interval_secs", "premium_index", 10),
+    ("settlement_price_interval_secs", "settlement_prices", 10),
+    ("equity_snapshot_interval_secs", "equity_snapshots", 10),
+    ("referral_settlement_interval_secs", "referral_settlement", 10),
+    ("liquidation_interval_secs", "liquidation", 1),
+    ("margin_call_interval_secs", "margin_calls", 1),
+    ("tp_sl_interval_secs", "tp_sl_keeper", 1),
+    ("index_poll_interval_secs", "index_poller", 1),
+    ("oracle_poll_interval_secs", "oracle_poller", 1),
+    ("mark_price_interval_secs", "mark_price", 1),
+    ("tape_export_interval_secs", "tape_exporter", 1),
+    ("history_export_interval_secs", "history_exporter", 1),
+    ("mm_quote_sample_secs", "mm_quote_sampler", 1),
+    ("mm_quote_interval_secs", "mm_quoter", 1),
+    ("orderbook_snapshot_interval_secs", "orderbook_snapshot", 5),
+    ("surveillance_interval_secs", "surveillance", 5),
+    ("dead_man_interval_secs", "dead_man_switch", 1),
+    ("gas_oracle_interval_secs", "gas_oracle", 5),
+    ("archive_interval_secs", "history_archiver", 60),
+    ("deposit_confirmation_interval_secs", "deposit_confirmations", 1),
+    ("leader_election_interval_secs", "leader_election", 1),
+];
+
+/// Config reload errors
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("Failed to load config: {0}")]
+    Load(String),
+
+    #[error("Invalid {key}: {reason}")]
+    Invalid { key: String, reason: String },
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// One changed key
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigChange {
+    pub key: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+/// Outcome of a reload
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ConfigDiff {
+    /// Reloadable changes, now in effect
+    pub applied: Vec<ConfigChange>,
+    /// Changed keys that only take effect after a restart (values omitted)
+    pub restart_required: Vec<String>,
+}
+
+/// Broadcast after changes are applied
+#[derive(Debug, Clone)]
+pub struct ConfigChangedEvent {
+    pub changes: Vec<ConfigChange>,
+}
+
+impl ConfigChangedEvent {
+    pub fn changed(&self, key: &str) -> bool {
+        self.changes.iter().any(|change| change.key == key)
+    }
+}
+
+/// Audit trail row
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct ConfigChangeRecord {
+    pub id: Uuid,
+    pub key: String,
+    pub old_value: sqlx::types::Json<Value>,
+    pub new_value: sqlx::types::Json<Value>,
+    pub changed_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+fn to_map(config: &AppConfig) -> Result<Map<String, Value>, ConfigError> {
+    match serde_json::to_value(config) {
+        Ok(Value::Object(map)) => Ok(map),
+        Ok(_) => Err(ConfigError::Load("config is not an object".to_string())),
+        Err(e) => Err(ConfigError::Load(e.to_string())),
+    }
+}
+
+/// Keys that differ between `current` and `candidate`
+pub fn diff(current: &AppConfig, candidate: &AppConfig) -> Result<ConfigDiff, ConfigError> {
+    let current = to_map(current)?;
+    let mut diff = ConfigDiff::default();
+    for (key, new) in to_map(candidate)? {
+        let old = current.get(&key).cloned().unwrap_or(Value::Null);
+        if old == new {
+            continue;
+        }
+        if HOT_RELOADABLE.contains(&key.as_str()) {
+            diff.applied.push(ConfigChange { key, old, new });
+        } else {
+            diff.restart_required.push(key);
+        }
+    }
+    Ok(diff)
+}
+
+/// Check a reloadable change; `config` is the candidate it comes from
+fn validate(config: &AppConfig, change: &ConfigChange) -> Result<(), String> {
+    let decimal = || {
+        change
+            .new
+            .as_str()
+            .and_then(|value| value.parse::<Decimal>().ok())
+            .ok_or_else(|| format!("not a number: {}", change.new))
+    };
+    let at_least = |min: u64| match change.new.as_u64() {
+        Some(value) if value >= min => Ok(()),
+        _ => Err(format!("must be at least {}", min)),
+    };
+    match change.key.as_str() {
+        "maintenance_margin_rate" | "liquidation_target_margin_ratio" => {
+            let rate = decimal()?;
+            if rate <= Decimal::ZERO || rate >= Decimal::ONE {
+                return Err("must be between 0 and 1".to_string());
+            }
+            Ok(())
+        }
+        "liquidation_penalty_ratio"
+        | "rfq_min_amount"
+        | "withdraw_daily_limit"
+        | "withdraw_review_threshold"
+        | "withdraw_velocity_deposit_threshold"
+        | "referral_min_payout" => {
+            if decimal()? < Decimal::ZERO {
+                return Err("must not be negative".to_string());
+            }
+            Ok(())
+        }
+        "rfq_quote_window_ms"
+        | "rate_limit_user_per_minute"
+        | "rate_limit_user_burst"
+        | "rate_limit_ip_per_minute"
+        | "rate_limit_ip_burst"
+        | "ws_ping_interval_secs"
+        | "ws_idle_timeout_secs"
+        | "mm_mirror_stale_secs"
+        | "ticker_book_levels"
+        | "referral_epoch_days"
+        | "outbox_retention_hours" => at_least(1),
+        "rate_limit_routes" => RateLimitSettings::from_config(config).map(|_| ()).map_err(|e| e.to_string()),
+        "mm_cross_policy" => config.mm_cross_policy.parse::<CrossPolicy>().map(|_| ()).map_err(|e| e.to_string()),
+        key => match JOB_INTERVALS.iter().find(|(interval_key, _, _)| *interval_key == key) {
+            Some((_, _, min_secs)) => at_least(*min_secs),
+            None => Ok(()),
+        },
+    }
+}
+
+/// The running config, with hot-reloadable keys swapped in place
+pub struct ConfigStore {
+    current: RwLock<Arc<AppConfig>>,
+    sender: broadcast::Sender<ConfigChangedEvent>,
+}
+
+impl ConfigStore {
+    pub fn new(config: AppConfig) -> Self {
+        let (sender, _) = broadcast::channel(16);
+        Self {
+            current: RwLock::new(Arc::new(config)),
+            sender,
+        }
+    }
+
+    pub fn current(&self) -> Arc<AppConfig> {
+        self.current.read().clone()
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ConfigChangedEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Apply the reloadable keys that differ in `candidate`. Nothing is applied
+    /// if any of them is invalid.
+    pub fn apply(&self, candidate: &AppConfig) -> Result<ConfigDiff, ConfigError> {
+        let mut current = self.current.write();
+        let diff = diff(&current, candidate)?;
+        if diff.applied.is_empty() {
+            return Ok(diff);
+        }
+        for change in &diff.applied {
+            validate(candidate, change).map_err(|reason| ConfigError::Invalid {
+                key: change.key.clone(),
+                reason,
+            })?;
+        }
+
+        let mut merged = to_map(&current)?;
+        for change in &diff.applied {
+            merged.insert(change.key.clone(), change.new.clone());
+        }
+        let merged: AppConfig =
+            serde_json::from_value(Value::Object(merged)).map_err(|e| ConfigError::Load(e.to_string()))?;
+        *current = Arc::new(merged);
+        drop(current);
+
+        for change in &diff.applied {
+            tracing::info!("Config {} changed: {} -> {}", change.key, change.old, change.new);
+        }
+        let _ = self.sender.send(ConfigChangedEvent {
+            changes: diff.applied.clone(),
+        });
+        Ok(diff)
+    }
+
+    /// Append applied changes to the audit trail
+    pub async fn record_changes(pool: &PgPool, changed_by: &str, changes: &[ConfigChange]) -> Result<(), ConfigError> {
+        let mut tx = pool.begin().await?;
+        for change in changes {
+            sqlx::query(
+                r#"
+                INSERT INTO config_changes (key, old_value, new_value, changed_by)
+                VALUES ($1, $2, $3, $4)
+                "#,
+            )
+            .bind(&change.key)
+            .bind(sqlx::types::Json(&change.old))
+            .bind(sqlx::types::Json(&change.new))
+            .bind(changed_by.to_lowercase())
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Audit trail, newest first
+    pub async fn changes(pool: &PgPool, limit: i64) -> Result<Vec<ConfigChangeRecord>, ConfigError> {
+        let records = sqlx::query_as(
+            r#"
+            SELECT id, key, old_value, new_value, changed_by, created_at
+            FROM config_changes
+            ORDER BY created_at DESC, id DESC
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(overrides: Value) -> AppConfig {
+        let mut base = serde_json::json!({
+            "database_url": "postgres://localhost/test",
+            "jwt_secret": "secret",
+            "rpc_url": "http://localhost:8545",
+            "chain_id": 1,
+            "vault_address": "0x0",
+            "referral_storage_address": "0x0",
+            "referral_rebate_address": "0x0",
+            "backend_signer_private_key": "0x0",
+        });
+        base.as_object_mut().unwrap().extend(overrides.as_object().unwrap().clone());
+        serde_json::from_value(base).unwrap()
+    }
+
+    #[test]
+    fn test_applies_reloadable_keys_only() {
+        let store = ConfigStore::new(config(serde_json::json!({})));
+        let mut events = store.subscribe();
+
+        let candidate = config(serde_json::json!({
+            "maintenance_margin_rate": "0.01",
+            "rate_limit_user_burst": 20,
+            "jwt_secret": "rotated",
+        }));
+        let diff = store.apply(&candidate).unwrap();
+
+        let applied: Vec<&str> = diff.applied.iter().map(|c| c.key.as_str()).collect();
+        assert_eq!(applied, vec!["maintenance_margin_rate", "rate_limit_user_burst"]);
+        assert_eq!(diff.restart_required, vec!["jwt_secret".to_string()]);
+
+        let current = store.current();
+        assert_eq!(current.maintenance_margin_rate, "0.01");
+        assert_eq!(current.rate_limit_user_burst, 20);
+        assert_eq!(current.jwt_secret, "secret");
+        assert!(events.try_recv().unwrap().changed("rate_limit_user_burst"));
+
+        // Unchanged reload is a no-op
+        assert!(store.apply(&candidate).unwrap().applied.is_empty());
+    }
+
+    #[test]
+    fn test_rejects_invalid_values() {
+        let store = ConfigStore::new(config(serde_json::json!({})));
+        for overrides in [
+            serde_json::json!({ "maintenance_margin_rate": "1.5" }),
+            serde_json::json!({ "rfq_min_amount": "lots" }),
+            serde_json::json!({ "archive_interval_secs": 5 }),
+            serde_json::json!({ "rate_limit_routes": "{\"/orders\": {\"burst\": 1, \"per_minute\": 1}}" }),
+            // One bad key blocks the good ones
+            serde_json::json!({ "rate_limit_ip_burst": 0, "rfq_quote_window_ms": 5000 }),
+        ] {
+            let result = store.apply(&config(overrides));
+            assert!(matches!(result, Err(ConfigError::Invalid { .. })));
+        }
+        assert_eq!(store.current().rfq_quote_window_ms, config(serde_json::json!({})).rfq_quote_window_ms);
+    }
+}
@@ -4,7 +4,7 @@
 //! for high-frequency trading workloads.
 
 // Note: timescale module contains legacy K-line functionality for futures trading.
-// Kept for reference but not used in prediction market implementation.
+// The server does not use it; ztdx-admin uses it to refresh K-line aggregates.
 #[allow(dead_code)]
 pub mod timescale;
 
@@ -141,6 +141,13 @@ impl Database {
         }
     }
 
+    /// Publish pool usage to the `db_connections_*` gauges
+    pub fn record_pool_metrics(&self) {
+        let stats = self.stats();
+        let idle = stats.idle as i64;
+        crate::metrics::set_db_connections(stats.size as i64 - idle, idle);
+    }
+
     /// Check if database is healthy
     pub async fn health_check(&self) -> bool {
         sqlx::query("SELECT 1")
@@ -3,7 +3,7 @@
 //! Provides efficient access to time-series data including K-line queries,
 //! continuous aggregate management, and compression operations.
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Datelike, Duration, Months, TimeZone, Utc};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
@@ -23,6 +23,26 @@ pub struct Kline {
     pub trade_count: i64,
 }
 
+impl Kline {
+    /// Flat candle for a bucket without trades: OHLC at the previous close
+    pub fn flat(symbol: &str, open_time: DateTime<Utc>, close: Decimal) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            open_time,
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume: Decimal::ZERO,
+            quote_volume: Decimal::ZERO,
+            trade_count: 0,
+        }
+    }
+}
+
+/// Origin `time_bucket` aligns weeks to (a Monday, 2000-01-03T00:00:00Z)
+const WEEK_ORIGIN_SECS: i64 = 946_857_600;
+
 /// K-line period/interval
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum KlinePeriod {
@@ -33,9 +53,42 @@ pub enum KlinePeriod {
     FourHours,
     OneDay,
     OneWeek,
+    OneMonth,
 }
 
 impl KlinePeriod {
+    /// All periods, base period first, each rollup after the one it refreshes after
+    pub const ALL: [KlinePeriod; 8] = [
+        KlinePeriod::OneMinute,
+        KlinePeriod::FiveMinutes,
+        KlinePeriod::FifteenMinutes,
+        KlinePeriod::OneHour,
+        KlinePeriod::FourHours,
+        KlinePeriod::OneDay,
+        KlinePeriod::OneWeek,
+        KlinePeriod::OneMonth,
+    ];
+
+    /// Whether this is the period aggregated directly from trades; every
+    /// other period is rolled up from it
+    pub fn is_base(&self) -> bool {
+        *self == KlinePeriod::OneMinute
+    }
+
+    /// `time_bucket` width of this period
+    pub fn bucket_interval(&self) -> &'static str {
+        match self {
+            KlinePeriod::OneMinute => "1 minute",
+            KlinePeriod::FiveMinutes => "5 minutes",
+            KlinePeriod::FifteenMinutes => "15 minutes",
+            KlinePeriod::OneHour => "1 hour",
+            KlinePeriod::FourHours => "4 hours",
+            KlinePeriod::OneDay => "1 day",
+            KlinePeriod::OneWeek => "1 week",
+            KlinePeriod::OneMonth => "1 month",
+        }
+    }
+
     /// Get the table/view name for this period
     pub fn table_name(&self) -> &'static str {
         match self {
@@ -46,10 +99,11 @@ impl KlinePeriod {
             KlinePeriod::FourHours => "klines_4h",
             KlinePeriod::OneDay => "klines_1d",
             KlinePeriod::OneWeek => "klines_1w",
+            KlinePeriod::OneMonth => "klines_1mo",
         }
     }
 
-    /// Get the interval duration in seconds
+    /// Get the interval duration in seconds (30 days for a month)
     pub fn interval_seconds(&self) -> i64 {
         match self {
             KlinePeriod::OneMinute => 60,
@@ -59,11 +113,49 @@ impl KlinePeriod {
             KlinePeriod::FourHours => 14400,
             KlinePeriod::OneDay => 86400,
             KlinePeriod::OneWeek => 604800,
+            KlinePeriod::OneMonth => 2592000,
         }
     }
 
-    /// Parse period from string (e.g., "1m", "5m", "1h", "1d")
-    pub fn from_str(s: &str) -> Option<Self> {
+    /// Start of the bucket holding `time`, aligned as `time_bucket` aligns
+    /// (weeks start on Monday, months on the 1st)
+    pub fn bucket_start(&self, time: DateTime<Utc>) -> DateTime<Utc> {
+        let (origin, width) = match self {
+            KlinePeriod::OneMonth => {
+                return Utc
+                    .with_ymd_and_hms(time.year(), time.month(), 1, 0, 0, 0)
+                    .single()
+                    .unwrap_or(time);
+            }
+            KlinePeriod::OneWeek => (WEEK_ORIGIN_SECS, self.interval_seconds()),
+            _ => (0, self.interval_seconds()),
+        };
+        let start = origin + (time.timestamp() - origin).div_euclid(width) * width;
+        DateTime::from_timestamp(start, 0).unwrap_or(time)
+    }
+
+    /// Start of the bucket after the one starting at `start`
+    pub fn next_bucket(&self, start: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            KlinePeriod::OneMonth => start + Months::new(1),
+            _ => start + Duration::seconds(self.interval_seconds()),
+        }
+    }
+
+    /// Start of the bucket before the one starting at `start`
+    pub fn previous_bucket(&self, start: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            KlinePeriod::OneMonth => start - Months::new(1),
+            _ => start - Duration::seconds(self.interval_seconds()),
+        }
+    }
+
+    /// Parse period from string (e.g., "1m", "5m", "1h", "1d", "1M")
+    pub fn parse(s: &str) -> Option<Self> {
+        // Case matters only here: "1M" is a month, "1m" a minute
+        if s == "1M" {
+            return Some(KlinePeriod::OneMonth);
+        }
         match s.to_lowercase().as_str() {
             "1m" | "1min" => Some(KlinePeriod::OneMinute),
             "5m" | "5min" => Some(KlinePeriod::FiveMinutes),
@@ -72,6 +164,7 @@ impl KlinePeriod {
             "4h" | "240m" => Some(KlinePeriod::FourHours),
             "1d" | "1day" => Some(KlinePeriod::OneDay),
             "1w" | "1week" => Some(KlinePeriod::OneWeek),
+            "1mo" | "1month" => Some(KlinePeriod::OneMonth),
             _ => None,
         }
     }
@@ -86,8 +179,46 @@ impl KlinePeriod {
             KlinePeriod::FourHours => "4h",
             KlinePeriod::OneDay => "1d",
             KlinePeriod::OneWeek => "1w",
+            KlinePeriod::OneMonth => "1M",
+        }
+    }
+}
+
+/// Fill the buckets from `start` (inclusive) to `end` (exclusive) that have
+/// no candle with a flat candle at the previous close. Buckets before the
+/// first known close (`previous_close`, or the first candle) stay empty.
+/// Returns the candles oldest first.
+pub fn fill_kline_gaps(
+    symbol: &str,
+    period: KlinePeriod,
+    mut candles: Vec<Kline>,
+    previous_close: Option<Decimal>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Vec<Kline> {
+    candles.sort_by_key(|k| k.open_time);
+    let mut candles = candles.into_iter().peekable();
+    let mut filled = Vec::new();
+    let mut last_close = previous_close;
+
+    let mut bucket = period.bucket_start(start);
+    if bucket < start {
+        bucket = period.next_bucket(bucket);
+    }
+    while bucket < end {
+        let mut traded = false;
+        while let Some(candle) = candles.next_if(|k| k.open_time <= bucket) {
+            traded |= candle.open_time == bucket;
+            last_close = Some(candle.close);
+            filled.push(candle);
+        }
+        if let (false, Some(close)) = (traded, last_close) {
+            filled.push(Kline::flat(symbol, bucket, close));
         }
+        bucket = period.next_bucket(bucket);
     }
+    filled.extend(candles);
+    filled
 }
 
 /// TimescaleDB operations
@@ -155,6 +286,94 @@ impl TimescaleOps {
             .await
     }
 
+    /// Get the most recent `limit` K-lines before `end_time` (and from
+    /// `start_time`), newest first, with buckets without trades filled by flat
+    /// candles. Buckets after now are never filled.
+    pub async fn get_klines_filled(
+        &self,
+        symbol: &str,
+        period: KlinePeriod,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        limit: i32,
+    ) -> Result<Vec<Kline>, sqlx::Error> {
+        let end_time = end_time.min(Utc::now());
+        if limit <= 0 || start_time >= end_time {
+            return Ok(Vec::new());
+        }
+
+        // Window of the last `limit` buckets, so filled candles count too
+        let mut window_start = period.bucket_start(end_time);
+        if window_start >= end_time {
+            window_start = period.previous_bucket(window_start);
+        }
+        for _ in 1..limit {
+            if window_start <= start_time {
+                break;
+            }
+            window_start = period.previous_bucket(window_start);
+        }
+        let window_start = window_start.max(start_time);
+
+        let candles = self.get_klines(symbol, period, window_start, end_time, limit).await?;
+        let previous_close = self.get_close_before(symbol, period, window_start).await?;
+
+        let mut filled = fill_kline_gaps(
+            &symbol.to_uppercase(),
+            period,
+            candles,
+            previous_close,
+            window_start,
+            end_time,
+        );
+        filled.reverse();
+        filled.truncate(limit as usize);
+        Ok(filled)
+    }
+
+    /// Close of the last K-line before `time`
+    pub async fn get_close_before(
+        &self,
+        symbol: &str,
+        period: KlinePeriod,
+        time: DateTime<Utc>,
+    ) -> Result<Option<Decimal>, sqlx::Error> {
+        let query = format!(
+            "SELECT close FROM {} WHERE symbol = $1 AND bucket < $2 ORDER BY bucket DESC LIMIT 1",
+            period.table_name()
+        );
+
+        sqlx::query_scalar(&query)
+            .bind(symbol.to_uppercase())
+            .bind(time)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    /// Whether candle gaps of a symbol are filled (None = not set, use the default)
+    pub async fn gap_fill_setting(&self, symbol: &str) -> Result<Option<bool>, sqlx::Error> {
+        sqlx::query_scalar("SELECT gap_fill FROM kline_settings WHERE symbol = $1")
+            .bind(symbol.to_uppercase())
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    /// Turn candle gap filling of a symbol on or off
+    pub async fn set_gap_fill(&self, symbol: &str, gap_fill: bool) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO kline_settings (symbol, gap_fill)
+            VALUES ($1, $2)
+            ON CONFLICT (symbol) DO UPDATE SET gap_fill = $2, updated_at = NOW()
+            "#,
+        )
+        .bind(symbol.to_uppercase())
+        .bind(gap_fill)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
     /// Get latest K-line for a symbol and period
     pub async fn get_latest_kline(
         &self,
@@ -248,6 +467,90 @@ impl TimescaleOps {
         Ok(())
     }
 
+    /// Refresh the 1m candles from trades, then every rollup from the 1m
+    /// candles, so all periods agree for the range
+    pub async fn refresh_all_klines(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        for period in KlinePeriod::ALL {
+            self.refresh_continuous_aggregate(period, start_time, end_time).await?;
+        }
+        Ok(())
+    }
+
+    /// Compare a stored rollup with candles aggregated from `klines_1m` on
+    /// read. Returns the buckets that differ, are missing, or are extra.
+    /// Only whole buckets inside the range are compared.
+    pub async fn check_rollup(
+        &self,
+        period: KlinePeriod,
+        symbol: Option<&str>,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<Vec<KlineMismatch>, sqlx::Error> {
+        if period.is_base() {
+            return Ok(Vec::new());
+        }
+        let interval = period.bucket_interval();
+
+        let query = format!(
+            r#"
+            WITH derived AS (
+                SELECT
+                    symbol,
+                    time_bucket('{interval}', bucket) AS bucket,
+                    FIRST(open, bucket) AS open,
+                    MAX(high) AS high,
+                    MIN(low) AS low,
+                    LAST(close, bucket) AS close,
+                    SUM(volume) AS volume,
+                    SUM(quote_volume) AS quote_volume,
+                    SUM(trade_count)::BIGINT AS trade_count
+                FROM klines_1m
+                WHERE ($1::VARCHAR IS NULL OR symbol = $1)
+                  AND bucket >= time_bucket('{interval}', $2::TIMESTAMPTZ)
+                  AND bucket < time_bucket('{interval}', $3::TIMESTAMPTZ)
+                GROUP BY symbol, time_bucket('{interval}', bucket)
+            ),
+            stored AS (
+                SELECT symbol, bucket, open, high, low, close, volume, quote_volume, trade_count
+                FROM {table}
+                WHERE ($1::VARCHAR IS NULL OR symbol = $1)
+                  AND bucket >= time_bucket('{interval}', $2::TIMESTAMPTZ)
+                  AND bucket < time_bucket('{interval}', $3::TIMESTAMPTZ)
+            )
+            SELECT
+                COALESCE(d.symbol, s.symbol) AS symbol,
+                COALESCE(d.bucket, s.bucket) AS bucket,
+                d.close AS derived_close,
+                s.close AS stored_close,
+                d.volume AS derived_volume,
+                s.volume AS stored_volume,
+                d.trade_count AS derived_trade_count,
+                s.trade_count AS stored_trade_count
+            FROM derived d
+            FULL OUTER JOIN stored s ON s.symbol = d.symbol AND s.bucket = d.bucket
+            WHERE d.symbol IS NULL
+               OR s.symbol IS NULL
+               OR (d.open, d.high, d.low, d.close, d.volume, d.quote_volume, d.trade_count)
+                  IS DISTINCT FROM
+                  (s.open, s.high, s.low, s.close, s.volume, s.quote_volume, s.trade_count)
+            ORDER BY 1, 2
+            "#,
+            interval = interval,
+            table = period.table_name(),
+        );
+
+        sqlx::query_as::<_, KlineMismatch>(&query)
+            .bind(symbol.map(str::to_uppercase))
+            .bind(start_time)
+            .bind(end_time)
+            .fetch_all(&self.pool)
+            .await
+    }
+
     /// Get compression statistics for the trades table
     pub async fn get_compression_stats(&self) -> Result<CompressionStats, sqlx::Error> {
         let result = sqlx::query_as::<_, CompressionStats>(
@@ -305,6 +608,21 @@ impl TimescaleOps {
     }
 }
 
+/// A rollup bucket that disagrees with the 1m candles it is built from.
+/// `derived_*` is `None` if the bucket has no 1m candles, `stored_*` if the
+/// rollup has no row for it.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct KlineMismatch {
+    pub symbol: String,
+    pub bucket: DateTime<Utc>,
+    pub derived_close: Option<Decimal>,
+    pub stored_close: Option<Decimal>,
+    pub derived_volume: Option<Decimal>,
+    pub stored_volume: Option<Decimal>,
+    pub derived_trade_count: Option<i64>,
+    pub stored_trade_count: Option<i64>,
+}
+
 /// Compression statistics
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct CompressionStats {
@@ -337,12 +655,22 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_kline_period_from_str() {
-        assert_eq!(KlinePeriod::from_str("1m"), Some(KlinePeriod::OneMinute));
-        assert_eq!(KlinePeriod::from_str("5m"), Some(KlinePeriod::FiveMinutes));
-        assert_eq!(KlinePeriod::from_str("1h"), Some(KlinePeriod::OneHour));
-        assert_eq!(KlinePeriod::from_str("1d"), Some(KlinePeriod::OneDay));
-        assert_eq!(KlinePeriod::from_str("invalid"), None);
+    fn test_kline_period_parse() {
+        assert_eq!(KlinePeriod::parse("1m"), Some(KlinePeriod::OneMinute));
+        assert_eq!(KlinePeriod::parse("5m"), Some(KlinePeriod::FiveMinutes));
+        assert_eq!(KlinePeriod::parse("1h"), Some(KlinePeriod::OneHour));
+        assert_eq!(KlinePeriod::parse("1d"), Some(KlinePeriod::OneDay));
+        assert_eq!(KlinePeriod::parse("invalid"), None);
+    }
+
+    #[test]
+    fn test_kline_period_month_is_case_sensitive() {
+        assert_eq!(KlinePeriod::parse("1M"), Some(KlinePeriod::OneMonth));
+        assert_eq!(KlinePeriod::parse("1mo"), Some(KlinePeriod::OneMonth));
+        assert_eq!(KlinePeriod::OneMonth.table_name(), "klines_1mo");
+        for period in KlinePeriod::ALL {
+            assert_eq!(KlinePeriod::parse(period.to_str()), Some(period));
+        }
     }
 
     #[test]
@@ -352,6 +680,72 @@ mod tests {
         assert_eq!(KlinePeriod::OneDay.table_name(), "klines_1d");
     }
 
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn candle(time: &str, close: Decimal) -> Kline {
+        Kline {
+            volume: Decimal::ONE,
+            trade_count: 1,
+            ..Kline::flat("BTCUSDT", utc(time), close)
+        }
+    }
+
+    #[test]
+    fn test_bucket_alignment() {
+        let t = utc("2026-10-15T13:47:12Z");
+        assert_eq!(KlinePeriod::FiveMinutes.bucket_start(t), utc("2026-10-15T13:45:00Z"));
+        assert_eq!(KlinePeriod::FourHours.bucket_start(t), utc("2026-10-15T12:00:00Z"));
+        // 2026-10-15 is a Thursday; weeks start on Monday
+        assert_eq!(KlinePeriod::OneWeek.bucket_start(t), utc("2026-10-12T00:00:00Z"));
+        assert_eq!(KlinePeriod::OneMonth.bucket_start(t), utc("2026-10-01T00:00:00Z"));
+        assert_eq!(KlinePeriod::OneMonth.next_bucket(utc("2026-12-01T00:00:00Z")), utc("2027-01-01T00:00:00Z"));
+    }
+
+    #[test]
+    fn test_gaps_filled_with_previous_close() {
+        let candles = vec![
+            candle("2026-10-15T10:03:00Z", Decimal::new(52, 2)),
+            candle("2026-10-15T10:01:00Z", Decimal::new(50, 2)),
+        ];
+        let filled = fill_kline_gaps(
+            "BTCUSDT",
+            KlinePeriod::OneMinute,
+            candles,
+            None,
+            utc("2026-10-15T10:00:00Z"),
+            utc("2026-10-15T10:05:00Z"),
+        );
+
+        // Nothing before the first close; 10:02 and 10:04 are flat
+        let times: Vec<_> = filled.iter().map(|k| k.open_time).collect();
+        assert_eq!(
+            times,
+            vec![
+                utc("2026-10-15T10:01:00Z"),
+                utc("2026-10-15T10:02:00Z"),
+                utc("2026-10-15T10:03:00Z"),
+                utc("2026-10-15T10:04:00Z"),
+            ]
+        );
+        assert_eq!(filled[1].open, Decimal::new(50, 2));
+        assert_eq!(filled[1].volume, Decimal::ZERO);
+        assert_eq!(filled[3].close, Decimal::new(52, 2));
+
+        // A close before the range fills from its start
+        let filled = fill_kline_gaps(
+            "BTCUSDT",
+            KlinePeriod::OneMinute,
+            Vec::new(),
+            Some(Decimal::new(49, 2)),
+            utc("2026-10-15T10:00:30Z"),
+            utc("2026-10-15T10:03:00Z"),
+        );
+        assert_eq!(filled.len(), 2);
+        assert_eq!(filled[0].open_time, utc("2026-10-15T10:01:00Z"));
+    }
+
     #[test]
     fn test_compression_ratio() {
         let stats = CompressionStats {
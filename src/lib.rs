@@ -0,0 +1,164 @@
+//! Polymarket-style prediction market backend
+//!
+//! Library root shared by the API server (`src/main.rs`) and the `ztdx-admin`
+//! operations CLI (`src/bin/ztdx-admin.rs`).
+
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+pub mod api;
+pub mod auth;
+pub mod cache;
+pub mod chaos;
+pub mod config;
+pub mod db;
+pub mod metrics;
+pub mod models;
+pub mod services;
+pub mod utils;
+pub mod websocket;
+
+use crate::api::middleware::rate_limit::RateLimiter;
+use crate::cache::CacheManager;
+use crate::config::reload::ConfigStore;
+use crate::config::AppConfig;
+use crate::db::Database;
+use crate::services::api_keys::ApiKeyService;
+use crate::services::auth_tokens::AuthTokenService;
+use crate::services::archive::ArchiveStore;
+use crate::services::export::ExportService;
+use crate::services::features::FeatureService;
+use crate::services::fees::FeeService;
+use crate::services::funding::FundingEvent;
+use crate::services::chains::ChainRegistry;
+use crate::services::deposit_confirmations::DepositConfirmationTracker;
+use crate::services::gas_oracle::GasOracle;
+use crate::services::idempotency::IdempotencyService;
+use crate::services::jobs::JobScheduler;
+use crate::services::leader::LeaderElection;
+use crate::services::market::calendar::SessionEvent;
+use crate::services::market::index_price::IndexAggregator;
+use crate::services::market::mark_price::MarkPriceService;
+use crate::services::market::symbols::SymbolRegistry;
+use crate::services::market::MarketService;
+use crate::services::matching::{DeadManSwitch, MatchingEngine, ReplicationService};
+use crate::services::mm_imbalance::MmImbalanceService;
+use crate::services::mm_inventory::MmInventoryService;
+use crate::services::mm_mirror::MmMirrorService;
+use crate::services::mm_quoter::MmQuoterService;
+use crate::services::nonce::NonceService;
+use crate::services::notifications::NotificationEvent;
+use crate::services::price_feed::PriceFeedService;
+use crate::services::rfq::RfqEvent;
+use crate::services::shutdown::ShutdownCoordinator;
+use crate::services::market::ticker::TickerService;
+use crate::services::settlement_price::SettlementPriceService;
+use crate::services::stats::StatsService;
+use crate::services::tape::TapeService;
+use crate::services::token_price::TokenPriceService;
+use crate::services::user_events::UserEventBus;
+use crate::websocket::conflation::ConflatedTradeMessage;
+use crate::websocket::connections::ConnectionRegistry;
+use crate::websocket::signing::{FeedSigner, SignedFeedMessage};
+use metrics_exporter_prometheus::PrometheusHandle;
+
+/// Order update event for real-time WebSocket push
+#[derive(Debug, Clone, Serialize)]
+pub struct OrderUpdateEvent {
+    pub user_address: String,
+    pub order: models::order::OrderResponse,
+}
+
+/// Position update event for real-time WebSocket push
+#[derive(Debug, Clone, Serialize)]
+pub struct PositionUpdateEvent {
+    pub user_address: String,
+    pub position: services::position::PositionMargin,
+}
+
+pub struct AppState {
+    pub config: AppConfig,
+    pub db: Database,
+    pub cache: Arc<CacheManager>,
+    pub matching_engine: Arc<MatchingEngine>,
+    /// Warm-standby replication of the engine's commands
+    pub replication: Arc<ReplicationService>,
+    /// Auto-cancel countdowns of quoting accounts
+    pub dead_man: Arc<DeadManSwitch>,
+    pub market_service: Arc<MarketService>,
+    pub mark_price_service: Arc<MarkPriceService>,
+    /// External index prices aggregated from exchange venues
+    pub index_aggregator: Arc<IndexAggregator>,
+    /// Frontend symbol aliases of the trading pairs
+    pub symbols: Arc<SymbolRegistry>,
+    /// Chainlink / Pyth on-chain price feeds
+    pub price_feeds: Arc<PriceFeedService>,
+    /// Quote asset → collateral conversion for settlement
+    pub token_prices: Arc<TokenPriceService>,
+    /// Per-account feature entitlements
+    pub features: Arc<FeatureService>,
+    /// VIP fee tiers by rolling taker volume
+    pub fees: Arc<FeeService>,
+    /// Stored responses for retried writes
+    pub idempotency: Arc<IdempotencyService>,
+    /// Nonces of signed trading messages
+    pub nonces: Arc<NonceService>,
+    /// Per-account / per-IP request limits
+    pub rate_limiter: Arc<RateLimiter>,
+    /// Config with hot-reloaded values (`config` stays as loaded at startup)
+    pub live_config: Arc<ConfigStore>,
+    pub order_update_sender: broadcast::Sender<OrderUpdateEvent>,
+    pub position_update_sender: broadcast::Sender<PositionUpdateEvent>,
+    /// Committed balance / position / order changes of all users
+    pub user_events: Arc<UserEventBus>,
+    pub rfq_sender: broadcast::Sender<RfqEvent>,
+    pub funding_sender: broadcast::Sender<FundingEvent>,
+    /// Liquidation / ADL notices for the affected accounts
+    pub notification_sender: broadcast::Sender<NotificationEvent>,
+    pub session_sender: broadcast::Sender<SessionEvent>,
+    /// Market data feed signer (None when feed signing is disabled)
+    pub feed_signer: Option<Arc<FeedSigner>>,
+    pub signed_feed_sender: broadcast::Sender<SignedFeedMessage>,
+    /// Conflated trade windows for `conflate_ms` subscriptions
+    pub conflated_trades_sender: broadcast::Sender<ConflatedTradeMessage>,
+    /// Open WebSocket connections per IP / user
+    pub ws_connections: Arc<ConnectionRegistry>,
+    /// History archive store (None when archiving is disabled)
+    pub archive: Option<Arc<dyn ArchiveStore>>,
+    /// Trade tape exports
+    pub tape: Arc<TapeService>,
+    /// Account history exports
+    pub exports: Arc<ExportService>,
+    /// Trading bot API keys
+    pub api_keys: Arc<ApiKeyService>,
+    /// Access / refresh tokens and their revocation
+    pub auth_tokens: Arc<AuthTokenService>,
+    /// Open interest and volume snapshots
+    pub stats: Arc<StatsService>,
+    /// Rolling 24h ticker, rebuilt from trades on startup
+    pub ticker: Arc<TickerService>,
+    /// Official end-of-day settlement prices
+    pub settlement_prices: Arc<SettlementPriceService>,
+    /// Auto-MM quote uptime and inventory report
+    pub mm_inventory: Arc<MmInventoryService>,
+    /// Auto-MM protection against one-sided taker flow
+    pub mm_imbalance: Arc<MmImbalanceService>,
+    /// Auto-MM two-sided quoting around the index
+    pub mm_quoter: Arc<MmQuoterService>,
+    /// External books mirrored into the auto-MM's quotes
+    pub mm_mirror: Arc<MmMirrorService>,
+    /// Gas price congestion, scales withdrawal fees
+    pub gas_oracle: Arc<GasOracle>,
+    /// Chains deposits and withdrawals run on
+    pub chains: Arc<ChainRegistry>,
+    /// Chain heads and deposit confirmations / reorgs
+    pub deposit_confirmations: Arc<DepositConfirmationTracker>,
+    /// Periodic background jobs
+    pub jobs: Arc<JobScheduler>,
+    /// Election of the instance running the singleton jobs
+    pub leader: Arc<LeaderElection>,
+    /// Shutdown phases and the workers to wait for
+    pub shutdown: Arc<ShutdownCoordinator>,
+    pub metrics_handle: PrometheusHandle,
+}
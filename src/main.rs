@@ -1,47 +1,74 @@
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
-use axum::{middleware, routing::get, Router};
-use serde::Serialize;
+use axum::{http::HeaderName, middleware, routing::get, Router};
+use futures::FutureExt;
 use tokio::sync::broadcast;
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::trace::TraceLayer;
+use tracing::Instrument;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-/// Order update event for real-time WebSocket push
-#[derive(Debug, Clone, Serialize)]
-pub struct OrderUpdateEvent {
-    pub user_address: String,
-    pub order: models::order::OrderResponse,
-}
-
-mod api;
-mod auth;
-mod cache;
-mod config;
-mod db;
-mod metrics;
-mod models;
-mod services;
-mod utils;
-mod websocket;
-
-use crate::cache::{CacheConfig, CacheManager};
-use crate::config::AppConfig;
-use crate::db::Database;
-use crate::services::matching::MatchingEngine;
-use crate::services::market::MarketService;
-use metrics_exporter_prometheus::PrometheusHandle;
-
-pub struct AppState {
-    pub config: AppConfig,
-    pub db: Database,
-    pub cache: Arc<CacheManager>,
-    pub matching_engine: Arc<MatchingEngine>,
-    pub market_service: Arc<MarketService>,
-    pub order_update_sender: broadcast::Sender<OrderUpdateEvent>,
-    pub metrics_handle: PrometheusHandle,
-}
+use polymarket_backend::{api, auth, chaos, metrics, websocket, AppState, OrderUpdateEvent, PositionUpdateEvent};
+use polymarket_backend::api::middleware::rate_limit::{RateLimitSettings, RateLimiter};
+use polymarket_backend::cache::{CacheConfig, CacheManager};
+use polymarket_backend::cache::pubsub::Publisher;
+use polymarket_backend::config::reload::{ConfigStore, JOB_INTERVALS};
+use polymarket_backend::config::AppConfig;
+use polymarket_backend::db::Database;
+use polymarket_backend::services::matching::snapshot::BookSnapshotter;
+use polymarket_backend::services::matching::{
+    DeadManSwitch, EngineJournal, JournalConfig, MatchingEngine, OrderReconciler, ReconcileConfig, ReplicationConfig,
+    ReplicationRole, ReplicationService,
+};
+use polymarket_backend::services::api_keys::ApiKeyService;
+use polymarket_backend::services::auth_tokens::AuthTokenService;
+use polymarket_backend::services::archive::{self, ArchiveConfig, ArchiveService};
+use polymarket_backend::services::export::ExportService;
+use polymarket_backend::services::features::{FeatureService, RpcBalanceChecker, TokenBalanceChecker};
+use polymarket_backend::services::fees::FeeService;
+use polymarket_backend::services::idempotency::IdempotencyService;
+use polymarket_backend::services::funding::{self, FundingEvent, FundingService};
+use polymarket_backend::services::jobs::{JobScheduler, Schedule};
+use polymarket_backend::services::leader::{LeaderElection, JOBS_LEADER_KEY};
+use polymarket_backend::services::liquidation::{LiquidationService, LiquidationSettings};
+use polymarket_backend::services::margin_call::{MarginCallConfig, MarginCallService};
+use polymarket_backend::services::market::calendar::SessionEvent;
+use polymarket_backend::services::market::index_price::{self, IndexAggregator};
+use polymarket_backend::services::market::mark_price::{CacheIndexSource, MarkPriceService, ProbabilityIndexSource};
+use polymarket_backend::services::market::MarketService;
+use polymarket_backend::services::market_data_relay::{self, RelayMode};
+use polymarket_backend::services::price_feed::{self, PriceFeedService};
+use polymarket_backend::services::rfq::{RfqEvent, RfqExecutionConfig, RfqService};
+use polymarket_backend::services::shutdown::{self, ShutdownCoordinator};
+use polymarket_backend::services::mm_guard::CrossPolicy;
+use polymarket_backend::services::mm_imbalance::{self, MmImbalanceService};
+use polymarket_backend::services::mm_inventory::MmInventoryService;
+use polymarket_backend::services::mm_mirror::{self, MmMirrorService};
+use polymarket_backend::services::mm_quoter::MmQuoterService;
+use polymarket_backend::services::nonce::NonceService;
+use polymarket_backend::services::notifications::NotificationEvent;
+use polymarket_backend::services::outbox;
+use polymarket_backend::services::chains::ChainRegistry;
+use polymarket_backend::services::deposit_confirmations::DepositConfirmationTracker;
+use polymarket_backend::services::gas_oracle::GasOracle;
+use polymarket_backend::services::market::symbols::SymbolRegistry;
+use polymarket_backend::services::market::ticker::{self, TickerService};
+use polymarket_backend::services::portfolio::PortfolioService;
+use polymarket_backend::services::position::{PositionService, Settlement};
+use polymarket_backend::services::premium_index::PremiumIndexService;
+use polymarket_backend::services::referral_settlement::ReferralSettlementService;
+use polymarket_backend::services::settlement_price::SettlementPriceService;
+use polymarket_backend::services::stats::StatsService;
+use polymarket_backend::services::surveillance::{SurveillanceConfig, SurveillanceService};
+use polymarket_backend::services::tp_sl::TpSlService;
+use polymarket_backend::services::tape::TapeService;
+use polymarket_backend::services::token_price::TokenPriceService;
+use polymarket_backend::services::user_events::UserEventBus;
+use polymarket_backend::websocket::conflation::{self, ConflatedTradeMessage};
+use polymarket_backend::websocket::connections::ConnectionRegistry;
+use polymarket_backend::websocket::signing::{self, FeedSigner, SignedFeedMessage};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -54,19 +81,31 @@ async fn main() -> anyhow::Result<()> {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
+    // Operational tools moved to ztdx-admin
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(tool) = args.iter().find(|a| *a == "--replay-journal" || *a == "--repair-deposits") {
+        anyhow::bail!("{} moved to ztdx-admin ({})", tool, tool.trim_start_matches("--"));
+    }
+
     // Load configuration
     dotenvy::dotenv().ok();
     let config = AppConfig::load()?;
 
     tracing::info!("Starting Polymarket Backend v{}", env!("CARGO_PKG_VERSION"));
+    #[cfg(feature = "chaos")]
+    tracing::warn!("Built with chaos fault injection; faults are controlled via /admin/chaos");
     tracing::info!("Environment: {}", config.environment);
 
     // Initialize Prometheus metrics
     let metrics_handle = metrics::init_metrics();
-    tracing::info!("Prometheus metrics initialized");
+    metrics::configure_symbol_labels(config.metrics_per_symbol_labels, config.metrics_max_symbols);
+    tracing::info!(
+        "Prometheus metrics initialized (per-symbol labels: {}, max symbols: {})",
+        config.metrics_per_symbol_labels, config.metrics_max_symbols
+    );
 
     // Initialize EIP-712 domain from config
-    crate::auth::eip712::init_domain(config.chain_id, &config.vault_address);
+    auth::eip712::init_domain(config.chain_id, &config.vault_address);
 
     // Initialize database
     let db = Database::connect(&config.database_url).await?;
@@ -83,98 +122,1287 @@ async fn main() -> anyhow::Result<()> {
 
     // Initialize market service
     let market_service = Arc::new(MarketService::new());
-    tracing::info!("Market service initialized");
-
-    // Initialize matching engine
-    let matching_engine = Arc::new(MatchingEngine::new());
-    tracing::info!("Matching engine initialized");
 
-    // Recover open limit orders from database
-    match matching_engine.recover_orders_from_db(&db.pool).await {
-        Ok(count) => {
-            if count > 0 {
-                tracing::info!("Recovered {} open limit orders to orderbook", count);
-            } else {
-                tracing::info!("No open orders to recover");
+    // Feature entitlements (token gates checked on chain when enabled)
+    let balance_checker: Option<Arc<dyn TokenBalanceChecker>> = if config.feature_token_gates_enabled {
+        match RpcBalanceChecker::new(&config.rpc_url) {
+            Ok(checker) => Some(Arc::new(checker)),
+            Err(e) => {
+                tracing::error!("Token-gated features disabled: {}", e);
+                None
             }
         }
+    } else {
+        None
+    };
+    let features = Arc::new(FeatureService::new(
+        balance_checker,
+        std::time::Duration::from_secs(config.feature_gate_cache_secs),
+    ));
+
+    // VIP fee tiers (applied to fills at persistence)
+    let fees = Arc::new(FeeService::new(
+        config.fee_volume_window_days,
+        std::time::Duration::from_secs(config.fee_volume_cache_secs),
+    ));
+    match fees.load_tiers(&db.pool).await {
+        Ok(count) => tracing::info!("Loaded {} VIP fee tiers", count),
+        Err(e) => tracing::error!("Failed to load VIP fee tiers, charging market schedules: {}", e),
+    }
+
+    // External index aggregator (exchange spot prices)
+    let index_instruments = index_price::parse_instruments(&config.index_instruments).unwrap_or_else(|e| {
+        tracing::error!("External index disabled: {}", e);
+        Vec::new()
+    });
+    let index_aggregator = Arc::new(IndexAggregator::new(index_instruments, config.index_aggregation_params()));
+
+    // External books the auto-MM mirrors
+    let mirror_books = mm_mirror::parse_books(&config.mm_mirror_books).unwrap_or_else(|e| {
+        tracing::error!("MM mirroring disabled: {}", e);
+        Vec::new()
+    });
+    let mm_mirror = Arc::new(MmMirrorService::new(mirror_books));
+
+    // On-chain oracle feeds (Chainlink / Pyth)
+    let price_feeds = match price_feed::parse_feeds(&config.price_feeds).and_then(|feeds| {
+        PriceFeedService::new(feeds, config.oracle_feed_params(), &config.rpc_url, &config.pyth_contract_address)
+    }) {
+        Ok(service) => service,
         Err(e) => {
-            tracing::error!("Failed to recover orders from database: {}", e);
-            tracing::warn!("Starting with empty orderbook");
+            tracing::error!("On-chain price feeds disabled: {}", e);
+            PriceFeedService::new(Vec::new(), config.oracle_feed_params(), &config.rpc_url, "")?
+        }
+    };
+    let price_feeds = Arc::new(price_feeds);
+
+    // Settlement conversion (exchange index first, then on-chain oracles)
+    let token_prices = Arc::new(
+        TokenPriceService::new(config.collateral_symbol(), &config.usd_pegged_assets)
+            .with_source(
+                index_aggregator.clone(),
+                index_aggregator.instruments().iter().map(|i| i.name.clone()).collect::<Vec<_>>(),
+            )
+            .with_source(
+                price_feeds.clone(),
+                price_feeds.feeds().iter().map(|f| f.symbol.clone()).collect::<Vec<_>>(),
+            ),
+    );
+    if !token_prices.has_path(config.collateral_symbol()) {
+        tracing::warn!("Collateral {} has no USD price; only collateral-quoted markets can be listed", config.collateral_symbol());
+    }
+
+    // Mark price service (index sources: oracle probability, cached index, exchange aggregator, on-chain oracles)
+    let mark_price_service = Arc::new(
+        MarkPriceService::new(config.mark_price_config())
+            .with_source(Arc::new(ProbabilityIndexSource::new(db.pool.clone())))
+            .with_source(Arc::new(CacheIndexSource::new(cache.clone())))
+            .with_source(index_aggregator.clone())
+            .with_source(price_feeds.clone()),
+    );
+    if let Err(e) = mark_price_service.validate(&config.mark_price_config()) {
+        tracing::warn!("Default mark price config: {} (using all index sources)", e);
+    }
+    match mark_price_service.load_configs(&db.pool).await {
+        Ok(0) => {}
+        Ok(count) => tracing::info!("Loaded {} per-symbol mark price configs", count),
+        Err(e) => tracing::error!("Failed to load mark price configs: {}", e),
+    }
+    tracing::info!("Market service initialized");
+
+    // Initialize matching engine (with write-ahead journal if enabled)
+    let journal_config = JournalConfig::from_env();
+    let replication_config = ReplicationConfig::from_env();
+    let standby = replication_config.role == ReplicationRole::Standby;
+    if standby && replication_config.primary_url.is_empty() {
+        anyhow::bail!("ENGINE_REPLICATION_PRIMARY_URL is required for a standby");
+    }
+    if standby && replication_config.secret.is_empty() {
+        anyhow::bail!("ENGINE_REPLICATION_SECRET is required for a standby");
+    }
+    // A gateway node serves market data relayed from the engine node and matches nothing
+    let relay_mode = config.market_data_relay.parse::<RelayMode>()?;
+    let gateway = relay_mode == RelayMode::Consume;
+    if relay_mode != RelayMode::Off && cache.redis().is_none() {
+        anyhow::bail!("market_data_relay={} requires Redis", config.market_data_relay);
+    }
+    let engine = MatchingEngine::new().with_replication_backlog(replication_config.backlog);
+    let (matching_engine, replay_journal) = if journal_config.enabled {
+        let journal = Arc::new(EngineJournal::open(&journal_config)?);
+        let has_entries = journal.last_sequence() > 0;
+        (Arc::new(engine.with_journal(journal)), has_entries)
+    } else {
+        (Arc::new(engine), false)
+    };
+    matching_engine.set_standby(standby || gateway);
+    tracing::info!(
+        "Matching engine initialized (journal: {}, role: {:?}, market data relay: {:?})",
+        if journal_config.enabled { "enabled" } else { "disabled" },
+        replication_config.role,
+        relay_mode
+    );
+
+    // Load market trading calendars (markets without one trade 24/7)
+    match matching_engine.load_calendars(&db.pool).await {
+        Ok(0) => {}
+        Ok(count) => tracing::info!("Loaded {} market trading calendars", count),
+        Err(e) => tracing::error!("Failed to load trading calendars: {}", e),
+    }
+
+    // Load fee tiers and per-market trading rules (pause, delist, limits)
+    match matching_engine.load_market_rules(&db.pool).await {
+        Ok(count) => tracing::info!("Loaded trading rules for {} markets", count),
+        Err(e) => tracing::error!("Failed to load market trading rules: {}", e),
+    }
+
+    // Rebuild orderbook: snapshots + journal tail if the journal has entries,
+    // otherwise recover open limit orders from database. A standby without
+    // journal entries starts empty and replicates the primary from sequence 0.
+    let journal_path = replay_journal.then_some(journal_config.path.as_path());
+    if gateway {
+        tracing::info!("Gateway starting with an empty orderbook, serving books relayed from the engine node");
+    } else if standby && !replay_journal {
+        tracing::info!("Standby starting with an empty orderbook, replicating from the primary");
+    } else {
+        match matching_engine.recover(&db.pool, journal_path).await {
+            Ok(report) => tracing::info!(
+                "Engine recovered from {:?}: {} books, {} orders restored, {} journal entries replayed",
+                report.source,
+                report.books,
+                report.orders,
+                report.replayed
+            ),
+            Err(e) if replay_journal => return Err(e.into()),
+            Err(e) => {
+                tracing::error!("Failed to recover orders from database: {}", e);
+                tracing::warn!("Starting with empty orderbook");
+            }
         }
     }
+    matching_engine.resume_replication();
+    let replication = Arc::new(ReplicationService::new(matching_engine.clone(), replication_config));
+
+    // Resume trade execution ids after the last persisted trade
+    match matching_engine.load_exec_sequence(&db.pool).await {
+        Ok(last) => tracing::info!("Trade execution ids resume after {}", last),
+        Err(e) => tracing::error!("Failed to load last trade execution id: {}", e),
+    }
 
     // Create order update broadcast channel for real-time WebSocket push
     let (order_update_sender, _) = broadcast::channel::<OrderUpdateEvent>(1000);
+    let (position_update_sender, _) = broadcast::channel::<PositionUpdateEvent>(1000);
     tracing::info!("Order update broadcast channel created");
 
+    // User data event buses (fed by Postgres notifications, see listener below)
+    let user_events = Arc::new(UserEventBus::new(10000));
+
+    // Create RFQ notification broadcast channel (market maker quote requests / results)
+    let (rfq_sender, _) = broadcast::channel::<RfqEvent>(1000);
+
+    // Create funding notification broadcast channel (per-position funding payments)
+    let (funding_sender, _) = broadcast::channel::<FundingEvent>(1000);
+
+    // Create account notification broadcast channel (liquidations, ADL)
+    let (notification_sender, _) = broadcast::channel::<NotificationEvent>(1000);
+
+    // Create trading session broadcast channel (market open/close announcements)
+    let (session_sender, _) = broadcast::channel::<SessionEvent>(1000);
+
+    // Create signed market data channel (pre-signed trade messages for WebSocket fan-out)
+    let (signed_feed_sender, _) = broadcast::channel::<SignedFeedMessage>(10000);
+    // Create conflated trades channel (one message per symbol and conflation window)
+    let (conflated_trades_sender, _) = broadcast::channel::<ConflatedTradeMessage>(10000);
+    let feed_signer = config.ws_feed_signing_enabled.then(|| {
+        Arc::new(FeedSigner::new(&config.ws_feed_signing_secret, config.ws_feed_key_rotation_secs))
+    });
+
+    // Open history archive store
+    let archive = if config.archive_enabled {
+        let store = archive::build_store(&config.archive_backend, &config.archive_location)?;
+        tracing::info!("History archive enabled ({} at {})", store.backend(), config.archive_location);
+        Some(store)
+    } else {
+        None
+    };
+
+    // Open trade tape export store
+    let tape_store = archive::build_store(&config.archive_backend, &config.tape_export_location)?;
+    if config.tape_signing_secret.is_empty() {
+        tracing::warn!("Trade tape signing secret not set; tape manifests will be unsigned");
+    }
+    let tape = Arc::new(TapeService::new(tape_store, archive.clone(), &config.tape_signing_secret));
+
+    // Open account history export store
+    let export_store = archive::build_store(&config.archive_backend, &config.history_export_location)?;
+    let exports = Arc::new(ExportService::new(export_store, archive.clone(), config.collateral_symbol()));
+
+    // Rate limiter (buckets shared through Redis when it is up)
+    let rate_limiter = Arc::new(RateLimiter::new(RateLimitSettings::from_config(&config)?, cache.redis().cloned()));
+    // Fail fast on a bad MM crossing policy (read per quote afterwards)
+    config.mm_cross_policy.parse::<CrossPolicy>()?;
+    let nonces = Arc::new(NonceService::new(cache.redis().cloned()));
+    if !config.rate_limit_enabled {
+        tracing::warn!("Rate limiting disabled");
+    }
+
+    // Deposit / withdrawal chains
+    let chains = Arc::new(ChainRegistry::new(config.primary_chain(), &config.extra_chains)?);
+    tracing::info!(
+        "Chains: {}",
+        chains.all().iter().map(|chain| format!("{} ({})", chain.name, chain.chain_id)).collect::<Vec<_>>().join(", ")
+    );
+
+    // Background job leader election (singleton jobs run on one instance)
+    let leader = Arc::new(LeaderElection::new(db.pool.clone(), JOBS_LEADER_KEY, LeaderElection::default_instance_id()));
+
     // Build application state
     let state = Arc::new(AppState {
         config: config.clone(),
         db,
         cache,
         matching_engine,
+        replication,
+        dead_man: Arc::new(DeadManSwitch::new()),
         market_service,
+        mark_price_service,
+        index_aggregator,
+        symbols: Arc::new(SymbolRegistry::new(&config.get_trading_pairs(), &config.symbol_aliases)?),
+        price_feeds,
+        token_prices,
+        features,
+        fees,
+        idempotency: Arc::new(IdempotencyService::new(std::time::Duration::from_secs(
+            config.idempotency_key_ttl_secs,
+        ))),
+        nonces,
+        rate_limiter,
+        live_config: Arc::new(ConfigStore::new(config.clone())),
         order_update_sender,
+        position_update_sender,
+        user_events,
+        rfq_sender,
+        funding_sender,
+        notification_sender,
+        session_sender,
+        feed_signer,
+        signed_feed_sender,
+        conflated_trades_sender,
+        ws_connections: Arc::new(ConnectionRegistry::new()),
+        archive,
+        tape,
+        exports,
+        api_keys: Arc::new(ApiKeyService::new(config.api_key_secret())),
+        auth_tokens: Arc::new(AuthTokenService::new(
+            &config.jwt_secret,
+            config.jwt_expiry_seconds,
+            config.refresh_token_expiry_seconds,
+        )),
+        stats: Arc::new(StatsService::new()),
+        ticker: Arc::new(TickerService::new()),
+        settlement_prices: Arc::new(SettlementPriceService::new()),
+        mm_inventory: Arc::new(MmInventoryService::new()),
+        mm_imbalance: Arc::new(MmImbalanceService::new()),
+        mm_quoter: Arc::new(MmQuoterService::new()),
+        mm_mirror,
+        gas_oracle: Arc::new(GasOracle::new(
+            &config.rpc_url,
+            config.gas_baseline_gwei(),
+            config.gas_congestion_max_multiplier(),
+        )),
+        deposit_confirmations: Arc::new(DepositConfirmationTracker::new(chains.all(), chains.primary_id())),
+        chains,
+        jobs: Arc::new(JobScheduler::new().with_leader(leader.clone())),
+        leader,
+        shutdown: Arc::new(ShutdownCoordinator::new()),
         metrics_handle,
     });
 
+    // Forward committed balance / position / order changes to the WebSocket buses
+    let listener_state = state.clone();
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = listener_state.user_events.listen(&listener_state.db.pool).await {
+                tracing::error!("User data event listener failed, reconnecting: {}", e);
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    });
+
     // Start trade persistence worker
     let mut trade_receiver = state.matching_engine.subscribe_trades();
-    let db_pool = state.db.pool.clone();
-    tokio::spawn(async move {
-        use crate::services::matching::OrderFlowOrchestrator;
+    let worker_state = state.clone();
+    state.shutdown.spawn(async move {
+        use polymarket_backend::services::matching::OrderFlowOrchestrator;
         tracing::info!("Trade persistence worker started");
 
-        while let Ok(trade_event) = trade_receiver.recv().await {
-            match OrderFlowOrchestrator::persist_trade(&db_pool, &trade_event).await {
-                Ok(_) => {
-                    tracing::debug!(
-                        "Persisted trade {} (maker: {}, taker: {})",
-                        trade_event.trade_id,
-                        trade_event.maker_address,
-                        trade_event.taker_address
-                    );
+        let state = worker_state;
+        loop {
+            // Queued trades are persisted before stopping
+            let received = tokio::select! {
+                biased;
+                received = trade_receiver.recv() => received,
+                _ = state.shutdown.stopping() => break,
+            };
+            let mut trade_event = match received {
+                Ok(trade_event) => trade_event,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    tracing::error!("Trade persistence lagged, {} trades not persisted", n);
+                    continue;
                 }
-                Err(e) => {
-                    tracing::error!(
-                        "Failed to persist trade {}: {}",
-                        trade_event.trade_id,
-                        e
-                    );
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            let span = OrderFlowOrchestrator::trade_span(&trade_event);
+            async {
+                if chaos::fails(chaos::Fault::DropTradeEvent) {
+                    return;
+                }
+                // Charge each side at its VIP tier as of this fill
+                if let Err(e) = state.fees.apply(&state.db.pool, &mut trade_event).await {
+                    tracing::warn!("Fee tier lookup for trade {} failed, charging the market schedule: {}", trade_event.trade_id, e);
+                }
+
+                // Realized PnL of markets quoted in another asset is converted into collateral.
+                // The engine has filled the trade: without a rate it is persisted anyway and
+                // its PnL credited by the deferred settlement job.
+                let settlement = match trade_settlement(&state, trade_event.market_id).await {
+                    Ok(settlement) => settlement,
+                    Err(e) => {
+                        tracing::warn!("Deferring PnL settlement of trade {}: {}", trade_event.trade_id, e);
+                        Settlement::deferred(state.config.collateral_symbol(), None)
+                    }
+                };
+
+                match OrderFlowOrchestrator::persist_trade(&state.db.pool, &trade_event, &settlement).await {
+                    Ok(_) => {
+                        tracing::debug!(
+                            "Persisted trade {} (maker: {}, taker: {})",
+                            trade_event.trade_id,
+                            trade_event.maker_address,
+                            trade_event.taker_address
+                        );
+                    }
+                    Err(e) => {
+                        tracing::error!(
+                            "Failed to persist trade {}: {}",
+                            trade_event.trade_id,
+                            e
+                        );
+                    }
                 }
             }
+            .instrument(span)
+            .await;
         }
         tracing::warn!("Trade persistence worker stopped");
     });
     tracing::info!("Trade persistence worker spawned");
 
+    // Periodic services run as named jobs (status under /admin/jobs)
+    let jobs = state.jobs.clone();
+
+    // Job leader: one instance runs the `leader_only` jobs. Standby and
+    // gateway engines don't campaign: liquidations need a live engine.
+    match state.leader.campaign(!state.matching_engine.is_standby()).await {
+        Ok(leader) => tracing::info!("Instance {} job leader: {}", state.leader.instance_id(), leader),
+        Err(e) => tracing::error!("Job leader election failed: {}", e),
+    }
+    let leader_state = state.clone();
+    let leader_interval = config.leader_election_interval_secs.max(1);
+    jobs.register("leader_election", Schedule::every(Duration::from_secs(leader_interval)).delayed(), move || {
+        let state = leader_state.clone();
+        async move {
+            state.leader.campaign(!state.matching_engine.is_standby()).await?;
+            Ok(())
+        }
+        .boxed()
+    })?;
+
+    // Market statistics: open interest / volume snapshots, also read by the metrics sampler
+    let stats_state = state.clone();
+    let stats_interval = config.market_stats_interval_secs.max(10);
+    jobs.register("market_stats", Schedule::every(Duration::from_secs(stats_interval)), move || {
+        let state = stats_state.clone();
+        async move {
+            let retention_days = state.live_config.current().market_stats_retention_days;
+            state.stats.snapshot(&state.db.pool, retention_days).await?;
+            Ok(())
+        }
+        .boxed()
+    })?;
+    tracing::info!("Market statistics snapshots scheduled (every {}s)", stats_interval);
+
+    // Daily settlement prices: published once the day's TWAP window has closed
+    let settlement_price_state = state.clone();
+    let settlement_price_interval = config.settlement_price_interval_secs.max(10);
+    jobs.register(
+        "settlement_prices",
+        Schedule::every(Duration::from_secs(settlement_price_interval)).leader_only(),
+        move || {
+            let state = settlement_price_state.clone();
+            async move {
+                let window_mins = state.live_config.current().settlement_price_window_mins;
+                state
+                    .settlement_prices
+                    .publish_due(&state.db.pool, chrono::Utc::now(), window_mins)
+                    .await?;
+                Ok(())
+            }
+            .boxed()
+        },
+    )?;
+    tracing::info!("Settlement price publication scheduled (every {}s)", settlement_price_interval);
+
+    // Daily equity snapshots: taken once the day's settlement prices are in
+    let equity_state = state.clone();
+    let equity_interval = config.equity_snapshot_interval_secs.max(10);
+    jobs.register("equity_snapshots", Schedule::every(Duration::from_secs(equity_interval)).leader_only(), move || {
+        let state = equity_state.clone();
+        async move {
+            PortfolioService::snapshot_due(&state.db.pool, chrono::Utc::now(), state.config.collateral_symbol()).await?;
+            Ok(())
+        }
+        .boxed()
+    })?;
+    tracing::info!("Equity snapshots scheduled (every {}s)", equity_interval);
+
+    // Deferred PnL settlement: credits PnL of trades persisted before their conversion rate was known
+    let deferred_pnl_state = state.clone();
+    jobs.register("deferred_pnl", Schedule::every(Duration::from_secs(30)).leader_only(), move || {
+        let state = deferred_pnl_state.clone();
+        async move {
+            for pnl in PositionService::deferred_pnl(&state.db.pool, 100).await? {
+                match trade_settlement(&state, pnl.market_id).await {
+                    Ok(settlement) => {
+                        PositionService::settle_deferred_pnl(&state.db.pool, pnl.id, &settlement).await?;
+                    }
+                    Err(e) => tracing::debug!("PnL {} still deferred: {}", pnl.id, e),
+                }
+            }
+            Ok(())
+        }
+        .boxed()
+    })?;
+
+    // Referral settlement: pays out pending referral earnings once each epoch ends
+    let referral_state = state.clone();
+    let referral_interval = config.referral_settlement_interval_secs.max(10);
+    jobs.register(
+        "referral_settlement",
+        Schedule::every(Duration::from_secs(referral_interval)).leader_only(),
+        move || {
+            let state = referral_state.clone();
+            async move {
+                let config = state.live_config.current();
+                ReferralSettlementService::settle_due(
+                    &state.db.pool,
+                    chrono::Utc::now(),
+                    config.referral_epoch_days,
+                    config.referral_min_payout(),
+                )
+                .await?;
+                Ok(())
+            }
+            .boxed()
+        },
+    )?;
+    tracing::info!("Referral settlement scheduled (every {}s)", referral_interval);
+
+    // Per-symbol metrics sampler
+    let sampler_state = state.clone();
+    let sample_interval = config.metrics_symbol_sample_interval_secs.max(1);
+    jobs.register("symbol_metrics", Schedule::every(Duration::from_secs(sample_interval)), move || {
+        let state = sampler_state.clone();
+        async move {
+            state.matching_engine.publish_symbol_metrics(state.stats.open_interest());
+            Ok(())
+        }
+        .boxed()
+    })?;
+    tracing::info!("Per-symbol metrics sampler scheduled (every {}s)", sample_interval);
+
+    // DB pool gauges
+    let pool_state = state.clone();
+    jobs.register("db_pool_metrics", Schedule::every(Duration::from_secs(15)), move || {
+        let state = pool_state.clone();
+        async move {
+            state.db.record_pool_metrics();
+            Ok(())
+        }
+        .boxed()
+    })?;
+
+    // RFQ executor: fills the best quote once each quote window closes
+    let rfq_state = state.clone();
+    let rfq_config = Arc::new(RfqExecutionConfig {
+        token: config.collateral_symbol().to_string(),
+        update_last_price: config.rfq_block_trades_update_last_price,
+    });
+    jobs.register("rfq_executor", Schedule::every(Duration::from_millis(250)), move || {
+        let state = rfq_state.clone();
+        let rfq_config = rfq_config.clone();
+        async move {
+            // A standby's engine refuses commands until it is promoted
+            if state.matching_engine.is_standby() {
+                return Ok(());
+            }
+            RfqService::execute_due(&state.db.pool, &state.matching_engine, &state.rfq_sender, &rfq_config).await?;
+            Ok(())
+        }
+        .boxed()
+    })?;
+    tracing::info!("RFQ executor scheduled");
+
+    // Dead man's switch: cancel resting orders of accounts whose countdown ran out
+    let dead_man_state = state.clone();
+    let dead_man_interval = config.dead_man_interval_secs.max(1);
+    jobs.register("dead_man_switch", Schedule::every(Duration::from_secs(dead_man_interval)), move || {
+        let state = dead_man_state.clone();
+        async move {
+            if state.matching_engine.is_standby() {
+                return Ok(());
+            }
+            let n = state
+                .dead_man
+                .sweep(&state.db.pool, &state.matching_engine, state.config.collateral_symbol())
+                .await?;
+            if n > 0 {
+                tracing::warn!("Dead man's switch cancelled {} resting orders", n);
+            }
+            Ok(())
+        }
+        .boxed()
+    })?;
+
+    // Reconcile open orders between the database and the engine: once now
+    // (workers are running, no client orders yet), then periodically
+    let reconciler = Arc::new(OrderReconciler::new(ReconcileConfig {
+        policy: config.order_reconcile_policy.parse()?,
+        grace_secs: config.order_reconcile_grace_secs.max(0),
+        token: config.collateral_symbol().to_string(),
+    }));
+    if standby {
+        tracing::info!("Startup order reconciliation skipped on standby");
+    } else if let Err(e) = reconciler.sweep(&state.db.pool, &state.matching_engine, true).await {
+        tracing::error!("Startup order reconciliation failed: {}", e);
+    }
+    if config.order_reconcile_interval_secs > 0 {
+        let reconcile_state = state.clone();
+        let reconcile_interval = config.order_reconcile_interval_secs;
+        // The startup sweep already ran
+        let schedule = Schedule::every(Duration::from_secs(reconcile_interval)).delayed();
+        jobs.register("order_reconciler", schedule, move || {
+            let state = reconcile_state.clone();
+            let reconciler = reconciler.clone();
+            async move {
+                if state.matching_engine.is_standby() {
+                    return Ok(());
+                }
+                reconciler.sweep(&state.db.pool, &state.matching_engine, false).await?;
+                Ok(())
+            }
+            .boxed()
+        })?;
+        tracing::info!("Order reconciler scheduled (every {}s, policy {})", reconcile_interval, config.order_reconcile_policy);
+    }
+
+    // Liquidation engine (dry run unless LIQUIDATION_DRY_RUN=false)
+    if config.liquidation_enabled {
+        let liquidation_state = state.clone();
+        let liquidation_interval = config.liquidation_interval_secs.max(1);
+        jobs.register("liquidation", Schedule::every(Duration::from_secs(liquidation_interval)).leader_only(), move || {
+            let state = liquidation_state.clone();
+            // Rebuilt every cycle so reloaded rates apply
+            let config = state.live_config.current();
+            let settings = LiquidationSettings {
+                dry_run: config.liquidation_dry_run,
+                maintenance_margin_rate: config.maintenance_margin_rate(),
+                target_margin_ratio: config.liquidation_target_margin_ratio(),
+                penalty_ratio: config.liquidation_penalty_ratio(),
+                token: config.collateral_symbol().to_string(),
+            };
+            async move {
+                let report = LiquidationService::run_cycle(
+                    &state.db.pool,
+                    &state.matching_engine,
+                    &state.market_service,
+                    &settings,
+                    &state.notification_sender,
+                )
+                .await?;
+                if report.planned > 0 || report.recovered > 0 {
+                    tracing::warn!(
+                        "Liquidation cycle: {} of {} accounts under maintenance, {} closes planned, {} executed, {} failed, {} deferred, {} stale recovered",
+                        report.accounts_liquidated,
+                        report.accounts_checked,
+                        report.planned,
+                        report.executed,
+                        report.failed,
+                        report.skipped,
+                        report.recovered
+                    );
+                }
+                Ok(())
+            }
+            .boxed()
+        })?;
+        tracing::info!(
+            "Liquidation engine scheduled (every {}s, {})",
+            liquidation_interval,
+            if config.liquidation_dry_run { "dry run" } else { "live" }
+        );
+    }
+
+    // Margin call ladder: notice, alert and auto-reduce ahead of liquidation
+    if config.margin_call_enabled {
+        let margin_call_state = state.clone();
+        let margin_call_interval = config.margin_call_interval_secs.max(1);
+        let http = reqwest::Client::new();
+        jobs.register("margin_calls", Schedule::every(Duration::from_secs(margin_call_interval)).leader_only(), move || {
+            let state = margin_call_state.clone();
+            let http = http.clone();
+            let config = state.live_config.current();
+            let margin_call_config = MarginCallConfig {
+                maintenance_margin_rate: config.maintenance_margin_rate(),
+                token: config.collateral_symbol().to_string(),
+                email_relay_url: Some(config.margin_call_email_relay_url.clone()).filter(|url| !url.is_empty()),
+            };
+            async move {
+                let report = MarginCallService::run_cycle(
+                    &state.db.pool,
+                    &state.matching_engine,
+                    &state.market_service,
+                    &http,
+                    &margin_call_config,
+                    &state.notification_sender,
+                )
+                .await?;
+                if report.escalated > 0 {
+                    tracing::warn!(
+                        "Margin call cycle: {} of {} accounts escalated, {} auto-reduce orders, {} recovered",
+                        report.escalated,
+                        report.accounts_checked,
+                        report.reduce_orders,
+                        report.recovered
+                    );
+                }
+                Ok(())
+            }
+            .boxed()
+        })?;
+        tracing::info!("Margin call ladder scheduled (every {}s)", margin_call_interval);
+    }
+
+    // TP/SL keeper: executes positions' take-profit / stop-loss legs
+    let tp_sl_state = state.clone();
+    let tp_sl_interval = config.tp_sl_interval_secs.max(1);
+    jobs.register("tp_sl_keeper", Schedule::every(Duration::from_secs(tp_sl_interval)).leader_only(), move || {
+        let state = tp_sl_state.clone();
+        async move {
+            let run = TpSlService::run(&state.db.pool, &state.matching_engine).await?;
+            if run.executed > 0 || run.failed > 0 {
+                tracing::info!(
+                    "TP/SL keeper: {} legs executed, {} failed, {} cancelled",
+                    run.executed, run.failed, run.cancelled
+                );
+            }
+            Ok(())
+        }
+        .boxed()
+    })?;
+    tracing::info!("TP/SL keeper scheduled (every {}s)", tp_sl_interval);
+
+    // Funding settler: applies due funding rates to open positions
+    let funding_state = state.clone();
+    let funding_token = config.collateral_symbol().to_string();
+    let funding_interval = config.funding_settlement_interval_secs.max(1);
+    let clamp_alert_intervals = config.funding_clamp_alert_intervals;
+    let alert_webhook = config.funding_alert_webhook_url.clone();
+    let http = reqwest::Client::new();
+    jobs.register("funding_settler", Schedule::every(Duration::from_secs(funding_interval)).leader_only(), move || {
+        let state = funding_state.clone();
+        let token = funding_token.clone();
+        let alert_webhook = alert_webhook.clone();
+        let http = http.clone();
+        async move {
+            let cycle = FundingService::settle_due(
+                &state.db.pool,
+                &token,
+                &state.matching_engine,
+                &state.funding_sender,
+                clamp_alert_intervals,
+            )
+            .await?;
+            if cycle.applied > 0 {
+                tracing::info!("Funding applied to {} positions", cycle.applied);
+            }
+            for alert in &cycle.alerts {
+                tracing::warn!(
+                    "Funding rate for {} at its {} ({}) for {} consecutive intervals",
+                    alert.symbol, alert.side, alert.bound, alert.streak
+                );
+                if !alert_webhook.is_empty() {
+                    if let Err(e) = funding::send_clamp_alert(&http, &alert_webhook, alert).await {
+                        tracing::error!("Failed to deliver funding clamp alert for {}: {}", alert.symbol, e);
+                    }
+                }
+            }
+            Ok(())
+        }
+        .boxed()
+    })?;
+    tracing::info!("Funding settler scheduled (every {}s)", funding_interval);
+
+    // Funding premium index: per-minute premium samples behind predicted funding rates
+    let premium_state = state.clone();
+    let premium_interval = config.premium_index_interval_secs.max(10);
+    jobs.register("premium_index", Schedule::every(Duration::from_secs(premium_interval)).leader_only(), move || {
+        let state = premium_state.clone();
+        async move {
+            let retention_days = state.live_config.current().premium_index_retention_days;
+            PremiumIndexService::sample(
+                &state.db.pool,
+                &state.matching_engine,
+                &state.mark_price_service,
+                retention_days,
+            )
+            .await?;
+            Ok(())
+        }
+        .boxed()
+    })?;
+    tracing::info!("Funding premium index sampling scheduled (every {}s)", premium_interval);
+
+    // External index poller
+    if !state.index_aggregator.instruments().is_empty() {
+        let index_state = state.clone();
+        let index_interval = config.index_poll_interval_secs.max(1);
+        jobs.register("index_poller", Schedule::every(Duration::from_secs(index_interval)), move || {
+            let state = index_state.clone();
+            async move {
+                state.index_aggregator.poll(&state.cache).await;
+                Ok(())
+            }
+            .boxed()
+        })?;
+        tracing::info!(
+            "Index poller scheduled for {} instruments (every {}s)",
+            state.index_aggregator.instruments().len(),
+            index_interval
+        );
+    }
+
+    // On-chain oracle poller
+    if !state.price_feeds.feeds().is_empty() {
+        let oracle_state = state.clone();
+        let oracle_interval = config.oracle_poll_interval_secs.max(1);
+        jobs.register("oracle_poller", Schedule::every(Duration::from_secs(oracle_interval)), move || {
+            let state = oracle_state.clone();
+            async move {
+                state.price_feeds.poll().await;
+                Ok(())
+            }
+            .boxed()
+        })?;
+        tracing::info!(
+            "Oracle poller scheduled for {} feeds (every {}s)",
+            state.price_feeds.feeds().len(),
+            oracle_interval
+        );
+    }
+
+    // Mark price updater: median of book mid, index + basis EMA and last trade
+    let mark_state = state.clone();
+    let mark_interval = config.mark_price_interval_secs.max(1);
+    jobs.register("mark_price", Schedule::every(Duration::from_secs(mark_interval)), move || {
+        let state = mark_state.clone();
+        async move {
+            state.mark_price_service.update_all(&state.matching_engine, &state.cache).await;
+            Ok(())
+        }
+        .boxed()
+    })?;
+    tracing::info!("Mark price updater scheduled (every {}s)", mark_interval);
+
+    // Session watcher: announces trading session opens/closes
+    let session_state = state.clone();
+    let last_open = Arc::new(parking_lot::Mutex::new(std::collections::HashMap::<uuid::Uuid, bool>::new()));
+    jobs.register("session_watcher", Schedule::every(Duration::from_secs(1)), move || {
+        let session_state = session_state.clone();
+        let last_open = last_open.clone();
+        async move {
+            let now = chrono::Utc::now();
+            let calendars = session_state.matching_engine.calendars();
+            let mut last_open = last_open.lock();
+            last_open.retain(|id, _| calendars.iter().any(|(market_id, _)| market_id == id));
+            for (market_id, calendar) in calendars {
+                let state = calendar.state(now);
+                if last_open.insert(market_id, state.is_open) == Some(!state.is_open) {
+                    tracing::info!(
+                        "Market {} trading session {}",
+                        market_id,
+                        if state.is_open { "opened" } else { "closed" }
+                    );
+                    let _ = session_state.session_sender.send(SessionEvent {
+                        market_id,
+                        state,
+                        timestamp: now.timestamp_millis(),
+                    });
+                }
+            }
+            Ok(())
+        }
+        .boxed()
+    })?;
+    tracing::info!("Session watcher scheduled (every 1s)");
+
+    // Start signed trade feed
+    if let Some(signer) = state.feed_signer.clone() {
+        signing::spawn_trade_feed(state.matching_engine.clone(), signer, state.signed_feed_sender.clone());
+        tracing::info!("Signed market data feed enabled (key rotation every {}s)", config.ws_feed_key_rotation_secs);
+    }
+
+    // Multi-node market data: the engine node publishes, gateway nodes consume
+    match (relay_mode, state.cache.redis(), state.cache.pubsub_opt()) {
+        (RelayMode::Publish, Some(redis), _) => {
+            market_data_relay::spawn_publisher(state.matching_engine.clone(), Publisher::new(redis.clone()));
+            tracing::info!("Publishing market data to Redis for gateway nodes");
+        }
+        (RelayMode::Consume, _, Some(pubsub)) => {
+            market_data_relay::spawn_consumer(state.matching_engine.clone(), pubsub.create_subscriber());
+        }
+        _ => {}
+    }
+
+    // Warm standby: follow the primary's engine commands until promoted
+    if standby {
+        tokio::spawn(state.replication.clone().run());
+    }
+
+    // Rolling 24h ticker: rebuilt from persisted trades, then fed by the engine
+    ticker::spawn(state.ticker.clone(), state.matching_engine.clone(), state.db.pool.clone());
+    mm_imbalance::spawn(
+        state.mm_imbalance.clone(),
+        state.matching_engine.clone(),
+        state.live_config.clone(),
+        state.db.pool.clone(),
+    );
+
+    // Start trade conflation for low-bandwidth `conflate_ms` subscribers
+    conflation::spawn_trade_conflation(
+        state.matching_engine.clone(),
+        state.feed_signer.clone(),
+        state.conflated_trades_sender.clone(),
+    );
+
+    // Trade tape exporter: runs queued export jobs
+    let tape_state = state.clone();
+    let tape_interval = config.tape_export_interval_secs.max(1);
+    jobs.register("tape_exporter", Schedule::every(Duration::from_secs(tape_interval)).leader_only(), move || {
+        let state = tape_state.clone();
+        async move {
+            let n = state.tape.run_pending(&state.db.pool).await?;
+            if n > 0 {
+                tracing::info!("Trade tape exporter completed {} exports", n);
+            }
+            Ok(())
+        }
+        .boxed()
+    })?;
+    tracing::info!("Trade tape exporter scheduled (every {}s)", tape_interval);
+
+    // History exporter: runs queued account history exports
+    let export_state = state.clone();
+    let export_interval = config.history_export_interval_secs.max(1);
+    jobs.register("history_exporter", Schedule::every(Duration::from_secs(export_interval)).leader_only(), move || {
+        let state = export_state.clone();
+        async move {
+            let n = state.exports.run_pending(&state.db.pool).await?;
+            if n > 0 {
+                tracing::info!("History exporter completed {} exports", n);
+            }
+            Ok(())
+        }
+        .boxed()
+    })?;
+    tracing::info!("History exporter scheduled (every {}s)", export_interval);
+
+    // Auto-MM quote sampler: feeds the quote uptime of the inventory report
+    if !config.auto_mm_test_account.is_empty() {
+        let mm_state = state.clone();
+        let mm_interval = config.mm_quote_sample_secs.max(1);
+        jobs.register("mm_quote_sampler", Schedule::every(Duration::from_secs(mm_interval)), move || {
+            let state = mm_state.clone();
+            async move {
+                let address = state.live_config.current().auto_mm_test_account.clone();
+                state.mm_inventory.sample(&state.matching_engine, &address);
+                Ok(())
+            }
+            .boxed()
+        })?;
+        tracing::info!("MM quote sampler scheduled (every {}s)", mm_interval);
+    }
+
+    // MM quoter: keeps the auto-MM's two-sided quotes around the index
+    if !config.auto_mm_test_account.is_empty() {
+        match state.mm_quoter.load_params(&state.db.pool).await {
+            Ok(symbols) => tracing::info!("MM quoting parameters loaded ({} symbols)", symbols),
+            Err(e) => tracing::error!("Failed to load MM quoting parameters: {}", e),
+        }
+        if !state.mm_mirror.books().is_empty() {
+            state.mm_mirror.spawn();
+            tracing::info!("MM mirror feeds started ({} books)", state.mm_mirror.books().len());
+        }
+        let quoter_state = state.clone();
+        let quoter_interval = config.mm_quote_interval_secs.max(1);
+        jobs.register("mm_quoter", Schedule::every(Duration::from_secs(quoter_interval)).leader_only(), move || {
+            let state = quoter_state.clone();
+            async move {
+                let live = state.live_config.current();
+                let address = live.auto_mm_test_account.clone();
+                let collateral = state.config.collateral_symbol();
+                if !live.auto_mm_enabled {
+                    let pulled = state.mm_quoter.pull_all(&state.db.pool, &state.matching_engine, &address, collateral).await?;
+                    if pulled > 0 {
+                        tracing::info!("MM quoting disabled: quotes pulled on {} symbols", pulled);
+                    }
+                    return Ok(());
+                }
+                let imbalance = live.mm_imbalance_protection.then_some(state.mm_imbalance.as_ref());
+                let mirror_max_age = chrono::Duration::seconds(live.mm_mirror_stale_secs as i64);
+                let run = state
+                    .mm_quoter
+                    .run(
+                        &state.db.pool,
+                        &state.matching_engine,
+                        &state.mark_price_service,
+                        &state.market_service,
+                        imbalance,
+                        Some((state.mm_mirror.as_ref(), mirror_max_age)),
+                        &address,
+                        collateral,
+                        live.mm_cross_policy.parse().unwrap_or(CrossPolicy::Off),
+                    )
+                    .await?;
+                if run.refreshed > 0 || run.halted > 0 {
+                    tracing::debug!(
+                        "MM quoter: {} symbols, {} requoted ({} quotes), {} halted",
+                        run.symbols, run.refreshed, run.placed, run.halted
+                    );
+                }
+                Ok(())
+            }
+            .boxed()
+        })?;
+        tracing::info!("MM quoter scheduled (every {}s)", quoter_interval);
+    }
+
+    // Gas oracle: congestion multiplier for withdrawal fee quotes
+    let gas_state = state.clone();
+    let gas_interval = config.gas_oracle_interval_secs.max(5);
+    jobs.register("gas_oracle", Schedule::every(Duration::from_secs(gas_interval)), move || {
+        let state = gas_state.clone();
+        async move {
+            if let Some(reading) = state.gas_oracle.poll().await? {
+                tracing::debug!(
+                    "Gas price {} gwei, congestion x{}",
+                    reading.gas_price_gwei,
+                    reading.congestion_multiplier
+                );
+            }
+            Ok(())
+        }
+        .boxed()
+    })?;
+    tracing::info!("Gas oracle scheduled (every {}s)", gas_interval);
+
+    // Deposit confirmations: credit deposits once buried, revert reorged ones
+    let deposit_state = state.clone();
+    let deposit_interval = config.deposit_confirmation_interval_secs.max(1);
+    jobs.register("deposit_confirmations", Schedule::every(Duration::from_secs(deposit_interval)).leader_only(), move || {
+        let state = deposit_state.clone();
+        async move {
+            let config = state.live_config.current();
+            let runs = state
+                .deposit_confirmations
+                .poll(&state.db.pool, config.deposit_confirmations, config.deposit_finality_blocks)
+                .await;
+            for run in runs.iter().filter(|run| run.credited + run.finalized + run.moved + run.reverted > 0) {
+                tracing::info!(
+                    "Deposits on chain {} at head {:?}: {} credited, {} finalized, {} moved, {} reverted",
+                    run.chain_id,
+                    run.head,
+                    run.credited,
+                    run.finalized,
+                    run.moved,
+                    run.reverted
+                );
+            }
+            Ok(())
+        }
+        .boxed()
+    })?;
+    tracing::info!("Deposit confirmations scheduled (every {}s)", deposit_interval);
+
+    // Orderbook snapshots: bound journal replay on restart
+    let snapshotter = Arc::new(BookSnapshotter::new());
+    if journal_config.enabled {
+        let snapshot_state = state.clone();
+        let job_snapshotter = snapshotter.clone();
+        let snapshot_interval = config.orderbook_snapshot_interval_secs.max(5);
+        jobs.register("orderbook_snapshot", Schedule::every(Duration::from_secs(snapshot_interval)), move || {
+            let state = snapshot_state.clone();
+            let snapshotter = job_snapshotter.clone();
+            async move {
+                // The primary snapshots the books a standby mirrors
+                if state.matching_engine.is_standby() {
+                    return Ok(());
+                }
+                let n = snapshotter.run(&state.matching_engine, &state.db.pool).await?;
+                if n > 0 {
+                    tracing::debug!("Snapshotted {} orderbooks", n);
+                }
+                Ok(())
+            }
+            .boxed()
+        })?;
+        tracing::info!("Orderbook snapshots scheduled (every {}s)", snapshot_interval);
+
+        // Trade surveillance: scans the journal for manipulative order patterns
+        let surveillance = Arc::new(SurveillanceService::new(
+            &journal_config.path,
+            SurveillanceConfig {
+                window_secs: config.surveillance_window_secs.max(60),
+                ..SurveillanceConfig::default()
+            },
+        ));
+        let surveillance_pool = state.db.pool.clone();
+        let surveillance_interval = config.surveillance_interval_secs.max(5);
+        jobs.register("surveillance", Schedule::every(Duration::from_secs(surveillance_interval)), move || {
+            let surveillance = surveillance.clone();
+            let pool = surveillance_pool.clone();
+            async move {
+                let n = surveillance.run(&pool).await?;
+                if n > 0 {
+                    tracing::info!("Surveillance queued {} alerts", n);
+                }
+                Ok(())
+            }
+            .boxed()
+        })?;
+        tracing::info!("Trade surveillance scheduled (every {}s)", surveillance_interval);
+    }
+
+    // Drop idempotency keys past their TTL
+    let idempotency_pool = state.db.pool.clone();
+    jobs.register("idempotency_purge", Schedule::every(Duration::from_secs(3600)).leader_only(), move || {
+        let pool = idempotency_pool.clone();
+        async move {
+            let n = IdempotencyService::purge_expired(&pool).await?;
+            if n > 0 {
+                tracing::debug!("Purged {} expired idempotency keys", n);
+            }
+            Ok(())
+        }
+        .boxed()
+    })?;
+
+    // Forget used trading nonces and signatures once their signed timestamps can no longer validate
+    let nonce_pool = state.db.pool.clone();
+    jobs.register("nonce_purge", Schedule::every(Duration::from_secs(3600)).leader_only(), move || {
+        let pool = nonce_pool.clone();
+        async move {
+            let n = NonceService::purge_used(&pool, 86400).await?;
+            if n > 0 {
+                tracing::debug!("Purged {} used trading nonces", n);
+            }
+            Ok(())
+        }
+        .boxed()
+    })?;
+
+    // Event outbox: publish persisted trades to Redis, retrying until Redis takes them
+    if let Some(redis) = state.cache.redis() {
+        let outbox_pool = state.db.pool.clone();
+        let publisher = Arc::new(Publisher::new(redis.clone()));
+        jobs.register("outbox_relay", Schedule::every(Duration::from_millis(250)).leader_only(), move || {
+            let pool = outbox_pool.clone();
+            let publisher = publisher.clone();
+            async move {
+                let run = outbox::publish_pending(&pool, &publisher).await?;
+                let pending = if run.published == 0 && !run.failed { 0 } else { outbox::pending(&pool).await? };
+                metrics::set_outbox_pending(pending);
+                Ok(())
+            }
+            .boxed()
+        })?;
+    }
+    let outbox_state = state.clone();
+    jobs.register("outbox_purge", Schedule::every(Duration::from_secs(3600)).leader_only(), move || {
+        let state = outbox_state.clone();
+        async move {
+            let retention_hours = state.live_config.current().outbox_retention_hours;
+            let n = outbox::purge(&state.db.pool, retention_hours).await?;
+            if n > 0 {
+                tracing::debug!("Purged {} outbox events", n);
+            }
+            Ok(())
+        }
+        .boxed()
+    })?;
+
+    // Drop expired refresh tokens and revocation entries
+    let token_pool = state.db.pool.clone();
+    jobs.register("auth_token_purge", Schedule::every(Duration::from_secs(3600)).leader_only(), move || {
+        let pool = token_pool.clone();
+        async move {
+            let n = AuthTokenService::purge_expired(&pool).await?;
+            if n > 0 {
+                tracing::debug!("Purged {} expired refresh tokens and revocations", n);
+            }
+            Ok(())
+        }
+        .boxed()
+    })?;
+
+    // Forget in-memory rate limit buckets that have refilled
+    let rate_limiter = state.rate_limiter.clone();
+    jobs.register("rate_limit_prune", Schedule::every(Duration::from_secs(60)), move || {
+        let rate_limiter = rate_limiter.clone();
+        async move {
+            rate_limiter.prune_local();
+            Ok(())
+        }
+        .boxed()
+    })?;
+
+    // History archiver: exports (and optionally prunes) rows past retention
+    if let Some(store) = state.archive.clone() {
+        let archive_pool = state.db.pool.clone();
+        let archive_config = Arc::new(ArchiveConfig {
+            retention_days: config.archive_retention_days,
+            prune: config.archive_prune,
+        });
+        let archive_interval = config.archive_interval_secs.max(60);
+        jobs.register("history_archiver", Schedule::every(Duration::from_secs(archive_interval)).leader_only(), move || {
+            let pool = archive_pool.clone();
+            let store = store.clone();
+            let archive_config = archive_config.clone();
+            async move {
+                let n = ArchiveService::run_cycle(&pool, store.as_ref(), &archive_config).await?;
+                if n > 0 {
+                    tracing::info!("History archiver wrote {} segments", n);
+                }
+                Ok(())
+            }
+            .boxed()
+        })?;
+        tracing::info!("History archiver scheduled (every {}s)", archive_interval);
+    }
+
+    // Push reloaded config values into the services that copied them at startup
+    let mut config_events = state.live_config.subscribe();
+    let reload_state = state.clone();
+    tokio::spawn(async move {
+        loop {
+            let event = match config_events.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            let config = reload_state.live_config.current();
+            if event.changes.iter().any(|change| change.key.starts_with("rate_limit_")) {
+                match RateLimitSettings::from_config(&config) {
+                    Ok(settings) => reload_state.rate_limiter.update(settings),
+                    Err(e) => tracing::error!("Failed to reload rate limits: {}", e),
+                }
+            }
+            for (key, job, min_secs) in JOB_INTERVALS {
+                let Some(secs) = event.changes.iter().find(|c| c.key == *key).and_then(|c| c.new.as_u64()) else {
+                    continue;
+                };
+                // Jobs that are disabled in this deployment are not registered
+                if reload_state.jobs.reschedule(job, Duration::from_secs(secs.max(*min_secs))).is_err() {
+                    tracing::debug!("Job {} not running; {} applies on restart", job, key);
+                }
+            }
+        }
+    });
+
     // Build router
     let app = Router::new()
         .route("/health", get(health_check))
         .route("/metrics", get(metrics_endpoint))
         .nest("/api/v1", api::routes::create_router(state.clone()))
+        .nest("/api/v2", api::routes::v2::create_router(state.clone()))
         .nest("/ws", websocket::routes::create_router(state.clone()))
+        .layer(middleware::from_fn(api::deprecation::deprecation_middleware))
         .layer(middleware::from_fn(api::middleware::metrics_middleware))
+        .layer(middleware::from_fn(api::middleware::request_id_middleware))
         .layer(
             CorsLayer::new()
                 .allow_origin(Any)
                 .allow_methods(Any)
-                .allow_headers(Any),
+                .allow_headers(Any)
+                .expose_headers([
+                    HeaderName::from_static(api::middleware::request_id::REQUEST_ID_HEADER),
+                    HeaderName::from_static("deprecation"),
+                    HeaderName::from_static("sunset"),
+                    HeaderName::from_static("link"),
+                    HeaderName::from_static(api::deprecation::DEPRECATED_FIELDS_HEADER),
+                ]),
         )
         .layer(TraceLayer::new_for_http())
-        .with_state(state);
+        .with_state(state.clone());
 
     // Start server
     let addr = SocketAddr::from(([0, 0, 0, 0], config.port));
     tracing::info!("Server listening on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    let coordinator = state.shutdown.clone();
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(async move {
+            shutdown::signal().await;
+            coordinator.begin();
+        })
+        .await?;
+
+    // No request is in flight anymore: flush workers, stop jobs, snapshot the engine
+    tracing::info!("HTTP server stopped, flushing background workers");
+    state.shutdown.stop_workers();
+    let timeout = Duration::from_secs(config.shutdown_timeout_secs);
+    if tokio::time::timeout(timeout, state.jobs.shutdown()).await.is_err() {
+        tracing::warn!("Jobs still running after {:?}", timeout);
+    }
+    // Hand the singleton jobs to another instance right away
+    state.leader.resign().await;
+    state.shutdown.wait(timeout).await;
+    match state.matching_engine.write_snapshot(std::path::Path::new(&config.engine_snapshot_path)) {
+        Ok(orders) => tracing::info!("Engine snapshot with {} resting orders written to {}", orders, config.engine_snapshot_path),
+        Err(e) => tracing::error!("Failed to write engine snapshot: {}", e),
+    }
+    if journal_config.enabled {
+        match snapshotter.run(&state.matching_engine, &state.db.pool).await {
+            Ok(books) => tracing::info!("Snapshotted {} orderbooks for warm restart", books),
+            Err(e) => tracing::error!("Failed to snapshot orderbooks: {}", e),
+        }
+    }
+    tracing::info!("Shutdown complete");
 
     Ok(())
 }
 
+/// Settlement currency of a market's trades
+async fn trade_settlement(state: &AppState, market_id: uuid::Uuid) -> Result<Settlement, String> {
+    let config = state
+        .market_service
+        .get_market_config(&state.db.pool, market_id)
+        .await
+        .map_err(|e| format!("market {} not loaded: {}", market_id, e))?;
+    let quote_asset = config.and_then(|c| c.quote_asset.clone());
+    state.token_prices.settlement(quote_asset.as_deref()).await.map_err(|e| e.to_string())
+}
+
 async fn health_check() -> &'static str {
     "OK"
 }
@@ -7,11 +7,17 @@
 //! - Cache metrics (hits, misses, latency)
 //! - Database metrics (query latency, connections)
 //! - WebSocket metrics (connections, messages)
+//! - Per-symbol metrics (open orders, resting depth, open interest, trades),
+//!   limited to the top-N most active symbols plus an "other" bucket
 
 #![allow(dead_code)]
 
 use metrics::{counter, gauge, histogram};
 use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use parking_lot::RwLock;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
 use std::time::Instant;
 
 /// Metric names as constants for consistency
@@ -26,6 +32,15 @@ pub mod names {
     pub const ORDERS_MATCHED_TOTAL: &str = "orders_matched_total";
     pub const ORDERS_CANCELLED_TOTAL: &str = "orders_cancelled_total";
     pub const ORDER_MATCH_DURATION_SECONDS: &str = "order_match_duration_seconds";
+    pub const ORDER_CANCEL_DURATION_SECONDS: &str = "order_cancel_duration_seconds";
+    pub const ENGINE_LANE_WAIT_SECONDS: &str = "engine_lane_wait_seconds";
+    pub const ENGINE_CANCEL_OVERTAKES_TOTAL: &str = "engine_cancel_overtakes_total";
+    pub const ENGINE_REPLICATION_LAG_ENTRIES: &str = "engine_replication_lag_entries";
+    pub const ENGINE_REPLICATION_LAG_SECONDS: &str = "engine_replication_lag_seconds";
+    pub const ENGINE_REPLICATION_PROMOTIONS_TOTAL: &str = "engine_replication_promotions_total";
+    pub const ENGINE_REPLICATION_AUTH_FAILURES_TOTAL: &str = "engine_replication_auth_failures_total";
+    pub const ORDER_RECONCILE_MISMATCHES_TOTAL: &str = "order_reconcile_mismatches_total";
+    pub const ORDER_RECONCILE_LAST_MISMATCHES: &str = "order_reconcile_last_mismatches";
     pub const TRADES_EXECUTED_TOTAL: &str = "trades_executed_total";
     pub const TRADE_VOLUME_USDC: &str = "trade_volume_usdc";
 
@@ -54,14 +69,66 @@ pub mod names {
     pub const WS_CONNECTIONS_ACTIVE: &str = "ws_connections_active";
     pub const WS_MESSAGES_SENT_TOTAL: &str = "ws_messages_sent_total";
     pub const WS_MESSAGES_RECEIVED_TOTAL: &str = "ws_messages_received_total";
+    pub const WS_MESSAGES_DROPPED_TOTAL: &str = "ws_messages_dropped_total";
+    pub const WS_SEND_QUEUE_DEPTH: &str = "ws_send_queue_depth";
+    pub const WS_CONNECTIONS_AUTHENTICATED: &str = "ws_connections_authenticated";
+    pub const WS_CONNECTIONS_CLOSED_TOTAL: &str = "ws_connections_closed_total";
+
+    // Per-Symbol Metrics
+    pub const SYMBOL_OPEN_ORDERS: &str = "symbol_open_orders";
+    pub const SYMBOL_RESTING_DEPTH: &str = "symbol_resting_depth";
+    pub const SYMBOL_OPEN_INTEREST: &str = "symbol_open_interest";
+    pub const SYMBOL_TRADES_TOTAL: &str = "symbol_trades_total";
 
     // Settlement Metrics
     pub const SETTLEMENTS_TOTAL: &str = "settlements_total";
     pub const SETTLEMENT_AMOUNT_USDC: &str = "settlement_amount_usdc";
 
+    // Liquidation Metrics
+    pub const LIQUIDATIONS_TOTAL: &str = "liquidations_total";
+    pub const MARGIN_CALLS_TOTAL: &str = "margin_calls_total";
+    pub const TP_SL_LEGS_TOTAL: &str = "tp_sl_legs_total";
+
+    // Position Metrics
+    pub const POSITION_VERSION_CONFLICTS_TOTAL: &str = "position_version_conflicts_total";
+
+    // Funding Metrics
+    pub const FUNDING_CLAMP_ALERTS_TOTAL: &str = "funding_clamp_alerts_total";
+    pub const SURVEILLANCE_ALERTS_TOTAL: &str = "surveillance_alerts_total";
+
+    // Market Maker Metrics
+    pub const MM_CROSSINGS_PREVENTED_TOTAL: &str = "mm_crossings_prevented_total";
+    pub const MM_IMBALANCE_PROTECTIONS_TOTAL: &str = "mm_imbalance_protections_total";
+    pub const MM_QUOTE_REFRESHES_TOTAL: &str = "mm_quote_refreshes_total";
+    pub const MM_QUOTING_HALTS_TOTAL: &str = "mm_quoting_halts_total";
+    pub const MM_MIRROR_STALE_TOTAL: &str = "mm_mirror_stale_total";
+
+    // Trade Tape Metrics
+    pub const TAPE_EXPORTS_TOTAL: &str = "tape_exports_total";
+    pub const DATA_EXPORTS_TOTAL: &str = "data_exports_total";
+
+    // Index Price Metrics
+    pub const INDEX_SOURCE_ERRORS_TOTAL: &str = "index_source_errors_total";
+    pub const INDEX_QUOTES_REJECTED_TOTAL: &str = "index_quotes_rejected_total";
+
     // Oracle Metrics
     pub const ORACLE_UPDATES_TOTAL: &str = "oracle_updates_total";
     pub const ORACLE_ERRORS_TOTAL: &str = "oracle_errors_total";
+    pub const ORACLE_PRICE_AGE_SECONDS: &str = "oracle_price_age_seconds";
+    pub const ORACLE_PRICE_DEVIATION: &str = "oracle_price_deviation";
+
+    // Background Job Metrics
+    pub const JOB_RUNS_TOTAL: &str = "job_runs_total";
+    pub const JOB_RUN_DURATION_SECONDS: &str = "job_run_duration_seconds";
+    pub const JOB_LAST_SUCCESS_TIMESTAMP: &str = "job_last_success_timestamp";
+    pub const JOB_CONSECUTIVE_FAILURES: &str = "job_consecutive_failures";
+    pub const JOB_LEADER: &str = "job_leader";
+    pub const JOB_LEADER_TRANSITIONS_TOTAL: &str = "job_leader_transitions_total";
+
+    // Event Outbox Metrics
+    pub const OUTBOX_PUBLISHED_TOTAL: &str = "outbox_published_total";
+    pub const OUTBOX_PUBLISH_FAILURES_TOTAL: &str = "outbox_publish_failures_total";
+    pub const OUTBOX_PENDING: &str = "outbox_pending";
 }
 
 /// Label keys
@@ -79,6 +146,17 @@ pub mod labels {
     pub const OPERATION: &str = "operation";
     pub const QUERY_TYPE: &str = "query_type";
     pub const SOURCE: &str = "source";
+    pub const STREAM: &str = "stream";
+    pub const SYMBOL: &str = "symbol";
+    pub const REASON: &str = "reason";
+    pub const INSTANCE: &str = "instance";
+    pub const LANE: &str = "lane";
+    pub const KIND: &str = "kind";
+    pub const ACTION: &str = "action";
+    pub const MODE: &str = "mode";
+    pub const BOUND: &str = "bound";
+    pub const JOB: &str = "job";
+    pub const PATTERN: &str = "pattern";
 }
 
 /// Initialize Prometheus metrics exporter
@@ -99,6 +177,17 @@ pub fn init_metrics() -> PrometheusHandle {
             &[0.0001, 0.0005, 0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.5],
         )
         .unwrap()
+        // Cancel latency (lane wait + apply) and per-lane admission wait buckets
+        .set_buckets_for_metric(
+            Matcher::Full(names::ORDER_CANCEL_DURATION_SECONDS.to_string()),
+            &[0.00005, 0.0001, 0.0005, 0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.5],
+        )
+        .unwrap()
+        .set_buckets_for_metric(
+            Matcher::Full(names::ENGINE_LANE_WAIT_SECONDS.to_string()),
+            &[0.00001, 0.00005, 0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5],
+        )
+        .unwrap()
         // Cache operation duration buckets
         .set_buckets_for_metric(
             Matcher::Full(names::CACHE_OPERATION_DURATION_SECONDS.to_string()),
@@ -110,6 +199,18 @@ pub fn init_metrics() -> PrometheusHandle {
             Matcher::Full(names::DB_QUERY_DURATION_SECONDS.to_string()),
             &[0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 5.0],
         )
+        .unwrap()
+        // Background job run duration buckets (in seconds)
+        .set_buckets_for_metric(
+            Matcher::Full(names::JOB_RUN_DURATION_SECONDS.to_string()),
+            &[0.001, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 15.0, 60.0, 300.0],
+        )
+        .unwrap()
+        // Per-connection WebSocket send queue depth (messages)
+        .set_buckets_for_metric(
+            Matcher::Full(names::WS_SEND_QUEUE_DEPTH.to_string()),
+            &[0.0, 1.0, 5.0, 10.0, 50.0, 100.0, 250.0, 500.0, 1000.0],
+        )
         .unwrap();
 
     builder
@@ -141,9 +242,9 @@ pub fn record_http_request(method: &str, endpoint: &str, status: u16, duration_s
     .record(duration_secs);
 }
 
-/// Track in-flight requests
-pub fn set_http_requests_in_flight(count: i64) {
-    gauge!(names::HTTP_REQUESTS_IN_FLIGHT).set(count as f64);
+/// Track in-flight requests (+1 when a request starts, -1 when it ends)
+pub fn add_http_requests_in_flight(delta: i64) {
+    gauge!(names::HTTP_REQUESTS_IN_FLIGHT).increment(delta as f64);
 }
 
 // ============================================================================
@@ -179,6 +280,196 @@ pub fn record_order_match_duration(duration_secs: f64) {
     histogram!(names::ORDER_MATCH_DURATION_SECONDS).record(duration_secs);
 }
 
+/// Record end-to-end engine cancel latency (including lane wait)
+pub fn record_order_cancel_duration(duration_secs: f64) {
+    histogram!(names::ORDER_CANCEL_DURATION_SECONDS).record(duration_secs);
+}
+
+/// Record time a command waited for its market's lane
+pub fn record_lane_wait(lane: &str, duration_secs: f64) {
+    histogram!(
+        names::ENGINE_LANE_WAIT_SECONDS,
+        labels::LANE => lane.to_string()
+    )
+    .record(duration_secs);
+}
+
+/// Record a cancel admitted ahead of waiting placements
+pub fn record_cancel_overtake() {
+    counter!(names::ENGINE_CANCEL_OVERTAKES_TOTAL).increment(1);
+}
+
+/// Set how far a warm standby is behind its primary (commands, and age of
+/// the newest applied command)
+pub fn set_replication_lag(entries: u64, seconds: f64) {
+    gauge!(names::ENGINE_REPLICATION_LAG_ENTRIES).set(entries as f64);
+    gauge!(names::ENGINE_REPLICATION_LAG_SECONDS).set(seconds);
+}
+
+/// Record a warm standby promoted to primary
+pub fn record_replication_promotion() {
+    counter!(names::ENGINE_REPLICATION_PROMOTIONS_TOTAL).increment(1);
+}
+
+/// Record a replication request refused for its secret: by this primary
+/// (`role` = primary) or by the primary this standby follows (standby)
+pub fn record_replication_auth_failure(role: &str) {
+    counter!(names::ENGINE_REPLICATION_AUTH_FAILURES_TOTAL, labels::MODE => role.to_string()).increment(1);
+}
+
+/// Record an order found out of sync between the database and the engine, and how it was resolved
+pub fn record_order_reconcile_mismatch(kind: &str, action: &str) {
+    counter!(
+        names::ORDER_RECONCILE_MISMATCHES_TOTAL,
+        labels::KIND => kind.to_string(),
+        labels::ACTION => action.to_string()
+    )
+    .increment(1);
+}
+
+/// Set the mismatch counts found by the latest reconciliation sweep
+pub fn set_order_reconcile_last_mismatches(kind: &str, count: usize) {
+    gauge!(
+        names::ORDER_RECONCILE_LAST_MISMATCHES,
+        labels::KIND => kind.to_string()
+    )
+    .set(count as f64);
+}
+
+/// Record a liquidation position close (mode: executed, failed or dry_run)
+pub fn record_liquidation(mode: &str) {
+    counter!(
+        names::LIQUIDATIONS_TOTAL,
+        labels::MODE => mode.to_string()
+    )
+    .increment(1);
+}
+
+/// Record a margin call escalation (level: notice, alert or reduce)
+pub fn record_margin_call(level: &str) {
+    counter!(
+        names::MARGIN_CALLS_TOTAL,
+        labels::KIND => level.to_string()
+    )
+    .increment(1);
+}
+
+/// Record a finished TP/SL leg (kind: take_profit or stop_loss; status: executed, failed or cancelled)
+pub fn record_tp_sl_leg(kind: &str, status: &str) {
+    counter!(
+        names::TP_SL_LEGS_TOTAL,
+        labels::KIND => kind.to_string(),
+        labels::STATUS => status.to_string()
+    )
+    .increment(1);
+}
+
+/// Record a position fill retried after a concurrent update
+pub fn record_position_version_conflict() {
+    counter!(names::POSITION_VERSION_CONFLICTS_TOTAL).increment(1);
+}
+
+/// Record a funding rate left on a clamp bound ("cap" or "floor") too long
+pub fn record_funding_clamp_alert(side: &str) {
+    counter!(
+        names::FUNDING_CLAMP_ALERTS_TOTAL,
+        labels::BOUND => side.to_string()
+    )
+    .increment(1);
+}
+
+/// Record a queued surveillance alert by pattern
+pub fn record_surveillance_alert(pattern: &str) {
+    counter!(
+        names::SURVEILLANCE_ALERTS_TOTAL,
+        labels::PATTERN => pattern.to_string()
+    )
+    .increment(1);
+}
+
+/// Record an MM quote kept from crossing the book ("clamped", "cancelled" or "rejected")
+pub fn record_mm_crossing_prevented(action: &str) {
+    counter!(
+        names::MM_CROSSINGS_PREVENTED_TOTAL,
+        labels::ACTION => action.to_string()
+    )
+    .increment(1);
+}
+
+/// Record an MM imbalance protection triggered on a symbol
+pub fn record_mm_imbalance_protection(symbol: &str) {
+    counter!(
+        names::MM_IMBALANCE_PROTECTIONS_TOTAL,
+        labels::SYMBOL => symbol.to_string()
+    )
+    .increment(1);
+}
+
+/// Record an MM quote ladder rebuilt ("start", "price" or "inventory")
+pub fn record_mm_quote_refresh(reason: &str) {
+    counter!(
+        names::MM_QUOTE_REFRESHES_TOTAL,
+        labels::REASON => reason.to_string()
+    )
+    .increment(1);
+}
+
+/// Record MM quoting halted on a symbol by its loss limit
+pub fn record_mm_quoting_halted(symbol: &str) {
+    counter!(
+        names::MM_QUOTING_HALTS_TOTAL,
+        labels::SYMBOL => symbol.to_string()
+    )
+    .increment(1);
+}
+
+/// Record mirrored MM quotes pulled on a symbol by a stale external feed
+pub fn record_mm_mirror_stale(symbol: &str) {
+    counter!(
+        names::MM_MIRROR_STALE_TOTAL,
+        labels::SYMBOL => symbol.to_string()
+    )
+    .increment(1);
+}
+
+/// Record a finished trade tape export ("completed" or "failed")
+pub fn record_tape_export(status: &str) {
+    counter!(
+        names::TAPE_EXPORTS_TOTAL,
+        labels::STATUS => status.to_string()
+    )
+    .increment(1);
+}
+
+/// Record a finished history export ("completed" or "failed")
+pub fn record_data_export(kind: &str, status: &str) {
+    counter!(
+        names::DATA_EXPORTS_TOTAL,
+        labels::KIND => kind.to_string(),
+        labels::STATUS => status.to_string()
+    )
+    .increment(1);
+}
+
+/// Record a failed index price fetch from a venue
+pub fn record_index_source_error(source: &str) {
+    counter!(
+        names::INDEX_SOURCE_ERRORS_TOTAL,
+        labels::SOURCE => source.to_string()
+    )
+    .increment(1);
+}
+
+/// Record a venue quote left out of an index price ("stale" or "outlier")
+pub fn record_index_quote_rejected(source: &str, reason: &str) {
+    counter!(
+        names::INDEX_QUOTES_REJECTED_TOTAL,
+        labels::SOURCE => source.to_string(),
+        labels::REASON => reason.to_string()
+    )
+    .increment(1);
+}
+
 /// Record trade execution
 pub fn record_trade_executed(match_type: &str, volume_usdc: f64) {
     counter!(
@@ -322,6 +613,191 @@ pub fn record_ws_message_received() {
     counter!(names::WS_MESSAGES_RECEIVED_TOTAL).increment(1);
 }
 
+/// Record WebSocket messages dropped for a slow client
+///
+/// `reason` is one of "overflow", "coalesced", "resync" or "broadcast_lag".
+pub fn record_ws_messages_dropped(stream: &str, reason: &str, count: u64) {
+    counter!(
+        names::WS_MESSAGES_DROPPED_TOTAL,
+        labels::STREAM => stream.to_string(),
+        labels::REASON => reason.to_string()
+    )
+    .increment(count);
+}
+
+/// Record a connection's pending outbound queue depth
+pub fn record_ws_send_queue_depth(depth: usize) {
+    histogram!(names::WS_SEND_QUEUE_DEPTH).record(depth as f64);
+}
+
+/// Set active WebSocket connections with an authenticated user
+pub fn set_ws_authenticated_connections(count: i64) {
+    gauge!(names::WS_CONNECTIONS_AUTHENTICATED).set(count as f64);
+}
+
+/// Record a WebSocket connection closed or refused by the server
+///
+/// `reason` is one of "idle_timeout", "ip_limit" or "user_limit".
+pub fn record_ws_connection_closed(reason: &str) {
+    counter!(names::WS_CONNECTIONS_CLOSED_TOTAL, labels::REASON => reason.to_string()).increment(1);
+}
+
+// ============================================================================
+// Per-Symbol Metrics
+// ============================================================================
+
+/// Label used for symbols outside the top-N
+pub const OTHER_SYMBOL_LABEL: &str = "other";
+
+/// Label used for every symbol when per-symbol labels are disabled
+pub const ALL_SYMBOLS_LABEL: &str = "all";
+
+/// Cardinality guard for the `symbol` label
+///
+/// Only the `max_symbols` most active symbols (ranked at each sample) get their
+/// own label; everything else is reported as "other". Labels stay stable
+/// between samples so counters are not split across label sets mid-interval.
+pub struct SymbolLabels {
+    enabled: bool,
+    max_symbols: usize,
+    /// Symbols currently labelled individually
+    top: RwLock<HashSet<String>>,
+    /// Labels emitted by the last gauge sample (zeroed when they drop out)
+    emitted: RwLock<HashSet<String>>,
+    /// Trades per symbol since the last sample (used for ranking)
+    trades_since_sample: RwLock<HashMap<String, AtomicU64>>,
+}
+
+impl SymbolLabels {
+    pub fn new(enabled: bool, max_symbols: usize) -> Self {
+        Self {
+            enabled,
+            max_symbols,
+            top: RwLock::new(HashSet::new()),
+            emitted: RwLock::new(HashSet::new()),
+            trades_since_sample: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Label value for a symbol
+    pub fn label(&self, symbol: &str) -> String {
+        if !self.enabled {
+            ALL_SYMBOLS_LABEL.to_string()
+        } else if self.top.read().contains(symbol) {
+            symbol.to_string()
+        } else {
+            OTHER_SYMBOL_LABEL.to_string()
+        }
+    }
+
+    /// Count a trade towards the symbol's activity ranking
+    fn note_trade(&self, symbol: &str) {
+        if let Some(count) = self.trades_since_sample.read().get(symbol) {
+            count.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        self.trades_since_sample
+            .write()
+            .entry(symbol.to_string())
+            .or_insert_with(|| AtomicU64::new(0))
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Re-rank symbols by activity (trades since last sample, then open orders)
+    /// and keep the top `max_symbols`
+    fn rerank(&self, samples: &[SymbolBookSample]) {
+        let trades: HashMap<String, u64> = std::mem::take(&mut *self.trades_since_sample.write())
+            .into_iter()
+            .map(|(symbol, count)| (symbol, count.into_inner()))
+            .collect();
+
+        let mut ranked: Vec<(&str, u64, i64)> = samples
+            .iter()
+            .map(|s| (s.symbol.as_str(), trades.get(&s.symbol).copied().unwrap_or(0), s.open_orders))
+            .collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then(b.2.cmp(&a.2)).then(a.0.cmp(b.0)));
+
+        *self.top.write() = ranked
+            .into_iter()
+            .take(self.max_symbols)
+            .map(|(symbol, _, _)| symbol.to_string())
+            .collect();
+    }
+}
+
+static SYMBOL_LABELS: OnceLock<SymbolLabels> = OnceLock::new();
+
+/// Configure per-symbol labelling (call once at startup; later calls are ignored)
+pub fn configure_symbol_labels(enabled: bool, max_symbols: usize) {
+    let _ = SYMBOL_LABELS.set(SymbolLabels::new(enabled, max_symbols));
+}
+
+fn symbol_labels() -> &'static SymbolLabels {
+    SYMBOL_LABELS.get_or_init(|| SymbolLabels::new(true, 20))
+}
+
+/// Point-in-time book state for one symbol
+#[derive(Debug, Clone, Default)]
+pub struct SymbolBookSample {
+    pub symbol: String,
+    pub open_orders: i64,
+    pub bid_depth: f64,
+    pub ask_depth: f64,
+    pub open_interest: f64,
+}
+
+/// Record an executed trade for a symbol
+pub fn record_symbol_trade(symbol: &str) {
+    let guard = symbol_labels();
+    guard.note_trade(symbol);
+    counter!(names::SYMBOL_TRADES_TOTAL, labels::SYMBOL => guard.label(symbol)).increment(1);
+}
+
+/// Publish per-symbol book gauges
+///
+/// Re-ranks the top-N symbols, sums the remaining symbols into "other", and
+/// zeroes labels that were emitted last time but are no longer present.
+pub fn set_symbol_book_metrics(samples: &[SymbolBookSample]) {
+    let guard = symbol_labels();
+    guard.rerank(samples);
+
+    let mut aggregated: HashMap<String, SymbolBookSample> = HashMap::new();
+    for sample in samples {
+        let entry = aggregated.entry(guard.label(&sample.symbol)).or_default();
+        entry.open_orders += sample.open_orders;
+        entry.bid_depth += sample.bid_depth;
+        entry.ask_depth += sample.ask_depth;
+        entry.open_interest += sample.open_interest;
+    }
+
+    let mut emitted = guard.emitted.write();
+    for stale in emitted.iter().filter(|label| !aggregated.contains_key(*label)) {
+        set_symbol_gauges(stale, &SymbolBookSample::default());
+    }
+    *emitted = aggregated.keys().cloned().collect();
+
+    for (label, sample) in &aggregated {
+        set_symbol_gauges(label, sample);
+    }
+}
+
+fn set_symbol_gauges(label: &str, sample: &SymbolBookSample) {
+    gauge!(names::SYMBOL_OPEN_ORDERS, labels::SYMBOL => label.to_string()).set(sample.open_orders as f64);
+    gauge!(
+        names::SYMBOL_RESTING_DEPTH,
+        labels::SYMBOL => label.to_string(),
+        labels::ORDER_SIDE => "bid"
+    )
+    .set(sample.bid_depth);
+    gauge!(
+        names::SYMBOL_RESTING_DEPTH,
+        labels::SYMBOL => label.to_string(),
+        labels::ORDER_SIDE => "ask"
+    )
+    .set(sample.ask_depth);
+    gauge!(names::SYMBOL_OPEN_INTEREST, labels::SYMBOL => label.to_string()).set(sample.open_interest);
+}
+
 // ============================================================================
 // Settlement Metrics
 // ============================================================================
@@ -359,6 +835,76 @@ pub fn record_oracle_error(source: &str) {
     .increment(1);
 }
 
+/// Record how old an oracle's latest on-chain price is
+pub fn record_oracle_price_age(symbol: &str, source: &str, age_secs: i64) {
+    gauge!(
+        names::ORACLE_PRICE_AGE_SECONDS,
+        labels::SYMBOL => symbol.to_string(),
+        labels::SOURCE => source.to_string()
+    )
+    .set(age_secs as f64);
+}
+
+/// Record the relative deviation between two oracles' prices for a feed
+pub fn record_oracle_deviation(symbol: &str, deviation: f64) {
+    gauge!(
+        names::ORACLE_PRICE_DEVIATION,
+        labels::SYMBOL => symbol.to_string()
+    )
+    .set(deviation);
+}
+
+// ============================================================================
+// Background Job Metrics
+// ============================================================================
+
+/// Record a finished background job run
+pub fn record_job_run(job: &str, success: bool, duration_secs: f64, consecutive_failures: u64) {
+    counter!(
+        names::JOB_RUNS_TOTAL,
+        labels::JOB => job.to_string(),
+        labels::STATUS => if success { "ok" } else { "error" }
+    )
+    .increment(1);
+    histogram!(names::JOB_RUN_DURATION_SECONDS, labels::JOB => job.to_string()).record(duration_secs);
+    gauge!(names::JOB_CONSECUTIVE_FAILURES, labels::JOB => job.to_string()).set(consecutive_failures as f64);
+    if success {
+        gauge!(names::JOB_LAST_SUCCESS_TIMESTAMP, labels::JOB => job.to_string())
+            .set(chrono::Utc::now().timestamp() as f64);
+    }
+}
+
+/// Set whether this instance is the background job leader (1) or not (0)
+pub fn set_leader(instance: &str, leader: bool) {
+    gauge!(names::JOB_LEADER, labels::INSTANCE => instance.to_string()).set(if leader { 1.0 } else { 0.0 });
+}
+
+/// Record this instance gaining or losing job leadership
+pub fn record_leader_transition(leader: bool) {
+    counter!(
+        names::JOB_LEADER_TRANSITIONS_TOTAL,
+        labels::ACTION => if leader { "acquired" } else { "lost" }
+    )
+    .increment(1);
+}
+
+// ============================================================================
+// Event Outbox Metrics
+// ============================================================================
+
+/// Record an outbox relay run
+pub fn record_outbox_run(published: usize, failed: bool) {
+    counter!(names::OUTBOX_PUBLISHED_TOTAL).increment(published as u64);
+    if failed {
+        counter!(names::OUTBOX_PUBLISH_FAILURES_TOTAL).increment(1);
+    }
+}
+
+/// Set the number of outbox events waiting to be published
+pub fn set_outbox_pending(pending: i64) {
+    gauge!(names::OUTBOX_PENDING).set(pending as f64);
+}
+
 // ============================================================================
 // Timer Helper
 // ============================================================================
@@ -392,6 +938,34 @@ impl Default for Timer {
 mod tests {
     use super::*;
 
+    fn sample(symbol: &str, open_orders: i64) -> SymbolBookSample {
+        SymbolBookSample {
+            symbol: symbol.to_string(),
+            open_orders,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_symbol_labels_top_n() {
+        let guard = SymbolLabels::new(true, 2);
+        guard.note_trade("c");
+        guard.rerank(&[sample("a", 10), sample("b", 5), sample("c", 1)]);
+
+        // "c" ranks first on trades, "a" second on open orders
+        assert_eq!(guard.label("c"), "c");
+        assert_eq!(guard.label("a"), "a");
+        assert_eq!(guard.label("b"), OTHER_SYMBOL_LABEL);
+        assert_eq!(guard.label("unknown"), OTHER_SYMBOL_LABEL);
+    }
+
+    #[test]
+    fn test_symbol_labels_disabled() {
+        let guard = SymbolLabels::new(false, 2);
+        guard.rerank(&[sample("a", 10)]);
+        assert_eq!(guard.label("a"), ALL_SYMBOLS_LABEL);
+    }
+
     #[test]
     fn test_timer() {
         let timer = Timer::new();
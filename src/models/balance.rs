@@ -22,6 +22,9 @@ pub struct BalanceResponse {
     pub available: Decimal,
     pub frozen: Decimal,
     pub total: Decimal,
+    /// Chains the token can be withdrawn on (balances are shared across chains)
+    #[serde(default)]
+    pub chains: Vec<u64>,
 }
 
 impl From<Balance> for BalanceResponse {
@@ -31,6 +34,7 @@ impl From<Balance> for BalanceResponse {
             available: balance.available.clone(),
             frozen: balance.frozen.clone(),
             total: balance.available + balance.frozen,
+            chains: Vec::new(),
         }
     }
 }
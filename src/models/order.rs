@@ -115,6 +115,38 @@ impl std::str::FromStr for OrderType {
     }
 }
 
+/// 订单持久化确认模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DurabilityMode {
+    /// 撮合引擎预写日志落盘后才确认 (需启用引擎日志)
+    AckAfterJournal,
+    /// 内存撮合后即确认
+    #[default]
+    AckFast,
+}
+
+impl fmt::Display for DurabilityMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DurabilityMode::AckAfterJournal => write!(f, "ack_after_journal"),
+            DurabilityMode::AckFast => write!(f, "ack_fast"),
+        }
+    }
+}
+
+impl std::str::FromStr for DurabilityMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ack_after_journal" => Ok(DurabilityMode::AckAfterJournal),
+            "ack_fast" => Ok(DurabilityMode::AckFast),
+            _ => Err(format!("Invalid durability mode: {}", s)),
+        }
+    }
+}
+
 /// 订单状态
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "order_status", rename_all = "lowercase")]
@@ -285,8 +317,9 @@ pub struct CreateOrderRequest {
     /// 订单方向
     pub side: OrderSide,
 
-    /// 订单类型
-    pub order_type: OrderType,
+    /// 订单类型 (省略时使用账户偏好, 默认限价单)
+    #[serde(default)]
+    pub order_type: Option<OrderType>,
 
     /// 概率价格 (0.01 - 0.99)
     pub price: Decimal,
@@ -294,11 +327,36 @@ pub struct CreateOrderRequest {
     /// 订单数量 (份额)
     pub amount: Decimal,
 
-    /// EIP-712 签名
+    /// EIP-712 签名 (API key 请求无需签名)
+    #[serde(default)]
     pub signature: String,
 
     /// 签名时间戳 (毫秒)
     pub timestamp: u64,
+
+    /// 服务器时间令牌 (GET /time, 时钟偏差时使用)
+    #[serde(default)]
+    pub timestamp_token: Option<String>,
+
+    /// 交易 nonce (GET /auth/nonce/trading, 签名时包含)
+    #[serde(default)]
+    pub nonce: Option<u64>,
+
+    /// 杠杆倍数 (省略时使用账户偏好)
+    #[serde(default)]
+    pub leverage: Option<u32>,
+
+    /// 市价单滑点容忍度 (省略时使用账户偏好)
+    #[serde(default)]
+    pub slippage_tolerance: Option<Decimal>,
+
+    /// 只减仓 (省略时使用账户偏好)
+    #[serde(default)]
+    pub reduce_only: Option<bool>,
+
+    /// 持久化确认模式 (省略时使用账户偏好, 默认 ack_fast)
+    #[serde(default)]
+    pub durability: Option<DurabilityMode>,
 }
 
 #[allow(dead_code)]
@@ -310,10 +368,20 @@ impl CreateOrderRequest {
     /// 最小订单价值 (USDC)
     pub const MIN_ORDER_VALUE: &'static str = "1.0";
 
+    /// 最小价格 (Decimal)
+    pub fn min_price() -> Decimal {
+        Decimal::from_str_exact(Self::MIN_PRICE).unwrap()
+    }
+
+    /// 最大价格 (Decimal)
+    pub fn max_price() -> Decimal {
+        Decimal::from_str_exact(Self::MAX_PRICE).unwrap()
+    }
+
     /// 验证请求
     pub fn validate(&self) -> Result<(), OrderValidationError> {
-        let min_price = Decimal::from_str_exact(Self::MIN_PRICE).unwrap();
-        let max_price = Decimal::from_str_exact(Self::MAX_PRICE).unwrap();
+        let min_price = Self::min_price();
+        let max_price = Self::max_price();
         let min_value = Decimal::from_str_exact(Self::MIN_ORDER_VALUE).unwrap();
 
         // 价格范围检查
@@ -473,6 +541,16 @@ mod tests {
         assert!(!OrderStatus::Rejected.is_active());
     }
 
+    #[test]
+    fn test_durability_mode_round_trip() {
+        for mode in [DurabilityMode::AckAfterJournal, DurabilityMode::AckFast] {
+            assert_eq!(mode.to_string().parse::<DurabilityMode>(), Ok(mode));
+            assert_eq!(serde_json::to_value(mode).unwrap(), mode.to_string());
+        }
+        assert_eq!(DurabilityMode::default(), DurabilityMode::AckFast);
+        assert!("ack_later".parse::<DurabilityMode>().is_err());
+    }
+
     #[test]
     fn test_order_remaining_amount() {
         let order = Order {
@@ -525,11 +603,17 @@ mod tests {
             outcome_id: Uuid::new_v4(),
             share_type: ShareType::Yes,
             side: OrderSide::Buy,
-            order_type: OrderType::Limit,
+            order_type: Some(OrderType::Limit),
             price: dec!(0.65),
             amount: dec!(10),
             signature: "0x".to_string(),
             timestamp: 1704067200000,
+            timestamp_token: None,
+            nonce: None,
+            leverage: None,
+            slippage_tolerance: None,
+            reduce_only: None,
+            durability: None,
         };
         assert!(valid_req.validate().is_ok());
 
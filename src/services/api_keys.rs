@@ -0,0 +1,411 @@
+//! API Keys
+//!
+//! Key / secret pairs for trading bots, so they don't need a hot wallet to
+//! sign every request. A key carries scopes: `read` (GET requests) and
+//! `trade` (orders and every other write); withdrawals and key or sub-account
+//! management always need the wallet's own session. A key can be limited to
+//! client IPs or CIDR ranges and bound to one of the owner's sub-accounts.
+//!
+//! Requests are signed with HMAC-SHA256 instead of per-order EIP-712
+//! signatures:
+//!
+//! ```text
+//! X-API-Key:       <api key>
+//! X-API-Timestamp: <unix millis>
+//! X-API-Signature: hex(HMAC-SHA256(secret, timestamp + METHOD + path?query + body))
+//! ```
+//!
+//! The timestamp must be within [`RECV_WINDOW_MS`] of the server clock.
+//! Orders keep their nonce and timestamp, so replays are still refused.
+//!
+//! Secrets are never stored: a key's secret is derived from the server's
+//! `api_key_secret` (HMAC-SHA256 over the key) and shown once, when the key
+//! is issued. Revoking a key takes effect on its next request.
+
+use chrono::{DateTime, Utc};
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::net::IpAddr;
+use uuid::Uuid;
+
+use crate::services::sub_account::SubAccountService;
+
+/// Most active keys per owner
+pub const MAX_ACTIVE_KEYS: i64 = 10;
+
+/// Largest clock difference accepted on a signed request
+pub const RECV_WINDOW_MS: i64 = 5_000;
+
+/// Longest key label and IP allowlist
+const MAX_LABEL_LEN: usize = 64;
+const MAX_ALLOWLIST_LEN: usize = 20;
+
+/// API key errors
+#[derive(Debug, thiserror::Error)]
+pub enum ApiKeyError {
+    #[error("API key not found: {0}")]
+    NotFound(Uuid),
+
+    #[error("Invalid API key request: {0}")]
+    Invalid(String),
+
+    #[error("API key limit reached ({0} active)")]
+    LimitReached(i64),
+
+    #[error("Unknown or revoked API key")]
+    UnknownKey,
+
+    #[error("Request timestamp outside the {RECV_WINDOW_MS}ms window")]
+    StaleTimestamp,
+
+    #[error("Invalid request signature")]
+    BadSignature,
+
+    #[error("Client IP not allowed: {0}")]
+    IpNotAllowed(String),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// What a key may do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiScope {
+    Read,
+    Trade,
+}
+
+impl ApiScope {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ApiScope::Read => "read",
+            ApiScope::Trade => "trade",
+        }
+    }
+
+    fn parse(s: &str) -> Result<Self, ApiKeyError> {
+        match s {
+            "read" => Ok(ApiScope::Read),
+            "trade" => Ok(ApiScope::Trade),
+            "withdraw" => Err(ApiKeyError::Invalid("withdrawals cannot be granted to API keys".to_string())),
+            other => Err(ApiKeyError::Invalid(format!("unknown scope: {}", other))),
+        }
+    }
+}
+
+/// An issued key (without its secret)
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub owner_address: String,
+    pub label: String,
+    pub api_key: String,
+    pub scopes: Vec<String>,
+    pub ip_allowlist: Vec<String>,
+    pub sub_account_id: Option<Uuid>,
+    pub status: String,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    pub fn has_scope(&self, scope: ApiScope) -> bool {
+        self.scopes.iter().any(|s| s == scope.as_str())
+    }
+}
+
+/// A newly issued key with its secret, shown once
+#[derive(Debug, Clone, Serialize)]
+pub struct IssuedApiKey {
+    #[serde(flatten)]
+    pub key: ApiKey,
+    pub secret: String,
+}
+
+/// A key request
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewApiKey {
+    pub label: String,
+    pub scopes: Vec<String>,
+    #[serde(default)]
+    pub ip_allowlist: Vec<String>,
+    pub sub_account_id: Option<Uuid>,
+}
+
+/// Whether an IP matches an allowlist entry (an address or a CIDR range)
+pub fn ip_matches(entry: &str, ip: IpAddr) -> bool {
+    let Some((network, prefix)) = entry.split_once('/') else {
+        return entry.parse::<IpAddr>().is_ok_and(|allowed| allowed == ip);
+    };
+    let (Ok(network), Ok(prefix)) = (network.parse::<IpAddr>(), prefix.parse::<u32>()) else {
+        return false;
+    };
+    match (network, ip) {
+        (IpAddr::V4(network), IpAddr::V4(ip)) if prefix <= 32 => {
+            let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+            u32::from(network) & mask == u32::from(ip) & mask
+        }
+        (IpAddr::V6(network), IpAddr::V6(ip)) if prefix <= 128 => {
+            let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+            u128::from(network) & mask == u128::from(ip) & mask
+        }
+        _ => false,
+    }
+}
+
+/// Whether an allowlist admits a client IP (an empty list admits any)
+pub fn ip_allowed(allowlist: &[String], client_ip: &str) -> bool {
+    if allowlist.is_empty() {
+        return true;
+    }
+    client_ip
+        .parse::<IpAddr>()
+        .is_ok_and(|ip| allowlist.iter().any(|entry| ip_matches(entry, ip)))
+}
+
+/// Bytes a request signature covers
+pub fn signing_payload(timestamp: i64, method: &str, path_and_query: &str, body: &[u8]) -> Vec<u8> {
+    let mut payload = format!("{}{}{}", timestamp, method.to_uppercase(), path_and_query).into_bytes();
+    payload.extend_from_slice(body);
+    payload
+}
+
+/// A request signed with an API key
+#[derive(Debug, Clone, Copy)]
+pub struct SignedRequest<'a> {
+    pub api_key: &'a str,
+    /// Unix millis
+    pub timestamp: i64,
+    /// Hex signature
+    pub signature: &'a str,
+    /// See [`signing_payload`]
+    pub payload: &'a [u8],
+    pub client_ip: &'a str,
+}
+
+/// Issues, verifies and revokes API keys
+pub struct ApiKeyService {
+    secret: hmac::Key,
+}
+
+impl ApiKeyService {
+    pub fn new(secret: &str) -> Self {
+        Self {
+            secret: hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes()),
+        }
+    }
+
+    /// Secret of a key
+    fn key_secret(&self, api_key: &str) -> String {
+        hex::encode(hmac::sign(&self.secret, api_key.as_bytes()))
+    }
+
+    /// Issue a key; the secret is returned only here
+    pub async fn issue(&self, pool: &PgPool, owner: &str, request: &NewApiKey) -> Result<IssuedApiKey, ApiKeyError> {
+        let owner = owner.to_lowercase();
+        let label = request.label.trim();
+        if label.is_empty() || label.len() > MAX_LABEL_LEN {
+            return Err(ApiKeyError::Invalid(format!("label must be 1 to {} characters", MAX_LABEL_LEN)));
+        }
+        let mut scopes = request
+            .scopes
+            .iter()
+            .map(|s| ApiScope::parse(&s.trim().to_lowercase()))
+            .collect::<Result<Vec<_>, _>>()?;
+        scopes.sort_by_key(|s| s.as_str());
+        scopes.dedup();
+        if scopes.is_empty() {
+            return Err(ApiKeyError::Invalid("at least one scope is required".to_string()));
+        }
+        if request.ip_allowlist.len() > MAX_ALLOWLIST_LEN {
+            return Err(ApiKeyError::Invalid(format!("at most {} allowlist entries", MAX_ALLOWLIST_LEN)));
+        }
+        let ip_allowlist: Vec<String> = request.ip_allowlist.iter().map(|entry| entry.trim().to_string()).collect();
+        if let Some(entry) = ip_allowlist.iter().find(|entry| !valid_allowlist_entry(entry)) {
+            return Err(ApiKeyError::Invalid(format!("not an IP address or CIDR range: {}", entry)));
+        }
+        if let Some(id) = request.sub_account_id {
+            SubAccountService::get(pool, &owner, id)
+                .await
+                .map_err(|_| ApiKeyError::Invalid(format!("unknown sub-account: {}", id)))?;
+        }
+
+        let mut tx = pool.begin().await?;
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+            .bind(format!("api_keys:{}", owner))
+            .execute(&mut *tx)
+            .await?;
+        let active: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM api_keys WHERE owner_address = $1 AND status = 'active'")
+                .bind(&owner)
+                .fetch_one(&mut *tx)
+                .await?;
+        if active >= MAX_ACTIVE_KEYS {
+            return Err(ApiKeyError::LimitReached(MAX_ACTIVE_KEYS));
+        }
+
+        let mut bytes = [0u8; 16];
+        SystemRandom::new().fill(&mut bytes).expect("system RNG failed");
+        let api_key = format!("pk_{}", hex::encode(bytes));
+        let scopes: Vec<&str> = scopes.iter().map(|s| s.as_str()).collect();
+
+        let key: ApiKey = sqlx::query_as(
+            r#"
+            INSERT INTO api_keys (id, owner_address, label, api_key, scopes, ip_allowlist, sub_account_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id, owner_address, label, api_key, scopes, ip_allowlist, sub_account_id, status,
+                      last_used_at, created_at, revoked_at
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(&owner)
+        .bind(label)
+        .bind(&api_key)
+        .bind(&scopes)
+        .bind(&ip_allowlist)
+        .bind(request.sub_account_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        tracing::info!("API key {} ({}) issued to {} with scopes {:?}", key.id, key.label, owner, key.scopes);
+        let secret = self.key_secret(&api_key);
+        Ok(IssuedApiKey { key, secret })
+    }
+
+    /// Keys of an owner, newest first
+    pub async fn list(pool: &PgPool, owner: &str) -> Result<Vec<ApiKey>, ApiKeyError> {
+        let rows = sqlx::query_as(
+            r#"
+            SELECT id, owner_address, label, api_key, scopes, ip_allowlist, sub_account_id, status,
+                   last_used_at, created_at, revoked_at
+            FROM api_keys
+            WHERE owner_address = $1
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(owner.to_lowercase())
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
+    }
+
+    /// Revoke a key
+    pub async fn revoke(pool: &PgPool, owner: &str, id: Uuid) -> Result<ApiKey, ApiKeyError> {
+        let key: ApiKey = sqlx::query_as(
+            r#"
+            UPDATE api_keys SET
+                status = 'revoked',
+                revoked_at = COALESCE(revoked_at, NOW())
+            WHERE id = $1 AND owner_address = $2
+            RETURNING id, owner_address, label, api_key, scopes, ip_allowlist, sub_account_id, status,
+                      last_used_at, created_at, revoked_at
+            "#,
+        )
+        .bind(id)
+        .bind(owner.to_lowercase())
+        .fetch_optional(pool)
+        .await?
+        .ok_or(ApiKeyError::NotFound(id))?;
+
+        tracing::info!("API key {} of {} revoked", id, key.owner_address);
+        Ok(key)
+    }
+
+    /// Check a signed request and return its key
+    pub async fn authenticate(
+        &self,
+        pool: &PgPool,
+        request: SignedRequest<'_>,
+        now: DateTime<Utc>,
+    ) -> Result<ApiKey, ApiKeyError> {
+        if (now.timestamp_millis() - request.timestamp).abs() > RECV_WINDOW_MS {
+            return Err(ApiKeyError::StaleTimestamp);
+        }
+        let key: ApiKey = sqlx::query_as(
+            r#"
+            SELECT id, owner_address, label, api_key, scopes, ip_allowlist, sub_account_id, status,
+                   last_used_at, created_at, revoked_at
+            FROM api_keys
+            WHERE api_key = $1 AND status = 'active'
+            "#,
+        )
+        .bind(request.api_key)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(ApiKeyError::UnknownKey)?;
+
+        let secret = hmac::Key::new(hmac::HMAC_SHA256, self.key_secret(request.api_key).as_bytes());
+        let signature = hex::decode(request.signature.trim()).map_err(|_| ApiKeyError::BadSignature)?;
+        hmac::verify(&secret, request.payload, &signature).map_err(|_| ApiKeyError::BadSignature)?;
+
+        if !ip_allowed(&key.ip_allowlist, request.client_ip) {
+            return Err(ApiKeyError::IpNotAllowed(request.client_ip.to_string()));
+        }
+
+        sqlx::query("UPDATE api_keys SET last_used_at = $2 WHERE id = $1")
+            .bind(key.id)
+            .bind(now)
+            .execute(pool)
+            .await?;
+        Ok(key)
+    }
+}
+
+/// An IP or CIDR block as accepted by [`ip_matches`]
+pub fn valid_allowlist_entry(entry: &str) -> bool {
+    match entry.split_once('/') {
+        Some((network, prefix)) => match (network.parse::<IpAddr>(), prefix.parse::<u32>()) {
+            (Ok(IpAddr::V4(_)), Ok(prefix)) => prefix <= 32,
+            (Ok(IpAddr::V6(_)), Ok(prefix)) => prefix <= 128,
+            _ => false,
+        },
+        None => entry.parse::<IpAddr>().is_ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ip_allowlist() {
+        let allowlist = vec!["10.0.0.0/8".to_string(), "192.168.1.7".to_string(), "2001:db8::/32".to_string()];
+
+        assert!(ip_allowed(&allowlist, "10.20.30.40"));
+        assert!(ip_allowed(&allowlist, "192.168.1.7"));
+        assert!(ip_allowed(&allowlist, "2001:db8::1"));
+        assert!(!ip_allowed(&allowlist, "192.168.1.8"));
+        assert!(!ip_allowed(&allowlist, "11.0.0.1"));
+        assert!(!ip_allowed(&allowlist, "unknown"));
+        assert!(ip_allowed(&[], "unknown"));
+        assert!(ip_matches("0.0.0.0/0", "8.8.8.8".parse().unwrap()));
+        assert!(!valid_allowlist_entry("10.0.0.0/33"));
+    }
+
+    #[test]
+    fn test_signature_round_trip() {
+        let service = ApiKeyService::new("server-secret");
+        let secret = service.key_secret("pk_test");
+        assert_eq!(secret.len(), 64);
+        assert_ne!(secret, service.key_secret("pk_other"));
+
+        let payload = signing_payload(1_700_000_000_000, "post", "/api/v1/orders?x=1", br#"{"amount":"1"}"#);
+        assert!(payload.starts_with(b"1700000000000POST/api/v1/orders?x=1{"));
+
+        let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+        let signature = hmac::sign(&key, &payload);
+        assert!(hmac::verify(&key, &payload, signature.as_ref()).is_ok());
+        assert!(hmac::verify(&key, b"tampered", signature.as_ref()).is_err());
+    }
+
+    #[test]
+    fn test_withdraw_scope_is_refused() {
+        assert_eq!(ApiScope::parse("trade").unwrap(), ApiScope::Trade);
+        assert!(matches!(ApiScope::parse("withdraw"), Err(ApiKeyError::Invalid(_))));
+    }
+}
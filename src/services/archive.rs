@@ -0,0 +1,646 @@
+//! History Archive Service
+//!
+//! Exports old history out of Postgres into immutable segments on a pluggable
+//! storage backend:
+//! - closed orders (filled / cancelled / rejected), by `updated_at`
+//! - trades, by `created_at`
+//! - imported klines (`klines_historical`), by `open_time`
+//!
+//! Each export cycle walks every table forward one UTC day at a time, from the
+//! table's archive watermark up to the retention cutoff. Every day window becomes
+//! one segment object plus an entry in `manifest.json`, which records the table,
+//! time range, row count and object key. Once a segment is safely stored the rows
+//! may be pruned from Postgres (`archive_prune`); pruned segments are then served
+//! by the history endpoints when a page runs past the rows still in the database.
+//!
+//! Segments are written as JSON lines. The storage backend sits behind
+//! [`ArchiveStore`]; the local filesystem backend ships here, and object storage
+//! (S3-compatible) or columnar formats (Parquet) plug in behind the same trait and
+//! the manifest's `format` field.
+
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use futures::future::BoxFuture;
+use rust_decimal::Decimal;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sqlx::postgres::PgRow;
+use sqlx::PgPool;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::info;
+use uuid::Uuid;
+
+/// Manifest object key
+const MANIFEST_KEY: &str = "manifest.json";
+
+/// Maximum day windows exported per table per cycle
+const MAX_WINDOWS_PER_CYCLE: usize = 30;
+
+/// Archive errors
+#[derive(Debug, thiserror::Error)]
+pub enum ArchiveError {
+    #[error("Unsupported archive backend: {0}")]
+    UnsupportedBackend(String),
+
+    #[error("Invalid archive key: {0}")]
+    InvalidKey(String),
+
+    #[error("Archive IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Archive encoding error: {0}")]
+    Encoding(#[from] serde_json::Error),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+// ============================================================================
+// Storage backends
+// ============================================================================
+
+/// Object storage for archive segments and the manifest
+pub trait ArchiveStore: Send + Sync {
+    /// Backend name, for logs
+    fn backend(&self) -> &'static str;
+
+    /// Write (or replace) an object
+    fn put<'a>(&'a self, key: &'a str, data: Vec<u8>) -> BoxFuture<'a, Result<(), ArchiveError>>;
+
+    /// Read an object; `None` if it does not exist
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<Vec<u8>>, ArchiveError>>;
+}
+
+/// Archive store on the local filesystem (or a mounted volume)
+pub struct LocalArchiveStore {
+    root: PathBuf,
+}
+
+impl LocalArchiveStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, ArchiveError> {
+        let relative = Path::new(key);
+        if key.is_empty()
+            || relative.is_absolute()
+            || relative.components().any(|c| !matches!(c, std::path::Component::Normal(_)))
+        {
+            return Err(ArchiveError::InvalidKey(key.to_string()));
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+impl ArchiveStore for LocalArchiveStore {
+    fn backend(&self) -> &'static str {
+        "local"
+    }
+
+    fn put<'a>(&'a self, key: &'a str, data: Vec<u8>) -> BoxFuture<'a, Result<(), ArchiveError>> {
+        Box::pin(async move {
+            let path = self.path(key)?;
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            // Write-then-rename so readers never see a partial object
+            let tmp = path.with_extension("tmp");
+            tokio::fs::write(&tmp, data).await?;
+            tokio::fs::rename(&tmp, &path).await?;
+            Ok(())
+        })
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<Vec<u8>>, ArchiveError>> {
+        Box::pin(async move {
+            match tokio::fs::read(self.path(key)?).await {
+                Ok(data) => Ok(Some(data)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e.into()),
+            }
+        })
+    }
+}
+
+/// Build the configured archive store
+pub fn build_store(backend: &str, location: &str) -> Result<Arc<dyn ArchiveStore>, ArchiveError> {
+    match backend {
+        "local" => Ok(Arc::new(LocalArchiveStore::new(location))),
+        other => Err(ArchiveError::UnsupportedBackend(other.to_string())),
+    }
+}
+
+// ============================================================================
+// Manifest
+// ============================================================================
+
+/// Archived tables
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveTable {
+    Orders,
+    Trades,
+    Klines,
+}
+
+impl ArchiveTable {
+    pub const ALL: [ArchiveTable; 3] = [ArchiveTable::Orders, ArchiveTable::Trades, ArchiveTable::Klines];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ArchiveTable::Orders => "orders",
+            ArchiveTable::Trades => "trades",
+            ArchiveTable::Klines => "klines",
+        }
+    }
+}
+
+/// Segment encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SegmentFormat {
+    JsonLines,
+}
+
+/// One archived time range of one table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SegmentMeta {
+    pub id: Uuid,
+    pub table: ArchiveTable,
+    /// Inclusive start of the range
+    pub from: DateTime<Utc>,
+    /// Exclusive end of the range
+    pub to: DateTime<Utc>,
+    pub row_count: u64,
+    pub key: String,
+    pub format: SegmentFormat,
+    /// Rows have been deleted from Postgres and are served from this segment
+    pub pruned: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Index of all archive segments
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    pub segments: Vec<SegmentMeta>,
+}
+
+impl Manifest {
+    /// End of the archived range for a table (exports resume from here)
+    pub fn watermark(&self, table: ArchiveTable) -> Option<DateTime<Utc>> {
+        self.segments.iter().filter(|s| s.table == table).map(|s| s.to).max()
+    }
+
+    /// Pruned segments of a table overlapping `[from, to)`, newest first
+    pub fn pruned_segments(
+        &self,
+        table: ArchiveTable,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Vec<&SegmentMeta> {
+        let mut segments: Vec<&SegmentMeta> = self
+            .segments
+            .iter()
+            .filter(|s| s.table == table && s.pruned)
+            .filter(|s| from.is_none_or(|from| s.to > from))
+            .filter(|s| to.is_none_or(|to| s.from < to))
+            .collect();
+        segments.sort_by_key(|s| std::cmp::Reverse(s.from));
+        segments
+    }
+}
+
+/// Day windows to export: from `start` (floored to the day) up to `cutoff`,
+/// keeping only complete days
+pub fn export_windows(start: DateTime<Utc>, cutoff: DateTime<Utc>, max: usize) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let Ok(mut from) = start.duration_trunc(Duration::days(1)) else {
+        return Vec::new();
+    };
+    let mut windows = Vec::new();
+    while windows.len() < max {
+        let to = from + Duration::days(1);
+        if to > cutoff {
+            break;
+        }
+        windows.push((from, to));
+        from = to;
+    }
+    windows
+}
+
+// ============================================================================
+// Archived rows
+// ============================================================================
+
+/// Archived order row
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ArchivedOrder {
+    pub id: Uuid,
+    pub user_address: String,
+    pub market_id: Uuid,
+    pub outcome_id: Uuid,
+    pub share_type: String,
+    pub side: String,
+    pub order_type: String,
+    pub price: Decimal,
+    pub amount: Decimal,
+    pub filled_amount: Decimal,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Archived trade row
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ArchivedTrade {
+    pub id: Uuid,
+    pub market_id: Uuid,
+    pub outcome_id: Uuid,
+    pub share_type: String,
+    pub side: String,
+    pub maker_address: String,
+    pub taker_address: String,
+    pub price: Decimal,
+    pub amount: Decimal,
+    pub maker_fee: Decimal,
+    pub taker_fee: Decimal,
+    pub is_block_trade: bool,
+    pub created_at: DateTime<Utc>,
+    /// Engine execution sequence number (absent in older segments)
+    #[serde(default)]
+    pub exec_id: Option<i64>,
+}
+
+/// Archived kline row
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ArchivedKline {
+    pub symbol: String,
+    pub period: String,
+    pub open_time: DateTime<Utc>,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    pub volume: Decimal,
+    pub quote_volume: Option<Decimal>,
+    pub trade_count: Option<i32>,
+}
+
+const ORDERS_SELECT: &str = r#"
+    SELECT id, user_address, market_id, outcome_id, share_type::text AS share_type,
+           side::text AS side, order_type::text AS order_type, price, amount,
+           filled_amount, status::text AS status, created_at, updated_at
+    FROM orders
+    WHERE status IN ('filled', 'cancelled', 'rejected') AND updated_at >= $1 AND updated_at < $2
+    ORDER BY updated_at
+"#;
+
+const ORDERS_DELETE: &str = r#"
+    DELETE FROM orders
+    WHERE status IN ('filled', 'cancelled', 'rejected') AND updated_at >= $1 AND updated_at < $2
+"#;
+
+const TRADES_SELECT: &str = r#"
+    SELECT id, market_id, outcome_id, share_type::text AS share_type, side::text AS side,
+           maker_address, taker_address, price, amount, maker_fee, taker_fee,
+           is_block_trade, created_at, exec_id
+    FROM trades
+    WHERE created_at >= $1 AND created_at < $2
+    ORDER BY created_at
+"#;
+
+const TRADES_DELETE: &str = "DELETE FROM trades WHERE created_at >= $1 AND created_at < $2";
+
+const KLINES_SELECT: &str = r#"
+    SELECT symbol, period, open_time, open, high, low, close, volume, quote_volume, trade_count
+    FROM klines_historical
+    WHERE open_time >= $1 AND open_time < $2
+    ORDER BY open_time
+"#;
+
+const KLINES_DELETE: &str = "DELETE FROM klines_historical WHERE open_time >= $1 AND open_time < $2";
+
+/// Export cycle settings
+#[derive(Debug, Clone)]
+pub struct ArchiveConfig {
+    /// Rows older than this many days are archived
+    pub retention_days: i64,
+    /// Delete archived rows from Postgres
+    pub prune: bool,
+}
+
+/// History archive service
+pub struct ArchiveService;
+
+impl ArchiveService {
+    /// Load the manifest (empty if none has been written yet)
+    pub async fn load_manifest(store: &dyn ArchiveStore) -> Result<Manifest, ArchiveError> {
+        match store.get(MANIFEST_KEY).await? {
+            Some(data) => Ok(serde_json::from_slice(&data)?),
+            None => Ok(Manifest::default()),
+        }
+    }
+
+    async fn save_manifest(store: &dyn ArchiveStore, manifest: &Manifest) -> Result<(), ArchiveError> {
+        store.put(MANIFEST_KEY, serde_json::to_vec_pretty(manifest)?).await
+    }
+
+    /// Run one export cycle over all tables; returns the number of segments written
+    pub async fn run_cycle(
+        pool: &PgPool,
+        store: &dyn ArchiveStore,
+        config: &ArchiveConfig,
+    ) -> Result<usize, ArchiveError> {
+        let mut manifest = Self::load_manifest(store).await?;
+        let cutoff = Utc::now() - Duration::days(config.retention_days.max(1));
+        let mut written = 0;
+
+        for table in ArchiveTable::ALL {
+            let start = match manifest.watermark(table) {
+                Some(watermark) => Some(watermark),
+                None => Self::oldest_row(pool, table).await?,
+            };
+            let Some(start) = start else { continue };
+
+            for (from, to) in export_windows(start, cutoff, MAX_WINDOWS_PER_CYCLE) {
+                let segment = match table {
+                    ArchiveTable::Orders => {
+                        Self::export_window::<ArchivedOrder>(pool, store, table, ORDERS_SELECT, from, to).await?
+                    }
+                    ArchiveTable::Trades => {
+                        Self::export_window::<ArchivedTrade>(pool, store, table, TRADES_SELECT, from, to).await?
+                    }
+                    ArchiveTable::Klines => {
+                        Self::export_window::<ArchivedKline>(pool, store, table, KLINES_SELECT, from, to).await?
+                    }
+                };
+                manifest.segments.push(segment);
+                Self::save_manifest(store, &manifest).await?;
+                written += 1;
+
+                if config.prune {
+                    let delete = match table {
+                        ArchiveTable::Orders => ORDERS_DELETE,
+                        ArchiveTable::Trades => TRADES_DELETE,
+                        ArchiveTable::Klines => KLINES_DELETE,
+                    };
+                    let pruned = sqlx::query(delete).bind(from).bind(to).execute(pool).await?.rows_affected();
+                    if let Some(segment) = manifest.segments.last_mut() {
+                        segment.pruned = true;
+                    }
+                    Self::save_manifest(store, &manifest).await?;
+                    info!("Pruned {} archived {} rows ({} - {})", pruned, table.as_str(), from, to);
+                }
+            }
+        }
+
+        Ok(written)
+    }
+
+    /// Oldest archivable row time of a table
+    async fn oldest_row(pool: &PgPool, table: ArchiveTable) -> Result<Option<DateTime<Utc>>, ArchiveError> {
+        let sql = match table {
+            ArchiveTable::Orders => {
+                "SELECT MIN(updated_at) FROM orders WHERE status IN ('filled', 'cancelled', 'rejected')"
+            }
+            ArchiveTable::Trades => "SELECT MIN(created_at) FROM trades",
+            ArchiveTable::Klines => "SELECT MIN(open_time) FROM klines_historical",
+        };
+        let oldest: Option<DateTime<Utc>> = sqlx::query_scalar(sql).fetch_one(pool).await?;
+        Ok(oldest)
+    }
+
+    /// Export one window of a table into a segment object
+    async fn export_window<T>(
+        pool: &PgPool,
+        store: &dyn ArchiveStore,
+        table: ArchiveTable,
+        select: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<SegmentMeta, ArchiveError>
+    where
+        T: for<'r> sqlx::FromRow<'r, PgRow> + Serialize + Send + Unpin,
+    {
+        let rows: Vec<T> = sqlx::query_as(select).bind(from).bind(to).fetch_all(pool).await?;
+
+        let mut data = Vec::new();
+        for row in &rows {
+            serde_json::to_writer(&mut data, row)?;
+            data.push(b'\n');
+        }
+
+        let key = format!(
+            "{}/{}/{}.jsonl",
+            table.as_str(),
+            from.format("%Y/%m"),
+            from.format("%Y-%m-%d")
+        );
+        store.put(&key, data).await?;
+
+        info!("Archived {} {} rows ({} - {}) to {}", rows.len(), table.as_str(), from, to, key);
+
+        Ok(SegmentMeta {
+            id: Uuid::new_v4(),
+            table,
+            from,
+            to,
+            row_count: rows.len() as u64,
+            key,
+            format: SegmentFormat::JsonLines,
+            pruned: false,
+            created_at: Utc::now(),
+        })
+    }
+
+    /// Read every row of a segment
+    async fn read_segment<T: DeserializeOwned>(
+        store: &dyn ArchiveStore,
+        segment: &SegmentMeta,
+    ) -> Result<Vec<T>, ArchiveError> {
+        let Some(data) = store.get(&segment.key).await? else {
+            tracing::warn!("Archive segment {} is missing", segment.key);
+            return Ok(Vec::new());
+        };
+        data.split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).map_err(ArchiveError::from))
+            .collect()
+    }
+
+    /// Walk pruned segments newest-first, skipping `skip` matching rows and
+    /// collecting up to `take`
+    async fn collect_pruned<T, F, K>(
+        store: &dyn ArchiveStore,
+        table: ArchiveTable,
+        skip: usize,
+        take: usize,
+        matches: F,
+        sort_key: K,
+    ) -> Result<Vec<T>, ArchiveError>
+    where
+        T: DeserializeOwned,
+        F: Fn(&T) -> bool,
+        K: Fn(&T) -> DateTime<Utc>,
+    {
+        let mut skip = skip;
+        let mut out = Vec::new();
+        if take == 0 {
+            return Ok(out);
+        }
+
+        let manifest = Self::load_manifest(store).await?;
+        for segment in manifest.pruned_segments(table, None, None) {
+            let mut rows: Vec<T> = Self::read_segment::<T>(store, segment)
+                .await?
+                .into_iter()
+                .filter(|row| matches(row))
+                .collect();
+            if rows.len() <= skip {
+                skip -= rows.len();
+                continue;
+            }
+            rows.sort_by_key(|row| std::cmp::Reverse(sort_key(row)));
+            out.extend(rows.into_iter().skip(skip).take(take - out.len()));
+            skip = 0;
+            if out.len() >= take {
+                break;
+            }
+        }
+        Ok(out)
+    }
+
+    /// Archived (pruned) trades created in `[from, to)`, oldest first
+    pub async fn trades_between(
+        store: &dyn ArchiveStore,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<ArchivedTrade>, ArchiveError> {
+        let manifest = Self::load_manifest(store).await?;
+        let mut trades = Vec::new();
+        for segment in manifest.pruned_segments(ArchiveTable::Trades, Some(from), Some(to)) {
+            let rows: Vec<ArchivedTrade> = Self::read_segment(store, segment).await?;
+            trades.extend(rows.into_iter().filter(|t| t.created_at >= from && t.created_at < to));
+        }
+        trades.sort_by_key(|t| t.created_at);
+        Ok(trades)
+    }
+
+    /// A user's archived (pruned) trades, newest first
+    pub async fn user_trades(
+        store: &dyn ArchiveStore,
+        user_address: &str,
+        market_id: Option<Uuid>,
+        skip: usize,
+        take: usize,
+    ) -> Result<Vec<ArchivedTrade>, ArchiveError> {
+        let user = user_address.to_lowercase();
+        Self::collect_pruned(
+            store,
+            ArchiveTable::Trades,
+            skip,
+            take,
+            |t: &ArchivedTrade| {
+                (t.maker_address == user || t.taker_address == user)
+                    && market_id.is_none_or(|m| t.market_id == m)
+            },
+            |t| t.created_at,
+        )
+        .await
+    }
+
+    /// A user's archived (pruned) orders, newest first
+    pub async fn user_orders(
+        store: &dyn ArchiveStore,
+        user_address: &str,
+        market_id: Option<Uuid>,
+        status: Option<&str>,
+        skip: usize,
+        take: usize,
+    ) -> Result<Vec<ArchivedOrder>, ArchiveError> {
+        let user = user_address.to_lowercase();
+        Self::collect_pruned(
+            store,
+            ArchiveTable::Orders,
+            skip,
+            take,
+            |o: &ArchivedOrder| {
+                o.user_address == user
+                    && market_id.is_none_or(|m| o.market_id == m)
+                    && status.is_none_or(|s| o.status == s)
+            },
+            |o| o.created_at,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn ts(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, day, hour, 0, 0).unwrap()
+    }
+
+    fn segment(table: ArchiveTable, day: u32, pruned: bool) -> SegmentMeta {
+        SegmentMeta {
+            id: Uuid::new_v4(),
+            table,
+            from: ts(day, 0),
+            to: ts(day + 1, 0),
+            row_count: 1,
+            key: format!("{}/{}.jsonl", table.as_str(), day),
+            format: SegmentFormat::JsonLines,
+            pruned,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_export_windows_only_complete_days() {
+        let windows = export_windows(ts(1, 13), ts(4, 6), 10);
+        assert_eq!(windows, vec![(ts(1, 0), ts(2, 0)), (ts(2, 0), ts(3, 0)), (ts(3, 0), ts(4, 0))]);
+
+        assert_eq!(export_windows(ts(1, 13), ts(4, 6), 2).len(), 2);
+        assert!(export_windows(ts(4, 1), ts(4, 6), 10).is_empty());
+    }
+
+    #[test]
+    fn test_manifest_watermark_and_range_lookup() {
+        let manifest = Manifest {
+            segments: vec![
+                segment(ArchiveTable::Trades, 1, true),
+                segment(ArchiveTable::Trades, 2, true),
+                segment(ArchiveTable::Trades, 3, false),
+                segment(ArchiveTable::Orders, 5, true),
+            ],
+        };
+
+        assert_eq!(manifest.watermark(ArchiveTable::Trades), Some(ts(4, 0)));
+        assert_eq!(manifest.watermark(ArchiveTable::Klines), None);
+
+        // Unpruned segments are still served from Postgres
+        let all: Vec<_> = manifest.pruned_segments(ArchiveTable::Trades, None, None).iter().map(|s| s.from).collect();
+        assert_eq!(all, vec![ts(2, 0), ts(1, 0)]);
+
+        let ranged = manifest.pruned_segments(ArchiveTable::Trades, Some(ts(2, 12)), None);
+        assert_eq!(ranged.len(), 1);
+        assert_eq!(ranged[0].from, ts(2, 0));
+    }
+
+    #[tokio::test]
+    async fn test_local_store_roundtrip_and_key_validation() {
+        let root = std::env::temp_dir().join(format!("archive-test-{}", Uuid::new_v4()));
+        let store = LocalArchiveStore::new(&root);
+
+        assert!(store.get("trades/x.jsonl").await.unwrap().is_none());
+        store.put("trades/x.jsonl", b"{}\n".to_vec()).await.unwrap();
+        assert_eq!(store.get("trades/x.jsonl").await.unwrap(), Some(b"{}\n".to_vec()));
+
+        assert!(matches!(store.put("../escape", Vec::new()).await, Err(ArchiveError::InvalidKey(_))));
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,349 @@
+//! Session Tokens
+//!
+//! Login issues an access token (JWT) and a refresh token.
+//! `POST /auth/refresh` exchanges a refresh token for a new pair; refresh
+//! tokens rotate, so each one is accepted once. Presenting a token that was
+//! already exchanged means it leaked: the whole family (every token
+//! descending from the same login) is revoked, along with every access token
+//! issued so far, and the user has to sign in again. Only SHA-256 hashes of
+//! refresh tokens are stored.
+//!
+//! Access tokens are refused before they expire when
+//!
+//! - their id (`jti`) is on the revocation list, which logout adds the
+//!   caller's token to, or
+//! - they were issued before the user's `sessions_revoked_at`, set when the
+//!   user logs out everywhere, an admin revokes a compromised account's
+//!   sessions or a refresh token is reused.
+//!
+//! [`AuthTokenService::verify`] is the check the HTTP middleware and the
+//! WebSocket JWT path share.
+
+use chrono::{DateTime, Duration, Utc};
+use ring::digest::{digest, SHA256};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::auth::jwt::{Claims, JwtManager};
+
+/// Session token errors
+#[derive(Debug, thiserror::Error)]
+pub enum AuthTokenError {
+    #[error("Invalid or expired token")]
+    InvalidToken,
+
+    #[error("Token has been revoked")]
+    Revoked,
+
+    #[error("Invalid or expired refresh token")]
+    InvalidRefreshToken,
+
+    #[error("Refresh token was already used; all sessions of this login are revoked")]
+    RefreshTokenReused,
+
+    #[error("Failed to sign token: {0}")]
+    Signing(String),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// An access token with its refresh token
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenPair {
+    pub token: String,
+    /// Unix seconds
+    pub expires_at: i64,
+    pub refresh_token: String,
+    /// Unix seconds
+    pub refresh_expires_at: i64,
+}
+
+/// A stored refresh token
+#[derive(Debug, sqlx::FromRow)]
+struct RefreshTokenRow {
+    id: Uuid,
+    user_address: String,
+    family_id: Uuid,
+    expires_at: DateTime<Utc>,
+    used_at: Option<DateTime<Utc>>,
+    revoked_at: Option<DateTime<Utc>>,
+}
+
+/// What presenting a refresh token does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RefreshOutcome {
+    /// Revoked or expired
+    Invalid,
+    /// Already exchanged: the token leaked
+    Reused,
+    /// Exchanged for a new pair
+    Rotate,
+}
+
+impl RefreshTokenRow {
+    fn outcome(&self, now: DateTime<Utc>) -> RefreshOutcome {
+        if self.revoked_at.is_some() || self.expires_at <= now {
+            RefreshOutcome::Invalid
+        } else if self.used_at.is_some() {
+            RefreshOutcome::Reused
+        } else {
+            RefreshOutcome::Rotate
+        }
+    }
+}
+
+/// Whether an access token issued at `iat` (unix seconds) predates the
+/// user's `sessions_revoked_at`
+fn revoked_by_sessions(iat: i64, sessions_revoked_at: Option<DateTime<Utc>>) -> bool {
+    sessions_revoked_at.is_some_and(|revoked_at| DateTime::from_timestamp(iat, 0).is_none_or(|issued| revoked_at > issued))
+}
+
+/// Stored hash of a refresh token
+fn hash_refresh_token(token: &str) -> String {
+    hex::encode(digest(&SHA256, token.as_bytes()))
+}
+
+/// Issues, rotates, verifies and revokes session tokens
+pub struct AuthTokenService {
+    jwt: JwtManager,
+    refresh_expiry_seconds: u64,
+}
+
+impl AuthTokenService {
+    pub fn new(jwt_secret: &str, expiry_seconds: u64, refresh_expiry_seconds: u64) -> Self {
+        Self {
+            jwt: JwtManager::new(jwt_secret, expiry_seconds),
+            refresh_expiry_seconds,
+        }
+    }
+
+    /// Tokens of a new login
+    pub async fn issue(&self, pool: &PgPool, address: &str) -> Result<TokenPair, AuthTokenError> {
+        let mut tx = pool.begin().await?;
+        let pair = self.issue_in_family(&mut tx, &address.to_lowercase(), Uuid::new_v4()).await?;
+        tx.commit().await?;
+        Ok(pair)
+    }
+
+    async fn issue_in_family(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        address: &str,
+        family_id: Uuid,
+    ) -> Result<TokenPair, AuthTokenError> {
+        let (token, claims) = self
+            .jwt
+            .issue_token(address)
+            .map_err(|e| AuthTokenError::Signing(e.to_string()))?;
+
+        let mut bytes = [0u8; 32];
+        SystemRandom::new().fill(&mut bytes).expect("system RNG failed");
+        let refresh_token = format!("rt_{}", hex::encode(bytes));
+        let refresh_expires_at = Utc::now() + Duration::seconds(self.refresh_expiry_seconds as i64);
+        sqlx::query(
+            r#"
+            INSERT INTO refresh_tokens (id, user_address, token_hash, family_id, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(address)
+        .bind(hash_refresh_token(&refresh_token))
+        .bind(family_id)
+        .bind(refresh_expires_at)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(TokenPair {
+            token,
+            expires_at: claims.exp,
+            refresh_token,
+            refresh_expires_at: refresh_expires_at.timestamp(),
+        })
+    }
+
+    /// Exchange a refresh token for a new pair
+    pub async fn refresh(&self, pool: &PgPool, refresh_token: &str) -> Result<TokenPair, AuthTokenError> {
+        let mut tx = pool.begin().await?;
+        let row: Option<RefreshTokenRow> = sqlx::query_as(
+            r#"
+            SELECT id, user_address, family_id, expires_at, used_at, revoked_at
+            FROM refresh_tokens
+            WHERE token_hash = $1
+            FOR UPDATE
+            "#,
+        )
+        .bind(hash_refresh_token(refresh_token.trim()))
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(row) = row else {
+            return Err(AuthTokenError::InvalidRefreshToken);
+        };
+        match row.outcome(Utc::now()) {
+            RefreshOutcome::Invalid => return Err(AuthTokenError::InvalidRefreshToken),
+            RefreshOutcome::Reused => {
+                sqlx::query("UPDATE refresh_tokens SET revoked_at = NOW() WHERE family_id = $1 AND revoked_at IS NULL")
+                    .bind(row.family_id)
+                    .execute(&mut *tx)
+                    .await?;
+                // Access tokens already minted from the family are as leaked as it is
+                sqlx::query("UPDATE users SET sessions_revoked_at = NOW(), updated_at = NOW() WHERE address = $1")
+                    .bind(&row.user_address)
+                    .execute(&mut *tx)
+                    .await?;
+                tx.commit().await?;
+                tracing::warn!(
+                    "Refresh token reuse for {}: revoked token family {} and issued access tokens",
+                    row.user_address, row.family_id
+                );
+                return Err(AuthTokenError::RefreshTokenReused);
+            }
+            RefreshOutcome::Rotate => {}
+        }
+
+        sqlx::query("UPDATE refresh_tokens SET used_at = NOW() WHERE id = $1")
+            .bind(row.id)
+            .execute(&mut *tx)
+            .await?;
+        let pair = self.issue_in_family(&mut tx, &row.user_address, row.family_id).await?;
+        tx.commit().await?;
+        Ok(pair)
+    }
+
+    /// Decode an access token and refuse it if it was revoked
+    pub async fn verify(&self, pool: &PgPool, token: &str) -> Result<Claims, AuthTokenError> {
+        let claims = self.jwt.verify_token(token).map_err(|_| AuthTokenError::InvalidToken)?;
+        let (listed, sessions_revoked_at): (bool, Option<DateTime<Utc>>) = sqlx::query_as(
+            r#"
+            SELECT EXISTS (SELECT 1 FROM revoked_access_tokens WHERE jti = $1),
+                   (SELECT sessions_revoked_at FROM users WHERE address = $2)
+            "#,
+        )
+        .bind(&claims.jti)
+        .bind(claims.sub.to_lowercase())
+        .fetch_one(pool)
+        .await?;
+        if listed || revoked_by_sessions(claims.iat, sessions_revoked_at) {
+            return Err(AuthTokenError::Revoked);
+        }
+        Ok(claims)
+    }
+
+    /// Log out one session: revoke its access token and, when given, the
+    /// refresh token family it belongs to
+    pub async fn logout(
+        pool: &PgPool,
+        claims: &Claims,
+        refresh_token: Option<&str>,
+    ) -> Result<(), AuthTokenError> {
+        let address = claims.sub.to_lowercase();
+        let mut tx = pool.begin().await?;
+        if !claims.jti.is_empty() {
+            sqlx::query(
+                r#"
+                INSERT INTO revoked_access_tokens (jti, user_address, expires_at)
+                VALUES ($1, $2, to_timestamp($3))
+                ON CONFLICT (jti) DO NOTHING
+                "#,
+            )
+            .bind(&claims.jti)
+            .bind(&address)
+            .bind(claims.exp as f64)
+            .execute(&mut *tx)
+            .await?;
+        }
+        if let Some(refresh_token) = refresh_token {
+            sqlx::query(
+                r#"
+                UPDATE refresh_tokens SET revoked_at = NOW()
+                WHERE revoked_at IS NULL AND family_id = (
+                    SELECT family_id FROM refresh_tokens WHERE token_hash = $1 AND user_address = $2
+                )
+                "#,
+            )
+            .bind(hash_refresh_token(refresh_token.trim()))
+            .bind(&address)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        tracing::info!("User {} logged out", address);
+        Ok(())
+    }
+
+    /// Revoke every session of a user: all refresh tokens, and access tokens
+    /// issued until now
+    pub async fn revoke_all(pool: &PgPool, address: &str) -> Result<u64, AuthTokenError> {
+        let address = address.to_lowercase();
+        let mut tx = pool.begin().await?;
+        sqlx::query("UPDATE users SET sessions_revoked_at = NOW(), updated_at = NOW() WHERE address = $1")
+            .bind(&address)
+            .execute(&mut *tx)
+            .await?;
+        let revoked = sqlx::query(
+            "UPDATE refresh_tokens SET revoked_at = NOW() WHERE user_address = $1 AND revoked_at IS NULL",
+        )
+        .bind(&address)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        tx.commit().await?;
+        tracing::info!("Revoked all sessions of {} ({} refresh tokens)", address, revoked);
+        Ok(revoked)
+    }
+
+    /// Drop revocation entries and refresh tokens past their expiry
+    pub async fn purge_expired(pool: &PgPool) -> Result<u64, sqlx::Error> {
+        let access = sqlx::query("DELETE FROM revoked_access_tokens WHERE expires_at < NOW()")
+            .execute(pool)
+            .await?
+            .rows_affected();
+        let refresh = sqlx::query("DELETE FROM refresh_tokens WHERE expires_at < NOW()")
+            .execute(pool)
+            .await?
+            .rows_affected();
+        Ok(access + refresh)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refresh_token_hash() {
+        let hash = hash_refresh_token("rt_abc");
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, hash_refresh_token("rt_abc"));
+        assert_ne!(hash, hash_refresh_token("rt_abd"));
+    }
+
+    #[test]
+    fn test_refresh_reuse_revokes_issued_access_tokens() {
+        let now = Utc::now();
+        let mut row = RefreshTokenRow {
+            id: Uuid::new_v4(),
+            user_address: "0xabc".to_string(),
+            family_id: Uuid::new_v4(),
+            expires_at: now + Duration::days(7),
+            used_at: None,
+            revoked_at: None,
+        };
+        assert_eq!(row.outcome(now), RefreshOutcome::Rotate);
+        row.used_at = Some(now - Duration::minutes(10));
+        assert_eq!(row.outcome(now), RefreshOutcome::Reused);
+
+        // Reuse stamps sessions_revoked_at: access tokens minted from the
+        // family so far are refused, later logins are not
+        let issued = (now - Duration::minutes(10)).timestamp();
+        assert!(revoked_by_sessions(issued, Some(now)));
+        assert!(!revoked_by_sessions((now + Duration::seconds(1)).timestamp(), Some(now)));
+        assert!(!revoked_by_sessions(issued, None));
+
+        row.revoked_at = Some(now);
+        assert_eq!(row.outcome(now), RefreshOutcome::Invalid);
+    }
+}
@@ -0,0 +1,162 @@
+//! Chain Registry
+//!
+//! Chains deposits are accepted from and withdrawals are paid out on. The
+//! primary chain comes from `chain_id`, `rpc_url`, `vault_address` and the
+//! collateral token settings; `extra_chains` adds more as JSON:
+//!
+//! ```json
+//! [{"chain_id": 8453, "name": "base", "rpc_url": "https://...",
+//!   "vault_address": "0x...", "tokens": {"USDT": "0x..."}}]
+//! ```
+//!
+//! Balances are shared across chains: a deposit on one chain can be
+//! withdrawn on any chain that supports the token. Deposits and withdrawals
+//! record their chain; rows from before multi-chain support have no
+//! `chain_id` and belong to the primary chain. Typed signatures (EIP-712)
+//! keep the primary chain's domain.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Chain registry errors
+#[derive(Debug, thiserror::Error)]
+pub enum ChainError {
+    #[error("Unsupported chain: {0}")]
+    UnknownChain(u64),
+
+    #[error("Token {token} is not supported on chain {chain_id}")]
+    UnsupportedToken { chain_id: u64, token: String },
+
+    #[error("Invalid extra_chains config: {0}")]
+    InvalidConfig(String),
+}
+
+/// One chain's settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainConfig {
+    pub chain_id: u64,
+    pub name: String,
+    #[serde(skip_serializing)]
+    pub rpc_url: String,
+    pub vault_address: String,
+    /// Token symbol (upper case) -> token address
+    pub tokens: BTreeMap<String, String>,
+    /// Deposit confirmations on this chain; None uses `deposit_confirmations`
+    #[serde(default)]
+    pub confirmations: Option<u64>,
+}
+
+impl ChainConfig {
+    /// Address of a token on this chain
+    pub fn token_address(&self, symbol: &str) -> Option<&str> {
+        self.tokens.get(&symbol.to_uppercase()).map(String::as_str)
+    }
+}
+
+pub struct ChainRegistry {
+    primary: u64,
+    chains: HashMap<u64, ChainConfig>,
+}
+
+impl ChainRegistry {
+    /// Registry of the primary chain plus the chains of `extra_json`
+    pub fn new(primary: ChainConfig, extra_json: &str) -> Result<Self, ChainError> {
+        let extra: Vec<ChainConfig> = if extra_json.trim().is_empty() {
+            Vec::new()
+        } else {
+            serde_json::from_str(extra_json).map_err(|e| ChainError::InvalidConfig(e.to_string()))?
+        };
+
+        let mut registry = Self {
+            primary: primary.chain_id,
+            chains: HashMap::new(),
+        };
+        for mut chain in std::iter::once(primary).chain(extra) {
+            chain.tokens = chain.tokens.into_iter().map(|(symbol, address)| (symbol.to_uppercase(), address)).collect();
+            if chain.tokens.is_empty() {
+                return Err(ChainError::InvalidConfig(format!("chain {} has no tokens", chain.chain_id)));
+            }
+            let chain_id = chain.chain_id;
+            if registry.chains.insert(chain_id, chain).is_some() {
+                return Err(ChainError::InvalidConfig(format!("chain {} is configured twice", chain_id)));
+            }
+        }
+        Ok(registry)
+    }
+
+    pub fn primary_id(&self) -> u64 {
+        self.primary
+    }
+
+    /// A configured chain; None selects the primary chain
+    pub fn get(&self, chain_id: Option<u64>) -> Result<&ChainConfig, ChainError> {
+        let chain_id = chain_id.unwrap_or(self.primary);
+        self.chains.get(&chain_id).ok_or(ChainError::UnknownChain(chain_id))
+    }
+
+    /// A configured chain that supports `token`
+    pub fn for_token(&self, chain_id: Option<u64>, token: &str) -> Result<&ChainConfig, ChainError> {
+        let chain = self.get(chain_id)?;
+        if chain.token_address(token).is_none() {
+            return Err(ChainError::UnsupportedToken {
+                chain_id: chain.chain_id,
+                token: token.to_string(),
+            });
+        }
+        Ok(chain)
+    }
+
+    /// All chains, primary first
+    pub fn all(&self) -> Vec<&ChainConfig> {
+        let mut chains: Vec<&ChainConfig> = self.chains.values().collect();
+        chains.sort_by_key(|chain| (chain.chain_id != self.primary, chain.chain_id));
+        chains
+    }
+
+    /// Chains a token can be deposited on and withdrawn to
+    pub fn chains_for_token(&self, token: &str) -> Vec<u64> {
+        self.all()
+            .into_iter()
+            .filter(|chain| chain.token_address(token).is_some())
+            .map(|chain| chain.chain_id)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn primary() -> ChainConfig {
+        ChainConfig {
+            chain_id: 42161,
+            name: "arbitrum".to_string(),
+            rpc_url: "http://localhost:8545".to_string(),
+            vault_address: "0xvault".to_string(),
+            tokens: BTreeMap::from([("usdt".to_string(), "0xusdt".to_string())]),
+            confirmations: None,
+        }
+    }
+
+    #[test]
+    fn test_registry_lookup() {
+        let extra = r#"[{"chain_id": 8453, "name": "base", "rpc_url": "http://base", "vault_address": "0xbase",
+                         "tokens": {"USDT": "0xbaseusdt", "USDC": "0xbaseusdc"}}]"#;
+        let registry = ChainRegistry::new(primary(), extra).unwrap();
+
+        assert_eq!(registry.get(None).unwrap().chain_id, 42161);
+        assert_eq!(registry.for_token(None, "usdt").unwrap().token_address("USDT"), Some("0xusdt"));
+        assert_eq!(registry.for_token(Some(8453), "USDC").unwrap().vault_address, "0xbase");
+        assert!(matches!(registry.for_token(None, "USDC"), Err(ChainError::UnsupportedToken { .. })));
+        assert!(matches!(registry.get(Some(1)), Err(ChainError::UnknownChain(1))));
+        assert_eq!(registry.chains_for_token("USDT"), vec![42161, 8453]);
+        assert_eq!(registry.chains_for_token("USDC"), vec![8453]);
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let duplicate = r#"[{"chain_id": 42161, "name": "again", "rpc_url": "", "vault_address": "", "tokens": {"USDT": "0x"}}]"#;
+        assert!(matches!(ChainRegistry::new(primary(), duplicate), Err(ChainError::InvalidConfig(_))));
+        assert!(matches!(ChainRegistry::new(primary(), "{"), Err(ChainError::InvalidConfig(_))));
+    }
+}
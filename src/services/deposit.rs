@@ -0,0 +1,570 @@
+//! Deposit Crediting
+//!
+//! Turns on-chain deposit logs into balance credits, exactly once, after
+//! they are buried deep enough to be safe from reorgs. Chain scanners
+//! re-scan a lookback window after restarts and reorgs, so the same log can
+//! be reported many times. A deposit is identified by `(tx_hash, log_index)`:
+//!
+//! - `deposits` holds one row per log (unique index)
+//! - the credit is journaled with the idempotency key
+//!   `deposit:<tx_hash>:<log_index>`, which the ledger applies at most once
+//!
+//! A reported log is recorded as `pending` and credited once it has
+//! [`Finality::confirmations`] confirmations; with zero it is credited on
+//! sight. Until it is [`Finality::finality_blocks`] deep the confirmation
+//! tracker ([`crate::services::deposit_confirmations`]) keeps checking its
+//! transaction: a deposit whose transaction left the chain is `reorged`
+//! before crediting and `reverted` (credit reversed) after. Both go back to
+//! `pending` if the transaction reappears ([`DepositService::restore`]); the
+//! credit and reversal of each such generation use keys suffixed with
+//! [`TrackedDeposit::reorgs`].
+//!
+//! Deposits record the chain they were made on
+//! ([`crate::services::chains`]); confirmations count against that chain.
+//!
+//! [`DepositService::find_double_credits`] and [`DepositService::repair`]
+//! detect and reverse deposits credited more than once before the key existed.
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::services::ledger::{BalanceChange, LedgerEntry, LedgerReason, LedgerService};
+
+/// Deposit errors
+#[derive(Debug, thiserror::Error)]
+pub enum DepositError {
+    #[error("Deposit amount must be positive")]
+    InvalidAmount,
+
+    #[error("Deposit {tx_hash}:{log_index} is already recorded with a different owner or amount")]
+    Conflict { tx_hash: String, log_index: i32 },
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// A deposit log seen on chain
+#[derive(Debug, Clone, Deserialize)]
+pub struct DepositEvent {
+    pub tx_hash: String,
+    pub log_index: i32,
+    pub user_address: String,
+    pub token: String,
+    pub amount: Decimal,
+    pub block_number: i64,
+    /// Hash of the block the log was seen in
+    #[serde(default)]
+    pub block_hash: Option<String>,
+    /// Chain the log was seen on; None is the primary chain
+    #[serde(default)]
+    pub chain_id: Option<u64>,
+}
+
+impl DepositEvent {
+    /// Ledger key of the credit
+    pub fn idempotency_key(&self) -> String {
+        credit_key(&self.tx_hash, self.log_index)
+    }
+}
+
+fn credit_key(tx_hash: &str, log_index: i32) -> String {
+    format!("deposit:{}:{}", tx_hash.to_lowercase(), log_index)
+}
+
+/// Key of a generation's ledger entry: the first keeps the plain key
+fn generation_key(key: String, reorgs: i32) -> String {
+    if reorgs == 0 {
+        key
+    } else {
+        format!("{}:{}", key, reorgs)
+    }
+}
+
+/// Confirmation requirements, and the chain head they are counted against
+#[derive(Debug, Clone, Copy)]
+pub struct Finality {
+    /// Latest block seen; None until the chain has been polled
+    pub head: Option<u64>,
+    /// Confirmations before a deposit is credited; zero credits on sight
+    pub confirmations: u64,
+    /// Confirmations after which a credit is final and no longer rechecked
+    pub finality_blocks: u64,
+}
+
+impl Finality {
+    /// Confirmations of a block (1 when it is the head); None without a head
+    pub fn confirmations_of(&self, block_number: i64) -> Option<u64> {
+        let head = self.head?;
+        let block = u64::try_from(block_number).unwrap_or(0);
+        Some((head + 1).saturating_sub(block))
+    }
+
+    /// Deep enough to credit
+    pub fn is_confirmed(&self, block_number: i64) -> bool {
+        self.confirmations == 0 || self.confirmations_of(block_number).is_some_and(|c| c >= self.confirmations)
+    }
+
+    /// Deep enough to stop rechecking
+    pub fn is_final(&self, block_number: i64) -> bool {
+        let depth = self.finality_blocks.max(self.confirmations);
+        self.confirmations_of(block_number).is_some_and(|c| c >= depth)
+    }
+}
+
+/// Result of reporting a deposit
+#[derive(Debug, Serialize)]
+pub struct CreditOutcome {
+    pub deposit_id: Uuid,
+    pub status: String,
+    /// None until the chain head is known
+    pub confirmations: Option<u64>,
+    /// The credit; None if the deposit is not yet confirmed or was already
+    /// credited (nothing changed)
+    pub entry: Option<LedgerEntry>,
+}
+
+/// A deposit whose journaled credits exceed its amount
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct DoubleCredit {
+    pub deposit_id: Uuid,
+    pub user_address: String,
+    pub token: String,
+    pub amount: Decimal,
+    /// Net of all deposit credits and reversals
+    pub credited: Decimal,
+    /// Journal rows for the deposit
+    pub entries: i64,
+}
+
+impl DoubleCredit {
+    /// Amount credited beyond the deposit
+    pub fn excess(&self) -> Decimal {
+        (self.credited - self.amount).max(Decimal::ZERO)
+    }
+
+    /// Ledger key of the reversal. It changes with the journal, so repairing
+    /// the same state twice reverses once.
+    pub fn reversal_key(&self) -> String {
+        format!("deposit-reversal:{}:{}", self.deposit_id, self.entries)
+    }
+}
+
+/// A deposit the confirmation tracker still checks: `pending` or `credited`,
+/// or recently `reorged` or `reverted`
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct TrackedDeposit {
+    pub id: Uuid,
+    pub user_address: String,
+    pub token: String,
+    pub amount: Decimal,
+    pub tx_hash: String,
+    pub log_index: i32,
+    pub block_number: i64,
+    pub block_hash: Option<String>,
+    /// None for deposits from before multi-chain support (primary chain)
+    pub chain_id: Option<i64>,
+    pub status: String,
+    /// Consecutive polls that found no receipt
+    pub missing_polls: i32,
+    /// Chain head at the first of those polls
+    pub missing_since: Option<i64>,
+    /// Times the deposit left the chain
+    pub reorgs: i32,
+}
+
+impl TrackedDeposit {
+    /// Ledger key of the current generation's credit
+    pub fn credit_key(&self) -> String {
+        generation_key(credit_key(&self.tx_hash, self.log_index), self.reorgs)
+    }
+
+    /// Ledger key of the current generation's reversal
+    pub fn reversal_key(&self) -> String {
+        generation_key(format!("deposit-reorg:{}:{}", self.tx_hash, self.log_index), self.reorgs)
+    }
+}
+
+const TRACKED_COLUMNS: &str = "id, user_address, token, amount, tx_hash, log_index, block_number, block_hash, chain_id, \
+     status, missing_polls, missing_since, reorgs";
+
+/// Columns set when a deposit leaves the chain
+const DROPPED: &str = "reverted_at = NOW(), reorgs = reorgs + 1, missing_polls = 0, missing_since = NULL";
+
+/// How long a dropped deposit is rechecked for its transaction reappearing
+const DROPPED_RECHECK: &str = "1 day";
+
+pub struct DepositService;
+
+impl DepositService {
+    /// Record a deposit log and credit it if it is confirmed. Safe to call
+    /// any number of times for the same log.
+    pub async fn credit(pool: &PgPool, event: &DepositEvent, finality: &Finality) -> Result<CreditOutcome, DepositError> {
+        if event.amount <= Decimal::ZERO {
+            return Err(DepositError::InvalidAmount);
+        }
+        let tx_hash = event.tx_hash.to_lowercase();
+        let user_address = event.user_address.to_lowercase();
+        let block_hash = event.block_hash.as_deref().map(str::to_lowercase);
+        let chain_id = event.chain_id.map(|id| id as i64);
+
+        let mut tx = pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO deposits (user_address, token, amount, tx_hash, log_index, block_number, block_hash, chain_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (tx_hash, log_index) DO NOTHING
+            "#,
+        )
+        .bind(&user_address)
+        .bind(&event.token)
+        .bind(event.amount)
+        .bind(&tx_hash)
+        .bind(event.log_index)
+        .bind(event.block_number)
+        .bind(&block_hash)
+        .bind(chain_id)
+        .execute(&mut *tx)
+        .await?;
+
+        // Serializes concurrent reports of the same log
+        let mut deposit: TrackedDeposit = sqlx::query_as(&format!(
+            "SELECT {} FROM deposits WHERE tx_hash = $1 AND log_index = $2 FOR UPDATE",
+            TRACKED_COLUMNS
+        ))
+        .bind(&tx_hash)
+        .bind(event.log_index)
+        .fetch_one(&mut *tx)
+        .await?;
+        let other_chain = deposit.chain_id.zip(chain_id).is_some_and(|(recorded, reported)| recorded != reported);
+        if deposit.user_address != user_address
+            || deposit.token != event.token
+            || deposit.amount != event.amount
+            || other_chain
+        {
+            return Err(DepositError::Conflict {
+                tx_hash,
+                log_index: event.log_index,
+            });
+        }
+
+        // Seen again after a reorg dropped it: the transaction was mined anew
+        if deposit.status == "reorged" {
+            sqlx::query("UPDATE deposits SET status = 'pending', block_number = $1, block_hash = $2, reverted_at = NULL WHERE id = $3")
+                .bind(event.block_number)
+                .bind(&block_hash)
+                .bind(deposit.id)
+                .execute(&mut *tx)
+                .await?;
+            deposit.status = "pending".to_string();
+            deposit.block_number = event.block_number;
+        }
+
+        let entry = if deposit.status == "pending" && finality.is_confirmed(deposit.block_number) {
+            let entry = Self::credit_locked(&mut tx, &deposit).await?;
+            deposit.status = "credited".to_string();
+            entry
+        } else {
+            None
+        };
+        tx.commit().await?;
+
+        Ok(CreditOutcome {
+            deposit_id: deposit.id,
+            status: deposit.status,
+            confirmations: finality.confirmations_of(deposit.block_number),
+            entry,
+        })
+    }
+
+    /// Credit a locked `pending` deposit
+    async fn credit_locked(conn: &mut PgConnection, deposit: &TrackedDeposit) -> Result<Option<LedgerEntry>, DepositError> {
+        let change = BalanceChange::credit(&deposit.user_address, &deposit.token, deposit.amount, LedgerReason::Deposit)
+            .reference(deposit.id)
+            .idempotency_key(deposit.credit_key());
+        let entry = LedgerService::apply(&mut *conn, &change).await?;
+
+        sqlx::query("UPDATE deposits SET status = 'credited', credited_at = COALESCE(credited_at, NOW()) WHERE id = $1")
+            .bind(deposit.id)
+            .execute(&mut *conn)
+            .await?;
+        if entry.is_some() {
+            tracing::info!(
+                "Credited deposit {}:{} of {} {} to {}",
+                deposit.tx_hash, deposit.log_index, deposit.amount, deposit.token, deposit.user_address
+            );
+        }
+        Ok(entry)
+    }
+
+    /// Deposits of a chain still pending or not yet final, and those dropped
+    /// within the recheck window, lowest block first
+    pub async fn unsettled(
+        pool: &PgPool,
+        chain_id: u64,
+        is_primary: bool,
+        limit: i64,
+    ) -> Result<Vec<TrackedDeposit>, DepositError> {
+        let rows = sqlx::query_as(&format!(
+            r#"
+            SELECT {} FROM deposits
+            WHERE (status IN ('pending', 'credited')
+                   OR (status IN ('reorged', 'reverted') AND reverted_at > NOW() - INTERVAL '{}'))
+              AND (chain_id = $1 OR ($2 AND chain_id IS NULL))
+            ORDER BY block_number
+            LIMIT $3
+            "#,
+            TRACKED_COLUMNS, DROPPED_RECHECK
+        ))
+        .bind(chain_id as i64)
+        .bind(is_primary)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
+    }
+
+    /// Credit a recorded deposit that reached its confirmations. None if it
+    /// is no longer pending.
+    pub async fn credit_confirmed(pool: &PgPool, deposit_id: Uuid) -> Result<Option<LedgerEntry>, DepositError> {
+        let mut tx = pool.begin().await?;
+        let Some(deposit) = Self::lock(&mut tx, deposit_id, "pending").await? else {
+            return Ok(None);
+        };
+        let entry = Self::credit_locked(&mut tx, &deposit).await?;
+        tx.commit().await?;
+        Ok(entry)
+    }
+
+    /// Stop rechecking a credited deposit
+    pub async fn finalize(pool: &PgPool, deposit_id: Uuid) -> Result<bool, DepositError> {
+        let updated = sqlx::query(
+            "UPDATE deposits SET status = 'finalized', finalized_at = NOW() WHERE id = $1 AND status = 'credited'",
+        )
+        .bind(deposit_id)
+        .execute(pool)
+        .await?
+        .rows_affected();
+        Ok(updated > 0)
+    }
+
+    /// The deposit's transaction was re-mined in another block
+    pub async fn move_block(
+        pool: &PgPool,
+        deposit_id: Uuid,
+        block_number: i64,
+        block_hash: &str,
+    ) -> Result<(), DepositError> {
+        sqlx::query(
+            r#"
+            UPDATE deposits SET block_number = $1, block_hash = $2
+            WHERE id = $3 AND status IN ('pending', 'credited')
+            "#,
+        )
+        .bind(block_number)
+        .bind(block_hash.to_lowercase())
+        .bind(deposit_id)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Record a poll that found no receipt for the deposit's transaction
+    pub async fn mark_missing(pool: &PgPool, deposit_id: Uuid, head: u64) -> Result<(), DepositError> {
+        sqlx::query(
+            r#"
+            UPDATE deposits
+            SET missing_polls = missing_polls + 1, missing_since = COALESCE(missing_since, $1)
+            WHERE id = $2 AND status IN ('pending', 'credited')
+            "#,
+        )
+        .bind(head as i64)
+        .bind(deposit_id)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// The receipt was found again: restart the missing count
+    pub async fn clear_missing(pool: &PgPool, deposit_id: Uuid) -> Result<(), DepositError> {
+        sqlx::query("UPDATE deposits SET missing_polls = 0, missing_since = NULL WHERE id = $1")
+            .bind(deposit_id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    /// The transaction of a `reorged` or `reverted` deposit is back on chain:
+    /// it returns to `pending` at its new block and is credited again once
+    /// confirmed, under the next generation's key
+    pub async fn restore(
+        pool: &PgPool,
+        deposit_id: Uuid,
+        block_number: i64,
+        block_hash: &str,
+    ) -> Result<bool, DepositError> {
+        let updated = sqlx::query(
+            r#"
+            UPDATE deposits
+            SET status = 'pending', block_number = $1, block_hash = $2, reverted_at = NULL,
+                missing_polls = 0, missing_since = NULL
+            WHERE id = $3 AND status IN ('reorged', 'reverted')
+            "#,
+        )
+        .bind(block_number)
+        .bind(block_hash.to_lowercase())
+        .bind(deposit_id)
+        .execute(pool)
+        .await?
+        .rows_affected();
+        Ok(updated > 0)
+    }
+
+    /// The deposit's transaction left the chain: a pending deposit becomes
+    /// `reorged`, a credited one `reverted` with its credit reversed. The
+    /// reversal is unchecked, like [`DepositService::repair`]. Finalized
+    /// deposits are left alone. Either starts a new generation, so a later
+    /// [`DepositService::restore`] credits afresh.
+    pub async fn revert(pool: &PgPool, deposit_id: Uuid) -> Result<Option<LedgerEntry>, DepositError> {
+        let mut tx = pool.begin().await?;
+        let deposit: Option<TrackedDeposit> =
+            sqlx::query_as(&format!("SELECT {} FROM deposits WHERE id = $1 FOR UPDATE", TRACKED_COLUMNS))
+                .bind(deposit_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+        let Some(deposit) = deposit else {
+            return Ok(None);
+        };
+
+        let entry = match deposit.status.as_str() {
+            "pending" => {
+                sqlx::query(&format!("UPDATE deposits SET status = 'reorged', {} WHERE id = $1", DROPPED))
+                    .bind(deposit.id)
+                    .execute(&mut *tx)
+                    .await?;
+                tracing::warn!("Deposit {}:{} dropped by a reorg before crediting", deposit.tx_hash, deposit.log_index);
+                None
+            }
+            "credited" => {
+                let change =
+                    BalanceChange::debit(&deposit.user_address, &deposit.token, deposit.amount, LedgerReason::DepositReversal)
+                        .reference(deposit.id)
+                        .idempotency_key(deposit.reversal_key());
+                let entry = LedgerService::apply(&mut tx, &change).await?;
+                sqlx::query(&format!("UPDATE deposits SET status = 'reverted', {} WHERE id = $1", DROPPED))
+                    .bind(deposit.id)
+                    .execute(&mut *tx)
+                    .await?;
+                tracing::warn!(
+                    "Deposit {}:{} dropped by a reorg: reversed {} {} credited to {}",
+                    deposit.tx_hash, deposit.log_index, deposit.amount, deposit.token, deposit.user_address
+                );
+                entry
+            }
+            _ => None,
+        };
+        tx.commit().await?;
+        Ok(entry)
+    }
+
+    async fn lock(conn: &mut PgConnection, deposit_id: Uuid, status: &str) -> Result<Option<TrackedDeposit>, DepositError> {
+        let deposit = sqlx::query_as(&format!(
+            "SELECT {} FROM deposits WHERE id = $1 AND status = $2 FOR UPDATE",
+            TRACKED_COLUMNS
+        ))
+        .bind(deposit_id)
+        .bind(status)
+        .fetch_optional(&mut *conn)
+        .await?;
+        Ok(deposit)
+    }
+
+    /// Deposits credited more than their amount
+    pub async fn find_double_credits(pool: &PgPool) -> Result<Vec<DoubleCredit>, DepositError> {
+        let found = sqlx::query_as(
+            r#"
+            SELECT d.id AS deposit_id, d.user_address, d.token, d.amount,
+                   SUM(l.available_delta) AS credited, COUNT(*) AS entries
+            FROM deposits d
+            JOIN balance_ledger l ON l.reference_id = d.id AND l.reason IN ('deposit', 'deposit_reversal')
+            GROUP BY d.id, d.user_address, d.token, d.amount
+            HAVING SUM(l.available_delta) > d.amount
+            ORDER BY MIN(l.created_at)
+            "#,
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(found)
+    }
+
+    /// Debit the excess of a double credit. The debit is unchecked: funds that
+    /// were never deposited are owed even if already spent, so available may
+    /// go negative. None if this state was already repaired.
+    pub async fn repair(pool: &PgPool, double_credit: &DoubleCredit) -> Result<Option<LedgerEntry>, DepositError> {
+        let change = BalanceChange::debit(
+            &double_credit.user_address,
+            &double_credit.token,
+            double_credit.excess(),
+            LedgerReason::DepositReversal,
+        )
+        .reference(double_credit.deposit_id)
+        .idempotency_key(double_credit.reversal_key());
+        Ok(LedgerService::post(pool, &change).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_keys_identify_the_log_and_the_repair() {
+        let event = DepositEvent {
+            tx_hash: "0xABC".to_string(),
+            log_index: 3,
+            user_address: "0xUser".to_string(),
+            token: "USDT".to_string(),
+            amount: dec!(100),
+            block_number: 1,
+            block_hash: None,
+            chain_id: None,
+        };
+        assert_eq!(event.idempotency_key(), "deposit:0xabc:3");
+
+        let double_credit = DoubleCredit {
+            deposit_id: Uuid::nil(),
+            user_address: "0xuser".to_string(),
+            token: "USDT".to_string(),
+            amount: dec!(100),
+            credited: dec!(300),
+            entries: 3,
+        };
+        assert_eq!(double_credit.excess(), dec!(200));
+        assert_eq!(
+            double_credit.reversal_key(),
+            "deposit-reversal:00000000-0000-0000-0000-000000000000:3"
+        );
+    }
+
+    #[test]
+    fn test_confirmations_count_from_the_head() {
+        let finality = Finality {
+            head: Some(111),
+            confirmations: 12,
+            finality_blocks: 64,
+        };
+        assert_eq!(finality.confirmations_of(100), Some(12));
+        assert_eq!(finality.confirmations_of(200), Some(0));
+        assert!(finality.is_confirmed(100));
+        assert!(!finality.is_confirmed(101));
+        assert!(!finality.is_final(100));
+
+        let unknown = Finality { head: None, ..finality };
+        assert_eq!(unknown.confirmations_of(100), None);
+        assert!(!unknown.is_confirmed(100));
+        assert!(Finality { confirmations: 0, ..unknown }.is_confirmed(100));
+    }
+}
@@ -0,0 +1,430 @@
+//! Deposit Confirmation Tracker
+//!
+//! Follows the head (`eth_blockNumber`) of every configured chain
+//! ([`crate::services::chains`]) and walks each chain's deposits that are not
+//! final yet. For each one it fetches the transaction receipt and:
+//!
+//! - reverts the deposit if the transaction failed, or is gone from the
+//!   chain on [`MISSING_POLLS`] polls in a row spanning [`MISSING_BLOCKS`]
+//!   blocks, so a single lagging node can't reverse a credit (see
+//!   [`DepositService::revert`])
+//! - restores a `reorged` or `reverted` deposit whose transaction reappears
+//!   (see [`DepositService::restore`])
+//! - moves it if the transaction was re-mined in another block, so its
+//!   confirmations count from there
+//! - credits it once it has `deposit_confirmations` confirmations (or the
+//!   chain's own `confirmations`)
+//! - finalizes it once it has `deposit_finality_blocks`
+//!
+//! Without a usable RPC URL a chain has no head: its deposits wait as
+//! `pending` unless no confirmations are required. A chain whose RPC fails
+//! doesn't hold up the others.
+
+use std::collections::HashMap;
+
+use ethers::providers::{Http, Middleware, Provider};
+use ethers::types::H256;
+use parking_lot::RwLock;
+use serde::Serialize;
+use sqlx::PgPool;
+use std::str::FromStr;
+
+use crate::services::chains::ChainConfig;
+use crate::services::deposit::{DepositError, DepositService, Finality, TrackedDeposit};
+
+/// Most deposits checked per run
+const BATCH_SIZE: i64 = 500;
+
+/// Consecutive polls a receipt must be missing before the deposit reverts
+pub const MISSING_POLLS: i32 = 3;
+
+/// Blocks the head must advance past the first missing poll before reverting
+pub const MISSING_BLOCKS: u64 = 12;
+
+/// Confirmation tracker errors
+#[derive(Debug, thiserror::Error)]
+pub enum DepositConfirmationError {
+    #[error("Chain error: {0}")]
+    ChainError(String),
+
+    #[error(transparent)]
+    Deposit(#[from] DepositError),
+}
+
+/// Where a deposit's transaction is on chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceiptInfo {
+    pub block_number: u64,
+    pub block_hash: String,
+    pub succeeded: bool,
+}
+
+/// What to do with a tracked deposit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DepositAction {
+    Wait,
+    Credit,
+    Finalize,
+    Move { block_number: i64, block_hash: String },
+    /// No receipt yet not long enough to revert
+    Missing,
+    Revert,
+    /// A dropped deposit's transaction is back on chain
+    Restore { block_number: i64, block_hash: String },
+}
+
+/// Decide a deposit's next step from its receipt (None when the node has no
+/// receipt for the transaction)
+pub fn next_action(deposit: &TrackedDeposit, receipt: Option<&ReceiptInfo>, finality: &Finality) -> DepositAction {
+    let dropped = matches!(deposit.status.as_str(), "reorged" | "reverted");
+    let Some(receipt) = receipt else {
+        let Some(head) = finality.head else {
+            return DepositAction::Wait;
+        };
+        // A node behind the deposit's block hasn't seen it yet
+        if dropped || head < u64::try_from(deposit.block_number).unwrap_or(0) {
+            return DepositAction::Wait;
+        }
+        let since = deposit.missing_since.and_then(|since| u64::try_from(since).ok()).unwrap_or(head);
+        let gone = deposit.missing_polls + 1 >= MISSING_POLLS && head >= since + MISSING_BLOCKS;
+        return if gone { DepositAction::Revert } else { DepositAction::Missing };
+    };
+    if dropped {
+        return if receipt.succeeded {
+            DepositAction::Restore {
+                block_number: receipt.block_number as i64,
+                block_hash: receipt.block_hash.clone(),
+            }
+        } else {
+            DepositAction::Wait
+        };
+    }
+    if !receipt.succeeded {
+        return DepositAction::Revert;
+    }
+    let moved = i64::try_from(receipt.block_number).ok() != Some(deposit.block_number)
+        || deposit
+            .block_hash
+            .as_deref()
+            .is_some_and(|hash| !hash.eq_ignore_ascii_case(&receipt.block_hash));
+    if moved {
+        return DepositAction::Move {
+            block_number: receipt.block_number as i64,
+            block_hash: receipt.block_hash.clone(),
+        };
+    }
+    match deposit.status.as_str() {
+        "pending" if finality.is_confirmed(deposit.block_number) => DepositAction::Credit,
+        "credited" if finality.is_final(deposit.block_number) => DepositAction::Finalize,
+        _ => DepositAction::Wait,
+    }
+}
+
+/// Outcome of a tracker run on one chain
+#[derive(Debug, Default, Serialize)]
+pub struct ConfirmationRun {
+    pub chain_id: u64,
+    pub head: Option<u64>,
+    pub credited: usize,
+    pub finalized: usize,
+    pub moved: usize,
+    /// Deposits without a receipt, not yet reverted
+    pub missing: usize,
+    pub reverted: usize,
+    pub restored: usize,
+}
+
+/// One chain's provider and latest head
+struct ChainHead {
+    provider: Option<Provider<Http>>,
+    head: RwLock<Option<u64>>,
+    confirmations: Option<u64>,
+    is_primary: bool,
+}
+
+pub struct DepositConfirmationTracker {
+    chains: HashMap<u64, ChainHead>,
+}
+
+impl DepositConfirmationTracker {
+    /// Tracker of `chains`; a chain with an unusable RPC URL has no head
+    pub fn new<'a>(chains: impl IntoIterator<Item = &'a ChainConfig>, primary: u64) -> Self {
+        let chains = chains
+            .into_iter()
+            .map(|chain| {
+                let provider = match Provider::<Http>::try_from(chain.rpc_url.as_str()) {
+                    Ok(provider) => Some(provider),
+                    Err(e) => {
+                        tracing::warn!("Deposit confirmations on chain {} disabled, invalid RPC URL: {}", chain.chain_id, e);
+                        None
+                    }
+                };
+                let head = ChainHead {
+                    provider,
+                    head: RwLock::new(None),
+                    confirmations: chain.confirmations,
+                    is_primary: chain.chain_id == primary,
+                };
+                (chain.chain_id, head)
+            })
+            .collect();
+        Self { chains }
+    }
+
+    /// Latest block seen on a chain
+    pub fn head(&self, chain_id: u64) -> Option<u64> {
+        self.chains.get(&chain_id).and_then(|chain| *chain.head.read())
+    }
+
+    /// Requirements of a chain, counted against its latest head.
+    /// `confirmations` applies unless the chain sets its own.
+    pub fn finality(&self, chain_id: u64, confirmations: u64, finality_blocks: u64) -> Finality {
+        let chain = self.chains.get(&chain_id);
+        Finality {
+            head: self.head(chain_id),
+            confirmations: chain.and_then(|chain| chain.confirmations).unwrap_or(confirmations),
+            finality_blocks,
+        }
+    }
+
+    async fn receipt(provider: &Provider<Http>, tx_hash: &str) -> Result<Option<ReceiptInfo>, DepositConfirmationError> {
+        let hash = H256::from_str(tx_hash).map_err(|e| DepositConfirmationError::ChainError(e.to_string()))?;
+        let receipt = provider
+            .get_transaction_receipt(hash)
+            .await
+            .map_err(|e| DepositConfirmationError::ChainError(e.to_string()))?;
+        Ok(receipt.and_then(|receipt| {
+            Some(ReceiptInfo {
+                block_number: receipt.block_number?.as_u64(),
+                block_hash: format!("{:?}", receipt.block_hash?),
+                succeeded: receipt.status.is_none_or(|status| status.as_u64() == 1),
+            })
+        }))
+    }
+
+    /// Advance the unsettled deposits of every chain; a failing chain is
+    /// logged and skipped
+    pub async fn poll(&self, pool: &PgPool, confirmations: u64, finality_blocks: u64) -> Vec<ConfirmationRun> {
+        let mut runs = Vec::new();
+        for (&chain_id, chain) in &self.chains {
+            match self.poll_chain(pool, chain_id, chain, confirmations, finality_blocks).await {
+                Ok(Some(run)) => runs.push(run),
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Deposit confirmations on chain {} failed: {}", chain_id, e),
+            }
+        }
+        runs
+    }
+
+    /// Refresh a chain's head and advance its unsettled deposits
+    async fn poll_chain(
+        &self,
+        pool: &PgPool,
+        chain_id: u64,
+        chain: &ChainHead,
+        confirmations: u64,
+        finality_blocks: u64,
+    ) -> Result<Option<ConfirmationRun>, DepositConfirmationError> {
+        let Some(provider) = &chain.provider else {
+            return Ok(None);
+        };
+        let head = provider
+            .get_block_number()
+            .await
+            .map_err(|e| DepositConfirmationError::ChainError(e.to_string()))?
+            .as_u64();
+        *chain.head.write() = Some(head);
+        let finality = self.finality(chain_id, confirmations, finality_blocks);
+
+        let mut run = ConfirmationRun {
+            chain_id,
+            head: Some(head),
+            ..Default::default()
+        };
+        for deposit in DepositService::unsettled(pool, chain_id, chain.is_primary, BATCH_SIZE).await? {
+            // Nothing to decide for a pending deposit short of its confirmations
+            if deposit.status == "pending" && !finality.is_confirmed(deposit.block_number) {
+                continue;
+            }
+            let receipt = Self::receipt(provider, &deposit.tx_hash).await?;
+            if receipt.is_some() && deposit.missing_polls > 0 {
+                DepositService::clear_missing(pool, deposit.id).await?;
+            }
+            match next_action(&deposit, receipt.as_ref(), &finality) {
+                DepositAction::Wait => {}
+                DepositAction::Credit => {
+                    DepositService::credit_confirmed(pool, deposit.id).await?;
+                    run.credited += 1;
+                }
+                DepositAction::Finalize => {
+                    if DepositService::finalize(pool, deposit.id).await? {
+                        run.finalized += 1;
+                    }
+                }
+                DepositAction::Move { block_number, block_hash } => {
+                    tracing::info!(
+                        "Deposit {}:{} on chain {} moved from block {} to {}",
+                        deposit.tx_hash,
+                        deposit.log_index,
+                        chain_id,
+                        deposit.block_number,
+                        block_number
+                    );
+                    DepositService::move_block(pool, deposit.id, block_number, &block_hash).await?;
+                    run.moved += 1;
+                }
+                DepositAction::Missing => {
+                    DepositService::mark_missing(pool, deposit.id, head).await?;
+                    run.missing += 1;
+                }
+                DepositAction::Revert => {
+                    DepositService::revert(pool, deposit.id).await?;
+                    run.reverted += 1;
+                }
+                DepositAction::Restore { block_number, block_hash } => {
+                    if DepositService::restore(pool, deposit.id, block_number, &block_hash).await? {
+                        tracing::warn!(
+                            "Deposit {}:{} on chain {} reappeared in block {}: back to pending",
+                            deposit.tx_hash,
+                            deposit.log_index,
+                            chain_id,
+                            block_number
+                        );
+                        run.restored += 1;
+                    }
+                }
+            }
+        }
+        Ok(Some(run))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+    use uuid::Uuid;
+
+    const BLOCK_HASH: &str = "0xaaaa";
+
+    fn deposit(status: &str, block_number: i64) -> TrackedDeposit {
+        TrackedDeposit {
+            id: Uuid::nil(),
+            user_address: "0xuser".to_string(),
+            token: "USDC".to_string(),
+            amount: Decimal::from(100),
+            tx_hash: "0xabc".to_string(),
+            log_index: 0,
+            block_number,
+            block_hash: Some(BLOCK_HASH.to_string()),
+            chain_id: None,
+            status: status.to_string(),
+            missing_polls: 0,
+            missing_since: None,
+            reorgs: 0,
+        }
+    }
+
+    fn missing(status: &str, block_number: i64, missing_polls: i32, missing_since: i64) -> TrackedDeposit {
+        TrackedDeposit {
+            missing_polls,
+            missing_since: Some(missing_since),
+            ..deposit(status, block_number)
+        }
+    }
+
+    fn receipt(block_number: u64, block_hash: &str, succeeded: bool) -> ReceiptInfo {
+        ReceiptInfo {
+            block_number,
+            block_hash: block_hash.to_string(),
+            succeeded,
+        }
+    }
+
+    fn finality(head: u64) -> Finality {
+        Finality {
+            head: Some(head),
+            confirmations: 12,
+            finality_blocks: 64,
+        }
+    }
+
+    #[test]
+    fn test_deposit_advances_with_confirmations() {
+        let mined = receipt(100, BLOCK_HASH, true);
+        assert_eq!(next_action(&deposit("pending", 100), Some(&mined), &finality(110)), DepositAction::Wait);
+        assert_eq!(next_action(&deposit("pending", 100), Some(&mined), &finality(111)), DepositAction::Credit);
+        assert_eq!(next_action(&deposit("credited", 100), Some(&mined), &finality(162)), DepositAction::Wait);
+        assert_eq!(next_action(&deposit("credited", 100), Some(&mined), &finality(163)), DepositAction::Finalize);
+    }
+
+    #[test]
+    fn test_reorged_deposits() {
+        // Re-mined in another block
+        assert_eq!(
+            next_action(&deposit("pending", 100), Some(&receipt(103, "0xbbbb", true)), &finality(120)),
+            DepositAction::Move {
+                block_number: 103,
+                block_hash: "0xbbbb".to_string()
+            }
+        );
+        // Same height, different block
+        assert!(matches!(
+            next_action(&deposit("credited", 100), Some(&receipt(100, "0xbbbb", true)), &finality(120)),
+            DepositAction::Move { .. }
+        ));
+        assert_eq!(
+            next_action(&deposit("credited", 100), Some(&receipt(100, BLOCK_HASH, false)), &finality(120)),
+            DepositAction::Revert
+        );
+        // The node hasn't reached the deposit's block
+        assert_eq!(next_action(&deposit("pending", 100), None, &finality(99)), DepositAction::Wait);
+    }
+
+    #[test]
+    fn test_missing_receipt_reverts_only_after_polls_and_blocks() {
+        // One missing receipt is recorded, not acted on
+        assert_eq!(next_action(&deposit("credited", 100), None, &finality(120)), DepositAction::Missing);
+        assert_eq!(next_action(&missing("credited", 100, 1, 120), None, &finality(125)), DepositAction::Missing);
+        // Enough polls, but the head hasn't moved far enough
+        assert_eq!(next_action(&missing("credited", 100, 5, 120), None, &finality(131)), DepositAction::Missing);
+        // Enough blocks, but too few polls
+        assert_eq!(next_action(&missing("credited", 100, 1, 120), None, &finality(140)), DepositAction::Missing);
+        assert_eq!(next_action(&missing("credited", 100, 2, 120), None, &finality(132)), DepositAction::Revert);
+        assert_eq!(next_action(&missing("pending", 100, 2, 120), None, &finality(132)), DepositAction::Revert);
+        // A receipt found again goes on as usual
+        assert_eq!(
+            next_action(&missing("credited", 100, 2, 120), Some(&receipt(100, BLOCK_HASH, true)), &finality(132)),
+            DepositAction::Wait
+        );
+    }
+
+    #[test]
+    fn test_dropped_deposit_is_restored_when_its_receipt_reappears() {
+        for status in ["reorged", "reverted"] {
+            assert_eq!(
+                next_action(&deposit(status, 100), Some(&receipt(104, "0xbbbb", true)), &finality(120)),
+                DepositAction::Restore {
+                    block_number: 104,
+                    block_hash: "0xbbbb".to_string()
+                }
+            );
+            assert_eq!(next_action(&deposit(status, 100), None, &finality(200)), DepositAction::Wait);
+            assert_eq!(
+                next_action(&deposit(status, 100), Some(&receipt(104, "0xbbbb", false)), &finality(120)),
+                DepositAction::Wait
+            );
+        }
+    }
+
+    #[test]
+    fn test_each_generation_has_its_own_ledger_keys() {
+        let first = deposit("credited", 100);
+        assert_eq!(first.credit_key(), "deposit:0xabc:0");
+        assert_eq!(first.reversal_key(), "deposit-reorg:0xabc:0");
+        let restored = TrackedDeposit { reorgs: 1, ..first };
+        assert_eq!(restored.credit_key(), "deposit:0xabc:0:1");
+        assert_eq!(restored.reversal_key(), "deposit-reorg:0xabc:0:1");
+    }
+}
+
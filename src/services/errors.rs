@@ -0,0 +1,392 @@
+//! Service Error Taxonomy
+//!
+//! Service errors stay typed per service (thiserror enums); this module
+//! classifies them into the few kinds callers act on, so handlers can tell a
+//! retryable failure from a fatal one without matching every variant:
+//!
+//! | kind                  | meaning                              | retry          |
+//! |-----------------------|--------------------------------------|----------------|
+//! | `NotFound`            | the resource does not exist          | no             |
+//! | `InvalidInput`        | the request is wrong                 | no             |
+//! | `InsufficientMargin`  | not enough margin / balance          | after funding  |
+//! | `Conflict`            | lost a race (version, serialization) | yes            |
+//! | `UpstreamUnavailable` | database / RPC / engine unavailable  | yes, backoff   |
+//! | `Internal`            | bug or unexpected state              | no             |
+//!
+//! Every classified error also has a stable machine-readable code. The HTTP
+//! mapping lives in [`crate::api::error`]. Queries run outside a service
+//! classify through the `sqlx::Error` impl. The legacy referral handlers
+//! (`api::handlers::referral`) are not compiled in and keep their own
+//! mapping; referral settlement is covered by [`ReferralSettlementError`].
+
+use crate::services::api_keys::ApiKeyError;
+use crate::services::auth_tokens::AuthTokenError;
+use crate::services::matching::{MatchingError, ReplicationError};
+use crate::services::portfolio::PortfolioError;
+use crate::services::position::PositionError;
+use crate::services::referral_settlement::ReferralSettlementError;
+use crate::services::risk_profile::RiskProfileError;
+use crate::services::session_keys::SessionKeyError;
+use crate::services::sub_account::SubAccountError;
+use crate::services::withdrawal_fees::WithdrawalFeeError;
+use crate::services::withdrawal_risk::WithdrawalRiskError;
+
+/// What a caller can do about a failure
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    NotFound,
+    InvalidInput,
+    InsufficientMargin,
+    Conflict,
+    UpstreamUnavailable,
+    Internal,
+}
+
+impl ErrorKind {
+    /// Whether repeating the same request may succeed
+    pub fn retryable(&self) -> bool {
+        matches!(self, ErrorKind::Conflict | ErrorKind::UpstreamUnavailable)
+    }
+}
+
+/// A service error classified into the taxonomy
+pub trait ServiceError: std::error::Error {
+    fn kind(&self) -> ErrorKind;
+
+    /// Stable error code (`ORDER_NOT_FOUND`, ...)
+    fn code(&self) -> &'static str;
+}
+
+/// Classify a database error
+pub fn sqlx_kind(e: &sqlx::Error) -> ErrorKind {
+    match e {
+        sqlx::Error::RowNotFound => ErrorKind::NotFound,
+        sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed | sqlx::Error::Io(_) | sqlx::Error::Tls(_) => {
+            ErrorKind::UpstreamUnavailable
+        }
+        sqlx::Error::Database(db) => match db.code().as_deref() {
+            // serialization_failure, deadlock_detected, unique_violation
+            Some("40001") | Some("40P01") | Some("23505") => ErrorKind::Conflict,
+            // admin_shutdown, cannot_connect_now, too_many_connections
+            Some("57P01") | Some("57P03") | Some("53300") => ErrorKind::UpstreamUnavailable,
+            _ => ErrorKind::Internal,
+        },
+        _ => ErrorKind::Internal,
+    }
+}
+
+/// Error code of a database error
+fn sqlx_code(e: &sqlx::Error) -> &'static str {
+    match sqlx_kind(e) {
+        ErrorKind::NotFound => "NOT_FOUND",
+        ErrorKind::Conflict => "DB_CONFLICT",
+        ErrorKind::UpstreamUnavailable => "DB_UNAVAILABLE",
+        _ => "DB_ERROR",
+    }
+}
+
+/// Queries made outside a service (handlers, jobs) classify their errors
+/// directly
+impl ServiceError for sqlx::Error {
+    fn kind(&self) -> ErrorKind {
+        sqlx_kind(self)
+    }
+
+    fn code(&self) -> &'static str {
+        sqlx_code(self)
+    }
+}
+
+impl ServiceError for ApiKeyError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            ApiKeyError::NotFound(_) => ErrorKind::NotFound,
+            ApiKeyError::DatabaseError(e) => sqlx_kind(e),
+            _ => ErrorKind::InvalidInput,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            ApiKeyError::NotFound(_) => "API_KEY_NOT_FOUND",
+            ApiKeyError::Invalid(_) => "INVALID_API_KEY_REQUEST",
+            ApiKeyError::LimitReached(_) => "API_KEY_LIMIT",
+            ApiKeyError::UnknownKey => "UNKNOWN_API_KEY",
+            ApiKeyError::StaleTimestamp => "STALE_TIMESTAMP",
+            ApiKeyError::BadSignature => "SIGNATURE_INVALID",
+            ApiKeyError::IpNotAllowed(_) => "IP_NOT_ALLOWED",
+            ApiKeyError::DatabaseError(e) => sqlx_code(e),
+        }
+    }
+}
+
+impl ServiceError for SessionKeyError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            SessionKeyError::NotFound(_) => ErrorKind::NotFound,
+            SessionKeyError::DatabaseError(e) => sqlx_kind(e),
+            _ => ErrorKind::InvalidInput,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            SessionKeyError::NotFound(_) => "SESSION_KEY_NOT_FOUND",
+            SessionKeyError::Invalid(_) => "INVALID_SESSION_KEY_REQUEST",
+            SessionKeyError::LimitReached(_) => "SESSION_KEY_LIMIT",
+            SessionKeyError::DatabaseError(e) => sqlx_code(e),
+        }
+    }
+}
+
+impl ServiceError for AuthTokenError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            AuthTokenError::Signing(_) => ErrorKind::Internal,
+            AuthTokenError::DatabaseError(e) => sqlx_kind(e),
+            _ => ErrorKind::InvalidInput,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            AuthTokenError::InvalidToken => "TOKEN_INVALID",
+            AuthTokenError::Revoked => "TOKEN_REVOKED",
+            AuthTokenError::InvalidRefreshToken => "REFRESH_TOKEN_INVALID",
+            AuthTokenError::RefreshTokenReused => "REFRESH_TOKEN_REUSED",
+            AuthTokenError::Signing(_) => "JWT_GENERATION_FAILED",
+            AuthTokenError::DatabaseError(e) => sqlx_code(e),
+        }
+    }
+}
+
+impl ServiceError for MatchingError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            MatchingError::SymbolNotFound(_)
+            | MatchingError::MarketNotFound(_)
+            | MatchingError::OutcomeNotFound(_)
+            | MatchingError::OrderNotFound(_) => ErrorKind::NotFound,
+            MatchingError::InvalidPrice(_)
+            | MatchingError::InvalidAmount(_)
+            | MatchingError::InvalidSide(_)
+            | MatchingError::MarketNotActive(_)
+            | MatchingError::MarketClosed(_)
+            | MatchingError::RuleViolation(_)
+            // The book can't fill it now; resubmitting the same order won't help
+            | MatchingError::InsufficientLiquidity => ErrorKind::InvalidInput,
+            MatchingError::DatabaseError(_) | MatchingError::Standby => ErrorKind::UpstreamUnavailable,
+            MatchingError::Journal(_) | MatchingError::InternalError(_) => ErrorKind::Internal,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            MatchingError::SymbolNotFound(_) => "SYMBOL_NOT_FOUND",
+            MatchingError::MarketNotFound(_) => "MARKET_NOT_FOUND",
+            MatchingError::OutcomeNotFound(_) => "OUTCOME_NOT_FOUND",
+            MatchingError::OrderNotFound(_) => "ORDER_NOT_FOUND",
+            MatchingError::InvalidPrice(_) => "INVALID_PRICE",
+            MatchingError::InvalidAmount(_) | MatchingError::RuleViolation(_) => "INVALID_AMOUNT",
+            MatchingError::InvalidSide(_) => "INVALID_SIDE",
+            MatchingError::MarketNotActive(_) => "MARKET_NOT_ACTIVE",
+            MatchingError::MarketClosed(_) => "MARKET_CLOSED",
+            MatchingError::InsufficientLiquidity => "INSUFFICIENT_LIQUIDITY",
+            MatchingError::DatabaseError(_) => "MATCHING_UNAVAILABLE",
+            MatchingError::Standby => "ENGINE_STANDBY",
+            MatchingError::Journal(_) => "JOURNAL_ERROR",
+            MatchingError::InternalError(_) => "MATCHING_ERROR",
+        }
+    }
+}
+
+impl ServiceError for PositionError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            PositionError::UserNotFound(_) | PositionError::PositionNotFound(_) => ErrorKind::NotFound,
+            PositionError::InvalidMarginMode(_) => ErrorKind::InvalidInput,
+            PositionError::MaintenanceBreach { .. } => ErrorKind::InsufficientMargin,
+            PositionError::DatabaseError(e) => sqlx_kind(e),
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            PositionError::UserNotFound(_) => "USER_NOT_FOUND",
+            PositionError::PositionNotFound(_) => "POSITION_NOT_FOUND",
+            PositionError::InvalidMarginMode(_) => "INVALID_MARGIN_MODE",
+            PositionError::MaintenanceBreach { .. } => "MAINTENANCE_MARGIN_BREACH",
+            PositionError::DatabaseError(e) => sqlx_code(e),
+        }
+    }
+}
+
+impl ServiceError for PortfolioError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            PortfolioError::InvalidRange(_) => ErrorKind::InvalidInput,
+            PortfolioError::DatabaseError(e) => sqlx_kind(e),
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            PortfolioError::InvalidRange(_) => "INVALID_RANGE",
+            PortfolioError::DatabaseError(e) => sqlx_code(e),
+        }
+    }
+}
+
+impl ServiceError for ReferralSettlementError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            ReferralSettlementError::InvalidEpochLength(_) => ErrorKind::InvalidInput,
+            ReferralSettlementError::DatabaseError(e) => sqlx_kind(e),
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            ReferralSettlementError::InvalidEpochLength(_) => "INVALID_EPOCH_LENGTH",
+            ReferralSettlementError::DatabaseError(e) => sqlx_code(e),
+        }
+    }
+}
+
+impl ServiceError for ReplicationError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            ReplicationError::BacklogExceeded { .. }
+            | ReplicationError::AheadOfPrimary { .. }
+            | ReplicationError::NotStandby => ErrorKind::InvalidInput,
+            ReplicationError::Primary(_) => ErrorKind::UpstreamUnavailable,
+            ReplicationError::Unauthorized | ReplicationError::Journal(_) => ErrorKind::Internal,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            ReplicationError::BacklogExceeded { .. } => "REPLICATION_BACKLOG_EXCEEDED",
+            ReplicationError::AheadOfPrimary { .. } => "REPLICATION_DIVERGED",
+            ReplicationError::NotStandby => "NOT_STANDBY",
+            ReplicationError::Primary(_) => "REPLICATION_PRIMARY_UNAVAILABLE",
+            ReplicationError::Unauthorized => "REPLICATION_UNAUTHORIZED",
+            ReplicationError::Journal(_) => "REPLICATION_JOURNAL_ERROR",
+        }
+    }
+}
+
+impl ServiceError for RiskProfileError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            RiskProfileError::UnknownProfile(_) | RiskProfileError::AcknowledgmentRequired { .. } => {
+                ErrorKind::InvalidInput
+            }
+            RiskProfileError::DatabaseError(e) => sqlx_kind(e),
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            RiskProfileError::UnknownProfile(_) => "UNKNOWN_RISK_PROFILE",
+            RiskProfileError::AcknowledgmentRequired { .. } => "ACKNOWLEDGMENT_REQUIRED",
+            RiskProfileError::DatabaseError(e) => sqlx_code(e),
+        }
+    }
+}
+
+impl ServiceError for SubAccountError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            SubAccountError::NotFound(_) => ErrorKind::NotFound,
+            SubAccountError::InvalidLabel(_)
+            | SubAccountError::LabelTaken(_)
+            | SubAccountError::LimitReached(_)
+            | SubAccountError::InvalidTransfer(_) => ErrorKind::InvalidInput,
+            SubAccountError::InsufficientBalance => ErrorKind::InsufficientMargin,
+            SubAccountError::DatabaseError(e) => sqlx_kind(e),
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            SubAccountError::NotFound(_) => "SUB_ACCOUNT_NOT_FOUND",
+            SubAccountError::InvalidLabel(_) => "INVALID_LABEL",
+            SubAccountError::LabelTaken(_) => "LABEL_TAKEN",
+            SubAccountError::LimitReached(_) => "SUB_ACCOUNT_LIMIT",
+            SubAccountError::InvalidTransfer(_) => "INVALID_TRANSFER",
+            SubAccountError::InsufficientBalance => "INSUFFICIENT_BALANCE",
+            SubAccountError::DatabaseError(e) => sqlx_code(e),
+        }
+    }
+}
+
+impl ServiceError for WithdrawalFeeError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            WithdrawalFeeError::InvalidPolicy(_) | WithdrawalFeeError::InvalidQuote => ErrorKind::InvalidInput,
+            WithdrawalFeeError::FeeExceedsAmount { .. } => ErrorKind::InsufficientMargin,
+            WithdrawalFeeError::DatabaseError(e) => sqlx_kind(e),
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            WithdrawalFeeError::InvalidPolicy(_) => "INVALID_FEE_POLICY",
+            WithdrawalFeeError::InvalidQuote => "INVALID_QUOTE",
+            WithdrawalFeeError::FeeExceedsAmount { .. } => "FEE_EXCEEDS_AMOUNT",
+            WithdrawalFeeError::DatabaseError(e) => sqlx_code(e),
+        }
+    }
+}
+
+impl ServiceError for WithdrawalRiskError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            WithdrawalRiskError::NotFound(_) => ErrorKind::NotFound,
+            WithdrawalRiskError::DatabaseError(e) => sqlx_kind(e),
+            _ => ErrorKind::InvalidInput,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            WithdrawalRiskError::DailyLimitExceeded { .. } => "WITHDRAW_DAILY_LIMIT",
+            WithdrawalRiskError::NotFound(_) => "WITHDRAWAL_NOT_FOUND",
+            WithdrawalRiskError::NotUnderReview { .. } => "WITHDRAWAL_NOT_UNDER_REVIEW",
+            WithdrawalRiskError::DatabaseError(e) => sqlx_code(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classifies_matching_errors() {
+        let not_found = MatchingError::OrderNotFound("o".to_string());
+        assert_eq!((not_found.kind(), not_found.code()), (ErrorKind::NotFound, "ORDER_NOT_FOUND"));
+
+        let db = MatchingError::DatabaseError("pool timed out".to_string());
+        assert_eq!(db.kind(), ErrorKind::UpstreamUnavailable);
+        assert!(db.kind().retryable());
+        assert!(!MatchingError::InternalError("bug".to_string()).kind().retryable());
+
+        let thin = MatchingError::InsufficientLiquidity;
+        assert_eq!((thin.kind(), thin.code()), (ErrorKind::InvalidInput, "INSUFFICIENT_LIQUIDITY"));
+        assert!(!thin.kind().retryable());
+    }
+
+    #[test]
+    fn test_classifies_database_errors() {
+        assert_eq!(sqlx_kind(&sqlx::Error::PoolTimedOut), ErrorKind::UpstreamUnavailable);
+        assert_eq!(sqlx_kind(&sqlx::Error::RowNotFound), ErrorKind::NotFound);
+        assert_eq!(sqlx_kind(&sqlx::Error::ColumnNotFound("x".to_string())), ErrorKind::Internal);
+
+        assert_eq!(sqlx::Error::RowNotFound.code(), "NOT_FOUND");
+
+        let e = PositionError::DatabaseError(sqlx::Error::PoolTimedOut);
+        assert_eq!((e.kind(), e.code()), (ErrorKind::UpstreamUnavailable, "DB_UNAVAILABLE"));
+    }
+}
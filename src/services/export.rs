@@ -0,0 +1,1342 @@
+//! History Export
+//!
+//! Bulk history for compliance and tax reporting, beyond what paginated
+//! endpoints can reasonably serve. An account queues an export job in
+//! `data_exports` for one dataset over a time range:
+//!
+//! - `trades`: the account's fills, with its side, role (maker/taker), fee,
+//!   fee token and realized PnL as attributed in `trade_fills`; a self-trade
+//!   is one row per role
+//! - `funding_settlements`: funding applied to the account's positions
+//! - `candles`: one symbol's K-lines of one period
+//! - `tax`: the account's trades, funding and fees as tax records, in the CSV
+//!   layout of a tax tool (`koinly` or `cointracking`)
+//!
+//! The worker claims pending jobs, streams the rows out of Postgres into a CSV
+//! file and stores it in the export store under `history/{address}/{id}.csv`.
+//! Trades already pruned from Postgres are read back from the history archive.
+//! The job then carries the row count, size and SHA-256 of the file, and the
+//! account downloads it through the job's download link.
+//!
+//! Files are written as CSV. Columnar formats (Parquet) are accepted by the
+//! API's format field but not built into this service and are rejected.
+//!
+//! Tax records are written from the account's side with positive amounts:
+//! what left the account is "sent", what arrived is "received". A buy sends
+//! collateral and receives outcome shares (`YES-1a2b3c4d`: share type and the
+//! first 8 hex digits of the outcome id); fees are their own column. Funding
+//! paid and fees charged outside trades (withdrawal, liquidation) are sent;
+//! funding received and negative fees (rebates) are received. Market
+//! resolution payouts are not included.
+
+use chrono::{DateTime, Duration, Utc};
+use futures::TryStreamExt;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::db::timescale::{Kline, KlinePeriod};
+use crate::services::archive::{ArchiveError, ArchiveService, ArchiveStore, ArchivedTrade};
+use crate::services::position::{attributions_by_fill, FillAttribution, FillRole, PositionError, PositionService};
+use crate::services::tape::{sha256_hex, ExportStatus};
+
+/// Maximum jobs run per worker cycle
+const MAX_JOBS_PER_CYCLE: usize = 5;
+
+/// Running jobs older than this are assumed abandoned and claimed again
+const STALE_JOB_MINUTES: i64 = 60;
+
+/// Longest range one export may cover
+pub const MAX_RANGE_DAYS: i64 = 366;
+
+/// Pending or running exports an account may have at once
+pub const MAX_ACTIVE_JOBS: i64 = 3;
+
+/// Rows beyond this fail the export; narrow the range instead
+pub const MAX_ROWS: u64 = 2_000_000;
+
+const JOB_COLUMNS: &str = r#"
+    id, user_address, kind, format, symbol, period, market_id, range_start, range_end,
+    status, row_count, byte_size, data_key, data_sha256, error, created_at, started_at, completed_at
+"#;
+
+/// History export errors
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    #[error("Unknown export kind: {0}")]
+    InvalidKind(String),
+
+    #[error("Unsupported export format: {0}")]
+    UnsupportedFormat(String),
+
+    #[error("Invalid range: {0}")]
+    InvalidRange(String),
+
+    #[error("Format {0} is not available for {1} exports")]
+    FormatNotForKind(String, String),
+
+    #[error("Candle exports need a symbol and a period")]
+    MissingSymbol,
+
+    #[error("Invalid period: {0}")]
+    InvalidPeriod(String),
+
+    #[error("Too many exports in progress (at most {0})")]
+    TooManyActive(i64),
+
+    #[error("Export exceeds {0} rows; narrow the range")]
+    TooManyRows(u64),
+
+    #[error("Export not found")]
+    NotFound,
+
+    #[error("Export is not completed")]
+    NotReady,
+
+    #[error("Archive error: {0}")]
+    Archive(#[from] ArchiveError),
+
+    #[error("Position error: {0}")]
+    Position(#[from] PositionError),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// Dataset of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportKind {
+    Trades,
+    FundingSettlements,
+    Candles,
+    Tax,
+}
+
+impl ExportKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportKind::Trades => "trades",
+            ExportKind::FundingSettlements => "funding_settlements",
+            ExportKind::Candles => "candles",
+            ExportKind::Tax => "tax",
+        }
+    }
+}
+
+impl FromStr for ExportKind {
+    type Err = ExportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "trades" => Ok(ExportKind::Trades),
+            "funding_settlements" => Ok(ExportKind::FundingSettlements),
+            "candles" => Ok(ExportKind::Candles),
+            "tax" => Ok(ExportKind::Tax),
+            other => Err(ExportError::InvalidKind(other.to_string())),
+        }
+    }
+}
+
+/// File format of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    /// Koinly universal CSV (tax exports)
+    Koinly,
+    /// CoinTracking CSV import (tax exports)
+    #[serde(rename = "cointracking")]
+    CoinTracking,
+}
+
+impl ExportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Koinly => "koinly",
+            ExportFormat::CoinTracking => "cointracking",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        "text/csv"
+    }
+
+    /// File extension
+    pub fn extension(&self) -> &'static str {
+        "csv"
+    }
+
+    /// Whether files of an export kind can be written in this format
+    pub fn supports(&self, kind: ExportKind) -> bool {
+        match self {
+            ExportFormat::Csv => kind != ExportKind::Tax,
+            ExportFormat::Koinly | ExportFormat::CoinTracking => kind == ExportKind::Tax,
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = ExportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "koinly" => Ok(ExportFormat::Koinly),
+            "cointracking" => Ok(ExportFormat::CoinTracking),
+            other => Err(ExportError::UnsupportedFormat(other.to_string())),
+        }
+    }
+}
+
+/// A requested export
+#[derive(Debug, Clone)]
+pub struct ExportRequest {
+    pub kind: ExportKind,
+    pub format: ExportFormat,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Candle symbol (candles only)
+    pub symbol: Option<String>,
+    /// Candle period (candles only)
+    pub period: Option<KlinePeriod>,
+    /// Restrict trades / funding settlements to one market
+    pub market_id: Option<Uuid>,
+}
+
+impl ExportRequest {
+    pub fn validate(&self, now: DateTime<Utc>) -> Result<(), ExportError> {
+        if self.from >= self.to {
+            return Err(ExportError::InvalidRange("from must be before to".to_string()));
+        }
+        if self.from > now {
+            return Err(ExportError::InvalidRange("range starts in the future".to_string()));
+        }
+        if self.to - self.from > Duration::days(MAX_RANGE_DAYS) {
+            return Err(ExportError::InvalidRange(format!("at most {} days per export", MAX_RANGE_DAYS)));
+        }
+        if !self.format.supports(self.kind) {
+            return Err(ExportError::FormatNotForKind(
+                self.format.as_str().to_string(),
+                self.kind.as_str().to_string(),
+            ));
+        }
+        if self.kind == ExportKind::Candles && (self.symbol.is_none() || self.period.is_none()) {
+            return Err(ExportError::MissingSymbol);
+        }
+        Ok(())
+    }
+}
+
+/// History export job
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct DataExport {
+    pub id: Uuid,
+    #[serde(skip)]
+    pub user_address: String,
+    pub kind: String,
+    pub format: String,
+    pub symbol: Option<String>,
+    pub period: Option<String>,
+    pub market_id: Option<Uuid>,
+    pub range_start: DateTime<Utc>,
+    pub range_end: DateTime<Utc>,
+    pub status: String,
+    pub row_count: Option<i64>,
+    pub byte_size: Option<i64>,
+    #[serde(skip)]
+    pub data_key: Option<String>,
+    pub data_sha256: Option<String>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl DataExport {
+    /// Download file name
+    pub fn filename(&self) -> String {
+        let (name, extension) = match self.format.parse::<ExportFormat>() {
+            Ok(ExportFormat::Csv) => (self.kind.clone(), "csv"),
+            Ok(format) => (format!("{}-{}", self.kind, self.format), format.extension()),
+            Err(_) => (self.kind.clone(), "csv"),
+        };
+        format!(
+            "{}-{}-{}.{}",
+            name,
+            self.range_start.format("%Y%m%d"),
+            self.range_end.format("%Y%m%d"),
+            extension
+        )
+    }
+}
+
+// ============================================================================
+// CSV encoding
+// ============================================================================
+
+/// CSV file built up row by row
+pub struct CsvWriter {
+    buf: Vec<u8>,
+    rows: u64,
+}
+
+impl CsvWriter {
+    /// Start a file with its header row
+    pub fn new(header: &[&str]) -> Self {
+        let mut writer = Self { buf: Vec::new(), rows: 0 };
+        writer.write_fields(header.iter());
+        writer
+    }
+
+    /// Append one data row
+    pub fn row(&mut self, fields: &[&dyn Display]) -> Result<(), ExportError> {
+        if self.rows >= MAX_ROWS {
+            return Err(ExportError::TooManyRows(MAX_ROWS));
+        }
+        self.write_fields(fields.iter());
+        self.rows += 1;
+        Ok(())
+    }
+
+    fn write_fields<T: Display>(&mut self, fields: impl Iterator<Item = T>) {
+        for (i, field) in fields.enumerate() {
+            if i > 0 {
+                self.buf.push(b',');
+            }
+            self.buf.extend_from_slice(csv_field(&field.to_string()).as_bytes());
+        }
+        self.buf.extend_from_slice(b"\r\n");
+    }
+
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+/// Quote a field when it contains a separator, quote or line break (RFC 4180)
+pub fn csv_field(value: &str) -> std::borrow::Cow<'_, str> {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\"")).into()
+    } else {
+        value.into()
+    }
+}
+
+/// Optional value as a CSV field (empty when absent)
+struct Opt<T>(Option<T>);
+
+impl<T: Display> Display for Opt<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            Some(value) => value.fmt(f),
+            None => Ok(()),
+        }
+    }
+}
+
+const TRADE_HEADER: &[&str] = &[
+    "trade_id", "time", "market_id", "outcome_id", "share_type", "side", "role", "price", "amount", "fee", "exec_id",
+    "fee_token", "realized_pnl",
+];
+
+/// One role of the account in a trade, from its point of view
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradeFill {
+    pub role: FillRole,
+    pub side: String,
+    pub share_type: String,
+    /// The account's price (the complement for mint / merge makers)
+    pub price: Decimal,
+    pub fee: Decimal,
+    /// None for trades from before fill attribution
+    pub fee_token: Option<String>,
+    pub realized_pnl: Option<Decimal>,
+}
+
+impl TradeFill {
+    /// The account's fill in `role`: its `trade_fills` attribution when
+    /// recorded; otherwise derived from the trade, where the maker is taken
+    /// to trade against the taker's side (not true of mint / merge makers,
+    /// which only attribution records)
+    pub fn of(trade: &ArchivedTrade, role: FillRole, attribution: Option<&FillAttribution>) -> Self {
+        if let Some(fill) = attribution {
+            return Self {
+                role,
+                side: fill.side.clone(),
+                share_type: fill.share_type.as_str().to_string(),
+                price: fill.price,
+                fee: fill.fee,
+                fee_token: Some(fill.fee_token.clone()),
+                realized_pnl: Some(fill.realized_pnl),
+            };
+        }
+        let (side, fee) = match role {
+            FillRole::Taker => (trade.side.clone(), trade.taker_fee),
+            FillRole::Maker => (if trade.side == "buy" { "sell" } else { "buy" }.to_string(), trade.maker_fee),
+        };
+        Self {
+            role,
+            side,
+            share_type: trade.share_type.clone(),
+            price: trade.price,
+            fee,
+            fee_token: None,
+            realized_pnl: None,
+        }
+    }
+}
+
+/// Write one of the account's fills
+fn write_trade(csv: &mut CsvWriter, trade: &ArchivedTrade, fill: &TradeFill) -> Result<(), ExportError> {
+    csv.row(&[
+        &trade.id,
+        &trade.created_at.to_rfc3339(),
+        &trade.market_id,
+        &trade.outcome_id,
+        &fill.share_type,
+        &fill.side,
+        &fill.role.as_str(),
+        &fill.price,
+        &trade.amount,
+        &fill.fee,
+        &Opt(trade.exec_id),
+        &Opt(fill.fee_token.as_ref()),
+        &Opt(fill.realized_pnl),
+    ])
+}
+
+#[derive(sqlx::FromRow)]
+struct FundingRow {
+    id: Uuid,
+    settled_at: DateTime<Utc>,
+    symbol: String,
+    funding_rate: Decimal,
+    mark_price: Option<Decimal>,
+    position_size: Decimal,
+    funding_fee: Decimal,
+    is_long: bool,
+    margin_mode: Option<String>,
+    token: Option<String>,
+}
+
+const FUNDING_HEADER: &[&str] = &[
+    "settlement_id", "time", "symbol", "funding_rate", "mark_price", "position_size", "funding_fee", "is_long",
+    "margin_mode", "token",
+];
+
+const CANDLE_HEADER: &[&str] = &[
+    "open_time", "symbol", "open", "high", "low", "close", "volume", "quote_volume", "trade_count",
+];
+
+// ============================================================================
+// Tax records
+// ============================================================================
+
+/// What a tax record represents
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaxEvent {
+    Trade,
+    FundingPaid,
+    FundingReceived,
+    /// Fee charged outside a trade's fee column
+    Fee,
+    /// Negative fee
+    Rebate,
+}
+
+impl TaxEvent {
+    /// Koinly label (empty for plain trades)
+    fn koinly_label(&self) -> &'static str {
+        match self {
+            TaxEvent::Trade => "",
+            TaxEvent::FundingPaid => "margin fee",
+            TaxEvent::FundingReceived => "realized gain",
+            TaxEvent::Fee => "cost",
+            TaxEvent::Rebate => "reward",
+        }
+    }
+
+    /// CoinTracking transaction type
+    fn cointracking_type(&self) -> &'static str {
+        match self {
+            TaxEvent::Trade => "Trade",
+            TaxEvent::FundingPaid => "Margin Fee",
+            TaxEvent::FundingReceived => "Derivatives / Futures Profit",
+            TaxEvent::Fee => "Other Fee",
+            TaxEvent::Rebate => "Reward / Bonus",
+        }
+    }
+}
+
+/// One taxable event from the account's side; amounts are positive
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaxRecord {
+    pub time: DateTime<Utc>,
+    pub event: TaxEvent,
+    /// Amount and currency that left the account
+    pub sent: Option<(Decimal, String)>,
+    /// Amount and currency that arrived
+    pub received: Option<(Decimal, String)>,
+    pub fee: Option<(Decimal, String)>,
+    pub description: String,
+    /// Trade, funding settlement or ledger entry id
+    pub reference: String,
+}
+
+/// Currency code of an outcome share (`YES-1a2b3c4d`)
+pub fn share_currency(share_type: &str, outcome_id: Uuid) -> String {
+    let outcome = outcome_id.simple().to_string();
+    format!("{}-{}", share_type.to_uppercase(), &outcome[..8])
+}
+
+/// Tax records of one of the account's fills: the swap, plus a rebate
+/// record for a negative fee. Fees are in the fill's fee token, or
+/// `collateral` when it isn't recorded.
+pub fn trade_tax_records(trade: &ArchivedTrade, fill: &TradeFill, collateral: &str) -> Vec<TaxRecord> {
+    let buys = fill.side == "buy";
+    let fee = fill.fee;
+    let fee_token = fill.fee_token.as_deref().unwrap_or(collateral);
+    let shares = (trade.amount, share_currency(&fill.share_type, trade.outcome_id));
+    let notional = (trade.amount * fill.price, collateral.to_string());
+    let (sent, received) = if buys { (notional, shares) } else { (shares, notional) };
+    let description = format!(
+        "{} {} {} shares of market {} outcome {} at {}",
+        if buys { "Buy" } else { "Sell" },
+        trade.amount,
+        fill.share_type,
+        trade.market_id,
+        trade.outcome_id,
+        fill.price
+    );
+
+    let mut records = vec![TaxRecord {
+        time: trade.created_at,
+        event: TaxEvent::Trade,
+        sent: Some(sent),
+        received: Some(received),
+        fee: (fee > Decimal::ZERO).then(|| (fee, fee_token.to_string())),
+        description,
+        reference: trade.id.to_string(),
+    }];
+    if fee < Decimal::ZERO {
+        records.push(TaxRecord {
+            time: trade.created_at,
+            event: TaxEvent::Rebate,
+            sent: None,
+            received: Some((-fee, fee_token.to_string())),
+            fee: None,
+            description: format!("Maker rebate on trade {}", trade.id),
+            reference: trade.id.to_string(),
+        });
+    }
+    records
+}
+
+/// Tax record of a funding settlement (`funding_fee` is paid by the position,
+/// negative when received)
+pub fn funding_tax_record(
+    id: Uuid,
+    settled_at: DateTime<Utc>,
+    symbol: &str,
+    funding_fee: Decimal,
+    token: &str,
+) -> Option<TaxRecord> {
+    if funding_fee.is_zero() {
+        return None;
+    }
+    let paid = funding_fee > Decimal::ZERO;
+    let amount = Some((funding_fee.abs(), token.to_string()));
+    Some(TaxRecord {
+        time: settled_at,
+        event: if paid { TaxEvent::FundingPaid } else { TaxEvent::FundingReceived },
+        sent: if paid { amount.clone() } else { None },
+        received: if paid { None } else { amount },
+        fee: None,
+        description: format!("Funding {} on {}", if paid { "paid" } else { "received" }, symbol),
+        reference: id.to_string(),
+    })
+}
+
+/// Tax record of a fee charged through the ledger (`charged` is the amount
+/// taken from the account, negative for a refund)
+pub fn fee_tax_record(id: Uuid, at: DateTime<Utc>, reason: &str, charged: Decimal, token: &str) -> Option<TaxRecord> {
+    if charged.is_zero() {
+        return None;
+    }
+    let amount = Some((charged.abs(), token.to_string()));
+    let refund = charged < Decimal::ZERO;
+    Some(TaxRecord {
+        time: at,
+        event: if refund { TaxEvent::Rebate } else { TaxEvent::Fee },
+        sent: if refund { None } else { amount.clone() },
+        received: if refund { amount } else { None },
+        fee: None,
+        description: format!("{} {}", reason.replace('_', " "), if refund { "refund" } else { "charged" }),
+        reference: id.to_string(),
+    })
+}
+
+const KOINLY_HEADER: &[&str] = &[
+    "Date", "Sent Amount", "Sent Currency", "Received Amount", "Received Currency", "Fee Amount", "Fee Currency",
+    "Net Worth Amount", "Net Worth Currency", "Label", "Description", "TxHash",
+];
+
+const COINTRACKING_HEADER: &[&str] = &[
+    "Type", "Buy Amount", "Buy Currency", "Sell Amount", "Sell Currency", "Fee", "Fee Currency", "Exchange",
+    "Trade-Group", "Comment", "Date", "Tx-ID",
+];
+
+/// Header row of a tax format
+fn tax_header(format: ExportFormat) -> &'static [&'static str] {
+    match format {
+        ExportFormat::CoinTracking => COINTRACKING_HEADER,
+        _ => KOINLY_HEADER,
+    }
+}
+
+/// Write one tax record in a tax tool's layout
+fn write_tax_record(csv: &mut CsvWriter, format: ExportFormat, record: &TaxRecord) -> Result<(), ExportError> {
+    let amount = |leg: &Option<(Decimal, String)>| Opt(leg.as_ref().map(|(amount, _)| *amount));
+    let currency = |leg: &Option<(Decimal, String)>| Opt(leg.as_ref().map(|(_, currency)| currency.clone()));
+    match format {
+        ExportFormat::CoinTracking => csv.row(&[
+            &record.event.cointracking_type(),
+            &amount(&record.received),
+            &currency(&record.received),
+            &amount(&record.sent),
+            &currency(&record.sent),
+            &amount(&record.fee),
+            &currency(&record.fee),
+            &"Polymarket",
+            &"",
+            &record.description,
+            &record.time.format("%d.%m.%Y %H:%M:%S"),
+            &record.reference,
+        ]),
+        _ => csv.row(&[
+            &record.time.format("%Y-%m-%d %H:%M:%S UTC"),
+            &amount(&record.sent),
+            &currency(&record.sent),
+            &amount(&record.received),
+            &currency(&record.received),
+            &amount(&record.fee),
+            &currency(&record.fee),
+            &"",
+            &"",
+            &record.event.koinly_label(),
+            &record.description,
+            &record.reference,
+        ]),
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct LedgerFeeRow {
+    id: Uuid,
+    created_at: DateTime<Utc>,
+    reason: String,
+    token: String,
+    /// Taken from the account (the entry's derived external leg)
+    external_delta: Decimal,
+}
+
+// ============================================================================
+// Service
+// ============================================================================
+
+/// History export service
+pub struct ExportService {
+    store: Arc<dyn ArchiveStore>,
+    /// History archive holding pruned trades (None when archiving is disabled)
+    archive: Option<Arc<dyn ArchiveStore>>,
+    /// Collateral token symbol, the quote currency of tax records
+    collateral: String,
+}
+
+impl ExportService {
+    pub fn new(store: Arc<dyn ArchiveStore>, archive: Option<Arc<dyn ArchiveStore>>, collateral: &str) -> Self {
+        Self {
+            store,
+            archive,
+            collateral: collateral.to_string(),
+        }
+    }
+
+    /// Queue an export for an account
+    pub async fn request(&self, pool: &PgPool, user_address: &str, req: &ExportRequest) -> Result<DataExport, ExportError> {
+        req.validate(Utc::now())?;
+        let user = user_address.to_lowercase();
+
+        let active: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM data_exports WHERE user_address = $1 AND status IN ('pending', 'running')",
+        )
+        .bind(&user)
+        .fetch_one(pool)
+        .await?;
+        if active >= MAX_ACTIVE_JOBS {
+            return Err(ExportError::TooManyActive(MAX_ACTIVE_JOBS));
+        }
+
+        let sql = format!(
+            r#"
+            INSERT INTO data_exports (user_address, kind, format, symbol, period, market_id, range_start, range_end)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING {JOB_COLUMNS}
+            "#
+        );
+        let job = sqlx::query_as(&sql)
+            .bind(&user)
+            .bind(req.kind.as_str())
+            .bind(req.format.as_str())
+            .bind(&req.symbol)
+            .bind(req.period.map(|p| p.to_str()))
+            .bind(req.market_id)
+            .bind(req.from)
+            .bind(req.to)
+            .fetch_one(pool)
+            .await?;
+        Ok(job)
+    }
+
+    /// The account's tax export of a calendar year in a format: an export
+    /// still running or completed after the year ended is returned as is,
+    /// otherwise a new one is queued. Returns the job and whether it is new.
+    pub async fn tax_export(
+        &self,
+        pool: &PgPool,
+        user_address: &str,
+        year: i32,
+        format: ExportFormat,
+    ) -> Result<(DataExport, bool), ExportError> {
+        let (from, to) = tax_year(year)?;
+        let sql = format!(
+            r#"
+            SELECT {JOB_COLUMNS} FROM data_exports
+            WHERE user_address = $1 AND kind = 'tax' AND format = $2 AND range_start = $3 AND range_end = $4
+              AND (status IN ('pending', 'running') OR (status = 'completed' AND completed_at >= range_end))
+            ORDER BY created_at DESC
+            LIMIT 1
+            "#
+        );
+        let existing: Option<DataExport> = sqlx::query_as(&sql)
+            .bind(user_address.to_lowercase())
+            .bind(format.as_str())
+            .bind(from)
+            .bind(to)
+            .fetch_optional(pool)
+            .await?;
+        if let Some(job) = existing {
+            return Ok((job, false));
+        }
+
+        let request = ExportRequest {
+            kind: ExportKind::Tax,
+            format,
+            from,
+            to,
+            symbol: None,
+            period: None,
+            market_id: None,
+        };
+        Ok((self.request(pool, user_address, &request).await?, true))
+    }
+
+    /// Look up one of an account's export jobs
+    pub async fn get(&self, pool: &PgPool, user_address: &str, id: Uuid) -> Result<DataExport, ExportError> {
+        let sql = format!("SELECT {JOB_COLUMNS} FROM data_exports WHERE id = $1 AND user_address = $2");
+        sqlx::query_as(&sql)
+            .bind(id)
+            .bind(user_address.to_lowercase())
+            .fetch_optional(pool)
+            .await?
+            .ok_or(ExportError::NotFound)
+    }
+
+    /// An account's export jobs, newest first
+    pub async fn list(&self, pool: &PgPool, user_address: &str, limit: i64) -> Result<Vec<DataExport>, ExportError> {
+        let sql = format!(
+            "SELECT {JOB_COLUMNS} FROM data_exports WHERE user_address = $1 ORDER BY created_at DESC LIMIT $2"
+        );
+        let jobs = sqlx::query_as(&sql)
+            .bind(user_address.to_lowercase())
+            .bind(limit)
+            .fetch_all(pool)
+            .await?;
+        Ok(jobs)
+    }
+
+    /// File of a completed export
+    pub async fn data(&self, pool: &PgPool, user_address: &str, id: Uuid) -> Result<(DataExport, Vec<u8>), ExportError> {
+        let job = self.get(pool, user_address, id).await?;
+        if job.status != ExportStatus::Completed.as_str() {
+            return Err(ExportError::NotReady);
+        }
+        let key = job.data_key.clone().ok_or(ExportError::NotReady)?;
+        let data = self.store.get(&key).await?.ok_or(ExportError::NotFound)?;
+        Ok((job, data))
+    }
+
+    /// Run queued exports; returns the number of jobs completed
+    pub async fn run_pending(&self, pool: &PgPool) -> Result<usize, ExportError> {
+        let mut completed = 0;
+        for _ in 0..MAX_JOBS_PER_CYCLE {
+            let Some(job) = Self::claim(pool).await? else {
+                break;
+            };
+            match self.generate(pool, &job).await {
+                Ok(rows) => {
+                    completed += 1;
+                    crate::metrics::record_data_export(&job.kind, ExportStatus::Completed.as_str());
+                    tracing::info!("Export {} ({} for {}) completed: {} rows", job.id, job.kind, job.user_address, rows);
+                }
+                Err(e) => {
+                    crate::metrics::record_data_export(&job.kind, ExportStatus::Failed.as_str());
+                    tracing::error!("Export {} ({} for {}) failed: {}", job.id, job.kind, job.user_address, e);
+                    sqlx::query("UPDATE data_exports SET status = 'failed', error = $1, completed_at = NOW() WHERE id = $2")
+                        .bind(e.to_string())
+                        .bind(job.id)
+                        .execute(pool)
+                        .await?;
+                }
+            }
+        }
+        Ok(completed)
+    }
+
+    /// Claim the oldest pending (or abandoned running) job
+    async fn claim(pool: &PgPool) -> Result<Option<DataExport>, ExportError> {
+        let sql = format!(
+            r#"
+            UPDATE data_exports SET status = 'running', started_at = NOW()
+            WHERE id = (
+                SELECT id FROM data_exports
+                WHERE status = 'pending'
+                   OR (status = 'running' AND started_at < NOW() - make_interval(mins => $1))
+                ORDER BY created_at
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING {JOB_COLUMNS}
+            "#
+        );
+        let job = sqlx::query_as(&sql)
+            .bind(STALE_JOB_MINUTES as i32)
+            .fetch_optional(pool)
+            .await?;
+        Ok(job)
+    }
+
+    /// Write the file of a job and mark it completed
+    async fn generate(&self, pool: &PgPool, job: &DataExport) -> Result<u64, ExportError> {
+        let csv = match job.kind.parse()? {
+            ExportKind::Trades => self.write_trades(pool, job).await?,
+            ExportKind::FundingSettlements => Self::write_funding(pool, job).await?,
+            ExportKind::Candles => Self::write_candles(pool, job).await?,
+            ExportKind::Tax => self.write_tax(pool, job).await?,
+        };
+        let rows = csv.rows();
+        let data = csv.into_bytes();
+        let sha256 = sha256_hex(&data);
+        let byte_size = data.len() as i64;
+        let extension = job.format.parse::<ExportFormat>().map(|f| f.extension()).unwrap_or("csv");
+        let key = format!("history/{}/{}.{}", job.user_address, job.id, extension);
+        self.store.put(&key, data).await?;
+
+        sqlx::query(
+            r#"
+            UPDATE data_exports SET
+                status = 'completed', row_count = $1, byte_size = $2, data_key = $3, data_sha256 = $4,
+                error = NULL, completed_at = NOW()
+            WHERE id = $5
+            "#,
+        )
+        .bind(rows as i64)
+        .bind(byte_size)
+        .bind(&key)
+        .bind(&sha256)
+        .bind(job.id)
+        .execute(pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// The account's trades, oldest first
+    async fn write_trades(&self, pool: &PgPool, job: &DataExport) -> Result<CsvWriter, ExportError> {
+        let mut csv = CsvWriter::new(TRADE_HEADER);
+        self.for_each_trade(pool, job, |trade, fill| write_trade(&mut csv, trade, fill)).await?;
+        Ok(csv)
+    }
+
+    /// Visit the account's fills in the job's range, oldest first: pruned
+    /// archive segments, then Postgres. A self-trade is visited once per role.
+    async fn for_each_trade(
+        &self,
+        pool: &PgPool,
+        job: &DataExport,
+        mut visit: impl FnMut(&ArchivedTrade, &TradeFill) -> Result<(), ExportError>,
+    ) -> Result<(), ExportError> {
+        let user = job.user_address.as_str();
+        // Attribution is written just after its trade: widen the range so
+        // fills at its edges are found
+        let attributions = attributions_by_fill(
+            PositionService::fill_attributions_between(
+                pool,
+                user,
+                job.range_start - Duration::hours(1),
+                job.range_end + Duration::hours(1),
+            )
+            .await?,
+        );
+        let mut visit_fills = |trade: &ArchivedTrade| {
+            for role in FillRole::of(user, &trade.maker_address, &trade.taker_address) {
+                let fill = TradeFill::of(trade, role, attributions.get(&(trade.id, role)));
+                visit(trade, &fill)?;
+            }
+            Ok::<_, ExportError>(())
+        };
+
+        let mut archived_ids = HashSet::new();
+        if let Some(archive) = &self.archive {
+            let archived = ArchiveService::trades_between(archive.as_ref(), job.range_start, job.range_end).await?;
+            for trade in archived.iter().filter(|t| {
+                (t.maker_address == user || t.taker_address == user) && job.market_id.is_none_or(|m| t.market_id == m)
+            }) {
+                archived_ids.insert(trade.id);
+                visit_fills(trade)?;
+            }
+        }
+
+        let mut rows = sqlx::query_as::<_, ArchivedTrade>(
+            r#"
+            SELECT id, market_id, outcome_id, share_type::text AS share_type, side::text AS side,
+                   maker_address, taker_address, price, amount, maker_fee, taker_fee,
+                   is_block_trade, created_at, exec_id
+            FROM trades
+            WHERE (maker_address = $1 OR taker_address = $1)
+              AND created_at >= $2 AND created_at < $3
+              AND ($4::uuid IS NULL OR market_id = $4)
+            ORDER BY created_at, id
+            "#,
+        )
+        .bind(user)
+        .bind(job.range_start)
+        .bind(job.range_end)
+        .bind(job.market_id)
+        .fetch(pool);
+        while let Some(trade) = rows.try_next().await? {
+            if !archived_ids.contains(&trade.id) {
+                visit_fills(&trade)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The account's trades, funding and ledger fees as tax records, oldest first
+    async fn write_tax(&self, pool: &PgPool, job: &DataExport) -> Result<CsvWriter, ExportError> {
+        let format: ExportFormat = job.format.parse()?;
+        let user = job.user_address.as_str();
+        let mut records: Vec<TaxRecord> = Vec::new();
+        let check_rows = |records: &Vec<TaxRecord>| {
+            if records.len() as u64 > MAX_ROWS {
+                Err(ExportError::TooManyRows(MAX_ROWS))
+            } else {
+                Ok(())
+            }
+        };
+
+        self.for_each_trade(pool, job, |trade, fill| {
+            records.extend(trade_tax_records(trade, fill, &self.collateral));
+            check_rows(&records)
+        })
+        .await?;
+
+        let mut funding = sqlx::query_as::<_, FundingRow>(
+            r#"
+            SELECT id, settled_at, symbol, funding_rate, mark_price, position_size, funding_fee,
+                   is_long, margin_mode, token
+            FROM funding_settlements
+            WHERE user_address = $1 AND settled_at >= $2 AND settled_at < $3
+            ORDER BY settled_at, id
+            "#,
+        )
+        .bind(user)
+        .bind(job.range_start)
+        .bind(job.range_end)
+        .fetch(pool);
+        while let Some(row) = funding.try_next().await? {
+            let token = row.token.as_deref().unwrap_or(&self.collateral);
+            records.extend(funding_tax_record(row.id, row.settled_at, &row.symbol, row.funding_fee, token));
+            check_rows(&records)?;
+        }
+        drop(funding);
+
+        let mut fees = sqlx::query_as::<_, LedgerFeeRow>(
+            r#"
+            SELECT id, created_at, reason, token, external_delta
+            FROM balance_ledger
+            WHERE user_address = $1 AND reason IN ('withdrawal_fee', 'liquidation_fee')
+              AND created_at >= $2 AND created_at < $3
+            ORDER BY created_at, id
+            "#,
+        )
+        .bind(user)
+        .bind(job.range_start)
+        .bind(job.range_end)
+        .fetch(pool);
+        while let Some(row) = fees.try_next().await? {
+            records.extend(fee_tax_record(row.id, row.created_at, &row.reason, row.external_delta, &row.token));
+            check_rows(&records)?;
+        }
+        drop(fees);
+
+        // Stable: same-time records keep trade, funding, fee order
+        records.sort_by_key(|r| r.time);
+        let mut csv = CsvWriter::new(tax_header(format));
+        for record in &records {
+            write_tax_record(&mut csv, format, record)?;
+        }
+        Ok(csv)
+    }
+
+    /// Funding applied to the account's positions, oldest first
+    async fn write_funding(pool: &PgPool, job: &DataExport) -> Result<CsvWriter, ExportError> {
+        let mut csv = CsvWriter::new(FUNDING_HEADER);
+        let market_prefix = job.market_id.map(|m| format!("{}:%", m));
+        let mut rows = sqlx::query_as::<_, FundingRow>(
+            r#"
+            SELECT id, settled_at, symbol, funding_rate, mark_price, position_size, funding_fee,
+                   is_long, margin_mode, token
+            FROM funding_settlements
+            WHERE user_address = $1
+              AND settled_at >= $2 AND settled_at < $3
+              AND ($4::text IS NULL OR symbol LIKE $4)
+            ORDER BY settled_at, id
+            "#,
+        )
+        .bind(&job.user_address)
+        .bind(job.range_start)
+        .bind(job.range_end)
+        .bind(market_prefix)
+        .fetch(pool);
+        while let Some(row) = rows.try_next().await? {
+            csv.row(&[
+                &row.id,
+                &row.settled_at.to_rfc3339(),
+                &row.symbol,
+                &row.funding_rate,
+                &Opt(row.mark_price),
+                &row.position_size,
+                &row.funding_fee,
+                &row.is_long,
+                &Opt(row.margin_mode),
+                &Opt(row.token),
+            ])?;
+        }
+        Ok(csv)
+    }
+
+    /// One symbol's candles of one period, oldest first
+    async fn write_candles(pool: &PgPool, job: &DataExport) -> Result<CsvWriter, ExportError> {
+        let symbol = job.symbol.as_deref().ok_or(ExportError::MissingSymbol)?;
+        let period_str = job.period.as_deref().ok_or(ExportError::MissingSymbol)?;
+        let period =
+            KlinePeriod::parse(period_str).ok_or_else(|| ExportError::InvalidPeriod(period_str.to_string()))?;
+
+        let mut csv = CsvWriter::new(CANDLE_HEADER);
+        let sql = format!(
+            r#"
+            SELECT symbol, bucket, open, high, low, close, volume, quote_volume, trade_count
+            FROM {}
+            WHERE symbol = $1 AND bucket >= $2 AND bucket < $3
+            ORDER BY bucket
+            "#,
+            period.table_name()
+        );
+        let mut rows = sqlx::query_as::<_, Kline>(&sql)
+            .bind(symbol.to_uppercase())
+            .bind(job.range_start)
+            .bind(job.range_end)
+            .fetch(pool);
+        while let Some(kline) = rows.try_next().await? {
+            csv.row(&[
+                &kline.open_time.to_rfc3339(),
+                &kline.symbol,
+                &kline.open,
+                &kline.high,
+                &kline.low,
+                &kline.close,
+                &kline.volume,
+                &kline.quote_volume,
+                &kline.trade_count,
+            ])?;
+        }
+        Ok(csv)
+    }
+}
+
+/// `[Jan 1 of year, Jan 1 of the next year)` in UTC
+pub fn tax_year(year: i32) -> Result<(DateTime<Utc>, DateTime<Utc>), ExportError> {
+    let start = |year: i32| {
+        chrono::NaiveDate::from_ymd_opt(year, 1, 1)
+            .and_then(|d| d.and_hms_opt(0, 0, 0))
+            .map(|t| t.and_utc())
+            .ok_or_else(|| ExportError::InvalidRange(format!("invalid year {}", year)))
+    };
+    Ok((start(year)?, start(year + 1)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::market::ShareType;
+    use rust_decimal_macros::dec;
+
+    fn request(kind: ExportKind, days: i64) -> ExportRequest {
+        let from = Utc::now() - Duration::days(days);
+        ExportRequest {
+            kind,
+            format: ExportFormat::Csv,
+            from,
+            to: from + Duration::days(days),
+            symbol: None,
+            period: None,
+            market_id: None,
+        }
+    }
+
+    #[test]
+    fn test_validates_requests() {
+        let now = Utc::now();
+        assert!(request(ExportKind::Trades, 30).validate(now).is_ok());
+        assert!(matches!(
+            request(ExportKind::Trades, MAX_RANGE_DAYS + 1).validate(now),
+            Err(ExportError::InvalidRange(_))
+        ));
+        assert!(matches!(request(ExportKind::Candles, 1).validate(now), Err(ExportError::MissingSymbol)));
+
+        let mut candles = request(ExportKind::Candles, 1);
+        candles.symbol = Some("BTCUSDT".to_string());
+        candles.period = Some(KlinePeriod::OneHour);
+        assert!(candles.validate(now).is_ok());
+
+        assert_eq!("CSV".parse::<ExportFormat>().unwrap(), ExportFormat::Csv);
+        assert!(matches!("parquet".parse::<ExportFormat>(), Err(ExportError::UnsupportedFormat(_))));
+    }
+
+    #[test]
+    fn test_writes_csv_from_the_account_side() {
+        let user = "0xmaker";
+        let trade = ArchivedTrade {
+            id: Uuid::new_v4(),
+            market_id: Uuid::new_v4(),
+            outcome_id: Uuid::new_v4(),
+            share_type: "yes".to_string(),
+            side: "buy".to_string(),
+            maker_address: user.to_string(),
+            taker_address: "0xtaker".to_string(),
+            price: dec!(0.55),
+            amount: dec!(10),
+            maker_fee: dec!(0.01),
+            taker_fee: dec!(0.02),
+            is_block_trade: false,
+            created_at: Utc::now(),
+            exec_id: None,
+        };
+
+        assert_eq!(FillRole::of(user, &trade.maker_address, &trade.taker_address), vec![FillRole::Maker]);
+        let fill = TradeFill::of(&trade, FillRole::Maker, None);
+        let mut csv = CsvWriter::new(TRADE_HEADER);
+        write_trade(&mut csv, &trade, &fill).unwrap();
+        assert_eq!(csv.rows(), 1);
+
+        let text = String::from_utf8(csv.into_bytes()).unwrap();
+        let lines: Vec<&str> = text.split("\r\n").collect();
+        assert_eq!(lines[0], TRADE_HEADER.join(","));
+        let fields: Vec<&str> = lines[1].split(',').collect();
+        assert_eq!(&fields[5..], &["sell", "maker", "0.55", "10", "0.01", "", "", ""]);
+    }
+
+    #[test]
+    fn test_writes_tax_records_with_positive_amounts() {
+        let user = "0xmaker";
+        let outcome_id = Uuid::parse_str("1a2b3c4d-0000-0000-0000-000000000000").unwrap();
+        let trade = ArchivedTrade {
+            id: Uuid::new_v4(),
+            market_id: Uuid::new_v4(),
+            outcome_id,
+            share_type: "yes".to_string(),
+            // Taker sold, so the maker bought
+            side: "sell".to_string(),
+            maker_address: user.to_string(),
+            taker_address: "0xtaker".to_string(),
+            price: dec!(0.25),
+            amount: dec!(10),
+            maker_fee: dec!(-0.01),
+            taker_fee: dec!(0.02),
+            is_block_trade: false,
+            created_at: Utc::now(),
+            exec_id: None,
+        };
+
+        let fill = TradeFill::of(&trade, FillRole::Maker, None);
+        let records = trade_tax_records(&trade, &fill, "USDC");
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].sent, Some((dec!(2.50), "USDC".to_string())));
+        assert_eq!(records[0].received, Some((dec!(10), "YES-1a2b3c4d".to_string())));
+        assert_eq!(records[0].fee, None);
+        assert_eq!(records[1].event, TaxEvent::Rebate);
+        assert_eq!(records[1].received, Some((dec!(0.01), "USDC".to_string())));
+
+        // Received funding is incoming; zero funding is no record
+        let funding = funding_tax_record(Uuid::new_v4(), Utc::now(), "s", dec!(-0.3), "USDC").unwrap();
+        assert_eq!(funding.event, TaxEvent::FundingReceived);
+        assert_eq!((funding.sent, funding.received), (None, Some((dec!(0.3), "USDC".to_string()))));
+        assert!(funding_tax_record(Uuid::new_v4(), Utc::now(), "s", Decimal::ZERO, "USDC").is_none());
+
+        let mut csv = CsvWriter::new(tax_header(ExportFormat::Koinly));
+        write_tax_record(&mut csv, ExportFormat::Koinly, &records[0]).unwrap();
+        let fee = fee_tax_record(Uuid::new_v4(), Utc::now(), "withdrawal_fee", dec!(1.5), "USDC").unwrap();
+        write_tax_record(&mut csv, ExportFormat::Koinly, &fee).unwrap();
+        let text = String::from_utf8(csv.into_bytes()).unwrap();
+        let lines: Vec<&str> = text.split("\r\n").collect();
+        assert_eq!(lines[0], KOINLY_HEADER.join(","));
+        let fields: Vec<&str> = lines[1].split(',').collect();
+        assert_eq!(&fields[1..10], &["2.50", "USDC", "10", "YES-1a2b3c4d", "", "", "", "", ""]);
+        let fields: Vec<&str> = lines[2].split(',').collect();
+        assert_eq!(&fields[1..10], &["1.5", "USDC", "", "", "", "", "", "", "cost"]);
+    }
+
+    fn attribution(trade: &ArchivedTrade, role: FillRole, side: &str, price: Decimal, fee: Decimal) -> FillAttribution {
+        FillAttribution {
+            trade_id: trade.id,
+            role: role.as_str().to_string(),
+            side: side.to_string(),
+            share_type: ShareType::No,
+            position_id: Uuid::new_v4(),
+            price,
+            amount: trade.amount,
+            fee,
+            fee_token: "USDT".to_string(),
+            realized_pnl: dec!(1.25),
+        }
+    }
+
+    fn yes_trade(side: &str, maker: &str, taker: &str) -> ArchivedTrade {
+        ArchivedTrade {
+            id: Uuid::new_v4(),
+            market_id: Uuid::new_v4(),
+            outcome_id: Uuid::parse_str("1a2b3c4d-0000-0000-0000-000000000000").unwrap(),
+            share_type: "yes".to_string(),
+            side: side.to_string(),
+            maker_address: maker.to_string(),
+            taker_address: taker.to_string(),
+            price: dec!(0.60),
+            amount: dec!(10),
+            maker_fee: dec!(0.01),
+            taker_fee: dec!(0.02),
+            is_block_trade: false,
+            created_at: Utc::now(),
+            exec_id: Some(7),
+        }
+    }
+
+    #[test]
+    fn test_mint_maker_fill_comes_from_its_attribution() {
+        // Taker buys YES at 0.60 against a maker buying NO at 0.40: a mint,
+        // so the maker bought too
+        let user = "0xmaker";
+        let trade = yes_trade("buy", user, "0xtaker");
+        let minted = attribution(&trade, FillRole::Maker, "buy", dec!(0.40), dec!(0.01));
+        let fill = TradeFill::of(&trade, FillRole::Maker, Some(&minted));
+        assert_eq!((fill.side.as_str(), fill.share_type.as_str(), fill.price), ("buy", "no", dec!(0.40)));
+
+        let mut csv = CsvWriter::new(TRADE_HEADER);
+        write_trade(&mut csv, &trade, &fill).unwrap();
+        let text = String::from_utf8(csv.into_bytes()).unwrap();
+        let fields: Vec<&str> = text.split("\r\n").nth(1).unwrap().split(',').collect();
+        assert_eq!(&fields[4..], &["no", "buy", "maker", "0.40", "10", "0.01", "7", "USDT", "1.25"]);
+
+        // Sends collateral at its own price, pays the fee in its fee token
+        let records = trade_tax_records(&trade, &fill, "USDC");
+        assert_eq!(records[0].sent, Some((dec!(4.00), "USDC".to_string())));
+        assert_eq!(records[0].received, Some((dec!(10), "NO-1a2b3c4d".to_string())));
+        assert_eq!(records[0].fee, Some((dec!(0.01), "USDT".to_string())));
+    }
+
+    #[test]
+    fn test_merge_maker_sells_with_the_taker() {
+        // Taker sells YES into a maker selling NO: a merge, both sell
+        let user = "0xmaker";
+        let trade = yes_trade("sell", user, "0xtaker");
+        let merged = attribution(&trade, FillRole::Maker, "sell", dec!(0.40), dec!(-0.02));
+        let fill = TradeFill::of(&trade, FillRole::Maker, Some(&merged));
+        assert_eq!(fill.side, "sell");
+        assert_eq!(fill.realized_pnl, Some(dec!(1.25)));
+
+        let records = trade_tax_records(&trade, &fill, "USDC");
+        assert_eq!(records[0].sent, Some((dec!(10), "NO-1a2b3c4d".to_string())));
+        assert_eq!(records[0].received, Some((dec!(4.00), "USDC".to_string())));
+        assert_eq!(records[1].event, TaxEvent::Rebate);
+        assert_eq!(records[1].received, Some((dec!(0.02), "USDT".to_string())));
+    }
+
+    #[test]
+    fn test_self_trade_is_one_fill_per_role() {
+        let user = "0xself";
+        let trade = yes_trade("buy", user, user);
+        let roles = FillRole::of(user, &trade.maker_address, &trade.taker_address);
+        assert_eq!(roles, vec![FillRole::Maker, FillRole::Taker]);
+
+        let attributions = attributions_by_fill(vec![
+            attribution(&trade, FillRole::Maker, "sell", dec!(0.60), dec!(0.01)),
+            FillAttribution {
+                realized_pnl: Decimal::ZERO,
+                ..attribution(&trade, FillRole::Taker, "buy", dec!(0.60), dec!(0.02))
+            },
+        ]);
+        let fills: Vec<TradeFill> = roles
+            .into_iter()
+            .map(|role| TradeFill::of(&trade, role, attributions.get(&(trade.id, role))))
+            .collect();
+        let summary: Vec<_> = fills.iter().map(|f| (f.role, f.side.as_str(), f.fee, f.realized_pnl)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (FillRole::Maker, "sell", dec!(0.01), Some(dec!(1.25))),
+                (FillRole::Taker, "buy", dec!(0.02), Some(Decimal::ZERO)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tax_formats_only_for_tax_exports() {
+        let mut tax = request(ExportKind::Tax, 30);
+        assert!(matches!(tax.validate(Utc::now()), Err(ExportError::FormatNotForKind(..))));
+        tax.format = ExportFormat::CoinTracking;
+        assert!(tax.validate(Utc::now()).is_ok());
+        assert_eq!("cointracking".parse::<ExportFormat>().unwrap(), ExportFormat::CoinTracking);
+
+        let (from, to) = tax_year(2025).unwrap();
+        assert_eq!(from.to_rfc3339(), "2025-01-01T00:00:00+00:00");
+        assert_eq!(to.to_rfc3339(), "2026-01-01T00:00:00+00:00");
+    }
+
+    #[test]
+    fn test_quotes_csv_fields() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
@@ -0,0 +1,407 @@
+//! Account Feature Entitlements
+//!
+//! Beta features (copy trading, RFQ, ...) are gated per account. A feature
+//! (`feature_flags`) is available to an account when any of these hold:
+//!
+//! - the flag is enabled for everyone (`enabled_for_all`)
+//! - an admin granted it to the account (`account_features`)
+//! - the flag is token-gated (`gate_token`) and the account holds at least
+//!   `gate_min_balance` raw units of that ERC-20 / ERC-721 contract
+//!
+//! Token gates are checked on chain with `balanceOf(owner)` through the
+//! configured RPC endpoint and cached for a short while; when token gating is
+//! disabled (or the RPC call fails) a gate simply does not entitle.
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use ethers::providers::{Http, Middleware, Provider};
+use ethers::types::{Address, Bytes, TransactionRequest};
+use futures::future::BoxFuture;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::chaos::{self, Fault};
+
+/// `balanceOf(address)` selector (ERC-20 and ERC-721)
+const BALANCE_OF_SELECTOR: [u8; 4] = [0x70, 0xa0, 0x82, 0x31];
+
+/// Feature errors
+#[derive(Debug, thiserror::Error)]
+pub enum FeatureError {
+    #[error("Unknown feature: {0}")]
+    UnknownFeature(String),
+
+    #[error("Invalid address: {0}")]
+    InvalidAddress(String),
+
+    #[error("Chain error: {0}")]
+    ChainError(String),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// A gated feature
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct FeatureFlag {
+    pub key: String,
+    pub description: Option<String>,
+    pub enabled_for_all: bool,
+    /// ERC-20 / ERC-721 contract whose holders are entitled
+    pub gate_token: Option<String>,
+    /// Minimum balance (raw token units) for the token gate
+    pub gate_min_balance: Decimal,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// How an account came to have a feature
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntitlementSource {
+    Global,
+    Grant,
+    Token,
+}
+
+/// A feature available to an account
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Entitlement {
+    pub feature: String,
+    pub source: EntitlementSource,
+}
+
+/// Features an account is entitled to, given its grants and gate token balances
+pub fn resolve_entitlements(
+    flags: &[FeatureFlag],
+    grants: &HashSet<String>,
+    token_balances: &HashMap<String, Decimal>,
+) -> Vec<Entitlement> {
+    flags
+        .iter()
+        .filter_map(|flag| {
+            let source = if flag.enabled_for_all {
+                EntitlementSource::Global
+            } else if grants.contains(&flag.key) {
+                EntitlementSource::Grant
+            } else if flag
+                .gate_token
+                .as_ref()
+                .and_then(|token| token_balances.get(&token.to_lowercase()))
+                .is_some_and(|balance| *balance >= flag.gate_min_balance)
+            {
+                EntitlementSource::Token
+            } else {
+                return None;
+            };
+            Some(Entitlement {
+                feature: flag.key.clone(),
+                source,
+            })
+        })
+        .collect()
+}
+
+/// Calldata for `balanceOf(owner)`
+pub fn balance_of_calldata(owner: Address) -> Vec<u8> {
+    let mut data = BALANCE_OF_SELECTOR.to_vec();
+    data.extend_from_slice(&[0u8; 12]);
+    data.extend_from_slice(owner.as_bytes());
+    data
+}
+
+/// Decode a `uint256` return value (saturating at the largest Decimal)
+pub fn decode_balance(output: &[u8]) -> Option<Decimal> {
+    if output.len() < 32 {
+        return None;
+    }
+    let value = ethers::types::U256::from_big_endian(&output[..32]);
+    Some(value.to_string().parse().unwrap_or(Decimal::MAX))
+}
+
+// ============================================================================
+// Token Balances
+// ============================================================================
+
+/// Reads token balances for token gates
+pub trait TokenBalanceChecker: Send + Sync {
+    /// Raw `balanceOf(owner)` of `token`
+    fn balance_of<'a>(&'a self, token: &'a str, owner: &'a str) -> BoxFuture<'a, Result<Decimal, FeatureError>>;
+}
+
+/// Token balances read over JSON-RPC (`eth_call`)
+pub struct RpcBalanceChecker {
+    provider: Provider<Http>,
+}
+
+impl RpcBalanceChecker {
+    pub fn new(rpc_url: &str) -> Result<Self, FeatureError> {
+        let provider = Provider::<Http>::try_from(rpc_url).map_err(|e| FeatureError::ChainError(e.to_string()))?;
+        Ok(Self { provider })
+    }
+}
+
+impl TokenBalanceChecker for RpcBalanceChecker {
+    fn balance_of<'a>(&'a self, token: &'a str, owner: &'a str) -> BoxFuture<'a, Result<Decimal, FeatureError>> {
+        Box::pin(async move {
+            let token: Address = token.parse().map_err(|_| FeatureError::InvalidAddress(token.to_string()))?;
+            let owner: Address = owner.parse().map_err(|_| FeatureError::InvalidAddress(owner.to_string()))?;
+            if chaos::fails(Fault::RpcError) {
+                return Err(FeatureError::ChainError("injected RPC error".to_string()));
+            }
+
+            let call = TransactionRequest::new().to(token).data(Bytes::from(balance_of_calldata(owner)));
+            let output = self
+                .provider
+                .call(&call.into(), None)
+                .await
+                .map_err(|e| FeatureError::ChainError(e.to_string()))?;
+            decode_balance(&output).ok_or_else(|| FeatureError::ChainError("malformed balanceOf result".to_string()))
+        })
+    }
+}
+
+// ============================================================================
+// Service
+// ============================================================================
+
+/// Feature entitlement lookups (with cached token gate balances)
+pub struct FeatureService {
+    checker: Option<Arc<dyn TokenBalanceChecker>>,
+    balance_cache: DashMap<(String, String), (Decimal, Instant)>,
+    cache_ttl: Duration,
+}
+
+impl FeatureService {
+    /// `checker` None disables token gates
+    pub fn new(checker: Option<Arc<dyn TokenBalanceChecker>>, cache_ttl: Duration) -> Self {
+        Self {
+            checker,
+            balance_cache: DashMap::new(),
+            cache_ttl,
+        }
+    }
+
+    /// All feature flags
+    pub async fn list_flags(pool: &PgPool) -> Result<Vec<FeatureFlag>, FeatureError> {
+        let flags = sqlx::query_as(
+            r#"
+            SELECT key, description, enabled_for_all, gate_token, gate_min_balance, updated_at
+            FROM feature_flags
+            ORDER BY key
+            "#,
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(flags)
+    }
+
+    /// Create or update a feature flag
+    pub async fn upsert_flag(
+        pool: &PgPool,
+        key: &str,
+        description: Option<&str>,
+        enabled_for_all: bool,
+        gate_token: Option<&str>,
+        gate_min_balance: Decimal,
+    ) -> Result<FeatureFlag, FeatureError> {
+        if let Some(token) = gate_token {
+            token.parse::<Address>().map_err(|_| FeatureError::InvalidAddress(token.to_string()))?;
+        }
+
+        let flag = sqlx::query_as(
+            r#"
+            INSERT INTO feature_flags (key, description, enabled_for_all, gate_token, gate_min_balance)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (key) DO UPDATE SET
+                description = $2,
+                enabled_for_all = $3,
+                gate_token = $4,
+                gate_min_balance = $5,
+                updated_at = NOW()
+            RETURNING key, description, enabled_for_all, gate_token, gate_min_balance, updated_at
+            "#,
+        )
+        .bind(key)
+        .bind(description)
+        .bind(enabled_for_all)
+        .bind(gate_token.map(str::to_lowercase))
+        .bind(gate_min_balance)
+        .fetch_one(pool)
+        .await?;
+        Ok(flag)
+    }
+
+    /// Grant a feature to an account
+    pub async fn grant(pool: &PgPool, feature: &str, user_address: &str, granted_by: &str) -> Result<(), FeatureError> {
+        user_address.parse::<Address>().map_err(|_| FeatureError::InvalidAddress(user_address.to_string()))?;
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO account_features (user_address, feature, granted_by)
+            SELECT $1, key, $3 FROM feature_flags WHERE key = $2
+            ON CONFLICT (user_address, feature) DO NOTHING
+            "#,
+        )
+        .bind(user_address.to_lowercase())
+        .bind(feature)
+        .bind(granted_by.to_lowercase())
+        .execute(pool)
+        .await?;
+
+        if result.rows_affected() == 0 && !Self::flag_exists(pool, feature).await? {
+            return Err(FeatureError::UnknownFeature(feature.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Revoke a granted feature
+    pub async fn revoke(pool: &PgPool, feature: &str, user_address: &str) -> Result<bool, FeatureError> {
+        let result = sqlx::query("DELETE FROM account_features WHERE user_address = $1 AND feature = $2")
+            .bind(user_address.to_lowercase())
+            .bind(feature)
+            .execute(pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn flag_exists(pool: &PgPool, feature: &str) -> Result<bool, FeatureError> {
+        let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM feature_flags WHERE key = $1)")
+            .bind(feature)
+            .fetch_one(pool)
+            .await?;
+        Ok(exists)
+    }
+
+    /// Cached gate token balance; None when unknown (gating disabled or RPC failure)
+    async fn token_balance(&self, token: &str, owner: &str) -> Option<Decimal> {
+        let checker = self.checker.as_ref()?;
+        let key = (token.to_lowercase(), owner.to_lowercase());
+        if let Some(entry) = self.balance_cache.get(&key) {
+            if entry.1.elapsed() < self.cache_ttl {
+                return Some(entry.0);
+            }
+        }
+
+        match checker.balance_of(&key.0, &key.1).await {
+            Ok(balance) => {
+                self.balance_cache.insert(key, (balance, Instant::now()));
+                Some(balance)
+            }
+            Err(e) => {
+                tracing::warn!("Token gate check of {} for {} failed: {}", key.0, key.1, e);
+                None
+            }
+        }
+    }
+
+    /// Features available to an account
+    pub async fn entitlements(&self, pool: &PgPool, user_address: &str) -> Result<Vec<Entitlement>, FeatureError> {
+        let user_address = user_address.to_lowercase();
+        let flags = Self::list_flags(pool).await?;
+        let grants: HashSet<String> =
+            sqlx::query_scalar::<_, String>("SELECT feature FROM account_features WHERE user_address = $1")
+                .bind(&user_address)
+                .fetch_all(pool)
+                .await?
+                .into_iter()
+                .collect();
+
+        // Only look up tokens for gates not already satisfied otherwise
+        let mut token_balances = HashMap::new();
+        for flag in &flags {
+            let Some(token) = flag.gate_token.as_ref() else {
+                continue;
+            };
+            if flag.enabled_for_all || grants.contains(&flag.key) || token_balances.contains_key(token) {
+                continue;
+            }
+            if let Some(balance) = self.token_balance(token, &user_address).await {
+                token_balances.insert(token.to_lowercase(), balance);
+            }
+        }
+
+        Ok(resolve_entitlements(&flags, &grants, &token_balances))
+    }
+
+    /// Whether an account has a feature (unknown features are not available)
+    pub async fn has_feature(&self, pool: &PgPool, user_address: &str, feature: &str) -> Result<bool, FeatureError> {
+        Ok(self
+            .entitlements(pool, user_address)
+            .await?
+            .iter()
+            .any(|e| e.feature == feature))
+    }
+
+    /// Feature required to subscribe to a WebSocket channel, if any
+    pub fn channel_feature(channel: &str) -> Option<&'static str> {
+        match channel {
+            "rfq" => Some("rfq"),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn flag(key: &str, enabled_for_all: bool, gate_token: Option<&str>, min: Decimal) -> FeatureFlag {
+        FeatureFlag {
+            key: key.to_string(),
+            description: None,
+            enabled_for_all,
+            gate_token: gate_token.map(str::to_string),
+            gate_min_balance: min,
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_resolve_entitlements() {
+        let nft = "0x00000000000000000000000000000000000000aa";
+        let flags = vec![
+            flag("rfq", true, None, dec!(1)),
+            flag("copy_trading", false, Some(nft), dec!(1)),
+            flag("vaults_v2", false, None, dec!(1)),
+            flag("whale_desk", false, Some(nft), dec!(5)),
+        ];
+        let grants: HashSet<String> = ["vaults_v2".to_string()].into();
+        let balances: HashMap<String, Decimal> = [(nft.to_string(), dec!(2))].into();
+
+        let entitlements = resolve_entitlements(&flags, &grants, &balances);
+        let sources: Vec<(&str, EntitlementSource)> =
+            entitlements.iter().map(|e| (e.feature.as_str(), e.source)).collect();
+        assert_eq!(
+            sources,
+            vec![
+                ("rfq", EntitlementSource::Global),
+                ("copy_trading", EntitlementSource::Token),
+                ("vaults_v2", EntitlementSource::Grant),
+            ]
+        );
+
+        // Without a known balance the token gate does not entitle
+        let entitlements = resolve_entitlements(&flags, &grants, &HashMap::new());
+        assert!(!entitlements.iter().any(|e| e.feature == "copy_trading"));
+    }
+
+    #[test]
+    fn test_balance_of_encoding() {
+        let owner: Address = "0x1111111111111111111111111111111111111111".parse().unwrap();
+        let calldata = balance_of_calldata(owner);
+        assert_eq!(calldata.len(), 36);
+        assert_eq!(&calldata[..4], &BALANCE_OF_SELECTOR);
+        assert_eq!(&calldata[16..], owner.as_bytes());
+
+        let mut output = [0u8; 32];
+        output[30] = 0x01;
+        output[31] = 0x2c;
+        assert_eq!(decode_balance(&output), Some(dec!(300)));
+        assert_eq!(decode_balance(&output[..8]), None);
+    }
+}
@@ -0,0 +1,350 @@
+//! VIP Fee Tiers
+//!
+//! Trading fees follow the market's fee schedule (`fee_tiers`); the account's
+//! VIP tier then scales what each side of a fill pays:
+//!
+//! - takers pay `taker_fee_pct` percent of the schedule's taker fee
+//! - makers pay `maker_fee_pct` percent of the schedule's maker fee; a
+//!   negative percentage is a rebate paid to the maker
+//!
+//! An account's tier is the highest tier whose `min_volume` its taker volume
+//! over the last `fee_volume_window_days` reaches, unless an admin pinned a
+//! tier for the account (`user_fee_overrides`). Tiers are applied when trades
+//! are persisted, so every fill is charged at the tier the account holds at
+//! that moment. Volumes and overrides are cached for `fee_volume_cache_secs`.
+
+use dashmap::DashMap;
+use ethers::types::Address;
+use parking_lot::RwLock;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::time::{Duration, Instant};
+
+use crate::services::matching::TradeEvent;
+
+/// Fee tier errors
+#[derive(Debug, thiserror::Error)]
+pub enum FeeError {
+    #[error("Unknown fee tier level: {0}")]
+    UnknownTier(i32),
+
+    #[error("Invalid fee tier: {0}")]
+    InvalidTier(String),
+
+    #[error("Invalid address: {0}")]
+    InvalidAddress(String),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// A VIP fee tier
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct VipTier {
+    #[serde(default)]
+    pub level: i32,
+    pub name: String,
+    /// Taker volume (collateral notional) over the window that qualifies
+    pub min_volume: Decimal,
+    /// Percent of the schedule's taker fee charged
+    pub taker_fee_pct: i32,
+    /// Percent of the schedule's maker fee charged (negative = rebate)
+    pub maker_fee_pct: i32,
+}
+
+impl VipTier {
+    /// Charges the market schedule unchanged; used when no tiers are configured
+    fn base() -> Self {
+        Self {
+            level: 0,
+            name: "VIP0".to_string(),
+            min_volume: Decimal::ZERO,
+            taker_fee_pct: 100,
+            maker_fee_pct: 100,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), FeeError> {
+        if self.level < 0 {
+            return Err(FeeError::InvalidTier("level must not be negative".to_string()));
+        }
+        if self.name.trim().is_empty() {
+            return Err(FeeError::InvalidTier("name must not be empty".to_string()));
+        }
+        if self.min_volume < Decimal::ZERO {
+            return Err(FeeError::InvalidTier("min_volume must not be negative".to_string()));
+        }
+        if !(0..=100).contains(&self.taker_fee_pct) {
+            return Err(FeeError::InvalidTier("taker_fee_pct must be 0 to 100".to_string()));
+        }
+        if !(-100..=100).contains(&self.maker_fee_pct) {
+            return Err(FeeError::InvalidTier("maker_fee_pct must be -100 to 100".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Taker fee for a fill whose schedule fee is `schedule_fee`
+    pub fn taker_fee(&self, schedule_fee: Decimal) -> Decimal {
+        schedule_fee * Decimal::from(self.taker_fee_pct) / Decimal::ONE_HUNDRED
+    }
+
+    /// Maker fee (negative = rebate) for a fill whose schedule fee is `schedule_fee`
+    pub fn maker_fee(&self, schedule_fee: Decimal) -> Decimal {
+        schedule_fee * Decimal::from(self.maker_fee_pct) / Decimal::ONE_HUNDRED
+    }
+}
+
+/// Highest tier `volume` qualifies for (tiers ordered by level)
+pub fn tier_for_volume(tiers: &[VipTier], volume: Decimal) -> Option<&VipTier> {
+    tiers.iter().filter(|tier| volume >= tier.min_volume).max_by_key(|tier| tier.level)
+}
+
+/// An account's current fee tier
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountFeeTier {
+    #[serde(flatten)]
+    pub tier: VipTier,
+    /// Taker volume over the window
+    pub volume: Decimal,
+    pub window_days: i64,
+    /// Pinned by an admin rather than derived from volume
+    pub overridden: bool,
+    /// Next tier up and the volume it needs (None at the top tier or when pinned)
+    pub next_tier: Option<VipTier>,
+}
+
+/// Cached volume and override of an account
+struct AccountFees {
+    volume: Decimal,
+    override_level: Option<i32>,
+    loaded_at: Instant,
+}
+
+/// VIP tier lookups (tiers held in memory, account volumes cached)
+pub struct FeeService {
+    tiers: RwLock<Vec<VipTier>>,
+    accounts: DashMap<String, AccountFees>,
+    window_days: i64,
+    cache_ttl: Duration,
+}
+
+impl FeeService {
+    pub fn new(window_days: i64, cache_ttl: Duration) -> Self {
+        Self {
+            tiers: RwLock::new(Vec::new()),
+            accounts: DashMap::new(),
+            window_days: window_days.max(1),
+            cache_ttl,
+        }
+    }
+
+    /// Load tiers from the database; returns the number of tiers
+    pub async fn load_tiers(&self, pool: &PgPool) -> Result<usize, FeeError> {
+        let tiers: Vec<VipTier> = sqlx::query_as(
+            "SELECT level, name, min_volume, taker_fee_pct, maker_fee_pct FROM vip_fee_tiers ORDER BY level",
+        )
+        .fetch_all(pool)
+        .await?;
+        let count = tiers.len();
+        *self.tiers.write() = tiers;
+        Ok(count)
+    }
+
+    /// Configured tiers, by level
+    pub fn tiers(&self) -> Vec<VipTier> {
+        self.tiers.read().clone()
+    }
+
+    /// Create or update a tier
+    pub async fn upsert_tier(&self, pool: &PgPool, tier: &VipTier) -> Result<VipTier, FeeError> {
+        tier.validate()?;
+        let tier: VipTier = sqlx::query_as(
+            r#"
+            INSERT INTO vip_fee_tiers (level, name, min_volume, taker_fee_pct, maker_fee_pct)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (level) DO UPDATE SET
+                name = $2,
+                min_volume = $3,
+                taker_fee_pct = $4,
+                maker_fee_pct = $5,
+                updated_at = NOW()
+            RETURNING level, name, min_volume, taker_fee_pct, maker_fee_pct
+            "#,
+        )
+        .bind(tier.level)
+        .bind(tier.name.trim())
+        .bind(tier.min_volume)
+        .bind(tier.taker_fee_pct)
+        .bind(tier.maker_fee_pct)
+        .fetch_one(pool)
+        .await?;
+        self.load_tiers(pool).await?;
+        Ok(tier)
+    }
+
+    /// Pin an account to a tier
+    pub async fn set_override(
+        &self,
+        pool: &PgPool,
+        user_address: &str,
+        level: i32,
+        note: Option<&str>,
+        set_by: &str,
+    ) -> Result<VipTier, FeeError> {
+        user_address.parse::<Address>().map_err(|_| FeeError::InvalidAddress(user_address.to_string()))?;
+        let tier = self.tier(level).ok_or(FeeError::UnknownTier(level))?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO user_fee_overrides (user_address, level, note, set_by)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (user_address) DO UPDATE SET
+                level = $2,
+                note = $3,
+                set_by = $4,
+                updated_at = NOW()
+            "#,
+        )
+        .bind(user_address.to_lowercase())
+        .bind(level)
+        .bind(note)
+        .bind(set_by.to_lowercase())
+        .execute(pool)
+        .await?;
+        self.accounts.remove(&user_address.to_lowercase());
+        Ok(tier)
+    }
+
+    /// Remove an account's pinned tier
+    pub async fn clear_override(&self, pool: &PgPool, user_address: &str) -> Result<bool, FeeError> {
+        let result = sqlx::query("DELETE FROM user_fee_overrides WHERE user_address = $1")
+            .bind(user_address.to_lowercase())
+            .execute(pool)
+            .await?;
+        self.accounts.remove(&user_address.to_lowercase());
+        Ok(result.rows_affected() > 0)
+    }
+
+    fn tier(&self, level: i32) -> Option<VipTier> {
+        self.tiers.read().iter().find(|tier| tier.level == level).cloned()
+    }
+
+    /// Window taker volume and pinned tier of an account (cached)
+    async fn account_fees(&self, pool: &PgPool, user_address: &str) -> Result<(Decimal, Option<i32>), FeeError> {
+        let user_address = user_address.to_lowercase();
+        if let Some(entry) = self.accounts.get(&user_address) {
+            if entry.loaded_at.elapsed() < self.cache_ttl {
+                return Ok((entry.volume, entry.override_level));
+            }
+        }
+
+        let volume: Decimal = sqlx::query_scalar(
+            r#"
+            SELECT COALESCE(SUM(price * amount), 0)
+            FROM trades
+            WHERE taker_address = $1 AND created_at > NOW() - make_interval(days => $2)
+            "#,
+        )
+        .bind(&user_address)
+        .bind(self.window_days as i32)
+        .fetch_one(pool)
+        .await?;
+        let override_level: Option<i32> =
+            sqlx::query_scalar("SELECT level FROM user_fee_overrides WHERE user_address = $1")
+                .bind(&user_address)
+                .fetch_optional(pool)
+                .await?;
+
+        self.accounts.insert(
+            user_address,
+            AccountFees {
+                volume,
+                override_level,
+                loaded_at: Instant::now(),
+            },
+        );
+        Ok((volume, override_level))
+    }
+
+    /// Current fee tier of an account
+    pub async fn account_tier(&self, pool: &PgPool, user_address: &str) -> Result<AccountFeeTier, FeeError> {
+        let (volume, override_level) = self.account_fees(pool, user_address).await?;
+        let tiers = self.tiers();
+
+        let pinned = override_level.and_then(|level| tiers.iter().find(|tier| tier.level == level));
+        let tier = pinned
+            .or_else(|| tier_for_volume(&tiers, volume))
+            .cloned()
+            .unwrap_or_else(VipTier::base);
+        let next_tier = if pinned.is_some() {
+            None
+        } else {
+            tiers.iter().filter(|t| t.level > tier.level && t.min_volume > volume).min_by_key(|t| t.level).cloned()
+        };
+
+        Ok(AccountFeeTier {
+            tier,
+            volume,
+            window_days: self.window_days,
+            overridden: pinned.is_some(),
+            next_tier,
+        })
+    }
+
+    /// Replace a trade's schedule fees with what its maker and taker pay at
+    /// their current tiers, and count the fill towards the taker's volume
+    pub async fn apply(&self, pool: &PgPool, trade: &mut TradeEvent) -> Result<(), FeeError> {
+        let taker = self.account_tier(pool, &trade.taker_address).await?;
+        let maker = self.account_tier(pool, &trade.maker_address).await?;
+        trade.taker_fee = taker.tier.taker_fee(trade.taker_fee);
+        trade.maker_fee = maker.tier.maker_fee(trade.maker_fee);
+
+        if let Some(mut entry) = self.accounts.get_mut(&trade.taker_address.to_lowercase()) {
+            entry.volume += trade.price * trade.amount;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn tier(level: i32, min_volume: Decimal, taker_fee_pct: i32, maker_fee_pct: i32) -> VipTier {
+        VipTier {
+            level,
+            name: format!("VIP{}", level),
+            min_volume,
+            taker_fee_pct,
+            maker_fee_pct,
+        }
+    }
+
+    #[test]
+    fn test_volume_picks_highest_qualifying_tier() {
+        let tiers = vec![
+            tier(0, dec!(0), 100, 100),
+            tier(1, dec!(100000), 90, 80),
+            tier(2, dec!(1000000), 80, -20),
+        ];
+        assert_eq!(tier_for_volume(&tiers, dec!(0)).unwrap().level, 0);
+        assert_eq!(tier_for_volume(&tiers, dec!(99999.99)).unwrap().level, 0);
+        assert_eq!(tier_for_volume(&tiers, dec!(100000)).unwrap().level, 1);
+        assert_eq!(tier_for_volume(&tiers, dec!(5000000)).unwrap().level, 2);
+        assert!(tier_for_volume(&tiers[1..], dec!(10)).is_none());
+    }
+
+    #[test]
+    fn test_tiers_scale_schedule_fees_and_rebate_makers() {
+        let vip = tier(2, dec!(1000000), 80, -20);
+        assert!(vip.validate().is_ok());
+        assert_eq!(vip.taker_fee(dec!(1.5)), dec!(1.2));
+        assert_eq!(vip.maker_fee(dec!(0.5)), dec!(-0.1));
+
+        assert!(tier(1, dec!(0), 101, 0).validate().is_err());
+        assert!(tier(1, dec!(0), 50, -101).validate().is_err());
+        assert!(tier(1, dec!(-1), 50, 50).validate().is_err());
+    }
+}
@@ -0,0 +1,841 @@
+//! Funding Settlement
+//!
+//! Applies stored funding rates to open positions once their funding time
+//! arrives. Rates live in `funding_rates`, keyed by outcome
+//! (`{market_id}:{outcome_id}`); each unsettled rate whose `next_funding_time`
+//! has passed is settled once against every non-zero holding of that outcome:
+//!
+//! - funding fee = signed amount * mark price * rate, so with a positive rate
+//!   longs pay and shorts receive (and the reverse for a negative rate)
+//! - `cross` positions: the fee is debited from / credited to the collateral
+//!   balance
+//! - `isolated` positions: the fee is folded into the position's cost basis
+//!   (`avg_cost`); only the part the cost basis cannot absorb touches the
+//!   balance
+//!
+//! Each application is recorded in `funding_settlements` and pushed to the
+//! position owner as a `funding` event on their `positions` channel. The rate
+//! row is locked and marked settled in the same transaction, so concurrent
+//! settlers never apply a rate twice.
+//!
+//! Rates that fall due while their market is outside its trading session are
+//! deferred to the next session open rather than charged across the gap.
+//!
+//! Each settled rate is also compared against the symbol's clamp bounds in
+//! `market_funding_config`. A run of consecutive clamped intervals on the same
+//! side is tracked there, and once it reaches the configured length a
+//! `ClampAlert` is recorded in `funding_clamp_alerts` and returned to the
+//! caller (and again every further run of that length).
+//!
+//! Funding parameters are changed through `update_config` only, which writes
+//! the old and new values to `funding_parameter_changes` in the same
+//! transaction as the change itself.
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::services::ledger::{BalanceChange, LedgerReason, LedgerService};
+use crate::services::matching::MatchingEngine;
+use crate::services::position::MarginMode;
+
+/// Maximum number of rates settled per cycle
+const MAX_RATES_PER_CYCLE: usize = 100;
+
+/// Funding settlement errors
+#[derive(Debug, thiserror::Error)]
+pub enum FundingError {
+    #[error("Invalid funding parameters: {0}")]
+    InvalidParameters(String),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// A funding payment applied to one position
+#[derive(Debug, Clone, Serialize)]
+pub struct FundingSettlement {
+    pub id: Uuid,
+    pub funding_rate_id: Uuid,
+    pub position_id: Uuid,
+    pub market_id: Uuid,
+    pub outcome_id: Uuid,
+    pub share_type: String,
+    pub funding_rate: Decimal,
+    pub mark_price: Decimal,
+    /// Signed holding (negative = short)
+    pub position_size: Decimal,
+    /// Paid by the position (negative = received)
+    pub funding_fee: Decimal,
+    pub margin_mode: MarginMode,
+    pub token: String,
+    pub settled_at: DateTime<Utc>,
+}
+
+/// Funding notification for a position owner
+#[derive(Debug, Clone)]
+pub struct FundingEvent {
+    /// Lowercase address of the position owner
+    pub user_address: String,
+    pub settlement: FundingSettlement,
+}
+
+/// How a funding fee lands on a position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FundingApplication {
+    /// Paid by the position (negative = received)
+    pub fee: Decimal,
+    /// Position average cost after funding
+    pub avg_cost: Decimal,
+    /// Change to the collateral balance
+    pub balance_delta: Decimal,
+}
+
+impl FundingApplication {
+    /// Part of the fee settled against the balance (negative = received).
+    /// This is what a position's `funding_paid` records: funding absorbed
+    /// into the cost basis is already in its realized PnL.
+    pub fn balance_fee(&self) -> Decimal {
+        -self.balance_delta
+    }
+}
+
+/// Funding paid by a signed holding of `amount` shares (negative = received)
+pub fn funding_fee(amount: Decimal, mark_price: Decimal, rate: Decimal) -> Decimal {
+    amount * mark_price * rate
+}
+
+/// Apply a funding rate to a holding under its margin mode
+///
+/// Isolated positions absorb the fee into their cost basis; if that would push
+/// the average cost below zero it is floored at zero and the remainder settles
+/// against the balance instead.
+pub fn apply_funding(
+    mode: MarginMode,
+    amount: Decimal,
+    avg_cost: Decimal,
+    mark_price: Decimal,
+    rate: Decimal,
+) -> FundingApplication {
+    let fee = funding_fee(amount, mark_price, rate);
+
+    if mode == MarginMode::Cross || amount.is_zero() {
+        return FundingApplication {
+            fee,
+            avg_cost,
+            balance_delta: -fee,
+        };
+    }
+
+    let cost_basis = amount * avg_cost + fee;
+    let adjusted = cost_basis / amount;
+    if adjusted < Decimal::ZERO {
+        FundingApplication {
+            fee,
+            avg_cost: Decimal::ZERO,
+            balance_delta: -cost_basis,
+        }
+    } else {
+        FundingApplication {
+            fee,
+            avg_cost: adjusted,
+            balance_delta: Decimal::ZERO,
+        }
+    }
+}
+
+/// Parse a funding symbol (`{market_id}:{outcome_id}`)
+pub fn parse_symbol(symbol: &str) -> Option<(Uuid, Uuid)> {
+    let (market_id, outcome_id) = symbol.split_once(':')?;
+    Some((market_id.parse().ok()?, outcome_id.parse().ok()?))
+}
+
+/// Clamp bound a funding rate sat on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClampSide {
+    /// Rate at or above `max_funding_rate`
+    Cap,
+    /// Rate at or below `min_funding_rate`
+    Floor,
+}
+
+impl ClampSide {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ClampSide::Cap => "cap",
+            ClampSide::Floor => "floor",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "cap" => Some(ClampSide::Cap),
+            "floor" => Some(ClampSide::Floor),
+            _ => None,
+        }
+    }
+}
+
+/// Which clamp bound (if any) a rate is pinned to
+pub fn clamp_side(rate: Decimal, min_rate: Decimal, max_rate: Decimal) -> Option<ClampSide> {
+    if rate >= max_rate {
+        Some(ClampSide::Cap)
+    } else if rate <= min_rate {
+        Some(ClampSide::Floor)
+    } else {
+        None
+    }
+}
+
+/// Length of the clamp run after an interval landed on `side`
+///
+/// The run continues only while the rate stays on the same bound; flipping
+/// from cap to floor starts a new run.
+pub fn next_clamp_streak(prev_side: Option<ClampSide>, prev_streak: u32, side: Option<ClampSide>) -> u32 {
+    match side {
+        None => 0,
+        Some(side) if prev_side == Some(side) => prev_streak.saturating_add(1),
+        Some(_) => 1,
+    }
+}
+
+/// Whether a clamp run of `streak` intervals should raise an alert
+///
+/// Fires when the run reaches `threshold` and again every `threshold`
+/// intervals after that, so a persistently pinned market keeps alerting
+/// without alerting on every settlement. A zero threshold disables alerts.
+pub fn should_alert(streak: u32, threshold: u32) -> bool {
+    threshold > 0 && streak > 0 && streak.is_multiple_of(threshold)
+}
+
+/// Funding rate pinned to a clamp bound for too many consecutive intervals
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct ClampAlert {
+    pub id: Uuid,
+    pub symbol: String,
+    pub funding_rate_id: Option<Uuid>,
+    pub side: String,
+    pub streak: i32,
+    pub funding_rate: Decimal,
+    pub bound: Decimal,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Per-symbol funding parameters
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct FundingConfig {
+    pub symbol: String,
+    pub funding_interval_hours: i32,
+    pub max_funding_rate: Decimal,
+    pub min_funding_rate: Decimal,
+    pub impact_pool_size: Decimal,
+    pub clamp_side: Option<String>,
+    pub clamp_streak: i32,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Funding parameter update; omitted fields keep their current value
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FundingParamsUpdate {
+    pub funding_interval_hours: Option<i32>,
+    pub max_funding_rate: Option<Decimal>,
+    pub min_funding_rate: Option<Decimal>,
+    pub impact_pool_size: Option<Decimal>,
+}
+
+/// The governed funding parameters of one symbol
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct FundingParams {
+    pub funding_interval_hours: i32,
+    pub max_funding_rate: Decimal,
+    pub min_funding_rate: Decimal,
+    pub impact_pool_size: Decimal,
+}
+
+impl Default for FundingParams {
+    /// Column defaults of `market_funding_config`
+    fn default() -> Self {
+        Self {
+            funding_interval_hours: 8,
+            max_funding_rate: Decimal::new(1, 2),
+            min_funding_rate: Decimal::new(-1, 2),
+            impact_pool_size: Decimal::ZERO,
+        }
+    }
+}
+
+impl FundingParams {
+    /// Apply an update on top of these parameters
+    pub fn with(self, update: &FundingParamsUpdate) -> Self {
+        Self {
+            funding_interval_hours: update.funding_interval_hours.unwrap_or(self.funding_interval_hours),
+            max_funding_rate: update.max_funding_rate.unwrap_or(self.max_funding_rate),
+            min_funding_rate: update.min_funding_rate.unwrap_or(self.min_funding_rate),
+            impact_pool_size: update.impact_pool_size.unwrap_or(self.impact_pool_size),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), FundingError> {
+        if self.funding_interval_hours <= 0 {
+            return Err(FundingError::InvalidParameters(
+                "funding_interval_hours must be positive".to_string(),
+            ));
+        }
+        if self.min_funding_rate > self.max_funding_rate {
+            return Err(FundingError::InvalidParameters(
+                "min_funding_rate must not exceed max_funding_rate".to_string(),
+            ));
+        }
+        if self.impact_pool_size < Decimal::ZERO {
+            return Err(FundingError::InvalidParameters(
+                "impact_pool_size must not be negative".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Governance log entry for a funding parameter change
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct FundingParameterChange {
+    pub id: Uuid,
+    pub symbol: String,
+    pub changed_by: String,
+    pub old_values: Option<serde_json::Value>,
+    pub new_values: serde_json::Value,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Outcome of one settlement cycle
+#[derive(Debug, Default)]
+pub struct FundingCycle {
+    /// Positions funding was applied to
+    pub applied: usize,
+    /// Clamp alerts raised during the cycle
+    pub alerts: Vec<ClampAlert>,
+}
+
+/// Outcome of settling a single rate
+struct SettledRate {
+    events: Vec<FundingEvent>,
+    alert: Option<ClampAlert>,
+}
+
+/// Clamp bounds and current run of a symbol
+#[derive(Debug, sqlx::FromRow)]
+struct ClampState {
+    min_funding_rate: Decimal,
+    max_funding_rate: Decimal,
+    clamp_side: Option<String>,
+    clamp_streak: i32,
+}
+
+/// Due funding rate
+#[derive(Debug, sqlx::FromRow)]
+struct DueRate {
+    id: Uuid,
+    symbol: String,
+    funding_rate: Decimal,
+    mark_price: Decimal,
+}
+
+/// Holding a rate is applied to
+#[derive(Debug, sqlx::FromRow)]
+struct FundedShare {
+    id: Uuid,
+    user_address: String,
+    share_type: String,
+    amount: Decimal,
+    avg_cost: Decimal,
+    margin_mode: String,
+}
+
+/// Post a clamp alert to the risk webhook
+pub async fn send_clamp_alert(http: &reqwest::Client, url: &str, alert: &ClampAlert) -> Result<(), reqwest::Error> {
+    http.post(url)
+        .json(alert)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// Funding settlement service
+pub struct FundingService;
+
+impl FundingService {
+    /// Settle every due funding rate, notifying affected users. Rates that
+    /// keep a symbol clamped for `clamp_alert_intervals` consecutive intervals
+    /// raise alerts, returned alongside the number of positions funded.
+    pub async fn settle_due(
+        pool: &PgPool,
+        token: &str,
+        engine: &MatchingEngine,
+        notifier: &broadcast::Sender<FundingEvent>,
+        clamp_alert_intervals: u32,
+    ) -> Result<FundingCycle, FundingError> {
+        let mut cycle = FundingCycle::default();
+        for _ in 0..MAX_RATES_PER_CYCLE {
+            let Some(settled) = Self::settle_next(pool, token, engine, clamp_alert_intervals).await? else {
+                break;
+            };
+            cycle.applied += settled.events.len();
+            for event in settled.events {
+                // No receivers just means no WebSocket clients are connected
+                let _ = notifier.send(event);
+            }
+            cycle.alerts.extend(settled.alert);
+        }
+        Ok(cycle)
+    }
+
+    /// Settle (or defer) the oldest due rate; None when nothing is due
+    async fn settle_next(
+        pool: &PgPool,
+        token: &str,
+        engine: &MatchingEngine,
+        clamp_alert_intervals: u32,
+    ) -> Result<Option<SettledRate>, FundingError> {
+        let mut tx = pool.begin().await?;
+
+        let rate: Option<DueRate> = sqlx::query_as(
+            r#"
+            SELECT id, symbol, funding_rate, mark_price
+            FROM funding_rates
+            WHERE settled_at IS NULL AND next_funding_time <= NOW()
+            ORDER BY next_funding_time
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+            "#,
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(rate) = rate else {
+            return Ok(None);
+        };
+
+        // Market closed: push the funding time to the next session open
+        let closed = parse_symbol(&rate.symbol)
+            .and_then(|(market_id, _)| engine.session_state(market_id))
+            .filter(|session| !session.is_open);
+        if let Some(session) = closed {
+            let next_open = session
+                .next_open
+                .and_then(DateTime::from_timestamp_millis)
+                .unwrap_or_else(|| Utc::now() + chrono::Duration::days(1));
+            sqlx::query("UPDATE funding_rates SET next_funding_time = $1 WHERE id = $2")
+                .bind(next_open)
+                .bind(rate.id)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+            tracing::debug!("Deferred funding rate {} ({}) to {}", rate.id, rate.symbol, next_open);
+            return Ok(Some(SettledRate { events: Vec::new(), alert: None }));
+        }
+
+        let mut events = Vec::new();
+        match parse_symbol(&rate.symbol) {
+            Some((market_id, outcome_id)) => {
+                let holdings: Vec<FundedShare> = sqlx::query_as(
+                    r#"
+                    SELECT s.id, s.user_address, s.share_type::text AS share_type, s.amount, s.avg_cost,
+                           COALESCE(s.margin_mode, u.margin_mode, 'isolated') AS margin_mode
+                    FROM shares s
+                    LEFT JOIN users u ON u.address = s.user_address
+                    WHERE s.market_id = $1 AND s.outcome_id = $2 AND s.amount <> 0
+                    ORDER BY s.id
+                    FOR UPDATE OF s
+                    "#,
+                )
+                .bind(market_id)
+                .bind(outcome_id)
+                .fetch_all(&mut *tx)
+                .await?;
+
+                for share in holdings {
+                    let mode: MarginMode = share.margin_mode.parse().unwrap_or_default();
+                    let application = apply_funding(mode, share.amount, share.avg_cost, rate.mark_price, rate.funding_rate);
+                    if application.fee.is_zero() {
+                        continue;
+                    }
+
+                    sqlx::query(
+                        r#"
+                        UPDATE shares
+                        SET avg_cost = $1, funding_paid = funding_paid + $2, version = version + 1, updated_at = NOW()
+                        WHERE id = $3
+                        "#,
+                    )
+                    .bind(application.avg_cost)
+                    .bind(application.balance_fee())
+                    .bind(share.id)
+                    .execute(&mut *tx)
+                    .await?;
+
+                    if !application.balance_delta.is_zero() {
+                        let change = BalanceChange::credit(&share.user_address, token, application.balance_delta, LedgerReason::Funding)
+                            .reference(rate.id);
+                        LedgerService::apply(&mut tx, &change).await?;
+                    }
+
+                    let (id, settled_at): (Uuid, DateTime<Utc>) = sqlx::query_as(
+                        r#"
+                        INSERT INTO funding_settlements (
+                            funding_rate_id, share_id, user_address, symbol, funding_rate,
+                            mark_price, position_size, funding_fee, is_long, margin_mode, token
+                        )
+                        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                        RETURNING id, settled_at
+                        "#,
+                    )
+                    .bind(rate.id)
+                    .bind(share.id)
+                    .bind(&share.user_address)
+                    .bind(&rate.symbol)
+                    .bind(rate.funding_rate)
+                    .bind(rate.mark_price)
+                    .bind(share.amount)
+                    .bind(application.fee)
+                    .bind(share.amount > Decimal::ZERO)
+                    .bind(mode.as_str())
+                    .bind(token)
+                    .fetch_one(&mut *tx)
+                    .await?;
+
+                    events.push(FundingEvent {
+                        user_address: share.user_address.to_lowercase(),
+                        settlement: FundingSettlement {
+                            id,
+                            funding_rate_id: rate.id,
+                            position_id: share.id,
+                            market_id,
+                            outcome_id,
+                            share_type: share.share_type,
+                            funding_rate: rate.funding_rate,
+                            mark_price: rate.mark_price,
+                            position_size: share.amount,
+                            funding_fee: application.fee,
+                            margin_mode: mode,
+                            token: token.to_string(),
+                            settled_at,
+                        },
+                    });
+                }
+            }
+            None => {
+                tracing::error!("Funding rate {} has invalid symbol {:?}; marking settled without applying", rate.id, rate.symbol);
+            }
+        }
+
+        sqlx::query("UPDATE funding_rates SET settled_at = NOW() WHERE id = $1")
+            .bind(rate.id)
+            .execute(&mut *tx)
+            .await?;
+        let alert = Self::track_clamp(&mut tx, &rate, clamp_alert_intervals).await?;
+        tx.commit().await?;
+
+        tracing::info!(
+            "Settled funding rate {} for {} ({}) across {} positions",
+            rate.id, rate.symbol, rate.funding_rate, events.len()
+        );
+        Ok(Some(SettledRate { events, alert }))
+    }
+
+    /// Advance the symbol's clamp run with a settled rate, recording an alert
+    /// when the run is long enough
+    async fn track_clamp(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        rate: &DueRate,
+        clamp_alert_intervals: u32,
+    ) -> Result<Option<ClampAlert>, FundingError> {
+        let state: Option<ClampState> = sqlx::query_as(
+            r#"
+            SELECT min_funding_rate, max_funding_rate, clamp_side, clamp_streak
+            FROM market_funding_config
+            WHERE symbol = $1
+            FOR UPDATE
+            "#,
+        )
+        .bind(&rate.symbol)
+        .fetch_optional(&mut **tx)
+        .await?;
+        // No configured bounds, nothing to clamp against
+        let Some(state) = state else {
+            return Ok(None);
+        };
+
+        let side = clamp_side(rate.funding_rate, state.min_funding_rate, state.max_funding_rate);
+        let prev_side = state.clamp_side.as_deref().and_then(ClampSide::parse);
+        let streak = next_clamp_streak(prev_side, state.clamp_streak.max(0) as u32, side);
+
+        sqlx::query("UPDATE market_funding_config SET clamp_side = $1, clamp_streak = $2 WHERE symbol = $3")
+            .bind(side.map(|s| s.as_str()))
+            .bind(streak as i32)
+            .bind(&rate.symbol)
+            .execute(&mut **tx)
+            .await?;
+
+        let Some(side) = side.filter(|_| should_alert(streak, clamp_alert_intervals)) else {
+            return Ok(None);
+        };
+        let bound = match side {
+            ClampSide::Cap => state.max_funding_rate,
+            ClampSide::Floor => state.min_funding_rate,
+        };
+        let alert: ClampAlert = sqlx::query_as(
+            r#"
+            INSERT INTO funding_clamp_alerts (symbol, funding_rate_id, side, streak, funding_rate, bound)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, symbol, funding_rate_id, side, streak, funding_rate, bound, created_at
+            "#,
+        )
+        .bind(&rate.symbol)
+        .bind(rate.id)
+        .bind(side.as_str())
+        .bind(streak as i32)
+        .bind(rate.funding_rate)
+        .bind(bound)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        crate::metrics::record_funding_clamp_alert(side.as_str());
+        Ok(Some(alert))
+    }
+
+    /// Funding parameters of every configured symbol
+    pub async fn list_configs(pool: &PgPool) -> Result<Vec<FundingConfig>, FundingError> {
+        let configs = sqlx::query_as(
+            r#"
+            SELECT symbol, funding_interval_hours, max_funding_rate, min_funding_rate,
+                   impact_pool_size, clamp_side, clamp_streak, updated_at
+            FROM market_funding_config
+            ORDER BY symbol
+            "#,
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(configs)
+    }
+
+    /// Funding parameters of an outcome (`{market_id}:{outcome_id}`), the
+    /// column defaults if it has no config
+    pub async fn params(pool: &PgPool, symbol: &str) -> Result<FundingParams, FundingError> {
+        let params: Option<FundingParams> = sqlx::query_as(
+            r#"
+            SELECT funding_interval_hours, max_funding_rate, min_funding_rate, impact_pool_size
+            FROM market_funding_config
+            WHERE symbol = $1
+            "#,
+        )
+        .bind(symbol)
+        .fetch_optional(pool)
+        .await?;
+        Ok(params.unwrap_or_default())
+    }
+
+    /// Change a symbol's funding parameters (creating its config if needed),
+    /// logging the old and new values with the admin who made the change
+    pub async fn update_config(
+        pool: &PgPool,
+        symbol: &str,
+        update: &FundingParamsUpdate,
+        changed_by: &str,
+        reason: Option<&str>,
+    ) -> Result<FundingConfig, FundingError> {
+        let mut tx = pool.begin().await?;
+
+        let old: Option<FundingParams> = sqlx::query_as(
+            r#"
+            SELECT funding_interval_hours, max_funding_rate, min_funding_rate, impact_pool_size
+            FROM market_funding_config
+            WHERE symbol = $1
+            FOR UPDATE
+            "#,
+        )
+        .bind(symbol)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let new = old.unwrap_or_default().with(update);
+        new.validate()?;
+
+        let config: FundingConfig = sqlx::query_as(
+            r#"
+            INSERT INTO market_funding_config (
+                symbol, funding_interval_hours, max_funding_rate, min_funding_rate, impact_pool_size
+            )
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (symbol) DO UPDATE SET
+                funding_interval_hours = EXCLUDED.funding_interval_hours,
+                max_funding_rate = EXCLUDED.max_funding_rate,
+                min_funding_rate = EXCLUDED.min_funding_rate,
+                impact_pool_size = EXCLUDED.impact_pool_size,
+                updated_at = NOW()
+            RETURNING symbol, funding_interval_hours, max_funding_rate, min_funding_rate,
+                      impact_pool_size, clamp_side, clamp_streak, updated_at
+            "#,
+        )
+        .bind(symbol)
+        .bind(new.funding_interval_hours)
+        .bind(new.max_funding_rate)
+        .bind(new.min_funding_rate)
+        .bind(new.impact_pool_size)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if old != Some(new) {
+            sqlx::query(
+                r#"
+                INSERT INTO funding_parameter_changes (symbol, changed_by, old_values, new_values, reason)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+            )
+            .bind(symbol)
+            .bind(changed_by.to_lowercase())
+            .bind(old.map(|o| serde_json::to_value(o).unwrap_or_default()))
+            .bind(serde_json::to_value(new).unwrap_or_default())
+            .bind(reason)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(config)
+    }
+
+    /// Funding parameter governance log, newest first
+    pub async fn list_changes(
+        pool: &PgPool,
+        symbol: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<FundingParameterChange>, FundingError> {
+        let changes = sqlx::query_as(
+            r#"
+            SELECT id, symbol, changed_by, old_values, new_values, reason, created_at
+            FROM funding_parameter_changes
+            WHERE $1::text IS NULL OR symbol = $1
+            ORDER BY created_at DESC
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(symbol)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await?;
+        Ok(changes)
+    }
+
+    /// Recent clamp alerts, newest first
+    pub async fn list_alerts(
+        pool: &PgPool,
+        symbol: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<ClampAlert>, FundingError> {
+        let alerts = sqlx::query_as(
+            r#"
+            SELECT id, symbol, funding_rate_id, side, streak, funding_rate, bound, created_at
+            FROM funding_clamp_alerts
+            WHERE $1::text IS NULL OR symbol = $1
+            ORDER BY created_at DESC
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(symbol)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await?;
+        Ok(alerts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_positive_rate_longs_pay_shorts_receive() {
+        let long = apply_funding(MarginMode::Cross, dec!(100), dec!(0.5), dec!(0.6), dec!(0.01));
+        assert_eq!(long.fee, dec!(0.6));
+        assert_eq!(long.balance_delta, dec!(-0.6));
+        assert_eq!(long.avg_cost, dec!(0.5));
+
+        let short = apply_funding(MarginMode::Cross, dec!(-100), dec!(0.5), dec!(0.6), dec!(0.01));
+        assert_eq!(short.fee, dec!(-0.6));
+        assert_eq!(short.balance_delta, dec!(0.6));
+    }
+
+    #[test]
+    fn test_isolated_funding_moves_cost_basis() {
+        // Long pays: cost basis 50 + 0.6
+        let paid = apply_funding(MarginMode::Isolated, dec!(100), dec!(0.5), dec!(0.6), dec!(0.01));
+        assert_eq!(paid.avg_cost, dec!(0.506));
+        assert_eq!(paid.balance_delta, Decimal::ZERO);
+        assert_eq!(paid.balance_fee(), Decimal::ZERO);
+
+        // Long receives more than its cost basis: floored at zero, remainder credited
+        let received = apply_funding(MarginMode::Isolated, dec!(10), dec!(0.01), dec!(0.5), dec!(-0.1));
+        assert_eq!(received.avg_cost, Decimal::ZERO);
+        assert_eq!(received.balance_delta, dec!(0.4));
+    }
+
+    #[test]
+    fn test_clamp_streak_and_alerts() {
+        let (min, max) = (dec!(-0.01), dec!(0.01));
+        assert_eq!(clamp_side(dec!(0.01), min, max), Some(ClampSide::Cap));
+        assert_eq!(clamp_side(dec!(-0.02), min, max), Some(ClampSide::Floor));
+        assert_eq!(clamp_side(dec!(0.005), min, max), None);
+
+        // Three intervals at the cap, then a flip to the floor restarts the run
+        let mut side = None;
+        let mut streak = 0;
+        for rate in [dec!(0.01), dec!(0.01), dec!(0.01)] {
+            let next = clamp_side(rate, min, max);
+            streak = next_clamp_streak(side, streak, next);
+            side = next;
+        }
+        assert_eq!(streak, 3);
+        assert!(should_alert(streak, 3));
+        assert_eq!(next_clamp_streak(side, streak, Some(ClampSide::Floor)), 1);
+        assert_eq!(next_clamp_streak(side, streak, None), 0);
+
+        // Re-alerts every threshold intervals, never when disabled
+        assert!(!should_alert(4, 3));
+        assert!(should_alert(6, 3));
+        assert!(!should_alert(3, 0));
+    }
+
+    #[test]
+    fn test_funding_params_update_and_validation() {
+        let params = FundingParams::default().with(&FundingParamsUpdate {
+            max_funding_rate: Some(dec!(0.02)),
+            ..Default::default()
+        });
+        assert_eq!(params.max_funding_rate, dec!(0.02));
+        assert_eq!(params.min_funding_rate, dec!(-0.01));
+        assert!(params.validate().is_ok());
+
+        let inverted = params.with(&FundingParamsUpdate {
+            min_funding_rate: Some(dec!(0.03)),
+            ..Default::default()
+        });
+        assert!(inverted.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_symbol() {
+        let market = Uuid::new_v4();
+        let outcome = Uuid::new_v4();
+        assert_eq!(parse_symbol(&format!("{}:{}", market, outcome)), Some((market, outcome)));
+        assert_eq!(parse_symbol("BTCUSDT"), None);
+    }
+}
@@ -0,0 +1,108 @@
+//! Gas Oracle
+//!
+//! Polls the chain's gas price (`eth_gasPrice` through `rpc_url`) and turns it
+//! into a congestion multiplier against a baseline: 1 at or below the
+//! baseline, growing linearly above it up to a cap. Without a reading (RPC
+//! down, not polled yet) the multiplier is 1.
+
+use chrono::{DateTime, Utc};
+use ethers::providers::{Http, Middleware, Provider};
+use parking_lot::RwLock;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+/// Wei per gwei
+const WEI_PER_GWEI: u64 = 1_000_000_000;
+
+/// Gas oracle errors
+#[derive(Debug, thiserror::Error)]
+pub enum GasOracleError {
+    #[error("Chain error: {0}")]
+    ChainError(String),
+}
+
+/// Latest gas price reading
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct GasReading {
+    pub gas_price_gwei: Decimal,
+    pub congestion_multiplier: Decimal,
+    pub observed_at: DateTime<Utc>,
+}
+
+/// `gwei / baseline`, floored at 1 and capped at `max`
+pub fn congestion_multiplier(gwei: Decimal, baseline_gwei: Decimal, max: Decimal) -> Decimal {
+    if baseline_gwei <= Decimal::ZERO {
+        return Decimal::ONE;
+    }
+    (gwei / baseline_gwei).round_dp(4).clamp(Decimal::ONE, max.max(Decimal::ONE))
+}
+
+pub struct GasOracle {
+    provider: Option<Provider<Http>>,
+    baseline_gwei: Decimal,
+    max_multiplier: Decimal,
+    latest: RwLock<Option<GasReading>>,
+}
+
+impl GasOracle {
+    /// Oracle polling `rpc_url`; an unusable URL leaves it without readings
+    pub fn new(rpc_url: &str, baseline_gwei: Decimal, max_multiplier: Decimal) -> Self {
+        let provider = match Provider::<Http>::try_from(rpc_url) {
+            Ok(provider) => Some(provider),
+            Err(e) => {
+                tracing::warn!("Gas oracle disabled, invalid RPC URL: {}", e);
+                None
+            }
+        };
+        Self {
+            provider,
+            baseline_gwei,
+            max_multiplier,
+            latest: RwLock::new(None),
+        }
+    }
+
+    /// Fetch the current gas price
+    pub async fn poll(&self) -> Result<Option<GasReading>, GasOracleError> {
+        let Some(provider) = &self.provider else {
+            return Ok(None);
+        };
+        let wei = provider
+            .get_gas_price()
+            .await
+            .map_err(|e| GasOracleError::ChainError(e.to_string()))?;
+        let wei = Decimal::from_str_exact(&wei.to_string()).map_err(|e| GasOracleError::ChainError(e.to_string()))?;
+        let gas_price_gwei = (wei / Decimal::from(WEI_PER_GWEI)).round_dp(4);
+
+        let reading = GasReading {
+            gas_price_gwei,
+            congestion_multiplier: congestion_multiplier(gas_price_gwei, self.baseline_gwei, self.max_multiplier),
+            observed_at: Utc::now(),
+        };
+        *self.latest.write() = Some(reading);
+        Ok(Some(reading))
+    }
+
+    pub fn latest(&self) -> Option<GasReading> {
+        *self.latest.read()
+    }
+
+    /// Current congestion multiplier (1 without a reading)
+    pub fn multiplier(&self) -> Decimal {
+        self.latest().map_or(Decimal::ONE, |reading| reading.congestion_multiplier)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_multiplier_scales_above_baseline_within_cap() {
+        assert_eq!(congestion_multiplier(dec!(10), dec!(30), dec!(3)), dec!(1));
+        assert_eq!(congestion_multiplier(dec!(45), dec!(30), dec!(3)), dec!(1.5));
+        assert_eq!(congestion_multiplier(dec!(300), dec!(30), dec!(3)), dec!(3));
+        assert_eq!(congestion_multiplier(dec!(300), dec!(0), dec!(3)), dec!(1));
+    }
+}
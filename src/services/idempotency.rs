@@ -0,0 +1,171 @@
+//! Idempotency Keys
+//!
+//! Lets clients retry `POST /orders` and `POST /withdraw/request` safely. A
+//! request carrying a key claims it before it runs; once it completes, its
+//! response is stored under the key and replayed to every retry until the key
+//! expires. A key is scoped to the account and endpoint and bound to the
+//! request body it was first used with.
+//!
+//! Server errors (5xx) release the key so the request can be retried for real,
+//! unless the request had already taken effect; see
+//! [`crate::api::middleware::idempotency`].
+
+use chrono::{Duration, Utc};
+use sha3::{Digest, Keccak256};
+use sqlx::PgPool;
+
+/// Outcome of claiming a key
+#[derive(Debug, Clone, PartialEq)]
+pub enum Claim {
+    /// First use: run the request
+    New,
+    /// The first request with this key is still running
+    InProgress,
+    /// The key was used with a different request body
+    Mismatch,
+    /// The request already completed with this response
+    Replay { status: u16, body: String },
+}
+
+/// Stored responses for idempotency keys
+pub struct IdempotencyService {
+    ttl: Duration,
+}
+
+/// Hash identifying a request body
+pub fn request_hash(body: &[u8]) -> String {
+    hex::encode(Keccak256::digest(body))
+}
+
+impl IdempotencyService {
+    pub fn new(ttl: std::time::Duration) -> Self {
+        Self {
+            ttl: Duration::from_std(ttl).unwrap_or_else(|_| Duration::days(1)),
+        }
+    }
+
+    /// Claim `key` for a request, or find what became of an earlier one
+    pub async fn claim(
+        &self,
+        pool: &PgPool,
+        user_address: &str,
+        scope: &str,
+        key: &str,
+        request_hash: &str,
+    ) -> Result<Claim, sqlx::Error> {
+        let user_address = user_address.to_lowercase();
+
+        // Expired keys are free again
+        sqlx::query(
+            r#"
+            DELETE FROM idempotency_keys
+            WHERE user_address = $1 AND scope = $2 AND idempotency_key = $3 AND expires_at <= NOW()
+            "#,
+        )
+        .bind(&user_address)
+        .bind(scope)
+        .bind(key)
+        .execute(pool)
+        .await?;
+
+        let claimed = sqlx::query(
+            r#"
+            INSERT INTO idempotency_keys (user_address, scope, idempotency_key, request_hash, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (user_address, scope, idempotency_key) DO NOTHING
+            "#,
+        )
+        .bind(&user_address)
+        .bind(scope)
+        .bind(key)
+        .bind(request_hash)
+        .bind(Utc::now() + self.ttl)
+        .execute(pool)
+        .await?;
+        if claimed.rows_affected() == 1 {
+            return Ok(Claim::New);
+        }
+
+        let existing: Option<(String, Option<i16>, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT request_hash, response_status, response_body
+            FROM idempotency_keys
+            WHERE user_address = $1 AND scope = $2 AND idempotency_key = $3
+            "#,
+        )
+        .bind(&user_address)
+        .bind(scope)
+        .bind(key)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(match existing {
+            Some((hash, _, _)) if hash != request_hash => Claim::Mismatch,
+            Some((_, Some(status), body)) => Claim::Replay {
+                status: status as u16,
+                body: body.unwrap_or_default(),
+            },
+            // Still running, or released between the insert and the read
+            _ => Claim::InProgress,
+        })
+    }
+
+    /// Store the response of a claimed key
+    pub async fn complete(
+        &self,
+        pool: &PgPool,
+        user_address: &str,
+        scope: &str,
+        key: &str,
+        status: u16,
+        body: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE idempotency_keys
+            SET response_status = $4, response_body = $5
+            WHERE user_address = $1 AND scope = $2 AND idempotency_key = $3
+            "#,
+        )
+        .bind(user_address.to_lowercase())
+        .bind(scope)
+        .bind(key)
+        .bind(status as i16)
+        .bind(body)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Give up a claimed key so the request can be retried
+    pub async fn release(&self, pool: &PgPool, user_address: &str, scope: &str, key: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM idempotency_keys WHERE user_address = $1 AND scope = $2 AND idempotency_key = $3")
+            .bind(user_address.to_lowercase())
+            .bind(scope)
+            .bind(key)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Drop expired keys; returns how many were removed
+    pub async fn purge_expired(pool: &PgPool) -> Result<u64, sqlx::Error> {
+        let purged = sqlx::query("DELETE FROM idempotency_keys WHERE expires_at <= NOW()")
+            .execute(pool)
+            .await?;
+        Ok(purged.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_hash_is_stable_per_body() {
+        let hash = request_hash(br#"{"amount":"1"}"#);
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, request_hash(br#"{"amount":"1"}"#));
+        assert_ne!(hash, request_hash(br#"{"amount":"2"}"#));
+    }
+}
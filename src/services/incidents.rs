@@ -0,0 +1,386 @@
+//! Status Page Incidents
+//!
+//! Operators open an incident against one or more components (degraded
+//! matching, delayed withdrawals, ...) and post updates as it moves through
+//! `investigating → identified → monitoring → resolved`. Every change is
+//! kept in `incident_updates`, so the status page shows the full timeline.
+//!
+//! A component's current status is the worst impact among the unresolved
+//! incidents that list it, or `operational` when there are none.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Incident errors
+#[derive(Debug, thiserror::Error)]
+pub enum IncidentError {
+    #[error("Incident not found: {0}")]
+    NotFound(Uuid),
+
+    #[error("Incident {0} is already resolved")]
+    AlreadyResolved(Uuid),
+
+    #[error("An incident needs at least one component")]
+    NoComponents,
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// Part of the platform shown on the status page
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Component {
+    Api,
+    Matching,
+    Websocket,
+    MarketData,
+    Deposits,
+    Withdrawals,
+}
+
+impl Component {
+    pub const ALL: [Component; 6] = [
+        Component::Api,
+        Component::Matching,
+        Component::Websocket,
+        Component::MarketData,
+        Component::Deposits,
+        Component::Withdrawals,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Component::Api => "api",
+            Component::Matching => "matching",
+            Component::Websocket => "websocket",
+            Component::MarketData => "market_data",
+            Component::Deposits => "deposits",
+            Component::Withdrawals => "withdrawals",
+        }
+    }
+}
+
+/// How badly an incident affects its components (ordered by severity)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Impact {
+    Degraded,
+    PartialOutage,
+    MajorOutage,
+}
+
+impl Impact {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Impact::Degraded => "degraded",
+            Impact::PartialOutage => "partial_outage",
+            Impact::MajorOutage => "major_outage",
+        }
+    }
+}
+
+impl FromStr for Impact {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "degraded" => Ok(Impact::Degraded),
+            "partial_outage" => Ok(Impact::PartialOutage),
+            "major_outage" => Ok(Impact::MajorOutage),
+            _ => Err(format!("Unknown impact: {}", s)),
+        }
+    }
+}
+
+/// Incident lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IncidentStatus {
+    Investigating,
+    Identified,
+    Monitoring,
+    Resolved,
+}
+
+impl IncidentStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IncidentStatus::Investigating => "investigating",
+            IncidentStatus::Identified => "identified",
+            IncidentStatus::Monitoring => "monitoring",
+            IncidentStatus::Resolved => "resolved",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct Incident {
+    pub id: Uuid,
+    pub title: String,
+    pub components: Vec<String>,
+    pub impact: String,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    /// Timeline, oldest first
+    #[sqlx(skip)]
+    pub updates: Vec<IncidentUpdate>,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct IncidentUpdate {
+    pub id: Uuid,
+    #[serde(skip)]
+    pub incident_id: Uuid,
+    pub status: String,
+    pub impact: String,
+    pub message: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Current status of a component
+#[derive(Debug, Clone, Serialize)]
+pub struct ComponentStatus {
+    pub component: Component,
+    /// `operational` or the worst impact of its open incidents
+    pub status: &'static str,
+    /// Unresolved incidents affecting the component
+    pub incidents: Vec<Uuid>,
+}
+
+/// New incident
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewIncident {
+    pub title: String,
+    pub components: Vec<Component>,
+    pub impact: Impact,
+    #[serde(default = "default_status")]
+    pub status: IncidentStatus,
+    pub message: String,
+}
+
+fn default_status() -> IncidentStatus {
+    IncidentStatus::Investigating
+}
+
+/// Update posted to an incident; omitted fields stay as they are
+#[derive(Debug, Clone, Deserialize)]
+pub struct IncidentChange {
+    pub status: Option<IncidentStatus>,
+    pub impact: Option<Impact>,
+    pub message: String,
+}
+
+/// Status of every component given the unresolved incidents
+pub fn component_statuses(active: &[Incident]) -> Vec<ComponentStatus> {
+    Component::ALL
+        .into_iter()
+        .map(|component| {
+            let affecting: Vec<&Incident> = active
+                .iter()
+                .filter(|incident| incident.resolved_at.is_none())
+                .filter(|incident| incident.components.iter().any(|c| c == component.as_str()))
+                .collect();
+            let worst = affecting.iter().filter_map(|incident| incident.impact.parse::<Impact>().ok()).max();
+            ComponentStatus {
+                component,
+                status: worst.map_or("operational", |impact| impact.as_str()),
+                incidents: affecting.iter().map(|incident| incident.id).collect(),
+            }
+        })
+        .collect()
+}
+
+/// Overall status: the worst component status
+pub fn overall_status(components: &[ComponentStatus]) -> &'static str {
+    components
+        .iter()
+        .filter_map(|component| component.status.parse::<Impact>().ok())
+        .max()
+        .map_or("operational", |impact| impact.as_str())
+}
+
+const INCIDENT_COLUMNS: &str = "id, title, components, impact, status, created_at, updated_at, resolved_at";
+
+pub struct IncidentService;
+
+impl IncidentService {
+    /// Open an incident with its first update
+    pub async fn open(pool: &PgPool, new: &NewIncident, author: &str) -> Result<Incident, IncidentError> {
+        if new.components.is_empty() {
+            return Err(IncidentError::NoComponents);
+        }
+        let components: Vec<&str> = new.components.iter().map(Component::as_str).collect();
+        let resolved = new.status == IncidentStatus::Resolved;
+
+        let mut tx = pool.begin().await?;
+        let mut incident: Incident = sqlx::query_as(&format!(
+            r#"
+            INSERT INTO incidents (title, components, impact, status, resolved_at)
+            VALUES ($1, $2, $3, $4, CASE WHEN $5 THEN NOW() END)
+            RETURNING {INCIDENT_COLUMNS}
+            "#
+        ))
+        .bind(&new.title)
+        .bind(&components)
+        .bind(new.impact.as_str())
+        .bind(new.status.as_str())
+        .bind(resolved)
+        .fetch_one(&mut *tx)
+        .await?;
+        incident.updates.push(Self::record_update(&mut tx, &incident, &new.message, author).await?);
+        tx.commit().await?;
+
+        tracing::warn!("Incident {} opened by {}: {} ({})", incident.id, author, incident.title, incident.impact);
+        Ok(incident)
+    }
+
+    /// Post an update, changing status and/or impact; resolved incidents are closed
+    pub async fn update(pool: &PgPool, id: Uuid, change: &IncidentChange, author: &str) -> Result<Incident, IncidentError> {
+        let mut tx = pool.begin().await?;
+        let current: Incident = sqlx::query_as(&format!("SELECT {INCIDENT_COLUMNS} FROM incidents WHERE id = $1 FOR UPDATE"))
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or(IncidentError::NotFound(id))?;
+        if current.resolved_at.is_some() {
+            return Err(IncidentError::AlreadyResolved(id));
+        }
+
+        let status = change.status.map_or(current.status.as_str(), |s| s.as_str());
+        let impact = change.impact.map_or(current.impact.as_str(), |i| i.as_str());
+        let incident: Incident = sqlx::query_as(&format!(
+            r#"
+            UPDATE incidents
+            SET status = $2, impact = $3, updated_at = NOW(),
+                resolved_at = CASE WHEN $2 = 'resolved' THEN NOW() END
+            WHERE id = $1
+            RETURNING {INCIDENT_COLUMNS}
+            "#
+        ))
+        .bind(id)
+        .bind(status)
+        .bind(impact)
+        .fetch_one(&mut *tx)
+        .await?;
+        Self::record_update(&mut tx, &incident, &change.message, author).await?;
+        tx.commit().await?;
+
+        tracing::info!("Incident {} updated by {}: {} ({})", id, author, incident.status, incident.impact);
+        let mut incidents = Self::with_updates(pool, vec![incident]).await?;
+        Ok(incidents.remove(0))
+    }
+
+    async fn record_update(
+        tx: &mut sqlx::PgConnection,
+        incident: &Incident,
+        message: &str,
+        author: &str,
+    ) -> Result<IncidentUpdate, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            INSERT INTO incident_updates (incident_id, status, impact, message, author)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, incident_id, status, impact, message, created_at
+            "#,
+        )
+        .bind(incident.id)
+        .bind(&incident.status)
+        .bind(&incident.impact)
+        .bind(message)
+        .bind(author.to_lowercase())
+        .fetch_one(&mut *tx)
+        .await
+    }
+
+    /// Unresolved incidents, newest first
+    pub async fn active(pool: &PgPool) -> Result<Vec<Incident>, IncidentError> {
+        let incidents = sqlx::query_as(&format!(
+            "SELECT {INCIDENT_COLUMNS} FROM incidents WHERE resolved_at IS NULL ORDER BY created_at DESC"
+        ))
+        .fetch_all(pool)
+        .await?;
+        Self::with_updates(pool, incidents).await
+    }
+
+    /// Incidents opened in the last `days` days plus any still unresolved,
+    /// newest first, with their timelines
+    pub async fn history(pool: &PgPool, days: i64, limit: i64) -> Result<Vec<Incident>, IncidentError> {
+        let incidents = sqlx::query_as(&format!(
+            r#"
+            SELECT {INCIDENT_COLUMNS} FROM incidents
+            WHERE resolved_at IS NULL OR created_at >= NOW() - make_interval(days => $1::int)
+            ORDER BY created_at DESC
+            LIMIT $2
+            "#
+        ))
+        .bind(days)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+        Self::with_updates(pool, incidents).await
+    }
+
+    async fn with_updates(pool: &PgPool, mut incidents: Vec<Incident>) -> Result<Vec<Incident>, IncidentError> {
+        let ids: Vec<Uuid> = incidents.iter().map(|incident| incident.id).collect();
+        let updates: Vec<IncidentUpdate> = sqlx::query_as(
+            r#"
+            SELECT id, incident_id, status, impact, message, created_at
+            FROM incident_updates
+            WHERE incident_id = ANY($1)
+            ORDER BY created_at, id
+            "#,
+        )
+        .bind(&ids)
+        .fetch_all(pool)
+        .await?;
+        for update in updates {
+            if let Some(incident) = incidents.iter_mut().find(|incident| incident.id == update.incident_id) {
+                incident.updates.push(update);
+            }
+        }
+        Ok(incidents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn incident(components: &[&str], impact: Impact, resolved: bool) -> Incident {
+        Incident {
+            id: Uuid::new_v4(),
+            title: "test".to_string(),
+            components: components.iter().map(|c| c.to_string()).collect(),
+            impact: impact.as_str().to_string(),
+            status: if resolved { "resolved" } else { "investigating" }.to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            resolved_at: resolved.then(Utc::now),
+            updates: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_components_take_worst_open_impact() {
+        let active = vec![
+            incident(&["matching", "websocket"], Impact::Degraded, false),
+            incident(&["matching"], Impact::PartialOutage, false),
+            incident(&["withdrawals"], Impact::MajorOutage, true),
+        ];
+        let statuses = component_statuses(&active);
+        let status = |component: Component| statuses.iter().find(|s| s.component == component).unwrap();
+
+        assert_eq!(status(Component::Matching).status, "partial_outage");
+        assert_eq!(status(Component::Matching).incidents.len(), 2);
+        assert_eq!(status(Component::Websocket).status, "degraded");
+        assert_eq!(status(Component::Withdrawals).status, "operational");
+        assert_eq!(overall_status(&statuses), "partial_outage");
+        assert_eq!(overall_status(&component_statuses(&[])), "operational");
+    }
+}
@@ -0,0 +1,396 @@
+//! Background Jobs
+//!
+//! Periodic services (funding settlement, liquidations, price pollers,
+//! exporters, ...) run as named jobs on a shared scheduler instead of ad-hoc
+//! spawn loops. For every job the scheduler keeps:
+//!
+//! - its schedule (fixed interval, optionally skipping the immediate first run)
+//! - the last run's start, finish, duration and error
+//! - run / error counters and the current failure streak
+//!
+//! Job health is exported to metrics after each run, and admins can trigger a
+//! run on demand. A job never overlaps itself: manual triggers are queued onto
+//! the job's own loop. Intervals can be changed at runtime (config reload); the
+//! next run is then one new interval away. On shutdown, loops stop after the
+//! run in progress, if any.
+//!
+//! Jobs with cluster-wide effects (settling funding, liquidating, exporting,
+//! purging) are scheduled `leader_only`: with several instances deployed
+//! they run only on the elected leader ([`crate::services::leader`]), and
+//! their ticks are skipped elsewhere. Jobs maintaining per-process state
+//! (price pollers, metrics, the engine's own sweeps) run everywhere.
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+
+use crate::metrics;
+use crate::services::leader::LeaderElection;
+
+/// Failure of a job run; any service error converts into it with `?`
+pub type JobRunError = Box<dyn std::error::Error + Send + Sync>;
+
+/// One run of a job
+pub type JobFuture = BoxFuture<'static, Result<(), JobRunError>>;
+
+type JobFn = Arc<dyn Fn() -> JobFuture + Send + Sync>;
+
+/// Job scheduler errors
+#[derive(Debug, thiserror::Error)]
+pub enum JobError {
+    #[error("Job not found: {0}")]
+    NotFound(String),
+
+    #[error("Job already registered: {0}")]
+    AlreadyRegistered(String),
+
+    #[error("Job {0} runs on the leader instance only")]
+    NotLeader(String),
+}
+
+/// When a job runs
+#[derive(Debug, Clone, Copy)]
+pub struct Schedule {
+    pub interval: Duration,
+    /// Run as soon as the job is registered (otherwise after one interval)
+    pub run_at_start: bool,
+    /// Run only while this instance is the job leader
+    pub leader_only: bool,
+}
+
+impl Schedule {
+    /// Every `interval`, starting immediately
+    pub fn every(interval: Duration) -> Self {
+        Self {
+            interval,
+            run_at_start: true,
+            leader_only: false,
+        }
+    }
+
+    /// Wait one interval before the first run
+    pub fn delayed(mut self) -> Self {
+        self.run_at_start = false;
+        self
+    }
+
+    /// Skip runs unless this instance is the job leader
+    pub fn leader_only(mut self) -> Self {
+        self.leader_only = true;
+        self
+    }
+}
+
+/// Status of a registered job
+#[derive(Debug, Clone, Serialize)]
+pub struct JobStatus {
+    pub name: String,
+    pub interval_ms: u64,
+    pub leader_only: bool,
+    pub running: bool,
+    pub runs: u64,
+    pub errors: u64,
+    pub consecutive_errors: u64,
+    pub last_started_at: Option<DateTime<Utc>>,
+    pub last_finished_at: Option<DateTime<Utc>>,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub last_duration_ms: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+impl JobStatus {
+    fn new(name: &str, schedule: &Schedule) -> Self {
+        Self {
+            name: name.to_string(),
+            interval_ms: schedule.interval.as_millis() as u64,
+            leader_only: schedule.leader_only,
+            running: false,
+            runs: 0,
+            errors: 0,
+            consecutive_errors: 0,
+            last_started_at: None,
+            last_finished_at: None,
+            last_success_at: None,
+            last_duration_ms: None,
+            last_error: None,
+        }
+    }
+
+    fn started(&mut self, at: DateTime<Utc>) {
+        self.running = true;
+        self.last_started_at = Some(at);
+    }
+
+    /// Record a finished run
+    fn finished(&mut self, at: DateTime<Utc>, duration: Duration, result: Result<(), String>) {
+        self.running = false;
+        self.runs += 1;
+        self.last_finished_at = Some(at);
+        self.last_duration_ms = Some(duration.as_millis() as u64);
+        match result {
+            Ok(()) => {
+                self.consecutive_errors = 0;
+                self.last_success_at = Some(at);
+                self.last_error = None;
+            }
+            Err(e) => {
+                self.errors += 1;
+                self.consecutive_errors += 1;
+                self.last_error = Some(e);
+            }
+        }
+    }
+}
+
+/// An error and its sources, outermost first
+fn error_chain(e: &(dyn std::error::Error + 'static)) -> String {
+    let mut message = e.to_string();
+    let mut source = e.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
+
+struct Job {
+    run: JobFn,
+    status: Mutex<JobStatus>,
+    trigger: Notify,
+    rescheduled: Notify,
+}
+
+impl Job {
+    async fn run_once(&self) {
+        let name = self.status.lock().name.clone();
+        self.status.lock().started(Utc::now());
+        let started = Instant::now();
+
+        // A panicking run counts as a failure instead of killing the job
+        let result = match AssertUnwindSafe((self.run)()).catch_unwind().await {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(e)) => Err(error_chain(&*e)),
+            Err(_) => Err("job panicked".to_string()),
+        };
+        let duration = started.elapsed();
+
+        if let Err(e) = &result {
+            tracing::error!("Job {} failed: {}", name, e);
+        }
+        let success = result.is_ok();
+        let mut status = self.status.lock();
+        status.finished(Utc::now(), duration, result);
+        metrics::record_job_run(&name, success, duration.as_secs_f64(), status.consecutive_errors);
+    }
+}
+
+/// Runs registered jobs on their schedules
+#[derive(Default)]
+pub struct JobScheduler {
+    jobs: DashMap<String, Arc<Job>>,
+    /// Election gating `leader_only` jobs (None = single instance, always runs them)
+    leader: Option<Arc<LeaderElection>>,
+    stop: CancellationToken,
+    loops: TaskTracker,
+}
+
+impl JobScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gate `leader_only` jobs on `leader`
+    pub fn with_leader(mut self, leader: Arc<LeaderElection>) -> Self {
+        self.leader = Some(leader);
+        self
+    }
+
+    /// Whether this instance may run `leader_only` jobs
+    pub fn is_leader(&self) -> bool {
+        self.leader.as_ref().is_none_or(|leader| leader.is_leader())
+    }
+
+    /// Register a job and start its loop
+    pub fn register<F>(&self, name: &str, schedule: Schedule, run: F) -> Result<(), JobError>
+    where
+        F: Fn() -> JobFuture + Send + Sync + 'static,
+    {
+        let job = Arc::new(Job {
+            run: Arc::new(run),
+            status: Mutex::new(JobStatus::new(name, &schedule)),
+            trigger: Notify::new(),
+            rescheduled: Notify::new(),
+        });
+        match self.jobs.entry(name.to_string()) {
+            dashmap::mapref::entry::Entry::Occupied(_) => return Err(JobError::AlreadyRegistered(name.to_string())),
+            dashmap::mapref::entry::Entry::Vacant(entry) => {
+                entry.insert(job.clone());
+            }
+        }
+
+        let name = name.to_string();
+        let stop = self.stop.clone();
+        let leader = self.leader.clone().filter(|_| schedule.leader_only);
+        self.loops.spawn(async move {
+            let mut interval = tokio::time::interval(schedule.interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            if !schedule.run_at_start {
+                interval.tick().await;
+            }
+            loop {
+                tokio::select! {
+                    _ = stop.cancelled() => break,
+                    _ = interval.tick() => {}
+                    _ = job.trigger.notified() => {
+                        tracing::info!("Job {} triggered manually", name);
+                    }
+                    _ = job.rescheduled.notified() => {
+                        let period = Duration::from_millis(job.status.lock().interval_ms);
+                        interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+                        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                        tracing::info!("Job {} rescheduled (every {:?})", name, period);
+                        continue;
+                    }
+                }
+                if leader.as_ref().is_some_and(|leader| !leader.is_leader()) {
+                    tracing::debug!("Job {} skipped: not the leader", name);
+                    continue;
+                }
+                job.run_once().await;
+            }
+        });
+        Ok(())
+    }
+
+    /// Queue an immediate run (after the current one, if running)
+    pub fn trigger(&self, name: &str) -> Result<JobStatus, JobError> {
+        let job = self.jobs.get(name).ok_or_else(|| JobError::NotFound(name.to_string()))?;
+        if job.status.lock().leader_only && !self.is_leader() {
+            return Err(JobError::NotLeader(name.to_string()));
+        }
+        job.trigger.notify_one();
+        let status = job.status.lock().clone();
+        Ok(status)
+    }
+
+    /// Change a job's interval
+    pub fn reschedule(&self, name: &str, interval: Duration) -> Result<(), JobError> {
+        let job = self.jobs.get(name).ok_or_else(|| JobError::NotFound(name.to_string()))?;
+        job.status.lock().interval_ms = interval.as_millis() as u64;
+        job.rescheduled.notify_one();
+        Ok(())
+    }
+
+    /// Stop every job loop and wait for runs in progress to finish
+    pub async fn shutdown(&self) {
+        self.stop.cancel();
+        self.loops.close();
+        self.loops.wait().await;
+        tracing::info!("Job scheduler stopped");
+    }
+
+    /// Status of one job
+    pub fn status(&self, name: &str) -> Option<JobStatus> {
+        self.jobs.get(name).map(|job| job.status.lock().clone())
+    }
+
+    /// Status of every job, by name
+    pub fn statuses(&self) -> Vec<JobStatus> {
+        let mut statuses: Vec<JobStatus> = self.jobs.iter().map(|job| job.status.lock().clone()).collect();
+        statuses.sort_by(|a, b| a.name.cmp(&b.name));
+        statuses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_tracks_failure_streaks() {
+        let mut status = JobStatus::new("funding", &Schedule::every(Duration::from_secs(60)));
+        let now = Utc::now();
+
+        status.started(now);
+        assert!(status.running);
+        status.finished(now, Duration::from_millis(5), Err("db down".to_string()));
+        status.finished(now, Duration::from_millis(7), Err("db down".to_string()));
+        assert_eq!((status.runs, status.errors, status.consecutive_errors), (2, 2, 2));
+        assert_eq!(status.last_error.as_deref(), Some("db down"));
+        assert_eq!(status.last_success_at, None);
+        assert!(!status.running);
+
+        status.finished(now, Duration::from_millis(3), Ok(()));
+        assert_eq!((status.runs, status.errors, status.consecutive_errors), (3, 2, 0));
+        assert_eq!(status.last_error, None);
+        assert_eq!(status.last_duration_ms, Some(3));
+        assert_eq!(status.last_success_at, Some(now));
+    }
+
+    #[tokio::test]
+    async fn test_trigger_runs_job_and_rejects_unknown_names() {
+        let scheduler = JobScheduler::new();
+        let runs = Arc::new(std::sync::atomic::AtomicU64::new(0));
+        let counter = runs.clone();
+        scheduler
+            .register("sweep", Schedule::every(Duration::from_secs(3600)).delayed(), move || {
+                let counter = counter.clone();
+                async move {
+                    counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    Err("nothing to sweep".into())
+                }
+                .boxed()
+            })
+            .unwrap();
+        assert!(matches!(
+            scheduler.register("sweep", Schedule::every(Duration::from_secs(1)), || async { Ok(()) }.boxed()),
+            Err(JobError::AlreadyRegistered(_))
+        ));
+        assert!(matches!(scheduler.trigger("missing"), Err(JobError::NotFound(_))));
+        assert!(matches!(scheduler.reschedule("missing", Duration::from_secs(1)), Err(JobError::NotFound(_))));
+        scheduler.reschedule("sweep", Duration::from_secs(1800)).unwrap();
+        assert_eq!(scheduler.status("sweep").unwrap().interval_ms, 1_800_000);
+
+        scheduler.trigger("sweep").unwrap();
+        for _ in 0..100 {
+            if scheduler.status("sweep").is_some_and(|s| s.runs == 1) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let status = scheduler.status("sweep").unwrap();
+        assert_eq!(runs.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!((status.runs, status.errors), (1, 1));
+        assert_eq!(status.last_error.as_deref(), Some("nothing to sweep"));
+
+        tokio::time::timeout(Duration::from_secs(1), scheduler.shutdown()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_leader_only_jobs_need_leadership() {
+        let pool = sqlx::postgres::PgPoolOptions::new().connect_lazy("postgres://localhost/unused").unwrap();
+        let leader = Arc::new(LeaderElection::new(pool, 1, "follower"));
+        let scheduler = JobScheduler::new().with_leader(leader);
+        let every_hour = Schedule::every(Duration::from_secs(3600)).delayed();
+        scheduler.register("settle", every_hour.leader_only(), || async { Ok(()) }.boxed()).unwrap();
+        scheduler.register("poll", every_hour, || async { Ok(()) }.boxed()).unwrap();
+
+        assert!(!scheduler.is_leader());
+        assert!(scheduler.status("settle").unwrap().leader_only);
+        assert!(matches!(scheduler.trigger("settle"), Err(JobError::NotLeader(_))));
+        assert!(scheduler.trigger("poll").is_ok());
+        assert!(JobScheduler::new().is_leader());
+
+        tokio::time::timeout(Duration::from_secs(1), scheduler.shutdown()).await.unwrap();
+    }
+}
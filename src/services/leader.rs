@@ -0,0 +1,183 @@
+//! Leader Election
+//!
+//! Singleton background jobs (liquidations, funding settlement, exports,
+//! purges, ...) must run on one instance only, however many are deployed.
+//! Instances campaign for a Postgres session advisory lock, held on a
+//! dedicated connection outside the pool: the instance holding it is the
+//! leader, and the lock passes on as soon as that connection closes (crash,
+//! network loss, shutdown).
+//!
+//! Each campaign round the leader checks its connection is still alive and
+//! followers try to take the lock. A leader only notices a lost connection
+//! at its next round, while Postgres may release the lock (and a follower
+//! take it) as soon as the session drops: two instances can both lead for up
+//! to one campaign interval, plus any run in progress. Leader-only jobs must
+//! therefore stay safe to run twice (row locks, unique keys, idempotent
+//! ledger entries) rather than rely on exclusivity.
+//!
+//! The leader's connection carries its instance id as `application_name`,
+//! which is how every instance can tell who leads.
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::Serialize;
+use sqlx::{Connection, PgConnection, PgPool};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::metrics;
+
+/// Advisory lock key of the background job leader
+pub const JOBS_LEADER_KEY: i64 = 0x6a6f_6273_4c44; // "jobsLD"
+
+/// Leadership seen from this instance
+#[derive(Debug, Clone, Serialize)]
+pub struct LeaderStatus {
+    pub instance_id: String,
+    pub is_leader: bool,
+    /// When this instance became leader
+    pub leader_since: Option<DateTime<Utc>>,
+    /// Instance currently holding the lock (None when nobody leads)
+    pub leader: Option<String>,
+}
+
+pub struct LeaderElection {
+    pool: PgPool,
+    key: i64,
+    instance_id: String,
+    /// Dedicated connection the lock is (or would be) held on
+    conn: tokio::sync::Mutex<Option<PgConnection>>,
+    leader: AtomicBool,
+    since: Mutex<Option<DateTime<Utc>>>,
+}
+
+impl LeaderElection {
+    pub fn new(pool: PgPool, key: i64, instance_id: impl Into<String>) -> Self {
+        Self {
+            pool,
+            key,
+            instance_id: instance_id.into(),
+            conn: tokio::sync::Mutex::new(None),
+            leader: AtomicBool::new(false),
+            since: Mutex::new(None),
+        }
+    }
+
+    /// Id of this instance: `INSTANCE_ID`, else host name and process id
+    pub fn default_instance_id() -> String {
+        std::env::var("INSTANCE_ID")
+            .ok()
+            .filter(|id| !id.is_empty())
+            .unwrap_or_else(|| {
+                let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "localhost".to_string());
+                format!("{}-{}", host, std::process::id())
+            })
+    }
+
+    pub fn instance_id(&self) -> &str {
+        &self.instance_id
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.leader.load(Ordering::SeqCst)
+    }
+
+    /// One campaign round: keep or take the lock, or give it up when this
+    /// instance may not lead (e.g. its engine is a standby). Returns whether
+    /// this instance leads afterwards.
+    pub async fn campaign(&self, eligible: bool) -> Result<bool, sqlx::Error> {
+        let mut conn = self.conn.lock().await;
+        if !eligible {
+            // Closing the connection releases the lock
+            if let Some(conn) = conn.take() {
+                let _ = conn.close().await;
+            }
+            self.set_leader(false);
+            return Ok(false);
+        }
+
+        let result = match conn.as_mut() {
+            Some(held) if self.is_leader() => sqlx::query("SELECT 1").execute(&mut *held).await.map(|_| true),
+            _ => self.try_lock(&mut conn).await,
+        };
+        match result {
+            Ok(leader) => {
+                self.set_leader(leader);
+                Ok(leader)
+            }
+            Err(e) => {
+                // The lock may be gone with the connection: stop leading first
+                *conn = None;
+                self.set_leader(false);
+                Err(e)
+            }
+        }
+    }
+
+    async fn try_lock(&self, conn: &mut Option<PgConnection>) -> Result<bool, sqlx::Error> {
+        if conn.is_none() {
+            // Detached: a pooled connection would keep the lock after being returned
+            let mut fresh = self.pool.acquire().await?.detach();
+            sqlx::query("SELECT set_config('application_name', $1, false)")
+                .bind(&self.instance_id)
+                .execute(&mut fresh)
+                .await?;
+            *conn = Some(fresh);
+        }
+        let held = conn.as_mut().expect("connection opened above");
+        sqlx::query_scalar("SELECT pg_try_advisory_lock($1)")
+            .bind(self.key)
+            .fetch_one(held)
+            .await
+    }
+
+    /// Give up leadership (on shutdown), letting another instance take over
+    pub async fn resign(&self) {
+        if let Some(conn) = self.conn.lock().await.take() {
+            let _ = conn.close().await;
+        }
+        self.set_leader(false);
+    }
+
+    /// Instance currently holding the lock
+    pub async fn current_leader(&self) -> Result<Option<String>, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT a.application_name
+            FROM pg_locks l
+            JOIN pg_stat_activity a ON a.pid = l.pid
+            WHERE l.locktype = 'advisory' AND l.granted
+              AND ((l.classid::bigint << 32) | l.objid::bigint) = $1
+            LIMIT 1
+            "#,
+        )
+        .bind(self.key)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    pub async fn status(&self) -> Result<LeaderStatus, sqlx::Error> {
+        let leader = self.current_leader().await?;
+        Ok(LeaderStatus {
+            instance_id: self.instance_id.clone(),
+            is_leader: self.is_leader(),
+            leader_since: *self.since.lock(),
+            leader,
+        })
+    }
+
+    fn set_leader(&self, leader: bool) {
+        let was_leader = self.leader.swap(leader, Ordering::SeqCst);
+        metrics::set_leader(&self.instance_id, leader);
+        if was_leader == leader {
+            return;
+        }
+        metrics::record_leader_transition(leader);
+        if leader {
+            *self.since.lock() = Some(Utc::now());
+            tracing::info!("👑 Instance {} is now the background job leader", self.instance_id);
+        } else {
+            *self.since.lock() = None;
+            tracing::warn!("Instance {} is no longer the background job leader", self.instance_id);
+        }
+    }
+}
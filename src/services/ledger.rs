@@ -0,0 +1,562 @@
+//! Balance Ledger
+//!
+//! Every balance mutation goes through [`LedgerService::apply`], which updates
+//! `balances` and appends a journal row to `balance_ledger` in the caller's
+//! transaction. Each row is posted double-entry to `ledger_postings`: the
+//! user's available and frozen legs, and a counter-leg on the system account
+//! the funds came from or went to ([`SystemAccount`]), whose balance is kept
+//! in `ledger_accounts`:
+//!
+//! | Change          | available | frozen | system account |
+//! |-----------------|-----------|--------|----------------|
+//! | credit          | +x        |        | -x             |
+//! | debit           | -x        |        | +x             |
+//! | freeze          | -x        | +x     |                |
+//! | unfreeze        | +x        | -x     |                |
+//! | settle frozen   |           | -x     | +x             |
+//!
+//! The legs of an entry sum to zero; a deferred constraint trigger refuses
+//! to commit a transaction holding an unbalanced entry. Transfers between
+//! users (trades, vault deposits, sub-account transfers, ...) post both
+//! sides against the clearing account, which nets to zero once both legs
+//! are in.
+//!
+//! Rows keep the balances before and after, so an account's history can be
+//! replayed and checked against `balances`.
+//!
+//! Every applied change publishes the new balance as a [`BalanceEvent`],
+//! delivered when the transaction commits.
+//!
+//! Changes that must land exactly once (deposit credits) carry an idempotency
+//! key; a unique index on `balance_ledger.idempotency_key` rejects a second
+//! row for the same key.
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use std::str::FromStr;
+use uuid::Uuid;
+
+use crate::services::user_events::{self, BalanceEvent};
+
+/// Why a balance changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerReason {
+    OrderFreeze,
+    OrderUnfreeze,
+    WithdrawalFreeze,
+    WithdrawalUnfreeze,
+    Withdrawal,
+    WithdrawalFee,
+    RealizedPnl,
+    Funding,
+    LiquidationFee,
+    MarketSettlement,
+    VaultDeposit,
+    VaultWithdrawal,
+    RfqFill,
+    ReferralClaim,
+    ReferralPayout,
+    Deposit,
+    DepositReversal,
+    SubAccountTransfer,
+}
+
+impl LedgerReason {
+    pub const ALL: [LedgerReason; 18] = [
+        LedgerReason::OrderFreeze,
+        LedgerReason::OrderUnfreeze,
+        LedgerReason::WithdrawalFreeze,
+        LedgerReason::WithdrawalUnfreeze,
+        LedgerReason::Withdrawal,
+        LedgerReason::WithdrawalFee,
+        LedgerReason::RealizedPnl,
+        LedgerReason::Funding,
+        LedgerReason::LiquidationFee,
+        LedgerReason::MarketSettlement,
+        LedgerReason::VaultDeposit,
+        LedgerReason::VaultWithdrawal,
+        LedgerReason::RfqFill,
+        LedgerReason::ReferralClaim,
+        LedgerReason::ReferralPayout,
+        LedgerReason::Deposit,
+        LedgerReason::DepositReversal,
+        LedgerReason::SubAccountTransfer,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LedgerReason::OrderFreeze => "order_freeze",
+            LedgerReason::OrderUnfreeze => "order_unfreeze",
+            LedgerReason::WithdrawalFreeze => "withdrawal_freeze",
+            LedgerReason::WithdrawalUnfreeze => "withdrawal_unfreeze",
+            LedgerReason::Withdrawal => "withdrawal",
+            LedgerReason::WithdrawalFee => "withdrawal_fee",
+            LedgerReason::RealizedPnl => "realized_pnl",
+            LedgerReason::Funding => "funding",
+            LedgerReason::LiquidationFee => "liquidation_fee",
+            LedgerReason::MarketSettlement => "market_settlement",
+            LedgerReason::VaultDeposit => "vault_deposit",
+            LedgerReason::VaultWithdrawal => "vault_withdrawal",
+            LedgerReason::RfqFill => "rfq_fill",
+            LedgerReason::ReferralClaim => "referral_claim",
+            LedgerReason::ReferralPayout => "referral_payout",
+            LedgerReason::Deposit => "deposit",
+            LedgerReason::DepositReversal => "deposit_reversal",
+            LedgerReason::SubAccountTransfer => "sub_account_transfer",
+        }
+    }
+}
+
+impl FromStr for LedgerReason {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LedgerReason::ALL
+            .into_iter()
+            .find(|reason| reason.as_str() == s)
+            .ok_or_else(|| format!("Unknown ledger reason: {}", s))
+    }
+}
+
+/// Platform account on the other side of a balance change
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemAccount {
+    /// Funds entering or leaving the platform on chain
+    Chain,
+    /// Platform income and expenses (fees, referral rewards)
+    Treasury,
+    /// Liquidation fees
+    InsuranceFund,
+    /// Transfers between users (trades, settlements, vaults, sub-accounts)
+    Clearing,
+}
+
+impl SystemAccount {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SystemAccount::Chain => "system:chain",
+            SystemAccount::Treasury => "system:treasury",
+            SystemAccount::InsuranceFund => "system:insurance_fund",
+            SystemAccount::Clearing => "system:clearing",
+        }
+    }
+
+    /// Counter-account of changes made for `reason`
+    pub fn for_reason(reason: LedgerReason) -> Self {
+        match reason {
+            LedgerReason::Deposit | LedgerReason::DepositReversal | LedgerReason::Withdrawal => SystemAccount::Chain,
+            LedgerReason::WithdrawalFee | LedgerReason::ReferralClaim | LedgerReason::ReferralPayout => {
+                SystemAccount::Treasury
+            }
+            LedgerReason::LiquidationFee => SystemAccount::InsuranceFund,
+            LedgerReason::OrderFreeze
+            | LedgerReason::OrderUnfreeze
+            | LedgerReason::WithdrawalFreeze
+            | LedgerReason::WithdrawalUnfreeze
+            | LedgerReason::RealizedPnl
+            | LedgerReason::Funding
+            | LedgerReason::MarketSettlement
+            | LedgerReason::VaultDeposit
+            | LedgerReason::VaultWithdrawal
+            | LedgerReason::RfqFill
+            | LedgerReason::SubAccountTransfer => SystemAccount::Clearing,
+        }
+    }
+}
+
+/// One leg of a journal entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct LedgerLeg {
+    /// User address or system account
+    pub account: String,
+    /// `available`, `frozen` or `system`
+    pub bucket: &'static str,
+    pub amount: Decimal,
+}
+
+/// A balance mutation, as deltas to the available and frozen buckets
+#[derive(Debug, Clone)]
+pub struct BalanceChange {
+    pub user_address: String,
+    pub token: String,
+    pub available: Decimal,
+    pub frozen: Decimal,
+    pub reason: LedgerReason,
+    /// Order, withdrawal, trade, market, ... the change belongs to
+    pub reference_id: Option<Uuid>,
+    /// Refuse the change if it would take available below zero
+    pub checked: bool,
+    /// Apply at most once per key
+    pub idempotency_key: Option<String>,
+}
+
+impl BalanceChange {
+    fn new(user_address: &str, token: &str, available: Decimal, frozen: Decimal, reason: LedgerReason) -> Self {
+        Self {
+            user_address: user_address.to_lowercase(),
+            token: token.to_string(),
+            available,
+            frozen,
+            reason,
+            reference_id: None,
+            checked: false,
+            idempotency_key: None,
+        }
+    }
+
+    /// Funds entering the account (negative amounts debit)
+    pub fn credit(user_address: &str, token: &str, amount: Decimal, reason: LedgerReason) -> Self {
+        Self::new(user_address, token, amount, Decimal::ZERO, reason)
+    }
+
+    /// Funds leaving the account's available balance
+    pub fn debit(user_address: &str, token: &str, amount: Decimal, reason: LedgerReason) -> Self {
+        Self::new(user_address, token, -amount, Decimal::ZERO, reason)
+    }
+
+    /// Available → frozen
+    pub fn freeze(user_address: &str, token: &str, amount: Decimal, reason: LedgerReason) -> Self {
+        Self::new(user_address, token, -amount, amount, reason)
+    }
+
+    /// Frozen → available
+    pub fn unfreeze(user_address: &str, token: &str, amount: Decimal, reason: LedgerReason) -> Self {
+        Self::new(user_address, token, amount, -amount, reason)
+    }
+
+    /// Frozen funds leaving the account
+    pub fn settle_frozen(user_address: &str, token: &str, amount: Decimal, reason: LedgerReason) -> Self {
+        Self::new(user_address, token, Decimal::ZERO, -amount, reason)
+    }
+
+    pub fn reference(mut self, reference_id: Uuid) -> Self {
+        self.reference_id = Some(reference_id);
+        self
+    }
+
+    pub fn checked(mut self) -> Self {
+        self.checked = true;
+        self
+    }
+
+    pub fn idempotency_key(mut self, key: impl Into<String>) -> Self {
+        self.idempotency_key = Some(key.into());
+        self
+    }
+
+    /// Amount posted to the system account: the negated user legs
+    pub fn external(&self) -> Decimal {
+        -(self.available + self.frozen)
+    }
+
+    /// Non-zero legs of the change, summing to zero
+    pub fn legs(&self) -> Vec<LedgerLeg> {
+        let legs = [
+            (self.user_address.as_str(), "available", self.available),
+            (self.user_address.as_str(), "frozen", self.frozen),
+            (SystemAccount::for_reason(self.reason).as_str(), "system", self.external()),
+        ];
+        legs.into_iter()
+            .filter(|(_, _, amount)| !amount.is_zero())
+            .map(|(account, bucket, amount)| LedgerLeg {
+                account: account.to_string(),
+                bucket,
+                amount,
+            })
+            .collect()
+    }
+}
+
+/// Journal row
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct LedgerEntry {
+    pub id: Uuid,
+    pub token: String,
+    pub reason: String,
+    pub reference_id: Option<Uuid>,
+    pub available_delta: Decimal,
+    pub frozen_delta: Decimal,
+    pub external_delta: Decimal,
+    pub available_before: Decimal,
+    pub available_after: Decimal,
+    pub frozen_before: Decimal,
+    pub frozen_after: Decimal,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Ledger page filter
+#[derive(Debug, Clone, Default)]
+pub struct LedgerFilter {
+    pub token: Option<String>,
+    pub reason: Option<LedgerReason>,
+}
+
+/// Single entry point for balance mutations
+pub struct LedgerService;
+
+impl LedgerService {
+    /// Apply `change` and journal it; None if a checked change would overdraw
+    /// the available balance, or its idempotency key is already journaled
+    /// (nothing is written). Run it in the transaction that owns the change so
+    /// the journal row commits with it.
+    pub async fn apply(conn: &mut PgConnection, change: &BalanceChange) -> Result<Option<LedgerEntry>, sqlx::Error> {
+        if let Some(key) = &change.idempotency_key {
+            // A concurrent duplicate still fails on the unique index
+            let applied: bool =
+                sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM balance_ledger WHERE idempotency_key = $1)")
+                    .bind(key)
+                    .fetch_one(&mut *conn)
+                    .await?;
+            if applied {
+                return Ok(None);
+            }
+        }
+
+        let balance: Option<(Decimal, Decimal)> = if change.checked {
+            // An account without a balance row has nothing available
+            sqlx::query_as(
+                r#"
+                UPDATE balances
+                SET available = available + $3, frozen = frozen + $4, updated_at = NOW()
+                WHERE user_address = $1 AND token = $2 AND available + $3 >= 0
+                RETURNING available, frozen
+                "#,
+            )
+            .bind(&change.user_address)
+            .bind(&change.token)
+            .bind(change.available)
+            .bind(change.frozen)
+            .fetch_optional(&mut *conn)
+            .await?
+        } else {
+            let balance = sqlx::query_as(
+                r#"
+                INSERT INTO balances (user_address, token, available, frozen)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (user_address, token) DO UPDATE SET
+                    available = balances.available + $3,
+                    frozen = balances.frozen + $4,
+                    updated_at = NOW()
+                RETURNING available, frozen
+                "#,
+            )
+            .bind(&change.user_address)
+            .bind(&change.token)
+            .bind(change.available)
+            .bind(change.frozen)
+            .fetch_one(&mut *conn)
+            .await?;
+            Some(balance)
+        };
+        let Some((available_after, frozen_after)) = balance else {
+            return Ok(None);
+        };
+        let (available_before, frozen_before) = (available_after - change.available, frozen_after - change.frozen);
+
+        let entry: LedgerEntry = sqlx::query_as(
+            r#"
+            INSERT INTO balance_ledger (
+                user_address, token, reason, reference_id,
+                available_delta, frozen_delta, external_delta,
+                available_before, available_after, frozen_before, frozen_after, idempotency_key
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            RETURNING id, token, reason, reference_id, available_delta, frozen_delta, external_delta,
+                      available_before, available_after, frozen_before, frozen_after, created_at
+            "#,
+        )
+        .bind(&change.user_address)
+        .bind(&change.token)
+        .bind(change.reason.as_str())
+        .bind(change.reference_id)
+        .bind(change.available)
+        .bind(change.frozen)
+        .bind(change.external())
+        .bind(available_before)
+        .bind(available_after)
+        .bind(frozen_before)
+        .bind(frozen_after)
+        .bind(&change.idempotency_key)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Self::post_legs(&mut *conn, &entry, change).await?;
+
+        let event = BalanceEvent {
+            user_address: change.user_address.clone(),
+            token: change.token.clone(),
+            available: available_after,
+            frozen: frozen_after,
+            reason: change.reason,
+        };
+        user_events::publish(&mut *conn, &event).await?;
+
+        Ok(Some(entry))
+    }
+
+    /// Post the legs of a journal entry and move the system account's balance
+    async fn post_legs(conn: &mut PgConnection, entry: &LedgerEntry, change: &BalanceChange) -> Result<(), sqlx::Error> {
+        let legs = change.legs();
+        debug_assert_eq!(legs.iter().map(|leg| leg.amount).sum::<Decimal>(), Decimal::ZERO);
+        for leg in &legs {
+            sqlx::query(
+                r#"
+                INSERT INTO ledger_postings (entry_id, account, bucket, token, amount)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+            )
+            .bind(entry.id)
+            .bind(&leg.account)
+            .bind(leg.bucket)
+            .bind(&change.token)
+            .bind(leg.amount)
+            .execute(&mut *conn)
+            .await?;
+
+            if leg.bucket == "system" {
+                sqlx::query(
+                    r#"
+                    INSERT INTO ledger_accounts (account, token, balance)
+                    VALUES ($1, $2, $3)
+                    ON CONFLICT (account, token) DO UPDATE SET
+                        balance = ledger_accounts.balance + $3,
+                        updated_at = NOW()
+                    "#,
+                )
+                .bind(&leg.account)
+                .bind(&change.token)
+                .bind(leg.amount)
+                .execute(&mut *conn)
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply a standalone change in its own transaction
+    pub async fn post(pool: &PgPool, change: &BalanceChange) -> Result<Option<LedgerEntry>, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        let entry = Self::apply(&mut tx, change).await?;
+        tx.commit().await?;
+        Ok(entry)
+    }
+
+    /// A user's journal, newest first, with the total matching the filter
+    pub async fn get_entries(
+        pool: &PgPool,
+        user_address: &str,
+        filter: &LedgerFilter,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<LedgerEntry>, i64), sqlx::Error> {
+        let user_address = user_address.to_lowercase();
+        let reason = filter.reason.map(|r| r.as_str());
+
+        let entries = sqlx::query_as(
+            r#"
+            SELECT id, token, reason, reference_id, available_delta, frozen_delta, external_delta,
+                   available_before, available_after, frozen_before, frozen_after, created_at
+            FROM balance_ledger
+            WHERE user_address = $1
+              AND ($2::text IS NULL OR token = $2)
+              AND ($3::text IS NULL OR reason = $3)
+            ORDER BY created_at DESC, id DESC
+            LIMIT $4 OFFSET $5
+            "#,
+        )
+        .bind(&user_address)
+        .bind(&filter.token)
+        .bind(reason)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await?;
+
+        let total: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM balance_ledger
+            WHERE user_address = $1
+              AND ($2::text IS NULL OR token = $2)
+              AND ($3::text IS NULL OR reason = $3)
+            "#,
+        )
+        .bind(&user_address)
+        .bind(&filter.token)
+        .bind(reason)
+        .fetch_one(pool)
+        .await?;
+
+        Ok((entries, total))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_changes_balance_against_external_leg() {
+        let changes = [
+            BalanceChange::credit("0xA", "USDC", dec!(5), LedgerReason::RealizedPnl),
+            BalanceChange::debit("0xa", "USDC", dec!(5), LedgerReason::LiquidationFee),
+            BalanceChange::freeze("0xa", "USDC", dec!(5), LedgerReason::OrderFreeze),
+            BalanceChange::unfreeze("0xa", "USDC", dec!(5), LedgerReason::OrderUnfreeze),
+            BalanceChange::settle_frozen("0xa", "USDC", dec!(5), LedgerReason::Withdrawal),
+        ];
+        let externals: Vec<Decimal> = changes.iter().map(BalanceChange::external).collect();
+        assert_eq!(externals, vec![dec!(-5), dec!(5), dec!(0), dec!(0), dec!(5)]);
+        for change in &changes {
+            assert_eq!(change.available + change.frozen + change.external(), Decimal::ZERO);
+            assert_eq!(change.user_address, "0xa");
+        }
+    }
+
+    #[test]
+    fn test_posts_balanced_legs_against_system_accounts() {
+        let fee = BalanceChange::debit("0xa", "USDC", dec!(2), LedgerReason::LiquidationFee);
+        assert_eq!(
+            fee.legs(),
+            vec![
+                LedgerLeg { account: "0xa".to_string(), bucket: "available", amount: dec!(-2) },
+                LedgerLeg { account: "system:insurance_fund".to_string(), bucket: "system", amount: dec!(2) },
+            ]
+        );
+
+        // A freeze moves funds inside the account: no system leg
+        let freeze = BalanceChange::freeze("0xa", "USDC", dec!(5), LedgerReason::OrderFreeze);
+        assert_eq!(freeze.legs().iter().map(|leg| leg.bucket).collect::<Vec<_>>(), vec!["available", "frozen"]);
+
+        let withdrawal = BalanceChange::settle_frozen("0xa", "USDC", dec!(5), LedgerReason::Withdrawal);
+        assert_eq!(withdrawal.legs()[1].account, "system:chain");
+
+        for reason in LedgerReason::ALL {
+            let change = BalanceChange::credit("0xa", "USDC", dec!(3), reason);
+            assert_eq!(change.legs().iter().map(|leg| leg.amount).sum::<Decimal>(), Decimal::ZERO);
+        }
+    }
+
+    #[test]
+    fn test_transfers_between_users_net_out_in_clearing() {
+        let buyer = BalanceChange::debit("0xa", "USDC", dec!(7), LedgerReason::RfqFill);
+        let seller = BalanceChange::credit("0xb", "USDC", dec!(7), LedgerReason::RfqFill);
+        let clearing: Decimal = [buyer, seller]
+            .iter()
+            .flat_map(BalanceChange::legs)
+            .filter(|leg| leg.account == SystemAccount::Clearing.as_str())
+            .map(|leg| leg.amount)
+            .sum();
+        assert_eq!(clearing, Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_reasons_round_trip() {
+        for reason in LedgerReason::ALL {
+            assert_eq!(reason.as_str().parse::<LedgerReason>(), Ok(reason));
+        }
+        assert!("deposit_bonus".parse::<LedgerReason>().is_err());
+    }
+}
@@ -0,0 +1,701 @@
+//! Liquidation Engine
+//!
+//! Periodically checks accounts with cross-margined positions and liquidates
+//! those whose cross pool is at or beyond maintenance margin (a margin ratio
+//! of 1 or more). Isolated long positions are backed by their own cost basis
+//! and can never breach maintenance, so only the cross pool is considered.
+//!
+//! Liquidations are partial: positions are closed largest first, selling only
+//! enough shares (market sell through the matching engine) to bring the
+//! pool's margin ratio back down to `target_margin_ratio`. Closing `n` of
+//! notional releases `n * mmr` of maintenance margin; a `penalty_ratio` share
+//! of that released margin is charged as the liquidation fee and routed to the
+//! outcome's insurance fund. Since the fee is a fraction of the released
+//! margin, a partial close always improves the ratio.
+//!
+//! Each liquidation is recorded in `liquidations` as `pending` before its
+//! order is submitted and finalized (`executed` / `failed`) exactly once;
+//! accounts with a pending liquidation are skipped until it resolves. A
+//! liquidation still pending after [`PENDING_TIMEOUT_SECS`] (the process died
+//! or failed between submitting and finalizing) is reconciled at the start of
+//! the next cycle against what its order filled: forced orders never rest in
+//! the book, so the `orders` row and the trades taken by the order are the
+//! whole record. In dry-run mode the plan is only logged and counted.
+//!
+//! Executed liquidations are announced to the account on the notification
+//! channel (WebSocket `notifications`).
+
+use chrono::Utc;
+use rust_decimal::{Decimal, RoundingStrategy};
+use sqlx::PgPool;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::metrics;
+use crate::services::ledger::{BalanceChange, LedgerReason, LedgerService};
+use crate::services::market::MarketService;
+use crate::services::matching::{ForcedTrade, MatchResult, MatchingEngine, Side};
+use crate::services::notifications::{LiquidationNotice, Notification, NotificationEvent};
+use crate::services::position::{MarginMode, PositionError, PositionMargin, PositionService};
+
+/// Share amounts are stored with 8 decimal places
+const SHARE_DECIMALS: u32 = 8;
+
+/// Age after which a pending liquidation is reconciled instead of blocking
+/// its account
+pub const PENDING_TIMEOUT_SECS: i64 = 300;
+
+/// Liquidation errors
+#[derive(Debug, thiserror::Error)]
+pub enum LiquidationError {
+    #[error("Position error: {0}")]
+    PositionError(#[from] PositionError),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// Liquidation settings
+#[derive(Debug, Clone)]
+pub struct LiquidationSettings {
+    /// Log planned liquidations without executing them
+    pub dry_run: bool,
+    /// Maintenance rate of markets without risk-limit tiers
+    pub maintenance_margin_rate: Decimal,
+    /// Margin ratio a partial liquidation restores the pool to
+    pub target_margin_ratio: Decimal,
+    /// Share of the released maintenance margin charged as the liquidation fee
+    pub penalty_ratio: Decimal,
+    /// Collateral token
+    pub token: String,
+}
+
+/// One position close in a liquidation plan
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiquidationStep {
+    pub position_id: Uuid,
+    pub market_id: Uuid,
+    pub outcome_id: Uuid,
+    pub symbol: String,
+    /// Shares to sell
+    pub amount: Decimal,
+    /// Position size before the close
+    pub position_amount: Decimal,
+    pub avg_cost: Decimal,
+    pub mark_price: Decimal,
+    pub liquidation_price: Option<Decimal>,
+    /// Maintenance rate of the position's risk-limit tier
+    pub maintenance_margin_rate: Decimal,
+}
+
+impl LiquidationStep {
+    pub fn is_partial(&self) -> bool {
+        self.amount < self.position_amount
+    }
+
+    /// Notice of the executed close; partial whenever shares are left, also
+    /// when a planned full close filled short
+    pub fn notice(
+        &self,
+        liquidation_id: Uuid,
+        order_id: Uuid,
+        filled_amount: Decimal,
+        price: Decimal,
+        fee: Decimal,
+        realized_pnl: Decimal,
+    ) -> LiquidationNotice {
+        LiquidationNotice {
+            liquidation_id,
+            order_id,
+            symbol: self.symbol.clone(),
+            market_id: self.market_id,
+            outcome_id: self.outcome_id,
+            filled_amount,
+            position_amount: self.position_amount,
+            price,
+            mark_price: self.mark_price,
+            fee,
+            realized_pnl,
+            is_partial: filled_amount < self.position_amount,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+/// Outcome of a liquidation cycle
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LiquidationReport {
+    pub accounts_checked: usize,
+    pub accounts_liquidated: usize,
+    pub planned: usize,
+    pub executed: usize,
+    pub failed: usize,
+    /// Closes deferred because the market is outside its trading session
+    pub skipped: usize,
+    /// Stale pending liquidations reconciled
+    pub recovered: usize,
+}
+
+/// How a stale pending liquidation resolves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StaleResolution {
+    pub executed: bool,
+    pub filled_amount: Decimal,
+    /// Average fill price, when trades were recorded
+    pub price: Option<Decimal>,
+}
+
+/// Resolve a stale pending liquidation from its persisted order's filled
+/// amount (None if it was never persisted) and the amount and notional of
+/// the trades it took. Anything filled means the close happened.
+pub fn resolve_stale(order_filled: Option<Decimal>, traded: Decimal, traded_notional: Decimal) -> StaleResolution {
+    let filled_amount = order_filled.unwrap_or(Decimal::ZERO).max(traded);
+    StaleResolution {
+        executed: filled_amount > Decimal::ZERO,
+        filled_amount,
+        price: (traded > Decimal::ZERO).then(|| traded_notional / traded),
+    }
+}
+
+/// Plan the position closes that bring a cross pool from at/over maintenance
+/// back to `target_ratio`
+///
+/// For the pool (equity `E`, maintenance `M`), closing notional `n` of a
+/// position gives `M' = M - r n` and `E' = E - s r n` (`r` the position's
+/// maintenance rate, `s` penalty ratio), reducing the excess `M - t E` by
+/// `r (1 - t s) n`. Positions are closed largest value first, each covering as
+/// much of the excess as it can at its own rate; if the pool cannot be
+/// restored every cross position is closed in full. Empty if the pool is
+/// healthy.
+pub fn plan_liquidation(
+    collateral: Decimal,
+    positions: &[PositionMargin],
+    target_ratio: Decimal,
+    penalty_ratio: Decimal,
+) -> Vec<LiquidationStep> {
+    let mut cross: Vec<&PositionMargin> = positions
+        .iter()
+        .filter(|p| p.margin_mode == MarginMode::Cross && p.amount > Decimal::ZERO)
+        .collect();
+    let value: Decimal = cross.iter().map(|p| p.value).sum();
+    let maintenance: Decimal = cross.iter().map(|p| p.maintenance_margin).sum();
+    let equity = collateral + value;
+
+    if maintenance.is_zero() || (equity > Decimal::ZERO && maintenance < equity) {
+        return Vec::new();
+    }
+
+    // Without equity nothing can be restored: close everything
+    let close_all = equity <= Decimal::ZERO;
+    let mut excess = maintenance - target_ratio * equity;
+
+    cross.sort_by_key(|p| std::cmp::Reverse(p.value));
+    let mut steps = Vec::new();
+    for p in cross {
+        if !close_all && excess <= Decimal::ZERO {
+            break;
+        }
+        let relief = p.maintenance_margin_rate * (Decimal::ONE - target_ratio * penalty_ratio);
+        let needed = if close_all || relief <= Decimal::ZERO { p.value } else { excess / relief };
+        let amount = if needed >= p.value || p.mark_price <= Decimal::ZERO {
+            p.amount
+        } else {
+            (needed / p.mark_price)
+                .round_dp_with_strategy(SHARE_DECIMALS, RoundingStrategy::AwayFromZero)
+                .min(p.amount)
+        };
+        excess -= amount * p.mark_price * relief;
+        steps.push(LiquidationStep {
+            position_id: p.id,
+            market_id: p.market_id,
+            outcome_id: p.outcome_id,
+            symbol: format!("{}:{}:{}", p.market_id, p.outcome_id, p.share_type),
+            amount,
+            position_amount: p.amount,
+            avg_cost: p.avg_cost,
+            mark_price: p.mark_price,
+            liquidation_price: p.liquidation_price,
+            maintenance_margin_rate: p.maintenance_margin_rate,
+        });
+    }
+    steps
+}
+
+/// Liquidation service
+pub struct LiquidationService;
+
+impl LiquidationService {
+    /// Check every account with cross positions and liquidate those under water
+    pub async fn run_cycle(
+        pool: &PgPool,
+        engine: &MatchingEngine,
+        markets: &MarketService,
+        settings: &LiquidationSettings,
+        notifier: &broadcast::Sender<NotificationEvent>,
+    ) -> Result<LiquidationReport, LiquidationError> {
+        let recovered = Self::recover_stale(pool).await?;
+
+        // A pending liquidation that outlived the timeout doesn't block its
+        // account, even if reconciling it failed
+        let accounts: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT DISTINCT s.user_address
+            FROM shares s
+            JOIN users u ON u.address = s.user_address
+            WHERE s.amount > 0 AND COALESCE(s.margin_mode, u.margin_mode) = 'cross'
+              AND NOT EXISTS (
+                  SELECT 1 FROM liquidations l
+                  WHERE l.user_address = s.user_address AND l.status = 'pending'
+                    AND l.created_at > NOW() - make_interval(secs => $1)
+              )
+            "#,
+        )
+        .bind(PENDING_TIMEOUT_SECS as f64)
+        .fetch_all(pool)
+        .await?;
+
+        let mut report = LiquidationReport {
+            accounts_checked: accounts.len(),
+            recovered,
+            ..Default::default()
+        };
+
+        for user_address in accounts {
+            let account = PositionService::get_account_margin(
+                pool,
+                engine,
+                markets,
+                &user_address,
+                &settings.token,
+                settings.maintenance_margin_rate,
+            )
+            .await?;
+
+            let steps = plan_liquidation(
+                account.collateral,
+                &account.positions,
+                settings.target_margin_ratio,
+                settings.penalty_ratio,
+            );
+            if steps.is_empty() {
+                continue;
+            }
+
+            report.accounts_liquidated += 1;
+            report.planned += steps.len();
+            tracing::warn!(
+                "Account {} under maintenance (margin ratio {}): {} position closes planned{}",
+                user_address,
+                account.margin_ratio,
+                steps.len(),
+                if settings.dry_run { " (dry run)" } else { "" }
+            );
+
+            // The fee can never exceed what the account has left
+            let cross_value: Decimal = account
+                .positions
+                .iter()
+                .filter(|p| p.margin_mode == MarginMode::Cross)
+                .map(|p| p.value)
+                .sum();
+            let mut fee_budget = (account.collateral + cross_value).max(Decimal::ZERO);
+
+            for step in steps {
+                if settings.dry_run {
+                    metrics::record_liquidation("dry_run");
+                    tracing::warn!(
+                        "[dry run] Would sell {} of {} shares of {} for {} at mark {}",
+                        step.amount, step.position_amount, step.symbol, user_address, step.mark_price
+                    );
+                    continue;
+                }
+
+                // Outside the trading session: retried on a later cycle once open
+                if engine.check_session(&step.symbol).is_err() {
+                    report.skipped += 1;
+                    tracing::debug!("Market closed, deferring liquidation of {} on {}", user_address, step.symbol);
+                    continue;
+                }
+
+                match Self::execute_step(pool, engine, settings, &user_address, account.collateral, &step, &mut fee_budget).await {
+                    Ok(Some(notice)) => {
+                        report.executed += 1;
+                        // No receivers when nobody is connected
+                        let _ = notifier.send(NotificationEvent {
+                            user_address: user_address.to_lowercase(),
+                            notification: Notification::Liquidation(notice),
+                        });
+                    }
+                    Ok(None) => report.executed += 1,
+                    Err(e) => {
+                        report.failed += 1;
+                        tracing::error!("Liquidation of {} on {} failed: {}", user_address, step.symbol, e);
+                    }
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Finalize liquidations pending longer than [`PENDING_TIMEOUT_SECS`]
+    /// from what their orders filled; returns how many were resolved. The
+    /// fee of a close recovered this way is not charged.
+    pub async fn recover_stale(pool: &PgPool) -> Result<usize, LiquidationError> {
+        let stale: Vec<(Uuid, Option<Uuid>, String)> = sqlx::query_as(
+            r#"
+            SELECT id, order_id, user_address
+            FROM liquidations
+            WHERE status = 'pending' AND created_at <= NOW() - make_interval(secs => $1)
+            ORDER BY created_at
+            "#,
+        )
+        .bind(PENDING_TIMEOUT_SECS as f64)
+        .fetch_all(pool)
+        .await?;
+
+        let mut recovered = 0;
+        for (liquidation_id, order_id, user_address) in stale {
+            let order_filled: Option<Decimal> = match order_id {
+                Some(order_id) => sqlx::query_scalar("SELECT filled_amount FROM orders WHERE id = $1")
+                    .bind(order_id)
+                    .fetch_optional(pool)
+                    .await?,
+                None => None,
+            };
+            let (traded, traded_notional): (Decimal, Decimal) = sqlx::query_as(
+                "SELECT COALESCE(SUM(amount), 0), COALESCE(SUM(amount * price), 0) FROM trades WHERE taker_order_id = $1",
+            )
+            .bind(order_id)
+            .fetch_one(pool)
+            .await?;
+            let resolution = resolve_stale(order_filled, traded, traded_notional);
+            let status = if resolution.executed { "executed" } else { "failed" };
+
+            let updated = sqlx::query(
+                r#"
+                UPDATE liquidations SET
+                    status = $2,
+                    filled_amount = $3,
+                    pnl = COALESCE(($4 - entry_price) * $3, pnl)
+                WHERE id = $1 AND status = 'pending'
+                "#,
+            )
+            .bind(liquidation_id)
+            .bind(status)
+            .bind(resolution.filled_amount)
+            .bind(resolution.price)
+            .execute(pool)
+            .await?
+            .rows_affected();
+            if updated == 0 {
+                continue;
+            }
+            recovered += 1;
+            metrics::record_liquidation(status);
+            tracing::warn!(
+                "Recovered stale liquidation {} of {}: {} ({} filled, fee not charged)",
+                liquidation_id,
+                user_address,
+                status,
+                resolution.filled_amount
+            );
+        }
+        Ok(recovered)
+    }
+
+    /// Record, submit and finalize one position close; the notice of the
+    /// close if this call finalized it
+    async fn execute_step(
+        pool: &PgPool,
+        engine: &MatchingEngine,
+        settings: &LiquidationSettings,
+        user_address: &str,
+        collateral: Decimal,
+        step: &LiquidationStep,
+        fee_budget: &mut Decimal,
+    ) -> Result<Option<LiquidationNotice>, LiquidationError> {
+        let order_id = Uuid::new_v4();
+
+        let liquidation_id: Uuid = sqlx::query_scalar(
+            r#"
+            INSERT INTO liquidations (
+                share_id, market_id, outcome_id, order_id, user_address, symbol, side,
+                position_size_usd, position_size_tokens, collateral_amount, entry_price,
+                liquidation_price, mark_price, remaining_collateral, pnl,
+                target_amount, is_partial, status
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, 'long', $7, $8, $9, $10, $11, $12, $9, 0, $13, $14, 'pending')
+            RETURNING id
+            "#,
+        )
+        .bind(step.position_id)
+        .bind(step.market_id)
+        .bind(step.outcome_id)
+        .bind(order_id)
+        .bind(user_address)
+        .bind(&step.symbol)
+        .bind(step.position_amount * step.mark_price)
+        .bind(step.position_amount)
+        .bind(collateral)
+        .bind(step.avg_cost)
+        .bind(step.liquidation_price.unwrap_or(step.mark_price))
+        .bind(step.mark_price)
+        .bind(step.amount)
+        .bind(step.is_partial())
+        .fetch_one(pool)
+        .await?;
+
+        let result = match engine.submit_forced_order(
+            order_id,
+            &step.symbol,
+            user_address,
+            Side::Sell,
+            step.amount,
+            ForcedTrade::Liquidation,
+        ) {
+            Ok(result) => result,
+            Err(e) => {
+                sqlx::query("UPDATE liquidations SET status = 'failed' WHERE id = $1 AND status = 'pending'")
+                    .bind(liquidation_id)
+                    .execute(pool)
+                    .await?;
+                metrics::record_liquidation("failed");
+                tracing::error!("Engine rejected liquidation order {} for {}: {}", order_id, step.symbol, e);
+                return Ok(None);
+            }
+        };
+
+        Self::persist_order(pool, step, user_address, &result).await?;
+
+        let price = result.average_price.unwrap_or(step.mark_price);
+        let notional = result.filled_amount * price;
+        let fee = (notional * step.maintenance_margin_rate * settings.penalty_ratio).min(*fee_budget);
+        let pnl = (price - step.avg_cost) * result.filled_amount;
+
+        let mut tx = pool.begin().await?;
+
+        // The budget counts cross positions whose proceeds arrive later: never
+        // charge more than is available now
+        let available: Option<Decimal> =
+            sqlx::query_scalar("SELECT available FROM balances WHERE user_address = $1 AND token = $2 FOR UPDATE")
+                .bind(user_address)
+                .bind(&settings.token)
+                .fetch_optional(&mut *tx)
+                .await?;
+        let fee = fee.min(available.unwrap_or_default().max(Decimal::ZERO));
+
+        // Finalize exactly once
+        let finalized = sqlx::query(
+            r#"
+            UPDATE liquidations SET
+                status = 'executed',
+                filled_amount = $2,
+                liquidation_fee = $3,
+                insurance_fund_contribution = $3,
+                pnl = $4,
+                remaining_collateral = $5
+            WHERE id = $1 AND status = 'pending'
+            "#,
+        )
+        .bind(liquidation_id)
+        .bind(result.filled_amount)
+        .bind(fee)
+        .bind(pnl)
+        .bind(collateral + notional - fee)
+        .execute(&mut *tx)
+        .await?;
+        if finalized.rows_affected() == 0 {
+            return Ok(None);
+        }
+
+        if fee > Decimal::ZERO {
+            let change = BalanceChange::debit(user_address, &settings.token, fee, LedgerReason::LiquidationFee)
+                .reference(liquidation_id);
+            LedgerService::apply(&mut tx, &change).await?;
+
+            let balance_after: Decimal = sqlx::query_scalar(
+                r#"
+                INSERT INTO insurance_fund (symbol, balance, total_contributions)
+                VALUES ($1, $2, $2)
+                ON CONFLICT (symbol) DO UPDATE SET
+                    balance = insurance_fund.balance + $2,
+                    total_contributions = insurance_fund.total_contributions + $2,
+                    updated_at = NOW()
+                RETURNING balance
+                "#,
+            )
+            .bind(&step.symbol)
+            .bind(fee)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            sqlx::query(
+                r#"
+                INSERT INTO insurance_fund_transactions (symbol, transaction_type, amount, balance_after, liquidation_id)
+                VALUES ($1, 'contribution', $2, $3, $4)
+                "#,
+            )
+            .bind(&step.symbol)
+            .bind(fee)
+            .bind(balance_after)
+            .bind(liquidation_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        *fee_budget -= fee;
+
+        metrics::record_liquidation("executed");
+        tracing::warn!(
+            "Liquidated {} of {} shares of {} for {} at {} (fee {} to insurance fund{})",
+            result.filled_amount,
+            step.position_amount,
+            step.symbol,
+            user_address,
+            price,
+            fee,
+            if step.is_partial() { ", partial" } else { "" }
+        );
+        Ok(Some(step.notice(liquidation_id, order_id, result.filled_amount, price, fee, pnl)))
+    }
+
+    /// Persist the liquidation order; any unfilled remainder of the market order is cancelled
+    async fn persist_order(
+        pool: &PgPool,
+        step: &LiquidationStep,
+        user_address: &str,
+        result: &MatchResult,
+    ) -> Result<(), sqlx::Error> {
+        let status = if result.filled_amount >= step.amount { "filled" } else { "cancelled" };
+
+        sqlx::query(
+            r#"
+            INSERT INTO orders (
+                id, user_address, market_id, outcome_id, share_type,
+                side, order_type, price, amount, filled_amount, status
+            )
+            SELECT $1, $2, $3, $4, share_type, 'sell'::order_side, 'market'::order_type, $5, $6, $7, $8::order_status
+            FROM shares WHERE id = $9
+            ON CONFLICT (id) DO NOTHING
+            "#,
+        )
+        .bind(result.order_id)
+        .bind(user_address)
+        .bind(step.market_id)
+        .bind(step.outcome_id)
+        .bind(result.average_price.unwrap_or(step.mark_price))
+        .bind(step.amount)
+        .bind(result.filled_amount)
+        .bind(status)
+        .bind(step.position_id)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::market::ShareType;
+    use crate::services::position::{account_margin, position_margin};
+    use rust_decimal_macros::dec;
+
+    fn cross_position(amount: Decimal, mark: Decimal) -> PositionMargin {
+        position_margin(
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            ShareType::Yes,
+            amount,
+            dec!(0.5),
+            mark,
+            dec!(0.1),
+            MarginMode::Cross,
+        )
+    }
+
+    #[test]
+    fn test_healthy_pool_is_not_liquidated() {
+        let positions = vec![cross_position(dec!(100), dec!(0.5))];
+        assert!(plan_liquidation(dec!(10), &positions, dec!(0.8), dec!(0.5)).is_empty());
+    }
+
+    #[test]
+    fn test_partial_liquidation_restores_target_ratio() {
+        // value 50, maintenance 5, equity 4 => ratio 1.25
+        let positions = vec![cross_position(dec!(100), dec!(0.5))];
+        let steps = plan_liquidation(dec!(-46), &positions, dec!(0.8), dec!(0.5));
+        assert_eq!(steps.len(), 1);
+        assert!(steps[0].is_partial());
+
+        // Apply the close: sell at mark, charge the penalty
+        let sold = steps[0].amount * dec!(0.5);
+        let fee = sold * dec!(0.1) * dec!(0.5);
+        let after = vec![cross_position(dec!(100) - steps[0].amount, dec!(0.5))];
+        let summary = account_margin(MarginMode::Cross, dec!(-46) + sold - fee, after);
+        assert!(summary.margin_ratio <= dec!(0.8));
+    }
+
+    #[test]
+    fn test_insolvent_pool_is_closed_in_full() {
+        let positions = vec![cross_position(dec!(100), dec!(0.5)), cross_position(dec!(10), dec!(0.2))];
+        let steps = plan_liquidation(dec!(-60), &positions, dec!(0.8), dec!(0.5));
+        assert_eq!(steps.len(), 2);
+        assert!(steps.iter().all(|s| !s.is_partial()));
+        assert_eq!(steps[0].position_amount, dec!(100));
+    }
+
+    #[test]
+    fn test_stale_pending_liquidation_resolves_from_its_fills() {
+        // Never reached the engine, or rejected before persisting
+        assert_eq!(
+            resolve_stale(None, Decimal::ZERO, Decimal::ZERO),
+            StaleResolution {
+                executed: false,
+                filled_amount: Decimal::ZERO,
+                price: None
+            }
+        );
+        // Filled but died before the order was persisted
+        assert_eq!(
+            resolve_stale(None, dec!(10), dec!(4.5)),
+            StaleResolution {
+                executed: true,
+                filled_amount: dec!(10),
+                price: Some(dec!(0.45))
+            }
+        );
+        // Order persisted, trades not yet written
+        assert_eq!(
+            resolve_stale(Some(dec!(6)), Decimal::ZERO, Decimal::ZERO),
+            StaleResolution {
+                executed: true,
+                filled_amount: dec!(6),
+                price: None
+            }
+        );
+        // Persisted with nothing filled
+        assert!(!resolve_stale(Some(Decimal::ZERO), Decimal::ZERO, Decimal::ZERO).executed);
+    }
+
+    #[test]
+    fn test_notice_reports_the_executed_close() {
+        let positions = vec![cross_position(dec!(100), dec!(0.5))];
+        let step = plan_liquidation(dec!(-60), &positions, dec!(0.8), dec!(0.5)).remove(0);
+        let (liquidation_id, order_id) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let notice = step.notice(liquidation_id, order_id, dec!(100), dec!(0.45), dec!(2.25), dec!(-5));
+        assert_eq!((notice.liquidation_id, notice.order_id), (liquidation_id, order_id));
+        assert_eq!((notice.symbol.as_str(), notice.market_id), (step.symbol.as_str(), step.market_id));
+        assert_eq!((notice.filled_amount, notice.position_amount), (dec!(100), dec!(100)));
+        assert_eq!((notice.price, notice.mark_price), (dec!(0.45), dec!(0.5)));
+        assert_eq!((notice.fee, notice.realized_pnl), (dec!(2.25), dec!(-5)));
+        assert!(!notice.is_partial);
+
+        // A full close the book could only fill in part leaves shares behind
+        assert!(step.notice(liquidation_id, order_id, dec!(60), dec!(0.45), dec!(1.35), dec!(-3)).is_partial);
+    }
+}
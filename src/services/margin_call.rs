@@ -0,0 +1,681 @@
+//! Margin Call Escalation
+//!
+//! Watches the margin ratio (maintenance / equity) of every account with open
+//! positions and escalates in steps as it approaches liquidation at 1:
+//!
+//! 1. notice: in-app notice on the WebSocket `notifications` channel
+//! 2. alert: the notice is also posted to the account's webhook and emailed
+//!    through the email relay
+//! 3. reduce: as alert, and if the account opted in, every position is cut by
+//!    `auto_reduce_percent` with market sells
+//!
+//! Thresholds, webhook, email and reduce percentage are set per account
+//! (`margin_call_settings`, defaults otherwise). Each step fires once: the
+//! step reached is kept in `margin_call_state` and only a higher step acts
+//! again, while a recovering ratio lowers it. Every action is logged in
+//! `margin_call_events`.
+
+use chrono::{DateTime, Utc};
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::metrics;
+use crate::services::market::MarketService;
+use crate::services::matching::{MatchingEngine, OrderType, Side};
+use crate::services::notifications::{MarginCallNotice, Notification, NotificationEvent};
+use crate::services::position::{AccountMargin, PositionError, PositionMargin, PositionService};
+
+/// Share amounts are stored with 8 decimal places
+const SHARE_DECIMALS: u32 = 8;
+
+/// Margin call errors
+#[derive(Debug, thiserror::Error)]
+pub enum MarginCallError {
+    #[error("Margin call ratios must rise from notice to alert to reduce, between 0 and 1")]
+    InvalidRatios,
+
+    #[error("Auto-reduce percent must be between 0 and 100")]
+    InvalidReducePercent,
+
+    #[error("Invalid webhook URL: {0}")]
+    InvalidWebhook(String),
+
+    #[error("Invalid email address: {0}")]
+    InvalidEmail(String),
+
+    #[error("Position error: {0}")]
+    PositionError(#[from] PositionError),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// Escalation step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MarginCallLevel {
+    Notice = 1,
+    Alert = 2,
+    Reduce = 3,
+}
+
+impl MarginCallLevel {
+    pub fn from_i16(level: i16) -> Option<Self> {
+        match level {
+            1 => Some(MarginCallLevel::Notice),
+            2 => Some(MarginCallLevel::Alert),
+            3 => Some(MarginCallLevel::Reduce),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MarginCallLevel::Notice => "notice",
+            MarginCallLevel::Alert => "alert",
+            MarginCallLevel::Reduce => "reduce",
+        }
+    }
+}
+
+/// Per-account ladder settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MarginCallSettings {
+    /// Margin ratio of the in-app notice
+    pub notice_ratio: Decimal,
+    /// Margin ratio of the webhook / email alert
+    pub alert_ratio: Decimal,
+    /// Margin ratio of the auto-reduce step
+    pub reduce_ratio: Decimal,
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+    /// Percent of every position sold at the reduce step (0 = off)
+    #[serde(default)]
+    pub auto_reduce_percent: Decimal,
+}
+
+impl Default for MarginCallSettings {
+    fn default() -> Self {
+        Self {
+            notice_ratio: Decimal::new(6, 1),
+            alert_ratio: Decimal::new(75, 2),
+            reduce_ratio: Decimal::new(9, 1),
+            webhook_url: None,
+            email: None,
+            auto_reduce_percent: Decimal::ZERO,
+        }
+    }
+}
+
+impl MarginCallSettings {
+    pub fn validate(&self) -> Result<(), MarginCallError> {
+        if self.notice_ratio <= Decimal::ZERO
+            || self.notice_ratio >= self.alert_ratio
+            || self.alert_ratio >= self.reduce_ratio
+            || self.reduce_ratio >= Decimal::ONE
+        {
+            return Err(MarginCallError::InvalidRatios);
+        }
+        if self.auto_reduce_percent < Decimal::ZERO || self.auto_reduce_percent > Decimal::ONE_HUNDRED {
+            return Err(MarginCallError::InvalidReducePercent);
+        }
+        if let Some(url) = &self.webhook_url {
+            if !(url.starts_with("https://") || url.starts_with("http://")) {
+                return Err(MarginCallError::InvalidWebhook(url.clone()));
+            }
+        }
+        if let Some(email) = &self.email {
+            let valid = email
+                .split_once('@')
+                .is_some_and(|(local, domain)| !local.is_empty() && domain.contains('.'))
+                && !email.contains(char::is_whitespace);
+            if !valid {
+                return Err(MarginCallError::InvalidEmail(email.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn threshold(&self, level: MarginCallLevel) -> Decimal {
+        match level {
+            MarginCallLevel::Notice => self.notice_ratio,
+            MarginCallLevel::Alert => self.alert_ratio,
+            MarginCallLevel::Reduce => self.reduce_ratio,
+        }
+    }
+
+    /// Highest step the margin ratio has reached
+    pub fn level_for(&self, margin_ratio: Decimal) -> Option<MarginCallLevel> {
+        [MarginCallLevel::Reduce, MarginCallLevel::Alert, MarginCallLevel::Notice]
+            .into_iter()
+            .find(|level| margin_ratio >= self.threshold(*level))
+    }
+}
+
+/// Margin ratio an account is escalated on; an account whose maintenance is
+/// no longer backed by any equity counts as fully used (1)
+pub fn escalation_ratio(account: &AccountMargin) -> Decimal {
+    if account.maintenance_margin > Decimal::ZERO && account.equity <= Decimal::ZERO {
+        return Decimal::ONE;
+    }
+    account.margin_ratio
+}
+
+/// Shares sold from a position at the reduce step, rounded down
+pub fn reduce_amount(amount: Decimal, percent: Decimal) -> Decimal {
+    (amount * percent / Decimal::ONE_HUNDRED).round_dp_with_strategy(SHARE_DECIMALS, RoundingStrategy::ToZero)
+}
+
+/// Settings of a ladder cycle
+#[derive(Debug, Clone)]
+pub struct MarginCallConfig {
+    /// Maintenance rate of markets without risk-limit tiers
+    pub maintenance_margin_rate: Decimal,
+    /// Collateral token
+    pub token: String,
+    /// HTTP relay that delivers alert emails (None = email is skipped)
+    pub email_relay_url: Option<String>,
+}
+
+/// Outcome of a ladder cycle
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MarginCallReport {
+    pub accounts_checked: usize,
+    pub escalated: usize,
+    pub recovered: usize,
+    /// Auto-reduce orders submitted
+    pub reduce_orders: usize,
+}
+
+/// A logged ladder action
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct MarginCallEventRecord {
+    pub id: Uuid,
+    pub level: i16,
+    pub margin_ratio: Decimal,
+    /// in_app, webhook, email or auto_reduce
+    pub action: String,
+    /// sent, failed or skipped
+    pub status: String,
+    pub detail: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Webhook body: the notice, addressed
+#[derive(Debug, Serialize)]
+struct WebhookPayload<'a> {
+    user_address: &'a str,
+    #[serde(flatten)]
+    notice: &'a MarginCallNotice,
+}
+
+/// Email relay request
+#[derive(Debug, Serialize)]
+struct EmailPayload<'a> {
+    to: &'a str,
+    subject: String,
+    text: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct SettingsRow {
+    user_address: String,
+    notice_ratio: Decimal,
+    alert_ratio: Decimal,
+    reduce_ratio: Decimal,
+    webhook_url: Option<String>,
+    email: Option<String>,
+    auto_reduce_percent: Decimal,
+}
+
+impl From<SettingsRow> for MarginCallSettings {
+    fn from(row: SettingsRow) -> Self {
+        Self {
+            notice_ratio: row.notice_ratio,
+            alert_ratio: row.alert_ratio,
+            reduce_ratio: row.reduce_ratio,
+            webhook_url: row.webhook_url,
+            email: row.email,
+            auto_reduce_percent: row.auto_reduce_percent,
+        }
+    }
+}
+
+const SETTINGS_SELECT: &str = r#"
+    SELECT user_address, notice_ratio, alert_ratio, reduce_ratio, webhook_url, email, auto_reduce_percent
+    FROM margin_call_settings
+"#;
+
+pub struct MarginCallService;
+
+impl MarginCallService {
+    /// Ladder settings of a user (defaults if never set)
+    pub async fn get_settings(pool: &PgPool, user_address: &str) -> Result<MarginCallSettings, MarginCallError> {
+        let row: Option<SettingsRow> = sqlx::query_as(&format!("{} WHERE user_address = $1", SETTINGS_SELECT))
+            .bind(user_address.to_lowercase())
+            .fetch_optional(pool)
+            .await?;
+        Ok(row.map(MarginCallSettings::from).unwrap_or_default())
+    }
+
+    /// Replace a user's ladder settings
+    pub async fn set_settings(
+        pool: &PgPool,
+        user_address: &str,
+        settings: &MarginCallSettings,
+    ) -> Result<(), MarginCallError> {
+        settings.validate()?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO margin_call_settings (
+                user_address, notice_ratio, alert_ratio, reduce_ratio, webhook_url, email, auto_reduce_percent
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (user_address) DO UPDATE SET
+                notice_ratio = $2,
+                alert_ratio = $3,
+                reduce_ratio = $4,
+                webhook_url = $5,
+                email = $6,
+                auto_reduce_percent = $7,
+                updated_at = NOW()
+            "#,
+        )
+        .bind(user_address.to_lowercase())
+        .bind(settings.notice_ratio)
+        .bind(settings.alert_ratio)
+        .bind(settings.reduce_ratio)
+        .bind(&settings.webhook_url)
+        .bind(&settings.email)
+        .bind(settings.auto_reduce_percent)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Most recent ladder actions of a user
+    pub async fn history(
+        pool: &PgPool,
+        user_address: &str,
+        limit: i64,
+    ) -> Result<Vec<MarginCallEventRecord>, MarginCallError> {
+        Ok(sqlx::query_as(
+            r#"
+            SELECT id, level, margin_ratio, action, status, detail, created_at
+            FROM margin_call_events
+            WHERE user_address = $1
+            ORDER BY created_at DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(user_address.to_lowercase())
+        .bind(limit)
+        .fetch_all(pool)
+        .await?)
+    }
+
+    /// Check every account with positions (or a standing margin call) and
+    /// escalate those that reached a higher step
+    pub async fn run_cycle(
+        pool: &PgPool,
+        engine: &MatchingEngine,
+        markets: &MarketService,
+        http: &reqwest::Client,
+        config: &MarginCallConfig,
+        notifier: &broadcast::Sender<NotificationEvent>,
+    ) -> Result<MarginCallReport, MarginCallError> {
+        let accounts: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT user_address FROM shares WHERE amount > 0
+            UNION
+            SELECT user_address FROM margin_call_state
+            "#,
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let states: HashMap<String, i16> = sqlx::query_as::<_, (String, i16)>(
+            "SELECT user_address, level FROM margin_call_state",
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .collect();
+
+        let settings: HashMap<String, MarginCallSettings> = sqlx::query_as::<_, SettingsRow>(SETTINGS_SELECT)
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .map(|row| (row.user_address.clone(), MarginCallSettings::from(row)))
+            .collect();
+        let defaults = MarginCallSettings::default();
+
+        let mut report = MarginCallReport {
+            accounts_checked: accounts.len(),
+            ..Default::default()
+        };
+
+        for user_address in accounts {
+            let key = user_address.to_lowercase();
+            let account = PositionService::get_account_margin(
+                pool,
+                engine,
+                markets,
+                &user_address,
+                &config.token,
+                config.maintenance_margin_rate,
+            )
+            .await?;
+            let ratio = escalation_ratio(&account);
+            let settings = settings.get(&key).unwrap_or(&defaults);
+            let level = settings.level_for(ratio);
+            let previous = states.get(&key).copied().and_then(MarginCallLevel::from_i16);
+
+            if level <= previous {
+                if level < previous {
+                    Self::set_state(pool, &key, level, ratio).await?;
+                    report.recovered += 1;
+                }
+                continue;
+            }
+            let Some(level) = level else { continue };
+
+            report.escalated += 1;
+            Self::set_state(pool, &key, Some(level), ratio).await?;
+            report.reduce_orders +=
+                Self::escalate(pool, engine, http, config, notifier, &key, &account, settings, level, ratio).await?;
+        }
+
+        Ok(report)
+    }
+
+    /// Carry out one step; the number of auto-reduce orders submitted
+    #[allow(clippy::too_many_arguments)]
+    async fn escalate(
+        pool: &PgPool,
+        engine: &MatchingEngine,
+        http: &reqwest::Client,
+        config: &MarginCallConfig,
+        notifier: &broadcast::Sender<NotificationEvent>,
+        user_address: &str,
+        account: &AccountMargin,
+        settings: &MarginCallSettings,
+        level: MarginCallLevel,
+        ratio: Decimal,
+    ) -> Result<usize, MarginCallError> {
+        metrics::record_margin_call(level.as_str());
+        tracing::warn!(
+            "Margin call ({}) for {}: margin ratio {} reached {}",
+            level.as_str(),
+            user_address,
+            ratio,
+            settings.threshold(level)
+        );
+
+        let reduces = level == MarginCallLevel::Reduce && settings.auto_reduce_percent > Decimal::ZERO;
+        let notice = MarginCallNotice {
+            level,
+            margin_ratio: ratio,
+            threshold: settings.threshold(level),
+            equity: account.equity,
+            maintenance_margin: account.maintenance_margin,
+            auto_reduce_percent: reduces.then_some(settings.auto_reduce_percent),
+            timestamp: Utc::now(),
+        };
+
+        // No receivers when the account isn't connected
+        let _ = notifier.send(NotificationEvent {
+            user_address: user_address.to_string(),
+            notification: Notification::MarginCall(notice.clone()),
+        });
+        Self::log_event(pool, user_address, level, ratio, "in_app", "sent", None).await?;
+
+        if level >= MarginCallLevel::Alert {
+            match &settings.webhook_url {
+                Some(url) => {
+                    let payload = WebhookPayload { user_address, notice: &notice };
+                    let sent = async { http.post(url).json(&payload).send().await?.error_for_status() }.await;
+                    Self::log_delivery(pool, user_address, level, ratio, "webhook", sent.map(|_| ())).await?;
+                }
+                None => {
+                    Self::log_event(pool, user_address, level, ratio, "webhook", "skipped", Some("no webhook set")).await?
+                }
+            }
+
+            match (&settings.email, &config.email_relay_url) {
+                (Some(email), Some(relay)) => {
+                    let payload = EmailPayload {
+                        to: email,
+                        subject: format!("Margin call: margin ratio at {}", ratio.round_dp(4)),
+                        text: format!(
+                            "Your account {} has a margin ratio of {} (equity {}, maintenance margin {}). \
+                             Positions are liquidated at a margin ratio of 1; add collateral or reduce positions.",
+                            user_address,
+                            ratio.round_dp(4),
+                            account.equity.round_dp(2),
+                            account.maintenance_margin.round_dp(2)
+                        ),
+                    };
+                    let sent = async { http.post(relay).json(&payload).send().await?.error_for_status() }.await;
+                    Self::log_delivery(pool, user_address, level, ratio, "email", sent.map(|_| ())).await?;
+                }
+                (None, _) => Self::log_event(pool, user_address, level, ratio, "email", "skipped", Some("no email set")).await?,
+                (Some(_), None) => {
+                    Self::log_event(pool, user_address, level, ratio, "email", "skipped", Some("no email relay configured"))
+                        .await?
+                }
+            }
+        }
+
+        if !reduces {
+            return Ok(0);
+        }
+        let mut submitted = 0;
+        for position in account.positions.iter().filter(|p| p.amount > Decimal::ZERO) {
+            let amount = reduce_amount(position.amount, settings.auto_reduce_percent);
+            if amount.is_zero() {
+                continue;
+            }
+            let (status, detail) = match Self::submit_reduce(pool, engine, user_address, position, amount).await {
+                Ok(detail) => {
+                    submitted += 1;
+                    ("sent", detail)
+                }
+                Err(detail) => ("failed", detail),
+            };
+            Self::log_event(pool, user_address, level, ratio, "auto_reduce", status, Some(&detail)).await?;
+        }
+        Ok(submitted)
+    }
+
+    /// Market-sell part of a position; a description of the order either way
+    async fn submit_reduce(
+        pool: &PgPool,
+        engine: &MatchingEngine,
+        user_address: &str,
+        position: &PositionMargin,
+        amount: Decimal,
+    ) -> Result<String, String> {
+        let symbol = format!("{}:{}:{}", position.market_id, position.outcome_id, position.share_type);
+        let order_id = Uuid::new_v4();
+        let result = engine
+            .submit_order(order_id, &symbol, user_address, Side::Sell, OrderType::Market, amount, None, 1)
+            .map_err(|e| format!("sell {} of {} on {} rejected: {}", amount, position.amount, symbol, e))?;
+
+        // Any unfilled remainder of the market order is cancelled
+        let status = if result.filled_amount >= amount { "filled" } else { "cancelled" };
+        sqlx::query(
+            r#"
+            INSERT INTO orders (
+                id, user_address, market_id, outcome_id, share_type,
+                side, order_type, price, amount, filled_amount, status
+            )
+            VALUES ($1, $2, $3, $4, $5::share_type, 'sell'::order_side, 'market'::order_type, $6, $7, $8, $9::order_status)
+            ON CONFLICT (id) DO NOTHING
+            "#,
+        )
+        .bind(order_id)
+        .bind(user_address)
+        .bind(position.market_id)
+        .bind(position.outcome_id)
+        .bind(position.share_type.to_string())
+        .bind(result.average_price.unwrap_or(position.mark_price))
+        .bind(amount)
+        .bind(result.filled_amount)
+        .bind(status)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("order {} on {} not persisted: {}", order_id, symbol, e))?;
+
+        tracing::warn!(
+            "Auto-reduced {} by {} of {} shares on {} (order {})",
+            user_address, result.filled_amount, position.amount, symbol, order_id
+        );
+        Ok(format!("order {} sold {} of {} on {}", order_id, result.filled_amount, position.amount, symbol))
+    }
+
+    async fn set_state(
+        pool: &PgPool,
+        user_address: &str,
+        level: Option<MarginCallLevel>,
+        ratio: Decimal,
+    ) -> Result<(), sqlx::Error> {
+        match level {
+            Some(level) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO margin_call_state (user_address, level, margin_ratio)
+                    VALUES ($1, $2, $3)
+                    ON CONFLICT (user_address) DO UPDATE SET level = $2, margin_ratio = $3, updated_at = NOW()
+                    "#,
+                )
+                .bind(user_address)
+                .bind(level as i16)
+                .bind(ratio)
+                .execute(pool)
+                .await?;
+            }
+            None => {
+                sqlx::query("DELETE FROM margin_call_state WHERE user_address = $1")
+                    .bind(user_address)
+                    .execute(pool)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn log_delivery(
+        pool: &PgPool,
+        user_address: &str,
+        level: MarginCallLevel,
+        ratio: Decimal,
+        action: &str,
+        sent: Result<(), reqwest::Error>,
+    ) -> Result<(), sqlx::Error> {
+        match sent {
+            Ok(()) => Self::log_event(pool, user_address, level, ratio, action, "sent", None).await,
+            Err(e) => {
+                tracing::error!("Margin call {} for {} not delivered: {}", action, user_address, e);
+                Self::log_event(pool, user_address, level, ratio, action, "failed", Some(&e.to_string())).await
+            }
+        }
+    }
+
+    async fn log_event(
+        pool: &PgPool,
+        user_address: &str,
+        level: MarginCallLevel,
+        ratio: Decimal,
+        action: &str,
+        status: &str,
+        detail: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO margin_call_events (user_address, level, margin_ratio, action, status, detail)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(user_address)
+        .bind(level as i16)
+        .bind(ratio)
+        .bind(action)
+        .bind(status)
+        .bind(detail)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::position::{account_margin, position_margin, MarginMode};
+    use crate::models::market::ShareType;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_levels_follow_thresholds() {
+        let settings = MarginCallSettings::default();
+        assert!(settings.validate().is_ok());
+        assert_eq!(settings.level_for(dec!(0.5)), None);
+        assert_eq!(settings.level_for(dec!(0.6)), Some(MarginCallLevel::Notice));
+        assert_eq!(settings.level_for(dec!(0.8)), Some(MarginCallLevel::Alert));
+        assert_eq!(settings.level_for(dec!(0.95)), Some(MarginCallLevel::Reduce));
+        assert!(Some(MarginCallLevel::Alert) > None);
+    }
+
+    #[test]
+    fn test_settings_validation() {
+        let mut settings = MarginCallSettings {
+            alert_ratio: dec!(0.5),
+            ..Default::default()
+        };
+        assert!(matches!(settings.validate(), Err(MarginCallError::InvalidRatios)));
+
+        settings = MarginCallSettings {
+            auto_reduce_percent: dec!(120),
+            ..Default::default()
+        };
+        assert!(matches!(settings.validate(), Err(MarginCallError::InvalidReducePercent)));
+
+        settings = MarginCallSettings {
+            webhook_url: Some("ftp://example.com".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(settings.validate(), Err(MarginCallError::InvalidWebhook(_))));
+
+        settings = MarginCallSettings {
+            email: Some("trader@example".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(settings.validate(), Err(MarginCallError::InvalidEmail(_))));
+    }
+
+    #[test]
+    fn test_insolvent_account_escalates_fully() {
+        let position = position_margin(
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            ShareType::Yes,
+            dec!(100),
+            dec!(0.5),
+            dec!(0.5),
+            dec!(0.1),
+            MarginMode::Cross,
+        );
+        let account = account_margin(MarginMode::Cross, dec!(-60), vec![position]);
+        assert_eq!(escalation_ratio(&account), Decimal::ONE);
+        assert_eq!(reduce_amount(dec!(10.123456789), dec!(25)), dec!(2.53086419));
+    }
+}
@@ -0,0 +1,215 @@
+//! Trading Calendar
+//!
+//! Optional per-market trading sessions. Markets without a calendar trade
+//! 24/7. A calendar is stored as JSON on the market (`markets.trading_calendar`):
+//!
+//! ```json
+//! {
+//!   "utc_offset_minutes": -300,
+//!   "sessions": [{ "days": ["mon", "tue", "wed", "thu", "fri"], "open": "09:30", "close": "16:00" }],
+//!   "holidays": ["2026-12-25"]
+//! }
+//! ```
+//!
+//! Times are local to the fixed `utc_offset_minutes`. A session whose close is
+//! not after its open runs overnight into the next day (e.g. FX `17:00` to
+//! `17:00`). Holidays cancel sessions that open on that date. Back-to-back
+//! sessions are treated as one continuous window.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Utc, Weekday};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// How far ahead the next open / close is searched
+const LOOKAHEAD_DAYS: i64 = 14;
+
+/// Calendar validation errors
+#[derive(Debug, thiserror::Error)]
+pub enum CalendarError {
+    #[error("Trading calendar must define at least one session")]
+    NoSessions,
+
+    #[error("Session {0} has no trading days")]
+    NoDays(usize),
+
+    #[error("UTC offset out of range: {0} minutes")]
+    InvalidOffset(i32),
+}
+
+/// A recurring trading window
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+    /// Days the session opens on
+    pub days: Vec<Weekday>,
+    #[serde(serialize_with = "serialize_time", deserialize_with = "deserialize_time")]
+    pub open: NaiveTime,
+    #[serde(serialize_with = "serialize_time", deserialize_with = "deserialize_time")]
+    pub close: NaiveTime,
+}
+
+/// Trading sessions and holidays for a market
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TradingCalendar {
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+    pub sessions: Vec<Session>,
+    #[serde(default)]
+    pub holidays: Vec<NaiveDate>,
+}
+
+/// Session state at a point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SessionState {
+    pub is_open: bool,
+    /// Unix millis of the next open (None while open, or if none is scheduled)
+    pub next_open: Option<i64>,
+    /// Unix millis of the current session's close (None while closed)
+    pub next_close: Option<i64>,
+}
+
+/// Session open/close transition for a market (WebSocket announcement)
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionEvent {
+    pub market_id: uuid::Uuid,
+    #[serde(flatten)]
+    pub state: SessionState,
+    /// Unix millis
+    pub timestamp: i64,
+}
+
+fn serialize_time<S: Serializer>(time: &NaiveTime, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&time.format("%H:%M").to_string())
+}
+
+fn deserialize_time<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveTime, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    NaiveTime::parse_from_str(&s, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(&s, "%H:%M:%S"))
+        .map_err(serde::de::Error::custom)
+}
+
+impl TradingCalendar {
+    pub fn validate(&self) -> Result<(), CalendarError> {
+        if self.sessions.is_empty() {
+            return Err(CalendarError::NoSessions);
+        }
+        if let Some(i) = self.sessions.iter().position(|s| s.days.is_empty()) {
+            return Err(CalendarError::NoDays(i));
+        }
+        if self.utc_offset_minutes.abs() > 14 * 60 {
+            return Err(CalendarError::InvalidOffset(self.utc_offset_minutes));
+        }
+        Ok(())
+    }
+
+    fn offset(&self) -> Duration {
+        Duration::minutes(self.utc_offset_minutes as i64)
+    }
+
+    /// Merged trading windows opening on local dates `from..from + days`
+    fn windows(&self, from: NaiveDate, days: i64) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+        let mut windows = Vec::new();
+        for day in 0..days {
+            let date = from + Duration::days(day);
+            if self.holidays.contains(&date) {
+                continue;
+            }
+            for session in self.sessions.iter().filter(|s| s.days.contains(&date.weekday())) {
+                let open = date.and_time(session.open).and_utc() - self.offset();
+                let mut close = date.and_time(session.close).and_utc() - self.offset();
+                if close <= open {
+                    close += Duration::days(1);
+                }
+                windows.push((open, close));
+            }
+        }
+
+        windows.sort();
+        let mut merged: Vec<(DateTime<Utc>, DateTime<Utc>)> = Vec::new();
+        for (open, close) in windows {
+            match merged.last_mut() {
+                Some(last) if open <= last.1 => last.1 = last.1.max(close),
+                _ => merged.push((open, close)),
+            }
+        }
+        merged
+    }
+
+    pub fn is_open(&self, at: DateTime<Utc>) -> bool {
+        self.state(at).is_open
+    }
+
+    /// Whether trading is open at `at`, and when that next changes
+    pub fn state(&self, at: DateTime<Utc>) -> SessionState {
+        // Start a day early to catch overnight sessions still running
+        let local_date = (at + self.offset()).date_naive() - Duration::days(1);
+        let windows = self.windows(local_date, LOOKAHEAD_DAYS + 1);
+
+        match windows.iter().find(|(open, close)| *open <= at && at < *close) {
+            Some((_, close)) => SessionState {
+                is_open: true,
+                next_open: None,
+                next_close: Some(close.timestamp_millis()),
+            },
+            None => SessionState {
+                is_open: false,
+                next_open: windows
+                    .iter()
+                    .find(|(open, _)| *open > at)
+                    .map(|(open, _)| open.timestamp_millis()),
+                next_close: None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn weekdays() -> TradingCalendar {
+        serde_json::from_value(serde_json::json!({
+            "utc_offset_minutes": -300,
+            "sessions": [{"days": ["mon", "tue", "wed", "thu", "fri"], "open": "09:30", "close": "16:00"}],
+            "holidays": ["2026-12-25"]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_weekday_session_with_offset() {
+        let calendar = weekdays();
+        calendar.validate().unwrap();
+
+        // Thu 2026-10-15 10:00 local = 15:00 UTC
+        let open = Utc.with_ymd_and_hms(2026, 10, 15, 15, 0, 0).unwrap();
+        let state = calendar.state(open);
+        assert!(state.is_open);
+        assert_eq!(state.next_close, Some(Utc.with_ymd_and_hms(2026, 10, 15, 21, 0, 0).unwrap().timestamp_millis()));
+
+        // Sat: closed until Mon 09:30 local
+        let weekend = Utc.with_ymd_and_hms(2026, 10, 17, 15, 0, 0).unwrap();
+        let state = calendar.state(weekend);
+        assert!(!state.is_open);
+        assert_eq!(state.next_open, Some(Utc.with_ymd_and_hms(2026, 10, 19, 14, 30, 0).unwrap().timestamp_millis()));
+    }
+
+    #[test]
+    fn test_holiday_is_closed() {
+        let christmas = Utc.with_ymd_and_hms(2026, 12, 25, 16, 0, 0).unwrap();
+        assert!(!weekdays().is_open(christmas));
+    }
+
+    #[test]
+    fn test_overnight_sessions_merge() {
+        let calendar: TradingCalendar = serde_json::from_value(serde_json::json!({
+            "sessions": [{"days": ["sun", "mon", "tue", "wed", "thu"], "open": "22:00", "close": "22:00"}]
+        }))
+        .unwrap();
+
+        // Wed 03:00 UTC: inside Tue's session, which runs into Wed's; closes Fri 22:00
+        let state = calendar.state(Utc.with_ymd_and_hms(2026, 10, 14, 3, 0, 0).unwrap());
+        assert!(state.is_open);
+        assert_eq!(state.next_close, Some(Utc.with_ymd_and_hms(2026, 10, 16, 22, 0, 0).unwrap().timestamp_millis()));
+    }
+}
@@ -0,0 +1,443 @@
+//! External Index Prices
+//!
+//! Aggregates spot prices for configured instruments from several exchanges
+//! (Binance, OKX, Coinbase) into one index price per instrument, so index and
+//! mark prices do not rely on the internal book alone.
+//!
+//! Each poll fetches every source of an instrument and then aggregates its
+//! quotes:
+//!
+//! 1. quotes older than `stale_secs` are dropped
+//! 2. the weighted median of the fresh quotes is the reference price
+//! 3. quotes deviating from it by more than `max_deviation` are rejected
+//! 4. the index is the weighted mean of the remaining quotes, provided at
+//!    least `min_sources` remain
+//!
+//! An instrument whose last successful aggregation is older than `stale_secs`
+//! has no index price. Instruments are configured as JSON
+//! (`index_instruments`):
+//!
+//! ```json
+//! [{ "name": "BTC-USD", "sources": [
+//!     { "venue": "binance", "pair": "BTCUSDT", "weight": 1 },
+//!     { "venue": "okx", "pair": "BTC-USDT", "weight": 1 },
+//!     { "venue": "coinbase", "pair": "BTC-USD", "weight": 2 }
+//! ] }]
+//! ```
+//!
+//! The aggregator is the `exchange` index source of the mark price service,
+//! for symbols named after an instrument.
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use futures::future::BoxFuture;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+
+use super::mark_price::IndexSource;
+use crate::cache::CacheManager;
+use crate::metrics;
+
+/// Timeout for a single venue request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Index price errors
+#[derive(Debug, thiserror::Error)]
+pub enum IndexPriceError {
+    #[error("Invalid index instruments config: {0}")]
+    InvalidConfig(String),
+
+    #[error("HTTP error: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    #[error("Unexpected response from {0}")]
+    BadResponse(Venue),
+}
+
+/// A spot price venue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Venue {
+    Binance,
+    Okx,
+    Coinbase,
+}
+
+impl Venue {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Venue::Binance => "binance",
+            Venue::Okx => "okx",
+            Venue::Coinbase => "coinbase",
+        }
+    }
+
+    /// Ticker endpoint for a venue pair
+    fn ticker_url(&self, pair: &str) -> String {
+        match self {
+            Venue::Binance => format!("https://api.binance.com/api/v3/ticker/price?symbol={}", pair),
+            Venue::Okx => format!("https://www.okx.com/api/v5/market/ticker?instId={}", pair),
+            Venue::Coinbase => format!("https://api.exchange.coinbase.com/products/{}/ticker", pair),
+        }
+    }
+
+    /// Last price from a ticker response
+    pub fn parse_ticker(&self, body: &Value) -> Option<Decimal> {
+        let price = match self {
+            Venue::Binance | Venue::Coinbase => body.get("price"),
+            Venue::Okx => body.get("data")?.get(0)?.get("last"),
+        }?;
+        price.as_str()?.parse().ok().filter(|p: &Decimal| *p > Decimal::ZERO)
+    }
+}
+
+impl std::fmt::Display for Venue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One venue feeding an instrument
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexSourceConfig {
+    pub venue: Venue,
+    /// Pair name on the venue (e.g. `BTCUSDT`, `BTC-USDT`)
+    pub pair: String,
+    #[serde(default = "default_weight")]
+    pub weight: Decimal,
+}
+
+fn default_weight() -> Decimal {
+    Decimal::ONE
+}
+
+/// An index instrument and its sources
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexInstrument {
+    pub name: String,
+    pub sources: Vec<IndexSourceConfig>,
+}
+
+/// Parse the `index_instruments` JSON config (empty = no instruments)
+pub fn parse_instruments(config: &str) -> Result<Vec<IndexInstrument>, IndexPriceError> {
+    if config.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let instruments: Vec<IndexInstrument> =
+        serde_json::from_str(config).map_err(|e| IndexPriceError::InvalidConfig(e.to_string()))?;
+    for instrument in &instruments {
+        if instrument.sources.is_empty() {
+            return Err(IndexPriceError::InvalidConfig(format!("{} has no sources", instrument.name)));
+        }
+        if instrument.sources.iter().any(|s| s.weight <= Decimal::ZERO) {
+            return Err(IndexPriceError::InvalidConfig(format!("{} has a non-positive weight", instrument.name)));
+        }
+    }
+    Ok(instruments)
+}
+
+/// Aggregation parameters
+#[derive(Debug, Clone, Copy)]
+pub struct AggregationParams {
+    /// Largest accepted relative deviation from the weighted median
+    pub max_deviation: Decimal,
+    /// Quotes (and index prices) older than this are stale
+    pub stale_secs: i64,
+    /// Fewest accepted quotes that make an index price
+    pub min_sources: usize,
+}
+
+/// A venue price observation
+#[derive(Debug, Clone, Copy)]
+pub struct Quote {
+    pub venue: Venue,
+    pub price: Decimal,
+    pub weight: Decimal,
+    pub received_at: DateTime<Utc>,
+}
+
+/// Why a quote was left out of an index price
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectReason {
+    Stale,
+    Outlier,
+}
+
+impl RejectReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RejectReason::Stale => "stale",
+            RejectReason::Outlier => "outlier",
+        }
+    }
+}
+
+/// Aggregated index price of an instrument
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexPrice {
+    pub instrument: String,
+    pub price: Decimal,
+    pub sources: Vec<Venue>,
+    pub rejected: Vec<(Venue, RejectReason)>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Weighted median of (price, weight) pairs
+fn weighted_median(mut quotes: Vec<(Decimal, Decimal)>) -> Option<Decimal> {
+    quotes.sort_by_key(|(price, _)| *price);
+    let total: Decimal = quotes.iter().map(|(_, w)| *w).sum();
+    let mut cumulative = Decimal::ZERO;
+    for (price, weight) in &quotes {
+        cumulative += *weight;
+        if cumulative * Decimal::TWO >= total {
+            return Some(*price);
+        }
+    }
+    None
+}
+
+/// Aggregate quotes into an index price
+///
+/// Returns the accepted venues, the weighted mean and the rejections; the price
+/// is None when fewer than `min_sources` quotes survive.
+pub fn aggregate(
+    quotes: &[Quote],
+    now: DateTime<Utc>,
+    params: &AggregationParams,
+) -> (Option<Decimal>, Vec<Venue>, Vec<(Venue, RejectReason)>) {
+    let mut rejected = Vec::new();
+    let fresh: Vec<&Quote> = quotes
+        .iter()
+        .filter(|q| {
+            let is_fresh = (now - q.received_at).num_seconds() <= params.stale_secs;
+            if !is_fresh {
+                rejected.push((q.venue, RejectReason::Stale));
+            }
+            is_fresh
+        })
+        .collect();
+
+    let Some(reference) = weighted_median(fresh.iter().map(|q| (q.price, q.weight)).collect()) else {
+        return (None, Vec::new(), rejected);
+    };
+
+    let accepted: Vec<&Quote> = fresh
+        .into_iter()
+        .filter(|q| {
+            let within = ((q.price - reference).abs() / reference) <= params.max_deviation;
+            if !within {
+                rejected.push((q.venue, RejectReason::Outlier));
+            }
+            within
+        })
+        .collect();
+
+    let venues = accepted.iter().map(|q| q.venue).collect();
+    if accepted.len() < params.min_sources.max(1) {
+        return (None, venues, rejected);
+    }
+    let weight: Decimal = accepted.iter().map(|q| q.weight).sum();
+    let price = accepted.iter().map(|q| q.price * q.weight).sum::<Decimal>() / weight;
+    (Some(price), venues, rejected)
+}
+
+/// Polls venues and keeps the latest index price per instrument
+pub struct IndexAggregator {
+    instruments: Vec<IndexInstrument>,
+    params: AggregationParams,
+    http: reqwest::Client,
+    quotes: DashMap<(String, Venue), Quote>,
+    prices: DashMap<String, IndexPrice>,
+}
+
+impl IndexAggregator {
+    pub fn new(instruments: Vec<IndexInstrument>, params: AggregationParams) -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self {
+            instruments,
+            params,
+            http,
+            quotes: DashMap::new(),
+            prices: DashMap::new(),
+        }
+    }
+
+    pub fn instruments(&self) -> &[IndexInstrument] {
+        &self.instruments
+    }
+
+    async fn fetch(&self, source: &IndexSourceConfig) -> Result<Decimal, IndexPriceError> {
+        let body: Value = self
+            .http
+            .get(source.venue.ticker_url(&source.pair))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        source
+            .venue
+            .parse_ticker(&body)
+            .ok_or(IndexPriceError::BadResponse(source.venue))
+    }
+
+    /// Fetch every source and re-aggregate every instrument
+    pub async fn poll(&self, cache: &CacheManager) {
+        for instrument in &self.instruments {
+            let fetched = futures::future::join_all(instrument.sources.iter().map(|s| self.fetch(s))).await;
+            let now = Utc::now();
+            for (source, result) in instrument.sources.iter().zip(fetched) {
+                match result {
+                    Ok(price) => {
+                        self.quotes.insert(
+                            (instrument.name.clone(), source.venue),
+                            Quote {
+                                venue: source.venue,
+                                price,
+                                weight: source.weight,
+                                received_at: now,
+                            },
+                        );
+                    }
+                    Err(e) => {
+                        metrics::record_index_source_error(source.venue.as_str());
+                        tracing::warn!("Index source {} for {} failed: {}", source.venue, instrument.name, e);
+                    }
+                }
+            }
+
+            let quotes: Vec<Quote> = instrument
+                .sources
+                .iter()
+                .filter_map(|s| self.quotes.get(&(instrument.name.clone(), s.venue)).map(|q| *q))
+                .collect();
+            let (price, sources, rejected) = aggregate(&quotes, now, &self.params);
+            for (venue, reason) in &rejected {
+                metrics::record_index_quote_rejected(venue.as_str(), reason.as_str());
+            }
+
+            let Some(price) = price else {
+                tracing::warn!(
+                    "Index {}: only {} usable sources ({} rejected), keeping last price",
+                    instrument.name,
+                    sources.len(),
+                    rejected.len()
+                );
+                continue;
+            };
+
+            self.prices.insert(
+                instrument.name.clone(),
+                IndexPrice {
+                    instrument: instrument.name.clone(),
+                    price,
+                    sources,
+                    rejected,
+                    updated_at: now,
+                },
+            );
+            if let Some(price_cache) = cache.price_opt() {
+                if let Err(e) = price_cache.set_index_price(&instrument.name, price).await {
+                    tracing::warn!("Failed to cache index price for {}: {}", instrument.name, e);
+                }
+            }
+        }
+    }
+
+    /// Latest index price of an instrument, unless stale
+    pub fn index(&self, instrument: &str) -> Option<IndexPrice> {
+        let price = self.prices.get(instrument)?;
+        let age = (Utc::now() - price.updated_at).num_seconds();
+        (age <= self.params.stale_secs).then(|| price.clone())
+    }
+
+    /// Latest fresh index prices of all instruments
+    pub fn indices(&self) -> Vec<IndexPrice> {
+        self.instruments.iter().filter_map(|i| self.index(&i.name)).collect()
+    }
+}
+
+impl IndexSource for IndexAggregator {
+    fn name(&self) -> &'static str {
+        "exchange"
+    }
+
+    fn index_price<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Option<Decimal>> {
+        Box::pin(async move { self.index(symbol).map(|i| i.price) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn params() -> AggregationParams {
+        AggregationParams {
+            max_deviation: dec!(0.02),
+            stale_secs: 30,
+            min_sources: 2,
+        }
+    }
+
+    fn quote(venue: Venue, price: Decimal, weight: Decimal, age_secs: i64, now: DateTime<Utc>) -> Quote {
+        Quote {
+            venue,
+            price,
+            weight,
+            received_at: now - chrono::Duration::seconds(age_secs),
+        }
+    }
+
+    #[test]
+    fn test_aggregate_rejects_outliers_and_stale_quotes() {
+        let now = Utc::now();
+        let quotes = vec![
+            quote(Venue::Binance, dec!(100), dec!(1), 1, now),
+            quote(Venue::Okx, dec!(130), dec!(1), 1, now),
+            quote(Venue::Coinbase, dec!(101), dec!(2), 1, now),
+        ];
+        let (price, sources, rejected) = aggregate(&quotes, now, &params());
+        // (100 * 1 + 101 * 2) / 3
+        assert_eq!(price.map(|p| p.round_dp(4)), Some(dec!(100.6667)));
+        assert_eq!(sources, vec![Venue::Binance, Venue::Coinbase]);
+        assert_eq!(rejected, vec![(Venue::Okx, RejectReason::Outlier)]);
+
+        // With Coinbase stale only Binance is left: below min_sources
+        let quotes = vec![
+            quote(Venue::Binance, dec!(100), dec!(1), 1, now),
+            quote(Venue::Coinbase, dec!(101), dec!(2), 60, now),
+        ];
+        let (price, _, rejected) = aggregate(&quotes, now, &params());
+        assert_eq!(price, None);
+        assert_eq!(rejected, vec![(Venue::Coinbase, RejectReason::Stale)]);
+    }
+
+    #[test]
+    fn test_parse_venue_tickers() {
+        let binance = serde_json::json!({"symbol": "BTCUSDT", "price": "67000.50"});
+        let okx = serde_json::json!({"code": "0", "data": [{"instId": "BTC-USDT", "last": "67001.1"}]});
+        let coinbase = serde_json::json!({"price": "66999.99", "volume": "1"});
+        assert_eq!(Venue::Binance.parse_ticker(&binance), Some(dec!(67000.50)));
+        assert_eq!(Venue::Okx.parse_ticker(&okx), Some(dec!(67001.1)));
+        assert_eq!(Venue::Coinbase.parse_ticker(&coinbase), Some(dec!(66999.99)));
+        assert_eq!(Venue::Okx.parse_ticker(&serde_json::json!({"data": []})), None);
+    }
+
+    #[test]
+    fn test_parse_instruments() {
+        let instruments = parse_instruments(
+            r#"[{"name": "BTC-USD", "sources": [{"venue": "binance", "pair": "BTCUSDT"}, {"venue": "coinbase", "pair": "BTC-USD", "weight": 2}]}]"#,
+        )
+        .unwrap();
+        assert_eq!(instruments[0].sources[0].weight, dec!(1));
+        assert!(parse_instruments("").unwrap().is_empty());
+        assert!(parse_instruments(r#"[{"name": "X", "sources": []}]"#).is_err());
+    }
+}
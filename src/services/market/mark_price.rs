@@ -0,0 +1,462 @@
+//! Mark Price
+//!
+//! Mark prices drive margin, liquidation and funding, so they must not follow
+//! a single print on a thin internal book. For each symbol the mark is the
+//! median of:
+//!
+//! - the internal book mid (best bid / best ask)
+//! - the index price plus an EMA of the basis (mid - index), so a persistent
+//!   premium is carried while a momentary spike is damped
+//! - the last trade price
+//!
+//! Missing components are dropped (the median of two is their average); with
+//! no index the mark falls back to mid, then last trade. Symbols can instead
+//! be configured to mark at the last trade.
+//!
+//! The index is the median of the pluggable [`IndexSource`]s that have a price
+//! for the symbol (oracle probability, cached external index, ...). Per-symbol
+//! settings live in `mark_price_configs`; other symbols use the defaults from
+//! the application config.
+
+use dashmap::DashMap;
+use futures::future::BoxFuture;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::str::FromStr;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::cache::CacheManager;
+use crate::models::market::ShareType;
+use crate::services::matching::MatchingEngine;
+
+/// Mark price errors
+#[derive(Debug, thiserror::Error)]
+pub enum MarkPriceError {
+    #[error("Invalid mark price method: {0}")]
+    InvalidMethod(String),
+
+    #[error("Unknown index source: {0}")]
+    UnknownSource(String),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// How a symbol's mark price is derived
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarkPriceMethod {
+    /// Median of mid, index + basis EMA and last trade
+    Median,
+    /// Last trade price (legacy behaviour)
+    LastTrade,
+}
+
+impl FromStr for MarkPriceMethod {
+    type Err = MarkPriceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "median" => Ok(Self::Median),
+            "last_trade" => Ok(Self::LastTrade),
+            other => Err(MarkPriceError::InvalidMethod(other.to_string())),
+        }
+    }
+}
+
+/// Mark price settings for a symbol
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarkPriceConfig {
+    pub method: MarkPriceMethod,
+    /// Time constant of the basis EMA
+    pub basis_ema_secs: u64,
+    /// Index sources to use (empty = all registered sources)
+    #[serde(default)]
+    pub index_sources: Vec<String>,
+}
+
+/// Inputs to the mark price of one symbol
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MarkInputs {
+    pub best_bid: Option<Decimal>,
+    pub best_ask: Option<Decimal>,
+    pub last_price: Option<Decimal>,
+    pub index_price: Option<Decimal>,
+}
+
+impl MarkInputs {
+    pub fn mid(&self) -> Option<Decimal> {
+        match (self.best_bid, self.best_ask) {
+            (Some(bid), Some(ask)) => Some((bid + ask) / Decimal::TWO),
+            _ => None,
+        }
+    }
+}
+
+/// Time-weighted EMA of the basis (mid - index)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BasisEma {
+    pub value: Decimal,
+    /// Unix millis of the last sample
+    pub updated_ms: i64,
+}
+
+impl BasisEma {
+    /// Fold in a basis sample; the weight grows with the time since the last one
+    pub fn update(previous: Option<BasisEma>, basis: Decimal, now_ms: i64, period_secs: u64) -> BasisEma {
+        let value = match previous {
+            Some(prev) if period_secs > 0 => {
+                let elapsed = Decimal::from((now_ms - prev.updated_ms).max(0));
+                let alpha = (elapsed / Decimal::from(period_secs * 1000)).min(Decimal::ONE);
+                prev.value + alpha * (basis - prev.value)
+            }
+            _ => basis,
+        };
+        BasisEma { value, updated_ms: now_ms }
+    }
+}
+
+/// Median of the given values (None if empty)
+pub fn median(mut values: Vec<Decimal>) -> Option<Decimal> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort();
+    let mid = values.len() / 2;
+    Some(if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / Decimal::TWO
+    } else {
+        values[mid]
+    })
+}
+
+/// Mark price from its inputs and the current basis EMA
+pub fn compute_mark(method: MarkPriceMethod, inputs: &MarkInputs, basis_ema: Option<Decimal>) -> Option<Decimal> {
+    let mid = inputs.mid();
+    match method {
+        MarkPriceMethod::LastTrade => inputs.last_price.or(mid),
+        MarkPriceMethod::Median => {
+            let Some(index) = inputs.index_price else {
+                return mid.or(inputs.last_price);
+            };
+            let basis_price = index + basis_ema.unwrap_or_default();
+            median([mid, Some(basis_price), inputs.last_price].into_iter().flatten().collect())
+        }
+    }
+}
+
+// ============================================================================
+// Index Sources
+// ============================================================================
+
+/// A source of index prices
+pub trait IndexSource: Send + Sync {
+    /// Source name, as used in `index_sources`
+    fn name(&self) -> &'static str;
+
+    /// Index price for a symbol (`{market_id}:{outcome_id}:{share_type}`), if known
+    fn index_price<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Option<Decimal>>;
+}
+
+/// Oracle probability of the outcome (`outcomes.probability`), complemented for No shares
+pub struct ProbabilityIndexSource {
+    pool: PgPool,
+}
+
+impl ProbabilityIndexSource {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl IndexSource for ProbabilityIndexSource {
+    fn name(&self) -> &'static str {
+        "probability"
+    }
+
+    fn index_price<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Option<Decimal>> {
+        Box::pin(async move {
+            let mut parts = symbol.split(':');
+            let _market_id = parts.next()?;
+            let outcome_id: Uuid = parts.next()?.parse().ok()?;
+            let share_type: ShareType = parts.next()?.parse().ok()?;
+
+            let probability: Option<Decimal> =
+                sqlx::query_scalar("SELECT probability FROM outcomes WHERE id = $1")
+                    .bind(outcome_id)
+                    .fetch_optional(&self.pool)
+                    .await
+                    .ok()
+                    .flatten();
+
+            probability.map(|p| match share_type {
+                ShareType::Yes => p,
+                ShareType::No => Decimal::ONE - p,
+            })
+        })
+    }
+}
+
+/// External index prices published to the Redis price cache
+pub struct CacheIndexSource {
+    cache: Arc<CacheManager>,
+}
+
+impl CacheIndexSource {
+    pub fn new(cache: Arc<CacheManager>) -> Self {
+        Self { cache }
+    }
+}
+
+impl IndexSource for CacheIndexSource {
+    fn name(&self) -> &'static str {
+        "cache"
+    }
+
+    fn index_price<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Option<Decimal>> {
+        Box::pin(async move { self.cache.price_opt()?.get_index_price(symbol).await })
+    }
+}
+
+// ============================================================================
+// Service
+// ============================================================================
+
+/// Per-symbol mark price update
+#[derive(Debug, Clone, Copy)]
+pub struct MarkUpdate {
+    pub mark_price: Decimal,
+    pub index_price: Option<Decimal>,
+    pub basis_ema: Option<Decimal>,
+}
+
+/// Computes and publishes mark prices for every orderbook
+pub struct MarkPriceService {
+    sources: Vec<Arc<dyn IndexSource>>,
+    default_config: MarkPriceConfig,
+    configs: DashMap<String, MarkPriceConfig>,
+    emas: DashMap<String, BasisEma>,
+}
+
+impl MarkPriceService {
+    pub fn new(default_config: MarkPriceConfig) -> Self {
+        Self {
+            sources: Vec::new(),
+            default_config,
+            configs: DashMap::new(),
+            emas: DashMap::new(),
+        }
+    }
+
+    /// Register an index source
+    pub fn with_source(mut self, source: Arc<dyn IndexSource>) -> Self {
+        self.sources.push(source);
+        self
+    }
+
+    /// Names of the registered index sources
+    pub fn source_names(&self) -> Vec<&'static str> {
+        self.sources.iter().map(|s| s.name()).collect()
+    }
+
+    /// Settings in effect for a symbol
+    pub fn config(&self, symbol: &str) -> MarkPriceConfig {
+        self.configs
+            .get(symbol)
+            .map(|c| c.clone())
+            .unwrap_or_else(|| self.default_config.clone())
+    }
+
+    /// Reject configs naming sources that are not registered
+    pub fn validate(&self, config: &MarkPriceConfig) -> Result<(), MarkPriceError> {
+        match config.index_sources.iter().find(|name| !self.source_names().contains(&name.as_str())) {
+            Some(unknown) => Err(MarkPriceError::UnknownSource(unknown.clone())),
+            None => Ok(()),
+        }
+    }
+
+    /// Set (or with None, reset to the default) a symbol's settings
+    pub async fn set_config(
+        &self,
+        pool: &PgPool,
+        symbol: &str,
+        config: Option<MarkPriceConfig>,
+    ) -> Result<MarkPriceConfig, MarkPriceError> {
+        match config {
+            Some(config) => {
+                self.validate(&config)?;
+                sqlx::query(
+                    r#"
+                    INSERT INTO mark_price_configs (symbol, method, basis_ema_secs, index_sources)
+                    VALUES ($1, $2, $3, $4)
+                    ON CONFLICT (symbol) DO UPDATE SET
+                        method = $2,
+                        basis_ema_secs = $3,
+                        index_sources = $4,
+                        updated_at = NOW()
+                    "#,
+                )
+                .bind(symbol)
+                .bind(match config.method {
+                    MarkPriceMethod::Median => "median",
+                    MarkPriceMethod::LastTrade => "last_trade",
+                })
+                .bind(config.basis_ema_secs as i64)
+                .bind(&config.index_sources)
+                .execute(pool)
+                .await?;
+                self.configs.insert(symbol.to_string(), config.clone());
+                Ok(config)
+            }
+            None => {
+                sqlx::query("DELETE FROM mark_price_configs WHERE symbol = $1")
+                    .bind(symbol)
+                    .execute(pool)
+                    .await?;
+                self.configs.remove(symbol);
+                Ok(self.default_config.clone())
+            }
+        }
+    }
+
+    /// Load per-symbol settings from the database
+    pub async fn load_configs(&self, pool: &PgPool) -> Result<usize, MarkPriceError> {
+        let rows: Vec<(String, String, i64, Vec<String>)> =
+            sqlx::query_as("SELECT symbol, method, basis_ema_secs, index_sources FROM mark_price_configs")
+                .fetch_all(pool)
+                .await?;
+
+        let count = rows.len();
+        for (symbol, method, basis_ema_secs, index_sources) in rows {
+            let method = method.parse().unwrap_or(self.default_config.method);
+            self.configs.insert(
+                symbol,
+                MarkPriceConfig {
+                    method,
+                    basis_ema_secs: basis_ema_secs.max(0) as u64,
+                    index_sources,
+                },
+            );
+        }
+        Ok(count)
+    }
+
+    /// Index price for a symbol: median of the selected sources that have one
+    async fn index_price(&self, symbol: &str, config: &MarkPriceConfig) -> Option<Decimal> {
+        let mut prices = Vec::new();
+        for source in &self.sources {
+            if !config.index_sources.is_empty() && !config.index_sources.iter().any(|n| n == source.name()) {
+                continue;
+            }
+            if let Some(price) = source.index_price(symbol).await {
+                prices.push(price);
+            }
+        }
+        median(prices)
+    }
+
+    /// Index price for a symbol with its configured sources
+    pub async fn symbol_index_price(&self, symbol: &str) -> Option<Decimal> {
+        let config = self.config(symbol);
+        self.index_price(symbol, &config).await
+    }
+
+    /// Recompute one symbol's mark price
+    pub async fn update_symbol(&self, engine: &MatchingEngine, symbol: &str, now_ms: i64) -> Option<MarkUpdate> {
+        let config = self.config(symbol);
+        let (best_bid, best_ask) = engine.get_best_prices(symbol).unwrap_or((None, None));
+        let inputs = MarkInputs {
+            best_bid,
+            best_ask,
+            last_price: engine.get_orderbook_ref(symbol).and_then(|ob| ob.last_trade_price()),
+            index_price: self.index_price(symbol, &config).await,
+        };
+
+        let basis_ema = match (inputs.mid(), inputs.index_price) {
+            (Some(mid), Some(index)) => {
+                let previous = self.emas.get(symbol).map(|e| *e);
+                let ema = BasisEma::update(previous, mid - index, now_ms, config.basis_ema_secs);
+                self.emas.insert(symbol.to_string(), ema);
+                Some(ema.value)
+            }
+            _ => self.emas.get(symbol).map(|e| e.value),
+        };
+
+        let mark_price = compute_mark(config.method, &inputs, basis_ema)?;
+        engine.set_mark_price(symbol, mark_price);
+        Some(MarkUpdate {
+            mark_price,
+            index_price: inputs.index_price,
+            basis_ema,
+        })
+    }
+
+    /// Recompute mark prices for every orderbook; returns the number updated
+    pub async fn update_all(&self, engine: &MatchingEngine, cache: &CacheManager) -> usize {
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        let mut updated = 0;
+        for symbol in engine.orderbook_symbols() {
+            let Some(update) = self.update_symbol(engine, &symbol, now_ms).await else {
+                continue;
+            };
+            updated += 1;
+            if let Some(price_cache) = cache.price_opt() {
+                if let Err(e) = price_cache.set_mark_price(&symbol, update.mark_price).await {
+                    tracing::warn!("Failed to cache mark price for {}: {}", symbol, e);
+                }
+            }
+        }
+        updated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_median_mark_ignores_last_trade_spike() {
+        let inputs = MarkInputs {
+            best_bid: Some(dec!(0.48)),
+            best_ask: Some(dec!(0.52)),
+            last_price: Some(dec!(0.95)),
+            index_price: Some(dec!(0.49)),
+        };
+        // Median of mid 0.50, index + basis 0.50, last 0.95
+        assert_eq!(compute_mark(MarkPriceMethod::Median, &inputs, Some(dec!(0.01))), Some(dec!(0.50)));
+        assert_eq!(compute_mark(MarkPriceMethod::LastTrade, &inputs, None), Some(dec!(0.95)));
+
+        // Without an index: mid, then last trade
+        let no_index = MarkInputs { index_price: None, ..inputs };
+        assert_eq!(compute_mark(MarkPriceMethod::Median, &no_index, None), Some(dec!(0.50)));
+        let last_only = MarkInputs {
+            last_price: Some(dec!(0.4)),
+            ..Default::default()
+        };
+        assert_eq!(compute_mark(MarkPriceMethod::Median, &last_only, None), Some(dec!(0.4)));
+    }
+
+    #[test]
+    fn test_basis_ema_is_time_weighted() {
+        let first = BasisEma::update(None, dec!(0.02), 0, 60);
+        assert_eq!(first.value, dec!(0.02));
+
+        // 15s of a 60s period moves a quarter of the way
+        let next = BasisEma::update(Some(first), dec!(0.10), 15_000, 60);
+        assert_eq!(next.value, dec!(0.04));
+
+        // A full period or more replaces it
+        let last = BasisEma::update(Some(next), dec!(-0.01), 120_000, 60);
+        assert_eq!(last.value, dec!(-0.01));
+    }
+
+    #[test]
+    fn test_median() {
+        assert_eq!(median(vec![]), None);
+        assert_eq!(median(vec![dec!(3), dec!(1), dec!(2)]), Some(dec!(2)));
+        assert_eq!(median(vec![dec!(0.4), dec!(0.6)]), Some(dec!(0.5)));
+    }
+}
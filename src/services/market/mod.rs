@@ -1,26 +1,43 @@
 #![allow(dead_code)]
 //! Market Data Service
 
+pub mod calendar;
+pub mod index_price;
+pub mod mark_price;
+pub mod rules;
+pub mod symbols;
+pub mod ticker;
+
+use dashmap::DashMap;
 use rust_decimal::Decimal;
-// use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use self::rules::RuleViolation;
+use crate::services::matching::FeeConfig;
 
+/// Market configuration, loaded from the `markets` row (and its fee tier) on
+/// first use and cached until invalidated by an admin change
+#[derive(Default)]
 pub struct MarketService {
-    // TODO: Price feeds, market configs
+    configs: DashMap<Uuid, Arc<MarketConfig>>,
 }
 
 impl MarketService {
     pub fn new() -> Self {
-        Self {}
+        Self::default()
     }
 
     /// Get current mark price for a symbol
-    pub async fn get_mark_price(&self, _symbol: &str) -> anyhow::Result<Decimal> {
+    pub async fn get_mark_price(&self, _symbol: &str) -> Result<Decimal, sqlx::Error> {
         // TODO: Get from price service
         Ok(Decimal::new(50000, 0))
     }
 
     /// Get funding rate
-    pub async fn get_funding_rate(&self, _symbol: &str) -> anyhow::Result<FundingInfo> {
+    pub async fn get_funding_rate(&self, _symbol: &str) -> Result<FundingInfo, sqlx::Error> {
         // TODO: Get from funding service
         Ok(FundingInfo {
             rate: Decimal::ZERO,
@@ -28,36 +45,251 @@ impl MarketService {
         })
     }
 
-    /// Get market configuration
-    pub fn get_market_config(&self, symbol: &str) -> Option<MarketConfig> {
-        // TODO: Load from config/database
-        Some(MarketConfig {
-            symbol: symbol.to_string(),
-            base_asset: "BTC".to_string(),
-            quote_asset: "USD".to_string(),
-            min_order_size: Decimal::new(1, 4),
-            max_order_size: Decimal::new(1000, 0),
-            tick_size: Decimal::new(1, 1),
-            max_leverage: 100,
-            maintenance_margin_rate: Decimal::new(5, 3), // 0.5%
-            maker_fee: Decimal::new(2, 4),               // 0.02%
-            taker_fee: Decimal::new(5, 4),               // 0.05%
-        })
+    /// Get market configuration (None if the market does not exist)
+    pub async fn get_market_config(
+        &self,
+        pool: &PgPool,
+        market_id: Uuid,
+    ) -> Result<Option<Arc<MarketConfig>>, sqlx::Error> {
+        if let Some(config) = self.configs.get(&market_id) {
+            return Ok(Some(config.clone()));
+        }
+
+        let row: Option<MarketConfigRow> = sqlx::query_as(
+            r#"
+            SELECT m.id, m.tick_size, m.lot_size, m.min_order_size, m.max_order_size, m.max_leverage,
+                   m.fee_tier, f.base_fee_bps, f.max_fee_bps, f.maker_discount_pct, m.margin_tiers,
+                   m.quote_asset, m.high_volatility
+            FROM markets m
+            LEFT JOIN fee_tiers f ON f.name = m.fee_tier
+            WHERE m.id = $1
+            "#,
+        )
+        .bind(market_id)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row.map(|row| {
+            let config = Arc::new(MarketConfig::from(row));
+            self.configs.insert(market_id, config.clone());
+            config
+        }))
+    }
+
+    /// Drop a market's cached configuration
+    pub fn invalidate(&self, market_id: Uuid) {
+        self.configs.remove(&market_id);
+    }
+
+    /// Drop every cached configuration (e.g. after a fee tier change)
+    pub fn invalidate_all(&self) {
+        self.configs.clear();
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct MarketConfig {
-    pub symbol: String,
-    pub base_asset: String,
-    pub quote_asset: String,
-    pub min_order_size: Decimal,
-    pub max_order_size: Decimal,
-    pub tick_size: Decimal,
-    pub max_leverage: i32,
+/// Risk limit for positions up to a notional size: the maintenance margin
+/// rate and leverage cap that apply to them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarginTier {
+    /// Upper notional bound (None = unbounded, only valid on the last tier)
+    pub max_notional: Option<Decimal>,
     pub maintenance_margin_rate: Decimal,
+    /// Leverage cap within the tier (None = the market cap)
+    #[serde(default)]
+    pub max_leverage: Option<u32>,
+}
+
+/// Validate margin tiers: ascending bounds, rates in (0, 1), only the last
+/// tier unbounded. Larger tiers may not be more lenient: rates never fall and
+/// leverage caps never rise.
+pub fn validate_margin_tiers(tiers: &[MarginTier]) -> Result<(), String> {
+    let mut previous = Decimal::ZERO;
+    let mut previous_rate = Decimal::ZERO;
+    let mut previous_leverage = u32::MAX;
+    for (i, tier) in tiers.iter().enumerate() {
+        if tier.maintenance_margin_rate <= Decimal::ZERO || tier.maintenance_margin_rate >= Decimal::ONE {
+            return Err(format!("margin tier {} rate must be between 0 and 1", i));
+        }
+        if tier.maintenance_margin_rate < previous_rate {
+            return Err(format!("margin tier {} rate is below the previous tier", i));
+        }
+        previous_rate = tier.maintenance_margin_rate;
+        if let Some(leverage) = tier.max_leverage {
+            if leverage == 0 || leverage > previous_leverage {
+                return Err(format!("margin tier {} leverage must be 1 to {}", i, previous_leverage));
+            }
+            previous_leverage = leverage;
+        }
+        match tier.max_notional {
+            Some(max) if max <= previous => {
+                return Err(format!("margin tier {} bound must be above the previous tier", i));
+            }
+            Some(max) => previous = max,
+            None if i + 1 < tiers.len() => {
+                return Err(format!("only the last margin tier may be unbounded (tier {})", i));
+            }
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+#[derive(sqlx::FromRow)]
+struct MarketConfigRow {
+    id: Uuid,
+    tick_size: Option<Decimal>,
+    lot_size: Option<Decimal>,
+    min_order_size: Option<Decimal>,
+    max_order_size: Option<Decimal>,
+    max_leverage: i32,
+    fee_tier: Option<String>,
+    base_fee_bps: Option<i32>,
+    max_fee_bps: Option<i32>,
+    maker_discount_pct: Option<i32>,
+    margin_tiers: sqlx::types::Json<Vec<MarginTier>>,
+    quote_asset: Option<String>,
+    high_volatility: bool,
+}
+
+impl From<MarketConfigRow> for MarketConfig {
+    fn from(row: MarketConfigRow) -> Self {
+        let fees = match (row.base_fee_bps, row.max_fee_bps, row.maker_discount_pct) {
+            (Some(base), Some(max), Some(discount)) => FeeConfig {
+                base_fee_bps: base.max(0) as u32,
+                max_fee_bps: max.max(0) as u32,
+                maker_discount_pct: discount.clamp(0, 100) as u32,
+            },
+            _ => FeeConfig::default(),
+        };
+        let taker_fee = Decimal::from(fees.base_fee_bps) / Decimal::from(10_000);
+        let maker_fee = taker_fee * Decimal::from(100 - fees.maker_discount_pct.min(100)) / Decimal::from(100);
+        let max_fee = Decimal::from(fees.max_fee_bps) / Decimal::from(10_000);
+
+        Self {
+            market_id: row.id,
+            tick_size: row.tick_size,
+            lot_size: row.lot_size,
+            min_order_size: row.min_order_size,
+            max_order_size: row.max_order_size,
+            max_leverage: row.max_leverage.max(1) as u32,
+            fee_tier: row.fee_tier,
+            maker_fee,
+            taker_fee,
+            max_fee,
+            margin_tiers: row.margin_tiers.0,
+            quote_asset: row.quote_asset,
+            high_volatility: row.high_volatility,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MarketConfig {
+    pub market_id: Uuid,
+    pub tick_size: Option<Decimal>,
+    pub lot_size: Option<Decimal>,
+    pub min_order_size: Option<Decimal>,
+    pub max_order_size: Option<Decimal>,
+    pub max_leverage: u32,
+    /// Fee tier name (None = engine default schedule)
+    pub fee_tier: Option<String>,
+    /// Base fee rates before the symmetric price factor
     pub maker_fee: Decimal,
     pub taker_fee: Decimal,
+    /// Cap on the fee per share, after the price factor
+    pub max_fee: Decimal,
+    pub margin_tiers: Vec<MarginTier>,
+    /// Asset prices and PnL are quoted in (None = the collateral token)
+    pub quote_asset: Option<String>,
+    /// Only accounts on a risk profile with high-volatility access may trade it
+    pub high_volatility: bool,
+}
+
+impl MarketConfig {
+    /// Check an order amount against the market's size limits
+    pub fn check_order_size(&self, amount: Decimal) -> Result<(), RuleViolation> {
+        if let Some(min) = self.min_order_size {
+            if amount < min {
+                return Err(RuleViolation::BelowMinSize { amount, min });
+            }
+        }
+        if let Some(max) = self.max_order_size {
+            if amount > max {
+                return Err(RuleViolation::AboveMaxSize { amount, max });
+            }
+        }
+        Ok(())
+    }
+
+    /// Risk limit tier covering a position notional (None = no tiers set)
+    pub fn risk_limit(&self, notional: Decimal) -> Option<&MarginTier> {
+        self.margin_tiers
+            .iter()
+            .find(|tier| tier.max_notional.is_none_or(|max| notional <= max))
+            .or(self.margin_tiers.last())
+    }
+
+    /// Maintenance margin rate for a position notional (None = no tiers set,
+    /// use the global rate)
+    pub fn maintenance_margin_rate(&self, notional: Decimal) -> Option<Decimal> {
+        self.risk_limit(notional).map(|tier| tier.maintenance_margin_rate)
+    }
+
+    /// Leverage cap for a position notional: the tier's cap, never above the
+    /// market's
+    pub fn max_leverage_for(&self, notional: Decimal) -> u32 {
+        self.risk_limit(notional)
+            .and_then(|tier| tier.max_leverage)
+            .map_or(self.max_leverage, |cap| cap.min(self.max_leverage))
+    }
+
+    /// Risk-limit brackets by position notional; markets without tiers have
+    /// a single bracket at the global maintenance rate
+    pub fn risk_limits(&self, default_maintenance_margin_rate: Decimal) -> Vec<RiskLimit> {
+        if self.margin_tiers.is_empty() {
+            return vec![RiskLimit {
+                min_notional: Decimal::ZERO,
+                max_notional: None,
+                maintenance_margin_rate: default_maintenance_margin_rate,
+                max_leverage: self.max_leverage,
+            }];
+        }
+        let mut min_notional = Decimal::ZERO;
+        self.margin_tiers
+            .iter()
+            .map(|tier| {
+                let limit = RiskLimit {
+                    min_notional,
+                    max_notional: tier.max_notional,
+                    maintenance_margin_rate: tier.maintenance_margin_rate,
+                    max_leverage: tier.max_leverage.map_or(self.max_leverage, |cap| cap.min(self.max_leverage)),
+                };
+                min_notional = tier.max_notional.unwrap_or(min_notional);
+                limit
+            })
+            .collect()
+    }
+
+    /// Check an order's leverage against the risk limit of the position
+    /// notional it would result in
+    pub fn check_leverage(&self, notional: Decimal, leverage: u32) -> Result<(), RuleViolation> {
+        let max = self.max_leverage_for(notional);
+        if leverage > max {
+            return Err(RuleViolation::LeverageTooHigh { requested: leverage, max });
+        }
+        Ok(())
+    }
+}
+
+/// One risk-limit bracket
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RiskLimit {
+    /// Lower notional bound (exclusive, except for the first bracket)
+    pub min_notional: Decimal,
+    /// Upper notional bound (None = unbounded)
+    pub max_notional: Option<Decimal>,
+    pub maintenance_margin_rate: Decimal,
+    pub max_leverage: u32,
 }
 
 #[derive(Debug)]
@@ -65,3 +297,102 @@ pub struct FundingInfo {
     pub rate: Decimal,
     pub next_funding_time: i64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tier(max: Option<i64>, rate: &str) -> MarginTier {
+        MarginTier {
+            max_notional: max.map(Decimal::from),
+            maintenance_margin_rate: rate.parse().unwrap(),
+            max_leverage: None,
+        }
+    }
+
+    #[test]
+    fn test_margin_tiers_pick_first_covering_bound() {
+        let tiers = vec![tier(Some(10_000), "0.005"), tier(Some(100_000), "0.01"), tier(None, "0.025")];
+        assert!(validate_margin_tiers(&tiers).is_ok());
+        assert!(validate_margin_tiers(&[tier(None, "0.01"), tier(Some(5), "0.02")]).is_err());
+        assert!(validate_margin_tiers(&[tier(Some(10), "0.01"), tier(Some(10), "0.02")]).is_err());
+        assert!(validate_margin_tiers(&[tier(Some(10), "1")]).is_err());
+
+        let mut config = MarketConfig {
+            market_id: Uuid::nil(),
+            tick_size: None,
+            lot_size: None,
+            min_order_size: Some(Decimal::from(5)),
+            max_order_size: Some(Decimal::from(1_000)),
+            max_leverage: 1,
+            fee_tier: None,
+            maker_fee: Decimal::ZERO,
+            taker_fee: Decimal::ZERO,
+            max_fee: Decimal::ZERO,
+            margin_tiers: tiers,
+            quote_asset: None,
+            high_volatility: false,
+        };
+        assert_eq!(config.maintenance_margin_rate(Decimal::from(10_000)), Some("0.005".parse().unwrap()));
+        assert_eq!(config.maintenance_margin_rate(Decimal::from(50_000)), Some("0.01".parse().unwrap()));
+        assert_eq!(config.maintenance_margin_rate(Decimal::from(500_000)), Some("0.025".parse().unwrap()));
+
+        // Bounded top tier covers larger notionals too
+        config.margin_tiers.pop();
+        assert_eq!(config.maintenance_margin_rate(Decimal::from(500_000)), Some("0.01".parse().unwrap()));
+        config.margin_tiers.clear();
+        assert_eq!(config.maintenance_margin_rate(Decimal::ONE), None);
+
+        assert!(config.check_order_size(Decimal::from(5)).is_ok());
+        assert!(matches!(config.check_order_size(Decimal::from(4)), Err(RuleViolation::BelowMinSize { .. })));
+        assert!(matches!(config.check_order_size(Decimal::from(1_001)), Err(RuleViolation::AboveMaxSize { .. })));
+    }
+
+    #[test]
+    fn test_risk_limits_cap_leverage_by_notional() {
+        let capped = |max: Option<i64>, rate: &str, leverage: Option<u32>| MarginTier {
+            max_leverage: leverage,
+            ..tier(max, rate)
+        };
+        let tiers = vec![
+            capped(Some(10_000), "0.01", Some(20)),
+            capped(Some(100_000), "0.02", Some(10)),
+            capped(None, "0.05", Some(3)),
+        ];
+        assert!(validate_margin_tiers(&tiers).is_ok());
+        // Larger tiers may not be more lenient
+        assert!(validate_margin_tiers(&[capped(Some(10), "0.01", Some(5)), capped(None, "0.02", Some(10))]).is_err());
+        assert!(validate_margin_tiers(&[capped(Some(10), "0.02", None), capped(None, "0.01", None)]).is_err());
+        assert!(validate_margin_tiers(&[capped(None, "0.01", Some(0))]).is_err());
+
+        let config = MarketConfig {
+            market_id: Uuid::nil(),
+            tick_size: None,
+            lot_size: None,
+            min_order_size: None,
+            max_order_size: None,
+            max_leverage: 15,
+            fee_tier: None,
+            maker_fee: Decimal::ZERO,
+            taker_fee: Decimal::ZERO,
+            max_fee: Decimal::ZERO,
+            margin_tiers: tiers,
+            quote_asset: None,
+            high_volatility: false,
+        };
+        // The market cap still applies inside a more lenient tier
+        assert_eq!(config.max_leverage_for(Decimal::from(5_000)), 15);
+        assert_eq!(config.max_leverage_for(Decimal::from(50_000)), 10);
+        assert_eq!(config.max_leverage_for(Decimal::from(500_000)), 3);
+        assert!(config.check_leverage(Decimal::from(50_000), 10).is_ok());
+        let limits = config.risk_limits(Decimal::new(5, 3));
+        assert_eq!(
+            limits.iter().map(|l| (l.min_notional, l.max_leverage)).collect::<Vec<_>>(),
+            vec![(Decimal::ZERO, 15), (Decimal::from(10_000), 10), (Decimal::from(100_000), 3)]
+        );
+        assert_eq!(
+            config.check_leverage(Decimal::from(500_000), 5),
+            Err(RuleViolation::LeverageTooHigh { requested: 5, max: 3 })
+        );
+    }
+}
@@ -0,0 +1,461 @@
+//! Per-market Trading Rules
+//!
+//! Runtime trading controls for a market, managed through the admin API and
+//! stored on the `markets` row:
+//!
+//! - `paused`: new orders are rejected; resting orders stay on the book
+//! - `delisted`: the market is removed from trading and listings, and its
+//!   resting orders are cancelled
+//! - `max_leverage`: highest leverage an order may request
+//! - `fee_tier`: named fee schedule from `fee_tiers` (None = engine default)
+//! - `tick_size` / `lot_size`: order prices and amounts must be multiples
+//! - `high_volatility`: only risk profiles with access may trade the market
+//!
+//! The matching engine holds the rules of every market and reloads them on
+//! each admin change, so no restart is needed.
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{validate_margin_tiers, MarginTier};
+use crate::services::ledger::{BalanceChange, LedgerReason, LedgerService};
+use crate::services::matching::{FeeConfig, MatchingEngine, Side};
+
+/// Market rules management errors
+#[derive(Debug, thiserror::Error)]
+pub enum MarketRulesError {
+    #[error("Market not found: {0}")]
+    MarketNotFound(Uuid),
+
+    #[error("Unknown fee tier: {0}")]
+    UnknownFeeTier(String),
+
+    #[error("Invalid market rules: {0}")]
+    InvalidRules(String),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// Why an order breaks a market's rules
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum RuleViolation {
+    #[error("market is paused")]
+    Paused,
+
+    #[error("market is delisted")]
+    Delisted,
+
+    #[error("leverage {requested} exceeds the market cap of {max}")]
+    LeverageTooHigh { requested: u32, max: u32 },
+
+    #[error("price {price} is not a multiple of the tick size {tick}")]
+    OffTick { price: Decimal, tick: Decimal },
+
+    #[error("amount {amount} is not a multiple of the lot size {lot}")]
+    OffLot { amount: Decimal, lot: Decimal },
+
+    #[error("amount {amount} is below the minimum order size {min}")]
+    BelowMinSize { amount: Decimal, min: Decimal },
+
+    #[error("amount {amount} is above the maximum order size {max}")]
+    AboveMaxSize { amount: Decimal, max: Decimal },
+}
+
+/// Trading rules of one market
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarketRules {
+    pub paused: bool,
+    pub delisted_at: Option<DateTime<Utc>>,
+    pub max_leverage: u32,
+    pub fee_tier: Option<String>,
+    pub tick_size: Option<Decimal>,
+    pub lot_size: Option<Decimal>,
+}
+
+impl Default for MarketRules {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            delisted_at: None,
+            max_leverage: 1,
+            fee_tier: None,
+            tick_size: None,
+            lot_size: None,
+        }
+    }
+}
+
+impl MarketRules {
+    /// Validate admin-set limits
+    pub fn validate(&self) -> Result<(), MarketRulesError> {
+        if self.max_leverage == 0 {
+            return Err(MarketRulesError::InvalidRules("max_leverage must be at least 1".to_string()));
+        }
+        if self.tick_size.is_some_and(|t| t <= Decimal::ZERO || t >= Decimal::ONE) {
+            return Err(MarketRulesError::InvalidRules("tick_size must be between 0 and 1".to_string()));
+        }
+        if self.lot_size.is_some_and(|l| l <= Decimal::ZERO) {
+            return Err(MarketRulesError::InvalidRules("lot_size must be positive".to_string()));
+        }
+        Ok(())
+    }
+
+    pub fn is_delisted(&self) -> bool {
+        self.delisted_at.is_some()
+    }
+
+    /// Check a new order against the rules
+    pub fn check_order(&self, price: Option<Decimal>, amount: Decimal, leverage: u32) -> Result<(), RuleViolation> {
+        if self.is_delisted() {
+            return Err(RuleViolation::Delisted);
+        }
+        if self.paused {
+            return Err(RuleViolation::Paused);
+        }
+        if leverage > self.max_leverage {
+            return Err(RuleViolation::LeverageTooHigh {
+                requested: leverage,
+                max: self.max_leverage,
+            });
+        }
+        if let (Some(tick), Some(price)) = (self.tick_size, price) {
+            if !is_multiple(price, tick) {
+                return Err(RuleViolation::OffTick { price, tick });
+            }
+        }
+        if let Some(lot) = self.lot_size {
+            if !is_multiple(amount, lot) {
+                return Err(RuleViolation::OffLot { amount, lot });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether `value` is a whole multiple of a positive `step`
+fn is_multiple(value: Decimal, step: Decimal) -> bool {
+    step <= Decimal::ZERO || (value % step).is_zero()
+}
+
+/// Named fee schedule markets can be assigned to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct FeeTier {
+    /// Taken from the path when set through the admin API
+    #[serde(default)]
+    pub name: String,
+    pub base_fee_bps: i32,
+    pub max_fee_bps: i32,
+    pub maker_discount_pct: i32,
+}
+
+impl FeeTier {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.base_fee_bps < 0 || self.max_fee_bps < 0 {
+            return Err("fee rates must not be negative".to_string());
+        }
+        if self.base_fee_bps > self.max_fee_bps {
+            return Err("base_fee_bps must not exceed max_fee_bps".to_string());
+        }
+        if !(0..=100).contains(&self.maker_discount_pct) {
+            return Err("maker_discount_pct must be between 0 and 100".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn fee_config(&self) -> FeeConfig {
+        FeeConfig {
+            base_fee_bps: self.base_fee_bps.max(0) as u32,
+            max_fee_bps: self.max_fee_bps.max(0) as u32,
+            maker_discount_pct: self.maker_discount_pct.clamp(0, 100) as u32,
+        }
+    }
+}
+
+/// Limits an admin can change on a market (replaces the current ones)
+#[derive(Debug, Clone, Deserialize)]
+pub struct MarketLimits {
+    #[serde(default = "default_max_leverage")]
+    pub max_leverage: u32,
+    pub fee_tier: Option<String>,
+    pub tick_size: Option<Decimal>,
+    pub lot_size: Option<Decimal>,
+    pub min_order_size: Option<Decimal>,
+    pub max_order_size: Option<Decimal>,
+    #[serde(default)]
+    pub margin_tiers: Vec<MarginTier>,
+    /// Restrict the market to risk profiles with high-volatility access
+    #[serde(default)]
+    pub high_volatility: bool,
+}
+
+fn default_max_leverage() -> u32 {
+    1
+}
+
+type RulesRow = (bool, Option<DateTime<Utc>>, i32, Option<String>, Option<Decimal>, Option<Decimal>);
+
+fn rules_from_row((paused, delisted_at, max_leverage, fee_tier, tick_size, lot_size): RulesRow) -> MarketRules {
+    MarketRules {
+        paused,
+        delisted_at,
+        max_leverage: max_leverage.max(1) as u32,
+        fee_tier,
+        tick_size,
+        lot_size,
+    }
+}
+
+const RULES_COLUMNS: &str = "trading_paused, delisted_at, max_leverage, fee_tier, tick_size, lot_size";
+
+/// Runtime market management: persists rule changes and pushes them into the
+/// matching engine
+pub struct MarketRulesService;
+
+impl MarketRulesService {
+    /// Current rules of a market
+    pub async fn get(pool: &PgPool, market_id: Uuid) -> Result<MarketRules, MarketRulesError> {
+        let row: Option<RulesRow> = sqlx::query_as(&format!("SELECT {RULES_COLUMNS} FROM markets WHERE id = $1"))
+            .bind(market_id)
+            .fetch_optional(pool)
+            .await?;
+        row.map(rules_from_row).ok_or(MarketRulesError::MarketNotFound(market_id))
+    }
+
+    /// Validate limits (including that the fee tier exists) into the rules
+    /// of an active market
+    pub async fn check_limits(pool: &PgPool, limits: &MarketLimits) -> Result<MarketRules, MarketRulesError> {
+        let rules = MarketRules {
+            max_leverage: limits.max_leverage,
+            fee_tier: limits.fee_tier.clone(),
+            tick_size: limits.tick_size,
+            lot_size: limits.lot_size,
+            ..Default::default()
+        };
+        rules.validate()?;
+        if limits.min_order_size.is_some_and(|min| min <= Decimal::ZERO)
+            || limits.max_order_size.is_some_and(|max| max <= Decimal::ZERO)
+        {
+            return Err(MarketRulesError::InvalidRules("order size limits must be positive".to_string()));
+        }
+        if let (Some(min), Some(max)) = (limits.min_order_size, limits.max_order_size) {
+            if min > max {
+                return Err(MarketRulesError::InvalidRules(
+                    "min_order_size must not exceed max_order_size".to_string(),
+                ));
+            }
+        }
+        validate_margin_tiers(&limits.margin_tiers).map_err(MarketRulesError::InvalidRules)?;
+        if let Some(tier) = &limits.fee_tier {
+            let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM fee_tiers WHERE name = $1)")
+                .bind(tier)
+                .fetch_one(pool)
+                .await?;
+            if !exists {
+                return Err(MarketRulesError::UnknownFeeTier(tier.clone()));
+            }
+        }
+        Ok(rules)
+    }
+
+    /// Replace a market's leverage cap, fee tier, tick / lot sizes, order size
+    /// limits, margin tiers and high-volatility flag
+    pub async fn set_limits(
+        pool: &PgPool,
+        engine: &MatchingEngine,
+        market_id: Uuid,
+        limits: &MarketLimits,
+    ) -> Result<MarketRules, MarketRulesError> {
+        Self::check_limits(pool, limits).await?;
+
+        let row: Option<RulesRow> = sqlx::query_as(&format!(
+            r#"
+            UPDATE markets SET max_leverage = $1, fee_tier = $2, tick_size = $3, lot_size = $4,
+                               min_order_size = $5, max_order_size = $6, margin_tiers = $7,
+                               high_volatility = $9
+            WHERE id = $8
+            RETURNING {RULES_COLUMNS}
+            "#
+        ))
+        .bind(limits.max_leverage as i32)
+        .bind(&limits.fee_tier)
+        .bind(limits.tick_size)
+        .bind(limits.lot_size)
+        .bind(limits.min_order_size)
+        .bind(limits.max_order_size)
+        .bind(sqlx::types::Json(&limits.margin_tiers))
+        .bind(market_id)
+        .bind(limits.high_volatility)
+        .fetch_optional(pool)
+        .await?;
+        Self::publish(engine, market_id, row)
+    }
+
+    /// Pause or resume new orders on a market
+    pub async fn set_paused(
+        pool: &PgPool,
+        engine: &MatchingEngine,
+        market_id: Uuid,
+        paused: bool,
+    ) -> Result<MarketRules, MarketRulesError> {
+        let row: Option<RulesRow> = sqlx::query_as(&format!(
+            "UPDATE markets SET trading_paused = $1 WHERE id = $2 RETURNING {RULES_COLUMNS}"
+        ))
+        .bind(paused)
+        .bind(market_id)
+        .fetch_optional(pool)
+        .await?;
+        Self::publish(engine, market_id, row)
+    }
+
+    /// Delist a market: stop trading, hide it from listings and cancel its
+    /// resting orders (releasing frozen collateral). Returns the new rules and
+    /// the number of orders cancelled.
+    pub async fn delist(
+        pool: &PgPool,
+        engine: &MatchingEngine,
+        market_id: Uuid,
+        collateral_token: &str,
+    ) -> Result<(MarketRules, usize), MarketRulesError> {
+        let row: Option<RulesRow> = sqlx::query_as(&format!(
+            "UPDATE markets SET delisted_at = COALESCE(delisted_at, NOW()) WHERE id = $1 RETURNING {RULES_COLUMNS}"
+        ))
+        .bind(market_id)
+        .fetch_optional(pool)
+        .await?;
+        // Publish first so no new orders land while the book is drained
+        let rules = Self::publish(engine, market_id, row)?;
+
+        let prefix = format!("{}:", market_id);
+        let mut cancelled = 0;
+        for (symbol, order) in engine.resting_orders() {
+            if !symbol.starts_with(&prefix) {
+                continue;
+            }
+            match engine.cancel_order(&symbol, order.id, &order.user_address) {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => {
+                    tracing::error!("Failed to cancel order {} while delisting {}: {}", order.id, market_id, e);
+                    continue;
+                }
+            }
+
+            let mut tx = pool.begin().await?;
+            sqlx::query("UPDATE orders SET status = 'cancelled'::order_status, updated_at = NOW() WHERE id = $1")
+                .bind(order.id)
+                .execute(&mut *tx)
+                .await?;
+            if order.side == Side::Buy {
+                let change = BalanceChange::unfreeze(
+                    &order.user_address,
+                    collateral_token,
+                    order.remaining_amount * order.price,
+                    LedgerReason::OrderUnfreeze,
+                )
+                .reference(order.id);
+                LedgerService::apply(&mut tx, &change).await?;
+            }
+            tx.commit().await?;
+            cancelled += 1;
+        }
+
+        Ok((rules, cancelled))
+    }
+
+    /// All fee tiers
+    pub async fn list_fee_tiers(pool: &PgPool) -> Result<Vec<FeeTier>, MarketRulesError> {
+        let tiers = sqlx::query_as(
+            "SELECT name, base_fee_bps, max_fee_bps, maker_discount_pct FROM fee_tiers ORDER BY name",
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(tiers)
+    }
+
+    /// Create or update a fee tier; markets on the tier pick it up immediately
+    pub async fn upsert_fee_tier(
+        pool: &PgPool,
+        engine: &MatchingEngine,
+        tier: &FeeTier,
+    ) -> Result<FeeTier, MarketRulesError> {
+        tier.validate().map_err(MarketRulesError::InvalidRules)?;
+        let saved: FeeTier = sqlx::query_as(
+            r#"
+            INSERT INTO fee_tiers (name, base_fee_bps, max_fee_bps, maker_discount_pct)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (name) DO UPDATE SET
+                base_fee_bps = EXCLUDED.base_fee_bps,
+                max_fee_bps = EXCLUDED.max_fee_bps,
+                maker_discount_pct = EXCLUDED.maker_discount_pct,
+                updated_at = NOW()
+            RETURNING name, base_fee_bps, max_fee_bps, maker_discount_pct
+            "#,
+        )
+        .bind(&tier.name)
+        .bind(tier.base_fee_bps)
+        .bind(tier.max_fee_bps)
+        .bind(tier.maker_discount_pct)
+        .fetch_one(pool)
+        .await?;
+        engine.set_fee_tier(&saved.name, Some(saved.fee_config()));
+        Ok(saved)
+    }
+
+    fn publish(
+        engine: &MatchingEngine,
+        market_id: Uuid,
+        row: Option<RulesRow>,
+    ) -> Result<MarketRules, MarketRulesError> {
+        let rules = row.map(rules_from_row).ok_or(MarketRulesError::MarketNotFound(market_id))?;
+        engine.set_market_rules(market_id, rules.clone());
+        Ok(rules)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_check_order() {
+        let rules = MarketRules {
+            tick_size: Some(dec!(0.01)),
+            lot_size: Some(dec!(5)),
+            max_leverage: 2,
+            ..Default::default()
+        };
+        assert!(rules.check_order(Some(dec!(0.55)), dec!(10), 1).is_ok());
+        assert!(matches!(rules.check_order(Some(dec!(0.555)), dec!(10), 1), Err(RuleViolation::OffTick { .. })));
+        assert!(matches!(rules.check_order(Some(dec!(0.55)), dec!(12), 1), Err(RuleViolation::OffLot { .. })));
+        assert_eq!(
+            rules.check_order(Some(dec!(0.55)), dec!(10), 3),
+            Err(RuleViolation::LeverageTooHigh { requested: 3, max: 2 })
+        );
+        // Market orders have no price to check against the tick
+        assert!(rules.check_order(None, dec!(10), 1).is_ok());
+
+        let paused = MarketRules { paused: true, ..rules.clone() };
+        assert_eq!(paused.check_order(Some(dec!(0.55)), dec!(10), 1), Err(RuleViolation::Paused));
+        let delisted = MarketRules { delisted_at: Some(Utc::now()), ..rules };
+        assert_eq!(delisted.check_order(Some(dec!(0.55)), dec!(10), 1), Err(RuleViolation::Delisted));
+    }
+
+    #[test]
+    fn test_fee_tier_validation() {
+        let tier = FeeTier {
+            name: "vip".to_string(),
+            base_fee_bps: 100,
+            max_fee_bps: 500,
+            maker_discount_pct: 100,
+        };
+        assert!(tier.validate().is_ok());
+        assert_eq!(tier.fee_config().maker_discount_pct, 100);
+        assert!(FeeTier { base_fee_bps: 600, ..tier.clone() }.validate().is_err());
+        assert!(FeeTier { maker_discount_pct: 120, ..tier }.validate().is_err());
+    }
+}
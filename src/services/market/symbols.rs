@@ -0,0 +1,194 @@
+//! Symbol Aliases
+//!
+//! Frontends name pairs differently from the backend (`BTC-USD` in
+//! TradingView vs `BTCUSDT` here). The registry maps every accepted spelling
+//! of a configured trading pair to its canonical symbol:
+//!
+//! - the canonical symbol itself (`BTCUSDT`)
+//! - base/quote with a `-`, `/` or `_` separator, against the quote or, for
+//!   USDT pairs, USD (`BTC-USD`, `BTC/USDT`, `BTC_USD`, ...)
+//! - extra aliases from `symbol_aliases` (JSON, e.g. `{"BTCUSDT": ["XBT-USD"]}`)
+//!
+//! Matching is case-insensitive. Prediction market keys
+//! (`market_id:outcome_id:share_type`) and bare market ids are already
+//! canonical and pass through unchanged.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+/// Quote assets a canonical symbol is split on, longest first
+const QUOTE_ASSETS: [&str; 4] = ["USDT", "USDC", "USD", "BTC"];
+
+/// Separators frontends put between base and quote
+const SEPARATORS: [char; 3] = ['-', '/', '_'];
+
+/// Symbol alias errors
+#[derive(Debug, thiserror::Error)]
+pub enum SymbolError {
+    #[error("Unknown symbol '{symbol}'. Supported formats: BTCUSDT, BTC-USD, BTC-USDT, BTC/USD, BTC_USD (case-insensitive); supported symbols: {}", supported.join(", "))]
+    Unknown { symbol: String, supported: Vec<String> },
+
+    #[error("Invalid symbol_aliases config: {0}")]
+    InvalidConfig(String),
+}
+
+/// Canonical symbol and the aliases that resolve to it
+#[derive(Debug, Clone, Serialize)]
+pub struct SymbolMapping {
+    pub canonical: String,
+    pub aliases: Vec<String>,
+}
+
+pub struct SymbolRegistry {
+    /// Upper-cased alias -> canonical
+    lookup: HashMap<String, String>,
+    /// Canonical -> aliases (display form)
+    mappings: BTreeMap<String, Vec<String>>,
+}
+
+impl SymbolRegistry {
+    /// Registry of `pairs` plus the extra aliases of `aliases_json`
+    pub fn new(pairs: &[String], aliases_json: &str) -> Result<Self, SymbolError> {
+        let extra: HashMap<String, Vec<String>> = if aliases_json.trim().is_empty() {
+            HashMap::new()
+        } else {
+            serde_json::from_str(aliases_json).map_err(|e| SymbolError::InvalidConfig(e.to_string()))?
+        };
+
+        let mut registry = Self {
+            lookup: HashMap::new(),
+            mappings: BTreeMap::new(),
+        };
+        for pair in pairs {
+            let canonical = pair.trim().to_uppercase();
+            if canonical.is_empty() {
+                continue;
+            }
+            registry.add(&canonical, &canonical);
+            for alias in derived_aliases(&canonical) {
+                registry.add(&canonical, &alias);
+            }
+        }
+        for (canonical, aliases) in extra {
+            let canonical = canonical.trim().to_uppercase();
+            if !registry.mappings.contains_key(&canonical) {
+                return Err(SymbolError::InvalidConfig(format!(
+                    "aliases given for {} which is not a trading pair",
+                    canonical
+                )));
+            }
+            for alias in aliases {
+                if let Some(existing) = registry.lookup.get(&alias.trim().to_uppercase()) {
+                    if *existing != canonical {
+                        return Err(SymbolError::InvalidConfig(format!(
+                            "alias {} maps to both {} and {}",
+                            alias, existing, canonical
+                        )));
+                    }
+                }
+                registry.add(&canonical, alias.trim());
+            }
+        }
+        Ok(registry)
+    }
+
+    fn add(&mut self, canonical: &str, alias: &str) {
+        let key = alias.to_uppercase();
+        if self.lookup.insert(key, canonical.to_string()).is_some() {
+            return;
+        }
+        let aliases = self.mappings.entry(canonical.to_string()).or_default();
+        if alias != canonical {
+            aliases.push(alias.to_string());
+        }
+    }
+
+    /// Canonical symbol of `symbol`
+    pub fn resolve(&self, symbol: &str) -> Result<String, SymbolError> {
+        let symbol = symbol.trim();
+        if is_market_key(symbol) {
+            return Ok(symbol.to_string());
+        }
+        self.lookup.get(&symbol.to_uppercase()).cloned().ok_or_else(|| SymbolError::Unknown {
+            symbol: symbol.to_string(),
+            supported: self.mappings.keys().cloned().collect(),
+        })
+    }
+
+    /// Canonical symbols with their aliases
+    pub fn mappings(&self) -> Vec<SymbolMapping> {
+        self.mappings
+            .iter()
+            .map(|(canonical, aliases)| SymbolMapping {
+                canonical: canonical.clone(),
+                aliases: aliases.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Prediction market key or bare market id
+fn is_market_key(symbol: &str) -> bool {
+    let mut parts = symbol.split(':');
+    parts.next().is_some_and(|id| Uuid::parse_str(id).is_ok())
+}
+
+/// Separator spellings of a canonical symbol (`BTCUSDT` -> `BTC-USDT`, `BTC-USD`, ...)
+fn derived_aliases(canonical: &str) -> Vec<String> {
+    let Some((base, quote)) = QUOTE_ASSETS
+        .iter()
+        .find_map(|quote| canonical.strip_suffix(quote).filter(|base| !base.is_empty()).map(|base| (base, *quote)))
+    else {
+        return Vec::new();
+    };
+
+    let mut quotes = vec![quote];
+    if quote == "USDT" {
+        quotes.push("USD");
+    }
+    let mut aliases = Vec::new();
+    for quote in quotes {
+        for separator in SEPARATORS {
+            aliases.push(format!("{}{}{}", base, separator, quote));
+        }
+    }
+    aliases
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> SymbolRegistry {
+        let pairs = vec!["BTCUSDT".to_string(), "ETHUSDT".to_string()];
+        SymbolRegistry::new(&pairs, r#"{"BTCUSDT": ["XBT-USD"]}"#).unwrap()
+    }
+
+    #[test]
+    fn test_resolves_aliases_case_insensitively() {
+        let registry = registry();
+        for alias in ["BTCUSDT", "btcusdt", "BTC-USD", "btc/usdt", "BTC_USD", "xbt-usd"] {
+            assert_eq!(registry.resolve(alias).unwrap(), "BTCUSDT", "{}", alias);
+        }
+        assert_eq!(registry.resolve("ETH-USD").unwrap(), "ETHUSDT");
+
+        let key = format!("{}:{}:yes", Uuid::new_v4(), Uuid::new_v4());
+        assert_eq!(registry.resolve(&key).unwrap(), key);
+
+        let err = registry.resolve("DOGE-USD").unwrap_err().to_string();
+        assert!(err.contains("DOGE-USD") && err.contains("BTCUSDT, ETHUSDT"), "{}", err);
+
+        let btc = &registry.mappings()[0];
+        assert_eq!(btc.canonical, "BTCUSDT");
+        assert!(btc.aliases.contains(&"BTC-USD".to_string()) && btc.aliases.contains(&"XBT-USD".to_string()));
+    }
+
+    #[test]
+    fn test_rejects_aliases_of_unknown_or_conflicting_pairs() {
+        let pairs = vec!["BTCUSDT".to_string(), "ETHUSDT".to_string()];
+        assert!(SymbolRegistry::new(&pairs, r#"{"SOLUSDT": ["SOL-USD"]}"#).is_err());
+        assert!(SymbolRegistry::new(&pairs, r#"{"ETHUSDT": ["BTC-USD"]}"#).is_err());
+        assert!(SymbolRegistry::new(&pairs, "not json").is_err());
+    }
+}
@@ -0,0 +1,365 @@
+//! Rolling 24h Ticker
+//!
+//! Open, high, low, last, change and volume over the last 24 hours, per
+//! symbol. The statistics are kept as one-minute bars: on startup the bars of
+//! the last 24 hours are rebuilt from the persisted trades, then every engine
+//! trade updates the current bar. The ticker is therefore correct right after
+//! a deploy instead of starting from empty memory.
+//!
+//! The trade stream is subscribed to before the rebuild query runs and is
+//! only drained once the rebuild is done, so no trade falls between the two;
+//! trades already covered by the rebuild are skipped by execution sequence
+//! number.
+//!
+//! Next to the statistics, [`book_ticker`] summarises the top levels of the
+//! live book: best bid / ask, spread, a depth-weighted mid and the imbalance
+//! between the two sides.
+
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use parking_lot::RwLock;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+use crate::services::matching::{MatchingEngine, TradeEvent};
+
+/// Statistics window
+const WINDOW_HOURS: i64 = 24;
+
+/// One minute of trades of a symbol
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct MinuteBar {
+    pub start: DateTime<Utc>,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    pub volume: Decimal,
+    pub quote_volume: Decimal,
+    pub trade_count: i64,
+}
+
+impl MinuteBar {
+    fn new(start: DateTime<Utc>, price: Decimal, amount: Decimal) -> Self {
+        Self {
+            start,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: amount,
+            quote_volume: amount * price,
+            trade_count: 1,
+        }
+    }
+
+    fn add(&mut self, price: Decimal, amount: Decimal) {
+        self.high = self.high.max(price);
+        self.low = self.low.min(price);
+        self.close = price;
+        self.volume += amount;
+        self.quote_volume += amount * price;
+        self.trade_count += 1;
+    }
+}
+
+/// Minute bar of a symbol, as rebuilt from trades
+#[derive(sqlx::FromRow)]
+struct SymbolBarRow {
+    symbol: String,
+    #[sqlx(flatten)]
+    bar: MinuteBar,
+}
+
+/// Rolling 24h statistics of a symbol
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RollingTicker {
+    pub open_24h: Decimal,
+    pub high_24h: Decimal,
+    pub low_24h: Decimal,
+    pub last_price: Decimal,
+    pub price_change_24h: Decimal,
+    /// Change relative to the open, in percent
+    pub price_change_percent_24h: Decimal,
+    pub volume_24h: Decimal,
+    pub quote_volume_24h: Decimal,
+    pub trade_count_24h: i64,
+}
+
+/// Aggregate the bars of a symbol that are still inside the window
+pub fn rolling_ticker(bars: &VecDeque<MinuteBar>, since: DateTime<Utc>) -> Option<RollingTicker> {
+    let mut bars = bars.iter().filter(|bar| bar.start >= since);
+    let first = bars.next()?;
+    let mut ticker = RollingTicker {
+        open_24h: first.open,
+        high_24h: first.high,
+        low_24h: first.low,
+        last_price: first.close,
+        price_change_24h: Decimal::ZERO,
+        price_change_percent_24h: Decimal::ZERO,
+        volume_24h: first.volume,
+        quote_volume_24h: first.quote_volume,
+        trade_count_24h: first.trade_count,
+    };
+    for bar in bars {
+        ticker.high_24h = ticker.high_24h.max(bar.high);
+        ticker.low_24h = ticker.low_24h.min(bar.low);
+        ticker.last_price = bar.close;
+        ticker.volume_24h += bar.volume;
+        ticker.quote_volume_24h += bar.quote_volume;
+        ticker.trade_count_24h += bar.trade_count;
+    }
+    ticker.price_change_24h = ticker.last_price - ticker.open_24h;
+    if !ticker.open_24h.is_zero() {
+        ticker.price_change_percent_24h = (ticker.price_change_24h / ticker.open_24h * Decimal::ONE_HUNDRED).round_dp(2);
+    }
+    Some(ticker)
+}
+
+/// Top of the live book of a symbol
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BookTicker {
+    pub best_bid: Option<Decimal>,
+    pub best_ask: Option<Decimal>,
+    /// Best ask - best bid (None with an empty side)
+    pub spread: Option<Decimal>,
+    /// Size-weighted price of each side over the top levels, weighted by the
+    /// other side's depth: leans towards the thinner side, where the price gives
+    pub depth_weighted_mid: Option<Decimal>,
+    /// Summed size of the top levels of each side
+    pub bid_depth: Decimal,
+    pub ask_depth: Decimal,
+    /// (bid depth - ask depth) / (bid depth + ask depth), from -1 (asks only) to 1 (bids only)
+    pub imbalance: Option<Decimal>,
+    /// Levels per side covered
+    pub levels: usize,
+}
+
+/// Best price, summed size and size-weighted price of the top `levels` of a side
+fn side_depth(side: &[[String; 2]], levels: usize) -> (Option<Decimal>, Decimal, Option<Decimal>) {
+    let parsed: Vec<(Decimal, Decimal)> = side
+        .iter()
+        .take(levels)
+        .filter_map(|[price, size]| Some((price.parse().ok()?, size.parse().ok()?)))
+        .filter(|(_, size): &(Decimal, Decimal)| *size > Decimal::ZERO)
+        .collect();
+    let depth: Decimal = parsed.iter().map(|(_, size)| size).sum();
+    let notional: Decimal = parsed.iter().map(|(price, size)| price * size).sum();
+    let weighted = (!depth.is_zero()).then(|| notional / depth);
+    (parsed.first().map(|(price, _)| *price), depth, weighted)
+}
+
+/// Summarise the top `levels` of a book snapshot (`[price, size]` levels, best first)
+pub fn book_ticker(bids: &[[String; 2]], asks: &[[String; 2]], levels: usize) -> BookTicker {
+    let (best_bid, bid_depth, bid_price) = side_depth(bids, levels);
+    let (best_ask, ask_depth, ask_price) = side_depth(asks, levels);
+    let total = bid_depth + ask_depth;
+    let depth_weighted_mid = match (bid_price, ask_price) {
+        (Some(bid), Some(ask)) => Some(((bid * ask_depth + ask * bid_depth) / total).round_dp(6)),
+        _ => None,
+    };
+    BookTicker {
+        best_bid,
+        best_ask,
+        spread: best_bid.zip(best_ask).map(|(bid, ask)| ask - bid),
+        depth_weighted_mid,
+        bid_depth,
+        ask_depth,
+        imbalance: (!total.is_zero()).then(|| ((bid_depth - ask_depth) / total).round_dp(4)),
+        levels,
+    }
+}
+
+/// Rolling 24h ticker of every traded symbol
+#[derive(Default)]
+pub struct TickerService {
+    /// Minute bars per symbol, oldest first
+    bars: RwLock<HashMap<String, VecDeque<MinuteBar>>>,
+    /// Highest execution sequence covered by the startup rebuild
+    rebuilt_through: AtomicU64,
+}
+
+impl TickerService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rolling 24h statistics of a symbol (None without trades in the window)
+    pub fn ticker(&self, symbol: &str) -> Option<RollingTicker> {
+        let since = Utc::now() - Duration::hours(WINDOW_HOURS);
+        self.bars.read().get(symbol).and_then(|bars| rolling_ticker(bars, since))
+    }
+
+    /// Rebuild the minute bars of the last 24 hours from persisted trades;
+    /// returns the number of symbols loaded
+    pub async fn rebuild(&self, pool: &PgPool) -> Result<usize, sqlx::Error> {
+        let rows: Vec<SymbolBarRow> = sqlx::query_as(
+            r#"
+            SELECT market_id::text || ':' || outcome_id::text || ':' || share_type::text AS symbol,
+                   date_trunc('minute', created_at) AS start,
+                   (array_agg(price ORDER BY created_at, exec_id))[1] AS open,
+                   MAX(price) AS high,
+                   MIN(price) AS low,
+                   (array_agg(price ORDER BY created_at DESC, exec_id DESC))[1] AS close,
+                   SUM(amount) AS volume,
+                   SUM(amount * price) AS quote_volume,
+                   COUNT(*) AS trade_count
+            FROM trades
+            WHERE created_at >= NOW() - make_interval(hours => $1)
+            GROUP BY 1, 2
+            ORDER BY 1, 2
+            "#,
+        )
+        .bind(WINDOW_HOURS as i32)
+        .fetch_all(pool)
+        .await?;
+        let last_exec_id: Option<i64> = sqlx::query_scalar("SELECT MAX(exec_id) FROM trades").fetch_one(pool).await?;
+
+        let mut rebuilt: HashMap<String, VecDeque<MinuteBar>> = HashMap::new();
+        for row in rows {
+            rebuilt.entry(row.symbol).or_default().push_back(row.bar);
+        }
+        let count = rebuilt.len();
+
+        *self.bars.write() = rebuilt;
+        self.rebuilt_through.store(last_exec_id.unwrap_or(0).max(0) as u64, Ordering::SeqCst);
+        Ok(count)
+    }
+
+    /// Add an engine trade to the current minute bar
+    pub fn record(&self, trade: &TradeEvent) {
+        if trade.exec_id != 0 && trade.exec_id <= self.rebuilt_through.load(Ordering::SeqCst) {
+            return;
+        }
+        let at = DateTime::from_timestamp_millis(trade.timestamp).unwrap_or_else(Utc::now);
+        let start = at.duration_trunc(Duration::minutes(1)).unwrap_or(at);
+        let since = at - Duration::hours(WINDOW_HOURS);
+
+        let mut bars = self.bars.write();
+        let bars = bars.entry(trade.symbol.clone()).or_default();
+        match bars.back_mut() {
+            Some(bar) if bar.start == start => bar.add(trade.price, trade.amount),
+            _ => bars.push_back(MinuteBar::new(start, trade.price, trade.amount)),
+        }
+        while bars.front().is_some_and(|bar| bar.start < since) {
+            bars.pop_front();
+        }
+    }
+}
+
+/// Rebuild the ticker from persisted trades, then keep it updated from the
+/// engine trade stream
+pub fn spawn(ticker: Arc<TickerService>, engine: Arc<MatchingEngine>, pool: PgPool) {
+    // Subscribe first: trades persisted after the rebuild query are still received
+    let mut trades = engine.subscribe_trades();
+    tokio::spawn(async move {
+        match ticker.rebuild(&pool).await {
+            Ok(symbols) => tracing::info!("24h ticker rebuilt from trades ({} symbols)", symbols),
+            Err(e) => tracing::error!("Failed to rebuild 24h ticker from trades: {}", e),
+        }
+        loop {
+            match trades.recv().await {
+                Ok(trade) => ticker.record(&trade),
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    tracing::warn!("24h ticker lagged by {} trades", n);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+        tracing::warn!("24h ticker stopped");
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::matching::Side;
+    use rust_decimal_macros::dec;
+    use uuid::Uuid;
+
+    fn trade(price: Decimal, amount: Decimal, timestamp: i64, exec_id: u64) -> TradeEvent {
+        let mut trade = TradeEvent::new(
+            "m:o:yes".to_string(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            "0xmaker".to_string(),
+            "0xtaker".to_string(),
+            Side::Buy,
+            price,
+            amount,
+            Decimal::ZERO,
+            Decimal::ZERO,
+        );
+        trade.timestamp = timestamp;
+        trade.exec_id = exec_id;
+        trade
+    }
+
+    #[test]
+    fn test_rolling_ticker_over_bars() {
+        let now = Utc::now().duration_trunc(Duration::minutes(1)).unwrap();
+        let service = TickerService::new();
+        let minute = 60_000;
+        let t0 = now.timestamp_millis() - 10 * minute;
+        service.record(&trade(dec!(0.40), dec!(10), t0, 1));
+        service.record(&trade(dec!(0.55), dec!(5), t0 + 1_000, 2));
+        service.record(&trade(dec!(0.35), dec!(5), t0 + minute, 3));
+        service.record(&trade(dec!(0.50), dec!(20), t0 + 2 * minute, 4));
+
+        let ticker = service.ticker("m:o:yes").unwrap();
+        assert_eq!(ticker.open_24h, dec!(0.40));
+        assert_eq!(ticker.high_24h, dec!(0.55));
+        assert_eq!(ticker.low_24h, dec!(0.35));
+        assert_eq!(ticker.last_price, dec!(0.50));
+        assert_eq!(ticker.price_change_24h, dec!(0.10));
+        assert_eq!(ticker.price_change_percent_24h, dec!(25));
+        assert_eq!(ticker.volume_24h, dec!(40));
+        assert_eq!(ticker.trade_count_24h, 4);
+        assert!(service.ticker("m:o:no").is_none());
+    }
+
+    fn levels(levels: &[(&str, &str)]) -> Vec<[String; 2]> {
+        levels.iter().map(|(price, size)| [price.to_string(), size.to_string()]).collect()
+    }
+
+    #[test]
+    fn test_book_ticker() {
+        let bids = levels(&[("0.50", "100"), ("0.49", "100"), ("0.40", "1000")]);
+        let asks = levels(&[("0.52", "50"), ("0.54", "50")]);
+        let book = book_ticker(&bids, &asks, 2);
+        assert_eq!((book.best_bid, book.best_ask, book.spread), (Some(dec!(0.50)), Some(dec!(0.52)), Some(dec!(0.02))));
+        assert_eq!((book.bid_depth, book.ask_depth), (dec!(200), dec!(100)));
+        // Bids 0.495 and asks 0.53, leaning to the thinner ask side
+        assert_eq!(book.depth_weighted_mid, Some(dec!(0.518333)));
+        assert_eq!(book.imbalance, Some(dec!(0.3333)));
+
+        let book = book_ticker(&bids, &[], 2);
+        assert_eq!((book.best_ask, book.spread, book.depth_weighted_mid), (None, None, None));
+        assert_eq!(book.imbalance, Some(Decimal::ONE));
+        assert_eq!(book_ticker(&[], &[], 10).imbalance, None);
+    }
+
+    #[test]
+    fn test_window_excludes_old_bars_and_rebuilt_trades() {
+        let now = Utc::now();
+        let service = TickerService::new();
+        service.rebuilt_through.store(5, Ordering::SeqCst);
+
+        // Already covered by the rebuild
+        service.record(&trade(dec!(0.9), dec!(1), now.timestamp_millis(), 5));
+        assert!(service.ticker("m:o:yes").is_none());
+
+        let old = (now - Duration::hours(25)).timestamp_millis();
+        service.record(&trade(dec!(0.2), dec!(1), old, 6));
+        assert!(service.ticker("m:o:yes").is_none());
+
+        service.record(&trade(dec!(0.6), dec!(2), now.timestamp_millis(), 7));
+        let ticker = service.ticker("m:o:yes").unwrap();
+        assert_eq!((ticker.open_24h, ticker.volume_24h), (dec!(0.6), dec!(2)));
+    }
+}
@@ -0,0 +1,202 @@
+//! Market Data Relay
+//!
+//! Runs the public market data feeds on more than one API node: one engine
+//! node matches orders and N gateway nodes serve WebSocket clients.
+//!
+//! ```text
+//! engine node (market_data_relay=publish)
+//!   persisted trades (event outbox) ──► Redis channel:trades:{SYMBOL}
+//!   MatchingEngine book updates ──────► Redis channel:orderbook:{SYMBOL}
+//!                                                  │  PSUBSCRIBE
+//!                                                  ▼
+//! gateway node (market_data_relay=consume)
+//!   MatchingEngine::relay_trade / relay_orderbook ──► WebSocket handler
+//! ```
+//!
+//! A gateway's engine holds no orders: it starts as a standby (trading
+//! commands fail with `ENGINE_STANDBY`, so trading routes belong on the engine
+//! node) and only re-broadcasts what it receives. Relayed trades keep their
+//! execution ids and book updates their sequence numbers, so clients see the
+//! same stream on every node; the relayed levels also serve book snapshots.
+//! User channels need no relay: their events already reach every node as
+//! Postgres notifications ([`crate::services::user_events`]).
+//!
+//! Trades reach Redis through the event outbox ([`crate::services::outbox`])
+//! once persisted, so a Redis outage delays them rather than dropping them;
+//! gateways skip redelivered trades by their outbox sequence. Messages
+//! published while a gateway reconnects are still lost: WebSocket clients
+//! recover book updates from the resulting sequence gap with a resync.
+
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::StreamExt;
+use tokio::sync::broadcast;
+
+use crate::cache::keys::CacheKey;
+use crate::cache::pubsub::{Publisher, Subscriber};
+use crate::services::matching::{MatchingEngine, OrderbookUpdate, TradeEvent};
+use crate::services::outbox::{Delivery, OutboxMessage, SequenceTracker};
+
+/// Relay errors
+#[derive(Debug, thiserror::Error)]
+pub enum RelayError {
+    #[error("Invalid market_data_relay mode: {0} (expected off, publish or consume)")]
+    InvalidMode(String),
+}
+
+/// Part a node plays in the relay
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelayMode {
+    /// Single node: nothing is relayed
+    Off,
+    /// Engine node: publishes its book updates to Redis (trades go through the outbox)
+    Publish,
+    /// Gateway node: serves the trades and book updates published to Redis
+    Consume,
+}
+
+impl FromStr for RelayMode {
+    type Err = RelayError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "off" | "" => Ok(RelayMode::Off),
+            "publish" => Ok(RelayMode::Publish),
+            "consume" => Ok(RelayMode::Consume),
+            _ => Err(RelayError::InvalidMode(s.to_string())),
+        }
+    }
+}
+
+/// A message received on a relay channel
+#[derive(Debug)]
+pub enum RelayedMessage {
+    /// A trade with its outbox sequence
+    Trade { sequence: i64, trade: Box<TradeEvent> },
+    Orderbook(OrderbookUpdate),
+}
+
+/// Decode a relay channel message; None for other channels or bad payloads
+pub fn decode(channel: &str, payload: &str) -> Option<RelayedMessage> {
+    if channel.starts_with(&CacheKey::channel_trades("")) {
+        serde_json::from_str::<OutboxMessage<TradeEvent>>(payload)
+            .ok()
+            .map(|message| RelayedMessage::Trade {
+                sequence: message.sequence,
+                trade: Box::new(message.payload),
+            })
+    } else if channel.starts_with(&CacheKey::channel_orderbook("")) {
+        serde_json::from_str(payload).ok().map(RelayedMessage::Orderbook)
+    } else {
+        None
+    }
+}
+
+/// Publish the engine's book updates to Redis (engine node). Trades are
+/// published by the outbox relay job.
+pub fn spawn_publisher(engine: Arc<MatchingEngine>, publisher: Publisher) {
+    let mut books = engine.subscribe_orderbook();
+    tokio::spawn(async move {
+        loop {
+            match books.recv().await {
+                Ok(update) => {
+                    if let Err(e) = publisher.publish_orderbook(&update.symbol, &update).await {
+                        tracing::warn!("Failed to publish market data to Redis: {}", e);
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    tracing::warn!("Market data relay lagged by {} book updates", n);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+        tracing::warn!("Market data relay publisher stopped");
+    });
+}
+
+/// Feed the trades and book updates published to Redis into the local
+/// engine's broadcasts (gateway node), reconnecting when the connection drops
+pub fn spawn_consumer(engine: Arc<MatchingEngine>, subscriber: Subscriber) {
+    let patterns = [CacheKey::channel_trades("*"), CacheKey::channel_orderbook("*")];
+    let reconnect_delay = Duration::from_millis(subscriber.config().reconnect_delay_ms);
+    tokio::spawn(async move {
+        let mut sequences = SequenceTracker::new();
+        loop {
+            match subscriber.psubscribe(&patterns).await {
+                Ok(pubsub) => {
+                    tracing::info!("Market data relay consuming {:?}", patterns);
+                    let mut messages = pubsub.into_on_message();
+                    while let Some(msg) = messages.next().await {
+                        let Ok(payload) = msg.get_payload::<String>() else {
+                            continue;
+                        };
+                        let channel = msg.get_channel_name();
+                        match decode(channel, &payload) {
+                            Some(RelayedMessage::Trade { sequence, trade }) => {
+                                match sequences.observe(channel, sequence) {
+                                    Delivery::Duplicate => continue,
+                                    Delivery::Gap { missed } => {
+                                        tracing::warn!("Market data relay missed {} trades on {}", missed, channel)
+                                    }
+                                    Delivery::InOrder => {}
+                                }
+                                engine.relay_trade(*trade);
+                            }
+                            Some(RelayedMessage::Orderbook(update)) => engine.relay_orderbook(update),
+                            None => tracing::warn!("Undecodable market data on {}", channel),
+                        }
+                    }
+                    tracing::warn!("Market data relay connection closed, reconnecting");
+                }
+                Err(e) => tracing::warn!("Market data relay failed to subscribe: {}", e),
+            }
+            if !subscriber.config().auto_reconnect {
+                break;
+            }
+            tokio::time::sleep(reconnect_delay).await;
+        }
+        tracing::warn!("Market data relay consumer stopped");
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relay_mode() {
+        assert_eq!("".parse::<RelayMode>().unwrap(), RelayMode::Off);
+        assert_eq!("Publish".parse::<RelayMode>().unwrap(), RelayMode::Publish);
+        assert_eq!("consume".parse::<RelayMode>().unwrap(), RelayMode::Consume);
+        assert!("gateway".parse::<RelayMode>().is_err());
+    }
+
+    #[test]
+    fn test_decode_orderbook() {
+        let update = OrderbookUpdate {
+            symbol: "m:o:yes".to_string(),
+            bids: vec![["0.55".to_string(), "100".to_string()]],
+            asks: vec![],
+            sequence: 7,
+            prev_sequence: 6,
+            bid_changes: vec![["0.55".to_string(), "100".to_string()]],
+            ask_changes: vec![],
+            checksum: 42,
+            timestamp: 1,
+        };
+        let payload = serde_json::to_string(&update).unwrap();
+
+        let channel = CacheKey::channel_orderbook(&update.symbol);
+        match decode(&channel, &payload) {
+            Some(RelayedMessage::Orderbook(decoded)) => {
+                assert_eq!(decoded.sequence, 7);
+                assert_eq!(decoded.bids, update.bids);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(decode(&CacheKey::channel_ticker("m:o:yes"), &payload).is_none());
+        assert!(decode(&channel, "not json").is_none());
+    }
+}
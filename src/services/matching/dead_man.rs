@@ -0,0 +1,206 @@
+//! Dead Man's Switch
+//!
+//! Quoting bots arm a countdown, either with `POST /orders/cancel-all-after`
+//! or the WebSocket `cancelallafter` message. Re-arming restarts it, and so
+//! does every WebSocket ping from the account. If the countdown runs out
+//! (the bot lost connectivity or hung), the watchdog sweep cancels all of the
+//! account's resting orders and releases their frozen collateral. A timeout
+//! of 0 disarms the switch.
+//!
+//! Timers live in memory next to the books they protect; the sweep runs as
+//! the `dead_man_switch` job, so a timer fires within one sweep interval of
+//! its deadline.
+
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use serde::Serialize;
+use sqlx::PgPool;
+
+use super::engine::MatchingEngine;
+use super::types::{OrderEntry, Side};
+use crate::services::ledger::{BalanceChange, LedgerReason, LedgerService};
+use crate::services::user_events::{self, OrderEvent};
+
+/// Shortest countdown accepted (ms)
+pub const MIN_TIMEOUT_MS: u64 = 1_000;
+
+/// Longest countdown accepted (ms)
+pub const MAX_TIMEOUT_MS: u64 = 86_400_000;
+
+/// Dead man's switch errors
+#[derive(Debug, thiserror::Error)]
+pub enum DeadManError {
+    #[error("Timeout must be 0 (disarm) or between {MIN_TIMEOUT_MS} and {MAX_TIMEOUT_MS} ms, got {0}")]
+    InvalidTimeout(u64),
+}
+
+/// An armed countdown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DeadManTimer {
+    pub timeout_ms: u64,
+    /// Resting orders are cancelled after this time unless re-armed
+    pub trigger_at: DateTime<Utc>,
+}
+
+/// Per-account auto-cancel countdowns
+#[derive(Default)]
+pub struct DeadManSwitch {
+    timers: DashMap<String, DeadManTimer>,
+}
+
+impl DeadManSwitch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Arm (or restart) the countdown of `user_address`; 0 disarms it
+    pub fn arm(&self, user_address: &str, timeout_ms: u64) -> Result<Option<DeadManTimer>, DeadManError> {
+        self.arm_at(user_address, timeout_ms, Utc::now())
+    }
+
+    fn arm_at(
+        &self,
+        user_address: &str,
+        timeout_ms: u64,
+        now: DateTime<Utc>,
+    ) -> Result<Option<DeadManTimer>, DeadManError> {
+        let user_address = user_address.to_lowercase();
+        if timeout_ms == 0 {
+            self.timers.remove(&user_address);
+            return Ok(None);
+        }
+        if !(MIN_TIMEOUT_MS..=MAX_TIMEOUT_MS).contains(&timeout_ms) {
+            return Err(DeadManError::InvalidTimeout(timeout_ms));
+        }
+        let timer = DeadManTimer {
+            timeout_ms,
+            trigger_at: now + Duration::milliseconds(timeout_ms as i64),
+        };
+        self.timers.insert(user_address, timer);
+        Ok(Some(timer))
+    }
+
+    /// Restart an armed countdown with its timeout (WebSocket heartbeat)
+    pub fn heartbeat(&self, user_address: &str) -> Option<DeadManTimer> {
+        self.heartbeat_at(user_address, Utc::now())
+    }
+
+    fn heartbeat_at(&self, user_address: &str, now: DateTime<Utc>) -> Option<DeadManTimer> {
+        let mut timer = self.timers.get_mut(&user_address.to_lowercase())?;
+        timer.trigger_at = now + Duration::milliseconds(timer.timeout_ms as i64);
+        Some(*timer)
+    }
+
+    /// Armed countdown of `user_address`
+    pub fn status(&self, user_address: &str) -> Option<DeadManTimer> {
+        self.timers.get(&user_address.to_lowercase()).map(|timer| *timer)
+    }
+
+    /// Disarm and return the accounts whose countdown ran out by `now`
+    fn take_expired(&self, now: DateTime<Utc>) -> Vec<String> {
+        let expired: Vec<String> = self
+            .timers
+            .iter()
+            .filter(|timer| timer.trigger_at <= now)
+            .map(|timer| timer.key().clone())
+            .collect();
+        expired
+            .into_iter()
+            .filter(|user| self.timers.remove_if(user, |_, timer| timer.trigger_at <= now).is_some())
+            .collect()
+    }
+
+    /// Cancel the resting orders of every account whose countdown ran out;
+    /// returns the number of orders cancelled
+    pub async fn sweep(
+        &self,
+        pool: &PgPool,
+        engine: &MatchingEngine,
+        collateral_token: &str,
+    ) -> Result<usize, sqlx::Error> {
+        let expired = self.take_expired(Utc::now());
+        if expired.is_empty() {
+            return Ok(0);
+        }
+
+        let mut cancelled = 0;
+        for (symbol, order) in engine.resting_orders() {
+            if expired.contains(&order.user_address.to_lowercase())
+                && cancel_resting_order(pool, engine, &symbol, &order, collateral_token).await?
+            {
+                cancelled += 1;
+            }
+        }
+
+        for user in &expired {
+            tracing::warn!("Dead man's switch fired for {}", user);
+        }
+        Ok(cancelled)
+    }
+}
+
+/// Cancel a resting order in the engine and the database, releasing the
+/// collateral a buy order holds; false if it already left the book
+pub async fn cancel_resting_order(
+    pool: &PgPool,
+    engine: &MatchingEngine,
+    symbol: &str,
+    order: &OrderEntry,
+    collateral_token: &str,
+) -> Result<bool, sqlx::Error> {
+    match engine.cancel_order(symbol, order.id, &order.user_address) {
+        Ok(true) => {}
+        Ok(false) => return Ok(false),
+        Err(e) => {
+            tracing::error!("Failed to cancel resting order {}: {}", order.id, e);
+            return Ok(false);
+        }
+    }
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE orders SET status = 'cancelled'::order_status, updated_at = NOW() WHERE id = $1")
+        .bind(order.id)
+        .execute(&mut *tx)
+        .await?;
+    user_events::publish(&mut *tx, &OrderEvent::status(&order.user_address, order.id, "cancelled", None)).await?;
+    if order.side == Side::Buy {
+        let change = BalanceChange::unfreeze(
+            &order.user_address,
+            collateral_token,
+            order.remaining_amount * order.price,
+            LedgerReason::OrderUnfreeze,
+        )
+        .reference(order.id);
+        LedgerService::apply(&mut tx, &change).await?;
+    }
+    tx.commit().await?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fires_only_without_heartbeat() {
+        let switch = DeadManSwitch::new();
+        let start = Utc::now();
+        switch.arm_at("0xMaker", 5_000, start).unwrap();
+        switch.arm_at("0xother", 5_000, start).unwrap();
+        assert!(switch.arm_at("0xmaker", 10, start).is_err());
+
+        // A heartbeat restarts the maker's countdown
+        let later = start + Duration::seconds(4);
+        assert_eq!(switch.heartbeat_at("0xmaker", later).unwrap().trigger_at, later + Duration::seconds(5));
+        assert!(switch.heartbeat_at("0xunarmed", later).is_none());
+
+        let expired = switch.take_expired(start + Duration::seconds(6));
+        assert_eq!(expired, vec!["0xother".to_string()]);
+        assert!(switch.status("0xother").is_none());
+        assert!(switch.status("0xmaker").is_some());
+
+        // Disarmed timers never fire
+        assert!(switch.arm_at("0xmaker", 0, later).unwrap().is_none());
+        assert!(switch.take_expired(start + Duration::days(1)).is_empty());
+    }
+}
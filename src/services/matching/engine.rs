@@ -11,17 +11,43 @@
 //! - **Merge**: Two sells for complementary shares (Yes sell + No sell → collateral)
 
 use super::history::HistoryManager;
-use super::orderbook::Orderbook;
+use super::journal::{EngineCommand, EngineJournal, JournalEntry, JournalError};
+use super::lane::{CommandLane, LaneGate, LaneGuard};
+use super::orderbook::{book_checksum, group_levels, Orderbook, CHECKSUM_DEPTH};
+use super::replication::ReplicationLog;
+use super::snapshot;
 use super::types::*;
 use crate::metrics;
 use crate::models::market::ShareType;
+use crate::services::market::calendar::{SessionState, TradingCalendar};
+use crate::services::market::rules::{FeeTier, MarketRules};
 use dashmap::DashMap;
 use rust_decimal::Decimal;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::broadcast;
-use tracing::{debug, info, warn};
+use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
+/// Depth of the book published to orderbook subscribers, all of it checksummed
+const BOOK_DEPTH: usize = CHECKSUM_DEPTH;
+
+/// Outcome a symbol belongs to (`market_id:outcome_id`); both share types
+/// of an outcome are matched together
+fn outcome_key(symbol: &str) -> &str {
+    symbol.rsplit_once(':').map_or(symbol, |(outcome, _)| outcome)
+}
+
+/// Last published book state for a symbol (used to compute L2 deltas)
+#[derive(Debug, Default)]
+struct BookState {
+    sequence: u64,
+    bids: Vec<[String; 2]>,
+    asks: Vec<[String; 2]>,
+}
+
 /// The main matching engine
 pub struct MatchingEngine {
     /// Map of symbol to orderbook (concurrent access)
@@ -41,6 +67,46 @@ pub struct MatchingEngine {
 
     /// Supported symbols
     symbols: Vec<String>,
+
+    /// Write-ahead journal (None = journaling disabled)
+    journal: Option<Arc<EngineJournal>>,
+
+    /// Sequence number of the last applied command
+    sequence: AtomicU64,
+
+    /// Recent sequenced commands served to warm standbys
+    replication: Arc<ReplicationLog>,
+
+    /// Warm standby: follows a primary's commands and accepts none of its own
+    standby: AtomicBool,
+
+    /// Per-symbol published book state (sequence + last levels)
+    book_states: DashMap<String, BookState>,
+
+    /// Per-market command admission gates (cancels ahead of placements)
+    lanes: DashMap<String, Arc<LaneGate>>,
+
+    /// Sequence of the last command applied per outcome (lane gate key)
+    applied: DashMap<String, u64>,
+
+    /// Trading calendars by market ID (markets without one trade 24/7)
+    calendars: DashMap<Uuid, Arc<TradingCalendar>>,
+
+    /// Published mark prices by symbol
+    mark_prices: DashMap<String, Decimal>,
+
+    /// Trading rules by market ID (markets without rules use the defaults)
+    rules: DashMap<Uuid, Arc<MarketRules>>,
+
+    /// Fee schedules by tier name
+    fee_tiers: DashMap<String, FeeConfig>,
+
+    /// Execution id of the last published trade; held while a trade is
+    /// numbered and sent so ids reach subscribers in order
+    exec_sequence: parking_lot::Mutex<u64>,
+
+    /// Orders being submitted by a liquidation or ADL, flagged on their trades
+    forced_orders: DashMap<Uuid, ForcedTrade>,
 }
 
 impl MatchingEngine {
@@ -71,6 +137,19 @@ impl MatchingEngine {
             history: Arc::new(HistoryManager::new()),
             fee_config: FeeConfig::default(),
             symbols,
+            journal: None,
+            sequence: AtomicU64::new(0),
+            replication: Arc::new(ReplicationLog::new(ReplicationLog::DEFAULT_CAPACITY)),
+            standby: AtomicBool::new(false),
+            book_states: DashMap::new(),
+            lanes: DashMap::new(),
+            applied: DashMap::new(),
+            calendars: DashMap::new(),
+            mark_prices: DashMap::new(),
+            rules: DashMap::new(),
+            fee_tiers: DashMap::new(),
+            exec_sequence: parking_lot::Mutex::new(0),
+            forced_orders: DashMap::new(),
         }
     }
 
@@ -80,6 +159,139 @@ impl MatchingEngine {
         self
     }
 
+    /// Attach a write-ahead journal; every submit/cancel is appended before it is applied
+    pub fn with_journal(mut self, journal: Arc<EngineJournal>) -> Self {
+        self.sequence = AtomicU64::new(journal.last_sequence());
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Keep up to `capacity` recent commands for standbys to catch up from
+    pub fn with_replication_backlog(mut self, capacity: usize) -> Self {
+        self.replication = Arc::new(ReplicationLog::new(capacity));
+        self
+    }
+
+    /// Sequence number of the last applied command
+    pub fn last_sequence(&self) -> u64 {
+        self.sequence.load(Ordering::SeqCst)
+    }
+
+    /// Whether commands are written to a journal before they are applied
+    pub fn journal_enabled(&self) -> bool {
+        self.journal.is_some()
+    }
+
+    /// Path of the write-ahead journal (None when journaling is disabled)
+    pub fn journal_path(&self) -> Option<&Path> {
+        self.journal.as_ref().map(|journal| journal.path())
+    }
+
+    /// Recent sequenced commands, for standbys to follow
+    pub fn replication_log(&self) -> &Arc<ReplicationLog> {
+        &self.replication
+    }
+
+    /// Start serving standbys from the current sequence; call once recovery
+    /// is done, so commands re-submitted while recovering are not served
+    pub fn resume_replication(&self) {
+        self.replication.reset(self.last_sequence());
+    }
+
+    /// Whether the engine is a warm standby
+    pub fn is_standby(&self) -> bool {
+        self.standby.load(Ordering::SeqCst)
+    }
+
+    /// Make the engine a warm standby (or promote it back)
+    ///
+    /// A standby refuses new commands and does not publish the trades its
+    /// replicated commands produce: the primary already persisted them.
+    pub fn set_standby(&self, standby: bool) {
+        self.standby.store(standby, Ordering::SeqCst);
+    }
+
+    /// Apply a command replicated from the primary
+    ///
+    /// The entry must directly follow the last applied one. It is journaled
+    /// under the primary's sequence (when journaling is enabled), applied, and
+    /// kept for standbys chained behind this one.
+    pub fn apply_replicated(&self, entry: &JournalEntry) -> Result<(), JournalError> {
+        let expected = self.last_sequence() + 1;
+        if entry.sequence != expected {
+            return Err(JournalError::SequenceGap {
+                expected,
+                found: entry.sequence,
+            });
+        }
+        if let Some(journal) = &self.journal {
+            journal.append_entry(entry)?;
+        }
+        // Failures are applied faithfully: the primary failed them too
+        if let Err(e) = self.apply_entry(entry) {
+            debug!("Replicated entry {} applied with error: {}", entry.sequence, e);
+        }
+        self.replication.push(entry.clone());
+        Ok(())
+    }
+
+    /// fsync the journal, returning the last sequence now on disk
+    /// (None when journaling is disabled)
+    pub fn sync_journal(&self) -> Result<Option<u64>, MatchingError> {
+        let Some(journal) = &self.journal else {
+            return Ok(None);
+        };
+        journal.sync().map(Some).map_err(|e| {
+            error!("Failed to sync engine journal: {}", e);
+            MatchingError::InternalError(format!("Journal sync failed: {}", e))
+        })
+    }
+
+    /// Wait for the market's gate on `lane`
+    ///
+    /// Both share types of an outcome share one gate, since mint/merge matching
+    /// touches the complement book.
+    fn enter_lane(&self, symbol: &str, lane: CommandLane) -> LaneGuard {
+        let gate = self
+            .lanes
+            .entry(outcome_key(symbol).to_string())
+            .or_insert_with(|| Arc::new(LaneGate::new()))
+            .clone();
+
+        let started = Instant::now();
+        let guard = gate.enter(lane);
+        metrics::record_lane_wait(lane.as_str(), started.elapsed().as_secs_f64());
+        if guard.overtook() {
+            metrics::record_cancel_overtake();
+        }
+        guard
+    }
+
+    /// Record a command in the journal (if enabled) and advance the sequence
+    fn journal_command(&self, command: EngineCommand) -> Result<u64, MatchingError> {
+        if self.is_standby() {
+            return Err(MatchingError::Standby);
+        }
+        let entry = match &self.journal {
+            Some(journal) => {
+                let entry = journal.record(command).map_err(|e| {
+                    error!("Failed to append to engine journal: {}", e);
+                    MatchingError::InternalError(format!("Journal write failed: {}", e))
+                })?;
+                self.sequence.store(entry.sequence, Ordering::SeqCst);
+                entry
+            }
+            None => JournalEntry {
+                sequence: self.sequence.fetch_add(1, Ordering::SeqCst) + 1,
+                timestamp: chrono::Utc::now().timestamp_millis(),
+                command,
+            },
+        };
+        let sequence = entry.sequence;
+        self.replication.push(entry);
+        Ok(sequence)
+    }
+
     /// Get supported symbols
     pub fn symbols(&self) -> &[String] {
         &self.symbols
@@ -110,24 +322,117 @@ impl MatchingEngine {
     }
 
     /// Broadcast orderbook update for a symbol
+    ///
+    /// The book state entry is locked before snapshotting and held until the
+    /// update is sent, so updates for a symbol are published in sequence order
+    /// and each delta is computed against the previously published levels.
     fn broadcast_orderbook_update(&self, symbol: &str) {
         if let Some(orderbook) = self.orderbooks.get(symbol) {
-            let snapshot = orderbook.snapshot(20); // Top 20 levels
+            let mut state = self.book_states.entry(symbol.to_string()).or_default();
+            let snapshot = orderbook.snapshot(BOOK_DEPTH);
+
+            let bid_changes = diff_levels(&state.bids, &snapshot.bids);
+            let ask_changes = diff_levels(&state.asks, &snapshot.asks);
+            if bid_changes.is_empty() && ask_changes.is_empty() && state.sequence > 0 {
+                return;
+            }
+
+            let prev_sequence = state.sequence;
+            state.sequence += 1;
+            state.bids = snapshot.bids.clone();
+            state.asks = snapshot.asks.clone();
+
             let update = OrderbookUpdate {
                 symbol: symbol.to_string(),
+                checksum: book_checksum(&snapshot.bids, &snapshot.asks),
                 bids: snapshot.bids,
                 asks: snapshot.asks,
+                sequence: state.sequence,
+                prev_sequence,
+                bid_changes,
+                ask_changes,
                 timestamp: chrono::Utc::now().timestamp_millis(),
             };
             let _ = self.orderbook_sender.send(update);
         }
     }
 
+    /// Get the last published book for a symbol together with its sequence
+    ///
+    /// Clients apply deltas with `sequence > snapshot sequence` on top of it.
+    pub fn get_book_snapshot(&self, symbol: &str) -> Result<(u64, OrderbookSnapshot), MatchingError> {
+        let Some(orderbook) = self.orderbooks.get(symbol) else {
+            let sequence = self.book_states.get(symbol).map(|state| state.sequence);
+            return self
+                .relayed_snapshot(symbol, BOOK_DEPTH)
+                .zip(sequence)
+                .map(|(snapshot, sequence)| (sequence, snapshot))
+                .ok_or_else(|| MatchingError::SymbolNotFound(symbol.to_string()));
+        };
+
+        match self.book_states.get(symbol) {
+            Some(state) => {
+                let mut snapshot = orderbook.snapshot(0);
+                snapshot.bids = state.bids.clone();
+                snapshot.asks = state.asks.clone();
+                Ok((state.sequence, snapshot))
+            }
+            None => Ok((0, orderbook.snapshot(BOOK_DEPTH))),
+        }
+    }
+
+    /// Publish a trade relayed from the engine node (gateway nodes), keeping
+    /// its execution id; returns the number of subscribers it reached
+    pub fn relay_trade(&self, event: TradeEvent) -> usize {
+        self.trade_sender.send(event).unwrap_or(0)
+    }
+
+    /// Publish an orderbook update relayed from the engine node (gateway
+    /// nodes). Its levels become the symbol's published book, served to
+    /// snapshots in place of a local orderbook. The checksum is recomputed, as
+    /// engine nodes predating it relay none.
+    pub fn relay_orderbook(&self, mut update: OrderbookUpdate) {
+        update.checksum = book_checksum(&update.bids, &update.asks);
+        let mut state = self.book_states.entry(update.symbol.clone()).or_default();
+        state.sequence = update.sequence;
+        state.bids = update.bids.clone();
+        state.asks = update.asks.clone();
+        let _ = self.orderbook_sender.send(update);
+    }
+
+    /// Last relayed book of a symbol without a local orderbook
+    fn relayed_snapshot(&self, symbol: &str, depth: usize) -> Option<OrderbookSnapshot> {
+        let state = self.book_states.get(symbol)?;
+        let levels = |levels: &[[String; 2]]| levels.iter().take(depth).cloned().collect();
+        Some(OrderbookSnapshot {
+            symbol: symbol.to_string(),
+            bids: levels(&state.bids),
+            asks: levels(&state.asks),
+            last_price: None,
+            timestamp: chrono::Utc::now().timestamp_millis(),
+        })
+    }
+
     /// Get history manager
     pub fn history(&self) -> Arc<HistoryManager> {
         Arc::clone(&self.history)
     }
 
+    /// Symbols with an orderbook
+    pub fn orderbook_symbols(&self) -> Vec<String> {
+        self.orderbooks.iter().map(|e| e.key().clone()).collect()
+    }
+
+    /// Publish a symbol's mark price
+    pub fn set_mark_price(&self, symbol: &str, price: Decimal) {
+        self.mark_prices.insert(symbol.to_string(), price);
+    }
+
+    /// Last published mark price for a symbol
+    pub fn mark_price(&self, symbol: &str) -> Option<Decimal> {
+        self.mark_prices.get(symbol).map(|p| *p)
+    }
+
     /// Get orderbook for a symbol
     pub fn get_orderbook_ref(&self, symbol: &str) -> Option<Arc<Orderbook>> {
         self.orderbooks.get(symbol).map(|ob| Arc::clone(ob.value()))
@@ -143,7 +448,7 @@ impl MatchingEngine {
     }
 
     /// Get the complement market key (Yes ↔ No)
-    fn get_complement_market_key(market_key: &str) -> Option<String> {
+    pub(crate) fn get_complement_market_key(market_key: &str) -> Option<String> {
         let (market_id, outcome_id, share_type) = Self::parse_market_key(market_key)?;
         let complement_type = share_type.complement();
         Some(format!("{}:{}:{}", market_id, outcome_id, complement_type))
@@ -182,6 +487,7 @@ impl MatchingEngine {
         let mut trades = Vec::new();
         let complement_price = Decimal::ONE - taker_price;
         let now = chrono::Utc::now().timestamp_millis();
+        let fee_config = self.fee_config_for(taker_market_key);
 
         // Parse market_key for trade records
         let (market_id, outcome_id, _) = Self::parse_market_key(taker_market_key)
@@ -204,8 +510,8 @@ impl MatchingEngine {
             let trade_amount = remaining_amount.min(maker_order.remaining_amount);
 
             // Calculate fees
-            let taker_fee = self.fee_config.calculate_taker_fee(taker_price, trade_amount);
-            let maker_fee = self.fee_config.calculate_maker_fee(maker_order.price, trade_amount);
+            let taker_fee = fee_config.calculate_taker_fee(taker_price, trade_amount);
+            let maker_fee = fee_config.calculate_maker_fee(maker_order.price, trade_amount);
 
             let trade = TradeExecution {
                 trade_id: Uuid::new_v4(),
@@ -262,6 +568,7 @@ impl MatchingEngine {
         let mut trades = Vec::new();
         let complement_price = Decimal::ONE - taker_price;
         let now = chrono::Utc::now().timestamp_millis();
+        let fee_config = self.fee_config_for(taker_market_key);
 
         // Parse market_key for trade records
         let (market_id, outcome_id, _) = Self::parse_market_key(taker_market_key)
@@ -284,8 +591,8 @@ impl MatchingEngine {
             let trade_amount = remaining_amount.min(maker_order.remaining_amount);
 
             // Calculate fees
-            let taker_fee = self.fee_config.calculate_taker_fee(taker_price, trade_amount);
-            let maker_fee = self.fee_config.calculate_maker_fee(maker_order.price, trade_amount);
+            let taker_fee = fee_config.calculate_taker_fee(taker_price, trade_amount);
+            let maker_fee = fee_config.calculate_maker_fee(maker_order.price, trade_amount);
 
             let trade = TradeExecution {
                 trade_id: Uuid::new_v4(),
@@ -329,6 +636,10 @@ impl MatchingEngine {
     /// 1. **Normal matching**: Match against opposite side in same orderbook
     /// 2. **Mint matching** (for buy orders): Match against buy orders in complement orderbook
     /// 3. **Merge matching** (for sell orders): Match against sell orders in complement orderbook
+    ///
+    /// Valid orders are written to the journal (when enabled) before matching.
+    /// Orders for a market outside its trading session are rejected.
+    #[allow(clippy::too_many_arguments)]
     pub fn submit_order(
         &self,
         order_id: Uuid,
@@ -338,15 +649,44 @@ impl MatchingEngine {
         order_type: OrderType,
         amount: Decimal,
         price: Option<Decimal>,
-        _leverage: u32,
+        leverage: u32,
     ) -> Result<MatchResult, MatchingError> {
-        // Get or create orderbook for this symbol/market_key
-        // For prediction markets, orderbooks are created dynamically
-        let orderbook = self.orderbooks
-            .entry(symbol.to_string())
-            .or_insert_with(|| Arc::new(Orderbook::new(symbol.to_string())))
-            .clone();
+        self.check_session(symbol)?;
+        self.check_rules(symbol, price, amount, leverage)?;
+        self.restore_order(order_id, symbol, user_address, side, order_type, amount, price, leverage)
+    }
 
+    /// Submit a market order closing a position by force; its trades carry
+    /// `forced` so the tape can tell them from voluntary ones
+    pub fn submit_forced_order(
+        &self,
+        order_id: Uuid,
+        symbol: &str,
+        user_address: &str,
+        side: Side,
+        amount: Decimal,
+        forced: ForcedTrade,
+    ) -> Result<MatchResult, MatchingError> {
+        self.forced_orders.insert(order_id, forced);
+        let result = self.submit_order(order_id, symbol, user_address, side, OrderType::Market, amount, None, 1);
+        self.forced_orders.remove(&order_id);
+        result
+    }
+
+    /// Submit an order that was already accepted earlier (recovery,
+    /// reconciliation), bypassing the trading session check
+    #[allow(clippy::too_many_arguments)]
+    pub fn restore_order(
+        &self,
+        order_id: Uuid,
+        symbol: &str,
+        user_address: &str,
+        side: Side,
+        order_type: OrderType,
+        amount: Decimal,
+        price: Option<Decimal>,
+        leverage: u32,
+    ) -> Result<MatchResult, MatchingError> {
         // Validate inputs
         if amount <= Decimal::ZERO {
             return Err(MatchingError::InvalidAmount("Amount must be positive".to_string()));
@@ -356,6 +696,41 @@ impl MatchingEngine {
             return Err(MatchingError::InvalidPrice("Limit order requires price".to_string()));
         }
 
+        let _lane = self.enter_lane(symbol, CommandLane::Normal);
+
+        let sequence = self.journal_command(EngineCommand::Submit {
+            order_id,
+            symbol: symbol.to_string(),
+            user_address: user_address.to_string(),
+            side,
+            order_type,
+            amount,
+            price,
+            leverage,
+        })?;
+        self.applied.insert(outcome_key(symbol).to_string(), sequence);
+
+        self.apply_submit(order_id, symbol, user_address, side, order_type, amount, price)
+    }
+
+    /// Apply a validated submit command to the orderbooks
+    fn apply_submit(
+        &self,
+        order_id: Uuid,
+        symbol: &str,
+        user_address: &str,
+        side: Side,
+        order_type: OrderType,
+        amount: Decimal,
+        price: Option<Decimal>,
+    ) -> Result<MatchResult, MatchingError> {
+        // Get or create orderbook for this symbol/market_key
+        // For prediction markets, orderbooks are created dynamically
+        let orderbook = self.orderbooks
+            .entry(symbol.to_string())
+            .or_insert_with(|| Arc::new(Orderbook::new(symbol.to_string())))
+            .clone();
+
         // Record order submission metric
         let timer = metrics::Timer::new();
         let side_str = match side {
@@ -389,7 +764,7 @@ impl MatchingEngine {
             side,
             amount,
             price,
-            &self.fee_config,
+            &self.fee_config_for(symbol),
         );
 
         // ========================================================================
@@ -463,16 +838,20 @@ impl MatchingEngine {
         }
 
         let filled_amount = amount - remaining;
+        let forced = self.forced_orders.get(&order_id).map(|forced| *forced);
 
         // Broadcast trade events and record metrics
         for trade in &trades {
             // Use from_execution to preserve match_type (Normal/Mint/Merge)
-            let event = TradeEvent::from_execution(
+            let mut event = TradeEvent::from_execution(
                 trade,
                 symbol.to_string(),
                 user_address.to_string(),
                 side,
             );
+            if let Some(forced) = forced {
+                event = event.with_forced(forced);
+            }
 
             // Record trade metrics
             let match_type_str = match trade.match_type {
@@ -485,6 +864,7 @@ impl MatchingEngine {
             // Record trade volume (convert Decimal to f64 for metrics)
             let volume_usdc = (trade.price * trade.amount).to_string().parse::<f64>().unwrap_or(0.0);
             metrics::record_trade_executed(match_type_str, volume_usdc);
+            metrics::record_symbol_trade(symbol);
 
             // Record mint/merge specific metrics
             match trade.match_type {
@@ -499,19 +879,17 @@ impl MatchingEngine {
                 MatchType::Mint => "🔨 MINT",
                 MatchType::Merge => "🔄 MERGE",
             };
-            match self.trade_sender.send(event.clone()) {
-                Ok(n) => {
-                    info!(
-                        "📊 {} Trade broadcast to {} subscribers: symbol={}, price={}, amount={}, side={}",
-                        match_type_log, n, event.symbol, event.price, event.amount, event.side
-                    );
-                }
-                Err(e) => {
-                    warn!(
-                        "⚠️  Failed to broadcast {} trade (no subscribers?): {} - symbol={}, price={}",
-                        match_type_log, e, event.symbol, event.price
-                    );
-                }
+            let (event, subscribers) = self.publish_trade(event);
+            if subscribers > 0 {
+                info!(
+                    "📊 {} Trade broadcast to {} subscribers: symbol={}, price={}, amount={}, side={}",
+                    match_type_log, subscribers, event.symbol, event.price, event.amount, event.side
+                );
+            } else {
+                warn!(
+                    "⚠️  Failed to broadcast {} trade (no subscribers) - symbol={}, price={}",
+                    match_type_log, event.symbol, event.price
+                );
             }
 
             // Store in history
@@ -618,6 +996,27 @@ impl MatchingEngine {
 
     /// Cancel an order
     pub fn cancel_order(&self, symbol: &str, order_id: Uuid, user_address: &str) -> Result<bool, MatchingError> {
+        if !self.orderbooks.contains_key(symbol) {
+            return Err(MatchingError::SymbolNotFound(symbol.to_string()));
+        }
+
+        let started = Instant::now();
+        let _lane = self.enter_lane(symbol, CommandLane::Priority);
+
+        let sequence = self.journal_command(EngineCommand::Cancel {
+            symbol: symbol.to_string(),
+            order_id,
+            user_address: user_address.to_string(),
+        })?;
+        self.applied.insert(outcome_key(symbol).to_string(), sequence);
+
+        let result = self.apply_cancel(symbol, order_id, user_address);
+        metrics::record_order_cancel_duration(started.elapsed().as_secs_f64());
+        result
+    }
+
+    /// Apply a cancel command to the orderbooks
+    fn apply_cancel(&self, symbol: &str, order_id: Uuid, user_address: &str) -> Result<bool, MatchingError> {
         let orderbook = self.orderbooks.get(symbol)
             .ok_or_else(|| MatchingError::SymbolNotFound(symbol.to_string()))?;
 
@@ -645,16 +1044,430 @@ impl MatchingEngine {
         }
     }
 
+    /// Amend a resting limit order to a new price and total amount
+    ///
+    /// Reducing the amount at an unchanged price updates the order in place,
+    /// keeping its queue priority. Any other change cancels and replaces the
+    /// order (same id) within one command under the market's lane, so the
+    /// order is never missing from or doubled in the book; the replacement
+    /// matches like a new order and joins the back of its price level.
+    pub fn amend_order(
+        &self,
+        symbol: &str,
+        order_id: Uuid,
+        user_address: &str,
+        price: Decimal,
+        amount: Decimal,
+    ) -> Result<AmendResult, MatchingError> {
+        let orderbook = self.get_orderbook_ref(symbol)
+            .ok_or_else(|| MatchingError::SymbolNotFound(symbol.to_string()))?;
+        let current = orderbook.get_order(&order_id)
+            .ok_or_else(|| MatchingError::OrderNotFound(order_id.to_string()))?;
+        Self::validate_amend(&current, user_address, price, amount)?;
+
+        self.check_session(symbol)?;
+        self.check_rules(symbol, Some(price), amount, 1)?;
+
+        // A pure size reduction only takes risk off the book, like a cancel
+        let lane = if price == current.price && amount <= current.original_amount {
+            CommandLane::Priority
+        } else {
+            CommandLane::Normal
+        };
+        let _lane = self.enter_lane(symbol, lane);
+
+        let sequence = self.journal_command(EngineCommand::Amend {
+            symbol: symbol.to_string(),
+            order_id,
+            user_address: user_address.to_string(),
+            price,
+            amount,
+        })?;
+        self.applied.insert(outcome_key(symbol).to_string(), sequence);
+
+        self.apply_amend(symbol, order_id, user_address, price, amount)
+    }
+
+    /// Check an amendment against the order it applies to
+    fn validate_amend(current: &OrderEntry, user_address: &str, price: Decimal, amount: Decimal) -> Result<(), MatchingError> {
+        if !current.user_address.eq_ignore_ascii_case(user_address) {
+            return Err(MatchingError::OrderNotFound(current.id.to_string()));
+        }
+        if price <= Decimal::ZERO || price >= Decimal::ONE {
+            return Err(MatchingError::InvalidPrice(format!(
+                "Price {} must be between 0 and 1 (exclusive)",
+                price
+            )));
+        }
+        let filled = current.original_amount - current.remaining_amount;
+        if amount <= filled {
+            return Err(MatchingError::InvalidAmount(format!(
+                "Amount {} must exceed the filled amount {}",
+                amount, filled
+            )));
+        }
+        Ok(())
+    }
+
+    /// Apply an amend command to the orderbooks
+    fn apply_amend(
+        &self,
+        symbol: &str,
+        order_id: Uuid,
+        user_address: &str,
+        price: Decimal,
+        amount: Decimal,
+    ) -> Result<AmendResult, MatchingError> {
+        let orderbook = self.get_orderbook_ref(symbol)
+            .ok_or_else(|| MatchingError::SymbolNotFound(symbol.to_string()))?;
+        let current = orderbook.get_order(&order_id)
+            .ok_or_else(|| MatchingError::OrderNotFound(order_id.to_string()))?;
+        Self::validate_amend(&current, user_address, price, amount)?;
+
+        let filled = current.original_amount - current.remaining_amount;
+        let remaining = amount - filled;
+        let status = if filled > Decimal::ZERO { OrderStatus::PartiallyFilled } else { OrderStatus::Open };
+
+        // Size reduction at the same price: keep the queue position
+        if price == current.price && remaining <= current.remaining_amount {
+            orderbook.reduce_order(order_id, amount, remaining)
+                .ok_or_else(|| MatchingError::OrderNotFound(order_id.to_string()))?;
+            self.history.update_order(user_address, &order_id.to_string(), |order| {
+                order.original_amount = amount.to_string();
+                order.remaining_amount = remaining.to_string();
+            });
+            info!("Order amended in place: id={}, amount={}, remaining={}", order_id, amount, remaining);
+            self.broadcast_orderbook_update(symbol);
+
+            return Ok(AmendResult {
+                order_id,
+                priority_kept: true,
+                price,
+                amount,
+                filled_amount: filled,
+                remaining_amount: remaining,
+                status,
+                trades: Vec::new(),
+            });
+        }
+
+        // Cancel and replace under the same id
+        orderbook.cancel_order(order_id);
+        let result = self.apply_submit(order_id, symbol, user_address, current.side, OrderType::Limit, remaining, Some(price))?;
+
+        // The replacement rests with its total amount, so earlier fills still count
+        if result.remaining_amount > Decimal::ZERO {
+            orderbook.reduce_order(order_id, amount, result.remaining_amount);
+        }
+
+        let filled_amount = filled + result.filled_amount;
+        let status = match result.status {
+            OrderStatus::Open => status,
+            other => other,
+        };
+        self.history.update_order(user_address, &order_id.to_string(), |order| {
+            order.original_amount = amount.to_string();
+            order.filled_amount = filled_amount.to_string();
+            order.status = status.to_string();
+        });
+        info!("Order replaced: id={}, price={}, amount={}, filled={}", order_id, price, amount, filled_amount);
+
+        Ok(AmendResult {
+            order_id,
+            priority_kept: false,
+            price,
+            amount,
+            filled_amount,
+            remaining_amount: result.remaining_amount,
+            status,
+            trades: result.trades,
+        })
+    }
+
     // ========================================================================
     // Query Operations
     // ========================================================================
 
+    // ========================================================================
+    // Trading Sessions
+    // ========================================================================
+
+    /// Set (or with None, clear) a market's trading calendar
+    pub fn set_calendar(&self, market_id: Uuid, calendar: Option<TradingCalendar>) {
+        match calendar {
+            Some(calendar) => {
+                self.calendars.insert(market_id, Arc::new(calendar));
+            }
+            None => {
+                self.calendars.remove(&market_id);
+            }
+        }
+    }
+
+    /// Markets with a trading calendar
+    pub fn calendars(&self) -> Vec<(Uuid, Arc<TradingCalendar>)> {
+        self.calendars.iter().map(|e| (*e.key(), e.value().clone())).collect()
+    }
+
+    /// Session state of a market (None = trades 24/7)
+    pub fn session_state(&self, market_id: Uuid) -> Option<SessionState> {
+        self.calendars.get(&market_id).map(|c| c.state(chrono::Utc::now()))
+    }
+
+    /// Reject if the symbol's market is outside its trading session
+    pub fn check_session(&self, symbol: &str) -> Result<(), MatchingError> {
+        let Some(market_id) = symbol.split(':').next().and_then(|id| id.parse::<Uuid>().ok()) else {
+            return Ok(());
+        };
+        match self.session_state(market_id) {
+            Some(state) if !state.is_open => Err(MatchingError::MarketClosed(market_id.to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Load market trading calendars from the database
+    pub async fn load_calendars(&self, pool: &sqlx::PgPool) -> Result<usize, MatchingError> {
+        let rows: Vec<(Uuid, sqlx::types::Json<TradingCalendar>)> = sqlx::query_as(
+            "SELECT id, trading_calendar FROM markets WHERE trading_calendar IS NOT NULL",
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let count = rows.len();
+        for (market_id, calendar) in rows {
+            self.set_calendar(market_id, Some(calendar.0));
+        }
+        Ok(count)
+    }
+
+    // ========================================================================
+    // Market Rules
+    // ========================================================================
+
+    /// Set a market's trading rules
+    pub fn set_market_rules(&self, market_id: Uuid, rules: MarketRules) {
+        self.rules.insert(market_id, Arc::new(rules));
+    }
+
+    /// Trading rules of a market (defaults when none are set)
+    pub fn market_rules(&self, market_id: Uuid) -> Arc<MarketRules> {
+        self.rules.get(&market_id).map(|r| r.clone()).unwrap_or_default()
+    }
+
+    /// Set (or with None, remove) a fee tier
+    pub fn set_fee_tier(&self, name: &str, tier: Option<FeeConfig>) {
+        match tier {
+            Some(fee_config) => {
+                self.fee_tiers.insert(name.to_string(), fee_config);
+            }
+            None => {
+                self.fee_tiers.remove(name);
+            }
+        }
+    }
+
+    /// Fee schedule of a symbol: its market's tier, else the engine default
+    fn fee_config_for(&self, symbol: &str) -> FeeConfig {
+        Self::parse_market_key(symbol)
+            .and_then(|(market_id, _, _)| self.rules.get(&market_id)?.fee_tier.clone())
+            .and_then(|tier| self.fee_tiers.get(&tier).map(|f| f.clone()))
+            .unwrap_or_else(|| self.fee_config.clone())
+    }
+
+    /// Reject a new order that breaks its market's rules
+    pub fn check_rules(
+        &self,
+        symbol: &str,
+        price: Option<Decimal>,
+        amount: Decimal,
+        leverage: u32,
+    ) -> Result<(), MatchingError> {
+        let Some(market_id) = symbol.split(':').next().and_then(|id| id.parse::<Uuid>().ok()) else {
+            return Ok(());
+        };
+        match self.rules.get(&market_id) {
+            Some(rules) => Ok(rules.check_order(price, amount, leverage)?),
+            None => Ok(()),
+        }
+    }
+
+    /// Load fee tiers and market trading rules from the database, replacing
+    /// what the engine holds; returns the number of markets with rules
+    pub async fn load_market_rules(&self, pool: &sqlx::PgPool) -> Result<usize, MatchingError> {
+        let tiers: Vec<FeeTier> =
+            sqlx::query_as("SELECT name, base_fee_bps, max_fee_bps, maker_discount_pct FROM fee_tiers")
+                .fetch_all(pool)
+                .await?;
+        // Swap entries in place so orders never see an empty table mid-reload
+        self.fee_tiers.retain(|name, _| tiers.iter().any(|t| &t.name == name));
+        for tier in tiers {
+            self.fee_tiers.insert(tier.name.clone(), tier.fee_config());
+        }
+
+        type RulesRow = (Uuid, bool, Option<chrono::DateTime<chrono::Utc>>, i32, Option<String>, Option<Decimal>, Option<Decimal>);
+        let rows: Vec<RulesRow> = sqlx::query_as(
+            r#"
+            SELECT id, trading_paused, delisted_at, max_leverage, fee_tier, tick_size, lot_size
+            FROM markets
+            "#,
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let market_ids: std::collections::HashSet<Uuid> = rows.iter().map(|r| r.0).collect();
+        self.rules.retain(|id, _| market_ids.contains(id));
+        let count = rows.len();
+        for (market_id, paused, delisted_at, max_leverage, fee_tier, tick_size, lot_size) in rows {
+            self.set_market_rules(
+                market_id,
+                MarketRules {
+                    paused,
+                    delisted_at,
+                    max_leverage: max_leverage.max(1) as u32,
+                    fee_tier,
+                    tick_size,
+                    lot_size,
+                },
+            );
+        }
+        Ok(count)
+    }
+
+    /// Write the resting orders of every book to `path` as JSON; returns the
+    /// number of orders written
+    pub fn write_snapshot(&self, path: &Path) -> std::io::Result<usize> {
+        let snapshot = EngineSnapshot {
+            sequence: self.last_sequence(),
+            taken_at: chrono::Utc::now().timestamp_millis(),
+            orders: self.resting_orders(),
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Write then rename, so a crash never leaves a partial snapshot
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec(&snapshot)?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(snapshot.orders.len())
+    }
+
+    /// Snapshot the books of every outcome with commands applied after
+    /// sequence `after`
+    ///
+    /// Each outcome is captured under its lane gate, so its books hold every
+    /// command on the outcome up to the snapshot sequence and none after it.
+    pub fn snapshot_books(&self, after: u64) -> Vec<BookSnapshot> {
+        let outcomes: Vec<String> = self
+            .applied
+            .iter()
+            .filter(|entry| *entry.value() > after)
+            .map(|entry| entry.key().clone())
+            .collect();
+        let taken_at = chrono::Utc::now().timestamp_millis();
+
+        let mut snapshots = Vec::new();
+        for outcome in outcomes {
+            let symbols: Vec<String> = self
+                .orderbooks
+                .iter()
+                .filter(|entry| outcome_key(entry.key()) == outcome)
+                .map(|entry| entry.key().clone())
+                .collect();
+            let Some(first) = symbols.first() else {
+                continue;
+            };
+
+            let _lane = self.enter_lane(first, CommandLane::Priority);
+            let sequence = self.last_sequence();
+            // Books created by a command that ran before the gate was taken
+            let symbols = symbols
+                .iter()
+                .cloned()
+                .chain(Self::get_complement_market_key(first))
+                .collect::<std::collections::BTreeSet<_>>();
+            for symbol in symbols {
+                let Some(book) = self.get_orderbook_ref(&symbol) else {
+                    continue;
+                };
+                snapshots.push(BookSnapshot {
+                    symbol,
+                    sequence,
+                    taken_at,
+                    last_trade_price: book.last_trade_price(),
+                    orders: book.resting_orders(),
+                });
+            }
+        }
+        snapshots
+    }
+
+    /// Replace books with snapshots, keeping their queue order; returns the
+    /// number of orders restored
+    pub fn restore_books(&self, snapshots: &[BookSnapshot]) -> usize {
+        let mut restored = 0;
+        for snapshot in snapshots {
+            let book = Orderbook::new(snapshot.symbol.clone());
+            for order in &snapshot.orders {
+                match book.add_order(order.clone()) {
+                    Ok(()) => restored += 1,
+                    Err(e) => warn!("Snapshot order {} on {} not restored: {}", order.id, snapshot.symbol, e),
+                }
+            }
+            if let Some(price) = snapshot.last_trade_price {
+                book.set_last_trade_price(price);
+            }
+            self.orderbooks.insert(snapshot.symbol.clone(), Arc::new(book));
+
+            let mut applied = self.applied.entry(outcome_key(&snapshot.symbol).to_string()).or_default();
+            *applied = (*applied).max(snapshot.sequence);
+            self.sequence.fetch_max(snapshot.sequence, Ordering::SeqCst);
+        }
+        restored
+    }
+
+    /// Resting orders across all orderbooks, with their symbol
+    pub fn resting_orders(&self) -> Vec<(String, OrderEntry)> {
+        self.orderbooks
+            .iter()
+            .flat_map(|entry| {
+                let symbol = entry.key().clone();
+                entry
+                    .value()
+                    .resting_orders()
+                    .into_iter()
+                    .map(move |order| (symbol.clone(), order))
+            })
+            .collect()
+    }
+
     /// Get orderbook snapshot
     pub fn get_orderbook(&self, symbol: &str, depth: usize) -> Result<OrderbookSnapshot, MatchingError> {
-        let orderbook = self.orderbooks.get(symbol)
-            .ok_or_else(|| MatchingError::SymbolNotFound(symbol.to_string()))?;
+        match self.orderbooks.get(symbol) {
+            Some(orderbook) => Ok(orderbook.snapshot(depth)),
+            // Gateway nodes only hold the books relayed from the engine node
+            None => self
+                .relayed_snapshot(symbol, depth)
+                .ok_or_else(|| MatchingError::SymbolNotFound(symbol.to_string())),
+        }
+    }
 
-        Ok(orderbook.snapshot(depth))
+    /// Get orderbook snapshot with levels merged into price buckets of `group`
+    pub fn get_grouped_orderbook(
+        &self,
+        symbol: &str,
+        depth: usize,
+        group: Decimal,
+    ) -> Result<OrderbookSnapshot, MatchingError> {
+        if let Some(orderbook) = self.orderbooks.get(symbol) {
+            return Ok(orderbook.grouped_snapshot(depth, group));
+        }
+        // Relayed books only carry their published top levels
+        let mut snapshot = self
+            .relayed_snapshot(symbol, usize::MAX)
+            .ok_or_else(|| MatchingError::SymbolNotFound(symbol.to_string()))?;
+        snapshot.bids = group_levels(&snapshot.bids, Side::Buy, group, depth);
+        snapshot.asks = group_levels(&snapshot.asks, Side::Sell, group, depth);
+        Ok(snapshot)
     }
 
     /// Get best bid/ask
@@ -675,16 +1488,86 @@ impl MatchingEngine {
         self.history.get_orders(user_address, query)
     }
 
-    /// Broadcast a trade event (for internal/market maker use)
-    pub fn broadcast_trade(&self, event: TradeEvent) -> Result<usize, broadcast::error::SendError<TradeEvent>> {
+    /// Broadcast a trade event (for internal/market maker use); returns the
+    /// number of subscribers it reached
+    pub fn broadcast_trade(&self, event: TradeEvent) -> usize {
+        let (event, subscribers) = self.publish_trade(event);
         // Also store in history
         self.history.store_trade(TradeRecord::from(&event));
-        self.trade_sender.send(event)
+        subscribers
+    }
+
+    /// Assign the next execution id to a trade and broadcast it
+    fn publish_trade(&self, mut event: TradeEvent) -> (TradeEvent, usize) {
+        if self.is_standby() {
+            return (event, 0);
+        }
+        let mut exec_sequence = self.exec_sequence.lock();
+        *exec_sequence += 1;
+        event.exec_id = *exec_sequence;
+        let subscribers = self.trade_sender.send(event.clone()).unwrap_or(0);
+        (event, subscribers)
+    }
+
+    /// Execution id of the last published trade
+    pub fn last_exec_id(&self) -> u64 {
+        *self.exec_sequence.lock()
+    }
+
+    /// Continue execution ids after the highest one persisted, so ids stay
+    /// consecutive across restarts. Call after journal replay: replayed trades
+    /// were persisted before the restart and keep their original ids.
+    pub async fn load_exec_sequence(&self, pool: &sqlx::PgPool) -> Result<u64, MatchingError> {
+        let last: Option<i64> = sqlx::query_scalar("SELECT MAX(exec_id) FROM trades")
+            .fetch_one(pool)
+            .await?;
+        let last = last.unwrap_or(0).max(0) as u64;
+        *self.exec_sequence.lock() = last;
+        Ok(last)
+    }
+
+    /// Publish per-symbol metrics (open orders, resting depth, open interest)
+    ///
+    /// Open interest is the total outstanding shares per outcome/share type,
+    /// from the latest market statistics snapshot.
+    pub fn publish_symbol_metrics(&self, mut open_interest: std::collections::HashMap<String, Decimal>) {
+        let mut samples = self.symbol_metric_samples(&open_interest);
+        for sample in &samples {
+            open_interest.remove(&sample.symbol);
+        }
+        // Outcomes with holdings but no live orderbook
+        samples.extend(open_interest.into_iter().map(|(symbol, amount)| metrics::SymbolBookSample {
+            symbol,
+            open_interest: decimal_to_f64(amount),
+            ..Default::default()
+        }));
+
+        metrics::set_symbol_book_metrics(&samples);
+    }
+
+    /// Per-symbol book samples for metrics
+    fn symbol_metric_samples(
+        &self,
+        open_interest: &std::collections::HashMap<String, Decimal>,
+    ) -> Vec<metrics::SymbolBookSample> {
+        self.orderbooks
+            .iter()
+            .map(|entry| {
+                let ob = entry.value();
+                metrics::SymbolBookSample {
+                    symbol: entry.key().clone(),
+                    open_orders: ob.order_count(),
+                    bid_depth: decimal_to_f64(ob.bid_depth()),
+                    ask_depth: decimal_to_f64(ob.ask_depth()),
+                    open_interest: open_interest.get(entry.key()).copied().map(decimal_to_f64).unwrap_or(0.0),
+                }
+            })
+            .collect()
     }
 
     /// Recover open limit orders from database on startup
     /// This ensures orderbook state is preserved after restart
-    pub async fn recover_orders_from_db(&self, pool: &sqlx::PgPool) -> anyhow::Result<usize> {
+    pub async fn recover_orders_from_db(&self, pool: &sqlx::PgPool) -> Result<usize, MatchingError> {
         use sqlx::Row;
 
         info!("🔄 Starting order recovery from database...");
@@ -732,7 +1615,7 @@ impl MatchingEngine {
             }
 
             // Submit the order to matching engine (this will add it to orderbook)
-            match self.submit_order(
+            match self.restore_order(
                 order_id,
                 &symbol,
                 &user_address,
@@ -757,6 +1640,143 @@ impl MatchingEngine {
         Ok(recovered_count)
     }
 
+    /// Rebuild engine state at startup
+    ///
+    /// With a journal, books are restored from their latest snapshots and
+    /// only the journal entries after each snapshot are replayed, so restart
+    /// time is bounded by the snapshot interval rather than the journal
+    /// length; price levels and queue priority come back exactly. Snapshots
+    /// ahead of the journal (e.g. a lost unsynced tail) are discarded in
+    /// favor of a full replay. Without a journal, open limit orders are
+    /// recovered from the database.
+    pub async fn recover(&self, pool: &sqlx::PgPool, journal_path: Option<&Path>) -> Result<RecoveryReport, MatchingError> {
+        let Some(path) = journal_path else {
+            let orders = self.recover_orders_from_db(pool).await?;
+            if let Err(e) = snapshot::clear(pool).await {
+                warn!("Failed to clear stale orderbook snapshots: {}", e);
+            }
+            return Ok(RecoveryReport {
+                source: RecoverySource::Database,
+                books: 0,
+                orders,
+                replayed: 0,
+            });
+        };
+
+        let entries = EngineJournal::read_entries(path)?;
+        let journal_end = entries.last().map_or(0, |entry| entry.sequence);
+        let snapshots = match snapshot::load(pool).await {
+            Ok(snapshots) => snapshots,
+            Err(e) => {
+                warn!("Failed to load orderbook snapshots, replaying the full journal: {}", e);
+                Vec::new()
+            }
+        };
+
+        if snapshots.is_empty() || snapshots.iter().any(|s| s.sequence > journal_end) {
+            if !snapshots.is_empty() {
+                warn!("Orderbook snapshots are ahead of the journal (sequence {}); replaying the full journal", journal_end);
+                if let Err(e) = snapshot::clear(pool).await {
+                    warn!("Failed to clear stale orderbook snapshots: {}", e);
+                }
+            }
+            let replayed = self.replay_entries(entries, path)?;
+            return Ok(RecoveryReport {
+                source: RecoverySource::Journal,
+                books: 0,
+                orders: 0,
+                replayed,
+            });
+        }
+
+        let orders = self.restore_books(&snapshots);
+        info!("Restored {} orderbooks ({} orders) from snapshots", snapshots.len(), orders);
+        let replayed = self.replay_entries(entries, path)?;
+        Ok(RecoveryReport {
+            source: RecoverySource::Snapshot,
+            books: snapshots.len(),
+            orders,
+            replayed,
+        })
+    }
+
+    /// Rebuild orderbook state by replaying a journal file in sequence order
+    ///
+    /// Commands are applied without being re-journaled. Replay is deterministic
+    /// in the resulting book state (resting orders, remaining amounts, queue
+    /// priority); trade IDs and timestamps are regenerated. Should be called on
+    /// an empty engine before any trade subscribers are attached, otherwise
+    /// replayed fills would be persisted twice.
+    pub fn replay_journal(&self, path: &Path) -> Result<usize, JournalError> {
+        self.replay_entries(EngineJournal::read_entries(path)?, path)
+    }
+
+    /// Apply one journal entry without re-journaling it; returns the match
+    /// result of a submit or amend (None for a cancel)
+    pub fn apply_entry(&self, entry: &JournalEntry) -> Result<Option<MatchResult>, MatchingError> {
+        self.applied.insert(outcome_key(entry.command.symbol()).to_string(), entry.sequence);
+        self.sequence.fetch_max(entry.sequence, Ordering::SeqCst);
+
+        match &entry.command {
+            EngineCommand::Submit {
+                order_id,
+                symbol,
+                user_address,
+                side,
+                order_type,
+                amount,
+                price,
+                ..
+            } => self
+                .apply_submit(*order_id, symbol, user_address, *side, *order_type, *amount, *price)
+                .map(Some),
+            EngineCommand::Cancel {
+                symbol,
+                order_id,
+                user_address,
+            } => self.apply_cancel(symbol, *order_id, user_address).map(|_| None),
+            EngineCommand::Amend {
+                symbol,
+                order_id,
+                user_address,
+                price,
+                amount,
+            } => self
+                .apply_amend(symbol, *order_id, user_address, *price, *amount)
+                .map(|amend| Some(MatchResult::from(amend))),
+        }
+    }
+
+    /// Replay journal entries, skipping those of outcomes whose restored
+    /// snapshot already includes them
+    fn replay_entries(&self, entries: Vec<JournalEntry>, path: &Path) -> Result<usize, JournalError> {
+        let entries: Vec<JournalEntry> = entries
+            .into_iter()
+            .filter(|entry| {
+                self.applied
+                    .get(outcome_key(entry.command.symbol()))
+                    .is_none_or(|applied| entry.sequence > *applied)
+            })
+            .collect();
+        let total = entries.len();
+
+        info!("🔄 Replaying {} journal entries from {}", total, path.display());
+
+        for entry in &entries {
+            // Failures are replayed faithfully: they failed the first time too
+            if let Err(e) = self.apply_entry(entry) {
+                debug!("Journal entry {} replayed with error: {}", entry.sequence, e);
+            }
+        }
+
+        info!(
+            "✅ Journal replay complete: {} entries applied, sequence={}",
+            total,
+            self.last_sequence()
+        );
+        Ok(total)
+    }
+
     // ========================================================================
     // Statistics
     // ========================================================================
@@ -783,10 +1803,17 @@ impl MatchingEngine {
             total_ask_depth,
             total_trades_recorded: history_stats.total_trades,
             total_orders_recorded: history_stats.total_orders,
+            last_sequence: self.last_sequence(),
+            journal_enabled: self.journal_enabled(),
         }
     }
 }
 
+/// Convert a Decimal to f64 for metrics
+fn decimal_to_f64(value: Decimal) -> f64 {
+    value.to_string().parse::<f64>().unwrap_or(0.0)
+}
+
 impl Default for MatchingEngine {
     fn default() -> Self {
         Self::new()
@@ -802,6 +1829,10 @@ pub struct EngineStats {
     pub total_ask_depth: Decimal,
     pub total_trades_recorded: usize,
     pub total_orders_recorded: usize,
+    /// Sequence number of the last applied engine command
+    pub last_sequence: u64,
+    /// Whether the write-ahead journal is enabled
+    pub journal_enabled: bool,
 }
 
 #[cfg(test)]
@@ -968,6 +1999,46 @@ mod tests {
         assert_eq!(trades.total_count, 1);
     }
 
+    #[test]
+    fn test_trades_get_consecutive_exec_ids_across_symbols() {
+        let engine = MatchingEngine::new();
+        let mut trades = engine.subscribe_trades();
+        let market_key1 = create_market_key();
+        let market_key2 = create_market_key();
+
+        for market_key in [&market_key1, &market_key2] {
+            engine.submit_order(Uuid::new_v4(), market_key, "0x1", Side::Sell, OrderType::Limit, dec!(10.0), Some(dec!(0.60)), 1).unwrap();
+            engine.submit_order(Uuid::new_v4(), market_key, "0x2", Side::Buy, OrderType::Limit, dec!(10.0), Some(dec!(0.60)), 1).unwrap();
+        }
+        let block = TradeEvent::new(
+            market_key1.clone(), Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4(),
+            "0x3".to_string(), "0x4".to_string(), Side::Buy, dec!(0.5), dec!(1), dec!(0), dec!(0),
+        );
+        assert_eq!(engine.broadcast_trade(block), 1);
+
+        let ids: Vec<u64> = std::iter::from_fn(|| trades.try_recv().ok()).map(|t| t.exec_id).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert_eq!(engine.last_exec_id(), 3);
+        let history = engine.get_trades(&market_key1, &TradeHistoryQuery::default());
+        assert!(history.trades.iter().any(|t| t.exec_id == 3));
+    }
+
+    #[test]
+    fn test_forced_order_trades_are_flagged() {
+        let engine = MatchingEngine::new();
+        let mut trades = engine.subscribe_trades();
+        let market_key = create_market_key();
+
+        engine.submit_order(Uuid::new_v4(), &market_key, "0x1", Side::Buy, OrderType::Limit, dec!(20.0), Some(dec!(0.60)), 1).unwrap();
+        let forced = Uuid::new_v4();
+        engine.submit_forced_order(forced, &market_key, "0x2", Side::Sell, dec!(5.0), ForcedTrade::Liquidation).unwrap();
+        engine.submit_order(Uuid::new_v4(), &market_key, "0x3", Side::Sell, OrderType::Market, dec!(5.0), None, 1).unwrap();
+
+        let flags: Vec<_> = std::iter::from_fn(|| trades.try_recv().ok()).map(|t| t.forced).collect();
+        assert_eq!(flags, vec![Some(ForcedTrade::Liquidation), None]);
+        assert!(engine.forced_orders.get(&forced).is_none());
+    }
+
     #[test]
     fn test_order_history() {
         let engine = MatchingEngine::new();
@@ -1128,4 +2199,200 @@ mod tests {
         assert_eq!(order_b.filled_amount, dec!(0));
         assert!(order_b.trades.is_empty());
     }
+
+    #[test]
+    fn test_orderbook_updates_carry_sequenced_deltas() {
+        let engine = MatchingEngine::new();
+        let market_key = create_market_key();
+        let mut rx = engine.subscribe_orderbook();
+
+        engine.submit_order(Uuid::new_v4(), &market_key, "0x1", Side::Sell, OrderType::Limit, dec!(100.0), Some(dec!(0.60)), 1).unwrap();
+        let first = rx.try_recv().unwrap();
+        assert_eq!(first.prev_sequence, 0);
+        assert_eq!(first.sequence, 1);
+        assert_eq!(first.ask_changes, vec![["0.60".to_string(), "100.0".to_string()]]);
+
+        engine.submit_order(Uuid::new_v4(), &market_key, "0x2", Side::Buy, OrderType::Limit, dec!(100.0), Some(dec!(0.60)), 1).unwrap();
+        let mut last = first;
+        while let Ok(update) = rx.try_recv() {
+            if update.symbol == market_key {
+                assert_eq!(update.prev_sequence, last.sequence);
+                last = update;
+            }
+        }
+        assert_eq!(last.ask_changes, vec![["0.60".to_string(), "0".to_string()]]);
+        assert!(last.asks.is_empty());
+
+        let (sequence, snapshot) = engine.get_book_snapshot(&market_key).unwrap();
+        assert_eq!(sequence, last.sequence);
+        assert!(snapshot.asks.is_empty());
+    }
+
+    #[test]
+    fn test_amend_keeps_priority_only_when_reducing() {
+        let engine = MatchingEngine::new();
+        let market_key = create_market_key();
+        let queue = |engine: &MatchingEngine| -> Vec<Uuid> {
+            engine.get_orderbook_ref(&market_key).unwrap().get_asks_at_price(dec!(0.60)).iter().map(|o| o.id).collect()
+        };
+
+        let first = Uuid::new_v4();
+        let second = Uuid::new_v4();
+        engine.submit_order(first, &market_key, "0x1", Side::Sell, OrderType::Limit, dec!(100.0), Some(dec!(0.60)), 1).unwrap();
+        engine.submit_order(second, &market_key, "0x2", Side::Sell, OrderType::Limit, dec!(100.0), Some(dec!(0.60)), 1).unwrap();
+
+        // Only the owner can amend, and not below what has filled
+        assert!(engine.amend_order(&market_key, first, "0x2", dec!(0.60), dec!(50.0)).is_err());
+        engine.submit_order(Uuid::new_v4(), &market_key, "0x3", Side::Buy, OrderType::Limit, dec!(20.0), Some(dec!(0.60)), 1).unwrap();
+        assert!(engine.amend_order(&market_key, first, "0x1", dec!(0.60), dec!(20.0)).is_err());
+
+        // Reducing in place keeps the front of the queue
+        let reduced = engine.amend_order(&market_key, first, "0x1", dec!(0.60), dec!(50.0)).unwrap();
+        assert!(reduced.priority_kept);
+        assert_eq!(reduced.filled_amount, dec!(20.0));
+        assert_eq!(reduced.remaining_amount, dec!(30.0));
+        assert_eq!(queue(&engine), vec![first, second]);
+
+        // Growing goes to the back
+        let grown = engine.amend_order(&market_key, first, "0x1", dec!(0.60), dec!(80.0)).unwrap();
+        assert!(!grown.priority_kept);
+        assert_eq!(grown.remaining_amount, dec!(60.0));
+        assert_eq!(queue(&engine), vec![second, first]);
+
+        // A price change that crosses the book fills as a replacement
+        engine.submit_order(Uuid::new_v4(), &market_key, "0x4", Side::Buy, OrderType::Limit, dec!(10.0), Some(dec!(0.55)), 1).unwrap();
+        let crossed = engine.amend_order(&market_key, first, "0x1", dec!(0.55), dec!(80.0)).unwrap();
+        assert_eq!(crossed.trades.len(), 1);
+        assert_eq!(crossed.filled_amount, dec!(30.0));
+        assert_eq!(crossed.remaining_amount, dec!(50.0));
+        assert_eq!(queue(&engine), vec![second]);
+        let book = engine.get_orderbook_ref(&market_key).unwrap();
+        assert_eq!(book.get_order(&first).unwrap().price, dec!(0.55));
+    }
+
+    #[test]
+    fn test_journal_replay_rebuilds_orderbook() {
+        use super::super::journal::JournalConfig;
+
+        let config = JournalConfig {
+            enabled: true,
+            path: std::env::temp_dir().join(format!("engine-replay-{}.log", Uuid::new_v4())),
+            fsync: false,
+        };
+        let journal = Arc::new(EngineJournal::open(&config).unwrap());
+        let engine = MatchingEngine::new().with_journal(journal);
+        let market_key = create_market_key();
+
+        let resting = Uuid::new_v4();
+        engine.submit_order(resting, &market_key, "0x1", Side::Sell, OrderType::Limit, dec!(100.0), Some(dec!(0.60)), 1).unwrap();
+        engine.submit_order(Uuid::new_v4(), &market_key, "0x2", Side::Buy, OrderType::Limit, dec!(40.0), Some(dec!(0.60)), 1).unwrap();
+        let cancelled = Uuid::new_v4();
+        engine.submit_order(cancelled, &market_key, "0x3", Side::Buy, OrderType::Limit, dec!(10.0), Some(dec!(0.50)), 1).unwrap();
+        engine.cancel_order(&market_key, cancelled, "0x3").unwrap();
+
+        // Invalid orders are rejected before being journaled
+        assert!(engine.submit_order(Uuid::new_v4(), &market_key, "0x4", Side::Buy, OrderType::Limit, dec!(0), Some(dec!(0.5)), 1).is_err());
+        assert_eq!(engine.stats().last_sequence, 4);
+
+        let replayed = MatchingEngine::new();
+        assert_eq!(replayed.replay_journal(&config.path).unwrap(), 4);
+
+        let original = engine.get_orderbook(&market_key, 10).unwrap();
+        let rebuilt = replayed.get_orderbook(&market_key, 10).unwrap();
+        assert_eq!(original.bids, rebuilt.bids);
+        assert_eq!(original.asks, rebuilt.asks);
+        assert_eq!(rebuilt.asks, vec![["0.60".to_string(), "60.0".to_string()]]);
+        assert_eq!(replayed.last_sequence(), 4);
+
+        let snapshot_path = config.path.with_extension("snapshot.json");
+        assert_eq!(replayed.write_snapshot(&snapshot_path).unwrap(), 1);
+        let snapshot: EngineSnapshot = serde_json::from_slice(&std::fs::read(&snapshot_path).unwrap()).unwrap();
+        assert_eq!(snapshot.sequence, 4);
+        assert_eq!(snapshot.orders[0].1.id, resting);
+
+        let _ = std::fs::remove_file(&config.path);
+        let _ = std::fs::remove_file(&snapshot_path);
+    }
+
+    #[test]
+    fn test_snapshot_restore_replays_only_journal_tail() {
+        use super::super::journal::JournalConfig;
+
+        let config = JournalConfig {
+            enabled: true,
+            path: std::env::temp_dir().join(format!("engine-snapshot-{}.log", Uuid::new_v4())),
+            fsync: false,
+        };
+        let journal = Arc::new(EngineJournal::open(&config).unwrap());
+        let engine = MatchingEngine::new().with_journal(journal);
+        let market_key = create_market_key();
+        let other_key = create_market_key();
+
+        // Two orders queued at one level, plus a partially filled ask
+        let first = Uuid::new_v4();
+        let second = Uuid::new_v4();
+        engine.submit_order(first, &market_key, "0x1", Side::Buy, OrderType::Limit, dec!(10), Some(dec!(0.40)), 1).unwrap();
+        engine.submit_order(second, &market_key, "0x2", Side::Buy, OrderType::Limit, dec!(20), Some(dec!(0.40)), 1).unwrap();
+        let ask = Uuid::new_v4();
+        engine.submit_order(ask, &market_key, "0x3", Side::Sell, OrderType::Limit, dec!(50), Some(dec!(0.60)), 1).unwrap();
+        engine.submit_order(Uuid::new_v4(), &market_key, "0x4", Side::Buy, OrderType::Limit, dec!(5), Some(dec!(0.60)), 1).unwrap();
+        let snapshots = engine.snapshot_books(0);
+        assert!(snapshots.iter().all(|s| s.sequence == 4));
+        assert!(engine.snapshot_books(4).is_empty());
+
+        // Commands after the snapshot come from the journal
+        engine.cancel_order(&market_key, ask, "0x3").unwrap();
+        engine.submit_order(Uuid::new_v4(), &other_key, "0x5", Side::Sell, OrderType::Limit, dec!(7), Some(dec!(0.70)), 1).unwrap();
+
+        let restored = MatchingEngine::new();
+        assert_eq!(restored.restore_books(&snapshots), 3);
+        assert_eq!(restored.replay_journal(&config.path).unwrap(), 2);
+
+        for key in [&market_key, &other_key] {
+            let original = engine.get_orderbook(key, 10).unwrap();
+            let rebuilt = restored.get_orderbook(key, 10).unwrap();
+            assert_eq!(original.bids, rebuilt.bids);
+            assert_eq!(original.asks, rebuilt.asks);
+        }
+        // Queue priority within a level is preserved
+        let ids = |e: &MatchingEngine| {
+            e.get_orderbook_ref(&market_key).unwrap().resting_orders().into_iter().map(|o| o.id).collect::<Vec<_>>()
+        };
+        assert_eq!(ids(&restored), vec![first, second]);
+        assert_eq!(restored.get_orderbook_ref(&market_key).unwrap().last_trade_price(), Some(dec!(0.60)));
+        assert_eq!(restored.last_sequence(), 6);
+
+        let _ = std::fs::remove_file(&config.path);
+    }
+
+    #[test]
+    fn test_orders_rejected_outside_session() {
+        let engine = MatchingEngine::new();
+        let market_key = create_market_key();
+        let market_id: Uuid = market_key.split(':').next().unwrap().parse().unwrap();
+
+        // Round-the-clock sessions with yesterday through tomorrow as holidays
+        let today = chrono::Utc::now().date_naive();
+        let calendar: TradingCalendar = serde_json::from_value(serde_json::json!({
+            "sessions": [{"days": ["mon", "tue", "wed", "thu", "fri", "sat", "sun"], "open": "00:00", "close": "00:00"}],
+            "holidays": [today.pred_opt(), today, today.succ_opt()]
+        }))
+        .unwrap();
+        engine.set_calendar(market_id, Some(calendar));
+
+        let result = engine.submit_order(
+            Uuid::new_v4(), &market_key, "0x1234", Side::Buy, OrderType::Limit, dec!(10), Some(dec!(0.5)), 1,
+        );
+        assert!(matches!(result, Err(MatchingError::MarketClosed(_))));
+        assert!(engine.session_state(market_id).unwrap().next_open.is_some());
+
+        // Recovery bypasses the session check
+        let restored = engine.restore_order(
+            Uuid::new_v4(), &market_key, "0x1234", Side::Buy, OrderType::Limit, dec!(10), Some(dec!(0.5)), 1,
+        );
+        assert!(restored.is_ok());
+
+        engine.set_calendar(market_id, None);
+        assert!(engine.check_session(&market_key).is_ok());
+    }
 }
@@ -113,6 +113,69 @@ impl HistoryManager {
         trades
     }
 
+    /// A page of the trades of `symbols`, newest first, next to `cursor`.
+    ///
+    /// Memory holds each symbol's latest trades only, so None means it can't
+    /// answer for sure: the cursor isn't held, or the page reaches back past
+    /// the oldest trade every symbol still holds (read those from the database).
+    pub fn trade_page(
+        &self,
+        symbols: &[String],
+        cursor: &TradeCursor,
+        side: Option<&str>,
+        limit: usize,
+    ) -> Option<TradePage> {
+        // Trades older than the newest of the symbols' oldest held trades may be missing
+        let mut horizon: Option<TradeKey> = None;
+        let mut trades: Vec<TradeRecord> = Vec::new();
+        for symbol in symbols {
+            if let Some(entry) = self.trade_history.get(symbol) {
+                if let Some(oldest) = entry.back() {
+                    horizon = horizon.max(Some(TradeKey::of(oldest)));
+                }
+                trades.extend(entry.iter().cloned());
+            }
+        }
+        trades.sort_by_cached_key(|trade| std::cmp::Reverse(TradeKey::of(trade)));
+
+        let matches_side = |trade: &TradeRecord| side.is_none_or(|side| trade.side.eq_ignore_ascii_case(side));
+        let position = |trade_id: &str| trades.iter().position(|trade| trade.trade_id == trade_id);
+        match cursor {
+            TradeCursor::Latest | TradeCursor::Before(_) => {
+                let start = match cursor {
+                    TradeCursor::Before(trade_id) => position(trade_id)? + 1,
+                    _ => 0,
+                };
+                let mut page: Vec<TradeRecord> = trades[start..]
+                    .iter()
+                    .take_while(|trade| horizon.as_ref().is_some_and(|horizon| TradeKey::of(trade) >= *horizon))
+                    .filter(|trade| matches_side(trade))
+                    .take(limit + 1)
+                    .cloned()
+                    .collect();
+                // Without one trade past the page, older ones may only be in the database
+                if page.len() <= limit {
+                    return None;
+                }
+                page.truncate(limit);
+                Some(TradePage { trades: page, has_more: true })
+            }
+            TradeCursor::After(trade_id) => {
+                // Everything newer than a held trade is held
+                let newer: Vec<&TradeRecord> = trades[..position(trade_id)?]
+                    .iter()
+                    .filter(|trade| matches_side(trade))
+                    .collect();
+                let has_more = newer.len() > limit;
+                let page = newer[newer.len().saturating_sub(limit)..]
+                    .iter()
+                    .map(|trade| (*trade).clone())
+                    .collect();
+                Some(TradePage { trades: page, has_more })
+            }
+        }
+    }
+
     /// Get recent trades across all symbols
     pub fn get_recent_trades(&self, limit: usize) -> Vec<TradeRecord> {
         let mut all_trades: Vec<TradeRecord> = Vec::new();
@@ -262,6 +325,35 @@ impl Default for HistoryManager {
     }
 }
 
+/// Where a page of trades starts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TradeCursor {
+    /// The most recent trades
+    Latest,
+    /// Trades older than this trade id
+    Before(String),
+    /// Trades newer than this trade id
+    After(String),
+}
+
+/// A page of trades, newest first
+#[derive(Debug, Clone)]
+pub struct TradePage {
+    pub trades: Vec<TradeRecord>,
+    /// More trades lie beyond the page (older for `Latest` / `Before`, newer for `After`)
+    pub has_more: bool,
+}
+
+/// Trade order: time, then execution id, then trade id (as the trades table sorts)
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct TradeKey(i64, u64, String);
+
+impl TradeKey {
+    fn of(trade: &TradeRecord) -> Self {
+        TradeKey(trade.timestamp, trade.exec_id, trade.trade_id.clone())
+    }
+}
+
 /// History statistics
 #[derive(Debug, Clone)]
 pub struct HistoryStats {
@@ -305,6 +397,9 @@ mod tests {
             maker_fee: "0.01".to_string(),
             taker_fee: "0.02".to_string(),
             timestamp: chrono::Utc::now().timestamp_millis(),
+            is_block_trade: false,
+            forced: None,
+            exec_id: 0,
         }
     }
 
@@ -418,4 +513,37 @@ mod tests {
         let recent = manager.get_recent_trades(10);
         assert_eq!(recent.len(), 2);
     }
+
+    #[test]
+    fn test_trade_pages() {
+        let history = HistoryManager::with_limits(3, 10);
+        let yes = "m:o:yes".to_string();
+        let no = "m:o:no".to_string();
+        // t1..t6 alternate between the books; the yes book only keeps t3, t5, t6
+        for (i, symbol) in [&yes, &no, &yes, &no, &yes, &yes].into_iter().enumerate() {
+            let mut trade = create_test_trade(&format!("t{}", i + 1), symbol, "0.5");
+            trade.timestamp = 1_000 + i as i64;
+            trade.exec_id = i as u64 + 1;
+            trade.side = if i % 2 == 0 { "buy" } else { "sell" }.to_string();
+            history.store_trade(trade);
+        }
+        let symbols = [yes, no];
+        let ids = |page: &TradePage| page.trades.iter().map(|t| t.trade_id.clone()).collect::<Vec<_>>();
+
+        let page = history.trade_page(&symbols, &TradeCursor::Latest, None, 2).unwrap();
+        assert_eq!(ids(&page), vec!["t6", "t5"]);
+        assert!(page.has_more);
+
+        // t2 and older may have been trimmed from the yes book
+        assert!(history.trade_page(&symbols, &TradeCursor::Before("t5".into()), None, 2).is_none());
+        assert!(history.trade_page(&symbols, &TradeCursor::Before("t9".into()), None, 2).is_none());
+
+        let page = history.trade_page(&symbols, &TradeCursor::After("t2".into()), None, 2).unwrap();
+        assert_eq!(ids(&page), vec!["t4", "t3"]);
+        assert!(page.has_more);
+
+        let page = history.trade_page(&symbols, &TradeCursor::After("t2".into()), Some("SELL"), 5).unwrap();
+        assert_eq!(ids(&page), vec!["t6", "t4"]);
+        assert!(!page.has_more);
+    }
 }
@@ -0,0 +1,442 @@
+//! Engine Write-Ahead Journal
+//!
+//! Append-only log of every command accepted by the matching engine.
+//! Each command is assigned a monotonically increasing sequence number and
+//! written to disk *before* it is applied to the in-memory orderbooks, so the
+//! engine state can be rebuilt deterministically after a crash by replaying
+//! the journal in sequence order.
+//!
+//! # Format
+//!
+//! One JSON-encoded [`JournalEntry`] per line (JSON Lines). The file is only
+//! ever appended to; compaction is left to external tooling.
+
+use parking_lot::Mutex;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use super::types::{OrderType, Side};
+
+/// Journal configuration
+#[derive(Debug, Clone)]
+pub struct JournalConfig {
+    /// Enable the write-ahead journal
+    pub enabled: bool,
+    /// Path of the journal file
+    pub path: PathBuf,
+    /// fsync after every append (slower, but survives power loss)
+    pub fsync: bool,
+}
+
+impl Default for JournalConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: PathBuf::from("data/engine.journal"),
+            fsync: false,
+        }
+    }
+}
+
+impl JournalConfig {
+    /// Create config from environment variables
+    pub fn from_env() -> Self {
+        Self {
+            enabled: std::env::var("ENGINE_JOURNAL_ENABLED")
+                .map(|v| v.to_lowercase() == "true" || v == "1")
+                .unwrap_or(false),
+            path: std::env::var("ENGINE_JOURNAL_PATH")
+                .map(PathBuf::from)
+                .unwrap_or_else(|_| PathBuf::from("data/engine.journal")),
+            fsync: std::env::var("ENGINE_JOURNAL_FSYNC")
+                .map(|v| v.to_lowercase() == "true" || v == "1")
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// A command accepted by the matching engine
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum EngineCommand {
+    /// Submit an order for matching
+    Submit {
+        order_id: Uuid,
+        symbol: String,
+        user_address: String,
+        side: Side,
+        order_type: OrderType,
+        amount: Decimal,
+        price: Option<Decimal>,
+        leverage: u32,
+    },
+    /// Cancel a resting order
+    Cancel {
+        symbol: String,
+        order_id: Uuid,
+        user_address: String,
+    },
+    /// Amend the price and/or total amount of a resting limit order
+    Amend {
+        symbol: String,
+        order_id: Uuid,
+        user_address: String,
+        price: Decimal,
+        amount: Decimal,
+    },
+}
+
+impl EngineCommand {
+    /// Symbol the command applies to
+    pub fn symbol(&self) -> &str {
+        match self {
+            EngineCommand::Submit { symbol, .. }
+            | EngineCommand::Cancel { symbol, .. }
+            | EngineCommand::Amend { symbol, .. } => symbol,
+        }
+    }
+}
+
+/// A single journal record
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Engine sequence number (starts at 1)
+    pub sequence: u64,
+    /// Wall-clock time the command was accepted (milliseconds)
+    pub timestamp: i64,
+    /// The command itself
+    pub command: EngineCommand,
+}
+
+/// Journal errors
+#[derive(Debug, thiserror::Error)]
+pub enum JournalError {
+    #[error("Journal I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Corrupt journal entry at line {line}: {message}")]
+    Corrupt { line: usize, message: String },
+
+    #[error("Journal sequence gap: expected {expected}, found {found}")]
+    SequenceGap { expected: u64, found: u64 },
+}
+
+/// Append-only engine journal backed by a file
+pub struct EngineJournal {
+    /// Journal file path
+    path: PathBuf,
+
+    /// Buffered writer (serialized by the mutex so entries never interleave)
+    writer: Mutex<BufWriter<File>>,
+
+    /// Last sequence number written
+    last_sequence: AtomicU64,
+
+    /// fsync after every append
+    fsync: bool,
+}
+
+impl EngineJournal {
+    /// Open (or create) a journal file, resuming from its last sequence number
+    pub fn open(config: &JournalConfig) -> Result<Self, JournalError> {
+        if let Some(parent) = config.path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+
+        let last_sequence = if config.path.exists() {
+            Self::truncate_partial_tail(&config.path)?;
+            Self::read_entries(&config.path)?
+                .last()
+                .map(|e| e.sequence)
+                .unwrap_or(0)
+        } else {
+            0
+        };
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&config.path)?;
+
+        info!(
+            "Engine journal opened at {} (last sequence: {})",
+            config.path.display(),
+            last_sequence
+        );
+
+        Ok(Self {
+            path: config.path.clone(),
+            writer: Mutex::new(BufWriter::new(file)),
+            last_sequence: AtomicU64::new(last_sequence),
+            fsync: config.fsync,
+        })
+    }
+
+    /// Journal file path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Last sequence number written to the journal
+    pub fn last_sequence(&self) -> u64 {
+        self.last_sequence.load(Ordering::SeqCst)
+    }
+
+    /// Append a command, returning its assigned sequence number
+    ///
+    /// The entry is flushed to the OS before returning so a crash after this
+    /// call cannot lose the command.
+    pub fn append(&self, command: EngineCommand) -> Result<u64, JournalError> {
+        self.record(command).map(|entry| entry.sequence)
+    }
+
+    /// Append a command like [`append`](Self::append), returning the whole
+    /// entry written
+    pub fn record(&self, command: EngineCommand) -> Result<JournalEntry, JournalError> {
+        let mut writer = self.writer.lock();
+
+        // Sequence is assigned under the lock so file order == sequence order
+        let sequence = self.last_sequence.load(Ordering::SeqCst) + 1;
+        let entry = JournalEntry {
+            sequence,
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            command,
+        };
+
+        let line = serde_json::to_string(&entry).map_err(|e| JournalError::Corrupt {
+            line: sequence as usize,
+            message: e.to_string(),
+        })?;
+        writer.write_all(line.as_bytes())?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        if self.fsync {
+            writer.get_ref().sync_data()?;
+        }
+
+        self.last_sequence.store(sequence, Ordering::SeqCst);
+        Ok(entry)
+    }
+
+    /// Append an entry sequenced elsewhere (a replicated primary entry),
+    /// keeping its sequence number and timestamp
+    ///
+    /// The entry must directly follow the last one written.
+    pub fn append_entry(&self, entry: &JournalEntry) -> Result<(), JournalError> {
+        let mut writer = self.writer.lock();
+
+        let expected = self.last_sequence.load(Ordering::SeqCst) + 1;
+        if entry.sequence != expected {
+            return Err(JournalError::SequenceGap {
+                expected,
+                found: entry.sequence,
+            });
+        }
+
+        let line = serde_json::to_string(entry).map_err(|e| JournalError::Corrupt {
+            line: entry.sequence as usize,
+            message: e.to_string(),
+        })?;
+        writer.write_all(line.as_bytes())?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        if self.fsync {
+            writer.get_ref().sync_data()?;
+        }
+
+        self.last_sequence.store(entry.sequence, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Force everything appended so far onto disk, returning the last
+    /// sequence number now durable
+    ///
+    /// A no-op beyond the flush when every append is already fsynced.
+    pub fn sync(&self) -> Result<u64, JournalError> {
+        let mut writer = self.writer.lock();
+        writer.flush()?;
+        if !self.fsync {
+            writer.get_ref().sync_data()?;
+        }
+        Ok(self.last_sequence.load(Ordering::SeqCst))
+    }
+
+    /// Drop an incomplete trailing line left by a crash mid-append, so new
+    /// entries are not glued onto it
+    fn truncate_partial_tail(path: &Path) -> Result<(), JournalError> {
+        let content = std::fs::read(path)?;
+        if content.is_empty() || content.ends_with(b"\n") {
+            return Ok(());
+        }
+
+        let valid_len = content
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map(|pos| pos + 1)
+            .unwrap_or(0);
+        warn!(
+            "Truncating {} bytes of partial journal tail in {}",
+            content.len() - valid_len,
+            path.display()
+        );
+        OpenOptions::new().write(true).open(path)?.set_len(valid_len as u64)?;
+        Ok(())
+    }
+
+    /// Read all entries from a journal file, validating sequence continuity
+    ///
+    /// A truncated final line (crash mid-write) is ignored with a warning;
+    /// corruption anywhere else is an error.
+    pub fn read_entries(path: &Path) -> Result<Vec<JournalEntry>, JournalError> {
+        let file = File::open(path)?;
+        let lines: Vec<String> = BufReader::new(file).lines().collect::<Result<_, _>>()?;
+        let total = lines.len();
+
+        let mut entries = Vec::with_capacity(total);
+        let mut expected = 1u64;
+
+        for (idx, line) in lines.into_iter().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let entry: JournalEntry = match serde_json::from_str(&line) {
+                Ok(entry) => entry,
+                Err(e) if idx + 1 == total => {
+                    warn!("Ignoring truncated journal tail at line {}: {}", idx + 1, e);
+                    break;
+                }
+                Err(e) => {
+                    return Err(JournalError::Corrupt {
+                        line: idx + 1,
+                        message: e.to_string(),
+                    })
+                }
+            };
+
+            if entry.sequence != expected {
+                return Err(JournalError::SequenceGap {
+                    expected,
+                    found: entry.sequence,
+                });
+            }
+            expected += 1;
+            entries.push(entry);
+        }
+
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn temp_config() -> JournalConfig {
+        JournalConfig {
+            enabled: true,
+            path: std::env::temp_dir().join(format!("engine-journal-{}.log", Uuid::new_v4())),
+            fsync: false,
+        }
+    }
+
+    fn submit(order_id: Uuid) -> EngineCommand {
+        EngineCommand::Submit {
+            order_id,
+            symbol: "m:o:yes".to_string(),
+            user_address: "0x1".to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            amount: dec!(10),
+            price: Some(dec!(0.5)),
+            leverage: 1,
+        }
+    }
+
+    #[test]
+    fn test_append_assigns_sequence() {
+        let config = temp_config();
+        let journal = EngineJournal::open(&config).unwrap();
+
+        assert_eq!(journal.append(submit(Uuid::new_v4())).unwrap(), 1);
+        assert_eq!(journal.append(submit(Uuid::new_v4())).unwrap(), 2);
+        assert_eq!(journal.last_sequence(), 2);
+        assert_eq!(journal.sync().unwrap(), 2);
+
+        let entries = EngineJournal::read_entries(&config.path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].sequence, 2);
+
+        let _ = std::fs::remove_file(&config.path);
+    }
+
+    #[test]
+    fn test_reopen_resumes_sequence() {
+        let config = temp_config();
+        {
+            let journal = EngineJournal::open(&config).unwrap();
+            journal.append(submit(Uuid::new_v4())).unwrap();
+        }
+
+        let journal = EngineJournal::open(&config).unwrap();
+        assert_eq!(journal.last_sequence(), 1);
+        assert_eq!(journal.append(submit(Uuid::new_v4())).unwrap(), 2);
+
+        let _ = std::fs::remove_file(&config.path);
+    }
+
+    #[test]
+    fn test_append_entry_keeps_primary_sequence() {
+        let config = temp_config();
+        let journal = EngineJournal::open(&config).unwrap();
+        let entry = |sequence| JournalEntry {
+            sequence,
+            timestamp: 1_700_000_000_000,
+            command: submit(Uuid::new_v4()),
+        };
+
+        journal.append_entry(&entry(1)).unwrap();
+        assert!(matches!(
+            journal.append_entry(&entry(3)),
+            Err(JournalError::SequenceGap { expected: 2, found: 3 })
+        ));
+        journal.append_entry(&entry(2)).unwrap();
+        assert_eq!(journal.append(submit(Uuid::new_v4())).unwrap(), 3);
+
+        let entries = EngineJournal::read_entries(&config.path).unwrap();
+        assert_eq!(entries[0].timestamp, 1_700_000_000_000);
+        assert_eq!(entries.len(), 3);
+
+        let _ = std::fs::remove_file(&config.path);
+    }
+
+    #[test]
+    fn test_truncated_tail_is_ignored() {
+        let config = temp_config();
+        {
+            let journal = EngineJournal::open(&config).unwrap();
+            journal.append(submit(Uuid::new_v4())).unwrap();
+        }
+        let mut file = OpenOptions::new().append(true).open(&config.path).unwrap();
+        file.write_all(b"{\"sequence\":2,\"timest").unwrap();
+
+        let entries = EngineJournal::read_entries(&config.path).unwrap();
+        assert_eq!(entries.len(), 1);
+
+        // Reopening drops the partial line so the next append stays readable
+        let journal = EngineJournal::open(&config).unwrap();
+        assert_eq!(journal.append(submit(Uuid::new_v4())).unwrap(), 2);
+        assert_eq!(EngineJournal::read_entries(&config.path).unwrap().len(), 2);
+
+        let _ = std::fs::remove_file(&config.path);
+    }
+}
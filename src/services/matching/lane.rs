@@ -0,0 +1,173 @@
+//! Command Lanes
+//!
+//! Per-market admission gate for engine commands. Commands for one market
+//! (both share types, since mint/merge matching spans the complement book) run
+//! one at a time through a [`LaneGate`] with two lanes:
+//!
+//! - **Priority**: cancels. A waiting cancel is admitted before any waiting
+//!   placement, so cancels never queue behind a burst of new orders.
+//! - **Normal**: placements. Admitted only when no cancel is waiting.
+//!
+//! Markets are gated independently; load on one market never delays another.
+
+use parking_lot::{Condvar, Mutex};
+use std::sync::Arc;
+
+/// Lane a command is admitted through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandLane {
+    Priority,
+    Normal,
+}
+
+impl CommandLane {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommandLane::Priority => "priority",
+            CommandLane::Normal => "normal",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct GateState {
+    busy: bool,
+    priority_waiting: usize,
+    normal_waiting: usize,
+}
+
+/// Two-lane admission gate for one market
+#[derive(Debug, Default)]
+pub struct LaneGate {
+    state: Mutex<GateState>,
+    ready: Condvar,
+}
+
+impl LaneGate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Block until a command on `lane` may run; the market is held until the guard drops
+    pub fn enter(self: &Arc<Self>, lane: CommandLane) -> LaneGuard {
+        let mut state = self.state.lock();
+        let overtook = match lane {
+            CommandLane::Priority => {
+                state.priority_waiting += 1;
+                while state.busy {
+                    self.ready.wait(&mut state);
+                }
+                state.priority_waiting -= 1;
+                state.normal_waiting > 0
+            }
+            CommandLane::Normal => {
+                state.normal_waiting += 1;
+                while state.busy || state.priority_waiting > 0 {
+                    self.ready.wait(&mut state);
+                }
+                state.normal_waiting -= 1;
+                false
+            }
+        };
+        state.busy = true;
+
+        LaneGuard {
+            gate: Arc::clone(self),
+            overtook,
+        }
+    }
+
+    fn release(&self) {
+        self.state.lock().busy = false;
+        self.ready.notify_all();
+    }
+
+    #[cfg(test)]
+    fn waiting(&self) -> (usize, usize) {
+        let state = self.state.lock();
+        (state.priority_waiting, state.normal_waiting)
+    }
+}
+
+/// Exclusive hold on a market's gate
+#[derive(Debug)]
+pub struct LaneGuard {
+    gate: Arc<LaneGate>,
+    overtook: bool,
+}
+
+impl LaneGuard {
+    /// A priority command was admitted ahead of waiting placements
+    pub fn overtook(&self) -> bool {
+        self.overtook
+    }
+}
+
+impl Drop for LaneGuard {
+    fn drop(&mut self) {
+        self.gate.release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    fn wait_for(gate: &LaneGate, waiting: (usize, usize)) {
+        for _ in 0..500 {
+            if gate.waiting() == waiting {
+                return;
+            }
+            thread::sleep(Duration::from_millis(2));
+        }
+        panic!("gate never reached {:?} waiting (now {:?})", waiting, gate.waiting());
+    }
+
+    #[test]
+    fn test_priority_lane_runs_before_waiting_placements() {
+        let gate = Arc::new(LaneGate::new());
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let held = gate.enter(CommandLane::Normal);
+
+        let mut handles = Vec::new();
+        for i in 0..3 {
+            let (gate, log) = (gate.clone(), log.clone());
+            handles.push(thread::spawn(move || {
+                let _guard = gate.enter(CommandLane::Normal);
+                log.lock().push(format!("place-{}", i));
+            }));
+        }
+        wait_for(&gate, (0, 3));
+
+        let (cancel_gate, cancel_log) = (gate.clone(), log.clone());
+        let cancel = thread::spawn(move || {
+            let guard = cancel_gate.enter(CommandLane::Priority);
+            cancel_log.lock().push("cancel".to_string());
+            guard.overtook()
+        });
+        wait_for(&gate, (1, 3));
+
+        drop(held);
+        assert!(cancel.join().unwrap());
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let log = log.lock();
+        assert_eq!(log.len(), 4);
+        assert_eq!(log[0], "cancel");
+    }
+
+    #[test]
+    fn test_uncontended_gate_does_not_block() {
+        let gate = Arc::new(LaneGate::new());
+        let guard = gate.enter(CommandLane::Priority);
+        assert!(!guard.overtook());
+        drop(guard);
+        drop(gate.enter(CommandLane::Normal));
+        assert_eq!(gate.waiting(), (0, 0));
+    }
+}
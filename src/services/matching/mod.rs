@@ -21,6 +21,12 @@
 //! - **Async Persistence**: Database operations are non-blocking
 //! - **History Tracking**: Keeps recent trades and orders in memory
 //! - **WebSocket Integration**: Broadcasts trade events in real-time
+//! - **Write-Ahead Journal**: Optional append-only command log for deterministic replay
+//! - **Priority Cancel Lane**: Cancels are admitted ahead of waiting placements per market
+//! - **Order Reconciliation**: Sweeps resolve open orders the database and engine disagree about
+//! - **Orderbook Snapshots**: Periodic per-symbol snapshots bound journal replay on restart
+//! - **Dead Man's Switch**: Cancels an account's resting orders when its heartbeat countdown runs out
+//! - **Warm Standby**: A secondary process follows the primary's commands and can be promoted on failover
 //!
 //! # Prediction Market Keys
 //!
@@ -29,21 +35,37 @@
 //!
 //! For example: `550e8400-e29b-41d4-a716-446655440000:660e8400-e29b-41d4-a716-446655440001:Yes`
 
+mod dead_man;
 mod engine;
 mod history;
+mod journal;
+mod lane;
 mod orderbook;
 mod orchestrator;
+mod reconcile;
+mod replication;
+pub mod snapshot;
 mod types;
 
 // Re-export main types
 // Note: Some of these may appear unused but are part of the public API
+pub use dead_man::{cancel_resting_order, DeadManError, DeadManSwitch, DeadManTimer};
 #[allow(unused_imports)]
 pub use engine::{EngineStats, MatchingEngine};
 #[allow(unused_imports)]
-pub use history::{HistoryManager, HistoryStats};
+pub use history::{HistoryManager, HistoryStats, TradeCursor, TradePage};
 #[allow(unused_imports)]
-pub use orderbook::Orderbook;
+pub use journal::{EngineCommand, EngineJournal, JournalConfig, JournalEntry, JournalError};
+#[allow(unused_imports)]
+pub use orderbook::{book_checksum, group_levels, validate_group, Orderbook, CHECKSUM_DEPTH};
 pub use orchestrator::OrderFlowOrchestrator;
+#[allow(unused_imports)]
+pub use reconcile::{find_mismatches, OrderReconciler, ReconcileConfig, ReconcileError, ReconcilePolicy, ReconcileReport};
+#[allow(unused_imports)]
+pub use replication::{
+    ReplicationBatch, ReplicationConfig, ReplicationError, ReplicationLog, ReplicationRole, ReplicationService,
+    ReplicationStatus, REPLICATION_SECRET_HEADER,
+};
 pub use types::*;
 
 #[cfg(test)]
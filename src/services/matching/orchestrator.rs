@@ -6,18 +6,24 @@
 //! 3. Process match results (including Mint/Merge logic)
 //! 4. Update share positions
 //! 5. Persist to database asynchronously
-//! 6. Broadcast updates via WebSocket
+//! 6. Broadcast updates via WebSocket (order fills and status changes are
+//!    published as `OrderEvent`s)
 
 #![allow(dead_code)]
 
 use super::engine::MatchingEngine;
 use super::types::*;
+use crate::cache::keys::CacheKey;
+use crate::chaos::{self, Fault};
 use crate::models::market::ShareType;
+use crate::services::outbox;
+use crate::services::position::{FillRole, PositionFill, PositionService, Settlement};
+use crate::services::user_events::{self, OrderChange, OrderEvent};
 use rust_decimal::Decimal;
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
 use std::sync::Arc;
 use tokio::sync::broadcast;
-use tracing::{debug, error, info, warn};
+use tracing::{debug, error, info, info_span, warn, Instrument};
 use uuid::Uuid;
 
 /// Order flow orchestrator for prediction markets
@@ -33,11 +39,14 @@ pub struct OrderFlowOrchestrator {
 
     /// Trade event receiver for persistence
     trade_receiver: Option<broadcast::Receiver<TradeEvent>>,
+
+    /// Collateral token realized PnL is credited in
+    collateral_token: String,
 }
 
 impl OrderFlowOrchestrator {
     /// Create a new orchestrator
-    pub fn new(engine: Arc<MatchingEngine>, pool: PgPool) -> Self {
+    pub fn new(engine: Arc<MatchingEngine>, pool: PgPool, collateral_token: impl Into<String>) -> Self {
         let trade_receiver = Some(engine.subscribe_trades());
 
         info!("OrderFlowOrchestrator initialized");
@@ -46,6 +55,7 @@ impl OrderFlowOrchestrator {
             engine,
             pool,
             trade_receiver,
+            collateral_token: collateral_token.into(),
         }
     }
 
@@ -59,6 +69,7 @@ impl OrderFlowOrchestrator {
         let pool = self.pool.clone();
         let engine = Arc::clone(&self.engine);
         let receiver = self.trade_receiver.take();
+        let settlement = Settlement::collateral(&self.collateral_token);
 
         if let Some(mut rx) = receiver {
             tokio::spawn(async move {
@@ -67,7 +78,8 @@ impl OrderFlowOrchestrator {
                 loop {
                     match rx.recv().await {
                         Ok(trade) => {
-                            if let Err(e) = Self::persist_trade(&pool, &trade).await {
+                            let span = Self::trade_span(&trade);
+                            if let Err(e) = Self::persist_trade(&pool, &trade, &settlement).instrument(span).await {
                                 error!("Failed to persist trade: {}", e);
                             }
                         }
@@ -86,6 +98,16 @@ impl OrderFlowOrchestrator {
         engine
     }
 
+    /// Span for persisting a trade, tied to the request that produced it
+    pub fn trade_span(trade: &TradeEvent) -> tracing::Span {
+        info_span!(
+            "persist_trade",
+            trade_id = %trade.trade_id,
+            taker_order_id = %trade.taker_order_id,
+            request_id = trade.request_id.as_deref().unwrap_or("-"),
+        )
+    }
+
     /// Process a new order for prediction market
     pub async fn process_order(
         &self,
@@ -150,7 +172,7 @@ impl OrderFlowOrchestrator {
             ).await {
                 error!("Failed to persist order {}: {}", order_id, e);
             }
-        });
+        }.in_current_span());
 
         info!(
             "Order processed: id={}, status={:?}, filled={}",
@@ -180,12 +202,13 @@ impl OrderFlowOrchestrator {
         if cancelled {
             // Update database asynchronously
             let pool = self.pool.clone();
+            let user_address = user_address.to_string();
 
             tokio::spawn(async move {
-                if let Err(e) = Self::update_order_status(&pool, order_id, "cancelled").await {
+                if let Err(e) = Self::update_order_status(&pool, &user_address, order_id, "cancelled").await {
                     error!("Failed to update order status: {}", e);
                 }
-            });
+            }.in_current_span());
 
             info!("Order cancelled: id={}", order_id);
         }
@@ -222,24 +245,96 @@ impl OrderFlowOrchestrator {
     // ========================================================================
 
     /// Persist a trade to database and update share positions
-    pub async fn persist_trade(pool: &PgPool, trade: &TradeEvent) -> Result<(), sqlx::Error> {
-        // Use the fees calculated by the matching engine
-        let maker_fee = trade.maker_fee;
-        let taker_fee = trade.taker_fee;
-        let _trade_value = trade.amount * trade.price;
+    ///
+    /// Fills are netted against existing positions; realized PnL is converted
+    /// and credited per `settlement`. A trade already in `trades` is skipped,
+    /// and every fill is claimed in `trade_fills` before it is applied, so a
+    /// replayed trade never moves shares or balances twice.
+    pub async fn persist_trade(pool: &PgPool, trade: &TradeEvent, settlement: &Settlement) -> Result<(), sqlx::Error> {
+        chaos::delay(Fault::DelayDbWrite).await;
+
+        // 1. Save trade record, with its outbox event in the same transaction
+        let mut tx = pool.begin().await?;
+        let inserted = Self::insert_trade(&mut tx, trade).await?;
+        // A replayed trade was applied and announced when first persisted
+        if inserted == 0 {
+            // Except a block trade settled before it was published: it gets its
+            // exec id and market data announcement now
+            if Self::assign_exec_id(&mut tx, trade).await? {
+                outbox::enqueue(&mut tx, &CacheKey::channel_trades(&trade.symbol), trade).await?;
+            }
+            tx.commit().await?;
+            debug!("Trade {} already persisted, skipping", trade.trade_id);
+            return Ok(());
+        }
+        outbox::enqueue(&mut tx, &CacheKey::channel_trades(&trade.symbol), trade).await?;
+        tx.commit().await?;
 
-        // 1. Save trade record
-        sqlx::query(
+        debug!("Persisted trade: {} (match_type={:?})", trade.trade_id, trade.match_type);
+
+        // 2. Update share positions based on match type
+        match trade.match_type {
+            MatchType::Normal => {
+                // Normal trade: transfer shares between maker and taker
+                Self::update_shares_normal(pool, trade, settlement).await?;
+            }
+            MatchType::Mint => {
+                // Mint: both parties receive new shares
+                Self::update_shares_mint(pool, trade, settlement).await?;
+            }
+            MatchType::Merge => {
+                // Merge: both parties redeem shares for collateral
+                Self::update_shares_merge(pool, trade, settlement).await?;
+            }
+        }
+
+        // 3. Record share changes for audit trail
+        let mut conn = pool.acquire().await?;
+        Self::record_share_changes(&mut conn, trade).await?;
+
+        // 4. Announce the fill on both orders
+        Self::announce_fills(&mut conn, trade).await?;
+
+        debug!("Updated share positions for trade: {}", trade.trade_id);
+        Ok(())
+    }
+
+    /// Persist a privately matched block trade (RFQ) inside the transaction
+    /// that settles its collateral: the trade, both share transfers and the
+    /// fill events commit or roll back together. The caller must hold both
+    /// holdings' row locks. The trade is published afterwards; its exec id and
+    /// market data announcement are added when [`Self::persist_trade`] sees it.
+    pub async fn persist_block_trade(
+        conn: &mut PgConnection,
+        trade: &TradeEvent,
+        settlement: &Settlement,
+    ) -> Result<(), sqlx::Error> {
+        Self::insert_trade(conn, trade).await?;
+
+        let (seller, buyer) = Self::normal_fills(trade);
+        PositionService::apply_fill_in(conn, &seller, -seller.amount, settlement).await?;
+        PositionService::apply_fill_in(conn, &buyer, buyer.amount, settlement).await?;
+
+        Self::record_share_changes(conn, trade).await?;
+        Self::announce_fills(conn, trade).await?;
+
+        debug!("Persisted block trade: {}", trade.trade_id);
+        Ok(())
+    }
+
+    /// Insert a trade record; returns the rows inserted (0 if already persisted)
+    async fn insert_trade(conn: &mut PgConnection, trade: &TradeEvent) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query(
             r#"
             INSERT INTO trades (
                 id, market_id, outcome_id, share_type, match_type,
                 maker_order_id, taker_order_id, maker_address, taker_address,
-                side, price, amount, maker_fee, taker_fee, created_at
+                side, price, amount, maker_fee, taker_fee, created_at, is_block_trade, exec_id, forced
             )
             VALUES (
                 $1, $2, $3, $4::share_type, $5::match_type,
                 $6, $7, $8, $9,
-                $10::order_side, $11, $12, $13, $14, to_timestamp($15::double precision / 1000)
+                $10::order_side, $11, $12, $13, $14, to_timestamp($15::double precision / 1000), $16, $17, $18
             )
             ON CONFLICT (id) DO NOTHING
             "#
@@ -256,39 +351,73 @@ impl OrderFlowOrchestrator {
         .bind(trade.side.to_string())
         .bind(trade.price)
         .bind(trade.amount)
-        .bind(maker_fee)
-        .bind(taker_fee)
+        // Fees as calculated by the matching engine
+        .bind(trade.maker_fee)
+        .bind(trade.taker_fee)
         .bind(trade.timestamp as f64)
-        .execute(pool)
+        .bind(trade.is_block_trade)
+        .bind(Self::exec_id(trade))
+        .bind(trade.forced.map(|forced| forced.to_string()))
+        .execute(&mut *conn)
         .await?;
+        Ok(result.rows_affected())
+    }
 
-        debug!("Persisted trade: {} (match_type={:?})", trade.trade_id, trade.match_type);
+    /// Record the exec id of a trade persisted before it was published;
+    /// false if it already had one (or this instance assigns none)
+    async fn assign_exec_id(conn: &mut PgConnection, trade: &TradeEvent) -> Result<bool, sqlx::Error> {
+        let Some(exec_id) = Self::exec_id(trade) else {
+            return Ok(false);
+        };
+        let updated = sqlx::query("UPDATE trades SET exec_id = $2 WHERE id = $1 AND exec_id IS NULL")
+            .bind(trade.trade_id)
+            .bind(exec_id)
+            .execute(&mut *conn)
+            .await?
+            .rows_affected();
+        Ok(updated > 0)
+    }
 
-        // 2. Update share positions based on match type
-        match trade.match_type {
-            MatchType::Normal => {
-                // Normal trade: transfer shares between maker and taker
-                Self::update_shares_normal(pool, trade).await?;
-            }
-            MatchType::Mint => {
-                // Mint: both parties receive new shares
-                Self::update_shares_mint(pool, trade).await?;
-            }
-            MatchType::Merge => {
-                // Merge: both parties redeem shares for collateral
-                Self::update_shares_merge(pool, trade).await?;
-            }
+    /// Exec id as stored (None until the trade is published by a primary)
+    fn exec_id(trade: &TradeEvent) -> Option<i64> {
+        (trade.exec_id > 0).then_some(trade.exec_id as i64)
+    }
+
+    /// Publish the fill on the maker's and the taker's order
+    async fn announce_fills(conn: &mut PgConnection, trade: &TradeEvent) -> Result<(), sqlx::Error> {
+        for (user_address, order_id) in [
+            (&trade.maker_address, trade.maker_order_id),
+            (&trade.taker_address, trade.taker_order_id),
+        ] {
+            let event = OrderEvent {
+                user_address: user_address.to_lowercase(),
+                order_id,
+                change: OrderChange::Fill {
+                    trade_id: trade.trade_id,
+                    amount: trade.amount,
+                    price: trade.price,
+                },
+            };
+            user_events::publish(&mut *conn, &event).await?;
         }
+        Ok(())
+    }
 
-        // 3. Record share changes for audit trail
-        Self::record_share_changes(pool, trade).await?;
+    /// Update shares for normal trade (transfer between parties)
+    async fn update_shares_normal(pool: &PgPool, trade: &TradeEvent, settlement: &Settlement) -> Result<(), sqlx::Error> {
+        let (seller, buyer) = Self::normal_fills(trade);
+
+        // Decrease seller's shares (closes any long first, realizing PnL)
+        PositionService::decrease_position(pool, &seller, settlement).await?;
+
+        // Increase buyer's shares (covers any short first)
+        PositionService::increase_position(pool, &buyer, settlement).await?;
 
-        debug!("Updated share positions for trade: {}", trade.trade_id);
         Ok(())
     }
 
-    /// Update shares for normal trade (transfer between parties)
-    async fn update_shares_normal(pool: &PgPool, trade: &TradeEvent) -> Result<(), sqlx::Error> {
+    /// Seller's and buyer's fills of a normal trade
+    fn normal_fills(trade: &TradeEvent) -> (PositionFill, PositionFill) {
         // Determine buyer and seller based on taker's side
         let is_buy = trade.side.to_lowercase() == "buy";
         let (buyer_address, seller_address) = if is_buy {
@@ -296,139 +425,117 @@ impl OrderFlowOrchestrator {
         } else {
             (&trade.maker_address, &trade.taker_address)
         };
+        let (buyer_fee, seller_fee) = if is_buy {
+            (trade.taker_fee, trade.maker_fee)
+        } else {
+            (trade.maker_fee, trade.taker_fee)
+        };
+        let (buyer_role, seller_role) = if is_buy {
+            (FillRole::Taker, FillRole::Maker)
+        } else {
+            (FillRole::Maker, FillRole::Taker)
+        };
 
-        // Decrease seller's shares
-        sqlx::query(
-            r#"
-            INSERT INTO shares (user_address, market_id, outcome_id, share_type, amount, avg_cost)
-            VALUES ($1, $2, $3, $4::share_type, -$5, $6)
-            ON CONFLICT (user_address, outcome_id) DO UPDATE SET
-                amount = shares.amount - $5,
-                updated_at = NOW()
-            "#
-        )
-        .bind(seller_address)
-        .bind(trade.market_id)
-        .bind(trade.outcome_id)
-        .bind(trade.share_type.to_string())
-        .bind(trade.amount)
-        .bind(trade.price)
-        .execute(pool)
-        .await?;
-
-        // Increase buyer's shares
-        sqlx::query(
-            r#"
-            INSERT INTO shares (user_address, market_id, outcome_id, share_type, amount, avg_cost)
-            VALUES ($1, $2, $3, $4::share_type, $5, $6)
-            ON CONFLICT (user_address, outcome_id) DO UPDATE SET
-                amount = shares.amount + $5,
-                avg_cost = (shares.avg_cost * shares.amount + $6 * $5) / (shares.amount + $5),
-                updated_at = NOW()
-            "#
-        )
-        .bind(buyer_address)
-        .bind(trade.market_id)
-        .bind(trade.outcome_id)
-        .bind(trade.share_type.to_string())
-        .bind(trade.amount)
-        .bind(trade.price)
-        .execute(pool)
-        .await?;
-
-        Ok(())
+        let fill = |user_address: &String, fee, role| PositionFill {
+            user_address: user_address.clone(),
+            market_id: trade.market_id,
+            outcome_id: trade.outcome_id,
+            share_type: trade.share_type,
+            amount: trade.amount,
+            price: trade.price,
+            trade_id: Some(trade.trade_id),
+            fee,
+            role,
+        };
+        (fill(seller_address, seller_fee, seller_role), fill(buyer_address, buyer_fee, buyer_role))
     }
 
     /// Update shares for mint trade (create new shares)
-    async fn update_shares_mint(pool: &PgPool, trade: &TradeEvent) -> Result<(), sqlx::Error> {
+    async fn update_shares_mint(pool: &PgPool, trade: &TradeEvent, settlement: &Settlement) -> Result<(), sqlx::Error> {
         // Both parties are buyers - each gets shares of their respective type
         // Maker gets shares of the complement type (since match was cross-outcome)
         let maker_share_type = trade.share_type.complement();
-        let taker_share_type = trade.share_type.clone();
+        let taker_share_type = trade.share_type;
 
         // Maker gets complement shares
-        sqlx::query(
-            r#"
-            INSERT INTO shares (user_address, market_id, outcome_id, share_type, amount, avg_cost)
-            VALUES ($1, $2, $3, $4::share_type, $5, $6)
-            ON CONFLICT (user_address, outcome_id) DO UPDATE SET
-                amount = shares.amount + $5,
-                avg_cost = (shares.avg_cost * shares.amount + $6 * $5) / (shares.amount + $5),
-                updated_at = NOW()
-            "#
+        PositionService::increase_position(
+            pool,
+            &PositionFill {
+                user_address: trade.maker_address.clone(),
+                market_id: trade.market_id,
+                outcome_id: trade.outcome_id,
+                share_type: maker_share_type,
+                amount: trade.amount,
+                price: Decimal::ONE - trade.price, // Complement price
+                trade_id: Some(trade.trade_id),
+                fee: trade.maker_fee,
+                role: FillRole::Maker,
+            },
+            settlement,
         )
-        .bind(&trade.maker_address)
-        .bind(trade.market_id)
-        .bind(trade.outcome_id)
-        .bind(maker_share_type.to_string())
-        .bind(trade.amount)
-        .bind(Decimal::ONE - trade.price)  // Complement price
-        .execute(pool)
         .await?;
 
         // Taker gets taker's share type
-        sqlx::query(
-            r#"
-            INSERT INTO shares (user_address, market_id, outcome_id, share_type, amount, avg_cost)
-            VALUES ($1, $2, $3, $4::share_type, $5, $6)
-            ON CONFLICT (user_address, outcome_id) DO UPDATE SET
-                amount = shares.amount + $5,
-                avg_cost = (shares.avg_cost * shares.amount + $6 * $5) / (shares.amount + $5),
-                updated_at = NOW()
-            "#
+        PositionService::increase_position(
+            pool,
+            &PositionFill {
+                user_address: trade.taker_address.clone(),
+                market_id: trade.market_id,
+                outcome_id: trade.outcome_id,
+                share_type: taker_share_type,
+                amount: trade.amount,
+                price: trade.price,
+                trade_id: Some(trade.trade_id),
+                fee: trade.taker_fee,
+                role: FillRole::Taker,
+            },
+            settlement,
         )
-        .bind(&trade.taker_address)
-        .bind(trade.market_id)
-        .bind(trade.outcome_id)
-        .bind(taker_share_type.to_string())
-        .bind(trade.amount)
-        .bind(trade.price)
-        .execute(pool)
         .await?;
 
         Ok(())
     }
 
     /// Update shares for merge trade (redeem shares for collateral)
-    async fn update_shares_merge(pool: &PgPool, trade: &TradeEvent) -> Result<(), sqlx::Error> {
+    async fn update_shares_merge(pool: &PgPool, trade: &TradeEvent, settlement: &Settlement) -> Result<(), sqlx::Error> {
         // Both parties are sellers - each loses shares, gets collateral back
         let maker_share_type = trade.share_type.complement();
-        let taker_share_type = trade.share_type.clone();
+        let taker_share_type = trade.share_type;
 
-        // Decrease maker's shares
-        sqlx::query(
-            r#"
-            INSERT INTO shares (user_address, market_id, outcome_id, share_type, amount, avg_cost)
-            VALUES ($1, $2, $3, $4::share_type, -$5, 0)
-            ON CONFLICT (user_address, outcome_id) DO UPDATE SET
-                amount = shares.amount - $5,
-                updated_at = NOW()
-            "#
+        // Decrease maker's shares (sold at the complement price)
+        PositionService::decrease_position(
+            pool,
+            &PositionFill {
+                user_address: trade.maker_address.clone(),
+                market_id: trade.market_id,
+                outcome_id: trade.outcome_id,
+                share_type: maker_share_type,
+                amount: trade.amount,
+                price: Decimal::ONE - trade.price,
+                trade_id: Some(trade.trade_id),
+                fee: trade.maker_fee,
+                role: FillRole::Maker,
+            },
+            settlement,
         )
-        .bind(&trade.maker_address)
-        .bind(trade.market_id)
-        .bind(trade.outcome_id)
-        .bind(maker_share_type.to_string())
-        .bind(trade.amount)
-        .execute(pool)
         .await?;
 
         // Decrease taker's shares
-        sqlx::query(
-            r#"
-            INSERT INTO shares (user_address, market_id, outcome_id, share_type, amount, avg_cost)
-            VALUES ($1, $2, $3, $4::share_type, -$5, 0)
-            ON CONFLICT (user_address, outcome_id) DO UPDATE SET
-                amount = shares.amount - $5,
-                updated_at = NOW()
-            "#
+        PositionService::decrease_position(
+            pool,
+            &PositionFill {
+                user_address: trade.taker_address.clone(),
+                market_id: trade.market_id,
+                outcome_id: trade.outcome_id,
+                share_type: taker_share_type,
+                amount: trade.amount,
+                price: trade.price,
+                trade_id: Some(trade.trade_id),
+                fee: trade.taker_fee,
+                role: FillRole::Taker,
+            },
+            settlement,
         )
-        .bind(&trade.taker_address)
-        .bind(trade.market_id)
-        .bind(trade.outcome_id)
-        .bind(taker_share_type.to_string())
-        .bind(trade.amount)
-        .execute(pool)
         .await?;
 
         // TODO: Credit collateral back to both parties' balances
@@ -438,7 +545,7 @@ impl OrderFlowOrchestrator {
     }
 
     /// Record share changes for audit trail
-    async fn record_share_changes(pool: &PgPool, trade: &TradeEvent) -> Result<(), sqlx::Error> {
+    async fn record_share_changes(conn: &mut PgConnection, trade: &TradeEvent) -> Result<(), sqlx::Error> {
         let change_type = match trade.match_type {
             MatchType::Normal => if trade.side.to_lowercase() == "buy" { "buy" } else { "sell" },
             MatchType::Mint => "mint",
@@ -464,7 +571,7 @@ impl OrderFlowOrchestrator {
         .bind(trade.price)
         .bind(trade.trade_id)
         .bind(trade.maker_order_id)
-        .execute(pool)
+        .execute(&mut *conn)
         .await?;
 
         // Record taker change
@@ -486,7 +593,7 @@ impl OrderFlowOrchestrator {
         .bind(trade.price)
         .bind(trade.trade_id)
         .bind(trade.taker_order_id)
-        .execute(pool)
+        .execute(&mut *conn)
         .await?;
 
         Ok(())
@@ -563,12 +670,15 @@ impl OrderFlowOrchestrator {
             .await?;
         }
 
+        let event = OrderEvent::status(user_address, result.order_id, status, Some(result.filled_amount));
+        user_events::publish(pool, &event).await?;
+
         debug!("Persisted order: {}", result.order_id);
         Ok(())
     }
 
     /// Update order status
-    async fn update_order_status(pool: &PgPool, order_id: Uuid, status: &str) -> Result<(), sqlx::Error> {
+    async fn update_order_status(pool: &PgPool, user_address: &str, order_id: Uuid, status: &str) -> Result<(), sqlx::Error> {
         sqlx::query(
             r#"
             UPDATE orders
@@ -580,6 +690,7 @@ impl OrderFlowOrchestrator {
         .bind(order_id)
         .execute(pool)
         .await?;
+        user_events::publish(pool, &OrderEvent::status(user_address, order_id, status, None)).await?;
 
         debug!("Updated order status: id={}, status={}", order_id, status);
         Ok(())
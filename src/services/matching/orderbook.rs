@@ -225,6 +225,23 @@ impl Orderbook {
         entry
     }
 
+    /// Shrink a resting order in place, keeping its position in the queue;
+    /// returns the updated order, None if it is not resting or would grow
+    pub fn reduce_order(&self, order_id: Uuid, original_amount: Decimal, remaining_amount: Decimal) -> Option<OrderEntry> {
+        let (side, price_level) = *self.order_index.get(&order_id)?;
+        let mut book = match side {
+            Side::Buy => self.bids.write(),
+            Side::Sell => self.asks.write(),
+        };
+        let order = book.get_mut(&price_level)?.iter_mut().find(|o| o.id == order_id)?;
+        if remaining_amount <= Decimal::ZERO || remaining_amount > order.remaining_amount {
+            return None;
+        }
+        order.original_amount = original_amount;
+        order.remaining_amount = remaining_amount;
+        Some(order.clone())
+    }
+
     /// Match an incoming order against the orderbook (Normal matching)
     /// Returns (trades, remaining_amount)
     ///
@@ -421,6 +438,24 @@ impl Orderbook {
         }
     }
 
+    /// Get an orderbook snapshot with levels merged into price buckets of
+    /// `group` (see [`aggregate_levels`]); `depth` counts buckets
+    pub fn grouped_snapshot(&self, depth: usize, group: Decimal) -> OrderbookSnapshot {
+        let level = |(price_level, orders): (&PriceLevel, &VecDeque<OrderEntry>)| {
+            (price_level.to_decimal(), orders.iter().map(|o| o.remaining_amount).sum())
+        };
+        let bids = aggregate_levels(self.bids.read().iter().rev().map(level), Side::Buy, group, depth);
+        let asks = aggregate_levels(self.asks.read().iter().map(level), Side::Sell, group, depth);
+
+        OrderbookSnapshot {
+            symbol: format!("{}:{}:{}", self.market_id, self.outcome_id, self.share_type),
+            bids,
+            asks,
+            last_price: self.last_trade_price(),
+            timestamp: chrono::Utc::now().timestamp_millis(),
+        }
+    }
+
     /// Get bid depth (total bids volume)
     pub fn bid_depth(&self) -> Decimal {
         let bids = self.bids.read();
@@ -466,6 +501,16 @@ impl Orderbook {
         }
     }
 
+    /// All resting orders (bids, then asks)
+    pub fn resting_orders(&self) -> Vec<OrderEntry> {
+        let bids = self.bids.read();
+        let asks = self.asks.read();
+        bids.values()
+            .chain(asks.values())
+            .flat_map(|level| level.iter().cloned())
+            .collect()
+    }
+
     /// Get all buy orders at a specific price level
     pub fn get_bids_at_price(&self, price: Decimal) -> Vec<OrderEntry> {
         let price_level = PriceLevel::from_decimal(price);
@@ -605,6 +650,91 @@ impl Orderbook {
     }
 }
 
+// ============================================================================
+// Price Grouping
+// ============================================================================
+
+/// Decimals of a price level (see [`PriceLevel`])
+const PRICE_DECIMALS: u32 = 8;
+
+/// Check a requested price grouping: a positive step of at most 1 with no
+/// more decimals than prices carry
+pub fn validate_group(group: Decimal) -> Result<Decimal, String> {
+    let group = group.normalize();
+    if group <= Decimal::ZERO || group > Decimal::ONE || group.scale() > PRICE_DECIMALS {
+        return Err(format!(
+            "Unsupported group {}; must be between 0 and 1 with at most {} decimals",
+            group, PRICE_DECIMALS
+        ));
+    }
+    Ok(group)
+}
+
+/// Price bucket of a level: bids round down and asks up, so a bucket never
+/// looks better than the prices in it
+fn bucket(price: Decimal, side: Side, group: Decimal) -> Decimal {
+    let steps = price / group;
+    let steps = match side {
+        Side::Buy => steps.floor(),
+        Side::Sell => steps.ceil(),
+    };
+    (steps * group).normalize()
+}
+
+/// Merge `(price, amount)` levels, best first, into buckets of `group`,
+/// keeping the best `depth` buckets. Reading stops at the first level past
+/// the last bucket kept.
+pub fn aggregate_levels(
+    levels: impl IntoIterator<Item = (Decimal, Decimal)>,
+    side: Side,
+    group: Decimal,
+    depth: usize,
+) -> Vec<[String; 2]> {
+    let mut buckets: Vec<(Decimal, Decimal)> = Vec::new();
+    for (price, amount) in levels {
+        let price = bucket(price, side, group);
+        if let Some((_, total)) = buckets.last_mut().filter(|(last, _)| *last == price) {
+            *total += amount;
+        } else if buckets.len() == depth {
+            break;
+        } else {
+            buckets.push((price, amount));
+        }
+    }
+    buckets
+        .into_iter()
+        .map(|(price, amount)| [price.to_string(), amount.to_string()])
+        .collect()
+}
+
+/// Group published `[price, amount]` levels, best first; unparseable levels are skipped
+pub fn group_levels(levels: &[[String; 2]], side: Side, group: Decimal, depth: usize) -> Vec<[String; 2]> {
+    let parsed = levels
+        .iter()
+        .filter_map(|[price, amount]| Some((price.parse().ok()?, amount.parse().ok()?)));
+    aggregate_levels(parsed, side, group, depth)
+}
+
+/// Levels per side covered by [`book_checksum`]
+pub const CHECKSUM_DEPTH: usize = 25;
+
+/// CRC32 of the top [`CHECKSUM_DEPTH`] levels of a published book, for clients
+/// keeping a local book from deltas to detect drift
+///
+/// Bids and asks are interleaved best first, each level as `price:size` with
+/// the strings exactly as published, joined by `:`
+/// (`bid1_price:bid1_size:ask1_price:ask1_size:bid2_price:...`); once one side
+/// runs out the other continues alone.
+pub fn book_checksum(bids: &[[String; 2]], asks: &[[String; 2]]) -> u32 {
+    let mut fields: Vec<&str> = Vec::with_capacity(4 * CHECKSUM_DEPTH);
+    for i in 0..CHECKSUM_DEPTH {
+        for level in [bids.get(i), asks.get(i)].into_iter().flatten() {
+            fields.extend([level[0].as_str(), level[1].as_str()]);
+        }
+    }
+    crc32fast::hash(fields.join(":").as_bytes())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -865,4 +995,65 @@ mod tests {
         assert_eq!(snapshot.bids[0][1], "300"); // Total bid at 0.60 (100 + 200)
         assert_eq!(snapshot.asks[0][1], "150");
     }
+
+    #[test]
+    fn test_grouped_snapshot() {
+        let (market_key, _, _) = create_market_key();
+        let book = Orderbook::new(market_key);
+        for (price, amount, side) in [
+            (dec!(0.43), dec!(10), Side::Buy),
+            (dec!(0.41), dec!(20), Side::Buy),
+            (dec!(0.39), dec!(30), Side::Buy),
+            (dec!(0.51), dec!(5), Side::Sell),
+            (dec!(0.55), dec!(7), Side::Sell),
+            (dec!(0.61), dec!(9), Side::Sell),
+        ] {
+            book.add_order(create_test_order(Uuid::new_v4(), price, amount, side)).unwrap();
+        }
+
+        let snapshot = book.grouped_snapshot(10, dec!(0.1));
+        // Bids round down, asks up
+        assert_eq!(snapshot.bids, vec![["0.4".to_string(), "30".to_string()], ["0.3".to_string(), "30".to_string()]]);
+        assert_eq!(snapshot.asks, vec![["0.6".to_string(), "12".to_string()], ["0.7".to_string(), "9".to_string()]]);
+
+        let snapshot = book.grouped_snapshot(1, dec!(0.05));
+        assert_eq!(snapshot.bids, vec![["0.4".to_string(), "30".to_string()]]);
+        assert_eq!(snapshot.asks, vec![["0.55".to_string(), "12".to_string()]]);
+    }
+
+    #[test]
+    fn test_group_levels() {
+        let levels = vec![
+            ["0.550".to_string(), "1".to_string()],
+            ["0.549".to_string(), "2".to_string()],
+            ["0.540".to_string(), "4".to_string()],
+        ];
+        assert_eq!(
+            group_levels(&levels, Side::Buy, dec!(0.01), 10),
+            vec![["0.55".to_string(), "1".to_string()], ["0.54".to_string(), "6".to_string()]]
+        );
+        assert!(validate_group(dec!(0.010)).is_ok());
+        assert!(validate_group(dec!(0)).is_err());
+        assert!(validate_group(dec!(10)).is_err());
+        assert!(validate_group(dec!(0.000000001)).is_err());
+    }
+
+    #[test]
+    fn test_book_checksum() {
+        let level = |price: &str, size: &str| [price.to_string(), size.to_string()];
+        let bids = vec![level("0.55", "100"), level("0.54", "20")];
+        let asks = vec![level("0.56", "10")];
+        assert_eq!(
+            book_checksum(&bids, &asks),
+            crc32fast::hash(b"0.55:100:0.56:10:0.54:20")
+        );
+        assert_eq!(book_checksum(&[], &[]), crc32fast::hash(b""));
+
+        // Levels past the checksum depth do not count
+        let deep: Vec<[String; 2]> = (0..30).map(|i| level(&format!("0.{:02}", 90 - i), "1")).collect();
+        let mut deeper = deep.clone();
+        deeper[CHECKSUM_DEPTH] = level("0.01", "5");
+        assert_eq!(book_checksum(&deep, &asks), book_checksum(&deeper, &asks));
+        assert_ne!(book_checksum(&bids, &asks), book_checksum(&bids[..1], &asks));
+    }
 }
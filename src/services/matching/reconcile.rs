@@ -0,0 +1,436 @@
+//! Order Reconciliation
+//!
+//! Detects open orders the database and the in-memory engine disagree about,
+//! typically after a crash between the engine accepting an order and the
+//! database catching up (or the reverse):
+//!
+//! - **missing in engine**: active (`open` / `partially_filled`) limit orders
+//!   in the database that no orderbook holds. Resolved per
+//!   [`ReconcilePolicy`]: re-injected into the engine with their remaining
+//!   amount, or cancelled with their frozen collateral released.
+//! - **orphaned in engine**: resting engine orders with no active database
+//!   order. The database is authoritative for funds, so these are always
+//!   removed from the book.
+//!
+//! Orders younger than the grace period are ignored, as they may still be in
+//! flight between the engine and the database. The startup sweep resolves
+//! mismatches immediately; periodic sweeps only resolve orders found out of
+//! sync on two consecutive sweeps, so fills still being persisted are not
+//! mistaken for phantoms.
+
+use parking_lot::Mutex;
+use rust_decimal::Decimal;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+use super::engine::MatchingEngine;
+use super::types::{OrderEntry, OrderType, Side};
+use crate::metrics;
+use crate::services::ledger::{BalanceChange, LedgerReason, LedgerService};
+
+/// Reconciliation errors
+#[derive(Debug, thiserror::Error)]
+pub enum ReconcileError {
+    #[error("Invalid reconcile policy: {0} (expected reinject or cancel)")]
+    InvalidPolicy(String),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// How database orders missing from the engine are resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReconcilePolicy {
+    /// Put the remaining amount back on the book
+    Reinject,
+    /// Cancel the order and release its frozen collateral
+    Cancel,
+}
+
+impl std::str::FromStr for ReconcilePolicy {
+    type Err = ReconcileError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "reinject" => Ok(ReconcilePolicy::Reinject),
+            "cancel" => Ok(ReconcilePolicy::Cancel),
+            _ => Err(ReconcileError::InvalidPolicy(s.to_string())),
+        }
+    }
+}
+
+/// Reconciliation settings
+#[derive(Debug, Clone)]
+pub struct ReconcileConfig {
+    pub policy: ReconcilePolicy,
+    /// Orders younger than this are left alone
+    pub grace_secs: i64,
+    /// Collateral token released when cancelling buy orders
+    pub token: String,
+}
+
+/// Active limit order as stored in the database
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct DbOpenOrder {
+    pub id: Uuid,
+    pub user_address: String,
+    pub market_id: Uuid,
+    pub outcome_id: Uuid,
+    pub share_type: String,
+    pub side: String,
+    pub price: Decimal,
+    pub amount: Decimal,
+    pub filled_amount: Decimal,
+    /// Unix millis of the last update
+    pub updated_ms: i64,
+}
+
+impl DbOpenOrder {
+    /// Orderbook key (`market_id:outcome_id:share_type`)
+    pub fn market_key(&self) -> String {
+        format!("{}:{}:{}", self.market_id, self.outcome_id, self.share_type)
+    }
+
+    pub fn remaining_amount(&self) -> Decimal {
+        self.amount - self.filled_amount
+    }
+
+    fn is_buy(&self) -> bool {
+        self.side.eq_ignore_ascii_case("buy")
+    }
+}
+
+/// Orders the database and engine disagree about
+#[derive(Debug, Default)]
+pub struct Mismatches {
+    pub missing_in_engine: Vec<DbOpenOrder>,
+    /// (symbol, order)
+    pub orphaned_in_engine: Vec<(String, OrderEntry)>,
+}
+
+/// Outcome of a sweep
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ReconcileReport {
+    pub missing_in_engine: usize,
+    pub orphaned_in_engine: usize,
+    pub reinjected: usize,
+    pub cancelled: usize,
+    pub removed: usize,
+    pub failed: usize,
+}
+
+impl ReconcileReport {
+    pub fn is_clean(&self) -> bool {
+        self.missing_in_engine == 0 && self.orphaned_in_engine == 0
+    }
+}
+
+/// Compare database-active orders with engine resting orders, skipping
+/// anything touched within `grace_ms` of `now_ms`
+pub fn find_mismatches(
+    db_orders: Vec<DbOpenOrder>,
+    engine_orders: Vec<(String, OrderEntry)>,
+    now_ms: i64,
+    grace_ms: i64,
+) -> Mismatches {
+    let in_engine: HashSet<Uuid> = engine_orders.iter().map(|(_, order)| order.id).collect();
+    let in_db: HashSet<Uuid> = db_orders.iter().map(|order| order.id).collect();
+    let settled = |ts: i64| now_ms - ts >= grace_ms;
+
+    Mismatches {
+        missing_in_engine: db_orders
+            .into_iter()
+            .filter(|order| !in_engine.contains(&order.id) && settled(order.updated_ms))
+            .filter(|order| order.remaining_amount() > Decimal::ZERO)
+            .collect(),
+        orphaned_in_engine: engine_orders
+            .into_iter()
+            .filter(|(_, order)| !in_db.contains(&order.id) && settled(order.timestamp))
+            .collect(),
+    }
+}
+
+/// Periodic database / engine order reconciler
+pub struct OrderReconciler {
+    config: ReconcileConfig,
+    /// Orders found out of sync by the previous sweep
+    suspects: Mutex<HashSet<Uuid>>,
+}
+
+impl OrderReconciler {
+    pub fn new(config: ReconcileConfig) -> Self {
+        Self {
+            config,
+            suspects: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Run one sweep; `immediate` resolves mismatches without waiting for a
+    /// second sighting (use only when no orders are in flight, i.e. at startup)
+    pub async fn sweep(
+        &self,
+        pool: &sqlx::PgPool,
+        engine: &MatchingEngine,
+        immediate: bool,
+    ) -> Result<ReconcileReport, ReconcileError> {
+        // Snapshot the engine first: orders placed afterwards show up only in
+        // the database and are covered by the grace period
+        let engine_orders = engine.resting_orders();
+        let db_orders = Self::open_orders(pool, None).await?;
+
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        let mut mismatches = find_mismatches(db_orders, engine_orders, now_ms, self.config.grace_secs * 1000);
+
+        let mut report = ReconcileReport {
+            missing_in_engine: mismatches.missing_in_engine.len(),
+            orphaned_in_engine: mismatches.orphaned_in_engine.len(),
+            ..Default::default()
+        };
+        metrics::set_order_reconcile_last_mismatches("missing_in_engine", report.missing_in_engine);
+        metrics::set_order_reconcile_last_mismatches("orphaned_in_engine", report.orphaned_in_engine);
+
+        // Only act on orders also seen on the previous sweep
+        {
+            let mut suspects = self.suspects.lock();
+            let current: HashSet<Uuid> = mismatches
+                .missing_in_engine
+                .iter()
+                .map(|order| order.id)
+                .chain(mismatches.orphaned_in_engine.iter().map(|(_, order)| order.id))
+                .collect();
+            if !immediate {
+                mismatches.missing_in_engine.retain(|order| suspects.contains(&order.id));
+                mismatches.orphaned_in_engine.retain(|(_, order)| suspects.contains(&order.id));
+            }
+            let resolved: HashSet<Uuid> = mismatches
+                .missing_in_engine
+                .iter()
+                .map(|order| order.id)
+                .chain(mismatches.orphaned_in_engine.iter().map(|(_, order)| order.id))
+                .collect();
+            *suspects = current.difference(&resolved).copied().collect();
+        }
+
+        for (symbol, order) in mismatches.orphaned_in_engine {
+            match engine.cancel_order(&symbol, order.id, &order.user_address) {
+                Ok(true) => {
+                    report.removed += 1;
+                    metrics::record_order_reconcile_mismatch("orphaned_in_engine", "removed");
+                    tracing::warn!("Removed orphaned engine order {} on {} (no active database order)", order.id, symbol);
+                }
+                // Filled or cancelled since the snapshot
+                Ok(false) => {}
+                Err(e) => {
+                    report.failed += 1;
+                    metrics::record_order_reconcile_mismatch("orphaned_in_engine", "failed");
+                    tracing::error!("Failed to remove orphaned engine order {}: {}", order.id, e);
+                }
+            }
+        }
+
+        for order in mismatches.missing_in_engine {
+            let (action, result) = match self.config.policy {
+                ReconcilePolicy::Reinject => ("reinjected", self.reinject(engine, &order)),
+                ReconcilePolicy::Cancel => ("cancelled", self.cancel(pool, &order).await),
+            };
+            match result {
+                Ok(true) => {
+                    if action == "reinjected" {
+                        report.reinjected += 1;
+                    } else {
+                        report.cancelled += 1;
+                    }
+                    metrics::record_order_reconcile_mismatch("missing_in_engine", action);
+                    tracing::warn!(
+                        "Order {} on {} was open in the database but not in the engine: {}",
+                        order.id, order.market_key(), action
+                    );
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    report.failed += 1;
+                    metrics::record_order_reconcile_mismatch("missing_in_engine", "failed");
+                    tracing::error!("Failed to reconcile order {} ({}): {}", order.id, action, e);
+                }
+            }
+        }
+
+        if report.is_clean() {
+            tracing::debug!("Order reconciliation: database and engine in sync");
+        } else {
+            tracing::info!(
+                "Order reconciliation: {} missing in engine, {} orphaned in engine ({} reinjected, {} cancelled, {} removed, {} failed)",
+                report.missing_in_engine,
+                report.orphaned_in_engine,
+                report.reinjected,
+                report.cancelled,
+                report.removed,
+                report.failed
+            );
+        }
+        Ok(report)
+    }
+
+    /// Active limit orders in the database, optionally of one account
+    pub async fn open_orders(pool: &sqlx::PgPool, user_address: Option<&str>) -> Result<Vec<DbOpenOrder>, ReconcileError> {
+        let orders = sqlx::query_as(
+            r#"
+            SELECT id, user_address, market_id, outcome_id, share_type::text AS share_type,
+                   side::text AS side, price, amount, filled_amount,
+                   (EXTRACT(EPOCH FROM updated_at) * 1000)::BIGINT AS updated_ms
+            FROM orders
+            WHERE status IN ('open', 'partially_filled') AND order_type = 'limit'
+              AND market_id IS NOT NULL AND outcome_id IS NOT NULL
+              AND ($1::TEXT IS NULL OR user_address = $1)
+            "#,
+        )
+        .bind(user_address.map(|address| address.to_lowercase()))
+        .fetch_all(pool)
+        .await?;
+        Ok(orders)
+    }
+
+    /// Cancel all active limit orders of an account in the database, releasing
+    /// their frozen collateral. The engine is not told: run it only while no
+    /// engine holds the orders (`ztdx-admin cancel-orders` checks no server
+    /// leads), or a resting order could still fill after its collateral was
+    /// released.
+    pub async fn cancel_user_orders(&self, pool: &sqlx::PgPool, user_address: &str) -> Result<Vec<Uuid>, ReconcileError> {
+        let mut cancelled = Vec::new();
+        for order in Self::open_orders(pool, Some(user_address)).await? {
+            if self.cancel(pool, &order).await? {
+                tracing::warn!("Force-cancelled order {} of {} on {}", order.id, order.user_address, order.market_key());
+                cancelled.push(order.id);
+            }
+        }
+        Ok(cancelled)
+    }
+
+    /// Put an order's remaining amount back on the book
+    fn reinject(&self, engine: &MatchingEngine, order: &DbOpenOrder) -> Result<bool, ReconcileError> {
+        let side = if order.is_buy() { Side::Buy } else { Side::Sell };
+        match engine.restore_order(
+            order.id,
+            &order.market_key(),
+            &order.user_address,
+            side,
+            OrderType::Limit,
+            order.remaining_amount(),
+            Some(order.price),
+            1,
+        ) {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                tracing::error!("Engine rejected re-injected order {}: {}", order.id, e);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Cancel an order in the database and release its frozen collateral
+    async fn cancel(&self, pool: &sqlx::PgPool, order: &DbOpenOrder) -> Result<bool, ReconcileError> {
+        let mut tx = pool.begin().await?;
+
+        let updated = sqlx::query(
+            r#"
+            UPDATE orders SET status = 'cancelled'::order_status, updated_at = NOW()
+            WHERE id = $1 AND status IN ('open', 'partially_filled') AND filled_amount = $2
+            "#,
+        )
+        .bind(order.id)
+        .bind(order.filled_amount)
+        .execute(&mut *tx)
+        .await?;
+
+        // Filled or cancelled since the sweep read it
+        if updated.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        if order.is_buy() {
+            let change = BalanceChange::unfreeze(
+                &order.user_address,
+                &self.config.token,
+                order.remaining_amount() * order.price,
+                LedgerReason::OrderUnfreeze,
+            )
+            .reference(order.id);
+            LedgerService::apply(&mut tx, &change).await?;
+        }
+
+        tx.commit().await?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::matching::TimeInForce;
+    use rust_decimal_macros::dec;
+
+    fn db_order(id: Uuid, updated_ms: i64) -> DbOpenOrder {
+        DbOpenOrder {
+            id,
+            user_address: "0xabc".to_string(),
+            market_id: Uuid::new_v4(),
+            outcome_id: Uuid::new_v4(),
+            share_type: "yes".to_string(),
+            side: "buy".to_string(),
+            price: dec!(0.5),
+            amount: dec!(10),
+            filled_amount: dec!(0),
+            updated_ms,
+        }
+    }
+
+    fn engine_order(id: Uuid, timestamp: i64) -> (String, OrderEntry) {
+        (
+            "m:o:yes".to_string(),
+            OrderEntry {
+                id,
+                user_address: "0xabc".to_string(),
+                price: dec!(0.5),
+                original_amount: dec!(10),
+                remaining_amount: dec!(10),
+                side: Side::Buy,
+                time_in_force: TimeInForce::GTC,
+                timestamp,
+            },
+        )
+    }
+
+    #[test]
+    fn test_find_mismatches_both_directions() {
+        let (shared, db_only, engine_only) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mismatches = find_mismatches(
+            vec![db_order(shared, 0), db_order(db_only, 0)],
+            vec![engine_order(shared, 0), engine_order(engine_only, 0)],
+            60_000,
+            30_000,
+        );
+        assert_eq!(mismatches.missing_in_engine.len(), 1);
+        assert_eq!(mismatches.missing_in_engine[0].id, db_only);
+        assert_eq!(mismatches.orphaned_in_engine.len(), 1);
+        assert_eq!(mismatches.orphaned_in_engine[0].1.id, engine_only);
+    }
+
+    #[test]
+    fn test_find_mismatches_respects_grace_period() {
+        let mismatches = find_mismatches(
+            vec![db_order(Uuid::new_v4(), 50_000)],
+            vec![engine_order(Uuid::new_v4(), 50_000)],
+            60_000,
+            30_000,
+        );
+        assert!(mismatches.missing_in_engine.is_empty());
+        assert!(mismatches.orphaned_in_engine.is_empty());
+    }
+
+    #[test]
+    fn test_policy_parse() {
+        assert_eq!("reinject".parse::<ReconcilePolicy>().unwrap(), ReconcilePolicy::Reinject);
+        assert_eq!("Cancel".parse::<ReconcilePolicy>().unwrap(), ReconcilePolicy::Cancel);
+        assert!("drop".parse::<ReconcilePolicy>().is_err());
+    }
+}
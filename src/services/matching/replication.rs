@@ -0,0 +1,666 @@
+//! Warm-Standby Replication
+//!
+//! A standby process follows the primary's engine commands (orders placed,
+//! cancelled and amended, in sequence order) and applies them to its own
+//! in-memory books, so on failover it can be promoted and take over matching
+//! within seconds instead of rebuilding from snapshots and the journal.
+//!
+//! ```text
+//! primary engine ──► ReplicationLog (recent commands)
+//!                        │  GET /internal/replication/entries?after=N  (long poll)
+//!                        ▼
+//! standby ReplicationService ──► MatchingEngine::apply_replicated
+//! ```
+//!
+//! Standbys authenticate with a shared secret (`ENGINE_REPLICATION_SECRET`,
+//! the same on every engine) sent in [`REPLICATION_SECRET_HEADER`]; it does
+//! not expire the way an admin session would. A primary without a secret
+//! serves no standby. Refused requests are counted on both sides and shown in
+//! the replication status.
+//!
+//! The primary keeps the last [`ReplicationLog::DEFAULT_CAPACITY`] commands in
+//! memory; a standby further behind is served from the primary's journal when
+//! journaling is enabled. Replicated commands are applied exactly as journal
+//! replay applies them, so the standby's books match the primary's at every
+//! sequence. A standby refuses commands of its own and does not publish the
+//! trades it reproduces: the primary persisted them already.
+//!
+//! Promotion (`POST /admin/replication/promote`) stops the follower after a
+//! final best-effort catch-up, resumes trade execution ids after the last
+//! persisted trade and starts accepting commands. Fencing the old primary is
+//! left to the operator (or the load balancer).
+
+use chrono::{DateTime, Utc};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+use super::engine::MatchingEngine;
+use super::journal::{EngineJournal, JournalEntry, JournalError};
+use crate::metrics;
+
+/// Largest batch served per request
+pub const MAX_BATCH: usize = 5_000;
+
+/// Longest a request may wait for new commands
+pub const MAX_WAIT: Duration = Duration::from_secs(30);
+
+/// Header carrying the replication secret
+pub const REPLICATION_SECRET_HEADER: &str = "x-replication-secret";
+
+/// Replication errors
+#[derive(Debug, thiserror::Error)]
+pub enum ReplicationError {
+    #[error("Sequence {requested} is no longer retained (oldest available: {oldest})")]
+    BacklogExceeded { requested: u64, oldest: u64 },
+
+    #[error("Sequence {requested} is ahead of the primary (at {head})")]
+    AheadOfPrimary { requested: u64, head: u64 },
+
+    #[error("Engine is not a standby")]
+    NotStandby,
+
+    #[error("Primary unavailable: {0}")]
+    Primary(String),
+
+    #[error("Primary refused the replication secret")]
+    Unauthorized,
+
+    #[error("Replication journal error: {0}")]
+    Journal(#[from] JournalError),
+}
+
+/// Role of this engine process
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplicationRole {
+    #[default]
+    Primary,
+    Standby,
+}
+
+/// Replication configuration
+#[derive(Debug, Clone)]
+pub struct ReplicationConfig {
+    /// Role this process starts in
+    pub role: ReplicationRole,
+    /// Base URL of the primary's API (standby only)
+    pub primary_url: String,
+    /// Shared secret standbys present to the primary, and a primary expects
+    /// (empty = a primary refuses every standby)
+    pub secret: String,
+    /// Commands kept in memory for standbys
+    pub backlog: usize,
+    /// Commands requested per pull
+    pub batch_size: usize,
+    /// How long a pull waits on the primary for new commands
+    pub poll_wait: Duration,
+}
+
+impl Default for ReplicationConfig {
+    fn default() -> Self {
+        Self {
+            role: ReplicationRole::Primary,
+            primary_url: String::new(),
+            secret: String::new(),
+            backlog: ReplicationLog::DEFAULT_CAPACITY,
+            batch_size: 1_000,
+            poll_wait: Duration::from_secs(5),
+        }
+    }
+}
+
+impl ReplicationConfig {
+    /// Create config from environment variables
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            role: match std::env::var("ENGINE_REPLICATION_ROLE").map(|v| v.to_lowercase()).as_deref() {
+                Ok("standby") => ReplicationRole::Standby,
+                _ => ReplicationRole::Primary,
+            },
+            primary_url: std::env::var("ENGINE_REPLICATION_PRIMARY_URL")
+                .map(|v| v.trim_end_matches('/').to_string())
+                .unwrap_or_default(),
+            secret: std::env::var("ENGINE_REPLICATION_SECRET").unwrap_or_default(),
+            backlog: std::env::var("ENGINE_REPLICATION_BACKLOG")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.backlog),
+            batch_size: std::env::var("ENGINE_REPLICATION_BATCH")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.batch_size)
+                .clamp(1, MAX_BATCH),
+            poll_wait: std::env::var("ENGINE_REPLICATION_POLL_WAIT_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.poll_wait)
+                .min(MAX_WAIT),
+        }
+    }
+}
+
+/// Bounded in-memory log of recent sequenced commands
+pub struct ReplicationLog {
+    inner: Mutex<LogInner>,
+    capacity: usize,
+    appended: Notify,
+}
+
+struct LogInner {
+    entries: VecDeque<JournalEntry>,
+    /// Highest sequence no longer (or never) held
+    floor: u64,
+}
+
+impl ReplicationLog {
+    pub const DEFAULT_CAPACITY: usize = 100_000;
+
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Mutex::new(LogInner {
+                entries: VecDeque::new(),
+                floor: 0,
+            }),
+            capacity: capacity.max(1),
+            appended: Notify::new(),
+        }
+    }
+
+    /// Drop everything held and continue after `sequence`
+    pub fn reset(&self, sequence: u64) {
+        let mut inner = self.inner.lock();
+        inner.entries.clear();
+        inner.floor = sequence;
+    }
+
+    /// Keep an entry, evicting the oldest beyond capacity
+    pub fn push(&self, entry: JournalEntry) {
+        let mut inner = self.inner.lock();
+        if entry.sequence <= inner.floor {
+            return;
+        }
+        // Commands of different markets are sequenced concurrently, so an
+        // entry can arrive just behind its successor
+        let position = inner
+            .entries
+            .iter()
+            .rposition(|held| held.sequence < entry.sequence)
+            .map_or(0, |idx| idx + 1);
+        inner.entries.insert(position, entry);
+        while inner.entries.len() > self.capacity {
+            if let Some(evicted) = inner.entries.pop_front() {
+                inner.floor = inner.floor.max(evicted.sequence);
+            }
+        }
+        drop(inner);
+        self.appended.notify_waiters();
+    }
+
+    /// Consecutive entries after `after`, at most `limit`
+    ///
+    /// Stops at the first missing sequence; an entry still being pushed is
+    /// served by the next call.
+    pub fn entries_after(&self, after: u64, limit: usize) -> Result<Vec<JournalEntry>, ReplicationError> {
+        let inner = self.inner.lock();
+        if after < inner.floor {
+            return Err(ReplicationError::BacklogExceeded {
+                requested: after,
+                oldest: inner.floor + 1,
+            });
+        }
+        let mut batch = Vec::new();
+        let held = inner.entries.iter().skip_while(|entry| entry.sequence <= after);
+        for (entry, expected) in held.zip(after + 1..) {
+            if entry.sequence != expected || batch.len() >= limit {
+                break;
+            }
+            batch.push(entry.clone());
+        }
+        Ok(batch)
+    }
+
+    /// Like [`entries_after`](Self::entries_after), waiting up to `wait` for
+    /// an entry when none is held yet
+    pub async fn wait_after(&self, after: u64, limit: usize, wait: Duration) -> Result<Vec<JournalEntry>, ReplicationError> {
+        // Registered before looking, so a push in between still wakes us
+        let appended = self.appended.notified();
+        let batch = self.entries_after(after, limit)?;
+        if !batch.is_empty() || wait.is_zero() {
+            return Ok(batch);
+        }
+        let _ = tokio::time::timeout(wait, appended).await;
+        self.entries_after(after, limit)
+    }
+}
+
+/// Commands served to a standby
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplicationBatch {
+    /// Last sequence of the serving engine
+    pub primary_sequence: u64,
+    pub entries: Vec<JournalEntry>,
+}
+
+/// Replication state of this process
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplicationStatus {
+    pub role: ReplicationRole,
+    /// Last sequence applied by this engine
+    pub applied_sequence: u64,
+    /// Last sequence reported by the primary (standby only)
+    pub primary_sequence: Option<u64>,
+    /// Commands the standby is behind
+    pub lag_entries: u64,
+    /// Age of the newest applied command while behind (0 when caught up)
+    pub lag_ms: i64,
+    pub last_contact: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub promoted_at: Option<DateTime<Utc>>,
+    /// Requests refused for their secret since startup: standbys refused by
+    /// this primary, or this standby refused by its primary
+    pub auth_failures: u64,
+    pub last_auth_failure: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default)]
+struct FollowerState {
+    primary_sequence: Option<u64>,
+    last_applied_timestamp: Option<i64>,
+    last_contact: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    promoted_at: Option<DateTime<Utc>>,
+    auth_failures: u64,
+    last_auth_failure: Option<DateTime<Utc>>,
+}
+
+/// Serves commands to standbys and, on a standby, follows the primary
+pub struct ReplicationService {
+    engine: Arc<MatchingEngine>,
+    config: ReplicationConfig,
+    http: reqwest::Client,
+    state: RwLock<FollowerState>,
+    /// Held while applying a batch, so promotion never interleaves with one
+    apply: tokio::sync::Mutex<()>,
+    stop: CancellationToken,
+}
+
+impl ReplicationService {
+    pub fn new(engine: Arc<MatchingEngine>, config: ReplicationConfig) -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(config.poll_wait + Duration::from_secs(10))
+            .build()
+            .unwrap_or_default();
+        Self {
+            engine,
+            config,
+            http,
+            state: RwLock::new(FollowerState::default()),
+            apply: tokio::sync::Mutex::new(()),
+            stop: CancellationToken::new(),
+        }
+    }
+
+    pub fn role(&self) -> ReplicationRole {
+        if self.engine.is_standby() {
+            ReplicationRole::Standby
+        } else {
+            ReplicationRole::Primary
+        }
+    }
+
+    pub fn status(&self) -> ReplicationStatus {
+        let state = self.state.read();
+        let applied_sequence = self.engine.last_sequence();
+        let role = self.role();
+        let lag_entries = match role {
+            ReplicationRole::Standby => state.primary_sequence.unwrap_or(0).saturating_sub(applied_sequence),
+            ReplicationRole::Primary => 0,
+        };
+        let lag_ms = match state.last_applied_timestamp {
+            Some(timestamp) if lag_entries > 0 => (Utc::now().timestamp_millis() - timestamp).max(0),
+            _ => 0,
+        };
+        ReplicationStatus {
+            role,
+            applied_sequence,
+            primary_sequence: state.primary_sequence,
+            lag_entries,
+            lag_ms,
+            last_contact: state.last_contact,
+            last_error: state.last_error.clone(),
+            promoted_at: state.promoted_at,
+            auth_failures: state.auth_failures,
+            last_auth_failure: state.last_auth_failure,
+        }
+    }
+
+    /// Whether a standby's request carries the replication secret; a refusal
+    /// is counted
+    pub fn authorize(&self, presented: Option<&str>) -> bool {
+        let authorized = !self.config.secret.is_empty()
+            && presented.is_some_and(|presented| secrets_match(presented, &self.config.secret));
+        if !authorized {
+            warn!("Replication request refused: missing or wrong secret");
+            self.record_auth_failure(ReplicationRole::Primary);
+        }
+        authorized
+    }
+
+    fn record_auth_failure(&self, role: ReplicationRole) {
+        let mut state = self.state.write();
+        state.auth_failures += 1;
+        state.last_auth_failure = Some(Utc::now());
+        metrics::record_replication_auth_failure(match role {
+            ReplicationRole::Primary => "primary",
+            ReplicationRole::Standby => "standby",
+        });
+    }
+
+    /// Commands after `after` for a standby, waiting up to `wait` when there
+    /// are none yet
+    ///
+    /// Served from memory, or from the journal once the standby has fallen
+    /// out of the in-memory backlog.
+    pub async fn entries_after(&self, after: u64, limit: usize, wait: Duration) -> Result<ReplicationBatch, ReplicationError> {
+        let head = self.engine.last_sequence();
+        if after > head {
+            return Err(ReplicationError::AheadOfPrimary { requested: after, head });
+        }
+        let limit = limit.clamp(1, MAX_BATCH);
+        let entries = match self.engine.replication_log().wait_after(after, limit, wait.min(MAX_WAIT)).await {
+            Err(ReplicationError::BacklogExceeded { .. }) if self.engine.journal_enabled() => {
+                let path = self.engine.journal_path().map(|path| path.to_path_buf());
+                let entries = tokio::task::spawn_blocking(move || match path {
+                    Some(path) => EngineJournal::read_entries(&path),
+                    None => Ok(Vec::new()),
+                })
+                .await
+                .map_err(|e| ReplicationError::Primary(e.to_string()))??;
+                entries
+                    .into_iter()
+                    .skip_while(|entry| entry.sequence <= after)
+                    .take(limit)
+                    .collect()
+            }
+            result => result?,
+        };
+        Ok(ReplicationBatch {
+            primary_sequence: self.engine.last_sequence(),
+            entries,
+        })
+    }
+
+    /// Follow the primary until promoted or stopped
+    pub async fn run(self: Arc<Self>) {
+        info!(
+            "Warm standby following {} from sequence {}",
+            self.config.primary_url,
+            self.engine.last_sequence()
+        );
+        let mut backoff = Duration::from_millis(250);
+        while self.engine.is_standby() && !self.stop.is_cancelled() {
+            let pulled = tokio::select! {
+                _ = self.stop.cancelled() => break,
+                pulled = self.pull(self.config.poll_wait) => pulled,
+            };
+            match pulled {
+                Ok(_) => backoff = Duration::from_millis(250),
+                Err(e) => {
+                    warn!("Replication pull failed: {}", e);
+                    self.state.write().last_error = Some(e.to_string());
+                    tokio::select! {
+                        _ = self.stop.cancelled() => break,
+                        _ = tokio::time::sleep(backoff) => {}
+                    }
+                    backoff = (backoff * 2).min(Duration::from_secs(5));
+                }
+            }
+        }
+        info!("Replication follower stopped at sequence {}", self.engine.last_sequence());
+    }
+
+    /// Fetch and apply one batch from the primary, returning the number of
+    /// commands applied
+    async fn pull(&self, wait: Duration) -> Result<usize, ReplicationError> {
+        let after = self.engine.last_sequence();
+        let mut request = self
+            .http
+            .get(format!("{}/internal/replication/entries", self.config.primary_url))
+            .query(&[
+                ("after", after.to_string()),
+                ("limit", self.config.batch_size.to_string()),
+                ("wait_ms", wait.as_millis().to_string()),
+            ]);
+        if !self.config.secret.is_empty() {
+            request = request.header(REPLICATION_SECRET_HEADER, &self.config.secret);
+        }
+        let response = request.send().await.map_err(|e| ReplicationError::Primary(e.to_string()))?;
+        if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+            self.record_auth_failure(ReplicationRole::Standby);
+            return Err(ReplicationError::Unauthorized);
+        }
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(ReplicationError::Primary(format!("{}: {}", status, body)));
+        }
+        let batch: ReplicationBatch = response.json().await.map_err(|e| ReplicationError::Primary(e.to_string()))?;
+
+        let applied = {
+            let _apply = self.apply.lock().await;
+            // Promoted while the request was in flight: the batch is stale
+            if !self.engine.is_standby() {
+                return Ok(0);
+            }
+            self.apply_batch(&batch)?
+        };
+
+        let status = self.status();
+        metrics::set_replication_lag(status.lag_entries, status.lag_ms as f64 / 1000.0);
+        Ok(applied)
+    }
+
+    fn apply_batch(&self, batch: &ReplicationBatch) -> Result<usize, ReplicationError> {
+        let mut applied = 0;
+        let mut result = Ok(());
+        for entry in &batch.entries {
+            // Entries already applied (a retried batch) are skipped
+            if entry.sequence <= self.engine.last_sequence() {
+                continue;
+            }
+            if let Err(e) = self.engine.apply_replicated(entry) {
+                result = Err(e);
+                break;
+            }
+            applied += 1;
+        }
+
+        let mut state = self.state.write();
+        state.primary_sequence = Some(batch.primary_sequence);
+        state.last_contact = Some(Utc::now());
+        if let Some(last) = batch.entries.last() {
+            state.last_applied_timestamp = Some(last.timestamp);
+        }
+        match result {
+            Ok(()) => {
+                state.last_error = None;
+                Ok(applied)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Take over matching: catch up with the primary if it still answers,
+    /// stop following and start accepting commands
+    pub async fn promote(&self, pool: &PgPool) -> Result<ReplicationStatus, ReplicationError> {
+        if !self.engine.is_standby() {
+            return Err(ReplicationError::NotStandby);
+        }
+        self.stop.cancel();
+
+        // Final catch-up; a dead primary is the usual reason to promote
+        match self.pull(Duration::ZERO).await {
+            Ok(applied) => info!("Final replication catch-up applied {} commands", applied),
+            Err(e) => warn!("Final replication catch-up failed, promoting at sequence {}: {}", self.engine.last_sequence(), e),
+        }
+
+        let _apply = self.apply.lock().await;
+        if !self.engine.is_standby() {
+            return Err(ReplicationError::NotStandby);
+        }
+        match self.engine.load_exec_sequence(pool).await {
+            Ok(last) => info!("Trade execution ids resume after {}", last),
+            Err(e) => error!("Failed to load last trade execution id on promotion: {}", e),
+        }
+        self.engine.set_standby(false);
+        {
+            let mut state = self.state.write();
+            state.promoted_at = Some(Utc::now());
+        }
+        metrics::set_replication_lag(0, 0.0);
+        metrics::record_replication_promotion();
+        warn!("🚨 Standby promoted to primary at sequence {}", self.engine.last_sequence());
+        Ok(self.status())
+    }
+}
+
+/// Compare secrets in time independent of where they differ
+fn secrets_match(presented: &str, expected: &str) -> bool {
+    presented.len() == expected.len()
+        && presented.bytes().zip(expected.bytes()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::journal::EngineCommand;
+    use super::super::types::{OrderType, Side};
+    use super::*;
+    use rust_decimal_macros::dec;
+    use uuid::Uuid;
+
+    fn entry(sequence: u64) -> JournalEntry {
+        JournalEntry {
+            sequence,
+            timestamp: 1_700_000_000_000 + sequence as i64,
+            command: EngineCommand::Cancel {
+                symbol: "m:o:yes".to_string(),
+                order_id: Uuid::new_v4(),
+                user_address: "0x1".to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_log_serves_consecutive_entries() {
+        let log = ReplicationLog::new(3);
+        log.push(entry(1));
+        log.push(entry(3));
+
+        // 2 is still being pushed: serve up to the gap only
+        let batch = log.entries_after(0, 10).unwrap();
+        assert_eq!(batch.iter().map(|e| e.sequence).collect::<Vec<_>>(), vec![1]);
+
+        log.push(entry(2));
+        let batch = log.entries_after(0, 2).unwrap();
+        assert_eq!(batch.iter().map(|e| e.sequence).collect::<Vec<_>>(), vec![1, 2]);
+        assert!(log.entries_after(3, 10).unwrap().is_empty());
+
+        // Evicting 1 moves the floor
+        log.push(entry(4));
+        assert!(matches!(
+            log.entries_after(0, 10),
+            Err(ReplicationError::BacklogExceeded { requested: 0, oldest: 2 })
+        ));
+        assert_eq!(log.entries_after(1, 10).unwrap().len(), 3);
+
+        log.reset(10);
+        assert!(log.entries_after(4, 10).is_err());
+        assert!(log.entries_after(10, 10).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_wait_after_wakes_on_push() {
+        let log = Arc::new(ReplicationLog::new(10));
+        let pusher = log.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            pusher.push(entry(1));
+        });
+        let batch = log.wait_after(0, 10, Duration::from_secs(5)).await.unwrap();
+        assert_eq!(batch.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_standby_mirrors_primary_book() {
+        let primary = Arc::new(MatchingEngine::new());
+        let standby = Arc::new(MatchingEngine::new());
+        standby.set_standby(true);
+
+        let symbol = format!("{}:{}:yes", Uuid::new_v4(), Uuid::new_v4());
+        let resting = Uuid::new_v4();
+        primary
+            .submit_order(resting, &symbol, "0xa", Side::Sell, OrderType::Limit, dec!(10), Some(dec!(0.6)), 1)
+            .unwrap();
+        primary
+            .submit_order(Uuid::new_v4(), &symbol, "0xb", Side::Buy, OrderType::Limit, dec!(4), Some(dec!(0.6)), 1)
+            .unwrap();
+        primary
+            .submit_order(Uuid::new_v4(), &symbol, "0xc", Side::Sell, OrderType::Limit, dec!(5), Some(dec!(0.7)), 1)
+            .unwrap();
+
+        let service = ReplicationService::new(primary.clone(), ReplicationConfig::default());
+        let batch = service.entries_after(0, 100, Duration::ZERO).await.unwrap();
+        assert_eq!(batch.primary_sequence, 3);
+
+        let mut trades = standby.subscribe_trades();
+        let follower = ReplicationService::new(standby.clone(), ReplicationConfig::default());
+        assert_eq!(follower.apply_batch(&batch).unwrap(), 3);
+        // Re-applying the same batch is a no-op
+        assert_eq!(follower.apply_batch(&batch).unwrap(), 0);
+
+        assert_eq!(standby.last_sequence(), 3);
+        let (mirrored, original) = (standby.get_orderbook(&symbol, 10).unwrap(), primary.get_orderbook(&symbol, 10).unwrap());
+        assert_eq!((mirrored.bids, mirrored.asks), (original.bids, original.asks));
+        // Fills were published by the primary, not again by the standby
+        assert!(trades.try_recv().is_err());
+        assert_eq!(follower.status().lag_entries, 0);
+
+        // A standby refuses its own commands
+        assert!(matches!(
+            standby.cancel_order(&symbol, resting, "0xa"),
+            Err(super::super::types::MatchingError::Standby)
+        ));
+        assert!(matches!(
+            service.entries_after(4, 100, Duration::ZERO).await,
+            Err(ReplicationError::AheadOfPrimary { requested: 4, head: 3 })
+        ));
+    }
+
+    #[test]
+    fn test_authorize_requires_the_secret() {
+        let engine = Arc::new(MatchingEngine::new());
+        let config = ReplicationConfig {
+            secret: "s3cret".to_string(),
+            ..ReplicationConfig::default()
+        };
+        let service = ReplicationService::new(engine.clone(), config);
+        assert!(service.authorize(Some("s3cret")));
+        assert!(!service.authorize(Some("s3cret2")));
+        assert!(!service.authorize(None));
+        assert_eq!(service.status().auth_failures, 2);
+        assert!(service.status().last_auth_failure.is_some());
+
+        // Without a secret no standby is served
+        let open = ReplicationService::new(engine, ReplicationConfig::default());
+        assert!(!open.authorize(Some("")));
+    }
+}
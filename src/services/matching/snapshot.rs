@@ -0,0 +1,107 @@
+//! Orderbook Snapshots
+//!
+//! Books are snapshotted periodically into `orderbook_snapshots` (one row
+//! per symbol) so a restart restores them directly and only replays the
+//! journal entries written since, instead of the whole journal.
+//!
+//! A run only rewrites the books of outcomes that saw commands since the
+//! previous run. Snapshots are only meaningful with the journal they were
+//! taken against; recovery clears them when it falls back to a full replay
+//! or to the database.
+
+use chrono::{DateTime, TimeZone, Utc};
+use rust_decimal::Decimal;
+use sqlx::types::Json;
+use sqlx::PgPool;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::engine::MatchingEngine;
+use super::types::{BookSnapshot, OrderEntry};
+
+#[derive(sqlx::FromRow)]
+struct SnapshotRow {
+    symbol: String,
+    sequence: i64,
+    taken_at: DateTime<Utc>,
+    last_trade_price: Option<Decimal>,
+    orders: Json<Vec<OrderEntry>>,
+}
+
+impl From<SnapshotRow> for BookSnapshot {
+    fn from(row: SnapshotRow) -> Self {
+        Self {
+            symbol: row.symbol,
+            sequence: row.sequence.max(0) as u64,
+            taken_at: row.taken_at.timestamp_millis(),
+            last_trade_price: row.last_trade_price,
+            orders: row.orders.0,
+        }
+    }
+}
+
+/// Upsert book snapshots; an older snapshot never replaces a newer one
+pub async fn save(pool: &PgPool, snapshots: &[BookSnapshot]) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    for snapshot in snapshots {
+        let taken_at = Utc.timestamp_millis_opt(snapshot.taken_at).single().unwrap_or_else(Utc::now);
+        sqlx::query(
+            r#"
+            INSERT INTO orderbook_snapshots (symbol, sequence, taken_at, last_trade_price, orders)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (symbol) DO UPDATE SET
+                sequence = EXCLUDED.sequence,
+                taken_at = EXCLUDED.taken_at,
+                last_trade_price = EXCLUDED.last_trade_price,
+                orders = EXCLUDED.orders
+            WHERE orderbook_snapshots.sequence <= EXCLUDED.sequence
+            "#,
+        )
+        .bind(&snapshot.symbol)
+        .bind(snapshot.sequence as i64)
+        .bind(taken_at)
+        .bind(snapshot.last_trade_price)
+        .bind(Json(&snapshot.orders))
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await
+}
+
+pub async fn load(pool: &PgPool) -> Result<Vec<BookSnapshot>, sqlx::Error> {
+    let rows: Vec<SnapshotRow> = sqlx::query_as(
+        "SELECT symbol, sequence, taken_at, last_trade_price, orders FROM orderbook_snapshots",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(BookSnapshot::from).collect())
+}
+
+/// Drop all snapshots (they no longer match the journal)
+pub async fn clear(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    Ok(sqlx::query("DELETE FROM orderbook_snapshots").execute(pool).await?.rows_affected())
+}
+
+/// Periodic snapshot writer
+#[derive(Default)]
+pub struct BookSnapshotter {
+    /// Engine sequence when the last successful run started
+    last_run: AtomicU64,
+}
+
+impl BookSnapshotter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Snapshot the books changed since the last run; returns the number of
+    /// books written
+    pub async fn run(&self, engine: &MatchingEngine, pool: &PgPool) -> Result<usize, sqlx::Error> {
+        let started_at = engine.last_sequence();
+        let snapshots = engine.snapshot_books(self.last_run.load(Ordering::SeqCst));
+        if !snapshots.is_empty() {
+            save(pool, &snapshots).await?;
+        }
+        self.last_run.store(started_at, Ordering::SeqCst);
+        Ok(snapshots.len())
+    }
+}
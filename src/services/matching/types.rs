@@ -8,6 +8,9 @@ use std::cmp::Ordering;
 use uuid::Uuid;
 
 use crate::models::market::ShareType;
+use crate::services::market::rules::RuleViolation;
+use crate::services::matching::journal::JournalError;
+use crate::utils::request_context;
 
 // ============================================================================
 // Price Level
@@ -229,12 +232,31 @@ impl std::fmt::Display for MatchType {
     }
 }
 
+/// Why a trade was forced on its taker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ForcedTrade {
+    /// Liquidation of an under-margined position
+    Liquidation,
+    /// Auto-deleveraging against a bankrupt position
+    Adl,
+}
+
+impl std::fmt::Display for ForcedTrade {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ForcedTrade::Liquidation => write!(f, "liquidation"),
+            ForcedTrade::Adl => write!(f, "adl"),
+        }
+    }
+}
+
 // ============================================================================
 // Order Entry (in orderbook)
 // ============================================================================
 
 /// An order entry in the orderbook
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrderEntry {
     /// Order ID
     pub id: Uuid,
@@ -268,6 +290,54 @@ impl OrderEntry {
     }
 }
 
+/// Resting orders of every book, written at shutdown
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EngineSnapshot {
+    /// Sequence of the last applied command
+    pub sequence: u64,
+    /// Unix millis
+    pub taken_at: i64,
+    /// (symbol, order)
+    pub orders: Vec<(String, OrderEntry)>,
+}
+
+/// Resting orders of one book at an engine sequence, for warm restarts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookSnapshot {
+    pub symbol: String,
+    /// Every command on the book's outcome up to this sequence is included
+    pub sequence: u64,
+    /// Unix millis
+    pub taken_at: i64,
+    pub last_trade_price: Option<Decimal>,
+    /// Bids then asks, best level first, in queue order within a level
+    pub orders: Vec<OrderEntry>,
+}
+
+/// How the engine state was rebuilt at startup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoverySource {
+    /// Book snapshots plus the journal entries after them
+    Snapshot,
+    /// Full journal replay
+    Journal,
+    /// Open limit orders from the database
+    Database,
+}
+
+/// Result of [`MatchingEngine::recover`](super::MatchingEngine::recover)
+#[derive(Debug, Clone, Serialize)]
+pub struct RecoveryReport {
+    pub source: RecoverySource,
+    /// Books restored from snapshots
+    pub books: usize,
+    /// Orders restored from snapshots or the database
+    pub orders: usize,
+    /// Journal entries replayed
+    pub replayed: usize,
+}
+
 // ============================================================================
 // Trade Execution
 // ============================================================================
@@ -315,9 +385,28 @@ pub struct TradeExecution {
     pub timestamp: i64,
 }
 
+/// Current [`TradeEvent`] schema version
+///
+/// - 1: untagged; symbol, order ids, addresses, side, price, amount, timestamp
+/// - 2: `version` tag; market / outcome / share type, match type, fees,
+///   block trade flag and execution id
+/// - 3: `forced` (liquidation / ADL)
+///
+/// Changes are additive: a new field gets a default so older payloads still
+/// parse, and consumers ignore fields they do not know. Removing or changing
+/// the meaning of a field needs a new event type, not a new version.
+pub const TRADE_EVENT_VERSION: u16 = 3;
+
 /// Trade event for broadcasting
-#[derive(Debug, Clone, Serialize)]
+///
+/// Deserializes every schema version; older payloads are upgraded to the
+/// current one (see [`TRADE_EVENT_VERSION`]).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "TradeEventWire")]
 pub struct TradeEvent {
+    /// Schema version (always current once parsed)
+    pub version: u16,
+
     /// Market key (format: market_id:outcome_id:share_type)
     pub symbol: String,
 
@@ -365,6 +454,96 @@ pub struct TradeEvent {
 
     /// Trade timestamp
     pub timestamp: i64,
+
+    /// Privately matched RFQ trade (not executed on the orderbook)
+    pub is_block_trade: bool,
+
+    /// Taker order placed by a liquidation or ADL rather than its owner
+    pub forced: Option<ForcedTrade>,
+
+    /// Engine-wide execution sequence number, assigned when the trade is
+    /// published (consecutive across all symbols; 0 = not yet published)
+    pub exec_id: u64,
+
+    /// HTTP request that produced the trade, for log correlation (internal,
+    /// never sent to clients)
+    #[serde(skip)]
+    pub request_id: Option<String>,
+}
+
+/// [`TradeEvent`] as found on the wire, any version
+#[derive(Deserialize)]
+struct TradeEventWire {
+    /// Absent before version 2
+    #[serde(default = "legacy_trade_event_version")]
+    version: u16,
+    symbol: String,
+    market_id: Option<Uuid>,
+    outcome_id: Option<Uuid>,
+    share_type: Option<ShareType>,
+    match_type: Option<MatchType>,
+    trade_id: Uuid,
+    maker_order_id: Uuid,
+    taker_order_id: Uuid,
+    maker_address: String,
+    taker_address: String,
+    side: String,
+    price: Decimal,
+    amount: Decimal,
+    #[serde(default)]
+    maker_fee: Decimal,
+    #[serde(default)]
+    taker_fee: Decimal,
+    timestamp: i64,
+    #[serde(default)]
+    is_block_trade: bool,
+    #[serde(default)]
+    forced: Option<ForcedTrade>,
+    #[serde(default)]
+    exec_id: u64,
+}
+
+fn legacy_trade_event_version() -> u16 {
+    1
+}
+
+impl From<TradeEventWire> for TradeEvent {
+    fn from(wire: TradeEventWire) -> Self {
+        if wire.version > TRADE_EVENT_VERSION {
+            tracing::debug!(
+                "Trade event version {} is newer than {}; ignoring unknown fields",
+                wire.version, TRADE_EVENT_VERSION
+            );
+        }
+        // Version 1 only carried the market key
+        let (market_id, outcome_id, share_type) = match (wire.market_id, wire.outcome_id, wire.share_type) {
+            (Some(market_id), Some(outcome_id), Some(share_type)) => (market_id, outcome_id, share_type),
+            _ => OrderbookSnapshot::parse_market_key(&wire.symbol).unwrap_or((Uuid::nil(), Uuid::nil(), ShareType::Yes)),
+        };
+        Self {
+            version: TRADE_EVENT_VERSION,
+            symbol: wire.symbol,
+            market_id,
+            outcome_id,
+            share_type,
+            match_type: wire.match_type.unwrap_or(MatchType::Normal),
+            trade_id: wire.trade_id,
+            maker_order_id: wire.maker_order_id,
+            taker_order_id: wire.taker_order_id,
+            maker_address: wire.maker_address,
+            taker_address: wire.taker_address,
+            side: wire.side,
+            price: wire.price,
+            amount: wire.amount,
+            maker_fee: wire.maker_fee,
+            taker_fee: wire.taker_fee,
+            timestamp: wire.timestamp,
+            is_block_trade: wire.is_block_trade,
+            forced: wire.forced,
+            exec_id: wire.exec_id,
+            request_id: None,
+        }
+    }
 }
 
 impl TradeEvent {
@@ -386,6 +565,7 @@ impl TradeEvent {
             .unwrap_or((Uuid::nil(), Uuid::nil(), ShareType::Yes));
 
         Self {
+            version: TRADE_EVENT_VERSION,
             symbol,
             market_id,
             outcome_id,
@@ -402,12 +582,17 @@ impl TradeEvent {
             maker_fee,
             taker_fee,
             timestamp: chrono::Utc::now().timestamp_millis(),
+            is_block_trade: false,
+            forced: None,
+            exec_id: 0,
+            request_id: request_context::current_request_id(),
         }
     }
 
     /// Create a TradeEvent from a TradeExecution
     pub fn from_execution(execution: &TradeExecution, symbol: String, taker_address: String, side: Side) -> Self {
         Self {
+            version: TRADE_EVENT_VERSION,
             symbol,
             market_id: execution.market_id,
             outcome_id: execution.outcome_id,
@@ -424,6 +609,10 @@ impl TradeEvent {
             maker_fee: execution.maker_fee,
             taker_fee: execution.taker_fee,
             timestamp: execution.timestamp,
+            is_block_trade: false,
+            forced: None,
+            exec_id: 0,
+            request_id: request_context::current_request_id(),
         }
     }
 
@@ -432,6 +621,23 @@ impl TradeEvent {
         self.match_type = match_type;
         self
     }
+
+    /// Flag as a privately matched block trade
+    pub fn with_block_trade(mut self) -> Self {
+        self.is_block_trade = true;
+        self
+    }
+
+    /// Flag the taker order as placed by a liquidation or ADL
+    pub fn with_forced(mut self, forced: ForcedTrade) -> Self {
+        self.forced = Some(forced);
+        self
+    }
+
+    /// Side that took liquidity from the book (None for block trades)
+    pub fn aggressor(&self) -> Option<&str> {
+        (!self.is_block_trade).then_some(self.side.as_str())
+    }
 }
 
 // ============================================================================
@@ -449,6 +655,39 @@ pub struct MatchResult {
     pub trades: Vec<TradeExecution>,
 }
 
+/// Result of amending a resting order
+#[derive(Debug, Clone)]
+pub struct AmendResult {
+    pub order_id: Uuid,
+    /// The order kept its place in the queue (size reduced at the same price)
+    pub priority_kept: bool,
+    pub price: Decimal,
+    /// Total order amount after the amendment
+    pub amount: Decimal,
+    /// Filled before and during the amendment
+    pub filled_amount: Decimal,
+    pub remaining_amount: Decimal,
+    pub status: OrderStatus,
+    /// Fills of the replacement order when the amendment crossed the book
+    pub trades: Vec<TradeExecution>,
+}
+
+impl From<AmendResult> for MatchResult {
+    fn from(amend: AmendResult) -> Self {
+        let traded: Decimal = amend.trades.iter().map(|t| t.amount).sum();
+        let average_price = (traded > Decimal::ZERO)
+            .then(|| amend.trades.iter().map(|t| t.price * t.amount).sum::<Decimal>() / traded);
+        Self {
+            order_id: amend.order_id,
+            status: amend.status,
+            filled_amount: amend.filled_amount,
+            remaining_amount: amend.remaining_amount,
+            average_price,
+            trades: amend.trades,
+        }
+    }
+}
+
 // ============================================================================
 // Orderbook Snapshot
 // ============================================================================
@@ -487,7 +726,11 @@ impl OrderbookSnapshot {
 }
 
 /// Orderbook update event for broadcasting
-#[derive(Debug, Clone, Serialize)]
+///
+/// Carries both the full top-of-book (`bids`/`asks`) and the L2 delta against
+/// the previous update for the same symbol (`bid_changes`/`ask_changes`).
+/// Deltas contain absolute level sizes; a size of "0" removes the level.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrderbookUpdate {
     /// Market key (format: market_id:outcome_id:share_type)
     pub symbol: String,
@@ -498,10 +741,49 @@ pub struct OrderbookUpdate {
     /// Updated ask levels
     pub asks: Vec<[String; 2]>,
 
+    /// Per-symbol book sequence number of this update
+    pub sequence: u64,
+
+    /// Sequence number of the previous update for this symbol
+    pub prev_sequence: u64,
+
+    /// Bid levels changed since `prev_sequence` [price, new_size]
+    pub bid_changes: Vec<[String; 2]>,
+
+    /// Ask levels changed since `prev_sequence` [price, new_size]
+    pub ask_changes: Vec<[String; 2]>,
+
+    /// [`super::book_checksum`] of `bids` / `asks`
+    #[serde(default)]
+    pub checksum: u32,
+
     /// Update timestamp
     pub timestamp: i64,
 }
 
+/// Compute changed price levels between two depth snapshots
+///
+/// Returns `[price, size]` for every level that is new or whose size changed,
+/// and `[price, "0"]` for every level that disappeared.
+pub fn diff_levels(prev: &[[String; 2]], curr: &[[String; 2]]) -> Vec<[String; 2]> {
+    let mut changes = Vec::new();
+
+    for [price, size] in curr {
+        let unchanged = prev.iter().any(|[p, s]| p == price && s == size);
+        if !unchanged {
+            changes.push([price.clone(), size.clone()]);
+        }
+    }
+
+    for [price, _] in prev {
+        if !curr.iter().any(|[p, _]| p == price) {
+            changes.push([price.clone(), "0".to_string()]);
+        }
+    }
+
+    changes
+}
+
 // ============================================================================
 // Trade Record (for history)
 // ============================================================================
@@ -524,6 +806,9 @@ pub struct TradeRecord {
     pub maker_fee: String,
     pub taker_fee: String,
     pub timestamp: i64,
+    pub is_block_trade: bool,
+    pub forced: Option<ForcedTrade>,
+    pub exec_id: u64,
 }
 
 impl From<&TradeEvent> for TradeRecord {
@@ -544,6 +829,9 @@ impl From<&TradeEvent> for TradeRecord {
             maker_fee: event.maker_fee.to_string(),
             taker_fee: event.taker_fee.to_string(),
             timestamp: event.timestamp,
+            is_block_trade: event.is_block_trade,
+            forced: event.forced,
+            exec_id: event.exec_id,
         }
     }
 }
@@ -705,16 +993,34 @@ pub enum MatchingError {
     #[error("Market not active: {0}")]
     MarketNotActive(String),
 
+    #[error("Market outside trading session: {0}")]
+    MarketClosed(String),
+
+    #[error("Order rejected by market rules: {0}")]
+    RuleViolation(#[from] RuleViolation),
+
     #[error("Insufficient liquidity")]
     InsufficientLiquidity,
 
+    #[error("Matching engine is a warm standby")]
+    Standby,
+
     #[error("Database error: {0}")]
     DatabaseError(String),
 
+    #[error("Engine journal error: {0}")]
+    Journal(#[from] JournalError),
+
     #[error("Internal error: {0}")]
     InternalError(String),
 }
 
+impl From<sqlx::Error> for MatchingError {
+    fn from(e: sqlx::Error) -> Self {
+        MatchingError::DatabaseError(e.to_string())
+    }
+}
+
 // ============================================================================
 // Fee Configuration (Prediction Market Symmetric Fee)
 // ============================================================================
@@ -866,6 +1172,21 @@ mod tests {
         assert!(maker_fee < taker_fee);
     }
 
+    #[test]
+    fn test_diff_levels() {
+        let level = |p: &str, s: &str| [p.to_string(), s.to_string()];
+        let prev = vec![level("0.55", "100"), level("0.50", "200")];
+        let curr = vec![level("0.55", "40"), level("0.45", "10")];
+
+        let changes = diff_levels(&prev, &curr);
+        assert_eq!(changes.len(), 3);
+        assert!(changes.contains(&level("0.55", "40")));
+        assert!(changes.contains(&level("0.45", "10")));
+        assert!(changes.contains(&level("0.50", "0")));
+
+        assert!(diff_levels(&curr, &curr).is_empty());
+    }
+
     #[test]
     fn test_order_history_query() {
         let query = OrderHistoryQuery {
@@ -881,4 +1202,90 @@ mod tests {
         assert!(query.matches_status("filled"));
         assert!(!query.matches_status("open"));
     }
+
+    // Trade event schema compatibility: payloads of every version must keep parsing
+
+    const MARKET: &str = "6f1a2b3c-0000-4000-8000-000000000001";
+    const OUTCOME: &str = "6f1a2b3c-0000-4000-8000-000000000002";
+
+    #[test]
+    fn test_trade_event_v1_payload() {
+        let v1 = format!(
+            r#"{{
+                "symbol": "{MARKET}:{OUTCOME}:no",
+                "trade_id": "00000000-0000-4000-8000-00000000000a",
+                "maker_order_id": "00000000-0000-4000-8000-00000000000b",
+                "taker_order_id": "00000000-0000-4000-8000-00000000000c",
+                "maker_address": "0xmaker",
+                "taker_address": "0xtaker",
+                "side": "buy",
+                "price": "0.42",
+                "amount": "10",
+                "timestamp": 1700000000000
+            }}"#
+        );
+        let event: TradeEvent = serde_json::from_str(&v1).unwrap();
+        assert_eq!(event.version, TRADE_EVENT_VERSION);
+        assert_eq!(event.market_id.to_string(), MARKET);
+        assert_eq!(event.outcome_id.to_string(), OUTCOME);
+        assert_eq!(event.share_type, ShareType::No);
+        assert_eq!(event.match_type, MatchType::Normal);
+        assert_eq!((event.maker_fee, event.taker_fee), (Decimal::ZERO, Decimal::ZERO));
+        assert_eq!(event.price, dec!(0.42));
+        assert!(!event.is_block_trade);
+        assert_eq!((event.forced, event.aggressor()), (None, Some("buy")));
+        assert_eq!(event.exec_id, 0);
+    }
+
+    #[test]
+    fn test_trade_event_round_trip() {
+        let mut event = TradeEvent::new(
+            format!("{MARKET}:{OUTCOME}:yes"),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            "0xmaker".to_string(),
+            "0xtaker".to_string(),
+            Side::Sell,
+            dec!(0.55),
+            dec!(3),
+            dec!(0.01),
+            dec!(0.02),
+        )
+        .with_match_type(MatchType::Merge)
+        .with_block_trade()
+        .with_forced(ForcedTrade::Adl);
+        event.exec_id = 77;
+        assert_eq!(event.aggressor(), None);
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["version"], TRADE_EVENT_VERSION);
+        assert_eq!(json["forced"], "adl");
+        let parsed: TradeEvent = serde_json::from_value(json).unwrap();
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), serde_json::to_value(&event).unwrap());
+    }
+
+    #[test]
+    fn test_trade_event_newer_version() {
+        let event = TradeEvent::new(
+            format!("{MARKET}:{OUTCOME}:yes"),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            "0xmaker".to_string(),
+            "0xtaker".to_string(),
+            Side::Buy,
+            dec!(0.5),
+            dec!(1),
+            Decimal::ZERO,
+            Decimal::ZERO,
+        );
+        // A future producer adds fields; this consumer ignores them
+        let mut json = serde_json::to_value(&event).unwrap();
+        json["version"] = serde_json::json!(TRADE_EVENT_VERSION + 1);
+        json["liquidity"] = serde_json::json!("maker");
+        let parsed: TradeEvent = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.trade_id, event.trade_id);
+        assert_eq!(parsed.version, TRADE_EVENT_VERSION);
+    }
 }
@@ -0,0 +1,221 @@
+//! MM Quote Crossing Guard
+//!
+//! During fast moves the internal auto-MM can send a quote that crosses its
+//! own or the real book and prints a wash-like trade. With `mm_cross_policy`
+//! set, limit orders of the MM account (`auto_mm_test_account`) are checked
+//! against the best opposite level before they reach the engine:
+//!
+//! - `clamp`: the price is pulled back to one tick inside the opposite level
+//! - `cancel`: the MM's own conflicting quotes are cancelled first, then the
+//!   price is clamped against the liquidity that is left
+//!
+//! The opposite level includes the complement book: a YES buy at p crosses a
+//! NO buy at q when p + q >= 1 (mint), and a YES sell crosses a NO sell when
+//! p + q <= 1 (merge). A quote that cannot be clamped inside the valid price
+//! range is rejected. Prevented crossings are counted in
+//! `mm_crossings_prevented_total`.
+
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::metrics;
+use crate::services::matching::{cancel_resting_order, MatchingEngine, Side};
+
+/// Lowest and highest valid probability price
+const MIN_PRICE: Decimal = Decimal::from_parts(1, 0, 0, false, 2);
+const MAX_PRICE: Decimal = Decimal::from_parts(99, 0, 0, false, 2);
+
+/// Price step when the market has no tick size
+const DEFAULT_TICK: Decimal = Decimal::from_parts(1, 0, 0, false, 2);
+
+/// MM quote guard errors
+#[derive(Debug, thiserror::Error)]
+pub enum MmGuardError {
+    #[error("Invalid mm_cross_policy: {0} (expected off, clamp or cancel)")]
+    InvalidPolicy(String),
+
+    #[error("Quote at {price} would cross the book at {level} and cannot be clamped")]
+    WouldCross { price: Decimal, level: Decimal },
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// How crossing MM quotes are handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrossPolicy {
+    /// No check
+    Off,
+    /// Pull the price back inside the opposite level
+    Clamp,
+    /// Cancel the MM's own conflicting quotes, then clamp
+    Cancel,
+}
+
+impl std::str::FromStr for CrossPolicy {
+    type Err = MmGuardError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "off" | "" => Ok(CrossPolicy::Off),
+            "clamp" => Ok(CrossPolicy::Clamp),
+            "cancel" => Ok(CrossPolicy::Cancel),
+            _ => Err(MmGuardError::InvalidPolicy(s.to_string())),
+        }
+    }
+}
+
+/// Quote after the guard
+#[derive(Debug, Clone, PartialEq)]
+pub struct GuardedQuote {
+    pub price: Decimal,
+    /// The price was moved to avoid crossing
+    pub clamped: bool,
+    /// Own quotes cancelled to make room
+    pub cancelled: Vec<Uuid>,
+}
+
+/// Best level a quote on `side` would trade against: the same book's
+/// opposite side, or the complement book's same side mirrored around 1
+pub fn crossing_level(
+    side: Side,
+    book: (Option<Decimal>, Option<Decimal>),
+    complement: (Option<Decimal>, Option<Decimal>),
+) -> Option<Decimal> {
+    let (best_bid, best_ask) = book;
+    let (complement_bid, complement_ask) = complement;
+    match side {
+        Side::Buy => [best_ask, complement_bid.map(|bid| Decimal::ONE - bid)].into_iter().flatten().min(),
+        Side::Sell => [best_bid, complement_ask.map(|ask| Decimal::ONE - ask)].into_iter().flatten().max(),
+    }
+}
+
+/// Price of a quote kept one tick inside `level`; None when that falls
+/// outside the valid price range
+pub fn clamp_price(side: Side, price: Decimal, level: Option<Decimal>, tick: Decimal) -> Option<Decimal> {
+    let Some(level) = level else {
+        return Some(price);
+    };
+    let clamped = match side {
+        Side::Buy if price >= level => level - tick,
+        Side::Sell if price <= level => level + tick,
+        _ => return Some(price),
+    };
+    (MIN_PRICE..=MAX_PRICE).contains(&clamped).then_some(clamped)
+}
+
+/// Would a resting order at `resting_price` trade with a quote at `price`?
+fn conflicts(side: Side, price: Decimal, resting_side: Side, resting_price: Decimal, complement: bool) -> bool {
+    match (side, complement) {
+        (Side::Buy, false) => resting_side == Side::Sell && resting_price <= price,
+        (Side::Sell, false) => resting_side == Side::Buy && resting_price >= price,
+        (Side::Buy, true) => resting_side == Side::Buy && resting_price + price >= Decimal::ONE,
+        (Side::Sell, true) => resting_side == Side::Sell && resting_price + price <= Decimal::ONE,
+    }
+}
+
+pub struct MmQuoteGuard;
+
+impl MmQuoteGuard {
+    /// Check an MM limit quote on `symbol` before it is submitted
+    #[allow(clippy::too_many_arguments)]
+    pub async fn check(
+        pool: &PgPool,
+        engine: &MatchingEngine,
+        policy: CrossPolicy,
+        symbol: &str,
+        mm_address: &str,
+        side: Side,
+        price: Decimal,
+        tick_size: Option<Decimal>,
+        collateral_token: &str,
+    ) -> Result<GuardedQuote, MmGuardError> {
+        let mut quote = GuardedQuote {
+            price,
+            clamped: false,
+            cancelled: Vec::new(),
+        };
+        if policy == CrossPolicy::Off {
+            return Ok(quote);
+        }
+        let complement_symbol = MatchingEngine::get_complement_market_key(symbol);
+
+        if policy == CrossPolicy::Cancel {
+            let books = [(symbol.to_string(), false)]
+                .into_iter()
+                .chain(complement_symbol.clone().map(|complement| (complement, true)));
+            for (book_symbol, is_complement) in books {
+                let Some(book) = engine.get_orderbook_ref(&book_symbol) else {
+                    continue;
+                };
+                for order in book.resting_orders() {
+                    if order.user_address.eq_ignore_ascii_case(mm_address)
+                        && conflicts(side, price, order.side, order.price, is_complement)
+                        && cancel_resting_order(pool, engine, &book_symbol, &order, collateral_token).await?
+                    {
+                        metrics::record_mm_crossing_prevented("cancelled");
+                        quote.cancelled.push(order.id);
+                    }
+                }
+            }
+        }
+
+        let best = |book_symbol: Option<&str>| {
+            book_symbol
+                .and_then(|book_symbol| engine.get_best_prices(book_symbol).ok())
+                .unwrap_or((None, None))
+        };
+        let level = crossing_level(side, best(Some(symbol)), best(complement_symbol.as_deref()));
+        match clamp_price(side, price, level, tick_size.unwrap_or(DEFAULT_TICK)) {
+            Some(clamped) if clamped == price => Ok(quote),
+            Some(clamped) => {
+                metrics::record_mm_crossing_prevented("clamped");
+                tracing::info!("Clamped MM {:?} quote on {} from {} to {}", side, symbol, price, clamped);
+                quote.price = clamped;
+                quote.clamped = true;
+                Ok(quote)
+            }
+            None => {
+                metrics::record_mm_crossing_prevented("rejected");
+                Err(MmGuardError::WouldCross {
+                    price,
+                    level: level.unwrap_or(price),
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_clamps_inside_same_and_complement_book() {
+        // YES asks at 0.60, NO bids at 0.45 (mints with a YES buy at 0.55+)
+        let level = crossing_level(Side::Buy, (Some(dec!(0.50)), Some(dec!(0.60))), (Some(dec!(0.45)), None));
+        assert_eq!(level, Some(dec!(0.55)));
+        assert_eq!(clamp_price(Side::Buy, dec!(0.58), level, dec!(0.01)), Some(dec!(0.54)));
+        assert_eq!(clamp_price(Side::Buy, dec!(0.52), level, dec!(0.01)), Some(dec!(0.52)));
+
+        // YES bids at 0.50, NO asks at 0.45 (merges with a YES sell at 0.55-)
+        let level = crossing_level(Side::Sell, (Some(dec!(0.50)), None), (None, Some(dec!(0.45))));
+        assert_eq!(level, Some(dec!(0.55)));
+        assert_eq!(clamp_price(Side::Sell, dec!(0.53), level, dec!(0.01)), Some(dec!(0.56)));
+
+        // Nothing left inside the price range
+        assert_eq!(clamp_price(Side::Buy, dec!(0.05), Some(dec!(0.01)), dec!(0.01)), None);
+        assert_eq!(clamp_price(Side::Sell, dec!(0.30), None, dec!(0.01)), Some(dec!(0.30)));
+    }
+
+    #[test]
+    fn test_finds_conflicting_own_quotes() {
+        assert!(conflicts(Side::Buy, dec!(0.60), Side::Sell, dec!(0.58), false));
+        assert!(!conflicts(Side::Buy, dec!(0.60), Side::Sell, dec!(0.61), false));
+        assert!(conflicts(Side::Buy, dec!(0.60), Side::Buy, dec!(0.40), true));
+        assert!(!conflicts(Side::Sell, dec!(0.60), Side::Sell, dec!(0.41), true));
+        assert!("CLAMP".parse::<CrossPolicy>().is_ok() && "maybe".parse::<CrossPolicy>().is_err());
+    }
+}
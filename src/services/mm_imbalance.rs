@@ -0,0 +1,538 @@
+//! MM Imbalance Protection
+//!
+//! One-sided taker flow runs the auto-MM over: when takers keep lifting its
+//! asks it keeps selling into a move. Taker volume is tracked per symbol over
+//! a rolling window; when the imbalance `(buy - sell) / (buy + sell)` reaches
+//! the symbol's threshold (with at least `min_volume` traded), the symbol is
+//! protected for `cooldown_secs`:
+//!
+//! - both sides of the MM's limit quotes move `widen_ticks` ticks away from
+//!   the book
+//! - quotes on the exposed side (asks when takers buy, bids when they sell)
+//!   are scaled by `size_factor`
+//!
+//! Flow is tracked in the order path, next to the crossing guard, only while
+//! `mm_imbalance_protection` is on. The in-process quoter adjusts its quotes
+//! before placing them; quotes the MM account signs through the API cannot be
+//! rewritten and are refused while the symbol is protected. Parameters are per symbol
+//! (`mm_imbalance_params`, built-in defaults otherwise) and every protection
+//! triggered is logged to `mm_imbalance_decisions` with the flow that caused
+//! it and the parameters applied. A protection is not extended: if the flow
+//! is still one-sided when it expires, a new one is triggered and logged.
+
+use chrono::{DateTime, Duration, Utc};
+use parking_lot::{Mutex, RwLock};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+use crate::config::reload::ConfigStore;
+use crate::metrics;
+use crate::services::matching::{MatchingEngine, Side, TradeEvent};
+
+/// Lowest and highest valid probability price
+const MIN_PRICE: Decimal = Decimal::from_parts(1, 0, 0, false, 2);
+const MAX_PRICE: Decimal = Decimal::from_parts(99, 0, 0, false, 2);
+
+/// Imbalance protection errors
+#[derive(Debug, thiserror::Error)]
+pub enum ImbalanceError {
+    #[error("Invalid imbalance parameters: {0}")]
+    InvalidParams(String),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// Protection parameters of a symbol
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ImbalanceParams {
+    pub enabled: bool,
+    pub window_secs: i32,
+    pub threshold: Decimal,
+    pub min_volume: Decimal,
+    pub widen_ticks: i32,
+    pub size_factor: Decimal,
+    pub cooldown_secs: i32,
+}
+
+impl Default for ImbalanceParams {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window_secs: 60,
+            threshold: Decimal::new(6, 1),
+            min_volume: Decimal::from(100),
+            widen_ticks: 2,
+            size_factor: Decimal::new(5, 1),
+            cooldown_secs: 120,
+        }
+    }
+}
+
+impl ImbalanceParams {
+    pub fn validate(&self) -> Result<(), ImbalanceError> {
+        let invalid = |reason: &str| Err(ImbalanceError::InvalidParams(reason.to_string()));
+        if self.window_secs <= 0 || self.cooldown_secs <= 0 {
+            return invalid("window_secs and cooldown_secs must be positive");
+        }
+        if self.threshold <= Decimal::ZERO || self.threshold > Decimal::ONE {
+            return invalid("threshold must be in (0, 1]");
+        }
+        if self.size_factor <= Decimal::ZERO || self.size_factor > Decimal::ONE {
+            return invalid("size_factor must be in (0, 1]");
+        }
+        if self.min_volume < Decimal::ZERO || self.widen_ticks < 0 {
+            return invalid("min_volume and widen_ticks cannot be negative");
+        }
+        Ok(())
+    }
+}
+
+/// Stored parameters of a symbol
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct SymbolParams {
+    pub symbol: String,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub params: ImbalanceParams,
+    pub updated_by: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Taker flow of a symbol over its window
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct TakerFlow {
+    pub buy_volume: Decimal,
+    pub sell_volume: Decimal,
+    /// (buy - sell) / (buy + sell), in [-1, 1]
+    pub imbalance: Decimal,
+}
+
+/// Imbalance of taker buy and sell volume (None without volume)
+pub fn flow_imbalance(buy_volume: Decimal, sell_volume: Decimal) -> Option<Decimal> {
+    let total = buy_volume + sell_volume;
+    (!total.is_zero()).then(|| ((buy_volume - sell_volume) / total).round_dp(4))
+}
+
+/// An active protection
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Protection {
+    pub symbol: String,
+    /// Side of the MM's quotes being hit
+    pub exposed_side: Side,
+    pub flow: TakerFlow,
+    pub params: ImbalanceParams,
+    pub triggered_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// An MM quote after protection
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdjustedQuote {
+    pub price: Decimal,
+    pub amount: Decimal,
+}
+
+/// Widen a quote and scale it down on the exposed side
+///
+/// The scaled size is rounded down to the lot size but never below one lot
+/// (nor above the original size).
+pub fn adjust_quote(
+    protection: &Protection,
+    side: Side,
+    price: Decimal,
+    amount: Decimal,
+    tick: Decimal,
+    lot: Option<Decimal>,
+) -> AdjustedQuote {
+    let widen = tick * Decimal::from(protection.params.widen_ticks);
+    let price = match side {
+        Side::Buy => price - widen,
+        Side::Sell => price + widen,
+    }
+    .clamp(MIN_PRICE, MAX_PRICE);
+
+    let amount = if side == protection.exposed_side {
+        let scaled = amount * protection.params.size_factor;
+        match lot {
+            Some(lot) if lot > Decimal::ZERO => ((scaled / lot).floor() * lot).max(lot).min(amount),
+            _ => scaled,
+        }
+    } else {
+        amount
+    };
+    AdjustedQuote { price, amount }
+}
+
+/// Recent taker trades of a symbol: (time in millis, taker buys, amount)
+type Flow = VecDeque<(i64, bool, Decimal)>;
+
+/// Imbalance protection of the auto-MM
+#[derive(Default)]
+pub struct MmImbalanceService {
+    flows: Mutex<HashMap<String, Flow>>,
+    /// Per-symbol parameters (defaults for symbols without a row)
+    params: RwLock<HashMap<String, ImbalanceParams>>,
+    protections: RwLock<HashMap<String, Protection>>,
+}
+
+impl MmImbalanceService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parameters of a symbol
+    pub fn params_for(&self, symbol: &str) -> ImbalanceParams {
+        self.params.read().get(symbol).cloned().unwrap_or_default()
+    }
+
+    /// Load stored parameters; returns the number of symbols configured
+    pub async fn load_params(&self, pool: &PgPool) -> Result<usize, ImbalanceError> {
+        let rows = Self::list_params(pool).await?;
+        let count = rows.len();
+        *self.params.write() = rows.into_iter().map(|row| (row.symbol, row.params)).collect();
+        Ok(count)
+    }
+
+    /// Stored parameters of every configured symbol
+    pub async fn list_params(pool: &PgPool) -> Result<Vec<SymbolParams>, ImbalanceError> {
+        let rows = sqlx::query_as(
+            r#"
+            SELECT symbol, enabled, window_secs, threshold, min_volume, widen_ticks, size_factor,
+                   cooldown_secs, updated_by, updated_at
+            FROM mm_imbalance_params
+            ORDER BY symbol
+            "#,
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
+    }
+
+    /// Store a symbol's parameters and apply them
+    pub async fn set_params(
+        &self,
+        pool: &PgPool,
+        symbol: &str,
+        params: &ImbalanceParams,
+        updated_by: &str,
+    ) -> Result<SymbolParams, ImbalanceError> {
+        params.validate()?;
+        let row: SymbolParams = sqlx::query_as(
+            r#"
+            INSERT INTO mm_imbalance_params (
+                symbol, enabled, window_secs, threshold, min_volume, widen_ticks, size_factor,
+                cooldown_secs, updated_by, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, NOW())
+            ON CONFLICT (symbol) DO UPDATE SET
+                enabled = EXCLUDED.enabled,
+                window_secs = EXCLUDED.window_secs,
+                threshold = EXCLUDED.threshold,
+                min_volume = EXCLUDED.min_volume,
+                widen_ticks = EXCLUDED.widen_ticks,
+                size_factor = EXCLUDED.size_factor,
+                cooldown_secs = EXCLUDED.cooldown_secs,
+                updated_by = EXCLUDED.updated_by,
+                updated_at = NOW()
+            RETURNING symbol, enabled, window_secs, threshold, min_volume, widen_ticks, size_factor,
+                      cooldown_secs, updated_by, updated_at
+            "#,
+        )
+        .bind(symbol)
+        .bind(params.enabled)
+        .bind(params.window_secs)
+        .bind(params.threshold)
+        .bind(params.min_volume)
+        .bind(params.widen_ticks)
+        .bind(params.size_factor)
+        .bind(params.cooldown_secs)
+        .bind(updated_by)
+        .fetch_one(pool)
+        .await?;
+
+        self.params.write().insert(symbol.to_string(), params.clone());
+        if !params.enabled {
+            self.protections.write().remove(symbol);
+        }
+        Ok(row)
+    }
+
+    /// Add a trade to its symbol's flow; returns the protection it triggers
+    pub fn record(&self, trade: &TradeEvent, now: DateTime<Utc>) -> Option<Protection> {
+        if trade.is_block_trade {
+            return None;
+        }
+        let params = self.params_for(&trade.symbol);
+        let since = (now - Duration::seconds(params.window_secs.into())).timestamp_millis();
+
+        let flow = {
+            let mut flows = self.flows.lock();
+            let trades = flows.entry(trade.symbol.clone()).or_default();
+            trades.push_back((trade.timestamp, trade.side == "buy", trade.amount));
+            while trades.front().is_some_and(|&(at, _, _)| at < since) {
+                trades.pop_front();
+            }
+            let mut buy_volume = Decimal::ZERO;
+            let mut sell_volume = Decimal::ZERO;
+            for &(_, is_buy, amount) in trades.iter() {
+                if is_buy {
+                    buy_volume += amount;
+                } else {
+                    sell_volume += amount;
+                }
+            }
+            TakerFlow {
+                buy_volume,
+                sell_volume,
+                imbalance: flow_imbalance(buy_volume, sell_volume)?,
+            }
+        };
+
+        if !params.enabled
+            || flow.buy_volume + flow.sell_volume < params.min_volume
+            || flow.imbalance.abs() < params.threshold
+            || self.protection(&trade.symbol, now).is_some()
+        {
+            return None;
+        }
+
+        let protection = Protection {
+            symbol: trade.symbol.clone(),
+            exposed_side: if flow.imbalance > Decimal::ZERO { Side::Sell } else { Side::Buy },
+            flow,
+            expires_at: now + Duration::seconds(params.cooldown_secs.into()),
+            params,
+            triggered_at: now,
+        };
+        self.protections.write().insert(trade.symbol.clone(), protection.clone());
+        Some(protection)
+    }
+
+    /// Active protection of a symbol
+    pub fn protection(&self, symbol: &str, now: DateTime<Utc>) -> Option<Protection> {
+        let active = self.protections.read().get(symbol).cloned()?;
+        if active.expires_at > now {
+            return Some(active);
+        }
+        self.protections.write().remove(symbol);
+        None
+    }
+
+    /// Every active protection
+    pub fn active(&self, now: DateTime<Utc>) -> Vec<Protection> {
+        let mut active: Vec<Protection> =
+            self.protections.read().values().filter(|p| p.expires_at > now).cloned().collect();
+        active.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        active
+    }
+
+    /// Log a triggered protection
+    pub async fn log_decision(pool: &PgPool, protection: &Protection) -> Result<(), ImbalanceError> {
+        sqlx::query(
+            r#"
+            INSERT INTO mm_imbalance_decisions (
+                symbol, exposed_side, imbalance, buy_volume, sell_volume, window_secs, threshold,
+                widen_ticks, size_factor, triggered_at, expires_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            "#,
+        )
+        .bind(&protection.symbol)
+        .bind(if protection.exposed_side == Side::Buy { "buy" } else { "sell" })
+        .bind(protection.flow.imbalance)
+        .bind(protection.flow.buy_volume)
+        .bind(protection.flow.sell_volume)
+        .bind(protection.params.window_secs)
+        .bind(protection.params.threshold)
+        .bind(protection.params.widen_ticks)
+        .bind(protection.params.size_factor)
+        .bind(protection.triggered_at)
+        .bind(protection.expires_at)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Logged protections, newest first
+    pub async fn decisions(
+        pool: &PgPool,
+        symbol: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<ImbalanceDecision>, ImbalanceError> {
+        let rows = sqlx::query_as(
+            r#"
+            SELECT id, symbol, exposed_side, imbalance, buy_volume, sell_volume, window_secs, threshold,
+                   widen_ticks, size_factor, triggered_at, expires_at
+            FROM mm_imbalance_decisions
+            WHERE ($1::text IS NULL OR symbol = $1)
+            ORDER BY triggered_at DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(symbol)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
+    }
+}
+
+/// A logged protection
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct ImbalanceDecision {
+    pub id: i64,
+    pub symbol: String,
+    pub exposed_side: String,
+    pub imbalance: Decimal,
+    pub buy_volume: Decimal,
+    pub sell_volume: Decimal,
+    pub window_secs: i32,
+    pub threshold: Decimal,
+    pub widen_ticks: i32,
+    pub size_factor: Decimal,
+    pub triggered_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Load the per-symbol parameters, then track taker flow from the engine
+/// trade stream and log every protection triggered
+pub fn spawn(
+    service: Arc<MmImbalanceService>,
+    engine: Arc<MatchingEngine>,
+    live_config: Arc<ConfigStore>,
+    pool: PgPool,
+) {
+    let mut trades = engine.subscribe_trades();
+    tokio::spawn(async move {
+        match service.load_params(&pool).await {
+            Ok(symbols) => tracing::info!("MM imbalance parameters loaded ({} symbols)", symbols),
+            Err(e) => tracing::error!("Failed to load MM imbalance parameters, using defaults: {}", e),
+        }
+        loop {
+            match trades.recv().await {
+                Ok(trade) => {
+                    if !live_config.current().mm_imbalance_protection {
+                        continue;
+                    }
+                    let Some(protection) = service.record(&trade, Utc::now()) else {
+                        continue;
+                    };
+                    metrics::record_mm_imbalance_protection(&protection.symbol);
+                    tracing::info!(
+                        "MM imbalance protection on {}: imbalance {} (buy {}, sell {}), {:?} side exposed until {}",
+                        protection.symbol,
+                        protection.flow.imbalance,
+                        protection.flow.buy_volume,
+                        protection.flow.sell_volume,
+                        protection.exposed_side,
+                        protection.expires_at
+                    );
+                    if let Err(e) = MmImbalanceService::log_decision(&pool, &protection).await {
+                        tracing::error!("Failed to log MM imbalance decision on {}: {}", protection.symbol, e);
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    tracing::warn!("MM imbalance tracker lagged by {} trades", n);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+        tracing::warn!("MM imbalance tracker stopped");
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use uuid::Uuid;
+
+    fn trade(side: Side, amount: Decimal, at: DateTime<Utc>) -> TradeEvent {
+        let mut trade = TradeEvent::new(
+            "m:o:yes".to_string(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            "0xmaker".to_string(),
+            "0xtaker".to_string(),
+            side,
+            dec!(0.5),
+            amount,
+            Decimal::ZERO,
+            Decimal::ZERO,
+        );
+        trade.timestamp = at.timestamp_millis();
+        trade
+    }
+
+    #[test]
+    fn test_triggers_on_one_sided_flow() {
+        let service = MmImbalanceService::new();
+        let now = Utc::now();
+
+        // Below min_volume, then balanced
+        assert!(service.record(&trade(Side::Buy, dec!(60), now), now).is_none());
+        assert!(service.record(&trade(Side::Sell, dec!(50), now), now).is_none());
+
+        // 260 bought vs 50 sold: imbalance 0.6774
+        let protection = service.record(&trade(Side::Buy, dec!(200), now), now).unwrap();
+        assert_eq!(protection.exposed_side, Side::Sell);
+        assert_eq!(protection.flow.imbalance, dec!(0.6774));
+        assert_eq!(protection.expires_at, now + Duration::seconds(120));
+
+        // Not re-triggered while active; gone after the cooldown
+        assert!(service.record(&trade(Side::Buy, dec!(100), now), now).is_none());
+        assert!(service.protection("m:o:yes", now + Duration::seconds(119)).is_some());
+        assert!(service.protection("m:o:yes", now + Duration::seconds(120)).is_none());
+    }
+
+    #[test]
+    fn test_old_trades_leave_the_window() {
+        let service = MmImbalanceService::new();
+        let now = Utc::now();
+        service.record(&trade(Side::Sell, dec!(500), now - Duration::seconds(61)), now - Duration::seconds(61));
+        assert!(service.protection("m:o:yes", now).is_some());
+
+        // The old sells no longer count against the new buys
+        let later = now + Duration::seconds(200);
+        let protection = service.record(&trade(Side::Buy, dec!(150), later), later).unwrap();
+        assert_eq!((protection.exposed_side, protection.flow.sell_volume), (Side::Sell, Decimal::ZERO));
+    }
+
+    #[test]
+    fn test_adjusts_quotes() {
+        let protection = Protection {
+            symbol: "m:o:yes".to_string(),
+            exposed_side: Side::Sell,
+            flow: TakerFlow {
+                buy_volume: dec!(300),
+                sell_volume: Decimal::ZERO,
+                imbalance: Decimal::ONE,
+            },
+            params: ImbalanceParams::default(),
+            triggered_at: Utc::now(),
+            expires_at: Utc::now(),
+        };
+        let tick = dec!(0.01);
+
+        // Exposed asks: wider and halved (to the lot); bids only wider
+        let ask = adjust_quote(&protection, Side::Sell, dec!(0.55), dec!(15), tick, Some(dec!(5)));
+        assert_eq!((ask.price, ask.amount), (dec!(0.57), dec!(5)));
+        let bid = adjust_quote(&protection, Side::Buy, dec!(0.45), dec!(15), tick, Some(dec!(5)));
+        assert_eq!((bid.price, bid.amount), (dec!(0.43), dec!(15)));
+
+        // Never below one lot or outside the price range
+        let ask = adjust_quote(&protection, Side::Sell, dec!(0.98), dec!(1), tick, Some(dec!(1)));
+        assert_eq!((ask.price, ask.amount), (dec!(0.99), dec!(1)));
+
+        let mut params = ImbalanceParams::default();
+        assert!(params.validate().is_ok());
+        params.size_factor = Decimal::ZERO;
+        assert!(params.validate().is_err());
+    }
+}
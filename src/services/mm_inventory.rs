@@ -0,0 +1,335 @@
+//! Market Maker Inventory
+//!
+//! Per-symbol inventory report for the internal auto-MM account: net
+//! position and average entry (`shares`), unrealized PnL at the mark price,
+//! quote uptime, fill ratio of its orders, and a suggested hedge priced at
+//! the external index.
+//!
+//! Quote uptime is sampled in memory (the `mm_quote_sampler` job): a sample
+//! counts as quoted when the account rests both a bid and an ask on the
+//! symbol. It covers the time since the process started.
+//!
+//! The suggested hedge only sells the excess of one side of an outcome over
+//! the other: a YES share paired with a NO share is worth exactly 1 at
+//! settlement and carries no risk.
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::{BTreeSet, HashMap};
+use uuid::Uuid;
+
+use crate::services::market::mark_price::MarkPriceService;
+use crate::services::matching::{MatchingEngine, Side};
+
+/// Inventory errors
+#[derive(Debug, thiserror::Error)]
+pub enum InventoryError {
+    #[error("Market maker account not configured")]
+    NoAccount,
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// Quote samples of one symbol
+#[derive(Debug, Clone, Copy, Default)]
+pub struct QuoteSamples {
+    pub samples: u64,
+    /// Samples with both a bid and an ask resting
+    pub two_sided: u64,
+}
+
+impl QuoteSamples {
+    pub fn uptime(&self) -> Option<Decimal> {
+        (self.samples > 0).then(|| (Decimal::from(self.two_sided) / Decimal::from(self.samples)).round_dp(4))
+    }
+}
+
+/// Position of the account in one symbol
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct PositionRow {
+    pub market_id: Uuid,
+    pub outcome_id: Uuid,
+    pub share_type: String,
+    pub amount: Decimal,
+    pub avg_cost: Decimal,
+}
+
+/// Order volume of the account in one symbol
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct FillRow {
+    pub market_id: Uuid,
+    pub outcome_id: Uuid,
+    pub share_type: String,
+    pub placed: Decimal,
+    pub filled: Decimal,
+}
+
+fn symbol(market_id: Uuid, outcome_id: Uuid, share_type: &str) -> String {
+    format!("{}:{}:{}", market_id, outcome_id, share_type)
+}
+
+/// Symbol of the other share type of the same outcome
+fn complement(symbol: &str) -> Option<String> {
+    let (outcome, share_type) = symbol.rsplit_once(':')?;
+    let other = match share_type {
+        "yes" => "no",
+        "no" => "yes",
+        _ => return None,
+    };
+    Some(format!("{}:{}", outcome, other))
+}
+
+/// One symbol of the report
+#[derive(Debug, Clone, Serialize)]
+pub struct InventoryLine {
+    pub symbol: String,
+    pub net_position: Decimal,
+    pub avg_entry: Decimal,
+    pub mark_price: Option<Decimal>,
+    pub unrealized_pnl: Option<Decimal>,
+    /// Share of samples with a two-sided quote
+    pub quote_uptime: Option<Decimal>,
+    /// Filled / placed amount of orders in the window
+    pub fill_ratio: Option<Decimal>,
+    pub index_price: Option<Decimal>,
+    /// Shares to trade in this symbol (negative sells)
+    pub suggested_hedge: Decimal,
+    /// |suggested_hedge| at the index price
+    pub hedge_notional: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InventoryReport {
+    pub address: String,
+    pub window_hours: i64,
+    pub sampling_since: DateTime<Utc>,
+    pub lines: Vec<InventoryLine>,
+}
+
+/// Prices of one symbol used by the report
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SymbolPrices {
+    pub mark: Option<Decimal>,
+    pub index: Option<Decimal>,
+}
+
+/// Combine positions, fills, quote samples and prices into report lines
+pub fn build_lines(
+    positions: &[PositionRow],
+    fills: &[FillRow],
+    quotes: &HashMap<String, QuoteSamples>,
+    prices: &HashMap<String, SymbolPrices>,
+) -> Vec<InventoryLine> {
+    let held: HashMap<String, &PositionRow> = positions
+        .iter()
+        .map(|p| (symbol(p.market_id, p.outcome_id, &p.share_type), p))
+        .collect();
+    let filled: HashMap<String, &FillRow> = fills
+        .iter()
+        .map(|f| (symbol(f.market_id, f.outcome_id, &f.share_type), f))
+        .collect();
+
+    // Symbols the account holds, traded or quoted
+    let mut symbols: BTreeSet<String> = BTreeSet::new();
+    symbols.extend(held.keys().cloned());
+    symbols.extend(filled.keys().cloned());
+    symbols.extend(quotes.iter().filter(|(_, q)| q.two_sided > 0).map(|(s, _)| s.clone()));
+
+    symbols
+        .into_iter()
+        .map(|sym| {
+            let position = held.get(&sym);
+            let net_position = position.map(|p| p.amount).unwrap_or_default();
+            let avg_entry = position.map(|p| p.avg_cost).unwrap_or_default();
+            let price = prices.get(&sym).copied().unwrap_or_default();
+            let mark_price = price.mark.or(price.index);
+
+            let paired = complement(&sym)
+                .and_then(|c| held.get(&c).map(|p| p.amount))
+                .unwrap_or_default();
+            let excess = net_position - paired;
+            let suggested_hedge = if excess > Decimal::ZERO { -excess } else { Decimal::ZERO };
+
+            InventoryLine {
+                unrealized_pnl: mark_price.map(|mark| ((mark - avg_entry) * net_position).round_dp(8)),
+                quote_uptime: quotes.get(&sym).and_then(QuoteSamples::uptime),
+                fill_ratio: filled
+                    .get(&sym)
+                    .filter(|f| f.placed > Decimal::ZERO)
+                    .map(|f| (f.filled / f.placed).round_dp(4)),
+                index_price: price.index,
+                hedge_notional: price.index.map(|index| (suggested_hedge.abs() * index).round_dp(8)),
+                suggested_hedge,
+                symbol: sym,
+                net_position,
+                avg_entry,
+                mark_price,
+            }
+        })
+        .collect()
+}
+
+/// Quote uptime tracker and inventory report for the MM account
+pub struct MmInventoryService {
+    quotes: DashMap<String, QuoteSamples>,
+    started_at: DateTime<Utc>,
+}
+
+impl Default for MmInventoryService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MmInventoryService {
+    pub fn new() -> Self {
+        Self {
+            quotes: DashMap::new(),
+            started_at: Utc::now(),
+        }
+    }
+
+    /// Record whether the account quotes both sides of each orderbook
+    pub fn sample(&self, engine: &MatchingEngine, address: &str) {
+        let mut sides: HashMap<String, (bool, bool)> = HashMap::new();
+        for (sym, order) in engine.resting_orders() {
+            if !order.user_address.eq_ignore_ascii_case(address) {
+                continue;
+            }
+            let entry = sides.entry(sym).or_default();
+            match order.side {
+                Side::Buy => entry.0 = true,
+                Side::Sell => entry.1 = true,
+            }
+        }
+
+        for sym in engine.orderbook_symbols() {
+            let two_sided = sides.get(&sym).is_some_and(|&(bid, ask)| bid && ask);
+            let mut samples = self.quotes.entry(sym).or_default();
+            samples.samples += 1;
+            if two_sided {
+                samples.two_sided += 1;
+            }
+        }
+    }
+
+    /// Inventory report of an account, with fill ratios over `window_hours`
+    pub async fn report(
+        &self,
+        pool: &PgPool,
+        engine: &MatchingEngine,
+        mark_prices: &MarkPriceService,
+        address: &str,
+        window_hours: i64,
+    ) -> Result<InventoryReport, InventoryError> {
+        if address.is_empty() {
+            return Err(InventoryError::NoAccount);
+        }
+        let address = address.to_lowercase();
+
+        let positions: Vec<PositionRow> = sqlx::query_as(
+            r#"
+            SELECT market_id, outcome_id, share_type::text AS share_type, amount, avg_cost
+            FROM shares
+            WHERE user_address = $1 AND amount <> 0
+            "#,
+        )
+        .bind(&address)
+        .fetch_all(pool)
+        .await?;
+
+        let fills: Vec<FillRow> = sqlx::query_as(
+            r#"
+            SELECT market_id, outcome_id, share_type::text AS share_type,
+                   SUM(amount) AS placed, SUM(filled_amount) AS filled
+            FROM orders
+            WHERE user_address = $1
+              AND market_id IS NOT NULL AND outcome_id IS NOT NULL
+              AND created_at >= NOW() - make_interval(hours => $2::INT)
+            GROUP BY market_id, outcome_id, share_type
+            "#,
+        )
+        .bind(&address)
+        .bind(window_hours as i32)
+        .fetch_all(pool)
+        .await?;
+
+        let quotes: HashMap<String, QuoteSamples> =
+            self.quotes.iter().map(|e| (e.key().clone(), *e.value())).collect();
+
+        let mut prices = HashMap::new();
+        let symbols = positions
+            .iter()
+            .map(|p| symbol(p.market_id, p.outcome_id, &p.share_type))
+            .chain(fills.iter().map(|f| symbol(f.market_id, f.outcome_id, &f.share_type)))
+            .chain(quotes.iter().filter(|(_, q)| q.two_sided > 0).map(|(s, _)| s.clone()));
+        for sym in symbols {
+            if prices.contains_key(&sym) {
+                continue;
+            }
+            let price = SymbolPrices {
+                mark: engine.mark_price(&sym),
+                index: mark_prices.symbol_index_price(&sym).await,
+            };
+            prices.insert(sym, price);
+        }
+
+        Ok(InventoryReport {
+            address,
+            window_hours,
+            sampling_since: self.started_at,
+            lines: build_lines(&positions, &fills, &quotes, &prices),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_reports_pnl_uptime_and_hedges_unpaired_shares() {
+        let market = Uuid::new_v4();
+        let outcome = Uuid::new_v4();
+        let positions = vec![
+            PositionRow { market_id: market, outcome_id: outcome, share_type: "yes".into(), amount: dec!(100), avg_cost: dec!(0.40) },
+            PositionRow { market_id: market, outcome_id: outcome, share_type: "no".into(), amount: dec!(30), avg_cost: dec!(0.55) },
+        ];
+        let fills = vec![FillRow {
+            market_id: market,
+            outcome_id: outcome,
+            share_type: "yes".into(),
+            placed: dec!(200),
+            filled: dec!(50),
+        }];
+        let yes = symbol(market, outcome, "yes");
+        let no = symbol(market, outcome, "no");
+        let quotes = HashMap::from([(yes.clone(), QuoteSamples { samples: 4, two_sided: 3 })]);
+        let prices = HashMap::from([
+            (yes.clone(), SymbolPrices { mark: Some(dec!(0.50)), index: Some(dec!(0.48)) }),
+            (no.clone(), SymbolPrices { mark: None, index: Some(dec!(0.52)) }),
+        ]);
+
+        let lines = build_lines(&positions, &fills, &quotes, &prices);
+        let line = |s: &str| lines.iter().find(|l| l.symbol == s).unwrap();
+
+        let yes_line = line(&yes);
+        assert_eq!(yes_line.unrealized_pnl, Some(dec!(10)));
+        assert_eq!(yes_line.quote_uptime, Some(dec!(0.75)));
+        assert_eq!(yes_line.fill_ratio, Some(dec!(0.25)));
+        // 30 YES are paired with the NO shares; the other 70 are sold
+        assert_eq!(yes_line.suggested_hedge, dec!(-70));
+        assert_eq!(yes_line.hedge_notional, Some(dec!(33.6)));
+
+        // Marked at the index when there is no mark price
+        let no_line = line(&no);
+        assert_eq!(no_line.mark_price, Some(dec!(0.52)));
+        assert_eq!(no_line.unrealized_pnl, Some(dec!(-0.9)));
+        assert_eq!(no_line.suggested_hedge, Decimal::ZERO);
+    }
+}
@@ -0,0 +1,563 @@
+//! MM Liquidity Mirroring
+//!
+//! Optional mode of the quoting engine ([`crate::services::mm_quoter`]): a
+//! symbol with a mirror book is quoted from an external venue's public depth
+//! stream instead of its ladder, so thin internal markets show realistic
+//! depth. The top `levels` levels of each side are copied with
+//!
+//! - sizes scaled by `size_factor` (and capped at `max_level_size`)
+//! - a defensive markup: bids `markup` lower, asks `markup` higher, rounded
+//!   away from the book to the tick
+//!
+//! The symbol's quoting parameters still apply their position and loss
+//! limits, and its quotes are rebuilt whenever the mirrored ladder changes.
+//!
+//! Kill switch: every frame from the venue (pongs to the feed's own pings
+//! included) refreshes the book's receive time, and a dropped connection
+//! clears the book. A book not heard from for `mm_mirror_stale_secs` is
+//! stale, and the symbol's quotes are pulled until the feed is back.
+//!
+//! Books are configured as JSON (`mm_mirror_books`) in one of two formats:
+//!
+//! - `polymarket`: the CLOB market channel (`book` snapshots and
+//!   `price_change` updates) of the asset `market`; `url` defaults to the
+//!   public endpoint
+//! - `depth`: full snapshots of `[price, size]` levels (`bids` / `asks`, at
+//!   the top level or under `data`), e.g. Binance or OKX partial depth
+//!   streams, with an optional `subscribe` message sent on connect
+//!
+//! ```json
+//! [{ "symbol": "{market_id}:{outcome_id}:yes", "format": "polymarket",
+//!    "market": "<asset id>", "size_factor": 0.1, "markup": 0.01, "levels": 5 }]
+//! ```
+
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use futures::{SinkExt, StreamExt};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::services::matching::Side;
+use crate::services::mm_quoter::{Quote, MAX_LEVELS};
+
+/// Lowest and highest valid probability price
+const MIN_PRICE: Decimal = Decimal::from_parts(1, 0, 0, false, 2);
+const MAX_PRICE: Decimal = Decimal::from_parts(99, 0, 0, false, 2);
+
+/// Public market channel of the Polymarket CLOB
+const POLYMARKET_WS_URL: &str = "wss://ws-subscriptions-clob.polymarket.com/ws/market";
+
+/// How often the feed pings the venue, keeping a quiet book fresh
+const PING_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Pause before reconnecting a dropped feed
+const RECONNECT_DELAY: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// Mirroring errors
+#[derive(Debug, thiserror::Error)]
+pub enum MirrorError {
+    #[error("Invalid mm_mirror_books config: {0}")]
+    InvalidConfig(String),
+}
+
+/// Message format of a venue's depth stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MirrorFormat {
+    Polymarket,
+    Depth,
+}
+
+/// An external book mirrored into a symbol
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MirrorBook {
+    /// Internal book (`{market_id}:{outcome_id}:{yes|no}`)
+    pub symbol: String,
+    pub format: MirrorFormat,
+    /// WebSocket endpoint (required for `depth`)
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Asset subscribed to (`polymarket`)
+    #[serde(default)]
+    pub market: Option<String>,
+    /// Message sent on connect (`depth`)
+    #[serde(default)]
+    pub subscribe: Option<Value>,
+    pub size_factor: Decimal,
+    pub markup: Decimal,
+    pub levels: usize,
+    #[serde(default)]
+    pub max_level_size: Option<Decimal>,
+}
+
+impl MirrorBook {
+    fn url(&self) -> &str {
+        match (&self.url, self.format) {
+            (Some(url), _) => url,
+            (None, MirrorFormat::Polymarket) => POLYMARKET_WS_URL,
+            (None, MirrorFormat::Depth) => "",
+        }
+    }
+
+    fn subscribe_message(&self) -> Option<String> {
+        match self.format {
+            MirrorFormat::Polymarket => {
+                let market = self.market.as_ref()?;
+                Some(serde_json::json!({ "assets_ids": [market], "type": "market" }).to_string())
+            }
+            MirrorFormat::Depth => self.subscribe.as_ref().map(|message| match message {
+                Value::String(text) => text.clone(),
+                other => other.to_string(),
+            }),
+        }
+    }
+}
+
+/// Parse the `mm_mirror_books` JSON config (empty = no mirroring)
+pub fn parse_books(config: &str) -> Result<Vec<MirrorBook>, MirrorError> {
+    if config.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let books: Vec<MirrorBook> = serde_json::from_str(config).map_err(|e| MirrorError::InvalidConfig(e.to_string()))?;
+    let invalid = |book: &MirrorBook, reason: &str| Err(MirrorError::InvalidConfig(format!("{}: {}", book.symbol, reason)));
+    for (i, book) in books.iter().enumerate() {
+        if book.symbol.split(':').count() != 3 || !(book.symbol.ends_with(":yes") || book.symbol.ends_with(":no")) {
+            return invalid(book, "symbol must be {market_id}:{outcome_id}:{yes|no}");
+        }
+        if books[..i].iter().any(|other| other.symbol == book.symbol) {
+            return invalid(book, "mirrored twice");
+        }
+        if book.size_factor <= Decimal::ZERO || book.size_factor > Decimal::ONE {
+            return invalid(book, "size_factor must be in (0, 1]");
+        }
+        if book.markup < Decimal::ZERO || book.max_level_size.is_some_and(|max| max <= Decimal::ZERO) {
+            return invalid(book, "markup cannot be negative and max_level_size must be positive");
+        }
+        if book.levels == 0 || book.levels > MAX_LEVELS as usize {
+            return invalid(book, &format!("levels must be between 1 and {}", MAX_LEVELS));
+        }
+        match book.format {
+            MirrorFormat::Polymarket if book.market.is_none() => return invalid(book, "polymarket needs a market"),
+            MirrorFormat::Depth if book.url.is_none() => return invalid(book, "depth needs a url"),
+            _ => {}
+        }
+    }
+    Ok(books)
+}
+
+/// Latest external book of a symbol
+#[derive(Debug, Clone, Default)]
+pub struct ExternalBook {
+    pub bids: BTreeMap<Decimal, Decimal>,
+    pub asks: BTreeMap<Decimal, Decimal>,
+    /// Last frame received from the venue
+    pub received_at: Option<DateTime<Utc>>,
+}
+
+impl ExternalBook {
+    pub fn best_bid(&self) -> Option<Decimal> {
+        self.bids.keys().next_back().copied()
+    }
+
+    pub fn best_ask(&self) -> Option<Decimal> {
+        self.asks.keys().next().copied()
+    }
+
+    /// Mid price, or the best price of the only side quoted
+    pub fn mid(&self) -> Option<Decimal> {
+        match (self.best_bid(), self.best_ask()) {
+            (Some(bid), Some(ask)) => Some((bid + ask) / Decimal::TWO),
+            (bid, ask) => bid.or(ask),
+        }
+    }
+
+    pub fn is_fresh(&self, now: DateTime<Utc>, max_age: Duration) -> bool {
+        self.received_at.is_some_and(|at| now - at <= max_age)
+    }
+}
+
+/// `[price, size, ...]` or `{"price", "size"}` level
+fn parse_level(level: &Value) -> Option<(Decimal, Decimal)> {
+    let (price, size) = match level {
+        Value::Array(fields) => (fields.first()?, fields.get(1)?),
+        Value::Object(fields) => (fields.get("price")?, fields.get("size")?),
+        _ => return None,
+    };
+    let parse = |value: &Value| match value {
+        Value::String(text) => text.parse::<Decimal>().ok(),
+        Value::Number(number) => number.to_string().parse::<Decimal>().ok(),
+        _ => None,
+    };
+    Some((parse(price)?, parse(size)?))
+}
+
+fn parse_side(levels: Option<&Value>) -> BTreeMap<Decimal, Decimal> {
+    levels
+        .and_then(Value::as_array)
+        .map(|levels| {
+            levels
+                .iter()
+                .filter_map(parse_level)
+                .filter(|(price, size)| *price > Decimal::ZERO && *size > Decimal::ZERO)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Apply one venue message to a book; true when the levels changed
+pub fn apply_message(book: &mut ExternalBook, format: MirrorFormat, market: Option<&str>, message: &Value) -> bool {
+    match format {
+        MirrorFormat::Depth => {
+            let data = match message.get("data") {
+                Some(Value::Array(items)) => items.first().unwrap_or(message),
+                Some(data @ Value::Object(_)) => data,
+                _ => message,
+            };
+            if data.get("bids").is_none() && data.get("asks").is_none() {
+                return false;
+            }
+            book.bids = parse_side(data.get("bids"));
+            book.asks = parse_side(data.get("asks"));
+            true
+        }
+        MirrorFormat::Polymarket => {
+            // Events arrive one at a time or batched in an array
+            if let Value::Array(events) = message {
+                let mut changed = false;
+                for event in events {
+                    changed |= apply_message(book, format, market, event);
+                }
+                return changed;
+            }
+            let for_market = |event: &Value| {
+                market.is_none() || event.get("asset_id").and_then(Value::as_str).is_none_or(|id| Some(id) == market)
+            };
+            match message.get("event_type").and_then(Value::as_str) {
+                Some("book") if for_market(message) => {
+                    book.bids = parse_side(message.get("bids"));
+                    book.asks = parse_side(message.get("asks"));
+                    true
+                }
+                Some("price_change") if for_market(message) => {
+                    let changes = message.get("changes").or_else(|| message.get("price_changes"));
+                    let mut changed = false;
+                    for change in changes.and_then(Value::as_array).into_iter().flatten() {
+                        if !for_market(change) {
+                            continue;
+                        }
+                        let (Some((price, size)), Some(side)) =
+                            (parse_level(change), change.get("side").and_then(Value::as_str))
+                        else {
+                            continue;
+                        };
+                        let levels = if side.eq_ignore_ascii_case("buy") { &mut book.bids } else { &mut book.asks };
+                        if size.is_zero() {
+                            levels.remove(&price);
+                        } else {
+                            levels.insert(price, size);
+                        }
+                        changed = true;
+                    }
+                    changed
+                }
+                _ => false,
+            }
+        }
+    }
+}
+
+/// Quotes mirroring the top of an external book, before position limits
+///
+/// Levels landing on the same price after the markup and tick rounding are
+/// merged; levels outside the price range are dropped.
+pub fn mirror_quotes(book: &ExternalBook, config: &MirrorBook, tick: Decimal) -> Vec<Quote> {
+    let bids = book.bids.iter().rev().map(|(price, size)| (Side::Buy, *price, *size));
+    let asks = book.asks.iter().map(|(price, size)| (Side::Sell, *price, *size));
+
+    let mut quotes: Vec<Quote> = Vec::new();
+    for (side, levels) in [(Side::Buy, bids.collect::<Vec<_>>()), (Side::Sell, asks.collect())] {
+        for (_, price, size) in levels.into_iter().take(config.levels) {
+            let price = match side {
+                Side::Buy => ((price - config.markup) / tick).floor() * tick,
+                Side::Sell => ((price + config.markup) / tick).ceil() * tick,
+            };
+            if !(MIN_PRICE..=MAX_PRICE).contains(&price) {
+                continue;
+            }
+            let cap = |amount: Decimal| config.max_level_size.map_or(amount, |max| amount.min(max));
+            match quotes.last_mut() {
+                Some(last) if last.side == side && last.price == price => last.amount = cap(last.amount + size * config.size_factor),
+                _ => quotes.push(Quote {
+                    side,
+                    price,
+                    amount: cap(size * config.size_factor),
+                }),
+            }
+        }
+    }
+    quotes
+}
+
+/// Feed state of a mirrored book
+#[derive(Debug, Clone, Serialize)]
+pub struct MirrorStatus {
+    pub symbol: String,
+    pub format: MirrorFormat,
+    pub url: String,
+    pub received_at: Option<DateTime<Utc>>,
+    pub stale: bool,
+    pub best_bid: Option<Decimal>,
+    pub best_ask: Option<Decimal>,
+    pub bid_levels: usize,
+    pub ask_levels: usize,
+}
+
+/// External books mirrored by the auto-MM
+pub struct MmMirrorService {
+    books: Vec<MirrorBook>,
+    depth: DashMap<String, ExternalBook>,
+}
+
+impl MmMirrorService {
+    pub fn new(books: Vec<MirrorBook>) -> Self {
+        Self {
+            books,
+            depth: DashMap::new(),
+        }
+    }
+
+    pub fn books(&self) -> &[MirrorBook] {
+        &self.books
+    }
+
+    /// Mirror configuration of a symbol
+    pub fn book_config(&self, symbol: &str) -> Option<&MirrorBook> {
+        self.books.iter().find(|book| book.symbol == symbol)
+    }
+
+    /// External book of a symbol, unless stale
+    pub fn fresh_book(&self, symbol: &str, now: DateTime<Utc>, max_age: Duration) -> Option<ExternalBook> {
+        self.depth
+            .get(symbol)
+            .filter(|book| book.is_fresh(now, max_age))
+            .map(|book| book.clone())
+    }
+
+    /// Feed state of every mirrored book
+    pub fn status(&self, now: DateTime<Utc>, max_age: Duration) -> Vec<MirrorStatus> {
+        self.books
+            .iter()
+            .map(|config| {
+                let book = self.depth.get(&config.symbol).map(|book| book.clone()).unwrap_or_default();
+                MirrorStatus {
+                    symbol: config.symbol.clone(),
+                    format: config.format,
+                    url: config.url().to_string(),
+                    received_at: book.received_at,
+                    stale: !book.is_fresh(now, max_age),
+                    best_bid: book.best_bid(),
+                    best_ask: book.best_ask(),
+                    bid_levels: book.bids.len(),
+                    ask_levels: book.asks.len(),
+                }
+            })
+            .collect()
+    }
+
+    fn touch(&self, symbol: &str) {
+        self.depth.entry(symbol.to_string()).or_default().received_at = Some(Utc::now());
+    }
+
+    /// Connect the feed of every mirrored book, reconnecting when one drops
+    pub fn spawn(self: &Arc<Self>) {
+        for config in self.books.clone() {
+            let service = self.clone();
+            tokio::spawn(async move {
+                loop {
+                    service.follow(&config).await;
+                    // Nothing seen before the drop is trusted: the book is stale at once
+                    service.depth.remove(&config.symbol);
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
+            });
+        }
+    }
+
+    /// Follow one feed until its connection drops
+    async fn follow(&self, config: &MirrorBook) {
+        let (stream, _) = match connect_async(config.url()).await {
+            Ok(connection) => connection,
+            Err(e) => {
+                tracing::warn!("MM mirror feed for {} failed to connect: {}", config.symbol, e);
+                return;
+            }
+        };
+        tracing::info!("MM mirror feed for {} connected to {}", config.symbol, config.url());
+        let (mut write, mut read) = stream.split();
+        if let Some(subscribe) = config.subscribe_message() {
+            if let Err(e) = write.send(Message::Text(subscribe)).await {
+                tracing::warn!("MM mirror feed for {} failed to subscribe: {}", config.symbol, e);
+                return;
+            }
+        }
+
+        let mut ping = tokio::time::interval(PING_INTERVAL);
+        loop {
+            tokio::select! {
+                frame = read.next() => match frame {
+                    Some(Ok(Message::Text(text))) => {
+                        self.touch(&config.symbol);
+                        // Polymarket answers its text pings with a bare PONG
+                        let Ok(message) = serde_json::from_str::<Value>(&text) else {
+                            continue;
+                        };
+                        if let Some(mut book) = self.depth.get_mut(&config.symbol) {
+                            apply_message(&mut book, config.format, config.market.as_deref(), &message);
+                        }
+                    }
+                    Some(Ok(Message::Ping(data))) => {
+                        self.touch(&config.symbol);
+                        if write.send(Message::Pong(data)).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Ok(_)) => self.touch(&config.symbol),
+                    Some(Err(e)) => {
+                        tracing::warn!("MM mirror feed for {} failed: {}", config.symbol, e);
+                        break;
+                    }
+                },
+                _ = ping.tick() => {
+                    let ping = match config.format {
+                        MirrorFormat::Polymarket => Message::Text("PING".to_string()),
+                        MirrorFormat::Depth => Message::Ping(Vec::new()),
+                    };
+                    if write.send(ping).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+        tracing::warn!("MM mirror feed for {} disconnected", config.symbol);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use serde_json::json;
+
+    fn config(format: MirrorFormat) -> MirrorBook {
+        MirrorBook {
+            symbol: "m:o:yes".to_string(),
+            format,
+            url: None,
+            market: Some("123".to_string()),
+            subscribe: None,
+            size_factor: dec!(0.1),
+            markup: dec!(0.01),
+            levels: 2,
+            max_level_size: None,
+        }
+    }
+
+    #[test]
+    fn test_polymarket_snapshot_and_changes() {
+        let mut book = ExternalBook::default();
+        let snapshot = json!({
+            "event_type": "book", "asset_id": "123",
+            "bids": [{ "price": "0.48", "size": "300" }, { "price": "0.47", "size": "500" }],
+            "asks": [{ "price": "0.52", "size": "200" }]
+        });
+        assert!(apply_message(&mut book, MirrorFormat::Polymarket, Some("123"), &snapshot));
+        assert_eq!((book.best_bid(), book.best_ask()), (Some(dec!(0.48)), Some(dec!(0.52))));
+
+        // Another asset's events are ignored; a zero size removes the level
+        let other = json!({ "event_type": "book", "asset_id": "456", "bids": [], "asks": [] });
+        assert!(!apply_message(&mut book, MirrorFormat::Polymarket, Some("123"), &other));
+        let change = json!([{
+            "event_type": "price_change", "asset_id": "123",
+            "changes": [{ "price": "0.48", "side": "BUY", "size": "0" }, { "price": "0.51", "side": "SELL", "size": "40" }]
+        }]);
+        assert!(apply_message(&mut book, MirrorFormat::Polymarket, Some("123"), &change));
+        assert_eq!((book.best_bid(), book.best_ask()), (Some(dec!(0.47)), Some(dec!(0.51))));
+        assert_eq!(book.mid(), Some(dec!(0.49)));
+    }
+
+    #[test]
+    fn test_depth_snapshot() {
+        let mut book = ExternalBook::default();
+        let message = json!({ "data": [{ "bids": [["0.40", "10", "0", "1"]], "asks": [["0.45", "12", "0", "1"]] }] });
+        assert!(apply_message(&mut book, MirrorFormat::Depth, None, &message));
+        assert_eq!((book.best_bid(), book.best_ask()), (Some(dec!(0.40)), Some(dec!(0.45))));
+        assert!(!apply_message(&mut book, MirrorFormat::Depth, None, &json!({ "event": "subscribe" })));
+    }
+
+    #[test]
+    fn test_mirror_quotes_scaled_and_marked_up() {
+        let mut book = ExternalBook::default();
+        book.bids.extend([(dec!(0.485), dec!(300)), (dec!(0.48), dec!(100)), (dec!(0.40), dec!(900))]);
+        book.asks.extend([(dec!(0.52), dec!(200)), (dec!(0.99), dec!(50))]);
+
+        let quotes = mirror_quotes(&book, &config(MirrorFormat::Polymarket), dec!(0.01));
+        // 0.485 and 0.48 both land on 0.47 and merge; the third bid is past `levels`;
+        // the second ask would quote at 1.00
+        assert_eq!(
+            quotes,
+            vec![
+                Quote { side: Side::Buy, price: dec!(0.47), amount: dec!(40) },
+                Quote { side: Side::Sell, price: dec!(0.53), amount: dec!(20) },
+            ]
+        );
+
+        let capped = MirrorBook {
+            max_level_size: Some(dec!(25)),
+            ..config(MirrorFormat::Polymarket)
+        };
+        assert_eq!(mirror_quotes(&book, &capped, dec!(0.01))[0].amount, dec!(25));
+    }
+
+    #[test]
+    fn test_staleness() {
+        let now = Utc::now();
+        let book = ExternalBook {
+            received_at: Some(now - Duration::seconds(11)),
+            ..Default::default()
+        };
+        assert!(!book.is_fresh(now, Duration::seconds(10)));
+        assert!(book.is_fresh(now, Duration::seconds(15)));
+        assert!(!ExternalBook::default().is_fresh(now, Duration::seconds(10)));
+    }
+
+    #[test]
+    fn test_parse_books() {
+        let symbol = format!("{}:{}:yes", uuid::Uuid::new_v4(), uuid::Uuid::new_v4());
+        let books = parse_books(&format!(
+            r#"[{{ "symbol": "{}", "format": "polymarket", "market": "1", "size_factor": 0.1, "markup": 0.01, "levels": 5 }}]"#,
+            symbol
+        ))
+        .unwrap();
+        assert_eq!(books[0].url(), POLYMARKET_WS_URL);
+        assert!(books[0].subscribe_message().unwrap().contains("\"assets_ids\":[\"1\"]"));
+        assert!(parse_books("").unwrap().is_empty());
+
+        // depth needs a url, size_factor stays within (0, 1]
+        assert!(parse_books(&format!(
+            r#"[{{ "symbol": "{}", "format": "depth", "size_factor": 0.1, "markup": 0, "levels": 5 }}]"#,
+            symbol
+        ))
+        .is_err());
+        assert!(parse_books(&format!(
+            r#"[{{ "symbol": "{}", "format": "polymarket", "market": "1", "size_factor": 2, "markup": 0, "levels": 5 }}]"#,
+            symbol
+        ))
+        .is_err());
+    }
+}
@@ -0,0 +1,815 @@
+//! MM Quoting Engine
+//!
+//! Keeps the auto-MM account (`auto_mm_test_account`) quoting both sides of
+//! each configured symbol around the symbol's external index price:
+//!
+//! - `levels` bids and asks, the innermost `spread` apart and each further
+//!   level `level_spacing` out, `level_size` shares each
+//! - skew: the center of the quotes moves against the inventory, by `skew`
+//!   at a full `max_position` (a long lowers both sides so its asks fill first)
+//! - position limit: bids never add up past a long of `max_position`, asks
+//!   past a short of it
+//! - loss limit: a symbol whose PnL over the last 24 hours (positions closed
+//!   since, plus the open position's realized and unrealized PnL, after fees
+//!   and funding) reaches `-max_loss` is halted. Its quotes are pulled until
+//!   its parameters are stored again.
+//!
+//! Inventory is the account's holding of the outcome: YES shares are a long
+//! of the YES book and a short of the NO book. Quotes are rebuilt when the
+//! index moves `refresh_threshold` from the price they were built on, when
+//! the inventory changes (a quote filled) and when parameters change; a
+//! rebuild cancels every resting order of the account on the symbol and
+//! places the new ladder. Quotes pass the crossing guard and imbalance
+//! protection like MM orders placed over the API, and only add liquidity:
+//! with `mm_cross_policy` off they are clamped.
+//!
+//! A symbol can instead mirror an external venue's book
+//! ([`crate::services::mm_mirror`]); the limits above still apply.
+//!
+//! Parameters are per symbol (`mm_quote_params`) and change at runtime;
+//! symbols without a row are not quoted. The `mm_quoter` job runs while
+//! `auto_mm_enabled` is on; turning it off pulls every quote.
+
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::{BTreeSet, HashMap};
+use uuid::Uuid;
+
+use crate::metrics;
+use crate::services::ledger::{BalanceChange, LedgerReason, LedgerService};
+use crate::services::market::mark_price::MarkPriceService;
+use crate::services::market::MarketService;
+use crate::services::matching::{cancel_resting_order, MatchingEngine, OrderType, Side};
+use crate::services::mm_guard::{CrossPolicy, MmGuardError, MmQuoteGuard};
+use crate::services::mm_imbalance::{adjust_quote, MmImbalanceService};
+use crate::services::mm_mirror::{mirror_quotes, MmMirrorService};
+
+/// Lowest and highest valid probability price
+const MIN_PRICE: Decimal = Decimal::from_parts(1, 0, 0, false, 2);
+const MAX_PRICE: Decimal = Decimal::from_parts(99, 0, 0, false, 2);
+
+/// Price step when the market has no tick size
+const DEFAULT_TICK: Decimal = Decimal::from_parts(1, 0, 0, false, 2);
+
+/// Most quote levels per side
+pub const MAX_LEVELS: i32 = 10;
+
+/// Quoting errors
+#[derive(Debug, thiserror::Error)]
+pub enum QuoterError {
+    #[error("Invalid quoting parameters: {0}")]
+    InvalidParams(String),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// Quoting parameters of a symbol
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct QuoteParams {
+    pub enabled: bool,
+    pub spread: Decimal,
+    pub levels: i32,
+    pub level_spacing: Decimal,
+    pub level_size: Decimal,
+    pub skew: Decimal,
+    pub max_position: Decimal,
+    pub max_loss: Decimal,
+    pub refresh_threshold: Decimal,
+}
+
+impl Default for QuoteParams {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            spread: Decimal::new(4, 2),
+            levels: 3,
+            level_spacing: Decimal::new(1, 2),
+            level_size: Decimal::from(100),
+            skew: Decimal::new(2, 2),
+            max_position: Decimal::from(1000),
+            max_loss: Decimal::from(500),
+            refresh_threshold: Decimal::new(5, 3),
+        }
+    }
+}
+
+impl QuoteParams {
+    pub fn validate(&self) -> Result<(), QuoterError> {
+        let invalid = |reason: &str| Err(QuoterError::InvalidParams(reason.to_string()));
+        if self.spread <= Decimal::ZERO || self.spread >= Decimal::ONE {
+            return invalid("spread must be in (0, 1)");
+        }
+        if !(1..=MAX_LEVELS).contains(&self.levels) {
+            return invalid(&format!("levels must be between 1 and {}", MAX_LEVELS));
+        }
+        if self.level_spacing < Decimal::ZERO || (self.levels > 1 && self.level_spacing.is_zero()) {
+            return invalid("level_spacing must be positive with more than one level");
+        }
+        if self.level_size <= Decimal::ZERO || self.max_position <= Decimal::ZERO {
+            return invalid("level_size and max_position must be positive");
+        }
+        if self.max_loss <= Decimal::ZERO || self.refresh_threshold <= Decimal::ZERO {
+            return invalid("max_loss and refresh_threshold must be positive");
+        }
+        if self.skew < Decimal::ZERO {
+            return invalid("skew cannot be negative");
+        }
+        Ok(())
+    }
+}
+
+/// Stored parameters of a symbol
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct SymbolQuoteParams {
+    pub symbol: String,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub params: QuoteParams,
+    pub updated_by: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A quote to place
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Quote {
+    pub side: Side,
+    pub price: Decimal,
+    pub amount: Decimal,
+}
+
+/// A quote resting in the book
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct PlacedQuote {
+    pub order_id: Uuid,
+    pub side: Side,
+    pub price: Decimal,
+    pub amount: Decimal,
+}
+
+/// Quoting state of a symbol
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QuoteState {
+    pub symbol: String,
+    /// Index price the quotes were built on
+    pub reference_price: Option<Decimal>,
+    /// Signed holding the quotes were built for
+    pub inventory: Decimal,
+    /// PnL over the last 24 hours at the last run
+    pub pnl: Decimal,
+    pub quotes: Vec<PlacedQuote>,
+    /// Quoted from an external book ([`crate::services::mm_mirror`])
+    pub mirrored: bool,
+    /// Why quotes are pulled for now ("no_index_price", "stale_feed" or "empty_book")
+    pub paused: Option<String>,
+    /// Why quoting stopped ("loss_limit"), until parameters are stored again
+    pub halted: Option<String>,
+    pub refreshed_at: Option<DateTime<Utc>>,
+    /// Quotes the last rebuild aimed for, before the crossing guard and imbalance protection
+    #[serde(skip)]
+    pub target: Vec<Quote>,
+}
+
+/// Outcome of a quoting run
+#[derive(Debug, Default, Serialize)]
+pub struct QuoterRun {
+    pub symbols: usize,
+    pub refreshed: usize,
+    pub placed: usize,
+    pub halted: usize,
+}
+
+/// Holding of the account in an outcome
+#[derive(sqlx::FromRow)]
+struct Holding {
+    share_type: String,
+    amount: Decimal,
+    avg_cost: Decimal,
+    /// Realized PnL of the open position after fees and funding
+    cycle_pnl: Decimal,
+}
+
+/// `{market_id}:{outcome_id}:{yes|no}` split into its parts
+fn split_symbol(symbol: &str) -> Option<(Uuid, Uuid, &str)> {
+    let mut parts = symbol.split(':');
+    let market_id = parts.next()?.parse().ok()?;
+    let outcome_id = parts.next()?.parse().ok()?;
+    let share_type = parts.next().filter(|s| matches!(*s, "yes" | "no"))?;
+    parts.next().is_none().then_some((market_id, outcome_id, share_type))
+}
+
+/// Center of the quotes: the reference moved against the inventory
+pub fn skewed_center(reference: Decimal, inventory: Decimal, params: &QuoteParams) -> Decimal {
+    let ratio = (inventory / params.max_position).clamp(-Decimal::ONE, Decimal::ONE);
+    reference - params.skew * ratio
+}
+
+/// Quote ladder around `reference` for a signed `inventory`
+///
+/// Bids round down and asks up to the tick; levels falling outside the
+/// price range or onto the previous level's price are dropped. Sizes then
+/// go through [`apply_limits`].
+pub fn build_quotes(
+    reference: Decimal,
+    inventory: Decimal,
+    params: &QuoteParams,
+    tick: Decimal,
+    lot: Option<Decimal>,
+) -> Vec<Quote> {
+    let center = skewed_center(reference, inventory, params);
+    let half = params.spread / Decimal::TWO;
+
+    let mut quotes = Vec::new();
+    for side in [Side::Buy, Side::Sell] {
+        let mut last_price = None;
+        for level in 0..params.levels {
+            let offset = half + params.level_spacing * Decimal::from(level);
+            let price = match side {
+                Side::Buy => ((center - offset) / tick).floor() * tick,
+                Side::Sell => ((center + offset) / tick).ceil() * tick,
+            };
+            if !(MIN_PRICE..=MAX_PRICE).contains(&price) || last_price == Some(price) {
+                continue;
+            }
+            last_price = Some(price);
+            quotes.push(Quote {
+                side,
+                price,
+                amount: params.level_size,
+            });
+        }
+    }
+    apply_limits(quotes, inventory, params.max_position, lot)
+}
+
+/// Round quote sizes down to the lot and stop each side at the position
+/// limit: bids add up to at most `max_position - inventory`, asks to
+/// `max_position + inventory`. Quotes are taken innermost first.
+pub fn apply_limits(quotes: Vec<Quote>, inventory: Decimal, max_position: Decimal, lot: Option<Decimal>) -> Vec<Quote> {
+    let to_lot = |amount: Decimal| match lot {
+        Some(lot) if lot > Decimal::ZERO => (amount / lot).floor() * lot,
+        _ => amount,
+    };
+    let mut bid_capacity = max_position - inventory;
+    let mut ask_capacity = max_position + inventory;
+    let mut limited = Vec::with_capacity(quotes.len());
+    for quote in quotes {
+        let capacity = match quote.side {
+            Side::Buy => &mut bid_capacity,
+            Side::Sell => &mut ask_capacity,
+        };
+        let amount = to_lot(quote.amount.min(*capacity));
+        if amount <= Decimal::ZERO {
+            continue;
+        }
+        *capacity -= amount;
+        limited.push(Quote { amount, ..quote });
+    }
+    limited
+}
+
+/// Why a symbol's quotes need rebuilding, if they do
+pub fn refresh_reason(
+    state: Option<&QuoteState>,
+    reference: Decimal,
+    inventory: Decimal,
+    threshold: Decimal,
+) -> Option<&'static str> {
+    let Some(quoted) = state.and_then(|state| state.reference_price.map(|price| (price, state.inventory))) else {
+        return Some("start");
+    };
+    if quoted.1 != inventory {
+        Some("inventory")
+    } else if (reference - quoted.0).abs() >= threshold {
+        Some("price")
+    } else {
+        None
+    }
+}
+
+/// Two-sided quoting of the auto-MM
+#[derive(Default)]
+pub struct MmQuoterService {
+    /// Per-symbol parameters (symbols without one are not quoted)
+    params: RwLock<HashMap<String, QuoteParams>>,
+    states: RwLock<HashMap<String, QuoteState>>,
+}
+
+impl MmQuoterService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load stored parameters; returns the number of symbols configured
+    pub async fn load_params(&self, pool: &PgPool) -> Result<usize, QuoterError> {
+        let rows = Self::list_params(pool).await?;
+        let count = rows.len();
+        *self.params.write() = rows.into_iter().map(|row| (row.symbol, row.params)).collect();
+        Ok(count)
+    }
+
+    /// Stored parameters of every configured symbol
+    pub async fn list_params(pool: &PgPool) -> Result<Vec<SymbolQuoteParams>, QuoterError> {
+        let rows = sqlx::query_as(
+            r#"
+            SELECT symbol, enabled, spread, levels, level_spacing, level_size, skew, max_position,
+                   max_loss, refresh_threshold, updated_by, updated_at
+            FROM mm_quote_params
+            ORDER BY symbol
+            "#,
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
+    }
+
+    /// Store a symbol's parameters and apply them: the symbol is requoted
+    /// on the next run, lifting a halt
+    pub async fn set_params(
+        &self,
+        pool: &PgPool,
+        symbol: &str,
+        params: &QuoteParams,
+        updated_by: &str,
+    ) -> Result<SymbolQuoteParams, QuoterError> {
+        params.validate()?;
+        let row: SymbolQuoteParams = sqlx::query_as(
+            r#"
+            INSERT INTO mm_quote_params (
+                symbol, enabled, spread, levels, level_spacing, level_size, skew, max_position,
+                max_loss, refresh_threshold, updated_by, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, NOW())
+            ON CONFLICT (symbol) DO UPDATE SET
+                enabled = EXCLUDED.enabled,
+                spread = EXCLUDED.spread,
+                levels = EXCLUDED.levels,
+                level_spacing = EXCLUDED.level_spacing,
+                level_size = EXCLUDED.level_size,
+                skew = EXCLUDED.skew,
+                max_position = EXCLUDED.max_position,
+                max_loss = EXCLUDED.max_loss,
+                refresh_threshold = EXCLUDED.refresh_threshold,
+                updated_by = EXCLUDED.updated_by,
+                updated_at = NOW()
+            RETURNING symbol, enabled, spread, levels, level_spacing, level_size, skew, max_position,
+                      max_loss, refresh_threshold, updated_by, updated_at
+            "#,
+        )
+        .bind(symbol)
+        .bind(params.enabled)
+        .bind(params.spread)
+        .bind(params.levels)
+        .bind(params.level_spacing)
+        .bind(params.level_size)
+        .bind(params.skew)
+        .bind(params.max_position)
+        .bind(params.max_loss)
+        .bind(params.refresh_threshold)
+        .bind(updated_by)
+        .fetch_one(pool)
+        .await?;
+
+        self.params.write().insert(symbol.to_string(), params.clone());
+        // The next run requotes the symbol (or pulls a disabled one)
+        if let Some(state) = self.states.write().get_mut(symbol) {
+            state.reference_price = None;
+            state.halted = None;
+        }
+        Ok(row)
+    }
+
+    /// Quoting state of every symbol quoted since startup
+    pub fn states(&self) -> Vec<QuoteState> {
+        let mut states: Vec<QuoteState> = self.states.read().values().cloned().collect();
+        states.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        states
+    }
+
+    /// Quote every configured symbol; `mirror` carries the mirrored books and
+    /// the age past which they are stale
+    #[allow(clippy::too_many_arguments)]
+    pub async fn run(
+        &self,
+        pool: &PgPool,
+        engine: &MatchingEngine,
+        mark_prices: &MarkPriceService,
+        markets: &MarketService,
+        imbalance: Option<&MmImbalanceService>,
+        mirror: Option<(&MmMirrorService, chrono::Duration)>,
+        mm_address: &str,
+        collateral_token: &str,
+        cross_policy: CrossPolicy,
+    ) -> Result<QuoterRun, QuoterError> {
+        // Quotes only add liquidity
+        let cross_policy = if cross_policy == CrossPolicy::Off { CrossPolicy::Clamp } else { cross_policy };
+        let symbols: BTreeSet<String> = self
+            .params
+            .read()
+            .keys()
+            .chain(self.states.read().keys())
+            .cloned()
+            .collect();
+
+        let mut run = QuoterRun::default();
+        for symbol in symbols {
+            let params = self.params.read().get(&symbol).cloned().filter(|params| params.enabled);
+            let Some(params) = params else {
+                if self.states.write().remove(&symbol).is_some() {
+                    Self::pull(pool, engine, &symbol, mm_address, collateral_token).await?;
+                    tracing::info!("MM quoting stopped on {}", symbol);
+                }
+                continue;
+            };
+            let Some((market_id, outcome_id, share_type)) = split_symbol(&symbol) else {
+                continue;
+            };
+            run.symbols += 1;
+
+            let mut state = self.states.read().get(&symbol).cloned().unwrap_or_else(|| QuoteState {
+                symbol: symbol.clone(),
+                ..Default::default()
+            });
+            if state.halted.is_some() {
+                continue;
+            }
+
+            let holding: Option<Holding> = sqlx::query_as(
+                r#"
+                SELECT share_type::text AS share_type, amount, avg_cost,
+                       realized_pnl - fees_paid - funding_paid AS cycle_pnl
+                FROM shares
+                WHERE user_address = $1 AND outcome_id = $2
+                "#,
+            )
+            .bind(mm_address)
+            .bind(outcome_id)
+            .fetch_optional(pool)
+            .await?;
+            let closed_pnl: Decimal = sqlx::query_scalar(
+                r#"
+                SELECT COALESCE(SUM(realized_pnl - fees - funding), 0)
+                FROM position_history
+                WHERE user_address = $1 AND outcome_id = $2 AND closed_at >= NOW() - INTERVAL '24 hours'
+                "#,
+            )
+            .bind(mm_address)
+            .bind(outcome_id)
+            .fetch_one(pool)
+            .await?;
+
+            let (inventory, open_pnl) = match &holding {
+                Some(holding) => {
+                    let held_symbol = format!("{}:{}:{}", market_id, outcome_id, holding.share_type);
+                    let mark = engine.mark_price(&held_symbol).unwrap_or(holding.avg_cost);
+                    let inventory = if holding.share_type == share_type { holding.amount } else { -holding.amount };
+                    (inventory, holding.cycle_pnl + (mark - holding.avg_cost) * holding.amount)
+                }
+                None => (Decimal::ZERO, Decimal::ZERO),
+            };
+            state.pnl = closed_pnl + open_pnl;
+
+            if state.pnl <= -params.max_loss {
+                Self::pull(pool, engine, &symbol, mm_address, collateral_token).await?;
+                state.quotes.clear();
+                state.halted = Some("loss_limit".to_string());
+                run.halted += 1;
+                metrics::record_mm_quoting_halted(&symbol);
+                tracing::warn!(
+                    "MM quoting halted on {}: 24h PnL {} reached the loss limit {}",
+                    symbol, state.pnl, params.max_loss
+                );
+                self.states.write().insert(symbol, state);
+                continue;
+            }
+
+            let market_config = markets.get_market_config(pool, market_id).await?;
+            let tick_size = market_config.as_ref().and_then(|config| config.tick_size);
+            let tick = tick_size.unwrap_or(DEFAULT_TICK);
+            let lot = market_config.as_ref().and_then(|config| config.lot_size);
+
+            // Mirrored symbols quote the external book, the others a ladder around the index
+            let now = Utc::now();
+            let mirrored = mirror.and_then(|(mirror, max_age)| {
+                mirror
+                    .book_config(&symbol)
+                    .map(|config| (config, mirror.fresh_book(&symbol, now, max_age)))
+            });
+            state.mirrored = mirrored.is_some();
+            let target = match mirrored {
+                Some((config, Some(book))) => book
+                    .mid()
+                    .map(|mid| (mid, apply_limits(mirror_quotes(&book, config, tick), inventory, params.max_position, lot)))
+                    .ok_or("empty_book"),
+                Some((_, None)) => Err("stale_feed"),
+                None => mark_prices
+                    .symbol_index_price(&symbol)
+                    .await
+                    .map(|reference| (reference, build_quotes(reference, inventory, &params, tick, lot)))
+                    .ok_or("no_index_price"),
+            };
+
+            // Nothing safe to quote around: pull the quotes until it is back
+            let (reference, target) = match target {
+                Ok(target) => target,
+                Err(reason) => {
+                    if state.paused.as_deref() != Some(reason) {
+                        Self::pull(pool, engine, &symbol, mm_address, collateral_token).await?;
+                        state.quotes.clear();
+                        state.reference_price = None;
+                        state.paused = Some(reason.to_string());
+                        if reason == "stale_feed" {
+                            metrics::record_mm_mirror_stale(&symbol);
+                        }
+                        tracing::warn!("MM quotes pulled on {}: {}", symbol, reason);
+                    }
+                    self.states.write().insert(symbol, state);
+                    continue;
+                }
+            };
+            state.paused = None;
+
+            let reason = refresh_reason(Some(&state), reference, inventory, params.refresh_threshold)
+                .or_else(|| (state.mirrored && state.target != target).then_some("book"));
+            let Some(reason) = reason else {
+                self.states.write().insert(symbol, state);
+                continue;
+            };
+
+            Self::pull(pool, engine, &symbol, mm_address, collateral_token).await?;
+            let protection = imbalance.and_then(|imbalance| imbalance.protection(&symbol, Utc::now()));
+
+            state.quotes.clear();
+            for &quote in &target {
+                let guarded = match MmQuoteGuard::check(
+                    pool,
+                    engine,
+                    cross_policy,
+                    &symbol,
+                    mm_address,
+                    quote.side,
+                    quote.price,
+                    tick_size,
+                    collateral_token,
+                )
+                .await
+                {
+                    Ok(guarded) => guarded,
+                    Err(MmGuardError::DatabaseError(e)) => return Err(e.into()),
+                    Err(_) => continue,
+                };
+                let quote = match &protection {
+                    Some(protection) => {
+                        let adjusted = adjust_quote(
+                            protection,
+                            quote.side,
+                            guarded.price,
+                            quote.amount,
+                            tick,
+                            lot,
+                        );
+                        Quote {
+                            side: quote.side,
+                            price: adjusted.price,
+                            amount: adjusted.amount,
+                        }
+                    }
+                    None => Quote {
+                        price: guarded.price,
+                        ..quote
+                    },
+                };
+                if let Some(placed) =
+                    Self::place(pool, engine, &symbol, market_id, outcome_id, share_type, mm_address, collateral_token, quote)
+                        .await?
+                {
+                    state.quotes.push(placed);
+                }
+            }
+
+            metrics::record_mm_quote_refresh(reason);
+            tracing::debug!(
+                "MM requoted {} ({}): reference {}, inventory {}, {} quotes",
+                symbol, reason, reference, inventory, state.quotes.len()
+            );
+            run.refreshed += 1;
+            run.placed += state.quotes.len();
+            state.reference_price = Some(reference);
+            state.inventory = inventory;
+            state.refreshed_at = Some(Utc::now());
+            state.target = target;
+            self.states.write().insert(symbol, state);
+        }
+        Ok(run)
+    }
+
+    /// Pull the quotes of every symbol quoted; returns the number of symbols
+    pub async fn pull_all(
+        &self,
+        pool: &PgPool,
+        engine: &MatchingEngine,
+        mm_address: &str,
+        collateral_token: &str,
+    ) -> Result<usize, QuoterError> {
+        let symbols: Vec<String> = self.states.write().drain().map(|(symbol, _)| symbol).collect();
+        for symbol in &symbols {
+            Self::pull(pool, engine, symbol, mm_address, collateral_token).await?;
+        }
+        Ok(symbols.len())
+    }
+
+    /// Cancel every resting order of the account on a symbol
+    async fn pull(
+        pool: &PgPool,
+        engine: &MatchingEngine,
+        symbol: &str,
+        mm_address: &str,
+        collateral_token: &str,
+    ) -> Result<usize, sqlx::Error> {
+        let Some(book) = engine.get_orderbook_ref(symbol) else {
+            return Ok(0);
+        };
+        let mut cancelled = 0;
+        for order in book.resting_orders() {
+            if order.user_address.eq_ignore_ascii_case(mm_address)
+                && cancel_resting_order(pool, engine, symbol, &order, collateral_token).await?
+            {
+                cancelled += 1;
+            }
+        }
+        Ok(cancelled)
+    }
+
+    /// Place one quote: freeze a bid's collateral, submit it and persist the
+    /// order. None when the account cannot fund it or the engine rejects it.
+    #[allow(clippy::too_many_arguments)]
+    async fn place(
+        pool: &PgPool,
+        engine: &MatchingEngine,
+        symbol: &str,
+        market_id: Uuid,
+        outcome_id: Uuid,
+        share_type: &str,
+        mm_address: &str,
+        collateral_token: &str,
+        quote: Quote,
+    ) -> Result<Option<PlacedQuote>, sqlx::Error> {
+        let order_id = Uuid::new_v4();
+        let collateral = quote.amount * quote.price;
+        if quote.side == Side::Buy {
+            let change = BalanceChange::freeze(mm_address, collateral_token, collateral, LedgerReason::OrderFreeze)
+                .reference(order_id)
+                .checked();
+            if LedgerService::post(pool, &change).await?.is_none() {
+                tracing::warn!("MM bid on {} skipped: {} {} not available", symbol, collateral, collateral_token);
+                return Ok(None);
+            }
+        }
+
+        let result = match engine.submit_order(
+            order_id,
+            symbol,
+            &mm_address.to_lowercase(),
+            quote.side,
+            OrderType::Limit,
+            quote.amount,
+            Some(quote.price),
+            1,
+        ) {
+            Ok(result) => result,
+            Err(e) => {
+                tracing::warn!("MM {} quote on {} at {} rejected: {}", quote.side, symbol, quote.price, e);
+                if quote.side == Side::Buy {
+                    let change =
+                        BalanceChange::unfreeze(mm_address, collateral_token, collateral, LedgerReason::OrderUnfreeze)
+                            .reference(order_id);
+                    LedgerService::post(pool, &change).await?;
+                }
+                return Ok(None);
+            }
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO orders (
+                id, user_address, market_id, outcome_id, share_type,
+                side, order_type, price, amount, filled_amount, status
+            )
+            VALUES ($1, $2, $3, $4, $5::share_type, $6::order_side, 'limit'::order_type, $7, $8, $9, $10::order_status)
+            ON CONFLICT (id) DO NOTHING
+            "#,
+        )
+        .bind(order_id)
+        .bind(mm_address.to_lowercase())
+        .bind(market_id)
+        .bind(outcome_id)
+        .bind(share_type)
+        .bind(quote.side.to_string())
+        .bind(quote.price)
+        .bind(quote.amount)
+        .bind(result.filled_amount)
+        .bind(result.status.to_string())
+        .execute(pool)
+        .await?;
+
+        Ok(Some(PlacedQuote {
+            order_id,
+            side: quote.side,
+            price: quote.price,
+            amount: quote.amount,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn prices(quotes: &[Quote], side: Side) -> Vec<Decimal> {
+        quotes.iter().filter(|q| q.side == side).map(|q| q.price).collect()
+    }
+
+    #[test]
+    fn test_flat_ladder() {
+        let params = QuoteParams::default();
+        let quotes = build_quotes(dec!(0.50), Decimal::ZERO, &params, dec!(0.01), None);
+        assert_eq!(prices(&quotes, Side::Buy), vec![dec!(0.48), dec!(0.47), dec!(0.46)]);
+        assert_eq!(prices(&quotes, Side::Sell), vec![dec!(0.52), dec!(0.53), dec!(0.54)]);
+        assert!(quotes.iter().all(|q| q.amount == dec!(100)));
+    }
+
+    #[test]
+    fn test_skews_against_inventory() {
+        let params = QuoteParams::default();
+        // Half the max long: center 0.50 - 0.02 * 0.5 = 0.49
+        assert_eq!(skewed_center(dec!(0.50), dec!(500), &params), dec!(0.49));
+        let quotes = build_quotes(dec!(0.50), dec!(500), &params, dec!(0.01), None);
+        assert_eq!(prices(&quotes, Side::Buy)[0], dec!(0.47));
+        assert_eq!(prices(&quotes, Side::Sell)[0], dec!(0.51));
+
+        // Past the limit the skew stays at its full size
+        assert_eq!(skewed_center(dec!(0.50), dec!(-5000), &params), dec!(0.52));
+    }
+
+    #[test]
+    fn test_position_limit() {
+        let params = QuoteParams::default();
+        // 150 shares of room to buy: one full level and one of 50
+        let quotes = build_quotes(dec!(0.50), dec!(850), &params, dec!(0.01), None);
+        let bids: Vec<Decimal> = quotes.iter().filter(|q| q.side == Side::Buy).map(|q| q.amount).collect();
+        assert_eq!(bids, vec![dec!(100), dec!(50)]);
+        // At the limit: asks only
+        let quotes = build_quotes(dec!(0.50), dec!(1000), &params, dec!(0.01), Some(dec!(10)));
+        assert!(quotes.iter().all(|q| q.side == Side::Sell));
+        assert_eq!(quotes.len(), 3);
+    }
+
+    #[test]
+    fn test_price_range_and_ticks() {
+        let params = QuoteParams {
+            spread: dec!(0.03),
+            ..Default::default()
+        };
+        // Bids round down, asks up; nothing below 0.01
+        let quotes = build_quotes(dec!(0.03), Decimal::ZERO, &params, dec!(0.01), None);
+        assert_eq!(prices(&quotes, Side::Buy), vec![dec!(0.01)]);
+        assert_eq!(prices(&quotes, Side::Sell), vec![dec!(0.05), dec!(0.06), dec!(0.07)]);
+    }
+
+    #[test]
+    fn test_refresh_reason() {
+        let state = QuoteState {
+            reference_price: Some(dec!(0.50)),
+            inventory: dec!(10),
+            ..Default::default()
+        };
+        assert_eq!(refresh_reason(None, dec!(0.50), dec!(10), dec!(0.005)), Some("start"));
+        assert_eq!(refresh_reason(Some(&state), dec!(0.503), dec!(10), dec!(0.005)), None);
+        assert_eq!(refresh_reason(Some(&state), dec!(0.505), dec!(10), dec!(0.005)), Some("price"));
+        assert_eq!(refresh_reason(Some(&state), dec!(0.50), dec!(20), dec!(0.005)), Some("inventory"));
+    }
+
+    #[test]
+    fn test_validate_params() {
+        assert!(QuoteParams::default().validate().is_ok());
+        let invalid = [
+            QuoteParams { spread: Decimal::ZERO, ..Default::default() },
+            QuoteParams { levels: MAX_LEVELS + 1, ..Default::default() },
+            QuoteParams { level_spacing: Decimal::ZERO, ..Default::default() },
+            QuoteParams { max_loss: Decimal::ZERO, ..Default::default() },
+            QuoteParams { skew: dec!(-0.01), ..Default::default() },
+        ];
+        assert!(invalid.iter().all(|params| params.validate().is_err()));
+        let single = QuoteParams {
+            levels: 1,
+            level_spacing: Decimal::ZERO,
+            ..Default::default()
+        };
+        assert!(single.validate().is_ok());
+        assert_eq!(split_symbol("m:o:yes"), None);
+    }
+}
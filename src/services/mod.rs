@@ -1,6 +1,57 @@
 //! Business logic services
 
+pub mod api_keys;
+pub mod auth_tokens;
+pub mod archive;
+pub mod chains;
+pub mod deposit;
+pub mod deposit_confirmations;
+pub mod errors;
+pub mod export;
+pub mod features;
+pub mod fees;
+pub mod idempotency;
+pub mod funding;
+pub mod gas_oracle;
+pub mod incidents;
+pub mod jobs;
+pub mod leader;
+pub mod ledger;
+pub mod liquidation;
+pub mod margin_call;
 pub mod matching;
 pub mod market;
+pub mod market_data_relay;
+pub mod mm_guard;
+pub mod mm_imbalance;
+pub mod mm_inventory;
+pub mod mm_mirror;
+pub mod mm_quoter;
+pub mod nonce;
+pub mod notifications;
 pub mod oracle;
+pub mod outbox;
+pub mod portfolio;
+pub mod position;
+pub mod preferences;
+pub mod premium_index;
+pub mod price_feed;
+pub mod referral_settlement;
+pub mod rfq;
+pub mod risk_disclosure;
+pub mod risk_profile;
+pub mod session_keys;
 pub mod settlement;
+pub mod settlement_price;
+pub mod shutdown;
+pub mod stats;
+pub mod sub_account;
+pub mod surveillance;
+pub mod tape;
+pub mod token_price;
+pub mod tp_sl;
+pub mod treasury;
+pub mod user_events;
+pub mod vault;
+pub mod withdrawal_fees;
+pub mod withdrawal_risk;
@@ -0,0 +1,181 @@
+//! Trading Nonces
+//!
+//! Signed trading messages (orders, cancels, amends) can carry a nonce from
+//! `GET /auth/nonce/trading`. Nonces are per account and increase with every
+//! issue; each one is accepted once, in any order, so requests signed
+//! concurrently do not race each other.
+//!
+//! Redis issues nonces (`INCR`) so every API instance hands out the same
+//! sequence, and the highest issued nonce is written through to
+//! `trading_nonces`. A fresh Redis counter starts from that high-water mark,
+//! and without Redis nonces are issued from the table directly. Used nonces
+//! are recorded in `used_trading_nonces`, which is what rejects replays.
+//!
+//! Signed messages without a nonce are accepted once too: the struct hash of
+//! each one is recorded in `used_signatures` while its timestamp is valid.
+
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::cache::keys::CacheKey;
+use crate::cache::RedisClient;
+
+/// Trading nonce errors
+#[derive(Debug, thiserror::Error)]
+pub enum NonceError {
+    #[error("Nonce {0} was not issued to this account")]
+    NotIssued(u64),
+
+    #[error("Nonce {0} has already been used")]
+    Reused(u64),
+
+    #[error("This signed message has already been used")]
+    SignatureReused,
+
+    #[error("Redis error: {0}")]
+    RedisError(#[from] redis::RedisError),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// Issues and redeems trading nonces
+pub struct NonceService {
+    redis: Option<Arc<RedisClient>>,
+}
+
+impl NonceService {
+    pub fn new(redis: Option<Arc<RedisClient>>) -> Self {
+        Self { redis }
+    }
+
+    /// Next nonce of `user_address`
+    pub async fn issue(&self, pool: &PgPool, user_address: &str) -> Result<u64, NonceError> {
+        let user_address = user_address.to_lowercase();
+        if let Some(redis) = &self.redis {
+            match Self::issue_shared(redis, pool, &user_address).await {
+                Err(NonceError::RedisError(e)) => {
+                    tracing::debug!("Issuing nonce from the database, Redis unavailable: {}", e)
+                }
+                result => return result,
+            }
+        }
+
+        let nonce: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO trading_nonces (user_address, last_issued)
+            VALUES ($1, 1)
+            ON CONFLICT (user_address) DO UPDATE SET
+                last_issued = trading_nonces.last_issued + 1,
+                updated_at = NOW()
+            RETURNING last_issued
+            "#,
+        )
+        .bind(&user_address)
+        .fetch_one(pool)
+        .await?;
+        Ok(nonce as u64)
+    }
+
+    async fn issue_shared(redis: &RedisClient, pool: &PgPool, user_address: &str) -> Result<u64, NonceError> {
+        let key = CacheKey::trading_nonce(user_address);
+        let mut nonce = redis.incr(&key).await?;
+        if nonce == 1 {
+            // New counter (first nonce or Redis flushed): continue above the high-water mark
+            let floor: Option<i64> =
+                sqlx::query_scalar("SELECT last_issued FROM trading_nonces WHERE user_address = $1")
+                    .bind(user_address)
+                    .fetch_optional(pool)
+                    .await?;
+            if let Some(floor) = floor.filter(|floor| *floor > 0) {
+                nonce = redis.incrby(&key, floor).await?;
+            }
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO trading_nonces (user_address, last_issued)
+            VALUES ($1, $2)
+            ON CONFLICT (user_address) DO UPDATE SET
+                last_issued = GREATEST(trading_nonces.last_issued, EXCLUDED.last_issued),
+                updated_at = NOW()
+            "#,
+        )
+        .bind(user_address)
+        .bind(nonce)
+        .execute(pool)
+        .await?;
+        Ok(nonce as u64)
+    }
+
+    /// Mark `nonce` used by `user_address`; it must have been issued to the
+    /// account and not used before
+    pub async fn consume(&self, pool: &PgPool, user_address: &str, nonce: u64) -> Result<(), NonceError> {
+        let user_address = user_address.to_lowercase();
+        let used = sqlx::query(
+            r#"
+            INSERT INTO used_trading_nonces (user_address, nonce)
+            SELECT user_address, $2 FROM trading_nonces
+            WHERE user_address = $1 AND $2 BETWEEN 1 AND last_issued
+            ON CONFLICT (user_address, nonce) DO NOTHING
+            "#,
+        )
+        .bind(&user_address)
+        .bind(nonce as i64)
+        .execute(pool)
+        .await?;
+        if used.rows_affected() == 1 {
+            return Ok(());
+        }
+
+        let reused: bool = sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM used_trading_nonces WHERE user_address = $1 AND nonce = $2)",
+        )
+        .bind(&user_address)
+        .bind(nonce as i64)
+        .fetch_one(pool)
+        .await?;
+        Err(if reused { NonceError::Reused(nonce) } else { NonceError::NotIssued(nonce) })
+    }
+
+    /// Mark a signed message (by its EIP-712 struct hash) used by
+    /// `user_address`; it must not have been used before
+    pub async fn consume_signature(
+        &self,
+        pool: &PgPool,
+        user_address: &str,
+        message_hash: &str,
+    ) -> Result<(), NonceError> {
+        let used = sqlx::query(
+            r#"
+            INSERT INTO used_signatures (message_hash, user_address)
+            VALUES ($1, $2)
+            ON CONFLICT (message_hash) DO NOTHING
+            "#,
+        )
+        .bind(message_hash.to_lowercase())
+        .bind(user_address.to_lowercase())
+        .execute(pool)
+        .await?;
+        if used.rows_affected() == 1 {
+            Ok(())
+        } else {
+            Err(NonceError::SignatureReused)
+        }
+    }
+
+    /// Forget nonces and signatures used before `older_than_secs` ago. Their
+    /// messages carry timestamps too old to pass validation, so they cannot
+    /// be replayed.
+    pub async fn purge_used(pool: &PgPool, older_than_secs: u64) -> Result<u64, sqlx::Error> {
+        let nonces = sqlx::query("DELETE FROM used_trading_nonces WHERE used_at < NOW() - make_interval(secs => $1)")
+            .bind(older_than_secs as f64)
+            .execute(pool)
+            .await?;
+        let signatures = sqlx::query("DELETE FROM used_signatures WHERE used_at < NOW() - make_interval(secs => $1)")
+            .bind(older_than_secs as f64)
+            .execute(pool)
+            .await?;
+        Ok(nonces.rows_affected() + signatures.rows_affected())
+    }
+}
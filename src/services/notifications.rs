@@ -0,0 +1,79 @@
+//! Account Notifications
+//!
+//! Forced position reductions the user did not ask for (liquidations and
+//! auto-deleveraging) and margin calls warning of them. The services that
+//! carry them out send a
+//! [`NotificationEvent`] on the notification channel; WebSocket connections
+//! deliver it to the affected account when it subscribes to `notifications`.
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::services::margin_call::MarginCallLevel;
+
+/// A position (partly) closed by the liquidation engine
+#[derive(Debug, Clone, Serialize)]
+pub struct LiquidationNotice {
+    pub liquidation_id: Uuid,
+    pub order_id: Uuid,
+    pub symbol: String,
+    pub market_id: Uuid,
+    pub outcome_id: Uuid,
+    /// Shares sold
+    pub filled_amount: Decimal,
+    /// Shares held before the liquidation
+    pub position_amount: Decimal,
+    pub price: Decimal,
+    pub mark_price: Decimal,
+    /// Liquidation fee charged (routed to the insurance fund)
+    pub fee: Decimal,
+    pub realized_pnl: Decimal,
+    pub is_partial: bool,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// A profitable position reduced against a bankrupt counterparty
+#[derive(Debug, Clone, Serialize)]
+pub struct AdlNotice {
+    pub adl_id: Uuid,
+    pub symbol: String,
+    /// Shares closed
+    pub amount: Decimal,
+    pub price: Decimal,
+    pub realized_pnl: Decimal,
+    /// Queue rank the position was selected at
+    pub rank: Option<i32>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// An account's margin ratio reached a margin call step
+#[derive(Debug, Clone, Serialize)]
+pub struct MarginCallNotice {
+    pub level: MarginCallLevel,
+    pub margin_ratio: Decimal,
+    /// Ratio of the step reached
+    pub threshold: Decimal,
+    pub equity: Decimal,
+    pub maintenance_margin: Decimal,
+    /// Percent of every position being sold (reduce step, if opted in)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_reduce_percent: Option<Decimal>,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Notification {
+    Liquidation(LiquidationNotice),
+    Adl(AdlNotice),
+    MarginCall(MarginCallNotice),
+}
+
+/// Notification addressed to one account
+#[derive(Debug, Clone)]
+pub struct NotificationEvent {
+    /// Lowercase address of the affected account
+    pub user_address: String,
+    pub notification: Notification,
+}
@@ -0,0 +1,227 @@
+//! Event Outbox
+//!
+//! Events bound for Redis are written to `event_outbox` in the same
+//! transaction as the rows they describe ([`enqueue`]), then published by the
+//! relay job ([`publish_pending`]). An event exists if and only if its trade
+//! was persisted, and a Redis outage only delays it: the relay keeps retrying
+//! from the oldest unpublished event, and stops at the first failure so a
+//! channel's events go out in order.
+//!
+//! Delivery is at least once: an event published just before its row could be
+//! marked is published again on the next run. Each channel numbers its events
+//! from 1 without gaps and every message carries its number
+//! ([`OutboxMessage`]), so consumers drop duplicates and notice missed
+//! messages with a [`SequenceTracker`].
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::{PgConnection, PgPool};
+
+use crate::cache::pubsub::Publisher;
+use crate::metrics;
+
+/// Most events published per relay run
+const BATCH_SIZE: i64 = 500;
+
+/// Outbox errors
+#[derive(Debug, thiserror::Error)]
+pub enum OutboxError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// What is published for an outbox event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxMessage<T> {
+    /// Position of the event in its channel, from 1
+    pub sequence: i64,
+    pub payload: T,
+}
+
+/// Outcome of a relay run
+#[derive(Debug, Default, Serialize)]
+pub struct RelayRun {
+    pub published: usize,
+    pub failed: bool,
+}
+
+#[derive(sqlx::FromRow)]
+struct PendingEvent {
+    id: i64,
+    channel: String,
+    sequence: i64,
+    payload: serde_json::Value,
+}
+
+/// Add an event for `channel` inside the caller's transaction; returns its sequence
+pub async fn enqueue<T: Serialize + Sync>(conn: &mut PgConnection, channel: &str, payload: &T) -> Result<i64, sqlx::Error> {
+    let sequence: i64 = sqlx::query_scalar(
+        r#"
+        INSERT INTO event_outbox_sequences (channel, last_sequence)
+        VALUES ($1, 1)
+        ON CONFLICT (channel) DO UPDATE SET last_sequence = event_outbox_sequences.last_sequence + 1
+        RETURNING last_sequence
+        "#,
+    )
+    .bind(channel)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    sqlx::query("INSERT INTO event_outbox (channel, sequence, payload) VALUES ($1, $2, $3)")
+        .bind(channel)
+        .bind(sequence)
+        .bind(Json(payload))
+        .execute(&mut *conn)
+        .await?;
+    Ok(sequence)
+}
+
+/// Publish the oldest unpublished events. A failed publish is recorded on its
+/// event and ends the run; it is retried on the next one.
+pub async fn publish_pending(pool: &PgPool, publisher: &Publisher) -> Result<RelayRun, OutboxError> {
+    let mut tx = pool.begin().await?;
+    // Rows stay locked until marked, so concurrent relays never publish the same batch
+    let events: Vec<PendingEvent> = sqlx::query_as(
+        r#"
+        SELECT id, channel, sequence, payload
+        FROM event_outbox
+        WHERE published_at IS NULL
+        ORDER BY id
+        LIMIT $1
+        FOR UPDATE SKIP LOCKED
+        "#,
+    )
+    .bind(BATCH_SIZE)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut run = RelayRun::default();
+    let mut published = Vec::with_capacity(events.len());
+    for event in events {
+        let message = OutboxMessage {
+            sequence: event.sequence,
+            payload: event.payload,
+        };
+        match publisher.publish_json(&event.channel, &message).await {
+            Ok(_) => published.push(event.id),
+            Err(e) => {
+                tracing::warn!("Outbox event {} on {} not published: {}", event.id, event.channel, e);
+                sqlx::query("UPDATE event_outbox SET attempts = attempts + 1, last_error = $2 WHERE id = $1")
+                    .bind(event.id)
+                    .bind(e.to_string())
+                    .execute(&mut *tx)
+                    .await?;
+                run.failed = true;
+                break;
+            }
+        }
+    }
+
+    if !published.is_empty() {
+        sqlx::query("UPDATE event_outbox SET published_at = NOW(), attempts = attempts + 1 WHERE id = ANY($1)")
+            .bind(&published)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    run.published = published.len();
+    metrics::record_outbox_run(run.published, run.failed);
+    Ok(run)
+}
+
+/// Events not published yet
+pub async fn pending(pool: &PgPool) -> Result<i64, OutboxError> {
+    let count = sqlx::query_scalar("SELECT COUNT(*) FROM event_outbox WHERE published_at IS NULL")
+        .fetch_one(pool)
+        .await?;
+    Ok(count)
+}
+
+/// Delete events older than `retention_hours`. Unpublished ones go too: with
+/// no relay running (no Redis) the outbox would otherwise grow forever.
+pub async fn purge(pool: &PgPool, retention_hours: i64) -> Result<u64, OutboxError> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM event_outbox
+        WHERE created_at < NOW() - make_interval(hours => $1::int)
+        "#,
+    )
+    .bind(retention_hours)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// How a received message relates to its channel's sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    /// Next in sequence (or the first seen on the channel)
+    InOrder,
+    /// Already seen: a redelivery
+    Duplicate,
+    /// Ahead of the sequence: `missed` messages never arrived
+    Gap { missed: i64 },
+}
+
+/// Last sequence seen per channel, on the consuming side
+#[derive(Debug, Default)]
+pub struct SequenceTracker {
+    last: HashMap<String, i64>,
+}
+
+impl SequenceTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Classify a message; duplicates leave the tracker unchanged
+    pub fn observe(&mut self, channel: &str, sequence: i64) -> Delivery {
+        let Some(last) = self.last.get_mut(channel) else {
+            self.last.insert(channel.to_string(), sequence);
+            return Delivery::InOrder;
+        };
+        if sequence <= *last {
+            return Delivery::Duplicate;
+        }
+        let missed = sequence - *last - 1;
+        *last = sequence;
+        if missed > 0 {
+            Delivery::Gap { missed }
+        } else {
+            Delivery::InOrder
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequence_tracker() {
+        let mut tracker = SequenceTracker::new();
+        assert_eq!(tracker.observe("channel:trades:A", 5), Delivery::InOrder);
+        assert_eq!(tracker.observe("channel:trades:A", 6), Delivery::InOrder);
+        assert_eq!(tracker.observe("channel:trades:A", 6), Delivery::Duplicate);
+        assert_eq!(tracker.observe("channel:trades:A", 4), Delivery::Duplicate);
+        assert_eq!(tracker.observe("channel:trades:A", 9), Delivery::Gap { missed: 2 });
+        assert_eq!(tracker.observe("channel:trades:A", 10), Delivery::InOrder);
+        // Channels are numbered independently
+        assert_eq!(tracker.observe("channel:trades:B", 1), Delivery::InOrder);
+    }
+
+    #[test]
+    fn test_message_round_trip() {
+        let message = OutboxMessage {
+            sequence: 3,
+            payload: serde_json::json!({ "trade_id": "t1" }),
+        };
+        let json = serde_json::to_string(&message).unwrap();
+        let decoded: OutboxMessage<serde_json::Value> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.sequence, 3);
+        assert_eq!(decoded.payload["trade_id"], "t1");
+    }
+}
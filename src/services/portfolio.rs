@@ -0,0 +1,361 @@
+//! Portfolio PnL Analytics
+//!
+//! Once a UTC day's settlement prices are published, every account with
+//! collateral or holdings gets one row in `equity_snapshots`: collateral,
+//! holdings marked at the day's settlement prices (average cost for a symbol
+//! without one), unrealized and realized PnL, traded volume and fees paid.
+//! Portfolio charts read these rows instead of replaying trades.
+//!
+//! The snapshot waits for the day's settlement prices for up to
+//! [`MARK_GRACE_MINS`] after midnight, then takes what is published.
+//! Collateral is read when the snapshot runs, shortly after the day ends.
+//!
+//! Range summaries (realized PnL, win rate, volume, fees) are computed from
+//! `pnl_history` and `trades` directly, so they include the current day.
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sqlx::PgPool;
+
+use crate::services::settlement_price::settled_day;
+
+/// How long after midnight a snapshot waits for missing settlement prices
+pub const MARK_GRACE_MINS: i64 = 60;
+
+/// Longest range served per request
+const MAX_RANGE_DAYS: i64 = 366;
+
+/// Portfolio errors
+#[derive(Debug, thiserror::Error)]
+pub enum PortfolioError {
+    #[error("Invalid date range: {0}")]
+    InvalidRange(String),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// End-of-day equity and PnL of an account
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+pub struct DailyPnl {
+    pub snapshot_date: NaiveDate,
+    pub token: String,
+    pub collateral: Decimal,
+    pub positions_value: Decimal,
+    pub unrealized_pnl: Decimal,
+    pub equity: Decimal,
+    /// Realized during the day
+    pub realized_pnl: Decimal,
+    /// Realized from the first day of the range through this day
+    #[sqlx(default)]
+    pub cumulative_realized_pnl: Decimal,
+    pub volume: Decimal,
+    pub fees_paid: Decimal,
+    pub trade_count: i32,
+}
+
+/// Trading performance of an account over a date range
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PnlSummary {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub realized_pnl: Decimal,
+    pub volume: Decimal,
+    pub fees_paid: Decimal,
+    pub trade_count: i64,
+    /// Position decreases that realized PnL
+    pub closed_count: i64,
+    pub winning_count: i64,
+    pub losing_count: i64,
+    /// Winning share of closes that were not breakeven, in percent
+    pub win_rate: Option<Decimal>,
+}
+
+/// Realized PnL statistics over a range
+#[derive(sqlx::FromRow)]
+struct RealizedRow {
+    realized_pnl: Option<Decimal>,
+    closed_count: i64,
+    winning_count: i64,
+    losing_count: i64,
+}
+
+/// Trading activity over a range
+#[derive(sqlx::FromRow)]
+struct ActivityRow {
+    volume: Option<Decimal>,
+    fees_paid: Option<Decimal>,
+    trade_count: i64,
+}
+
+/// Winning share of closes that were not breakeven, in percent
+pub fn win_rate(winning: i64, losing: i64) -> Option<Decimal> {
+    let decided = winning + losing;
+    (decided > 0).then(|| (Decimal::from(winning) * Decimal::ONE_HUNDRED / Decimal::from(decided)).round_dp(2))
+}
+
+/// Fill in the running realized PnL of consecutive days, oldest first
+pub fn accumulate(days: &mut [DailyPnl]) {
+    let mut total = Decimal::ZERO;
+    for day in days {
+        total += day.realized_pnl;
+        day.cumulative_realized_pnl = total;
+    }
+}
+
+/// Validate a date range
+pub fn check_range(from: NaiveDate, to: NaiveDate) -> Result<(), PortfolioError> {
+    if from > to {
+        return Err(PortfolioError::InvalidRange(format!("from {} is after to {}", from, to)));
+    }
+    if (to - from).num_days() > MAX_RANGE_DAYS {
+        return Err(PortfolioError::InvalidRange(format!("at most {} days per request", MAX_RANGE_DAYS)));
+    }
+    Ok(())
+}
+
+/// `[start of from, end of to)` in UTC
+fn day_bounds(from: NaiveDate, to: NaiveDate) -> Result<(DateTime<Utc>, DateTime<Utc>), PortfolioError> {
+    let start = from.and_hms_opt(0, 0, 0).map(|t| t.and_utc());
+    let end = (to + Duration::days(1)).and_hms_opt(0, 0, 0).map(|t| t.and_utc());
+    start.zip(end).ok_or_else(|| PortfolioError::InvalidRange(format!("{} to {}", from, to)))
+}
+
+/// Portfolio analytics service
+pub struct PortfolioService;
+
+impl PortfolioService {
+    /// Snapshot every account for the last closed day, once its settlement
+    /// prices are in (or the grace period is over); returns the number of
+    /// accounts recorded
+    pub async fn snapshot_due(pool: &PgPool, now: DateTime<Utc>, token: &str) -> Result<u64, PortfolioError> {
+        let date = settled_day(now);
+        let (day_start, day_end) = day_bounds(date, date)?;
+
+        if now < day_end + Duration::minutes(MARK_GRACE_MINS) {
+            let unpriced: i64 = sqlx::query_scalar(
+                r#"
+                SELECT COUNT(DISTINCT (s.market_id, s.outcome_id, s.share_type)) FROM shares s
+                WHERE s.amount <> 0 AND NOT EXISTS (
+                    SELECT 1 FROM settlement_prices p
+                    WHERE p.symbol = s.market_id::text || ':' || s.outcome_id::text || ':' || s.share_type::text
+                      AND p.settlement_date = $1
+                )
+                "#,
+            )
+            .bind(date)
+            .fetch_one(pool)
+            .await?;
+            if unpriced > 0 {
+                tracing::debug!("Equity snapshots for {} wait for {} settlement prices", date, unpriced);
+                return Ok(0);
+            }
+        }
+
+        let result = sqlx::query(
+            r#"
+            WITH accounts AS (
+                SELECT user_address FROM balances WHERE token = $2
+                UNION
+                SELECT user_address FROM shares WHERE amount <> 0
+            ),
+            pending AS (
+                SELECT a.user_address FROM accounts a
+                WHERE NOT EXISTS (
+                    SELECT 1 FROM equity_snapshots e WHERE e.user_address = a.user_address AND e.snapshot_date = $1
+                )
+            ),
+            holdings AS (
+                SELECT s.user_address,
+                       SUM(s.amount * COALESCE(p.price, s.avg_cost)) AS positions_value,
+                       SUM(s.amount * (COALESCE(p.price, s.avg_cost) - s.avg_cost)) AS unrealized_pnl
+                FROM shares s
+                LEFT JOIN settlement_prices p
+                  ON p.symbol = s.market_id::text || ':' || s.outcome_id::text || ':' || s.share_type::text
+                 AND p.settlement_date = $1
+                WHERE s.amount <> 0
+                GROUP BY s.user_address
+            ),
+            realized AS (
+                SELECT user_address, SUM(realized_pnl) AS realized_pnl
+                FROM pnl_history
+                WHERE created_at >= $3 AND created_at < $4
+                GROUP BY user_address
+            ),
+            fills AS (
+                SELECT maker_address AS user_address, amount * price AS notional, maker_fee AS fee
+                FROM trades WHERE created_at >= $3 AND created_at < $4
+                UNION ALL
+                SELECT taker_address, amount * price, taker_fee
+                FROM trades WHERE created_at >= $3 AND created_at < $4
+            ),
+            activity AS (
+                SELECT user_address, SUM(notional) AS volume, SUM(fee) AS fees_paid, COUNT(*) AS trade_count
+                FROM fills
+                GROUP BY user_address
+            )
+            INSERT INTO equity_snapshots (
+                user_address, snapshot_date, token, collateral, positions_value, unrealized_pnl, equity,
+                realized_pnl, volume, fees_paid, trade_count
+            )
+            SELECT p.user_address, $1, $2,
+                   COALESCE(b.available + b.frozen, 0),
+                   COALESCE(h.positions_value, 0),
+                   COALESCE(h.unrealized_pnl, 0),
+                   COALESCE(b.available + b.frozen, 0) + COALESCE(h.positions_value, 0),
+                   COALESCE(r.realized_pnl, 0),
+                   COALESCE(a.volume, 0),
+                   COALESCE(a.fees_paid, 0),
+                   COALESCE(a.trade_count, 0)
+            FROM pending p
+            LEFT JOIN balances b ON b.user_address = p.user_address AND b.token = $2
+            LEFT JOIN holdings h ON h.user_address = p.user_address
+            LEFT JOIN realized r ON r.user_address = p.user_address
+            LEFT JOIN activity a ON a.user_address = p.user_address
+            ON CONFLICT (user_address, snapshot_date) DO NOTHING
+            "#,
+        )
+        .bind(date)
+        .bind(token)
+        .bind(day_start)
+        .bind(day_end)
+        .execute(pool)
+        .await?;
+
+        let recorded = result.rows_affected();
+        if recorded > 0 {
+            tracing::info!("Equity snapshots for {}: {} accounts", date, recorded);
+        }
+        Ok(recorded)
+    }
+
+    /// Daily snapshots of an account for `[from, to]`, oldest first
+    pub async fn daily(
+        pool: &PgPool,
+        user_address: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<DailyPnl>, PortfolioError> {
+        check_range(from, to)?;
+        let mut days: Vec<DailyPnl> = sqlx::query_as(
+            r#"
+            SELECT snapshot_date, token, collateral, positions_value, unrealized_pnl, equity,
+                   realized_pnl, volume, fees_paid, trade_count
+            FROM equity_snapshots
+            WHERE user_address = $1 AND snapshot_date BETWEEN $2 AND $3
+            ORDER BY snapshot_date
+            "#,
+        )
+        .bind(user_address.to_lowercase())
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await?;
+        accumulate(&mut days);
+        Ok(days)
+    }
+
+    /// Realized PnL, win rate, volume and fees of an account over `[from, to]`
+    pub async fn summary(
+        pool: &PgPool,
+        user_address: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<PnlSummary, PortfolioError> {
+        check_range(from, to)?;
+        let (start, end) = day_bounds(from, to)?;
+        let user_address = user_address.to_lowercase();
+
+        let realized: RealizedRow = sqlx::query_as(
+            r#"
+            SELECT SUM(realized_pnl) AS realized_pnl,
+                   COUNT(*) AS closed_count,
+                   COUNT(*) FILTER (WHERE realized_pnl > 0) AS winning_count,
+                   COUNT(*) FILTER (WHERE realized_pnl < 0) AS losing_count
+            FROM pnl_history
+            WHERE user_address = $1 AND created_at >= $2 AND created_at < $3
+            "#,
+        )
+        .bind(&user_address)
+        .bind(start)
+        .bind(end)
+        .fetch_one(pool)
+        .await?;
+
+        let activity: ActivityRow = sqlx::query_as(
+            r#"
+            SELECT SUM(amount * price) AS volume,
+                   SUM(CASE WHEN maker_address = $1 THEN maker_fee ELSE 0 END
+                     + CASE WHEN taker_address = $1 THEN taker_fee ELSE 0 END) AS fees_paid,
+                   COUNT(*) AS trade_count
+            FROM trades
+            WHERE (maker_address = $1 OR taker_address = $1) AND created_at >= $2 AND created_at < $3
+            "#,
+        )
+        .bind(&user_address)
+        .bind(start)
+        .bind(end)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(PnlSummary {
+            from,
+            to,
+            realized_pnl: realized.realized_pnl.unwrap_or(Decimal::ZERO),
+            volume: activity.volume.unwrap_or(Decimal::ZERO),
+            fees_paid: activity.fees_paid.unwrap_or(Decimal::ZERO),
+            trade_count: activity.trade_count,
+            closed_count: realized.closed_count,
+            winning_count: realized.winning_count,
+            losing_count: realized.losing_count,
+            win_rate: win_rate(realized.winning_count, realized.losing_count),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn day(date: NaiveDate, realized_pnl: Decimal) -> DailyPnl {
+        DailyPnl {
+            snapshot_date: date,
+            token: "USDC".to_string(),
+            collateral: Decimal::ZERO,
+            positions_value: Decimal::ZERO,
+            unrealized_pnl: Decimal::ZERO,
+            equity: Decimal::ZERO,
+            realized_pnl,
+            cumulative_realized_pnl: Decimal::ZERO,
+            volume: Decimal::ZERO,
+            fees_paid: Decimal::ZERO,
+            trade_count: 0,
+        }
+    }
+
+    #[test]
+    fn test_win_rate_ignores_breakeven() {
+        assert_eq!(win_rate(3, 1), Some(dec!(75)));
+        assert_eq!(win_rate(1, 2), Some(dec!(33.33)));
+        assert_eq!(win_rate(0, 0), None);
+    }
+
+    #[test]
+    fn test_accumulates_realized_pnl() {
+        let d = NaiveDate::from_ymd_opt(2026, 10, 12).unwrap();
+        let mut days = vec![day(d, dec!(10)), day(d + Duration::days(1), dec!(-4)), day(d + Duration::days(2), dec!(1.5))];
+        accumulate(&mut days);
+        let cumulative: Vec<Decimal> = days.iter().map(|d| d.cumulative_realized_pnl).collect();
+        assert_eq!(cumulative, vec![dec!(10), dec!(6), dec!(7.5)]);
+    }
+
+    #[test]
+    fn test_check_range() {
+        let d = NaiveDate::from_ymd_opt(2026, 10, 12).unwrap();
+        assert!(check_range(d, d).is_ok());
+        assert!(check_range(d, d - Duration::days(1)).is_err());
+        assert!(check_range(d - Duration::days(400), d).is_err());
+    }
+}
@@ -0,0 +1,1835 @@
+//! Position Service
+//!
+//! Account-level view of a user's share holdings ("positions") and the margin
+//! backing them. Two margin modes are supported per user:
+//!
+//! - `isolated` (default): each position is backed only by its own cost basis;
+//!   the free collateral balance is not at risk and does not count as equity
+//! - `cross`: all open positions share the collateral balance, so account
+//!   equity = collateral (available + frozen) + positions marked to the book
+//!
+//! Individual positions may override the account mode (`shares.margin_mode`);
+//! positions without an override follow the account mode.
+//!
+//! In both modes maintenance margin is a rate of each position's marked value
+//! and the margin ratio is `total maintenance margin / equity` (>= 1 means the
+//! account is under water). The rate comes from the market's risk-limit tier
+//! for the position's notional (`markets.margin_tiers`), falling back to the
+//! global `maintenance_margin_rate` for markets without tiers.
+//!
+//! Fills are netted against the existing holding: a fill opposite to the
+//! position (a sell against a long, a buy against a short) first closes it,
+//! realizing PnL against the average cost, and only the residual opens a new
+//! position at the fill price. Realized PnL is credited to the collateral
+//! balance and recorded in `pnl_history`. Markets quoted in another asset
+//! realize PnL in that asset; it is converted into collateral at the
+//! settlement's rate, which is recorded with the PnL row.
+//!
+//! Holdings are versioned (`shares.version`). A fill reads the holding, nets
+//! against it and writes back only if the version is unchanged; a concurrent
+//! writer in between makes the write miss, and the fill is retried against the
+//! fresh row. Simultaneous fills for the same user and outcome therefore never
+//! overwrite each other.
+//!
+//! Each holding also carries the running totals of the position it currently
+//! holds ([`PositionCycle`]): shares and notional opened and closed, realized
+//! PnL, trading fees and funding paid since it opened. When the position
+//! closes (back to zero, flipped to the other side, liquidated or settled)
+//! the totals are archived to `position_history` and reset.
+//!
+//! A fill whose market or conversion rate cannot be loaded is still applied:
+//! its realized PnL is recorded unconverted with `settlement_pending` and
+//! converted, credited and added to the position's totals once the rate is
+//! known ([`PositionService::settle_deferred_pnl`]).
+//!
+//! Every trade fill is attributed in `trade_fills`: the holding it was netted
+//! into, the user's role and own side, the fee in the collateral token and
+//! the PnL the fill realized.
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::metrics;
+use crate::models::market::ShareType;
+use crate::services::ledger::{BalanceChange, LedgerReason, LedgerService};
+use crate::services::matching::MatchingEngine;
+use crate::services::market::MarketService;
+use crate::services::user_events::{self, PositionEvent};
+use crate::services::vault::mark_price;
+
+/// Attempts at applying a fill before giving up on version conflicts
+const MAX_FILL_ATTEMPTS: usize = 8;
+
+/// Run a versioned write until it lands: `attempt` returns None when the
+/// version it read was overwritten, and is retried (after `on_conflict`, with
+/// the attempt number) up to `max_attempts` times. None if every attempt
+/// conflicted.
+async fn retry_on_conflict<T, E, Fut>(
+    max_attempts: usize,
+    mut attempt: impl FnMut() -> Fut,
+    mut on_conflict: impl FnMut(usize),
+) -> Result<Option<T>, E>
+where
+    Fut: std::future::Future<Output = Result<Option<T>, E>>,
+{
+    for n in 1..=max_attempts {
+        if let Some(done) = attempt().await? {
+            return Ok(Some(done));
+        }
+        on_conflict(n);
+        tokio::task::yield_now().await;
+    }
+    Ok(None)
+}
+
+/// Position service errors
+#[derive(Debug, thiserror::Error)]
+pub enum PositionError {
+    #[error("User not found: {0}")]
+    UserNotFound(String),
+
+    #[error("Invalid margin mode: {0}")]
+    InvalidMarginMode(String),
+
+    #[error("Position not found: {0}")]
+    PositionNotFound(Uuid),
+
+    #[error("Switching to {mode} margin would breach maintenance margin (ratio {ratio})")]
+    MaintenanceBreach { mode: MarginMode, ratio: Decimal },
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// Account margin mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MarginMode {
+    #[default]
+    Isolated,
+    Cross,
+}
+
+impl MarginMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MarginMode::Isolated => "isolated",
+            MarginMode::Cross => "cross",
+        }
+    }
+}
+
+impl std::fmt::Display for MarginMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl std::str::FromStr for MarginMode {
+    type Err = PositionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "isolated" => Ok(MarginMode::Isolated),
+            "cross" => Ok(MarginMode::Cross),
+            _ => Err(PositionError::InvalidMarginMode(s.to_string())),
+        }
+    }
+}
+
+/// Margin figures for a single position
+#[derive(Debug, Clone, Serialize)]
+pub struct PositionMargin {
+    pub id: Uuid,
+    pub market_id: Uuid,
+    pub outcome_id: Uuid,
+    pub share_type: ShareType,
+    pub amount: Decimal,
+    pub avg_cost: Decimal,
+    pub mark_price: Decimal,
+    /// amount * avg_cost
+    pub cost_basis: Decimal,
+    /// amount * mark_price
+    pub value: Decimal,
+    pub unrealized_pnl: Decimal,
+    /// Rate of the risk-limit tier covering the position's value
+    pub maintenance_margin_rate: Decimal,
+    pub maintenance_margin: Decimal,
+    /// Effective margin mode (position override or account mode)
+    pub margin_mode: MarginMode,
+    /// Mark price at which the backing equity falls to maintenance (None = cannot be liquidated)
+    pub liquidation_price: Option<Decimal>,
+}
+
+/// Account-level margin summary
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountMargin {
+    pub margin_mode: MarginMode,
+    /// Collateral balance (available + frozen)
+    pub collateral: Decimal,
+    pub positions_value: Decimal,
+    pub unrealized_pnl: Decimal,
+    /// Cross: collateral + positions_value; isolated: positions_value
+    pub equity: Decimal,
+    pub maintenance_margin: Decimal,
+    pub margin_ratio: Decimal,
+    pub positions: Vec<PositionMargin>,
+}
+
+/// A fill against a holding
+#[derive(Debug, Clone)]
+pub struct PositionFill {
+    pub user_address: String,
+    pub market_id: Uuid,
+    pub outcome_id: Uuid,
+    pub share_type: ShareType,
+    pub amount: Decimal,
+    pub price: Decimal,
+    pub trade_id: Option<Uuid>,
+    /// Trading fee the user paid on the fill (collateral)
+    pub fee: Decimal,
+    /// Whether the user made or took the trade
+    pub role: FillRole,
+}
+
+/// The user's role in a trade
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FillRole {
+    Maker,
+    Taker,
+}
+
+impl FillRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FillRole::Maker => "maker",
+            FillRole::Taker => "taker",
+        }
+    }
+
+    /// Roles of `user` in a trade between `maker` and `taker`: both in a
+    /// self-trade, maker first
+    pub fn of(user: &str, maker: &str, taker: &str) -> Vec<FillRole> {
+        let mut roles = Vec::with_capacity(1);
+        if maker == user {
+            roles.push(FillRole::Maker);
+        }
+        if taker == user {
+            roles.push(FillRole::Taker);
+        }
+        roles
+    }
+}
+
+/// A user's side of a trade, as recorded in `trade_fills`
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct FillAttribution {
+    pub trade_id: Uuid,
+    pub role: String,
+    /// The user's own side (mint makers buy, merge makers sell)
+    pub side: String,
+    pub share_type: ShareType,
+    /// Holding the fill was netted into (`shares.id`)
+    pub position_id: Uuid,
+    /// The user's price (complement for mint / merge makers)
+    pub price: Decimal,
+    pub amount: Decimal,
+    pub fee: Decimal,
+    pub fee_token: String,
+    /// PnL the fill realized, in the collateral token
+    pub realized_pnl: Decimal,
+}
+
+impl FillAttribution {
+    pub fn fill_role(&self) -> FillRole {
+        if self.role == FillRole::Maker.as_str() {
+            FillRole::Maker
+        } else {
+            FillRole::Taker
+        }
+    }
+}
+
+/// Attributions by trade and role: a self-trade has one per role
+pub fn attributions_by_fill(fills: Vec<FillAttribution>) -> HashMap<(Uuid, FillRole), FillAttribution> {
+    fills.into_iter().map(|fill| ((fill.trade_id, fill.fill_role()), fill)).collect()
+}
+
+/// Realized PnL waiting for its settlement rate
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct DeferredPnl {
+    pub id: Uuid,
+    pub market_id: Uuid,
+    pub user_address: String,
+    pub trade_id: Option<Uuid>,
+    /// Unconverted PnL in the market's quote asset
+    pub quote_realized_pnl: Option<Decimal>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Currency realized PnL is settled in
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settlement {
+    /// Collateral token credited
+    pub token: String,
+    /// Asset the market is quoted in (None = the collateral token)
+    pub quote_asset: Option<String>,
+    /// Collateral per unit of quote asset
+    pub conversion_rate: Decimal,
+    /// The rate is not known yet: PnL is recorded unconverted and credited
+    /// later by [`PositionService::settle_deferred_pnl`]
+    pub deferred: bool,
+}
+
+impl Settlement {
+    /// Settlement of a market quoted in the collateral token itself
+    pub fn collateral(token: &str) -> Self {
+        Self {
+            token: token.to_string(),
+            quote_asset: None,
+            conversion_rate: Decimal::ONE,
+            deferred: false,
+        }
+    }
+
+    /// Settlement whose market or conversion rate could not be loaded: the
+    /// fill is applied, its realized PnL is credited once the rate is known
+    pub fn deferred(token: &str, quote_asset: Option<String>) -> Self {
+        Self {
+            token: token.to_string(),
+            quote_asset,
+            conversion_rate: Decimal::ZERO,
+            deferred: true,
+        }
+    }
+
+    /// Convert a quote-asset amount into collateral
+    pub fn convert(&self, amount: Decimal) -> Decimal {
+        amount * self.conversion_rate
+    }
+}
+
+/// Result of netting a fill against a holding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Netting {
+    /// Shares of the existing position closed by the fill
+    pub closed: Decimal,
+    pub realized_pnl: Decimal,
+    /// Signed holding after the fill (negative = short)
+    pub amount: Decimal,
+    pub avg_cost: Decimal,
+}
+
+/// Realized PnL record
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct PnlEntry {
+    pub id: Uuid,
+    pub market_id: Uuid,
+    pub outcome_id: Uuid,
+    pub share_type: String,
+    pub trade_id: Option<Uuid>,
+    pub token: String,
+    pub amount: Decimal,
+    pub entry_price: Decimal,
+    pub exit_price: Decimal,
+    /// Realized PnL in the collateral token
+    pub realized_pnl: Decimal,
+    /// Quote asset, for markets not quoted in the collateral token
+    pub quote_asset: Option<String>,
+    /// Realized PnL in the quote asset, before conversion
+    pub quote_realized_pnl: Option<Decimal>,
+    /// Collateral per unit of quote asset at settlement
+    pub conversion_rate: Decimal,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Why a position closed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CloseReason {
+    /// Closed (or flipped) by the user's own fills
+    Trade,
+    /// Closed by a liquidation order
+    Liquidation,
+    /// Redeemed when its market settled
+    Settlement,
+}
+
+impl CloseReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CloseReason::Trade => "trade",
+            CloseReason::Liquidation => "liquidation",
+            CloseReason::Settlement => "settlement",
+        }
+    }
+}
+
+/// Running totals of a position from the fill that opened it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, sqlx::FromRow)]
+pub struct PositionCycle {
+    /// Shares opened, and what they cost
+    pub entry_amount: Decimal,
+    pub entry_notional: Decimal,
+    /// Shares closed, and what they fetched
+    pub exit_amount: Decimal,
+    pub exit_notional: Decimal,
+    /// Realized PnL in collateral
+    pub realized_pnl: Decimal,
+    pub fees_paid: Decimal,
+    /// Funding paid from the balance (negative = received); funding an
+    /// isolated position absorbs into its cost basis shows in `realized_pnl`
+    pub funding_paid: Decimal,
+}
+
+impl PositionCycle {
+    pub fn avg_entry_price(&self) -> Decimal {
+        if self.entry_amount.is_zero() {
+            Decimal::ZERO
+        } else {
+            self.entry_notional / self.entry_amount
+        }
+    }
+
+    pub fn avg_exit_price(&self) -> Decimal {
+        if self.exit_amount.is_zero() {
+            Decimal::ZERO
+        } else {
+            self.exit_notional / self.exit_amount
+        }
+    }
+}
+
+/// A closed position
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct ClosedPosition {
+    pub id: Uuid,
+    pub market_id: Uuid,
+    pub outcome_id: Uuid,
+    pub share_type: String,
+    /// long or short
+    pub side: String,
+    pub token: String,
+    /// Shares opened over the life of the position
+    pub amount: Decimal,
+    pub avg_entry_price: Decimal,
+    pub avg_exit_price: Decimal,
+    /// Realized PnL before fees and balance-settled funding (after the
+    /// funding an isolated position absorbed into its cost basis)
+    pub realized_pnl: Decimal,
+    pub fees: Decimal,
+    /// Funding paid from the balance (negative = received)
+    pub funding: Decimal,
+    /// trade, liquidation or settlement
+    pub close_reason: String,
+    /// Fill that closed the position (None for settlements)
+    pub trade_id: Option<Uuid>,
+    pub opened_at: DateTime<Utc>,
+    pub closed_at: DateTime<Utc>,
+}
+
+/// Totals over closed positions
+#[derive(Debug, Clone, Default, Serialize, sqlx::FromRow)]
+pub struct PositionHistoryStats {
+    pub positions: i64,
+    /// Positions closed with a positive / negative net PnL
+    pub wins: i64,
+    pub losses: i64,
+    pub realized_pnl: Decimal,
+    pub fees: Decimal,
+    pub funding: Decimal,
+    /// realized_pnl - fees - funding
+    pub net_pnl: Decimal,
+}
+
+/// Share holding row backing a position
+#[derive(Debug, sqlx::FromRow)]
+struct ShareRow {
+    id: Uuid,
+    market_id: Uuid,
+    outcome_id: Uuid,
+    share_type: String,
+    amount: Decimal,
+    avg_cost: Decimal,
+    margin_mode: Option<String>,
+}
+
+/// Holding as read by a fill
+#[derive(Debug, sqlx::FromRow)]
+struct HeldShare {
+    id: Uuid,
+    amount: Decimal,
+    avg_cost: Decimal,
+    version: i64,
+    opened_at: Option<DateTime<Utc>>,
+    #[sqlx(flatten)]
+    cycle: PositionCycle,
+}
+
+/// A position being archived to `position_history`
+struct PositionClose<'a> {
+    share_id: Uuid,
+    long: bool,
+    token: &'a str,
+    cycle: PositionCycle,
+    reason: CloseReason,
+    trade_id: Option<Uuid>,
+    opened_at: Option<DateTime<Utc>>,
+}
+
+/// Build a position's margin figures from its holding and mark price
+#[allow(clippy::too_many_arguments)]
+pub fn position_margin(
+    id: Uuid,
+    market_id: Uuid,
+    outcome_id: Uuid,
+    share_type: ShareType,
+    amount: Decimal,
+    avg_cost: Decimal,
+    mark_price: Decimal,
+    maintenance_margin_rate: Decimal,
+    margin_mode: MarginMode,
+) -> PositionMargin {
+    let cost_basis = amount * avg_cost;
+    let value = amount * mark_price;
+    PositionMargin {
+        id,
+        market_id,
+        outcome_id,
+        share_type,
+        amount,
+        avg_cost,
+        mark_price,
+        cost_basis,
+        value,
+        unrealized_pnl: value - cost_basis,
+        maintenance_margin_rate,
+        maintenance_margin: value * maintenance_margin_rate,
+        margin_mode,
+        liquidation_price: None,
+    }
+}
+
+/// Net a signed fill (`delta` > 0 buys, < 0 sells) at `price` against a signed
+/// holding of `held` shares at `avg_cost`
+///
+/// Same-direction fills grow the position at a weighted average cost. Opposite
+/// fills close up to the whole position first, realizing PnL; any residual
+/// opens a position in the fill's direction at the fill price.
+pub fn net_position(held: Decimal, avg_cost: Decimal, delta: Decimal, price: Decimal) -> Netting {
+    let amount = held + delta;
+
+    if held.is_zero() || delta.is_zero() || held.is_sign_positive() == delta.is_sign_positive() {
+        let avg_cost = if amount.is_zero() {
+            avg_cost
+        } else {
+            (held.abs() * avg_cost + delta.abs() * price) / amount.abs()
+        };
+        return Netting {
+            closed: Decimal::ZERO,
+            realized_pnl: Decimal::ZERO,
+            amount,
+            avg_cost,
+        };
+    }
+
+    let closed = delta.abs().min(held.abs());
+    let per_share = if held.is_sign_positive() { price - avg_cost } else { avg_cost - price };
+    let flipped = !amount.is_zero() && amount.is_sign_positive() != held.is_sign_positive();
+
+    Netting {
+        closed,
+        realized_pnl: per_share * closed,
+        amount,
+        avg_cost: if flipped { price } else { avg_cost },
+    }
+}
+
+/// Advance a position's running totals by a fill already netted against a
+/// holding of `held` shares. Returns the totals of the position the fill
+/// closed, if any, and those of the position open afterwards.
+///
+/// A fill that closes and reopens (a flip) has its fee split between the two
+/// positions in proportion to the shares closed and opened.
+pub fn advance_cycle(
+    cycle: PositionCycle,
+    held: Decimal,
+    netting: &Netting,
+    price: Decimal,
+    fee: Decimal,
+    realized_pnl: Decimal,
+) -> (Option<PositionCycle>, PositionCycle) {
+    let mut current = if held.is_zero() { PositionCycle::default() } else { cycle };
+    let filled = (netting.amount - held).abs();
+    let opened = filled - netting.closed;
+    let fee_share = |part: Decimal| if filled.is_zero() { Decimal::ZERO } else { fee * part / filled };
+
+    let mut closed = None;
+    if netting.closed > Decimal::ZERO {
+        current.exit_amount += netting.closed;
+        current.exit_notional += netting.closed * price;
+        current.realized_pnl += realized_pnl;
+        current.fees_paid += fee_share(netting.closed);
+        if netting.amount.is_zero() || opened > Decimal::ZERO {
+            closed = Some(current);
+            current = PositionCycle::default();
+        }
+    }
+    if opened > Decimal::ZERO {
+        current.entry_amount += opened;
+        current.entry_notional += opened * price;
+        current.fees_paid += fee_share(opened);
+    }
+    (closed, current)
+}
+
+/// Maintenance margin / equity (zero when there is no equity to measure against)
+pub fn margin_ratio(maintenance_margin: Decimal, equity: Decimal) -> Decimal {
+    if equity <= Decimal::ZERO {
+        return Decimal::ZERO;
+    }
+    maintenance_margin / equity
+}
+
+/// Mark price at which a long position's backing equity falls to maintenance margin
+///
+/// `other_equity` / `other_maintenance` are the rest of the pool backing the
+/// position (collateral and other cross positions; zero when isolated). Solves
+/// `other_equity + amount * p = other_maintenance + amount * p * rate` for `p`.
+/// None if no positive price satisfies it (the position cannot be liquidated).
+pub fn liquidation_price(
+    amount: Decimal,
+    other_equity: Decimal,
+    other_maintenance: Decimal,
+    maintenance_margin_rate: Decimal,
+) -> Option<Decimal> {
+    if amount <= Decimal::ZERO || maintenance_margin_rate >= Decimal::ONE {
+        return None;
+    }
+    let price = (other_maintenance - other_equity) / (amount * (Decimal::ONE - maintenance_margin_rate));
+    (price > Decimal::ZERO).then_some(price)
+}
+
+/// Margin ratio of the pool backing a position: the cross pool for cross
+/// positions, the position alone for isolated ones
+pub fn backing_margin_ratio(collateral: Decimal, positions: &[PositionMargin], position: &PositionMargin) -> Decimal {
+    match position.margin_mode {
+        MarginMode::Isolated => margin_ratio(position.maintenance_margin, position.value),
+        MarginMode::Cross => {
+            let cross = positions.iter().filter(|p| p.margin_mode == MarginMode::Cross);
+            let (value, maintenance) = cross.fold((Decimal::ZERO, Decimal::ZERO), |(v, m), p| {
+                (v + p.value, m + p.maintenance_margin)
+            });
+            margin_ratio(maintenance, collateral + value)
+        }
+    }
+}
+
+/// Aggregate positions into an account margin summary
+///
+/// Cross positions share the collateral balance; isolated positions stand
+/// alone. The account margin ratio is the worst ratio across the cross pool
+/// and every isolated position. Fills in each position's liquidation price at
+/// the position's current maintenance rate.
+pub fn account_margin(mode: MarginMode, collateral: Decimal, mut positions: Vec<PositionMargin>) -> AccountMargin {
+    let positions_value: Decimal = positions.iter().map(|p| p.value).sum();
+    let unrealized_pnl: Decimal = positions.iter().map(|p| p.unrealized_pnl).sum();
+    let maintenance_margin: Decimal = positions.iter().map(|p| p.maintenance_margin).sum();
+
+    let (cross_value, cross_maintenance) = positions
+        .iter()
+        .filter(|p| p.margin_mode == MarginMode::Cross)
+        .fold((Decimal::ZERO, Decimal::ZERO), |(v, m), p| (v + p.value, m + p.maintenance_margin));
+    let cross_equity = collateral + cross_value;
+
+    for p in positions.iter_mut() {
+        p.liquidation_price = match p.margin_mode {
+            MarginMode::Cross => liquidation_price(
+                p.amount,
+                cross_equity - p.value,
+                cross_maintenance - p.maintenance_margin,
+                p.maintenance_margin_rate,
+            ),
+            MarginMode::Isolated => {
+                liquidation_price(p.amount, Decimal::ZERO, Decimal::ZERO, p.maintenance_margin_rate)
+            }
+        };
+    }
+
+    let has_cross = mode == MarginMode::Cross || positions.iter().any(|p| p.margin_mode == MarginMode::Cross);
+    let equity = if has_cross { collateral + positions_value } else { positions_value };
+
+    let cross_ratio = if has_cross { margin_ratio(cross_maintenance, cross_equity) } else { Decimal::ZERO };
+    let margin_ratio = positions
+        .iter()
+        .filter(|p| p.margin_mode == MarginMode::Isolated)
+        .map(|p| margin_ratio(p.maintenance_margin, p.value))
+        .fold(cross_ratio, Decimal::max);
+
+    AccountMargin {
+        margin_mode: mode,
+        collateral,
+        positions_value,
+        unrealized_pnl,
+        equity,
+        maintenance_margin,
+        margin_ratio,
+        positions,
+    }
+}
+
+/// Position service
+pub struct PositionService;
+
+impl PositionService {
+    /// Get a user's margin mode
+    pub async fn get_margin_mode(pool: &PgPool, user_address: &str) -> Result<MarginMode, PositionError> {
+        let mode: Option<String> = sqlx::query_scalar("SELECT margin_mode FROM users WHERE address = $1")
+            .bind(user_address.to_lowercase())
+            .fetch_optional(pool)
+            .await?;
+
+        match mode {
+            Some(mode) => mode.parse(),
+            None => Err(PositionError::UserNotFound(user_address.to_string())),
+        }
+    }
+
+    /// Set a user's margin mode
+    pub async fn set_margin_mode(pool: &PgPool, user_address: &str, mode: MarginMode) -> Result<(), PositionError> {
+        let result = sqlx::query("UPDATE users SET margin_mode = $1, updated_at = NOW() WHERE address = $2")
+            .bind(mode.as_str())
+            .bind(user_address.to_lowercase())
+            .execute(pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(PositionError::UserNotFound(user_address.to_string()));
+        }
+
+        tracing::info!("Margin mode for {} set to {}", user_address, mode);
+        Ok(())
+    }
+
+    /// Open positions marked to the book, with their effective margin mode and
+    /// the maintenance rate of their market's risk-limit tier
+    /// (`maintenance_margin_rate` for markets without tiers)
+    pub async fn get_positions(
+        pool: &PgPool,
+        engine: &MatchingEngine,
+        markets: &MarketService,
+        user_address: &str,
+        account_mode: MarginMode,
+        maintenance_margin_rate: Decimal,
+    ) -> Result<Vec<PositionMargin>, PositionError> {
+        let holdings: Vec<ShareRow> = sqlx::query_as(
+            r#"
+            SELECT id, market_id, outcome_id, share_type::text AS share_type, amount, avg_cost, margin_mode
+            FROM shares
+            WHERE user_address = $1 AND amount > 0
+            ORDER BY created_at
+            "#
+        )
+        .bind(user_address.to_lowercase())
+        .fetch_all(pool)
+        .await?;
+
+        let mut positions = Vec::with_capacity(holdings.len());
+        for row in holdings {
+            let share_type: ShareType = row.share_type.parse().unwrap_or(ShareType::Yes);
+            let margin_mode = row.margin_mode.and_then(|m| m.parse().ok()).unwrap_or(account_mode);
+            let symbol = format!("{}:{}:{}", row.market_id, row.outcome_id, share_type);
+            let mark = engine.mark_price(&symbol).unwrap_or_else(|| {
+                let (best_bid, best_ask) = engine.get_best_prices(&symbol).unwrap_or((None, None));
+                let last_price = engine.get_orderbook_ref(&symbol).and_then(|ob| ob.last_trade_price());
+                mark_price(best_bid, best_ask, last_price, row.avg_cost)
+            });
+            let rate = markets
+                .get_market_config(pool, row.market_id)
+                .await?
+                .and_then(|config| config.maintenance_margin_rate(row.amount * mark))
+                .unwrap_or(maintenance_margin_rate);
+            positions.push(position_margin(
+                row.id,
+                row.market_id,
+                row.outcome_id,
+                share_type,
+                row.amount,
+                row.avg_cost,
+                mark,
+                rate,
+                margin_mode,
+            ));
+        }
+
+        Ok(positions)
+    }
+
+    /// Compute the account margin summary
+    pub async fn get_account_margin(
+        pool: &PgPool,
+        engine: &MatchingEngine,
+        markets: &MarketService,
+        user_address: &str,
+        token: &str,
+        maintenance_margin_rate: Decimal,
+    ) -> Result<AccountMargin, PositionError> {
+        let mode = Self::get_margin_mode(pool, user_address).await?;
+        let collateral = Self::collateral(pool, user_address, token).await?;
+        let positions =
+            Self::get_positions(pool, engine, markets, user_address, mode, maintenance_margin_rate).await?;
+        Ok(account_margin(mode, collateral, positions))
+    }
+
+    /// Switch a single position between isolated and cross margin
+    ///
+    /// Rejected if the pool backing the position after the switch would be at
+    /// or beyond maintenance margin. Returns the updated position.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn set_position_margin_mode(
+        pool: &PgPool,
+        engine: &MatchingEngine,
+        markets: &MarketService,
+        position_id: Uuid,
+        user_address: &str,
+        mode: MarginMode,
+        token: &str,
+        maintenance_margin_rate: Decimal,
+    ) -> Result<PositionMargin, PositionError> {
+        let mut tx = pool.begin().await?;
+
+        // Lock the position row while validating the switch
+        let locked: Option<Uuid> = sqlx::query_scalar(
+            "SELECT id FROM shares WHERE id = $1 AND user_address = $2 AND amount > 0 FOR UPDATE",
+        )
+        .bind(position_id)
+        .bind(user_address.to_lowercase())
+        .fetch_optional(&mut *tx)
+        .await?;
+        if locked.is_none() {
+            return Err(PositionError::PositionNotFound(position_id));
+        }
+
+        let account_mode = Self::get_margin_mode(pool, user_address).await?;
+        let collateral = Self::collateral(pool, user_address, token).await?;
+        let mut positions =
+            Self::get_positions(pool, engine, markets, user_address, account_mode, maintenance_margin_rate).await?;
+
+        let Some(target) = positions.iter_mut().find(|p| p.id == position_id) else {
+            return Err(PositionError::PositionNotFound(position_id));
+        };
+        target.margin_mode = mode;
+        let target = target.clone();
+
+        let ratio = backing_margin_ratio(collateral, &positions, &target);
+        if ratio >= Decimal::ONE {
+            return Err(PositionError::MaintenanceBreach { mode, ratio });
+        }
+
+        sqlx::query("UPDATE shares SET margin_mode = $1, updated_at = NOW() WHERE id = $2")
+            .bind(mode.as_str())
+            .bind(position_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        tracing::info!("Position {} of {} switched to {} margin", position_id, user_address, mode);
+
+        let summary = account_margin(account_mode, collateral, positions);
+        summary
+            .positions
+            .into_iter()
+            .find(|p| p.id == position_id)
+            .ok_or(PositionError::PositionNotFound(position_id))
+    }
+
+    /// Apply a buy / mint fill to a holding (covers a short before going long)
+    pub async fn increase_position(
+        pool: &PgPool,
+        fill: &PositionFill,
+        settlement: &Settlement,
+    ) -> Result<Decimal, sqlx::Error> {
+        Self::apply_fill(pool, fill, fill.amount, settlement).await
+    }
+
+    /// Apply a sell / merge fill to a holding (closes a long before going short)
+    pub async fn decrease_position(
+        pool: &PgPool,
+        fill: &PositionFill,
+        settlement: &Settlement,
+    ) -> Result<Decimal, sqlx::Error> {
+        Self::apply_fill(pool, fill, -fill.amount, settlement).await
+    }
+
+    /// Net a signed fill into the holding, crediting realized PnL (converted
+    /// into collateral) to the user's settlement token balance and recording it
+    /// in `pnl_history`. The new holding is published as a `PositionEvent`.
+    /// Returns the realized PnL in collateral (zero for a trade fill that was
+    /// already applied).
+    ///
+    /// Retried while a concurrent writer changes the holding in between.
+    async fn apply_fill(
+        pool: &PgPool,
+        fill: &PositionFill,
+        delta: Decimal,
+        settlement: &Settlement,
+    ) -> Result<Decimal, sqlx::Error> {
+        let applied = retry_on_conflict(
+            MAX_FILL_ATTEMPTS,
+            || Self::try_apply_fill(pool, fill, delta, settlement),
+            |attempt| {
+                metrics::record_position_version_conflict();
+                tracing::debug!(
+                    "Position {}:{} changed concurrently, retrying fill (attempt {})",
+                    fill.user_address, fill.outcome_id, attempt
+                );
+            },
+        )
+        .await?;
+
+        applied.ok_or_else(|| {
+            sqlx::Error::Protocol(format!(
+                "position {}:{} still conflicting after {} attempts",
+                fill.user_address, fill.outcome_id, MAX_FILL_ATTEMPTS
+            ))
+        })
+    }
+
+    /// One versioned attempt at a fill; None if the holding changed since it was read
+    async fn try_apply_fill(
+        pool: &PgPool,
+        fill: &PositionFill,
+        delta: Decimal,
+        settlement: &Settlement,
+    ) -> Result<Option<Decimal>, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        let applied = Self::fill_in(&mut tx, fill, delta, settlement).await?;
+        match applied {
+            Some(_) => tx.commit().await?,
+            None => tx.rollback().await?,
+        }
+        Ok(applied)
+    }
+
+    /// Apply a signed fill inside the caller's transaction, which must hold the
+    /// holding's row lock (`SELECT ... FOR UPDATE`) so the versioned write
+    /// cannot conflict. Used where the shares move together with other
+    /// balances, e.g. an RFQ block trade settled with its collateral.
+    pub async fn apply_fill_in(
+        conn: &mut PgConnection,
+        fill: &PositionFill,
+        delta: Decimal,
+        settlement: &Settlement,
+    ) -> Result<Decimal, sqlx::Error> {
+        Self::fill_in(conn, fill, delta, settlement).await?.ok_or_else(|| {
+            sqlx::Error::Protocol(format!(
+                "position {}:{} changed while locked for a fill",
+                fill.user_address, fill.outcome_id
+            ))
+        })
+    }
+
+    /// Write a fill on `conn` without committing; None if the holding changed
+    /// since it was read (the caller must then roll back)
+    async fn fill_in(
+        conn: &mut PgConnection,
+        fill: &PositionFill,
+        delta: Decimal,
+        settlement: &Settlement,
+    ) -> Result<Option<Decimal>, sqlx::Error> {
+        let user_address = fill.user_address.to_lowercase();
+
+        let held: Option<HeldShare> = sqlx::query_as(
+            r#"
+            SELECT id, amount, avg_cost, version, opened_at,
+                   entry_amount, entry_notional, exit_amount, exit_notional, realized_pnl, fees_paid, funding_paid
+            FROM shares
+            WHERE user_address = $1 AND outcome_id = $2
+            "#,
+        )
+        .bind(&user_address)
+        .bind(fill.outcome_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        let (held_amount, held_cost) = held.as_ref().map_or((Decimal::ZERO, Decimal::ZERO), |h| (h.amount, h.avg_cost));
+        let netting = net_position(held_amount, held_cost, delta, fill.price);
+        let realized_pnl = settlement.convert(netting.realized_pnl);
+        let (closed, cycle) = advance_cycle(
+            held.as_ref().map(|h| h.cycle).unwrap_or_default(),
+            held_amount,
+            &netting,
+            fill.price,
+            fill.fee,
+            realized_pnl,
+        );
+        let opened_at = match &held {
+            _ if netting.amount.is_zero() => None,
+            Some(h) if closed.is_none() && !h.amount.is_zero() => h.opened_at.or_else(|| Some(Utc::now())),
+            _ => Some(Utc::now()),
+        };
+        let position_id = held.as_ref().map_or_else(Uuid::new_v4, |h| h.id);
+        let pnl_id = (netting.closed > Decimal::ZERO).then(Uuid::new_v4);
+
+        // Claim the fill before touching the holding: a replayed trade changes nothing
+        if let Some(trade_id) = fill.trade_id {
+            let claimed = sqlx::query(
+                r#"
+                INSERT INTO trade_fills (
+                    trade_id, user_address, role, side, market_id, outcome_id, share_type,
+                    position_id, price, amount, fee, fee_token, realized_pnl, pnl_id
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7::share_type, $8, $9, $10, $11, $12, $13, $14)
+                ON CONFLICT (trade_id, user_address, role) DO NOTHING
+                "#,
+            )
+            .bind(trade_id)
+            .bind(&user_address)
+            .bind(fill.role.as_str())
+            .bind(if delta > Decimal::ZERO { "buy" } else { "sell" })
+            .bind(fill.market_id)
+            .bind(fill.outcome_id)
+            .bind(fill.share_type.to_string())
+            .bind(position_id)
+            .bind(fill.price)
+            .bind(fill.amount)
+            .bind(fill.fee)
+            .bind(&settlement.token)
+            .bind(realized_pnl)
+            .bind(pnl_id)
+            .execute(&mut *conn)
+            .await?
+            .rows_affected();
+            if claimed == 0 {
+                tracing::debug!("Fill of trade {} for {} already applied", trade_id, user_address);
+                return Ok(Some(Decimal::ZERO));
+            }
+        }
+
+        let written = match &held {
+            // Only write over the version that was read
+            Some(h) => sqlx::query(
+                r#"
+                UPDATE shares
+                SET amount = $1, avg_cost = $2, opened_at = $3,
+                    entry_amount = $4, entry_notional = $5, exit_amount = $6, exit_notional = $7,
+                    realized_pnl = $8, fees_paid = $9, funding_paid = $10,
+                    version = version + 1, updated_at = NOW()
+                WHERE id = $11 AND version = $12
+                "#,
+            )
+            .bind(netting.amount)
+            .bind(netting.avg_cost)
+            .bind(opened_at)
+            .bind(cycle.entry_amount)
+            .bind(cycle.entry_notional)
+            .bind(cycle.exit_amount)
+            .bind(cycle.exit_notional)
+            .bind(cycle.realized_pnl)
+            .bind(cycle.fees_paid)
+            .bind(cycle.funding_paid)
+            .bind(h.id)
+            .bind(h.version)
+            .execute(&mut *conn)
+            .await?,
+            // First fill: a concurrent first fill may have inserted the row meanwhile
+            None => sqlx::query(
+                r#"
+                INSERT INTO shares (
+                    id, user_address, market_id, outcome_id, share_type, amount, avg_cost,
+                    opened_at, entry_amount, entry_notional, fees_paid
+                )
+                VALUES ($1, $2, $3, $4, $5::share_type, $6, $7, $8, $9, $10, $11)
+                ON CONFLICT (user_address, outcome_id) DO NOTHING
+                "#,
+            )
+            .bind(position_id)
+            .bind(&user_address)
+            .bind(fill.market_id)
+            .bind(fill.outcome_id)
+            .bind(fill.share_type.to_string())
+            .bind(netting.amount)
+            .bind(netting.avg_cost)
+            .bind(opened_at)
+            .bind(cycle.entry_amount)
+            .bind(cycle.entry_notional)
+            .bind(cycle.fees_paid)
+            .execute(&mut *conn)
+            .await?,
+        };
+        if written.rows_affected() == 0 {
+            return Ok(None);
+        }
+
+        if let Some(pnl_id) = pnl_id {
+            // A deferred settlement keeps the unconverted PnL until it can be credited
+            sqlx::query(
+                r#"
+                INSERT INTO pnl_history (
+                    id, user_address, market_id, outcome_id, share_type, trade_id,
+                    token, amount, entry_price, exit_price, realized_pnl,
+                    quote_asset, quote_realized_pnl, conversion_rate, settlement_pending
+                )
+                VALUES ($1, $2, $3, $4, $5::share_type, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+                "#,
+            )
+            .bind(pnl_id)
+            .bind(&user_address)
+            .bind(fill.market_id)
+            .bind(fill.outcome_id)
+            .bind(fill.share_type.to_string())
+            .bind(fill.trade_id)
+            .bind(&settlement.token)
+            .bind(netting.closed)
+            .bind(held_cost)
+            .bind(fill.price)
+            .bind(realized_pnl)
+            .bind(&settlement.quote_asset)
+            .bind((settlement.quote_asset.is_some() || settlement.deferred).then_some(netting.realized_pnl))
+            .bind(settlement.conversion_rate)
+            .bind(settlement.deferred)
+            .execute(&mut *conn)
+            .await?;
+
+            if !realized_pnl.is_zero() {
+                let change = BalanceChange {
+                    reference_id: fill.trade_id,
+                    ..BalanceChange::credit(&user_address, &settlement.token, realized_pnl, LedgerReason::RealizedPnl)
+                };
+                LedgerService::apply(conn, &change).await?;
+            }
+        }
+
+        if let (Some(cycle), Some(h)) = (closed, &held) {
+            let reason = match fill.trade_id {
+                Some(trade_id) if Self::is_liquidation_fill(conn, &user_address, trade_id).await? => {
+                    CloseReason::Liquidation
+                }
+                _ => CloseReason::Trade,
+            };
+            let close = PositionClose {
+                share_id: h.id,
+                long: h.amount > Decimal::ZERO,
+                token: &settlement.token,
+                cycle,
+                reason,
+                trade_id: fill.trade_id,
+                opened_at: h.opened_at,
+            };
+            Self::archive(conn, &close).await?;
+        }
+
+        let event = PositionEvent {
+            user_address: user_address.clone(),
+            market_id: fill.market_id,
+            outcome_id: fill.outcome_id,
+            share_type: fill.share_type,
+            amount: netting.amount,
+            avg_cost: netting.avg_cost,
+            trade_id: fill.trade_id,
+        };
+        user_events::publish(&mut *conn, &event).await?;
+
+        if netting.closed > Decimal::ZERO {
+            tracing::debug!(
+                "Realized PnL {} {} for {} closing {} shares of {} (now {})",
+                realized_pnl, settlement.token, user_address, netting.closed, fill.outcome_id, netting.amount
+            );
+        }
+        Ok(Some(realized_pnl))
+    }
+
+    /// Whether a trade filled one of the user's liquidation orders
+    async fn is_liquidation_fill(conn: &mut PgConnection, user_address: &str, trade_id: Uuid) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT EXISTS (
+                SELECT 1
+                FROM trades t
+                JOIN liquidations l ON l.order_id IN (t.maker_order_id, t.taker_order_id)
+                WHERE t.id = $1 AND LOWER(l.user_address) = $2
+            )
+            "#,
+        )
+        .bind(trade_id)
+        .bind(user_address)
+        .fetch_one(conn)
+        .await
+    }
+
+    /// Record a closed position in `position_history`
+    async fn archive(conn: &mut PgConnection, close: &PositionClose<'_>) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO position_history (
+                share_id, user_address, market_id, outcome_id, share_type, side, token,
+                amount, avg_entry_price, avg_exit_price, realized_pnl, fees, funding,
+                close_reason, trade_id, opened_at
+            )
+            SELECT id, user_address, market_id, outcome_id, share_type, $2, $3,
+                   $4, $5, $6, $7, $8, $9, $10, $11, COALESCE($12, NOW())
+            FROM shares
+            WHERE id = $1
+            "#,
+        )
+        .bind(close.share_id)
+        .bind(if close.long { "long" } else { "short" })
+        .bind(close.token)
+        .bind(close.cycle.entry_amount)
+        .bind(close.cycle.avg_entry_price())
+        .bind(close.cycle.avg_exit_price())
+        .bind(close.cycle.realized_pnl)
+        .bind(close.cycle.fees_paid)
+        .bind(close.cycle.funding_paid)
+        .bind(close.reason.as_str())
+        .bind(close.trade_id)
+        .bind(close.opened_at)
+        .execute(conn)
+        .await?;
+        Ok(())
+    }
+
+    /// Archive a holding redeemed by its market's settlement at
+    /// `payout_per_share`, inside the settlement's transaction. The caller
+    /// zeroes the holding itself.
+    pub async fn close_settled(
+        conn: &mut PgConnection,
+        share_id: Uuid,
+        payout_per_share: Decimal,
+        token: &str,
+    ) -> Result<(), sqlx::Error> {
+        let held: Option<HeldShare> = sqlx::query_as(
+            r#"
+            SELECT id, amount, avg_cost, version, opened_at,
+                   entry_amount, entry_notional, exit_amount, exit_notional, realized_pnl, fees_paid, funding_paid
+            FROM shares
+            WHERE id = $1
+            FOR UPDATE
+            "#,
+        )
+        .bind(share_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+        let Some(held) = held.filter(|h| !h.amount.is_zero()) else {
+            return Ok(());
+        };
+
+        let netting = net_position(held.amount, held.avg_cost, -held.amount, payout_per_share);
+        let (closed, _) = advance_cycle(
+            held.cycle,
+            held.amount,
+            &netting,
+            payout_per_share,
+            Decimal::ZERO,
+            netting.realized_pnl,
+        );
+        if let Some(cycle) = closed {
+            let close = PositionClose {
+                share_id,
+                long: held.amount > Decimal::ZERO,
+                token,
+                cycle,
+                reason: CloseReason::Settlement,
+                trade_id: None,
+                opened_at: held.opened_at,
+            };
+            Self::archive(&mut *conn, &close).await?;
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE shares
+            SET opened_at = NULL, entry_amount = 0, entry_notional = 0, exit_amount = 0, exit_notional = 0,
+                realized_pnl = 0, fees_paid = 0, funding_paid = 0
+            WHERE id = $1
+            "#,
+        )
+        .bind(share_id)
+        .execute(conn)
+        .await?;
+        Ok(())
+    }
+
+    /// Closed positions, most recently closed first, with totals over the same filter
+    pub async fn get_position_history(
+        pool: &PgPool,
+        user_address: &str,
+        market_id: Option<Uuid>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<ClosedPosition>, PositionHistoryStats), PositionError> {
+        let user_address = user_address.to_lowercase();
+
+        let positions: Vec<ClosedPosition> = sqlx::query_as(
+            r#"
+            SELECT id, market_id, outcome_id, share_type::text AS share_type, side, token,
+                   amount, avg_entry_price, avg_exit_price, realized_pnl, fees, funding,
+                   close_reason, trade_id, opened_at, closed_at
+            FROM position_history
+            WHERE user_address = $1 AND ($2::uuid IS NULL OR market_id = $2)
+            ORDER BY closed_at DESC
+            LIMIT $3 OFFSET $4
+            "#,
+        )
+        .bind(&user_address)
+        .bind(market_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await?;
+
+        let stats: PositionHistoryStats = sqlx::query_as(
+            r#"
+            SELECT COUNT(*) AS positions,
+                   COUNT(*) FILTER (WHERE realized_pnl - fees - funding > 0) AS wins,
+                   COUNT(*) FILTER (WHERE realized_pnl - fees - funding < 0) AS losses,
+                   COALESCE(SUM(realized_pnl), 0) AS realized_pnl,
+                   COALESCE(SUM(fees), 0) AS fees,
+                   COALESCE(SUM(funding), 0) AS funding,
+                   COALESCE(SUM(realized_pnl - fees - funding), 0) AS net_pnl
+            FROM position_history
+            WHERE user_address = $1 AND ($2::uuid IS NULL OR market_id = $2)
+            "#,
+        )
+        .bind(&user_address)
+        .bind(market_id)
+        .fetch_one(pool)
+        .await?;
+
+        Ok((positions, stats))
+    }
+
+    /// Realized PnL history, newest first, with the total over the same filter
+    pub async fn get_pnl_history(
+        pool: &PgPool,
+        user_address: &str,
+        market_id: Option<Uuid>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<PnlEntry>, Decimal), PositionError> {
+        let user_address = user_address.to_lowercase();
+
+        let entries: Vec<PnlEntry> = sqlx::query_as(
+            r#"
+            SELECT id, market_id, outcome_id, share_type::text AS share_type, trade_id, token,
+                   amount, entry_price, exit_price, realized_pnl,
+                   quote_asset, quote_realized_pnl, conversion_rate, created_at
+            FROM pnl_history
+            WHERE user_address = $1 AND ($2::uuid IS NULL OR market_id = $2)
+            ORDER BY created_at DESC
+            LIMIT $3 OFFSET $4
+            "#,
+        )
+        .bind(&user_address)
+        .bind(market_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await?;
+
+        let total: Option<Decimal> = sqlx::query_scalar(
+            "SELECT SUM(realized_pnl) FROM pnl_history WHERE user_address = $1 AND ($2::uuid IS NULL OR market_id = $2)",
+        )
+        .bind(&user_address)
+        .bind(market_id)
+        .fetch_one(pool)
+        .await?;
+
+        Ok((entries, total.unwrap_or(Decimal::ZERO)))
+    }
+
+    /// Realized PnL per trade for a user
+    pub async fn realized_pnl_by_trade(
+        pool: &PgPool,
+        user_address: &str,
+        trade_ids: &[Uuid],
+    ) -> Result<Vec<(Uuid, Decimal)>, PositionError> {
+        if trade_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let rows: Vec<(Uuid, Decimal)> = sqlx::query_as(
+            r#"
+            SELECT trade_id, SUM(realized_pnl)
+            FROM pnl_history
+            WHERE user_address = $1 AND trade_id = ANY($2)
+            GROUP BY trade_id
+            "#,
+        )
+        .bind(user_address.to_lowercase())
+        .bind(trade_ids)
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
+    }
+
+    /// Realized PnL recorded under a deferred settlement, oldest first
+    pub async fn deferred_pnl(pool: &PgPool, limit: i64) -> Result<Vec<DeferredPnl>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT id, market_id, user_address, trade_id, quote_realized_pnl, created_at
+            FROM pnl_history
+            WHERE settlement_pending
+            ORDER BY created_at
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Convert deferred PnL at `settlement` and credit it, updating the fill
+    /// attribution and the totals of the position cycle it belongs to.
+    /// Returns false if it was already settled.
+    pub async fn settle_deferred_pnl(pool: &PgPool, pnl_id: Uuid, settlement: &Settlement) -> Result<bool, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        let pending: Option<DeferredPnl> = sqlx::query_as(
+            r#"
+            SELECT id, market_id, user_address, trade_id, quote_realized_pnl, created_at
+            FROM pnl_history
+            WHERE id = $1 AND settlement_pending
+            FOR UPDATE
+            "#,
+        )
+        .bind(pnl_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(pending) = pending else {
+            return Ok(false);
+        };
+        let raw_pnl = pending.quote_realized_pnl.unwrap_or(Decimal::ZERO);
+        let realized_pnl = settlement.convert(raw_pnl);
+
+        sqlx::query(
+            r#"
+            UPDATE pnl_history
+            SET token = $2, realized_pnl = $3, quote_asset = $4, quote_realized_pnl = $5,
+                conversion_rate = $6, settlement_pending = FALSE
+            WHERE id = $1
+            "#,
+        )
+        .bind(pnl_id)
+        .bind(&settlement.token)
+        .bind(realized_pnl)
+        .bind(&settlement.quote_asset)
+        .bind(settlement.quote_asset.as_ref().map(|_| raw_pnl))
+        .bind(settlement.conversion_rate)
+        .execute(&mut *tx)
+        .await?;
+
+        let position_id: Option<Uuid> = sqlx::query_scalar(
+            "UPDATE trade_fills SET realized_pnl = $2, fee_token = $3 WHERE pnl_id = $1 RETURNING position_id",
+        )
+        .bind(pnl_id)
+        .bind(realized_pnl)
+        .bind(&settlement.token)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        // The cycle the fill closed into: archived if it has closed since, else still on the holding
+        if let Some(position_id) = position_id {
+            sqlx::query(
+                r#"
+                WITH archived AS (
+                    UPDATE position_history
+                    SET realized_pnl = realized_pnl + $2
+                    WHERE id = (
+                        SELECT id FROM position_history
+                        WHERE share_id = $1 AND opened_at <= $3 AND closed_at >= $3
+                        ORDER BY closed_at
+                        LIMIT 1
+                    )
+                    RETURNING id
+                )
+                UPDATE shares
+                SET realized_pnl = realized_pnl + $2, version = version + 1, updated_at = NOW()
+                WHERE id = $1 AND NOT EXISTS (SELECT 1 FROM archived)
+                "#,
+            )
+            .bind(position_id)
+            .bind(realized_pnl)
+            .bind(pending.created_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        if !realized_pnl.is_zero() {
+            let change = BalanceChange {
+                reference_id: pending.trade_id,
+                ..BalanceChange::credit(&pending.user_address, &settlement.token, realized_pnl, LedgerReason::RealizedPnl)
+            };
+            LedgerService::apply(&mut tx, &change).await?;
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// The user's fill attributions for the given trades
+    pub async fn fill_attributions(
+        pool: &PgPool,
+        user_address: &str,
+        trade_ids: &[Uuid],
+    ) -> Result<Vec<FillAttribution>, PositionError> {
+        if trade_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let rows = sqlx::query_as(
+            r#"
+            SELECT trade_id, role, side, share_type, position_id, price, amount, fee, fee_token, realized_pnl
+            FROM trade_fills
+            WHERE user_address = $1 AND trade_id = ANY($2)
+            ORDER BY trade_id, role DESC
+            "#,
+        )
+        .bind(user_address.to_lowercase())
+        .bind(trade_ids)
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
+    }
+
+    /// The user's fill attributions recorded in `[from, to)`
+    pub async fn fill_attributions_between(
+        pool: &PgPool,
+        user_address: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<FillAttribution>, PositionError> {
+        let rows = sqlx::query_as(
+            r#"
+            SELECT trade_id, role, side, share_type, position_id, price, amount, fee, fee_token, realized_pnl
+            FROM trade_fills
+            WHERE user_address = $1 AND created_at >= $2 AND created_at < $3
+            "#,
+        )
+        .bind(user_address.to_lowercase())
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
+    }
+
+    /// Collateral balance (available + frozen)
+    async fn collateral(pool: &PgPool, user_address: &str, token: &str) -> Result<Decimal, PositionError> {
+        let collateral: Option<Decimal> = sqlx::query_scalar(
+            "SELECT available + frozen FROM balances WHERE user_address = $1 AND token = $2",
+        )
+        .bind(user_address.to_lowercase())
+        .bind(token)
+        .fetch_optional(pool)
+        .await?;
+        Ok(collateral.unwrap_or(Decimal::ZERO))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::funding::apply_funding;
+    use rust_decimal_macros::dec;
+
+    fn position(amount: Decimal, avg_cost: Decimal, mark: Decimal, mode: MarginMode) -> PositionMargin {
+        position_margin(
+            Uuid::new_v4(),
+            Uuid::nil(),
+            Uuid::nil(),
+            ShareType::Yes,
+            amount,
+            avg_cost,
+            mark,
+            dec!(0.05),
+            mode,
+        )
+    }
+
+    #[test]
+    fn test_cross_margin_shares_collateral() {
+        let positions = vec![
+            position(dec!(100), dec!(0.5), dec!(0.6), MarginMode::Cross),
+            position(dec!(200), dec!(0.4), dec!(0.3), MarginMode::Cross),
+        ];
+        let summary = account_margin(MarginMode::Cross, dec!(40), positions);
+
+        // Values 60 + 60, pnl +10 - 20
+        assert_eq!(summary.positions_value, dec!(120));
+        assert_eq!(summary.unrealized_pnl, dec!(-10));
+        assert_eq!(summary.equity, dec!(160));
+        assert_eq!(summary.maintenance_margin, dec!(6));
+        assert_eq!(summary.margin_ratio, dec!(6) / dec!(160));
+    }
+
+    #[test]
+    fn test_isolated_margin_excludes_free_collateral() {
+        let positions = vec![position(dec!(100), dec!(0.5), dec!(0.6), MarginMode::Isolated)];
+        let summary = account_margin(MarginMode::Isolated, dec!(40), positions);
+
+        assert_eq!(summary.equity, dec!(60));
+        assert_eq!(summary.margin_ratio, dec!(0.05));
+    }
+
+    #[test]
+    fn test_empty_account_has_zero_ratio() {
+        let summary = account_margin(MarginMode::Cross, dec!(0), Vec::new());
+        assert_eq!(summary.margin_ratio, Decimal::ZERO);
+        assert_eq!("CROSS".parse::<MarginMode>().unwrap(), MarginMode::Cross);
+        assert!("portfolio".parse::<MarginMode>().is_err());
+    }
+
+    #[test]
+    fn test_liquidation_price() {
+        // Fully paid isolated position cannot be liquidated
+        assert_eq!(liquidation_price(dec!(100), dec!(0), dec!(0), dec!(0.05)), None);
+
+        // Cross pool already owes 20 of maintenance against 1 of other equity:
+        // 1 + 100p = 20 + 5p -> p = 0.2
+        assert_eq!(liquidation_price(dec!(100), dec!(1), dec!(20), dec!(0.05)), Some(dec!(0.2)));
+    }
+
+    #[test]
+    fn test_netting_closes_before_opening() {
+        // Sell 40 of a 100 long at 0.5 for 0.7
+        let partial = net_position(dec!(100), dec!(0.5), dec!(-40), dec!(0.7));
+        assert_eq!((partial.closed, partial.realized_pnl), (dec!(40), dec!(8)));
+        assert_eq!((partial.amount, partial.avg_cost), (dec!(60), dec!(0.5)));
+
+        // Selling 40 against a 10 long closes it and opens a 30 short at the fill price
+        let flip = net_position(dec!(10), dec!(0.6), dec!(-40), dec!(0.4));
+        assert_eq!((flip.closed, flip.realized_pnl), (dec!(10), dec!(-2)));
+        assert_eq!((flip.amount, flip.avg_cost), (dec!(-30), dec!(0.4)));
+
+        // Buying back the short realizes (entry - exit)
+        let cover = net_position(dec!(-30), dec!(0.4), dec!(30), dec!(0.3));
+        assert_eq!((cover.closed, cover.realized_pnl, cover.amount), (dec!(30), dec!(3), dec!(0)));
+    }
+
+    #[test]
+    fn test_cycle_archived_on_close() {
+        // Buy 100 at 0.4, buy 100 at 0.6, then sell 150 at 0.7 and 50 at 0.5
+        let steps = [(dec!(100), dec!(0.4)), (dec!(100), dec!(0.6)), (dec!(-150), dec!(0.7)), (dec!(-50), dec!(0.5))];
+        let (mut held, mut avg_cost, mut cycle) = (Decimal::ZERO, Decimal::ZERO, PositionCycle::default());
+        let mut closed = None;
+        for (delta, price) in steps {
+            let netting = net_position(held, avg_cost, delta, price);
+            let fee = delta.abs() * dec!(0.01);
+            (closed, cycle) = advance_cycle(cycle, held, &netting, price, fee, netting.realized_pnl);
+            (held, avg_cost) = (netting.amount, netting.avg_cost);
+        }
+
+        let closed = closed.expect("position closed");
+        assert_eq!(closed.entry_amount, dec!(200));
+        assert_eq!(closed.avg_entry_price(), dec!(0.5));
+        assert_eq!(closed.exit_amount, dec!(200));
+        assert_eq!(closed.avg_exit_price(), dec!(0.65));
+        // 150 * 0.2 + 50 * 0
+        assert_eq!(closed.realized_pnl, dec!(30));
+        assert_eq!(closed.fees_paid, dec!(4));
+        assert_eq!(cycle, PositionCycle::default());
+    }
+
+    #[test]
+    fn test_cycle_flip_splits_fee() {
+        // A 10 long (with funding paid) flipped by selling 40 at 0.4
+        let cycle = PositionCycle {
+            entry_amount: dec!(10),
+            entry_notional: dec!(6),
+            funding_paid: dec!(0.3),
+            ..Default::default()
+        };
+        let netting = net_position(dec!(10), dec!(0.6), dec!(-40), dec!(0.4));
+        let (closed, open) = advance_cycle(cycle, dec!(10), &netting, dec!(0.4), dec!(0.8), netting.realized_pnl);
+
+        let closed = closed.expect("long closed");
+        assert_eq!((closed.exit_amount, closed.realized_pnl), (dec!(10), dec!(-2)));
+        assert_eq!((closed.fees_paid, closed.funding_paid), (dec!(0.2), dec!(0.3)));
+        assert_eq!((open.entry_amount, open.entry_notional), (dec!(30), dec!(12)));
+        assert_eq!((open.fees_paid, open.funding_paid), (dec!(0.6), Decimal::ZERO));
+
+        // A partial close keeps the position open
+        let netting = net_position(dec!(-30), dec!(0.4), dec!(10), dec!(0.3));
+        let (closed, open) = advance_cycle(open, dec!(-30), &netting, dec!(0.3), Decimal::ZERO, netting.realized_pnl);
+        assert!(closed.is_none());
+        assert_eq!((open.exit_amount, open.realized_pnl), (dec!(10), dec!(1)));
+    }
+
+    #[test]
+    fn test_isolated_funding_counted_once() {
+        // Long 100 at 0.5 pays 0.3 funding, then sells everything at 0.6
+        let mut nets = Vec::new();
+        for mode in [MarginMode::Isolated, MarginMode::Cross] {
+            let funded = apply_funding(mode, dec!(100), dec!(0.5), dec!(0.6), dec!(0.005));
+            let cycle = PositionCycle {
+                entry_amount: dec!(100),
+                entry_notional: dec!(50),
+                funding_paid: funded.balance_fee(),
+                ..Default::default()
+            };
+            let netting = net_position(dec!(100), funded.avg_cost, dec!(-100), dec!(0.6));
+            let (closed, _) = advance_cycle(cycle, dec!(100), &netting, dec!(0.6), Decimal::ZERO, netting.realized_pnl);
+            let closed = closed.expect("position closed");
+            // As get_position_history nets a closed position
+            nets.push((closed.realized_pnl, closed.funding_paid, closed.realized_pnl - closed.fees_paid - closed.funding_paid));
+        }
+
+        // Isolated: the funding is in the realized PnL through the cost basis
+        assert_eq!(nets[0], (dec!(9.7), Decimal::ZERO, dec!(9.7)));
+        // Cross: paid from the balance and recorded as funding
+        assert_eq!(nets[1], (dec!(10), dec!(0.3), dec!(9.7)));
+    }
+
+    #[test]
+    fn test_deferred_settlement_credits_nothing_yet() {
+        let deferred = Settlement::deferred("USDT", Some("ETH".to_string()));
+        assert!(deferred.deferred);
+        assert_eq!(deferred.convert(dec!(0.25)), Decimal::ZERO);
+
+        let collateral = Settlement::collateral("USDT");
+        assert!(!collateral.deferred);
+        assert_eq!(collateral.convert(dec!(0.25)), dec!(0.25));
+    }
+
+    #[test]
+    fn test_netting_same_direction_averages_cost() {
+        let grown = net_position(dec!(100), dec!(0.5), dec!(100), dec!(0.7));
+        assert_eq!((grown.closed, grown.realized_pnl), (dec!(0), dec!(0)));
+        assert_eq!((grown.amount, grown.avg_cost), (dec!(200), dec!(0.6)));
+
+        let opened = net_position(dec!(0), dec!(0), dec!(-50), dec!(0.3));
+        assert_eq!((opened.amount, opened.avg_cost), (dec!(-50), dec!(0.3)));
+    }
+
+    #[test]
+    fn test_mixed_modes_pool_only_cross_positions() {
+        let cross = position(dec!(100), dec!(0.5), dec!(0.6), MarginMode::Cross);
+        let isolated = position(dec!(100), dec!(0.5), dec!(0.4), MarginMode::Isolated);
+        let positions = vec![cross.clone(), isolated.clone()];
+
+        // Cross pool: 40 collateral + 60 value, 3 maintenance
+        assert_eq!(backing_margin_ratio(dec!(40), &positions, &cross), dec!(0.03));
+        assert_eq!(backing_margin_ratio(dec!(40), &positions, &isolated), dec!(0.05));
+
+        let summary = account_margin(MarginMode::Isolated, dec!(40), positions);
+        assert_eq!(summary.equity, dec!(140));
+        assert_eq!(summary.margin_ratio, dec!(0.05));
+        assert!(summary.positions.iter().all(|p| p.liquidation_price.is_none()));
+    }
+
+    /// In-memory stand-in for a versioned `shares` row
+    #[derive(Default)]
+    struct VersionedHolding {
+        row: std::sync::Mutex<(Decimal, Decimal, i64)>,
+        conflicts: std::sync::atomic::AtomicUsize,
+    }
+
+    impl VersionedHolding {
+        /// One attempt of `try_apply_fill` against the row: read, net, and
+        /// write only over the version read
+        async fn try_apply(&self, delta: Decimal, price: Decimal) -> Result<Option<Decimal>, ()> {
+            let (held, cost, version) = *self.row.lock().unwrap();
+            let netting = net_position(held, cost, delta, price);
+            // Let other writers in between the read and the write
+            tokio::task::yield_now().await;
+
+            let mut row = self.row.lock().unwrap();
+            if row.2 != version {
+                return Ok(None);
+            }
+            *row = (netting.amount, netting.avg_cost, version + 1);
+            Ok(Some(netting.realized_pnl))
+        }
+
+        /// A fill through the retry loop `apply_fill` uses
+        async fn apply(&self, delta: Decimal, price: Decimal) -> Option<Decimal> {
+            retry_on_conflict(usize::MAX, || self.try_apply(delta, price), |_| {
+                self.conflicts.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            })
+            .await
+            .unwrap()
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_fills_are_not_lost() {
+        let holding = std::sync::Arc::new(VersionedHolding {
+            row: std::sync::Mutex::new((dec!(300), dec!(0.5), 0)),
+            ..Default::default()
+        });
+
+        // 8 tasks x 50 fills: buys of 2 at 0.5 and sells of 1 at 0.6 (never flips short)
+        let tasks: Vec<_> = (0..8)
+            .map(|i| {
+                let holding = holding.clone();
+                tokio::spawn(async move {
+                    let mut realized = Decimal::ZERO;
+                    for _ in 0..50 {
+                        let (delta, price) = if i % 2 == 0 { (dec!(2), dec!(0.5)) } else { (dec!(-1), dec!(0.6)) };
+                        realized += holding.apply(delta, price).await.unwrap();
+                    }
+                    realized
+                })
+            })
+            .collect();
+        let mut realized = Decimal::ZERO;
+        for task in tasks {
+            realized += task.await.unwrap();
+        }
+
+        // Every fill landed: 300 + 4*50*2 - 4*50*1, and every close realized 0.1/share
+        let (amount, avg_cost, version) = *holding.row.lock().unwrap();
+        assert_eq!(amount, dec!(500));
+        assert_eq!(avg_cost, dec!(0.5));
+        assert_eq!(version, 400);
+        assert_eq!(realized, dec!(20));
+    }
+
+    #[tokio::test]
+    async fn test_fill_gives_up_after_max_attempts() {
+        let attempts = std::cell::Cell::new(0);
+        let mut conflicts = Vec::new();
+        let applied: Result<Option<Decimal>, ()> = retry_on_conflict(
+            MAX_FILL_ATTEMPTS,
+            || {
+                attempts.set(attempts.get() + 1);
+                async { Ok(None) }
+            },
+            |n| conflicts.push(n),
+        )
+        .await;
+        assert_eq!(applied, Ok(None));
+        assert_eq!(attempts.get(), MAX_FILL_ATTEMPTS);
+        assert_eq!(conflicts, (1..=MAX_FILL_ATTEMPTS).collect::<Vec<_>>());
+
+        // Lands on the last attempt
+        let attempts = std::cell::Cell::new(0);
+        let applied: Result<Option<Decimal>, ()> = retry_on_conflict(
+            MAX_FILL_ATTEMPTS,
+            || {
+                attempts.set(attempts.get() + 1);
+                let landed = attempts.get() == MAX_FILL_ATTEMPTS;
+                async move { Ok(landed.then_some(dec!(1))) }
+            },
+            |_| {},
+        )
+        .await;
+        assert_eq!(applied, Ok(Some(dec!(1))));
+
+        // Database errors are not retried
+        let attempts = std::cell::Cell::new(0);
+        let applied: Result<Option<Decimal>, &str> = retry_on_conflict(
+            MAX_FILL_ATTEMPTS,
+            || {
+                attempts.set(attempts.get() + 1);
+                async { Err("connection reset") }
+            },
+            |_| {},
+        )
+        .await;
+        assert_eq!(applied, Err("connection reset"));
+        assert_eq!(attempts.get(), 1);
+    }
+}
@@ -0,0 +1,211 @@
+//! Order Preferences
+//!
+//! Per-account trading defaults kept server-side, so every client of an
+//! account behaves the same. They fill in the optional fields of an order
+//! that omits them:
+//!
+//! - `default_leverage`: leverage per market (default 1)
+//! - `default_order_type`: limit or market (default limit)
+//! - `slippage_tolerance`: how far past its price a market order may fill,
+//!   as a fraction of the price (default 0)
+//! - `reduce_only`: orders may only shrink a holding (default false)
+//! - `durability`: when an order is acknowledged, `ack_fast` or
+//!   `ack_after_journal` (default ack_fast)
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::models::{DurabilityMode, OrderSide, OrderType};
+
+/// Preference errors
+#[derive(Debug, thiserror::Error)]
+pub enum PreferencesError {
+    #[error("Invalid leverage {leverage} for market {market_id}")]
+    InvalidLeverage { market_id: Uuid, leverage: u32 },
+
+    #[error("Slippage tolerance must be between 0 and {0}")]
+    InvalidSlippage(Decimal),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// Account trading defaults
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OrderPreferences {
+    /// Leverage per market
+    #[serde(default)]
+    pub default_leverage: HashMap<Uuid, u32>,
+    #[serde(default)]
+    pub default_order_type: Option<OrderType>,
+    #[serde(default)]
+    pub slippage_tolerance: Decimal,
+    #[serde(default)]
+    pub reduce_only: bool,
+    #[serde(default)]
+    pub durability: Option<DurabilityMode>,
+}
+
+impl OrderPreferences {
+    /// Largest slippage tolerance accepted (50%)
+    pub const MAX_SLIPPAGE: Decimal = Decimal::from_parts(5, 0, 0, false, 1);
+
+    pub fn validate(&self) -> Result<(), PreferencesError> {
+        if let Some((&market_id, &leverage)) = self.default_leverage.iter().find(|(_, &leverage)| leverage == 0) {
+            return Err(PreferencesError::InvalidLeverage { market_id, leverage });
+        }
+        if self.slippage_tolerance < Decimal::ZERO || self.slippage_tolerance > Self::MAX_SLIPPAGE {
+            return Err(PreferencesError::InvalidSlippage(Self::MAX_SLIPPAGE));
+        }
+        Ok(())
+    }
+
+    pub fn leverage_for(&self, market_id: Uuid) -> u32 {
+        self.default_leverage.get(&market_id).copied().unwrap_or(1)
+    }
+
+    pub fn order_type(&self) -> OrderType {
+        self.default_order_type.unwrap_or(OrderType::Limit)
+    }
+
+    pub fn durability(&self) -> DurabilityMode {
+        self.durability.unwrap_or_default()
+    }
+}
+
+/// Worst price a market order at `price` may fill at, within the 0.01 - 0.99
+/// probability range and rounded onto the tick (towards `price`)
+pub fn slippage_limit(side: OrderSide, price: Decimal, tolerance: Decimal, tick: Option<Decimal>) -> Decimal {
+    let limit = match side {
+        OrderSide::Buy => price * (Decimal::ONE + tolerance),
+        OrderSide::Sell => price * (Decimal::ONE - tolerance),
+    };
+    let limit = match tick.filter(|tick| *tick > Decimal::ZERO) {
+        Some(tick) => {
+            let ticks = limit / tick;
+            let ticks = if side == OrderSide::Buy { ticks.floor() } else { ticks.ceil() };
+            ticks * tick
+        }
+        None => limit,
+    };
+    limit.clamp(Decimal::new(1, 2), Decimal::new(99, 2))
+}
+
+/// Whether an order only shrinks the holding `held` (negative = short)
+pub fn reduces_holding(held: Decimal, side: OrderSide, amount: Decimal) -> bool {
+    match side {
+        OrderSide::Buy => held < Decimal::ZERO && amount <= -held,
+        OrderSide::Sell => held > Decimal::ZERO && amount <= held,
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct PreferencesRow {
+    default_leverage: sqlx::types::Json<HashMap<Uuid, u32>>,
+    default_order_type: Option<String>,
+    slippage_tolerance: Decimal,
+    reduce_only: bool,
+    durability_mode: Option<String>,
+}
+
+pub struct PreferenceService;
+
+impl PreferenceService {
+    /// Preferences of a user (defaults if never set)
+    pub async fn get(pool: &PgPool, user_address: &str) -> Result<OrderPreferences, PreferencesError> {
+        let row: Option<PreferencesRow> = sqlx::query_as(
+            r#"
+            SELECT default_leverage, default_order_type, slippage_tolerance, reduce_only, durability_mode
+            FROM user_preferences
+            WHERE user_address = $1
+            "#,
+        )
+        .bind(user_address.to_lowercase())
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row
+            .map(|row| OrderPreferences {
+                default_leverage: row.default_leverage.0,
+                default_order_type: row.default_order_type.and_then(|t| t.parse().ok()),
+                slippage_tolerance: row.slippage_tolerance,
+                reduce_only: row.reduce_only,
+                durability: row.durability_mode.and_then(|m| m.parse().ok()),
+            })
+            .unwrap_or_default())
+    }
+
+    /// Replace a user's preferences
+    pub async fn set(pool: &PgPool, user_address: &str, preferences: &OrderPreferences) -> Result<(), PreferencesError> {
+        preferences.validate()?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO user_preferences (
+                user_address, default_leverage, default_order_type, slippage_tolerance, reduce_only, durability_mode
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (user_address) DO UPDATE SET
+                default_leverage = $2,
+                default_order_type = $3,
+                slippage_tolerance = $4,
+                reduce_only = $5,
+                durability_mode = $6,
+                updated_at = NOW()
+            "#,
+        )
+        .bind(user_address.to_lowercase())
+        .bind(sqlx::types::Json(&preferences.default_leverage))
+        .bind(preferences.default_order_type.map(|t| t.to_string()))
+        .bind(preferences.slippage_tolerance)
+        .bind(preferences.reduce_only)
+        .bind(preferences.durability.map(|m| m.to_string()))
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_validates_and_resolves_defaults() {
+        let market = Uuid::new_v4();
+        let mut prefs = OrderPreferences::default();
+        assert_eq!((prefs.leverage_for(market), prefs.order_type()), (1, OrderType::Limit));
+        assert_eq!(prefs.durability(), DurabilityMode::AckFast);
+
+        prefs.default_leverage.insert(market, 5);
+        prefs.slippage_tolerance = dec!(0.02);
+        assert!(prefs.validate().is_ok());
+        assert_eq!(prefs.leverage_for(market), 5);
+
+        prefs.slippage_tolerance = dec!(0.6);
+        assert!(matches!(prefs.validate(), Err(PreferencesError::InvalidSlippage(_))));
+        prefs.slippage_tolerance = Decimal::ZERO;
+        prefs.default_leverage.insert(market, 0);
+        assert!(matches!(prefs.validate(), Err(PreferencesError::InvalidLeverage { .. })));
+    }
+
+    #[test]
+    fn test_slippage_and_reduce_only() {
+        assert_eq!(slippage_limit(OrderSide::Buy, dec!(0.50), dec!(0.1), None), dec!(0.55));
+        assert_eq!(slippage_limit(OrderSide::Sell, dec!(0.50), dec!(0.1), None), dec!(0.45));
+        assert_eq!(slippage_limit(OrderSide::Buy, dec!(0.95), dec!(0.1), None), dec!(0.99));
+        // Rounded onto the tick, never past the tolerance
+        assert_eq!(slippage_limit(OrderSide::Buy, dec!(0.50), dec!(0.05), Some(dec!(0.02))), dec!(0.52));
+        assert_eq!(slippage_limit(OrderSide::Sell, dec!(0.50), dec!(0.05), Some(dec!(0.02))), dec!(0.48));
+
+        assert!(reduces_holding(dec!(10), OrderSide::Sell, dec!(10)));
+        assert!(!reduces_holding(dec!(10), OrderSide::Sell, dec!(11)));
+        assert!(!reduces_holding(dec!(10), OrderSide::Buy, dec!(1)));
+        assert!(reduces_holding(dec!(-4), OrderSide::Buy, dec!(4)));
+        assert!(!reduces_holding(Decimal::ZERO, OrderSide::Sell, dec!(1)));
+    }
+}
@@ -0,0 +1,269 @@
+//! Funding Premium Index
+//!
+//! Samples each outcome's (`{market_id}:{outcome_id}`) premium every minute
+//! into `funding_premium_index`: the YES book's mark price against its index
+//! price,
+//!
+//! - premium = (mark price - index price) / index price
+//!
+//! Funding rates are the average premium over a funding interval, clamped to
+//! the symbol's `min_funding_rate` / `max_funding_rate`. Intervals are aligned
+//! to the Unix epoch (00:00, 08:00 and 16:00 UTC for the default 8 hours), so
+//! the samples taken since the current interval began give a running estimate
+//! of its rate ([`FundingPrediction`]). The samples themselves are served as
+//! premium index history.
+
+use chrono::{DateTime, DurationRound, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sqlx::PgPool;
+
+use crate::services::funding::{self, FundingError, FundingParams, FundingService};
+use crate::services::market::mark_price::MarkPriceService;
+use crate::services::matching::MatchingEngine;
+
+/// Decimal places premiums are kept to
+const PREMIUM_DECIMALS: u32 = 12;
+
+/// Premium index errors
+#[derive(Debug, thiserror::Error)]
+pub enum PremiumIndexError {
+    #[error("Invalid symbol: {0} (expected {{market_id}}:{{outcome_id}})")]
+    InvalidSymbol(String),
+
+    #[error("Funding error: {0}")]
+    Funding(#[from] FundingError),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// One premium sample
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct PremiumSample {
+    pub symbol: String,
+    pub ts: DateTime<Utc>,
+    pub mark_price: Decimal,
+    pub index_price: Decimal,
+    pub premium: Decimal,
+}
+
+/// Running estimate of the current funding interval's rate
+#[derive(Debug, Clone, Serialize)]
+pub struct FundingPrediction {
+    pub symbol: String,
+    pub interval_start: DateTime<Utc>,
+    pub next_funding_time: DateTime<Utc>,
+    /// Samples taken in the interval so far
+    pub samples: i64,
+    /// Average premium of those samples (None before the first one)
+    pub premium_index: Option<Decimal>,
+    /// The premium index clamped to the funding bounds
+    pub predicted_rate: Option<Decimal>,
+    pub min_funding_rate: Decimal,
+    pub max_funding_rate: Decimal,
+}
+
+#[derive(sqlx::FromRow)]
+struct IntervalAverage {
+    samples: i64,
+    premium_index: Option<Decimal>,
+}
+
+/// Validate a funding symbol (`{market_id}:{outcome_id}`), returning it normalized
+pub fn parse_symbol(symbol: &str) -> Result<String, PremiumIndexError> {
+    funding::parse_symbol(symbol)
+        .map(|(market_id, outcome_id)| format!("{}:{}", market_id, outcome_id))
+        .ok_or_else(|| PremiumIndexError::InvalidSymbol(symbol.to_string()))
+}
+
+/// Premium of a mark price over its index; None without a positive index
+pub fn premium(mark_price: Decimal, index_price: Decimal) -> Option<Decimal> {
+    if index_price <= Decimal::ZERO {
+        return None;
+    }
+    Some(((mark_price - index_price) / index_price).round_dp(PREMIUM_DECIMALS))
+}
+
+/// The funding interval containing `now`, as `(start, end)`
+pub fn interval_bounds(now: DateTime<Utc>, interval_hours: i32) -> (DateTime<Utc>, DateTime<Utc>) {
+    let interval = chrono::Duration::hours(i64::from(interval_hours.max(1)));
+    let start = now.duration_trunc(interval).unwrap_or(now);
+    (start, start + interval)
+}
+
+/// Funding rate implied by an average premium
+pub fn predicted_rate(premium_index: Decimal, params: &FundingParams) -> Decimal {
+    premium_index.clamp(params.min_funding_rate, params.max_funding_rate)
+}
+
+pub struct PremiumIndexService;
+
+impl PremiumIndexService {
+    /// Sample the premium of every outcome with a YES book, and drop samples
+    /// older than `retention_days`. Returns the number of samples taken.
+    pub async fn sample(
+        pool: &PgPool,
+        engine: &MatchingEngine,
+        mark_prices: &MarkPriceService,
+        retention_days: i64,
+    ) -> Result<usize, PremiumIndexError> {
+        let ts = Utc::now().duration_trunc(chrono::Duration::minutes(1)).unwrap_or_else(|_| Utc::now());
+
+        let mut samples = Vec::new();
+        for book in engine.orderbook_symbols() {
+            let Some(symbol) = book.strip_suffix(":yes") else {
+                continue;
+            };
+            let Some(mark_price) = engine.mark_price(&book) else {
+                continue;
+            };
+            let Some(index_price) = mark_prices.symbol_index_price(&book).await else {
+                continue;
+            };
+            let Some(premium) = premium(mark_price, index_price) else {
+                continue;
+            };
+            samples.push(PremiumSample {
+                symbol: symbol.to_string(),
+                ts,
+                mark_price,
+                index_price,
+                premium,
+            });
+        }
+
+        let mut tx = pool.begin().await?;
+        for sample in &samples {
+            sqlx::query(
+                r#"
+                INSERT INTO funding_premium_index (symbol, ts, mark_price, index_price, premium)
+                VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT (symbol, ts) DO UPDATE SET
+                    mark_price = EXCLUDED.mark_price,
+                    index_price = EXCLUDED.index_price,
+                    premium = EXCLUDED.premium
+                "#,
+            )
+            .bind(&sample.symbol)
+            .bind(sample.ts)
+            .bind(sample.mark_price)
+            .bind(sample.index_price)
+            .bind(sample.premium)
+            .execute(&mut *tx)
+            .await?;
+        }
+        sqlx::query("DELETE FROM funding_premium_index WHERE ts < NOW() - make_interval(days => $1)")
+            .bind(retention_days.max(1) as i32)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(samples.len())
+    }
+
+    /// Predicted rate of the funding interval containing `now`
+    pub async fn predicted(
+        pool: &PgPool,
+        symbol: &str,
+        now: DateTime<Utc>,
+    ) -> Result<FundingPrediction, PremiumIndexError> {
+        let params = FundingService::params(pool, symbol).await?;
+        let (interval_start, next_funding_time) = interval_bounds(now, params.funding_interval_hours);
+
+        let average: IntervalAverage = sqlx::query_as(
+            r#"
+            SELECT COUNT(*) AS samples, AVG(premium) AS premium_index
+            FROM funding_premium_index
+            WHERE symbol = $1 AND ts >= $2 AND ts < $3
+            "#,
+        )
+        .bind(symbol)
+        .bind(interval_start)
+        .bind(next_funding_time)
+        .fetch_one(pool)
+        .await?;
+
+        let premium_index = average.premium_index.map(|p| p.round_dp(PREMIUM_DECIMALS));
+        Ok(FundingPrediction {
+            symbol: symbol.to_string(),
+            interval_start,
+            next_funding_time,
+            samples: average.samples,
+            premium_index,
+            predicted_rate: premium_index.map(|p| predicted_rate(p, &params)),
+            min_funding_rate: params.min_funding_rate,
+            max_funding_rate: params.max_funding_rate,
+        })
+    }
+
+    /// Samples of a symbol in `[start, end)`, newest first
+    pub async fn history(
+        pool: &PgPool,
+        symbol: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<PremiumSample>, PremiumIndexError> {
+        let samples = sqlx::query_as(
+            r#"
+            SELECT symbol, ts, mark_price, index_price, premium
+            FROM funding_premium_index
+            WHERE symbol = $1 AND ts >= $2 AND ts < $3
+            ORDER BY ts DESC
+            LIMIT $4
+            "#,
+        )
+        .bind(symbol)
+        .bind(start)
+        .bind(end)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+        Ok(samples)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_premium() {
+        assert_eq!(premium(dec!(0.55), dec!(0.50)), Some(dec!(0.1)));
+        assert_eq!(premium(dec!(0.45), dec!(0.50)), Some(dec!(-0.1)));
+        assert_eq!(premium(dec!(0.55), Decimal::ZERO), None);
+    }
+
+    #[test]
+    fn test_interval_bounds() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 15, 13, 42, 7).unwrap();
+        let (start, end) = interval_bounds(now, 8);
+        assert_eq!(start, Utc.with_ymd_and_hms(2026, 10, 15, 8, 0, 0).unwrap());
+        assert_eq!(end, Utc.with_ymd_and_hms(2026, 10, 15, 16, 0, 0).unwrap());
+
+        let (start, end) = interval_bounds(now, 1);
+        assert_eq!(start, Utc.with_ymd_and_hms(2026, 10, 15, 13, 0, 0).unwrap());
+        assert_eq!(end, Utc.with_ymd_and_hms(2026, 10, 15, 14, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_predicted_rate_clamped() {
+        let params = FundingParams::default();
+        assert_eq!(predicted_rate(dec!(0.004), &params), dec!(0.004));
+        assert_eq!(predicted_rate(dec!(0.25), &params), params.max_funding_rate);
+        assert_eq!(predicted_rate(dec!(-0.25), &params), params.min_funding_rate);
+    }
+
+    #[test]
+    fn test_parse_symbol() {
+        let market_id = uuid::Uuid::new_v4();
+        let outcome_id = uuid::Uuid::new_v4();
+        let symbol = format!("{}:{}", market_id, outcome_id);
+        assert_eq!(parse_symbol(&symbol.to_uppercase()).unwrap(), symbol);
+        assert!(parse_symbol(&format!("{}:yes", symbol)).is_err());
+        assert!(parse_symbol("BTC-USDT").is_err());
+    }
+}
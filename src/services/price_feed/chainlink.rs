@@ -0,0 +1,53 @@
+//! Chainlink aggregator reads
+//!
+//! Prices come from `AggregatorV3Interface.latestRoundData()`; `answer` is
+//! scaled by the aggregator's `decimals()`.
+
+use chrono::DateTime;
+use ethers::types::U256;
+use rust_decimal::Decimal;
+
+use super::{OraclePrice, OracleKind};
+
+/// `latestRoundData()` selector
+pub const LATEST_ROUND_DATA_SELECTOR: [u8; 4] = [0xfe, 0xaf, 0x96, 0x8c];
+
+/// `decimals()` selector
+pub const DECIMALS_SELECTOR: [u8; 4] = [0x31, 0x3c, 0xe5, 0x67];
+
+/// Decode `decimals()` (uint8)
+pub fn decode_decimals(output: &[u8]) -> Option<u32> {
+    let word = output.get(..32)?;
+    let value = U256::from_big_endian(word);
+    (value <= U256::from(28u8)).then(|| value.as_u32())
+}
+
+/// Decode `latestRoundData()` into a price: `(roundId, answer, startedAt,
+/// updatedAt, answeredInRound)`. Non-positive answers are rejected.
+pub fn decode_latest_round(output: &[u8], decimals: u32) -> Option<OraclePrice> {
+    if output.len() < 5 * 32 {
+        return None;
+    }
+    let answer = &output[32..64];
+    // int256: a set top bit is a negative answer
+    if answer[0] & 0x80 != 0 {
+        return None;
+    }
+    let answer = U256::from_big_endian(answer);
+    if answer.is_zero() || answer > U256::from(i128::MAX as u128) {
+        return None;
+    }
+    let price = Decimal::try_from_i128_with_scale(answer.as_u128() as i128, decimals).ok()?;
+
+    let updated_at = U256::from_big_endian(&output[96..128]);
+    if updated_at > U256::from(i64::MAX as u64) {
+        return None;
+    }
+    let published_at = DateTime::from_timestamp(updated_at.as_u64() as i64, 0)?;
+
+    Some(OraclePrice {
+        kind: OracleKind::Chainlink,
+        price: price.normalize(),
+        published_at,
+    })
+}
@@ -0,0 +1,422 @@
+//! On-chain Oracle Price Feeds
+//!
+//! Reads Chainlink aggregators and Pyth price feeds over the configured RPC
+//! (`rpc_url`). Each feed symbol can name a Chainlink aggregator, a Pyth feed
+//! id, or both; with both, the configured primary oracle is used and the other
+//! is the fallback when the primary errors or is older than `max_age_secs`:
+//!
+//! ```json
+//! [{ "symbol": "ETH-USD",
+//!    "chainlink": "0x5f4eC3Df9cbd43714FE2740f5E3616155c5b8419",
+//!    "pyth": "0xff61491a931112ddf1bd8147cd1b641375f79f5825126d665480874634fd0ace" }]
+//! ```
+//!
+//! Every read is counted with the oracle update / error metrics. The age of
+//! each source and the relative deviation between the two oracles are
+//! exported as gauges; a deviation beyond `max_deviation` is logged.
+//!
+//! The service is the `onchain` index source of the mark price service, for
+//! symbols named after a feed.
+
+pub mod chainlink;
+pub mod pyth;
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use ethers::providers::{Http, Middleware, Provider};
+use ethers::types::{Address, Bytes, TransactionRequest};
+use futures::future::BoxFuture;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::chaos::{self, Fault};
+use crate::metrics;
+use crate::services::market::mark_price::IndexSource;
+
+/// Price feed errors
+#[derive(Debug, thiserror::Error)]
+pub enum PriceFeedError {
+    #[error("Invalid price feed config: {0}")]
+    InvalidConfig(String),
+
+    #[error("No {0} source configured")]
+    NotConfigured(OracleKind),
+
+    #[error("RPC error: {0}")]
+    RpcError(String),
+
+    #[error("Malformed {0} response")]
+    BadResponse(OracleKind),
+}
+
+/// On-chain oracle network
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OracleKind {
+    Chainlink,
+    Pyth,
+}
+
+impl OracleKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OracleKind::Chainlink => "chainlink",
+            OracleKind::Pyth => "pyth",
+        }
+    }
+
+    pub fn other(&self) -> Self {
+        match self {
+            OracleKind::Chainlink => OracleKind::Pyth,
+            OracleKind::Pyth => OracleKind::Chainlink,
+        }
+    }
+}
+
+impl std::fmt::Display for OracleKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for OracleKind {
+    type Err = PriceFeedError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "chainlink" => Ok(OracleKind::Chainlink),
+            "pyth" => Ok(OracleKind::Pyth),
+            other => Err(PriceFeedError::InvalidConfig(format!("unknown oracle {:?}", other))),
+        }
+    }
+}
+
+/// A price read from one oracle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct OraclePrice {
+    pub kind: OracleKind,
+    pub price: Decimal,
+    /// When the oracle last updated the price on chain
+    pub published_at: DateTime<Utc>,
+}
+
+impl OraclePrice {
+    pub fn age_secs(&self, now: DateTime<Utc>) -> i64 {
+        (now - self.published_at).num_seconds()
+    }
+}
+
+/// One configured feed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedConfig {
+    pub symbol: String,
+    /// Chainlink aggregator address
+    pub chainlink: Option<String>,
+    /// Pyth price feed id (32-byte hex)
+    pub pyth: Option<String>,
+}
+
+/// Parse the `price_feeds` JSON config (empty = no feeds)
+pub fn parse_feeds(config: &str) -> Result<Vec<FeedConfig>, PriceFeedError> {
+    if config.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let feeds: Vec<FeedConfig> =
+        serde_json::from_str(config).map_err(|e| PriceFeedError::InvalidConfig(e.to_string()))?;
+    for feed in &feeds {
+        if feed.chainlink.is_none() && feed.pyth.is_none() {
+            return Err(PriceFeedError::InvalidConfig(format!("{} has no sources", feed.symbol)));
+        }
+        if let Some(aggregator) = &feed.chainlink {
+            Address::from_str(aggregator)
+                .map_err(|_| PriceFeedError::InvalidConfig(format!("{}: bad aggregator {}", feed.symbol, aggregator)))?;
+        }
+        if let Some(id) = &feed.pyth {
+            pyth::parse_feed_id(id)
+                .ok_or_else(|| PriceFeedError::InvalidConfig(format!("{}: bad Pyth feed id {}", feed.symbol, id)))?;
+        }
+    }
+    Ok(feeds)
+}
+
+/// Feed selection settings
+#[derive(Debug, Clone, Copy)]
+pub struct FeedParams {
+    pub primary: OracleKind,
+    /// Prices older than this are stale
+    pub max_age_secs: i64,
+    /// Relative oracle disagreement that is logged
+    pub max_deviation: Decimal,
+}
+
+/// Pick the primary price if fresh, else a fresh fallback
+pub fn select_price(
+    primary: Option<OraclePrice>,
+    fallback: Option<OraclePrice>,
+    now: DateTime<Utc>,
+    max_age_secs: i64,
+) -> Option<OraclePrice> {
+    let fresh = |p: &OraclePrice| p.age_secs(now) <= max_age_secs;
+    primary.filter(fresh).or(fallback.filter(fresh))
+}
+
+/// Relative deviation of `price` from `reference`
+pub fn deviation(price: Decimal, reference: Decimal) -> Option<Decimal> {
+    if reference.is_zero() {
+        return None;
+    }
+    Some(((price - reference) / reference).abs())
+}
+
+/// Latest selected price of a feed
+#[derive(Debug, Clone, Serialize)]
+pub struct FeedPrice {
+    pub symbol: String,
+    pub price: Decimal,
+    pub source: OracleKind,
+    pub published_at: DateTime<Utc>,
+    /// Relative deviation between the two oracles, when both were read
+    pub deviation: Option<Decimal>,
+}
+
+/// Polls on-chain oracles and keeps the latest price per feed
+pub struct PriceFeedService {
+    feeds: Vec<FeedConfig>,
+    params: FeedParams,
+    provider: Option<Provider<Http>>,
+    pyth_contract: Option<Address>,
+    /// Chainlink aggregator decimals (fixed per aggregator)
+    decimals: DashMap<Address, u32>,
+    prices: DashMap<String, FeedPrice>,
+}
+
+impl PriceFeedService {
+    pub fn new(
+        feeds: Vec<FeedConfig>,
+        params: FeedParams,
+        rpc_url: &str,
+        pyth_contract: &str,
+    ) -> Result<Self, PriceFeedError> {
+        let provider = if feeds.is_empty() {
+            None
+        } else {
+            Some(Provider::<Http>::try_from(rpc_url).map_err(|e| PriceFeedError::InvalidConfig(e.to_string()))?)
+        };
+        let pyth_contract = if pyth_contract.is_empty() {
+            None
+        } else {
+            Some(
+                Address::from_str(pyth_contract)
+                    .map_err(|_| PriceFeedError::InvalidConfig(format!("bad Pyth contract {}", pyth_contract)))?,
+            )
+        };
+        Ok(Self {
+            feeds,
+            params,
+            provider,
+            pyth_contract,
+            decimals: DashMap::new(),
+            prices: DashMap::new(),
+        })
+    }
+
+    pub fn feeds(&self) -> &[FeedConfig] {
+        &self.feeds
+    }
+
+    async fn call(&self, to: Address, data: Vec<u8>) -> Result<Bytes, PriceFeedError> {
+        if chaos::fails(Fault::RpcError) {
+            return Err(PriceFeedError::RpcError("injected RPC error".to_string()));
+        }
+        let provider = self.provider.as_ref().ok_or(PriceFeedError::RpcError("no provider".to_string()))?;
+        let call = TransactionRequest::new().to(to).data(Bytes::from(data));
+        provider
+            .call(&call.into(), None)
+            .await
+            .map_err(|e| PriceFeedError::RpcError(e.to_string()))
+    }
+
+    async fn read_chainlink(&self, aggregator: &str) -> Result<OraclePrice, PriceFeedError> {
+        let aggregator = Address::from_str(aggregator).map_err(|_| PriceFeedError::BadResponse(OracleKind::Chainlink))?;
+        let decimals = match self.decimals.get(&aggregator) {
+            Some(decimals) => *decimals,
+            None => {
+                let output = self.call(aggregator, chainlink::DECIMALS_SELECTOR.to_vec()).await?;
+                let decimals =
+                    chainlink::decode_decimals(&output).ok_or(PriceFeedError::BadResponse(OracleKind::Chainlink))?;
+                self.decimals.insert(aggregator, decimals);
+                decimals
+            }
+        };
+        let output = self.call(aggregator, chainlink::LATEST_ROUND_DATA_SELECTOR.to_vec()).await?;
+        chainlink::decode_latest_round(&output, decimals).ok_or(PriceFeedError::BadResponse(OracleKind::Chainlink))
+    }
+
+    async fn read_pyth(&self, feed_id: &str) -> Result<OraclePrice, PriceFeedError> {
+        let contract = self.pyth_contract.ok_or(PriceFeedError::NotConfigured(OracleKind::Pyth))?;
+        let feed_id = pyth::parse_feed_id(feed_id).ok_or(PriceFeedError::BadResponse(OracleKind::Pyth))?;
+        let output = self.call(contract, pyth::get_price_calldata(&feed_id)).await?;
+        pyth::decode_price(&output).ok_or(PriceFeedError::BadResponse(OracleKind::Pyth))
+    }
+
+    /// Read one oracle of a feed, recording update / error metrics
+    async fn read(&self, feed: &FeedConfig, kind: OracleKind, now: DateTime<Utc>) -> Option<OraclePrice> {
+        let result = match kind {
+            OracleKind::Chainlink => match &feed.chainlink {
+                Some(aggregator) => self.read_chainlink(aggregator).await,
+                None => return None,
+            },
+            OracleKind::Pyth => match &feed.pyth {
+                Some(id) => self.read_pyth(id).await,
+                None => return None,
+            },
+        };
+        match result {
+            Ok(price) => {
+                metrics::record_oracle_update(kind.as_str());
+                metrics::record_oracle_price_age(&feed.symbol, kind.as_str(), price.age_secs(now));
+                Some(price)
+            }
+            Err(e) => {
+                metrics::record_oracle_error(kind.as_str());
+                tracing::warn!("{} read for {} failed: {}", kind, feed.symbol, e);
+                None
+            }
+        }
+    }
+
+    /// Read every feed and refresh its selected price
+    pub async fn poll(&self) {
+        for feed in &self.feeds {
+            let now = Utc::now();
+            let primary_kind = self.params.primary;
+            let (primary, fallback) = futures::join!(
+                self.read(feed, primary_kind, now),
+                self.read(feed, primary_kind.other(), now)
+            );
+
+            let spread = match (primary, fallback) {
+                (Some(p), Some(f)) => deviation(f.price, p.price),
+                _ => None,
+            };
+            if let Some(spread) = spread {
+                metrics::record_oracle_deviation(&feed.symbol, spread.to_f64().unwrap_or(0.0));
+                if spread > self.params.max_deviation {
+                    tracing::warn!(
+                        "Oracles disagree on {}: {} {} vs {} {} ({} deviation)",
+                        feed.symbol,
+                        primary_kind,
+                        primary.map(|p| p.price).unwrap_or_default(),
+                        primary_kind.other(),
+                        fallback.map(|p| p.price).unwrap_or_default(),
+                        spread
+                    );
+                }
+            }
+
+            let Some(selected) = select_price(primary, fallback, now, self.params.max_age_secs) else {
+                tracing::warn!("No fresh oracle price for {}, keeping last price", feed.symbol);
+                continue;
+            };
+            if selected.kind != primary_kind {
+                tracing::debug!("Using {} fallback for {}", selected.kind, feed.symbol);
+            }
+            self.prices.insert(
+                feed.symbol.clone(),
+                FeedPrice {
+                    symbol: feed.symbol.clone(),
+                    price: selected.price,
+                    source: selected.kind,
+                    published_at: selected.published_at,
+                    deviation: spread,
+                },
+            );
+        }
+    }
+
+    /// Latest price of a feed, unless stale
+    pub fn price(&self, symbol: &str) -> Option<FeedPrice> {
+        let price = self.prices.get(symbol)?;
+        let age = (Utc::now() - price.published_at).num_seconds();
+        (age <= self.params.max_age_secs).then(|| price.clone())
+    }
+}
+
+impl IndexSource for PriceFeedService {
+    fn name(&self) -> &'static str {
+        "onchain"
+    }
+
+    fn index_price<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Option<Decimal>> {
+        Box::pin(async move { self.price(symbol).map(|p| p.price) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use rust_decimal_macros::dec;
+
+    fn word(value: u128) -> [u8; 32] {
+        let mut word = [0u8; 32];
+        word[16..].copy_from_slice(&value.to_be_bytes());
+        word
+    }
+
+    fn signed_word(value: i64) -> [u8; 32] {
+        let mut word = if value < 0 { [0xff; 32] } else { [0u8; 32] };
+        word[24..].copy_from_slice(&value.to_be_bytes());
+        word
+    }
+
+    #[test]
+    fn test_selectors() {
+        assert_eq!(ethers::utils::id("latestRoundData()"), chainlink::LATEST_ROUND_DATA_SELECTOR);
+        assert_eq!(ethers::utils::id("decimals()"), chainlink::DECIMALS_SELECTOR);
+        assert_eq!(ethers::utils::id("getPriceUnsafe(bytes32)"), pyth::GET_PRICE_UNSAFE_SELECTOR);
+    }
+
+    #[test]
+    fn test_decode_oracle_responses() {
+        // Chainlink: 2000.12345678 with 8 decimals, updated at t=1_700_000_000
+        let round = [word(7), word(200_012_345_678), word(1_700_000_000), word(1_700_000_000), word(7)].concat();
+        let price = chainlink::decode_latest_round(&round, 8).unwrap();
+        assert_eq!(price.price, dec!(2000.12345678));
+        assert_eq!(price.published_at.timestamp(), 1_700_000_000);
+
+        let negative = [word(7), signed_word(-1), word(0), word(0), word(7)].concat();
+        assert!(chainlink::decode_latest_round(&negative, 8).is_none());
+
+        // Pyth: 199_950_000_000 * 10^-8, conf, expo -8, publish time
+        let pyth_price = [signed_word(199_950_000_000), word(5), signed_word(-8), word(1_700_000_100)].concat();
+        let price = pyth::decode_price(&pyth_price).unwrap();
+        assert_eq!(price.price, dec!(1999.5));
+        assert_eq!(price.published_at.timestamp(), 1_700_000_100);
+        assert!(pyth::decode_price(&pyth_price[..64]).is_none());
+    }
+
+    #[test]
+    fn test_fallback_and_deviation() {
+        let now = Utc::now();
+        let price = |kind, price, age| OraclePrice {
+            kind,
+            price,
+            published_at: now - Duration::seconds(age),
+        };
+        let chainlink = price(OracleKind::Chainlink, dec!(2000), 30);
+        let pyth = price(OracleKind::Pyth, dec!(2010), 5);
+
+        assert_eq!(select_price(Some(chainlink), Some(pyth), now, 60), Some(chainlink));
+        // Stale or missing primary falls back
+        let stale = price(OracleKind::Chainlink, dec!(2000), 600);
+        assert_eq!(select_price(Some(stale), Some(pyth), now, 60), Some(pyth));
+        assert_eq!(select_price(None, Some(pyth), now, 60), Some(pyth));
+        assert_eq!(select_price(Some(stale), None, now, 60), None);
+
+        assert_eq!(deviation(dec!(2010), dec!(2000)), Some(dec!(0.005)));
+        assert_eq!(deviation(dec!(1), Decimal::ZERO), None);
+    }
+}
@@ -0,0 +1,57 @@
+//! Pyth contract reads
+//!
+//! Prices come from `getPriceUnsafe(bytes32 id)` on the chain's Pyth contract,
+//! which returns the latest pushed `(int64 price, uint64 conf, int32 expo,
+//! uint256 publishTime)` without a freshness check; staleness is judged by the
+//! feed service like any other source.
+
+use chrono::DateTime;
+use rust_decimal::Decimal;
+
+use super::{OraclePrice, OracleKind};
+
+/// `getPriceUnsafe(bytes32)` selector
+pub const GET_PRICE_UNSAFE_SELECTOR: [u8; 4] = [0x96, 0x83, 0x4a, 0xd3];
+
+/// Parse a hex Pyth price feed id
+pub fn parse_feed_id(id: &str) -> Option<[u8; 32]> {
+    let bytes = hex::decode(id.trim_start_matches("0x")).ok()?;
+    bytes.try_into().ok()
+}
+
+/// Calldata for `getPriceUnsafe(id)`
+pub fn get_price_calldata(feed_id: &[u8; 32]) -> Vec<u8> {
+    let mut data = GET_PRICE_UNSAFE_SELECTOR.to_vec();
+    data.extend_from_slice(feed_id);
+    data
+}
+
+/// Last 8 bytes of an ABI word as a sign-extended int64
+fn word_i64(word: &[u8]) -> i64 {
+    i64::from_be_bytes(word[24..32].try_into().unwrap_or_default())
+}
+
+/// Decode `getPriceUnsafe` into a price; non-positive prices are rejected
+pub fn decode_price(output: &[u8]) -> Option<OraclePrice> {
+    if output.len() < 4 * 32 {
+        return None;
+    }
+    let price = word_i64(&output[0..32]);
+    let expo = i32::from_be_bytes(output[92..96].try_into().ok()?);
+    let publish_time = word_i64(&output[96..128]);
+    if price <= 0 {
+        return None;
+    }
+
+    let price = if expo <= 0 {
+        Decimal::try_from_i128_with_scale(price as i128, expo.unsigned_abs()).ok()?
+    } else {
+        Decimal::from(price).checked_mul(Decimal::from(10i64.checked_pow(expo as u32)?))?
+    };
+
+    Some(OraclePrice {
+        kind: OracleKind::Pyth,
+        price: price.normalize(),
+        published_at: DateTime::from_timestamp(publish_time, 0)?,
+    })
+}
@@ -0,0 +1,339 @@
+//! Referral Earnings Settlement
+//!
+//! Referral commissions accrue as `pending` rows in `referral_earnings`. A
+//! scheduled worker settles them in fixed epochs of `referral_epoch_days`,
+//! aligned to Monday 00:00 UTC (so weekly epochs run Monday to Monday):
+//!
+//! - once an epoch has ended, each referrer's pending earnings created before
+//!   its end are summed per token
+//! - sums of at least `referral_min_payout` are credited to the referrer's
+//!   balance through the ledger (`referral_payout`), recorded in
+//!   `referral_payouts` and their earnings marked `claimed` with the epoch
+//! - smaller sums stay pending and carry over to the next epoch
+//!
+//! An epoch is settled in one transaction together with its `referral_epochs`
+//! row, whose unique end keeps it from being paid twice. Epochs missed while
+//! the worker was down are settled in order on its next run.
+
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::{BTreeMap, BTreeSet};
+use uuid::Uuid;
+
+use crate::services::ledger::{BalanceChange, LedgerReason, LedgerService};
+
+/// Epoch boundaries are counted from Monday 1970-01-05 00:00 UTC
+const EPOCH_ANCHOR_SECS: i64 = 4 * 86_400;
+
+/// Referral settlement errors
+#[derive(Debug, thiserror::Error)]
+pub enum ReferralSettlementError {
+    #[error("Invalid epoch length: {0} days")]
+    InvalidEpochLength(i64),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// A settled epoch
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct ReferralEpoch {
+    pub id: Uuid,
+    pub start_at: DateTime<Utc>,
+    pub end_at: DateTime<Utc>,
+    /// Referrers paid
+    pub referrer_count: i32,
+    /// Earnings rows paid
+    pub earnings_count: i32,
+    pub settled_at: DateTime<Utc>,
+}
+
+/// What a referrer was paid in one token for an epoch
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+pub struct ReferralPayout {
+    pub epoch_id: Uuid,
+    pub referrer_address: String,
+    pub token: String,
+    pub amount: Decimal,
+    pub earnings_count: i32,
+}
+
+/// End of the last epoch that ended at or before `now`
+pub fn epoch_end(now: DateTime<Utc>, epoch_days: i64) -> DateTime<Utc> {
+    let len = epoch_days * 86_400;
+    let elapsed = (now.timestamp() - EPOCH_ANCHOR_SECS).div_euclid(len);
+    DateTime::from_timestamp(EPOCH_ANCHOR_SECS + elapsed * len, 0).unwrap_or(now)
+}
+
+/// The epoch to settle next, if it has ended: the one after `last_end`, or
+/// the last ended epoch when nothing was settled yet
+pub fn next_epoch(
+    last_end: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+    epoch_days: i64,
+) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let len = Duration::days(epoch_days);
+    let (start, end) = match last_end {
+        Some(last) => (last, last + len),
+        None => {
+            let end = epoch_end(now, epoch_days);
+            (end - len, end)
+        }
+    };
+    (end <= now).then_some((start, end))
+}
+
+/// Sum settled earnings rows into one payout per referrer and token
+pub fn aggregate(epoch_id: Uuid, rows: Vec<(String, String, Decimal)>) -> Vec<ReferralPayout> {
+    let mut payouts: BTreeMap<(String, String), (Decimal, i32)> = BTreeMap::new();
+    for (referrer_address, token, commission) in rows {
+        let payout = payouts.entry((referrer_address, token)).or_default();
+        payout.0 += commission;
+        payout.1 += 1;
+    }
+    payouts
+        .into_iter()
+        .map(|((referrer_address, token), (amount, earnings_count))| ReferralPayout {
+            epoch_id,
+            referrer_address,
+            token,
+            amount,
+            earnings_count,
+        })
+        .collect()
+}
+
+/// Referral earnings settlement
+pub struct ReferralSettlementService;
+
+impl ReferralSettlementService {
+    /// Settle every epoch that has ended since the last settled one; returns
+    /// the epochs settled, oldest first
+    pub async fn settle_due(
+        pool: &PgPool,
+        now: DateTime<Utc>,
+        epoch_days: i64,
+        min_payout: Decimal,
+    ) -> Result<Vec<ReferralEpoch>, ReferralSettlementError> {
+        if epoch_days < 1 {
+            return Err(ReferralSettlementError::InvalidEpochLength(epoch_days));
+        }
+
+        let mut settled = Vec::new();
+        loop {
+            let last_end: Option<DateTime<Utc>> = sqlx::query_scalar("SELECT MAX(end_at) FROM referral_epochs")
+                .fetch_one(pool)
+                .await?;
+            let Some((start, end)) = next_epoch(last_end, now, epoch_days) else {
+                break;
+            };
+            match Self::settle_epoch(pool, start, end, min_payout).await? {
+                Some(epoch) => settled.push(epoch),
+                // Settled concurrently
+                None => break,
+            }
+        }
+        Ok(settled)
+    }
+
+    /// Pay out one epoch; None if it was already settled
+    async fn settle_epoch(
+        pool: &PgPool,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        min_payout: Decimal,
+    ) -> Result<Option<ReferralEpoch>, ReferralSettlementError> {
+        let mut tx = pool.begin().await?;
+
+        let epoch_id: Option<Uuid> = sqlx::query_scalar(
+            r#"
+            INSERT INTO referral_epochs (start_at, end_at)
+            VALUES ($1, $2)
+            ON CONFLICT (end_at) DO NOTHING
+            RETURNING id
+            "#,
+        )
+        .bind(start)
+        .bind(end)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(epoch_id) = epoch_id else {
+            return Ok(None);
+        };
+
+        // Referrers below the minimum keep their earnings pending
+        let rows: Vec<(String, String, Decimal)> = sqlx::query_as(
+            r#"
+            UPDATE referral_earnings
+            SET status = 'claimed', claimed_at = NOW(), epoch_id = $1
+            WHERE status = 'pending' AND created_at < $2
+              AND (referrer_address, token) IN (
+                  SELECT referrer_address, token
+                  FROM referral_earnings
+                  WHERE status = 'pending' AND created_at < $2
+                  GROUP BY referrer_address, token
+                  HAVING SUM(commission) > 0 AND SUM(commission) >= $3
+              )
+            RETURNING referrer_address, token, commission
+            "#,
+        )
+        .bind(epoch_id)
+        .bind(end)
+        .bind(min_payout)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let payouts = aggregate(epoch_id, rows);
+        for payout in &payouts {
+            sqlx::query(
+                r#"
+                INSERT INTO referral_payouts (epoch_id, referrer_address, token, amount, earnings_count)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+            )
+            .bind(epoch_id)
+            .bind(&payout.referrer_address)
+            .bind(&payout.token)
+            .bind(payout.amount)
+            .bind(payout.earnings_count)
+            .execute(&mut *tx)
+            .await?;
+
+            let change = BalanceChange {
+                reference_id: Some(epoch_id),
+                ..BalanceChange::credit(&payout.referrer_address, &payout.token, payout.amount, LedgerReason::ReferralPayout)
+            };
+            LedgerService::apply(&mut tx, &change).await?;
+        }
+
+        let referrers = payouts.iter().map(|p| &p.referrer_address).collect::<BTreeSet<_>>().len();
+        let earnings: i32 = payouts.iter().map(|p| p.earnings_count).sum();
+        let epoch: ReferralEpoch = sqlx::query_as(
+            r#"
+            UPDATE referral_epochs
+            SET referrer_count = $2, earnings_count = $3
+            WHERE id = $1
+            RETURNING id, start_at, end_at, referrer_count, earnings_count, settled_at
+            "#,
+        )
+        .bind(epoch_id)
+        .bind(referrers as i32)
+        .bind(earnings)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        tracing::info!(
+            "Settled referral epoch {} to {}: {} earnings paid to {} referrers",
+            start, end, earnings, referrers
+        );
+        Ok(Some(epoch))
+    }
+
+    /// Settled epochs, newest first, with the user's payouts in them
+    pub async fn epochs(
+        pool: &PgPool,
+        user_address: &str,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<ReferralEpoch>, Vec<ReferralPayout>), ReferralSettlementError> {
+        let epochs: Vec<ReferralEpoch> = sqlx::query_as(
+            r#"
+            SELECT id, start_at, end_at, referrer_count, earnings_count, settled_at
+            FROM referral_epochs
+            ORDER BY end_at DESC
+            LIMIT $1 OFFSET $2
+            "#,
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await?;
+
+        let epoch_ids: Vec<Uuid> = epochs.iter().map(|e| e.id).collect();
+        let payouts = sqlx::query_as(
+            r#"
+            SELECT epoch_id, referrer_address, token, amount, earnings_count
+            FROM referral_payouts
+            WHERE referrer_address = $1 AND epoch_id = ANY($2)
+            ORDER BY token
+            "#,
+        )
+        .bind(user_address.to_lowercase())
+        .bind(&epoch_ids)
+        .fetch_all(pool)
+        .await?;
+
+        Ok((epochs, payouts))
+    }
+
+    /// The user's earnings still waiting for an epoch, per token
+    pub async fn pending(pool: &PgPool, user_address: &str) -> Result<Vec<(String, Decimal)>, ReferralSettlementError> {
+        let pending = sqlx::query_as(
+            r#"
+            SELECT token, SUM(commission)
+            FROM referral_earnings
+            WHERE referrer_address = $1 AND status = 'pending'
+            GROUP BY token
+            ORDER BY token
+            "#,
+        )
+        .bind(user_address.to_lowercase())
+        .fetch_all(pool)
+        .await?;
+        Ok(pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use rust_decimal_macros::dec;
+
+    fn at(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, day, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_weekly_epochs_end_on_monday() {
+        // 2026-10-12 and 2026-10-19 are Mondays
+        assert_eq!(epoch_end(at(15, 9), 7), at(12, 0));
+        assert_eq!(epoch_end(at(19, 0), 7), at(19, 0));
+        assert_eq!(epoch_end(at(15, 9), 1), at(15, 0));
+    }
+
+    #[test]
+    fn test_next_epoch_catches_up_in_order() {
+        assert_eq!(next_epoch(None, at(15, 9), 7), Some((at(5, 0), at(12, 0))));
+        assert_eq!(next_epoch(Some(at(5, 0)), at(15, 9), 7), Some((at(5, 0), at(12, 0))));
+        assert_eq!(next_epoch(Some(at(12, 0)), at(15, 9), 7), None);
+        assert_eq!(next_epoch(Some(at(12, 0)), at(19, 0), 7), Some((at(12, 0), at(19, 0))));
+    }
+
+    #[test]
+    fn test_aggregates_per_referrer_and_token() {
+        let epoch_id = Uuid::new_v4();
+        let payouts = aggregate(
+            epoch_id,
+            vec![
+                ("0xb".to_string(), "USDC".to_string(), dec!(2)),
+                ("0xa".to_string(), "USDC".to_string(), dec!(1.5)),
+                ("0xb".to_string(), "USDC".to_string(), dec!(3)),
+                ("0xb".to_string(), "ETH".to_string(), dec!(0.1)),
+            ],
+        );
+        let summary: Vec<_> = payouts
+            .iter()
+            .map(|p| (p.referrer_address.as_str(), p.token.as_str(), p.amount, p.earnings_count))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("0xa", "USDC", dec!(1.5), 1), ("0xb", "ETH", dec!(0.1), 1), ("0xb", "USDC", dec!(5), 2)]
+        );
+        assert!(payouts.iter().all(|p| p.epoch_id == epoch_id));
+    }
+}
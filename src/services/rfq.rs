@@ -0,0 +1,709 @@
+//! RFQ (Request For Quote) Service
+//!
+//! Block trading for sizes too large for the orderbook:
+//! 1. A taker requests a quote for `amount` shares of one outcome/share type
+//! 2. Registered market makers are notified and may quote until `quote_deadline`
+//! 3. After the window closes the best quote is executed as a privately matched
+//!    trade: the collateral (buyer -> seller), the shares (seller -> buyer) and
+//!    the trade record move in one transaction, then the trade is reported
+//!    through the normal trade pipeline (`MatchingEngine::broadcast_trade`) flagged
+//!    as a block trade for market data
+//!
+//! If the best quote can no longer settle (insufficient balance or shares), the
+//! next best is tried; if none settles the request expires. Block trades are fee-free.
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sqlx::{PgConnection, PgPool};
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::models::market::ShareType;
+use crate::services::ledger::{BalanceChange, LedgerReason, LedgerService};
+use crate::services::matching::{MatchingEngine, OrderFlowOrchestrator, Side, TradeEvent};
+use crate::services::position::Settlement;
+
+/// RFQ request statuses
+pub const RFQ_OPEN: &str = "open";
+pub const RFQ_FILLED: &str = "filled";
+pub const RFQ_EXPIRED: &str = "expired";
+pub const RFQ_CANCELLED: &str = "cancelled";
+
+/// Maximum requests executed per sweep
+const EXECUTE_BATCH: i64 = 100;
+
+/// RFQ service errors
+#[derive(Debug, thiserror::Error)]
+pub enum RfqError {
+    #[error("RFQ not found: {0}")]
+    RequestNotFound(Uuid),
+
+    #[error("Market not found: {0}")]
+    MarketNotFound(Uuid),
+
+    #[error("Market is not active: {0}")]
+    MarketNotActive(Uuid),
+
+    #[error("Amount below RFQ minimum of {0}")]
+    AmountTooSmall(Decimal),
+
+    #[error("Price must be between 0 and 1")]
+    InvalidPrice,
+
+    #[error("Not a registered market maker: {0}")]
+    NotMarketMaker(String),
+
+    #[error("Cannot quote your own RFQ")]
+    SelfQuote,
+
+    #[error("RFQ is not open: {0}")]
+    RequestClosed(Uuid),
+
+    #[error("Quote window closed for RFQ: {0}")]
+    QuoteWindowClosed(Uuid),
+
+    #[error("Not the owner of RFQ: {0}")]
+    NotOwner(Uuid),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// Quote request
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct RfqRequest {
+    pub id: Uuid,
+    pub taker_address: String,
+    pub market_id: Uuid,
+    pub outcome_id: Uuid,
+    pub share_type: String,
+    pub side: String,
+    pub amount: Decimal,
+    pub status: String,
+    pub quote_deadline: DateTime<Utc>,
+    pub filled_quote_id: Option<Uuid>,
+    pub filled_price: Option<Decimal>,
+    pub trade_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl RfqRequest {
+    fn taker_side(&self) -> Side {
+        if self.side == "sell" {
+            Side::Sell
+        } else {
+            Side::Buy
+        }
+    }
+
+    /// Orderbook key for the requested outcome/share type
+    pub fn market_key(&self) -> String {
+        format!("{}:{}:{}", self.market_id, self.outcome_id, self.share_type)
+    }
+}
+
+/// Market maker quote
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct RfqQuote {
+    pub id: Uuid,
+    pub rfq_id: Uuid,
+    pub maker_address: String,
+    pub price: Decimal,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// New quote request, pushed to market makers
+#[derive(Debug, Clone, Serialize)]
+pub struct RfqRequestNotice {
+    pub rfq_id: Uuid,
+    pub market_id: Uuid,
+    pub outcome_id: Uuid,
+    pub share_type: String,
+    pub side: String,
+    pub amount: Decimal,
+    pub quote_deadline: i64,
+}
+
+/// Final state of a quote request, pushed to the taker and quoting makers
+#[derive(Debug, Clone, Serialize)]
+pub struct RfqResultNotice {
+    pub rfq_id: Uuid,
+    pub status: String,
+    pub price: Option<Decimal>,
+    pub amount: Decimal,
+    /// Maker whose quote was executed
+    pub maker_address: Option<String>,
+    pub trade_id: Option<Uuid>,
+}
+
+/// RFQ WebSocket notification
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum RfqNotification {
+    Request(RfqRequestNotice),
+    Result(RfqResultNotice),
+}
+
+impl RfqNotification {
+    /// WebSocket message type
+    pub fn kind(&self) -> &'static str {
+        match self {
+            RfqNotification::Request(_) => "rfq_request",
+            RfqNotification::Result(_) => "rfq_result",
+        }
+    }
+}
+
+/// RFQ notification addressed to specific users
+#[derive(Debug, Clone)]
+pub struct RfqEvent {
+    /// Lowercase addresses that should receive the notification
+    pub recipients: Vec<String>,
+    pub notification: RfqNotification,
+}
+
+/// Settings applied when executing block trades
+#[derive(Debug, Clone)]
+pub struct RfqExecutionConfig {
+    /// Collateral token symbol
+    pub token: String,
+    /// Whether block trades update the orderbook's last traded price
+    pub update_last_price: bool,
+}
+
+/// Order quotes best-first for the taker: lowest price for a buy, highest for a sell
+pub fn rank_quotes(mut quotes: Vec<RfqQuote>, taker_side: Side) -> Vec<RfqQuote> {
+    quotes.sort_by(|a, b| {
+        let by_price = match taker_side {
+            Side::Buy => a.price.cmp(&b.price),
+            Side::Sell => b.price.cmp(&a.price),
+        };
+        by_price.then(a.created_at.cmp(&b.created_at))
+    });
+    quotes
+}
+
+/// RFQ service
+pub struct RfqService;
+
+impl RfqService {
+    // ========================================================================
+    // Market Makers
+    // ========================================================================
+
+    /// Register (or reactivate) a market maker
+    pub async fn register_maker(pool: &PgPool, address: &str) -> Result<(), RfqError> {
+        sqlx::query(
+            r#"
+            INSERT INTO rfq_market_makers (address, active)
+            VALUES ($1, true)
+            ON CONFLICT (address) DO UPDATE SET active = true, updated_at = NOW()
+            "#
+        )
+        .bind(address.to_lowercase())
+        .execute(pool)
+        .await?;
+
+        info!("Registered RFQ market maker {}", address);
+        Ok(())
+    }
+
+    /// Deactivate a market maker
+    pub async fn deactivate_maker(pool: &PgPool, address: &str) -> Result<(), RfqError> {
+        let result = sqlx::query(
+            "UPDATE rfq_market_makers SET active = false, updated_at = NOW() WHERE address = $1",
+        )
+        .bind(address.to_lowercase())
+        .execute(pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RfqError::NotMarketMaker(address.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Active market maker addresses
+    pub async fn active_makers(pool: &PgPool) -> Result<Vec<String>, RfqError> {
+        let makers = sqlx::query_scalar("SELECT address FROM rfq_market_makers WHERE active ORDER BY address")
+            .fetch_all(pool)
+            .await?;
+        Ok(makers)
+    }
+
+    // ========================================================================
+    // Lifecycle
+    // ========================================================================
+
+    /// Open a quote request and notify market makers
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_request(
+        pool: &PgPool,
+        notifier: &broadcast::Sender<RfqEvent>,
+        taker_address: &str,
+        market_id: Uuid,
+        outcome_id: Uuid,
+        share_type: ShareType,
+        side: Side,
+        amount: Decimal,
+        min_amount: Decimal,
+        quote_window_ms: u64,
+    ) -> Result<RfqRequest, RfqError> {
+        if amount < min_amount || amount <= Decimal::ZERO {
+            return Err(RfqError::AmountTooSmall(min_amount));
+        }
+
+        let status: Option<String> = sqlx::query_scalar("SELECT status::text FROM markets WHERE id = $1")
+            .bind(market_id)
+            .fetch_optional(pool)
+            .await?;
+        match status.as_deref() {
+            None => return Err(RfqError::MarketNotFound(market_id)),
+            Some("active") => {}
+            Some(_) => return Err(RfqError::MarketNotActive(market_id)),
+        }
+
+        let taker_address = taker_address.to_lowercase();
+        let quote_deadline = Utc::now() + chrono::Duration::milliseconds(quote_window_ms as i64);
+
+        let request: RfqRequest = sqlx::query_as(
+            r#"
+            INSERT INTO rfq_requests (taker_address, market_id, outcome_id, share_type, side, amount, quote_deadline)
+            VALUES ($1, $2, $3, $4::share_type, $5::order_side, $6, $7)
+            RETURNING id, taker_address, market_id, outcome_id, share_type::text AS share_type,
+                      side::text AS side, amount, status, quote_deadline, filled_quote_id,
+                      filled_price, trade_id, created_at
+            "#
+        )
+        .bind(&taker_address)
+        .bind(market_id)
+        .bind(outcome_id)
+        .bind(share_type.to_string())
+        .bind(side.to_string())
+        .bind(amount)
+        .bind(quote_deadline)
+        .fetch_one(pool)
+        .await?;
+
+        let recipients: Vec<String> = Self::active_makers(pool)
+            .await?
+            .into_iter()
+            .filter(|maker| *maker != taker_address)
+            .collect();
+
+        info!(
+            "RFQ {} opened by {}: {} {} of {} ({} makers notified)",
+            request.id, taker_address, request.side, amount, request.market_key(), recipients.len()
+        );
+
+        let _ = notifier.send(RfqEvent {
+            recipients,
+            notification: RfqNotification::Request(RfqRequestNotice {
+                rfq_id: request.id,
+                market_id,
+                outcome_id,
+                share_type: request.share_type.clone(),
+                side: request.side.clone(),
+                amount,
+                quote_deadline: quote_deadline.timestamp_millis(),
+            }),
+        });
+
+        Ok(request)
+    }
+
+    /// Submit or replace a market maker's quote
+    pub async fn submit_quote(
+        pool: &PgPool,
+        rfq_id: Uuid,
+        maker_address: &str,
+        price: Decimal,
+    ) -> Result<RfqQuote, RfqError> {
+        if price <= Decimal::ZERO || price >= Decimal::ONE {
+            return Err(RfqError::InvalidPrice);
+        }
+
+        let maker_address = maker_address.to_lowercase();
+        let is_maker: Option<bool> = sqlx::query_scalar("SELECT active FROM rfq_market_makers WHERE address = $1")
+            .bind(&maker_address)
+            .fetch_optional(pool)
+            .await?;
+        if is_maker != Some(true) {
+            return Err(RfqError::NotMarketMaker(maker_address));
+        }
+
+        let mut tx = pool.begin().await?;
+
+        // Lock the request so quotes cannot race execution
+        let request = Self::load_request(&mut tx, rfq_id, true).await?;
+        if request.status != RFQ_OPEN {
+            return Err(RfqError::RequestClosed(rfq_id));
+        }
+        if Utc::now() >= request.quote_deadline {
+            return Err(RfqError::QuoteWindowClosed(rfq_id));
+        }
+        if request.taker_address == maker_address {
+            return Err(RfqError::SelfQuote);
+        }
+
+        let quote: RfqQuote = sqlx::query_as(
+            r#"
+            INSERT INTO rfq_quotes (rfq_id, maker_address, price)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (rfq_id, maker_address) DO UPDATE SET
+                price = $3,
+                created_at = NOW(),
+                updated_at = NOW()
+            RETURNING id, rfq_id, maker_address, price, status, created_at
+            "#
+        )
+        .bind(rfq_id)
+        .bind(&maker_address)
+        .bind(price)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(quote)
+    }
+
+    /// Cancel an open quote request (taker only)
+    pub async fn cancel_request(
+        pool: &PgPool,
+        notifier: &broadcast::Sender<RfqEvent>,
+        rfq_id: Uuid,
+        taker_address: &str,
+    ) -> Result<RfqRequest, RfqError> {
+        let mut tx = pool.begin().await?;
+
+        let request = Self::load_request(&mut tx, rfq_id, true).await?;
+        if request.taker_address != taker_address.to_lowercase() {
+            return Err(RfqError::NotOwner(rfq_id));
+        }
+        if request.status != RFQ_OPEN {
+            return Err(RfqError::RequestClosed(rfq_id));
+        }
+
+        let quotes = Self::close_request(&mut tx, &request, RFQ_CANCELLED, None).await?;
+        tx.commit().await?;
+
+        let request = RfqRequest { status: RFQ_CANCELLED.to_string(), ..request };
+        Self::notify_result(notifier, &request, &quotes, None);
+        Ok(request)
+    }
+
+    /// Get a quote request with its quotes
+    pub async fn get_request(pool: &PgPool, rfq_id: Uuid) -> Result<(RfqRequest, Vec<RfqQuote>), RfqError> {
+        let mut conn = pool.acquire().await?;
+        let request = Self::load_request(&mut conn, rfq_id, false).await?;
+        let quotes = Self::load_quotes(&mut conn, rfq_id).await?;
+        Ok((request, quotes))
+    }
+
+    /// Execute every open request whose quote window has closed
+    pub async fn execute_due(
+        pool: &PgPool,
+        engine: &MatchingEngine,
+        notifier: &broadcast::Sender<RfqEvent>,
+        config: &RfqExecutionConfig,
+    ) -> Result<usize, RfqError> {
+        let due: Vec<Uuid> = sqlx::query_scalar(
+            r#"
+            SELECT id FROM rfq_requests
+            WHERE status = 'open' AND quote_deadline <= NOW()
+            ORDER BY quote_deadline
+            LIMIT $1
+            "#
+        )
+        .bind(EXECUTE_BATCH)
+        .fetch_all(pool)
+        .await?;
+
+        let mut executed = 0;
+        for rfq_id in due {
+            match Self::execute(pool, engine, notifier, config, rfq_id).await {
+                Ok(true) => executed += 1,
+                Ok(false) => {}
+                Err(e) => warn!("Failed to execute RFQ {}: {}", rfq_id, e),
+            }
+        }
+        Ok(executed)
+    }
+
+    /// Execute the best settleable quote for a request (false = expired unfilled)
+    async fn execute(
+        pool: &PgPool,
+        engine: &MatchingEngine,
+        notifier: &broadcast::Sender<RfqEvent>,
+        config: &RfqExecutionConfig,
+        rfq_id: Uuid,
+    ) -> Result<bool, RfqError> {
+        let mut tx = pool.begin().await?;
+
+        let request = Self::load_request(&mut tx, rfq_id, true).await?;
+        if request.status != RFQ_OPEN {
+            return Ok(false);
+        }
+
+        let taker_side = request.taker_side();
+        let quotes = rank_quotes(Self::load_quotes(&mut tx, rfq_id).await?, taker_side);
+
+        let mut filled = None;
+        for quote in &quotes {
+            if Self::settle_collateral(&mut tx, &request, quote, &config.token).await? {
+                filled = Some(quote.clone());
+                break;
+            }
+            info!("RFQ {}: quote {} from {} cannot settle, trying next", rfq_id, quote.id, quote.maker_address);
+        }
+
+        let Some(quote) = filled else {
+            Self::close_request(&mut tx, &request, RFQ_EXPIRED, None).await?;
+            tx.commit().await?;
+
+            info!("RFQ {} expired with {} quotes and no fill", rfq_id, quotes.len());
+            let request = RfqRequest { status: RFQ_EXPIRED.to_string(), ..request };
+            Self::notify_result(notifier, &request, &quotes, None);
+            return Ok(false);
+        };
+
+        let trade_id = Uuid::new_v4();
+        let event = TradeEvent::new(
+            request.market_key(),
+            trade_id,
+            quote.id,
+            request.id,
+            quote.maker_address.clone(),
+            request.taker_address.clone(),
+            taker_side,
+            quote.price,
+            request.amount,
+            Decimal::ZERO,
+            Decimal::ZERO,
+        )
+        .with_block_trade();
+        let settlement = Settlement::collateral(&config.token);
+        OrderFlowOrchestrator::persist_block_trade(&mut tx, &event, &settlement).await?;
+        Self::close_request(&mut tx, &request, RFQ_FILLED, Some((&quote, trade_id))).await?;
+        tx.commit().await?;
+
+        // Settled already: the trade pipeline only assigns its exec id and
+        // announces it
+        if engine.broadcast_trade(event) == 0 {
+            warn!("No trade subscribers for block trade {}", trade_id);
+        }
+
+        if config.update_last_price {
+            if let Some(orderbook) = engine.get_orderbook_ref(&request.market_key()) {
+                orderbook.set_last_trade_price(quote.price);
+            }
+        }
+
+        info!(
+            "RFQ {} filled: {} {} @ {} with {} (trade {})",
+            rfq_id, request.side, request.amount, quote.price, quote.maker_address, trade_id
+        );
+
+        let request = RfqRequest {
+            status: RFQ_FILLED.to_string(),
+            filled_quote_id: Some(quote.id),
+            filled_price: Some(quote.price),
+            trade_id: Some(trade_id),
+            ..request
+        };
+        Self::notify_result(notifier, &request, &quotes, Some(&quote));
+        Ok(true)
+    }
+
+    // ========================================================================
+    // Helpers
+    // ========================================================================
+
+    async fn load_request(conn: &mut PgConnection, rfq_id: Uuid, for_update: bool) -> Result<RfqRequest, RfqError> {
+        let sql = format!(
+            r#"
+            SELECT id, taker_address, market_id, outcome_id, share_type::text AS share_type,
+                   side::text AS side, amount, status, quote_deadline, filled_quote_id,
+                   filled_price, trade_id, created_at
+            FROM rfq_requests
+            WHERE id = $1
+            {}
+            "#,
+            if for_update { "FOR UPDATE" } else { "" }
+        );
+
+        sqlx::query_as(&sql)
+            .bind(rfq_id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .ok_or(RfqError::RequestNotFound(rfq_id))
+    }
+
+    async fn load_quotes(conn: &mut PgConnection, rfq_id: Uuid) -> Result<Vec<RfqQuote>, RfqError> {
+        let quotes = sqlx::query_as(
+            r#"
+            SELECT id, rfq_id, maker_address, price, status, created_at
+            FROM rfq_quotes
+            WHERE rfq_id = $1
+            ORDER BY created_at
+            "#
+        )
+        .bind(rfq_id)
+        .fetch_all(&mut *conn)
+        .await?;
+        Ok(quotes)
+    }
+
+    /// Move collateral buyer -> seller for a quote, locking both holdings for
+    /// the share transfer; false if either side cannot settle
+    async fn settle_collateral(
+        conn: &mut PgConnection,
+        request: &RfqRequest,
+        quote: &RfqQuote,
+        token: &str,
+    ) -> Result<bool, RfqError> {
+        let (buyer, seller) = match request.taker_side() {
+            Side::Buy => (&request.taker_address, &quote.maker_address),
+            Side::Sell => (&quote.maker_address, &request.taker_address),
+        };
+
+        // Seller must hold the shares being sold
+        let held: Option<Decimal> = sqlx::query_scalar(
+            r#"
+            SELECT amount FROM shares
+            WHERE user_address = $1 AND outcome_id = $2 AND share_type = $3::share_type
+            FOR UPDATE
+            "#
+        )
+        .bind(seller)
+        .bind(request.outcome_id)
+        .bind(&request.share_type)
+        .fetch_optional(&mut *conn)
+        .await?;
+        if held.unwrap_or(Decimal::ZERO) < request.amount {
+            return Ok(false);
+        }
+
+        // Buyer's holding (if any) must not change before the shares arrive
+        sqlx::query("SELECT id FROM shares WHERE user_address = $1 AND outcome_id = $2 FOR UPDATE")
+            .bind(buyer.to_lowercase())
+            .bind(request.outcome_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+
+        let cost = quote.price * request.amount;
+        let debit = BalanceChange::debit(buyer, token, cost, LedgerReason::RfqFill)
+            .reference(request.id)
+            .checked();
+        if LedgerService::apply(&mut *conn, &debit).await?.is_none() {
+            return Ok(false);
+        }
+
+        let credit = BalanceChange::credit(seller, token, cost, LedgerReason::RfqFill).reference(request.id);
+        LedgerService::apply(&mut *conn, &credit).await?;
+
+        Ok(true)
+    }
+
+    /// Set the final request status and resolve its quotes; returns the quotes
+    async fn close_request(
+        conn: &mut PgConnection,
+        request: &RfqRequest,
+        status: &str,
+        fill: Option<(&RfqQuote, Uuid)>,
+    ) -> Result<Vec<RfqQuote>, RfqError> {
+        let (quote_id, price, trade_id) = match fill {
+            Some((quote, trade_id)) => (Some(quote.id), Some(quote.price), Some(trade_id)),
+            None => (None, None, None),
+        };
+
+        sqlx::query(
+            r#"
+            UPDATE rfq_requests
+            SET status = $2, filled_quote_id = $3, filled_price = $4, trade_id = $5, updated_at = NOW()
+            WHERE id = $1
+            "#
+        )
+        .bind(request.id)
+        .bind(status)
+        .bind(quote_id)
+        .bind(price)
+        .bind(trade_id)
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE rfq_quotes
+            SET status = CASE WHEN id = $2 THEN 'filled' ELSE 'rejected' END, updated_at = NOW()
+            WHERE rfq_id = $1
+            "#
+        )
+        .bind(request.id)
+        .bind(quote_id)
+        .execute(&mut *conn)
+        .await?;
+
+        Self::load_quotes(conn, request.id).await
+    }
+
+    /// Notify the taker and every quoting maker of the final request state
+    fn notify_result(
+        notifier: &broadcast::Sender<RfqEvent>,
+        request: &RfqRequest,
+        quotes: &[RfqQuote],
+        filled: Option<&RfqQuote>,
+    ) {
+        let mut recipients: Vec<String> = quotes.iter().map(|q| q.maker_address.clone()).collect();
+        recipients.push(request.taker_address.clone());
+
+        let _ = notifier.send(RfqEvent {
+            recipients,
+            notification: RfqNotification::Result(RfqResultNotice {
+                rfq_id: request.id,
+                status: request.status.clone(),
+                price: filled.map(|q| q.price),
+                amount: request.amount,
+                maker_address: filled.map(|q| q.maker_address.clone()),
+                trade_id: request.trade_id,
+            }),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn quote(maker: &str, price: Decimal, age_secs: i64) -> RfqQuote {
+        RfqQuote {
+            id: Uuid::new_v4(),
+            rfq_id: Uuid::nil(),
+            maker_address: maker.to_string(),
+            price,
+            status: "pending".to_string(),
+            created_at: Utc::now() - chrono::Duration::seconds(age_secs),
+        }
+    }
+
+    fn makers(quotes: &[RfqQuote]) -> Vec<&str> {
+        quotes.iter().map(|q| q.maker_address.as_str()).collect()
+    }
+
+    #[test]
+    fn test_rank_quotes_for_buy_prefers_lowest_price() {
+        let quotes = vec![quote("a", dec!(0.55), 3), quote("b", dec!(0.52), 1), quote("c", dec!(0.52), 2)];
+        // Equal prices: earlier quote first
+        assert_eq!(makers(&rank_quotes(quotes, Side::Buy)), vec!["c", "b", "a"]);
+    }
+
+    #[test]
+    fn test_rank_quotes_for_sell_prefers_highest_price() {
+        let quotes = vec![quote("a", dec!(0.45), 1), quote("b", dec!(0.48), 1), quote("c", dec!(0.40), 1)];
+        assert_eq!(makers(&rank_quotes(quotes, Side::Sell)), vec!["b", "a", "c"]);
+    }
+}
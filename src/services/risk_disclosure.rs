@@ -0,0 +1,265 @@
+//! Risk Disclosure
+//!
+//! Every parameter that affects a user's outcome on a symbol, in one
+//! machine-readable document: the fee schedule, the funding formula and its
+//! parameters, maintenance tiers, the liquidation fee and where it goes, the
+//! deleveraging policy and the price limits orders must respect.
+//!
+//! The document is assembled on request from the values the engine and the
+//! background jobs read themselves (the market's stored settings, the funding
+//! config, the live config), so published numbers cannot drift from the ones
+//! in force. Formulas are the ones implemented in [`FeeConfig::calculate_fee`],
+//! [`funding_fee`] and the liquidation engine.
+//!
+//! [`FeeConfig::calculate_fee`]: crate::services::matching::FeeConfig::calculate_fee
+//! [`funding_fee`]: crate::services::funding::funding_fee
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::models::order::CreateOrderRequest;
+use crate::services::fees::VipTier;
+use crate::services::funding::FundingParams;
+use crate::services::margin_call::MarginCallSettings;
+use crate::services::market::mark_price::MarkPriceConfig;
+use crate::services::market::{MarketConfig, RiskLimit};
+
+/// Fee formula, as implemented by the matching engine
+pub const FEE_FORMULA: &str = "fee = min(rate * min(price, 1 - price), max_fee) * amount";
+
+/// Funding formula, as implemented by funding settlement
+pub const FUNDING_FORMULA: &str = "funding = signed amount * mark price * rate";
+
+/// Liquidation fee formula, as implemented by the liquidation engine
+pub const LIQUIDATION_FEE_FORMULA: &str = "fee = closed notional * maintenance margin rate * penalty ratio";
+
+/// Risk disclosure of one symbol
+#[derive(Debug, Clone, Serialize)]
+pub struct RiskDisclosure {
+    pub symbol: String,
+    pub market_id: Uuid,
+    pub generated_at: DateTime<Utc>,
+    pub fees: FeeDisclosure,
+    pub funding: FundingDisclosure,
+    pub margin: MarginDisclosure,
+    pub liquidation: LiquidationDisclosure,
+    pub deleveraging: DeleveragingDisclosure,
+    pub price_limits: PriceLimits,
+    pub mark_price: MarkPriceConfig,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FeeDisclosure {
+    /// Fee tier of the market (None = engine default schedule)
+    pub fee_tier: Option<String>,
+    /// Rates before the price factor
+    pub taker_fee: Decimal,
+    pub maker_fee: Decimal,
+    /// Cap on the fee per share
+    pub max_fee: Decimal,
+    pub formula: &'static str,
+    /// Volume discounts on the schedule
+    pub vip_tiers: Vec<VipTier>,
+    /// Days of taker volume a VIP tier is measured over
+    pub vip_window_days: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FundingDisclosure {
+    pub interval_hours: i32,
+    /// Rate clamp per interval
+    pub min_rate: Decimal,
+    pub max_rate: Decimal,
+    /// Notional the impact bid / ask is measured at
+    pub impact_pool_size: Decimal,
+    pub formula: &'static str,
+    /// How the payment is settled per margin mode
+    pub settlement: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MarginDisclosure {
+    pub max_leverage: u32,
+    /// Maintenance margin rate and leverage cap by position notional
+    pub tiers: Vec<RiskLimit>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LiquidationDisclosure {
+    /// Margin ratio (maintenance / equity) at which a cross pool is liquidated
+    pub trigger_margin_ratio: Decimal,
+    /// Margin ratio a partial liquidation restores the pool to
+    pub target_margin_ratio: Decimal,
+    /// Share of the released maintenance margin charged as the fee
+    pub penalty_ratio: Decimal,
+    pub fee_formula: &'static str,
+    /// Where the liquidation fee goes (shares sum to 1)
+    pub fee_split: Vec<FeeShare>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FeeShare {
+    pub recipient: &'static str,
+    pub share: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeleveragingDisclosure {
+    /// Whether counterparties' profitable positions are ever reduced
+    pub auto_deleveraging: bool,
+    /// Account-level reduction ahead of liquidation
+    pub policy: &'static str,
+    /// Default margin ratios of the margin call ladder
+    pub notice_ratio: Decimal,
+    pub alert_ratio: Decimal,
+    pub reduce_ratio: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PriceLimits {
+    /// Lowest and highest accepted limit price
+    pub min_price: Decimal,
+    pub max_price: Decimal,
+    /// Price increment (None = any)
+    pub tick_size: Option<Decimal>,
+    pub lot_size: Option<Decimal>,
+    pub min_order_size: Option<Decimal>,
+    pub max_order_size: Option<Decimal>,
+}
+
+/// Live values a disclosure is built from
+#[derive(Debug, Clone)]
+pub struct DisclosureInputs<'a> {
+    pub symbol: &'a str,
+    pub market: &'a MarketConfig,
+    pub funding: FundingParams,
+    pub mark_price: MarkPriceConfig,
+    pub vip_tiers: Vec<VipTier>,
+    pub vip_window_days: i64,
+    /// Maintenance rate of markets without risk-limit tiers
+    pub maintenance_margin_rate: Decimal,
+    pub liquidation_target_margin_ratio: Decimal,
+    pub liquidation_penalty_ratio: Decimal,
+}
+
+/// Assemble the disclosure of a symbol
+pub fn build(inputs: DisclosureInputs<'_>, now: DateTime<Utc>) -> RiskDisclosure {
+    let market = inputs.market;
+    let ladder = MarginCallSettings::default();
+    RiskDisclosure {
+        symbol: inputs.symbol.to_string(),
+        market_id: market.market_id,
+        generated_at: now,
+        fees: FeeDisclosure {
+            fee_tier: market.fee_tier.clone(),
+            taker_fee: market.taker_fee,
+            maker_fee: market.maker_fee,
+            max_fee: market.max_fee,
+            formula: FEE_FORMULA,
+            vip_tiers: inputs.vip_tiers,
+            vip_window_days: inputs.vip_window_days,
+        },
+        funding: FundingDisclosure {
+            interval_hours: inputs.funding.funding_interval_hours,
+            min_rate: inputs.funding.min_funding_rate,
+            max_rate: inputs.funding.max_funding_rate,
+            impact_pool_size: inputs.funding.impact_pool_size,
+            formula: FUNDING_FORMULA,
+            settlement: "cross: debited from or credited to the collateral balance; \
+                         isolated: folded into the position's cost basis, the excess settles against the balance",
+        },
+        margin: MarginDisclosure {
+            max_leverage: market.max_leverage,
+            tiers: market.risk_limits(inputs.maintenance_margin_rate),
+        },
+        liquidation: LiquidationDisclosure {
+            trigger_margin_ratio: Decimal::ONE,
+            target_margin_ratio: inputs.liquidation_target_margin_ratio,
+            penalty_ratio: inputs.liquidation_penalty_ratio,
+            fee_formula: LIQUIDATION_FEE_FORMULA,
+            fee_split: vec![FeeShare {
+                recipient: "insurance_fund",
+                share: Decimal::ONE,
+            }],
+        },
+        deleveraging: DeleveragingDisclosure {
+            auto_deleveraging: false,
+            policy: "partial liquidation of the largest positions first; accounts that opt in are reduced by their \
+                     auto-reduce percentage once their margin ratio reaches the reduce ratio",
+            notice_ratio: ladder.notice_ratio,
+            alert_ratio: ladder.alert_ratio,
+            reduce_ratio: ladder.reduce_ratio,
+        },
+        price_limits: PriceLimits {
+            min_price: CreateOrderRequest::min_price(),
+            max_price: CreateOrderRequest::max_price(),
+            tick_size: market.tick_size,
+            lot_size: market.lot_size,
+            min_order_size: market.min_order_size,
+            max_order_size: market.max_order_size,
+        },
+        mark_price: inputs.mark_price,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::market::mark_price::MarkPriceMethod;
+    use crate::services::market::MarginTier;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_disclosure_reflects_market_settings() {
+        let market = MarketConfig {
+            market_id: Uuid::nil(),
+            tick_size: Some(dec!(0.01)),
+            lot_size: None,
+            min_order_size: Some(dec!(1)),
+            max_order_size: None,
+            max_leverage: 5,
+            fee_tier: Some("low".to_string()),
+            maker_fee: dec!(0.001),
+            taker_fee: dec!(0.002),
+            max_fee: dec!(0.01),
+            margin_tiers: vec![MarginTier {
+                max_notional: None,
+                maintenance_margin_rate: dec!(0.02),
+                max_leverage: Some(3),
+            }],
+            quote_asset: None,
+            high_volatility: false,
+        };
+        let funding = FundingParams {
+            funding_interval_hours: 4,
+            ..FundingParams::default()
+        };
+        let inputs = DisclosureInputs {
+            symbol: "m:o:yes",
+            market: &market,
+            funding,
+            mark_price: MarkPriceConfig {
+                method: MarkPriceMethod::Median,
+                basis_ema_secs: 60,
+                index_sources: Vec::new(),
+            },
+            vip_tiers: Vec::new(),
+            vip_window_days: 30,
+            maintenance_margin_rate: dec!(0.005),
+            liquidation_target_margin_ratio: dec!(0.8),
+            liquidation_penalty_ratio: dec!(0.5),
+        };
+
+        let disclosure = build(inputs, Utc::now());
+        assert_eq!(disclosure.fees.taker_fee, dec!(0.002));
+        assert_eq!(disclosure.funding.interval_hours, 4);
+        assert_eq!(disclosure.funding.max_rate, dec!(0.01));
+        // The tier's cap, not the market's, bounds leverage
+        assert_eq!(disclosure.margin.tiers[0].max_leverage, 3);
+        assert_eq!(disclosure.margin.tiers[0].maintenance_margin_rate, dec!(0.02));
+        assert_eq!(disclosure.liquidation.fee_split.iter().map(|s| s.share).sum::<Decimal>(), Decimal::ONE);
+        assert_eq!((disclosure.price_limits.min_price, disclosure.price_limits.max_price), (dec!(0.01), dec!(0.99)));
+    }
+}
@@ -0,0 +1,333 @@
+//! Account Risk Profiles
+//!
+//! Every wallet is on one of three named profiles bundling its limits:
+//!
+//! | profile        | max leverage | max open notional | high-volatility markets | default stop loss |
+//! |----------------|--------------|-------------------|-------------------------|-------------------|
+//! | `conservative` | 1x           | 5,000             | no                      | 20%               |
+//! | `standard`     | 3x           | 50,000            | no                      | none              |
+//! | `pro`          | 10x          | unlimited         | yes                     | none              |
+//!
+//! Wallets start on `standard`. Moving to a riskier profile needs an
+//! appropriateness acknowledgment of the current disclosure
+//! ([`DISCLOSURE_VERSION`]); moving to a safer one takes effect at once.
+//! Sub-accounts follow their owner's profile.
+//!
+//! Limits are enforced on new orders next to the market rules: the leverage
+//! cap applies on top of the market's, open notional is held positions at
+//! cost plus resting orders, and markets flagged `high_volatility` need a
+//! profile with access. Orders that only reduce a holding skip the notional
+//! and market checks. Trigger-order defaults are published with the profile
+//! for clients to apply to the stops they place.
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::fmt;
+use std::str::FromStr;
+
+/// Version of the risk disclosure an upgrade must acknowledge
+pub const DISCLOSURE_VERSION: i32 = 1;
+
+/// Risk profile errors
+#[derive(Debug, thiserror::Error)]
+pub enum RiskProfileError {
+    #[error("Unknown risk profile: {0}")]
+    UnknownProfile(String),
+
+    #[error("Moving to the {profile} profile requires acknowledging risk disclosure version {version}")]
+    AcknowledgmentRequired { profile: RiskProfile, version: i32 },
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// Named bundle of account limits
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RiskProfile {
+    Conservative,
+    #[default]
+    Standard,
+    Pro,
+}
+
+impl RiskProfile {
+    pub const ALL: [RiskProfile; 3] = [RiskProfile::Conservative, RiskProfile::Standard, RiskProfile::Pro];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RiskProfile::Conservative => "conservative",
+            RiskProfile::Standard => "standard",
+            RiskProfile::Pro => "pro",
+        }
+    }
+
+    pub fn limits(&self) -> ProfileLimits {
+        match self {
+            RiskProfile::Conservative => ProfileLimits {
+                max_leverage: 1,
+                max_open_notional: Some(Decimal::from(5_000)),
+                high_volatility_access: false,
+                default_stop_loss_pct: Some(Decimal::new(20, 2)),
+                default_take_profit_pct: None,
+            },
+            RiskProfile::Standard => ProfileLimits {
+                max_leverage: 3,
+                max_open_notional: Some(Decimal::from(50_000)),
+                high_volatility_access: false,
+                default_stop_loss_pct: None,
+                default_take_profit_pct: None,
+            },
+            RiskProfile::Pro => ProfileLimits {
+                max_leverage: 10,
+                max_open_notional: None,
+                high_volatility_access: true,
+                default_stop_loss_pct: None,
+                default_take_profit_pct: None,
+            },
+        }
+    }
+}
+
+impl fmt::Display for RiskProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for RiskProfile {
+    type Err = RiskProfileError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RiskProfile::ALL
+            .into_iter()
+            .find(|profile| profile.as_str() == s)
+            .ok_or_else(|| RiskProfileError::UnknownProfile(s.to_string()))
+    }
+}
+
+/// Limits of a profile
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProfileLimits {
+    /// Cap on top of each market's own
+    pub max_leverage: u32,
+    /// Positions at cost plus resting orders (None = unlimited)
+    pub max_open_notional: Option<Decimal>,
+    /// May trade markets flagged high-volatility
+    pub high_volatility_access: bool,
+    /// Stop loss suggested for new positions, as a fraction of entry price
+    pub default_stop_loss_pct: Option<Decimal>,
+    pub default_take_profit_pct: Option<Decimal>,
+}
+
+/// Why an order breaks the account's profile
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ProfileViolation {
+    #[error("leverage {requested} exceeds the {profile} profile cap of {max}")]
+    LeverageTooHigh { profile: RiskProfile, requested: u32, max: u32 },
+
+    #[error("open notional {notional} would exceed the {profile} profile limit of {max}")]
+    NotionalTooHigh { profile: RiskProfile, notional: Decimal, max: Decimal },
+
+    #[error("the {0} profile cannot trade high-volatility markets")]
+    HighVolatility(RiskProfile),
+}
+
+/// Check a new order against a profile
+///
+/// `open_notional` is the account's current open notional, `order_notional`
+/// what the order adds; reduce-only orders skip the notional and market checks.
+pub fn check_order(
+    profile: RiskProfile,
+    leverage: u32,
+    high_volatility: bool,
+    open_notional: Decimal,
+    order_notional: Decimal,
+    reduce_only: bool,
+) -> Result<(), ProfileViolation> {
+    let limits = profile.limits();
+    if leverage > limits.max_leverage {
+        return Err(ProfileViolation::LeverageTooHigh {
+            profile,
+            requested: leverage,
+            max: limits.max_leverage,
+        });
+    }
+    if reduce_only {
+        return Ok(());
+    }
+    if high_volatility && !limits.high_volatility_access {
+        return Err(ProfileViolation::HighVolatility(profile));
+    }
+    if let Some(max) = limits.max_open_notional {
+        let notional = open_notional + order_notional;
+        if notional > max {
+            return Err(ProfileViolation::NotionalTooHigh { profile, notional, max });
+        }
+    }
+    Ok(())
+}
+
+/// A wallet's profile
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountRiskProfile {
+    pub profile: RiskProfile,
+    pub limits: ProfileLimits,
+    pub acknowledged_version: Option<i32>,
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    /// Disclosure an upgrade must acknowledge
+    pub disclosure_version: i32,
+}
+
+impl AccountRiskProfile {
+    fn new(profile: RiskProfile, acknowledged_version: Option<i32>, acknowledged_at: Option<DateTime<Utc>>) -> Self {
+        Self {
+            profile,
+            limits: profile.limits(),
+            acknowledged_version,
+            acknowledged_at,
+            disclosure_version: DISCLOSURE_VERSION,
+        }
+    }
+}
+
+pub struct RiskProfileService;
+
+impl RiskProfileService {
+    /// Profile of a wallet (standard if never chosen)
+    pub async fn get(pool: &PgPool, user_address: &str) -> Result<AccountRiskProfile, RiskProfileError> {
+        let row: Option<(String, Option<i32>, Option<DateTime<Utc>>)> = sqlx::query_as(
+            "SELECT profile, acknowledged_version, acknowledged_at FROM account_risk_profiles WHERE user_address = $1",
+        )
+        .bind(user_address.to_lowercase())
+        .fetch_optional(pool)
+        .await?;
+        Ok(match row {
+            Some((profile, version, at)) => AccountRiskProfile::new(profile.parse()?, version, at),
+            None => AccountRiskProfile::new(RiskProfile::default(), None, None),
+        })
+    }
+
+    /// Move a wallet to a profile; riskier profiles need the current
+    /// disclosure acknowledged
+    pub async fn select(
+        pool: &PgPool,
+        user_address: &str,
+        profile: RiskProfile,
+        acknowledged_version: Option<i32>,
+    ) -> Result<AccountRiskProfile, RiskProfileError> {
+        let user_address = user_address.to_lowercase();
+        let current = Self::get(pool, &user_address).await?;
+        if profile == current.profile {
+            return Ok(current);
+        }
+        let acknowledged_version = if profile > current.profile {
+            if acknowledged_version != Some(DISCLOSURE_VERSION) {
+                return Err(RiskProfileError::AcknowledgmentRequired {
+                    profile,
+                    version: DISCLOSURE_VERSION,
+                });
+            }
+            Some(DISCLOSURE_VERSION)
+        } else {
+            None
+        };
+
+        let mut tx = pool.begin().await?;
+        let acknowledged_at: Option<DateTime<Utc>> = sqlx::query_scalar(
+            r#"
+            INSERT INTO account_risk_profiles (user_address, profile, acknowledged_version, acknowledged_at)
+            VALUES ($1, $2, $3, CASE WHEN $3::INTEGER IS NULL THEN NULL ELSE NOW() END)
+            ON CONFLICT (user_address) DO UPDATE SET
+                profile = EXCLUDED.profile,
+                acknowledged_version = COALESCE(EXCLUDED.acknowledged_version, account_risk_profiles.acknowledged_version),
+                acknowledged_at = COALESCE(EXCLUDED.acknowledged_at, account_risk_profiles.acknowledged_at),
+                updated_at = NOW()
+            RETURNING acknowledged_at
+            "#,
+        )
+        .bind(&user_address)
+        .bind(profile.as_str())
+        .bind(acknowledged_version)
+        .fetch_one(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            INSERT INTO risk_profile_changes (user_address, from_profile, to_profile, acknowledged_version)
+            VALUES ($1, $2, $3, $4)
+            "#,
+        )
+        .bind(&user_address)
+        .bind(current.profile.as_str())
+        .bind(profile.as_str())
+        .bind(acknowledged_version)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        tracing::info!("Risk profile of {} changed from {} to {}", user_address, current.profile, profile);
+        let acknowledged_version = acknowledged_version.or(current.acknowledged_version);
+        Ok(AccountRiskProfile::new(profile, acknowledged_version, acknowledged_at))
+    }
+
+    /// Held positions at cost plus resting orders of an account
+    pub async fn open_notional(pool: &PgPool, user_address: &str) -> Result<Decimal, RiskProfileError> {
+        let notional: Option<Decimal> = sqlx::query_scalar(
+            r#"
+            SELECT
+                COALESCE((SELECT SUM(ABS(amount) * avg_cost) FROM shares WHERE user_address = $1), 0)
+                + COALESCE((
+                    SELECT SUM((amount - filled_amount) * price)
+                    FROM orders
+                    WHERE user_address = $1 AND status IN ('open', 'pending', 'partially_filled')
+                ), 0)
+            "#,
+        )
+        .bind(user_address.to_lowercase())
+        .fetch_one(pool)
+        .await?;
+        Ok(notional.unwrap_or(Decimal::ZERO))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_profiles_order_by_risk() {
+        assert!(RiskProfile::Conservative < RiskProfile::Standard);
+        assert!(RiskProfile::Standard < RiskProfile::Pro);
+        assert_eq!("pro".parse::<RiskProfile>().unwrap(), RiskProfile::Pro);
+        assert!("yolo".parse::<RiskProfile>().is_err());
+    }
+
+    #[test]
+    fn test_check_order_against_profile() {
+        let conservative = RiskProfile::Conservative;
+        assert!(check_order(conservative, 1, false, dec!(4000), dec!(1000), false).is_ok());
+        assert_eq!(
+            check_order(conservative, 1, false, dec!(4000), dec!(1001), false),
+            Err(ProfileViolation::NotionalTooHigh {
+                profile: conservative,
+                notional: dec!(5001),
+                max: dec!(5000)
+            })
+        );
+        assert!(matches!(
+            check_order(conservative, 2, false, dec!(0), dec!(10), false),
+            Err(ProfileViolation::LeverageTooHigh { max: 1, .. })
+        ));
+        assert_eq!(
+            check_order(RiskProfile::Standard, 1, true, dec!(0), dec!(10), false),
+            Err(ProfileViolation::HighVolatility(RiskProfile::Standard))
+        );
+
+        // Reducing a holding is never blocked by notional or market access
+        assert!(check_order(conservative, 1, true, dec!(9000), dec!(1000), true).is_ok());
+        assert!(check_order(RiskProfile::Pro, 10, true, dec!(1_000_000), dec!(1), false).is_ok());
+    }
+}
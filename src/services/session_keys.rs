@@ -0,0 +1,313 @@
+//! Session Keys
+//!
+//! A wallet can delegate order signing to a session key, typically a key the
+//! frontend generates and keeps in the browser, so trading doesn't prompt the
+//! wallet on every order. The owner authorizes the key once by signing an
+//! EIP-712 `AuthorizeSessionKey` message naming the key, the actions it may
+//! sign and its expiry:
+//!
+//! - `place` - create orders
+//! - `amend` - amend orders
+//! - `cancel` - cancel orders (single and batch)
+//!
+//! Trading messages keep the owner's wallet in the `wallet` field; only the
+//! signature comes from the session key. [`crate::auth::eip712`] looks the
+//! recovered signer up here before rejecting a signature that isn't the
+//! owner's. Withdrawals, key management and login always need the wallet
+//! itself.
+//!
+//! Registering a key for the same address again replaces the previous
+//! authorization; revoking takes effect on the key's next signature. Keys
+//! are only registered by signed authorization: no contract in this
+//! deployment emits delegation events to index.
+
+use chrono::{DateTime, Duration, Utc};
+use ethers::types::Address;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Most active session keys per owner
+pub const MAX_ACTIVE_KEYS: i64 = 10;
+
+/// Longest lifetime of an authorization
+pub const MAX_SESSION_DAYS: i64 = 30;
+
+/// Session key errors
+#[derive(Debug, thiserror::Error)]
+pub enum SessionKeyError {
+    #[error("Session key not found: {0}")]
+    NotFound(Uuid),
+
+    #[error("Invalid session key request: {0}")]
+    Invalid(String),
+
+    #[error("Session key limit reached ({0} active)")]
+    LimitReached(i64),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// What a session key may sign
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionScope {
+    Place,
+    Amend,
+    Cancel,
+}
+
+impl SessionScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionScope::Place => "place",
+            SessionScope::Amend => "amend",
+            SessionScope::Cancel => "cancel",
+        }
+    }
+
+    fn parse(s: &str) -> Result<Self, SessionKeyError> {
+        match s {
+            "place" => Ok(SessionScope::Place),
+            "amend" => Ok(SessionScope::Amend),
+            "cancel" => Ok(SessionScope::Cancel),
+            other => Err(SessionKeyError::Invalid(format!("unknown scope: {}", other))),
+        }
+    }
+}
+
+/// A registered session key
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct SessionKey {
+    pub id: Uuid,
+    pub owner_address: String,
+    pub session_address: String,
+    pub scopes: Vec<String>,
+    pub expires_at: DateTime<Utc>,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+/// A session key authorization, as signed by the owner
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewSessionKey {
+    /// Address of the session key
+    pub session_key: String,
+    pub scopes: Vec<String>,
+    /// Unix seconds
+    pub expires_at: u64,
+}
+
+impl NewSessionKey {
+    /// Scopes as they appear in the signed message
+    pub fn signed_scopes(&self) -> String {
+        self.scopes.join(",")
+    }
+}
+
+/// Check an authorization and return its normalized scopes and expiry
+fn validate(
+    owner: &str,
+    request: &NewSessionKey,
+    now: DateTime<Utc>,
+) -> Result<(Vec<SessionScope>, DateTime<Utc>), SessionKeyError> {
+    let session_key = Address::from_str(&request.session_key)
+        .map_err(|_| SessionKeyError::Invalid(format!("not an address: {}", request.session_key)))?;
+    if Address::from_str(owner).is_ok_and(|owner| owner == session_key) {
+        return Err(SessionKeyError::Invalid("the session key must differ from the wallet".to_string()));
+    }
+    let mut scopes = request
+        .scopes
+        .iter()
+        .map(|s| SessionScope::parse(&s.trim().to_lowercase()))
+        .collect::<Result<Vec<_>, _>>()?;
+    scopes.sort_by_key(|s| s.as_str());
+    scopes.dedup();
+    if scopes.is_empty() {
+        return Err(SessionKeyError::Invalid("at least one scope is required".to_string()));
+    }
+    let expires_at = i64::try_from(request.expires_at)
+        .ok()
+        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        .ok_or_else(|| SessionKeyError::Invalid("invalid expiry".to_string()))?;
+    if expires_at <= now {
+        return Err(SessionKeyError::Invalid("the authorization has already expired".to_string()));
+    }
+    if expires_at > now + Duration::days(MAX_SESSION_DAYS) {
+        return Err(SessionKeyError::Invalid(format!("expiry is at most {} days ahead", MAX_SESSION_DAYS)));
+    }
+    Ok((scopes, expires_at))
+}
+
+/// Registers, looks up and revokes session keys
+pub struct SessionKeyService;
+
+impl SessionKeyService {
+    /// Register a key whose authorization signature the caller has verified,
+    /// replacing an active authorization of the same key
+    pub async fn register(
+        pool: &PgPool,
+        owner: &str,
+        request: &NewSessionKey,
+        signature: &str,
+        now: DateTime<Utc>,
+    ) -> Result<SessionKey, SessionKeyError> {
+        let owner = owner.to_lowercase();
+        let (scopes, expires_at) = validate(&owner, request, now)?;
+        let session_address = request.session_key.to_lowercase();
+
+        let mut tx = pool.begin().await?;
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+            .bind(format!("session_keys:{}", owner))
+            .execute(&mut *tx)
+            .await?;
+        let replaced = sqlx::query(
+            r#"
+            UPDATE session_keys SET status = 'revoked', revoked_at = NOW()
+            WHERE owner_address = $1 AND session_address = $2 AND status = 'active'
+            "#,
+        )
+        .bind(&owner)
+        .bind(&session_address)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        let active: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM session_keys WHERE owner_address = $1 AND status = 'active' AND expires_at > $2",
+        )
+        .bind(&owner)
+        .bind(now)
+        .fetch_one(&mut *tx)
+        .await?;
+        if active >= MAX_ACTIVE_KEYS {
+            return Err(SessionKeyError::LimitReached(MAX_ACTIVE_KEYS));
+        }
+
+        let scopes: Vec<&str> = scopes.iter().map(|s| s.as_str()).collect();
+        let key: SessionKey = sqlx::query_as(
+            r#"
+            INSERT INTO session_keys (id, owner_address, session_address, scopes, expires_at, authorization_signature)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, owner_address, session_address, scopes, expires_at, status, created_at, revoked_at
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(&owner)
+        .bind(&session_address)
+        .bind(&scopes)
+        .bind(expires_at)
+        .bind(signature)
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        tracing::info!(
+            "Session key {} registered for {} with scopes {:?} until {}{}",
+            session_address,
+            owner,
+            key.scopes,
+            key.expires_at,
+            if replaced > 0 { " (replacing its previous authorization)" } else { "" }
+        );
+        Ok(key)
+    }
+
+    /// Session keys of an owner, newest first
+    pub async fn list(pool: &PgPool, owner: &str) -> Result<Vec<SessionKey>, SessionKeyError> {
+        let rows = sqlx::query_as(
+            r#"
+            SELECT id, owner_address, session_address, scopes, expires_at, status, created_at, revoked_at
+            FROM session_keys
+            WHERE owner_address = $1
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(owner.to_lowercase())
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
+    }
+
+    /// Revoke a session key
+    pub async fn revoke(pool: &PgPool, owner: &str, id: Uuid) -> Result<SessionKey, SessionKeyError> {
+        let key: SessionKey = sqlx::query_as(
+            r#"
+            UPDATE session_keys SET
+                status = 'revoked',
+                revoked_at = COALESCE(revoked_at, NOW())
+            WHERE id = $1 AND owner_address = $2
+            RETURNING id, owner_address, session_address, scopes, expires_at, status, created_at, revoked_at
+            "#,
+        )
+        .bind(id)
+        .bind(owner.to_lowercase())
+        .fetch_optional(pool)
+        .await?
+        .ok_or(SessionKeyError::NotFound(id))?;
+
+        tracing::info!("Session key {} of {} revoked", key.session_address, key.owner_address);
+        Ok(key)
+    }
+
+    /// Whether `signer` holds an active, unexpired authorization from `owner`
+    /// covering `scope`
+    pub async fn is_authorized(
+        pool: &PgPool,
+        owner: &str,
+        signer: &str,
+        scope: SessionScope,
+        now: DateTime<Utc>,
+    ) -> Result<bool, SessionKeyError> {
+        let authorized = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM session_keys
+                WHERE owner_address = $1 AND session_address = $2 AND status = 'active'
+                  AND expires_at > $3 AND $4 = ANY(scopes)
+            )
+            "#,
+        )
+        .bind(owner.to_lowercase())
+        .bind(signer.to_lowercase())
+        .bind(now)
+        .bind(scope.as_str())
+        .fetch_one(pool)
+        .await?;
+        Ok(authorized)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OWNER: &str = "0xfde43f8e6e082975d246844def4fe8e704403d43";
+    const SESSION: &str = "0x1111111111111111111111111111111111111111";
+
+    fn request(session_key: &str, scopes: &[&str], expires_in: Duration, now: DateTime<Utc>) -> NewSessionKey {
+        NewSessionKey {
+            session_key: session_key.to_string(),
+            scopes: scopes.iter().map(|s| s.to_string()).collect(),
+            expires_at: (now + expires_in).timestamp() as u64,
+        }
+    }
+
+    #[test]
+    fn test_validate_authorization() {
+        let now = Utc::now();
+        let scopes = validate(OWNER, &request(SESSION, &["cancel", "Place", "cancel"], Duration::hours(1), now), now);
+        assert_eq!(scopes.unwrap().0, vec![SessionScope::Cancel, SessionScope::Place]);
+
+        let invalid = |req: NewSessionKey| matches!(validate(OWNER, &req, now), Err(SessionKeyError::Invalid(_)));
+        // The wallet can't delegate to itself
+        assert!(invalid(request(OWNER, &["place"], Duration::hours(1), now)));
+        assert!(invalid(request("not-an-address", &["place"], Duration::hours(1), now)));
+        assert!(invalid(request(SESSION, &[], Duration::hours(1), now)));
+        assert!(invalid(request(SESSION, &["withdraw"], Duration::hours(1), now)));
+        assert!(invalid(request(SESSION, &["place"], Duration::seconds(-1), now)));
+        assert!(invalid(request(SESSION, &["place"], Duration::days(MAX_SESSION_DAYS + 1), now)));
+    }
+}
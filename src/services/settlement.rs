@@ -10,6 +10,8 @@ use tracing::info;
 use uuid::Uuid;
 
 use crate::models::market::ShareType;
+use crate::services::ledger::{BalanceChange, LedgerReason, LedgerService};
+use crate::services::position::PositionService;
 
 /// Settlement service errors
 #[derive(Debug, thiserror::Error)]
@@ -190,11 +192,12 @@ impl SettlementService {
                 .execute(&mut *tx)
                 .await?;
 
-                // Zero out user's shares
+                // Archive the position, then zero out user's shares
+                PositionService::close_settled(&mut tx, share_id, payout_per_share, "USDC").await?;
                 sqlx::query(
                     r#"
                     UPDATE shares
-                    SET amount = 0, updated_at = NOW()
+                    SET amount = 0, version = version + 1, updated_at = NOW()
                     WHERE id = $1
                     "#
                 )
@@ -216,19 +219,9 @@ impl SettlementService {
 
         // 6. Credit user's USDC balance
         if total_payout > Decimal::ZERO {
-            sqlx::query(
-                r#"
-                INSERT INTO balances (user_address, token, available, frozen)
-                VALUES ($1, 'USDC', $2, 0)
-                ON CONFLICT (user_address, token) DO UPDATE SET
-                    available = balances.available + $2,
-                    updated_at = NOW()
-                "#
-            )
-            .bind(&user_address)
-            .bind(total_payout)
-            .execute(&mut *tx)
-            .await?;
+            let change = BalanceChange::credit(&user_address, "USDC", total_payout, LedgerReason::MarketSettlement)
+                .reference(market_id);
+            LedgerService::apply(&mut tx, &change).await?;
 
             info!(
                 "Settlement complete: user={}, market={}, payout={}",
@@ -0,0 +1,339 @@
+//! Daily Settlement Prices
+//!
+//! Publishes one official settlement price per symbol
+//! (`{market_id}:{outcome_id}:{share_type}`) and UTC day: the time-weighted
+//! average trade price over the last `settlement_price_window_mins` minutes
+//! before 00:00 UTC. The price in effect at the window start is the last
+//! trade before it, so a quiet window still weighs the standing price. A
+//! symbol with no trade in the window settles at its last trade; a symbol that
+//! never traded gets no price. Block trades are excluded.
+//!
+//! Prices are stored in `settlement_prices`, broadcast on the
+//! `settlement_prices:{symbol}` / `settlement_prices:*` WebSocket channels and
+//! served by `GET /markets/:symbol/settlement-prices`. [`SettlementPriceService::price`]
+//! is the canonical daily mark for anything settling or reporting per day.
+//! Once published, a day's price never changes.
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tokio::sync::broadcast;
+
+use crate::services::matching::OrderbookSnapshot;
+
+/// Settlement price errors
+#[derive(Debug, thiserror::Error)]
+pub enum SettlementPriceError {
+    #[error("Invalid symbol: {0}")]
+    InvalidSymbol(String),
+
+    #[error("Invalid date range: {0}")]
+    InvalidRange(String),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// How a settlement price was derived
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SettlementMethod {
+    /// Time-weighted average over the window
+    Twap,
+    /// No trade in the window: the last trade before it
+    LastTrade,
+}
+
+impl SettlementMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SettlementMethod::Twap => "twap",
+            SettlementMethod::LastTrade => "last_trade",
+        }
+    }
+}
+
+/// Published settlement price of one symbol for one day
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+pub struct SettlementPrice {
+    pub symbol: String,
+    pub settlement_date: NaiveDate,
+    pub price: Decimal,
+    pub method: String,
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    pub trade_count: i32,
+    pub published_at: DateTime<Utc>,
+}
+
+/// Time-weighted average price over `[start, end)`
+///
+/// `prior` is the price in effect at `start` (the last earlier trade);
+/// without it the average starts at the first trade. Trades must be sorted
+/// and inside the window.
+pub fn twap(
+    prior: Option<Decimal>,
+    trades: &[(DateTime<Utc>, Decimal)],
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Option<(Decimal, SettlementMethod)> {
+    if trades.is_empty() {
+        return prior.map(|price| (price, SettlementMethod::LastTrade));
+    }
+
+    let mut current = prior;
+    let mut since = start;
+    let mut weighted = Decimal::ZERO;
+    let mut total_ms = 0i64;
+    for &(at, price) in trades {
+        if let Some(current) = current {
+            let ms = (at - since).num_milliseconds().max(0);
+            weighted += current * Decimal::from(ms);
+            total_ms += ms;
+        }
+        current = Some(price);
+        since = at;
+    }
+    let last = current?;
+    let ms = (end - since).num_milliseconds().max(0);
+    weighted += last * Decimal::from(ms);
+    total_ms += ms;
+
+    if total_ms == 0 {
+        return Some((last, SettlementMethod::Twap));
+    }
+    Some(((weighted / Decimal::from(total_ms)).round_dp(8).normalize(), SettlementMethod::Twap))
+}
+
+/// UTC day whose settlement window has most recently closed at `now`
+pub fn settled_day(now: DateTime<Utc>) -> NaiveDate {
+    now.date_naive() - Duration::days(1)
+}
+
+/// Settlement price publisher
+pub struct SettlementPriceService {
+    sender: broadcast::Sender<SettlementPrice>,
+}
+
+impl Default for SettlementPriceService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SettlementPriceService {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(1000);
+        Self { sender }
+    }
+
+    /// Newly published settlement prices
+    pub fn subscribe(&self) -> broadcast::Receiver<SettlementPrice> {
+        self.sender.subscribe()
+    }
+
+    /// Publish the prices of the last closed day that are still missing;
+    /// returns the prices published
+    pub async fn publish_due(
+        &self,
+        pool: &PgPool,
+        now: DateTime<Utc>,
+        window_mins: i64,
+    ) -> Result<Vec<SettlementPrice>, SettlementPriceError> {
+        let date = settled_day(now);
+        let window_end = (date + Duration::days(1))
+            .and_hms_opt(0, 0, 0)
+            .map(|t| t.and_utc())
+            .ok_or_else(|| SettlementPriceError::InvalidRange(date.to_string()))?;
+        let window_start = window_end - Duration::minutes(window_mins.max(1));
+
+        // Symbols with holdings or trades on the day, not yet published
+        let symbols: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT symbol FROM (
+                SELECT market_id::text || ':' || outcome_id::text || ':' || share_type::text AS symbol
+                FROM shares
+                WHERE amount <> 0
+                UNION
+                SELECT market_id::text || ':' || outcome_id::text || ':' || share_type::text
+                FROM trades
+                WHERE created_at >= $1 - INTERVAL '1 day' AND created_at < $1
+            ) s
+            WHERE NOT EXISTS (
+                SELECT 1 FROM settlement_prices p WHERE p.symbol = s.symbol AND p.settlement_date = $2
+            )
+            ORDER BY symbol
+            "#,
+        )
+        .bind(window_end)
+        .bind(date)
+        .fetch_all(pool)
+        .await?;
+
+        let mut published = Vec::new();
+        for symbol in symbols {
+            match self.publish(pool, &symbol, date, window_start, window_end).await {
+                Ok(Some(price)) => published.push(price),
+                Ok(None) => {}
+                Err(e) => tracing::error!("Failed to publish settlement price of {} for {}: {}", symbol, date, e),
+            }
+        }
+        Ok(published)
+    }
+
+    /// Compute, store and broadcast one symbol's price (None: never traded,
+    /// or another instance published it first)
+    async fn publish(
+        &self,
+        pool: &PgPool,
+        symbol: &str,
+        date: NaiveDate,
+        window_start: DateTime<Utc>,
+        window_end: DateTime<Utc>,
+    ) -> Result<Option<SettlementPrice>, SettlementPriceError> {
+        let (market_id, outcome_id, share_type) = OrderbookSnapshot::parse_market_key(symbol)
+            .ok_or_else(|| SettlementPriceError::InvalidSymbol(symbol.to_string()))?;
+
+        let trades: Vec<(DateTime<Utc>, Decimal)> = sqlx::query_as(
+            r#"
+            SELECT created_at, price FROM trades
+            WHERE market_id = $1 AND outcome_id = $2 AND share_type = $3::share_type
+              AND NOT is_block_trade AND created_at >= $4 AND created_at < $5
+            ORDER BY created_at, exec_id
+            "#,
+        )
+        .bind(market_id)
+        .bind(outcome_id)
+        .bind(share_type.to_string())
+        .bind(window_start)
+        .bind(window_end)
+        .fetch_all(pool)
+        .await?;
+        let prior: Option<Decimal> = sqlx::query_scalar(
+            r#"
+            SELECT price FROM trades
+            WHERE market_id = $1 AND outcome_id = $2 AND share_type = $3::share_type
+              AND NOT is_block_trade AND created_at < $4
+            ORDER BY created_at DESC, exec_id DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(market_id)
+        .bind(outcome_id)
+        .bind(share_type.to_string())
+        .bind(window_start)
+        .fetch_optional(pool)
+        .await?;
+
+        let Some((price, method)) = twap(prior, &trades, window_start, window_end) else {
+            return Ok(None);
+        };
+
+        let stored: Option<SettlementPrice> = sqlx::query_as(
+            r#"
+            INSERT INTO settlement_prices (
+                symbol, settlement_date, price, method, window_start, window_end, trade_count
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (symbol, settlement_date) DO NOTHING
+            RETURNING symbol, settlement_date, price, method, window_start, window_end, trade_count, published_at
+            "#,
+        )
+        .bind(symbol)
+        .bind(date)
+        .bind(price)
+        .bind(method.as_str())
+        .bind(window_start)
+        .bind(window_end)
+        .bind(trades.len() as i32)
+        .fetch_optional(pool)
+        .await?;
+
+        if let Some(stored) = &stored {
+            tracing::info!("Settlement price {} for {}: {} ({})", stored.symbol, date, stored.price, stored.method);
+            // No receivers just means no WebSocket clients are connected
+            let _ = self.sender.send(stored.clone());
+        }
+        Ok(stored)
+    }
+
+    /// Canonical daily mark of a symbol
+    pub async fn price(
+        pool: &PgPool,
+        symbol: &str,
+        date: NaiveDate,
+    ) -> Result<Option<SettlementPrice>, SettlementPriceError> {
+        let price = sqlx::query_as(
+            r#"
+            SELECT symbol, settlement_date, price, method, window_start, window_end, trade_count, published_at
+            FROM settlement_prices
+            WHERE symbol = $1 AND settlement_date = $2
+            "#,
+        )
+        .bind(symbol)
+        .bind(date)
+        .fetch_optional(pool)
+        .await?;
+        Ok(price)
+    }
+
+    /// Published prices of a symbol for `[from, to]`, newest first
+    pub async fn history(
+        pool: &PgPool,
+        symbol: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<SettlementPrice>, SettlementPriceError> {
+        if from > to {
+            return Err(SettlementPriceError::InvalidRange(format!("from {} is after to {}", from, to)));
+        }
+        if (to - from).num_days() > 366 {
+            return Err(SettlementPriceError::InvalidRange("at most 366 days per request".to_string()));
+        }
+        let prices = sqlx::query_as(
+            r#"
+            SELECT symbol, settlement_date, price, method, window_start, window_end, trade_count, published_at
+            FROM settlement_prices
+            WHERE symbol = $1 AND settlement_date BETWEEN $2 AND $3
+            ORDER BY settlement_date DESC
+            "#,
+        )
+        .bind(symbol)
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await?;
+        Ok(prices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_twap_weights_by_time() {
+        let start = Utc.with_ymd_and_hms(2026, 10, 14, 23, 30, 0).unwrap();
+        let end = start + Duration::minutes(30);
+
+        // 0.40 standing for 10 minutes, 0.70 for the last 20
+        let trades = [(start + Duration::minutes(10), dec!(0.70))];
+        assert_eq!(twap(Some(dec!(0.40)), &trades, start, end), Some((dec!(0.6), SettlementMethod::Twap)));
+
+        // Without a prior trade the average starts at the first trade
+        assert_eq!(twap(None, &trades, start, end), Some((dec!(0.70), SettlementMethod::Twap)));
+
+        // Quiet window: last trade before it; never traded: no price
+        assert_eq!(twap(Some(dec!(0.40)), &[], start, end), Some((dec!(0.40), SettlementMethod::LastTrade)));
+        assert_eq!(twap(None, &[], start, end), None);
+    }
+
+    #[test]
+    fn test_settled_day() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 15, 0, 1, 0).unwrap();
+        assert_eq!(settled_day(now), NaiveDate::from_ymd_opt(2026, 10, 14).unwrap());
+    }
+}
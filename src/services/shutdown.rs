@@ -0,0 +1,152 @@
+//! Graceful Shutdown
+//!
+//! On SIGINT / SIGTERM the server shuts down in two phases:
+//!
+//! 1. **draining**: new orders are rejected (503 `SHUTTING_DOWN`), WebSocket
+//!    clients get a close frame, and the HTTP server stops accepting
+//!    connections while in-flight requests finish.
+//! 2. **stopping**: once no request can produce more work, background
+//!    workers flush what they have queued (trade persistence) and exit, job
+//!    loops stop after their current run, and the engine writes a snapshot
+//!    of its resting orders.
+//!
+//! Workers spawned through [`ShutdownCoordinator::spawn`] (or holding a
+//! [`ShutdownCoordinator::track`] token) are awaited, up to a timeout, before
+//! the process exits.
+
+use std::future::Future;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::task_tracker::TaskTrackerToken;
+use tokio_util::task::TaskTracker;
+
+/// Coordinates the shutdown phases and tracks the workers to wait for
+#[derive(Default)]
+pub struct ShutdownCoordinator {
+    draining: CancellationToken,
+    stopping: CancellationToken,
+    tasks: TaskTracker,
+}
+
+impl ShutdownCoordinator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enter the draining phase: stop accepting new work
+    pub fn begin(&self) {
+        if !self.draining.is_cancelled() {
+            tracing::info!("Shutdown requested, draining");
+        }
+        self.draining.cancel();
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.is_cancelled()
+    }
+
+    /// Resolves once draining starts
+    pub async fn draining(&self) {
+        self.draining.cancelled().await
+    }
+
+    /// Enter the stopping phase: workers flush and exit
+    pub fn stop_workers(&self) {
+        self.begin();
+        self.stopping.cancel();
+    }
+
+    /// Resolves once workers should flush and exit
+    pub async fn stopping(&self) {
+        self.stopping.cancelled().await
+    }
+
+    /// Spawn a worker that shutdown waits for
+    pub fn spawn<F>(&self, task: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.tasks.spawn(task)
+    }
+
+    /// Keep shutdown waiting while the token is held
+    pub fn track(&self) -> TaskTrackerToken {
+        self.tasks.token()
+    }
+
+    /// Wait for tracked workers; false if some were still running at `timeout`
+    pub async fn wait(&self, timeout: Duration) -> bool {
+        self.tasks.close();
+        let finished = tokio::time::timeout(timeout, self.tasks.wait()).await.is_ok();
+        if !finished {
+            tracing::warn!("Shutdown timed out with {} workers still running", self.tasks.len());
+        }
+        finished
+    }
+}
+
+/// Resolves on SIGINT (Ctrl-C) or, on Unix, SIGTERM
+pub async fn signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_waits_for_workers_to_flush() {
+        let shutdown = std::sync::Arc::new(ShutdownCoordinator::new());
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<u32>();
+        let flushed = std::sync::Arc::new(parking_lot::Mutex::new(Vec::new()));
+
+        let worker = shutdown.clone();
+        let sink = flushed.clone();
+        shutdown.spawn(async move {
+            loop {
+                tokio::select! {
+                    biased;
+                    Some(item) = rx.recv() => sink.lock().push(item),
+                    _ = worker.stopping() => break,
+                }
+            }
+        });
+
+        shutdown.begin();
+        assert!(shutdown.is_draining());
+        // Work produced while draining is still flushed
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        shutdown.stop_workers();
+
+        assert!(shutdown.wait(Duration::from_secs(1)).await);
+        assert_eq!(*flushed.lock(), vec![1, 2]);
+    }
+}
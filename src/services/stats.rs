@@ -0,0 +1,281 @@
+//! Market Statistics
+//!
+//! Snapshots per-symbol (`{market_id}:{outcome_id}:{share_type}`) market
+//! statistics every minute into `market_stats`:
+//!
+//! - open interest: outstanding shares (positive holdings), with the short
+//!   side (negative holdings) and the long/short ratio of the two
+//! - 24h volume, quote volume and trade count, from the trades table
+//!
+//! The latest snapshot is also kept in memory; the per-symbol metrics sampler
+//! reads its open interest instead of querying holdings itself. History is
+//! served in K-line periods, each bucket reporting the last snapshot in it.
+
+use chrono::{DateTime, DurationRound, Utc};
+use parking_lot::RwLock;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::db::timescale::KlinePeriod;
+
+/// Snapshot errors
+#[derive(Debug, thiserror::Error)]
+pub enum StatsError {
+    #[error("Invalid symbol: {0}")]
+    InvalidSymbol(String),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// Statistics of one symbol at one point in time
+#[derive(Debug, Clone, Default, PartialEq, Serialize, sqlx::FromRow)]
+pub struct MarketStats {
+    pub symbol: String,
+    #[serde(serialize_with = "serialize_millis")]
+    pub ts: DateTime<Utc>,
+    /// Outstanding shares (the long side)
+    pub open_interest: Decimal,
+    pub long_open_interest: Decimal,
+    pub short_open_interest: Decimal,
+    /// Long / short open interest (None without shorts)
+    pub long_short_ratio: Option<Decimal>,
+    pub volume_24h: Decimal,
+    pub quote_volume_24h: Decimal,
+    pub trade_count_24h: i64,
+}
+
+fn serialize_millis<S: serde::Serializer>(ts: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_i64(ts.timestamp_millis())
+}
+
+/// Validate a `{market_id}:{outcome_id}:{share_type}` symbol, returning its
+/// canonical (lowercase share type) form
+pub fn parse_symbol(symbol: &str) -> Result<String, StatsError> {
+    let invalid = || StatsError::InvalidSymbol(symbol.to_string());
+    let mut parts = symbol.split(':');
+    let (Some(market), Some(outcome), Some(share), None) = (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(invalid());
+    };
+    let market: Uuid = market.parse().map_err(|_| invalid())?;
+    let outcome: Uuid = outcome.parse().map_err(|_| invalid())?;
+    let share = share.to_lowercase();
+    if share != "yes" && share != "no" {
+        return Err(invalid());
+    }
+    Ok(format!("{}:{}:{}", market, outcome, share))
+}
+
+/// Long / short ratio of open interest (None without shorts)
+pub fn long_short_ratio(long: Decimal, short: Decimal) -> Option<Decimal> {
+    (!short.is_zero()).then(|| (long / short).round_dp(4))
+}
+
+/// Holdings aggregated per symbol
+#[derive(sqlx::FromRow)]
+struct OpenInterestRow {
+    symbol: String,
+    long_open_interest: Decimal,
+    short_open_interest: Decimal,
+}
+
+/// Trading activity per symbol over the last 24 hours
+#[derive(sqlx::FromRow)]
+struct VolumeRow {
+    symbol: String,
+    volume: Decimal,
+    quote_volume: Decimal,
+    trade_count: i64,
+}
+
+/// Market statistics service
+#[derive(Default)]
+pub struct StatsService {
+    /// Latest snapshot per symbol
+    latest: RwLock<HashMap<String, MarketStats>>,
+}
+
+impl StatsService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open interest of every symbol in the latest snapshot
+    pub fn open_interest(&self) -> HashMap<String, Decimal> {
+        self.latest
+            .read()
+            .iter()
+            .map(|(symbol, stats)| (symbol.clone(), stats.open_interest))
+            .collect()
+    }
+
+    /// Take a snapshot of every symbol with holdings or recent trades, store it
+    /// and drop snapshots older than `retention_days`; returns the number of
+    /// symbols recorded
+    pub async fn snapshot(&self, pool: &PgPool, retention_days: i64) -> Result<usize, StatsError> {
+        let ts = Utc::now().duration_trunc(chrono::Duration::minutes(1)).unwrap_or_else(|_| Utc::now());
+
+        let open_interest: Vec<OpenInterestRow> = sqlx::query_as(
+            r#"
+            SELECT market_id::text || ':' || outcome_id::text || ':' || share_type::text AS symbol,
+                   COALESCE(SUM(amount) FILTER (WHERE amount > 0), 0) AS long_open_interest,
+                   COALESCE(-SUM(amount) FILTER (WHERE amount < 0), 0) AS short_open_interest
+            FROM shares
+            WHERE amount <> 0
+            GROUP BY market_id, outcome_id, share_type
+            "#,
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let volumes: Vec<VolumeRow> = sqlx::query_as(
+            r#"
+            SELECT market_id::text || ':' || outcome_id::text || ':' || share_type::text AS symbol,
+                   SUM(amount) AS volume,
+                   SUM(amount * price) AS quote_volume,
+                   COUNT(*) AS trade_count
+            FROM trades
+            WHERE created_at >= $1 - INTERVAL '24 hours' AND created_at < $1
+            GROUP BY market_id, outcome_id, share_type
+            "#,
+        )
+        .bind(ts)
+        .fetch_all(pool)
+        .await?;
+
+        let mut snapshot: HashMap<String, MarketStats> = HashMap::new();
+        for row in open_interest {
+            snapshot.insert(
+                row.symbol.clone(),
+                MarketStats {
+                    symbol: row.symbol,
+                    ts,
+                    open_interest: row.long_open_interest,
+                    long_open_interest: row.long_open_interest,
+                    short_open_interest: row.short_open_interest,
+                    long_short_ratio: long_short_ratio(row.long_open_interest, row.short_open_interest),
+                    ..Default::default()
+                },
+            );
+        }
+        for row in volumes {
+            let stats = snapshot.entry(row.symbol.clone()).or_insert_with(|| MarketStats {
+                symbol: row.symbol,
+                ts,
+                ..Default::default()
+            });
+            stats.volume_24h = row.volume;
+            stats.quote_volume_24h = row.quote_volume;
+            stats.trade_count_24h = row.trade_count;
+        }
+
+        let mut tx = pool.begin().await?;
+        for stats in snapshot.values() {
+            sqlx::query(
+                r#"
+                INSERT INTO market_stats (
+                    symbol, ts, open_interest, long_open_interest, short_open_interest, long_short_ratio,
+                    volume_24h, quote_volume_24h, trade_count_24h
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                ON CONFLICT (symbol, ts) DO UPDATE SET
+                    open_interest = EXCLUDED.open_interest,
+                    long_open_interest = EXCLUDED.long_open_interest,
+                    short_open_interest = EXCLUDED.short_open_interest,
+                    long_short_ratio = EXCLUDED.long_short_ratio,
+                    volume_24h = EXCLUDED.volume_24h,
+                    quote_volume_24h = EXCLUDED.quote_volume_24h,
+                    trade_count_24h = EXCLUDED.trade_count_24h
+                "#,
+            )
+            .bind(&stats.symbol)
+            .bind(stats.ts)
+            .bind(stats.open_interest)
+            .bind(stats.long_open_interest)
+            .bind(stats.short_open_interest)
+            .bind(stats.long_short_ratio)
+            .bind(stats.volume_24h)
+            .bind(stats.quote_volume_24h)
+            .bind(stats.trade_count_24h)
+            .execute(&mut *tx)
+            .await?;
+        }
+        sqlx::query("DELETE FROM market_stats WHERE ts < NOW() - make_interval(days => $1)")
+            .bind(retention_days.max(1) as i32)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        let count = snapshot.len();
+        *self.latest.write() = snapshot;
+        Ok(count)
+    }
+
+    /// Snapshots of a symbol in `[start, end)`, one per period bucket (the
+    /// last one taken in it), newest first
+    pub async fn history(
+        pool: &PgPool,
+        symbol: &str,
+        period: KlinePeriod,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<MarketStats>, StatsError> {
+        let stats = sqlx::query_as(
+            r#"
+            SELECT symbol,
+                   time_bucket($2::interval, ts) AS ts,
+                   last(open_interest, ts) AS open_interest,
+                   last(long_open_interest, ts) AS long_open_interest,
+                   last(short_open_interest, ts) AS short_open_interest,
+                   last(long_short_ratio, ts) AS long_short_ratio,
+                   last(volume_24h, ts) AS volume_24h,
+                   last(quote_volume_24h, ts) AS quote_volume_24h,
+                   last(trade_count_24h, ts) AS trade_count_24h
+            FROM market_stats
+            WHERE symbol = $1 AND ts >= $3 AND ts < $4
+            GROUP BY symbol, time_bucket($2::interval, ts)
+            ORDER BY ts DESC
+            LIMIT $5
+            "#,
+        )
+        .bind(symbol)
+        .bind(period.bucket_interval())
+        .bind(start)
+        .bind(end)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parses_symbols() {
+        let market = Uuid::new_v4();
+        let outcome = Uuid::new_v4();
+        assert_eq!(
+            parse_symbol(&format!("{}:{}:YES", market, outcome)).unwrap(),
+            format!("{}:{}:yes", market, outcome)
+        );
+        assert!(parse_symbol(&format!("{}:{}", market, outcome)).is_err());
+        assert!(parse_symbol(&format!("{}:{}:maybe", market, outcome)).is_err());
+        assert!(parse_symbol("BTCUSDT").is_err());
+    }
+
+    #[test]
+    fn test_computes_long_short_ratio() {
+        assert_eq!(long_short_ratio(dec!(150), dec!(100)), Some(dec!(1.5)));
+        assert_eq!(long_short_ratio(dec!(1), dec!(3)), Some(dec!(0.3333)));
+        assert_eq!(long_short_ratio(dec!(10), Decimal::ZERO), None);
+    }
+}
@@ -0,0 +1,310 @@
+//! Sub-Accounts
+//!
+//! A wallet can open named sub-accounts, each with its own balances, orders
+//! and positions. Every sub-account trades under an address derived from the
+//! owner and its id (`keccak256(owner || id)`, last 20 bytes), so the ledger,
+//! the matching engine and the position service keep keying on plain
+//! addresses and a sub-account's state is isolated from its owner's without
+//! another key column. `sub_accounts` maps each derived address back to
+//! `(owner, sub_account_id)`.
+//!
+//! The owner acts as a sub-account by sending `X-Sub-Account: <id>` with its
+//! own credentials; orders are still signed by the owner's wallet. Inside a
+//! sub-account, reads are always allowed, trading needs `can_trade`, and
+//! withdrawals or sub-account management are refused: collateral leaves a
+//! sub-account only by an internal transfer back to the main account.
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sha3::{Digest, Keccak256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::ledger::{BalanceChange, LedgerReason, LedgerService};
+
+/// Most sub-accounts per owner
+pub const MAX_SUB_ACCOUNTS: i64 = 20;
+
+/// Longest sub-account label
+const MAX_LABEL_LEN: usize = 64;
+
+/// Sub-account errors
+#[derive(Debug, thiserror::Error)]
+pub enum SubAccountError {
+    #[error("Sub-account not found: {0}")]
+    NotFound(Uuid),
+
+    #[error("Invalid label: {0}")]
+    InvalidLabel(String),
+
+    #[error("Label already used: {0}")]
+    LabelTaken(String),
+
+    #[error("Sub-account limit reached ({0})")]
+    LimitReached(i64),
+
+    #[error("Invalid transfer: {0}")]
+    InvalidTransfer(&'static str),
+
+    #[error("Insufficient balance")]
+    InsufficientBalance,
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// A sub-account
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct SubAccount {
+    pub id: Uuid,
+    pub owner_address: String,
+    pub label: String,
+    /// Address the sub-account's balances, orders and positions are kept under
+    pub address: String,
+    pub can_trade: bool,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A transfer between an owner's accounts
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct SubAccountTransfer {
+    pub id: Uuid,
+    pub owner_address: String,
+    /// None is the main account
+    pub from_sub_account: Option<Uuid>,
+    pub to_sub_account: Option<Uuid>,
+    pub token: String,
+    pub amount: Decimal,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Address a sub-account trades under
+pub fn sub_account_address(owner: &str, id: Uuid) -> String {
+    let mut hasher = Keccak256::new();
+    hasher.update(owner.to_lowercase().as_bytes());
+    hasher.update(id.as_bytes());
+    format!("0x{}", hex::encode(&hasher.finalize()[12..]))
+}
+
+/// Trimmed label, if valid
+pub fn validate_label(label: &str) -> Result<String, SubAccountError> {
+    let label = label.trim();
+    if label.is_empty() || label.len() > MAX_LABEL_LEN {
+        return Err(SubAccountError::InvalidLabel(format!("must be 1 to {} characters", MAX_LABEL_LEN)));
+    }
+    if !label.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ' ' | '.')) {
+        return Err(SubAccountError::InvalidLabel(
+            "only letters, digits, spaces, '-', '_' and '.' are allowed".to_string(),
+        ));
+    }
+    Ok(label.to_string())
+}
+
+pub struct SubAccountService;
+
+impl SubAccountService {
+    /// Open a sub-account
+    pub async fn create(pool: &PgPool, owner: &str, label: &str, can_trade: bool) -> Result<SubAccount, SubAccountError> {
+        let owner = owner.to_lowercase();
+        let label = validate_label(label)?;
+
+        let mut tx = pool.begin().await?;
+        // Serialize creations per owner so the limit holds
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+            .bind(&owner)
+            .execute(&mut *tx)
+            .await?;
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sub_accounts WHERE owner_address = $1")
+            .bind(&owner)
+            .fetch_one(&mut *tx)
+            .await?;
+        if count >= MAX_SUB_ACCOUNTS {
+            return Err(SubAccountError::LimitReached(MAX_SUB_ACCOUNTS));
+        }
+
+        let id = Uuid::new_v4();
+        let sub_account: Option<SubAccount> = sqlx::query_as(
+            r#"
+            INSERT INTO sub_accounts (id, owner_address, label, address, can_trade)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (owner_address, label) DO NOTHING
+            RETURNING id, owner_address, label, address, can_trade, status, created_at, updated_at
+            "#,
+        )
+        .bind(id)
+        .bind(&owner)
+        .bind(&label)
+        .bind(sub_account_address(&owner, id))
+        .bind(can_trade)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let sub_account = sub_account.ok_or(SubAccountError::LabelTaken(label))?;
+        tx.commit().await?;
+
+        tracing::info!("Sub-account {} ({}) opened by {}", sub_account.id, sub_account.label, owner);
+        Ok(sub_account)
+    }
+
+    /// Sub-accounts of an owner
+    pub async fn list(pool: &PgPool, owner: &str) -> Result<Vec<SubAccount>, SubAccountError> {
+        let rows = sqlx::query_as(
+            r#"
+            SELECT id, owner_address, label, address, can_trade, status, created_at, updated_at
+            FROM sub_accounts
+            WHERE owner_address = $1
+            ORDER BY created_at
+            "#,
+        )
+        .bind(owner.to_lowercase())
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
+    }
+
+    /// A sub-account of an owner
+    pub async fn get(pool: &PgPool, owner: &str, id: Uuid) -> Result<SubAccount, SubAccountError> {
+        sqlx::query_as(
+            r#"
+            SELECT id, owner_address, label, address, can_trade, status, created_at, updated_at
+            FROM sub_accounts
+            WHERE id = $1 AND owner_address = $2
+            "#,
+        )
+        .bind(id)
+        .bind(owner.to_lowercase())
+        .fetch_optional(pool)
+        .await?
+        .ok_or(SubAccountError::NotFound(id))
+    }
+
+    /// Change a sub-account's permissions or freeze it
+    pub async fn update(
+        pool: &PgPool,
+        owner: &str,
+        id: Uuid,
+        can_trade: Option<bool>,
+        frozen: Option<bool>,
+    ) -> Result<SubAccount, SubAccountError> {
+        sqlx::query_as(
+            r#"
+            UPDATE sub_accounts SET
+                can_trade = COALESCE($3, can_trade),
+                status = CASE WHEN $4::BOOLEAN IS NULL THEN status WHEN $4 THEN 'frozen' ELSE 'active' END,
+                updated_at = NOW()
+            WHERE id = $1 AND owner_address = $2
+            RETURNING id, owner_address, label, address, can_trade, status, created_at, updated_at
+            "#,
+        )
+        .bind(id)
+        .bind(owner.to_lowercase())
+        .bind(can_trade)
+        .bind(frozen)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(SubAccountError::NotFound(id))
+    }
+
+    /// Move collateral between an owner's accounts (None is the main account)
+    pub async fn transfer(
+        pool: &PgPool,
+        owner: &str,
+        from: Option<Uuid>,
+        to: Option<Uuid>,
+        token: &str,
+        amount: Decimal,
+    ) -> Result<SubAccountTransfer, SubAccountError> {
+        if amount <= Decimal::ZERO {
+            return Err(SubAccountError::InvalidTransfer("amount must be positive"));
+        }
+        if from == to {
+            return Err(SubAccountError::InvalidTransfer("source and destination are the same account"));
+        }
+        let owner = owner.to_lowercase();
+        let from_address = match from {
+            Some(id) => Self::get(pool, &owner, id).await?.address,
+            None => owner.clone(),
+        };
+        let to_address = match to {
+            Some(id) => Self::get(pool, &owner, id).await?.address,
+            None => owner.clone(),
+        };
+
+        let id = Uuid::new_v4();
+        let mut tx = pool.begin().await?;
+        let debit = BalanceChange::debit(&from_address, token, amount, LedgerReason::SubAccountTransfer)
+            .reference(id)
+            .checked();
+        if LedgerService::apply(&mut tx, &debit).await?.is_none() {
+            return Err(SubAccountError::InsufficientBalance);
+        }
+        let credit = BalanceChange::credit(&to_address, token, amount, LedgerReason::SubAccountTransfer).reference(id);
+        LedgerService::apply(&mut tx, &credit).await?;
+
+        let transfer: SubAccountTransfer = sqlx::query_as(
+            r#"
+            INSERT INTO sub_account_transfers (id, owner_address, from_sub_account, to_sub_account, token, amount)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, owner_address, from_sub_account, to_sub_account, token, amount, created_at
+            "#,
+        )
+        .bind(id)
+        .bind(&owner)
+        .bind(from)
+        .bind(to)
+        .bind(token)
+        .bind(amount)
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        tracing::info!("Sub-account transfer {}: {} {} from {} to {}", id, amount, token, from_address, to_address);
+        Ok(transfer)
+    }
+
+    /// Transfers of an owner, newest first
+    pub async fn transfers(pool: &PgPool, owner: &str, limit: i64) -> Result<Vec<SubAccountTransfer>, SubAccountError> {
+        let rows = sqlx::query_as(
+            r#"
+            SELECT id, owner_address, from_sub_account, to_sub_account, token, amount, created_at
+            FROM sub_account_transfers
+            WHERE owner_address = $1
+            ORDER BY created_at DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(owner.to_lowercase())
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sub_account_address_is_stable_and_distinct() {
+        let owner = "0xAbC0000000000000000000000000000000000001";
+        let id = Uuid::from_u128(1);
+        let address = sub_account_address(owner, id);
+
+        assert_eq!(address.len(), 42);
+        assert!(address.starts_with("0x"));
+        assert_eq!(address, sub_account_address(&owner.to_lowercase(), id));
+        assert_ne!(address, sub_account_address(owner, Uuid::from_u128(2)));
+        assert_ne!(address, owner.to_lowercase());
+    }
+
+    #[test]
+    fn test_validate_label() {
+        assert_eq!(validate_label("  hedging-1 ").unwrap(), "hedging-1");
+        assert!(validate_label("   ").is_err());
+        assert!(validate_label("bot<script>").is_err());
+        assert!(validate_label(&"a".repeat(65)).is_err());
+    }
+}
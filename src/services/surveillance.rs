@@ -0,0 +1,646 @@
+//! Trade Surveillance
+//!
+//! Looks for manipulative order patterns per account and queues scored
+//! alerts (`surveillance_alerts`) for compliance review.
+//!
+//! The data source is the engine journal. New entries are applied to a
+//! shadow engine, which tells for every placement and cancel where the
+//! touch was and which orders traded, without touching the live books. The
+//! resulting account events are kept for the detection window and checked
+//! for:
+//!
+//! - **spoofing**: many cancels near the touch for every fill
+//! - **layering**: several price levels on one side cancelled together
+//!   around a fill on the other side
+//! - **momentum ignition**: a burst of placements and cancels followed by
+//!   the account's own aggressive trade
+//!
+//! An open alert for the same account, symbol and pattern suppresses new
+//! ones until the window has passed.
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::services::matching::{EngineCommand, EngineJournal, JournalEntry, JournalError, MatchType, MatchingEngine, Side, TradeExecution};
+
+/// Surveillance errors
+#[derive(Debug, thiserror::Error)]
+pub enum SurveillanceError {
+    #[error("Alert not found: {0}")]
+    NotFound(Uuid),
+
+    #[error("Invalid review status: {0}")]
+    InvalidStatus(String),
+
+    #[error("Journal error: {0}")]
+    Journal(#[from] JournalError),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// Detection thresholds
+#[derive(Debug, Clone)]
+pub struct SurveillanceConfig {
+    /// Events older than this are dropped (seconds)
+    pub window_secs: i64,
+    /// Orders within this distance of the best price are near the touch
+    pub touch_distance: Decimal,
+    /// Fewest near-touch cancels for a spoofing alert
+    pub spoof_min_cancels: usize,
+    /// Near-touch cancels per fill for a spoofing alert
+    pub spoof_cancel_ratio: Decimal,
+    /// Fewest distinct levels cancelled together for a layering alert
+    pub layer_min_levels: usize,
+    /// Span of a layering cancel or ignition burst (seconds)
+    pub burst_secs: i64,
+    /// Fewest placements + cancels in an ignition burst
+    pub burst_min_events: usize,
+    /// Longest gap between the burst and the aggressive trade (seconds)
+    pub ignition_follow_secs: i64,
+}
+
+impl Default for SurveillanceConfig {
+    fn default() -> Self {
+        Self {
+            window_secs: 600,
+            touch_distance: Decimal::new(1, 2),
+            spoof_min_cancels: 20,
+            spoof_cancel_ratio: Decimal::from(10),
+            layer_min_levels: 3,
+            burst_secs: 5,
+            burst_min_events: 10,
+            ignition_follow_secs: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Place,
+    Cancel,
+    /// The account's order traded (as taker when `aggressive`)
+    Fill,
+}
+
+/// One order action of an account, as seen by the shadow engine
+#[derive(Debug, Clone)]
+pub struct AccountEvent {
+    /// Unix millis
+    pub timestamp: i64,
+    pub user_address: String,
+    pub symbol: String,
+    pub kind: EventKind,
+    pub side: Side,
+    pub price: Option<Decimal>,
+    pub amount: Decimal,
+    /// Price within the touch distance of the best price on its side
+    pub near_touch: bool,
+    /// Fill taken from the book
+    pub aggressive: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Pattern {
+    Spoofing,
+    Layering,
+    MomentumIgnition,
+}
+
+impl Pattern {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Pattern::Spoofing => "spoofing",
+            Pattern::Layering => "layering",
+            Pattern::MomentumIgnition => "momentum_ignition",
+        }
+    }
+}
+
+/// A detected pattern, before it is queued
+#[derive(Debug, Clone, Serialize)]
+pub struct AlertCandidate {
+    pub user_address: String,
+    pub symbol: String,
+    pub pattern: Pattern,
+    /// 0-100
+    pub score: i32,
+    pub details: serde_json::Value,
+}
+
+/// Score from how far a measure exceeds its threshold: 50 at the
+/// threshold, 100 at twice it
+fn score(value: Decimal, threshold: Decimal) -> i32 {
+    if threshold <= Decimal::ZERO {
+        return 100;
+    }
+    (value / threshold * Decimal::from(50)).round().to_i32().unwrap_or(100).clamp(0, 100)
+}
+
+/// Whether `price` is within `distance` of `best` on `side`, or improves it
+fn near_touch(side: Side, price: Decimal, best: Option<Decimal>, distance: Decimal) -> bool {
+    match (side, best) {
+        (_, None) => true,
+        (Side::Buy, Some(best)) => price >= best - distance,
+        (Side::Sell, Some(best)) => price <= best + distance,
+    }
+}
+
+/// Check one account's events on one symbol (in time order)
+fn detect_symbol(events: &[&AccountEvent], config: &SurveillanceConfig) -> Vec<(Pattern, i32, serde_json::Value)> {
+    let mut found = Vec::new();
+    let burst_ms = config.burst_secs * 1000;
+
+    // Spoofing: near-touch cancels per fill
+    let touch_cancels = events.iter().filter(|e| e.kind == EventKind::Cancel && e.near_touch).count();
+    let fills = events.iter().filter(|e| e.kind == EventKind::Fill).count();
+    let ratio = Decimal::from(touch_cancels) / Decimal::from(fills.max(1));
+    if touch_cancels >= config.spoof_min_cancels && ratio >= config.spoof_cancel_ratio {
+        found.push((
+            Pattern::Spoofing,
+            score(ratio, config.spoof_cancel_ratio),
+            serde_json::json!({ "near_touch_cancels": touch_cancels, "fills": fills, "cancel_fill_ratio": ratio.round_dp(2) }),
+        ));
+    }
+
+    // Layering: levels on one side cancelled within a burst, with a fill on
+    // the other side shortly before or during the cancels
+    let mut best_layers: Option<(usize, Side)> = None;
+    for side in [Side::Buy, Side::Sell] {
+        let cancels: Vec<&AccountEvent> = events
+            .iter()
+            .copied()
+            .filter(|e| e.kind == EventKind::Cancel && e.side == side)
+            .collect();
+        for (i, first) in cancels.iter().enumerate() {
+            let burst: Vec<&AccountEvent> = cancels[i..]
+                .iter()
+                .copied()
+                .take_while(|e| e.timestamp - first.timestamp <= burst_ms)
+                .collect();
+            let levels: BTreeSet<Decimal> = burst.iter().filter_map(|e| e.price).collect();
+            if levels.len() < config.layer_min_levels {
+                continue;
+            }
+            let last = burst.last().map_or(first.timestamp, |e| e.timestamp);
+            let opposite_fill = events.iter().any(|e| {
+                e.kind == EventKind::Fill
+                    && e.side != side
+                    && e.timestamp >= first.timestamp - burst_ms
+                    && e.timestamp <= last
+            });
+            if opposite_fill && best_layers.is_none_or(|(n, _)| levels.len() > n) {
+                best_layers = Some((levels.len(), side));
+            }
+        }
+    }
+    if let Some((levels, side)) = best_layers {
+        found.push((
+            Pattern::Layering,
+            score(Decimal::from(levels), Decimal::from(config.layer_min_levels)),
+            serde_json::json!({ "levels": levels, "side": side }),
+        ));
+    }
+
+    // Momentum ignition: a burst of placements/cancels, then an own aggressive trade
+    let activity: Vec<&AccountEvent> = events
+        .iter()
+        .copied()
+        .filter(|e| matches!(e.kind, EventKind::Place | EventKind::Cancel))
+        .collect();
+    let mut best_burst: Option<usize> = None;
+    for (i, first) in activity.iter().enumerate() {
+        let burst: Vec<&AccountEvent> = activity[i..]
+            .iter()
+            .copied()
+            .take_while(|e| e.timestamp - first.timestamp <= burst_ms)
+            .collect();
+        if burst.len() < config.burst_min_events {
+            continue;
+        }
+        let end = burst.last().map_or(first.timestamp, |e| e.timestamp);
+        let followed = events.iter().any(|e| {
+            e.kind == EventKind::Fill
+                && e.aggressive
+                && e.timestamp >= end
+                && e.timestamp - end <= config.ignition_follow_secs * 1000
+        });
+        if followed && best_burst.is_none_or(|n| burst.len() > n) {
+            best_burst = Some(burst.len());
+        }
+    }
+    if let Some(events) = best_burst {
+        found.push((
+            Pattern::MomentumIgnition,
+            score(Decimal::from(events), Decimal::from(config.burst_min_events)),
+            serde_json::json!({ "burst_events": events, "burst_secs": config.burst_secs }),
+        ));
+    }
+
+    found
+}
+
+/// Detect patterns per account and symbol
+pub fn detect(events: &[AccountEvent], config: &SurveillanceConfig) -> Vec<AlertCandidate> {
+    let mut grouped: HashMap<(&str, &str), Vec<&AccountEvent>> = HashMap::new();
+    for event in events {
+        grouped
+            .entry((event.user_address.as_str(), event.symbol.as_str()))
+            .or_default()
+            .push(event);
+    }
+
+    let mut alerts = Vec::new();
+    for ((user_address, symbol), mut events) in grouped {
+        events.sort_by_key(|e| e.timestamp);
+        for (pattern, score, details) in detect_symbol(&events, config) {
+            alerts.push(AlertCandidate {
+                user_address: user_address.to_string(),
+                symbol: symbol.to_string(),
+                pattern,
+                score,
+                details,
+            });
+        }
+    }
+    alerts.sort_by_key(|a| std::cmp::Reverse(a.score));
+    alerts
+}
+
+/// Account events of one journal entry, applied to the shadow engine
+fn shadow_events(engine: &MatchingEngine, entry: &JournalEntry, distance: Decimal) -> Vec<AccountEvent> {
+    let event = |user: &str, symbol: &str, kind, side, price, amount, near_touch, aggressive| AccountEvent {
+        timestamp: entry.timestamp,
+        user_address: user.to_lowercase(),
+        symbol: symbol.to_string(),
+        kind,
+        side,
+        price,
+        amount,
+        near_touch,
+        aggressive,
+    };
+    let best = |symbol: &str, side: Side| {
+        let (bid, ask) = engine.get_best_prices(symbol).unwrap_or((None, None));
+        match side {
+            Side::Buy => bid,
+            Side::Sell => ask,
+        }
+    };
+
+    let fills = |events: &mut Vec<AccountEvent>, user: &str, symbol: &String, side: Side, trades: &[TradeExecution]| {
+        for trade in trades {
+            events.push(event(user, symbol, EventKind::Fill, side, Some(trade.price), trade.amount, false, true));
+            // Makers of mint/merge fills rest on the complement book on the same side
+            let (maker_symbol, maker_side) = match trade.match_type {
+                MatchType::Normal => (symbol.clone(), side.opposite()),
+                _ => (
+                    MatchingEngine::get_complement_market_key(symbol).unwrap_or_else(|| symbol.clone()),
+                    side,
+                ),
+            };
+            events.push(event(&trade.maker_address, &maker_symbol, EventKind::Fill, maker_side, Some(trade.price), trade.amount, false, false));
+        }
+    };
+
+    let mut events = Vec::new();
+    match &entry.command {
+        EngineCommand::Submit {
+            symbol,
+            user_address,
+            side,
+            amount,
+            price,
+            ..
+        } => {
+            let touch = price.is_some_and(|p| near_touch(*side, p, best(symbol, *side), distance));
+            events.push(event(user_address, symbol, EventKind::Place, *side, *price, *amount, touch, false));
+            if let Ok(Some(result)) = engine.apply_entry(entry) {
+                fills(&mut events, user_address, symbol, *side, &result.trades);
+            }
+        }
+        EngineCommand::Cancel {
+            symbol,
+            order_id,
+            user_address,
+        } => {
+            let order = engine.get_orderbook_ref(symbol).and_then(|book| book.get_order(order_id));
+            if let Some(order) = order {
+                let touch = near_touch(order.side, order.price, best(symbol, order.side), distance);
+                events.push(event(user_address, symbol, EventKind::Cancel, order.side, Some(order.price), order.remaining_amount, touch, false));
+            }
+            let _ = engine.apply_entry(entry);
+        }
+        EngineCommand::Amend {
+            symbol,
+            order_id,
+            user_address,
+            price,
+            ..
+        } => {
+            let order = engine.get_orderbook_ref(symbol).and_then(|book| book.get_order(order_id));
+            let touch = order.as_ref().is_some_and(|o| near_touch(o.side, o.price, best(symbol, o.side), distance));
+            let new_touch = order.as_ref().is_some_and(|o| near_touch(o.side, *price, best(symbol, o.side), distance));
+            let result = engine.apply_entry(entry);
+            let (Some(order), Ok(Some(result))) = (order, result) else {
+                return events;
+            };
+            // An amendment pulls the old quantity and, unless it only shrank in place, places the new one
+            let kept = *price == order.price && result.remaining_amount <= order.remaining_amount && result.trades.is_empty();
+            let pulled = if kept { order.remaining_amount - result.remaining_amount } else { order.remaining_amount };
+            if pulled > Decimal::ZERO {
+                events.push(event(user_address, symbol, EventKind::Cancel, order.side, Some(order.price), pulled, touch, false));
+            }
+            if !kept {
+                let placed = result.remaining_amount + result.trades.iter().map(|t| t.amount).sum::<Decimal>();
+                events.push(event(user_address, symbol, EventKind::Place, order.side, Some(*price), placed, new_touch, false));
+                fills(&mut events, user_address, symbol, order.side, &result.trades);
+            }
+        }
+    }
+    events
+}
+
+/// Review state of an alert
+pub const REVIEW_STATUSES: [&str; 3] = ["open", "dismissed", "escalated"];
+
+/// A queued surveillance alert
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct SurveillanceAlert {
+    pub id: Uuid,
+    pub user_address: String,
+    pub symbol: String,
+    pub pattern: String,
+    pub score: i32,
+    pub details: serde_json::Value,
+    pub status: String,
+    pub reviewed_by: Option<String>,
+    pub review_note: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+}
+
+/// Alert list filter
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AlertFilter {
+    pub status: Option<String>,
+    pub user_address: Option<String>,
+    pub pattern: Option<String>,
+}
+
+/// Journal-fed pattern detector and alert queue
+pub struct SurveillanceService {
+    journal_path: PathBuf,
+    config: SurveillanceConfig,
+    shadow: MatchingEngine,
+    /// Events inside the detection window, oldest first
+    events: Mutex<VecDeque<AccountEvent>>,
+}
+
+impl SurveillanceService {
+    pub fn new(journal_path: &Path, config: SurveillanceConfig) -> Self {
+        Self {
+            journal_path: journal_path.to_path_buf(),
+            config,
+            shadow: MatchingEngine::new(),
+            events: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Apply journal entries written since the last run to the shadow engine
+    fn ingest(&self) -> Result<usize, JournalError> {
+        if !self.journal_path.exists() {
+            return Ok(0);
+        }
+        let after = self.shadow.last_sequence();
+        let entries = EngineJournal::read_entries(&self.journal_path)?;
+        let mut events = self.events.lock();
+        let mut applied = 0;
+        for entry in entries.iter().filter(|entry| entry.sequence > after) {
+            events.extend(shadow_events(&self.shadow, entry, self.config.touch_distance));
+            applied += 1;
+        }
+
+        let cutoff = Utc::now().timestamp_millis() - self.config.window_secs * 1000;
+        while events.front().is_some_and(|e| e.timestamp < cutoff) {
+            events.pop_front();
+        }
+        Ok(applied)
+    }
+
+    /// Ingest new journal entries and queue alerts for detected patterns;
+    /// returns the number of alerts queued
+    pub async fn run(&self, pool: &PgPool) -> Result<usize, SurveillanceError> {
+        self.ingest()?;
+        let candidates = {
+            let mut events = self.events.lock();
+            detect(events.make_contiguous(), &self.config)
+        };
+
+        let mut queued = 0;
+        for alert in candidates {
+            let inserted = sqlx::query(
+                r#"
+                INSERT INTO surveillance_alerts (user_address, symbol, pattern, score, details)
+                SELECT $1, $2, $3, $4, $5
+                WHERE NOT EXISTS (
+                    SELECT 1 FROM surveillance_alerts
+                    WHERE user_address = $1 AND symbol = $2 AND pattern = $3
+                      AND status = 'open' AND created_at > NOW() - make_interval(secs => $6)
+                )
+                "#,
+            )
+            .bind(&alert.user_address)
+            .bind(&alert.symbol)
+            .bind(alert.pattern.as_str())
+            .bind(alert.score)
+            .bind(&alert.details)
+            .bind(self.config.window_secs as f64)
+            .execute(pool)
+            .await?;
+            if inserted.rows_affected() > 0 {
+                crate::metrics::record_surveillance_alert(alert.pattern.as_str());
+                tracing::warn!(
+                    "Surveillance alert: {} on {} by {} (score {})",
+                    alert.pattern.as_str(),
+                    alert.symbol,
+                    alert.user_address,
+                    alert.score
+                );
+                queued += 1;
+            }
+        }
+        Ok(queued)
+    }
+
+    /// Alerts, highest score first within newest
+    pub async fn list_alerts(
+        pool: &PgPool,
+        filter: &AlertFilter,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<SurveillanceAlert>, SurveillanceError> {
+        let alerts = sqlx::query_as(
+            r#"
+            SELECT id, user_address, symbol, pattern, score, details, status,
+                   reviewed_by, review_note, created_at, reviewed_at
+            FROM surveillance_alerts
+            WHERE ($1::text IS NULL OR status = $1)
+              AND ($2::text IS NULL OR user_address = $2)
+              AND ($3::text IS NULL OR pattern = $3)
+            ORDER BY created_at DESC, score DESC
+            LIMIT $4 OFFSET $5
+            "#,
+        )
+        .bind(filter.status.as_deref())
+        .bind(filter.user_address.as_deref().map(str::to_lowercase))
+        .bind(filter.pattern.as_deref())
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await?;
+        Ok(alerts)
+    }
+
+    /// Record a review decision on an alert
+    pub async fn review_alert(
+        pool: &PgPool,
+        id: Uuid,
+        status: &str,
+        reviewer: &str,
+        note: Option<&str>,
+    ) -> Result<SurveillanceAlert, SurveillanceError> {
+        if !REVIEW_STATUSES.contains(&status) {
+            return Err(SurveillanceError::InvalidStatus(status.to_string()));
+        }
+        sqlx::query_as(
+            r#"
+            UPDATE surveillance_alerts
+            SET status = $2, reviewed_by = $3, review_note = $4, reviewed_at = NOW()
+            WHERE id = $1
+            RETURNING id, user_address, symbol, pattern, score, details, status,
+                      reviewed_by, review_note, created_at, reviewed_at
+            "#,
+        )
+        .bind(id)
+        .bind(status)
+        .bind(reviewer.to_lowercase())
+        .bind(note)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(SurveillanceError::NotFound(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn event(timestamp: i64, kind: EventKind, side: Side, price: Decimal, near_touch: bool, aggressive: bool) -> AccountEvent {
+        AccountEvent {
+            timestamp,
+            user_address: "0xabc".to_string(),
+            symbol: "m:o:yes".to_string(),
+            kind,
+            side,
+            price: Some(price),
+            amount: dec!(10),
+            near_touch,
+            aggressive,
+        }
+    }
+
+    fn patterns(events: &[AccountEvent]) -> Vec<Pattern> {
+        detect(events, &SurveillanceConfig::default()).into_iter().map(|a| a.pattern).collect()
+    }
+
+    #[test]
+    fn test_flags_spoofing_only_above_cancel_ratio() {
+        let mut events: Vec<AccountEvent> = (0..30)
+            .map(|i| event(i * 60_000, EventKind::Cancel, Side::Buy, dec!(0.50), true, false))
+            .collect();
+        events.push(event(1, EventKind::Fill, Side::Sell, dec!(0.55), false, false));
+        assert_eq!(patterns(&events), vec![Pattern::Spoofing]);
+        let alert = &detect(&events, &SurveillanceConfig::default())[0];
+        // 30 cancels per fill against a ratio of 10
+        assert_eq!(alert.score, 100);
+
+        events.extend((0..3).map(|i| event(i, EventKind::Fill, Side::Sell, dec!(0.55), false, false)));
+        assert!(patterns(&events).is_empty());
+    }
+
+    #[test]
+    fn test_flags_layering_around_opposite_fill() {
+        let mut events = vec![event(1_000, EventKind::Fill, Side::Sell, dec!(0.60), false, false)];
+        events.extend(
+            [dec!(0.50), dec!(0.49), dec!(0.48)]
+                .into_iter()
+                .enumerate()
+                .map(|(i, price)| event(2_000 + i as i64 * 100, EventKind::Cancel, Side::Buy, price, false, false)),
+        );
+        assert_eq!(patterns(&events), vec![Pattern::Layering]);
+
+        // Without the opposite fill the cancels are unremarkable
+        assert!(patterns(&events[1..]).is_empty());
+    }
+
+    #[test]
+    fn test_flags_burst_followed_by_aggressive_trade() {
+        let mut events: Vec<AccountEvent> = (0..12)
+            .map(|i| {
+                let kind = if i % 2 == 0 { EventKind::Place } else { EventKind::Cancel };
+                event(i * 100, kind, Side::Buy, dec!(0.30), false, false)
+            })
+            .collect();
+        events.push(event(3_000, EventKind::Fill, Side::Sell, dec!(0.35), false, true));
+        assert_eq!(patterns(&events), vec![Pattern::MomentumIgnition]);
+
+        // A passive fill does not follow through
+        events.last_mut().unwrap().aggressive = false;
+        assert!(patterns(&events).is_empty());
+    }
+
+    #[test]
+    fn test_shadow_engine_marks_touch_and_fills() {
+        let engine = MatchingEngine::new();
+        let symbol = format!("{}:{}:yes", Uuid::new_v4(), Uuid::new_v4());
+        let submit = |sequence, user: &str, side, price| JournalEntry {
+            sequence,
+            timestamp: sequence as i64,
+            command: EngineCommand::Submit {
+                order_id: Uuid::new_v4(),
+                symbol: symbol.clone(),
+                user_address: user.to_string(),
+                side,
+                order_type: crate::services::matching::OrderType::Limit,
+                amount: dec!(10),
+                price: Some(price),
+                leverage: 1,
+            },
+        };
+
+        let bid = shadow_events(&engine, &submit(1, "0x1", Side::Buy, dec!(0.40)), dec!(0.01));
+        assert!(bid[0].near_touch);
+        let deep = shadow_events(&engine, &submit(2, "0x1", Side::Buy, dec!(0.30)), dec!(0.01));
+        assert!(!deep[0].near_touch);
+
+        let taker = shadow_events(&engine, &submit(3, "0x2", Side::Sell, dec!(0.40)), dec!(0.01));
+        let kinds: Vec<(EventKind, &str, bool)> =
+            taker.iter().map(|e| (e.kind, e.user_address.as_str(), e.aggressive)).collect();
+        assert_eq!(
+            kinds,
+            vec![(EventKind::Place, "0x2", false), (EventKind::Fill, "0x2", true), (EventKind::Fill, "0x1", false)]
+        );
+        assert_eq!(taker[2].side, Side::Buy);
+    }
+}
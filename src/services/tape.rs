@@ -0,0 +1,540 @@
+//! Trade Tape Export
+//!
+//! Produces the complete trade tape of one symbol (`{market_id}:{outcome_id}:{share_type}`)
+//! for one UTC day, for regulators and data partners. Exports run as background
+//! jobs tracked in `tape_exports`: a request queues a job (or returns the existing
+//! one for that symbol and day), the worker claims pending jobs and writes two
+//! objects to the export store:
+//!
+//! - `trades.ndjson.gz`: one trade per line, oldest first, gzip compressed
+//! - `manifest.json`: symbol, day, row count, byte size and SHA-256 checksums of
+//!   the compressed and uncompressed tape
+//!
+//! The manifest is signed with HMAC-SHA256 over its exact bytes when a signing
+//! secret is configured; the signature is kept on the job and returned with the
+//! manifest so recipients can verify the handoff end to end.
+//!
+//! Trades already pruned from Postgres are read back from the history archive,
+//! so a tape is complete regardless of retention. Only finished days can be
+//! exported.
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use flate2::{write::GzEncoder, Compression};
+use ring::{digest, hmac};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashSet;
+use std::io::Write;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::services::archive::{ArchiveError, ArchiveService, ArchiveStore, ArchivedTrade};
+
+/// Maximum jobs run per worker cycle
+const MAX_JOBS_PER_CYCLE: usize = 10;
+
+/// Running jobs older than this are assumed abandoned and claimed again
+const STALE_JOB_MINUTES: i64 = 60;
+
+const JOB_COLUMNS: &str = r#"
+    id, symbol, trade_date, status, requested_by, row_count, data_key, data_sha256,
+    manifest_key, manifest_signature, error, created_at, started_at, completed_at
+"#;
+
+/// Trade tape errors
+#[derive(Debug, thiserror::Error)]
+pub enum TapeError {
+    #[error("Invalid symbol: {0}")]
+    InvalidSymbol(String),
+
+    #[error("Trade tape for {0} is not available yet; only finished UTC days can be exported")]
+    DayNotFinished(NaiveDate),
+
+    #[error("Export not found")]
+    NotFound,
+
+    #[error("Export is not completed")]
+    NotReady,
+
+    #[error("Tape IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Tape encoding error: {0}")]
+    Encoding(#[from] serde_json::Error),
+
+    #[error("Archive error: {0}")]
+    Archive(#[from] ArchiveError),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// Export job state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+impl ExportStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportStatus::Pending => "pending",
+            ExportStatus::Running => "running",
+            ExportStatus::Completed => "completed",
+            ExportStatus::Failed => "failed",
+        }
+    }
+}
+
+/// Trade tape export job
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct TapeExport {
+    pub id: Uuid,
+    pub symbol: String,
+    pub trade_date: NaiveDate,
+    pub status: String,
+    pub requested_by: String,
+    pub row_count: Option<i64>,
+    #[serde(skip)]
+    pub data_key: Option<String>,
+    pub data_sha256: Option<String>,
+    #[serde(skip)]
+    pub manifest_key: Option<String>,
+    pub manifest_signature: Option<String>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// Symbol a tape is cut for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TapeSymbol {
+    pub market_id: Uuid,
+    pub outcome_id: Uuid,
+    /// "yes" or "no"
+    pub share_type: &'static str,
+}
+
+impl TapeSymbol {
+    pub fn parse(symbol: &str) -> Result<Self, TapeError> {
+        let invalid = || TapeError::InvalidSymbol(symbol.to_string());
+        let mut parts = symbol.split(':');
+        let (Some(market), Some(outcome), Some(share), None) = (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        let share_type = match share.to_lowercase().as_str() {
+            "yes" => "yes",
+            "no" => "no",
+            _ => return Err(invalid()),
+        };
+        Ok(Self {
+            market_id: market.parse().map_err(|_| invalid())?,
+            outcome_id: outcome.parse().map_err(|_| invalid())?,
+            share_type,
+        })
+    }
+
+    /// Canonical `{market_id}:{outcome_id}:{share_type}` form
+    pub fn key(&self) -> String {
+        format!("{}:{}:{}", self.market_id, self.outcome_id, self.share_type)
+    }
+
+    fn matches(&self, trade: &ArchivedTrade) -> bool {
+        trade.market_id == self.market_id && trade.outcome_id == self.outcome_id && trade.share_type == self.share_type
+    }
+}
+
+/// One file of an export
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TapeFile {
+    pub key: String,
+    pub format: String,
+    pub row_count: u64,
+    /// Size of the stored (compressed) object
+    pub bytes: u64,
+    /// SHA-256 of the stored (compressed) object
+    pub sha256: String,
+    /// SHA-256 of the decompressed NDJSON
+    pub uncompressed_sha256: String,
+}
+
+/// Export manifest written next to the tape
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TapeManifest {
+    pub export_id: Uuid,
+    pub symbol: String,
+    pub trade_date: NaiveDate,
+    /// Inclusive start of the tape
+    pub from: DateTime<Utc>,
+    /// Exclusive end of the tape
+    pub to: DateTime<Utc>,
+    pub row_count: u64,
+    pub files: Vec<TapeFile>,
+    /// Algorithm of the detached manifest signature ("hmac-sha256"), if signed
+    pub signature_algorithm: Option<String>,
+    pub generated_at: DateTime<Utc>,
+}
+
+/// Lowercase hex SHA-256 of `data`
+pub fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(digest::digest(&digest::SHA256, data))
+}
+
+/// Encode trades as NDJSON, returning the plain and gzip-compressed bytes
+pub fn encode_tape(trades: &[ArchivedTrade]) -> Result<(Vec<u8>, Vec<u8>), TapeError> {
+    let mut plain = Vec::new();
+    for trade in trades {
+        serde_json::to_writer(&mut plain, trade)?;
+        plain.push(b'\n');
+    }
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&plain)?;
+    let compressed = encoder.finish()?;
+    Ok((plain, compressed))
+}
+
+/// Hex HMAC-SHA256 of a manifest's bytes
+pub fn sign_manifest(key: &hmac::Key, manifest: &[u8]) -> String {
+    hex::encode(hmac::sign(key, manifest))
+}
+
+/// Trade tape export service
+pub struct TapeService {
+    store: Arc<dyn ArchiveStore>,
+    /// History archive holding pruned trades (None when archiving is disabled)
+    archive: Option<Arc<dyn ArchiveStore>>,
+    signing_key: Option<hmac::Key>,
+}
+
+impl TapeService {
+    /// Create the service; an empty secret leaves manifests unsigned
+    pub fn new(store: Arc<dyn ArchiveStore>, archive: Option<Arc<dyn ArchiveStore>>, signing_secret: &str) -> Self {
+        let signing_key =
+            (!signing_secret.is_empty()).then(|| hmac::Key::new(hmac::HMAC_SHA256, signing_secret.as_bytes()));
+        Self {
+            store,
+            archive,
+            signing_key,
+        }
+    }
+
+    /// Queue an export, returning the existing job for the same symbol and day
+    /// unless it failed (failed jobs are queued again)
+    pub async fn request(
+        &self,
+        pool: &PgPool,
+        symbol: &str,
+        trade_date: NaiveDate,
+        requested_by: &str,
+    ) -> Result<TapeExport, TapeError> {
+        let symbol = TapeSymbol::parse(symbol)?.key();
+        if trade_date >= Utc::now().date_naive() {
+            return Err(TapeError::DayNotFinished(trade_date));
+        }
+
+        let sql = format!(
+            r#"
+            INSERT INTO tape_exports (symbol, trade_date, requested_by)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (symbol, trade_date) DO UPDATE SET
+                status = 'pending',
+                requested_by = EXCLUDED.requested_by,
+                error = NULL,
+                created_at = NOW(),
+                started_at = NULL,
+                completed_at = NULL
+            WHERE tape_exports.status = 'failed'
+            RETURNING {JOB_COLUMNS}
+            "#
+        );
+        let queued: Option<TapeExport> = sqlx::query_as(&sql)
+            .bind(&symbol)
+            .bind(trade_date)
+            .bind(requested_by.to_lowercase())
+            .fetch_optional(pool)
+            .await?;
+        if let Some(job) = queued {
+            return Ok(job);
+        }
+
+        let sql = format!("SELECT {JOB_COLUMNS} FROM tape_exports WHERE symbol = $1 AND trade_date = $2");
+        let existing = sqlx::query_as(&sql)
+            .bind(&symbol)
+            .bind(trade_date)
+            .fetch_one(pool)
+            .await?;
+        Ok(existing)
+    }
+
+    /// Look up an export job
+    pub async fn get(&self, pool: &PgPool, id: Uuid) -> Result<TapeExport, TapeError> {
+        let sql = format!("SELECT {JOB_COLUMNS} FROM tape_exports WHERE id = $1");
+        sqlx::query_as(&sql)
+            .bind(id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or(TapeError::NotFound)
+    }
+
+    /// Manifest bytes and signature of a completed export
+    pub async fn manifest(&self, pool: &PgPool, id: Uuid) -> Result<(Vec<u8>, Option<String>), TapeError> {
+        let job = self.completed(pool, id).await?;
+        let key = job.manifest_key.ok_or(TapeError::NotReady)?;
+        let data = self.store.get(&key).await?.ok_or(TapeError::NotFound)?;
+        Ok((data, job.manifest_signature))
+    }
+
+    /// Compressed tape of a completed export
+    pub async fn data(&self, pool: &PgPool, id: Uuid) -> Result<(TapeExport, Vec<u8>), TapeError> {
+        let job = self.completed(pool, id).await?;
+        let key = job.data_key.clone().ok_or(TapeError::NotReady)?;
+        let data = self.store.get(&key).await?.ok_or(TapeError::NotFound)?;
+        Ok((job, data))
+    }
+
+    async fn completed(&self, pool: &PgPool, id: Uuid) -> Result<TapeExport, TapeError> {
+        let job = self.get(pool, id).await?;
+        if job.status != ExportStatus::Completed.as_str() {
+            return Err(TapeError::NotReady);
+        }
+        Ok(job)
+    }
+
+    /// Run queued exports; returns the number of jobs completed
+    pub async fn run_pending(&self, pool: &PgPool) -> Result<usize, TapeError> {
+        let mut completed = 0;
+        for _ in 0..MAX_JOBS_PER_CYCLE {
+            let Some(job) = Self::claim(pool).await? else {
+                break;
+            };
+            match self.generate(pool, &job).await {
+                Ok(rows) => {
+                    completed += 1;
+                    crate::metrics::record_tape_export(ExportStatus::Completed.as_str());
+                    tracing::info!("Trade tape {} ({} {}) exported: {} trades", job.id, job.symbol, job.trade_date, rows);
+                }
+                Err(e) => {
+                    crate::metrics::record_tape_export(ExportStatus::Failed.as_str());
+                    tracing::error!("Trade tape {} ({} {}) failed: {}", job.id, job.symbol, job.trade_date, e);
+                    sqlx::query("UPDATE tape_exports SET status = 'failed', error = $1, completed_at = NOW() WHERE id = $2")
+                        .bind(e.to_string())
+                        .bind(job.id)
+                        .execute(pool)
+                        .await?;
+                }
+            }
+        }
+        Ok(completed)
+    }
+
+    /// Claim the oldest pending (or abandoned running) job
+    async fn claim(pool: &PgPool) -> Result<Option<TapeExport>, TapeError> {
+        let sql = format!(
+            r#"
+            UPDATE tape_exports SET status = 'running', started_at = NOW()
+            WHERE id = (
+                SELECT id FROM tape_exports
+                WHERE status = 'pending'
+                   OR (status = 'running' AND started_at < NOW() - make_interval(mins => $1))
+                ORDER BY created_at
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING {JOB_COLUMNS}
+            "#
+        );
+        let job = sqlx::query_as(&sql)
+            .bind(STALE_JOB_MINUTES as i32)
+            .fetch_optional(pool)
+            .await?;
+        Ok(job)
+    }
+
+    /// Write the tape and manifest of a job and mark it completed
+    async fn generate(&self, pool: &PgPool, job: &TapeExport) -> Result<u64, TapeError> {
+        let symbol = TapeSymbol::parse(&job.symbol)?;
+        let from = job.trade_date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+        let to = from + Duration::days(1);
+
+        let trades = self.collect_trades(pool, &symbol, from, to).await?;
+        let (plain, compressed) = encode_tape(&trades)?;
+
+        let prefix = format!(
+            "tape/{}/{}/{}/{}",
+            symbol.market_id, symbol.outcome_id, symbol.share_type, job.trade_date
+        );
+        let data_key = format!("{}/trades.ndjson.gz", prefix);
+        let manifest_key = format!("{}/manifest.json", prefix);
+        let data_sha256 = sha256_hex(&compressed);
+
+        let manifest = TapeManifest {
+            export_id: job.id,
+            symbol: symbol.key(),
+            trade_date: job.trade_date,
+            from,
+            to,
+            row_count: trades.len() as u64,
+            files: vec![TapeFile {
+                key: data_key.clone(),
+                format: "ndjson+gzip".to_string(),
+                row_count: trades.len() as u64,
+                bytes: compressed.len() as u64,
+                sha256: data_sha256.clone(),
+                uncompressed_sha256: sha256_hex(&plain),
+            }],
+            signature_algorithm: self.signing_key.as_ref().map(|_| "hmac-sha256".to_string()),
+            generated_at: Utc::now(),
+        };
+        let manifest_bytes = serde_json::to_vec_pretty(&manifest)?;
+        let signature = self.signing_key.as_ref().map(|key| sign_manifest(key, &manifest_bytes));
+
+        self.store.put(&data_key, compressed).await?;
+        self.store.put(&manifest_key, manifest_bytes).await?;
+
+        sqlx::query(
+            r#"
+            UPDATE tape_exports SET
+                status = 'completed', row_count = $1, data_key = $2, data_sha256 = $3,
+                manifest_key = $4, manifest_signature = $5, error = NULL, completed_at = NOW()
+            WHERE id = $6
+            "#,
+        )
+        .bind(trades.len() as i64)
+        .bind(&data_key)
+        .bind(&data_sha256)
+        .bind(&manifest_key)
+        .bind(&signature)
+        .bind(job.id)
+        .execute(pool)
+        .await?;
+
+        Ok(trades.len() as u64)
+    }
+
+    /// Every trade of a symbol in `[from, to)`: Postgres plus pruned archive
+    /// segments, oldest first
+    async fn collect_trades(
+        &self,
+        pool: &PgPool,
+        symbol: &TapeSymbol,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<ArchivedTrade>, TapeError> {
+        let mut trades: Vec<ArchivedTrade> = sqlx::query_as(
+            r#"
+            SELECT id, market_id, outcome_id, share_type::text AS share_type, side::text AS side,
+                   maker_address, taker_address, price, amount, maker_fee, taker_fee,
+                   is_block_trade, created_at, exec_id
+            FROM trades
+            WHERE market_id = $1 AND outcome_id = $2 AND share_type::text = $3
+              AND created_at >= $4 AND created_at < $5
+            ORDER BY created_at, id
+            "#,
+        )
+        .bind(symbol.market_id)
+        .bind(symbol.outcome_id)
+        .bind(symbol.share_type)
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await?;
+
+        if let Some(archive) = &self.archive {
+            let mut seen: HashSet<Uuid> = trades.iter().map(|t| t.id).collect();
+            let archived = ArchiveService::trades_between(archive.as_ref(), from, to).await?;
+            trades.extend(archived.into_iter().filter(|t| symbol.matches(t) && seen.insert(t.id)));
+            trades.sort_by_key(|t| (t.created_at, t.id));
+        }
+        Ok(trades)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use rust_decimal_macros::dec;
+    use std::io::Read;
+
+    /// What a recipient does with a downloaded tape
+    fn decode_tape(compressed: &[u8]) -> Vec<ArchivedTrade> {
+        let mut plain = Vec::new();
+        GzDecoder::new(compressed).read_to_end(&mut plain).unwrap();
+        plain
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect()
+    }
+
+    fn verify_manifest(key: &hmac::Key, manifest: &[u8], signature: &str) -> bool {
+        hex::decode(signature).is_ok_and(|tag| hmac::verify(key, manifest, &tag).is_ok())
+    }
+
+    fn trade(secs: i64) -> ArchivedTrade {
+        ArchivedTrade {
+            id: Uuid::new_v4(),
+            market_id: Uuid::nil(),
+            outcome_id: Uuid::nil(),
+            share_type: "yes".to_string(),
+            side: "buy".to_string(),
+            maker_address: "0xmaker".to_string(),
+            taker_address: "0xtaker".to_string(),
+            price: dec!(0.55),
+            amount: dec!(10),
+            maker_fee: dec!(0),
+            taker_fee: dec!(0.01),
+            is_block_trade: false,
+            created_at: DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap(),
+            exec_id: Some(secs + 1),
+        }
+    }
+
+    #[test]
+    fn test_tape_round_trip_and_checksums() {
+        let trades = vec![trade(0), trade(5), trade(9)];
+        let (plain, compressed) = encode_tape(&trades).unwrap();
+
+        assert_eq!(plain.iter().filter(|b| **b == b'\n').count(), 3);
+        let decoded = decode_tape(&compressed);
+        assert_eq!(decoded.len(), 3);
+        assert_eq!(decoded[1].id, trades[1].id);
+
+        // Checksums are stable for the same bytes and change with the content
+        assert_eq!(sha256_hex(&compressed), sha256_hex(&compressed.clone()));
+        assert_eq!(sha256_hex(b"").len(), 64);
+        let (other, _) = encode_tape(&trades[..2]).unwrap();
+        assert_ne!(sha256_hex(&plain), sha256_hex(&other));
+    }
+
+    #[test]
+    fn test_manifest_signature() {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, b"tape-secret");
+        let manifest = br#"{"row_count":3}"#;
+        let signature = sign_manifest(&key, manifest);
+
+        assert!(verify_manifest(&key, manifest, &signature));
+        assert!(!verify_manifest(&key, br#"{"row_count":4}"#, &signature));
+        let other = hmac::Key::new(hmac::HMAC_SHA256, b"other-secret");
+        assert!(!verify_manifest(&other, manifest, &signature));
+        assert!(!verify_manifest(&key, manifest, "not-hex"));
+    }
+
+    #[test]
+    fn test_parse_symbol() {
+        let market = Uuid::new_v4();
+        let outcome = Uuid::new_v4();
+        let symbol = TapeSymbol::parse(&format!("{}:{}:YES", market, outcome)).unwrap();
+        assert_eq!(symbol.share_type, "yes");
+        assert_eq!(symbol.key(), format!("{}:{}:yes", market, outcome));
+
+        assert!(TapeSymbol::parse(&format!("{}:{}", market, outcome)).is_err());
+        assert!(TapeSymbol::parse(&format!("{}:{}:maybe", market, outcome)).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+//! Token Prices
+//!
+//! Converts a market's quote asset into the collateral token for settlement.
+//! Markets may be quoted in an asset other than the collateral; their realized
+//! PnL is converted into collateral when it is credited, at the rate returned
+//! here.
+//!
+//! Every asset is valued in USD:
+//!
+//! - assets in `usd_pegged_assets` are worth exactly 1
+//! - other assets use the first index source with a `{ASSET}-USD` price
+//!   (exchange index instruments, then on-chain feeds)
+//!
+//! The quote → collateral rate is `usd(quote) / usd(collateral)`. When no
+//! source has a fresh price the last computed rate is used, so settlement
+//! does not stall on a brief oracle outage; an asset that was never priced
+//! cannot be settled.
+
+use dashmap::DashMap;
+use rust_decimal::Decimal;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::services::market::mark_price::IndexSource;
+use crate::services::position::Settlement;
+
+/// Token price errors
+#[derive(Debug, thiserror::Error)]
+pub enum TokenPriceError {
+    #[error("No conversion path from {0} to the collateral token")]
+    NoConversionPath(String),
+
+    #[error("No price for {0}")]
+    NoPrice(String),
+}
+
+/// Values assets in USD and converts them into the collateral token
+pub struct TokenPriceService {
+    collateral: String,
+    pegged: HashSet<String>,
+    /// USD pairs some source is configured for
+    symbols: HashSet<String>,
+    sources: Vec<Arc<dyn IndexSource>>,
+    last_rates: DashMap<String, Decimal>,
+}
+
+impl TokenPriceService {
+    /// `pegged` is a comma-separated list of USD-pegged assets
+    pub fn new(collateral: &str, pegged: &str) -> Self {
+        Self {
+            collateral: normalize(collateral),
+            pegged: pegged.split(',').map(normalize).filter(|a| !a.is_empty()).collect(),
+            symbols: HashSet::new(),
+            sources: Vec::new(),
+            last_rates: DashMap::new(),
+        }
+    }
+
+    /// Add a price source and the symbols it is configured for
+    pub fn with_source(mut self, source: Arc<dyn IndexSource>, symbols: impl IntoIterator<Item = String>) -> Self {
+        self.sources.push(source);
+        self.symbols.extend(symbols);
+        self
+    }
+
+    /// Collateral token symbol
+    pub fn collateral(&self) -> &str {
+        &self.collateral
+    }
+
+    /// Whether `asset` can be converted into collateral with the configured
+    /// sources
+    pub fn has_path(&self, asset: &str) -> bool {
+        let asset = normalize(asset);
+        asset == self.collateral || (self.is_priced(&asset) && self.is_priced(&self.collateral))
+    }
+
+    fn is_priced(&self, asset: &str) -> bool {
+        self.pegged.contains(asset) || self.symbols.contains(&usd_symbol(asset))
+    }
+
+    /// USD value of one unit of `asset`, if a source has a fresh price
+    pub async fn usd_price(&self, asset: &str) -> Option<Decimal> {
+        let asset = normalize(asset);
+        if self.pegged.contains(&asset) {
+            return Some(Decimal::ONE);
+        }
+        let symbol = usd_symbol(&asset);
+        for source in &self.sources {
+            if let Some(price) = source.index_price(&symbol).await {
+                return Some(price);
+            }
+        }
+        None
+    }
+
+    /// Collateral per unit of `asset`
+    pub async fn rate(&self, asset: &str) -> Result<Decimal, TokenPriceError> {
+        let asset = normalize(asset);
+        if asset == self.collateral {
+            return Ok(Decimal::ONE);
+        }
+        if !self.has_path(&asset) {
+            return Err(TokenPriceError::NoConversionPath(asset));
+        }
+
+        let quote_usd = self.usd_price(&asset).await;
+        let collateral_usd = self.usd_price(&self.collateral).await;
+        if let Some(rate) = cross_rate(quote_usd, collateral_usd) {
+            self.last_rates.insert(asset, rate);
+            return Ok(rate);
+        }
+
+        match self.last_rates.get(&asset) {
+            Some(rate) => {
+                tracing::warn!("No fresh {} price, converting at last rate {}", asset, *rate);
+                Ok(*rate)
+            }
+            None => Err(TokenPriceError::NoPrice(asset)),
+        }
+    }
+
+    /// Settlement currency for a market quoted in `quote_asset` (None = the
+    /// collateral token)
+    pub async fn settlement(&self, quote_asset: Option<&str>) -> Result<Settlement, TokenPriceError> {
+        match quote_asset.map(normalize).filter(|asset| *asset != self.collateral) {
+            None => Ok(Settlement::collateral(&self.collateral)),
+            Some(asset) => Ok(Settlement {
+                token: self.collateral.clone(),
+                conversion_rate: self.rate(&asset).await?,
+                quote_asset: Some(asset),
+                deferred: false,
+            }),
+        }
+    }
+}
+
+fn normalize(asset: &str) -> String {
+    asset.trim().to_uppercase()
+}
+
+fn usd_symbol(asset: &str) -> String {
+    format!("{}-USD", asset)
+}
+
+/// `quote_usd / collateral_usd`, when both prices are known and positive
+fn cross_rate(quote_usd: Option<Decimal>, collateral_usd: Option<Decimal>) -> Option<Decimal> {
+    let (quote, collateral) = (quote_usd?, collateral_usd?);
+    (quote > Decimal::ZERO && collateral > Decimal::ZERO).then(|| quote / collateral)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future::BoxFuture;
+    use parking_lot::Mutex;
+    use rust_decimal_macros::dec;
+
+    struct FixedSource(Mutex<Option<Decimal>>);
+
+    impl IndexSource for FixedSource {
+        fn name(&self) -> &'static str {
+            "fixed"
+        }
+
+        fn index_price<'a>(&'a self, symbol: &'a str) -> BoxFuture<'a, Option<Decimal>> {
+            Box::pin(async move { (symbol == "ETH-USD").then(|| *self.0.lock()).flatten() })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_converts_through_usd_and_falls_back_to_last_rate() {
+        let source = Arc::new(FixedSource(Mutex::new(None)));
+        let prices = TokenPriceService::new("usdt", "USD, USDT,USDC")
+            .with_source(source.clone(), vec!["ETH-USD".to_string()]);
+
+        assert!(prices.has_path("eth"));
+        assert!(prices.has_path("USDC"));
+        assert!(!prices.has_path("SOL"));
+        assert!(matches!(prices.rate("SOL").await, Err(TokenPriceError::NoConversionPath(_))));
+        assert_eq!(prices.rate("USDC").await.unwrap(), Decimal::ONE);
+
+        // Never priced: cannot settle
+        assert!(matches!(prices.rate("ETH").await, Err(TokenPriceError::NoPrice(_))));
+
+        *source.0.lock() = Some(dec!(2500));
+        assert_eq!(prices.rate("ETH").await.unwrap(), dec!(2500));
+
+        // Feed gone stale: last rate is used
+        *source.0.lock() = None;
+        let settlement = prices.settlement(Some("eth")).await.unwrap();
+        assert_eq!(settlement.quote_asset.as_deref(), Some("ETH"));
+        assert_eq!(settlement.conversion_rate, dec!(2500));
+        assert_eq!(settlement.convert(dec!(-0.01)), dec!(-25));
+
+        let collateral = prices.settlement(Some("USDT")).await.unwrap();
+        assert_eq!((collateral.quote_asset, collateral.conversion_rate), (None, Decimal::ONE));
+    }
+
+    #[test]
+    fn test_cross_rate_needs_positive_prices() {
+        assert_eq!(cross_rate(Some(dec!(2)), Some(dec!(0.5))), Some(dec!(4)));
+        assert_eq!(cross_rate(Some(dec!(2)), Some(Decimal::ZERO)), None);
+        assert_eq!(cross_rate(None, Some(Decimal::ONE)), None);
+    }
+}
@@ -0,0 +1,643 @@
+//! Position Take-Profit / Stop-Loss
+//!
+//! A position (share holding) can carry a TP/SL set of several legs:
+//!
+//! - take-profit legs, each closing a percentage of the position as it was
+//!   when the set was placed (e.g. 50% at 0.70, then 25% at 0.80). Their
+//!   percentages add up to at most 100 and their prices move away from the
+//!   entry in execution order: ascending for longs, descending for shorts.
+//! - at most one stop, closing whatever remains. It sits below every target of
+//!   a long (above for a short).
+//!
+//! The keeper ([`TpSlService::run`]) compares each position's mark price with
+//! its pending legs. Take-profit legs execute strictly in order, one per run,
+//! so each leg sees the holding left by the previous fill; a triggered stop
+//! wins over them. Legs close with a market order (sell for a long, buy for a
+//! short) capped at the shares still held. Once the position is gone, because
+//! the last leg or anything else closed or flipped it, its remaining legs are
+//! cancelled.
+//!
+//! Placing a new set cancels the pending legs of the previous one. A leg is
+//! claimed (`triggered`) before its order is submitted and then finalized as
+//! `executed` or `failed`, so it never runs twice.
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::metrics;
+use crate::services::matching::{MatchResult, MatchingEngine, OrderType, Side};
+
+/// Share amounts are stored with 8 decimal places
+const SHARE_DECIMALS: u32 = 8;
+
+/// Most take-profit legs per position
+pub const MAX_TAKE_PROFITS: usize = 5;
+
+/// TP/SL errors
+#[derive(Debug, thiserror::Error)]
+pub enum TpSlError {
+    #[error("Position not found: {0}")]
+    PositionNotFound(Uuid),
+
+    #[error("Invalid TP/SL: {0}")]
+    InvalidLegs(String),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// Kind of TP/SL leg
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LegKind {
+    TakeProfit,
+    StopLoss,
+}
+
+impl LegKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LegKind::TakeProfit => "take_profit",
+            LegKind::StopLoss => "stop_loss",
+        }
+    }
+}
+
+/// A take-profit target
+#[derive(Debug, Clone, Deserialize)]
+pub struct TakeProfitTarget {
+    pub price: Decimal,
+    /// Percentage of the position closed at `price`
+    pub percent: Decimal,
+}
+
+/// A TP/SL set for one position
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SetTpSlRequest {
+    /// Executed in this order
+    #[serde(default)]
+    pub take_profits: Vec<TakeProfitTarget>,
+    /// Closes whatever remains
+    pub stop_loss: Option<Decimal>,
+}
+
+/// A TP/SL leg and how it went
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct TpSlLeg {
+    pub id: Uuid,
+    /// take_profit or stop_loss
+    pub kind: String,
+    pub leg_index: i16,
+    pub trigger_price: Decimal,
+    /// Take-profit legs only
+    pub close_percent: Option<Decimal>,
+    /// Signed position size the percentages apply to
+    pub base_amount: Decimal,
+    /// pending, triggered, executed, failed or cancelled
+    pub status: String,
+    pub order_id: Option<Uuid>,
+    pub filled_amount: Option<Decimal>,
+    pub avg_price: Option<Decimal>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TpSlLeg {
+    fn kind(&self) -> LegKind {
+        if self.kind == LegKind::StopLoss.as_str() {
+            LegKind::StopLoss
+        } else {
+            LegKind::TakeProfit
+        }
+    }
+}
+
+/// TP/SL status of a position
+#[derive(Debug, Clone, Serialize)]
+pub struct PositionTpSl {
+    pub position_id: Uuid,
+    pub symbol: String,
+    /// Shares held now (negative = short)
+    pub amount: Decimal,
+    pub mark_price: Option<Decimal>,
+    /// Legs of the latest set, in execution order
+    pub legs: Vec<TpSlLeg>,
+}
+
+/// Outcome of a keeper run
+#[derive(Debug, Default, Serialize)]
+pub struct KeeperRun {
+    pub executed: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+}
+
+#[derive(sqlx::FromRow)]
+struct Holding {
+    user_address: String,
+    market_id: Uuid,
+    outcome_id: Uuid,
+    share_type: String,
+    amount: Decimal,
+}
+
+impl Holding {
+    fn symbol(&self) -> String {
+        format!("{}:{}:{}", self.market_id, self.outcome_id, self.share_type)
+    }
+}
+
+/// Check a TP/SL set against a position (`long` or short)
+pub fn validate(long: bool, request: &SetTpSlRequest) -> Result<(), TpSlError> {
+    let invalid = |reason: &str| Err(TpSlError::InvalidLegs(reason.to_string()));
+    if request.take_profits.is_empty() && request.stop_loss.is_none() {
+        return invalid("at least one take-profit or a stop-loss is required");
+    }
+    if request.take_profits.len() > MAX_TAKE_PROFITS {
+        return Err(TpSlError::InvalidLegs(format!("at most {} take-profit legs", MAX_TAKE_PROFITS)));
+    }
+
+    let prices = request.take_profits.iter().map(|tp| tp.price).chain(request.stop_loss);
+    if prices.into_iter().any(|p| p <= Decimal::ZERO || p >= Decimal::ONE) {
+        return invalid("prices must be between 0 and 1");
+    }
+    if request
+        .take_profits
+        .iter()
+        .any(|tp| tp.percent <= Decimal::ZERO || tp.percent > Decimal::ONE_HUNDRED)
+    {
+        return invalid("take-profit percentages must be above 0 and at most 100");
+    }
+    let total: Decimal = request.take_profits.iter().map(|tp| tp.percent).sum();
+    if total > Decimal::ONE_HUNDRED {
+        return Err(TpSlError::InvalidLegs(format!("take-profit percentages add up to {} (over 100)", total)));
+    }
+
+    // Each target further from the entry than the one before
+    let ordered = request.take_profits.windows(2).all(|pair| {
+        if long {
+            pair[0].price < pair[1].price
+        } else {
+            pair[0].price > pair[1].price
+        }
+    });
+    if !ordered {
+        return invalid(if long {
+            "take-profit prices must ascend in execution order"
+        } else {
+            "take-profit prices must descend in execution order"
+        });
+    }
+    if let (Some(stop), Some(first)) = (request.stop_loss, request.take_profits.first()) {
+        if (long && stop >= first.price) || (!long && stop <= first.price) {
+            return invalid(if long {
+                "stop-loss must be below every take-profit"
+            } else {
+                "stop-loss must be above every take-profit"
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Whether a leg's trigger price has been reached
+pub fn triggered(long: bool, kind: LegKind, trigger_price: Decimal, mark_price: Decimal) -> bool {
+    match (kind, long) {
+        (LegKind::TakeProfit, true) | (LegKind::StopLoss, false) => mark_price >= trigger_price,
+        (LegKind::TakeProfit, false) | (LegKind::StopLoss, true) => mark_price <= trigger_price,
+    }
+}
+
+/// Shares a leg closes out of `held`
+pub fn leg_amount(kind: LegKind, base_amount: Decimal, close_percent: Option<Decimal>, held: Decimal) -> Decimal {
+    let held = held.abs();
+    match (kind, close_percent) {
+        (LegKind::TakeProfit, Some(percent)) => (base_amount.abs() * percent / Decimal::ONE_HUNDRED)
+            .round_dp(SHARE_DECIMALS)
+            .min(held),
+        _ => held,
+    }
+}
+
+/// The leg to execute at `mark_price`, out of a position's pending legs in
+/// execution order: a triggered stop, else the first take-profit if triggered
+pub fn due_leg(long: bool, pending: &[TpSlLeg], mark_price: Decimal) -> Option<&TpSlLeg> {
+    let stop = pending
+        .iter()
+        .find(|leg| leg.kind() == LegKind::StopLoss && triggered(long, LegKind::StopLoss, leg.trigger_price, mark_price));
+    stop.or_else(|| {
+        pending
+            .iter()
+            .find(|leg| leg.kind() == LegKind::TakeProfit)
+            .filter(|leg| triggered(long, LegKind::TakeProfit, leg.trigger_price, mark_price))
+    })
+}
+
+pub struct TpSlService;
+
+impl TpSlService {
+    /// Place a TP/SL set on a position, replacing the pending legs of the previous one
+    pub async fn set(
+        pool: &PgPool,
+        engine: &MatchingEngine,
+        position_id: Uuid,
+        user_address: &str,
+        request: &SetTpSlRequest,
+    ) -> Result<PositionTpSl, TpSlError> {
+        let user_address = user_address.to_lowercase();
+        let mut tx = pool.begin().await?;
+
+        let holding: Option<Holding> = sqlx::query_as(
+            r#"
+            SELECT user_address, market_id, outcome_id, share_type::text AS share_type, amount
+            FROM shares
+            WHERE id = $1 AND user_address = $2 AND amount <> 0
+            FOR UPDATE
+            "#,
+        )
+        .bind(position_id)
+        .bind(&user_address)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let holding = holding.ok_or(TpSlError::PositionNotFound(position_id))?;
+        validate(holding.amount > Decimal::ZERO, request)?;
+
+        sqlx::query(
+            r#"
+            UPDATE position_tp_sl_legs SET status = 'cancelled', updated_at = NOW()
+            WHERE share_id = $1 AND status = 'pending'
+            "#,
+        )
+        .bind(position_id)
+        .execute(&mut *tx)
+        .await?;
+
+        let set_id = Uuid::new_v4();
+        let legs = request
+            .take_profits
+            .iter()
+            .map(|tp| (LegKind::TakeProfit, tp.price, Some(tp.percent)))
+            .chain(request.stop_loss.map(|price| (LegKind::StopLoss, price, None)));
+        for (index, (kind, price, percent)) in legs.enumerate() {
+            sqlx::query(
+                r#"
+                INSERT INTO position_tp_sl_legs (
+                    set_id, share_id, user_address, kind, leg_index, trigger_price, close_percent, base_amount
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                "#,
+            )
+            .bind(set_id)
+            .bind(position_id)
+            .bind(&user_address)
+            .bind(kind.as_str())
+            .bind(index as i16)
+            .bind(price)
+            .bind(percent)
+            .bind(holding.amount)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        tracing::info!(
+            "TP/SL set on position {} of {}: {} take-profit legs, stop {:?}",
+            position_id, user_address, request.take_profits.len(), request.stop_loss
+        );
+        Self::status(pool, engine, position_id, &user_address).await
+    }
+
+    /// Cancel a position's pending legs; returns how many were cancelled
+    pub async fn cancel(pool: &PgPool, position_id: Uuid, user_address: &str) -> Result<u64, TpSlError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE position_tp_sl_legs SET status = 'cancelled', updated_at = NOW()
+            WHERE share_id = $1 AND user_address = $2 AND status = 'pending'
+            "#,
+        )
+        .bind(position_id)
+        .bind(user_address.to_lowercase())
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// The position with the legs of its latest TP/SL set
+    pub async fn status(
+        pool: &PgPool,
+        engine: &MatchingEngine,
+        position_id: Uuid,
+        user_address: &str,
+    ) -> Result<PositionTpSl, TpSlError> {
+        let holding: Option<Holding> = sqlx::query_as(
+            r#"
+            SELECT user_address, market_id, outcome_id, share_type::text AS share_type, amount
+            FROM shares
+            WHERE id = $1 AND user_address = $2
+            "#,
+        )
+        .bind(position_id)
+        .bind(user_address.to_lowercase())
+        .fetch_optional(pool)
+        .await?;
+        let holding = holding.ok_or(TpSlError::PositionNotFound(position_id))?;
+
+        let legs: Vec<TpSlLeg> = sqlx::query_as(
+            r#"
+            SELECT id, kind, leg_index, trigger_price, close_percent, base_amount, status,
+                   order_id, filled_amount, avg_price, error, created_at, updated_at
+            FROM position_tp_sl_legs
+            WHERE set_id = (
+                SELECT set_id FROM position_tp_sl_legs WHERE share_id = $1 ORDER BY created_at DESC LIMIT 1
+            )
+            ORDER BY leg_index
+            "#,
+        )
+        .bind(position_id)
+        .fetch_all(pool)
+        .await?;
+
+        let symbol = holding.symbol();
+        Ok(PositionTpSl {
+            position_id,
+            mark_price: engine.mark_price(&symbol),
+            symbol,
+            amount: holding.amount,
+            legs,
+        })
+    }
+
+    /// Execute due legs: at most one per position per run
+    pub async fn run(pool: &PgPool, engine: &MatchingEngine) -> Result<KeeperRun, TpSlError> {
+        let share_ids: Vec<Uuid> =
+            sqlx::query_scalar("SELECT DISTINCT share_id FROM position_tp_sl_legs WHERE status = 'pending'")
+                .fetch_all(pool)
+                .await?;
+
+        let mut run = KeeperRun::default();
+        for share_id in share_ids {
+            Self::run_position(pool, engine, share_id, &mut run).await?;
+        }
+        Ok(run)
+    }
+
+    async fn run_position(
+        pool: &PgPool,
+        engine: &MatchingEngine,
+        share_id: Uuid,
+        run: &mut KeeperRun,
+    ) -> Result<(), TpSlError> {
+        let holding: Option<Holding> = sqlx::query_as(
+            r#"
+            SELECT user_address, market_id, outcome_id, share_type::text AS share_type, amount
+            FROM shares
+            WHERE id = $1
+            "#,
+        )
+        .bind(share_id)
+        .fetch_optional(pool)
+        .await?;
+        let pending: Vec<TpSlLeg> = sqlx::query_as(
+            r#"
+            SELECT id, kind, leg_index, trigger_price, close_percent, base_amount, status,
+                   order_id, filled_amount, avg_price, error, created_at, updated_at
+            FROM position_tp_sl_legs
+            WHERE share_id = $1 AND status = 'pending'
+            ORDER BY leg_index
+            "#,
+        )
+        .bind(share_id)
+        .fetch_all(pool)
+        .await?;
+        let Some(first) = pending.first() else {
+            return Ok(());
+        };
+
+        // Closed or flipped since the set was placed: nothing left to protect
+        let long = first.base_amount > Decimal::ZERO;
+        let Some(holding) = holding.filter(|h| !h.amount.is_zero() && (h.amount > Decimal::ZERO) == long) else {
+            run.cancelled += Self::cancel_pending(pool, &pending).await?;
+            return Ok(());
+        };
+
+        let symbol = holding.symbol();
+        let Some(mark_price) = engine.mark_price(&symbol) else {
+            return Ok(());
+        };
+        let Some(leg) = due_leg(long, &pending, mark_price) else {
+            return Ok(());
+        };
+
+        let kind = leg.kind();
+        let amount = leg_amount(kind, leg.base_amount, leg.close_percent, holding.amount);
+        if amount <= Decimal::ZERO {
+            return Ok(());
+        }
+
+        // Claim the leg before its order exists, so it never runs twice
+        let order_id = Uuid::new_v4();
+        let claimed = sqlx::query(
+            r#"
+            UPDATE position_tp_sl_legs SET status = 'triggered', order_id = $2, updated_at = NOW()
+            WHERE id = $1 AND status = 'pending'
+            "#,
+        )
+        .bind(leg.id)
+        .bind(order_id)
+        .execute(pool)
+        .await?;
+        if claimed.rows_affected() == 0 {
+            return Ok(());
+        }
+
+        let side = if long { Side::Sell } else { Side::Buy };
+        let result = match engine.submit_order(
+            order_id,
+            &symbol,
+            &holding.user_address,
+            side,
+            OrderType::Market,
+            amount,
+            None,
+            1,
+        ) {
+            Ok(result) => result,
+            Err(e) => {
+                sqlx::query(
+                    "UPDATE position_tp_sl_legs SET status = 'failed', error = $2, updated_at = NOW() WHERE id = $1",
+                )
+                .bind(leg.id)
+                .bind(e.to_string())
+                .execute(pool)
+                .await?;
+                run.failed += 1;
+                metrics::record_tp_sl_leg(kind.as_str(), "failed");
+                tracing::warn!("TP/SL {} leg {} of position {} rejected: {}", kind.as_str(), leg.id, share_id, e);
+                return Ok(());
+            }
+        };
+
+        Self::persist_order(pool, &holding, side, amount, mark_price, &result).await?;
+        sqlx::query(
+            r#"
+            UPDATE position_tp_sl_legs
+            SET status = 'executed', filled_amount = $2, avg_price = $3, updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(leg.id)
+        .bind(result.filled_amount)
+        .bind(result.average_price)
+        .execute(pool)
+        .await?;
+        run.executed += 1;
+        metrics::record_tp_sl_leg(kind.as_str(), "executed");
+        tracing::info!(
+            "TP/SL {} leg {} closed {} of {} shares of {} for {} at mark {}",
+            kind.as_str(), leg.id, result.filled_amount, holding.amount.abs(), symbol, holding.user_address, mark_price
+        );
+
+        // The stop, or a leg that closed everything, ends the set
+        if kind == LegKind::StopLoss || result.filled_amount >= holding.amount.abs() {
+            let rest: Vec<TpSlLeg> = pending.iter().filter(|l| l.id != leg.id).cloned().collect();
+            run.cancelled += Self::cancel_pending(pool, &rest).await?;
+        }
+        Ok(())
+    }
+
+    async fn cancel_pending(pool: &PgPool, legs: &[TpSlLeg]) -> Result<usize, sqlx::Error> {
+        let ids: Vec<Uuid> = legs.iter().map(|leg| leg.id).collect();
+        let cancelled = sqlx::query(
+            r#"
+            UPDATE position_tp_sl_legs SET status = 'cancelled', updated_at = NOW()
+            WHERE id = ANY($1) AND status = 'pending'
+            "#,
+        )
+        .bind(&ids)
+        .execute(pool)
+        .await?
+        .rows_affected() as usize;
+        for leg in legs.iter().take(cancelled) {
+            metrics::record_tp_sl_leg(leg.kind().as_str(), "cancelled");
+        }
+        Ok(cancelled)
+    }
+
+    async fn persist_order(
+        pool: &PgPool,
+        holding: &Holding,
+        side: Side,
+        amount: Decimal,
+        mark_price: Decimal,
+        result: &MatchResult,
+    ) -> Result<(), sqlx::Error> {
+        let status = if result.filled_amount >= amount { "filled" } else { "cancelled" };
+        sqlx::query(
+            r#"
+            INSERT INTO orders (
+                id, user_address, market_id, outcome_id, share_type,
+                side, order_type, price, amount, filled_amount, status
+            )
+            VALUES ($1, $2, $3, $4, $5::share_type, $6::order_side, 'market'::order_type, $7, $8, $9, $10::order_status)
+            ON CONFLICT (id) DO NOTHING
+            "#,
+        )
+        .bind(result.order_id)
+        .bind(&holding.user_address)
+        .bind(holding.market_id)
+        .bind(holding.outcome_id)
+        .bind(&holding.share_type)
+        .bind(side.to_string())
+        .bind(result.average_price.unwrap_or(mark_price))
+        .bind(amount)
+        .bind(result.filled_amount)
+        .bind(status)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn targets(legs: &[(Decimal, Decimal)], stop_loss: Option<Decimal>) -> SetTpSlRequest {
+        SetTpSlRequest {
+            take_profits: legs
+                .iter()
+                .map(|&(price, percent)| TakeProfitTarget { price, percent })
+                .collect(),
+            stop_loss,
+        }
+    }
+
+    fn leg(kind: LegKind, index: i16, price: Decimal, percent: Option<Decimal>) -> TpSlLeg {
+        TpSlLeg {
+            id: Uuid::new_v4(),
+            kind: kind.as_str().to_string(),
+            leg_index: index,
+            trigger_price: price,
+            close_percent: percent,
+            base_amount: dec!(100),
+            status: "pending".to_string(),
+            order_id: None,
+            filled_amount: None,
+            avg_price: None,
+            error: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_validate_legs() {
+        // 50% at 0.7, 25% at 0.8, stop on the rest at 0.4
+        let long = targets(&[(dec!(0.7), dec!(50)), (dec!(0.8), dec!(25))], Some(dec!(0.4)));
+        assert!(validate(true, &long).is_ok());
+        // Wrong direction for a short
+        assert!(validate(false, &long).is_err());
+        assert!(validate(false, &targets(&[(dec!(0.3), dec!(50)), (dec!(0.2), dec!(50))], Some(dec!(0.6)))).is_ok());
+
+        assert!(validate(true, &targets(&[], None)).is_err());
+        assert!(validate(true, &targets(&[(dec!(0.7), dec!(60)), (dec!(0.8), dec!(50))], None)).is_err());
+        assert!(validate(true, &targets(&[(dec!(0.8), dec!(50)), (dec!(0.7), dec!(25))], None)).is_err());
+        assert!(validate(true, &targets(&[(dec!(0.7), dec!(50))], Some(dec!(0.75)))).is_err());
+        assert!(validate(true, &targets(&[(dec!(1.2), dec!(50))], None)).is_err());
+        assert!(validate(true, &targets(&[(dec!(0.7), Decimal::ZERO)], None)).is_err());
+        assert!(validate(true, &targets(&[], Some(dec!(0.4)))).is_ok());
+    }
+
+    #[test]
+    fn test_leg_amounts() {
+        // Percentages apply to the size the set was placed on, capped at what is left
+        assert_eq!(leg_amount(LegKind::TakeProfit, dec!(100), Some(dec!(50)), dec!(100)), dec!(50));
+        assert_eq!(leg_amount(LegKind::TakeProfit, dec!(100), Some(dec!(25)), dec!(50)), dec!(25));
+        assert_eq!(leg_amount(LegKind::TakeProfit, dec!(100), Some(dec!(50)), dec!(30)), dec!(30));
+        assert_eq!(leg_amount(LegKind::StopLoss, dec!(100), None, dec!(25)), dec!(25));
+        assert_eq!(leg_amount(LegKind::TakeProfit, dec!(-100), Some(dec!(50)), dec!(-100)), dec!(50));
+    }
+
+    #[test]
+    fn test_due_leg_in_order() {
+        let pending = vec![
+            leg(LegKind::TakeProfit, 0, dec!(0.7), Some(dec!(50))),
+            leg(LegKind::TakeProfit, 1, dec!(0.8), Some(dec!(25))),
+            leg(LegKind::StopLoss, 2, dec!(0.4), None),
+        ];
+        assert!(due_leg(true, &pending, dec!(0.6)).is_none());
+        // A jump past both targets still executes the first one first
+        assert_eq!(due_leg(true, &pending, dec!(0.85)).unwrap().leg_index, 0);
+        assert_eq!(due_leg(true, &pending[1..], dec!(0.85)).unwrap().leg_index, 1);
+        assert_eq!(due_leg(true, &pending, dec!(0.4)).unwrap().leg_index, 2);
+
+        assert!(triggered(false, LegKind::TakeProfit, dec!(0.3), dec!(0.25)));
+        assert!(triggered(false, LegKind::StopLoss, dec!(0.6), dec!(0.65)));
+        assert!(!triggered(false, LegKind::StopLoss, dec!(0.6), dec!(0.5)));
+    }
+}
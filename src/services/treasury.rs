@@ -0,0 +1,82 @@
+//! Treasury
+//!
+//! Income the platform keeps (withdrawal fees, ...) is recorded in
+//! `treasury_entries`, in the same transaction as the ledger change that
+//! collects it, so the treasury totals always match what left user balances.
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+/// Where treasury income came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TreasurySource {
+    WithdrawalFee,
+}
+
+impl TreasurySource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TreasurySource::WithdrawalFee => "withdrawal_fee",
+        }
+    }
+}
+
+/// Collected total per token and source
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct TreasuryTotal {
+    pub token: String,
+    pub source: String,
+    pub amount: Decimal,
+    pub entries: i64,
+    pub last_collected_at: DateTime<Utc>,
+}
+
+pub struct TreasuryService;
+
+impl TreasuryService {
+    /// Record collected income in the caller's transaction
+    pub async fn record(
+        conn: &mut PgConnection,
+        token: &str,
+        amount: Decimal,
+        source: TreasurySource,
+        reference_id: Option<Uuid>,
+    ) -> Result<(), sqlx::Error> {
+        if amount <= Decimal::ZERO {
+            return Ok(());
+        }
+        sqlx::query(
+            r#"
+            INSERT INTO treasury_entries (token, amount, source, reference_id)
+            VALUES ($1, $2, $3, $4)
+            "#,
+        )
+        .bind(token)
+        .bind(amount)
+        .bind(source.as_str())
+        .bind(reference_id)
+        .execute(conn)
+        .await?;
+        Ok(())
+    }
+
+    /// Collected totals, optionally since a time
+    pub async fn totals(pool: &PgPool, since: Option<DateTime<Utc>>) -> Result<Vec<TreasuryTotal>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT token, source, SUM(amount) AS amount, COUNT(*) AS entries, MAX(created_at) AS last_collected_at
+            FROM treasury_entries
+            WHERE $1::timestamptz IS NULL OR created_at >= $1
+            GROUP BY token, source
+            ORDER BY token, source
+            "#,
+        )
+        .bind(since)
+        .fetch_all(pool)
+        .await
+    }
+}
@@ -0,0 +1,196 @@
+//! User Data Events
+//!
+//! Balance, position and order changes are published where they are written:
+//! `LedgerService`, `PositionService` and the order flow orchestrator. Each
+//! event is sent as a Postgres notification in the writer's transaction, so
+//! it is delivered only once that transaction commits (a rolled-back change
+//! is never announced) and it reaches every API instance.
+//!
+//! [`UserEventBus`] listens on the three notification channels and fans the
+//! events out to in-process broadcast buses. WebSocket connections forward
+//! them to the owner's `balance`, `positions` and `orders` subscriptions
+//! without reading the database. Notifications sent while the listener is
+//! reconnecting are lost; subscribing again returns a fresh snapshot.
+
+use rust_decimal::Decimal;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgListener;
+use sqlx::{PgExecutor, PgPool};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::models::market::ShareType;
+use crate::services::ledger::LedgerReason;
+
+/// Event published on a Postgres notification channel
+pub trait UserEvent: Serialize + DeserializeOwned {
+    const CHANNEL: &'static str;
+}
+
+/// Balance after a journaled change
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BalanceEvent {
+    pub user_address: String,
+    pub token: String,
+    pub available: Decimal,
+    pub frozen: Decimal,
+    pub reason: LedgerReason,
+}
+
+impl UserEvent for BalanceEvent {
+    const CHANNEL: &'static str = "user_balance_events";
+}
+
+/// Share holding after a fill
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PositionEvent {
+    pub user_address: String,
+    pub market_id: Uuid,
+    pub outcome_id: Uuid,
+    pub share_type: ShareType,
+    pub amount: Decimal,
+    pub avg_cost: Decimal,
+    pub trade_id: Option<Uuid>,
+}
+
+impl UserEvent for PositionEvent {
+    const CHANNEL: &'static str = "user_position_events";
+}
+
+/// What happened to an order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum OrderChange {
+    /// Persisted with a new status
+    Status { status: String, filled_amount: Option<Decimal> },
+    /// Filled (partly) by a trade
+    Fill { trade_id: Uuid, amount: Decimal, price: Decimal },
+}
+
+/// Change of a user's order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderEvent {
+    pub user_address: String,
+    pub order_id: Uuid,
+    #[serde(flatten)]
+    pub change: OrderChange,
+}
+
+impl OrderEvent {
+    pub fn status(user_address: &str, order_id: Uuid, status: impl ToString, filled_amount: Option<Decimal>) -> Self {
+        Self {
+            user_address: user_address.to_lowercase(),
+            order_id,
+            change: OrderChange::Status {
+                status: status.to_string(),
+                filled_amount,
+            },
+        }
+    }
+}
+
+impl UserEvent for OrderEvent {
+    const CHANNEL: &'static str = "user_order_events";
+}
+
+/// Publish `event`; inside a transaction it is delivered on commit
+pub async fn publish<'c, E: UserEvent>(executor: impl PgExecutor<'c>, event: &E) -> Result<(), sqlx::Error> {
+    let payload = serde_json::to_string(event).map_err(|e| sqlx::Error::Protocol(e.to_string()))?;
+    sqlx::query("SELECT pg_notify($1, $2)")
+        .bind(E::CHANNEL)
+        .bind(payload)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// In-process buses of committed user data changes
+pub struct UserEventBus {
+    pub balances: broadcast::Sender<BalanceEvent>,
+    pub positions: broadcast::Sender<PositionEvent>,
+    pub orders: broadcast::Sender<OrderEvent>,
+}
+
+impl UserEventBus {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            balances: broadcast::channel(capacity).0,
+            positions: broadcast::channel(capacity).0,
+            orders: broadcast::channel(capacity).0,
+        }
+    }
+
+    /// Forward notifications to the buses until the connection fails
+    pub async fn listen(&self, pool: &PgPool) -> Result<(), sqlx::Error> {
+        let mut listener = PgListener::connect_with(pool).await?;
+        listener
+            .listen_all([BalanceEvent::CHANNEL, PositionEvent::CHANNEL, OrderEvent::CHANNEL])
+            .await?;
+        tracing::info!("Listening for user data events");
+
+        loop {
+            let notification = listener.recv().await?;
+            if let Err(e) = self.dispatch(notification.channel(), notification.payload()) {
+                tracing::warn!("Dropping malformed {} event: {}", notification.channel(), e);
+            }
+        }
+    }
+
+    fn dispatch(&self, channel: &str, payload: &str) -> Result<(), serde_json::Error> {
+        // Sending fails only when no connection is subscribed
+        match channel {
+            BalanceEvent::CHANNEL => {
+                let _ = self.balances.send(serde_json::from_str(payload)?);
+            }
+            PositionEvent::CHANNEL => {
+                let _ = self.positions.send(serde_json::from_str(payload)?);
+            }
+            OrderEvent::CHANNEL => {
+                let _ = self.orders.send(serde_json::from_str(payload)?);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_dispatches_notifications_by_channel() {
+        let bus = UserEventBus::new(8);
+        let mut balances = bus.balances.subscribe();
+        let mut orders = bus.orders.subscribe();
+
+        let balance = BalanceEvent {
+            user_address: "0xa".to_string(),
+            token: "USDC".to_string(),
+            available: dec!(95),
+            frozen: dec!(5),
+            reason: LedgerReason::OrderFreeze,
+        };
+        bus.dispatch(BalanceEvent::CHANNEL, &serde_json::to_string(&balance).unwrap()).unwrap();
+        assert_eq!(balances.try_recv().unwrap(), balance);
+
+        let fill = OrderEvent {
+            user_address: "0xa".to_string(),
+            order_id: Uuid::new_v4(),
+            change: OrderChange::Fill {
+                trade_id: Uuid::new_v4(),
+                amount: dec!(10),
+                price: dec!(0.55),
+            },
+        };
+        let payload = serde_json::to_string(&fill).unwrap();
+        assert!(payload.contains(r#""kind":"fill""#));
+        bus.dispatch(OrderEvent::CHANNEL, &payload).unwrap();
+        assert_eq!(orders.try_recv().unwrap(), fill);
+
+        assert!(bus.dispatch(OrderEvent::CHANNEL, "{}").is_err());
+        assert!(balances.try_recv().is_err());
+    }
+}
@@ -0,0 +1,625 @@
+//! LP Vault Service
+//!
+//! Pooled liquidity provider vaults that back the auto market maker:
+//! - Deposits move collateral from the user to the vault's operator (market maker)
+//!   account and mint vault shares at the current NAV per share
+//! - NAV = operator collateral balance + operator share holdings marked to the book,
+//!   so trading PnL and maker fees accrue to all share holders pro rata
+//! - Deposited shares are locked for `lock_epochs` epochs; withdrawals burn shares
+//!   at the current NAV per share and are limited by the operator's free collateral
+//!
+//! Every deposit and withdrawal is recorded in `lp_vault_ledger`.
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use sqlx::{PgConnection, PgPool};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::models::market::ShareType;
+use crate::services::ledger::{BalanceChange, LedgerReason, LedgerService};
+use crate::services::matching::MatchingEngine;
+
+/// Vault service errors
+#[derive(Debug, thiserror::Error)]
+pub enum VaultError {
+    #[error("Vault not found: {0}")]
+    VaultNotFound(Uuid),
+
+    #[error("Vault is not active: {0}")]
+    VaultInactive(Uuid),
+
+    #[error("Amount must be positive")]
+    InvalidAmount,
+
+    #[error("Insufficient balance")]
+    InsufficientBalance,
+
+    #[error("Insufficient vault shares")]
+    InsufficientShares,
+
+    #[error("Shares locked until {0}")]
+    SharesLocked(DateTime<Utc>),
+
+    #[error("Insufficient vault liquidity: {0} available")]
+    InsufficientLiquidity(Decimal),
+
+    #[error("Vault NAV is {0} with shares outstanding")]
+    Insolvent(Decimal),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// LP vault definition
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Vault {
+    pub id: Uuid,
+    pub name: String,
+    pub token: String,
+    pub operator_address: String,
+    pub epoch_seconds: i64,
+    pub lock_epochs: i32,
+    pub total_shares: Decimal,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Vault net asset value breakdown
+#[derive(Debug, Clone)]
+pub struct VaultNav {
+    /// Operator collateral (available + frozen)
+    pub cash: Decimal,
+    /// Operator share holdings marked to the book
+    pub positions_value: Decimal,
+    /// cash + positions_value
+    pub nav: Decimal,
+    pub total_shares: Decimal,
+    pub nav_per_share: Decimal,
+    pub epoch: i64,
+}
+
+/// A user's stake in a vault
+#[derive(Debug, Clone)]
+pub struct VaultPosition {
+    pub vault_id: Uuid,
+    pub shares: Decimal,
+    pub locked_shares: Decimal,
+    pub cost_basis: Decimal,
+    pub value: Decimal,
+    pub next_unlock_at: Option<DateTime<Utc>>,
+}
+
+/// Result of a deposit or withdrawal
+#[derive(Debug, Clone)]
+pub struct VaultLedgerEntry {
+    pub vault_id: Uuid,
+    pub entry_type: &'static str,
+    pub amount: Decimal,
+    pub shares: Decimal,
+    pub nav_per_share: Decimal,
+    pub epoch: i64,
+    pub unlock_at: Option<DateTime<Utc>>,
+}
+
+// ============================================================================
+// Share Math
+// ============================================================================
+
+/// NAV per share (1.0 for an empty vault, 0 for an insolvent one)
+pub fn nav_per_share(nav: Decimal, total_shares: Decimal) -> Decimal {
+    if total_shares <= Decimal::ZERO {
+        Decimal::ONE
+    } else if nav <= Decimal::ZERO {
+        Decimal::ZERO
+    } else {
+        nav / total_shares
+    }
+}
+
+/// Shares minted for a deposit at the given NAV; an insolvent vault takes no
+/// deposits, since new collateral would cover the existing holders' losses
+pub fn shares_for_deposit(amount: Decimal, nav: Decimal, total_shares: Decimal) -> Result<Decimal, VaultError> {
+    let price = nav_per_share(nav, total_shares);
+    if price <= Decimal::ZERO {
+        return Err(VaultError::Insolvent(nav));
+    }
+    Ok((amount / price).round_dp(8))
+}
+
+/// Collateral paid out for burning shares at the given NAV
+pub fn amount_for_shares(shares: Decimal, nav: Decimal, total_shares: Decimal) -> Decimal {
+    (shares * nav_per_share(nav, total_shares)).round_dp_with_strategy(8, rust_decimal::RoundingStrategy::ToZero)
+}
+
+/// Index of the epoch containing `now` (epochs start at vault creation)
+pub fn epoch_index(created_at: DateTime<Utc>, epoch_seconds: i64, now: DateTime<Utc>) -> i64 {
+    let elapsed = (now - created_at).num_seconds().max(0);
+    elapsed / epoch_seconds.max(1)
+}
+
+/// When shares deposited at `now` become withdrawable: the start of the epoch
+/// `lock_epochs` after the current one (immediately if `lock_epochs` is 0)
+pub fn unlock_time(created_at: DateTime<Utc>, epoch_seconds: i64, lock_epochs: i32, now: DateTime<Utc>) -> DateTime<Utc> {
+    if lock_epochs <= 0 {
+        return now;
+    }
+    let epoch = epoch_index(created_at, epoch_seconds, now);
+    created_at + chrono::Duration::seconds((epoch + lock_epochs as i64) * epoch_seconds.max(1))
+}
+
+/// Fallback mark price for a share holding (before the mark price service has
+/// published one): book mid, else last trade, else cost
+pub fn mark_price(
+    best_bid: Option<Decimal>,
+    best_ask: Option<Decimal>,
+    last_price: Option<Decimal>,
+    avg_cost: Decimal,
+) -> Decimal {
+    match (best_bid, best_ask) {
+        (Some(bid), Some(ask)) => (bid + ask) / Decimal::TWO,
+        _ => last_price.unwrap_or(avg_cost),
+    }
+}
+
+/// LP vault service
+pub struct VaultService;
+
+impl VaultService {
+    /// Create a new vault operated by `operator_address`
+    pub async fn create_vault(
+        pool: &PgPool,
+        name: &str,
+        token: &str,
+        operator_address: &str,
+        epoch_seconds: i64,
+        lock_epochs: i32,
+    ) -> Result<Vault, VaultError> {
+        let vault: Vault = sqlx::query_as(
+            r#"
+            INSERT INTO lp_vaults (name, token, operator_address, epoch_seconds, lock_epochs)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, name, token, operator_address, epoch_seconds, lock_epochs,
+                      total_shares, status, created_at
+            "#
+        )
+        .bind(name)
+        .bind(token)
+        .bind(operator_address.to_lowercase())
+        .bind(epoch_seconds)
+        .bind(lock_epochs)
+        .fetch_one(pool)
+        .await?;
+
+        info!("Created LP vault {} ({}) operated by {}", vault.id, vault.name, vault.operator_address);
+        Ok(vault)
+    }
+
+    /// List all vaults
+    pub async fn list_vaults(pool: &PgPool) -> Result<Vec<Vault>, VaultError> {
+        let vaults = sqlx::query_as(
+            r#"
+            SELECT id, name, token, operator_address, epoch_seconds, lock_epochs,
+                   total_shares, status, created_at
+            FROM lp_vaults
+            ORDER BY created_at ASC
+            "#
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(vaults)
+    }
+
+    /// Get a vault by id
+    pub async fn get_vault(pool: &PgPool, vault_id: Uuid) -> Result<Vault, VaultError> {
+        let mut conn = pool.acquire().await?;
+        Self::load_vault(&mut conn, vault_id, false).await
+    }
+
+    /// Compute a vault's NAV
+    pub async fn compute_nav(pool: &PgPool, engine: &MatchingEngine, vault: &Vault) -> Result<VaultNav, VaultError> {
+        let mut conn = pool.acquire().await?;
+        Self::compute_nav_with(&mut conn, engine, vault).await
+    }
+
+    /// Deposit collateral into a vault, minting shares at the current NAV
+    pub async fn deposit(
+        pool: &PgPool,
+        engine: &MatchingEngine,
+        vault_id: Uuid,
+        user_address: &str,
+        amount: Decimal,
+    ) -> Result<VaultLedgerEntry, VaultError> {
+        if amount <= Decimal::ZERO {
+            return Err(VaultError::InvalidAmount);
+        }
+        let user_address = user_address.to_lowercase();
+
+        let mut tx = pool.begin().await?;
+
+        // Lock the vault row so share issuance is serialized
+        let vault = Self::load_vault(&mut tx, vault_id, true).await?;
+        if vault.status != "active" {
+            return Err(VaultError::VaultInactive(vault_id));
+        }
+
+        // Price the deposit before the collateral moves
+        let nav = Self::compute_nav_with(&mut tx, engine, &vault).await?;
+        let shares = shares_for_deposit(amount, nav.nav, vault.total_shares)?;
+        if shares <= Decimal::ZERO {
+            return Err(VaultError::InvalidAmount);
+        }
+
+        // Move collateral: user -> operator
+        let debit = BalanceChange::debit(&user_address, &vault.token, amount, LedgerReason::VaultDeposit)
+            .reference(vault_id)
+            .checked();
+        if LedgerService::apply(&mut tx, &debit).await?.is_none() {
+            return Err(VaultError::InsufficientBalance);
+        }
+
+        let credit = BalanceChange::credit(&vault.operator_address, &vault.token, amount, LedgerReason::VaultDeposit)
+            .reference(vault_id);
+        LedgerService::apply(&mut tx, &credit).await?;
+
+        // Mint shares
+        sqlx::query(
+            r#"
+            INSERT INTO lp_vault_positions (vault_id, user_address, shares, cost_basis)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (vault_id, user_address) DO UPDATE SET
+                shares = lp_vault_positions.shares + $3,
+                cost_basis = lp_vault_positions.cost_basis + $4,
+                updated_at = NOW()
+            "#
+        )
+        .bind(vault_id)
+        .bind(&user_address)
+        .bind(shares)
+        .bind(amount)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("UPDATE lp_vaults SET total_shares = total_shares + $2, updated_at = NOW() WHERE id = $1")
+            .bind(vault_id)
+            .bind(shares)
+            .execute(&mut *tx)
+            .await?;
+
+        let unlock_at = unlock_time(vault.created_at, vault.epoch_seconds, vault.lock_epochs, Utc::now());
+        let entry = VaultLedgerEntry {
+            vault_id,
+            entry_type: "deposit",
+            amount,
+            shares,
+            nav_per_share: nav.nav_per_share,
+            epoch: nav.epoch,
+            unlock_at: Some(unlock_at),
+        };
+        Self::record_ledger(&mut tx, &user_address, &entry).await?;
+
+        tx.commit().await?;
+
+        info!(
+            "Vault deposit: vault={}, user={}, amount={}, shares={}, nav_per_share={}",
+            vault_id, user_address, amount, shares, nav.nav_per_share
+        );
+        Ok(entry)
+    }
+
+    /// Withdraw by burning unlocked shares at the current NAV
+    pub async fn withdraw(
+        pool: &PgPool,
+        engine: &MatchingEngine,
+        vault_id: Uuid,
+        user_address: &str,
+        shares: Decimal,
+    ) -> Result<VaultLedgerEntry, VaultError> {
+        if shares <= Decimal::ZERO {
+            return Err(VaultError::InvalidAmount);
+        }
+        let user_address = user_address.to_lowercase();
+
+        let mut tx = pool.begin().await?;
+        let vault = Self::load_vault(&mut tx, vault_id, true).await?;
+
+        let (held, cost_basis): (Decimal, Decimal) = sqlx::query_as(
+            r#"
+            SELECT shares, cost_basis FROM lp_vault_positions
+            WHERE vault_id = $1 AND user_address = $2
+            FOR UPDATE
+            "#
+        )
+        .bind(vault_id)
+        .bind(&user_address)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(VaultError::InsufficientShares)?;
+
+        if shares > held {
+            return Err(VaultError::InsufficientShares);
+        }
+
+        let now = Utc::now();
+        let (locked, next_unlock) = Self::locked_shares(&mut tx, vault_id, &user_address, held, now).await?;
+        if shares > held - locked {
+            return Err(VaultError::SharesLocked(next_unlock.unwrap_or(now)));
+        }
+
+        let nav = Self::compute_nav_with(&mut tx, engine, &vault).await?;
+        let amount = amount_for_shares(shares, nav.nav, vault.total_shares);
+        if amount <= Decimal::ZERO {
+            return Err(VaultError::Insolvent(nav.nav));
+        }
+
+        // Pay out of the operator's free collateral
+        let debit = BalanceChange::debit(&vault.operator_address, &vault.token, amount, LedgerReason::VaultWithdrawal)
+            .reference(vault_id)
+            .checked();
+        if LedgerService::apply(&mut tx, &debit).await?.is_none() {
+            let available: Option<Decimal> = sqlx::query_scalar(
+                "SELECT available FROM balances WHERE user_address = $1 AND token = $2",
+            )
+            .bind(&vault.operator_address)
+            .bind(&vault.token)
+            .fetch_optional(&mut *tx)
+            .await?;
+            return Err(VaultError::InsufficientLiquidity(available.unwrap_or(Decimal::ZERO)));
+        }
+
+        let credit = BalanceChange::credit(&user_address, &vault.token, amount, LedgerReason::VaultWithdrawal)
+            .reference(vault_id);
+        LedgerService::apply(&mut tx, &credit).await?;
+
+        // Burn shares, releasing cost basis pro rata
+        let released_basis = (cost_basis * shares / held).round_dp(8);
+        sqlx::query(
+            r#"
+            UPDATE lp_vault_positions
+            SET shares = shares - $3, cost_basis = GREATEST(cost_basis - $4, 0), updated_at = NOW()
+            WHERE vault_id = $1 AND user_address = $2
+            "#
+        )
+        .bind(vault_id)
+        .bind(&user_address)
+        .bind(shares)
+        .bind(released_basis)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("UPDATE lp_vaults SET total_shares = total_shares - $2, updated_at = NOW() WHERE id = $1")
+            .bind(vault_id)
+            .bind(shares)
+            .execute(&mut *tx)
+            .await?;
+
+        let entry = VaultLedgerEntry {
+            vault_id,
+            entry_type: "withdraw",
+            amount,
+            shares,
+            nav_per_share: nav.nav_per_share,
+            epoch: nav.epoch,
+            unlock_at: None,
+        };
+        Self::record_ledger(&mut tx, &user_address, &entry).await?;
+
+        tx.commit().await?;
+
+        info!(
+            "Vault withdrawal: vault={}, user={}, shares={}, amount={}, nav_per_share={}",
+            vault_id, user_address, shares, amount, nav.nav_per_share
+        );
+        Ok(entry)
+    }
+
+    /// Get a user's vault position
+    pub async fn get_position(
+        pool: &PgPool,
+        engine: &MatchingEngine,
+        vault_id: Uuid,
+        user_address: &str,
+    ) -> Result<VaultPosition, VaultError> {
+        let user_address = user_address.to_lowercase();
+        let mut conn = pool.acquire().await?;
+        let vault = Self::load_vault(&mut conn, vault_id, false).await?;
+
+        let (shares, cost_basis): (Decimal, Decimal) = sqlx::query_as(
+            "SELECT shares, cost_basis FROM lp_vault_positions WHERE vault_id = $1 AND user_address = $2",
+        )
+        .bind(vault_id)
+        .bind(&user_address)
+        .fetch_optional(&mut *conn)
+        .await?
+        .unwrap_or((Decimal::ZERO, Decimal::ZERO));
+
+        let (locked_shares, next_unlock_at) =
+            Self::locked_shares(&mut conn, vault_id, &user_address, shares, Utc::now()).await?;
+        let nav = Self::compute_nav_with(&mut conn, engine, &vault).await?;
+
+        Ok(VaultPosition {
+            vault_id,
+            shares,
+            locked_shares,
+            cost_basis,
+            value: amount_for_shares(shares, nav.nav, vault.total_shares),
+            next_unlock_at,
+        })
+    }
+
+    // ========================================================================
+    // Internal helpers
+    // ========================================================================
+
+    async fn load_vault(conn: &mut PgConnection, vault_id: Uuid, for_update: bool) -> Result<Vault, VaultError> {
+        let sql = if for_update {
+            r#"
+            SELECT id, name, token, operator_address, epoch_seconds, lock_epochs,
+                   total_shares, status, created_at
+            FROM lp_vaults WHERE id = $1
+            FOR UPDATE
+            "#
+        } else {
+            r#"
+            SELECT id, name, token, operator_address, epoch_seconds, lock_epochs,
+                   total_shares, status, created_at
+            FROM lp_vaults WHERE id = $1
+            "#
+        };
+
+        sqlx::query_as(sql)
+            .bind(vault_id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .ok_or(VaultError::VaultNotFound(vault_id))
+    }
+
+    /// NAV = operator collateral + operator share holdings marked to the book
+    async fn compute_nav_with(
+        conn: &mut PgConnection,
+        engine: &MatchingEngine,
+        vault: &Vault,
+    ) -> Result<VaultNav, VaultError> {
+        let cash: Option<Decimal> = sqlx::query_scalar(
+            "SELECT available + frozen FROM balances WHERE user_address = $1 AND token = $2",
+        )
+        .bind(&vault.operator_address)
+        .bind(&vault.token)
+        .fetch_optional(&mut *conn)
+        .await?;
+        let cash = cash.unwrap_or(Decimal::ZERO);
+
+        let holdings: Vec<(Uuid, Uuid, String, Decimal, Decimal)> = sqlx::query_as(
+            r#"
+            SELECT market_id, outcome_id, share_type::text, amount, avg_cost
+            FROM shares
+            WHERE user_address = $1 AND amount > 0
+            "#
+        )
+        .bind(&vault.operator_address)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut positions_value = Decimal::ZERO;
+        for (market_id, outcome_id, share_type_str, amount, avg_cost) in holdings {
+            let share_type: ShareType = share_type_str.parse().unwrap_or(ShareType::Yes);
+            let symbol = format!("{}:{}:{}", market_id, outcome_id, share_type);
+            let mark = engine.mark_price(&symbol).unwrap_or_else(|| {
+                let (best_bid, best_ask) = engine.get_best_prices(&symbol).unwrap_or((None, None));
+                let last_price = engine.get_orderbook_ref(&symbol).and_then(|ob| ob.last_trade_price());
+                mark_price(best_bid, best_ask, last_price, avg_cost)
+            });
+            positions_value += amount * mark;
+        }
+
+        let nav = cash + positions_value;
+        Ok(VaultNav {
+            cash,
+            positions_value,
+            nav,
+            total_shares: vault.total_shares,
+            nav_per_share: nav_per_share(nav, vault.total_shares),
+            epoch: epoch_index(vault.created_at, vault.epoch_seconds, Utc::now()),
+        })
+    }
+
+    /// Shares still inside their deposit lock, and the earliest time more unlock
+    async fn locked_shares(
+        conn: &mut PgConnection,
+        vault_id: Uuid,
+        user_address: &str,
+        held: Decimal,
+        now: DateTime<Utc>,
+    ) -> Result<(Decimal, Option<DateTime<Utc>>), VaultError> {
+        let (locked, next_unlock): (Option<Decimal>, Option<DateTime<Utc>>) = sqlx::query_as(
+            r#"
+            SELECT SUM(shares), MIN(unlock_at)
+            FROM lp_vault_ledger
+            WHERE vault_id = $1 AND user_address = $2 AND entry_type = 'deposit' AND unlock_at > $3
+            "#
+        )
+        .bind(vault_id)
+        .bind(user_address)
+        .bind(now)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok((locked.unwrap_or(Decimal::ZERO).min(held), next_unlock))
+    }
+
+    async fn record_ledger(
+        conn: &mut PgConnection,
+        user_address: &str,
+        entry: &VaultLedgerEntry,
+    ) -> Result<(), VaultError> {
+        sqlx::query(
+            r#"
+            INSERT INTO lp_vault_ledger (vault_id, user_address, entry_type, amount, shares, nav_per_share, epoch, unlock_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#
+        )
+        .bind(entry.vault_id)
+        .bind(user_address)
+        .bind(entry.entry_type)
+        .bind(entry.amount)
+        .bind(entry.shares)
+        .bind(entry.nav_per_share)
+        .bind(entry.epoch)
+        .bind(entry.unlock_at)
+        .execute(&mut *conn)
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_first_deposit_mints_one_to_one() {
+        assert_eq!(shares_for_deposit(dec!(100), dec!(0), dec!(0)).unwrap(), dec!(100));
+    }
+
+    #[test]
+    fn test_insolvent_vault_rejects_deposits() {
+        // Losses wiped out the NAV while 100 shares are outstanding
+        assert_eq!(nav_per_share(dec!(-20), dec!(100)), dec!(0));
+        assert!(matches!(
+            shares_for_deposit(dec!(50), dec!(-20), dec!(100)),
+            Err(VaultError::Insolvent(nav)) if nav == dec!(-20)
+        ));
+        assert!(matches!(shares_for_deposit(dec!(50), dec!(0), dec!(100)), Err(VaultError::Insolvent(_))));
+        assert_eq!(amount_for_shares(dec!(10), dec!(-20), dec!(100)), dec!(0));
+    }
+
+    #[test]
+    fn test_deposit_and_withdraw_at_nav() {
+        // Vault grew from 100 to 150 with 100 shares outstanding
+        let shares = shares_for_deposit(dec!(30), dec!(150), dec!(100)).unwrap();
+        assert_eq!(shares, dec!(20));
+
+        // After the deposit: NAV 180, 120 shares -> 1.5 per share
+        assert_eq!(amount_for_shares(dec!(20), dec!(180), dec!(120)), dec!(30));
+        assert_eq!(amount_for_shares(dec!(100), dec!(180), dec!(120)), dec!(150));
+    }
+
+    #[test]
+    fn test_unlock_time_rolls_to_epoch_boundary() {
+        let created = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let now = created + chrono::Duration::seconds(150);
+
+        // 100s epochs: deposit in epoch 1 with a 1-epoch lock unlocks at start of epoch 2
+        assert_eq!(epoch_index(created, 100, now), 1);
+        assert_eq!(unlock_time(created, 100, 1, now), created + chrono::Duration::seconds(200));
+        assert_eq!(unlock_time(created, 100, 0, now), now);
+    }
+
+    #[test]
+    fn test_mark_price_fallbacks() {
+        assert_eq!(mark_price(Some(dec!(0.40)), Some(dec!(0.50)), Some(dec!(0.1)), dec!(0.2)), dec!(0.45));
+        assert_eq!(mark_price(Some(dec!(0.40)), None, Some(dec!(0.1)), dec!(0.2)), dec!(0.1));
+        assert_eq!(mark_price(None, None, None, dec!(0.2)), dec!(0.2));
+    }
+}
@@ -0,0 +1,307 @@
+//! Withdrawal Fees
+//!
+//! Each token can have a fee policy (`withdrawal_fee_policies`) combining:
+//!
+//! - a flat component, scaled by the gas oracle's congestion multiplier when
+//!   `congestion_enabled` (it pays for the on-chain transfer)
+//! - a percentage of the amount, bounded by optional min / max fees
+//!
+//! Fees are computed when the user asks for a quote and locked to the quote
+//! id until it expires; the withdrawal request redeems the quote, so the fee
+//! charged is the one the user saw. Tokens without a policy withdraw free of
+//! charge and need no quote.
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+/// Withdrawal fee errors
+#[derive(Debug, thiserror::Error)]
+pub enum WithdrawalFeeError {
+    #[error("Invalid fee policy: {0}")]
+    InvalidPolicy(String),
+
+    #[error("Amount {amount} does not cover the withdrawal fee {fee}")]
+    FeeExceedsAmount { amount: Decimal, fee: Decimal },
+
+    #[error("Withdrawal quote is invalid, expired or already used")]
+    InvalidQuote,
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// Fee policy of a token
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct WithdrawalFeePolicy {
+    pub token: String,
+    pub flat_fee: Decimal,
+    /// Fraction of the amount, e.g. 0.001 = 0.1%
+    pub percentage: Decimal,
+    pub min_fee: Option<Decimal>,
+    pub max_fee: Option<Decimal>,
+    /// Scale the flat fee by network congestion
+    pub congestion_enabled: bool,
+    #[serde(default = "Utc::now")]
+    pub updated_at: DateTime<Utc>,
+}
+
+impl WithdrawalFeePolicy {
+    pub fn validate(&self) -> Result<(), WithdrawalFeeError> {
+        if self.flat_fee < Decimal::ZERO {
+            return Err(WithdrawalFeeError::InvalidPolicy("flat_fee must not be negative".to_string()));
+        }
+        if self.percentage < Decimal::ZERO || self.percentage >= Decimal::ONE {
+            return Err(WithdrawalFeeError::InvalidPolicy("percentage must be in [0, 1)".to_string()));
+        }
+        if self.min_fee.is_some_and(|min| min < Decimal::ZERO) || self.max_fee.is_some_and(|max| max < Decimal::ZERO) {
+            return Err(WithdrawalFeeError::InvalidPolicy("min_fee and max_fee must not be negative".to_string()));
+        }
+        if let (Some(min), Some(max)) = (self.min_fee, self.max_fee) {
+            if min > max {
+                return Err(WithdrawalFeeError::InvalidPolicy("min_fee must not exceed max_fee".to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Fee components for one withdrawal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FeeBreakdown {
+    /// Flat fee after the congestion multiplier
+    pub flat_fee: Decimal,
+    /// Percentage fee after the min / max bounds
+    pub percentage_fee: Decimal,
+    pub congestion_multiplier: Decimal,
+    pub fee: Decimal,
+}
+
+/// Fee of withdrawing `amount` under a policy at a congestion multiplier
+pub fn compute_fee(policy: &WithdrawalFeePolicy, amount: Decimal, congestion: Decimal) -> FeeBreakdown {
+    let congestion_multiplier = if policy.congestion_enabled {
+        congestion.max(Decimal::ONE)
+    } else {
+        Decimal::ONE
+    };
+    let flat_fee = (policy.flat_fee * congestion_multiplier).round_dp(8);
+
+    let mut percentage_fee = amount * policy.percentage;
+    if let Some(min) = policy.min_fee {
+        percentage_fee = percentage_fee.max(min);
+    }
+    if let Some(max) = policy.max_fee {
+        percentage_fee = percentage_fee.min(max);
+    }
+    let percentage_fee = percentage_fee.round_dp(8);
+
+    FeeBreakdown {
+        flat_fee,
+        percentage_fee,
+        congestion_multiplier,
+        fee: flat_fee + percentage_fee,
+    }
+}
+
+/// A fee locked for one withdrawal until `expires_at`
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct WithdrawalQuote {
+    pub id: Uuid,
+    pub user_address: String,
+    pub token: String,
+    pub amount: Decimal,
+    pub fee: Decimal,
+    pub flat_fee: Decimal,
+    pub percentage_fee: Decimal,
+    pub congestion_multiplier: Decimal,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+pub struct WithdrawalFeeService;
+
+impl WithdrawalFeeService {
+    pub async fn get_policy(pool: &PgPool, token: &str) -> Result<Option<WithdrawalFeePolicy>, WithdrawalFeeError> {
+        let policy = sqlx::query_as(
+            r#"
+            SELECT token, flat_fee, percentage, min_fee, max_fee, congestion_enabled, updated_at
+            FROM withdrawal_fee_policies
+            WHERE token = $1
+            "#,
+        )
+        .bind(token)
+        .fetch_optional(pool)
+        .await?;
+        Ok(policy)
+    }
+
+    pub async fn list_policies(pool: &PgPool) -> Result<Vec<WithdrawalFeePolicy>, WithdrawalFeeError> {
+        let policies = sqlx::query_as(
+            r#"
+            SELECT token, flat_fee, percentage, min_fee, max_fee, congestion_enabled, updated_at
+            FROM withdrawal_fee_policies
+            ORDER BY token
+            "#,
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(policies)
+    }
+
+    /// Create or replace a token's policy
+    pub async fn upsert_policy(
+        pool: &PgPool,
+        policy: &WithdrawalFeePolicy,
+    ) -> Result<WithdrawalFeePolicy, WithdrawalFeeError> {
+        policy.validate()?;
+        let policy = sqlx::query_as(
+            r#"
+            INSERT INTO withdrawal_fee_policies (token, flat_fee, percentage, min_fee, max_fee, congestion_enabled)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (token) DO UPDATE SET
+                flat_fee = EXCLUDED.flat_fee,
+                percentage = EXCLUDED.percentage,
+                min_fee = EXCLUDED.min_fee,
+                max_fee = EXCLUDED.max_fee,
+                congestion_enabled = EXCLUDED.congestion_enabled,
+                updated_at = NOW()
+            RETURNING token, flat_fee, percentage, min_fee, max_fee, congestion_enabled, updated_at
+            "#,
+        )
+        .bind(&policy.token)
+        .bind(policy.flat_fee)
+        .bind(policy.percentage)
+        .bind(policy.min_fee)
+        .bind(policy.max_fee)
+        .bind(policy.congestion_enabled)
+        .fetch_one(pool)
+        .await?;
+        Ok(policy)
+    }
+
+    /// Quote the fee of a withdrawal and lock it for `ttl_secs`
+    pub async fn quote(
+        pool: &PgPool,
+        user_address: &str,
+        token: &str,
+        amount: Decimal,
+        congestion: Decimal,
+        ttl_secs: i64,
+    ) -> Result<WithdrawalQuote, WithdrawalFeeError> {
+        let breakdown = match Self::get_policy(pool, token).await? {
+            Some(policy) => compute_fee(&policy, amount, congestion),
+            None => compute_fee(&WithdrawalFeePolicy::free(token), amount, congestion),
+        };
+        if breakdown.fee >= amount {
+            return Err(WithdrawalFeeError::FeeExceedsAmount { amount, fee: breakdown.fee });
+        }
+
+        let quote = sqlx::query_as(
+            r#"
+            INSERT INTO withdrawal_quotes
+                (user_address, token, amount, fee, flat_fee, percentage_fee, congestion_multiplier, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, NOW() + make_interval(secs => $8))
+            RETURNING id, user_address, token, amount, fee, flat_fee, percentage_fee,
+                      congestion_multiplier, expires_at, created_at
+            "#,
+        )
+        .bind(user_address.to_lowercase())
+        .bind(token)
+        .bind(amount)
+        .bind(breakdown.fee)
+        .bind(breakdown.flat_fee)
+        .bind(breakdown.percentage_fee)
+        .bind(breakdown.congestion_multiplier)
+        .bind(ttl_secs as f64)
+        .fetch_one(pool)
+        .await?;
+        Ok(quote)
+    }
+
+    /// Use a quote for a withdrawal in the caller's transaction; returns the
+    /// locked fee. The quote must belong to the user, match the token and
+    /// amount, be unexpired and unused.
+    pub async fn redeem_quote(
+        conn: &mut PgConnection,
+        quote_id: Uuid,
+        user_address: &str,
+        token: &str,
+        amount: Decimal,
+    ) -> Result<Decimal, WithdrawalFeeError> {
+        let fee: Option<Decimal> = sqlx::query_scalar(
+            r#"
+            UPDATE withdrawal_quotes SET used_at = NOW()
+            WHERE id = $1 AND user_address = $2 AND token = $3 AND amount = $4
+              AND used_at IS NULL AND expires_at > NOW()
+            RETURNING fee
+            "#,
+        )
+        .bind(quote_id)
+        .bind(user_address.to_lowercase())
+        .bind(token)
+        .bind(amount)
+        .fetch_optional(conn)
+        .await?;
+        fee.ok_or(WithdrawalFeeError::InvalidQuote)
+    }
+}
+
+impl WithdrawalFeePolicy {
+    /// No fee (tokens without a policy)
+    pub fn free(token: &str) -> Self {
+        Self {
+            token: token.to_string(),
+            flat_fee: Decimal::ZERO,
+            percentage: Decimal::ZERO,
+            min_fee: None,
+            max_fee: None,
+            congestion_enabled: false,
+            updated_at: Utc::now(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn policy() -> WithdrawalFeePolicy {
+        WithdrawalFeePolicy {
+            token: "USDT".to_string(),
+            flat_fee: dec!(1),
+            percentage: dec!(0.001),
+            min_fee: Some(dec!(0.5)),
+            max_fee: Some(dec!(20)),
+            congestion_enabled: true,
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_combines_flat_percentage_and_congestion() {
+        // 0.1% of 1000 = 1, flat 1 doubled by congestion
+        let fee = compute_fee(&policy(), dec!(1000), dec!(2));
+        assert_eq!(fee.flat_fee, dec!(2));
+        assert_eq!(fee.percentage_fee, dec!(1));
+        assert_eq!(fee.fee, dec!(3));
+
+        // Percentage bounded by min and max
+        assert_eq!(compute_fee(&policy(), dec!(100), dec!(1)).percentage_fee, dec!(0.5));
+        assert_eq!(compute_fee(&policy(), dec!(100000), dec!(1)).percentage_fee, dec!(20));
+
+        // Congestion ignored when disabled
+        let fixed = WithdrawalFeePolicy { congestion_enabled: false, ..policy() };
+        assert_eq!(compute_fee(&fixed, dec!(1000), dec!(3)).fee, dec!(2));
+    }
+
+    #[test]
+    fn test_rejects_inconsistent_policies() {
+        assert!(policy().validate().is_ok());
+        assert!(WithdrawalFeePolicy { percentage: dec!(1), ..policy() }.validate().is_err());
+        assert!(WithdrawalFeePolicy { min_fee: Some(dec!(30)), ..policy() }.validate().is_err());
+        assert!(WithdrawalFeePolicy { flat_fee: dec!(-1), ..policy() }.validate().is_err());
+    }
+}
@@ -0,0 +1,405 @@
+//! Withdrawal Risk Checks
+//!
+//! Every withdrawal request is assessed before its funds are frozen:
+//!
+//! - `daily_limit` - the user's withdrawals of the token over the last 24
+//!   hours, this one included, may not exceed `withdraw_daily_limit`; such
+//!   requests are rejected outright
+//! - `large_amount` - requests of at least `withdraw_review_threshold` wait
+//!   for manual review
+//! - `deposit_velocity` - so do requests made within
+//!   `withdraw_velocity_window_secs` of a deposit of at least
+//!   `withdraw_velocity_deposit_threshold` of the token
+//!
+//! A withdrawal under review has status `pending_review`: its funds stay
+//! frozen and it can't be confirmed until an admin approves it (status
+//! `pending`) or rejects it (status `rejected`, funds unfrozen). The user can
+//! still cancel it. Every decision, automatic or manual, is recorded in
+//! `withdrawal_reviews`. A zero limit or threshold disables its check.
+
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::services::ledger::{BalanceChange, LedgerReason, LedgerService};
+
+/// Withdrawal risk errors
+#[derive(Debug, thiserror::Error)]
+pub enum WithdrawalRiskError {
+    #[error("Daily withdrawal limit exceeded: {used} already withdrawn of {limit}")]
+    DailyLimitExceeded { limit: Decimal, used: Decimal },
+
+    #[error("Withdrawal not found: {0}")]
+    NotFound(Uuid),
+
+    #[error("Withdrawal {id} is not awaiting review (status: {status})")]
+    NotUnderReview { id: Uuid, status: String },
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+/// Risk limits, from config
+#[derive(Debug, Clone)]
+pub struct RiskPolicy {
+    /// Per user and token over a rolling 24 hours; zero disables
+    pub daily_limit: Decimal,
+    /// Requests of at least this amount are reviewed; zero disables
+    pub review_threshold: Decimal,
+    /// How long after a large deposit requests are reviewed; zero disables
+    pub velocity_window_secs: u64,
+    pub velocity_deposit_threshold: Decimal,
+}
+
+/// Outcome of an assessment or review
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReviewDecision {
+    Approved,
+    Review,
+    Rejected,
+}
+
+impl ReviewDecision {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReviewDecision::Approved => "approved",
+            ReviewDecision::Review => "review",
+            ReviewDecision::Rejected => "rejected",
+        }
+    }
+
+    /// Status of a withdrawal created with this decision
+    pub fn withdrawal_status(&self) -> &'static str {
+        match self {
+            ReviewDecision::Approved => "pending",
+            ReviewDecision::Review => "pending_review",
+            ReviewDecision::Rejected => "rejected",
+        }
+    }
+}
+
+/// What the policy is checked against
+#[derive(Debug, Clone, Default)]
+pub struct RiskInputs {
+    pub amount: Decimal,
+    /// Withdrawn over the last 24 hours, excluding cancelled and rejected
+    pub withdrawn_24h: Decimal,
+    /// Largest deposit within the velocity window
+    pub recent_deposit: Decimal,
+}
+
+/// Result of checking a request against the policy
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assessment {
+    pub decision: ReviewDecision,
+    pub reasons: Vec<String>,
+}
+
+/// A recorded decision
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct WithdrawalReview {
+    pub id: Uuid,
+    pub withdrawal_id: Option<Uuid>,
+    pub user_address: String,
+    pub token: String,
+    pub amount: Decimal,
+    pub decision: String,
+    pub reasons: Vec<String>,
+    pub reviewer: Option<String>,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A withdrawal awaiting review
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct QueuedWithdrawal {
+    pub id: Uuid,
+    pub user_address: String,
+    pub token: String,
+    pub amount: Decimal,
+    pub fee: Decimal,
+    pub created_at: DateTime<Utc>,
+    /// Why it was held
+    pub reasons: Vec<String>,
+}
+
+/// Check a request against the policy
+pub fn evaluate(policy: &RiskPolicy, inputs: &RiskInputs) -> Assessment {
+    if policy.daily_limit > Decimal::ZERO && inputs.withdrawn_24h + inputs.amount > policy.daily_limit {
+        return Assessment {
+            decision: ReviewDecision::Rejected,
+            reasons: vec!["daily_limit".to_string()],
+        };
+    }
+
+    let mut reasons = Vec::new();
+    if policy.review_threshold > Decimal::ZERO && inputs.amount >= policy.review_threshold {
+        reasons.push("large_amount".to_string());
+    }
+    if policy.velocity_window_secs > 0
+        && policy.velocity_deposit_threshold > Decimal::ZERO
+        && inputs.recent_deposit >= policy.velocity_deposit_threshold
+    {
+        reasons.push("deposit_velocity".to_string());
+    }
+    let decision = if reasons.is_empty() {
+        ReviewDecision::Approved
+    } else {
+        ReviewDecision::Review
+    };
+    Assessment { decision, reasons }
+}
+
+const REVIEW_COLUMNS: &str =
+    "id, withdrawal_id, user_address, token, amount, decision, reasons, reviewer, note, created_at";
+
+/// Assesses withdrawal requests and runs the review queue
+pub struct WithdrawalRiskService;
+
+impl WithdrawalRiskService {
+    /// Assess a request inside the transaction that creates the withdrawal.
+    /// Holds a per-user lock until the transaction ends, so concurrent
+    /// requests can't both pass the daily limit.
+    pub async fn assess(
+        conn: &mut PgConnection,
+        policy: &RiskPolicy,
+        user_address: &str,
+        token: &str,
+        amount: Decimal,
+        now: DateTime<Utc>,
+    ) -> Result<(Assessment, RiskInputs), WithdrawalRiskError> {
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+            .bind(format!("withdrawals:{}", user_address))
+            .execute(&mut *conn)
+            .await?;
+
+        let withdrawn_24h: Decimal = sqlx::query_scalar(
+            r#"
+            SELECT COALESCE(SUM(amount), 0) FROM withdrawals
+            WHERE user_address = $1 AND token = $2 AND created_at > $3
+              AND status NOT IN ('cancelled', 'rejected', 'failed')
+            "#,
+        )
+        .bind(user_address)
+        .bind(token)
+        .bind(now - Duration::hours(24))
+        .fetch_one(&mut *conn)
+        .await?;
+
+        let recent_deposit = if policy.velocity_window_secs > 0 {
+            sqlx::query_scalar(
+                r#"
+                SELECT COALESCE(MAX(amount), 0) FROM deposits
+                WHERE user_address = $1 AND token = $2 AND COALESCE(credited_at, created_at) > $3
+                "#,
+            )
+            .bind(user_address)
+            .bind(token)
+            .bind(now - Duration::seconds(policy.velocity_window_secs as i64))
+            .fetch_one(&mut *conn)
+            .await?
+        } else {
+            Decimal::ZERO
+        };
+
+        let inputs = RiskInputs {
+            amount,
+            withdrawn_24h,
+            recent_deposit,
+        };
+        Ok((evaluate(policy, &inputs), inputs))
+    }
+
+    /// Record a decision
+    #[allow(clippy::too_many_arguments)]
+    pub async fn record(
+        conn: &mut PgConnection,
+        withdrawal_id: Option<Uuid>,
+        user_address: &str,
+        token: &str,
+        amount: Decimal,
+        assessment: &Assessment,
+        reviewer: Option<&str>,
+        note: Option<&str>,
+    ) -> Result<WithdrawalReview, WithdrawalRiskError> {
+        let review = sqlx::query_as(&format!(
+            r#"
+            INSERT INTO withdrawal_reviews
+                (id, withdrawal_id, user_address, token, amount, decision, reasons, reviewer, note)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING {}
+            "#,
+            REVIEW_COLUMNS
+        ))
+        .bind(Uuid::new_v4())
+        .bind(withdrawal_id)
+        .bind(user_address)
+        .bind(token)
+        .bind(amount)
+        .bind(assessment.decision.as_str())
+        .bind(&assessment.reasons)
+        .bind(reviewer.map(str::to_lowercase))
+        .bind(note)
+        .fetch_one(&mut *conn)
+        .await?;
+        Ok(review)
+    }
+
+    /// Withdrawals awaiting review, oldest first
+    pub async fn queue(pool: &PgPool, limit: i64, offset: i64) -> Result<Vec<QueuedWithdrawal>, WithdrawalRiskError> {
+        let rows = sqlx::query_as(
+            r#"
+            SELECT w.id, w.user_address, w.token, w.amount, w.fee, w.created_at,
+                   COALESCE(r.reasons, '{}') AS reasons
+            FROM withdrawals w
+            LEFT JOIN LATERAL (
+                SELECT reasons FROM withdrawal_reviews
+                WHERE withdrawal_id = w.id AND decision = 'review'
+                ORDER BY created_at DESC
+                LIMIT 1
+            ) r ON TRUE
+            WHERE w.status = 'pending_review'
+            ORDER BY w.created_at
+            LIMIT $1 OFFSET $2
+            "#,
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
+    }
+
+    /// Decisions on a withdrawal, oldest first
+    pub async fn history(pool: &PgPool, withdrawal_id: Uuid) -> Result<Vec<WithdrawalReview>, WithdrawalRiskError> {
+        let rows = sqlx::query_as(&format!(
+            "SELECT {} FROM withdrawal_reviews WHERE withdrawal_id = $1 ORDER BY created_at",
+            REVIEW_COLUMNS
+        ))
+        .bind(withdrawal_id)
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
+    }
+
+    /// Release a held withdrawal: it can be confirmed again
+    pub async fn approve(
+        pool: &PgPool,
+        withdrawal_id: Uuid,
+        reviewer: &str,
+        note: Option<&str>,
+    ) -> Result<WithdrawalReview, WithdrawalRiskError> {
+        Self::decide(pool, withdrawal_id, ReviewDecision::Approved, reviewer, note).await
+    }
+
+    /// Refuse a held withdrawal and unfreeze its funds
+    pub async fn reject(
+        pool: &PgPool,
+        withdrawal_id: Uuid,
+        reviewer: &str,
+        note: Option<&str>,
+    ) -> Result<WithdrawalReview, WithdrawalRiskError> {
+        Self::decide(pool, withdrawal_id, ReviewDecision::Rejected, reviewer, note).await
+    }
+
+    async fn decide(
+        pool: &PgPool,
+        withdrawal_id: Uuid,
+        decision: ReviewDecision,
+        reviewer: &str,
+        note: Option<&str>,
+    ) -> Result<WithdrawalReview, WithdrawalRiskError> {
+        let mut tx = pool.begin().await?;
+        let withdrawal: Option<(String, String, Decimal, String)> =
+            sqlx::query_as("SELECT user_address, token, amount, status FROM withdrawals WHERE id = $1 FOR UPDATE")
+                .bind(withdrawal_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+        let (user_address, token, amount, status) = withdrawal.ok_or(WithdrawalRiskError::NotFound(withdrawal_id))?;
+        if status != "pending_review" {
+            return Err(WithdrawalRiskError::NotUnderReview { id: withdrawal_id, status });
+        }
+
+        if decision == ReviewDecision::Rejected {
+            let change = BalanceChange::unfreeze(&user_address, &token, amount, LedgerReason::WithdrawalUnfreeze)
+                .reference(withdrawal_id);
+            LedgerService::apply(&mut tx, &change).await?;
+        }
+        sqlx::query("UPDATE withdrawals SET status = $1, updated_at = NOW() WHERE id = $2")
+            .bind(decision.withdrawal_status())
+            .bind(withdrawal_id)
+            .execute(&mut *tx)
+            .await?;
+        let assessment = Assessment {
+            decision,
+            reasons: Vec::new(),
+        };
+        let review = Self::record(&mut tx, Some(withdrawal_id), &user_address, &token, amount, &assessment, Some(reviewer), note)
+            .await?;
+        tx.commit().await?;
+
+        tracing::info!(
+            "Withdrawal {} of {} {} by {} {} by {}",
+            withdrawal_id,
+            amount,
+            token,
+            user_address,
+            decision.as_str(),
+            reviewer
+        );
+        Ok(review)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RiskPolicy {
+        RiskPolicy {
+            daily_limit: Decimal::from(50_000),
+            review_threshold: Decimal::from(10_000),
+            velocity_window_secs: 3600,
+            velocity_deposit_threshold: Decimal::from(5_000),
+        }
+    }
+
+    fn inputs(amount: i64, withdrawn_24h: i64, recent_deposit: i64) -> RiskInputs {
+        RiskInputs {
+            amount: Decimal::from(amount),
+            withdrawn_24h: Decimal::from(withdrawn_24h),
+            recent_deposit: Decimal::from(recent_deposit),
+        }
+    }
+
+    #[test]
+    fn test_evaluate_policy() {
+        let policy = policy();
+        assert_eq!(evaluate(&policy, &inputs(100, 0, 0)).decision, ReviewDecision::Approved);
+
+        let rejected = evaluate(&policy, &inputs(1_000, 49_500, 0));
+        assert_eq!(rejected.decision, ReviewDecision::Rejected);
+        assert_eq!(rejected.reasons, vec!["daily_limit"]);
+        // Exactly at the limit passes
+        assert_eq!(evaluate(&policy, &inputs(500, 49_500, 0)).decision, ReviewDecision::Approved);
+
+        let held = evaluate(&policy, &inputs(10_000, 0, 5_000));
+        assert_eq!(held.decision, ReviewDecision::Review);
+        assert_eq!(held.reasons, vec!["large_amount", "deposit_velocity"]);
+        assert_eq!(evaluate(&policy, &inputs(100, 0, 4_999)).decision, ReviewDecision::Approved);
+    }
+
+    #[test]
+    fn test_zero_disables_checks() {
+        let policy = RiskPolicy {
+            daily_limit: Decimal::ZERO,
+            review_threshold: Decimal::ZERO,
+            velocity_window_secs: 0,
+            velocity_deposit_threshold: Decimal::from(5_000),
+        };
+        assert_eq!(evaluate(&policy, &inputs(1_000_000, 1_000_000, 1_000_000)).decision, ReviewDecision::Approved);
+    }
+}
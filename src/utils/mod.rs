@@ -1,3 +1,4 @@
+pub mod request_context;
 pub mod response;
 
 // pub use response::*;
@@ -0,0 +1,34 @@
+//! Request Context
+//!
+//! The ID of the HTTP request being handled, readable from code it runs
+//! synchronously or awaits (engine, services) without threading it through
+//! every signature. Set by [`crate::api::middleware::request_id_middleware`].
+
+use std::future::Future;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Run `future` with `request_id` as the current request
+pub async fn scope<F: Future>(request_id: String, future: F) -> F::Output {
+    REQUEST_ID.scope(request_id, future).await
+}
+
+/// ID of the request the current task is handling, if any
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_request_id_is_visible_inside_scope_only() {
+        assert_eq!(current_request_id(), None);
+        let seen = scope("req-1".to_string(), async { current_request_id() }).await;
+        assert_eq!(seen.as_deref(), Some("req-1"));
+        assert_eq!(current_request_id(), None);
+    }
+}
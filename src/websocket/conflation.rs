@@ -0,0 +1,317 @@
+//! Conflated Trade Feed
+//!
+//! Clients that cannot keep up with every trade during bursts subscribe to a
+//! per-symbol trades channel with a conflation window:
+//!
+//! ```json
+//! {"type":"subscribe","channel":"trades:{symbol}","conflate_ms":250}
+//! ```
+//!
+//! Trades are aggregated centrally, once per (symbol, window), and each window
+//! that saw trades is published as a single `conflatedtrades` message (count,
+//! volume, VWAP, last price/side). Connections only forward the messages of
+//! the window they asked for; full-fidelity subscribers of the same channel
+//! keep receiving every trade and are unaffected. With feed signing enabled,
+//! conflated messages are signed on their own `trades:{symbol}@{window}ms`
+//! stream.
+//!
+//! Windows are aligned to the wall clock and limited to
+//! [`CONFLATION_WINDOWS_MS`], so aggregation cost does not grow with the
+//! number of subscribers.
+
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+use crate::services::matching::{MatchingEngine, TradeEvent};
+use crate::websocket::signing::FeedSigner;
+
+/// Accepted `conflate_ms` values
+pub const CONFLATION_WINDOWS_MS: &[u64] = &[100, 250, 500, 1000, 5000];
+
+/// How often closed windows are published
+const FLUSH_TICK_MS: u64 = 50;
+
+/// Pre-serialized conflated message for fan-out to subscribed connections
+#[derive(Debug, Clone)]
+pub struct ConflatedTradeMessage {
+    /// Subscription (`trades:{symbol}`) that receives the message
+    pub channel: String,
+    /// Conflation window the subscription asked for
+    pub window_ms: u64,
+    /// Serialized message
+    pub text: Arc<str>,
+}
+
+/// Trades of one symbol within one conflation window
+#[derive(Debug, Clone, Serialize)]
+pub struct ConflatedTrades {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub symbol: String,
+    pub window_ms: u64,
+    /// Window start (Unix millis, inclusive)
+    pub start: i64,
+    /// Window end (Unix millis, exclusive)
+    pub end: i64,
+    pub count: u64,
+    pub volume: String,
+    /// Volume-weighted average price
+    pub vwap: String,
+    pub last_price: String,
+    pub last_side: String,
+    pub last_timestamp: i64,
+    /// Execution sequence numbers of the first and last trade in the window
+    pub first_exec_id: u64,
+    pub last_exec_id: u64,
+}
+
+/// Check a requested conflation window
+pub fn validate_window(conflate_ms: u64) -> Result<u64, String> {
+    if CONFLATION_WINDOWS_MS.contains(&conflate_ms) {
+        Ok(conflate_ms)
+    } else {
+        Err(format!(
+            "Unsupported conflate_ms {}; supported windows: {:?}",
+            conflate_ms, CONFLATION_WINDOWS_MS
+        ))
+    }
+}
+
+/// Running aggregate of one window
+#[derive(Debug, Clone)]
+struct TradeAggregate {
+    start: i64,
+    count: u64,
+    volume: Decimal,
+    notional: Decimal,
+    last_price: Decimal,
+    last_side: String,
+    last_timestamp: i64,
+    first_exec_id: u64,
+    last_exec_id: u64,
+}
+
+impl TradeAggregate {
+    fn new(start: i64, trade: &TradeEvent) -> Self {
+        Self {
+            start,
+            count: 1,
+            volume: trade.amount,
+            notional: trade.amount * trade.price,
+            last_price: trade.price,
+            last_side: trade.side.clone(),
+            last_timestamp: trade.timestamp,
+            first_exec_id: trade.exec_id,
+            last_exec_id: trade.exec_id,
+        }
+    }
+
+    fn add(&mut self, trade: &TradeEvent) {
+        self.count += 1;
+        self.volume += trade.amount;
+        self.notional += trade.amount * trade.price;
+        self.last_price = trade.price;
+        self.last_side = trade.side.clone();
+        self.last_timestamp = trade.timestamp;
+        self.last_exec_id = trade.exec_id;
+    }
+
+    /// Volume-weighted average price (last price for zero volume)
+    fn vwap(&self) -> Decimal {
+        if self.volume.is_zero() {
+            self.last_price
+        } else {
+            (self.notional / self.volume).round_dp(6).normalize()
+        }
+    }
+
+    fn into_message(self, symbol: &str, window_ms: u64) -> ConflatedTrades {
+        ConflatedTrades {
+            kind: "conflatedtrades",
+            symbol: symbol.to_string(),
+            window_ms,
+            start: self.start,
+            end: self.start + window_ms as i64,
+            count: self.count,
+            volume: self.volume.to_string(),
+            vwap: self.vwap().to_string(),
+            last_price: self.last_price.to_string(),
+            last_side: self.last_side,
+            last_timestamp: self.last_timestamp,
+            first_exec_id: self.first_exec_id,
+            last_exec_id: self.last_exec_id,
+        }
+    }
+}
+
+/// Open windows of every (symbol, window) pair
+#[derive(Debug, Default)]
+pub struct Conflator {
+    open: HashMap<(String, u64), TradeAggregate>,
+    /// Windows displaced by a newer one before their flush tick
+    closed: Vec<ConflatedTrades>,
+}
+
+impl Conflator {
+    /// Add a trade received at `now_ms` to the open window of every size
+    pub fn add(&mut self, trade: &TradeEvent, now_ms: i64) {
+        for &window_ms in CONFLATION_WINDOWS_MS {
+            let start = now_ms - now_ms.rem_euclid(window_ms as i64);
+            let key = (trade.symbol.clone(), window_ms);
+            match self.open.get_mut(&key) {
+                Some(aggregate) if aggregate.start == start => aggregate.add(trade),
+                _ => {
+                    // A trade arrived between a window's end and the next flush
+                    if let Some(previous) = self.open.insert(key, TradeAggregate::new(start, trade)) {
+                        self.closed.push(previous.into_message(&trade.symbol, window_ms));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Take every window that ended at or before `now_ms`
+    pub fn flush(&mut self, now_ms: i64) -> Vec<ConflatedTrades> {
+        let ended: Vec<(String, u64)> = self
+            .open
+            .iter()
+            .filter(|(&(_, window_ms), aggregate)| aggregate.start + window_ms as i64 <= now_ms)
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        let mut messages = std::mem::take(&mut self.closed);
+        for (symbol, window_ms) in ended {
+            if let Some(aggregate) = self.open.remove(&(symbol.clone(), window_ms)) {
+                messages.push(aggregate.into_message(&symbol, window_ms));
+            }
+        }
+        messages.sort_by_key(|m| (m.start, m.window_ms));
+        messages
+    }
+}
+
+/// Aggregate engine trades into conflation windows and broadcast each closed
+/// window to WebSocket connections
+pub fn spawn_trade_conflation(
+    engine: Arc<MatchingEngine>,
+    signer: Option<Arc<FeedSigner>>,
+    sender: broadcast::Sender<ConflatedTradeMessage>,
+) {
+    let mut trades = engine.subscribe_trades();
+    tokio::spawn(async move {
+        let mut conflator = Conflator::default();
+        let mut tick = tokio::time::interval(tokio::time::Duration::from_millis(FLUSH_TICK_MS));
+        tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        loop {
+            tokio::select! {
+                trade = trades.recv() => match trade {
+                    Ok(trade) => conflator.add(&trade, chrono::Utc::now().timestamp_millis()),
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        tracing::warn!("Trade conflation lagged by {} trades", n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                _ = tick.tick() => {
+                    for message in conflator.flush(chrono::Utc::now().timestamp_millis()) {
+                        let channel = format!("trades:{}", message.symbol);
+                        let text = match &signer {
+                            Some(signer) => {
+                                let stream = format!("{}@{}ms", channel, message.window_ms);
+                                match signer.sign_message(&stream, &message) {
+                                    Ok(text) => text,
+                                    Err(e) => {
+                                        tracing::error!("Failed to sign conflated trades on {}: {}", stream, e);
+                                        continue;
+                                    }
+                                }
+                            }
+                            None => match serde_json::to_string(&message) {
+                                Ok(text) => text,
+                                Err(_) => continue,
+                            },
+                        };
+                        // No receivers just means no WebSocket clients are connected
+                        let _ = sender.send(ConflatedTradeMessage {
+                            channel,
+                            window_ms: message.window_ms,
+                            text: text.into(),
+                        });
+                    }
+                }
+            }
+        }
+        tracing::warn!("Trade conflation stopped");
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::matching::Side;
+    use rust_decimal_macros::dec;
+    use uuid::Uuid;
+
+    fn trade(symbol: &str, price: Decimal, amount: Decimal, exec_id: u64) -> TradeEvent {
+        let mut trade = TradeEvent::new(
+            symbol.to_string(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            "0xmaker".to_string(),
+            "0xtaker".to_string(),
+            Side::Buy,
+            price,
+            amount,
+            Decimal::ZERO,
+            Decimal::ZERO,
+        );
+        trade.exec_id = exec_id;
+        trade
+    }
+
+    #[test]
+    fn test_validate_window() {
+        assert_eq!(validate_window(250), Ok(250));
+        assert!(validate_window(0).is_err());
+        assert!(validate_window(333).is_err());
+    }
+
+    #[test]
+    fn test_aggregates_window() {
+        let mut conflator = Conflator::default();
+        conflator.add(&trade("s", dec!(0.50), dec!(10), 1), 10_010);
+        conflator.add(&trade("s", dec!(0.60), dec!(30), 2), 10_090);
+
+        // Nothing has closed yet
+        assert!(conflator.flush(10_099).is_empty());
+
+        let closed = conflator.flush(10_100);
+        let m = closed.iter().find(|m| m.window_ms == 100).unwrap();
+        assert_eq!((m.start, m.end, m.count), (10_000, 10_100, 2));
+        assert_eq!(m.volume, "40");
+        assert_eq!(m.vwap, "0.575");
+        assert_eq!(m.last_price, "0.60");
+        assert_eq!((m.first_exec_id, m.last_exec_id), (1, 2));
+
+        // Longer windows are still open
+        assert!(closed.iter().all(|m| m.window_ms == 100));
+    }
+
+    #[test]
+    fn test_late_flush_keeps_both_windows() {
+        let mut conflator = Conflator::default();
+        conflator.add(&trade("s", dec!(0.5), dec!(1), 1), 10_050);
+        // Next window starts before the first one was flushed
+        conflator.add(&trade("s", dec!(0.7), dec!(1), 2), 10_120);
+
+        let closed: Vec<_> = conflator.flush(10_200).into_iter().filter(|m| m.window_ms == 100).collect();
+        assert_eq!(closed.len(), 2);
+        assert_eq!((closed[0].start, closed[0].last_exec_id), (10_000, 1));
+        assert_eq!((closed[1].start, closed[1].last_exec_id), (10_100, 2));
+        assert!(closed.iter().all(|m| m.symbol == "s"));
+    }
+}
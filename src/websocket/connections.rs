@@ -0,0 +1,147 @@
+//! WebSocket Connection Limits
+//!
+//! Counts open connections per client IP and per authenticated user. A
+//! connection holds a [`ConnectionSlot`] for its IP from the upgrade on, and
+//! one for its user once it authenticates; dropping a slot frees it.
+
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::metrics;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SlotKind {
+    Ip,
+    User,
+}
+
+#[derive(Default)]
+pub struct ConnectionRegistry {
+    by_ip: Mutex<HashMap<String, usize>>,
+    by_user: Mutex<HashMap<String, usize>>,
+}
+
+impl ConnectionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take a slot for a connection from `ip`; None when `max` are open (0 = unlimited)
+    pub fn open(self: &Arc<Self>, ip: &str, max: usize) -> Option<ConnectionSlot> {
+        self.acquire(SlotKind::Ip, ip, max)
+    }
+
+    /// Take a slot for a connection authenticated as `user`; None when `max` are open
+    pub fn authenticate(self: &Arc<Self>, user: &str, max: usize) -> Option<ConnectionSlot> {
+        let slot = self.acquire(SlotKind::User, user, max);
+        if slot.is_some() {
+            self.report_authenticated();
+        }
+        slot
+    }
+
+    /// Open connections from an IP
+    pub fn ip_connections(&self, ip: &str) -> usize {
+        self.by_ip.lock().get(ip).copied().unwrap_or(0)
+    }
+
+    /// Open connections of a user
+    pub fn user_connections(&self, user: &str) -> usize {
+        self.by_user.lock().get(user).copied().unwrap_or(0)
+    }
+
+    /// Open connections with an authenticated user
+    pub fn authenticated(&self) -> usize {
+        self.by_user.lock().values().sum()
+    }
+
+    fn counts(&self, kind: SlotKind) -> &Mutex<HashMap<String, usize>> {
+        match kind {
+            SlotKind::Ip => &self.by_ip,
+            SlotKind::User => &self.by_user,
+        }
+    }
+
+    fn acquire(self: &Arc<Self>, kind: SlotKind, key: &str, max: usize) -> Option<ConnectionSlot> {
+        let mut counts = self.counts(kind).lock();
+        let count = counts.entry(key.to_string()).or_insert(0);
+        if max > 0 && *count >= max {
+            return None;
+        }
+        *count += 1;
+        Some(ConnectionSlot {
+            registry: self.clone(),
+            kind,
+            key: key.to_string(),
+        })
+    }
+
+    fn release(&self, kind: SlotKind, key: &str) {
+        let mut counts = self.counts(kind).lock();
+        if let Some(count) = counts.get_mut(key) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                counts.remove(key);
+            }
+        }
+    }
+
+    fn report_authenticated(&self) {
+        metrics::set_ws_authenticated_connections(self.authenticated() as i64);
+    }
+}
+
+/// An open connection counted against its IP or user
+pub struct ConnectionSlot {
+    registry: Arc<ConnectionRegistry>,
+    kind: SlotKind,
+    key: String,
+}
+
+impl ConnectionSlot {
+    /// The IP or user address the slot counts against
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.registry.release(self.kind, &self.key);
+        if self.kind == SlotKind::User {
+            self.registry.report_authenticated();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slots_are_capped_and_freed() {
+        let registry = Arc::new(ConnectionRegistry::new());
+        let first = registry.open("10.0.0.1", 2).unwrap();
+        let _second = registry.open("10.0.0.1", 2).unwrap();
+        assert!(registry.open("10.0.0.1", 2).is_none());
+        assert!(registry.open("10.0.0.2", 2).is_some());
+
+        drop(first);
+        assert_eq!(registry.ip_connections("10.0.0.1"), 1);
+        assert!(registry.open("10.0.0.1", 2).is_some());
+    }
+
+    #[test]
+    fn test_user_slots() {
+        let registry = Arc::new(ConnectionRegistry::new());
+        let slot = registry.authenticate("0xabc", 1).unwrap();
+        assert!(registry.authenticate("0xabc", 1).is_none());
+        assert!(registry.authenticate("0xabc", 0).is_some());
+        assert_eq!(registry.authenticated(), 1);
+
+        drop(slot);
+        assert_eq!(registry.user_connections("0xabc"), 0);
+        assert_eq!(registry.authenticated(), 0);
+    }
+}
@@ -2,64 +2,45 @@
 //!
 //! Phase 11: Complete WebSocket with proper authentication and real-time updates
 
-use axum::extract::ws::{Message, WebSocket};
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket};
 use futures::{SinkExt, StreamExt};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
 use std::sync::atomic::{AtomicI64, Ordering};
 use std::sync::Arc;
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::{Duration, Instant};
 use uuid::Uuid;
 
 use crate::auth::eip712::{verify_ws_auth_signature, WebSocketAuthMessage};
-use crate::auth::jwt::validate_token;
+use crate::auth::server_time::validate_request_timestamp;
+use crate::cache::orderbook_cache::PriceLevel as CachedLevel;
 use crate::metrics;
+use crate::services::features::FeatureService;
+use crate::services::market::ticker::book_ticker;
 #[allow(unused_imports)]
-use crate::services::matching::OrderbookUpdate;
+use crate::services::matching::{
+    book_checksum, group_levels, validate_group, ForcedTrade, OrderbookUpdate, Side, TradeEvent,
+};
+use crate::services::notifications::{AdlNotice, LiquidationNotice, MarginCallNotice, Notification, NotificationEvent};
+use crate::services::position::PositionMargin;
 use crate::AppState;
 
+use super::conflation;
+use super::connections::ConnectionSlot;
+use super::subscription::{PushOutcome, QueuePolicy, SubscriptionManager};
+
 /// Global WebSocket connection counter
 static WS_CONNECTION_COUNT: AtomicI64 = AtomicI64::new(0);
 
-/// Normalize symbol format to backend format (BTCUSDT)
-/// Supports multiple input formats:
-/// - "BTCUSDT" -> "BTCUSDT" (already correct)
-/// - "BTC-USD" -> "BTCUSDT" (frontend TradingView format)
-/// - "BTC-USDT" -> "BTCUSDT"
-/// - "btcusdt" -> "BTCUSDT" (lowercase)
-fn normalize_symbol(symbol: &str) -> String {
-    let upper = symbol.to_uppercase();
-    
-    // If already in BTCUSDT format (no separators), return as is
-    if !upper.contains('-') && !upper.contains('/') && !upper.contains('_') {
-        return upper;
-    }
-    
-    // Handle BTC-USD format (convert to BTCUSDT)
-    if upper.ends_with("-USD") {
-        let base = upper.strip_suffix("-USD").unwrap_or(&upper);
-        return format!("{}USDT", base);
-    }
-    
-    // Handle BTC-USDT format (convert to BTCUSDT)
-    if upper.contains("-USDT") {
-        return upper.replace("-", "");
-    }
-    
-    // Handle BTC/USD or BTC_USD formats
-    if upper.contains("/") || upper.contains("_") {
-        let cleaned = upper.replace("/", "").replace("_", "");
-        if !cleaned.ends_with("USDT") && cleaned.ends_with("USD") {
-            let base = cleaned.strip_suffix("USD").unwrap_or(&cleaned);
-            return format!("{}USDT", base);
-        }
-        return cleaned;
-    }
-    
-    // Default: return uppercase version
-    upper
-}
+/// Frames buffered between the connection loop and the socket writer
+const WRITER_BUFFER: usize = 64;
+
+/// Pending message count at which a connection is logged as slow
+const SLOW_CLIENT_WARN_DEPTH: usize = 500;
+
+/// Maximum channels accepted in one subscribe message
+const MAX_SUBSCRIBE_BATCH: usize = 100;
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
@@ -74,19 +55,41 @@ pub enum ClientMessage {
         timestamp: Option<u64>,
         #[serde(default)]
         token: Option<String>,
+        /// Server time token from GET /time (for clients with skewed clocks)
+        #[serde(default)]
+        timestamp_token: Option<String>,
     },
     /// Authenticate with JWT token (alternative to signature auth)
     AuthToken {
         token: String,
     },
+    /// Subscribe to one channel (`channel`) or many at once (`channels`)
     Subscribe {
-        channel: String,
+        #[serde(default)]
+        channel: Option<String>,
+        #[serde(default)]
+        channels: Vec<String>,
         #[serde(default)]
         token: Option<String>,
+        /// Conflate `trades:{symbol}` into one message per window of this many ms
+        #[serde(default)]
+        conflate_ms: Option<u64>,
+        /// Merge `orderbook:{symbol}` levels into price buckets of this size
+        #[serde(default)]
+        group: Option<Decimal>,
     },
     Unsubscribe {
         channel: String,
     },
+    /// Request a fresh snapshot for a `book:` channel (after a sequence gap)
+    Resync {
+        channel: String,
+    },
+    /// Arm the dead man's switch: resting orders are cancelled unless a ping
+    /// (or another `cancelallafter`) arrives within `timeout_ms`; 0 disarms
+    CancelAllAfter {
+        timeout_ms: u64,
+    },
     Ping,
 }
 
@@ -101,16 +104,29 @@ pub enum ServerMessage {
     Subscribed {
         channel: String,
     },
+    /// Ack for a multi-channel subscribe
+    SubscribedBatch {
+        accepted: Vec<String>,
+        rejected: Vec<RejectedChannel>,
+    },
     Unsubscribed {
         channel: String,
     },
     Trade {
         id: String,
+        /// Trade event schema version
+        version: u16,
         symbol: String,
         price: String,
         amount: String,
         side: String,
+        /// Side that took liquidity (None for block trades)
+        aggressor: Option<String>,
+        /// Forced on the taker by a liquidation or ADL
+        forced: Option<ForcedTrade>,
         timestamp: i64,
+        /// Engine execution sequence number (consecutive across all trades)
+        exec_id: u64,
     },
     Orderbook {
         symbol: String,
@@ -118,33 +134,37 @@ pub enum ServerMessage {
         asks: Vec<OrderbookLevel>,
         timestamp: i64,
     },
+    /// Pushed every 2 seconds on `ticker:{symbol}`
     Ticker {
         symbol: String,
-        last_price: String,
-        mark_price: String,
-        index_price: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        last_price: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        mark_price: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        index_price: Option<String>,
+        /// Rolling 24h statistics (zero without trades in the window)
         price_change_24h: String,
         price_change_percent_24h: String,
         high_24h: String,
         low_24h: String,
         volume_24h: String,
         volume_24h_usd: String,
-        /// Open Interest - Long position value in USD
-        open_interest_long: String,
-        /// Open Interest - Short position value in USD
-        open_interest_short: String,
-        /// Open Interest - Long percentage (e.g., "58")
-        open_interest_long_percent: String,
-        /// Open Interest - Short percentage (e.g., "42")
-        open_interest_short_percent: String,
-        /// Available liquidity for long positions
-        available_liquidity_long: String,
-        /// Available liquidity for short positions
-        available_liquidity_short: String,
-        /// Funding rate for long positions per hour (negative = pay)
-        funding_rate_long_1h: String,
-        /// Funding rate for short positions per hour (negative = pay)
-        funding_rate_short_1h: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        best_bid: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        best_ask: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        spread: Option<String>,
+        /// Mid of the top `ticker_book_levels` levels, leaning towards the thinner side
+        #[serde(skip_serializing_if = "Option::is_none")]
+        depth_weighted_mid: Option<String>,
+        bid_depth: String,
+        ask_depth: String,
+        /// (bid depth - ask depth) / total depth, from -1 to 1
+        #[serde(skip_serializing_if = "Option::is_none")]
+        imbalance: Option<String>,
+        timestamp: i64,
     },
     Position {
         id: String,
@@ -160,6 +180,8 @@ pub enum ServerMessage {
         updated_at: i64,
         #[serde(skip_serializing_if = "Option::is_none")]
         event: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        margin_mode: Option<String>,
     },
     Order {
         id: String,
@@ -186,6 +208,28 @@ pub enum ServerMessage {
         message: String,
     },
     Pong,
+    /// Own position liquidated (`notifications` channel)
+    LiquidationEvent {
+        #[serde(flatten)]
+        notice: LiquidationNotice,
+    },
+    /// Own position auto-deleveraged (`notifications` channel)
+    AdlEvent {
+        #[serde(flatten)]
+        notice: AdlNotice,
+    },
+    /// Own margin ratio reached a margin call step (`notifications` channel)
+    MarginCallEvent {
+        #[serde(flatten)]
+        notice: MarginCallNotice,
+    },
+    /// Dead man's switch state (ack for `cancelallafter`)
+    CancelAllAfter {
+        armed: bool,
+        timeout_ms: u64,
+        /// When resting orders are cancelled unless re-armed (ms)
+        trigger_at: Option<i64>,
+    },
     /// K-line update
     Kline {
         channel: String,
@@ -202,6 +246,8 @@ pub enum ServerMessage {
     /// Trade event for prediction markets
     MarketTrade {
         id: String,
+        /// Trade event schema version
+        version: u16,
         market_id: String,
         outcome_id: String,
         share_type: String,
@@ -210,6 +256,14 @@ pub enum ServerMessage {
         amount: String,
         side: String,
         timestamp: i64,
+        /// Privately matched RFQ trade
+        is_block_trade: bool,
+        /// Side that took liquidity (None for block trades)
+        aggressor: Option<String>,
+        /// Forced on the taker by a liquidation or ADL
+        forced: Option<ForcedTrade>,
+        /// Engine execution sequence number (consecutive across all trades)
+        exec_id: u64,
     },
     /// Orderbook update for prediction markets
     MarketOrderbook {
@@ -220,6 +274,27 @@ pub enum ServerMessage {
         asks: Vec<OrderbookLevel>,
         timestamp: i64,
     },
+    /// Full L2 book for a `book:` channel (sent on subscribe and resync)
+    BookSnapshot {
+        symbol: String,
+        sequence: u64,
+        bids: Vec<OrderbookLevel>,
+        asks: Vec<OrderbookLevel>,
+        /// CRC32 of the top 25 levels (see [`crate::services::matching::book_checksum`])
+        checksum: u32,
+        timestamp: i64,
+    },
+    /// Changed L2 levels since `prev_sequence` (size "0" = level removed)
+    BookDelta {
+        symbol: String,
+        sequence: u64,
+        prev_sequence: u64,
+        bids: Vec<OrderbookLevel>,
+        asks: Vec<OrderbookLevel>,
+        /// Checksum of the book once the delta is applied; on a mismatch resubscribe
+        checksum: u32,
+        timestamp: i64,
+    },
     /// Market status/probability update
     MarketUpdate {
         market_id: String,
@@ -241,6 +316,14 @@ pub enum ServerMessage {
     },
 }
 
+/// Channel rejected by a batch subscribe
+#[derive(Debug, Serialize, Clone)]
+pub struct RejectedChannel {
+    pub channel: String,
+    pub code: String,
+    pub message: String,
+}
+
 /// Orderbook level for WebSocket (frontend compatible format)
 #[derive(Debug, Serialize, Clone)]
 pub struct OrderbookLevel {
@@ -264,6 +347,61 @@ pub struct KlineData {
     pub is_final: bool,
 }
 
+/// Convert `[price, size]` pairs to WebSocket levels
+fn to_levels(levels: &[[String; 2]]) -> Vec<OrderbookLevel> {
+    levels
+        .iter()
+        .map(|[price, size]| OrderbookLevel { price: price.clone(), size: size.clone() })
+        .collect()
+}
+
+/// `[price, size]` pairs of cached orderbook levels
+fn cached_levels(levels: &[CachedLevel]) -> Vec<[String; 2]> {
+    levels
+        .iter()
+        .map(|level| [level.price.to_string(), level.amount.to_string()])
+        .collect()
+}
+
+/// Convert `[price, size]` pairs to WebSocket levels, merged into price
+/// buckets when the channel is grouped
+fn to_grouped_levels(levels: &[[String; 2]], side: Side, group: Option<Decimal>) -> Vec<OrderbookLevel> {
+    match group {
+        Some(group) => to_levels(&group_levels(levels, side, group, usize::MAX)),
+        None => to_levels(levels),
+    }
+}
+
+/// Enqueue a sequenced book snapshot, superseding any pending deltas for the symbol
+fn queue_book_snapshot(state: &Arc<AppState>, conn: &mut SubscriptionManager, symbol: &str) {
+    let msg = match state.matching_engine.get_book_snapshot(symbol) {
+        Ok((sequence, snapshot)) => {
+            conn.set_book_sequence(symbol, sequence);
+            ServerMessage::BookSnapshot {
+                symbol: symbol.to_string(),
+                sequence,
+                bids: to_levels(&snapshot.bids),
+                asks: to_levels(&snapshot.asks),
+                checksum: book_checksum(&snapshot.bids, &snapshot.asks),
+                timestamp: snapshot.timestamp,
+            }
+        }
+        Err(_) => {
+            // Book does not exist yet; the first delta will carry every level
+            conn.set_book_sequence(symbol, 0);
+            ServerMessage::BookSnapshot {
+                symbol: symbol.to_string(),
+                sequence: 0,
+                bids: vec![],
+                asks: vec![],
+                checksum: book_checksum(&[], &[]),
+                timestamp: chrono::Utc::now().timestamp_millis(),
+            }
+        }
+    };
+    conn.replace(&format!("book:{}", symbol), QueuePolicy::Resync, &msg);
+}
+
 /// Validate timestamp (within 5 minutes)
 #[allow(dead_code)]
 fn validate_timestamp(timestamp: u64) -> bool {
@@ -274,7 +412,11 @@ fn validate_timestamp(timestamp: u64) -> bool {
     now.abs_diff(timestamp) <= 300
 }
 
-pub async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
+/// Serve an upgraded connection; `_ip_slot` holds its place in the per-IP cap
+pub async fn handle_socket(socket: WebSocket, state: Arc<AppState>, _ip_slot: ConnectionSlot) {
+    // Shutdown waits for the close frame to go out
+    let _shutdown_guard = state.shutdown.track();
+
     // Track WebSocket connection
     let connection_count = WS_CONNECTION_COUNT.fetch_add(1, Ordering::SeqCst) + 1;
     metrics::set_ws_connections(connection_count);
@@ -282,14 +424,32 @@ pub async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
 
     let (mut sender, mut receiver) = socket.split();
 
+    // Dedicated writer task: the connection loop never awaits the socket, so a slow
+    // client backs up its own queues instead of lagging the broadcast receivers
+    let (out_tx, mut out_rx) = mpsc::channel::<Message>(WRITER_BUFFER);
+    let writer = tokio::spawn(async move {
+        while let Some(message) = out_rx.recv().await {
+            if sender.send(message).await.is_err() {
+                break;
+            }
+            metrics::record_ws_message_sent();
+        }
+    });
+
     let mut authenticated = false;
     let mut user_address: Option<String> = None;
-    let mut subscriptions: HashSet<String> = HashSet::new();
+    let mut conn = SubscriptionManager::default();
 
     // Subscribe to trade events from matching engine
     let mut trade_receiver = state.matching_engine.subscribe_trades();
     tracing::info!("📡 WebSocket subscribed to trade events from matching engine");
 
+    // Subscribe to the signed trade feed (only carries messages when feed signing is enabled)
+    let mut signed_feed_receiver = state.signed_feed_sender.subscribe();
+
+    // Subscribe to conflated trade windows (`conflate_ms` subscriptions)
+    let mut conflated_receiver = state.conflated_trades_sender.subscribe();
+
     // Subscribe to orderbook updates from matching engine
     let mut orderbook_receiver = state.matching_engine.subscribe_orderbook();
     tracing::info!("📡 WebSocket subscribed to orderbook events from matching engine");
@@ -298,19 +458,88 @@ pub async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
     let mut order_update_receiver = state.order_update_sender.subscribe();
     tracing::info!("📡 WebSocket subscribed to order update events");
 
+    // Subscribe to position update events (margin mode switches)
+    let mut position_update_receiver = state.position_update_sender.subscribe();
+
+    // Subscribe to committed balance / holding / order changes (private channels)
+    let mut balance_receiver = state.user_events.balances.subscribe();
+    let mut holding_receiver = state.user_events.positions.subscribe();
+    let mut order_event_receiver = state.user_events.orders.subscribe();
+
+    // Subscribe to liquidation / ADL notices (delivered to the affected account)
+    let mut notification_receiver = state.notification_sender.subscribe();
+
+    // Subscribe to RFQ notifications (delivered to their market maker / taker recipients)
+    let mut rfq_receiver = state.rfq_sender.subscribe();
+
+    // Subscribe to funding payments (delivered to the position owner)
+    let mut funding_receiver = state.funding_sender.subscribe();
+
+    // Subscribe to trading session opens/closes
+    let mut session_receiver = state.session_sender.subscribe();
+
+    // Subscribe to published end-of-day settlement prices
+    let mut settlement_price_receiver = state.settlement_prices.subscribe();
+
     // Ticker update interval (every 2 seconds)
     let mut ticker_interval = tokio::time::interval(tokio::time::Duration::from_secs(2));
 
     // Orderbook update interval (every 500ms for real-time feel)
     let mut orderbook_interval = tokio::time::interval(tokio::time::Duration::from_millis(500));
 
-    // Position/balance update interval for authenticated users (every 5 seconds)
-    let mut private_interval = tokio::time::interval(tokio::time::Duration::from_secs(5));
+    // Server pings; a connection that sends nothing (pongs included) for the idle timeout is closed
+    let live = state.live_config.current();
+    let ping_every = Duration::from_secs(live.ws_ping_interval_secs.max(1));
+    let idle_timeout = Duration::from_secs(live.ws_idle_timeout_secs.max(1));
+    let mut ping_interval = tokio::time::interval_at(Instant::now() + ping_every, ping_every);
+    let mut last_heard = Instant::now();
+
+    // Place in the authenticated user's connection cap
+    let mut user_slot: Option<ConnectionSlot> = None;
 
+    let mut close_frame: Option<CloseFrame<'static>> = None;
     loop {
         tokio::select! {
+            // Server shutting down: say goodbye with a close frame
+            _ = state.shutdown.draining() => {
+                close_frame = Some(CloseFrame {
+                    code: close_code::AWAY,
+                    reason: "server shutting down".into(),
+                });
+                break;
+            }
+
+            // Keepalive: ping the client, or close the connection once it stops answering
+            _ = ping_interval.tick() => {
+                if last_heard.elapsed() >= idle_timeout {
+                    tracing::info!("Closing idle WebSocket {:?}: nothing received for {:?}", user_address, last_heard.elapsed());
+                    metrics::record_ws_connection_closed("idle_timeout");
+                    close_frame = Some(CloseFrame {
+                        code: close_code::POLICY,
+                        reason: "idle timeout".into(),
+                    });
+                    break;
+                }
+                conn.push_control_frame(Message::Ping(Vec::new()));
+            }
+
+            // Drain queued messages into the writer as it frees up
+            permit = out_tx.reserve(), if conn.has_pending() => {
+                match permit {
+                    Ok(permit) => {
+                        if let Some(message) = conn.pop() {
+                            permit.send(message);
+                        }
+                    }
+                    Err(_) => break, // Writer exited (socket closed)
+                }
+            }
+
             // Handle incoming client messages
             msg = receiver.next() => {
+                if let Some(Ok(_)) = &msg {
+                    last_heard = Instant::now();
+                }
                 match msg {
                     Some(Ok(Message::Text(text))) => {
                         metrics::record_ws_message_received();
@@ -318,16 +547,35 @@ pub async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
                             &text,
                             &mut authenticated,
                             &mut user_address,
-                            &mut subscriptions,
+                            &mut conn,
                             &state,
-                            &mut sender,
                         ).await {
-                            let _ = sender.send(Message::Text(serde_json::to_string(&response).unwrap())).await;
-                            metrics::record_ws_message_sent();
+                            conn.push_control(&response);
+                        }
+
+                        // A login takes a place in the user's connection cap
+                        let slot_user = user_slot.as_ref().map(ConnectionSlot::key);
+                        if authenticated && user_address.as_deref() != slot_user {
+                            drop(user_slot.take());
+                            let address = user_address.clone().unwrap_or_default();
+                            let max_per_user = state.live_config.current().ws_max_connections_per_user;
+                            user_slot = state.ws_connections.authenticate(&address, max_per_user);
+                            if user_slot.is_none() {
+                                tracing::warn!("WebSocket closed for {}: {} connections open", address, max_per_user);
+                                metrics::record_ws_connection_closed("user_limit");
+                                close_frame = Some(CloseFrame {
+                                    code: close_code::POLICY,
+                                    reason: "too many connections for this user".into(),
+                                });
+                                break;
+                            }
                         }
                     }
                     Some(Ok(Message::Ping(data))) => {
-                        let _ = sender.send(Message::Pong(data)).await;
+                        if let Some(address) = user_address.as_deref().filter(|_| authenticated) {
+                            state.dead_man.heartbeat(address);
+                        }
+                        conn.push_control_frame(Message::Pong(data));
                     }
                     Some(Ok(Message::Close(_))) | None => {
                         break;
@@ -345,6 +593,8 @@ pub async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
             // Handle trade events from matching engine (prediction market trades)
             trade = trade_receiver.recv() => {
                 match trade {
+                    // Signed feed enabled: trades arrive pre-signed on the signed feed instead
+                    Ok(_) if state.feed_signer.is_some() => {}
                     Ok(trade_event) => {
                         // Generate unique trade ID
                         let trade_id = format!("{}-{}", trade_event.timestamp, Uuid::new_v4().to_string().split('-').next().unwrap_or("0"));
@@ -355,46 +605,30 @@ pub async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
                         let market_trade_channel = format!("trades:{}", market_id);
                         let market_channel = format!("market:{}", market_id);
 
-                        let subscribed_to_market = subscriptions.contains(&market_trade_channel)
-                            || subscriptions.contains(&market_channel)
-                            || subscriptions.contains("trades:*");
+                        let subscribed_to_market = conn.is_subscribed(&market_trade_channel)
+                            || conn.is_subscribed(&market_channel)
+                            || conn.is_subscribed("trades:*");
 
                         if subscribed_to_market {
-                            tracing::info!(
-                                "✅ Sending market trade: market_id={}, match_type={}",
+                            tracing::debug!(
+                                "✅ Queueing market trade: market_id={}, match_type={}",
                                 market_id, trade_event.match_type
                             );
 
-                            let msg = ServerMessage::MarketTrade {
-                                id: trade_id.clone(),
-                                market_id: market_id.clone(),
-                                outcome_id: trade_event.outcome_id.to_string(),
-                                share_type: trade_event.share_type.to_string(),
-                                match_type: trade_event.match_type.to_string().to_lowercase(),
-                                price: trade_event.price.to_string(),
-                                amount: trade_event.amount.to_string(),
-                                side: trade_event.side.clone(),
-                                timestamp: trade_event.timestamp,
-                            };
-                            let _ = sender.send(Message::Text(serde_json::to_string(&msg).unwrap())).await;
+                            let msg = market_trade_message(&trade_event, trade_id.clone());
+                            conn.push(&market_trade_channel, QueuePolicy::DropOldest, &msg);
                         }
 
                         // Also check legacy symbol-based channel (backwards compatibility)
                         let symbol_channel = format!("trades:{}", trade_event.symbol);
-                        if subscriptions.contains(&symbol_channel) {
-                            let msg = ServerMessage::Trade {
-                                id: trade_id,
-                                symbol: trade_event.symbol.clone(),
-                                price: trade_event.price.to_string(),
-                                amount: trade_event.amount.to_string(),
-                                side: trade_event.side.clone(),
-                                timestamp: trade_event.timestamp,
-                            };
-                            let _ = sender.send(Message::Text(serde_json::to_string(&msg).unwrap())).await;
+                        if conn.is_subscribed_full(&symbol_channel) {
+                            let msg = legacy_trade_message(&trade_event, trade_id);
+                            conn.push(&symbol_channel, QueuePolicy::DropOldest, &msg);
                         }
                     }
                     Err(broadcast::error::RecvError::Lagged(n)) => {
                         tracing::warn!("⚠️  Trade receiver lagged by {} messages - some trades may have been missed!", n);
+                        metrics::record_ws_messages_dropped("trades", "broadcast_lag", n);
                     }
                     Err(broadcast::error::RecvError::Closed) => {
                         tracing::error!("❌ Trade receiver closed - no more trade events will be received");
@@ -403,23 +637,48 @@ pub async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
                 }
             }
 
+            // Handle pre-signed market data (signed feed mode)
+            signed = signed_feed_receiver.recv() => {
+                match signed {
+                    Ok(signed) => {
+                        if signed.channels.iter().any(|c| conn.is_subscribed_full(c)) {
+                            conn.push_text(&signed.stream, QueuePolicy::DropOldest, signed.text.to_string());
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        metrics::record_ws_messages_dropped("trades", "broadcast_lag", n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {}
+                }
+            }
+
+            // Handle conflated trade windows (only the window the client asked for)
+            conflated = conflated_receiver.recv() => {
+                match conflated {
+                    Ok(conflated) => {
+                        if conn.conflation(&conflated.channel) == Some(conflated.window_ms) {
+                            conn.push_text(&conflated.channel, QueuePolicy::DropOldest, conflated.text.to_string());
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        metrics::record_ws_messages_dropped("trades", "broadcast_lag", n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {}
+                }
+            }
+
             // Handle orderbook updates from matching engine
             orderbook = orderbook_receiver.recv() => {
                 match orderbook {
                     Ok(orderbook_update) => {
-                        // Convert to frontend-compatible format
-                        let bids: Vec<OrderbookLevel> = orderbook_update.bids
-                            .iter()
-                            .map(|[price, size]| OrderbookLevel { price: price.clone(), size: size.clone() })
-                            .collect();
-                        let asks: Vec<OrderbookLevel> = orderbook_update.asks
-                            .iter()
-                            .map(|[price, size]| OrderbookLevel { price: price.clone(), size: size.clone() })
-                            .collect();
-
                         // Symbol format for prediction markets: {market_id}:{outcome_id}:{share_type}
                         let symbol = &orderbook_update.symbol;
 
+                        // Convert to frontend-compatible format, grouped as the symbol's channel asked
+                        let group = conn.grouping(&format!("orderbook:{}", symbol));
+                        let bids = to_grouped_levels(&orderbook_update.bids, Side::Buy, group);
+                        let asks = to_grouped_levels(&orderbook_update.asks, Side::Sell, group);
+
                         // Check for prediction market orderbook channel
                         // Channels: "orderbook:{market_id}:{outcome_id}:{share_type}", "orderbook:{market_id}", "market:{market_id}"
                         let parts: Vec<&str> = symbol.split(':').collect();
@@ -433,10 +692,10 @@ pub async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
                             let market_ob_channel = format!("orderbook:{}", market_id);
                             let market_channel = format!("market:{}", market_id);
 
-                            let subscribed = subscriptions.contains(&specific_channel)
-                                || subscriptions.contains(&market_ob_channel)
-                                || subscriptions.contains(&market_channel)
-                                || subscriptions.contains("orderbook:*");
+                            let subscribed = conn.is_subscribed(&specific_channel)
+                                || conn.is_subscribed(&market_ob_channel)
+                                || conn.is_subscribed(&market_channel)
+                                || conn.is_subscribed("orderbook:*");
 
                             if subscribed {
                                 let msg = ServerMessage::MarketOrderbook {
@@ -447,24 +706,58 @@ pub async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
                                     asks: asks.clone(),
                                     timestamp: orderbook_update.timestamp,
                                 };
-                                let _ = sender.send(Message::Text(serde_json::to_string(&msg).unwrap())).await;
+                                conn.push(&format!("marketorderbook:{}", symbol), QueuePolicy::KeepLatest, &msg);
                             }
                         }
 
                         // Also check legacy symbol-based channel (backwards compatibility)
                         let orderbook_channel = format!("orderbook:{}", symbol);
-                        if subscriptions.contains(&orderbook_channel) {
+                        if conn.is_subscribed(&orderbook_channel) {
                             let msg = ServerMessage::Orderbook {
                                 symbol: symbol.clone(),
                                 bids,
                                 asks,
                                 timestamp: orderbook_update.timestamp,
                             };
-                            let _ = sender.send(Message::Text(serde_json::to_string(&msg).unwrap())).await;
+                            conn.push(&orderbook_channel, QueuePolicy::KeepLatest, &msg);
+                        }
+
+                        // Sequenced L2 delta channel: "book:{market_id}:{outcome_id}:{share_type}"
+                        if let Some(last_sequence) = conn.book_sequence(symbol) {
+                            if orderbook_update.sequence <= last_sequence {
+                                // Already reflected in the snapshot we queued
+                            } else if orderbook_update.prev_sequence != last_sequence {
+                                tracing::debug!(
+                                    "Book sequence gap for {}: expected {}, got {}",
+                                    symbol, last_sequence, orderbook_update.prev_sequence
+                                );
+                                queue_book_snapshot(&state, &mut conn, symbol);
+                            } else {
+                                conn.set_book_sequence(symbol, orderbook_update.sequence);
+                                let msg = ServerMessage::BookDelta {
+                                    symbol: symbol.clone(),
+                                    sequence: orderbook_update.sequence,
+                                    prev_sequence: orderbook_update.prev_sequence,
+                                    bids: to_levels(&orderbook_update.bid_changes),
+                                    asks: to_levels(&orderbook_update.ask_changes),
+                                    checksum: orderbook_update.checksum,
+                                    timestamp: orderbook_update.timestamp,
+                                };
+                                let book_channel = format!("book:{}", symbol);
+                                if conn.push(&book_channel, QueuePolicy::Resync, &msg) == PushOutcome::NeedsResync {
+                                    // Client is too slow for deltas; start over from a snapshot
+                                    queue_book_snapshot(&state, &mut conn, symbol);
+                                }
+                            }
                         }
                     }
                     Err(broadcast::error::RecvError::Lagged(n)) => {
                         tracing::warn!("Orderbook receiver lagged by {} messages", n);
+                        metrics::record_ws_messages_dropped("orderbook", "broadcast_lag", n);
+                        // Deltas were dropped; resync every sequenced book from a fresh snapshot
+                        for symbol in conn.book_symbols() {
+                            queue_book_snapshot(&state, &mut conn, &symbol);
+                        }
                     }
                     Err(broadcast::error::RecvError::Closed) => {
                         // Continue without orderbook updates
@@ -479,7 +772,7 @@ pub async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
                         // Only send to the user who owns this order
                         if authenticated && user_address.is_some() {
                             let addr = user_address.as_ref().unwrap().to_lowercase();
-                            if addr == event.user_address && subscriptions.contains("orders") {
+                            if addr == event.user_address && conn.is_subscribed("orders") {
                                 tracing::info!(
                                     "📤 Sending real-time order update to {}: order_id={}, status={:?}",
                                     addr, event.order.order_id, event.order.status
@@ -489,12 +782,13 @@ pub async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
                                     "type": "order_update",
                                     "data": event.order
                                 });
-                                let _ = sender.send(Message::Text(serde_json::to_string(&msg).unwrap())).await;
+                                conn.push("orders", QueuePolicy::DropOldest, &msg);
                             }
                         }
                     }
                     Err(broadcast::error::RecvError::Lagged(n)) => {
                         tracing::warn!("Order update receiver lagged by {} messages", n);
+                        metrics::record_ws_messages_dropped("orders", "broadcast_lag", n);
                     }
                     Err(broadcast::error::RecvError::Closed) => {
                         // Continue without order updates
@@ -502,84 +796,275 @@ pub async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
                 }
             }
 
-            // Ticker updates - simplified for prediction markets
+            // Handle position updates (real-time push when a position's margin mode changes)
+            position_update = position_update_receiver.recv() => {
+                match position_update {
+                    Ok(event) => {
+                        if let Some(addr) = user_address.as_ref().filter(|_| authenticated) {
+                            if addr.to_lowercase() == event.user_address && conn.is_subscribed("positions") {
+                                let msg = position_message(&event.position, "margin_mode_changed");
+                                conn.push("positions", QueuePolicy::DropOldest, &msg);
+                            }
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        tracing::warn!("Position update receiver lagged by {} messages", n);
+                        metrics::record_ws_messages_dropped("positions", "broadcast_lag", n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        // Continue without position updates
+                    }
+                }
+            }
+
+            // Handle funding payments applied to the user's positions
+            funding_event = funding_receiver.recv() => {
+                match funding_event {
+                    Ok(event) => {
+                        if let Some(addr) = user_address.as_ref().filter(|_| authenticated) {
+                            if addr.to_lowercase() == event.user_address && conn.is_subscribed("positions") {
+                                let msg = serde_json::json!({
+                                    "channel": "positions",
+                                    "type": "funding",
+                                    "data": event.settlement
+                                });
+                                conn.push("positions", QueuePolicy::DropOldest, &msg);
+                            }
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        tracing::warn!("Funding receiver lagged by {} messages", n);
+                        metrics::record_ws_messages_dropped("positions", "broadcast_lag", n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        // Continue without funding notifications
+                    }
+                }
+            }
+
+            // Handle trading session announcements
+            // Channels: "sessions", "market:{market_id}"
+            session_event = session_receiver.recv() => {
+                match session_event {
+                    Ok(event) => {
+                        let market_channel = format!("market:{}", event.market_id);
+                        if conn.is_subscribed("sessions") || conn.is_subscribed(&market_channel) {
+                            let msg = serde_json::json!({
+                                "channel": "sessions",
+                                "type": "session",
+                                "data": event
+                            });
+                            conn.push("sessions", QueuePolicy::DropOldest, &msg);
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        tracing::warn!("Session receiver lagged by {} messages", n);
+                        metrics::record_ws_messages_dropped("sessions", "broadcast_lag", n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        // Continue without session announcements
+                    }
+                }
+            }
+
+            // Handle end-of-day settlement prices
+            // Channels: "settlement_prices:{symbol}", "settlement_prices:*"
+            settlement_price = settlement_price_receiver.recv() => {
+                match settlement_price {
+                    Ok(price) => {
+                        let channel = format!("settlement_prices:{}", price.symbol);
+                        if conn.is_subscribed(&channel) || conn.is_subscribed("settlement_prices:*") {
+                            let msg = serde_json::json!({
+                                "channel": channel,
+                                "type": "settlement_price",
+                                "data": price
+                            });
+                            conn.push(&channel, QueuePolicy::DropOldest, &msg);
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        metrics::record_ws_messages_dropped("settlement_prices", "broadcast_lag", n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {}
+                }
+            }
+
+            // Handle liquidation / ADL / margin call notices for the user's own positions
+            notification = notification_receiver.recv() => {
+                match notification {
+                    Ok(event) => {
+                        if let Some(addr) = user_address.as_deref().filter(|_| authenticated) {
+                            if conn.is_subscribed("notifications") {
+                                if let Some(msg) = notification_message(event, addr) {
+                                    conn.push("notifications", QueuePolicy::DropOldest, &msg);
+                                }
+                            }
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        tracing::warn!("Notification receiver lagged by {} messages", n);
+                        metrics::record_ws_messages_dropped("notifications", "broadcast_lag", n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        // Continue without notifications
+                    }
+                }
+            }
+
+            // Handle RFQ notifications (quote requests for market makers, results for participants)
+            rfq_event = rfq_receiver.recv() => {
+                match rfq_event {
+                    Ok(event) => {
+                        if let Some(addr) = user_address.as_ref().filter(|_| authenticated) {
+                            let addr = addr.to_lowercase();
+                            if conn.is_subscribed("rfq") && event.recipients.contains(&addr) {
+                                let msg = serde_json::json!({
+                                    "channel": "rfq",
+                                    "type": event.notification.kind(),
+                                    "data": event.notification
+                                });
+                                conn.push("rfq", QueuePolicy::DropOldest, &msg);
+                            }
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        tracing::warn!("RFQ receiver lagged by {} messages", n);
+                        metrics::record_ws_messages_dropped("rfq", "broadcast_lag", n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        // Continue without RFQ notifications
+                    }
+                }
+            }
+
+            // Ticker updates of subscribed symbols
             _ = ticker_interval.tick() => {
-                // TODO: Implement prediction market ticker updates if needed
-                // For now, ticker updates are not supported in the prediction market version
+                let channels: Vec<String> = conn.channels().filter(|c| c.starts_with("ticker:")).cloned().collect();
+                for channel in channels {
+                    let msg = ticker_message(&state, channel.strip_prefix("ticker:").unwrap_or("")).await;
+                    conn.push(&channel, QueuePolicy::KeepLatest, &msg);
+                }
+
+                // Sample this connection's backlog so slow clients show up in metrics
+                let pending = conn.pending();
+                metrics::record_ws_send_queue_depth(pending);
+                if pending >= SLOW_CLIENT_WARN_DEPTH {
+                    tracing::warn!("Slow WebSocket client {:?}: {} messages pending", user_address, pending);
+                }
             }
 
             // Orderbook updates from Redis cache
             _ = orderbook_interval.tick() => {
                 if let Some(orderbook_cache) = state.cache.orderbook_opt() {
-                    for channel in &subscriptions {
-                        if channel.starts_with("orderbook:") {
-                            let raw_symbol = channel.strip_prefix("orderbook:").unwrap_or("");
-                            let symbol = normalize_symbol(raw_symbol);
-                            let cached = orderbook_cache.get_orderbook(&symbol, Some(20)).await;
-                            if !cached.bids.is_empty() || !cached.asks.is_empty() {
-                                let bids: Vec<OrderbookLevel> = cached.bids
-                                    .iter()
-                                    .map(|level| OrderbookLevel {
-                                        price: level.price.to_string(),
-                                        size: level.amount.to_string(),
-                                    })
-                                    .collect();
-                                let asks: Vec<OrderbookLevel> = cached.asks
-                                    .iter()
-                                    .map(|level| OrderbookLevel {
-                                        price: level.price.to_string(),
-                                        size: level.amount.to_string(),
-                                    })
-                                    .collect();
-                                let msg = ServerMessage::Orderbook {
-                                    symbol: cached.symbol,
-                                    bids,
-                                    asks,
-                                    timestamp: cached.timestamp,
-                                };
-                                let _ = sender.send(Message::Text(serde_json::to_string(&msg).unwrap())).await;
-                            }
+                    let channels: Vec<String> = conn
+                        .channels()
+                        .filter(|c| c.starts_with("orderbook:"))
+                        .cloned()
+                        .collect();
+                    for channel in channels {
+                        let raw_symbol = channel.strip_prefix("orderbook:").unwrap_or("");
+                        let symbol = state.symbols.resolve(raw_symbol).unwrap_or_else(|_| raw_symbol.to_string());
+                        let cached = orderbook_cache.get_orderbook(&symbol, Some(20)).await;
+                        if !cached.bids.is_empty() || !cached.asks.is_empty() {
+                            let group = conn.grouping(&channel);
+                            let bids = to_grouped_levels(&cached_levels(&cached.bids), Side::Buy, group);
+                            let asks = to_grouped_levels(&cached_levels(&cached.asks), Side::Sell, group);
+                            let msg = ServerMessage::Orderbook {
+                                symbol: cached.symbol,
+                                bids,
+                                asks,
+                                timestamp: cached.timestamp,
+                            };
+                            conn.push(&channel, QueuePolicy::KeepLatest, &msg);
                         }
                     }
                 }
             }
 
-            // Private data updates (positions, orders, balances)
-            _ = private_interval.tick() => {
-                if authenticated && user_address.is_some() {
-                    let address = user_address.as_ref().unwrap().to_lowercase();
-
-                    // Send position updates
-                    if subscriptions.contains("positions") {
-                        if let Ok(positions) = fetch_user_positions(&state, &address).await {
-                            for position in positions {
-                                let _ = sender.send(Message::Text(serde_json::to_string(&position).unwrap())).await;
+            // Committed balance changes (ledger)
+            balance_event = balance_receiver.recv() => {
+                match balance_event {
+                    Ok(event) => {
+                        if let Some(addr) = user_address.as_ref().filter(|_| authenticated) {
+                            if addr.to_lowercase() == event.user_address && conn.is_subscribed("balance") {
+                                let msg = balance_message(&state, event.token, event.available, event.frozen);
+                                conn.push("balance", QueuePolicy::DropOldest, &msg);
                             }
                         }
                     }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        tracing::warn!("Balance event receiver lagged by {} messages", n);
+                        metrics::record_ws_messages_dropped("balance", "broadcast_lag", n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        // Continue without balance updates
+                    }
+                }
+            }
 
-                    // Send balance updates
-                    if subscriptions.contains("balance") {
-                        if let Ok(balances) = fetch_user_balances(&state, &address).await {
-                            for balance in balances {
-                                let _ = sender.send(Message::Text(serde_json::to_string(&balance).unwrap())).await;
+            // Committed share holding changes (fills)
+            holding_event = holding_receiver.recv() => {
+                match holding_event {
+                    Ok(event) => {
+                        if let Some(addr) = user_address.as_ref().filter(|_| authenticated) {
+                            if addr.to_lowercase() == event.user_address && conn.is_subscribed("positions") {
+                                let msg = serde_json::json!({
+                                    "channel": "positions",
+                                    "type": "position_update",
+                                    "data": event
+                                });
+                                conn.push("positions", QueuePolicy::DropOldest, &msg);
                             }
                         }
                     }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        tracing::warn!("Holding event receiver lagged by {} messages", n);
+                        metrics::record_ws_messages_dropped("positions", "broadcast_lag", n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        // Continue without holding updates
+                    }
+                }
+            }
 
-                    // Send open order updates
-                    if subscriptions.contains("orders") {
-                        if let Ok(orders) = fetch_user_orders(&state, &address).await {
-                            for order in orders {
-                                let _ = sender.send(Message::Text(serde_json::to_string(&order).unwrap())).await;
+            // Committed order fills and status changes
+            order_event = order_event_receiver.recv() => {
+                match order_event {
+                    Ok(event) => {
+                        if let Some(addr) = user_address.as_ref().filter(|_| authenticated) {
+                            if addr.to_lowercase() == event.user_address && conn.is_subscribed("orders") {
+                                let msg = serde_json::json!({
+                                    "channel": "orders",
+                                    "type": "order_event",
+                                    "data": event
+                                });
+                                conn.push("orders", QueuePolicy::DropOldest, &msg);
                             }
                         }
                     }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        tracing::warn!("Order event receiver lagged by {} messages", n);
+                        metrics::record_ws_messages_dropped("orders", "broadcast_lag", n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        // Continue without order events
+                    }
                 }
             }
         }
     }
 
+    if let Some(frame) = close_frame {
+        let close = Message::Close(Some(frame));
+        if out_tx.send(close).await.is_ok() {
+            drop(out_tx);
+            let _ = tokio::time::timeout(std::time::Duration::from_secs(1), writer).await;
+        }
+    } else {
+        drop(out_tx);
+        writer.abort();
+    }
+
     // Track WebSocket disconnection
     let connection_count = WS_CONNECTION_COUNT.fetch_sub(1, Ordering::SeqCst) - 1;
     metrics::set_ws_connections(connection_count);
@@ -590,9 +1075,8 @@ async fn handle_client_message(
     text: &str,
     authenticated: &mut bool,
     user_address: &mut Option<String>,
-    subscriptions: &mut HashSet<String>,
+    conn: &mut SubscriptionManager,
     state: &Arc<AppState>,
-    sender: &mut futures::stream::SplitSink<WebSocket, Message>,
 ) -> Result<(), ServerMessage> {
     let client_msg: ClientMessage = serde_json::from_str(text).map_err(|e| ServerMessage::Error {
         code: "INVALID_MESSAGE".to_string(),
@@ -605,10 +1089,11 @@ async fn handle_client_message(
             signature,
             timestamp,
             token,
+            timestamp_token,
         } => {
             // Check if token-based auth (JWT)
             if let Some(jwt_token) = token {
-                match validate_token(&jwt_token, &state.config.jwt_secret) {
+                match state.auth_tokens.verify(&state.db.pool, &jwt_token).await {
                     Ok(claims) => {
                         *authenticated = true;
                         *user_address = Some(claims.sub.to_lowercase());
@@ -619,7 +1104,7 @@ async fn handle_client_message(
                             success: true,
                             message: None,
                         };
-                        let _ = sender.send(Message::Text(serde_json::to_string(&response).unwrap())).await;
+                        conn.push_control(&response);
                     }
                     Err(e) => {
                         tracing::warn!("WebSocket JWT validation failed: {}", e);
@@ -627,7 +1112,7 @@ async fn handle_client_message(
                             success: false,
                             message: Some("Invalid or expired token".to_string()),
                         };
-                        let _ = sender.send(Message::Text(serde_json::to_string(&response).unwrap())).await;
+                        conn.push_control(&response);
                     }
                 }
                 return Ok(());
@@ -641,24 +1126,19 @@ async fn handle_client_message(
                         success: false,
                         message: Some("Missing required fields for signature auth".to_string()),
                     };
-                    let _ = sender.send(Message::Text(serde_json::to_string(&response).unwrap())).await;
+                    conn.push_control(&response);
                     return Ok(());
                 }
             };
 
-            // 验证时间戳（5分钟内有效）
-            let now = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs();
-
-            if now.abs_diff(timestamp) > 300 {
+            // 验证时间戳（5分钟内有效，或在服务器时间令牌的5分钟内）
+            if !validate_request_timestamp(timestamp, timestamp_token.as_deref(), &state.config.jwt_secret) {
                 tracing::warn!("WebSocket auth timestamp expired for address: {}", address);
                 let response = ServerMessage::AuthResult {
                     success: false,
                     message: Some("Timestamp expired".to_string()),
                 };
-                let _ = sender.send(Message::Text(serde_json::to_string(&response).unwrap())).await;
+                conn.push_control(&response);
                 return Ok(());
             }
 
@@ -676,7 +1156,7 @@ async fn handle_client_message(
                         success: false,
                         message: Some("Invalid signature format".to_string()),
                     };
-                    let _ = sender.send(Message::Text(serde_json::to_string(&response).unwrap())).await;
+                    conn.push_control(&response);
                     return Ok(());
                 }
             };
@@ -687,7 +1167,7 @@ async fn handle_client_message(
                     success: false,
                     message: Some("Signature verification failed".to_string()),
                 };
-                let _ = sender.send(Message::Text(serde_json::to_string(&response).unwrap())).await;
+                conn.push_control(&response);
                 return Ok(());
             }
 
@@ -702,12 +1182,12 @@ async fn handle_client_message(
                 success: true,
                 message: None,
             };
-            let _ = sender.send(Message::Text(serde_json::to_string(&response).unwrap())).await;
+            conn.push_control(&response);
         }
 
         ClientMessage::AuthToken { token } => {
             // Validate JWT token
-            match validate_token(&token, &state.config.jwt_secret) {
+            match state.auth_tokens.verify(&state.db.pool, &token).await {
                 Ok(claims) => {
                     *authenticated = true;
                     *user_address = Some(claims.sub.to_lowercase());
@@ -718,7 +1198,7 @@ async fn handle_client_message(
                         success: true,
                         message: None,
                     };
-                    let _ = sender.send(Message::Text(serde_json::to_string(&response).unwrap())).await;
+                    conn.push_control(&response);
                 }
                 Err(e) => {
                     tracing::warn!("WebSocket JWT validation failed: {}", e);
@@ -726,16 +1206,16 @@ async fn handle_client_message(
                         success: false,
                         message: Some("Invalid or expired token".to_string()),
                     };
-                    let _ = sender.send(Message::Text(serde_json::to_string(&response).unwrap())).await;
+                    conn.push_control(&response);
                 }
             }
         }
 
-        ClientMessage::Subscribe { channel, token } => {
+        ClientMessage::Subscribe { channel, channels, token, conflate_ms, group } => {
             // If token is provided with subscribe, try to authenticate first
             if let Some(jwt_token) = token {
                 if !*authenticated {
-                    if let Ok(claims) = validate_token(&jwt_token, &state.config.jwt_secret) {
+                    if let Ok(claims) = state.auth_tokens.verify(&state.db.pool, &jwt_token).await {
                         *authenticated = true;
                         *user_address = Some(claims.sub.to_lowercase());
                         tracing::info!("WebSocket auto-authenticated via subscribe token: {}", claims.sub);
@@ -743,136 +1223,448 @@ async fn handle_client_message(
                 }
             }
 
-            // Check if private channel requires auth
-            let is_private = channel.starts_with("positions")
-                || channel.starts_with("orders")
-                || channel.starts_with("balance");
+            // Single channel: legacy ack / error behaviour
+            if channels.is_empty() {
+                let channel = channel.ok_or_else(|| ServerMessage::Error {
+                    code: "INVALID_MESSAGE".to_string(),
+                    message: "Subscribe requires 'channel' or 'channels'".to_string(),
+                })?;
+                let (window, group) = check_channel_access(&channel, *authenticated)
+                    .and_then(|()| check_channel_symbol(state, &channel))
+                    .and_then(|()| check_channel_conflation(state, &channel, conflate_ms))
+                    .and_then(|window| check_channel_group(&channel, group).map(|group| (window, group)))
+                    .map_err(|(code, message)| ServerMessage::Error {
+                        code: code.to_string(),
+                        message,
+                    })?;
+                check_channel_feature(state, &channel, user_address).await.map_err(|(code, message)| {
+                    ServerMessage::Error {
+                        code: code.to_string(),
+                        message,
+                    }
+                })?;
 
-            if is_private && !*authenticated {
-                return Err(ServerMessage::Error {
-                    code: "AUTH_REQUIRED".to_string(),
-                    message: "Authentication required for private channels".to_string(),
-                });
+                conn.subscribe(&channel);
+                conn.set_conflation(&channel, window);
+                conn.set_grouping(&channel, group);
+                tracing::info!(
+                    "✅ Client subscribed to '{}' (total subscriptions: {})",
+                    channel, conn.subscription_count()
+                );
+                tracing::debug!("Current subscriptions: {:?}", conn.channels().collect::<Vec<_>>());
+
+                let response = ServerMessage::Subscribed { channel: channel.clone() };
+                conn.push_control(&response);
+
+                send_initial_data(&channel, *authenticated, user_address, conn, state).await;
+                return Ok(());
             }
 
-            subscriptions.insert(channel.clone());
-            
-            tracing::info!(
-                "✅ Client subscribed to '{}' (total subscriptions: {})",
-                channel, subscriptions.len()
-            );
-            tracing::debug!("Current subscriptions: {:?}", subscriptions);
-
-            let response = ServerMessage::Subscribed { channel: channel.clone() };
-            let _ = sender.send(Message::Text(serde_json::to_string(&response).unwrap())).await;
-
-            // Send initial data for certain channels
-            if channel.starts_with("orderbook:") {
-                let raw_symbol = channel.strip_prefix("orderbook:").unwrap_or("");
-                let symbol = normalize_symbol(raw_symbol);
-                // Try Redis cache first, then fallback to matching engine
-                let orderbook_msg = if let Some(orderbook_cache) = state.cache.orderbook_opt() {
-                    let cached = orderbook_cache.get_orderbook(&symbol, Some(20)).await;
-                    if !cached.bids.is_empty() || !cached.asks.is_empty() {
-                        let bids: Vec<OrderbookLevel> = cached.bids
-                            .iter()
-                            .map(|level| OrderbookLevel {
-                                price: level.price.to_string(),
-                                size: level.amount.to_string(),
-                            })
-                            .collect();
-                        let asks: Vec<OrderbookLevel> = cached.asks
-                            .iter()
-                            .map(|level| OrderbookLevel {
-                                price: level.price.to_string(),
-                                size: level.amount.to_string(),
-                            })
-                            .collect();
-                        Some(ServerMessage::Orderbook {
-                            symbol: cached.symbol,
-                            bids,
-                            asks,
-                            timestamp: cached.timestamp,
-                        })
-                    } else {
-                        None
-                    }
-                } else {
-                    None
+            // Batch: one ack listing accepted and rejected channels, then initial data
+            let (requested, overflow) = batch_channels(channel, channels);
+            let mut accepted = Vec::new();
+            let mut rejected = Vec::new();
+            for channel in requested {
+                let result = match check_channel_access(&channel, *authenticated)
+                    .and_then(|()| check_channel_symbol(state, &channel))
+                    .and_then(|()| check_channel_conflation(state, &channel, conflate_ms))
+                    .and_then(|window| check_channel_group(&channel, group).map(|group| (window, group)))
+                {
+                    Ok(settings) => check_channel_feature(state, &channel, user_address).await.map(|()| settings),
+                    Err(e) => Err(e),
                 };
 
-                // Fallback to matching engine if Redis cache is empty
-                let msg = orderbook_msg.unwrap_or_else(|| {
-                    if let Ok(snapshot) = state.matching_engine.get_orderbook(&symbol, 20) {
-                        let bids: Vec<OrderbookLevel> = snapshot.bids
-                            .into_iter()
-                            .map(|[price, size]| OrderbookLevel { price, size })
-                            .collect();
-                        let asks: Vec<OrderbookLevel> = snapshot.asks
-                            .into_iter()
-                            .map(|[price, size]| OrderbookLevel { price, size })
-                            .collect();
-                        ServerMessage::Orderbook {
-                            symbol: snapshot.symbol,
-                            bids,
-                            asks,
-                            timestamp: snapshot.timestamp,
-                        }
-                    } else {
-                        ServerMessage::Orderbook {
-                            symbol: symbol.to_string(),
-                            bids: vec![],
-                            asks: vec![],
-                            timestamp: chrono::Utc::now().timestamp_millis(),
-                        }
-                    }
-                });
-                let _ = sender.send(Message::Text(serde_json::to_string(&msg).unwrap())).await;
-            } else if channel.starts_with("ticker:") {
-                // TODO: Implement prediction market ticker subscription
-                // For now, just acknowledge the subscription without sending data
-                tracing::debug!("Ticker subscription for prediction markets not yet implemented");
-            } else if channel == "positions" && *authenticated && user_address.is_some() {
-                let address = user_address.as_ref().unwrap().to_lowercase();
-                if let Ok(positions) = fetch_user_positions(state, &address).await {
-                    for position in positions {
-                        let _ = sender.send(Message::Text(serde_json::to_string(&position).unwrap())).await;
+                match result {
+                    Ok((window, group)) => {
+                        conn.subscribe(&channel);
+                        conn.set_conflation(&channel, window);
+                        conn.set_grouping(&channel, group);
+                        accepted.push(channel);
                     }
-                }
-            } else if channel == "balance" && *authenticated && user_address.is_some() {
-                let address = user_address.as_ref().unwrap().to_lowercase();
-                if let Ok(balances) = fetch_user_balances(state, &address).await {
-                    for balance in balances {
-                        let _ = sender.send(Message::Text(serde_json::to_string(&balance).unwrap())).await;
-                    }
-                }
-            } else if channel == "orders" && *authenticated && user_address.is_some() {
-                let address = user_address.as_ref().unwrap().to_lowercase();
-                if let Ok(orders) = fetch_user_orders(state, &address).await {
-                    for order in orders {
-                        let _ = sender.send(Message::Text(serde_json::to_string(&order).unwrap())).await;
+                    Err((code, message)) => {
+                        rejected.push(RejectedChannel { channel, code: code.to_string(), message });
                     }
                 }
             }
-            // TODO: Add kline support for prediction markets if needed
+            rejected.extend(overflow);
+
+            tracing::info!(
+                "✅ Client batch-subscribed to {} channels ({} rejected, total subscriptions: {})",
+                accepted.len(), rejected.len(), conn.subscription_count()
+            );
+
+            let response = ServerMessage::SubscribedBatch {
+                accepted: accepted.clone(),
+                rejected,
+            };
+            conn.push_control(&response);
+
+            for channel in &accepted {
+                send_initial_data(channel, *authenticated, user_address, conn, state).await;
+            }
         }
 
         ClientMessage::Unsubscribe { channel } => {
-            subscriptions.remove(&channel);
+            conn.unsubscribe(&channel);
 
             let response = ServerMessage::Unsubscribed { channel };
-            let _ = sender.send(Message::Text(serde_json::to_string(&response).unwrap())).await;
+            conn.push_control(&response);
+        }
+
+        ClientMessage::Resync { channel } => {
+            let symbol = channel
+                .strip_prefix("book:")
+                .filter(|_| conn.is_subscribed(&channel))
+                .ok_or_else(|| ServerMessage::Error {
+                    code: "NOT_SUBSCRIBED".to_string(),
+                    message: format!("Not subscribed to book channel '{}'", channel),
+                })?;
+            queue_book_snapshot(state, conn, symbol);
+        }
+
+        ClientMessage::CancelAllAfter { timeout_ms } => {
+            let address = user_address
+                .as_deref()
+                .filter(|_| *authenticated)
+                .ok_or_else(|| ServerMessage::Error {
+                    code: "AUTH_REQUIRED".to_string(),
+                    message: "Authentication required for cancel-all-after".to_string(),
+                })?;
+            let timer = state.dead_man.arm(address, timeout_ms).map_err(|e| ServerMessage::Error {
+                code: "INVALID_TIMEOUT".to_string(),
+                message: e.to_string(),
+            })?;
+            conn.push_control(&ServerMessage::CancelAllAfter {
+                armed: timer.is_some(),
+                timeout_ms: timer.map_or(0, |timer| timer.timeout_ms),
+                trigger_at: timer.map(|timer| timer.trigger_at.timestamp_millis()),
+            });
         }
 
         ClientMessage::Ping => {
+            // Pings are the dead man's switch heartbeat
+            if let Some(address) = user_address.as_deref().filter(|_| *authenticated) {
+                state.dead_man.heartbeat(address);
+            }
             let response = ServerMessage::Pong;
-            let _ = sender.send(Message::Text(serde_json::to_string(&response).unwrap())).await;
+            conn.push_control(&response);
         }
     }
 
     Ok(())
 }
 
+/// Message delivering a notification, if it is addressed to `user_address`
+fn notification_message(event: NotificationEvent, user_address: &str) -> Option<ServerMessage> {
+    if !user_address.eq_ignore_ascii_case(&event.user_address) {
+        return None;
+    }
+    Some(match event.notification {
+        Notification::Liquidation(notice) => ServerMessage::LiquidationEvent { notice },
+        Notification::Adl(notice) => ServerMessage::AdlEvent { notice },
+        Notification::MarginCall(notice) => ServerMessage::MarginCallEvent { notice },
+    })
+}
+
+/// Channels of a batch subscribe (`channel` first, then `channels`) in
+/// order without duplicates, split at `MAX_SUBSCRIBE_BATCH`: the rest come
+/// back rejected
+fn batch_channels(channel: Option<String>, channels: Vec<String>) -> (Vec<String>, Vec<RejectedChannel>) {
+    let mut requested: Vec<String> = Vec::new();
+    for channel in channel.into_iter().chain(channels) {
+        if !requested.contains(&channel) {
+            requested.push(channel);
+        }
+    }
+    let overflow = requested
+        .split_off(requested.len().min(MAX_SUBSCRIBE_BATCH))
+        .into_iter()
+        .map(|channel| RejectedChannel {
+            channel,
+            code: "TOO_MANY_CHANNELS".to_string(),
+            message: format!("At most {} channels per subscribe", MAX_SUBSCRIBE_BATCH),
+        })
+        .collect();
+    (requested, overflow)
+}
+
+/// Check whether the connection may subscribe to a channel, returning (code, message) on rejection
+fn check_channel_access(channel: &str, authenticated: bool) -> Result<(), (&'static str, String)> {
+    if channel.trim().is_empty() {
+        return Err(("INVALID_CHANNEL", "Channel name must not be empty".to_string()));
+    }
+
+    // Check if private channel requires auth
+    let is_private = channel.starts_with("positions")
+        || channel.starts_with("orders")
+        || channel.starts_with("balance")
+        || channel == "rfq"
+        || channel == "notifications";
+
+    if is_private && !authenticated {
+        return Err(("AUTH_REQUIRED", "Authentication required for private channels".to_string()));
+    }
+
+    Ok(())
+}
+
+/// Reject orderbook and ticker channels of unknown symbols, listing the accepted formats
+fn check_channel_symbol(state: &Arc<AppState>, channel: &str) -> Result<(), (&'static str, String)> {
+    match channel.strip_prefix("orderbook:").or_else(|| channel.strip_prefix("ticker:")) {
+        Some(symbol) => state
+            .symbols
+            .resolve(symbol)
+            .map(|_| ())
+            .map_err(|e| ("UNKNOWN_SYMBOL", e.to_string())),
+        None => Ok(()),
+    }
+}
+
+/// Validate a `conflate_ms` request: only per-symbol `trades:` channels are
+/// conflated, in one of the supported windows
+fn check_channel_conflation(
+    state: &Arc<AppState>,
+    channel: &str,
+    conflate_ms: Option<u64>,
+) -> Result<Option<u64>, (&'static str, String)> {
+    let Some(conflate_ms) = conflate_ms else {
+        return Ok(None);
+    };
+    let symbol = channel
+        .strip_prefix("trades:")
+        .map(|symbol| state.symbols.resolve(symbol))
+        .transpose()
+        .map_err(|e| ("UNKNOWN_SYMBOL", e.to_string()))?;
+    // Bare market ids would mix outcomes and share types in one VWAP
+    if !symbol.is_some_and(|symbol| symbol.contains(':') || Uuid::parse_str(&symbol).is_err()) {
+        return Err((
+            "CONFLATION_UNSUPPORTED",
+            format!("Channel '{}' cannot be conflated; use trades:{{symbol}}", channel),
+        ));
+    }
+    conflation::validate_window(conflate_ms)
+        .map(Some)
+        .map_err(|message| ("INVALID_CONFLATION", message))
+}
+
+/// Validate a `group` request: only `orderbook:` channels are grouped
+fn check_channel_group(channel: &str, group: Option<Decimal>) -> Result<Option<Decimal>, (&'static str, String)> {
+    let Some(group) = group else {
+        return Ok(None);
+    };
+    if !channel.starts_with("orderbook:") {
+        return Err((
+            "GROUPING_UNSUPPORTED",
+            format!("Channel '{}' cannot be grouped; use orderbook:{{symbol}}", channel),
+        ));
+    }
+    validate_group(group).map(Some).map_err(|message| ("INVALID_GROUP", message))
+}
+
+/// Check that the account has the feature a channel is gated behind, if any
+async fn check_channel_feature(
+    state: &Arc<AppState>,
+    channel: &str,
+    user_address: &Option<String>,
+) -> Result<(), (&'static str, String)> {
+    let Some(feature) = FeatureService::channel_feature(channel) else {
+        return Ok(());
+    };
+    let Some(address) = user_address.as_deref() else {
+        return Err(("AUTH_REQUIRED", "Authentication required for private channels".to_string()));
+    };
+
+    match state.features.has_feature(&state.db.pool, address, feature).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(("FEATURE_NOT_ENABLED", format!("Feature '{}' is not enabled for this account", feature))),
+        Err(e) => {
+            tracing::error!("Feature check for channel '{}' failed: {}", channel, e);
+            Err(("FEATURE_CHECK_FAILED", "Feature check failed".to_string()))
+        }
+    }
+}
+
+/// Queue the initial data for a newly subscribed channel
+async fn send_initial_data(
+    channel: &str,
+    authenticated: bool,
+    user_address: &Option<String>,
+    conn: &mut SubscriptionManager,
+    state: &Arc<AppState>,
+) {
+    if channel.starts_with("orderbook:") {
+        let raw_symbol = channel.strip_prefix("orderbook:").unwrap_or("");
+        let symbol = state.symbols.resolve(raw_symbol).unwrap_or_else(|_| raw_symbol.to_string());
+        let group = conn.grouping(channel);
+        // Try Redis cache first, then fallback to matching engine
+        let orderbook_msg = if let Some(orderbook_cache) = state.cache.orderbook_opt() {
+            let cached = orderbook_cache.get_orderbook(&symbol, Some(20)).await;
+            if !cached.bids.is_empty() || !cached.asks.is_empty() {
+                let bids = to_grouped_levels(&cached_levels(&cached.bids), Side::Buy, group);
+                let asks = to_grouped_levels(&cached_levels(&cached.asks), Side::Sell, group);
+                Some(ServerMessage::Orderbook {
+                    symbol: cached.symbol,
+                    bids,
+                    asks,
+                    timestamp: cached.timestamp,
+                })
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+
+        // Fallback to matching engine if Redis cache is empty
+        let msg = orderbook_msg.unwrap_or_else(|| {
+            let snapshot = match group {
+                Some(group) => state.matching_engine.get_grouped_orderbook(&symbol, 20, group),
+                None => state.matching_engine.get_orderbook(&symbol, 20),
+            };
+            if let Ok(snapshot) = snapshot {
+                let bids: Vec<OrderbookLevel> = snapshot.bids
+                    .into_iter()
+                    .map(|[price, size]| OrderbookLevel { price, size })
+                    .collect();
+                let asks: Vec<OrderbookLevel> = snapshot.asks
+                    .into_iter()
+                    .map(|[price, size]| OrderbookLevel { price, size })
+                    .collect();
+                ServerMessage::Orderbook {
+                    symbol: snapshot.symbol,
+                    bids,
+                    asks,
+                    timestamp: snapshot.timestamp,
+                }
+            } else {
+                ServerMessage::Orderbook {
+                    symbol: symbol.to_string(),
+                    bids: vec![],
+                    asks: vec![],
+                    timestamp: chrono::Utc::now().timestamp_millis(),
+                }
+            }
+        });
+        conn.push_control(&msg);
+    } else if let Some(symbol) = channel.strip_prefix("book:") {
+        queue_book_snapshot(state, conn, symbol);
+    } else if let Some(symbol) = channel.strip_prefix("ticker:") {
+        let msg = ticker_message(state, symbol).await;
+        conn.push_control(&msg);
+    } else if channel == "positions" && authenticated && user_address.is_some() {
+        let address = user_address.as_ref().unwrap().to_lowercase();
+        if let Ok(positions) = fetch_user_positions(state, &address).await {
+            for position in positions {
+                conn.push_control(&position);
+            }
+        }
+    } else if channel == "balance" && authenticated && user_address.is_some() {
+        let address = user_address.as_ref().unwrap().to_lowercase();
+        if let Ok(balances) = fetch_user_balances(state, &address).await {
+            for balance in balances {
+                conn.push_control(&balance);
+            }
+        }
+    } else if channel == "orders" && authenticated && user_address.is_some() {
+        let address = user_address.as_ref().unwrap().to_lowercase();
+        if let Ok(orders) = fetch_user_orders(state, &address).await {
+            for order in orders {
+                conn.push_control(&order);
+            }
+        }
+    }
+    // TODO: Add kline support for prediction markets if needed
+}
+
+/// Ticker of a symbol: last price, rolling 24h statistics and the top of the live book
+async fn ticker_message(state: &Arc<AppState>, raw_symbol: &str) -> ServerMessage {
+    let symbol = state.symbols.resolve(raw_symbol).unwrap_or_else(|_| raw_symbol.to_string());
+    let levels = state.live_config.current().ticker_book_levels;
+    let snapshot = state.matching_engine.get_orderbook(&symbol, levels).ok();
+    let book = snapshot.as_ref().map(|snapshot| book_ticker(&snapshot.bids, &snapshot.asks, levels));
+    let stats = state.ticker.ticker(&symbol);
+    let index_price = state.mark_price_service.symbol_index_price(&symbol).await;
+
+    let text = |value: Option<Decimal>| value.map(|value| value.to_string());
+    let stat = |value: Option<Decimal>| value.unwrap_or(Decimal::ZERO).to_string();
+    ServerMessage::Ticker {
+        last_price: text(stats.as_ref().map(|stats| stats.last_price))
+            .or_else(|| text(snapshot.as_ref().and_then(|snapshot| snapshot.last_price))),
+        mark_price: text(state.matching_engine.mark_price(&symbol)),
+        index_price: text(index_price),
+        price_change_24h: stat(stats.as_ref().map(|stats| stats.price_change_24h)),
+        price_change_percent_24h: stat(stats.as_ref().map(|stats| stats.price_change_percent_24h)),
+        high_24h: stat(stats.as_ref().map(|stats| stats.high_24h)),
+        low_24h: stat(stats.as_ref().map(|stats| stats.low_24h)),
+        volume_24h: stat(stats.as_ref().map(|stats| stats.volume_24h)),
+        volume_24h_usd: stat(stats.as_ref().map(|stats| stats.quote_volume_24h)),
+        best_bid: text(book.as_ref().and_then(|book| book.best_bid)),
+        best_ask: text(book.as_ref().and_then(|book| book.best_ask)),
+        spread: text(book.as_ref().and_then(|book| book.spread)),
+        depth_weighted_mid: text(book.as_ref().and_then(|book| book.depth_weighted_mid)),
+        bid_depth: stat(book.as_ref().map(|book| book.bid_depth)),
+        ask_depth: stat(book.as_ref().map(|book| book.ask_depth)),
+        imbalance: text(book.as_ref().and_then(|book| book.imbalance)),
+        timestamp: chrono::Utc::now().timestamp_millis(),
+        symbol,
+    }
+}
+
+/// Public trade message for `trades:{market_id}` / `market:{market_id}` channels
+pub fn market_trade_message(trade: &TradeEvent, id: String) -> ServerMessage {
+    ServerMessage::MarketTrade {
+        id,
+        version: trade.version,
+        market_id: trade.market_id.to_string(),
+        outcome_id: trade.outcome_id.to_string(),
+        share_type: trade.share_type.to_string(),
+        match_type: trade.match_type.to_string().to_lowercase(),
+        price: trade.price.to_string(),
+        amount: trade.amount.to_string(),
+        side: trade.side.clone(),
+        timestamp: trade.timestamp,
+        is_block_trade: trade.is_block_trade,
+        aggressor: trade.aggressor().map(str::to_string),
+        forced: trade.forced,
+        exec_id: trade.exec_id,
+    }
+}
+
+/// Trade message for the legacy `trades:{symbol}` channel
+pub fn legacy_trade_message(trade: &TradeEvent, id: String) -> ServerMessage {
+    ServerMessage::Trade {
+        id,
+        version: trade.version,
+        symbol: trade.symbol.clone(),
+        price: trade.price.to_string(),
+        amount: trade.amount.to_string(),
+        side: trade.side.clone(),
+        aggressor: trade.aggressor().map(str::to_string),
+        forced: trade.forced,
+        timestamp: trade.timestamp,
+        exec_id: trade.exec_id,
+    }
+}
+
+/// Build a position message from a margin-annotated share holding
+fn position_message(position: &PositionMargin, event: &str) -> ServerMessage {
+    ServerMessage::Position {
+        id: position.id.to_string(),
+        symbol: format!("{}:{}", position.market_id, position.share_type),
+        side: position.share_type.to_string(),
+        size: position.amount.to_string(),
+        entry_price: position.avg_cost.to_string(),
+        mark_price: position.mark_price.to_string(),
+        liquidation_price: position.liquidation_price.unwrap_or(Decimal::ZERO).to_string(),
+        unrealized_pnl: position.unrealized_pnl.to_string(),
+        leverage: 1,
+        margin: position.cost_basis.to_string(),
+        updated_at: chrono::Utc::now().timestamp_millis(),
+        event: Some(event.to_string()),
+        margin_mode: Some(position.margin_mode.to_string()),
+    }
+}
+
 /// Fetch user positions from database
 /// Note: In prediction markets, "positions" are actually share holdings
 async fn fetch_user_positions(state: &Arc<AppState>, address: &str) -> Result<Vec<ServerMessage>, sqlx::Error> {
@@ -906,6 +1698,7 @@ async fn fetch_user_positions(state: &Arc<AppState>, address: &str) -> Result<Ve
             margin: (shares * avg_price).to_string(),
             updated_at: updated_at.timestamp_millis(),
             event: None,
+            margin_mode: None,
         });
     }
 
@@ -921,25 +1714,26 @@ async fn fetch_user_balances(state: &Arc<AppState>, address: &str) -> Result<Vec
     .fetch_all(&state.db.pool)
     .await?;
 
-    let messages: Vec<ServerMessage> = rows
+    Ok(rows
         .into_iter()
-        .map(|(token, available, frozen)| {
-            // Get symbol from config if possible, otherwise use token address
-            let symbol = state.config.get_token_symbol(&token)
-                .map(|s| s.to_string())
-                .unwrap_or_else(|| token.clone());
-
-            ServerMessage::Balance {
-                token,
-                symbol,
-                available: available.to_string(),
-                frozen: frozen.to_string(),
-                total: (available + frozen).to_string(),
-            }
-        })
-        .collect();
+        .map(|(token, available, frozen)| balance_message(state, token, available, frozen))
+        .collect())
+}
 
-    Ok(messages)
+/// Build a balance message for one token
+fn balance_message(state: &AppState, token: String, available: Decimal, frozen: Decimal) -> ServerMessage {
+    // Get symbol from config if possible, otherwise use token address
+    let symbol = state.config.get_token_symbol(&token)
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| token.clone());
+
+    ServerMessage::Balance {
+        token,
+        symbol,
+        available: available.to_string(),
+        frozen: frozen.to_string(),
+        total: (available + frozen).to_string(),
+    }
 }
 
 /// Fetch user open orders from database
@@ -977,3 +1771,136 @@ async fn fetch_user_orders(state: &Arc<AppState>, address: &str) -> Result<Vec<S
 
     Ok(messages)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscribe(json: &str) -> (Option<String>, Vec<String>) {
+        match serde_json::from_str(json).unwrap() {
+            ClientMessage::Subscribe { channel, channels, .. } => (channel, channels),
+            other => panic!("unexpected message: {:?}", other),
+        }
+    }
+
+    /// Accepted and rejected channels of a batch, checking access only
+    fn partition(channel: Option<String>, channels: Vec<String>, authenticated: bool) -> (Vec<String>, Vec<(String, String)>) {
+        let (requested, overflow) = batch_channels(channel, channels);
+        let mut accepted = Vec::new();
+        let mut rejected = Vec::new();
+        for channel in requested {
+            match check_channel_access(&channel, authenticated) {
+                Ok(()) => accepted.push(channel),
+                Err((code, _)) => rejected.push((channel, code.to_string())),
+            }
+        }
+        rejected.extend(overflow.into_iter().map(|r| (r.channel, r.code)));
+        (accepted, rejected)
+    }
+
+    #[test]
+    fn test_subscribe_accepts_channel_channels_or_both() {
+        assert_eq!(subscribe(r#"{"type":"subscribe","channel":"trades:a"}"#), (Some("trades:a".to_string()), vec![]));
+        assert_eq!(
+            subscribe(r#"{"type":"subscribe","channels":["trades:a","orders"]}"#),
+            (None, vec!["trades:a".to_string(), "orders".to_string()])
+        );
+        assert_eq!(
+            subscribe(r#"{"type":"subscribe","channel":"trades:a","channels":["orders"]}"#),
+            (Some("trades:a".to_string()), vec!["orders".to_string()])
+        );
+        assert_eq!(subscribe(r#"{"type":"subscribe"}"#), (None, vec![]));
+    }
+
+    #[test]
+    fn test_batch_puts_channel_first_and_drops_duplicates() {
+        let (requested, overflow) = batch_channels(
+            Some("trades:a".to_string()),
+            vec!["ticker:b".to_string(), "trades:a".to_string(), "ticker:b".to_string(), "orders".to_string()],
+        );
+        assert_eq!(requested, vec!["trades:a", "ticker:b", "orders"]);
+        assert!(overflow.is_empty());
+    }
+
+    #[test]
+    fn test_batch_rejects_channels_past_the_limit() {
+        let channels: Vec<String> = (0..MAX_SUBSCRIBE_BATCH + 2).map(|i| format!("trades:{}", i)).collect();
+        let (requested, overflow) = batch_channels(None, channels);
+        assert_eq!(requested.len(), MAX_SUBSCRIBE_BATCH);
+        assert_eq!(requested.last().unwrap(), &format!("trades:{}", MAX_SUBSCRIBE_BATCH - 1));
+        let overflow: Vec<_> = overflow.into_iter().map(|r| (r.channel, r.code)).collect();
+        assert_eq!(
+            overflow,
+            [MAX_SUBSCRIBE_BATCH, MAX_SUBSCRIBE_BATCH + 1]
+                .map(|i| (format!("trades:{}", i), "TOO_MANY_CHANNELS".to_string()))
+        );
+
+        // Duplicates don't count toward the limit
+        let channels = vec!["trades:a".to_string(); MAX_SUBSCRIBE_BATCH + 1];
+        let (requested, overflow) = batch_channels(Some("trades:a".to_string()), channels);
+        assert_eq!((requested.len(), overflow.len()), (1, 0));
+    }
+
+    #[test]
+    fn test_private_channels_need_auth() {
+        for channel in ["positions", "orders", "balance", "rfq", "notifications"] {
+            assert_eq!(check_channel_access(channel, false).unwrap_err().0, "AUTH_REQUIRED");
+            assert!(check_channel_access(channel, true).is_ok());
+        }
+        assert!(check_channel_access("trades:a", false).is_ok());
+        assert_eq!(check_channel_access(" ", true).unwrap_err().0, "INVALID_CHANNEL");
+    }
+
+    #[test]
+    fn test_batch_lists_accepted_and_rejected_channels() {
+        let channels = vec!["orders".to_string(), "".to_string(), "trades:a".to_string(), "orders".to_string()];
+
+        let (accepted, rejected) = partition(Some("trades:a".to_string()), channels.clone(), false);
+        assert_eq!(accepted, vec!["trades:a"]);
+        assert_eq!(
+            rejected,
+            vec![("orders".to_string(), "AUTH_REQUIRED".to_string()), ("".to_string(), "INVALID_CHANNEL".to_string())]
+        );
+
+        let (accepted, rejected) = partition(Some("trades:a".to_string()), channels, true);
+        assert_eq!(accepted, vec!["trades:a", "orders"]);
+        assert_eq!(rejected, vec![("".to_string(), "INVALID_CHANNEL".to_string())]);
+    }
+
+    fn liquidation_event(user_address: &str) -> NotificationEvent {
+        NotificationEvent {
+            user_address: user_address.to_string(),
+            notification: Notification::Liquidation(LiquidationNotice {
+                liquidation_id: Uuid::nil(),
+                order_id: Uuid::nil(),
+                symbol: "m:o:yes".to_string(),
+                market_id: Uuid::nil(),
+                outcome_id: Uuid::nil(),
+                filled_amount: Decimal::new(60, 0),
+                position_amount: Decimal::new(100, 0),
+                price: Decimal::new(45, 2),
+                mark_price: Decimal::new(5, 1),
+                fee: Decimal::new(135, 2),
+                realized_pnl: Decimal::new(-3, 0),
+                is_partial: true,
+                timestamp: chrono::Utc::now(),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_notifications_reach_only_their_account() {
+        assert!(notification_message(liquidation_event("0xabc"), "0xdef").is_none());
+        assert!(notification_message(liquidation_event("0xabc"), "0xABC").is_some());
+    }
+
+    #[test]
+    fn test_liquidation_notice_is_flattened_into_its_event() {
+        let msg = notification_message(liquidation_event("0xabc"), "0xabc").unwrap();
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(json["type"], "liquidationevent");
+        assert_eq!(json["symbol"], "m:o:yes");
+        assert_eq!(json["filled_amount"], "60");
+        assert_eq!(json["is_partial"], true);
+    }
+}
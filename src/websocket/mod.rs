@@ -1,6 +1,10 @@
 pub mod routes;
 pub mod handler;
 pub mod channels;
+pub mod conflation;
+pub mod connections;
+pub mod signing;
+pub mod subscription;
 // pub mod binance_proxy; // Not needed for prediction markets
 
 // pub use routes::*;
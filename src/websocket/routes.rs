@@ -1,19 +1,30 @@
 use axum::{
     extract::{
         ws::WebSocketUpgrade,
-        State,
+        ConnectInfo, State,
     },
-    response::Response,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     routing::get,
+    Json,
     Router,
 };
+use serde::Serialize;
+use std::net::SocketAddr;
 use std::sync::Arc;
 
+use crate::metrics;
 use crate::websocket::handler::handle_socket;
 // [DISABLED] Binance proxy - using internal data only
 // use crate::websocket::binance_proxy::binance_kline_handler;
 use crate::AppState;
 
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: String,
+}
+
 pub fn create_router(_state: Arc<AppState>) -> Router<Arc<AppState>> {
     Router::new()
         .route("/", get(ws_handler))
@@ -24,6 +35,23 @@ pub fn create_router(_state: Arc<AppState>) -> Router<Arc<AppState>> {
 async fn ws_handler(
     ws: WebSocketUpgrade,
     State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
 ) -> Response {
-    ws.on_upgrade(move |socket| handle_socket(socket, state))
+    // Refuse the upgrade once the IP is at its connection cap
+    let ip = state.rate_limiter.client_ip_from(&headers, Some(peer));
+    let max_per_ip = state.live_config.current().ws_max_connections_per_ip;
+    let Some(ip_slot) = state.ws_connections.open(&ip, max_per_ip) else {
+        tracing::warn!("WebSocket connection refused for {}: {} connections open", ip, max_per_ip);
+        metrics::record_ws_connection_closed("ip_limit");
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(ErrorResponse {
+                error: "Too many WebSocket connections from this IP".to_string(),
+                code: "TOO_MANY_CONNECTIONS".to_string(),
+            }),
+        )
+            .into_response();
+    };
+    ws.on_upgrade(move |socket| handle_socket(socket, state, ip_slot))
 }
@@ -0,0 +1,294 @@
+//! Signed Market Data Feed
+//!
+//! Optional tamper evidence for partners redistributing our market data.
+//! When enabled, public trade messages are signed once, centrally, and fanned
+//! out to every subscribed connection. Each signed message carries:
+//!
+//! - `stream`: the sequence stream (e.g. `trades:{market_id}`)
+//! - `seq`: per-stream sequence number, starting at 1 (gaps = missed messages)
+//! - `signature`: `{ "alg": "ed25519", "key_id", "sig" }`, `sig` hex-encoded
+//!
+//! The signature covers the canonical JSON of the message without its
+//! `signature` field: object keys sorted, no whitespace. Kline messages are
+//! signed through the same [`FeedSigner::sign_message`] once a kline stream is
+//! published.
+//!
+//! Keys rotate every `ws_feed_key_rotation_secs`. Each epoch's Ed25519 key is
+//! derived from the configured secret (HMAC-SHA256 over the epoch), so every
+//! instance signs with the same key and restarts keep keys stable. The previous,
+//! current and next public keys are published at `GET /market-data/keys`.
+
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+use crate::services::matching::MatchingEngine;
+use crate::websocket::handler::{legacy_trade_message, market_trade_message};
+
+/// Signature algorithm
+pub const FEED_SIGNATURE_ALG: &str = "ed25519";
+
+/// Pre-signed message for fan-out to subscribed connections
+#[derive(Debug, Clone)]
+pub struct SignedFeedMessage {
+    /// Queue stream the message is delivered on
+    pub stream: String,
+    /// Subscriptions that receive the message (any one is enough)
+    pub channels: Vec<String>,
+    /// Serialized signed message
+    pub text: Arc<str>,
+}
+
+/// Published feed verification key
+#[derive(Debug, Clone, Serialize)]
+pub struct FeedKeyInfo {
+    pub key_id: String,
+    pub algorithm: &'static str,
+    /// Hex-encoded Ed25519 public key
+    pub public_key: String,
+    /// Unix millis
+    pub valid_from: i64,
+    pub valid_until: i64,
+}
+
+/// Signs market data messages with rotating Ed25519 keys
+pub struct FeedSigner {
+    secret: hmac::Key,
+    rotation_secs: u64,
+    /// Signing key of the current epoch
+    current: Mutex<Option<(u64, Arc<Ed25519KeyPair>)>>,
+    /// Last sequence number per stream
+    sequences: DashMap<String, u64>,
+}
+
+impl FeedSigner {
+    /// Create a signer; an empty secret generates a random one (keys then change on restart)
+    pub fn new(secret: &str, rotation_secs: u64) -> Self {
+        let secret_bytes = if secret.is_empty() {
+            tracing::warn!("No feed signing secret configured; using a random one (keys change on restart)");
+            let mut bytes = [0u8; 32];
+            SystemRandom::new().fill(&mut bytes).expect("system RNG failed");
+            bytes.to_vec()
+        } else {
+            secret.as_bytes().to_vec()
+        };
+
+        Self {
+            secret: hmac::Key::new(hmac::HMAC_SHA256, &secret_bytes),
+            rotation_secs: rotation_secs.max(60),
+            current: Mutex::new(None),
+            sequences: DashMap::new(),
+        }
+    }
+
+    fn epoch_at(&self, unix_secs: u64) -> u64 {
+        unix_secs / self.rotation_secs
+    }
+
+    fn key_id(epoch: u64) -> String {
+        format!("{}-{}", FEED_SIGNATURE_ALG, epoch)
+    }
+
+    /// Deterministic signing key for a rotation epoch
+    fn epoch_key(&self, epoch: u64) -> Ed25519KeyPair {
+        let seed = hmac::sign(&self.secret, format!("feed-key:{}", epoch).as_bytes());
+        Ed25519KeyPair::from_seed_unchecked(seed.as_ref()).expect("HMAC-SHA256 output is a valid Ed25519 seed")
+    }
+
+    fn current_key(&self) -> (u64, Arc<Ed25519KeyPair>) {
+        let epoch = self.epoch_at(chrono::Utc::now().timestamp() as u64);
+        let mut current = self.current.lock();
+        match current.as_ref() {
+            Some((cached, key)) if *cached == epoch => (epoch, key.clone()),
+            _ => {
+                let key = Arc::new(self.epoch_key(epoch));
+                *current = Some((epoch, key.clone()));
+                tracing::info!("Feed signing key rotated to {}", Self::key_id(epoch));
+                (epoch, key)
+            }
+        }
+    }
+
+    /// Previous, current and next verification keys
+    pub fn public_keys(&self) -> Vec<FeedKeyInfo> {
+        let current = self.epoch_at(chrono::Utc::now().timestamp() as u64);
+        (current.saturating_sub(1)..=current + 1)
+            .map(|epoch| FeedKeyInfo {
+                key_id: Self::key_id(epoch),
+                algorithm: FEED_SIGNATURE_ALG,
+                public_key: hex::encode(self.epoch_key(epoch).public_key().as_ref()),
+                valid_from: (epoch * self.rotation_secs * 1000) as i64,
+                valid_until: ((epoch + 1) * self.rotation_secs * 1000) as i64,
+            })
+            .collect()
+    }
+
+    pub fn rotation_secs(&self) -> u64 {
+        self.rotation_secs
+    }
+
+    /// Stamp a message with the stream's next sequence number and sign it
+    pub fn sign_message<T: Serialize>(&self, stream: &str, msg: &T) -> Result<String, serde_json::Error> {
+        let seq = {
+            let mut entry = self.sequences.entry(stream.to_string()).or_insert(0);
+            *entry += 1;
+            *entry
+        };
+
+        let mut value = serde_json::to_value(msg)?;
+        let Value::Object(fields) = &mut value else {
+            return Err(serde::ser::Error::custom("feed messages must be JSON objects"));
+        };
+        fields.insert("stream".to_string(), Value::from(stream));
+        fields.insert("seq".to_string(), Value::from(seq));
+
+        let (epoch, key) = self.current_key();
+        let signature = key.sign(&canonical_json(&value)?);
+
+        if let Value::Object(fields) = &mut value {
+            fields.insert(
+                "signature".to_string(),
+                json!({
+                    "alg": FEED_SIGNATURE_ALG,
+                    "key_id": Self::key_id(epoch),
+                    "sig": hex::encode(signature.as_ref()),
+                }),
+            );
+        }
+        serde_json::to_string(&value)
+    }
+}
+
+/// Canonical signing bytes: keys sorted at every level, compact
+pub fn canonical_json(value: &Value) -> Result<Vec<u8>, serde_json::Error> {
+    fn sorted(value: &Value) -> Value {
+        match value {
+            Value::Object(map) => {
+                let mut keys: Vec<&String> = map.keys().collect();
+                keys.sort();
+                let mut out = Map::new();
+                for key in keys {
+                    out.insert(key.clone(), sorted(&map[key]));
+                }
+                Value::Object(out)
+            }
+            Value::Array(items) => Value::Array(items.iter().map(sorted).collect()),
+            other => other.clone(),
+        }
+    }
+    serde_json::to_vec(&sorted(value))
+}
+
+/// Sign every engine trade once and broadcast it to WebSocket connections
+pub fn spawn_trade_feed(
+    engine: Arc<MatchingEngine>,
+    signer: Arc<FeedSigner>,
+    sender: broadcast::Sender<SignedFeedMessage>,
+) {
+    let mut trades = engine.subscribe_trades();
+    tokio::spawn(async move {
+        loop {
+            let trade = match trades.recv().await {
+                Ok(trade) => trade,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    tracing::warn!("Signed trade feed lagged by {} trades", n);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+
+            let trade_id = trade.trade_id.to_string();
+            let market_id = trade.market_id.to_string();
+            let market_stream = format!("trades:{}", market_id);
+            let symbol_stream = format!("trades:{}", trade.symbol);
+
+            let signed = [
+                (
+                    market_stream.clone(),
+                    vec![market_stream.clone(), format!("market:{}", market_id), "trades:*".to_string()],
+                    signer.sign_message(&market_stream, &market_trade_message(&trade, trade_id.clone())),
+                ),
+                (
+                    symbol_stream.clone(),
+                    vec![symbol_stream.clone()],
+                    signer.sign_message(&symbol_stream, &legacy_trade_message(&trade, trade_id)),
+                ),
+            ];
+
+            for (stream, channels, text) in signed {
+                match text {
+                    Ok(text) => {
+                        // No receivers just means no WebSocket clients are connected
+                        let _ = sender.send(SignedFeedMessage {
+                            stream,
+                            channels,
+                            text: text.into(),
+                        });
+                    }
+                    Err(e) => tracing::error!("Failed to sign trade {}: {}", trade.trade_id, e),
+                }
+            }
+        }
+        tracing::warn!("Signed trade feed stopped");
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::signature::{UnparsedPublicKey, ED25519};
+
+    fn verify(signer: &FeedSigner, text: &str) -> bool {
+        let mut value: Value = serde_json::from_str(text).unwrap();
+        let signature = value.as_object_mut().unwrap().remove("signature").unwrap();
+        let key = signer
+            .public_keys()
+            .into_iter()
+            .find(|k| k.key_id == signature["key_id"])
+            .unwrap();
+        let public_key = hex::decode(key.public_key).unwrap();
+        let sig = hex::decode(signature["sig"].as_str().unwrap()).unwrap();
+        UnparsedPublicKey::new(&ED25519, public_key)
+            .verify(&canonical_json(&value).unwrap(), &sig)
+            .is_ok()
+    }
+
+    #[test]
+    fn test_signed_message_verifies_and_detects_tampering() {
+        let signer = FeedSigner::new("test-secret", 3600);
+        let text = signer.sign_message("trades:m1", &json!({"type": "markettrade", "price": "0.55"})).unwrap();
+        assert!(verify(&signer, &text));
+
+        let tampered = text.replace("0.55", "0.56");
+        assert!(!verify(&signer, &tampered));
+    }
+
+    #[test]
+    fn test_sequences_are_per_stream() {
+        let signer = FeedSigner::new("test-secret", 3600);
+        let seq = |stream: &str| -> u64 {
+            let text = signer.sign_message(stream, &json!({"type": "trade"})).unwrap();
+            serde_json::from_str::<Value>(&text).unwrap()["seq"].as_u64().unwrap()
+        };
+        assert_eq!(seq("trades:a"), 1);
+        assert_eq!(seq("trades:a"), 2);
+        assert_eq!(seq("trades:b"), 1);
+    }
+
+    #[test]
+    fn test_keys_are_deterministic_per_secret() {
+        let a = FeedSigner::new("secret", 3600).public_keys();
+        let b = FeedSigner::new("secret", 3600).public_keys();
+        let c = FeedSigner::new("other", 3600).public_keys();
+        assert_eq!(a.len(), 3);
+        assert_eq!(a[1].public_key, b[1].public_key);
+        assert_ne!(a[1].public_key, c[1].public_key);
+        assert_ne!(a[0].public_key, a[1].public_key);
+    }
+}
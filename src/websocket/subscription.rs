@@ -0,0 +1,392 @@
+//! WebSocket Subscription Manager
+//!
+//! Per-connection subscription state and outbound message queues.
+//!
+//! Broadcast events are pushed into per-stream queues instead of being written
+//! to the socket inline, so a slow client can no longer stall the connection
+//! loop and make its broadcast receivers lag (silently losing trades). When a
+//! stream queue is full, its [`QueuePolicy`] decides what is given up:
+//!
+//! - `KeepLatest`: full-state messages (orderbook snapshots) - only the newest is kept
+//! - `DropOldest`: event streams (trades, orders) - the oldest queued message is dropped
+//! - `Resync`: sequenced deltas (`book:` channels) - the queue is discarded and the
+//!   caller must enqueue a fresh snapshot
+//!
+//! Control messages (auth results, subscribe acks, errors, pongs) are never dropped.
+
+use axum::extract::ws::Message;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::metrics;
+
+/// Default per-stream queue capacity
+pub const DEFAULT_STREAM_CAPACITY: usize = 256;
+
+/// Overflow policy for a stream queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueuePolicy {
+    /// Keep only the most recent message
+    KeepLatest,
+    /// Drop the oldest message when full
+    DropOldest,
+    /// Discard the queue when full; caller must resend a snapshot
+    Resync,
+}
+
+/// Result of pushing a message onto a stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushOutcome {
+    /// Message queued
+    Queued,
+    /// Message replaced an older pending message
+    Coalesced,
+    /// Message queued after dropping the oldest pending message
+    Dropped,
+    /// Queue overflowed and was discarded; a snapshot must be enqueued
+    NeedsResync,
+}
+
+/// Pending messages for one stream
+#[derive(Debug)]
+struct StreamQueue {
+    policy: QueuePolicy,
+    /// (enqueue order, message)
+    items: VecDeque<(u64, Message)>,
+}
+
+/// Subscription state and outbound queues for a single connection
+#[derive(Debug)]
+pub struct SubscriptionManager {
+    /// Subscribed channels
+    channels: HashSet<String>,
+
+    /// Last book sequence enqueued per `book:` symbol
+    book_sequences: HashMap<String, u64>,
+
+    /// Conflation window (ms) per conflated `trades:` channel
+    conflation: HashMap<String, u64>,
+
+    /// Price bucket size per grouped `orderbook:` channel
+    grouping: HashMap<String, Decimal>,
+
+    /// Control messages (never dropped)
+    control: VecDeque<Message>,
+
+    /// Per-stream queues
+    streams: HashMap<String, StreamQueue>,
+
+    /// Enqueue counter, used to drain streams in arrival order
+    next_seq: u64,
+
+    /// Per-stream queue capacity
+    capacity: usize,
+}
+
+impl Default for SubscriptionManager {
+    fn default() -> Self {
+        Self::new(DEFAULT_STREAM_CAPACITY)
+    }
+}
+
+impl SubscriptionManager {
+    /// Create a manager with the given per-stream capacity
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            channels: HashSet::new(),
+            book_sequences: HashMap::new(),
+            conflation: HashMap::new(),
+            grouping: HashMap::new(),
+            control: VecDeque::new(),
+            streams: HashMap::new(),
+            next_seq: 0,
+            capacity: capacity.max(1),
+        }
+    }
+
+    // ========================================================================
+    // Subscriptions
+    // ========================================================================
+
+    /// Subscribe to a channel
+    pub fn subscribe(&mut self, channel: &str) {
+        self.channels.insert(channel.to_string());
+    }
+
+    /// Unsubscribe from a channel, discarding its book state, conflation, grouping and pending messages
+    pub fn unsubscribe(&mut self, channel: &str) {
+        self.channels.remove(channel);
+        self.conflation.remove(channel);
+        self.grouping.remove(channel);
+        if let Some(symbol) = channel.strip_prefix("book:") {
+            self.book_sequences.remove(symbol);
+        }
+        self.streams.remove(channel);
+    }
+
+    /// Check whether a channel is subscribed
+    pub fn is_subscribed(&self, channel: &str) -> bool {
+        self.channels.contains(channel)
+    }
+
+    /// Subscribed channels
+    pub fn channels(&self) -> impl Iterator<Item = &String> {
+        self.channels.iter()
+    }
+
+    /// Number of subscribed channels
+    pub fn subscription_count(&self) -> usize {
+        self.channels.len()
+    }
+
+    /// Set (or clear, with None) the conflation window of a channel
+    pub fn set_conflation(&mut self, channel: &str, window_ms: Option<u64>) {
+        match window_ms {
+            Some(window_ms) => self.conflation.insert(channel.to_string(), window_ms),
+            None => self.conflation.remove(channel),
+        };
+    }
+
+    /// Conflation window of a channel (None = every message is delivered)
+    pub fn conflation(&self, channel: &str) -> Option<u64> {
+        self.conflation.get(channel).copied()
+    }
+
+    /// Set (or clear, with None) the price grouping of a channel
+    pub fn set_grouping(&mut self, channel: &str, group: Option<Decimal>) {
+        match group {
+            Some(group) => self.grouping.insert(channel.to_string(), group),
+            None => self.grouping.remove(channel),
+        };
+    }
+
+    /// Price grouping of a channel (None = levels as they are)
+    pub fn grouping(&self, channel: &str) -> Option<Decimal> {
+        self.grouping.get(channel).copied()
+    }
+
+    /// Whether a channel is subscribed without conflation
+    pub fn is_subscribed_full(&self, channel: &str) -> bool {
+        self.is_subscribed(channel) && !self.conflation.contains_key(channel)
+    }
+
+    // ========================================================================
+    // Book Sequences
+    // ========================================================================
+
+    /// Last book sequence enqueued for a symbol (None = not a `book:` subscriber)
+    pub fn book_sequence(&self, symbol: &str) -> Option<u64> {
+        self.book_sequences.get(symbol).copied()
+    }
+
+    /// Record the last book sequence enqueued for a symbol
+    pub fn set_book_sequence(&mut self, symbol: &str, sequence: u64) {
+        self.book_sequences.insert(symbol.to_string(), sequence);
+    }
+
+    /// Symbols with a `book:` subscription
+    pub fn book_symbols(&self) -> Vec<String> {
+        self.book_sequences.keys().cloned().collect()
+    }
+
+    // ========================================================================
+    // Outbound Queues
+    // ========================================================================
+
+    /// Queue a control message
+    pub fn push_control<T: Serialize>(&mut self, msg: &T) {
+        self.control.push_back(Message::Text(serde_json::to_string(msg).unwrap()));
+    }
+
+    /// Queue a raw control frame (e.g. Pong)
+    pub fn push_control_frame(&mut self, frame: Message) {
+        self.control.push_back(frame);
+    }
+
+    /// Queue a message on a stream, applying the stream's overflow policy
+    pub fn push<T: Serialize>(&mut self, stream: &str, policy: QueuePolicy, msg: &T) -> PushOutcome {
+        self.push_text(stream, policy, serde_json::to_string(msg).unwrap())
+    }
+
+    /// Queue an already-serialized message on a stream (e.g. a pre-signed feed message)
+    pub fn push_text(&mut self, stream: &str, policy: QueuePolicy, text: String) -> PushOutcome {
+        let message = Message::Text(text);
+        let seq = self.next_seq;
+        self.next_seq += 1;
+
+        let capacity = self.capacity;
+        let queue = self.streams.entry(stream.to_string()).or_insert_with(|| StreamQueue {
+            policy,
+            items: VecDeque::new(),
+        });
+
+        let outcome = match queue.policy {
+            QueuePolicy::KeepLatest if !queue.items.is_empty() => {
+                let dropped = queue.items.len() as u64;
+                queue.items.clear();
+                metrics::record_ws_messages_dropped(stream_kind(stream), "coalesced", dropped);
+                PushOutcome::Coalesced
+            }
+            QueuePolicy::DropOldest if queue.items.len() >= capacity => {
+                queue.items.pop_front();
+                metrics::record_ws_messages_dropped(stream_kind(stream), "overflow", 1);
+                PushOutcome::Dropped
+            }
+            QueuePolicy::Resync if queue.items.len() >= capacity => {
+                let dropped = queue.items.len() as u64 + 1;
+                queue.items.clear();
+                metrics::record_ws_messages_dropped(stream_kind(stream), "resync", dropped);
+                return PushOutcome::NeedsResync;
+            }
+            _ => PushOutcome::Queued,
+        };
+
+        queue.items.push_back((seq, message));
+        outcome
+    }
+
+    /// Replace everything pending on a stream with a single message (e.g. a snapshot)
+    pub fn replace<T: Serialize>(&mut self, stream: &str, policy: QueuePolicy, msg: &T) {
+        if let Some(queue) = self.streams.get_mut(stream) {
+            queue.items.clear();
+        }
+        self.push(stream, policy, msg);
+    }
+
+    /// Whether any message is waiting to be written
+    pub fn has_pending(&self) -> bool {
+        !self.control.is_empty() || self.streams.values().any(|q| !q.items.is_empty())
+    }
+
+    /// Total number of pending messages
+    pub fn pending(&self) -> usize {
+        self.control.len() + self.streams.values().map(|q| q.items.len()).sum::<usize>()
+    }
+
+    /// Take the next message to write: control first, then streams in arrival order
+    pub fn pop(&mut self) -> Option<Message> {
+        if let Some(frame) = self.control.pop_front() {
+            return Some(frame);
+        }
+
+        let stream = self
+            .streams
+            .iter()
+            .filter_map(|(name, q)| q.items.front().map(|(seq, _)| (*seq, name)))
+            .min_by_key(|(seq, _)| *seq)
+            .map(|(_, name)| name.clone())?;
+
+        self.streams
+            .get_mut(&stream)
+            .and_then(|q| q.items.pop_front())
+            .map(|(_, message)| message)
+    }
+}
+
+/// Metric label for a stream key ("trades:abc" -> "trades")
+pub fn stream_kind(stream: &str) -> &str {
+    stream.split(':').next().unwrap_or(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(msg: Message) -> String {
+        match msg {
+            Message::Text(t) => t,
+            other => panic!("unexpected frame: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_keep_latest_coalesces() {
+        let mut conn = SubscriptionManager::new(8);
+        assert_eq!(conn.push("orderbook:a", QueuePolicy::KeepLatest, &1), PushOutcome::Queued);
+        assert_eq!(conn.push("orderbook:a", QueuePolicy::KeepLatest, &2), PushOutcome::Coalesced);
+
+        assert_eq!(conn.pending(), 1);
+        assert_eq!(text(conn.pop().unwrap()), "2");
+        assert!(!conn.has_pending());
+    }
+
+    #[test]
+    fn test_drop_oldest_when_full() {
+        let mut conn = SubscriptionManager::new(2);
+        conn.push("trades:a", QueuePolicy::DropOldest, &1);
+        conn.push("trades:a", QueuePolicy::DropOldest, &2);
+        assert_eq!(conn.push("trades:a", QueuePolicy::DropOldest, &3), PushOutcome::Dropped);
+
+        assert_eq!(text(conn.pop().unwrap()), "2");
+        assert_eq!(text(conn.pop().unwrap()), "3");
+        assert!(conn.pop().is_none());
+    }
+
+    #[test]
+    fn test_resync_discards_queue() {
+        let mut conn = SubscriptionManager::new(2);
+        conn.push("book:a", QueuePolicy::Resync, &1);
+        conn.push("book:a", QueuePolicy::Resync, &2);
+        assert_eq!(conn.push("book:a", QueuePolicy::Resync, &3), PushOutcome::NeedsResync);
+        assert!(!conn.has_pending());
+
+        conn.replace("book:a", QueuePolicy::Resync, &"snapshot");
+        assert_eq!(text(conn.pop().unwrap()), "\"snapshot\"");
+    }
+
+    #[test]
+    fn test_pop_order_control_first_then_arrival() {
+        let mut conn = SubscriptionManager::default();
+        conn.push("trades:a", QueuePolicy::DropOldest, &"t1");
+        conn.push("orderbook:a", QueuePolicy::KeepLatest, &"ob");
+        conn.push("trades:a", QueuePolicy::DropOldest, &"t2");
+        conn.push_control(&"ack");
+
+        let order: Vec<String> = std::iter::from_fn(|| conn.pop()).map(text).collect();
+        assert_eq!(order, vec!["\"ack\"", "\"t1\"", "\"ob\"", "\"t2\""]);
+    }
+
+    #[test]
+    fn test_unsubscribe_clears_book_state() {
+        let mut conn = SubscriptionManager::default();
+        conn.subscribe("book:a");
+        conn.set_book_sequence("a", 7);
+        conn.push("book:a", QueuePolicy::Resync, &1);
+
+        conn.unsubscribe("book:a");
+        assert!(!conn.is_subscribed("book:a"));
+        assert_eq!(conn.book_sequence("a"), None);
+        assert!(!conn.has_pending());
+    }
+
+    #[test]
+    fn test_conflation_per_channel() {
+        let mut conn = SubscriptionManager::default();
+        conn.subscribe("trades:a");
+        conn.set_conflation("trades:a", Some(250));
+        conn.subscribe("trades:b");
+
+        assert_eq!(conn.conflation("trades:a"), Some(250));
+        assert!(!conn.is_subscribed_full("trades:a"));
+        assert!(conn.is_subscribed_full("trades:b"));
+
+        conn.set_conflation("trades:a", None);
+        assert!(conn.is_subscribed_full("trades:a"));
+
+        conn.set_conflation("trades:a", Some(100));
+        conn.unsubscribe("trades:a");
+        assert_eq!(conn.conflation("trades:a"), None);
+    }
+
+    #[test]
+    fn test_grouping_cleared_on_unsubscribe() {
+        let mut conn = SubscriptionManager::default();
+        conn.subscribe("orderbook:a");
+        conn.set_grouping("orderbook:a", Some(Decimal::new(5, 2)));
+        assert_eq!(conn.grouping("orderbook:a"), Some(Decimal::new(5, 2)));
+
+        conn.unsubscribe("orderbook:a");
+        assert_eq!(conn.grouping("orderbook:a"), None);
+    }
+}